@@ -0,0 +1,100 @@
+//! Records what `blvm_governance::build_info` reports about the build: git commit and dirty
+//! flag, build time, rustc version and enabled features. Also extracts the doc comments of
+//! the configuration settings for `blvm_governance::config_template`.
+
+use std::process::Command;
+
+/// Trimmed stdout of a command that succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// The fields of the structs in `source`, with their doc comments: `(struct, field, type,
+/// doc)` in declaration order.
+fn struct_fields(source: &str) -> Vec<(String, String, String, String)> {
+    let mut fields = Vec::new();
+    let mut owner: Option<String> = None;
+    let mut doc: Vec<&str> = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix("///") {
+            doc.push(text.strip_prefix(' ').unwrap_or(text));
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") || line.starts_with("#[") {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("pub struct ") {
+            owner = name.strip_suffix(" {").map(str::to_string);
+        } else if line == "}" {
+            owner = None;
+        } else if let Some(owner) = &owner {
+            let field = line.strip_prefix("pub ").and_then(|f| f.strip_suffix(','));
+            if let Some((name, ty)) = field.and_then(|f| f.split_once(": ")) {
+                fields.push((
+                    owner.clone(),
+                    name.to_string(),
+                    ty.to_string(),
+                    doc.join("\n"),
+                ));
+            }
+        }
+        doc.clear();
+    }
+    fields
+}
+
+fn main() {
+    let commit = output("git", &["rev-parse", "HEAD"]).unwrap_or_default();
+    let dirty = !commit.is_empty()
+        && output("git", &["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|status| !status.is_empty());
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_default();
+    // SOURCE_DATE_EPOCH gives reproducible builds a fixed time
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|f| f.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=BLVM_BUILD_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=BLVM_BUILD_GIT_DIRTY={}", dirty);
+    println!("cargo:rustc-env=BLVM_BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rustc-env=BLVM_BUILD_RUSTC={}", rustc_version);
+    println!("cargo:rustc-env=BLVM_BUILD_FEATURES={}", features.join(","));
+
+    let config = std::fs::read_to_string("src/config.rs").expect("read src/config.rs");
+    let mut docs = String::from("pub(crate) const FIELDS: &[Field] = &[\n");
+    for (owner, name, ty, doc) in struct_fields(&config) {
+        docs.push_str(&format!(
+            "    Field {{ owner: {:?}, name: {:?}, ty: {:?}, doc: {:?} }},\n",
+            owner, name, ty, doc
+        ));
+    }
+    docs.push_str("];\n");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    std::fs::write(std::path::Path::new(&out_dir).join("config_docs.rs"), docs)
+        .expect("write config_docs.rs");
+
+    // Rebuilt on commits, checkouts and source changes rather than on every build
+    if let Some(git_dir) = output("git", &["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/index", git_dir);
+    }
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
@@ -0,0 +1,394 @@
+//! Local endpoints for submitting votes and vetoes
+//!
+//! With `[governance.health] actions_token` set, the health listener (see [`crate::health`])
+//! also accepts `POST /actions/veto` and `POST /actions/vote`, so the operator of an economic
+//! node can cast a veto or a vote with a plain HTTP request rather than an IPC message. Both
+//! take a JSON [`ActionRequest`]:
+//!
+//! ```json
+//! {"proposal_id": "42", "node_id": "<64 hex>", "action": "veto", "reason": "unsafe",
+//!  "height": 840000, "signatures": [{"key_index": 0, "signature": "<hex>"}]}
+//! ```
+//!
+//! `action` may be left out, and must name the endpoint's action if given; both need the
+//! `height` they were signed at, at most `reserve.max_challenge_depth` blocks below the tip,
+//! and a vote `vote` ("yes", "no" or "abstain"). Requests must carry `Authorization: Bearer <token>`.
+//! Before anything is sent, the signatures are checked against the registry: over
+//! [`multisig::veto_message`] or [`multisig::vote_message`], by `threshold` keys of the
+//! node's key set or, for a node without one, by its public key; the node must be
+//! registered and active. The action is then submitted with
+//! [`NodeApiIpc::submit_governance_action`], which needs `allow_actions`, and the node's
+//! answer is returned as an [`ActionOutcome`] with 200, whether it accepted the action or not.
+//!
+//! Otherwise the answer is `{"error": ..}` with 401 without the token, 400 for a body that
+//! does not parse or validate, 403 when the signatures do not show control of the node, 501
+//! when no module on the node takes governance actions, 502 when the node could not be
+//! asked, and 503 while actions are not allowed, the module is
+//! paused (see [`crate::pause`]) or not connected to the node. Every request is written to the audit log with its outcome
+//! (see [`crate::audit_log`]); submitted actions are in the action audit trail as well (see
+//! [`crate::audit`]).
+
+use crate::audit_log::AuditLog;
+use crate::economic_nodes::{multisig, parse_node_id, EconomicNodeRegistry, KeySignature};
+use crate::error::GovernanceError;
+use crate::node_api::{ActionOutcome, GovernanceAction, NodeApiIpc};
+use crate::tally::VoteChoice;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// An action the endpoints submit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Veto,
+    Vote,
+}
+
+impl ActionKind {
+    /// The action served on `path`, if any.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/actions/veto" => Some(Self::Veto),
+            "/actions/vote" => Some(Self::Vote),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Veto => "veto",
+            Self::Vote => "vote",
+        }
+    }
+}
+
+/// One signature of a request, by the key at `key_index` of the node's key set (0 for a
+/// node without one).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HexSignature {
+    pub key_index: u32,
+    /// Hex.
+    pub signature: String,
+}
+
+/// Body of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ActionRequest {
+    pub proposal_id: String,
+    /// Hex id of the economic node acting.
+    pub node_id: String,
+    /// "veto" or "vote", matching the endpoint.
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub reason: String,
+    /// Needed by votes.
+    #[serde(default)]
+    pub vote: Option<String>,
+    /// Block height the action was signed at.
+    #[serde(default)]
+    pub height: Option<u64>,
+    #[serde(default)]
+    pub signatures: Vec<HexSignature>,
+}
+
+/// A request checked for shape, ready to be checked against the registry.
+#[derive(Debug, Clone, PartialEq)]
+struct Checked {
+    /// What the signatures are over.
+    message: String,
+    /// Height the message was signed at.
+    height: u64,
+    signatures: Vec<KeySignature>,
+    action: GovernanceAction,
+}
+
+impl ActionRequest {
+    fn check(&self, kind: ActionKind) -> Result<Checked, String> {
+        if let Some(action) = &self.action {
+            if action != kind.as_str() {
+                return Err(format!(
+                    "action {:?} does not match /actions/{}",
+                    action,
+                    kind.as_str()
+                ));
+            }
+        }
+        let node_id = parse_node_id(&self.node_id)
+            .ok_or_else(|| "node_id must be 64 hex characters".to_string())?;
+        let mut signatures = self
+            .signatures
+            .iter()
+            .map(|s| {
+                hex::decode(&s.signature)
+                    .map(|signature| KeySignature {
+                        key_index: s.key_index,
+                        signature,
+                    })
+                    .map_err(|_| format!("signature of key {} is not hex", s.key_index))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if signatures.is_empty() {
+            return Err("signatures are required".to_string());
+        }
+        signatures.sort_by_key(|s| s.key_index);
+        let signature = signatures
+            .iter()
+            .map(|s| hex::encode(&s.signature))
+            .collect::<Vec<_>>()
+            .join(",");
+        let height = self
+            .height
+            .ok_or_else(|| format!("height is required for a {}", kind.as_str()))?;
+        let (message, action) = match kind {
+            ActionKind::Veto => (
+                multisig::veto_message(&node_id, &self.proposal_id, &self.reason, height),
+                GovernanceAction::Veto {
+                    proposal_id: self.proposal_id.clone(),
+                    node_identity: self.node_id.clone(),
+                    signature,
+                    reason: self.reason.clone(),
+                },
+            ),
+            ActionKind::Vote => {
+                let vote = self
+                    .vote
+                    .clone()
+                    .filter(|v| VoteChoice::parse(v).is_some())
+                    .ok_or_else(|| "vote must be yes, no or abstain".to_string())?;
+                (
+                    multisig::vote_message(&node_id, &self.proposal_id, &vote, height),
+                    GovernanceAction::Vote {
+                        proposal_id: self.proposal_id.clone(),
+                        node_identity: self.node_id.clone(),
+                        vote,
+                        signature,
+                    },
+                )
+            }
+        };
+        Ok(Checked {
+            message,
+            height,
+            signatures,
+            action,
+        })
+    }
+}
+
+/// A request as written to the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionRequestRecord {
+    pub action: ActionKind,
+    /// `None` if the request was not read.
+    pub node_id: Option<String>,
+    pub proposal_id: Option<String>,
+    /// HTTP status answered.
+    pub status: u16,
+    /// Whether the node accepted the action.
+    pub accepted: bool,
+    /// The node's reason, or why the request failed.
+    pub detail: Option<String>,
+}
+
+/// An answer to a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionResponse {
+    pub status: u16,
+    /// JSON.
+    pub body: String,
+}
+
+/// Whether `given` is `token`, compared in time independent of where they differ.
+pub(crate) fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Serves the action endpoints on one connection to the node.
+pub struct LocalActions {
+    token: String,
+    registry: Arc<EconomicNodeRegistry>,
+    node_api: NodeApiIpc,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+impl LocalActions {
+    pub fn new(token: String, registry: Arc<EconomicNodeRegistry>, node_api: NodeApiIpc) -> Self {
+        Self {
+            token,
+            registry,
+            node_api,
+            audit_log: None,
+        }
+    }
+
+    /// Write each request and its outcome to `log`.
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Answer a request for `kind` with the `Authorization` header `authorization`.
+    pub async fn handle(
+        &self,
+        kind: ActionKind,
+        authorization: Option<&str>,
+        body: &[u8],
+    ) -> ActionResponse {
+        let mut record = ActionRequestRecord {
+            action: kind,
+            node_id: None,
+            proposal_id: None,
+            status: 200,
+            accepted: false,
+            detail: None,
+        };
+        let body = match self.submit(kind, authorization, body, &mut record).await {
+            Ok(outcome) => {
+                record.accepted = outcome.accepted;
+                record.detail = outcome.reason.clone();
+                serde_json::to_string(&outcome).unwrap_or_default()
+            }
+            Err((status, error)) => {
+                warn!(
+                    "Refused {} request for node {}: {}",
+                    kind.as_str(),
+                    record.node_id.as_deref().unwrap_or("unknown"),
+                    error
+                );
+                record.status = status;
+                record.detail = Some(error.clone());
+                serde_json::json!({ "error": error }).to_string()
+            }
+        };
+        if let Some(log) = &self.audit_log {
+            log.action_request(&record);
+        }
+        ActionResponse {
+            status: record.status,
+            body,
+        }
+    }
+
+    /// Check the request and submit it, or the status and error to answer with.
+    async fn submit(
+        &self,
+        kind: ActionKind,
+        authorization: Option<&str>,
+        body: &[u8],
+        record: &mut ActionRequestRecord,
+    ) -> Result<ActionOutcome, (u16, String)> {
+        let authorized = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| token_matches(given.trim(), &self.token));
+        if !authorized {
+            return Err((401, "missing or wrong bearer token".to_string()));
+        }
+        let request: ActionRequest = serde_json::from_slice(body)
+            .map_err(|e| (400, format!("invalid request body: {}", e)))?;
+        record.node_id = Some(request.node_id.clone());
+        record.proposal_id = Some(request.proposal_id.clone());
+        let checked = request.check(kind).map_err(|reason| (400, reason))?;
+        self.registry
+            .check_signed_height(checked.height, kind.as_str())
+            .await
+            .map_err(|e| (403, e.to_string()))?;
+        self.registry
+            .check_control(
+                &request.node_id,
+                &checked.message,
+                &checked.signatures,
+                kind.as_str(),
+            )
+            .await
+            .map_err(|e| (403, e.to_string()))?;
+        info!(
+            "Submitting {} on proposal {} for economic node {}",
+            kind.as_str(),
+            request.proposal_id,
+            request.node_id
+        );
+        self.node_api
+            .submit_governance_action(&checked.action)
+            .await
+            .map_err(|e| match e {
+                GovernanceError::ActionsDisabled { .. } | GovernanceError::Paused { .. } => {
+                    (503, e.to_string())
+                }
+                GovernanceError::Unsupported { .. } => (501, e.to_string()),
+                e => (502, e.to_string()),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(action: Option<&str>, vote: Option<&str>) -> ActionRequest {
+        ActionRequest {
+            proposal_id: "42".to_string(),
+            node_id: hex::encode([1u8; 32]),
+            action: action.map(str::to_string),
+            reason: String::new(),
+            vote: vote.map(str::to_string),
+            height: Some(100),
+            signatures: vec![
+                HexSignature {
+                    key_index: 1,
+                    signature: "bb".to_string(),
+                },
+                HexSignature {
+                    key_index: 0,
+                    signature: "aa".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cre", "s3cret"));
+        assert!(!token_matches("s3creT", "s3cret"));
+        assert!(!token_matches("", "s3cret"));
+    }
+
+    #[test]
+    fn test_check_request() {
+        let checked = request(Some("vote"), Some("yes"))
+            .check(ActionKind::Vote)
+            .unwrap();
+        assert_eq!(
+            checked.message,
+            multisig::vote_message(&[1u8; 32], "42", "yes", 100)
+        );
+        // Forwarded in key order
+        assert!(matches!(
+            checked.action,
+            GovernanceAction::Vote { signature, .. } if signature == "aa,bb"
+        ));
+
+        assert!(request(Some("veto"), Some("yes"))
+            .check(ActionKind::Vote)
+            .is_err());
+        assert!(request(None, Some("maybe"))
+            .check(ActionKind::Vote)
+            .is_err());
+        assert!(request(None, None).check(ActionKind::Veto).is_ok());
+        let mut undated = request(None, None);
+        undated.height = None;
+        assert!(undated.check(ActionKind::Veto).is_err());
+        undated.vote = Some("yes".to_string());
+        assert!(undated.check(ActionKind::Vote).is_err());
+        let mut unsigned = request(None, None);
+        unsigned.signatures.clear();
+        assert!(unsigned.check(ActionKind::Veto).is_err());
+    }
+}
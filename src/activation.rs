@@ -0,0 +1,149 @@
+//! Activation countdowns of merged proposals
+//!
+//! A merged proposal of a tier with a delay under `[governance.activation.delays]` activates
+//! that many blocks after the height it was merged at. As blocks arrive, the proposal store
+//! (see [`crate::proposals`]) sends `proposal_activation_countdown` to the webhook each time
+//! the blocks remaining fall to a multiple of `countdown_interval_blocks`, and
+//! `proposal_activated` once the tip reaches the activation height. When several multiples
+//! are passed at once, e.g. after the module was stopped, one countdown is sent.
+//!
+//! A block at or below the merge height replaces the block the proposal was merged on: the
+//! merge is undone, the countdown cancelled with `proposal_activation_cancelled`, and the
+//! proposal is open again until the node's merge event arrives anew. Once activated, a
+//! proposal stays activated.
+
+use serde::{Deserialize, Serialize};
+
+/// A notification due for a merged proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    /// Blocks remaining until activation.
+    Countdown { blocks_remaining: u64 },
+    /// The tip reached the activation height.
+    Activated,
+    /// A reorg undid the merge.
+    Cancelled,
+}
+
+impl Notice {
+    /// Event type of the webhook notification.
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::Countdown { .. } => "proposal_activation_countdown",
+            Self::Activated => "proposal_activated",
+            Self::Cancelled => "proposal_activation_cancelled",
+        }
+    }
+}
+
+/// Activation of a merged proposal, as stored with the proposals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activation {
+    pub proposal_id: String,
+    pub tier: String,
+    pub merged_height: u64,
+    pub activation_height: u64,
+    /// Blocks remaining at which the next countdown is due; 0 when none is.
+    next_countdown: u64,
+    /// Height the tip was at when the activation was seen, if it has been.
+    pub activated_height: Option<u64>,
+}
+
+impl Activation {
+    /// Activation of `proposal_id`, merged at `merged_height`, `delay` blocks later.
+    pub fn new(
+        proposal_id: &str,
+        tier: &str,
+        merged_height: u64,
+        delay: u64,
+        interval: u64,
+    ) -> Self {
+        Self {
+            proposal_id: proposal_id.to_string(),
+            tier: tier.to_string(),
+            merged_height,
+            activation_height: merged_height.saturating_add(delay),
+            next_countdown: below(delay, interval),
+            activated_height: None,
+        }
+    }
+
+    pub fn is_activated(&self) -> bool {
+        self.activated_height.is_some()
+    }
+
+    /// The notification due at `height`, if any, recording it as sent. Countdowns are due at
+    /// multiples of `interval` blocks remaining.
+    pub fn advance(&mut self, height: u64, interval: u64) -> Option<Notice> {
+        if self.is_activated() {
+            return None;
+        }
+        if height <= self.merged_height {
+            return Some(Notice::Cancelled);
+        }
+        if height >= self.activation_height {
+            self.activated_height = Some(height);
+            return Some(Notice::Activated);
+        }
+        let blocks_remaining = self.activation_height - height;
+        if self.next_countdown == 0 || blocks_remaining > self.next_countdown {
+            return None;
+        }
+        self.next_countdown = below(blocks_remaining, interval);
+        Some(Notice::Countdown { blocks_remaining })
+    }
+}
+
+/// The largest multiple of `interval` below `blocks`, or 0 without an interval.
+fn below(blocks: u64, interval: u64) -> u64 {
+    if interval == 0 {
+        return 0;
+    }
+    blocks.saturating_sub(1) / interval * interval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown_at_each_interval() {
+        let mut activation = Activation::new("1", "core", 100, 4032, 1000);
+        assert_eq!(activation.activation_height, 4132);
+        assert_eq!(activation.advance(131, 1000), None);
+        assert_eq!(
+            activation.advance(132, 1000),
+            Some(Notice::Countdown {
+                blocks_remaining: 4000
+            })
+        );
+        assert_eq!(activation.advance(133, 1000), None);
+
+        // Two multiples passed at once: one countdown
+        assert_eq!(
+            activation.advance(2200, 1000),
+            Some(Notice::Countdown {
+                blocks_remaining: 1932
+            })
+        );
+        assert_eq!(
+            activation.advance(3132, 1000),
+            Some(Notice::Countdown {
+                blocks_remaining: 1000
+            })
+        );
+        assert_eq!(activation.advance(4131, 1000), None);
+        assert_eq!(activation.advance(4132, 1000), Some(Notice::Activated));
+        assert_eq!(activation.activated_height, Some(4132));
+        // Activated for good, whatever the chain does
+        assert_eq!(activation.advance(50, 1000), None);
+    }
+
+    #[test]
+    fn test_reorg_below_the_merge_cancels() {
+        let mut activation = Activation::new("1", "core", 100, 10, 0);
+        assert_eq!(activation.advance(105, 0), None);
+        assert_eq!(activation.advance(100, 0), Some(Notice::Cancelled));
+        assert!(!activation.is_activated());
+    }
+}
@@ -0,0 +1,254 @@
+//! Local admin socket
+//!
+//! While it runs, the module listens on `admin.sock` in its data directory for requests from
+//! `blvm-governance` subcommands on the same host. A client writes one request line and reads
+//! one line of JSON back, and the connection is closed. The socket is readable and writable
+//! by its owner only. One left behind by an earlier run is replaced: the data directory lock
+//! means no other module process is using it (see [`crate::storage`]).
+//!
+//! Requests:
+//!
+//! - `status`: the [`ModuleStatus`] (see [`crate::status`]), printed by
+//!   `blvm-governance status`.
+//! - `pause` and `resume`, optionally followed by a space and a JSON [`Trigger`]: pause or
+//!   resume outbound effects (see [`crate::pause`]) and answer with the [`PauseStatus`], for
+//!   `blvm-governance pause` and `blvm-governance resume`. A bare request is recorded as
+//!   coming from `admin_socket`.
+//!
+//! Anything else is answered with `{"error": ..}`.
+
+use crate::error::GovernanceError;
+use crate::pause::{PauseControl, PauseStatus, Trigger};
+use crate::status::{ModuleStatus, StatusCollector};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// Name of the socket in the data directory.
+pub const ADMIN_SOCKET: &str = "admin.sock";
+
+/// Longest request line read from a client.
+const MAX_REQUEST_BYTES: u64 = 1024;
+
+/// Time allowed for one request and its answer, on either side.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The answer to `request`, without the newline.
+async fn answer(request: &str, status: &StatusCollector, pause: &PauseControl) -> String {
+    let error = |message: String| serde_json::json!({ "error": message }).to_string();
+    let (command, trigger) = request.split_once(' ').unwrap_or((request, ""));
+    match command {
+        "status" if trigger.is_empty() => {
+            serde_json::to_string(&status.collect().await).unwrap_or_default()
+        }
+        "pause" | "resume" => {
+            let trigger = match trigger.trim() {
+                "" => Trigger::new("admin_socket"),
+                json => match serde_json::from_str::<Trigger>(json) {
+                    Ok(trigger) if trigger.source.is_empty() => Trigger {
+                        source: "admin_socket".to_string(),
+                        ..trigger
+                    },
+                    Ok(trigger) => trigger,
+                    Err(e) => return error(format!("invalid {} request: {}", command, e)),
+                },
+            };
+            let result = if command == "pause" {
+                pause.pause(trigger)
+            } else {
+                pause.resume(trigger)
+            };
+            match result {
+                Ok(status) => serde_json::to_string(&status).unwrap_or_default(),
+                Err(e) => error(e.to_string()),
+            }
+        }
+        _ => error(format!("unknown request {:?}", request)),
+    }
+}
+
+/// Serve requests on a socket at `path` until the returned task is aborted, which removes the
+/// socket.
+#[cfg(unix)]
+pub fn spawn(
+    path: &Path,
+    status: Arc<StatusCollector>,
+    pause: Arc<PauseControl>,
+) -> Result<JoinHandle<()>, GovernanceError> {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(GovernanceError::io(path.display())(e))
+        }
+        _ => {}
+    }
+    let listener =
+        tokio::net::UnixListener::bind(path).map_err(GovernanceError::io(path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(GovernanceError::io(path.display()))?;
+    info!("Serving the admin socket at {}", path.display());
+
+    struct RemoveOnDrop(std::path::PathBuf);
+    impl Drop for RemoveOnDrop {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+    let socket = RemoveOnDrop(path.to_path_buf());
+    Ok(tokio::spawn(async move {
+        let _socket = socket;
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    debug!("Admin socket accept failed: {}", e);
+                    continue;
+                }
+            };
+            let status = Arc::clone(&status);
+            let pause = Arc::clone(&pause);
+            tokio::spawn(async move {
+                let served =
+                    tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &status, &pause)).await;
+                if let Ok(Err(e)) = served {
+                    debug!("Admin request failed: {}", e);
+                }
+            });
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+pub fn spawn(
+    path: &Path,
+    _status: Arc<StatusCollector>,
+    _pause: Arc<PauseControl>,
+) -> Result<JoinHandle<()>, GovernanceError> {
+    Err(GovernanceError::io(path.display())(
+        std::io::ErrorKind::Unsupported.into(),
+    ))
+}
+
+#[cfg(unix)]
+async fn respond(
+    mut stream: tokio::net::UnixStream,
+    status: &StatusCollector,
+    pause: &PauseControl,
+) -> std::io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    let (reader, mut writer) = stream.split();
+    let mut request = String::new();
+    BufReader::new(reader.take(MAX_REQUEST_BYTES))
+        .read_line(&mut request)
+        .await?;
+    let answer = answer(request.trim(), status, pause).await;
+    writer.write_all(format!("{}\n", answer).as_bytes()).await?;
+    writer.shutdown().await
+}
+
+/// Send `request` to the module serving the socket at `path`, and return its answer.
+#[cfg(unix)]
+pub async fn request(path: &Path, request: &str) -> Result<serde_json::Value, GovernanceError> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    let exchange = async {
+        let mut stream = tokio::net::UnixStream::connect(path).await?;
+        stream
+            .write_all(format!("{}\n", request).as_bytes())
+            .await?;
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer).await?;
+        Ok::<_, std::io::Error>(answer)
+    };
+    let unreachable = format!(
+        "cannot reach the module at {}; is it running?",
+        path.display()
+    );
+    let answer = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()))
+        .map_err(GovernanceError::io(unreachable))?;
+    let answer: serde_json::Value =
+        serde_json::from_str(&answer).map_err(GovernanceError::serialization(request))?;
+    match answer.get("error").and_then(|e| e.as_str()) {
+        Some(error) => Err(GovernanceError::ModuleError(error.to_string())),
+        None => Ok(answer),
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn request(path: &Path, _request: &str) -> Result<serde_json::Value, GovernanceError> {
+    Err(GovernanceError::io(path.display())(
+        std::io::ErrorKind::Unsupported.into(),
+    ))
+}
+
+/// The status of the module serving the socket at `path`.
+pub async fn status(path: &Path) -> Result<ModuleStatus, GovernanceError> {
+    let answer = request(path, "status").await?;
+    serde_json::from_value(answer).map_err(GovernanceError::serialization("status"))
+}
+
+/// Pause (`pause` true) or resume the module serving the socket at `path`, as `trigger`.
+pub async fn set_paused(
+    path: &Path,
+    pause: bool,
+    trigger: &Trigger,
+) -> Result<PauseStatus, GovernanceError> {
+    let command = if pause { "pause" } else { "resume" };
+    let trigger =
+        serde_json::to_string(trigger).map_err(GovernanceError::serialization(command))?;
+    let answer = request(path, &format!("{} {}", command, trigger)).await?;
+    serde_json::from_value(answer).map_err(GovernanceError::serialization(command))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_status_over_the_socket() {
+        let dir = std::env::temp_dir().join(format!("blvm_admin_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ADMIN_SOCKET);
+        // Left behind by an earlier run
+        std::fs::write(&path, "").unwrap();
+        let collector = Arc::new(StatusCollector::new(Instant::now()));
+        let pause = Arc::new(PauseControl::open(&dir).unwrap());
+        let server = spawn(&path, collector, Arc::clone(&pause)).unwrap();
+
+        let reported = status(&path).await.unwrap();
+        assert_eq!(
+            reported.schema_version,
+            crate::status::STATUS_SCHEMA_VERSION
+        );
+        assert!(!reported.connected);
+        let error = request(&path, "restart").await.unwrap_err();
+        assert!(error.to_string().contains("unknown request \"restart\""));
+
+        let trigger = Trigger {
+            reason: Some("incident".to_string()),
+            ..Trigger::new("cli")
+        };
+        let paused = set_paused(&path, true, &trigger).await.unwrap();
+        assert!(paused.paused);
+        assert_eq!(paused.trigger, Some(trigger));
+        assert!(pause.is_paused());
+        let resumed = request(&path, "resume").await.unwrap();
+        assert_eq!(resumed["paused"], false);
+        assert_eq!(resumed["trigger"]["source"], "admin_socket");
+        assert!(request(&path, "pause {").await.is_err());
+
+        server.abort();
+        let _ = server.await;
+        assert!(!path.exists());
+        assert!(status(&path)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("is it running?"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
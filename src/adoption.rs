@@ -0,0 +1,465 @@
+//! Adoption of activated proposals
+//!
+//! A proposal configured under `[governance.adoption.proposals.<proposal_id>]` is followed
+//! once it activates (see [`crate::activation`]), to show how quickly the network takes it
+//! up. Its rule says what adoption is:
+//!
+//! - with `bit`, for a miner-enforced change: the share of the last `window_blocks` blocks
+//!   whose header signals the bit (see [`signals`]), counting only blocks from the activation
+//!   height, so the window fills up over the first `window_blocks` after activation;
+//! - with `capability`, for a node-level feature: the share of active registered economic
+//!   nodes that report the capability. Nodes report theirs through the
+//!   `report_node_capabilities` API method (see [`crate::api`]); each report replaces the
+//!   node's earlier one.
+//!
+//! At each `NewBlock` the [`AdoptionTracker`] measures every activated proposal it follows.
+//! The first time a proposal's adoption reaches one of `milestones_percent`,
+//! `proposal_adoption_milestone` is sent to the webhook with the [`AdoptionLevel`]. Reached
+//! milestones and reported capabilities are stored in the module database, so a milestone is
+//! sent once. The headers of the window are kept in memory: after a restart they are read
+//! again from the node's current chain ([`AdoptionTracker::rebuild`]), as are heights
+//! missing from the window, and a reorg drops those above the fork
+//! ([`AdoptionTracker::roll_back_to`]).
+//!
+//! The levels are served by the `get_adoption` API method, as `adoption_percent` on
+//! `/metrics` (see [`crate::prometheus`]) and in epoch summaries, measured at the epoch's last
+//! block (see [`crate::epoch_summary`]).
+
+use crate::config::{AdoptionConfig, AdoptionRule};
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::node_api::NodeApiIpc;
+use crate::proposals::ProposalStore;
+use crate::signaling::signals;
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use blvm_protocol::Hash;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+const ADOPTION_TREE: &str = "adoption";
+
+const CAPABILITIES_KEY: &[u8] = b"capabilities";
+
+const MILESTONES_KEY: &[u8] = b"milestones";
+
+/// Event type of the webhook notification.
+pub const MILESTONE_EVENT_TYPE: &str = "proposal_adoption_milestone";
+
+/// What adoption of a proposal is measured on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Measure {
+    /// Recent blocks signaling the proposal's bit.
+    Blocks,
+    /// Active registered nodes reporting the proposal's capability.
+    Nodes,
+}
+
+impl Measure {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Measure::Blocks => "blocks",
+            Measure::Nodes => "nodes",
+        }
+    }
+}
+
+/// Adoption of one activated proposal at a height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionLevel {
+    pub proposal_id: String,
+    pub measure: Measure,
+    pub activation_height: u64,
+    /// Height measured at.
+    pub height: u64,
+    /// Blocks signaling, or nodes reporting the capability.
+    pub adopted: u64,
+    /// Blocks in the window, or active nodes.
+    pub total: u64,
+    pub percent: f64,
+}
+
+/// Share of `adopted` in `total`, in percent; 0 of nothing.
+fn percent(adopted: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    adopted as f64 * 100.0 / total as f64
+}
+
+/// Blocks in `versions` from `start` to `height` and those signaling `bit`.
+fn count_blocks(versions: &BTreeMap<u64, u32>, start: u64, height: u64, bit: u8) -> (u64, u64) {
+    let blocks = versions.range(start..=height);
+    blocks.fold((0, 0), |(adopted, total), (_, version)| {
+        (adopted + u64::from(signals(*version, bit)), total + 1)
+    })
+}
+
+/// What is kept between blocks.
+#[derive(Debug, Default)]
+struct State {
+    /// Header versions of the window, by height.
+    versions: BTreeMap<u64, u32>,
+    /// Levels at the last block measured, by proposal id.
+    levels: BTreeMap<String, AdoptionLevel>,
+}
+
+/// Measures the adoption of activated proposals.
+pub struct AdoptionTracker {
+    config: AdoptionConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    node_api: NodeApiIpc,
+    proposals: Arc<ProposalStore>,
+    registry: Arc<EconomicNodeRegistry>,
+    /// Where milestones are sent.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    /// Held while a block is measured, re-reads included, so blocks are measured in order.
+    state: tokio::sync::Mutex<State>,
+}
+
+impl AdoptionTracker {
+    pub fn new(
+        config: AdoptionConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+        node_api: NodeApiIpc,
+        proposals: Arc<ProposalStore>,
+        registry: Arc<EconomicNodeRegistry>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            node_api,
+            proposals,
+            registry,
+            webhook: None,
+            state: tokio::sync::Mutex::new(State::default()),
+        }
+    }
+
+    /// Send milestones to `webhook`.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Whether any proposal is followed, so blocks are needed.
+    pub fn is_enabled(&self) -> bool {
+        !self.config.proposals.is_empty()
+    }
+
+    /// Levels at the last block measured, by proposal id.
+    pub async fn levels(&self) -> Vec<AdoptionLevel> {
+        self.state.lock().await.levels.values().cloned().collect()
+    }
+
+    /// Milestones reached, by proposal id.
+    pub fn milestones(&self) -> Result<BTreeMap<String, Vec<f64>>, GovernanceError> {
+        self.load(MILESTONES_KEY)
+    }
+
+    /// Record the capabilities node `node_id` (hex) reports, replacing those it reported
+    /// before.
+    pub fn report_capabilities(
+        &self,
+        node_id: &str,
+        capabilities: BTreeSet<String>,
+    ) -> Result<(), GovernanceError> {
+        let mut reported: BTreeMap<String, BTreeSet<String>> = self.load(CAPABILITIES_KEY)?;
+        debug!(
+            "Node {} reports {} capabilities",
+            node_id,
+            capabilities.len()
+        );
+        if capabilities.is_empty() {
+            reported.remove(node_id);
+        } else {
+            reported.insert(node_id.to_string(), capabilities);
+        }
+        self.save(CAPABILITIES_KEY, &reported)
+    }
+
+    /// Measure the proposals followed at the block `hash` at `height` from a `NewBlock` event,
+    /// and send the milestones reached.
+    pub async fn handle_block(
+        &self,
+        hash: &Hash,
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let Some(header) = self.node_api.get_block_header(hash).await? else {
+            debug!("No header for block {} at {}", hex::encode(hash), height);
+            return Ok(());
+        };
+        let mut state = self.state.lock().await;
+        state.versions.split_off(&height);
+        state.versions.insert(height, header.header.version as u32);
+        self.update(&mut state, height, node_api).await
+    }
+
+    /// Measure the proposals followed at the node's chain tip, reading the window's headers
+    /// from the node, e.g. at startup.
+    pub async fn rebuild(&self, node_api: &dyn NodeAPI) -> Result<(), GovernanceError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let tip = self.node_api.get_best_block().await?;
+        let mut state = self.state.lock().await;
+        state.versions.clear();
+        self.update(&mut state, tip.height, node_api).await
+    }
+
+    /// Forget the headers above `fork_height` after a reorg replaced them. Returns the number
+    /// forgotten.
+    pub async fn roll_back_to(&self, fork_height: u64) -> u64 {
+        let mut state = self.state.lock().await;
+        state
+            .versions
+            .split_off(&fork_height.saturating_add(1))
+            .len() as u64
+    }
+
+    /// Adoption of the proposals activated by `height`, with blocks read from the node's
+    /// current chain, for epoch summaries.
+    pub async fn levels_at(&self, height: u64) -> Result<Vec<AdoptionLevel>, GovernanceError> {
+        let mut versions = BTreeMap::new();
+        let followed = self.followed(height)?;
+        self.read_window(&mut versions, height, &followed).await?;
+        self.measure(&versions, height, &followed).await
+    }
+
+    /// Measure at `height`, store the levels and send the milestones reached.
+    async fn update(
+        &self,
+        state: &mut State,
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let followed = self.followed(height)?;
+        state.versions = state.versions.split_off(&self.window_start(height));
+        self.read_window(&mut state.versions, height, &followed)
+            .await?;
+        let levels = self.measure(&state.versions, height, &followed).await?;
+        let mut milestones = self.milestones()?;
+        let mut reached = Vec::new();
+        for level in &levels {
+            let done = milestones.entry(level.proposal_id.clone()).or_default();
+            for milestone in &self.config.milestones_percent {
+                if level.percent >= *milestone && !done.contains(milestone) {
+                    info!(
+                        "Adoption of proposal {} reached {}%: {} of {} {:?}",
+                        level.proposal_id, milestone, level.adopted, level.total, level.measure
+                    );
+                    done.push(*milestone);
+                    reached.push((*milestone, level.clone()));
+                }
+            }
+        }
+        if !reached.is_empty() {
+            milestones.retain(|_, done| !done.is_empty());
+            self.save(MILESTONES_KEY, &milestones)?;
+        }
+        state.levels = levels
+            .into_iter()
+            .map(|level| (level.proposal_id.clone(), level))
+            .collect();
+        self.announce(reached, node_api).await;
+        Ok(())
+    }
+
+    /// The followed proposals activated by `height`, with their activation heights.
+    fn followed(&self, height: u64) -> Result<Vec<(String, AdoptionRule, u64)>, GovernanceError> {
+        let activated = self.proposals.activated()?;
+        Ok(self
+            .config
+            .proposals
+            .iter()
+            .filter_map(|(proposal_id, rule)| {
+                let activation = activated.get(proposal_id)?;
+                (activation.activation_height <= height).then(|| {
+                    (
+                        proposal_id.clone(),
+                        rule.clone(),
+                        activation.activation_height,
+                    )
+                })
+            })
+            .collect())
+    }
+
+    /// First height of the window ending at `height`.
+    fn window_start(&self, height: u64) -> u64 {
+        height
+            .saturating_add(1)
+            .saturating_sub(self.config.window_blocks.max(1))
+    }
+
+    /// Read the headers of the window ending at `height` missing from `versions`, from the
+    /// earliest activation of the `followed` proposals measured on blocks.
+    async fn read_window(
+        &self,
+        versions: &mut BTreeMap<u64, u32>,
+        height: u64,
+        followed: &[(String, AdoptionRule, u64)],
+    ) -> Result<(), GovernanceError> {
+        let window_start = self.window_start(height);
+        let Some(start) = followed
+            .iter()
+            .filter(|(_, rule, _)| rule.bit.is_some())
+            .map(|(_, _, activation_height)| (*activation_height).max(window_start))
+            .min()
+        else {
+            return Ok(());
+        };
+        let Some(first) = (start..=height).find(|h| !versions.contains_key(h)) else {
+            return Ok(());
+        };
+        debug!(
+            "Re-reading {} block headers for adoption",
+            height + 1 - first
+        );
+        let bulk = self.node_api.bulk();
+        let mut blocks = std::pin::pin!(bulk.stream_blocks(first..height.saturating_add(1)));
+        while let Some(block) = blocks.next().await {
+            let (height, block) = block?;
+            versions.insert(height, block.header.version as u32);
+        }
+        Ok(())
+    }
+
+    /// Adoption of the `followed` proposals at `height`, from the headers in `versions`.
+    async fn measure(
+        &self,
+        versions: &BTreeMap<u64, u32>,
+        height: u64,
+        followed: &[(String, AdoptionRule, u64)],
+    ) -> Result<Vec<AdoptionLevel>, GovernanceError> {
+        let window_start = self.window_start(height);
+        let nodes = if followed
+            .iter()
+            .any(|(_, rule, _)| rule.capability.is_some())
+        {
+            self.active_capabilities().await?
+        } else {
+            Vec::new()
+        };
+        let mut levels = Vec::new();
+        for (proposal_id, rule, activation_height) in followed {
+            let (measure, adopted, total) = match (rule.bit, &rule.capability) {
+                (Some(bit), _) => {
+                    let start = (*activation_height).max(window_start);
+                    let (adopted, total) = count_blocks(versions, start, height, bit);
+                    (Measure::Blocks, adopted, total)
+                }
+                (None, Some(capability)) => {
+                    let adopted = nodes
+                        .iter()
+                        .filter(|capabilities| capabilities.contains(capability))
+                        .count();
+                    (Measure::Nodes, adopted as u64, nodes.len() as u64)
+                }
+                (None, None) => continue,
+            };
+            levels.push(AdoptionLevel {
+                proposal_id: proposal_id.clone(),
+                measure,
+                activation_height: *activation_height,
+                height,
+                adopted,
+                total,
+                percent: percent(adopted, total),
+            });
+        }
+        Ok(levels)
+    }
+
+    /// The capabilities reported by each active registered node, none for those that have
+    /// not reported any.
+    async fn active_capabilities(&self) -> Result<Vec<BTreeSet<String>>, GovernanceError> {
+        let mut reported: BTreeMap<String, BTreeSet<String>> = self.load(CAPABILITIES_KEY)?;
+        Ok(self
+            .registry
+            .list_nodes()
+            .await
+            .into_iter()
+            .filter(|node| node.deactivated.is_none())
+            .map(|node| {
+                reported
+                    .remove(&hex::encode(node.node_id))
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    async fn announce(&self, reached: Vec<(f64, AdoptionLevel)>, node_api: &dyn NodeAPI) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        for (milestone, level) in reached {
+            let mut data = serde_json::json!(level);
+            data["milestone_percent"] = serde_json::json!(milestone);
+            let intent = webhook.record_intent(MILESTONE_EVENT_TYPE, &data);
+            webhook
+                .notify_recorded(MILESTONE_EVENT_TYPE, data, intent, node_api)
+                .await;
+        }
+    }
+
+    fn save(&self, key: &[u8], value: &impl Serialize) -> Result<(), GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(ADOPTION_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(value).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(key, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn load<T: serde::de::DeserializeOwned + Default>(
+        &self,
+        key: &[u8],
+    ) -> Result<T, GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(ADOPTION_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(key) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(T::default()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_counted_from_activation_in_the_window() {
+        let versions: BTreeMap<u64, u32> = (90..=110)
+            .map(|height| {
+                let version = if height % 2 == 0 {
+                    0x2000_0004
+                } else {
+                    0x2000_0000
+                };
+                (height, version)
+            })
+            .collect();
+        // Heights 100..=110: 6 of 11 signal bit 2
+        assert_eq!(count_blocks(&versions, 100, 110, 2), (6, 11));
+        assert_eq!(count_blocks(&versions, 100, 110, 1), (0, 11));
+        assert_eq!(percent(6, 12), 50.0);
+        assert_eq!(percent(0, 0), 0.0);
+    }
+}
@@ -0,0 +1,412 @@
+//! Error rate alerts
+//!
+//! Error reports (see [`crate::error_report`]) reach the node and the log, which nobody may be
+//! watching. The [`AlertMonitor`] pages someone instead: it counts the reported errors of each
+//! category (the error code name, such as `webhook_undeliverable`) over the last
+//! `[governance.alerts] window_secs`, and when a category reaches `count` errors or
+//! `rate_per_min` errors a minute in the window, posts one `module_alert` payload with status
+//! `firing` to `url`. That URL is separate from the data webhook, so alerts can go to a pager
+//! and are not lost with the deliveries they are about. Once the category is back below both
+//! thresholds, one `resolved` alert follows.
+//!
+//! After a firing alert, no other is raised for the category for `cooldown_secs`, however the
+//! errors come and go; a recovery is only sent for an alert that was sent. An alert that cannot
+//! be posted stays due and is tried again at the next check. The thresholds and URL apply on
+//! reload (see [`crate::config_reload`]). Alerts name the network of the node the module is
+//! connected to, once it has said (see [`crate::network`]).
+
+use crate::config::AlertConfig;
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Time between checks of the thresholds.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Longest wait for the alert URL to take an alert.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    /// The category is over a threshold.
+    Firing,
+    /// The category is back below the thresholds after a firing alert.
+    Resolved,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertThresholds {
+    pub count: u64,
+    pub rate_per_min: f64,
+}
+
+/// One alert, as posted to the alert URL (see [`crate::schema`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Alert {
+    /// Always `module_alert`.
+    pub event_type: String,
+    pub status: AlertStatus,
+    /// Error code name.
+    pub category: String,
+    pub window_secs: u64,
+    /// Errors of the category in the window.
+    pub count: u64,
+    /// Errors a minute, averaged over the window.
+    pub rate_per_min: f64,
+    pub thresholds: AlertThresholds,
+    /// Message of the latest error of the category.
+    pub sample_error: Option<String>,
+    pub node_id: Option<String>,
+    /// Network of the node, if known.
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Unix seconds.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Default)]
+struct Category {
+    /// Errors by Unix second, oldest first, within the window.
+    errors: VecDeque<(u64, u64)>,
+    /// Message of the latest error.
+    sample: Option<String>,
+    /// Whether a firing alert was sent and not yet resolved.
+    firing: bool,
+    /// When the last firing alert was sent, in Unix seconds.
+    alerted_at: Option<u64>,
+}
+
+impl Category {
+    /// Forget errors that are out of the window at `now`.
+    fn prune(&mut self, now: u64, window_secs: u64) {
+        while self
+            .errors
+            .front()
+            .is_some_and(|(at, _)| at.saturating_add(window_secs) <= now)
+        {
+            self.errors.pop_front();
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.errors.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Counts reported errors by category and posts alerts when they exceed the thresholds.
+/// Kept across connections.
+#[derive(Debug)]
+pub struct AlertMonitor {
+    config: RwLock<AlertConfig>,
+    node_id: Option<String>,
+    /// Network of the node on the current connection.
+    network: RwLock<Option<String>>,
+    client: reqwest::Client,
+    categories: Mutex<BTreeMap<String, Category>>,
+}
+
+impl Default for AlertMonitor {
+    fn default() -> Self {
+        Self::new(AlertConfig::default())
+    }
+}
+
+impl AlertMonitor {
+    pub fn new(config: AlertConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            node_id: None,
+            network: RwLock::default(),
+            client: reqwest::Client::new(),
+            categories: Mutex::default(),
+        }
+    }
+
+    /// Name the node in alerts as `node_id`.
+    pub fn with_node_id(mut self, node_id: Option<String>) -> Self {
+        self.node_id = node_id;
+        self
+    }
+
+    /// Name `network` as the node's in alerts from now on; set on each connection.
+    pub fn set_network(&self, network: Option<String>) {
+        *self.network.write().unwrap() = network;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.read().unwrap().url.is_some()
+    }
+
+    /// Take new thresholds and URL. Returns whether anything changed. Disabling alerts
+    /// forgets the errors counted so far.
+    pub fn reconfigure(&self, config: &AlertConfig) -> bool {
+        let mut current = self.config.write().unwrap();
+        if *current == *config {
+            return false;
+        }
+        if config.url.is_none() {
+            self.categories.lock().unwrap().clear();
+        }
+        *current = config.clone();
+        true
+    }
+
+    /// Count an error of `category` with `message` at `now` (Unix seconds).
+    pub fn observe(&self, category: &str, message: &str, now: u64) {
+        let window_secs = {
+            let config = self.config.read().unwrap();
+            if config.url.is_none() {
+                return;
+            }
+            config.window_secs.max(1)
+        };
+        let mut categories = self.categories.lock().unwrap();
+        let category = categories.entry(category.to_string()).or_default();
+        category.prune(now, window_secs);
+        match category.errors.back_mut() {
+            Some((at, n)) if *at == now => *n += 1,
+            _ => category.errors.push_back((now, 1)),
+        }
+        category.sample = Some(message.to_string());
+    }
+
+    /// Alerts due at `now`: a firing alert for each category over a threshold and out of
+    /// its cooldown, and a recovery for each firing one back below them.
+    pub fn due(&self, now: u64) -> Vec<Alert> {
+        let config = self.config.read().unwrap();
+        if config.url.is_none() {
+            return Vec::new();
+        }
+        let window_secs = config.window_secs.max(1);
+        let mut due = Vec::new();
+        let mut categories = self.categories.lock().unwrap();
+        for (name, category) in categories.iter_mut() {
+            category.prune(now, window_secs);
+            let count = category.count();
+            let rate_per_min = count as f64 * 60.0 / window_secs as f64;
+            let exceeded = (config.count > 0 && count >= config.count)
+                || (config.rate_per_min > 0.0 && rate_per_min >= config.rate_per_min);
+            let cooled = category
+                .alerted_at
+                .is_none_or(|at| now >= at.saturating_add(config.cooldown_secs));
+            let status = match (exceeded, category.firing) {
+                (true, false) if cooled => AlertStatus::Firing,
+                (false, true) => AlertStatus::Resolved,
+                _ => continue,
+            };
+            due.push(Alert {
+                event_type: "module_alert".to_string(),
+                status,
+                category: name.clone(),
+                window_secs,
+                count,
+                rate_per_min,
+                thresholds: AlertThresholds {
+                    count: config.count,
+                    rate_per_min: config.rate_per_min,
+                },
+                sample_error: category.sample.clone(),
+                node_id: self.node_id.clone(),
+                network: self.network.read().unwrap().clone(),
+                timestamp: now,
+            });
+        }
+        // Quiet categories are forgotten, unless an alert is in force or cooling down
+        categories.retain(|_, c| {
+            !c.errors.is_empty()
+                || c.firing
+                || c.alerted_at
+                    .is_some_and(|at| now < at.saturating_add(config.cooldown_secs))
+        });
+        due
+    }
+
+    /// Record that `alert`, taken from [`Self::due`], was posted.
+    pub fn mark_sent(&self, alert: &Alert) {
+        let mut categories = self.categories.lock().unwrap();
+        let category = categories.entry(alert.category.clone()).or_default();
+        match alert.status {
+            AlertStatus::Firing => {
+                category.firing = true;
+                category.alerted_at = Some(alert.timestamp);
+            }
+            AlertStatus::Resolved => category.firing = false,
+        }
+    }
+
+    /// Post the alerts due at `now`. Returns how many were posted; those that could not be
+    /// stay due.
+    pub async fn check(&self, now: u64) -> Result<usize, GovernanceError> {
+        let due = self.due(now);
+        let Some(url) = self.config.read().unwrap().url.clone() else {
+            return Ok(0);
+        };
+        let mut sent = 0;
+        for alert in &due {
+            let response = self
+                .client
+                .post(&url)
+                .timeout(SEND_TIMEOUT)
+                .json(alert)
+                .send()
+                .await
+                .map_err(|e| GovernanceError::webhook_request(&url, e))?;
+            if !response.status().is_success() {
+                return Err(GovernanceError::webhook_status(&url, response.status()));
+            }
+            match alert.status {
+                AlertStatus::Firing => warn!(
+                    "Alert sent: {} errors of {} in the last {}s",
+                    alert.count, alert.category, alert.window_secs
+                ),
+                AlertStatus::Resolved => info!("Alert resolved: {}", alert.category),
+            }
+            self.mark_sent(alert);
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Check the thresholds every [`CHECK_INTERVAL`] until the task is aborted.
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let monitor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut failing = false;
+            loop {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                match monitor.check(crate::clock::unix_now()).await {
+                    Ok(_) => failing = false,
+                    Err(e) => {
+                        // Once per outage; the alerts stay due
+                        if !failing {
+                            warn!("Failed to send alert: {}", e.chain());
+                        }
+                        failing = true;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(count: u64, rate_per_min: f64) -> AlertMonitor {
+        AlertMonitor::new(AlertConfig {
+            url: Some("http://127.0.0.1:1/alerts".to_string()),
+            window_secs: 60,
+            count,
+            rate_per_min,
+            cooldown_secs: 600,
+        })
+    }
+
+    fn statuses(alerts: &[Alert]) -> Vec<(&str, AlertStatus)> {
+        alerts
+            .iter()
+            .map(|a| (a.category.as_str(), a.status))
+            .collect()
+    }
+
+    #[test]
+    fn test_count_in_window() {
+        let alerts = monitor(3, 0.0);
+        alerts.observe("webhook_undeliverable", "refused", 0);
+        alerts.observe("webhook_undeliverable", "refused", 30);
+        assert!(alerts.due(30).is_empty());
+        // The first has left the window
+        alerts.observe("webhook_undeliverable", "timed out", 61);
+        assert!(alerts.due(61).is_empty());
+        alerts.observe("webhook_undeliverable", "timed out", 62);
+        let due = alerts.due(62);
+        assert_eq!(
+            statuses(&due),
+            vec![("webhook_undeliverable", AlertStatus::Firing)]
+        );
+        assert_eq!(due[0].count, 3);
+        assert_eq!(due[0].sample_error.as_deref(), Some("timed out"));
+        // Not marked sent: still due
+        assert_eq!(alerts.due(63).len(), 1);
+    }
+
+    #[test]
+    fn test_rate_threshold() {
+        let alerts = monitor(0, 5.0);
+        for _ in 0..4 {
+            alerts.observe("registry_write_failed", "disk full", 10);
+        }
+        assert!(alerts.due(10).is_empty());
+        alerts.observe("registry_write_failed", "disk full", 11);
+        let due = alerts.due(11);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].rate_per_min, 5.0);
+    }
+
+    #[test]
+    fn test_cooldown_limits_alerts_per_category() {
+        let alerts = monitor(2, 0.0);
+        let burst = |at: u64| {
+            alerts.observe("webhook_undeliverable", "503", at);
+            alerts.observe("webhook_undeliverable", "503", at);
+        };
+        burst(0);
+        let due = alerts.due(0);
+        alerts.mark_sent(&due[0]);
+        assert!(alerts.due(1).is_empty());
+
+        // Recovered, then failing again within the cooldown: recovery only
+        let due = alerts.due(100);
+        assert_eq!(
+            statuses(&due),
+            vec![("webhook_undeliverable", AlertStatus::Resolved)]
+        );
+        alerts.mark_sent(&due[0]);
+        burst(200);
+        assert!(alerts.due(200).is_empty());
+
+        // Other categories are not held back
+        burst(599);
+        alerts.observe("handler_failed", "no such proposal", 599);
+        alerts.observe("handler_failed", "no such proposal", 599);
+        assert_eq!(
+            statuses(&alerts.due(599)),
+            vec![("handler_failed", AlertStatus::Firing)]
+        );
+
+        // Raised again once the cooldown has passed, if still failing
+        assert_eq!(
+            statuses(&alerts.due(600)),
+            vec![
+                ("handler_failed", AlertStatus::Firing),
+                ("webhook_undeliverable", AlertStatus::Firing)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let alerts = monitor(10, 0.0);
+        for _ in 0..5 {
+            alerts.observe("webhook_undeliverable", "503", 0);
+        }
+        assert!(alerts.due(0).is_empty());
+        let mut config = alerts.config.read().unwrap().clone();
+        assert!(!alerts.reconfigure(&config));
+        config.count = 5;
+        assert!(alerts.reconfigure(&config));
+        assert_eq!(alerts.due(0).len(), 1);
+
+        config.url = None;
+        assert!(alerts.reconfigure(&config));
+        alerts.observe("webhook_undeliverable", "503", 0);
+        assert!(alerts.due(0).is_empty());
+        assert!(alerts.categories.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+//! Proposal amendments
+//!
+//! Proposals get amended while they are voted on: the content hash changes, or the proposal is
+//! moved to another tier. The node publishes no event for it, so the proposal store compares
+//! the node's record of a proposal it already has with the content hash and tier it keeps for
+//! it, whenever it looks the proposal up: on its created event, on
+//! [`ProposalStore::refresh`] and, with `[governance.amendments] refresh_interval_blocks` set,
+//! for every open proposal every that many blocks ([`ProposalStore::refresh_open`]).
+//!
+//! A change is an [`Amendment`], added to the proposal's history
+//! ([`ProposalStore::amendments`]) and sent to the webhook as `proposal_amended`. A new
+//! content hash is checked against its commitment again when there is a content verifier
+//! (see [`crate::content`]); the earlier check no longer applies either way. A new tier takes
+//! that tier's tally thresholds and voting window, so its milestones are reached anew and its
+//! deadline and reminders follow the new window; the registry's veto threshold is the same
+//! for every tier. The votes cast before are kept or dropped by `votes` ([`AmendedVotes`]);
+//! dropped votes must be cast again.
+//!
+//! Once voting has closed, the proposal merged, rejected or expired or its deadline reached,
+//! a change is not taken: it is added to the history as rejected and alerted on as
+//! `amendment_rejected`, once for as long as the node reports the same change.
+//!
+//! [`ProposalStore::refresh`]: crate::proposals::ProposalStore::refresh
+//! [`ProposalStore::refresh_open`]: crate::proposals::ProposalStore::refresh_open
+//! [`ProposalStore::amendments`]: crate::proposals::ProposalStore::amendments
+
+use crate::config::AmendedVotes;
+use crate::node_api::ProposalDetails;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Event sent to the webhook when a proposal is amended.
+pub const AMENDED_EVENT: &str = "proposal_amended";
+
+/// Alert sent to the webhook when an amendment comes after voting closed.
+pub const REJECTED_EVENT: &str = "amendment_rejected";
+
+/// A value before and after an amendment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+/// One amendment of a proposal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amendment {
+    /// Chain tip when it was found.
+    pub height: Option<u64>,
+    /// The content hash, if it changed.
+    pub content_hash: Option<Change<Option<String>>>,
+    /// The tier, if it changed.
+    pub tier: Option<Change<String>>,
+    /// Whether it was taken; not once voting had closed.
+    pub accepted: bool,
+    /// Votes dropped with it.
+    pub votes_reset: u64,
+}
+
+impl Amendment {
+    /// The amendment `details` makes to a proposal of `tier` with the content hash
+    /// `content_hash`, if it changes either.
+    pub fn between(
+        content_hash: &Option<String>,
+        tier: &str,
+        details: &ProposalDetails,
+        height: Option<u64>,
+    ) -> Option<Self> {
+        let content_hash = (*content_hash != details.content_hash).then(|| Change {
+            from: content_hash.clone(),
+            to: details.content_hash.clone(),
+        });
+        let tier = (tier != details.tier).then(|| Change {
+            from: tier.to_string(),
+            to: details.tier.clone(),
+        });
+        if content_hash.is_none() && tier.is_none() {
+            return None;
+        }
+        Some(Self {
+            height,
+            content_hash,
+            tier,
+            accepted: true,
+            votes_reset: 0,
+        })
+    }
+
+    /// Whether the votes cast before it are dropped under `policy`.
+    pub fn resets_votes(&self, policy: AmendedVotes) -> bool {
+        match policy {
+            AmendedVotes::Keep => false,
+            AmendedVotes::ResetOnContent => self.content_hash.is_some(),
+            AmendedVotes::Reset => true,
+        }
+    }
+
+    /// Whether it changes the same values to the same values as `other`.
+    fn same_change(&self, other: &Amendment) -> bool {
+        self.content_hash == other.content_hash && self.tier == other.tier
+    }
+}
+
+/// What changed, e.g. "tier standard to core".
+impl fmt::Display for Amendment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hash = |hash: &Option<String>| hash.clone().unwrap_or_else(|| "none".to_string());
+        let mut changes = Vec::new();
+        if let Some(change) = &self.content_hash {
+            changes.push(format!(
+                "content hash {} to {}",
+                hash(&change.from),
+                hash(&change.to)
+            ));
+        }
+        if let Some(change) = &self.tier {
+            changes.push(format!("tier {} to {}", change.from, change.to));
+        }
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+/// The amendments of every proposal, and the content hash each has. Stored with the
+/// proposals.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Amendments {
+    /// Content hash of each proposal as first recorded or last amended, by id.
+    content_hashes: HashMap<String, Option<String>>,
+    /// Amendments by proposal id, oldest first.
+    history: HashMap<String, Vec<Amendment>>,
+}
+
+impl Amendments {
+    /// The content hash of `proposal_id`, if it has been recorded.
+    pub fn content_hash(&self, proposal_id: &str) -> Option<&Option<String>> {
+        self.content_hashes.get(proposal_id)
+    }
+
+    /// The amendments of `proposal_id`, oldest first.
+    pub fn history(&self, proposal_id: &str) -> &[Amendment] {
+        self.history.get(proposal_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Record the content hash of `details` as its proposal's, unless one is recorded; a
+    /// proposal's first record is not an amendment.
+    pub fn record_first(&mut self, details: &ProposalDetails) {
+        self.content_hashes
+            .entry(details.proposal_id.clone())
+            .or_insert_with(|| details.content_hash.clone());
+    }
+
+    /// The amendment `details` makes to its proposal, of `tier`, found at `height`, if it
+    /// changes the content hash or the tier; rejected if voting is `closed`. A rejected one
+    /// is only found once while the node reports the same change.
+    pub fn find(
+        &mut self,
+        tier: &str,
+        details: &ProposalDetails,
+        height: Option<u64>,
+        closed: bool,
+    ) -> Option<Amendment> {
+        self.record_first(details);
+        let id = &details.proposal_id;
+        let mut amendment = Amendment::between(&self.content_hashes[id], tier, details, height)?;
+        if closed {
+            let last = self.history(id).last();
+            if last.is_some_and(|last| !last.accepted && last.same_change(&amendment)) {
+                return None;
+            }
+            amendment.accepted = false;
+        }
+        Some(amendment)
+    }
+
+    /// Add `amendment` to the history of `proposal_id`, and its content hash as the
+    /// proposal's if it was taken.
+    pub fn add(&mut self, proposal_id: &str, amendment: Amendment) {
+        if let (true, Some(change)) = (amendment.accepted, &amendment.content_hash) {
+            self.content_hashes
+                .insert(proposal_id.to_string(), change.to.clone());
+        }
+        self.history
+            .entry(proposal_id.to_string())
+            .or_default()
+            .push(amendment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(content_hash: &str, tier: &str) -> ProposalDetails {
+        ProposalDetails {
+            proposal_id: "1".to_string(),
+            title: "Proposal 1".to_string(),
+            description: None,
+            content_hash: Some(content_hash.to_string()),
+            commitment_txid: None,
+            content_url: None,
+            tier: tier.to_string(),
+            author: "alice".to_string(),
+            status: "open".to_string(),
+            created_height: 100,
+            conflict_domain: None,
+            conflicts_with: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_amendments_found_once() {
+        let mut amendments = Amendments::default();
+        // The first record is not an amendment
+        assert_eq!(
+            amendments.find("standard", &details("aa", "standard"), Some(100), false),
+            None
+        );
+        let amendment = amendments
+            .find("standard", &details("bb", "core"), Some(110), false)
+            .unwrap();
+        assert!(amendment.accepted);
+        assert_eq!(
+            amendment.to_string(),
+            "content hash aa to bb, tier standard to core"
+        );
+        assert!(amendment.resets_votes(AmendedVotes::ResetOnContent));
+        assert!(!amendment.resets_votes(AmendedVotes::Keep));
+        amendments.add("1", amendment);
+        assert_eq!(amendments.content_hash("1"), Some(&Some("bb".to_string())));
+        assert_eq!(
+            amendments.find("core", &details("bb", "core"), Some(120), false),
+            None
+        );
+
+        // After voting closed, rejected and alerted on once
+        let rejected = amendments
+            .find("core", &details("bb", "standard"), Some(130), true)
+            .unwrap();
+        assert!(!rejected.accepted);
+        amendments.add("1", rejected);
+        assert_eq!(
+            amendments.find("core", &details("bb", "standard"), Some(140), true),
+            None
+        );
+        assert_eq!(amendments.content_hash("1"), Some(&Some("bb".to_string())));
+        assert_eq!(amendments.history("1").len(), 2);
+        assert!(amendments
+            .find("core", &details("cc", "core"), Some(150), true)
+            .is_some());
+    }
+}
@@ -0,0 +1,644 @@
+//! Governance state anchored on-chain
+//!
+//! With `[governance.anchor] enabled` (which requires `allow_actions`), the [`Anchorer`]
+//! commits to the governance state every `interval_blocks` blocks, at each `NewBlock` whose
+//! height is a multiple of it, in an `OP_RETURN` output of a transaction the node builds, pays
+//! for from its wallet and broadcasts:
+//!
+//! 1. the fee rate is the node's estimate for `confirmation_target_blocks`
+//!    ([`NodeApiIpc::estimate_fee_rate`]), or its minimum relay fee when it has no estimate,
+//!    capped at `max_fee_rate_sat_per_vbyte`;
+//! 2. the node builds and signs the transaction ([`NodeApiIpc::fund_op_return`]), then
+//!    broadcasts it ([`NodeApiIpc::broadcast_transaction`]);
+//! 3. at each later block the transaction is looked up ([`NodeApiIpc::get_transaction`])
+//!    until it has `confirmations`.
+//!
+//! Each failed attempt raises an `anchor_failed` error report (see [`crate::error_report`])
+//! and is tried again at the next block, up to `max_attempts` in all. Failures retrying
+//! cannot fix, such as [`GovernanceError::InsufficientFunds`] or a node without a
+//! transaction index to confirm with, give the anchor up at once, as does a transaction not
+//! confirmed `confirmation_timeout_blocks` after its broadcast. A transaction is never
+//! rebroadcast: the next interval anchors the state afresh. With `--dry-run=all` the
+//! commitment is logged and nothing is sent. While the module is paused (see
+//! [`crate::pause`]) broadcast anchors are still checked for confirmation, but pending ones
+//! are not tried again and no interval is anchored.
+//!
+//! # Format
+//!
+//! A commitment is [`ANCHOR_MAGIC`], the version byte [`ANCHOR_VERSION`], the height the
+//! state was taken at (8 bytes, little-endian), the registry root and the proposals hash, 77
+//! bytes in a single push ([`AnchorData::script`]):
+//!
+//! ```text
+//! OP_RETURN OP_PUSHDATA1 77 <"BLVA" 0x01 <height> <32-byte registry root> <32-byte proposals hash>>
+//! ```
+//!
+//! In version 1, the registry root is the merkle root of the economic node registry (see
+//! [`crate::economic_nodes::commitment`], whose own encoding is version 1), and the proposals
+//! hash is the SHA-256 of the proposal store's records in proposal id order, each encoded
+//! with bincode as stored ([`proposals_hash`]). Both are taken when the block is handled, so
+//! they cover the events of that block and before. Any change to the layout or to either
+//! encoding takes a new version; readers must ignore versions they do not know.
+//!
+//! Anchors are stored in the module database, the latest [`ANCHORS_KEPT`] of them, each with
+//! its commitment, txid and outcome. Every broadcast, confirmation and anchor given up is
+//! recorded in the audit log (see [`crate::audit_log`]), and the latest anchor is in the
+//! `anchor` section of the status report. When the pipeline rolls back a reorg
+//! ([`Anchorer::roll_back_to`]), anchors confirmed above the fork wait for confirmation
+//! again, and anchors of blocks above the fork not yet broadcast are dropped.
+
+use crate::audit_log::AuditLog;
+use crate::config::AnchorConfig;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::error_report::{ErrorCode, ErrorReport, ErrorReporter};
+use crate::node_api::{FeeRate, FundedTransaction, NodeApiIpc};
+use crate::proposals::{GovernanceProposal, ProposalStore};
+use blvm_protocol::Hash;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const ANCHORS_TREE: &str = "anchors";
+
+const ANCHORS_KEY: &[u8] = b"anchors";
+
+/// Anchors kept in the database; older ones are dropped.
+pub const ANCHORS_KEPT: usize = 256;
+
+/// First bytes of a commitment's data.
+pub const ANCHOR_MAGIC: &[u8; 4] = b"BLVA";
+
+/// Version of the commitment format, after the magic.
+pub const ANCHOR_VERSION: u8 = 0x01;
+
+/// Length of a version 1 commitment's data.
+const DATA_LEN: usize = 4 + 1 + 8 + 32 + 32;
+
+const OP_RETURN: u8 = 0x6a;
+const OP_PUSHDATA1: u8 = 0x4c;
+
+/// SHA-256 over `proposals` in proposal id order, each encoded with bincode.
+pub fn proposals_hash(proposals: &[GovernanceProposal]) -> Result<Hash, GovernanceError> {
+    let mut sorted: Vec<&GovernanceProposal> = proposals.iter().collect();
+    sorted.sort_by(|a, b| a.proposal_id.cmp(&b.proposal_id));
+    let mut hasher = Sha256::new();
+    for proposal in sorted {
+        let encoded =
+            bincode::serialize(proposal).map_err(GovernanceError::encoding("serialize"))?;
+        hasher.update(encoded);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// What a commitment commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorData {
+    pub height: u64,
+    pub registry_root: Hash,
+    pub proposals_hash: Hash,
+}
+
+impl AnchorData {
+    /// The data pushed, magic and version first.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(DATA_LEN);
+        data.extend_from_slice(ANCHOR_MAGIC);
+        data.push(ANCHOR_VERSION);
+        data.extend_from_slice(&self.height.to_le_bytes());
+        data.extend_from_slice(&self.registry_root);
+        data.extend_from_slice(&self.proposals_hash);
+        data
+    }
+
+    /// The `OP_RETURN` script of the commitment.
+    pub fn script(&self) -> Vec<u8> {
+        let mut script = vec![OP_RETURN, OP_PUSHDATA1, DATA_LEN as u8];
+        script.extend(self.to_bytes());
+        script
+    }
+
+    /// The commitment `script` carries, if it is a version 1 commitment.
+    pub fn from_script(script: &[u8]) -> Option<Self> {
+        let data = crate::content::op_return_data(script)?;
+        let rest = data
+            .strip_prefix(ANCHOR_MAGIC.as_slice())?
+            .strip_prefix(&[ANCHOR_VERSION])?;
+        if rest.len() != DATA_LEN - ANCHOR_MAGIC.len() - 1 {
+            return None;
+        }
+        Some(Self {
+            height: u64::from_le_bytes(rest[..8].try_into().ok()?),
+            registry_root: rest[8..40].try_into().ok()?,
+            proposals_hash: rest[40..].try_into().ok()?,
+        })
+    }
+}
+
+/// Where an anchor is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorStatus {
+    /// Not broadcast yet; tried again at the next block.
+    Pending,
+    /// Broadcast, waiting for confirmations.
+    Broadcast,
+    Confirmed,
+    /// Given up.
+    Failed,
+}
+
+impl AnchorStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Broadcast => "broadcast",
+            Self::Confirmed => "confirmed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// One anchor of the governance state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    /// Height the state was taken at.
+    pub height: u64,
+    pub version: u8,
+    /// Hex.
+    pub registry_root: String,
+    pub node_count: usize,
+    /// Hex.
+    pub proposals_hash: String,
+    pub proposal_count: usize,
+    pub status: AnchorStatus,
+    pub attempts: u32,
+    /// Hex, once broadcast.
+    pub txid: Option<String>,
+    pub fee_sats: Option<u64>,
+    /// Tip when the transaction was broadcast, or the fork of a reorg that took its
+    /// confirmation away.
+    pub broadcast_height: Option<u64>,
+    /// Hex hash of the block the transaction confirmed in.
+    pub block_hash: Option<String>,
+    pub confirmed_height: Option<u64>,
+    /// Why the last attempt failed, or why the anchor was given up.
+    pub error: Option<String>,
+}
+
+impl Anchor {
+    fn new(data: &AnchorData, node_count: usize, proposal_count: usize) -> Self {
+        Self {
+            height: data.height,
+            version: ANCHOR_VERSION,
+            registry_root: hex::encode(data.registry_root),
+            node_count,
+            proposals_hash: hex::encode(data.proposals_hash),
+            proposal_count,
+            status: AnchorStatus::Pending,
+            attempts: 0,
+            txid: None,
+            fee_sats: None,
+            broadcast_height: None,
+            block_hash: None,
+            confirmed_height: None,
+            error: None,
+        }
+    }
+
+    /// What the anchor commits to.
+    pub fn data(&self) -> Option<AnchorData> {
+        let hash = |hex: &str| -> Option<Hash> { hex::decode(hex).ok()?.try_into().ok() };
+        Some(AnchorData {
+            height: self.height,
+            registry_root: hash(&self.registry_root)?,
+            proposals_hash: hash(&self.proposals_hash)?,
+        })
+    }
+}
+
+/// The `anchor` section of the status report.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnchorStatusReport {
+    pub interval_blocks: u64,
+    pub latest: Option<Anchor>,
+    pub last_confirmed: Option<Anchor>,
+    /// Anchors kept, by status.
+    pub anchors: BTreeMap<String, u64>,
+}
+
+/// Anchors the governance state every `interval_blocks` blocks.
+pub struct Anchorer {
+    config: AnchorConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    node_api: NodeApiIpc,
+    registry: Arc<EconomicNodeRegistry>,
+    proposals: Arc<ProposalStore>,
+    audit_log: Option<Arc<AuditLog>>,
+    errors: Option<Arc<ErrorReporter>>,
+    dry_run: bool,
+    /// Held while a block is handled, so each anchor is made once.
+    update: tokio::sync::Mutex<()>,
+}
+
+impl Anchorer {
+    pub fn new(
+        config: AnchorConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+        node_api: NodeApiIpc,
+        registry: Arc<EconomicNodeRegistry>,
+        proposals: Arc<ProposalStore>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            node_api,
+            registry,
+            proposals,
+            audit_log: None,
+            errors: None,
+            dry_run: false,
+            update: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Record broadcasts, confirmations and anchors given up in `log`.
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Report failures to `errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Log commitments instead of sending them.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Whether anchoring is enabled, so blocks are needed.
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.config.interval_blocks > 0
+    }
+
+    /// Stored anchors, oldest first.
+    pub fn anchors(&self) -> Result<Vec<Anchor>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.into_values().collect())
+    }
+
+    /// The stored anchor of the state at `height`, if one was made.
+    pub fn anchor(&self, height: u64) -> Result<Option<Anchor>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.remove(&height))
+    }
+
+    /// At the `NewBlock` at `height`: check broadcast anchors for confirmation, try pending
+    /// ones again, and anchor the state if `height` is on the interval.
+    pub async fn handle_block(&self, height: u64) -> Result<(), GovernanceError> {
+        let _update = self.update.lock().await;
+        let mut anchors = Self::load_from(&self.db)?;
+        let mut changed = false;
+        let paused = self.node_api.is_paused();
+        for anchor in anchors.values_mut() {
+            match anchor.status {
+                AnchorStatus::Broadcast => changed |= self.check_confirmation(anchor, height).await,
+                AnchorStatus::Pending if anchor.height < height && !paused => {
+                    self.attempt(anchor, height).await;
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        if paused && height.is_multiple_of(self.config.interval_blocks) {
+            info!("Paused, state at height {} not anchored", height);
+        } else if height.is_multiple_of(self.config.interval_blocks)
+            && !anchors.contains_key(&height)
+        {
+            let (data, node_count, proposal_count) = self.commitment(height).await?;
+            if self.dry_run {
+                info!(
+                    "Dry run, anchor of height {} not broadcast: {}",
+                    height,
+                    hex::encode(data.script())
+                );
+            } else {
+                let mut anchor = Anchor::new(&data, node_count, proposal_count);
+                self.attempt(&mut anchor, height).await;
+                anchors.insert(height, anchor);
+                changed = true;
+            }
+        }
+        while anchors.len() > ANCHORS_KEPT {
+            anchors.pop_first();
+        }
+        if changed {
+            self.save(&anchors)?;
+        }
+        Ok(())
+    }
+
+    /// The state at `height`, and the number of nodes and proposals it covers.
+    async fn commitment(&self, height: u64) -> Result<(AnchorData, usize, usize), GovernanceError> {
+        let registry = self.registry.commitment().await;
+        let proposals = self.proposals.load_proposals()?;
+        let data = AnchorData {
+            height,
+            registry_root: registry.root,
+            proposals_hash: proposals_hash(&proposals)?,
+        };
+        Ok((data, registry.node_count, proposals.len()))
+    }
+
+    /// Have the node build and broadcast `anchor`, at the tip `height`.
+    async fn attempt(&self, anchor: &mut Anchor, height: u64) {
+        anchor.attempts += 1;
+        let result = match anchor.data() {
+            Some(data) => self.broadcast(&data).await,
+            None => Err(GovernanceError::Storage(format!(
+                "anchor of height {} has an invalid commitment",
+                anchor.height
+            ))),
+        };
+        match result {
+            Ok(tx) => {
+                info!(
+                    "Anchored the state of height {} in {} ({} sats)",
+                    anchor.height,
+                    hex::encode(tx.txid),
+                    tx.fee_sats
+                );
+                anchor.status = AnchorStatus::Broadcast;
+                anchor.txid = Some(hex::encode(tx.txid));
+                anchor.fee_sats = Some(tx.fee_sats);
+                anchor.broadcast_height = Some(height);
+                anchor.error = None;
+                self.audit(anchor);
+            }
+            Err(e) => {
+                let retry = e.retryability().should_retry(anchor.attempts)
+                    && anchor.attempts < self.config.max_attempts;
+                let message = format!("attempt {}: {}", anchor.attempts, e.chain());
+                if retry {
+                    warn!(
+                        "Failed to anchor the state of height {}, trying again at the next block: {}",
+                        anchor.height, message
+                    );
+                    self.report(anchor, &message);
+                    anchor.error = Some(message);
+                } else {
+                    self.give_up(anchor, message);
+                }
+            }
+        }
+    }
+
+    /// Build and broadcast the transaction of `data`.
+    async fn broadcast(&self, data: &AnchorData) -> Result<FundedTransaction, GovernanceError> {
+        let fee_rate = self.fee_rate().await?;
+        let tx = self
+            .node_api
+            .fund_op_return(&data.to_bytes(), fee_rate)
+            .await?;
+        self.node_api.broadcast_transaction(&tx).await?;
+        Ok(tx)
+    }
+
+    /// Fee rate to pay: the estimate for the confirmation target, else the minimum relay fee,
+    /// within the configured limit.
+    async fn fee_rate(&self) -> Result<FeeRate, GovernanceError> {
+        let target = self.config.confirmation_target_blocks;
+        let rate = match self.node_api.estimate_fee_rate(target).await {
+            Ok(rate) => rate,
+            Err(GovernanceError::FeeEstimateUnavailable { .. }) => {
+                self.node_api.get_min_relay_fee().await?
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(match self.config.max_fee_rate_sat_per_vbyte {
+            0 => rate,
+            max => rate.min(FeeRate::from_sat_per_vbyte(max)),
+        })
+    }
+
+    /// Look up the transaction of `anchor` at the tip `height`. Returns whether the anchor
+    /// changed.
+    async fn check_confirmation(&self, anchor: &mut Anchor, height: u64) -> bool {
+        let txid = anchor
+            .txid
+            .as_deref()
+            .and_then(|txid| hex::decode(txid).ok())
+            .and_then(|txid| Hash::try_from(txid).ok());
+        let Some(txid) = txid else {
+            self.give_up(anchor, "invalid txid".to_string());
+            return true;
+        };
+        match self.node_api.get_transaction(&txid).await {
+            Ok(Some(info)) if info.confirmations >= self.config.confirmations => {
+                info!(
+                    "Anchor of height {} confirmed in block {} at height {}",
+                    anchor.height,
+                    hex::encode(info.block_hash),
+                    info.height
+                );
+                anchor.status = AnchorStatus::Confirmed;
+                anchor.block_hash = Some(hex::encode(info.block_hash));
+                anchor.confirmed_height = Some(info.height);
+                self.audit(anchor);
+                return true;
+            }
+            Ok(_) => {}
+            Err(e @ GovernanceError::NotIndexed { .. }) => {
+                self.give_up(anchor, format!("cannot confirm: {}", e));
+                return true;
+            }
+            Err(e) => warn!(
+                "Failed to look up anchor {}: {}",
+                hex::encode(txid),
+                e.chain()
+            ),
+        }
+        let waited = height.saturating_sub(anchor.broadcast_height.unwrap_or(height));
+        if waited < self.config.confirmation_timeout_blocks {
+            return false;
+        }
+        self.give_up(
+            anchor,
+            format!("not confirmed {} blocks after its broadcast", waited),
+        );
+        true
+    }
+
+    fn give_up(&self, anchor: &mut Anchor, reason: String) {
+        warn!(
+            "Gave up anchoring the state of height {}: {}",
+            anchor.height, reason
+        );
+        self.report(anchor, &reason);
+        anchor.status = AnchorStatus::Failed;
+        anchor.error = Some(reason);
+        self.audit(anchor);
+    }
+
+    fn report(&self, anchor: &Anchor, message: &str) {
+        if let Some(errors) = &self.errors {
+            errors.report(
+                ErrorReport::new(ErrorCode::AnchorFailed, message)
+                    .with_context("height", anchor.height.to_string()),
+            );
+        }
+    }
+
+    fn audit(&self, anchor: &Anchor) {
+        if let Some(log) = &self.audit_log {
+            log.anchor(anchor);
+        }
+    }
+
+    /// Put anchors confirmed above `fork_height` back to waiting for confirmation, and drop
+    /// those of blocks above it not yet broadcast. Returns the number of anchors changed.
+    pub async fn roll_back_to(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        let _update = self.update.lock().await;
+        let mut anchors = Self::load_from(&self.db)?;
+        let before = anchors.len();
+        anchors.retain(|height, anchor| {
+            *height <= fork_height || anchor.status != AnchorStatus::Pending
+        });
+        let mut reverted = (before - anchors.len()) as u64;
+        for anchor in anchors.values_mut() {
+            if anchor.status == AnchorStatus::Confirmed
+                && anchor.confirmed_height.is_some_and(|h| h > fork_height)
+            {
+                anchor.status = AnchorStatus::Broadcast;
+                anchor.block_hash = None;
+                anchor.confirmed_height = None;
+                anchor.broadcast_height = Some(fork_height);
+                reverted += 1;
+            }
+        }
+        if reverted > 0 {
+            self.save(&anchors)?;
+        }
+        Ok(reverted)
+    }
+
+    fn save(&self, anchors: &BTreeMap<u64, Anchor>) -> Result<(), GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(ANCHORS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(anchors).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(ANCHORS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    /// Load stored anchors, by height.
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<BTreeMap<u64, Anchor>, GovernanceError> {
+        let tree = db
+            .open_tree(ANCHORS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(ANCHORS_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(BTreeMap::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    /// The `anchor` status section.
+    pub fn status_report(&self) -> Result<AnchorStatusReport, GovernanceError> {
+        let anchors = Self::load_from(&self.db)?;
+        let mut counts = BTreeMap::new();
+        for anchor in anchors.values() {
+            *counts
+                .entry(anchor.status.as_str().to_string())
+                .or_default() += 1;
+        }
+        let last_confirmed = anchors
+            .values()
+            .rev()
+            .find(|a| a.status == AnchorStatus::Confirmed)
+            .cloned();
+        Ok(AnchorStatusReport {
+            interval_blocks: self.config.interval_blocks,
+            latest: anchors.into_values().next_back(),
+            last_confirmed,
+            anchors: counts,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::status::StatusProvider for Anchorer {
+    fn section(&self) -> &'static str {
+        "anchor"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        match self.status_report() {
+            Ok(report) => crate::status::section(&report),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_script_round_trip() {
+        let data = AnchorData {
+            height: 288,
+            registry_root: [1; 32],
+            proposals_hash: [2; 32],
+        };
+        let script = data.script();
+        assert_eq!(script.len(), 80);
+        assert_eq!(&script[..3], &[OP_RETURN, OP_PUSHDATA1, 77]);
+        assert_eq!(&script[3..8], b"BLVA\x01");
+        assert_eq!(&script[8..16], &288u64.to_le_bytes());
+        assert_eq!(AnchorData::from_script(&script), Some(data));
+
+        let mut other_version = script.clone();
+        other_version[7] = 0x02;
+        assert_eq!(AnchorData::from_script(&other_version), None);
+        assert_eq!(AnchorData::from_script(&script[..79]), None);
+    }
+
+    #[test]
+    fn test_proposals_hash_ignores_order() {
+        let proposal = |id: &str| GovernanceProposal {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+            status: crate::proposals::ProposalStatus::Voting,
+            votes: Vec::new(),
+            author: None,
+            created_height: Some(100),
+            closed_height: None,
+            milestones: Vec::new(),
+        };
+        let hash = proposals_hash(&[proposal("1"), proposal("2")]).unwrap();
+        assert_eq!(
+            proposals_hash(&[proposal("2"), proposal("1")]).unwrap(),
+            hash
+        );
+        assert_ne!(proposals_hash(&[proposal("1")]).unwrap(), hash);
+        let mut voted = proposal("2");
+        voted.votes.push(crate::proposals::ProposalVote {
+            voter: "alice".to_string(),
+            vote: "yes".to_string(),
+        });
+        assert_ne!(proposals_hash(&[proposal("1"), voted]).unwrap(), hash);
+    }
+}
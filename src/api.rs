@@ -11,24 +11,138 @@ use std::sync::Arc;
 pub struct GovernanceModuleApi {
     proposal_store: Arc<crate::proposals::ProposalStore>,
     economic_nodes: Arc<crate::economic_nodes::EconomicNodeRegistry>,
-    webhook_url: Option<String>,
+    webhook: Arc<crate::webhook::GovernanceWebhookClient>,
     node_api: Arc<dyn NodeAPI>,
+    heartbeat: Option<Arc<crate::heartbeat::Heartbeat>>,
+    events: Option<Arc<crate::event_queue::EventQueue>>,
+    subscriptions: Option<Arc<crate::subscriptions::EventSubscriptions>>,
+    stream: Option<Arc<crate::event_stream::EventStreamMonitor>>,
+    metrics: Option<Arc<crate::ipc_metrics::IpcMetrics>>,
+    pipeline: Option<Arc<crate::pipeline::Pipeline>>,
+    shutdown: Option<Arc<crate::shutdown::Shutdown>>,
+    config_reload: Option<Arc<crate::config_reload::ConfigReloader>>,
+    epoch_summaries: Option<Arc<crate::epoch_summary::EpochSummarizer>>,
+    delegations: Option<Arc<crate::delegation::DelegationRegistry>>,
+    adoption: Option<Arc<crate::adoption::AdoptionTracker>>,
 }
 
 impl GovernanceModuleApi {
     pub fn new(
         proposal_store: Arc<crate::proposals::ProposalStore>,
         economic_nodes: Arc<crate::economic_nodes::EconomicNodeRegistry>,
-        webhook_url: Option<String>,
+        webhook: Arc<crate::webhook::GovernanceWebhookClient>,
         node_api: Arc<dyn NodeAPI>,
     ) -> Self {
         Self {
             proposal_store,
             economic_nodes,
-            webhook_url,
+            webhook,
             node_api,
+            heartbeat: None,
+            events: None,
+            subscriptions: None,
+            stream: None,
+            metrics: None,
+            pipeline: None,
+            shutdown: None,
+            config_reload: None,
+            epoch_summaries: None,
+            delegations: None,
+            adoption: None,
         }
     }
+
+    /// Report IPC liveness from `heartbeat` in `get_ipc_status`.
+    pub fn with_heartbeat(mut self, heartbeat: Arc<crate::heartbeat::Heartbeat>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Report event queue counters in `get_ipc_status`.
+    pub fn with_event_queue(mut self, events: Arc<crate::event_queue::EventQueue>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Allow event subscriptions to be read and changed through `get_event_subscriptions`
+    /// and `set_event_subscriptions`.
+    pub fn with_subscriptions(
+        mut self,
+        subscriptions: Arc<crate::subscriptions::EventSubscriptions>,
+    ) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    /// Report missed-event detection in `get_ipc_status`.
+    pub fn with_event_stream(mut self, stream: Arc<crate::event_stream::EventStreamMonitor>) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Report request latency and event counts in `get_ipc_status`.
+    pub fn with_ipc_metrics(mut self, metrics: Arc<crate::ipc_metrics::IpcMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Report per-handler counters and events dropped at dispatch because no handler needs
+    /// them in `get_ipc_status`.
+    pub fn with_pipeline(mut self, pipeline: Arc<crate::pipeline::Pipeline>) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    /// Let the node stop the module gracefully through `shutdown`.
+    pub fn with_shutdown(mut self, shutdown: Arc<crate::shutdown::Shutdown>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Let the node have the configuration file re-read and applied through `reload_config`.
+    pub fn with_config_reload(
+        mut self,
+        config_reload: Arc<crate::config_reload::ConfigReloader>,
+    ) -> Self {
+        self.config_reload = Some(config_reload);
+        self
+    }
+
+    /// Serve stored epoch summaries through `list_epoch_summaries` and `get_epoch_summary`.
+    pub fn with_epoch_summaries(
+        mut self,
+        epoch_summaries: Arc<crate::epoch_summary::EpochSummarizer>,
+    ) -> Self {
+        self.epoch_summaries = Some(epoch_summaries);
+        self
+    }
+
+    /// Serve delegations through `get_delegations` and `get_delegation`, and let them be
+    /// changed through `record_delegation` and `revoke_delegation`.
+    pub fn with_delegations(
+        mut self,
+        delegations: Arc<crate::delegation::DelegationRegistry>,
+    ) -> Self {
+        self.delegations = Some(delegations);
+        self
+    }
+
+    /// Serve adoption of activated proposals through `get_adoption`, and take the
+    /// capabilities nodes report through `report_node_capabilities`.
+    pub fn with_adoption(mut self, adoption: Arc<crate::adoption::AdoptionTracker>) -> Self {
+        self.adoption = Some(adoption);
+        self
+    }
+
+    /// The delegation registry, or an error for `method` if delegation is not enabled.
+    fn delegations(
+        &self,
+        method: &str,
+    ) -> Result<&Arc<crate::delegation::DelegationRegistry>, ModuleError> {
+        self.delegations.as_ref().ok_or_else(|| {
+            ModuleError::OperationError(format!("{} requires delegation.enabled", method))
+        })
+    }
 }
 
 #[async_trait::async_trait]
@@ -37,29 +151,52 @@ impl ModuleAPI for GovernanceModuleApi {
         &self,
         method: &str,
         params: &[u8],
-        _caller_module_id: &str,
+        caller_module_id: &str,
     ) -> Result<Vec<u8>, ModuleError> {
         match method {
             "get_proposals" => {
                 let proposals = self.proposal_store.load_proposals().map_err(|e| {
-                    ModuleError::OperationError(format!("Failed to load proposals: {}", e))
+                    ModuleError::OperationError(format!("Failed to load proposals: {}", e.chain()))
                 })?;
                 serde_json::to_vec(&proposals).map_err(|e| {
                     ModuleError::OperationError(format!("Serialization error: {}", e))
                 })
             }
+            "get_activations" => {
+                let activations = self.proposal_store.upcoming_activations().map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to load activations: {}", e.chain()))
+                })?;
+                serde_json::to_vec(&activations).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
             "get_economic_nodes" => {
-                let nodes = self.economic_nodes.list_nodes().await;
+                let nodes = self.economic_nodes.list_nodes_with_reputation().await;
+                let height = self.economic_nodes.current_height().await;
+                let config = self.economic_nodes.config();
+                let decay = &config.decay;
                 let json_nodes: Vec<serde_json::Value> = nodes
                     .into_iter()
-                    .map(|n| {
+                    .map(|(n, reputation)| {
+                        let effective_weight =
+                            crate::economic_nodes::decay::effective_weight(&n, height, decay);
                         serde_json::json!({
                             "node_id": hex::encode(n.node_id),
+                            "node_type": n.node_type,
                             "hashpower_percentage": n.hashpower_percentage,
+                            "raw_weight": crate::economic_nodes::decay::raw_weight(&n),
+                            "effective_weight": effective_weight,
                             "economic_activity_percentage": n.economic_activity_percentage,
                             "registered_at": n.registered_at,
                             "last_seen": n.last_seen,
                             "veto_count": n.veto_count,
+                            "keys": n.keys,
+                            "lightning_pubkey": n
+                                .lightning
+                                .as_ref()
+                                .map(|l| hex::encode(&l.node_pubkey)),
+                            "weight_history": n.weight_history,
+                            "reputation": reputation,
                         })
                     })
                     .collect();
@@ -67,10 +204,377 @@ impl ModuleAPI for GovernanceModuleApi {
                     ModuleError::OperationError(format!("Serialization error: {}", e))
                 })
             }
+            "get_economic_node" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let node_id = params_json
+                    .get("node_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(crate::economic_nodes::parse_node_id)
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_economic_node requires node_id (64 hex characters)".to_string(),
+                        )
+                    })?;
+                let include_archived = params_json
+                    .get("include_archived")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let mut details = self.economic_nodes.get_node(&node_id).await;
+                if details.is_none() && include_archived {
+                    details = self.economic_nodes.get_archived_node(&node_id).await;
+                }
+                let response = match details {
+                    Some(details) => serde_json::json!({ "found": true, "node": details }),
+                    None => serde_json::json!({ "found": false }),
+                };
+                serde_json::to_vec(&response).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_address_challenge" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let node_id = params_json
+                    .get("node_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(crate::economic_nodes::parse_node_id)
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_address_challenge requires node_id (64 hex characters)"
+                                .to_string(),
+                        )
+                    })?;
+                let challenge = self.economic_nodes.address_challenge(&node_id);
+                serde_json::to_vec(&serde_json::json!({ "challenge": challenge })).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_sybil_report" => {
+                let report = self.economic_nodes.sybil_report().await;
+                serde_json::to_vec(&report).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_registry_commitment" => {
+                let commitment = self.economic_nodes.commitment().await;
+                serde_json::to_vec(&commitment).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_total_weight_at" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let height = params_json
+                    .get("height")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_total_weight_at requires height (number)".to_string(),
+                        )
+                    })?;
+                let total_weight = self.economic_nodes.total_weight_at(height).await;
+                serde_json::to_vec(&serde_json::json!({
+                    "height": height,
+                    "total_weight": total_weight,
+                }))
+                .map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_registry_metrics" => {
+                let metrics = self.economic_nodes.metrics().await;
+                serde_json::to_vec(&metrics).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "list_epoch_snapshots" => {
+                // Summaries only; fetch entries with get_epoch_snapshot
+                let snapshots: Vec<serde_json::Value> = self
+                    .economic_nodes
+                    .list_snapshots()
+                    .into_iter()
+                    .map(|s| {
+                        serde_json::json!({
+                            "id": s.id,
+                            "height": s.height,
+                            "commitment": s.commitment,
+                            "node_count": s.nodes.len(),
+                            "total_weight": s.total_weight(),
+                        })
+                    })
+                    .collect();
+                serde_json::to_vec(&snapshots).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_epoch_snapshot" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let id = params_json.get("id").and_then(|v| v.as_u64()).ok_or_else(|| {
+                    ModuleError::OperationError("get_epoch_snapshot requires id (number)".to_string())
+                })?;
+                let snapshot = self.economic_nodes.snapshot(id);
+                serde_json::to_vec(&snapshot).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "list_epoch_summaries" => {
+                let summaries = match &self.epoch_summaries {
+                    Some(epoch_summaries) => epoch_summaries.summaries().map_err(|e| {
+                        ModuleError::OperationError(format!("Failed to load epoch summaries: {}", e.chain()))
+                    })?,
+                    None => Vec::new(),
+                };
+                serde_json::to_vec(&summaries).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_epoch_summary" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let epoch = params_json.get("epoch").and_then(|v| v.as_u64()).ok_or_else(|| {
+                    ModuleError::OperationError("get_epoch_summary requires epoch (number)".to_string())
+                })?;
+                let summary = match &self.epoch_summaries {
+                    Some(epoch_summaries) => epoch_summaries.summary(epoch).map_err(|e| {
+                        ModuleError::OperationError(format!("Failed to load epoch summaries: {}", e.chain()))
+                    })?,
+                    None => None,
+                };
+                serde_json::to_vec(&summary).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_adoption" => {
+                let response = match &self.adoption {
+                    Some(adoption) => {
+                        let milestones = adoption.milestones().map_err(|e| {
+                            ModuleError::OperationError(format!("Failed to load adoption milestones: {}", e.chain()))
+                        })?;
+                        serde_json::json!({ "levels": adoption.levels().await, "milestones": milestones })
+                    }
+                    None => serde_json::json!({ "levels": [], "milestones": {} }),
+                };
+                serde_json::to_vec(&response).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "report_node_capabilities" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let node_id = params_json
+                    .get("node_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(crate::economic_nodes::parse_node_id)
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "report_node_capabilities requires node_id (64 hex characters)"
+                                .to_string(),
+                        )
+                    })?;
+                let capabilities: std::collections::BTreeSet<String> = params_json
+                    .get("capabilities")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "report_node_capabilities requires capabilities (array of strings)"
+                                .to_string(),
+                        )
+                    })?;
+                let adoption = self.adoption.as_ref().ok_or_else(|| {
+                    ModuleError::OperationError(
+                        "report_node_capabilities requires the adoption tracker".to_string(),
+                    )
+                })?;
+                if self.economic_nodes.get_node(&node_id).await.is_none() {
+                    return Err(ModuleError::OperationError(format!(
+                        "Economic node {} is not registered",
+                        hex::encode(node_id)
+                    )));
+                }
+                let node_id = hex::encode(node_id);
+                adoption.report_capabilities(&node_id, capabilities).map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to store capabilities: {}", e.chain()))
+                })?;
+                serde_json::to_vec(&serde_json::json!({ "ok": true, "node_id": node_id }))
+                    .map_err(|e| {
+                        ModuleError::OperationError(format!("Serialization error: {}", e))
+                    })
+            }
+            "get_veto_tally" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let proposal_id = params_json
+                    .get("proposal_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_veto_tally requires proposal_id (string)".to_string(),
+                        )
+                    })?;
+                let tally = self.economic_nodes.veto_tally(proposal_id).await;
+                serde_json::to_vec(&serde_json::json!({
+                    "tally": tally,
+                    "snapshot_id": self.economic_nodes.proposal_snapshot_id(proposal_id),
+                    "veto_percent": tally.veto_percent(),
+                    "threshold_crossed": tally.crossed(),
+                }))
+                .map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "simulate_veto" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let mut scenario = parse_veto_scenario(&params_json)?;
+                if scenario.tier.is_none() {
+                    if let Some(proposal_id) = &scenario.proposal_id {
+                        scenario.tier = self
+                            .proposal_store
+                            .load_proposals()
+                            .map_err(|e| {
+                                ModuleError::OperationError(format!(
+                                    "Failed to load proposals: {}",
+                                    e
+                                ))
+                            })?
+                            .into_iter()
+                            .find(|p| &p.proposal_id == proposal_id)
+                            .map(|p| p.tier);
+                    }
+                }
+                let simulation = self
+                    .economic_nodes
+                    .simulate_veto(&scenario)
+                    .await
+                    .map_err(|e| ModuleError::OperationError(e.to_string()))?;
+                serde_json::to_vec(&simulation).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "backup" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let path = params_json
+                    .get("path")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "backup requires path (empty or missing directory)".to_string(),
+                        )
+                    })?;
+                let manifest = crate::backup::backup(
+                    &self.economic_nodes,
+                    &self.proposal_store,
+                    None,
+                    std::path::Path::new(path),
+                )
+                .await
+                .map_err(|e| ModuleError::OperationError(format!("Backup failed: {}", e.chain())))?;
+                serde_json::to_vec(&manifest).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "reconcile_now" => {
+                let summary = self.economic_nodes.reconcile_now().await.map_err(|e| {
+                    ModuleError::OperationError(format!("Reconciliation failed: {}", e.chain()))
+                })?;
+                serde_json::to_vec(&summary).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
             "get_webhook_status" => {
+                let url = self.webhook.webhook_url();
                 let status = serde_json::json!({
-                    "enabled": self.webhook_url.is_some(),
-                    "url": self.webhook_url.as_deref(),
+                    "enabled": url.is_some(),
+                    "url": url,
+                });
+                serde_json::to_vec(&status).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_ipc_status" => {
+                let status = serde_json::json!({
+                    "heartbeat_enabled": self.heartbeat.is_some(),
+                    "heartbeat": self.heartbeat.as_ref().map(|h| h.status()),
+                    "event_queue": self.events.as_ref().map(|q| q.stats()),
+                    "event_stream": self.stream.as_ref().map(|s| s.status()),
+                    "metrics": self.metrics.as_ref().map(|m| m.snapshot()),
+                    "filtered_events": self.pipeline.as_ref().map(|p| p.filtered()),
+                    "handlers": self.pipeline.as_ref().map(|p| p.handler_stats()),
+                });
+                serde_json::to_vec(&status).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "shutdown" => {
+                // { "deadline_secs": 30 } (optional)
+                let shutdown = self.shutdown.as_ref().ok_or_else(|| {
+                    ModuleError::OperationError("shutdown not available".to_string())
+                })?;
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let deadline_secs = params_json.get("deadline_secs").and_then(|v| v.as_u64());
+                let accepted = shutdown.request(deadline_secs);
+                if accepted {
+                    tracing::info!(
+                        "Shutdown requested by {} (deadline {:?}s)",
+                        caller_module_id,
+                        deadline_secs
+                    );
+                }
+                let status = serde_json::json!({
+                    "accepted": accepted,
+                    "in_flight": shutdown.in_flight(),
+                    "exit_code": shutdown.reason().exit_code(),
+                });
+                serde_json::to_vec(&status).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "reload_config" => {
+                let config_reload = self.config_reload.as_ref().ok_or_else(|| {
+                    ModuleError::OperationError("config reload not available".to_string())
+                })?;
+                let summary = config_reload.reload(caller_module_id).await;
+                serde_json::to_vec(&summary).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_event_subscriptions" | "set_event_subscriptions" => {
+                let subscriptions = self.subscriptions.as_ref().ok_or_else(|| {
+                    ModuleError::OperationError("event subscriptions not available".to_string())
+                })?;
+                if method == "set_event_subscriptions" {
+                    // { "subscribe": ["NewBlock"], "unsubscribe": [..] }
+                    let params_json: serde_json::Value = serde_json::from_slice(params)
+                        .unwrap_or(serde_json::json!({}));
+                    let names = |key: &str| -> Vec<String> {
+                        params_json
+                            .get(key)
+                            .and_then(|v| v.as_array())
+                            .map(|a| {
+                                a.iter()
+                                    .filter_map(|v| v.as_str().map(String::from))
+                                    .collect()
+                            })
+                            .unwrap_or_default()
+                    };
+                    let subscribe = subscriptions
+                        .parse(&names("subscribe"))
+                        .map_err(|e| ModuleError::OperationError(e.to_string()))?;
+                    let unsubscribe = subscriptions
+                        .parse(&names("unsubscribe"))
+                        .map_err(|e| ModuleError::OperationError(e.to_string()))?;
+                    subscriptions.remove(unsubscribe);
+                    subscriptions
+                        .add(subscribe)
+                        .map_err(|e| ModuleError::OperationError(e.to_string()))?;
+                }
+                let status = serde_json::json!({
+                    "subscribed": subscriptions.current().iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
+                    "supported": subscriptions.supported().iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
                 });
                 serde_json::to_vec(&status).map_err(|e| {
                     ModuleError::OperationError(format!("Serialization error: {}", e))
@@ -210,6 +714,291 @@ impl ModuleAPI for GovernanceModuleApi {
                     ModuleError::OperationError(format!("Serialization error: {}", e))
                 })
             }
+            "register_economic_node" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let node_id = params_json
+                    .get("node_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "register_economic_node requires node_id (string)".to_string(),
+                        )
+                    })?
+                    .to_string();
+                let node_type = params_json
+                    .get("node_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let hashpower_percent = params_json
+                    .get("hashpower_percent")
+                    .and_then(|v| v.as_f64());
+                let claim = match params_json.get("reserve") {
+                    Some(reserve) => Some(parse_reserve_claim(reserve)?),
+                    None => None,
+                };
+                let address_proof = match params_json.get("address_proof") {
+                    Some(proof) => Some(
+                        serde_json::from_value::<crate::economic_nodes::AddressProof>(
+                            proof.clone(),
+                        )
+                        .map_err(|e| {
+                            ModuleError::OperationError(format!("invalid address_proof: {}", e))
+                        })?,
+                    ),
+                    None => None,
+                };
+                if let Some(lightning) = params_json.get("lightning") {
+                    let (identity, signature, funding_scripts) = parse_lightning(lightning)?;
+                    let accepted = self
+                        .economic_nodes
+                        .register_lightning(
+                            Some(caller_module_id),
+                            &node_id,
+                            hashpower_percent,
+                            identity,
+                            &signature,
+                            &funding_scripts,
+                        )
+                        .await
+                        .map_err(|e| {
+                            ModuleError::OperationError(format!("Failed to register node: {}", e))
+                        })?;
+                    return serde_json::to_vec(&serde_json::json!({
+                        "ok": accepted,
+                        "node_id": node_id,
+                        "node_type": crate::economic_nodes::lightning::LIGHTNING_NODE_TYPE,
+                    }))
+                    .map_err(|e| {
+                        ModuleError::OperationError(format!("Serialization error: {}", e))
+                    });
+                }
+                let registered = match (params_json.get("keys"), address_proof) {
+                    (_, Some(proof)) => {
+                        self.economic_nodes
+                            .register_with_address_proof(
+                                Some(caller_module_id),
+                                &node_id,
+                                &node_type,
+                                hashpower_percent,
+                                proof,
+                            )
+                            .await
+                    }
+                    (Some(keys), None) => {
+                        let keys = parse_key_set(keys)?;
+                        let signatures = parse_key_signatures(&params_json)?;
+                        self.economic_nodes
+                            .register_multisig(
+                                Some(caller_module_id),
+                                &node_id,
+                                &node_type,
+                                hashpower_percent,
+                                keys,
+                                &signatures,
+                            )
+                            .await
+                    }
+                    (None, None) => {
+                        self.economic_nodes
+                            .register_from(
+                                Some(caller_module_id),
+                                &node_id,
+                                &node_type,
+                                hashpower_percent,
+                                claim,
+                            )
+                            .await
+                    }
+                };
+                let accepted = registered.map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to register node: {}", e))
+                })?;
+                let verification = match crate::economic_nodes::parse_node_id(&node_id) {
+                    Some(id) => self
+                        .economic_nodes
+                        .list_nodes()
+                        .await
+                        .into_iter()
+                        .find(|n| n.node_id == id)
+                        .map(|n| n.verification),
+                    None => None,
+                };
+                serde_json::to_vec(&serde_json::json!({
+                    "ok": accepted,
+                    "node_id": node_id,
+                    "verification": verification,
+                }))
+                .map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "veto_proposal" | "revoke_veto" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let field = |name: &str| -> Result<String, ModuleError> {
+                    params_json
+                        .get(name)
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                        .ok_or_else(|| {
+                            ModuleError::OperationError(format!(
+                                "{} requires {} (string)",
+                                method, name
+                            ))
+                        })
+                };
+                let proposal_id = field("proposal_id")?;
+                let node_id = field("node_id")?;
+                let signed = parse_signed(&params_json)?;
+                let ok = if method == "veto_proposal" {
+                    let reason = field("reason").unwrap_or_default();
+                    self.economic_nodes
+                        .veto(&proposal_id, &node_id, &reason, signed.as_ref())
+                        .await
+                        .map(|_| true)
+                } else {
+                    self.economic_nodes
+                        .revoke_veto(&proposal_id, &node_id, signed.as_ref())
+                        .await
+                }
+                .map_err(|e| ModuleError::OperationError(format!("{} failed: {}", method, e)))?;
+                serde_json::to_vec(&serde_json::json!({
+                    "ok": ok,
+                    "proposal_id": proposal_id,
+                    "node_id": node_id,
+                }))
+                .map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_tallies" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let proposal_id = params_json
+                    .get("proposal_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_tallies requires proposal_id (string)".to_string(),
+                        )
+                    })?;
+                let tallies = self.proposal_store.tallies(proposal_id).map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to load tallies: {}", e.chain()))
+                })?;
+                let weighted = self.proposal_store.weighted_tally(proposal_id).map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to load tallies: {}", e.chain()))
+                })?;
+                let mut tallies = serde_json::json!(tallies);
+                if let Some(weighted) = weighted {
+                    tallies["weighted"] = serde_json::json!(weighted);
+                }
+                serde_json::to_vec(&tallies).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_conflicts" => {
+                let graph = self.proposal_store.conflicts().map_err(|e| {
+                    ModuleError::OperationError(format!("Failed to load conflicts: {}", e.chain()))
+                })?;
+                serde_json::to_vec(&graph).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_delegations" => {
+                let records = match &self.delegations {
+                    Some(delegations) => delegations.records(),
+                    None => Vec::new(),
+                };
+                serde_json::to_vec(&records).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "get_delegation" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let identity = params_json
+                    .get("identity")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "get_delegation requires identity (string)".to_string(),
+                        )
+                    })?;
+                let height = params_json.get("height").and_then(|v| v.as_u64());
+                let delegation = self.delegations(method)?.identity(identity, height);
+                serde_json::to_vec(&delegation).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "record_delegation" | "revoke_delegation" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let delegations = self.delegations(method)?;
+                let delegator = params_json
+                    .get("delegator")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(format!("{} requires delegator (string)", method))
+                    })?;
+                let delegate = match method {
+                    "record_delegation" => Some(
+                        params_json
+                            .get("delegate")
+                            .and_then(|v| v.as_str())
+                            .ok_or_else(|| {
+                                ModuleError::OperationError(
+                                    "record_delegation requires delegate (string)".to_string(),
+                                )
+                            })?,
+                    ),
+                    _ => None,
+                };
+                let expiry_height = params_json.get("expiry_height").and_then(|v| v.as_u64());
+                let record = delegations
+                    .announce(delegator, delegate, expiry_height)
+                    .map_err(|e| ModuleError::OperationError(format!("Delegation rejected: {}", e)))?;
+                // Open proposals are counted with the change at once
+                self.proposal_store
+                    .update_tallies(self.node_api.as_ref())
+                    .await
+                    .map_err(|e| {
+                        ModuleError::OperationError(format!("Failed to update tallies: {}", e.chain()))
+                    })?;
+                serde_json::to_vec(&record).map_err(|e| {
+                    ModuleError::OperationError(format!("Serialization error: {}", e))
+                })
+            }
+            "rotate_economic_node_keys" => {
+                let params_json: serde_json::Value = serde_json::from_slice(params)
+                    .unwrap_or(serde_json::json!({}));
+                let node_id = params_json
+                    .get("node_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(
+                            "rotate_economic_node_keys requires node_id (string)".to_string(),
+                        )
+                    })?
+                    .to_string();
+                let keys = parse_key_set(params_json.get("keys").unwrap_or(&serde_json::Value::Null))?;
+                let signed = parse_signed(&params_json)?.ok_or_else(|| {
+                    ModuleError::OperationError(
+                        "rotate_economic_node_keys requires signatures".to_string(),
+                    )
+                })?;
+                self.economic_nodes
+                    .rotate_keys(&node_id, keys, &signed)
+                    .await
+                    .map_err(|e| {
+                        ModuleError::OperationError(format!("Key rotation failed: {}", e))
+                    })?;
+                serde_json::to_vec(&serde_json::json!({ "ok": true, "node_id": node_id }))
+                    .map_err(|e| {
+                        ModuleError::OperationError(format!("Serialization error: {}", e))
+                    })
+            }
             _ => Err(ModuleError::OperationError(format!("Unknown method: {}", method))),
         }
     }
@@ -217,11 +1006,42 @@ impl ModuleAPI for GovernanceModuleApi {
     fn list_methods(&self) -> Vec<String> {
         vec![
             "get_proposals".to_string(),
+            "get_activations".to_string(),
             "get_economic_nodes".to_string(),
+            "get_economic_node".to_string(),
+            "get_address_challenge".to_string(),
+            "get_sybil_report".to_string(),
+            "get_registry_commitment".to_string(),
+            "get_veto_tally".to_string(),
+            "simulate_veto".to_string(),
+            "list_epoch_snapshots".to_string(),
+            "get_epoch_snapshot".to_string(),
+            "list_epoch_summaries".to_string(),
+            "get_epoch_summary".to_string(),
+            "get_adoption".to_string(),
+            "report_node_capabilities".to_string(),
+            "get_tallies".to_string(),
+            "get_conflicts".to_string(),
+            "get_delegations".to_string(),
+            "get_delegation".to_string(),
+            "record_delegation".to_string(),
+            "revoke_delegation".to_string(),
+            "get_registry_metrics".to_string(),
+            "get_total_weight_at".to_string(),
             "get_webhook_status".to_string(),
+            "get_ipc_status".to_string(),
+            "get_event_subscriptions".to_string(),
+            "set_event_subscriptions".to_string(),
             "create_proposal".to_string(),
             "record_proposal_vote".to_string(),
             "record_proposal_merged".to_string(),
+            "register_economic_node".to_string(),
+            "veto_proposal".to_string(),
+            "revoke_veto".to_string(),
+            "rotate_economic_node_keys".to_string(),
+            "reconcile_now".to_string(),
+            "backup".to_string(),
+            "shutdown".to_string(),
         ]
     }
 
@@ -229,3 +1049,201 @@ impl ModuleAPI for GovernanceModuleApi {
         1
     }
 }
+
+/// Parse a reserve claim: `{ "public_key": hex, "outpoints": ["txid:vout", ..], "signature": hex }`.
+fn parse_reserve_claim(
+    value: &serde_json::Value,
+) -> Result<crate::economic_nodes::ReserveClaim, ModuleError> {
+    let hex_field = |name: &str| -> Result<Vec<u8>, ModuleError> {
+        value
+            .get(name)
+            .and_then(|v| v.as_str())
+            .and_then(|s| hex::decode(s).ok())
+            .ok_or_else(|| ModuleError::OperationError(format!("reserve.{} must be hex", name)))
+    };
+    let outpoints = value
+        .get("outpoints")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| {
+            ModuleError::OperationError("reserve.outpoints must be an array".to_string())
+        })?
+        .iter()
+        .map(|o| {
+            o.as_str()
+                .and_then(crate::economic_nodes::ClaimedOutpoint::parse)
+                .ok_or_else(|| {
+                    ModuleError::OperationError(format!("invalid outpoint: {}", o))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(crate::economic_nodes::ReserveClaim {
+        public_key: hex_field("public_key")?,
+        outpoints,
+        signature: hex_field("signature")?,
+    })
+}
+
+/// Parse a Lightning identity, its signature and the channels' funding scripts:
+/// `{ "node_pubkey": hex, "signature": zbase32, "channel_outpoints": ["txid:vout", ..],
+/// "funding_scripts": [hex, ..] }`.
+fn parse_lightning(
+    value: &serde_json::Value,
+) -> Result<(crate::economic_nodes::LightningIdentity, String, Vec<Vec<u8>>), ModuleError> {
+    let node_pubkey = value
+        .get("node_pubkey")
+        .and_then(|v| v.as_str())
+        .and_then(|s| hex::decode(s).ok())
+        .filter(|k| k.len() == 33)
+        .ok_or_else(|| {
+            ModuleError::OperationError(
+                "lightning.node_pubkey must be a 33-byte hex public key".to_string(),
+            )
+        })?;
+    let signature = value
+        .get("signature")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            ModuleError::OperationError("lightning.signature must be a string".to_string())
+        })?
+        .to_string();
+    let channel_outpoints = match value.get("channel_outpoints").and_then(|v| v.as_array()) {
+        Some(outpoints) => outpoints
+            .iter()
+            .map(|o| {
+                o.as_str()
+                    .and_then(crate::economic_nodes::ClaimedOutpoint::parse)
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(format!("invalid outpoint: {}", o))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let funding_scripts = match value.get("funding_scripts").and_then(|v| v.as_array()) {
+        Some(scripts) => scripts
+            .iter()
+            .map(|s| {
+                s.as_str()
+                    .and_then(|h| hex::decode(h).ok())
+                    .ok_or_else(|| {
+                        ModuleError::OperationError(format!("invalid funding script: {}", s))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    Ok((
+        crate::economic_nodes::LightningIdentity {
+            node_pubkey,
+            channel_outpoints,
+        },
+        signature,
+        funding_scripts,
+    ))
+}
+
+/// Parse a veto simulation: `{ "proposal_id": str, "tier": str, "nodes": [hex, ..],
+/// "categories": [str, ..], "snapshot_id": n }`, all optional.
+fn parse_veto_scenario(
+    value: &serde_json::Value,
+) -> Result<crate::economic_nodes::VetoScenario, ModuleError> {
+    let string = |field: &str| value.get(field).and_then(|v| v.as_str()).map(String::from);
+    let list = |field: &str| -> Vec<serde_json::Value> {
+        value
+            .get(field)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let nodes = list("nodes")
+        .iter()
+        .map(|n| {
+            n.as_str()
+                .and_then(crate::economic_nodes::parse_node_id)
+                .ok_or_else(|| ModuleError::OperationError(format!("invalid node id: {}", n)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let categories = list("categories")
+        .iter()
+        .filter_map(|c| c.as_str().map(String::from))
+        .collect();
+    Ok(crate::economic_nodes::VetoScenario {
+        proposal_id: string("proposal_id"),
+        tier: string("tier"),
+        nodes,
+        categories,
+        snapshot: value.get("snapshot_id").and_then(|v| v.as_u64()),
+    })
+}
+
+/// Parse a key set: `{ "public_keys": [hex, ..], "threshold": m }`.
+fn parse_key_set(
+    value: &serde_json::Value,
+) -> Result<crate::economic_nodes::KeySet, ModuleError> {
+    let public_keys = value
+        .get("public_keys")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| ModuleError::OperationError("keys.public_keys must be an array".to_string()))?
+        .iter()
+        .map(|k| {
+            k.as_str()
+                .and_then(|s| hex::decode(s).ok())
+                .ok_or_else(|| ModuleError::OperationError(format!("invalid public key: {}", k)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let threshold = value
+        .get("threshold")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| ModuleError::OperationError("keys.threshold must be a number".to_string()))?;
+    Ok(crate::economic_nodes::KeySet {
+        public_keys,
+        threshold: threshold as u32,
+    })
+}
+
+/// Parse `"signatures": [{ "key_index": i, "signature": hex }, ..]` (missing means none).
+/// `signatures` with the `height` they were signed at, or `None` without signatures.
+fn parse_signed(
+    params: &serde_json::Value,
+) -> Result<Option<crate::economic_nodes::SignedAt>, ModuleError> {
+    let signatures = parse_key_signatures(params)?;
+    if signatures.is_empty() {
+        return Ok(None);
+    }
+    let height = params
+        .get("height")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| {
+            ModuleError::OperationError(
+                "signatures require the height they were signed at".to_string(),
+            )
+        })?;
+    Ok(Some(crate::economic_nodes::SignedAt { height, signatures }))
+}
+
+fn parse_key_signatures(
+    params: &serde_json::Value,
+) -> Result<Vec<crate::economic_nodes::KeySignature>, ModuleError> {
+    let Some(signatures) = params.get("signatures") else {
+        return Ok(Vec::new());
+    };
+    signatures
+        .as_array()
+        .ok_or_else(|| ModuleError::OperationError("signatures must be an array".to_string()))?
+        .iter()
+        .map(|s| {
+            let key_index = s.get("key_index").and_then(|v| v.as_u64());
+            let signature = s
+                .get("signature")
+                .and_then(|v| v.as_str())
+                .and_then(|h| hex::decode(h).ok());
+            match (key_index, signature) {
+                (Some(key_index), Some(signature)) => Ok(crate::economic_nodes::KeySignature {
+                    key_index: key_index as u32,
+                    signature,
+                }),
+                _ => Err(ModuleError::OperationError(format!("invalid signature: {}", s))),
+            }
+        })
+        .collect()
+}
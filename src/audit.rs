@@ -0,0 +1,100 @@
+//! Audit trail of governance actions submitted to the node
+//!
+//! Every veto or other action the module submits with
+//! [`crate::node_api::NodeApiIpc::submit_governance_action`] is recorded here with the full
+//! request and the node's response (or the error, if there was none), whether or not the node
+//! accepted it. Entries are kept in the module database when one is attached, so the trail
+//! survives restarts, and are also written to the audit log when one is given (see
+//! [`crate::audit_log`]).
+
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+const AUDIT_TREE: &str = "audit";
+const ACTIONS_KEY: &[u8] = b"actions";
+
+/// One submission to the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionAuditEntry {
+    /// When it was submitted, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Action type, e.g. "veto".
+    pub action: String,
+    /// Request as sent, JSON.
+    pub request: String,
+    /// Response as received, JSON; `None` if the request failed.
+    pub response: Option<String>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Submissions to the node, oldest first.
+#[derive(Default)]
+pub struct ActionAudit {
+    entries: Mutex<Vec<ActionAuditEntry>>,
+    db: Option<Arc<dyn blvm_node::storage::database::Database>>,
+    log: Option<Arc<crate::audit_log::AuditLog>>,
+}
+
+impl ActionAudit {
+    /// An audit trail kept in `db`, loading the entries already there.
+    pub fn open(
+        db: Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Self, GovernanceError> {
+        let entries = Self::load_from(&db)?;
+        Ok(Self {
+            entries: Mutex::new(entries),
+            db: Some(db),
+            log: None,
+        })
+    }
+
+    /// Also write each entry to `log`.
+    pub fn with_audit_log(mut self, log: Arc<crate::audit_log::AuditLog>) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Append `entry`. It is kept in memory even if it cannot be persisted.
+    pub fn record(&self, entry: ActionAuditEntry) -> Result<(), GovernanceError> {
+        if let Some(log) = &self.log {
+            log.action(&entry);
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(entry);
+        self.save(&entries)
+    }
+
+    pub fn entries(&self) -> Vec<ActionAuditEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    fn save(&self, entries: &[ActionAuditEntry]) -> Result<(), GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let tree = db
+            .open_tree(AUDIT_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(entries).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(ACTIONS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Vec<ActionAuditEntry>, GovernanceError> {
+        let tree = db
+            .open_tree(AUDIT_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(ACTIONS_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
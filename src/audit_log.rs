@@ -0,0 +1,637 @@
+//! Tamper-evident audit log
+//!
+//! With `[governance.audit_log] enabled`, what the module sees and does is recorded as JSON
+//! lines in `audit/` in the data directory: every event received from the node (its type and
+//! its encoding, and with `record_blocks` the block of a new block event), every webhook
+//! delivery (endpoint, with its password and query values masked, event type and outcome),
+//! every registry change and new registry commitment, every governance action submitted to
+//! the node with the node's answer, every request to the local action endpoints with its
+//! outcome (see [`crate::actions`]), each handler's rollback after a reorg (see
+//! [`crate::pipeline`]), each governance state anchor broadcast, confirmed or given up
+//! (see [`crate::anchor`]), each pause and resume of outbound effects with what triggered
+//! it (see [`crate::pause`]), and each conflict between two votes of one voter with the vote
+//! counted (see [`crate::vote_check`]). Entries made while an event is handled carry its
+//! `trace_id` (see [`crate::trace`]). Recorded events can be fed through the handlers again
+//! with `blvm-governance replay` (see [`crate::replay`]).
+//!
+//! Entries are numbered from 0 and chained: each carries the `hash` of the one before it as
+//! `prev_hash` (zeros for the first) and its own `hash`, the SHA-256 of the entry's JSON
+//! without that field. Altering, removing, inserting or reordering entries breaks the chain
+//! there; `blvm-governance verify-audit` replays it from entry 0 and reports the first broken
+//! link ([`verify`]). Entries removed from the end leave a valid chain, so `verify-audit` also
+//! prints the head (last number and hash) to compare with a copy kept elsewhere; the module
+//! logs it when it opens the log.
+//!
+//! Files are named after their first entry, `audit-<number>.jsonl`, and a new one is started
+//! when the next entry would take the current one past `max_file_bytes`, and with
+//! `rotate_daily`, with the first entry of each UTC day. The chain continues across files.
+//! The module never deletes them. An entry is written with a single write, so only a crash
+//! can leave one incomplete; it is removed when the log is next opened.
+
+use crate::actions::ActionRequestRecord;
+use crate::anchor::Anchor;
+use crate::audit::ActionAuditEntry;
+use crate::config::AuditLogConfig;
+use crate::economic_nodes::{RegistryChange, RegistryCommitment};
+use crate::error::GovernanceError;
+use crate::node_api::Reorg;
+use crate::pause::Trigger;
+use crate::vote_check::VoteConflict;
+use blvm_node::module::ipc::protocol::EventMessage;
+use blvm_protocol::Block;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const FILE_PREFIX: &str = "audit-";
+const FILE_SUFFIX: &str = ".jsonl";
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// What an entry records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// An event received from the node.
+    Event,
+    /// A webhook delivery, after its retries.
+    WebhookDelivery,
+    /// A registry change, as published to subscribers.
+    RegistryChange,
+    /// A new registry commitment, after any change to the registered nodes.
+    RegistryCommitment,
+    /// A governance action submitted to the node.
+    Action,
+    /// A request to the local action endpoints, and its outcome.
+    ActionRequest,
+    /// A handler's state rolled back to the fork of a reorg.
+    Rollback,
+    /// A governance state anchor broadcast, confirmed or given up.
+    Anchor,
+    /// A request to pause or resume outbound effects.
+    Pause,
+    /// Two conflicting votes of one voter, and the one counted.
+    VoteConflict,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Number of the entry, from 0.
+    pub seq: u64,
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub kind: AuditKind,
+    pub data: serde_json::Value,
+    /// `hash` of the previous entry; [`GENESIS_HASH`] for the first.
+    pub prev_hash: String,
+    /// Hex SHA-256 of the entry's JSON without this field.
+    pub hash: String,
+}
+
+/// What an entry's hash covers: all of it but the hash.
+#[derive(Serialize)]
+struct Hashed<'a> {
+    seq: u64,
+    timestamp: u64,
+    kind: AuditKind,
+    data: &'a serde_json::Value,
+    prev_hash: &'a str,
+}
+
+impl AuditEntry {
+    /// The hash the entry should have.
+    pub fn compute_hash(&self) -> String {
+        let hashed = Hashed {
+            seq: self.seq,
+            timestamp: self.timestamp,
+            kind: self.kind,
+            data: &self.data,
+            prev_hash: &self.prev_hash,
+        };
+        // Serializing a value to a Vec cannot fail
+        let json = serde_json::to_vec(&hashed).unwrap_or_default();
+        hex::encode(Sha256::digest(json))
+    }
+}
+
+/// Last entry of the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditHead {
+    pub seq: u64,
+    pub hash: String,
+}
+
+/// File being appended to.
+#[derive(Debug)]
+struct Current {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// UTC day (Unix days) of its first entry.
+    day: u64,
+}
+
+#[derive(Debug)]
+struct Writer {
+    current: Option<Current>,
+    next_seq: u64,
+    prev_hash: String,
+}
+
+/// Appends chained entries to the files in one directory. Kept across connections.
+#[derive(Debug)]
+pub struct AuditLog {
+    dir: PathBuf,
+    config: AuditLogConfig,
+    writer: Mutex<Writer>,
+}
+
+/// Audit log files in `dir` by the number of their first entry, in order.
+pub(crate) fn files(dir: &Path) -> Result<Vec<(u64, PathBuf)>, GovernanceError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(GovernanceError::io(dir.display())(e)),
+    };
+    let mut files: Vec<(u64, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let seq = name
+                .strip_prefix(FILE_PREFIX)?
+                .strip_suffix(FILE_SUFFIX)?
+                .parse()
+                .ok()?;
+            Some((seq, entry.path()))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+impl AuditLog {
+    /// Open the log in `dir`, continuing the chain from its last entry. An incomplete last
+    /// entry, left by a crash, is removed. Fails if the last complete entry cannot be read.
+    pub fn open(dir: &Path, config: &AuditLogConfig) -> Result<Self, GovernanceError> {
+        let mut writer = Writer {
+            current: None,
+            next_seq: 0,
+            prev_hash: GENESIS_HASH.to_string(),
+        };
+        if let Some((_, path)) = files(dir)?.pop() {
+            let mut content = std::fs::read(&path).map_err(GovernanceError::io(path.display()))?;
+            let complete = content
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+            if complete < content.len() {
+                warn!(
+                    "Removing an incomplete entry at the end of {}",
+                    path.display()
+                );
+                OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|file| file.set_len(complete as u64))
+                    .map_err(GovernanceError::io(path.display()))?;
+                content.truncate(complete);
+            }
+            let mut lines = content.split(|&b| b == b'\n').filter(|l| !l.is_empty());
+            let operation = path.display().to_string();
+            let parse = |line: &[u8]| {
+                serde_json::from_slice::<AuditEntry>(line)
+                    .map_err(GovernanceError::serialization(&operation))
+            };
+            if let Some(first) = lines.next() {
+                let first = parse(first)?;
+                let last = match lines.next_back() {
+                    Some(last) => parse(last)?,
+                    None => first.clone(),
+                };
+                writer.next_seq = last.seq + 1;
+                writer.prev_hash = last.hash;
+                let file = OpenOptions::new()
+                    .append(true)
+                    .open(&path)
+                    .map_err(GovernanceError::io(path.display()))?;
+                writer.current = Some(Current {
+                    path,
+                    file,
+                    size: content.len() as u64,
+                    day: first.timestamp / SECS_PER_DAY,
+                });
+            }
+        }
+        let log = Self {
+            dir: dir.to_path_buf(),
+            config: config.clone(),
+            writer: Mutex::new(writer),
+        };
+        match log.head() {
+            Some(head) => info!(
+                "Audit log {}: continuing after entry {} ({})",
+                dir.display(),
+                head.seq,
+                head.hash
+            ),
+            None => info!("Audit log {}: starting a new chain", dir.display()),
+        }
+        Ok(log)
+    }
+
+    /// The last entry written, if any.
+    pub fn head(&self) -> Option<AuditHead> {
+        let writer = self.writer.lock().unwrap();
+        writer.next_seq.checked_sub(1).map(|seq| AuditHead {
+            seq,
+            hash: writer.prev_hash.clone(),
+        })
+    }
+
+    /// Append an entry recording `data` now.
+    pub fn record(
+        &self,
+        kind: AuditKind,
+        data: serde_json::Value,
+    ) -> Result<AuditEntry, GovernanceError> {
+        self.record_at(kind, data, crate::clock::unix_now())
+    }
+
+    /// Append an entry recording `data` at `now` (Unix seconds).
+    pub fn record_at(
+        &self,
+        kind: AuditKind,
+        data: serde_json::Value,
+        now: u64,
+    ) -> Result<AuditEntry, GovernanceError> {
+        let mut writer = self.writer.lock().unwrap();
+        let mut entry = AuditEntry {
+            seq: writer.next_seq,
+            timestamp: now,
+            kind,
+            data,
+            prev_hash: writer.prev_hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        let mut line =
+            serde_json::to_vec(&entry).map_err(GovernanceError::serialization("audit entry"))?;
+        line.push(b'\n');
+
+        let day = now / SECS_PER_DAY;
+        let full = writer.current.as_ref().is_none_or(|current| {
+            (current.size > 0 && current.size + line.len() as u64 > self.config.max_file_bytes)
+                || (self.config.rotate_daily && current.day != day)
+        });
+        if full {
+            writer.current = Some(self.start_file(entry.seq, day)?);
+        }
+        let current = writer.current.as_mut().expect("a file is open");
+        current
+            .file
+            .write_all(&line)
+            .map_err(GovernanceError::io(current.path.display()))?;
+        current.size += line.len() as u64;
+        writer.next_seq += 1;
+        writer.prev_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// A new file for entries from `seq` on, the first written on `day`.
+    fn start_file(&self, seq: u64, day: u64) -> Result<Current, GovernanceError> {
+        std::fs::create_dir_all(&self.dir).map_err(GovernanceError::io(self.dir.display()))?;
+        let path = self
+            .dir
+            .join(format!("{}{:012}{}", FILE_PREFIX, seq, FILE_SUFFIX));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(GovernanceError::io(path.display()))?;
+        Ok(Current {
+            path,
+            file,
+            size: 0,
+            day,
+        })
+    }
+
+    /// Record `kind` for a hook whose work goes on whether or not the entry is written, with
+    /// the trace id of the event being handled.
+    fn note(&self, kind: AuditKind, mut data: serde_json::Value) {
+        if let Some(fields) = data.as_object_mut() {
+            fields.insert("trace_id".to_string(), crate::trace::current_id().into());
+        }
+        if let Err(e) = self.record(kind, data) {
+            warn!("Failed to write audit log entry: {}", e.chain());
+        }
+    }
+
+    /// Whether the block of each new block event is recorded with it.
+    pub fn records_blocks(&self) -> bool {
+        self.config.record_blocks
+    }
+
+    /// Record that `event` was received from the node, with `block` if it is a new block
+    /// event and blocks are recorded. Both are kept as hex of their bincode encoding.
+    pub fn event_received(
+        &self,
+        event: &EventMessage,
+        block: Option<&Block>,
+    ) -> Result<AuditEntry, GovernanceError> {
+        let encoded =
+            bincode::serialize(event).map_err(GovernanceError::encoding("serialize event"))?;
+        let mut data = serde_json::json!({
+            "event_type": format!("{:?}", event.event_type),
+            "event": hex::encode(encoded),
+            "trace_id": crate::trace::current_id(),
+        });
+        if let Some(block) = block {
+            let encoded =
+                bincode::serialize(block).map_err(GovernanceError::encoding("serialize block"))?;
+            data["block"] = serde_json::Value::String(hex::encode(encoded));
+        }
+        self.record(AuditKind::Event, data)
+    }
+
+    /// Record a webhook delivery of `event_type` to `url`: the status the receiver answered,
+    /// or why there was none.
+    pub fn webhook_delivery(
+        &self,
+        url: &str,
+        event_type: &str,
+        status: Option<u16>,
+        error: Option<&str>,
+    ) {
+        self.note(
+            AuditKind::WebhookDelivery,
+            serde_json::json!({
+                "url": crate::webhook::masked_url(url),
+                "event_type": event_type,
+                "delivered": error.is_none(),
+                "status": status,
+                "error": error,
+            }),
+        );
+    }
+
+    /// Record a change of the registry.
+    pub fn registry_change(&self, change: &RegistryChange) {
+        match serde_json::to_value(change) {
+            Ok(data) => self.note(AuditKind::RegistryChange, data),
+            Err(e) => warn!("Failed to encode registry change for the audit log: {}", e),
+        }
+    }
+
+    /// Record the registry commitment after a change of the registered nodes.
+    pub fn registry_commitment(&self, commitment: &RegistryCommitment, height: u64) {
+        self.note(
+            AuditKind::RegistryCommitment,
+            serde_json::json!({
+                "version": commitment.version,
+                "root": commitment.root_hex(),
+                "node_count": commitment.node_count,
+                "height": height,
+            }),
+        );
+    }
+
+    /// Record a governance action submitted to the node, with its answer.
+    pub fn action(&self, entry: &ActionAuditEntry) {
+        match serde_json::to_value(entry) {
+            Ok(data) => self.note(AuditKind::Action, data),
+            Err(e) => warn!("Failed to encode action for the audit log: {}", e),
+        }
+    }
+
+    /// Record a request to the local action endpoints, whether or not it was submitted.
+    pub fn action_request(&self, record: &ActionRequestRecord) {
+        match serde_json::to_value(record) {
+            Ok(data) => self.note(AuditKind::ActionRequest, data),
+            Err(e) => warn!("Failed to encode action request for the audit log: {}", e),
+        }
+    }
+
+    /// Record an anchor whose state changed, with its commitment and txid.
+    pub fn anchor(&self, anchor: &Anchor) {
+        match serde_json::to_value(anchor) {
+            Ok(data) => self.note(AuditKind::Anchor, data),
+            Err(e) => warn!("Failed to encode anchor for the audit log: {}", e),
+        }
+    }
+
+    /// Record a request to `action` ("pause" or "resume") from `trigger`, and whether it
+    /// changed anything.
+    pub fn pause(&self, action: &str, trigger: &Trigger, changed: bool) {
+        self.note(
+            AuditKind::Pause,
+            serde_json::json!({
+                "action": action,
+                "source": trigger.source,
+                "actor": trigger.actor,
+                "reason": trigger.reason,
+                "changed": changed,
+            }),
+        );
+    }
+
+    /// Record `conflict` between two votes of one voter.
+    pub fn vote_conflict(&self, conflict: &VoteConflict) {
+        match serde_json::to_value(conflict) {
+            Ok(data) => self.note(AuditKind::VoteConflict, data),
+            Err(e) => warn!("Failed to encode vote conflict for the audit log: {}", e),
+        }
+    }
+
+    /// Record the rollback of `handler` to the fork of `reorg`: the number of records it
+    /// reverted, or why it failed.
+    pub fn rollback(
+        &self,
+        handler: &str,
+        reorg: &Reorg,
+        reverted: Option<u64>,
+        error: Option<&str>,
+    ) {
+        self.note(
+            AuditKind::Rollback,
+            serde_json::json!({
+                "handler": handler,
+                "fork_height": reorg.fork_height,
+                "previous_tip": reorg.previous.map(|tip| serde_json::json!({
+                    "hash": hex::encode(tip.hash),
+                    "height": tip.height,
+                })),
+                "tip": {
+                    "hash": hex::encode(reorg.tip.hash),
+                    "height": reorg.tip.height,
+                },
+                "reverted": reverted,
+                "error": error,
+            }),
+        );
+    }
+}
+
+/// Outcome of [`verify`] on an intact log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Verified {
+    pub files: usize,
+    pub entries: u64,
+    /// Last entry; `None` if the log is empty.
+    pub head: Option<AuditHead>,
+}
+
+/// Replay the chain in `dir` from entry 0. Fails with
+/// [`GovernanceError::AuditChainBroken`] at the first entry that is unreadable, out of
+/// sequence, not linked to the one before it, or whose hash does not match.
+pub fn verify(dir: &Path) -> Result<Verified, GovernanceError> {
+    let files = files(dir)?;
+    let mut expected_seq = 0;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (_, path) in &files {
+        let file = File::open(path).map_err(GovernanceError::io(path.display()))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(GovernanceError::io(path.display()))?;
+            let broken = |problem: String| GovernanceError::AuditChainBroken {
+                file: path.display().to_string(),
+                line: i + 1,
+                problem,
+            };
+            let entry: AuditEntry = serde_json::from_str(&line)
+                .map_err(|e| broken(format!("not an audit entry: {}", e)))?;
+            if entry.seq != expected_seq {
+                return Err(broken(format!(
+                    "entry {} where entry {} should be",
+                    entry.seq, expected_seq
+                )));
+            }
+            if entry.prev_hash != prev_hash {
+                return Err(broken(format!(
+                    "entry {} does not follow the hash of the entry before it",
+                    entry.seq
+                )));
+            }
+            if entry.compute_hash() != entry.hash {
+                return Err(broken(format!(
+                    "entry {} does not match its hash; it has been altered",
+                    entry.seq
+                )));
+            }
+            expected_seq += 1;
+            prev_hash = entry.hash;
+        }
+    }
+    Ok(Verified {
+        files: files.len(),
+        entries: expected_seq,
+        head: expected_seq.checked_sub(1).map(|seq| AuditHead {
+            seq,
+            hash: prev_hash,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("blvm_audit_log_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn config(max_file_bytes: u64, rotate_daily: bool) -> AuditLogConfig {
+        AuditLogConfig {
+            enabled: true,
+            max_file_bytes,
+            rotate_daily,
+            record_blocks: false,
+        }
+    }
+
+    #[test]
+    fn test_chain_continues_across_files_and_restarts() {
+        let dir = temp_dir("rotate");
+        let day = 1_700_006_400 / SECS_PER_DAY * SECS_PER_DAY;
+        let log = AuditLog::open(&dir, &config(1 << 20, true)).unwrap();
+        assert!(!dir.exists());
+        let first = log
+            .record_at(AuditKind::Event, serde_json::json!({"n": 0}), day)
+            .unwrap();
+        assert_eq!((first.seq, first.prev_hash.as_str()), (0, GENESIS_HASH));
+        let second = log
+            .record_at(AuditKind::Event, serde_json::json!({"n": 1}), day + 60)
+            .unwrap();
+        assert_eq!(second.prev_hash, first.hash);
+        // The next day starts a new file
+        log.record_at(
+            AuditKind::Event,
+            serde_json::json!({"n": 2}),
+            day + SECS_PER_DAY,
+        )
+        .unwrap();
+        drop(log);
+
+        let log = AuditLog::open(&dir, &config(1 << 20, true)).unwrap();
+        let fourth = log
+            .record_at(
+                AuditKind::Action,
+                serde_json::json!({"n": 3}),
+                day + SECS_PER_DAY,
+            )
+            .unwrap();
+        assert_eq!(fourth.seq, 3);
+        let names: Vec<u64> = files(&dir).unwrap().into_iter().map(|(s, _)| s).collect();
+        assert_eq!(names, vec![0, 2]);
+        let verified = verify(&dir).unwrap();
+        assert_eq!((verified.files, verified.entries), (2, 4));
+        assert_eq!(verified.head, log.head());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rotates_by_size() {
+        let dir = temp_dir("size");
+        let log = AuditLog::open(&dir, &config(400, false)).unwrap();
+        for n in 0..6 {
+            log.record_at(AuditKind::Event, serde_json::json!({"n": n}), n)
+                .unwrap();
+        }
+        let files = files(&dir).unwrap();
+        assert!(files.len() > 1);
+        for (_, path) in &files {
+            assert!(std::fs::metadata(path).unwrap().len() <= 400);
+        }
+        assert_eq!(verify(&dir).unwrap().entries, 6);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_incomplete_entry_is_removed_on_open() {
+        let dir = temp_dir("torn");
+        let log = AuditLog::open(&dir, &config(1 << 20, false)).unwrap();
+        log.record_at(AuditKind::Event, serde_json::json!({}), 0)
+            .unwrap();
+        drop(log);
+        let path = dir.join("audit-000000000000.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\":1,\"timest").unwrap();
+
+        let log = AuditLog::open(&dir, &config(1 << 20, false)).unwrap();
+        assert_eq!(log.head().unwrap().seq, 0);
+        log.record_at(AuditKind::Event, serde_json::json!({}), 1)
+            .unwrap();
+        assert_eq!(verify(&dir).unwrap().entries, 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,239 @@
+//! Online backups
+//!
+//! A backup is a module data directory: a fresh module DB holding a point-in-time copy of
+//! the registry and proposal trees, the module's `config.toml` if there is one, and a
+//! `backup.json` manifest. The copy is stamped with the network of the data (see
+//! [`crate::network`]), so a backup restored for a node on another network is refused like
+//! the directory it came from. Restoring is starting the module with `--data-dir <backup>`;
+//! [`verify`] checks a backup against its manifest first.
+
+use crate::config::BackupConfig;
+use crate::economic_nodes::{commitment, schema, EconomicNodeRegistry};
+use crate::error::GovernanceError;
+use crate::proposals::ProposalStore;
+use crate::storage::DataDir;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+pub const MANIFEST_FILE: &str = "backup.json";
+
+/// Directory scheduled backups are written to, under the module data directory.
+pub const BACKUPS_DIR: &str = "backups";
+
+/// Written alongside each backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Registry store schema version of the copied records.
+    pub schema_version: u32,
+    /// Registry commitment root (hex) of the copied nodes.
+    pub commitment: String,
+    /// Encoding version of `commitment`; manifests written before it was recorded are
+    /// version 1.
+    #[serde(default = "first_commitment_version")]
+    pub commitment_version: u32,
+    pub node_count: u64,
+    /// Unix seconds.
+    pub created_at: u64,
+    pub module_version: String,
+    /// Network the copied data belongs to, if the module store was stamped with one.
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+fn first_commitment_version() -> u32 {
+    1
+}
+
+fn open_db(dir: &Path) -> Result<blvm_sdk::module::ModuleDb, GovernanceError> {
+    blvm_sdk::module::ModuleDb::open_with_migrations(
+        dir,
+        blvm_sdk::migrations!(
+            1 => crate::storage::up_v1,
+            2 => crate::storage::up_v2,
+            3 => crate::storage::up_v3
+        ),
+    )
+    .map_err(GovernanceError::database(format!("open {}", dir.display())))
+}
+
+fn read_manifest(dir: &Path) -> Result<BackupManifest, GovernanceError> {
+    let path = dir.join(MANIFEST_FILE);
+    let data = std::fs::read(&path).map_err(GovernanceError::io(path.display()))?;
+    serde_json::from_slice(&data).map_err(|source| GovernanceError::Serialization {
+        operation: path.display().to_string(),
+        source,
+    })
+}
+
+/// Back up the running module to `dir`, which must not exist or be empty. `config` is the
+/// module's `config.toml`, copied if present.
+pub async fn backup(
+    registry: &EconomicNodeRegistry,
+    proposals: &ProposalStore,
+    config: Option<&Path>,
+    dir: &Path,
+) -> Result<BackupManifest, GovernanceError> {
+    let occupied = std::fs::read_dir(dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if occupied {
+        return Err(GovernanceError::Storage(format!(
+            "backup directory {} is not empty",
+            dir.display()
+        )));
+    }
+    std::fs::create_dir_all(dir).map_err(GovernanceError::io(dir.display()))?;
+
+    let target = open_db(dir)?.as_db();
+    let commitment = registry.copy_to(&target).await?;
+    proposals.copy_to(&target)?;
+    let network = crate::network::stored_in(proposals.store())?;
+    if let Some(network) = &network {
+        crate::network::stamp_store(&target, network)?;
+    }
+    drop(target);
+
+    if let Some(config) = config.filter(|c| c.exists()) {
+        std::fs::copy(config, dir.join("config.toml"))
+            .map_err(GovernanceError::io(config.display()))?;
+    }
+    let manifest = BackupManifest {
+        schema_version: schema::SCHEMA_VERSION,
+        commitment: commitment.root_hex(),
+        commitment_version: commitment.version,
+        node_count: commitment.node_count as u64,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        module_version: env!("CARGO_PKG_VERSION").to_string(),
+        network,
+    };
+    let data = serde_json::to_vec_pretty(&manifest)
+        .map_err(GovernanceError::serialization("backup manifest"))?;
+    std::fs::write(dir.join(MANIFEST_FILE), data).map_err(GovernanceError::io(dir.display()))?;
+    info!(
+        "Backed up {} economic nodes to {} (commitment {})",
+        manifest.node_count,
+        dir.display(),
+        manifest.commitment
+    );
+    Ok(manifest)
+}
+
+/// Check that the backup in `dir` matches its manifest.
+pub fn verify(dir: &Path) -> Result<BackupManifest, GovernanceError> {
+    let manifest = read_manifest(dir)?;
+    if manifest.schema_version > schema::SCHEMA_VERSION {
+        return Err(GovernanceError::Storage(format!(
+            "backup schema version {} is newer than the supported version {}",
+            manifest.schema_version,
+            schema::SCHEMA_VERSION
+        )));
+    }
+    let db = open_db(dir)?.as_db();
+    let nodes = EconomicNodeRegistry::load_from(&db)?;
+    let actual = commitment::compute_version(manifest.commitment_version, nodes.values())
+        .ok_or_else(|| {
+            GovernanceError::Storage(format!(
+                "backup commitment version {} is not supported",
+                manifest.commitment_version
+            ))
+        })?
+        .root_hex();
+    if actual != manifest.commitment {
+        return Err(GovernanceError::Storage(format!(
+            "backup commitment {} does not match manifest {}",
+            actual, manifest.commitment
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Delete all but the newest `retention` backups in `backups_dir`. Returns the number deleted.
+pub fn prune(backups_dir: &Path, retention: usize) -> Result<usize, GovernanceError> {
+    let mut backups: Vec<PathBuf> = match std::fs::read_dir(backups_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.join(MANIFEST_FILE).exists())
+            .collect(),
+        Err(_) => return Ok(0),
+    };
+    // Scheduled backups are named by creation time, so names sort oldest first
+    backups.sort();
+    let excess = backups.len().saturating_sub(retention);
+    for path in &backups[..excess] {
+        std::fs::remove_dir_all(path).map_err(GovernanceError::io(path.display()))?;
+    }
+    Ok(excess)
+}
+
+/// Back up to `<data_dir>/backups/<unix seconds>` every `interval_secs`, keeping the newest
+/// `retention` backups.
+pub fn spawn_scheduled(
+    registry: Arc<EconomicNodeRegistry>,
+    proposals: Arc<ProposalStore>,
+    data_dir: DataDir,
+    config: BackupConfig,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if config.interval_secs == 0 {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let backups_dir = data_dir.backups();
+        let config_path = crate::config::file_path(data_dir.root());
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(config.interval_secs));
+        // The first tick completes immediately; wait a full interval before the first backup
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let name = format!(
+                "{:020}",
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            );
+            let dir = backups_dir.join(name);
+            if let Err(e) = backup(&registry, &proposals, Some(&config_path), &dir).await {
+                warn!(
+                    "Scheduled backup to {} failed: {}",
+                    dir.display(),
+                    e.chain()
+                );
+                continue;
+            }
+            match prune(&backups_dir, config.retention) {
+                Ok(0) => {}
+                Ok(n) => info!("Deleted {} old backups", n),
+                Err(e) => warn!("Failed to prune old backups: {}", e.chain()),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let root = std::env::temp_dir().join(format!("blvm_backup_prune_{}", std::process::id()));
+        for name in ["0003", "0001", "0002", "partial"] {
+            let dir = root.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            if name != "partial" {
+                std::fs::write(dir.join(MANIFEST_FILE), b"{}").unwrap();
+            }
+        }
+        assert_eq!(prune(&root, 2).unwrap(), 1);
+        assert!(!root.join("0001").exists());
+        assert!(root.join("0002").exists() && root.join("0003").exists());
+        // Directories without a manifest are not backups and are left alone
+        assert!(root.join("partial").exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,153 @@
+//! What this binary was built from
+//!
+//! Gathered by the build script (`build.rs`) when the crate is compiled: the git commit and
+//! whether the working tree had uncommitted changes, the build time (`SOURCE_DATE_EPOCH` if
+//! set), the rustc version and the enabled cargo features, alongside the crate version and
+//! the node IPC protocol versions the module speaks. Shown by `--version` and the `version`
+//! subcommand, included in the status reports sent to the node (see
+//! [`crate::status_report`]) and in `test-webhook` payloads, and served on `GET /version` by
+//! the health listener (see [`crate::health`]).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Node IPC protocol versions this build can talk to, oldest first. The protocol carries no
+/// version yet, so this is the one message format the module is built against.
+pub const IPC_PROTOCOL_VERSIONS: ProtocolRange = ProtocolRange { min: 1, max: 1 };
+
+/// A range of protocol versions, both ends included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Version and build details of the running binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Commit built from; `None` when built outside a git checkout.
+    pub git_commit: Option<String>,
+    /// Whether tracked files had uncommitted changes.
+    pub git_dirty: bool,
+    /// Build time, RFC 3339 in UTC.
+    pub build_timestamp: String,
+    pub rustc_version: String,
+    /// Enabled cargo features, sorted.
+    pub features: Vec<String>,
+    pub ipc_protocol: ProtocolRange,
+}
+
+impl BuildInfo {
+    /// This binary's build information.
+    pub fn current() -> &'static BuildInfo {
+        static CURRENT: OnceLock<BuildInfo> = OnceLock::new();
+        CURRENT.get_or_init(|| BuildInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: Some(env!("BLVM_BUILD_GIT_COMMIT"))
+                .filter(|c| !c.is_empty())
+                .map(String::from),
+            git_dirty: env!("BLVM_BUILD_GIT_DIRTY") == "true",
+            build_timestamp: rfc3339(env!("BLVM_BUILD_TIMESTAMP").parse().unwrap_or_default()),
+            rustc_version: env!("BLVM_BUILD_RUSTC").to_string(),
+            features: env!("BLVM_BUILD_FEATURES")
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect(),
+            ipc_protocol: IPC_PROTOCOL_VERSIONS,
+        })
+    }
+
+    /// The details after the version, one per line.
+    fn details(&self) -> String {
+        let commit = match (&self.git_commit, self.git_dirty) {
+            (Some(commit), true) => format!("{} (dirty)", commit),
+            (Some(commit), false) => commit.clone(),
+            (None, _) => "unknown".to_string(),
+        };
+        let features = if self.features.is_empty() {
+            "none".to_string()
+        } else {
+            self.features.join(", ")
+        };
+        let protocol = &self.ipc_protocol;
+        format!(
+            "commit:       {}\nbuilt:        {}\nrustc:        {}\nfeatures:     {}\nipc protocol: {}..={}",
+            commit, self.build_timestamp, self.rustc_version, features, protocol.min, protocol.max
+        )
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blvm-governance {}\n{}", self.version, self.details())
+    }
+}
+
+/// What `--version` prints after the binary's name.
+pub fn long_version() -> &'static str {
+    static LONG_VERSION: OnceLock<String> = OnceLock::new();
+    LONG_VERSION.get_or_init(|| {
+        let info = BuildInfo::current();
+        format!("{}\n{}", info.version, info.details())
+    })
+}
+
+/// Year, month and day of `days` since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 UTC time.
+pub(crate) fn rfc3339(secs: u64) -> String {
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(1_735_689_599), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_current_build_info() {
+        let info = BuildInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.rustc_version.starts_with("rustc "));
+        assert_eq!(
+            info.features.iter().any(|f| f == "testing"),
+            cfg!(feature = "testing")
+        );
+        assert_eq!(info.ipc_protocol, IPC_PROTOCOL_VERSIONS);
+
+        let json = serde_json::to_value(info).unwrap();
+        assert_eq!(serde_json::from_value::<BuildInfo>(json).unwrap(), *info);
+        assert!(long_version().starts_with(&format!("{}\ncommit:", info.version)));
+        assert!(info.to_string().starts_with("blvm-governance "));
+    }
+}
@@ -0,0 +1,278 @@
+//! Proof-of-work arithmetic
+//!
+//! A block's work is the expected number of hashes needed to find it, 2^256 / (target + 1),
+//! with the target decoded from the header's compact `bits`. Sums of work outgrow any
+//! primitive integer, so [`Work`] is a 256-bit unsigned integer, serialized as 64 hex digits
+//! as Bitcoin Core prints `chainwork`. Difficulty follows Core's `GetDifficulty`: the
+//! difficulty-1 target over the block's target, as a float.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// An amount of work: a 256-bit unsigned integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Work([u64; 4]);
+
+impl Work {
+    pub const ZERO: Work = Work([0; 4]);
+
+    pub fn from_u128(value: u128) -> Self {
+        Self([value as u64, (value >> 64) as u64, 0, 0])
+    }
+
+    /// The value if it fits in 128 bits.
+    pub fn to_u128(self) -> Option<u128> {
+        (self.0[2] == 0 && self.0[3] == 0).then(|| (self.0[1] as u128) << 64 | self.0[0] as u128)
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+            limbs[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Self(limbs)
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().rev().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn is_zero(self) -> bool {
+        self == Self::ZERO
+    }
+
+    pub fn checked_add(self, other: Work) -> Option<Work> {
+        let mut sum = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in sum.iter_mut().enumerate() {
+            let (partial, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (total, c2) = partial.overflowing_add(carry as u64);
+            *limb = total;
+            carry = c1 || c2;
+        }
+        (!carry).then_some(Work(sum))
+    }
+
+    pub fn saturating_add(self, other: Work) -> Work {
+        self.checked_add(other).unwrap_or(Work([u64::MAX; 4]))
+    }
+
+    pub fn checked_sub(self, other: Work) -> Option<Work> {
+        let mut difference = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in difference.iter_mut().enumerate() {
+            let (partial, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (total, b2) = partial.overflowing_sub(borrow as u64);
+            *limb = total;
+            borrow = b1 || b2;
+        }
+        (!borrow).then_some(Work(difference))
+    }
+
+    fn bit(self, i: usize) -> bool {
+        self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    fn shl(self, shift: u32) -> Work {
+        let (limbs, bits) = ((shift / 64) as usize, shift % 64);
+        let mut shifted = [0u64; 4];
+        for (i, limb) in shifted.iter_mut().enumerate().skip(limbs) {
+            *limb = self.0[i - limbs] << bits;
+            if bits > 0 && i > limbs {
+                *limb |= self.0[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        Work(shifted)
+    }
+
+    /// `self / divisor`, by long division. `divisor` must be below 2^255 and not zero.
+    fn div(self, divisor: Work) -> Work {
+        let mut quotient = Work::ZERO;
+        let mut remainder = Work::ZERO;
+        for i in (0..256).rev() {
+            remainder = remainder.shl(1);
+            remainder.0[0] |= self.bit(i) as u64;
+            if remainder >= divisor {
+                remainder = remainder.checked_sub(divisor).unwrap();
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        quotient
+    }
+}
+
+impl Ord for Work {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for Work {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::iter::Sum for Work {
+    fn sum<I: Iterator<Item = Work>>(iter: I) -> Self {
+        iter.fold(Work::ZERO, Work::saturating_add)
+    }
+}
+
+impl fmt::Display for Work {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.to_be_bytes()))
+    }
+}
+
+impl FromStr for Work {
+    type Err = String;
+
+    /// Up to 64 hex digits, with or without a `0x` prefix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(format!("invalid work {:?}", s));
+        }
+        let padded = format!("{:0>64}", digits);
+        let bytes = hex::decode(padded).map_err(|e| format!("invalid work {:?}: {}", s, e))?;
+        Ok(Self::from_be_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl Serialize for Work {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Work {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The target encoded by compact `bits`; `None` if it is negative or does not fit.
+pub fn target_from_bits(bits: u32) -> Option<Work> {
+    let size = bits >> 24;
+    let word = bits & 0x007f_ffff;
+    let negative = word != 0 && bits & 0x0080_0000 != 0;
+    let overflow =
+        word != 0 && (size > 34 || (word > 0xff && size > 33) || (word > 0xffff && size > 32));
+    if negative || overflow {
+        return None;
+    }
+    Some(if size <= 3 {
+        Work::from_u128((word >> (8 * (3 - size))) as u128)
+    } else {
+        Work::from_u128(word as u128).shl(8 * (size - 3))
+    })
+}
+
+/// Work of a block with compact target `bits`; zero for an invalid or zero target.
+pub fn work_from_bits(bits: u32) -> Work {
+    match target_from_bits(bits) {
+        Some(target) if !target.is_zero() => {
+            // 2^256 / (target + 1) = ~target / (target + 1) + 1, without 257-bit numbers
+            let not_target = Work(target.0.map(|limb| !limb));
+            let divisor = target.checked_add(Work::from_u128(1)).unwrap();
+            not_target.div(divisor).saturating_add(Work::from_u128(1))
+        }
+        _ => Work::ZERO,
+    }
+}
+
+/// Difficulty of a block with compact target `bits`, as Bitcoin Core computes it.
+pub fn difficulty_from_bits(bits: u32) -> f64 {
+    let mantissa = bits & 0x00ff_ffff;
+    if mantissa == 0 {
+        return 0.0;
+    }
+    let mut shift = (bits >> 24) & 0xff;
+    let mut difficulty = 0x0000_ffff as f64 / mantissa as f64;
+    while shift < 29 {
+        difficulty *= 256.0;
+        shift += 1;
+    }
+    while shift > 29 {
+        difficulty /= 256.0;
+        shift -= 1;
+    }
+    difficulty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_work_from_mainnet_bits() {
+        // Genesis, block 100000 and block 840000
+        let vectors = [
+            (0x1d00_ffff, "100010001", 1.0),
+            (0x1b04_04cb, "3fb3ab764c00", 16307.420938523983),
+            (0x1703_4219, "4e9235f043634662e0cb", 86388558925171.02),
+        ];
+        for (bits, work, difficulty) in vectors {
+            assert_eq!(
+                work_from_bits(bits),
+                work.parse::<Work>().unwrap(),
+                "{:08x}",
+                bits
+            );
+            assert_eq!(difficulty_from_bits(bits), difficulty, "{:08x}", bits);
+        }
+        assert_eq!(work_from_bits(0x1d00_ffff).to_u128(), Some(4_295_032_833));
+
+        // Core's chainwork after the genesis block and block 1
+        let chainwork: Work = [0x1d00_ffff, 0x1d00_ffff]
+            .map(work_from_bits)
+            .into_iter()
+            .sum();
+        assert_eq!(
+            chainwork.to_string(),
+            "0000000000000000000000000000000000000000000000000000000200020002"
+        );
+    }
+
+    #[test]
+    fn test_invalid_bits() {
+        assert_eq!(target_from_bits(0x0180_0001), None);
+        assert_eq!(target_from_bits(0x2301_0000), None);
+        assert_eq!(work_from_bits(0x0180_0001), Work::ZERO);
+        assert_eq!(work_from_bits(0), Work::ZERO);
+        assert_eq!(
+            target_from_bits(0x0312_3456).unwrap().to_u128(),
+            Some(0x12_3456)
+        );
+        assert_eq!(target_from_bits(0x0112_3456).unwrap().to_u128(), Some(0x12));
+    }
+
+    #[test]
+    fn test_work_arithmetic_and_serialization() {
+        let big = Work::from_u128(u128::MAX);
+        let sum = big.checked_add(Work::from_u128(1)).unwrap();
+        assert_eq!(sum.to_u128(), None);
+        assert!(sum > big);
+        assert_eq!(sum.checked_sub(big), Some(Work::from_u128(1)));
+        assert_eq!(big.checked_sub(sum), None);
+        assert_eq!(Work([u64::MAX; 4]).checked_add(Work::from_u128(1)), None);
+
+        let json = serde_json::to_string(&sum).unwrap();
+        assert_eq!(
+            json,
+            "\"0000000000000000000000000000000100000000000000000000000000000000\""
+        );
+        assert_eq!(serde_json::from_str::<Work>(&json).unwrap(), sum);
+        assert_eq!("0x10".parse::<Work>().unwrap(), Work::from_u128(16));
+        assert!("xyz".parse::<Work>().is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Event checkpoint and block backfill
+//!
+//! `checkpoint.json` in the data directory's `state/` (see [`crate::storage::DataDir`]) records the last block whose `NewBlock` event was
+//! fully processed by every handler of the [`Pipeline`](crate::pipeline::Pipeline), the
+//! number of events fully processed (`sequence`), and which handlers have already completed
+//! the block in progress. It is written atomically after each step. The node does not replay
+//! events, so when the module (re)connects, blocks announced since the checkpoint are fetched
+//! from the node and queued as `NewBlock` events ahead of live ones; handlers that completed
+//! a replayed block before a crash are not run for it again. A `NewBlock` event for the
+//! checkpointed block itself is skipped, so an overlapping replay is not processed twice.
+//! A reorg rolled back by the pipeline moves the checkpoint back to the fork
+//! ([`Checkpointer::rewind`]), so the blocks of the new chain are backfilled from there.
+
+use crate::error::GovernanceError;
+use crate::event_queue::EventQueue;
+use crate::node_api::NodeApiIpc;
+use crate::shutdown::Shutdown;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload};
+use blvm_node::module::EventType;
+use blvm_protocol::Hash;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Last fully processed block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub height: u64,
+    /// Hex block hash.
+    pub block_hash: String,
+}
+
+/// Handlers that have completed a block not yet fully processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockProgress {
+    pub height: u64,
+    /// Hex block hash.
+    pub block_hash: String,
+    pub completed: Vec<String>,
+}
+
+/// Contents of `checkpoint.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CheckpointFile {
+    #[serde(flatten)]
+    last: Option<Checkpoint>,
+    /// Events fully processed.
+    #[serde(default)]
+    sequence: u64,
+    #[serde(default)]
+    in_progress: Option<BlockProgress>,
+}
+
+#[derive(Debug)]
+pub struct Checkpointer {
+    path: PathBuf,
+    state: Mutex<CheckpointFile>,
+}
+
+/// Write `data` to `path` via a temporary file and rename, so readers never see a partial
+/// file.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<(), GovernanceError> {
+    use std::io::Write;
+    let tmp = path.with_extension("json.tmp");
+    let err = |e| GovernanceError::io(tmp.display())(e);
+    let mut file = std::fs::File::create(&tmp).map_err(err)?;
+    file.write_all(data).map_err(err)?;
+    file.sync_all().map_err(err)?;
+    std::fs::rename(&tmp, path).map_err(GovernanceError::io(path.display()))
+}
+
+impl Checkpointer {
+    /// Load the checkpoint in `dir`, if there is one.
+    pub fn open(dir: &Path) -> Result<Self, GovernanceError> {
+        let path = dir.join(CHECKPOINT_FILE);
+        let state = match std::fs::read(&path) {
+            Ok(data) => {
+                serde_json::from_slice(&data).map_err(|source| GovernanceError::Serialization {
+                    operation: path.display().to_string(),
+                    source,
+                })?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CheckpointFile::default(),
+            Err(e) => return Err(GovernanceError::io(path.display())(e)),
+        };
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn last(&self) -> Option<Checkpoint> {
+        self.state.lock().unwrap().last.clone()
+    }
+
+    /// Events fully processed.
+    pub fn sequence(&self) -> u64 {
+        self.state.lock().unwrap().sequence
+    }
+
+    /// Whether this block is the checkpointed one.
+    pub fn is_processed(&self, height: u64, block_hash: &Hash) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .last
+            .as_ref()
+            .is_some_and(|c| c.height == height && c.block_hash == hex::encode(block_hash))
+    }
+
+    /// Whether `handler` has completed this block.
+    pub fn is_completed(&self, height: u64, block_hash: &Hash, handler: &str) -> bool {
+        self.is_processed(height, block_hash)
+            || self
+                .state
+                .lock()
+                .unwrap()
+                .in_progress
+                .as_ref()
+                .is_some_and(|p| {
+                    p.height == height
+                        && p.block_hash == hex::encode(block_hash)
+                        && p.completed.iter().any(|h| h == handler)
+                })
+    }
+
+    /// Apply `change` and write the result; the state is unchanged if the write fails.
+    fn update(&self, change: impl FnOnce(&mut CheckpointFile)) -> Result<(), GovernanceError> {
+        let mut state = self.state.lock().unwrap();
+        let mut next = state.clone();
+        change(&mut next);
+        let data =
+            serde_json::to_vec(&next).map_err(GovernanceError::serialization("checkpoint"))?;
+        write_atomic(&self.path, &data)?;
+        *state = next;
+        Ok(())
+    }
+
+    /// Record that `handler` has completed this block.
+    pub fn record_handler(
+        &self,
+        height: u64,
+        block_hash: &Hash,
+        handler: &str,
+    ) -> Result<(), GovernanceError> {
+        let block_hash = hex::encode(block_hash);
+        self.update(|state| {
+            let progress = match &mut state.in_progress {
+                Some(p) if p.height == height && p.block_hash == block_hash => p,
+                other => other.insert(BlockProgress {
+                    height,
+                    block_hash,
+                    completed: Vec::new(),
+                }),
+            };
+            progress.completed.push(handler.to_string());
+        })
+    }
+
+    /// Record a fully processed block.
+    pub fn record(&self, height: u64, block_hash: &Hash) -> Result<(), GovernanceError> {
+        let block_hash = hex::encode(block_hash);
+        self.update(|state| {
+            state.last = Some(Checkpoint { height, block_hash });
+            state.sequence += 1;
+            state.in_progress = None;
+        })
+    }
+
+    /// Record a fully processed event other than `NewBlock`.
+    pub fn record_event(&self) -> Result<(), GovernanceError> {
+        self.update(|state| state.sequence += 1)
+    }
+
+    /// Move the checkpoint back to `block_hash` at `height`, after a reorg replaced the
+    /// blocks above it, so they are backfilled again. A checkpoint at or below `height` is
+    /// left as it is.
+    pub fn rewind(&self, height: u64, block_hash: &Hash) -> Result<(), GovernanceError> {
+        if self.last().is_none_or(|c| c.height <= height) {
+            return Ok(());
+        }
+        let block_hash = hex::encode(block_hash);
+        self.update(|state| {
+            state.last = Some(Checkpoint { height, block_hash });
+            state.in_progress = None;
+        })
+    }
+}
+
+/// Blocks after `checkpoint` up to the node's tip, oldest first, at most the newest
+/// `max_blocks`. The blocks are streamed from the node by height and each hash is taken from
+/// the next block's parent hash, so only hashes are kept and they are all on the node's
+/// current chain.
+pub async fn missed_blocks(
+    node_api: &NodeApiIpc,
+    checkpoint: &Checkpoint,
+    max_blocks: u64,
+) -> Result<Vec<(u64, Hash)>, GovernanceError> {
+    let tip = node_api.get_best_block().await?;
+    if max_blocks == 0 || tip.height < checkpoint.height {
+        return Ok(Vec::new());
+    }
+    // A reorg since the checkpoint replaced its block; the new one is missed too
+    let mut first = checkpoint.height + 1;
+    if hex::encode(node_api.get_block_hash_at(checkpoint.height).await?) != checkpoint.block_hash {
+        warn!(
+            "Checkpointed block at height {} is no longer on the node's chain",
+            checkpoint.height
+        );
+        first = checkpoint.height;
+    }
+    if tip.height < first {
+        return Ok(Vec::new());
+    }
+    let start = first.max(tip.height.saturating_sub(max_blocks - 1));
+    if start > first {
+        warn!(
+            "{} blocks missed since height {}; backfilling only the newest {}",
+            tip.height - checkpoint.height,
+            checkpoint.height,
+            max_blocks
+        );
+    }
+    let mut blocks = Vec::with_capacity((tip.height - start + 1) as usize);
+    let mut children = std::pin::pin!(node_api.stream_blocks(start + 1..tip.height + 1));
+    while let Some(child) = children.next().await {
+        let (height, child) = child?;
+        blocks.push((height - 1, child.header.prev_block_hash));
+    }
+    blocks.push((tip.height, tip.hash));
+    Ok(blocks)
+}
+
+/// Queue `NewBlock` events for blocks missed since the checkpoint. Returns the number queued.
+pub async fn backfill(
+    checkpointer: &Checkpointer,
+    node_api: &NodeApiIpc,
+    queue: &EventQueue,
+    shutdown: &Arc<Shutdown>,
+    max_blocks: u64,
+) -> Result<usize, GovernanceError> {
+    let Some(checkpoint) = checkpointer.last() else {
+        return Ok(0);
+    };
+    let blocks = missed_blocks(node_api, &checkpoint, max_blocks).await?;
+    if !blocks.is_empty() {
+        info!(
+            "Backfilling {} blocks missed since height {}",
+            blocks.len(),
+            checkpoint.height
+        );
+    }
+    let mut queued = 0;
+    for (height, block_hash) in blocks {
+        let event = EventMessage {
+            event_type: EventType::NewBlock,
+            payload: EventPayload::NewBlock { block_hash, height },
+        };
+        if queue.send(event, shutdown.track()).await {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_reload() {
+        let dir = std::env::temp_dir().join(format!("blvm_checkpoint_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checkpointer = Checkpointer::open(&dir).unwrap();
+        assert_eq!(checkpointer.last(), None);
+
+        checkpointer.record(100, &[7u8; 32]).unwrap();
+        assert!(checkpointer.is_processed(100, &[7u8; 32]));
+        // A reorg at the same height is a different block
+        assert!(!checkpointer.is_processed(100, &[8u8; 32]));
+        assert!(!dir.join("checkpoint.json.tmp").exists());
+
+        let reopened = Checkpointer::open(&dir).unwrap();
+        assert_eq!(reopened.last().unwrap().height, 100);
+        assert_eq!(reopened.sequence(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_handler_progress() {
+        let dir =
+            std::env::temp_dir().join(format!("blvm_checkpoint_progress_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checkpointer = Checkpointer::open(&dir).unwrap();
+        checkpointer
+            .record_handler(5, &[1u8; 32], "webhook")
+            .unwrap();
+        assert!(checkpointer.is_completed(5, &[1u8; 32], "webhook"));
+        assert!(!checkpointer.is_completed(5, &[1u8; 32], "registry"));
+
+        // Progress survives a restart; a different block starts over
+        let reopened = Checkpointer::open(&dir).unwrap();
+        assert!(reopened.is_completed(5, &[1u8; 32], "webhook"));
+        reopened.record_handler(6, &[2u8; 32], "registry").unwrap();
+        assert!(!reopened.is_completed(5, &[1u8; 32], "webhook"));
+
+        reopened.record(6, &[2u8; 32]).unwrap();
+        assert!(reopened.is_completed(6, &[2u8; 32], "webhook"));
+        assert_eq!(reopened.sequence(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rewind() {
+        let dir =
+            std::env::temp_dir().join(format!("blvm_checkpoint_rewind_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checkpointer = Checkpointer::open(&dir).unwrap();
+        checkpointer.record(10, &[1u8; 32]).unwrap();
+        checkpointer
+            .record_handler(11, &[2u8; 32], "webhook")
+            .unwrap();
+
+        // Not past the fork: kept
+        checkpointer.rewind(12, &[9u8; 32]).unwrap();
+        assert!(checkpointer.is_processed(10, &[1u8; 32]));
+        assert!(checkpointer.is_completed(11, &[2u8; 32], "webhook"));
+
+        checkpointer.rewind(8, &[3u8; 32]).unwrap();
+        let reopened = Checkpointer::open(&dir).unwrap();
+        assert!(reopened.is_processed(8, &[3u8; 32]));
+        assert!(!reopened.is_completed(11, &[2u8; 32], "webhook"));
+        assert_eq!(reopened.sequence(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_reads_checkpoint_without_progress() {
+        let dir = std::env::temp_dir().join(format!("blvm_checkpoint_v1_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let block_hash = hex::encode([3u8; 32]);
+        std::fs::write(
+            dir.join(CHECKPOINT_FILE),
+            format!(r#"{{"height":9,"block_hash":"{}"}}"#, block_hash),
+        )
+        .unwrap();
+        let checkpointer = Checkpointer::open(&dir).unwrap();
+        assert!(checkpointer.is_processed(9, &[3u8; 32]));
+        assert_eq!(checkpointer.sequence(), 0);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,1038 @@
+//! Command line of the `blvm-governance` binary
+//!
+//! Without a subcommand, or with `run`, the binary runs the module and connects to the node.
+//! `self-test [--skip-node] [--skip-webhook]` connects to check that it can (see
+//! [`crate::self_test`]). The other subcommands are for operators and never connect to the
+//! node; they work from the configuration file and the module's data directory:
+//!
+//! - `check-config` validates the configuration (see [`crate::config_check`]).
+//! - `init-config [--out <file>] [--force]` writes a starter configuration with every setting
+//!   at its default (see [`crate::config_template`]), to stdout without `--out`.
+//! - `test-webhook [--event-type proposal_created]` posts a synthetic payload to the
+//!   configured webhook and prints the response status.
+//! - `export-registry --out <file> [--format json|csv]` writes the stored registry.
+//! - `export-summaries --out <file> [--format json|csv]` writes the stored epoch summaries
+//!   (see [`crate::epoch_summary`]).
+//! - `export-history --out <dir> [--from-height <h>] [--to-height <h>] [--format csv|parquet]`
+//!   writes the governance history as flat tables (see [`crate::history_export`]).
+//! - `export-delegations --out <file> [--format json|csv]` writes the stored delegation
+//!   records (see [`crate::delegation`]).
+//! - `export-conflicts --out <file> [--format json|csv]` writes the stored conflict graph of
+//!   proposals (see [`crate::conflicts`]).
+//! - `export-veto-evidence --proposal <id> --signing-key <file> --out <file>` writes a signed
+//!   bundle of the evidence for the stored veto tally on a proposal, and
+//!   `verify-veto-evidence <file>` checks one (see [`crate::veto_evidence`]).
+//! - `show-node <id> [--include-archived]` prints one stored node.
+//! - `verify-audit` replays the audit log's hash chain and fails at the first broken link
+//!   (see [`crate::audit_log`]).
+//! - `recompute-participation` rebuilds the stored participation statistics from the stored
+//!   history (see [`crate::participation`]).
+//! - `dump-schema [--out <dir>]` writes the JSON Schema documents of the webhook and alert
+//!   payloads (see [`crate::schema`]), to stdout as one object without `--out`.
+//!
+//! `replay --handlers <webhook,registry,proposals> [--from-height <h>] [--to-height <h>]
+//! [--since <unix secs>] [--until <unix secs>] [--file <audit lines>] [--offline]` feeds
+//! recorded events through handlers again (see [`crate::replay`]); it connects to the node
+//! unless `--offline`.
+//! - `version [--json]` prints the version and build details (see [`crate::build_info`]),
+//!   as `--version` does.
+//!
+//! `export-registry`, `export-summaries`, `export-history`, `export-delegations`,
+//! `export-conflicts`, `export-veto-evidence`, `show-node` and `recompute-participation`
+//! open the module store directly, so they fail while a running module holds it; use the
+//! module's CLI commands through the node then.
+//!
+//! `status [--json]` is the opposite: it asks the module running on the data directory for its
+//! status over the admin socket (see [`crate::admin`] and [`crate::status`]), and fails if none
+//! is. So do `pause [--reason <text>]` and `resume [--reason <text>]`, which stop and restart
+//! the module's outbound effects (see [`crate::pause`]), recorded as coming from the user
+//! running them.
+
+use crate::build_info::BuildInfo;
+use crate::config::{DryRun, GovernanceConfig, LogFormat, LoggingConfig, CONFIG_ENV};
+use crate::economic_nodes::{parse_node_id, EconomicNodeDetails, EconomicNodeRegistry};
+use crate::error::GovernanceError;
+use crate::history_export::{HistoryFormat, HistoryRange};
+use crate::pause::Trigger;
+use crate::proposals::ProposalStore;
+use crate::replay::{ReplayHandler, ReplayRange};
+use crate::webhook::GovernanceWebhookClient;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Data directory when neither `--data-dir` nor `DATA_DIR` is given.
+pub const DEFAULT_DATA_DIR: &str = "data/modules/blvm-governance";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "blvm-governance",
+    version,
+    long_version = crate::build_info::long_version(),
+    about = "Governance webhook and economic node tracking module for blvm-node"
+)]
+pub struct Args {
+    /// Module id; given by the node when it spawns the module.
+    #[arg(long, env = "MODULE_ID", global = true)]
+    pub module_id: Option<String>,
+    /// Node IPC socket; given by the node when it spawns the module.
+    #[arg(long, env = "SOCKET_PATH", global = true)]
+    pub socket_path: Option<PathBuf>,
+    /// Module data directory.
+    #[arg(long, env = "DATA_DIR", global = true, default_value = DEFAULT_DATA_DIR)]
+    pub data_dir: PathBuf,
+    /// Configuration file [default: config.toml in the data directory]
+    #[arg(long, env = CONFIG_ENV, global = true)]
+    pub config: Option<PathBuf>,
+    /// Log output format [default: logging.format in the configuration, else full]
+    #[arg(long, value_enum, global = true)]
+    pub log_format: Option<LogFormat>,
+    /// Log level, overriding logging.level in the configuration and RUST_LOG.
+    #[arg(
+        long,
+        global = true,
+        value_parser = clap::builder::PossibleValuesParser::new(crate::logging::LEVELS)
+    )]
+    pub log_level: Option<String>,
+    /// Log webhook payloads instead of sending them; `--dry-run=all` also logs governance
+    /// actions instead of submitting them [default: dry_run in the configuration]
+    #[arg(
+        long,
+        value_enum,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "webhook",
+        value_name = "SCOPE"
+    )]
+    pub dry_run: Option<DryRun>,
+    /// Take over a data directory of another network than the node's, stamping it with the
+    /// node's, instead of refusing it (see `blvm_governance::network`).
+    #[arg(long, global = true)]
+    pub force_network_migration: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    /// Run the module (the default).
+    Run,
+    /// Validate the configuration file and exit.
+    CheckConfig,
+    /// Write a configuration file listing every setting with its default and description.
+    InitConfig {
+        /// File to write [default: stdout]
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Overwrite `--out` if it exists.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Post a synthetic event to the configured webhook and print the response status.
+    TestWebhook {
+        /// Event type of the payload.
+        #[arg(long, default_value = "proposal_created")]
+        event_type: String,
+    },
+    /// Write the stored registry to a file. With csv, veto tallies go to
+    /// `<out>.tallies.csv`.
+    ExportRegistry {
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+    /// Write the stored epoch summaries to a file, one row per epoch with csv.
+    ExportSummaries {
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+    /// Write proposals, votes, vetoes, weight changes and signaling as flat tables, with a
+    /// manifest, to a directory.
+    ExportHistory {
+        /// Directory to write to; created if needed.
+        #[arg(long)]
+        out: PathBuf,
+        /// Leave out rows below this height.
+        #[arg(long)]
+        from_height: Option<u64>,
+        /// Leave out rows above this height.
+        #[arg(long)]
+        to_height: Option<u64>,
+        #[arg(long, value_enum, default_value_t = HistoryFormat::Csv)]
+        format: HistoryFormat,
+    },
+    /// Write the stored delegation records to a file, one row per record with csv.
+    ExportDelegations {
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+    /// Write the stored conflict graph of proposals to a file, one row per conflict with csv.
+    ExportConflicts {
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+    /// Write a signed bundle of the evidence for the stored veto tally on a proposal.
+    ExportVetoEvidence {
+        #[arg(long)]
+        proposal: String,
+        /// File holding the secp256k1 key to sign the bundle with, in hex.
+        #[arg(long)]
+        signing_key: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Check a veto evidence bundle, and exit non-zero if any check fails.
+    VerifyVetoEvidence {
+        /// Bundle written by `export-veto-evidence`.
+        file: PathBuf,
+    },
+    /// Print one stored economic node.
+    ShowNode {
+        /// Node id, 64 hex characters.
+        id: String,
+        /// Also look in the archive of evicted nodes.
+        #[arg(long)]
+        include_archived: bool,
+    },
+    /// Replay the audit log's hash chain and report the first broken link.
+    VerifyAudit,
+    /// Rebuild the stored participation statistics from the stored proposals, ballots,
+    /// snapshots and vetoes.
+    RecomputeParticipation,
+    /// Feed recorded events through handlers again, and exit non-zero if any failed.
+    Replay(ReplayArgs),
+    /// Check the configuration, the store, the node connection and the webhook, and exit
+    /// non-zero if any check fails.
+    SelfTest {
+        /// Do not connect to the node.
+        #[arg(long)]
+        skip_node: bool,
+        /// Do not post to the webhook.
+        #[arg(long)]
+        skip_webhook: bool,
+    },
+    /// Print the status of the module running on the data directory.
+    Status {
+        /// Print it as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Pause the outbound effects of the module running on the data directory.
+    Pause {
+        /// Why, for the audit log.
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Resume the outbound effects of the module running on the data directory, sending what
+    /// was held while paused.
+    Resume {
+        /// Why, for the audit log.
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Write the JSON Schema document of each outbound payload.
+    DumpSchema {
+        /// Directory to write `<name>.schema.json` files to; created if needed [default: print
+        /// them all as one JSON object]
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Print the version and build details.
+    Version {
+        /// Print them as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, clap::Args)]
+pub struct ReplayArgs {
+    /// Handlers to drive, comma-separated.
+    #[arg(long, value_enum, value_delimiter = ',', required = true)]
+    pub handlers: Vec<ReplayHandler>,
+    /// First height to replay.
+    #[arg(long)]
+    pub from_height: Option<u64>,
+    /// Last height to replay.
+    #[arg(long)]
+    pub to_height: Option<u64>,
+    /// First time to replay, in Unix seconds.
+    #[arg(long)]
+    pub since: Option<u64>,
+    /// Last time to replay, in Unix seconds.
+    #[arg(long)]
+    pub until: Option<u64>,
+    /// File of audit log lines to read [default: the audit log in the data directory]
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+    /// Do not connect to the node; serve blocks recorded in the log instead.
+    #[arg(long)]
+    pub offline: bool,
+}
+
+impl ReplayArgs {
+    pub fn range(&self) -> ReplayRange {
+        ReplayRange {
+            from_height: self.from_height,
+            to_height: self.to_height,
+            since: self.since,
+            until: self.until,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl Args {
+    /// The configuration file: `--config`, else `config.toml` in the data directory.
+    pub fn config_file(&self) -> PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("config.toml"))
+    }
+
+    /// `config` with `--log-format` and `--log-level` applied.
+    pub fn logging(&self, config: &LoggingConfig) -> LoggingConfig {
+        LoggingConfig {
+            format: self.log_format.unwrap_or(config.format),
+            level: self.log_level.clone().or_else(|| config.level.clone()),
+            ..config.clone()
+        }
+    }
+
+    /// The configuration file's contents, or the defaults if there is none.
+    pub fn read_config(&self) -> Result<GovernanceConfig, GovernanceError> {
+        let path = self.config_file();
+        if self.config.is_some() && !path.exists() {
+            return Err(GovernanceError::ConfigError(format!(
+                "configuration file {} does not exist",
+                path.display()
+            )));
+        }
+        GovernanceConfig::from_file(&path)
+    }
+}
+
+/// Run an offline subcommand, returning what to print. `None` for `run`, `self-test` and
+/// `replay`, which main handles.
+pub async fn execute(args: &Args) -> Option<Result<String, GovernanceError>> {
+    let result = match args.command.as_ref()? {
+        Command::Run | Command::SelfTest { .. } | Command::Replay(_) => return None,
+        Command::CheckConfig => check_config(&args.config_file()),
+        Command::InitConfig { out, force } => init_config(out.as_deref(), *force),
+        Command::TestWebhook { event_type } => match args.read_config() {
+            Ok(config) => test_webhook(&config, event_type).await,
+            Err(e) => Err(e),
+        },
+        Command::ExportRegistry { out, format } => args
+            .read_config()
+            .and_then(|config| export_registry(&args.data_dir, &config, out, *format)),
+        Command::ExportSummaries { out, format } => export_summaries(&args.data_dir, out, *format),
+        Command::ExportHistory {
+            out,
+            from_height,
+            to_height,
+            format,
+        } => {
+            let range = HistoryRange {
+                from_height: *from_height,
+                to_height: *to_height,
+            };
+            export_history(&args.data_dir, range, out, *format)
+        }
+        Command::ExportDelegations { out, format } => {
+            export_delegations(&args.data_dir, out, *format)
+        }
+        Command::ExportConflicts { out, format } => export_conflicts(&args.data_dir, out, *format),
+        Command::ExportVetoEvidence {
+            proposal,
+            signing_key,
+            out,
+        } => args.read_config().and_then(|config| {
+            export_veto_evidence(&args.data_dir, &config, proposal, signing_key, out)
+        }),
+        Command::VerifyVetoEvidence { file } => verify_veto_evidence(file),
+        Command::ShowNode {
+            id,
+            include_archived,
+        } => args
+            .read_config()
+            .and_then(|config| show_node(&args.data_dir, &config, id, *include_archived)),
+        Command::VerifyAudit => verify_audit(&args.data_dir),
+        Command::RecomputeParticipation => recompute_participation(&args.data_dir),
+        Command::Status { json } => status(&args.data_dir, *json).await,
+        Command::Pause { reason } => set_paused(&args.data_dir, true, reason.clone()).await,
+        Command::Resume { reason } => set_paused(&args.data_dir, false, reason.clone()).await,
+        Command::DumpSchema { out } => dump_schema(out.as_deref()),
+        Command::Version { json: false } => Ok(BuildInfo::current().to_string()),
+        Command::Version { json: true } => serde_json::to_string_pretty(BuildInfo::current())
+            .map_err(GovernanceError::serialization("version")),
+    };
+    Some(result)
+}
+
+/// `check-config`: read and validate the configuration file at `path`.
+pub fn check_config(path: &Path) -> Result<String, GovernanceError> {
+    if !path.exists() {
+        return Err(GovernanceError::ConfigError(format!(
+            "configuration file {} does not exist",
+            path.display()
+        )));
+    }
+    let config = GovernanceConfig::from_file(path)?;
+    crate::config_check::ensure_valid(path, &config)?;
+    Ok(format!("{}: configuration is valid", path.display()))
+}
+
+/// `init-config`: the starter configuration, or write it to `out` and say so. An existing
+/// `out` is only replaced with `force`.
+pub fn init_config(out: Option<&Path>, force: bool) -> Result<String, GovernanceError> {
+    let text = crate::config_template::render();
+    let Some(out) = out else {
+        return Ok(text);
+    };
+    if out.exists() && !force {
+        return Err(GovernanceError::ConfigError(format!(
+            "{} already exists; use --force to replace it",
+            out.display()
+        )));
+    }
+    std::fs::write(out, text).map_err(GovernanceError::io(out.display()))?;
+    Ok(format!("Wrote {}", out.display()))
+}
+
+/// `dump-schema`: the schema documents of the outbound payloads as one JSON object, by name,
+/// or write each to `<name>.schema.json` in `out` and say so.
+pub fn dump_schema(out: Option<&Path>) -> Result<String, GovernanceError> {
+    let documents = crate::schema::documents();
+    let Some(out) = out else {
+        return serde_json::to_string_pretty(&documents)
+            .map_err(GovernanceError::serialization("dump-schema"));
+    };
+    std::fs::create_dir_all(out).map_err(GovernanceError::io(out.display()))?;
+    for (name, document) in &documents {
+        let path = out.join(format!("{}.schema.json", name));
+        let text = serde_json::to_string_pretty(document)
+            .map_err(GovernanceError::serialization("dump-schema"))?;
+        std::fs::write(&path, text + "\n").map_err(GovernanceError::io(path.display()))?;
+    }
+    Ok(format!(
+        "Wrote {} schemas to {}",
+        documents.len(),
+        out.display()
+    ))
+}
+
+/// `test-webhook`: post a synthetic `event_type` payload to the configured webhook. A
+/// response other than 2xx is an error.
+pub async fn test_webhook(
+    config: &GovernanceConfig,
+    event_type: &str,
+) -> Result<String, GovernanceError> {
+    let client = GovernanceWebhookClient::new(config).await?;
+    let (url, status) = client.send_test(event_type).await?;
+    if !status.is_success() {
+        return Err(GovernanceError::webhook_status(&url, status));
+    }
+    Ok(format!("{} {}: {}", event_type, url, status))
+}
+
+/// Open the module store in `data_dir`, which must exist, checking and migrating its layout
+/// (see [`DataDir`](crate::storage::DataDir)).
+pub fn open_store(
+    data_dir: &Path,
+) -> Result<Arc<dyn blvm_node::storage::database::Database>, GovernanceError> {
+    if !data_dir.is_dir() {
+        return Err(GovernanceError::Storage(format!(
+            "data directory {} does not exist",
+            data_dir.display()
+        )));
+    }
+    let layout = crate::storage::DataDir::open(data_dir)?;
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        layout.store(),
+        blvm_sdk::migrations!(
+            1 => crate::storage::up_v1,
+            2 => crate::storage::up_v2,
+            3 => crate::storage::up_v3
+        ),
+    )
+    .map_err(GovernanceError::database(data_dir.display()))?;
+    Ok(db.as_db())
+}
+
+/// `export-registry`: write the registry stored in `data_dir` to `out`.
+pub fn export_registry(
+    data_dir: &Path,
+    config: &GovernanceConfig,
+    out: &Path,
+    format: ExportFormat,
+) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let tiers: HashMap<String, String> = ProposalStore::new(Arc::clone(&db))
+        .load_proposals()?
+        .into_iter()
+        .map(|p| (p.proposal_id, p.tier))
+        .collect();
+    let export = EconomicNodeRegistry::export_store(&db, &config.registry, &tiers)?;
+    let files = match format {
+        ExportFormat::Json => vec![(
+            out.to_path_buf(),
+            export
+                .to_json()
+                .map_err(GovernanceError::serialization("export-registry"))?,
+        )],
+        ExportFormat::Csv => {
+            let mut tallies = out.as_os_str().to_owned();
+            tallies.push(".tallies.csv");
+            vec![
+                (out.to_path_buf(), export.nodes_csv()),
+                (PathBuf::from(tallies), export.tallies_csv()),
+            ]
+        }
+    };
+    let mut output = format!(
+        "Exported {} nodes and {} tallies at height {}:\n",
+        export.nodes.len(),
+        export.tallies.len(),
+        export.height
+    );
+    for (path, contents) in files {
+        std::fs::write(&path, contents).map_err(GovernanceError::io(path.display()))?;
+        output.push_str(&format!("  {}\n", path.display()));
+    }
+    Ok(output)
+}
+
+/// `export-summaries`: write the epoch summaries stored in `data_dir` to `out`.
+pub fn export_summaries(
+    data_dir: &Path,
+    out: &Path,
+    format: ExportFormat,
+) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let summaries = crate::epoch_summary::EpochSummarizer::load_from(&db)?;
+    let contents = match format {
+        ExportFormat::Json => {
+            let summaries: Vec<_> = summaries.values().collect();
+            serde_json::to_string_pretty(&summaries)
+                .map_err(GovernanceError::serialization("export-summaries"))?
+        }
+        ExportFormat::Csv => crate::epoch_summary::summaries_csv(summaries.values()),
+    };
+    std::fs::write(out, contents).map_err(GovernanceError::io(out.display()))?;
+    Ok(format!(
+        "Exported {} epoch summaries to {}",
+        summaries.len(),
+        out.display()
+    ))
+}
+
+/// `export-history`: write the history stored in `data_dir`, and the votes in its audit log,
+/// to the directory `out`.
+pub fn export_history(
+    data_dir: &Path,
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let audit = crate::storage::DataDir::open(data_dir)?.audit();
+    let manifest = crate::history_export::export_history(&db, Some(&audit), range, out, format)?;
+    let mut output = format!("Exported history to {}:\n", out.display());
+    for table in &manifest.tables {
+        output.push_str(&format!("  {}: {} rows\n", table.file, table.rows));
+    }
+    Ok(output)
+}
+
+/// `export-delegations`: write the delegation records stored in `data_dir` to `out`.
+pub fn export_delegations(
+    data_dir: &Path,
+    out: &Path,
+    format: ExportFormat,
+) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let map = crate::delegation::DelegationRegistry::load_from(&db)?;
+    let records: Vec<_> = map.records().collect();
+    let contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&records)
+            .map_err(GovernanceError::serialization("export-delegations"))?,
+        ExportFormat::Csv => crate::delegation::records_csv(records.iter().copied()),
+    };
+    std::fs::write(out, contents).map_err(GovernanceError::io(out.display()))?;
+    Ok(format!(
+        "Exported {} delegation records to {}",
+        records.len(),
+        out.display()
+    ))
+}
+
+/// `export-conflicts`: write the conflict graph stored in `data_dir` to `out`.
+pub fn export_conflicts(
+    data_dir: &Path,
+    out: &Path,
+    format: ExportFormat,
+) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let graph = ProposalStore::conflicts_from(&db)?;
+    let contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&graph)
+            .map_err(GovernanceError::serialization("export-conflicts"))?,
+        ExportFormat::Csv => crate::conflicts::conflicts_csv(graph.conflicts.iter()),
+    };
+    std::fs::write(out, contents).map_err(GovernanceError::io(out.display()))?;
+    Ok(format!(
+        "Exported {} conflicts to {}",
+        graph.conflicts.len(),
+        out.display()
+    ))
+}
+
+/// `export-veto-evidence`: write the evidence for the veto tally on `proposal_id` stored in
+/// `data_dir` to `out`, signed with the key in `signing_key`.
+pub fn export_veto_evidence(
+    data_dir: &Path,
+    config: &GovernanceConfig,
+    proposal_id: &str,
+    signing_key: &Path,
+    out: &Path,
+) -> Result<String, GovernanceError> {
+    let key =
+        std::fs::read_to_string(signing_key).map_err(GovernanceError::io(signing_key.display()))?;
+    let key = crate::veto_evidence::parse_signing_key(&key)?;
+    let db = open_store(data_dir)?;
+    let proposals = ProposalStore::new(Arc::clone(&db));
+    let proposal = proposals
+        .proposal(proposal_id)?
+        .ok_or_else(|| GovernanceError::NotFound {
+            operation: "export-veto-evidence".to_string(),
+            what: format!("proposal {}", proposal_id),
+            source: None,
+        })?;
+    let content = proposals.content_check(proposal_id)?;
+    let registry =
+        EconomicNodeRegistry::veto_evidence_from_store(&db, &config.registry, proposal_id)?;
+    let bundle = crate::veto_evidence::assemble(proposal, content, registry).sign(&key)?;
+    std::fs::write(out, bundle.to_json()?).map_err(GovernanceError::io(out.display()))?;
+    let evidence = &bundle.evidence;
+    Ok(format!(
+        "Exported {} vetoes on {} ({:.2}%, threshold {}) to {}\nsha256 {}",
+        evidence.registry.vetoes.len(),
+        proposal_id,
+        evidence.veto_percent,
+        if evidence.crossed {
+            "crossed"
+        } else {
+            "not crossed"
+        },
+        out.display(),
+        bundle.hash()?
+    ))
+}
+
+/// `verify-veto-evidence`: check the bundle in `file`. A failed check is an error.
+pub fn verify_veto_evidence(file: &Path) -> Result<String, GovernanceError> {
+    let text = std::fs::read_to_string(file).map_err(GovernanceError::io(file.display()))?;
+    let bundle: crate::veto_evidence::EvidenceBundle = serde_json::from_str(&text)
+        .map_err(GovernanceError::serialization("verify-veto-evidence"))?;
+    let report = crate::veto_evidence::verify(&bundle)?;
+    if !report.is_valid() {
+        return Err(GovernanceError::ValidationError {
+            field: "veto_evidence".to_string(),
+            reason: format!("{}:\n{}", file.display(), report.to_string().trim_end()),
+        });
+    }
+    Ok(report.to_string().trim_end().to_string())
+}
+
+/// `show-node`: describe node `id` from the registry stored in `data_dir`.
+pub fn show_node(
+    data_dir: &Path,
+    config: &GovernanceConfig,
+    id: &str,
+    include_archived: bool,
+) -> Result<String, GovernanceError> {
+    let node_id = parse_node_id(id).ok_or_else(|| GovernanceError::ValidationError {
+        field: "node_id".to_string(),
+        reason: format!("expected 64 hex characters, got {:?}", id),
+    })?;
+    let db = open_store(data_dir)?;
+    match EconomicNodeRegistry::node_from_store(
+        &db,
+        &config.registry,
+        &node_id,
+        include_archived,
+        None,
+    )? {
+        Some(details) => Ok(format_node(id, &details)),
+        None => Err(GovernanceError::NotFound {
+            operation: "show-node".to_string(),
+            what: format!("economic node {}", id),
+            source: None,
+        }),
+    }
+}
+
+/// `verify-audit`: replay the audit log in `data_dir`, which must exist.
+pub fn verify_audit(data_dir: &Path) -> Result<String, GovernanceError> {
+    if !data_dir.is_dir() {
+        return Err(GovernanceError::Storage(format!(
+            "data directory {} does not exist",
+            data_dir.display()
+        )));
+    }
+    let dir = crate::storage::DataDir::open(data_dir)?.audit();
+    let verified = crate::audit_log::verify(&dir)?;
+    Ok(match verified.head {
+        Some(head) => format!(
+            "Audit log {}: {} entries in {} files, chain intact; last entry {} {}",
+            dir.display(),
+            verified.entries,
+            verified.files,
+            head.seq,
+            head.hash
+        ),
+        None => format!("Audit log {}: empty", dir.display()),
+    })
+}
+
+/// `recompute-participation`: rebuild the participation statistics stored in `data_dir`
+/// from the history stored with them.
+pub fn recompute_participation(data_dir: &Path) -> Result<String, GovernanceError> {
+    let db = open_store(data_dir)?;
+    let history = crate::participation::History::load_from(&db)?;
+    let participation = crate::participation::analyze(&history);
+    crate::participation::save_to(&db, &participation)?;
+    Ok(format!(
+        "Recomputed participation over {} closed proposals: {} voters, rate {}",
+        participation.counted.len(),
+        participation.voters.len(),
+        participation.rate().map_or_else(
+            || "none".to_string(),
+            |rate| format!("{:.2}%", rate * 100.0)
+        )
+    ))
+}
+
+/// `status`: ask the module running on `data_dir` for its status.
+pub async fn status(data_dir: &Path, json: bool) -> Result<String, GovernanceError> {
+    let status = crate::admin::status(&data_dir.join(crate::admin::ADMIN_SOCKET)).await?;
+    if json {
+        serde_json::to_string_pretty(&status).map_err(GovernanceError::serialization("status"))
+    } else {
+        Ok(status.to_string().trim_end().to_string())
+    }
+}
+
+/// `pause` and `resume`: pause or resume the module running on `data_dir`, as the current
+/// user.
+pub async fn set_paused(
+    data_dir: &Path,
+    pause: bool,
+    reason: Option<String>,
+) -> Result<String, GovernanceError> {
+    let trigger = Trigger {
+        actor: std::env::var("USER").ok(),
+        reason,
+        ..Trigger::new("cli")
+    };
+    let socket = data_dir.join(crate::admin::ADMIN_SOCKET);
+    let status = crate::admin::set_paused(&socket, pause, &trigger).await?;
+    Ok(match (status.paused, status.draining) {
+        (true, _) => "Paused: outbound effects are held until resumed".to_string(),
+        (false, true) => "Resumed: sending what was held while paused".to_string(),
+        (false, false) => "Running".to_string(),
+    })
+}
+
+/// Human-readable record of node `id`, as printed by `show-node`.
+pub fn format_node(id: &str, details: &EconomicNodeDetails) -> String {
+    let n = &details.node;
+    let mut out = format!(
+        "Economic node {}{}
+               type: {}
+               registered at: {} | last seen: {} | last announced: {}
+               hashpower: {}% | verified weight: {} sats | verification: {:?}
+               weight: {} raw, {} effective
+               reputation: {:.3} (longevity {:.3}, liveness {:.3}, stability {:.3}, veto record {:.3})
+               key set: {}
+               vetoes ({}):
+",
+        id,
+        if details.archived { " (archived)" } else { "" },
+        if n.node_type.is_empty() {
+            "unknown"
+        } else {
+            n.node_type.as_str()
+        },
+        n.registered_at,
+        n.last_seen,
+        n.last_announced,
+        n.hashpower_percentage,
+        n.verified_weight,
+        n.verification,
+        details.raw_weight,
+        details.effective_weight,
+        details.reputation.score,
+        details.reputation.longevity,
+        details.reputation.liveness,
+        details.reputation.stability,
+        details.reputation.veto_record,
+        n.keys
+            .as_ref()
+            .map(|k| format!("{} of {}", k.threshold, k.public_keys.len()))
+            .unwrap_or_else(|| "none".into()),
+        n.veto_history.len(),
+    );
+    for v in &n.veto_history {
+        out.push_str(&format!(
+            "    {} at {} ({:?}): {}\n",
+            v.proposal_id, v.height, v.outcome, v.reason
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subcommands() {
+        let args = Args::try_parse_from(["blvm-governance"]).unwrap();
+        assert_eq!(args.command, None);
+
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-registry",
+            "--out",
+            "registry.csv",
+            "--format",
+            "csv",
+            "--data-dir",
+            "/var/lib/governance",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportRegistry {
+                out: PathBuf::from("registry.csv"),
+                format: ExportFormat::Csv,
+            })
+        );
+        assert_eq!(
+            args.config_file(),
+            PathBuf::from("/var/lib/governance/config.toml")
+        );
+
+        let args = Args::try_parse_from(["blvm-governance", "test-webhook"]).unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::TestWebhook {
+                event_type: "proposal_created".to_string()
+            })
+        );
+        assert!(Args::try_parse_from(["blvm-governance", "export-registry"]).is_err());
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-history",
+            "--out",
+            "history",
+            "--from-height",
+            "800000",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportHistory {
+                out: PathBuf::from("history"),
+                from_height: Some(800_000),
+                to_height: None,
+                format: HistoryFormat::Csv,
+            })
+        );
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-summaries",
+            "--out",
+            "epochs.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportSummaries {
+                out: PathBuf::from("epochs.json"),
+                format: ExportFormat::Json,
+            })
+        );
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-delegations",
+            "--out",
+            "delegations.csv",
+            "--format",
+            "csv",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportDelegations {
+                out: PathBuf::from("delegations.csv"),
+                format: ExportFormat::Csv,
+            })
+        );
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-conflicts",
+            "--out",
+            "conflicts.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportConflicts {
+                out: PathBuf::from("conflicts.json"),
+                format: ExportFormat::Json,
+            })
+        );
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "export-veto-evidence",
+            "--proposal",
+            "p1",
+            "--signing-key",
+            "evidence.key",
+            "--out",
+            "p1.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::ExportVetoEvidence {
+                proposal: "p1".to_string(),
+                signing_key: PathBuf::from("evidence.key"),
+                out: PathBuf::from("p1.json"),
+            })
+        );
+        assert!(Args::try_parse_from(["blvm-governance", "verify-veto-evidence"]).is_err());
+
+        let args = Args::try_parse_from(["blvm-governance", "verify-audit"]).unwrap();
+        assert_eq!(args.command, Some(Command::VerifyAudit));
+        let args = Args::try_parse_from(["blvm-governance", "recompute-participation"]).unwrap();
+        assert_eq!(args.command, Some(Command::RecomputeParticipation));
+        let args = Args::try_parse_from(["blvm-governance", "status", "--json"]).unwrap();
+        assert_eq!(args.command, Some(Command::Status { json: true }));
+        let args =
+            Args::try_parse_from(["blvm-governance", "pause", "--reason", "incident"]).unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::Pause {
+                reason: Some("incident".to_string())
+            })
+        );
+
+        let args = Args::try_parse_from([
+            "blvm-governance",
+            "replay",
+            "--handlers",
+            "webhook,registry",
+            "--from-height",
+            "100",
+            "--offline",
+        ])
+        .unwrap();
+        let Some(Command::Replay(replay)) = args.command else {
+            panic!("expected replay, got {:?}", args.command);
+        };
+        assert_eq!(
+            replay.handlers,
+            vec![ReplayHandler::Webhook, ReplayHandler::Registry]
+        );
+        assert_eq!(
+            replay.range(),
+            ReplayRange {
+                from_height: Some(100),
+                ..Default::default()
+            }
+        );
+        assert!(replay.offline && replay.file.is_none());
+        assert!(Args::try_parse_from(["blvm-governance", "replay"]).is_err());
+        assert!(
+            Args::try_parse_from(["blvm-governance", "replay", "--handlers", "clock"]).is_err()
+        );
+
+        let args =
+            Args::try_parse_from(["blvm-governance", "init-config", "--out", "c.toml"]).unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::InitConfig {
+                out: Some(PathBuf::from("c.toml")),
+                force: false,
+            })
+        );
+
+        let args = Args::try_parse_from(["blvm-governance", "self-test", "--skip-node"]).unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::SelfTest {
+                skip_node: true,
+                skip_webhook: false,
+            })
+        );
+
+        let args = Args::try_parse_from(["blvm-governance", "dump-schema"]).unwrap();
+        assert_eq!(args.command, Some(Command::DumpSchema { out: None }));
+
+        let config = LoggingConfig {
+            format: LogFormat::Pretty,
+            level: Some("debug".to_string()),
+            ..Default::default()
+        };
+        let args =
+            Args::try_parse_from(["blvm-governance", "--log-format", "json", "check-config"])
+                .unwrap();
+        let logging = args.logging(&config);
+        assert_eq!(logging.format, LogFormat::Json);
+        assert_eq!(logging.level.as_deref(), Some("debug"));
+        let args = Args::try_parse_from(["blvm-governance", "--log-level", "warn"]).unwrap();
+        assert_eq!(args.logging(&config).level.as_deref(), Some("warn"));
+        assert!(Args::try_parse_from(["blvm-governance", "--log-level", "loud"]).is_err());
+
+        let dry_run = |args: &[&str]| {
+            Args::try_parse_from(std::iter::once("blvm-governance").chain(args.iter().copied()))
+                .map(|a| a.dry_run)
+        };
+        assert_eq!(dry_run(&[]).unwrap(), None);
+        assert_eq!(dry_run(&["--dry-run"]).unwrap(), Some(DryRun::Webhook));
+        assert_eq!(
+            dry_run(&["run", "--dry-run=all"]).unwrap(),
+            Some(DryRun::All)
+        );
+        // Not taken as the scope
+        assert_eq!(
+            dry_run(&["--dry-run", "run"]).unwrap(),
+            Some(DryRun::Webhook)
+        );
+        assert!(dry_run(&["--dry-run=everything"]).is_err());
+
+        assert!(
+            Args::try_parse_from(["blvm-governance", "run", "--force-network-migration"])
+                .unwrap()
+                .force_network_migration
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Local clock check against block timestamps
+//!
+//! Webhook payload timestamps assume the local clock is roughly right; a host whose clock is
+//! far off sends payloads receivers reject or misorder. The [`ClockMonitor`] compares it with
+//! the chain: for each `NewBlock` at the chain tip it fetches the header and records how far
+//! the local time at arrival is ahead of the block's timestamp. The median over the last
+//! `[governance.clock] samples` blocks is the estimated skew.
+//!
+//! Block timestamps are only loosely tied to real time: consensus accepts one up to two
+//! hours ahead of the network's time, and miners' clocks lag. A skew is therefore only
+//! reported once it exceeds that slack ([`BLOCK_TIME_SLACK_SECS`]) by more than
+//! `max_skew_secs`, and not before a full window of samples has been seen. Blocks below the
+//! tip (replayed after a restart or backfilled) are not sampled. Nor are historical blocks
+//! streaming past during initial sync, which would look like a fast clock: a skew is only
+//! reported once the node says it is synced (asked at most once a minute), and the samples
+//! are dropped while it is not.
+//!
+//! A skew is logged as a warning when first detected, shown in the status report and in
+//! `/readyz` (see [`crate::health`]). With `use_block_time`, webhook payloads are stamped
+//! with the time estimated from block timestamps while it lasts.
+
+use crate::config::ClockConfig;
+use crate::error::GovernanceError;
+use crate::ipc_metrics::IpcMethod;
+use crate::node_api::{default_timeout, with_timeout, TipTracker};
+use blvm_node::module::traits::NodeAPI;
+use blvm_protocol::Hash;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// How far a block timestamp may legitimately be from real time, in seconds.
+pub const BLOCK_TIME_SLACK_SECS: u64 = 2 * 60 * 60;
+
+/// How long the node's answer that it is synced is relied on.
+const SYNC_RECHECK: Duration = Duration::from_secs(60);
+
+/// The clock check, as reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockStatus {
+    /// Seconds the local clock is ahead of block time (negative: behind), the median over
+    /// the sampled blocks; `None` until a full window has been seen.
+    pub skew_secs: Option<i64>,
+    pub samples: usize,
+    /// Whether the skew is beyond the block timestamp slack and `max_skew_secs`.
+    pub skewed: bool,
+    /// Whether the node said it was still syncing when last asked.
+    pub syncing: bool,
+    /// Whether webhook payloads are stamped with block-derived time.
+    pub using_block_time: bool,
+}
+
+#[derive(Default)]
+struct ClockState {
+    /// Local time at arrival minus block time, in seconds, by height of the latest sampled
+    /// blocks.
+    lags: BTreeMap<u64, i64>,
+    skewed: bool,
+    syncing: bool,
+    /// When the node last said it was synced.
+    synced_at: Option<Instant>,
+}
+
+impl ClockState {
+    /// Median lag, once `window` blocks are sampled.
+    fn skew(&self, window: usize) -> Option<i64> {
+        if self.lags.is_empty() || self.lags.len() < window {
+            return None;
+        }
+        let mut lags: Vec<i64> = self.lags.values().copied().collect();
+        lags.sort_unstable();
+        Some(lags[lags.len() / 2])
+    }
+}
+
+/// Estimates the skew of the local clock from the blocks of the current connection. Kept
+/// across connections, like the skew it measures.
+pub struct ClockMonitor {
+    config: ClockConfig,
+    /// Chain tip of the current connection.
+    tip: Mutex<Option<Arc<TipTracker>>>,
+    state: Mutex<ClockState>,
+}
+
+impl Default for ClockMonitor {
+    fn default() -> Self {
+        Self::new(ClockConfig::default())
+    }
+}
+
+impl ClockMonitor {
+    pub fn new(config: ClockConfig) -> Self {
+        Self {
+            config,
+            tip: Mutex::new(None),
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Sample only blocks at `tip` from now on.
+    pub fn connected(&self, tip: Arc<TipTracker>) {
+        *self.tip.lock().unwrap() = Some(tip);
+    }
+
+    /// The connection to the node has dropped.
+    pub fn disconnected(&self) {
+        *self.tip.lock().unwrap() = None;
+    }
+
+    /// Record that the block at `height`, with timestamp `block_time`, arrived at local time
+    /// `now` (both Unix seconds). Returns the estimated skew, once a full window is sampled.
+    pub fn sample(&self, height: u64, block_time: u64, now: u64) -> Option<i64> {
+        let window = self.config.samples.max(1);
+        let mut state = self.state.lock().unwrap();
+        state.lags.insert(height, now as i64 - block_time as i64);
+        while state.lags.len() > window {
+            state.lags.pop_first();
+        }
+        state.skew(window)
+    }
+
+    /// Whether `skew` is beyond what block timestamps explain.
+    pub fn exceeds(&self, skew: i64) -> bool {
+        skew.unsigned_abs() > BLOCK_TIME_SLACK_SECS + self.config.max_skew_secs
+    }
+
+    /// Sample the block `hash` at `height` from a `NewBlock` event, and update the skew.
+    pub async fn check_block(
+        &self,
+        hash: &Hash,
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        // Only blocks at the tip arrive in real time
+        let tip = self.tip.lock().unwrap().as_ref().and_then(|t| t.current());
+        if tip.is_some_and(|tip| height < tip.height) {
+            return Ok(());
+        }
+        let header = with_timeout(
+            "get_block_header",
+            default_timeout(IpcMethod::GetBlockHeader),
+            node_api.get_block_header(hash),
+        )
+        .await?;
+        let Some(header) = header else {
+            return Ok(());
+        };
+        let Some(skew) = self.sample(height, header.timestamp, unix_now()) else {
+            return Ok(());
+        };
+        if self.exceeds(skew) && !self.confirm_synced(node_api).await? {
+            return Ok(());
+        }
+        self.update(skew);
+        Ok(())
+    }
+
+    /// Whether the node says it is synced. While it is not, the samples are dropped.
+    async fn confirm_synced(&self, node_api: &dyn NodeAPI) -> Result<bool, GovernanceError> {
+        let synced_at = self.state.lock().unwrap().synced_at;
+        if synced_at.is_some_and(|at| at.elapsed() < SYNC_RECHECK) {
+            return Ok(true);
+        }
+        let info = with_timeout(
+            "get_chain_info",
+            default_timeout(IpcMethod::GetChainInfo),
+            node_api.get_chain_info(),
+        )
+        .await?;
+        let mut state = self.state.lock().unwrap();
+        if info.is_synced {
+            state.synced_at = Some(Instant::now());
+            state.syncing = false;
+        } else {
+            if !state.syncing {
+                info!("Node is syncing; clock check paused until it catches up");
+            }
+            state.syncing = true;
+            state.synced_at = None;
+            state.lags.clear();
+        }
+        Ok(info.is_synced)
+    }
+
+    /// Take `skew` as the current estimate.
+    fn update(&self, skew: i64) {
+        let skewed = self.exceeds(skew);
+        let mut state = self.state.lock().unwrap();
+        if skewed && !state.skewed {
+            let effect = if self.config.use_block_time {
+                "webhook timestamps are taken from block time until it is fixed"
+            } else {
+                "webhook timestamps will be off"
+            };
+            warn!(
+                "LOCAL CLOCK SKEWED: {}s {} the timestamps of the last {} blocks; {}. Check \
+                 the host's time synchronisation.",
+                skew.unsigned_abs(),
+                if skew > 0 { "ahead of" } else { "behind" },
+                state.lags.len(),
+                effect
+            );
+        } else if !skewed && state.skewed {
+            info!("Local clock agrees with block timestamps again ({}s)", skew);
+        }
+        state.skewed = skewed;
+        state.syncing = false;
+    }
+
+    pub fn status(&self) -> ClockStatus {
+        let state = self.state.lock().unwrap();
+        ClockStatus {
+            skew_secs: state.skew(self.config.samples.max(1)),
+            samples: state.lags.len(),
+            skewed: state.skewed,
+            syncing: state.syncing,
+            using_block_time: state.skewed && self.config.use_block_time,
+        }
+    }
+
+    /// Unix seconds to stamp payloads with: the local clock, corrected by the skew while it
+    /// is skewed and `use_block_time` is set.
+    pub fn payload_time(&self) -> u64 {
+        let now = unix_now();
+        let state = self.state.lock().unwrap();
+        match state.skew(self.config.samples.max(1)) {
+            Some(skew) if state.skewed && self.config.use_block_time => {
+                (now as i64 - skew).max(0) as u64
+            }
+            _ => now,
+        }
+    }
+}
+
+/// Local Unix time in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(samples: usize, use_block_time: bool) -> ClockMonitor {
+        ClockMonitor::new(ClockConfig {
+            samples,
+            use_block_time,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_skew_is_median_of_full_window() {
+        let clock = monitor(3, false);
+        let now = 1_700_000_000;
+        assert_eq!(clock.sample(1, now - 60, now), None);
+        assert_eq!(clock.sample(2, now + 7000, now), None);
+        // One block two hours in the future does not move the median
+        assert_eq!(clock.sample(3, now - 30, now), Some(30));
+        // The oldest sample leaves the window
+        assert_eq!(clock.sample(4, now - 90, now), Some(30));
+        assert_eq!(clock.sample(5, now - 120, now), Some(90));
+        assert_eq!(clock.status().samples, 3);
+    }
+
+    #[test]
+    fn test_slack_is_tolerated() {
+        let clock = monitor(1, true);
+        let limit = (BLOCK_TIME_SLACK_SECS + 600) as i64;
+        assert!(!clock.exceeds(limit));
+        assert!(!clock.exceeds(-limit));
+        assert!(clock.exceeds(limit + 1));
+        assert!(clock.exceeds(-limit - 1));
+
+        // Stamped from block time only while skewed
+        let now = unix_now();
+        clock.sample(1, now - 3 * 3600, now);
+        assert!(clock.payload_time().abs_diff(now) < 5);
+        clock.update(3 * 3600);
+        assert!(clock.status().using_block_time);
+        assert!(clock.payload_time().abs_diff(now - 3 * 3600) < 5);
+    }
+}
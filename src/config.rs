@@ -1,10 +1,33 @@
 //! Governance module configuration.
 //!
-//! Loaded from config.toml in module data dir. Node overrides via [modules.governance] and
-//! MODULE_CONFIG_* env vars.
+//! Read from `config.toml` in the module data dir, or from the file given by `--config <path>`
+//! or the `BLLVM_GOVERNANCE_CONFIG` environment variable (the flag wins over the variable).
+//! Settings apply in this order, later ones winning:
+//!
+//! 1. defaults;
+//! 2. the `[governance]` table of the file;
+//! 3. the module context config the node passes when it spawns the module (its
+//!    `[modules.governance]` section and `MODULE_CONFIG_*` variables), keyed like
+//!    `governance.webhook_url` or `governance.registry.veto.threshold_percent`.
+//!
+//! Keys in either that are not settings are logged with the settings they may have been meant
+//! as, and otherwise ignored.
+//!
+//! String values in the file, inside lists and tables too, may refer to environment variables
+//! as `${VAR}`, or `${VAR:-default}` for a default used when `VAR` is unset or empty; `$${` is
+//! a literal `${`. They are expanded when the file is read, before the settings are checked,
+//! and reading fails naming the variable if one without a default is unset. Values from the
+//! module context are taken as they are.
 
+use crate::error::GovernanceError;
 use blvm_sdk_macros::config;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the configuration file when `--config` is not given.
+pub const CONFIG_ENV: &str = "BLLVM_GOVERNANCE_CONFIG";
 
 /// Governance module configuration.
 ///
@@ -12,7 +35,7 @@ use serde::{Deserialize, Serialize};
 /// Node override: `[modules.governance]` or `[modules.blvm-governance]` in node config.
 /// Env override: `MODULE_CONFIG_WEBHOOK_URL`, `MODULE_CONFIG_NODE_ID`.
 #[config(name = "governance")]
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GovernanceConfig {
     /// Webhook URL for governance events (e.g. https://governance.example.com/webhook)
     #[serde(default)]
@@ -30,18 +53,1822 @@ pub struct GovernanceConfig {
     /// Retry count for failed webhook deliveries.
     #[serde(default = "default_webhook_retry_count")]
     pub webhook_retry_count: u32,
+    /// Event types delivered to the webhook (e.g. "proposal_created", "registry_expired");
+    /// empty delivers all.
+    #[serde(default)]
+    pub webhook_events: Vec<String>,
+    /// Network the webhook takes data of (e.g. "mainnet"); while the node is on another, its
+    /// deliveries are blocked and reported (see `blvm_governance::network`). Unset takes any.
+    #[serde(default)]
+    pub webhook_network: Option<String>,
+    /// Format of webhook deliveries: "json", "cbor" or "msgpack" (see
+    /// `blvm_governance::payload_format`). A webhook answering 415 to a binary one gets JSON
+    /// from then on.
+    #[serde(default)]
+    pub webhook_content_type: WebhookContentType,
+    /// Seconds between checks of `config.toml` for changes to apply while running (0
+    /// disables; see `blvm_governance::config_reload`).
+    #[serde(default = "default_config_reload_secs")]
+    pub config_reload_secs: u64,
     /// Governance tier: "maintainer" | "contributor".
     #[serde(default)]
     pub governance_tier: Option<String>,
+    /// Allow the module to submit vetoes and other governance actions to the node. Every
+    /// submission is recorded in the audit trail (see `blvm_governance::audit`).
+    #[serde(default)]
+    pub allow_actions: bool,
+    /// Build webhook payloads as usual but log them instead of sending them: `true` for
+    /// webhooks only, `"all"` to also log governance actions and veto results instead of
+    /// submitting them to the node. `--dry-run` overrides it; read at startup.
+    #[serde(default)]
+    pub dry_run: DryRun,
+    /// In dry-run mode, also write each payload to a file under `<data dir>/dry-run/`.
+    #[serde(default)]
+    pub dry_run_files: bool,
+
+    /// Economic node registry settings (`[governance.registry]`).
+    #[serde(default)]
+    pub registry: RegistryConfig,
+    /// Scheduled backups (`[governance.backup]`).
+    #[serde(default)]
+    pub backup: BackupConfig,
+    /// Reconnection to the node after the connection drops (`[governance.reconnect]`).
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    /// Dead-connection detection on the IPC socket (`[governance.heartbeat]`).
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// Graceful shutdown (`[governance.shutdown]`).
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    /// Queue between event dispatch and processing (`[governance.events]`).
+    #[serde(default)]
+    pub events: EventQueueConfig,
+    /// Timeouts of requests to the node by method, overriding the defaults, e.g.
+    /// `get_block = "5s"` under `[governance.nodeapi_timeouts]` (`ms`, `s` or `m`).
+    #[serde(default)]
+    pub nodeapi_timeouts: std::collections::BTreeMap<String, String>,
+    /// Requests to the node (`[governance.ipc]`).
+    #[serde(default)]
+    pub ipc: IpcConfig,
+    /// Local log output (`[governance.logging]`).
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Forwarding of log records to the node (`[governance.log_forward]`).
+    #[serde(default)]
+    pub log_forward: LogForwardConfig,
+    /// Local HTTP health and readiness endpoints (`[governance.health]`).
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// What a panic does (`[governance.crash]`).
+    #[serde(default)]
+    pub crash: CrashConfig,
+    /// Memory held by queues and caches (`[governance.memory]`).
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    /// Local clock check against block timestamps (`[governance.clock]`).
+    #[serde(default)]
+    pub clock: ClockConfig,
+    /// Error reports sent to the node (`[governance.error_reports]`).
+    #[serde(default)]
+    pub error_reports: ErrorReportConfig,
+    /// Error rate alerts sent to a dedicated URL (`[governance.alerts]`).
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    /// Tamper-evident log of events and actions (`[governance.audit_log]`).
+    #[serde(default)]
+    pub audit_log: AuditLogConfig,
+    /// Vote tally thresholds by proposal tier (`[governance.tally]`).
+    #[serde(default)]
+    pub tally: TallyConfig,
+    /// Voting windows and deadline reminders (`[governance.deadlines]`).
+    #[serde(default)]
+    pub deadlines: DeadlineConfig,
+    /// Activation delays of merged proposals (`[governance.activation]`).
+    #[serde(default)]
+    pub activation: ActivationConfig,
+    /// Miner signaling tracked from block headers (`[governance.signaling]`).
+    #[serde(default)]
+    pub signaling: SignalingConfig,
+    /// Governance epoch summaries (`[governance.epoch_summary]`).
+    #[serde(default)]
+    pub epoch_summary: EpochSummaryConfig,
+    /// Adoption of activated proposals (`[governance.adoption]`).
+    #[serde(default)]
+    pub adoption: AdoptionConfig,
+    /// Conflicts between proposals (`[governance.conflicts]`).
+    #[serde(default)]
+    pub conflicts: ConflictConfig,
+    /// Amendments of proposals' content and tier (`[governance.amendments]`).
+    #[serde(default)]
+    pub amendments: AmendmentConfig,
+    /// Pull request events received from GitHub (`[governance.github]`).
+    #[serde(default)]
+    pub github: GithubConfig,
+    /// Proposal content checked against its on-chain commitment (`[governance.content]`).
+    #[serde(default)]
+    pub content: ContentConfig,
+    /// Vote delegation (`[governance.delegation]`).
+    #[serde(default)]
+    pub delegation: DelegationConfig,
+    /// Governance state commitments anchored on-chain (`[governance.anchor]`).
+    #[serde(default)]
+    pub anchor: AnchorConfig,
+    /// Periodic digests in place of individual webhook notifications
+    /// (`[governance.digest]`).
+    #[serde(default)]
+    pub digest: DigestConfig,
+    /// Per-voter participation statistics (`[governance.participation]`).
+    #[serde(default)]
+    pub participation: ParticipationConfig,
+    /// Alerts as veto tallies approach the threshold (`[governance.veto_escalation]`).
+    #[serde(default)]
+    pub veto_escalation: VetoEscalationConfig,
+}
+
+/// Reconnection backoff configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnection attempt, in milliseconds.
+    pub initial_backoff_ms: u64,
+    /// Upper bound on the delay between attempts, in seconds. A connection that stays up
+    /// this long resets the backoff.
+    pub max_backoff_secs: u64,
+    /// Exit after this many seconds without a connection (0 retries forever).
+    pub give_up_after_secs: u64,
+    /// Exit if the first connection is not made within this many seconds of starting
+    /// (0 retries forever).
+    pub connect_timeout_secs: u64,
+    /// Wait for the node's socket file to appear before the first connection attempt.
+    pub wait_for_socket: bool,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_secs: 60,
+            give_up_after_secs: 0,
+            connect_timeout_secs: 300,
+            wait_for_socket: false,
+        }
+    }
+}
+
+/// Local log output configuration; `--log-format` and `--log-level` override it. Read once
+/// at startup, before anything is logged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// How records are written: "full", "compact", "pretty" or "json".
+    pub format: LogFormat,
+    /// Level of every record logged ("error", "warn", "info", "debug", "trace" or "off"),
+    /// overriding `RUST_LOG`; unset uses `RUST_LOG`, else "info".
+    pub level: Option<String>,
+    /// Levels of the records of some targets (module paths) and the modules below them,
+    /// overriding `level` for those, e.g. `"blvm_governance::webhook" = "debug"` under
+    /// `[governance.logging.targets]`. Applied again when the configuration is reloaded.
+    pub targets: std::collections::BTreeMap<String, String>,
+    /// Directory to also write logs to, as `blvm-governance.log` rotated by size; unset
+    /// only writes to the console. Created if missing; startup fails if it is not writable.
+    pub dir: Option<PathBuf>,
+    /// Size in bytes at which the log file is rotated.
+    pub max_file_bytes: u64,
+    /// Rotated log files kept besides the current one; older ones are deleted.
+    pub max_files: usize,
+    /// Whether to keep writing to the console while writing to `dir`.
+    pub console: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: None,
+            targets: std::collections::BTreeMap::new(),
+            dir: None,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            console: true,
+        }
+    }
+}
+
+/// How log records are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One line per record, with its spans and their fields.
+    #[default]
+    Full,
+    /// Like `full`, with span names left out.
+    Compact,
+    /// Several indented lines per record, for reading at a terminal.
+    Pretty,
+    /// One JSON object per line: `timestamp`, `level`, `target`, `message`, then the fields of
+    /// the record and of every span it is in (`trace_id`, `proposal_id`, ...) as top-level
+    /// keys.
+    Json,
+}
+
+/// Format of webhook payloads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookContentType {
+    /// JSON, `application/json`.
+    #[default]
+    Json,
+    /// CBOR, `application/cbor`.
+    Cbor,
+    /// MessagePack, `application/msgpack`.
+    Msgpack,
+}
+
+impl WebhookContentType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Cbor => "cbor",
+            Self::Msgpack => "msgpack",
+        }
+    }
+}
+
+/// What dry-run mode keeps from reaching the outside. Written `false`, `true` or `"all"` in
+/// the configuration, and `--dry-run` or `--dry-run=all` on the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DryRun {
+    /// Everything is sent.
+    #[default]
+    Off,
+    /// Webhook deliveries are logged instead of sent.
+    Webhook,
+    /// Webhook deliveries, governance actions and veto results are logged instead of sent.
+    All,
+}
+
+impl DryRun {
+    /// Whether webhook deliveries are logged instead of sent.
+    pub fn webhook(self) -> bool {
+        self != DryRun::Off
+    }
+
+    /// Whether submissions to the node are logged instead of sent.
+    pub fn actions(self) -> bool {
+        self == DryRun::All
+    }
+}
+
+impl Serialize for DryRun {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DryRun::Off => serializer.serialize_bool(false),
+            DryRun::Webhook => serializer.serialize_bool(true),
+            DryRun::All => serializer.serialize_str("all"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DryRun {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Bool(bool),
+            Str(String),
+        }
+        match Value::deserialize(deserializer)? {
+            Value::Bool(false) => Ok(DryRun::Off),
+            Value::Bool(true) => Ok(DryRun::Webhook),
+            Value::Str(s) => match s.as_str() {
+                "false" | "off" => Ok(DryRun::Off),
+                "true" | "webhook" => Ok(DryRun::Webhook),
+                "all" => Ok(DryRun::All),
+                _ => Err(serde::de::Error::custom(format!(
+                    "dry_run must be true, false or \"all\", not {:?}",
+                    s
+                ))),
+            },
+        }
+    }
+}
+
+/// Log forwarding configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogForwardConfig {
+    /// Forward records at or above this level ("error", "warn", ...); unset disables.
+    pub level: Option<String>,
+    /// Records sent to the node per second at most.
+    pub max_per_sec: u32,
+    /// Records buffered while sending is behind or the node is disconnected.
+    pub buffer: usize,
+}
+
+impl Default for LogForwardConfig {
+    fn default() -> Self {
+        Self {
+            level: None,
+            max_per_sec: 20,
+            buffer: 1000,
+        }
+    }
+}
+
+/// Health endpoint configuration. See `blvm_governance::health`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Address to serve `GET /healthz` and `GET /readyz` on, e.g. "127.0.0.1:9180"; unset
+    /// disables the listener.
+    pub listen: Option<String>,
+    /// Seconds the event loop may go without progress before the module is reported
+    /// unhealthy.
+    pub stall_secs: u64,
+    /// Consecutive failed webhook deliveries after which the module is reported not ready
+    /// (0 disables the check).
+    pub webhook_failure_limit: u64,
+    /// Also serve `GET /metrics` in the Prometheus text format.
+    pub metrics: bool,
+    /// Bearer token required by `POST /actions/veto` and `POST /actions/vote`, which submit
+    /// signed votes and vetoes to the node; unset disables them. Needs `allow_actions`.
+    pub actions_token: Option<String>,
+    /// Bearer token required by `POST /admin/pause` and `POST /admin/resume`, which pause and
+    /// resume the module's outbound effects; unset disables them.
+    pub admin_token: Option<String>,
+    /// Also serve the read-only `GET /proposals`, `/economic-nodes`, `/epochs/{n}/summary`,
+    /// `/status` and `/dashboard`, which need `actions_token` when it is set.
+    pub queries: bool,
+    /// Also serve the recent governance activity as `GET /feed.atom` and `GET /feed.rss`, for
+    /// feed readers; they need no token.
+    pub feed: bool,
+    /// Entries in the feeds, most recent first.
+    pub feed_entries: usize,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            stall_secs: 60,
+            webhook_failure_limit: 20,
+            metrics: false,
+            actions_token: None,
+            admin_token: None,
+            queries: false,
+            feed: false,
+            feed_entries: 50,
+        }
+    }
+}
+
+/// Panic handling configuration. See `blvm_governance::crash`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashConfig {
+    /// Catch a panic in an event handler, mark the handler unhealthy and keep the others
+    /// running, instead of reporting the crash and exiting.
+    pub isolate_handler_panics: bool,
+    /// Seconds each crash notification, to the node and to the webhook, may take.
+    pub report_timeout_secs: u64,
+}
+
+impl Default for CrashConfig {
+    fn default() -> Self {
+        Self {
+            isolate_handler_panics: false,
+            report_timeout_secs: 2,
+        }
+    }
+}
+
+/// Memory budget configuration. See `blvm_governance::memory`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Bytes the event queue and the caches may hold together, divided among them in fixed
+    /// shares (0 bounds them only by their entry counts).
+    pub budget_bytes: u64,
+}
+
+/// Clock sanity check configuration. See `blvm_governance::clock`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// Compare the local clock with the timestamps of new blocks.
+    pub enabled: bool,
+    /// Blocks the skew is the median over; nothing is reported until this many are seen.
+    pub samples: usize,
+    /// Skew tolerated, in seconds, beyond the two hours a block timestamp may legitimately
+    /// be off by.
+    pub max_skew_secs: u64,
+    /// While the local clock is skewed, stamp webhook payloads with the time estimated from
+    /// block timestamps instead.
+    pub use_block_time: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            samples: 11,
+            max_skew_secs: 600,
+            use_block_time: false,
+        }
+    }
+}
+
+/// Error report configuration. See `blvm_governance::error_report`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorReportConfig {
+    /// Send reports of significant errors to the node.
+    pub enabled: bool,
+    /// Seconds between batches sent to the node.
+    pub batch_secs: u64,
+    /// Most reports in one batch; the rest wait for the next.
+    pub max_batch: usize,
+    /// Seconds before a report that keeps recurring is sent again, with its updated count.
+    pub repeat_secs: u64,
+    /// Reports kept for the status report and `/errors`, most recent first.
+    pub recent: usize,
+}
+
+impl Default for ErrorReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            batch_secs: 10,
+            max_batch: 20,
+            repeat_secs: 300,
+            recent: 20,
+        }
+    }
+}
+
+/// Error rate alert configuration. See `blvm_governance::alert`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Where `module_alert` payloads are posted; unset disables alerting. Separate from
+    /// `webhook_url`, so alerts can go to a pager rather than the data receiver.
+    pub url: Option<String>,
+    /// Seconds over which errors of each category are counted.
+    pub window_secs: u64,
+    /// Errors of one category within the window that raise an alert; 0 disables.
+    pub count: u64,
+    /// Errors per minute of one category, averaged over the window, that raise an alert;
+    /// 0 disables.
+    pub rate_per_min: f64,
+    /// Seconds after an alert before another is raised for the same category.
+    pub cooldown_secs: u64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            window_secs: 300,
+            count: 10,
+            rate_per_min: 0.0,
+            cooldown_secs: 900,
+        }
+    }
+}
+
+/// Audit log configuration. See `blvm_governance::audit_log`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    /// Record events received, webhook deliveries, registry changes and governance actions
+    /// in `audit/` in the data directory.
+    pub enabled: bool,
+    /// Size in bytes past which the next entry starts a new file.
+    pub max_file_bytes: u64,
+    /// Also start a new file with the first entry of each day (UTC).
+    pub rotate_daily: bool,
+    /// Also record the block of each new block event, so that `replay --offline` can serve
+    /// it. Asks the node for every block.
+    pub record_blocks: bool,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_file_bytes: 64 * 1024 * 1024,
+            rotate_daily: true,
+            record_blocks: false,
+        }
+    }
+}
+
+/// Vote tally configuration. See `blvm_governance::tally`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TallyConfig {
+    /// Thresholds by proposal tier, e.g. `[governance.tally.tiers.standard]`. Proposals of
+    /// other tiers are tallied without any. Read at startup.
+    pub tiers: std::collections::BTreeMap<String, TierThresholds>,
+    /// Registry weights voters carry in weight-based tallies: `off`, `current` (the live
+    /// registry's) or `snapshot` (the epoch snapshot each proposal is pinned to). Read at
+    /// startup.
+    pub weights: WeightSource,
+    /// Weight of voters not in the registry, e.g. 0 to leave them out of weight-based tallies.
+    pub default_weight: f64,
+    /// Weight-based thresholds by proposal tier, e.g. `[governance.tally.weighted.core]`; they
+    /// replace the tier's count-based ones. Need `weights`. Read at startup.
+    pub weighted: std::collections::BTreeMap<String, WeightThresholds>,
+    /// Blocks between two different votes of one voter within which they conflict, the later
+    /// height or else the stronger objection winning, rather than the later replacing the
+    /// earlier; 0 for votes at the same height only. Unset, votes never conflict. See
+    /// `blvm_governance::vote_check`.
+    pub vote_conflict_window_blocks: Option<u64>,
+}
+
+/// Where voters' weights in weight-based tallies come from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightSource {
+    /// Tallies are not weighted.
+    #[default]
+    Off,
+    /// Effective weights in the live registry, decayed and capped as for vetoes.
+    Current,
+    /// Weights in the epoch snapshot the proposal is pinned to, else the live registry's.
+    Snapshot,
+}
+
+/// Thresholds of one proposal tier.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TierThresholds {
+    /// Voters, abstentions included, that make a quorum; 0 needs none.
+    pub quorum: u64,
+    /// Percent of the yes and no votes that must be yes for approval; 0 disables approval.
+    pub approval_percent: f64,
+}
+
+/// Weight-based thresholds of one proposal tier.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeightThresholds {
+    /// Percent of the registry's total weight, abstentions included, that makes a quorum; 0
+    /// needs none.
+    pub quorum_percent: f64,
+    /// Percent of the yes and no weight that must be yes for approval; 0 disables approval.
+    pub approval_percent: f64,
+}
+
+/// Voting deadline configuration. See `blvm_governance::deadlines`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadlineConfig {
+    /// Voting window in blocks from a proposal's created height, by tier, e.g.
+    /// `standard = 2016` under `[governance.deadlines.windows]`. Proposals of other tiers have
+    /// no deadline. Read at startup.
+    pub windows: std::collections::BTreeMap<String, u64>,
+    /// Blocks before the deadline at which a reminder is sent.
+    pub reminders: Vec<u64>,
+}
+
+impl Default for DeadlineConfig {
+    fn default() -> Self {
+        Self {
+            windows: std::collections::BTreeMap::new(),
+            reminders: vec![1000, 100, 10],
+        }
+    }
+}
+
+/// Activation configuration. See `blvm_governance::activation`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivationConfig {
+    /// Blocks from merge to activation, by tier, e.g. `core = 4032` under
+    /// `[governance.activation.delays]`. Merged proposals of other tiers are not followed.
+    /// Read at startup.
+    pub delays: std::collections::BTreeMap<String, u64>,
+    /// A countdown is sent each time the blocks remaining fall to a multiple of this; 0 sends
+    /// none.
+    pub countdown_interval_blocks: u64,
+}
+
+impl Default for ActivationConfig {
+    fn default() -> Self {
+        Self {
+            delays: std::collections::BTreeMap::new(),
+            countdown_interval_blocks: 1008,
+        }
+    }
+}
+
+/// Miner signaling configuration. See `blvm_governance::signaling`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignalingConfig {
+    /// Proposals that need miner signaling, by proposal id, e.g.
+    /// `[governance.signaling.proposals.42]`. Read at startup.
+    pub proposals: std::collections::BTreeMap<String, SignalingRule>,
+}
+
+/// How one proposal is signaled for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignalingRule {
+    /// Version bit signaled for, 0 to 28.
+    pub bit: u8,
+    /// Blocks per window; windows start at multiples of it.
+    pub window_blocks: u64,
+    /// Percent of a window's blocks that must signal for lock-in.
+    pub threshold_percent: f64,
+}
+
+impl Default for SignalingRule {
+    fn default() -> Self {
+        Self {
+            bit: 0,
+            window_blocks: 2016,
+            threshold_percent: 90.0,
+        }
+    }
+}
+
+/// Epoch summary configuration. See `blvm_governance::epoch_summary`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EpochSummaryConfig {
+    /// Blocks per epoch; epochs start at multiples of it. 0 disables summaries. Read at
+    /// startup.
+    pub length_blocks: u64,
+    /// Blocks past an epoch's last block before it is summarized.
+    pub confirmations: u64,
+}
+
+impl Default for EpochSummaryConfig {
+    fn default() -> Self {
+        Self {
+            length_blocks: 0,
+            confirmations: 6,
+        }
+    }
+}
+
+/// Adoption tracking configuration. See `blvm_governance::adoption`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdoptionConfig {
+    /// Most recent blocks miner adoption is measured over.
+    pub window_blocks: u64,
+    /// Adoption percentages at which `proposal_adoption_milestone` is sent, once each.
+    pub milestones_percent: Vec<f64>,
+    /// Activated proposals followed, by proposal id, e.g.
+    /// `[governance.adoption.proposals.42]`. Read at startup.
+    pub proposals: std::collections::BTreeMap<String, AdoptionRule>,
+}
+
+impl Default for AdoptionConfig {
+    fn default() -> Self {
+        Self {
+            window_blocks: 2016,
+            milestones_percent: vec![50.0, 90.0],
+            proposals: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+/// How adoption of one proposal is measured; exactly one of the two is set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdoptionRule {
+    /// Version bit of blocks that adopted a miner-enforced change: the share of recent
+    /// blocks signaling it.
+    pub bit: Option<u8>,
+    /// Capability economic nodes report once they run a node-level feature: the share of
+    /// active registered nodes reporting it.
+    pub capability: Option<String>,
+}
+
+/// Conflict detection configuration. See `blvm_governance::conflicts`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConflictConfig {
+    /// Blocks a merged proposal still conflicts with others: one created, or one merged,
+    /// within this many blocks of its merge is flagged or alerted on.
+    pub merge_window_blocks: u64,
+}
+
+impl Default for ConflictConfig {
+    fn default() -> Self {
+        Self {
+            merge_window_blocks: 2016,
+        }
+    }
+}
+
+/// Proposal amendment configuration. See `blvm_governance::amendments`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmendmentConfig {
+    /// Blocks between lookups of every open proposal on the node, which find changes to
+    /// their content hash or tier; 0 finds them only when a proposal is looked up anyway, on
+    /// its created event or a refresh. Read at startup.
+    pub refresh_interval_blocks: u64,
+    /// What becomes of the votes cast before an amendment: `keep` them, drop them when the
+    /// content hash changes (`reset_on_content`) or on any amendment (`reset`).
+    pub votes: AmendedVotes,
+}
+
+/// What becomes of a proposal's votes when it is amended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmendedVotes {
+    /// They still count.
+    #[default]
+    Keep,
+    /// They are dropped when the content hash changes, and kept when only the tier does.
+    ResetOnContent,
+    /// They are dropped.
+    Reset,
+}
+
+/// GitHub webhook receiver configuration. See `blvm_governance::github`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GithubConfig {
+    /// Secret of the GitHub webhook, checked against `X-Hub-Signature-256`; unset disables
+    /// `POST /integrations/github` on the health listener.
+    pub secret: Option<String>,
+    /// Repositories ("owner/name") whose pull requests are proposals; deliveries for others
+    /// are ignored.
+    pub repositories: Vec<String>,
+    /// Prefix of the label naming a pull request's proposal, e.g. "proposal:42".
+    pub proposal_label_prefix: String,
+    /// Prefix of the label naming its tier, e.g. "tier:standard".
+    pub tier_label_prefix: String,
+}
+
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            repositories: Vec::new(),
+            proposal_label_prefix: "proposal:".to_string(),
+            tier_label_prefix: "tier:".to_string(),
+        }
+    }
+}
+
+/// Proposal content verification configuration. See `blvm_governance::content`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentConfig {
+    /// Check the content hash of each new proposal against the `OP_RETURN` of its
+    /// commitment transaction. Read at startup.
+    pub verify: bool,
+    /// Also fetch the full text from the proposal's `content_url`, if it has one, and check
+    /// that it hashes to the content hash.
+    pub fetch_text: bool,
+    /// Longest wait for the full text, in seconds.
+    pub fetch_timeout_secs: u64,
+    /// Longest full text fetched, in bytes; longer ones are left unverified.
+    pub max_text_bytes: u64,
+}
+
+impl Default for ContentConfig {
+    fn default() -> Self {
+        Self {
+            verify: false,
+            fetch_text: false,
+            fetch_timeout_secs: 10,
+            max_text_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// Vote delegation configuration. See `blvm_governance::delegation`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DelegationConfig {
+    /// Record delegations and count delegated votes in tallies. Read at startup.
+    pub enabled: bool,
+    /// Also read delegations from `OP_RETURN` outputs of new blocks. Read at startup.
+    pub on_chain: bool,
+}
+
+/// On-chain anchoring configuration. See `blvm_governance::anchor`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnchorConfig {
+    /// Have the node broadcast a commitment to the governance state every `interval_blocks`
+    /// blocks, paid from its wallet. Requires `allow_actions`; read at startup.
+    pub enabled: bool,
+    /// Blocks between anchors; one is made at each height that is a multiple of it.
+    pub interval_blocks: u64,
+    /// Confirmation target of the fee estimate the anchor pays, in blocks.
+    pub confirmation_target_blocks: u32,
+    /// Highest fee rate an anchor pays, in sat/vB, whatever the estimate (0 for no limit).
+    pub max_fee_rate_sat_per_vbyte: u64,
+    /// Confirmations after which an anchor is recorded as confirmed.
+    pub confirmations: u64,
+    /// Blocks after its broadcast an anchor may take to confirm before it is given up.
+    pub confirmation_timeout_blocks: u64,
+    /// Attempts to build and broadcast an anchor, one per block, before it is given up.
+    pub max_attempts: u32,
+}
+
+impl Default for AnchorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_blocks: 144,
+            confirmation_target_blocks: 6,
+            max_fee_rate_sat_per_vbyte: 50,
+            confirmations: 1,
+            confirmation_timeout_blocks: 72,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Digest configuration. See `blvm_governance::digest`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    /// How the webhook is notified: "event" sends each notification as it happens, "digest"
+    /// collects them and sends one `governance_digest` per period instead. Read at startup.
+    pub mode: DigestMode,
+    /// Seconds per period, counted from the Unix epoch, so 86400 sends a digest at each
+    /// midnight UTC. Ignored when `schedule` is set.
+    pub every_secs: u64,
+    /// When to send digests instead, as a cron expression in UTC: minute, hour, day of month,
+    /// month and day of week, e.g. "0 9 * * 1-5".
+    pub schedule: Option<String>,
+    /// Clock the period is measured on: "wall" for the local clock, "block" for the
+    /// timestamp of the latest block.
+    pub clock: DigestClock,
+    /// Send a digest saying all was quiet at the end of a period without notifications,
+    /// rather than nothing.
+    pub all_quiet: bool,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            mode: DigestMode::Event,
+            every_secs: 86_400,
+            schedule: None,
+            clock: DigestClock::Wall,
+            all_quiet: false,
+        }
+    }
+}
+
+/// How webhook notifications are delivered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestMode {
+    /// Each as it happens.
+    #[default]
+    Event,
+    /// Collected into a periodic digest.
+    Digest,
+}
+
+/// Clock a digest schedule is measured on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestClock {
+    /// The local clock, checked every few seconds.
+    #[default]
+    Wall,
+    /// The timestamp of the latest block, checked as each block arrives.
+    Block,
+}
+
+/// Participation statistics configuration. See `blvm_governance::participation`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticipationConfig {
+    /// Count each proposal's eligible voters, votes, vote latency and vetoes once voting on
+    /// it closes. Read at startup.
+    pub enabled: bool,
+}
+
+/// Veto escalation configuration. See `blvm_governance::escalation`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VetoEscalationConfig {
+    /// Shares of the veto threshold, in percent, e.g. [50.0, 75.0, 90.0]: a proposal's veto
+    /// tally entering one sends `veto_escalation`, falling back below it `veto_deescalation`,
+    /// once each. Empty sends neither. Read at startup.
+    pub bands: Vec<f64>,
+    /// Largest vetoing nodes named in each message.
+    pub contributors: usize,
+    /// Name the vetoing nodes by type and weight only, leaving out their ids.
+    pub redact_node_ids: bool,
+}
+
+impl Default for VetoEscalationConfig {
+    fn default() -> Self {
+        Self {
+            bands: Vec::new(),
+            contributors: 3,
+            redact_node_ids: false,
+        }
+    }
+}
+
+/// Node request configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpcConfig {
+    /// Longest wait for the node to answer a request, retries included; methods with a
+    /// shorter default keep it (see `[governance.nodeapi_timeouts]`).
+    pub request_timeout_secs: u64,
+    /// Attempts per request when the node fails with a transient error, the first included.
+    pub request_attempts: u32,
+    /// Milliseconds before the first retry of a failed request; doubled for each further one.
+    pub retry_backoff_ms: u64,
+    /// Reconcile the registry with the node when missed events are detected.
+    pub reconcile_on_gap: bool,
+    /// Most blocks backfilled on (re)connect from the last checkpoint (0 disables backfill).
+    pub max_backfill_blocks: u64,
+    /// Seconds between logged request and event summaries (0 disables).
+    pub metrics_log_interval_secs: u64,
+    /// Seconds between status reports sent to the node (0 disables).
+    pub status_report_interval_secs: u64,
+    /// Largest payload sent to the node, in bytes; larger ones fail without being sent.
+    /// Should not exceed the node's own message size limit.
+    pub max_message_bytes: usize,
+    /// Requests to the node in flight at once; further requests wait.
+    pub max_in_flight: usize,
+    /// Of `max_in_flight`, permits only governance event handling may use, never backfill
+    /// or reconciliation.
+    pub reserved_interactive: usize,
+    /// Seconds fee rates from the node are reused for (0 asks the node every time).
+    pub fee_cache_ttl_secs: u64,
+    /// Users other than the module's own allowed to own the node socket.
+    pub allowed_socket_uids: Vec<u32>,
+    /// Groups allowed to own the node socket.
+    pub allowed_socket_gids: Vec<u32>,
+    /// Connect without checking the socket's owner and permissions (development only).
+    pub insecure_socket: bool,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 30,
+            request_attempts: crate::node_api::DEFAULT_REQUEST_ATTEMPTS,
+            retry_backoff_ms: crate::node_api::DEFAULT_RETRY_BACKOFF.as_millis() as u64,
+            reconcile_on_gap: false,
+            max_backfill_blocks: 1000,
+            metrics_log_interval_secs: 300,
+            status_report_interval_secs: 60,
+            max_message_bytes: crate::node_api::DEFAULT_MAX_MESSAGE_BYTES,
+            max_in_flight: crate::node_api::DEFAULT_MAX_IN_FLIGHT,
+            reserved_interactive: crate::node_api::DEFAULT_RESERVED_INTERACTIVE,
+            fee_cache_ttl_secs: crate::node_api::DEFAULT_FEE_CACHE_TTL.as_secs(),
+            allowed_socket_uids: Vec::new(),
+            allowed_socket_gids: Vec::new(),
+            insecure_socket: false,
+        }
+    }
+}
+
+impl IpcConfig {
+    pub fn retry_policy(&self) -> crate::node_api::RetryPolicy {
+        crate::node_api::RetryPolicy {
+            attempts: self.request_attempts.max(1),
+            backoff: std::time::Duration::from_millis(self.retry_backoff_ms),
+        }
+    }
+}
+
+/// Event queue configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventQueueConfig {
+    /// Events queued for processing before the overflow policy applies.
+    pub capacity: usize,
+    /// What happens to an event when the queue is full: "backpressure" waits for room,
+    /// "drop_low_priority" drops `NewBlock` events.
+    pub policy: OverflowPolicy,
+    /// Events processed concurrently; events for the same proposal, node or block chain
+    /// are still processed in order.
+    pub parallelism: usize,
+}
+
+impl Default for EventQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            policy: OverflowPolicy::Backpressure,
+            parallelism: 4,
+        }
+    }
+}
+
+/// What happens to an event when the event queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait for room; the module stops reading events from the node meanwhile.
+    #[default]
+    Backpressure,
+    /// Drop `NewBlock` events; governance events still wait for room.
+    DropLowPriority,
+}
+
+/// Graceful shutdown configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// Seconds to wait for accepted events and queued webhook deliveries before exiting.
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout_secs: 10,
+        }
+    }
+}
+
+/// IPC heartbeat configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeartbeatConfig {
+    /// Seconds between heartbeats, and how long each waits for a response (0 disables).
+    pub interval_secs: u64,
+    /// Consecutive missed heartbeats after which the connection is considered dead.
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            max_missed: 3,
+        }
+    }
+}
+
+/// Scheduled backup configuration. Backups are written under `<data_dir>/backups`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Seconds between scheduled backups (0 disables).
+    pub interval_secs: u64,
+    /// Scheduled backups kept; older ones are deleted.
+    pub retention: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            retention: 7,
+        }
+    }
 }
 
 fn default_webhook_retry_count() -> u32 {
     3
 }
 
+fn default_config_reload_secs() -> u64 {
+    30
+}
+
+/// Economic node registry configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistryConfig {
+    /// Reputation scoring settings.
+    pub reputation: ReputationConfig,
+    /// Registration rate limits and spam protection.
+    pub rate_limit: RateLimitConfig,
+    /// Proof-of-reserve verification.
+    pub reserve: ReserveConfig,
+    /// Seconds between reconciliations against the node's economic node set (0 disables).
+    pub reconcile_interval_secs: u64,
+    /// Registration payload validation bounds.
+    pub validation: ValidationConfig,
+    /// Maximum number of stored economic nodes; weaker entries are evicted to the archive.
+    pub max_nodes: usize,
+    /// Veto aggregation and reporting to the node.
+    pub veto: VetoConfig,
+    /// Nodes that have not re-announced within this many blocks count as expired (0 = never).
+    pub expiry_blocks: u64,
+    /// Epoch snapshots of the registry.
+    pub epoch: EpochConfig,
+    /// Allowlist and blocklist of node identities.
+    pub access: AccessListConfig,
+    /// Weight change records kept per node.
+    pub weight_history_len: usize,
+    /// Decay of weight backed by stale reserve verifications.
+    pub decay: DecayConfig,
+    /// Sybil clustering heuristics.
+    pub sybil: SybilConfig,
+    /// Store compaction.
+    pub compaction: CompactionConfig,
+}
+
+/// Registry store compaction configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompactionConfig {
+    /// Archived (evicted) nodes are dropped this many blocks after eviction (0 keeps them).
+    pub archive_retention_blocks: u64,
+    /// Compact automatically once the stored registry exceeds this many bytes (0 disables).
+    pub auto_threshold_bytes: u64,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            archive_retention_blocks: 52_560,
+            auto_threshold_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Sybil clustering configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SybilConfig {
+    /// Minimum pairwise confidence for two registrations to be clustered.
+    pub min_confidence: f64,
+    /// Registrations at most this many blocks apart count as one burst.
+    pub burst_window_blocks: u64,
+    /// Metadata and burst groups larger than this are background noise, not evidence.
+    pub max_weak_group: usize,
+    /// Cap each suspected cluster's combined weight in tallies at this percentile of
+    /// individual node weights (0 disables; stored records are never changed).
+    pub cap_percentile: f64,
+}
+
+impl Default for SybilConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.5,
+            burst_window_blocks: 1,
+            max_weak_group: 20,
+            cap_percentile: 0.0,
+        }
+    }
+}
+
+/// Weight decay schedule, by blocks since a node's reserve was last verified.
+///
+/// `steps` takes precedence when non-empty; with neither set, weight does not decay.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecayConfig {
+    /// Blocks after which weight halves (0 disables exponential decay).
+    pub half_life_blocks: u64,
+    /// Piecewise schedule: the factor of the last step reached applies.
+    pub steps: Vec<DecayStep>,
+}
+
+/// One step of a piecewise decay schedule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecayStep {
+    /// Blocks since the last reserve verification from which the step applies.
+    pub after_blocks: u64,
+    /// Multiplier applied to the weight.
+    pub factor: f64,
+}
+
+/// Access list configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessListConfig {
+    /// File of node ids / public keys whose registrations and vetoes are ignored.
+    pub blocklist_path: Option<std::path::PathBuf>,
+    /// File of node ids / public keys allowed to register when `allowlist_mode` is on.
+    pub allowlist_path: Option<std::path::PathBuf>,
+    /// Only identities on the allowlist may register.
+    pub allowlist_mode: bool,
+    /// Seconds between checks of the list files for changes (0 disables hot reload).
+    pub reload_interval_secs: u64,
+}
+
+impl Default for AccessListConfig {
+    fn default() -> Self {
+        Self {
+            blocklist_path: None,
+            allowlist_path: None,
+            allowlist_mode: false,
+            reload_interval_secs: 30,
+        }
+    }
+}
+
+/// Epoch snapshot configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EpochConfig {
+    /// Epoch length in blocks; a snapshot is taken at each multiple (0 disables snapshots).
+    pub length_blocks: u64,
+    /// Snapshots kept, newest first; snapshots pinned by open proposals are always kept.
+    pub retention: usize,
+}
+
+impl Default for EpochConfig {
+    fn default() -> Self {
+        Self {
+            length_blocks: 2016,
+            retention: 12,
+        }
+    }
+}
+
+/// Veto aggregation configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VetoConfig {
+    /// Share of registered weight (percent) whose vetoes block a proposal.
+    pub threshold_percent: f64,
+    /// Compute tallies but never report them to the node.
+    pub observe_only: bool,
+    /// Seconds between retries of unsent veto results.
+    pub report_retry_secs: u64,
+    /// Tally each proposal against the epoch snapshot in effect when it was created instead
+    /// of the live registry.
+    pub use_epoch_snapshot: bool,
+}
+
+impl Default for VetoConfig {
+    fn default() -> Self {
+        Self {
+            threshold_percent: 30.0,
+            observe_only: false,
+            report_retry_secs: 30,
+            use_epoch_snapshot: false,
+        }
+    }
+}
+
+/// Registration payload validation bounds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Maximum accepted node id length, in characters.
+    pub max_node_id_len: usize,
+    /// Maximum accepted node type length, in characters.
+    pub max_node_type_len: usize,
+    /// Maximum claimed hashpower percentage.
+    pub max_hashpower_percent: f64,
+    /// Maximum outpoints in a reserve claim.
+    pub max_outpoints: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_node_id_len: 64,
+            max_node_type_len: 64,
+            max_hashpower_percent: 100.0,
+            max_outpoints: 256,
+        }
+    }
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            reputation: ReputationConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            reserve: ReserveConfig::default(),
+            reconcile_interval_secs: 3600,
+            validation: ValidationConfig::default(),
+            max_nodes: 10_000,
+            veto: VetoConfig::default(),
+            expiry_blocks: 4032,
+            epoch: EpochConfig::default(),
+            access: AccessListConfig::default(),
+            weight_history_len: 256,
+            decay: DecayConfig::default(),
+            sybil: SybilConfig::default(),
+            compaction: CompactionConfig::default(),
+        }
+    }
+}
+
+/// Proof-of-reserve verification configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReserveConfig {
+    /// Confirmations a claimed outpoint needs before it counts towards weight.
+    pub min_confirmations: u64,
+    /// Address proofs must sign a block hash at most this many blocks below the tip.
+    pub max_challenge_depth: u64,
+    /// How often to look in the node's mempool for transactions spending claimed outpoints,
+    /// in seconds (0 disables).
+    pub mempool_watch_secs: u64,
+}
+
+impl Default for ReserveConfig {
+    fn default() -> Self {
+        Self {
+            min_confirmations: 6,
+            max_challenge_depth: 144,
+            mempool_watch_secs: 30,
+        }
+    }
+}
+
+/// Registration rate limiting configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Length of the per-source rate limit window, in blocks.
+    pub window_blocks: u64,
+    /// Registrations accepted from one source within a window. Both the node id and the
+    /// module submitting the registration through the API are sources.
+    pub max_per_source_per_window: u32,
+    /// New (previously unknown) nodes accepted per block across all sources.
+    pub max_new_per_block: u32,
+    /// Minimum verified reserve, in sats, for a registration to be accepted. Checked after
+    /// the reserve is verified; a registration proving none has 0.
+    pub min_verified_sats: u64,
+    /// Log one in every N rejections.
+    pub log_sample_rate: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            window_blocks: 144,
+            max_per_source_per_window: 6,
+            max_new_per_block: 50,
+            min_verified_sats: 0,
+            log_sample_rate: 100,
+        }
+    }
+}
+
+/// Reputation scoring configuration.
+///
+/// Component weights are relative; they are normalized by their sum when the score is computed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReputationConfig {
+    /// Weight of longevity (blocks since first registration).
+    pub longevity_weight: f64,
+    /// Weight of the liveness ratio.
+    pub liveness_weight: f64,
+    /// Weight of claimed-weight stability.
+    pub stability_weight: f64,
+    /// Weight of the veto record (vetoes on proposals that failed vs merged anyway).
+    pub veto_record_weight: f64,
+    /// Blocks of registration after which longevity is maxed out.
+    pub longevity_target_blocks: u64,
+    /// A node counts as live at a block if it announced itself within this many blocks.
+    pub liveness_window_blocks: u64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            longevity_weight: 0.25,
+            liveness_weight: 0.25,
+            stability_weight: 0.25,
+            veto_record_weight: 0.25,
+            longevity_target_blocks: 26_280,
+            liveness_window_blocks: 2016,
+        }
+    }
+}
+
+impl Default for GovernanceConfig {
+    /// The settings of an empty configuration file.
+    fn default() -> Self {
+        toml::Table::new()
+            .try_into()
+            .expect("every setting has a default")
+    }
+}
+
 blvm_sdk::impl_module_config!(GovernanceConfig);
 
+/// Parse a duration such as "500ms", "5s" or "2m"; a bare number is seconds.
+pub(crate) fn parse_duration(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    match unit.trim() {
+        "ms" => Some(std::time::Duration::from_millis(amount)),
+        "" | "s" => Some(std::time::Duration::from_secs(amount)),
+        "m" => Some(std::time::Duration::from_secs(amount * 60)),
+        _ => None,
+    }
+}
+
+/// Configuration file named by `--config <path>` or `--config=<path>` in `args`, else by
+/// `env` (the value of [`CONFIG_ENV`]); `None` if neither names one.
+pub fn config_path(
+    args: impl IntoIterator<Item = String>,
+    env: Option<std::ffi::OsString>,
+) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    env.filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+/// Configuration file of this process: the one given by `--config` or [`CONFIG_ENV`], else
+/// `config.toml` in `data_dir`.
+pub fn file_path(data_dir: &Path) -> PathBuf {
+    config_path(std::env::args().skip(1), std::env::var_os(CONFIG_ENV))
+        .unwrap_or_else(|| data_dir.join("config.toml"))
+}
+
+/// A key in the configuration that is not a setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Dotted path below `[governance]`, e.g. `registry.veto.treshold_percent`.
+    pub key: String,
+    /// Settings it may have been meant as, closest first.
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown setting governance.{}", self.key)?;
+        if !self.suggestions.is_empty() {
+            let suggestions: Vec<String> = self
+                .suggestions
+                .iter()
+                .map(|s| format!("governance.{}", s))
+                .collect();
+            write!(f, " (did you mean {}?)", suggestions.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The configuration with every optional setting given, so that all of them appear in its
+/// serialized form.
+pub(crate) fn schema() -> toml::Table {
+    let mut sample = GovernanceConfig {
+        webhook_url: Some(String::new()),
+        node_id: Some(String::new()),
+        webhook_secret: Some(String::new()),
+        webhook_network: Some(String::new()),
+        governance_tier: Some(String::new()),
+        ..Default::default()
+    };
+    sample.log_forward.level = Some(String::new());
+    sample.logging.level = Some(String::new());
+    sample.logging.dir = Some(PathBuf::new());
+    sample.health.listen = Some(String::new());
+    sample.alerts.url = Some(String::new());
+    sample.registry.access.blocklist_path = Some(PathBuf::new());
+    sample.registry.access.allowlist_path = Some(PathBuf::new());
+    sample.digest.schedule = Some(String::new());
+    sample.tally.vote_conflict_window_blocks = Some(0);
+    sample.health.admin_token = Some(String::new());
+    sample.github.secret = Some(String::new());
+    sample.health.actions_token = Some(String::new());
+    match toml::Value::try_from(sample) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("the configuration serializes to a table"),
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Levenshtein distance, ignoring case and treating `-` as `_`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let normalize = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| {
+                if c == '-' {
+                    '_'
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != cb) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Paths of settings named `name` anywhere in `table`.
+fn paths_named(name: &str, table: &toml::Table, prefix: &str, found: &mut Vec<String>) {
+    for (key, value) in table {
+        let path = join(prefix, key);
+        if key == name {
+            found.push(path.clone());
+        }
+        if let toml::Value::Table(inner) = value {
+            paths_named(name, inner, &path, found);
+        }
+    }
+}
+
+/// Settings next to the unknown `key` (in `siblings`) spelled almost like it, closest first,
+/// then settings of the same name elsewhere.
+fn suggestions(
+    key: &str,
+    prefix: &str,
+    siblings: &toml::Table,
+    schema: &toml::Table,
+) -> Vec<String> {
+    let mut near: Vec<(usize, &String)> = siblings
+        .keys()
+        .map(|sibling| (edit_distance(key, sibling), sibling))
+        .filter(|&(distance, _)| distance <= 2 && distance < key.len())
+        .collect();
+    near.sort();
+    let mut found: Vec<String> = near.into_iter().map(|(_, s)| join(prefix, s)).collect();
+    paths_named(key, schema, "", &mut found);
+    found.dedup();
+    found
+}
+
+fn find_unknown(
+    table: &toml::Table,
+    settings: &toml::Table,
+    prefix: &str,
+    schema: &toml::Table,
+    found: &mut Vec<UnknownKey>,
+) {
+    for (key, value) in table {
+        let path = join(prefix, key);
+        match (settings.get(key), value) {
+            // An empty table in the schema is a map with keys of the user's choosing
+            (Some(toml::Value::Table(inner)), toml::Value::Table(value)) if !inner.is_empty() => {
+                find_unknown(value, inner, &path, schema, found)
+            }
+            (Some(_), _) => {}
+            (None, _) => found.push(UnknownKey {
+                suggestions: suggestions(key, prefix, settings, schema),
+                key: path,
+            }),
+        }
+    }
+}
+
+/// Keys in a `[governance]` table that are not settings.
+pub fn unknown_keys(section: &toml::Table) -> Vec<UnknownKey> {
+    let schema = schema();
+    let mut found = Vec::new();
+    find_unknown(section, &schema, "", &schema, &mut found);
+    found
+}
+
+/// The setting at `path` in `schema`: its default value, or an empty string for an entry of a
+/// map with keys of the user's choosing. `None` if there is no such setting.
+fn setting<'a>(schema: &'a toml::Table, path: &[&str]) -> Option<&'a toml::Value> {
+    static ANY: toml::Value = toml::Value::String(String::new());
+    let (first, rest) = path.split_first()?;
+    match (schema.get(*first)?, rest) {
+        (value, []) => Some(value),
+        (toml::Value::Table(inner), [_]) if inner.is_empty() => Some(&ANY),
+        (toml::Value::Table(inner), rest) => setting(inner, rest),
+        _ => None,
+    }
+}
+
+/// A value as TOML (`30`, `true`, `[1, 2]`), or as a string if it is not TOML.
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// A module context value as the type of the setting it is for. Lists can be given comma
+/// separated.
+fn context_value(value: &str, setting: &toml::Value) -> toml::Value {
+    match setting {
+        toml::Value::String(_) => toml::Value::String(value.to_string()),
+        toml::Value::Array(_) if !value.trim_start().starts_with('[') => toml::Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_value)
+                .collect(),
+        ),
+        _ => parse_value(value),
+    }
+}
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` in `value`, looking variables up with `env`; `$${`
+/// is a literal `${`.
+fn interpolate(value: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${") else {
+            expanded.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let end = after
+            .find('}')
+            .ok_or_else(|| "unterminated ${".to_string())?;
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after[..end], None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid variable name {:?}", name));
+        }
+        match (env(name), default) {
+            (Some(found), Some(default)) if found.is_empty() => expanded.push_str(default),
+            (Some(found), _) => expanded.push_str(&found),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => return Err(format!("environment variable {} is not set", name)),
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// [`interpolate`] every string in `value`, which is at `path` in the configuration.
+fn interpolate_value(
+    value: &mut toml::Value,
+    path: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    match value {
+        toml::Value::String(s) => {
+            *s = interpolate(s, env).map_err(|e| format!("governance.{}: {}", path, e))?
+        }
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate_value(item, path, env)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                interpolate_value(value, &join(path, key), env)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 impl GovernanceConfig {
+    fn parse(text: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut document = text.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        let section = match document.remove("governance") {
+            Some(toml::Value::Table(section)) => section,
+            Some(_) => return Err("governance is not a table".to_string()),
+            None => toml::Table::new(),
+        };
+        for unknown in unknown_keys(&section) {
+            tracing::warn!("Ignoring {}", unknown);
+        }
+        let mut section = toml::Value::Table(section);
+        interpolate_value(&mut section, "", env)?;
+        section
+            .try_into()
+            .map_err(|e| format!("[governance]: {}", e))
+    }
+
+    /// Parse the contents of a configuration file, expanding environment variables in its
+    /// strings. Keys that are not settings are logged and ignored.
+    pub fn from_toml(text: &str) -> Result<Self, GovernanceError> {
+        Self::parse(text, &process_env).map_err(GovernanceError::ConfigError)
+    }
+
+    /// Read the configuration file at `path`; defaults if there is none.
+    pub fn from_file(path: &Path) -> Result<Self, GovernanceError> {
+        let error = |e: String| GovernanceError::ConfigError(format!("{}: {}", path.display(), e));
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, &process_env).map_err(error),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(GovernanceError::io(path.display())(e)),
+        }
+    }
+
+    /// Apply module context config values over these settings. Keys outside `governance.`
+    /// are not the module's and are skipped; unknown settings are logged and skipped.
+    pub fn merge_context(
+        &mut self,
+        context: &HashMap<String, String>,
+    ) -> Result<(), GovernanceError> {
+        let schema = schema();
+        let mut merged = match toml::Value::try_from(&*self) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => unreachable!("the configuration serializes to a table"),
+        };
+        let mut keys: Vec<&String> = context.keys().collect();
+        keys.sort();
+        for key in keys {
+            let Some(path) = key.strip_prefix("governance.") else {
+                continue;
+            };
+            let path: Vec<&str> = path.split('.').collect();
+            let Some(kind) = setting(&schema, &path) else {
+                let (name, parents) = path.split_last().unwrap();
+                let prefix = parents.join(".");
+                let siblings = match setting(&schema, parents) {
+                    Some(toml::Value::Table(siblings)) => siblings,
+                    _ => &schema,
+                };
+                let unknown = UnknownKey {
+                    key: path.join("."),
+                    suggestions: suggestions(name, &prefix, siblings, &schema),
+                };
+                tracing::warn!("Ignoring module context value: {}", unknown);
+                continue;
+            };
+            let value = context_value(&context[key], kind);
+            let (name, parents) = path.split_last().unwrap();
+            let mut table = &mut merged;
+            for parent in parents {
+                let entry = table
+                    .entry(parent.to_string())
+                    .or_insert(toml::Value::Table(toml::Table::new()));
+                let toml::Value::Table(inner) = entry else {
+                    unreachable!("settings with nested settings are tables");
+                };
+                table = inner;
+            }
+            table.insert(name.to_string(), value);
+        }
+        *self = toml::Value::Table(merged)
+            .try_into()
+            .map_err(|e| GovernanceError::ConfigError(format!("module context: {}", e)))?;
+        Ok(())
+    }
+
+    /// The settings in effect: the file at `path`, then the module `context` over it.
+    pub fn read(path: &Path, context: &HashMap<String, String>) -> Result<Self, GovernanceError> {
+        let mut config = Self::from_file(path)?;
+        config.merge_context(context)?;
+        Ok(config)
+    }
+
+    /// Timeouts of requests to the node: each method's default capped at
+    /// `ipc.request_timeout_secs`, then `nodeapi_timeouts`. Unknown methods and unreadable
+    /// durations are logged and skipped.
+    pub fn request_timeouts(&self) -> crate::node_api::MethodTimeouts {
+        let cap = std::time::Duration::from_secs(self.ipc.request_timeout_secs.max(1));
+        let mut timeouts = crate::node_api::MethodTimeouts::capped(cap);
+        for (name, value) in &self.nodeapi_timeouts {
+            let Some(method) = crate::ipc_metrics::IpcMethod::from_name(name) else {
+                tracing::warn!("Ignoring timeout for unknown node method {}", name);
+                continue;
+            };
+            match parse_duration(value) {
+                Some(timeout) if !timeout.is_zero() => timeouts.set(method, timeout),
+                _ => tracing::warn!("Ignoring timeout {:?} for {}", value, name),
+            }
+        }
+        timeouts
+    }
+
     /// Convert to ModuleContext config map for webhook client compatibility.
     pub fn to_context_map(&self) -> std::collections::HashMap<String, String> {
         let mut m = std::collections::HashMap::new();
@@ -51,6 +1878,263 @@ impl GovernanceConfig {
         if let Some(ref id) = self.node_id {
             m.insert("governance.node_id".to_string(), id.clone());
         }
+        m.insert(
+            "governance.webhook_retry_count".to_string(),
+            self.webhook_retry_count.to_string(),
+        );
+        if !self.webhook_events.is_empty() {
+            m.insert(
+                "governance.webhook_events".to_string(),
+                self.webhook_events.join(","),
+            );
+        }
+        if let Some(ref network) = self.webhook_network {
+            m.insert("governance.webhook_network".to_string(), network.clone());
+        }
+        m.insert(
+            "governance.webhook_content_type".to_string(),
+            self.webhook_content_type.as_str().to_string(),
+        );
         m
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(config: &GovernanceConfig) -> serde_json::Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut config = GovernanceConfig {
+            webhook_url: Some("https://governance.example.com/webhook".to_string()),
+            webhook_events: vec!["proposal_created".to_string()],
+            webhook_content_type: WebhookContentType::Msgpack,
+            allow_actions: true,
+            ..Default::default()
+        };
+        config.registry.veto.threshold_percent = 12.5;
+        config.registry.decay.steps = vec![DecayStep {
+            after_blocks: 1000,
+            factor: 0.5,
+        }];
+        config.events.policy = OverflowPolicy::DropLowPriority;
+        config
+            .nodeapi_timeouts
+            .insert("get_block".to_string(), "5s".to_string());
+
+        let mut document = toml::Table::new();
+        document.insert(
+            "governance".to_string(),
+            toml::Value::try_from(&config).unwrap(),
+        );
+        let text = toml::to_string(&document).unwrap();
+        let parsed = GovernanceConfig::from_toml(&text).unwrap();
+        assert_eq!(json(&parsed), json(&config));
+
+        // Every setting, optional ones included, is known
+        assert_eq!(unknown_keys(&schema()), vec![]);
+        assert_eq!(
+            json(&GovernanceConfig::from_toml("").unwrap()),
+            json(&GovernanceConfig::default())
+        );
+        assert!(GovernanceConfig::from_toml("[governance]\nwebhook_retry_count = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_context_overrides_file() {
+        let path = std::env::temp_dir().join(format!("blvm_config_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[governance]\n\
+             webhook_url = \"https://file.example.com\"\n\
+             webhook_retry_count = 5\n\
+             [governance.registry.veto]\n\
+             threshold_percent = 20.0\n",
+        )
+        .unwrap();
+        let context: HashMap<String, String> = [
+            ("governance.webhook_url", "https://node.example.com"),
+            (
+                "governance.webhook_events",
+                "proposal_created, registry_expired",
+            ),
+            ("governance.registry.veto.threshold_percent", "10"),
+            ("governance.nodeapi_timeouts.get_block", "5s"),
+            ("governance.ipc.allowed_socket_uids", "1000,1001"),
+            ("governance.webhok_url", "https://typo.example.com"),
+            ("other_module.webhook_url", "https://other.example.com"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let config = GovernanceConfig::read(&path, &context).unwrap();
+        assert_eq!(
+            config.webhook_url.as_deref(),
+            Some("https://node.example.com")
+        );
+        assert_eq!(config.webhook_retry_count, 5);
+        assert_eq!(
+            config.webhook_events,
+            vec!["proposal_created", "registry_expired"]
+        );
+        assert_eq!(config.registry.veto.threshold_percent, 10.0);
+        assert_eq!(config.nodeapi_timeouts["get_block"], "5s");
+        assert_eq!(config.ipc.allowed_socket_uids, vec![1000, 1001]);
+
+        // A missing file leaves the defaults for the context to override
+        std::fs::remove_file(&path).unwrap();
+        let config = GovernanceConfig::read(&path, &context).unwrap();
+        assert_eq!(config.webhook_retry_count, 3);
+        assert_eq!(config.registry.veto.threshold_percent, 10.0);
+
+        let mut bad = HashMap::new();
+        bad.insert(
+            "governance.webhook_retry_count".to_string(),
+            "many".to_string(),
+        );
+        assert!(GovernanceConfig::read(&path, &bad).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_values() {
+        let dry_run = |value: &str| {
+            GovernanceConfig::from_toml(&format!("[governance]\ndry_run = {}", value))
+                .map(|c| c.dry_run)
+        };
+        assert_eq!(dry_run("false").unwrap(), DryRun::Off);
+        assert_eq!(dry_run("true").unwrap(), DryRun::Webhook);
+        assert_eq!(dry_run("\"all\"").unwrap(), DryRun::All);
+        assert!(dry_run("\"some\"").is_err());
+        assert_eq!(GovernanceConfig::default().dry_run, DryRun::Off);
+
+        // Written back as it is read
+        assert_eq!(
+            toml::Value::try_from(DryRun::Webhook).unwrap(),
+            toml::Value::Boolean(true)
+        );
+        assert_eq!(
+            toml::Value::try_from(DryRun::All).unwrap(),
+            toml::Value::String("all".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_suggest_settings() {
+        let section: toml::Table = "webhok_url = \"x\"\n\
+             threshold_percent = 10.0\n\
+             [registry.veto]\n\
+             treshold_percent = 10.0\n\
+             [nodeapi_timeouts]\n\
+             get_block = \"5s\"\n"
+            .parse()
+            .unwrap();
+        let mut unknown = unknown_keys(&section);
+        unknown.sort_by(|a, b| a.key.cmp(&b.key));
+        let found: Vec<(&str, Vec<&str>)> = unknown
+            .iter()
+            .map(|u| {
+                (
+                    u.key.as_str(),
+                    u.suggestions.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "registry.veto.treshold_percent",
+                    vec!["registry.veto.threshold_percent"]
+                ),
+                ("threshold_percent", vec!["registry.veto.threshold_percent"]),
+                ("webhok_url", vec!["webhook_url"]),
+            ]
+        );
+        assert_eq!(
+            unknown[2].to_string(),
+            "unknown setting governance.webhok_url (did you mean governance.webhook_url?)"
+        );
+    }
+
+    #[test]
+    fn test_environment_interpolation() {
+        let env = |name: &str| match name {
+            "GOV_WEBHOOK_SECRET" => Some("s3cret".to_string()),
+            "GOV_HOST" => Some("gov.example.com".to_string()),
+            "GOV_EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(
+            interpolate("https://${GOV_HOST}/hook?a=$1", &env).unwrap(),
+            "https://gov.example.com/hook?a=$1"
+        );
+        assert_eq!(
+            interpolate(
+                "${GOV_MISSING:-fallback}-${GOV_EMPTY:-empty}-${GOV_EMPTY}",
+                &env
+            )
+            .unwrap(),
+            "fallback-empty-"
+        );
+        assert_eq!(
+            interpolate("$${GOV_HOST} costs $$5", &env).unwrap(),
+            "${GOV_HOST} costs $$5"
+        );
+        assert!(interpolate("${GOV_HOST", &env).is_err());
+        assert!(interpolate("${not a name}", &env).is_err());
+
+        let text = "[governance]\n\
+             webhook_secret = \"${GOV_WEBHOOK_SECRET}\"\n\
+             webhook_events = [\"${GOV_EVENT:-proposal_created}\"]\n\
+             [governance.nodeapi_timeouts]\n\
+             get_block = \"${GOV_TIMEOUT:-5s}\"\n";
+        let config = GovernanceConfig::parse(text, &env).unwrap();
+        assert_eq!(config.webhook_secret.as_deref(), Some("s3cret"));
+        assert_eq!(config.webhook_events, vec!["proposal_created"]);
+        assert_eq!(config.nodeapi_timeouts["get_block"], "5s");
+
+        let error = GovernanceConfig::parse("[governance]\nnode_id = \"${GOV_NODE_ID}\"\n", &env)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "governance.node_id: environment variable GOV_NODE_ID is not set"
+        );
+
+        // Values from the module context are not expanded
+        let mut config = GovernanceConfig::default();
+        let mut context = HashMap::new();
+        context.insert(
+            "governance.node_id".to_string(),
+            "${GOV_NODE_ID}".to_string(),
+        );
+        config.merge_context(&context).unwrap();
+        assert_eq!(config.node_id.as_deref(), Some("${GOV_NODE_ID}"));
+    }
+
+    #[test]
+    fn test_config_path_flag_wins_over_env() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let env = Some(std::ffi::OsString::from("/etc/env.toml"));
+        assert_eq!(
+            config_path(
+                args(&["--module-id", "x", "--config", "a.toml"]),
+                env.clone()
+            ),
+            Some(PathBuf::from("a.toml"))
+        );
+        assert_eq!(
+            config_path(args(&["--config=b.toml"]), env.clone()),
+            Some(PathBuf::from("b.toml"))
+        );
+        assert_eq!(
+            config_path(args(&["--data-dir", "d"]), env),
+            Some(PathBuf::from("/etc/env.toml"))
+        );
+        assert_eq!(config_path(args(&[]), None), None);
+    }
+}
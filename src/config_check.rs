@@ -0,0 +1,1099 @@
+//! Startup validation of the configuration
+//!
+//! [`validate`] checks every setting whose value cannot work: unparseable URLs and durations,
+//! zero or out-of-range numbers, access list files that do not exist, and settings that only
+//! make sense together. It reports all of them at once rather than stopping at the first, so
+//! a broken deployment is fixed in one pass. The module runs it before connecting to the node
+//! and refuses to start on any violation; `blvm-governance check-config` runs it alone,
+//! without touching the network or the node's socket.
+
+use crate::config::{parse_duration, DigestMode, GovernanceConfig, WeightSource};
+use crate::digest::Cron;
+use crate::error::GovernanceError;
+use crate::ipc_metrics::IpcMethod;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Most retries of a webhook delivery.
+pub const MAX_WEBHOOK_RETRIES: u32 = 10;
+
+/// Most attempts of a request to the node.
+pub const MAX_REQUEST_ATTEMPTS: u32 = 10;
+
+/// Longest activation delay, about four years of blocks.
+pub const MAX_ACTIVATION_DELAY_BLOCKS: u64 = 210_000;
+
+/// A setting whose value cannot work.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Dotted path below `[governance]`, e.g. `ipc.request_timeout_secs`.
+    pub key: String,
+    pub problem: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "governance.{}: {}", self.key, self.problem)
+    }
+}
+
+#[derive(Default)]
+struct Violations(Vec<Violation>);
+
+impl Violations {
+    fn add(&mut self, key: &str, problem: impl Into<String>) {
+        self.0.push(Violation {
+            key: key.to_string(),
+            problem: problem.into(),
+        });
+    }
+
+    fn require(&mut self, ok: bool, key: &str, problem: &str) {
+        if !ok {
+            self.add(key, problem);
+        }
+    }
+
+    fn positive(&mut self, key: &str, value: u64) {
+        self.require(value > 0, key, "must be greater than 0");
+    }
+
+    /// `value` in `(0, max]`.
+    fn fraction_of(&mut self, key: &str, value: f64, max: f64) {
+        if value.is_nan() || value <= 0.0 || value > max {
+            self.add(
+                key,
+                format!("must be greater than 0 and at most {}, not {}", max, value),
+            );
+        }
+    }
+
+    fn file(&mut self, key: &str, path: Option<&Path>) {
+        if let Some(path) = path.filter(|p| !p.is_file()) {
+            self.add(key, format!("{} does not exist", path.display()));
+        }
+    }
+}
+
+/// Whether `target` is a Rust module path, `::`-separated identifiers.
+fn is_module_path(target: &str) -> bool {
+    target.split("::").all(|segment| {
+        let mut chars = segment.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// `url` an http or https URL with a host.
+fn check_http_url(key: &str, url: &str, found: &mut Violations) {
+    match reqwest::Url::parse(url) {
+        Err(e) => found.add(key, format!("not a URL: {}", e)),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => found.add(
+            key,
+            format!("scheme must be http or https, not {}", url.scheme()),
+        ),
+        Ok(url) => found.require(url.host().is_some(), key, "has no host"),
+    }
+}
+
+fn check_webhook(config: &GovernanceConfig, found: &mut Violations) {
+    if let Some(url) = &config.webhook_url {
+        check_http_url("webhook_url", url, found);
+    }
+    if config.webhook_secret.as_deref().is_some_and(str::is_empty) {
+        found.add(
+            "webhook_secret",
+            "is empty; remove it to send unsigned deliveries",
+        );
+    }
+    if config.webhook_secret.is_some() && config.webhook_url.is_none() {
+        found.add("webhook_secret", "is set but webhook_url is not");
+    }
+    if config.webhook_retry_count > MAX_WEBHOOK_RETRIES {
+        found.add(
+            "webhook_retry_count",
+            format!("must be at most {}", MAX_WEBHOOK_RETRIES),
+        );
+    }
+    if config.webhook_events.iter().any(|e| e.trim().is_empty()) {
+        found.add("webhook_events", "contains an empty event type");
+    }
+    if config
+        .webhook_network
+        .as_deref()
+        .is_some_and(|n| n.trim().is_empty())
+    {
+        found.add(
+            "webhook_network",
+            "is empty; remove it to deliver whatever the node's network",
+        );
+    }
+    if config.webhook_network.is_some() && config.webhook_url.is_none() {
+        found.add("webhook_network", "is set but webhook_url is not");
+    }
+    let alerts = &config.alerts;
+    if let Some(url) = &alerts.url {
+        check_http_url("alerts.url", url, found);
+        found.positive("alerts.window_secs", alerts.window_secs);
+        if alerts.rate_per_min.is_nan() || alerts.rate_per_min < 0.0 {
+            found.add("alerts.rate_per_min", "must be 0 (disabled) or more");
+        }
+        found.require(
+            alerts.count > 0 || alerts.rate_per_min > 0.0,
+            "alerts.count",
+            "and alerts.rate_per_min are both 0, so no alert can be raised",
+        );
+    }
+    if let Some(tier) = &config.governance_tier {
+        found.require(
+            matches!(tier.as_str(), "maintainer" | "contributor"),
+            "governance_tier",
+            "must be \"maintainer\" or \"contributor\"",
+        );
+    }
+}
+
+fn check_ipc(config: &GovernanceConfig, found: &mut Violations) {
+    let ipc = &config.ipc;
+    found.positive("ipc.request_timeout_secs", ipc.request_timeout_secs);
+    if !(1..=MAX_REQUEST_ATTEMPTS).contains(&ipc.request_attempts) {
+        found.add(
+            "ipc.request_attempts",
+            format!("must be between 1 and {}", MAX_REQUEST_ATTEMPTS),
+        );
+    }
+    found.positive("ipc.max_message_bytes", ipc.max_message_bytes as u64);
+    found.positive("ipc.max_in_flight", ipc.max_in_flight as u64);
+    if ipc.max_in_flight > 0 && ipc.reserved_interactive >= ipc.max_in_flight {
+        found.add(
+            "ipc.reserved_interactive",
+            "must be less than ipc.max_in_flight",
+        );
+    }
+    for (name, value) in &config.nodeapi_timeouts {
+        let key = format!("nodeapi_timeouts.{}", name);
+        if IpcMethod::from_name(name).is_none() {
+            found.add(&key, "not a node method");
+        }
+        match parse_duration(value) {
+            Some(timeout) if !timeout.is_zero() => {}
+            Some(_) => found.add(&key, "must be greater than 0"),
+            None => found.add(&key, format!("{:?} is not a duration (ms, s or m)", value)),
+        }
+    }
+
+    let reconnect = &config.reconnect;
+    found.positive("reconnect.initial_backoff_ms", reconnect.initial_backoff_ms);
+    found.positive("reconnect.max_backoff_secs", reconnect.max_backoff_secs);
+    if reconnect.initial_backoff_ms > reconnect.max_backoff_secs.saturating_mul(1000) {
+        found.add(
+            "reconnect.initial_backoff_ms",
+            "must not exceed reconnect.max_backoff_secs",
+        );
+    }
+    if config.heartbeat.interval_secs > 0 {
+        found.positive("heartbeat.max_missed", config.heartbeat.max_missed.into());
+    }
+    found.positive("events.capacity", config.events.capacity as u64);
+    found.positive("events.parallelism", config.events.parallelism as u64);
+    if config.backup.interval_secs > 0 {
+        found.positive("backup.retention", config.backup.retention as u64);
+    }
+    if let Some(level) = &config.log_forward.level {
+        found.require(
+            level.parse::<tracing::Level>().is_ok(),
+            "log_forward.level",
+            "must be one of error, warn, info, debug or trace",
+        );
+        found.positive(
+            "log_forward.max_per_sec",
+            config.log_forward.max_per_sec.into(),
+        );
+        found.positive("log_forward.buffer", config.log_forward.buffer as u64);
+    }
+    if let Some(level) = &config.logging.level {
+        found.require(
+            crate::logging::LEVELS.contains(&level.as_str()),
+            "logging.level",
+            "must be one of off, error, warn, info, debug or trace",
+        );
+    }
+    for (target, level) in &config.logging.targets {
+        let key = format!("logging.targets.{}", target);
+        if !is_module_path(target) {
+            found.add(&key, "not a module path, e.g. blvm_governance::webhook");
+        }
+        found.require(
+            crate::logging::LEVELS.contains(&level.as_str()),
+            &key,
+            "must be one of off, error, warn, info, debug or trace",
+        );
+    }
+    if config.logging.dir.is_some() {
+        found.positive("logging.max_file_bytes", config.logging.max_file_bytes);
+    }
+    if let Some(listen) = &config.health.listen {
+        if let Err(e) = listen.parse::<std::net::SocketAddr>() {
+            found.add(
+                "health.listen",
+                format!("{:?} is not an address and port: {}", listen, e),
+            );
+        }
+        found.positive("health.stall_secs", config.health.stall_secs);
+    }
+    if let Some(token) = &config.health.actions_token {
+        let key = "health.actions_token";
+        found.require(
+            !token.is_empty(),
+            key,
+            "is empty; remove it to disable the endpoints",
+        );
+        found.require(
+            config.health.listen.is_some(),
+            key,
+            "is set but health.listen is not",
+        );
+        found.require(config.allow_actions, key, "is set but allow_actions is not");
+    }
+    found.require(
+        !config.health.queries || config.health.listen.is_some(),
+        "health.queries",
+        "is set but health.listen is not",
+    );
+    if config.health.feed {
+        found.require(
+            config.health.listen.is_some(),
+            "health.feed",
+            "is set but health.listen is not",
+        );
+        found.positive("health.feed_entries", config.health.feed_entries as u64);
+    }
+    if let Some(token) = &config.health.admin_token {
+        let key = "health.admin_token";
+        found.require(
+            !token.is_empty(),
+            key,
+            "is empty; remove it to disable the endpoints",
+        );
+        found.require(
+            config.health.listen.is_some(),
+            key,
+            "is set but health.listen is not",
+        );
+    }
+    found.positive(
+        "crash.report_timeout_secs",
+        config.crash.report_timeout_secs,
+    );
+    if config.clock.enabled {
+        found.positive("clock.samples", config.clock.samples as u64);
+    }
+    if config.error_reports.enabled {
+        found.positive("error_reports.batch_secs", config.error_reports.batch_secs);
+        found.positive(
+            "error_reports.max_batch",
+            config.error_reports.max_batch as u64,
+        );
+    }
+    if config.audit_log.enabled {
+        found.positive("audit_log.max_file_bytes", config.audit_log.max_file_bytes);
+    }
+    for (tier, thresholds) in &config.tally.tiers {
+        let percent = thresholds.approval_percent;
+        found.require(
+            (0.0..=100.0).contains(&percent),
+            &format!("tally.tiers.{}.approval_percent", tier),
+            "must be between 0 (disabled) and 100",
+        );
+    }
+    for (tier, thresholds) in &config.tally.weighted {
+        for (key, percent) in [
+            ("quorum_percent", thresholds.quorum_percent),
+            ("approval_percent", thresholds.approval_percent),
+        ] {
+            found.require(
+                (0.0..=100.0).contains(&percent),
+                &format!("tally.weighted.{}.{}", tier, key),
+                "must be between 0 (disabled) and 100",
+            );
+        }
+    }
+    found.require(
+        config.tally.weighted.is_empty() || config.tally.weights != WeightSource::Off,
+        "tally.weighted",
+        "requires tally.weights",
+    );
+    found.require(
+        config.tally.default_weight >= 0.0,
+        "tally.default_weight",
+        "must not be negative",
+    );
+    for (tier, window) in &config.deadlines.windows {
+        found.positive(&format!("deadlines.windows.{}", tier), *window);
+    }
+    found.require(
+        !config.deadlines.reminders.contains(&0),
+        "deadlines.reminders",
+        "must be above 0; voting_closed is sent at the deadline",
+    );
+    for (tier, delay) in &config.activation.delays {
+        found.require(
+            (1..=MAX_ACTIVATION_DELAY_BLOCKS).contains(delay),
+            &format!("activation.delays.{}", tier),
+            &format!(
+                "must be between 1 and {} blocks",
+                MAX_ACTIVATION_DELAY_BLOCKS
+            ),
+        );
+    }
+    for (proposal_id, rule) in &config.signaling.proposals {
+        let key = format!("signaling.proposals.{}", proposal_id);
+        found.require(
+            rule.bit <= 28,
+            &format!("{}.bit", key),
+            "must be a version bit from 0 to 28",
+        );
+        found.positive(&format!("{}.window_blocks", key), rule.window_blocks);
+        found.require(
+            rule.threshold_percent > 0.0 && rule.threshold_percent <= 100.0,
+            &format!("{}.threshold_percent", key),
+            "must be above 0 and at most 100",
+        );
+    }
+    found.positive(
+        "conflicts.merge_window_blocks",
+        config.conflicts.merge_window_blocks,
+    );
+    let adoption = &config.adoption;
+    if !adoption.proposals.is_empty() {
+        found.positive("adoption.window_blocks", adoption.window_blocks);
+    }
+    found.require(
+        adoption
+            .milestones_percent
+            .iter()
+            .all(|m| *m > 0.0 && *m <= 100.0),
+        "adoption.milestones_percent",
+        "must each be above 0 and at most 100",
+    );
+    for (proposal_id, rule) in &adoption.proposals {
+        let key = format!("adoption.proposals.{}", proposal_id);
+        found.require(
+            rule.bit.is_some() != rule.capability.is_some(),
+            &key,
+            "must set exactly one of bit and capability",
+        );
+        found.require(
+            rule.bit.unwrap_or(0) <= 28,
+            &format!("{}.bit", key),
+            "must be a version bit from 0 to 28",
+        );
+        found.require(
+            rule.capability.as_deref() != Some(""),
+            &format!("{}.capability", key),
+            "is empty",
+        );
+    }
+    let epochs = &config.epoch_summary;
+    found.require(
+        epochs.length_blocks == 0 || epochs.confirmations < epochs.length_blocks,
+        "epoch_summary.confirmations",
+        "must be below epoch_summary.length_blocks",
+    );
+    let budget = config.memory.budget_bytes;
+    if budget != 0 && budget < crate::memory::MIN_BUDGET_BYTES {
+        found.add(
+            "memory.budget_bytes",
+            format!(
+                "must be 0 (no budget) or at least {} bytes",
+                crate::memory::MIN_BUDGET_BYTES
+            ),
+        );
+    }
+}
+
+fn check_github(config: &GovernanceConfig, found: &mut Violations) {
+    let github = &config.github;
+    if let Some(secret) = &github.secret {
+        let key = "github.secret";
+        found.require(
+            !secret.is_empty(),
+            key,
+            "is empty; remove it to disable the receiver",
+        );
+        found.require(
+            config.health.listen.is_some(),
+            key,
+            "is set but health.listen is not",
+        );
+        found.require(
+            !github.repositories.is_empty(),
+            "github.repositories",
+            "must name at least one repository",
+        );
+    }
+    for repository in &github.repositories {
+        let parts: Vec<&str> = repository.split('/').collect();
+        if parts.len() != 2 || parts.iter().any(|part| part.is_empty()) {
+            found.add(
+                "github.repositories",
+                format!("{:?} is not owner/name", repository),
+            );
+        }
+    }
+    let prefixes = [
+        (
+            "github.proposal_label_prefix",
+            &github.proposal_label_prefix,
+        ),
+        ("github.tier_label_prefix", &github.tier_label_prefix),
+    ];
+    for (key, prefix) in prefixes {
+        found.require(!prefix.is_empty(), key, "must not be empty");
+    }
+}
+
+fn check_content(config: &GovernanceConfig, found: &mut Violations) {
+    let content = &config.content;
+    if content.verify && content.fetch_text {
+        found.positive("content.fetch_timeout_secs", content.fetch_timeout_secs);
+        found.positive("content.max_text_bytes", content.max_text_bytes);
+    }
+    found.require(
+        content.verify || !content.fetch_text,
+        "content.fetch_text",
+        "requires content.verify",
+    );
+}
+
+fn check_delegation(config: &GovernanceConfig, found: &mut Violations) {
+    let delegation = &config.delegation;
+    found.require(
+        delegation.enabled || !delegation.on_chain,
+        "delegation.on_chain",
+        "requires delegation.enabled",
+    );
+}
+
+fn check_anchor(config: &GovernanceConfig, found: &mut Violations) {
+    let anchor = &config.anchor;
+    if !anchor.enabled {
+        return;
+    }
+    found.require(
+        config.allow_actions,
+        "anchor.enabled",
+        "requires allow_actions",
+    );
+    found.positive("anchor.interval_blocks", anchor.interval_blocks);
+    found.positive(
+        "anchor.confirmation_target_blocks",
+        anchor.confirmation_target_blocks as u64,
+    );
+    found.positive("anchor.confirmations", anchor.confirmations);
+    found.require(
+        anchor.confirmation_timeout_blocks >= anchor.confirmations,
+        "anchor.confirmation_timeout_blocks",
+        "must be at least anchor.confirmations",
+    );
+    found.positive("anchor.max_attempts", anchor.max_attempts as u64);
+}
+
+fn check_digest(config: &GovernanceConfig, found: &mut Violations) {
+    let digest = &config.digest;
+    if digest.mode != DigestMode::Digest {
+        return;
+    }
+    found.require(
+        config.webhook_url.is_some(),
+        "digest.mode",
+        "is \"digest\" but webhook_url is not set",
+    );
+    match &digest.schedule {
+        Some(schedule) => {
+            if let Err(e) = schedule.parse::<Cron>() {
+                found.add("digest.schedule", e);
+            }
+        }
+        None => found.positive("digest.every_secs", digest.every_secs),
+    }
+}
+
+fn check_veto_escalation(config: &GovernanceConfig, found: &mut Violations) {
+    let bands = &config.veto_escalation.bands;
+    for band in bands {
+        found.fraction_of("veto_escalation.bands", *band, 100.0);
+    }
+    for (i, band) in bands.iter().enumerate() {
+        if bands[..i].contains(band) {
+            found.add("veto_escalation.bands", format!("lists {} twice", band));
+        }
+    }
+}
+
+fn check_registry(config: &GovernanceConfig, found: &mut Violations) {
+    let registry = &config.registry;
+    found.positive("registry.max_nodes", registry.max_nodes as u64);
+    found.fraction_of(
+        "registry.veto.threshold_percent",
+        registry.veto.threshold_percent,
+        100.0,
+    );
+    found.fraction_of(
+        "registry.validation.max_hashpower_percent",
+        registry.validation.max_hashpower_percent,
+        100.0,
+    );
+    found.fraction_of(
+        "registry.sybil.min_confidence",
+        registry.sybil.min_confidence,
+        1.0,
+    );
+    let cap = registry.sybil.cap_percentile;
+    found.require(
+        (0.0..=100.0).contains(&cap),
+        "registry.sybil.cap_percentile",
+        "must be between 0 and 100",
+    );
+    found.positive(
+        "registry.rate_limit.window_blocks",
+        registry.rate_limit.window_blocks,
+    );
+
+    let reputation = &registry.reputation;
+    let weights = [
+        ("longevity_weight", reputation.longevity_weight),
+        ("liveness_weight", reputation.liveness_weight),
+        ("stability_weight", reputation.stability_weight),
+        ("veto_record_weight", reputation.veto_record_weight),
+    ];
+    for (name, weight) in weights {
+        if !weight.is_finite() || weight < 0.0 {
+            found.add(
+                &format!("registry.reputation.{}", name),
+                "must be 0 or more",
+            );
+        }
+    }
+    if weights.iter().map(|(_, w)| w).sum::<f64>() <= 0.0 {
+        found.add(
+            "registry.reputation",
+            "at least one component weight must be greater than 0",
+        );
+    }
+
+    for step in &registry.decay.steps {
+        if !(0.0..=1.0).contains(&step.factor) {
+            found.add(
+                "registry.decay.steps",
+                format!(
+                    "factor after {} blocks must be between 0 and 1, not {}",
+                    step.after_blocks, step.factor
+                ),
+            );
+        }
+    }
+    if registry.epoch.length_blocks > 0 {
+        found.positive("registry.epoch.retention", registry.epoch.retention as u64);
+    }
+
+    let access = &registry.access;
+    found.file(
+        "registry.access.blocklist_path",
+        access.blocklist_path.as_deref(),
+    );
+    found.file(
+        "registry.access.allowlist_path",
+        access.allowlist_path.as_deref(),
+    );
+    found.require(
+        !access.allowlist_mode || access.allowlist_path.is_some(),
+        "registry.access.allowlist_mode",
+        "requires registry.access.allowlist_path",
+    );
+}
+
+/// Every setting in `config` whose value cannot work.
+pub fn validate(config: &GovernanceConfig) -> Vec<Violation> {
+    let mut found = Violations::default();
+    check_webhook(config, &mut found);
+    check_ipc(config, &mut found);
+    check_registry(config, &mut found);
+    check_veto_escalation(config, &mut found);
+    check_github(config, &mut found);
+    check_content(config, &mut found);
+    check_delegation(config, &mut found);
+    check_anchor(config, &mut found);
+    check_digest(config, &mut found);
+    found.0
+}
+
+/// `violations` of the configuration read from `path`, one per line.
+pub fn report(path: &Path, violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| format!("{}: {}", path.display(), v))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Fail with every violation in `config`, read from `path`, if there are any.
+pub fn ensure_valid(path: &Path, config: &GovernanceConfig) -> Result<(), GovernanceError> {
+    let violations = validate(config);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(GovernanceError::ConfigError(format!(
+        "{} invalid settings\n{}",
+        violations.len(),
+        report(path, &violations)
+    )))
+}
+
+/// [`GovernanceConfig::read`] the configuration, failing if it is not valid.
+pub fn read_valid(
+    path: &Path,
+    context: &HashMap<String, String>,
+) -> Result<GovernanceConfig, GovernanceError> {
+    let config = GovernanceConfig::read(path, context)?;
+    ensure_valid(path, &config)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(config: &GovernanceConfig) -> Vec<String> {
+        validate(config).into_iter().map(|v| v.key).collect()
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert_eq!(validate(&GovernanceConfig::default()), vec![]);
+        let config = GovernanceConfig {
+            webhook_url: Some("https://governance.example.com/webhook".to_string()),
+            webhook_secret: Some("secret".to_string()),
+            governance_tier: Some("maintainer".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_all_violations_reported() {
+        let mut config = GovernanceConfig {
+            webhook_url: Some("ftp://governance.example.com".to_string()),
+            webhook_retry_count: 100,
+            ..Default::default()
+        };
+        config.ipc.request_timeout_secs = 0;
+        config.ipc.reserved_interactive = config.ipc.max_in_flight;
+        config.registry.veto.threshold_percent = 0.0;
+        config.registry.sybil.min_confidence = 1.5;
+        config.registry.access.allowlist_mode = true;
+        config.health.listen = Some("localhost".to_string());
+        config.crash.report_timeout_secs = 0;
+        config.memory.budget_bytes = 4096;
+        config
+            .nodeapi_timeouts
+            .insert("get_block".to_string(), "soon".to_string());
+        config
+            .nodeapi_timeouts
+            .insert("get_blocks".to_string(), "5s".to_string());
+
+        assert_eq!(
+            keys(&config),
+            vec![
+                "webhook_url",
+                "webhook_retry_count",
+                "ipc.request_timeout_secs",
+                "ipc.reserved_interactive",
+                "nodeapi_timeouts.get_block",
+                "nodeapi_timeouts.get_blocks",
+                "health.listen",
+                "crash.report_timeout_secs",
+                "memory.budget_bytes",
+                "registry.veto.threshold_percent",
+                "registry.sybil.min_confidence",
+                "registry.access.allowlist_mode",
+            ]
+        );
+
+        let path = Path::new("/etc/governance.toml");
+        let error = ensure_valid(path, &config).unwrap_err().to_string();
+        assert!(error.contains("12 invalid settings"));
+        assert!(error.contains(
+            "/etc/governance.toml: governance.webhook_url: scheme must be http or https, not ftp"
+        ));
+    }
+
+    #[test]
+    fn test_log_targets() {
+        let mut config = GovernanceConfig::default();
+        for (target, level) in [
+            ("blvm_governance::webhook", "debug"),
+            ("reqwest", "off"),
+            ("blvm_governance::", "info"),
+            ("blvm-governance", "info"),
+            ("blvm_governance=debug", "info"),
+            ("blvm_governance::node_api", "verbose"),
+        ] {
+            config
+                .logging
+                .targets
+                .insert(target.to_string(), level.to_string());
+        }
+        let violations = validate(&config);
+        assert_eq!(
+            violations
+                .iter()
+                .map(|v| v.key.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "logging.targets.blvm-governance",
+                "logging.targets.blvm_governance::",
+                "logging.targets.blvm_governance::node_api",
+                "logging.targets.blvm_governance=debug",
+            ]
+        );
+        assert_eq!(
+            violations[2].to_string(),
+            "governance.logging.targets.blvm_governance::node_api: must be one of off, error, \
+             warn, info, debug or trace"
+        );
+    }
+
+    #[test]
+    fn test_alerts() {
+        let mut config = GovernanceConfig::default();
+        config.alerts.window_secs = 0;
+        config.alerts.count = 0;
+        assert_eq!(validate(&config), vec![]);
+
+        config.alerts.url = Some("pager.example.com/alerts".to_string());
+        config.alerts.rate_per_min = -1.0;
+        assert_eq!(
+            keys(&config),
+            vec![
+                "alerts.url",
+                "alerts.window_secs",
+                "alerts.rate_per_min",
+                "alerts.count"
+            ]
+        );
+
+        config.alerts.url = Some("https://pager.example.com/alerts".to_string());
+        config.alerts.window_secs = 60;
+        config.alerts.rate_per_min = 2.5;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_tally_thresholds() {
+        let mut config = GovernanceConfig::default();
+        let thresholds = |approval_percent| crate::config::TierThresholds {
+            quorum: 3,
+            approval_percent,
+        };
+        let expected = vec!["tally.tiers.emergency.approval_percent"];
+        config.tally.tiers = [("standard", 66.7), ("emergency", 150.0)]
+            .into_iter()
+            .map(|(tier, percent)| (tier.to_string(), thresholds(percent)))
+            .collect();
+        assert_eq!(keys(&config), expected);
+
+        let emergency = config.tally.tiers.get_mut("emergency").unwrap();
+        emergency.approval_percent = f64::NAN;
+        assert_eq!(keys(&config), expected);
+    }
+
+    #[test]
+    fn test_weighted_tally_thresholds() {
+        let mut config = GovernanceConfig::default();
+        config.tally.weighted.insert(
+            "core".to_string(),
+            crate::config::WeightThresholds {
+                quorum_percent: 120.0,
+                approval_percent: 66.7,
+            },
+        );
+        config.tally.default_weight = -1.0;
+        assert_eq!(
+            keys(&config),
+            vec![
+                "tally.weighted.core.quorum_percent",
+                "tally.weighted",
+                "tally.default_weight"
+            ]
+        );
+
+        config.tally.weights = WeightSource::Snapshot;
+        config.tally.default_weight = 0.0;
+        let core = config.tally.weighted.get_mut("core").unwrap();
+        core.quorum_percent = 20.0;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_deadlines() {
+        let mut config = GovernanceConfig::default();
+        config.deadlines.windows = [("standard", 2016), ("emergency", 0)]
+            .into_iter()
+            .map(|(tier, window)| (tier.to_string(), window))
+            .collect();
+        config.deadlines.reminders = vec![100, 0];
+        assert_eq!(
+            keys(&config),
+            vec!["deadlines.windows.emergency", "deadlines.reminders"]
+        );
+
+        config.deadlines.windows.remove("emergency");
+        config.deadlines.reminders = vec![100];
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_activation_delays() {
+        let mut config = GovernanceConfig::default();
+        config.activation.delays = [("core", 4032), ("standard", 0), ("emergency", 1_000_000)]
+            .into_iter()
+            .map(|(tier, delay)| (tier.to_string(), delay))
+            .collect();
+        assert_eq!(
+            keys(&config),
+            vec!["activation.delays.emergency", "activation.delays.standard"]
+        );
+
+        config.activation.delays.retain(|tier, _| tier == "core");
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_signaling_rules() {
+        let mut config = GovernanceConfig::default();
+        config.signaling.proposals.insert(
+            "42".to_string(),
+            crate::config::SignalingRule {
+                bit: 29,
+                window_blocks: 0,
+                threshold_percent: 0.0,
+            },
+        );
+        assert_eq!(
+            keys(&config),
+            vec![
+                "signaling.proposals.42.bit",
+                "signaling.proposals.42.window_blocks",
+                "signaling.proposals.42.threshold_percent"
+            ]
+        );
+
+        config
+            .signaling
+            .proposals
+            .insert("42".to_string(), Default::default());
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_adoption_rules() {
+        let mut config = GovernanceConfig::default();
+        config.adoption.window_blocks = 0;
+        config.adoption.milestones_percent = vec![50.0, 120.0];
+        config.adoption.proposals.insert(
+            "42".to_string(),
+            crate::config::AdoptionRule {
+                bit: Some(29),
+                capability: Some(String::new()),
+            },
+        );
+        assert_eq!(
+            keys(&config),
+            vec![
+                "adoption.window_blocks",
+                "adoption.milestones_percent",
+                "adoption.proposals.42",
+                "adoption.proposals.42.bit",
+                "adoption.proposals.42.capability"
+            ]
+        );
+
+        config.adoption = Default::default();
+        config.adoption.proposals.insert(
+            "42".to_string(),
+            crate::config::AdoptionRule {
+                bit: Some(2),
+                capability: None,
+            },
+        );
+        assert_eq!(validate(&config), vec![]);
+
+        config.conflicts.merge_window_blocks = 0;
+        assert_eq!(keys(&config), vec!["conflicts.merge_window_blocks"]);
+    }
+
+    #[test]
+    fn test_epoch_summary_confirmations() {
+        let mut config = GovernanceConfig::default();
+        config.epoch_summary.confirmations = 100;
+        // Disabled
+        assert_eq!(validate(&config), vec![]);
+
+        config.epoch_summary.length_blocks = 100;
+        assert_eq!(keys(&config), vec!["epoch_summary.confirmations"]);
+        config.epoch_summary.confirmations = 99;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_actions_token_needs_listener_and_actions() {
+        let mut config = GovernanceConfig::default();
+        config.health.actions_token = Some(String::new());
+        assert_eq!(
+            keys(&config),
+            vec![
+                "health.actions_token",
+                "health.actions_token",
+                "health.actions_token"
+            ]
+        );
+
+        config.health.actions_token = Some("t0k".to_string());
+        config.health.listen = Some("127.0.0.1:9180".to_string());
+        config.allow_actions = true;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_admin_token_and_queries_need_listener() {
+        let mut config = GovernanceConfig::default();
+        config.health.admin_token = Some(String::new());
+        config.health.queries = true;
+        assert_eq!(
+            keys(&config),
+            vec!["health.queries", "health.admin_token", "health.admin_token"]
+        );
+
+        config.health.admin_token = Some("t0k".to_string());
+        config.health.listen = Some("127.0.0.1:9180".to_string());
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_feed_needs_listener_and_entries() {
+        let mut config = GovernanceConfig::default();
+        config.health.feed = true;
+        config.health.feed_entries = 0;
+        assert_eq!(keys(&config), vec!["health.feed", "health.feed_entries"]);
+
+        config.health.listen = Some("127.0.0.1:9180".to_string());
+        config.health.feed_entries = 20;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_github_receiver() {
+        let mut config = GovernanceConfig::default();
+        config.github.secret = Some("s3cret".to_string());
+        config.github.tier_label_prefix = String::new();
+        assert_eq!(
+            keys(&config),
+            vec![
+                "github.secret",
+                "github.repositories",
+                "github.tier_label_prefix"
+            ]
+        );
+
+        config.health.listen = Some("127.0.0.1:9180".to_string());
+        config.github.repositories = vec!["org/governance".to_string(), "org".to_string()];
+        config.github.tier_label_prefix = "tier:".to_string();
+        assert_eq!(keys(&config), vec!["github.repositories"]);
+        config.github.repositories.pop();
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_content_fetch_needs_verify() {
+        let mut config = GovernanceConfig::default();
+        config.content.fetch_text = true;
+        config.content.fetch_timeout_secs = 0;
+        assert_eq!(keys(&config), vec!["content.fetch_text"]);
+
+        config.content.verify = true;
+        assert_eq!(keys(&config), vec!["content.fetch_timeout_secs"]);
+        config.content.fetch_timeout_secs = 10;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_delegation_on_chain_needs_enabled() {
+        let mut config = GovernanceConfig::default();
+        config.delegation.on_chain = true;
+        assert_eq!(keys(&config), vec!["delegation.on_chain"]);
+
+        config.delegation.enabled = true;
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_anchor_needs_actions() {
+        let mut config = GovernanceConfig::default();
+        config.anchor.enabled = true;
+        config.anchor.max_attempts = 0;
+        assert_eq!(keys(&config), vec!["anchor.enabled", "anchor.max_attempts"]);
+
+        config.allow_actions = true;
+        config.anchor.max_attempts = 3;
+        assert_eq!(validate(&config), vec![]);
+        config.anchor.confirmation_timeout_blocks = 0;
+        assert_eq!(keys(&config), vec!["anchor.confirmation_timeout_blocks"]);
+    }
+
+    #[test]
+    fn test_digest_schedule() {
+        let mut config = GovernanceConfig::default();
+        config.digest.every_secs = 0;
+        assert_eq!(validate(&config), vec![]);
+        config.digest.mode = DigestMode::Digest;
+        assert_eq!(keys(&config), vec!["digest.mode", "digest.every_secs"]);
+
+        config.webhook_url = Some("https://governance.example.com/webhook".to_string());
+        config.digest.schedule = Some("0 25 * * *".to_string());
+        assert_eq!(keys(&config), vec!["digest.schedule"]);
+        config.digest.schedule = Some("0 9 * * 1-5".to_string());
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn test_veto_escalation_bands() {
+        let mut config = GovernanceConfig::default();
+        config.veto_escalation.bands = vec![50.0, 75.0, 100.0];
+        assert_eq!(validate(&config), vec![]);
+        config.veto_escalation.bands = vec![0.0, 75.0, 75.0, 120.0];
+        assert_eq!(
+            keys(&config),
+            vec![
+                "veto_escalation.bands",
+                "veto_escalation.bands",
+                "veto_escalation.bands"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_access_list_files_must_exist() {
+        let missing = std::env::temp_dir().join(format!("blvm_missing_{}", std::process::id()));
+        let mut config = GovernanceConfig::default();
+        config.registry.access.blocklist_path = Some(missing.clone());
+        assert_eq!(keys(&config), vec!["registry.access.blocklist_path"]);
+
+        std::fs::write(&missing, "").unwrap();
+        assert_eq!(validate(&config), vec![]);
+        std::fs::remove_file(&missing).ok();
+    }
+}
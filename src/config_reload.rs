@@ -0,0 +1,523 @@
+//! Applying `config.toml` changes while running
+//!
+//! The module's configuration file (`config.toml` or the one given by `--config`; see
+//! [`crate::config`]) is re-read when it changes (checked every `config_reload_secs`), on
+//! SIGHUP, and on the `reload_config` API request. The new configuration is validated first
+//! (see [`crate::config_check`]); if it is not valid, or the registry cannot take it, nothing
+//! is applied and the current one stays in effect. Otherwise the webhook settings,
+//! `[governance.registry]`, the log forwarding level and rate, `[governance.alerts]`, and the
+//! local log level and `[governance.logging.targets]` are applied to the running components
+//! through their `reconfigure` methods and [`crate::logging::set_filter`], without dropping
+//! queued deliveries or registry state. Other sections are read when a connection is set up
+//! or when the module starts, and changes to them are reported as applying then. The data
+//! directory and socket path are given by the node when it spawns the module; changes to them
+//! are reported as needing a restart.
+//!
+//! Each reload is summarized in a [`ReloadSummary`], logged and included in the status
+//! reports sent to the node. The local log filter in effect is logged after it.
+
+use crate::alert::AlertMonitor;
+use crate::config::{GovernanceConfig, LoggingConfig};
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::log_forward::LogForwarder;
+use crate::systemd::Notifier;
+use crate::webhook::GovernanceWebhookClient;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn changed<T: Serialize>(current: &T, new: &T) -> bool {
+    serde_json::to_value(current).ok() != serde_json::to_value(new).ok()
+}
+
+/// Sections changed between `current` and `new` that are not applied while running:
+/// those read when a connection is set up, and those read only at startup.
+pub fn deferred_changes(
+    current: &GovernanceConfig,
+    new: &GovernanceConfig,
+) -> (Vec<&'static str>, Vec<&'static str>) {
+    let mut connection = Vec::new();
+    if changed(&current.backup, &new.backup) {
+        connection.push("backup");
+    }
+    if changed(&current.heartbeat, &new.heartbeat) {
+        connection.push("heartbeat");
+    }
+    if changed(&current.events, &new.events) {
+        connection.push("events");
+    }
+    if current.config_reload_secs != new.config_reload_secs {
+        connection.push("config_reload_secs");
+    }
+    if current.crash.isolate_handler_panics != new.crash.isolate_handler_panics {
+        connection.push("crash.isolate_handler_panics");
+    }
+    let mut restart = Vec::new();
+    if changed(&current.reconnect, &new.reconnect) {
+        restart.push("reconnect");
+    }
+    if changed(&current.shutdown, &new.shutdown) {
+        restart.push("shutdown");
+    }
+    if changed(&current.ipc, &new.ipc) {
+        restart.push("ipc");
+    }
+    if changed(&current.health, &new.health) {
+        restart.push("health");
+    }
+    if current.crash.report_timeout_secs != new.crash.report_timeout_secs {
+        restart.push("crash.report_timeout_secs");
+    }
+    if changed(&current.memory, &new.memory) {
+        restart.push("memory");
+    }
+    if changed(&current.clock, &new.clock) {
+        restart.push("clock");
+    }
+    if changed(&current.error_reports, &new.error_reports) {
+        restart.push("error_reports");
+    }
+    if changed(&current.audit_log, &new.audit_log) {
+        restart.push("audit_log");
+    }
+    // The level and targets apply while running
+    let output = |logging: &LoggingConfig| LoggingConfig {
+        level: None,
+        targets: Default::default(),
+        ..logging.clone()
+    };
+    if changed(&output(&current.logging), &output(&new.logging)) {
+        restart.push("logging");
+    }
+    if current.dry_run != new.dry_run || current.dry_run_files != new.dry_run_files {
+        restart.push("dry_run");
+    }
+    // The level and rate apply while running
+    let (log_forward, new_log_forward) = (&current.log_forward, &new.log_forward);
+    if log_forward.level.is_some() != new_log_forward.level.is_some()
+        || log_forward.buffer != new_log_forward.buffer
+    {
+        restart.push("log_forward");
+    }
+    (connection, restart)
+}
+
+/// Where the node told the module to keep its data and find its socket. Fixed for the life
+/// of the process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulePaths {
+    pub data_dir: PathBuf,
+    pub socket_path: PathBuf,
+}
+
+/// Outcome of one reload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReloadSummary {
+    /// Unix seconds.
+    pub timestamp: u64,
+    /// What asked for the reload: "file", "SIGHUP", or the module that sent `reload_config`.
+    pub trigger: String,
+    /// Sections applied to the running module.
+    pub applied: Vec<String>,
+    /// Changed sections that apply from the next connection.
+    pub next_connection: Vec<String>,
+    /// Changed settings that apply only after a restart.
+    pub restart_required: Vec<String>,
+    /// Why the new configuration was rejected; the previous one stays in effect.
+    pub error: Option<String>,
+}
+
+type Load = dyn Fn() -> Result<(GovernanceConfig, ModulePaths), GovernanceError> + Send + Sync;
+
+/// Re-reads the configuration and applies it to the components of one connection.
+pub struct ConfigReloader {
+    load: Box<Load>,
+    /// Configuration and paths in effect. Held for the whole of a reload, so reloads do not
+    /// interleave.
+    current: tokio::sync::Mutex<(GovernanceConfig, ModulePaths)>,
+    webhook: Arc<GovernanceWebhookClient>,
+    registry: Arc<EconomicNodeRegistry>,
+    log_forwarder: Option<Arc<LogForwarder>>,
+    alerts: Option<Arc<AlertMonitor>>,
+    /// `--log-level`, which wins over `[governance.logging] level`.
+    log_level: Option<String>,
+    systemd: Option<Arc<Notifier>>,
+    last: Mutex<Option<ReloadSummary>>,
+}
+
+impl ConfigReloader {
+    /// `load` reads and validates the configuration, and reads the paths given by the node;
+    /// `current` and `paths` are the ones in effect.
+    pub fn new(
+        load: impl Fn() -> Result<(GovernanceConfig, ModulePaths), GovernanceError>
+            + Send
+            + Sync
+            + 'static,
+        current: GovernanceConfig,
+        paths: ModulePaths,
+        webhook: Arc<GovernanceWebhookClient>,
+        registry: Arc<EconomicNodeRegistry>,
+    ) -> Self {
+        Self {
+            load: Box::new(load),
+            current: tokio::sync::Mutex::new((current, paths)),
+            webhook,
+            registry,
+            log_forwarder: None,
+            alerts: None,
+            log_level: None,
+            systemd: None,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Apply the log forwarding level and rate to `forwarder`.
+    pub fn with_log_forwarder(mut self, forwarder: Arc<LogForwarder>) -> Self {
+        self.log_forwarder = Some(forwarder);
+        self
+    }
+
+    /// Apply the alert thresholds and URL to `alerts`.
+    pub fn with_alerts(mut self, alerts: Arc<AlertMonitor>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Keep the local log level at `level` (`--log-level`) whatever the configuration says;
+    /// the targets still apply.
+    pub fn with_log_level(mut self, level: Option<String>) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// Tell systemd while a reload is in progress.
+    pub fn with_systemd(mut self, notifier: Arc<Notifier>) -> Self {
+        self.systemd = Some(notifier);
+        self
+    }
+
+    /// Summary of the last reload, if there has been one.
+    pub fn last(&self) -> Option<ReloadSummary> {
+        self.last.lock().unwrap().clone()
+    }
+
+    /// Re-read the configuration and apply what can be applied while running. `trigger` is
+    /// recorded in the summary.
+    pub async fn reload(&self, trigger: &str) -> ReloadSummary {
+        let mut current = self.current.lock().await;
+        if let Some(systemd) = &self.systemd {
+            systemd.reloading();
+        }
+        let mut summary = ReloadSummary {
+            timestamp: now(),
+            trigger: trigger.to_string(),
+            applied: Vec::new(),
+            next_connection: Vec::new(),
+            restart_required: Vec::new(),
+            error: None,
+        };
+        match (self.load)() {
+            Ok((new, paths)) => {
+                let (config, current_paths) = &*current;
+                let (connection, mut restart) = deferred_changes(config, &new);
+                if paths.data_dir != current_paths.data_dir {
+                    restart.push("data_dir");
+                }
+                if paths.socket_path != current_paths.socket_path {
+                    restart.push("socket_path");
+                }
+                match self.apply(config, &new).await {
+                    Ok(applied) => {
+                        summary.applied = applied.into_iter().map(String::from).collect();
+                        summary.next_connection =
+                            connection.into_iter().map(String::from).collect();
+                        summary.restart_required = restart.into_iter().map(String::from).collect();
+                        current.0 = new;
+                    }
+                    Err(e) => summary.error = Some(e.to_string()),
+                }
+            }
+            Err(e) => summary.error = Some(e.to_string()),
+        }
+        drop(current);
+        if let Some(systemd) = &self.systemd {
+            systemd.reloaded();
+        }
+        log(&summary);
+        if let Some(filter) = crate::logging::current_filter() {
+            info!("Log filter: {}", filter);
+        }
+        *self.last.lock().unwrap() = Some(summary.clone());
+        summary
+    }
+
+    /// Apply `new` to the running components. The registry goes first since it is the only
+    /// one that can refuse it; if it does, nothing has been changed.
+    async fn apply(
+        &self,
+        current: &GovernanceConfig,
+        new: &GovernanceConfig,
+    ) -> Result<Vec<&'static str>, GovernanceError> {
+        let mut applied = Vec::new();
+        if changed(&current.registry, &new.registry) {
+            self.registry.reconfigure(new.registry.clone()).await?;
+            applied.push("registry");
+        }
+        if self.webhook.reconfigure(new) {
+            applied.push("webhook");
+        }
+        if let Some(forwarder) = &self.log_forwarder {
+            if forwarder.reconfigure(&new.log_forward) {
+                applied.push("log_forward");
+            }
+        }
+        if let Some(alerts) = &self.alerts {
+            if alerts.reconfigure(&new.alerts) {
+                applied.push("alerts");
+            }
+        }
+        let (logging, new_logging) = (&current.logging, &new.logging);
+        if (logging.level != new_logging.level || logging.targets != new_logging.targets)
+            && crate::logging::set_filter(new_logging, self.log_level.as_deref()).is_some()
+        {
+            applied.push("logging");
+        }
+        Ok(applied)
+    }
+
+    /// Reload whenever `path` changes, checking every `interval_secs` (0 disables), and
+    /// whenever `hangup` is notified, until the task is aborted.
+    pub fn spawn(
+        self: &Arc<Self>,
+        path: PathBuf,
+        interval_secs: u64,
+        hangup: Arc<Notify>,
+    ) -> tokio::task::JoinHandle<()> {
+        let reloader = Arc::clone(self);
+        let mut interval = (interval_secs > 0)
+            .then(|| tokio::time::interval(std::time::Duration::from_secs(interval_secs)));
+        let mut last_modified = modified(&path);
+        tokio::spawn(async move {
+            loop {
+                let trigger = tokio::select! {
+                    _ = hangup.notified() => "SIGHUP",
+                    _ = tick(&mut interval) => {
+                        if modified(&path) == last_modified {
+                            continue;
+                        }
+                        info!("{} changed, reloading configuration", path.display());
+                        "file"
+                    }
+                };
+                last_modified = modified(&path);
+                reloader.reload(trigger).await;
+            }
+        })
+    }
+}
+
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn log(summary: &ReloadSummary) {
+    if let Some(error) = &summary.error {
+        warn!(
+            "Configuration reload ({}) rejected, keeping the current configuration: {}",
+            summary.trigger, error
+        );
+        return;
+    }
+    let list = |sections: &[String]| match sections {
+        [] => "none".to_string(),
+        sections => sections.join(", "),
+    };
+    info!(
+        "Configuration reloaded ({}): applied {}; from the next connection {}",
+        summary.trigger,
+        list(&summary.applied),
+        list(&summary.next_connection)
+    );
+    if !summary.restart_required.is_empty() {
+        warn!(
+            "Configuration reload ({}): {} changed; restart the module to apply",
+            summary.trigger,
+            summary.restart_required.join(", ")
+        );
+    }
+}
+
+/// Notify `hangup` on every SIGHUP until the process exits. Returns `None` where there is no
+/// SIGHUP.
+pub fn spawn_hangup_listener(hangup: Arc<Notify>) -> Option<tokio::task::JoinHandle<()>> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut signals = match signal(SignalKind::hangup()) {
+            Ok(signals) => signals,
+            Err(e) => {
+                warn!("Cannot listen for SIGHUP: {}", e);
+                return None;
+            }
+        };
+        Some(tokio::spawn(async move {
+            while signals.recv().await.is_some() {
+                info!("Received SIGHUP, reloading configuration");
+                hangup.notify_one();
+            }
+        }))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = hangup;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockNodeApi;
+
+    #[test]
+    fn test_deferred_changes() {
+        let current = GovernanceConfig::default();
+        let mut new = current.clone();
+        new.webhook_url = Some("http://localhost:8080/webhook".to_string());
+        new.registry.veto.threshold_percent = 10.0;
+        new.alerts.url = Some("http://localhost:9093/alerts".to_string());
+        new.alerts.count = 3;
+        assert_eq!(deferred_changes(&current, &new), (vec![], vec![]));
+
+        new.events.capacity += 1;
+        new.reconnect.max_backoff_secs += 1;
+        new.log_forward.max_per_sec += 1;
+        assert_eq!(
+            deferred_changes(&current, &new),
+            (vec!["events"], vec!["reconnect"])
+        );
+        new.log_forward.level = Some("warn".to_string());
+        assert_eq!(
+            deferred_changes(&current, &new).1,
+            vec!["reconnect", "log_forward"]
+        );
+
+        // The local filter applies while running, the output only after a restart
+        new.logging.level = Some("debug".to_string());
+        new.logging
+            .targets
+            .insert("blvm_governance::webhook".to_string(), "trace".to_string());
+        assert_eq!(
+            deferred_changes(&current, &new).1,
+            vec!["reconnect", "log_forward"]
+        );
+        new.logging.console = false;
+        assert_eq!(
+            deferred_changes(&current, &new).1,
+            vec!["reconnect", "logging", "log_forward"]
+        );
+
+        new.crash.isolate_handler_panics = true;
+        new.crash.report_timeout_secs = 5;
+        new.memory.budget_bytes = crate::memory::MIN_BUDGET_BYTES;
+        new.clock.use_block_time = true;
+        new.error_reports.repeat_secs = 60;
+        new.audit_log.enabled = true;
+        assert_eq!(
+            deferred_changes(&current, &new),
+            (
+                vec!["events", "crash.isolate_handler_panics"],
+                vec![
+                    "reconnect",
+                    "crash.report_timeout_secs",
+                    "memory",
+                    "clock",
+                    "error_reports",
+                    "audit_log",
+                    "logging",
+                    "log_forward"
+                ]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_reload_keeps_configuration() {
+        let config = GovernanceConfig::default();
+        let paths = ModulePaths {
+            data_dir: PathBuf::from("/var/lib/governance"),
+            socket_path: PathBuf::from("/run/blvm/node.sock"),
+        };
+        let node_api = Arc::new(MockNodeApi::new(100));
+        let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+        let registry = Arc::new(
+            EconomicNodeRegistry::new(config.registry.clone(), node_api)
+                .await
+                .unwrap(),
+        );
+        let next: Arc<Mutex<(GovernanceConfig, ModulePaths)>> =
+            Arc::new(Mutex::new((config.clone(), paths.clone())));
+        let reloader = ConfigReloader::new(
+            {
+                let next = Arc::clone(&next);
+                move || Ok(next.lock().unwrap().clone())
+            },
+            config.clone(),
+            paths.clone(),
+            Arc::clone(&webhook),
+            Arc::clone(&registry),
+        );
+
+        // The access list file is missing, so the registry refuses the whole configuration
+        let mut rejected = config.clone();
+        rejected.webhook_url = Some("http://localhost:8080/webhook".to_string());
+        rejected.registry.veto.threshold_percent = 10.0;
+        rejected.registry.access.blocklist_path = Some(PathBuf::from("/nonexistent/blocklist"));
+        next.lock().unwrap().0 = rejected;
+        let summary = reloader.reload("SIGHUP").await;
+        assert!(summary.error.is_some());
+        assert!(summary.applied.is_empty());
+        assert_eq!(webhook.webhook_url(), None);
+        assert_eq!(
+            registry.config().veto.threshold_percent,
+            config.registry.veto.threshold_percent
+        );
+
+        let mut accepted = config.clone();
+        accepted.webhook_url = Some("http://localhost:8080/webhook".to_string());
+        accepted.registry.veto.threshold_percent = 10.0;
+        *next.lock().unwrap() = (
+            accepted,
+            ModulePaths {
+                data_dir: PathBuf::from("/srv/governance"),
+                ..paths
+            },
+        );
+        let summary = reloader.reload("file").await;
+        assert_eq!(summary.error, None);
+        assert_eq!(summary.applied, vec!["registry", "webhook"]);
+        assert_eq!(summary.restart_required, vec!["data_dir"]);
+        assert_eq!(registry.config().veto.threshold_percent, 10.0);
+        assert_eq!(reloader.last(), Some(summary));
+    }
+}
@@ -0,0 +1,282 @@
+//! Starter configuration file
+//!
+//! `blvm-governance init-config` writes [`render`]: every setting under `[governance]`, in the
+//! order and sections of [`GovernanceConfig`], each after its description, type and default.
+//! Settings with a value by default are set to it; the rest are written commented out. The
+//! descriptions are the doc comments of the configuration structs in `config.rs`, extracted
+//! by the build script, and the values are `GovernanceConfig::default()` serialized, so the
+//! file cannot fall behind the code: the tests check that every setting is in it with a
+//! description and a type, and that it reads back as the defaults.
+
+use crate::config::{
+    AmendedVotes, DigestClock, DigestMode, DryRun, GovernanceConfig, LogFormat, OverflowPolicy,
+    WebhookContentType, WeightSource,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A field of a configuration struct.
+pub(crate) struct Field {
+    /// Struct it is declared in.
+    owner: &'static str,
+    name: &'static str,
+    /// Rust type, as written.
+    ty: &'static str,
+    /// Doc comment, one line per line.
+    doc: &'static str,
+}
+
+include!(concat!(env!("OUT_DIR"), "/config_docs.rs"));
+
+/// The starter configuration file.
+pub fn render() -> String {
+    render_with(false)
+}
+
+/// With `uncomment`, settings without a default are written set to a placeholder.
+fn render_with(uncomment: bool) -> String {
+    let defaults = match toml::Value::try_from(GovernanceConfig::default()) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("the configuration serializes to a table"),
+    };
+    let mut out = format!(
+        "# blvm-governance {} configuration, written by `blvm-governance init-config`.\n\
+         #\n\
+         # Every setting is listed with its default. Settings without one are commented out;\n\
+         # uncomment and set them to use them. `blvm-governance check-config` validates the file.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    section(
+        &mut out,
+        "governance",
+        "GovernanceConfig",
+        &defaults,
+        uncomment,
+    );
+    out
+}
+
+fn fields(owner: &str) -> impl Iterator<Item = &'static Field> + '_ {
+    FIELDS.iter().filter(move |f| f.owner == owner)
+}
+
+/// Whether `ty` is a configuration struct of its own, written as a table.
+fn is_struct(ty: &str) -> bool {
+    fields(ty).next().is_some()
+}
+
+fn comment(out: &mut String, text: &str) {
+    for line in text.lines() {
+        if line.is_empty() {
+            out.push_str("#\n");
+        } else {
+            let _ = writeln!(out, "# {}", line);
+        }
+    }
+}
+
+fn section(out: &mut String, path: &str, owner: &str, values: &toml::Table, uncomment: bool) {
+    let _ = writeln!(out, "\n[{}]", path);
+    let (tables, settings): (Vec<&Field>, Vec<&Field>) =
+        fields(owner).partition(|f| is_struct(f.ty));
+    for field in settings {
+        out.push('\n');
+        comment(out, field.doc);
+        let kind = describe(field.ty).unwrap_or_else(|| field.ty.to_string());
+        match values.get(field.name) {
+            Some(value) => {
+                let _ = writeln!(out, "# Type: {}. Default: {}.", kind, value_text(value));
+                let _ = writeln!(out, "{} = {}", field.name, value_text(value));
+            }
+            None => {
+                let _ = writeln!(out, "# Type: {}. Default: unset.", kind);
+                let prefix = if uncomment { "" } else { "# " };
+                let _ = writeln!(out, "{}{} = {}", prefix, field.name, placeholder(field.ty));
+            }
+        }
+    }
+    for field in tables {
+        let empty = toml::Table::new();
+        let values = match values.get(field.name) {
+            Some(toml::Value::Table(table)) => table,
+            _ => &empty,
+        };
+        out.push('\n');
+        comment(out, field.doc);
+        section(
+            out,
+            &format!("{}.{}", path, field.name),
+            field.ty,
+            values,
+            uncomment,
+        );
+    }
+}
+
+fn value_text(value: &toml::Value) -> String {
+    match value {
+        toml::Value::Table(table) if table.is_empty() => "{}".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// `ty` with its path and generic arguments split off: `("Option", "String")` for
+/// `Option<String>`, `("u64", "")` for `u64`.
+fn split(ty: &str) -> (&str, &str) {
+    let (outer, args) = match ty.split_once('<') {
+        Some((outer, rest)) => (outer, rest.strip_suffix('>').unwrap_or(rest)),
+        None => (ty, ""),
+    };
+    (outer.rsplit("::").next().unwrap_or(outer), args)
+}
+
+/// The values of an enum setting as written in the file, e.g. `"full" or "json"`.
+fn one_of<T: Serialize>(variants: &[T]) -> String {
+    let values: Vec<String> = variants
+        .iter()
+        .filter_map(|v| toml::Value::try_from(v).ok())
+        .map(|v| v.to_string())
+        .collect();
+    match values.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// What a setting of Rust type `ty` is written as; `None` for a type this does not know.
+fn describe(ty: &str) -> Option<String> {
+    let (name, args) = split(ty);
+    Some(match name {
+        "Option" => format!("{}, optional", describe(args)?),
+        "Vec" => format!("list of {}s", describe(args)?),
+        "BTreeMap" | "HashMap" => {
+            let (_, value) = args.split_once(", ")?;
+            format!("table of {}s", describe(value)?)
+        }
+        "String" => "string".to_string(),
+        "PathBuf" => "path".to_string(),
+        "bool" => "boolean".to_string(),
+        "u32" | "u64" | "usize" => "integer".to_string(),
+        "f64" => "number".to_string(),
+        "LogFormat" => one_of(LogFormat::value_variants()),
+        "DryRun" => one_of(DryRun::value_variants()),
+        "OverflowPolicy" => one_of(&[
+            OverflowPolicy::Backpressure,
+            OverflowPolicy::DropLowPriority,
+        ]),
+        "WeightSource" => one_of(&[
+            WeightSource::Off,
+            WeightSource::Current,
+            WeightSource::Snapshot,
+        ]),
+        "AmendedVotes" => one_of(&[
+            AmendedVotes::Keep,
+            AmendedVotes::ResetOnContent,
+            AmendedVotes::Reset,
+        ]),
+        "DigestMode" => one_of(&[DigestMode::Event, DigestMode::Digest]),
+        "DigestClock" => one_of(&[DigestClock::Wall, DigestClock::Block]),
+        "WebhookContentType" => one_of(&[
+            WebhookContentType::Json,
+            WebhookContentType::Cbor,
+            WebhookContentType::Msgpack,
+        ]),
+        _ if is_struct(name) => "table".to_string(),
+        _ => return None,
+    })
+}
+
+/// Value written for a setting without a default, commented out.
+fn placeholder(ty: &str) -> &'static str {
+    let (name, args) = split(ty);
+    match (name, split(args).0) {
+        ("Option", "bool") => "false",
+        ("Option", "u32" | "u64" | "usize") => "0",
+        ("Option", "f64") => "0.0",
+        _ => "\"\"",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether both tables have the same keys, nested tables included.
+    fn same_keys(a: &toml::Table, b: &toml::Table, path: &str) {
+        let keys = |t: &toml::Table| t.keys().cloned().collect::<Vec<_>>();
+        let (mut ka, mut kb) = (keys(a), keys(b));
+        ka.sort();
+        kb.sort();
+        assert_eq!(ka, kb, "keys of [{}]", path);
+        for (key, value) in a {
+            if let (toml::Value::Table(x), Some(toml::Value::Table(y))) = (value, b.get(key)) {
+                same_keys(x, y, &format!("{}.{}", path, key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_template_reads_back_as_the_defaults() {
+        let text = render();
+        let config = GovernanceConfig::from_toml(&text).unwrap();
+        assert_eq!(
+            toml::Value::try_from(&config).unwrap(),
+            toml::Value::try_from(GovernanceConfig::default()).unwrap()
+        );
+        let document: toml::Table = text.parse().unwrap();
+        let section = document["governance"].as_table().unwrap();
+        assert!(crate::config::unknown_keys(section).is_empty());
+        assert!(crate::config_check::validate(&config).is_empty());
+    }
+
+    #[test]
+    fn test_every_setting_is_listed_and_described() {
+        let document: toml::Table = render_with(true).parse().unwrap();
+        same_keys(
+            document["governance"].as_table().unwrap(),
+            &crate::config::schema(),
+            "governance",
+        );
+
+        let mut owners = vec!["GovernanceConfig"];
+        while let Some(owner) = owners.pop() {
+            for field in fields(owner) {
+                assert!(
+                    !field.doc.trim().is_empty(),
+                    "{}.{} has no doc comment",
+                    owner,
+                    field.name
+                );
+                assert!(
+                    describe(field.ty).is_some(),
+                    "{}.{}: no description of type {}",
+                    owner,
+                    field.name,
+                    field.ty
+                );
+                if is_struct(field.ty) {
+                    owners.push(field.ty);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_describe_types() {
+        assert_eq!(describe("Option<String>").unwrap(), "string, optional");
+        assert_eq!(describe("Vec<u32>").unwrap(), "list of integers");
+        assert_eq!(
+            describe("std::collections::BTreeMap<String, String>").unwrap(),
+            "table of strings"
+        );
+        assert_eq!(describe("DryRun").unwrap(), "false, true or \"all\"");
+        assert_eq!(
+            describe("OverflowPolicy").unwrap(),
+            "\"backpressure\" or \"drop_low_priority\""
+        );
+        assert_eq!(describe("Vec<DecayStep>").unwrap(), "list of tables");
+        assert!(describe("Duration").is_none());
+    }
+}
@@ -0,0 +1,317 @@
+//! Conflicts between proposals
+//!
+//! Two proposals can be mutually exclusive, e.g. both changing the same parameter, so that
+//! merging both would be incoherent. A proposal declares it in the node's record of it (see
+//! [`ProposalDetails`]): a `conflict_domain`, the parameter or rule it changes, conflicts with
+//! every other proposal of the same domain, and `conflicts_with` lists proposals it conflicts
+//! with by id. Either side declaring it is enough.
+//!
+//! The proposal store (see [`crate::proposals`]) keeps the declarations and the conflicts
+//! found as a [`ConflictGraph`], stored with the proposals. When a proposal is created, or its
+//! record looked up again, its conflicts with open proposals and with those merged within
+//! `[governance.conflicts] merge_window_blocks` are flagged. The tally, deadline and
+//! activation notifications of a proposal carry the ids of those it conflicts with as
+//! `conflicts`.
+//!
+//! When a proposal is merged while one it conflicts with was merged within the window, the
+//! conflict is sent to the webhook as `proposal_conflict`, once. A proposal rejected or
+//! expired resolves its conflicts: they are cleared from the graph.
+//!
+//! The graph is served by the `get_conflicts` API method and written by the
+//! `export-conflicts` subcommand.
+
+use crate::node_api::ProposalDetails;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Event type of the webhook alert.
+pub const CONFLICT_EVENT: &str = "proposal_conflict";
+
+/// What a proposal declares it conflicts with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Declaration {
+    pub domain: Option<String>,
+    pub conflicts_with: Vec<String>,
+}
+
+impl Declaration {
+    /// The declaration in the node's record of a proposal.
+    pub fn of(details: &ProposalDetails) -> Self {
+        Self {
+            domain: details.conflict_domain.clone().filter(|d| !d.is_empty()),
+            conflicts_with: details.conflicts_with.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domain.is_none() && self.conflicts_with.is_empty()
+    }
+}
+
+/// Why two proposals conflict. Tagged with `kind` in JSON; stored untagged, since bincode
+/// cannot read an internally tagged enum back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// Both change `domain`.
+    Domain { domain: String },
+    /// One lists the other in `conflicts_with`.
+    Declared,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Reason", tag = "kind", rename_all = "snake_case")]
+enum TaggedReason {
+    Domain { domain: String },
+    Declared,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Reason", rename_all = "snake_case")]
+enum StoredReason {
+    Domain { domain: String },
+    Declared,
+}
+
+impl Serialize for Reason {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            TaggedReason::serialize(self, serializer)
+        } else {
+            StoredReason::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Reason {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            TaggedReason::deserialize(deserializer)
+        } else {
+            StoredReason::deserialize(deserializer)
+        }
+    }
+}
+
+/// A conflict between two proposals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// The two proposals, in id order.
+    pub proposals: [String; 2],
+    pub reason: Reason,
+    /// Chain tip when the conflict was found.
+    pub detected_height: Option<u64>,
+    /// Whether `proposal_conflict` was sent for both being merged.
+    pub alerted: bool,
+}
+
+impl Conflict {
+    fn new(a: &str, b: &str, reason: Reason, height: Option<u64>) -> Self {
+        let mut proposals = [a.to_string(), b.to_string()];
+        proposals.sort();
+        Self {
+            proposals,
+            reason,
+            detected_height: height,
+            alerted: false,
+        }
+    }
+
+    /// The other proposal, if `proposal_id` is one of the two.
+    pub fn partner(&self, proposal_id: &str) -> Option<&str> {
+        match &self.proposals {
+            [a, b] if a == proposal_id => Some(b),
+            [a, b] if b == proposal_id => Some(a),
+            _ => None,
+        }
+    }
+}
+
+/// The declarations of proposals and the conflicts found between them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConflictGraph {
+    /// Declarations, by proposal id; proposals declaring nothing are left out.
+    pub declarations: BTreeMap<String, Declaration>,
+    /// Conflicts in the order they were found.
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictGraph {
+    /// Why `a` and `b` conflict by their declarations, if they do.
+    pub fn reason(&self, a: &str, b: &str) -> Option<Reason> {
+        let lists = |of: &str, other: &str| {
+            self.declarations
+                .get(of)
+                .is_some_and(|d| d.conflicts_with.iter().any(|id| id == other))
+        };
+        if lists(a, b) || lists(b, a) {
+            return Some(Reason::Declared);
+        }
+        let domain = |of: &str| self.declarations.get(of)?.domain.as_ref();
+        match (domain(a), domain(b)) {
+            (Some(domain), Some(other)) if domain == other => Some(Reason::Domain {
+                domain: domain.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The conflict between `a` and `b`, if one was found.
+    pub fn between(&self, a: &str, b: &str) -> Option<&Conflict> {
+        self.conflicts.iter().find(|c| c.partner(a) == Some(b))
+    }
+
+    /// Record `declaration` as `proposal_id`'s, replacing the one before, and find its
+    /// conflicts with `candidates` at `height`. Conflicts its new declaration no longer makes
+    /// are dropped. Returns the conflicts found that were not known.
+    pub fn declare(
+        &mut self,
+        proposal_id: &str,
+        declaration: Declaration,
+        candidates: &[String],
+        height: Option<u64>,
+    ) -> Vec<Conflict> {
+        if declaration.is_empty() {
+            self.declarations.remove(proposal_id);
+        } else {
+            self.declarations
+                .insert(proposal_id.to_string(), declaration);
+        }
+        let conflicts = std::mem::take(&mut self.conflicts);
+        self.conflicts = conflicts
+            .into_iter()
+            .filter(|c| match c.partner(proposal_id) {
+                Some(other) => self.reason(proposal_id, other).is_some(),
+                None => true,
+            })
+            .collect();
+        let mut found = Vec::new();
+        for other in candidates {
+            if other == proposal_id || self.between(proposal_id, other).is_some() {
+                continue;
+            }
+            if let Some(reason) = self.reason(proposal_id, other) {
+                let conflict = Conflict::new(proposal_id, other, reason, height);
+                self.conflicts.push(conflict.clone());
+                found.push(conflict);
+            }
+        }
+        found
+    }
+
+    /// Ids of the proposals `proposal_id` conflicts with, in id order.
+    pub fn partners(&self, proposal_id: &str) -> Vec<String> {
+        let mut partners: Vec<String> = self
+            .conflicts
+            .iter()
+            .filter_map(|c| c.partner(proposal_id))
+            .map(str::to_string)
+            .collect();
+        partners.sort();
+        partners
+    }
+
+    /// Forget `proposal_id`'s declaration and conflicts, once it is rejected or expired.
+    /// Returns the number of conflicts cleared.
+    pub fn clear(&mut self, proposal_id: &str) -> usize {
+        self.declarations.remove(proposal_id);
+        let before = self.conflicts.len();
+        self.conflicts.retain(|c| c.partner(proposal_id).is_none());
+        before - self.conflicts.len()
+    }
+}
+
+/// Columns of [`conflicts_csv`].
+pub const CONFLICT_COLUMNS: [&str; 6] = [
+    "proposal_id",
+    "conflicting_proposal_id",
+    "reason",
+    "domain",
+    "detected_height",
+    "alerted",
+];
+
+/// `conflicts` as CSV, one row per conflict.
+pub fn conflicts_csv<'a>(conflicts: impl Iterator<Item = &'a Conflict>) -> String {
+    crate::economic_nodes::export::csv_table(
+        &CONFLICT_COLUMNS,
+        conflicts.map(|c| {
+            let (reason, domain) = match &c.reason {
+                Reason::Domain { domain } => ("domain", domain.as_str()),
+                Reason::Declared => ("declared", ""),
+            };
+            vec![
+                c.proposals[0].clone(),
+                c.proposals[1].clone(),
+                reason.to_string(),
+                domain.to_string(),
+                c.detected_height.map(|h| h.to_string()).unwrap_or_default(),
+                c.alerted.to_string(),
+            ]
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declaration(domain: Option<&str>, conflicts_with: &[&str]) -> Declaration {
+        Declaration {
+            domain: domain.map(str::to_string),
+            conflicts_with: conflicts_with.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_declared_either_way_or_by_domain() {
+        let mut graph = ConflictGraph::default();
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(graph
+            .declare("1", declaration(Some("fees"), &[]), &[], Some(100))
+            .is_empty());
+        assert!(graph
+            .declare("2", declaration(None, &[]), &ids(&["1"]), Some(100))
+            .is_empty());
+        // Declared by 3 only, and the same domain as 1
+        let found = graph.declare(
+            "3",
+            declaration(Some("fees"), &["2"]),
+            &ids(&["1", "2"]),
+            Some(101),
+        );
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            graph.between("3", "1").unwrap().reason,
+            Reason::Domain {
+                domain: "fees".to_string()
+            }
+        );
+        assert_eq!(graph.between("2", "3").unwrap().reason, Reason::Declared);
+        assert_eq!(graph.partners("3"), ids(&["1", "2"]));
+        assert_eq!(graph.partners("2"), ids(&["3"]));
+
+        // A new declaration drops the conflicts it no longer makes
+        graph.declare("3", declaration(None, &["2"]), &ids(&["1", "2"]), Some(102));
+        assert_eq!(graph.partners("3"), ids(&["2"]));
+        assert_eq!(graph.clear("2"), 1);
+        assert!(graph.partners("3").is_empty());
+        assert!(!graph.declarations.contains_key("2"));
+    }
+
+    #[test]
+    fn test_reason_round_trips_as_json_and_stored() {
+        let domain = Reason::Domain {
+            domain: "fees".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&domain).unwrap(),
+            serde_json::json!({ "kind": "domain", "domain": "fees" })
+        );
+        for reason in [domain, Reason::Declared] {
+            let json = serde_json::to_string(&reason).unwrap();
+            assert_eq!(serde_json::from_str::<Reason>(&json).unwrap(), reason);
+            let stored = bincode::serialize(&reason).unwrap();
+            assert_eq!(bincode::deserialize::<Reason>(&stored).unwrap(), reason);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Proposal content checked against its on-chain commitment
+//!
+//! A proposal's full text is kept off-chain; its hash is committed in the `OP_RETURN` of a
+//! transaction. The node's record of the proposal carries the content hash, the txid of the
+//! commitment and, optionally, a URL to the full text ([`ProposalDetails`]). With
+//! `[governance.content] verify` set, the [`ContentVerifier`] fetches the commitment with
+//! [`NodeApiIpc::get_transaction`] when a proposal is created, and compares the hash it
+//! commits to with the content hash; with `fetch_text` as well, the full text is fetched and
+//! hashed (SHA-256) too.
+//!
+//! A commitment is an `OP_RETURN` output whose pushed data is [`COMMITMENT_MAGIC`], the
+//! version byte [`COMMITMENT_VERSION`] and the 32-byte content hash ([`commitment`]):
+//!
+//! ```text
+//! OP_RETURN <"BLVG" 0x01 <32-byte hash>>
+//! ```
+//!
+//! The outcome is a [`ContentCheck`]: verified, a mismatch, or unverified when there is
+//! nothing to compare (no content hash, no commitment txid, a transaction the node does not
+//! know or without a commitment output, or a full text that could not be fetched). The
+//! proposal store keeps it with the proposal, adds `content_verified` to the notifications it
+//! sends about the proposal, and sends `content_mismatch` on a mismatch (see
+//! [`crate::proposals`]). Unverified proposals are not failures.
+
+use crate::config::ContentConfig;
+use crate::error::{Chain, GovernanceError};
+use crate::node_api::{NodeApiIpc, ProposalDetails};
+use blvm_protocol::Hash;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Event sent to the webhook when a proposal's content does not match its commitment.
+pub const MISMATCH_EVENT: &str = "content_mismatch";
+
+/// First bytes of a commitment's data.
+pub const COMMITMENT_MAGIC: &[u8; 4] = b"BLVG";
+
+/// Version of the commitment format, after the magic.
+pub const COMMITMENT_VERSION: u8 = 0x01;
+
+const OP_RETURN: u8 = 0x6a;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+
+/// The data an `OP_RETURN` script pushes, concatenated, or `None` if `script` is not
+/// `OP_RETURN` followed only by well-formed pushes.
+pub fn op_return_data(script: &[u8]) -> Option<Vec<u8>> {
+    let (&OP_RETURN, mut rest) = script.split_first()? else {
+        return None;
+    };
+    let mut data = Vec::new();
+    while let Some((&opcode, tail)) = rest.split_first() {
+        let (len, tail) = match opcode {
+            0x00..=0x4b => (opcode as usize, tail),
+            OP_PUSHDATA1 => (*tail.first()? as usize, &tail[1..]),
+            OP_PUSHDATA2 => {
+                let len = u16::from_le_bytes(tail.get(..2)?.try_into().ok()?);
+                (len as usize, &tail[2..])
+            }
+            OP_PUSHDATA4 => {
+                let len = u32::from_le_bytes(tail.get(..4)?.try_into().ok()?);
+                (usize::try_from(len).ok()?, &tail[4..])
+            }
+            _ => return None,
+        };
+        data.extend_from_slice(tail.get(..len)?);
+        rest = &tail[len..];
+    }
+    Some(data)
+}
+
+/// The content hash `script` commits to, if it is a commitment.
+pub fn commitment(script: &[u8]) -> Option<Hash> {
+    let data = op_return_data(script)?;
+    let hash = data
+        .strip_prefix(COMMITMENT_MAGIC.as_slice())?
+        .strip_prefix(&[COMMITMENT_VERSION])?;
+    hash.try_into().ok()
+}
+
+/// The script of a commitment to `hash`.
+pub fn commitment_script(hash: &Hash) -> Vec<u8> {
+    let mut script = vec![OP_RETURN, (COMMITMENT_MAGIC.len() + 1 + hash.len()) as u8];
+    script.extend_from_slice(COMMITMENT_MAGIC);
+    script.push(COMMITMENT_VERSION);
+    script.extend_from_slice(hash);
+    script
+}
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentStatus {
+    /// The commitment, and the full text if fetched, match the content hash.
+    Verified,
+    /// They do not.
+    Mismatch,
+    /// There was nothing to compare.
+    Unverified,
+}
+
+/// A proposal's content checked against its commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentCheck {
+    pub status: ContentStatus,
+    /// Hex, as the node reports it.
+    pub content_hash: Option<String>,
+    pub commitment_txid: Option<String>,
+    /// Hex hash in the commitment, if one was found.
+    pub committed_hash: Option<String>,
+    /// Hex SHA-256 of the full text, if fetched.
+    pub text_hash: Option<String>,
+    /// Height of the commitment's block.
+    pub commitment_height: Option<u64>,
+    /// Why it is unverified or a mismatch.
+    pub detail: Option<String>,
+}
+
+impl ContentCheck {
+    fn new(details: &ProposalDetails) -> Self {
+        Self {
+            status: ContentStatus::Unverified,
+            content_hash: details.content_hash.clone(),
+            commitment_txid: details.commitment_txid.clone(),
+            committed_hash: None,
+            text_hash: None,
+            commitment_height: None,
+            detail: None,
+        }
+    }
+
+    fn unverified(mut self, detail: impl Into<String>) -> Self {
+        self.status = ContentStatus::Unverified;
+        self.detail = Some(detail.into());
+        self
+    }
+
+    fn mismatch(mut self, detail: impl Into<String>) -> Self {
+        self.status = ContentStatus::Mismatch;
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// `content_verified` of notifications: whether it matched, `None` if unverified.
+    pub fn verified(&self) -> Option<bool> {
+        match self.status {
+            ContentStatus::Verified => Some(true),
+            ContentStatus::Mismatch => Some(false),
+            ContentStatus::Unverified => None,
+        }
+    }
+}
+
+/// A 32-byte hash from hex, if it is one.
+fn parse_hash(hex_hash: &str) -> Option<Hash> {
+    hex::decode(hex_hash.trim()).ok()?.try_into().ok()
+}
+
+/// Checks proposals' content against their commitments.
+pub struct ContentVerifier {
+    config: ContentConfig,
+    node_api: NodeApiIpc,
+    client: reqwest::Client,
+}
+
+impl ContentVerifier {
+    pub fn new(config: ContentConfig, node_api: NodeApiIpc) -> Result<Self, GovernanceError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.fetch_timeout_secs.max(1)))
+            .build()
+            .map_err(|source| GovernanceError::HttpClient { source })?;
+        Ok(Self {
+            config,
+            node_api,
+            client,
+        })
+    }
+
+    /// Check the content of the proposal `details` describes.
+    pub async fn check(&self, details: &ProposalDetails) -> ContentCheck {
+        let check = self.check_commitment(details).await;
+        let check = match check.status {
+            ContentStatus::Verified if self.config.fetch_text => {
+                self.check_text(details, check).await
+            }
+            _ => check,
+        };
+        match check.status {
+            ContentStatus::Verified => {
+                info!("Content of proposal {} verified", details.proposal_id)
+            }
+            ContentStatus::Mismatch => warn!(
+                "Content of proposal {} does not match its commitment: {}",
+                details.proposal_id,
+                check.detail.as_deref().unwrap_or_default()
+            ),
+            ContentStatus::Unverified => info!(
+                "Content of proposal {} unverified: {}",
+                details.proposal_id,
+                check.detail.as_deref().unwrap_or_default()
+            ),
+        }
+        check
+    }
+
+    async fn check_commitment(&self, details: &ProposalDetails) -> ContentCheck {
+        let mut check = ContentCheck::new(details);
+        let Some(content_hash) = &details.content_hash else {
+            return check.unverified("no content hash");
+        };
+        let Some(expected) = parse_hash(content_hash) else {
+            return check.mismatch(format!(
+                "content hash {:?} is not 32 bytes of hex",
+                content_hash
+            ));
+        };
+        let Some(txid) = &details.commitment_txid else {
+            return check.unverified("no commitment transaction");
+        };
+        let Some(txid_bytes) = parse_hash(txid) else {
+            return check.unverified(format!("commitment txid {:?} is not 32 bytes of hex", txid));
+        };
+        let info = match self.node_api.get_transaction(&txid_bytes).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return check.unverified("commitment transaction not found"),
+            Err(e) => {
+                warn!(
+                    "Failed to fetch commitment {} of proposal {}: {}",
+                    txid,
+                    details.proposal_id,
+                    e.chain()
+                );
+                return check.unverified(format!("commitment transaction not fetched: {}", e));
+            }
+        };
+        check.commitment_height = Some(info.height);
+        let committed = info
+            .tx
+            .outputs
+            .iter()
+            .find_map(|output| commitment(&output.script_pubkey));
+        let Some(committed) = committed else {
+            return check.unverified("no commitment output in the transaction");
+        };
+        check.committed_hash = Some(hex::encode(committed));
+        if committed != expected {
+            return check.mismatch("commitment is to a different hash");
+        }
+        check.status = ContentStatus::Verified;
+        check
+    }
+
+    /// Fetch the full text of a proposal whose commitment matched, and compare its hash.
+    async fn check_text(&self, details: &ProposalDetails, mut check: ContentCheck) -> ContentCheck {
+        let Some(url) = &details.content_url else {
+            return check;
+        };
+        let text = match self.fetch(url).await {
+            Ok(text) => text,
+            Err(e) => return check.unverified(format!("full text not fetched: {}", e)),
+        };
+        let hash: Hash = Sha256::digest(&text).into();
+        check.text_hash = Some(hex::encode(hash));
+        if check.committed_hash.as_deref() != check.text_hash.as_deref() {
+            return check.mismatch("full text hashes to a different hash");
+        }
+        check
+    }
+
+    /// The body at `url`, no longer than `max_text_bytes`.
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Chain(&e).to_string())?;
+        let limit = self.config.max_text_bytes;
+        if response.content_length().is_some_and(|len| len > limit) {
+            return Err(format!("longer than {} bytes", limit));
+        }
+        let mut text = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| Chain(&e).to_string())? {
+            text.extend_from_slice(&chunk);
+            if text.len() as u64 > limit {
+                return Err(format!("longer than {} bytes", limit));
+            }
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(hash: &Hash) -> Vec<u8> {
+        let mut payload = COMMITMENT_MAGIC.to_vec();
+        payload.push(COMMITMENT_VERSION);
+        payload.extend_from_slice(hash);
+        payload
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        assert_eq!(op_return_data(&[0x6a]), Some(Vec::new()));
+        assert_eq!(op_return_data(&[0x6a, 0x02, 1, 2]), Some(vec![1, 2]));
+        // Several pushes, with each PUSHDATA opcode
+        let script = [
+            0x6a, 0x01, 1, 0x4c, 0x02, 2, 3, 0x4d, 0x01, 0x00, 4, 0x4e, 0x01, 0, 0, 0, 5, 0x00,
+        ];
+        assert_eq!(op_return_data(&script), Some(vec![1, 2, 3, 4, 5]));
+
+        // Not OP_RETURN
+        assert_eq!(op_return_data(&[0x51, 0x01, 1]), None);
+        assert_eq!(op_return_data(&[]), None);
+        // Pushes past the end
+        assert_eq!(op_return_data(&[0x6a, 0x03, 1, 2]), None);
+        assert_eq!(op_return_data(&[0x6a, 0x4c]), None);
+        assert_eq!(op_return_data(&[0x6a, 0x4d, 0x01]), None);
+        // Not a push
+        assert_eq!(op_return_data(&[0x6a, 0x01, 1, 0x75]), None);
+    }
+
+    #[test]
+    fn test_commitment() {
+        let hash = [0xab; 32];
+        let script = commitment_script(&hash);
+        assert_eq!(script[..2], [0x6a, 0x25]);
+        assert_eq!(commitment(&script), Some(hash));
+
+        // Pushed with PUSHDATA1, or split across pushes
+        let mut script = vec![0x6a, 0x4c, 37];
+        script.extend_from_slice(&payload(&hash));
+        assert_eq!(commitment(&script), Some(hash));
+        let mut script = vec![0x6a, 0x05];
+        script.extend_from_slice(&payload(&hash)[..5]);
+        script.push(0x20);
+        script.extend_from_slice(&hash);
+        assert_eq!(commitment(&script), Some(hash));
+
+        // Another magic or version, a short hash, trailing bytes
+        let mut other = payload(&hash);
+        other[0] = b'X';
+        let push = |data: &[u8]| [&[0x6a, data.len() as u8], data].concat();
+        assert_eq!(commitment(&push(&other)), None);
+        let mut other = payload(&hash);
+        other[4] = 0x02;
+        assert_eq!(commitment(&push(&other)), None);
+        assert_eq!(commitment(&push(&payload(&hash)[..36])), None);
+        assert_eq!(commitment(&push(&[payload(&hash), vec![0]].concat())), None);
+        // A P2WPKH output
+        assert_eq!(
+            commitment(&[[0x00, 0x14].as_slice(), &[0; 20]].concat()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verified() {
+        let details = ProposalDetails {
+            proposal_id: "1".to_string(),
+            title: String::new(),
+            description: None,
+            content_hash: None,
+            commitment_txid: None,
+            content_url: None,
+            tier: "standard".to_string(),
+            author: "alice".to_string(),
+            status: "open".to_string(),
+            created_height: 100,
+            conflict_domain: None,
+            conflicts_with: Vec::new(),
+        };
+        let check = ContentCheck::new(&details);
+        assert_eq!(check.clone().unverified("none").verified(), None);
+        assert_eq!(check.clone().mismatch("differs").verified(), Some(false));
+        let mut verified = check;
+        verified.status = ContentStatus::Verified;
+        assert_eq!(verified.verified(), Some(true));
+    }
+}
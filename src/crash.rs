@@ -0,0 +1,346 @@
+//! Panics
+//!
+//! The binary installs a panic hook ([`install`]) once it has read its configuration. On a
+//! panic it writes a [`CrashReport`] (message, location, thread, the event handler that was
+//! running, backtrace and build) to `crashes/` in the data directory, then, best effort and
+//! within `[governance.crash] report_timeout_secs` each, tells the node with a fatal status
+//! message and posts a [`CRASH_EVENT`] webhook, and exits with [`CRASH_EXIT_CODE`]. The node
+//! then sees why the module went away, rather than just the socket closing. Panics before
+//! the hook is installed print to stderr and exit as Rust does.
+//!
+//! Event handlers are run through [`run_handler`], which records which handler is running.
+//! With `isolate_handler_panics`, a panic in a handler is caught instead: the pipeline marks
+//! the handler unhealthy, stops passing events to it and keeps the others running (see
+//! [`crate::pipeline`]). That suits handlers that keep their state behind locks they do not
+//! hold across awaits; a panic that poisons a lock other tasks share still crashes the
+//! module when they next take it.
+
+use crate::build_info::BuildInfo;
+use crate::shutdown::{send_goodbye, ShutdownReason};
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::Cell;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+
+/// Exit code after a panic, so supervisors can tell a crash from a fatal error (1) and from
+/// a shutdown the node asked for.
+pub const CRASH_EXIT_CODE: i32 = 4;
+
+/// Event type of the webhook sent on a crash.
+pub const CRASH_EVENT: &str = "module_crash";
+
+thread_local! {
+    /// Event handler being polled on this thread, and whether its panics are isolated.
+    static RUNNING: Cell<Option<(&'static str, bool)>> = const { Cell::new(None) };
+}
+
+/// What a panic left behind, written to the crash directory and sent to the webhook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    /// Event handler that was running on the thread, if one was.
+    pub handler: Option<String>,
+    pub backtrace: String,
+    pub build: BuildInfo,
+}
+
+impl CrashReport {
+    /// A report of a panic with `message` at `location` on the current thread, capturing the
+    /// backtrace.
+    pub fn new(message: String, location: Option<String>) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            message,
+            location,
+            thread: std::thread::current().name().map(str::to_string),
+            handler: RUNNING.get().map(|(name, _)| name.to_string()),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            build: BuildInfo::current().clone(),
+        }
+    }
+
+    /// One line: where it panicked, in which handler, and the message.
+    pub fn summary(&self) -> String {
+        let mut summary = "panicked".to_string();
+        if let Some(handler) = &self.handler {
+            summary.push_str(&format!(" in event handler {}", handler));
+        }
+        if let Some(location) = &self.location {
+            summary.push_str(&format!(" at {}", location));
+        }
+        format!("{}: {}", summary, self.message)
+    }
+
+    /// Write the report as JSON to a new file in `dir`, created if missing.
+    pub fn write(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "crash-{}-{}.json",
+            self.timestamp,
+            std::process::id()
+        ));
+        let json = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// The text of a panic payload.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "non-string panic payload".to_string(),
+        },
+    }
+}
+
+/// Node API and webhook client of a connection.
+type Connection = (Arc<dyn NodeAPI>, Arc<GovernanceWebhookClient>);
+
+/// Where crash reports go and who is told about them.
+pub struct CrashReporter {
+    dir: PathBuf,
+    timeout: Duration,
+    /// Node API and webhook client of the current connection.
+    connection: Mutex<Option<Connection>>,
+}
+
+impl CrashReporter {
+    /// Reports go to files in `dir`; each notification gets at most `timeout`.
+    pub fn new(dir: PathBuf, timeout: Duration) -> Self {
+        Self {
+            dir,
+            timeout,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Notify the node and the webhook of this connection from now on.
+    pub fn connected(&self, node_api: Arc<dyn NodeAPI>, webhook: Arc<GovernanceWebhookClient>) {
+        *self.connection.lock().unwrap() = Some((node_api, webhook));
+    }
+
+    /// The connection to the node has dropped; reports only go to a file.
+    pub fn disconnected(&self) {
+        *self.connection.lock().unwrap() = None;
+    }
+
+    /// Write `report` to a file, and tell the node and the webhook of the current connection,
+    /// if there is one. Blocks until both have answered or timed out; they are sent from a
+    /// thread of its own, since this may be called on a runtime thread. Returns the file
+    /// written.
+    pub fn report(&self, report: &CrashReport) -> Option<PathBuf> {
+        let written = match report.write(&self.dir) {
+            Ok(path) => {
+                error!("Crash report written to {}", path.display());
+                Some(path)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to write crash report to {}: {}",
+                    self.dir.display(),
+                    e
+                );
+                None
+            }
+        };
+        // The panic may have happened while the lock was held
+        let connection = match self.connection.try_lock() {
+            Ok(connection) => connection.clone(),
+            Err(_) => None,
+        };
+        if let Some((node_api, webhook)) = connection {
+            let report = report.clone();
+            let timeout = self.timeout;
+            let sender = std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build();
+                match runtime {
+                    Ok(runtime) => runtime.block_on(notify(&report, node_api, webhook, timeout)),
+                    Err(e) => warn!("Cannot send the crash report: {}", e),
+                }
+            });
+            let _ = sender.join();
+        }
+        written
+    }
+}
+
+/// Tell the node the module is going away because of `report`, and post it to the webhook.
+async fn notify(
+    report: &CrashReport,
+    node_api: Arc<dyn NodeAPI>,
+    webhook: Arc<GovernanceWebhookClient>,
+    timeout: Duration,
+) {
+    let reason = ShutdownReason::Fatal {
+        code: CRASH_EXIT_CODE,
+        error: report.summary(),
+    };
+    let status = async {
+        match tokio::time::timeout(timeout, send_goodbye(node_api.as_ref(), &reason)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to tell the node about the crash: {}", e.chain()),
+            Err(_) => warn!("Node did not take the crash report within {:?}", timeout),
+        }
+    };
+    let posted = async {
+        if let Err(e) = webhook.notify_crash(report, timeout).await {
+            warn!(
+                "Failed to post the crash report to the webhook: {}",
+                e.chain()
+            );
+        }
+    };
+    tokio::join!(status, posted);
+}
+
+/// Report panics through `reporter` and exit with [`CRASH_EXIT_CODE`], after the hooks
+/// installed before (e.g. the one of [`crate::logging`]) have run. Panics in a handler run
+/// by [`run_handler`] with isolation are only logged; the handler's caller deals with them.
+pub fn install(reporter: Arc<CrashReporter>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = panic_message(info.payload());
+        let location = info.location().map(|l| l.to_string());
+        if let Some((handler, true)) = RUNNING.get() {
+            error!(
+                "Event handler {} panicked at {}: {}",
+                handler,
+                location.as_deref().unwrap_or("unknown location"),
+                message
+            );
+            return;
+        }
+        previous(info);
+        let report = CrashReport::new(message, location);
+        error!("Module crashed: {}", report.summary());
+        reporter.report(&report);
+        crate::logging::flush();
+        std::process::exit(CRASH_EXIT_CODE);
+    }));
+}
+
+/// Marks the thread as running a handler while its future is polled.
+struct Running<F> {
+    handler: &'static str,
+    isolate: bool,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Running<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the previous value when dropped, also while unwinding.
+        struct Restore(Option<(&'static str, bool)>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                RUNNING.set(self.0);
+            }
+        }
+        let _restore = Restore(RUNNING.replace(Some((self.handler, self.isolate))));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Run `future`, the work of event handler `handler`, recording it as the running handler
+/// for crash reports. With `isolate`, a panic in it is caught and returned as its message
+/// instead of crashing the module.
+pub async fn run_handler<F: Future>(
+    handler: &'static str,
+    isolate: bool,
+    future: F,
+) -> Result<F::Output, String> {
+    let running = Running {
+        handler,
+        isolate,
+        future: Box::pin(future),
+    };
+    if !isolate {
+        return Ok(running.await);
+    }
+    AssertUnwindSafe(running)
+        .catch_unwind()
+        .await
+        .map_err(|payload| panic_message(&*payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static");
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "formatted 42");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(7u8)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "non-string panic payload");
+    }
+
+    #[tokio::test]
+    async fn test_run_handler() {
+        let running = run_handler("webhook", false, async { RUNNING.get() }).await;
+        assert_eq!(running, Ok(Some(("webhook", false))));
+        assert_eq!(RUNNING.get(), None);
+
+        let panicked = run_handler("economic_nodes", true, async {
+            tokio::task::yield_now().await;
+            panic!("registry invariant broken");
+        })
+        .await;
+        assert_eq!(
+            panicked,
+            Err::<(), _>("registry invariant broken".to_string())
+        );
+        assert_eq!(RUNNING.get(), None);
+    }
+
+    #[test]
+    fn test_report_file() {
+        let dir = std::env::temp_dir().join(format!("blvm_crash_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let report = RUNNING.with(|running| {
+            running.set(Some(("proposals", false)));
+            let report = CrashReport::new("oops".to_string(), Some("src/x.rs:1:2".to_string()));
+            running.set(None);
+            report
+        });
+        assert_eq!(
+            report.summary(),
+            "panicked in event handler proposals at src/x.rs:1:2: oops"
+        );
+
+        let path = report.write(&dir.join("crashes")).unwrap();
+        assert_eq!(
+            path.file_name().unwrap().to_string_lossy(),
+            format!("crash-{}-{}.json", report.timestamp, std::process::id())
+        );
+        let read: CrashReport = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(read, report);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
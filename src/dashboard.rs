@@ -0,0 +1,377 @@
+//! Dashboard snapshot of the whole module
+//!
+//! `GET /dashboard` on the health listener (see [`crate::query`]) answers with a [`Dashboard`]:
+//! the node connection and chain tip, the open proposals with their tallies and deadlines, the
+//! recent merges and activations, the veto tallies nearing their threshold, registry totals by
+//! category, the health of webhook delivery and the last epoch summary. A status page renders
+//! from this one request instead of polling every query and joining the answers itself.
+//!
+//! Each list is sorted most pressing first and cut to the request's `limit`
+//! ([`DEFAULT_LIMIT`], at most [`MAX_LIMIT`]) with its full length alongside, so the answer
+//! stays small however much is stored. Readers must ignore fields they do not know;
+//! [`DASHBOARD_VERSION`] is bumped when a field is removed or changes meaning.
+//! `tests/dashboard_test.rs` compares the serialized dashboard with a snapshot, so a schema
+//! change is always deliberate.
+
+use crate::economic_nodes::metrics::{CategoryMetrics, RegistryMetrics};
+use crate::economic_nodes::tally::VetoTally;
+use crate::epoch_summary::EpochSummary;
+use crate::heartbeat::HeartbeatStatus;
+use crate::proposals::{GovernanceProposal, ProposalStatus, StoreSnapshot};
+use crate::tally::Tally;
+use crate::webhook::DeliveryCounts;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Version of the [`Dashboard`] schema.
+pub const DASHBOARD_VERSION: u32 = 1;
+
+/// Entries in each list without `limit`.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Most entries in each list.
+pub const MAX_LIMIT: usize = 100;
+
+/// Share of its threshold a veto tally reaches to be listed as nearing it.
+pub const NEAR_THRESHOLD: f64 = 0.5;
+
+/// The state of the module, as `GET /dashboard` answers it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dashboard {
+    pub version: u32,
+    /// Unix seconds it was assembled at.
+    pub generated_at: u64,
+    /// Revisions of the proposal store and the registry it was read at, as in the `ETag`s of
+    /// the other queries: two dashboards with the same revision show the same proposals,
+    /// tallies and registry.
+    pub revision: String,
+    pub node: NodeState,
+    /// Soonest deadline first.
+    pub open_proposals: Capped<OpenProposal>,
+    /// Merges and activations, most recent first.
+    pub recent: Capped<RecentChange>,
+    /// Veto tallies at [`NEAR_THRESHOLD`] of their threshold or more, closest to it first.
+    pub vetoes: Capped<VetoStanding>,
+    pub registry: RegistryTotals,
+    /// Absent when the webhook client is not known to the query.
+    pub delivery: Option<DeliveryHealth>,
+    /// The latest epoch summarized, if any.
+    pub last_epoch: Option<EpochSummary>,
+}
+
+/// A list cut to the request's `limit`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capped<T> {
+    /// Entries before the cut.
+    pub total: usize,
+    pub items: Vec<T>,
+}
+
+impl<T> Capped<T> {
+    /// The first `limit` of `items`.
+    pub fn of(mut items: Vec<T>, limit: usize) -> Self {
+        let total = items.len();
+        items.truncate(limit);
+        Self { total, items }
+    }
+}
+
+/// The connection to the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeState {
+    /// Whether the connection is alive: open and, with heartbeats, not found dead.
+    pub connected: bool,
+    /// Height of the chain tip, once one is known.
+    pub tip_height: Option<u64>,
+    /// Absent without heartbeats.
+    pub heartbeat: Option<HeartbeatStatus>,
+}
+
+/// An open proposal and how its vote stands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenProposal {
+    pub proposal_id: String,
+    pub tier: String,
+    pub status: ProposalStatus,
+    pub created_height: Option<u64>,
+    /// Height by which it must be voted on, if its tier has a voting window.
+    pub deadline_height: Option<u64>,
+    /// Blocks from the tip to the deadline, 0 once it has passed.
+    pub blocks_left: Option<u64>,
+    /// The votes cast.
+    pub tally: Tally,
+    /// With delegations, as last computed.
+    pub delegated: Option<Tally>,
+}
+
+/// What happened to a proposal recently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Merged,
+    Activated,
+}
+
+/// A merge or an activation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentChange {
+    pub proposal_id: String,
+    pub tier: String,
+    pub change: ChangeKind,
+    /// Chain tip when it happened.
+    pub height: u64,
+}
+
+/// A veto tally nearing or past its threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VetoStanding {
+    pub proposal_id: String,
+    pub veto_percent: f64,
+    pub threshold_percent: f64,
+    pub vetoing_weight: f64,
+    pub total_weight: f64,
+    pub crossed: bool,
+}
+
+/// Registry totals, in all and by category.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryTotals {
+    pub registered: usize,
+    pub active: usize,
+    pub expired: usize,
+    pub deactivated: usize,
+    pub archived: usize,
+    pub total_weight: f64,
+    /// By `node_type`, "unknown" for nodes without one.
+    pub by_category: BTreeMap<String, CategoryMetrics>,
+}
+
+impl RegistryTotals {
+    pub fn of(metrics: RegistryMetrics) -> Self {
+        Self {
+            registered: metrics.registered,
+            active: metrics.active,
+            expired: metrics.expired,
+            deactivated: metrics.deactivated,
+            archived: metrics.archived,
+            total_weight: metrics.total_weight,
+            by_category: metrics.by_category,
+        }
+    }
+}
+
+/// How webhook delivery is keeping up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryHealth {
+    /// Since the module started.
+    pub deliveries: DeliveryCounts,
+    /// Deliveries failed in a row since the last successful one.
+    pub consecutive_failures: u64,
+    /// Block notifications held back, waiting for confirmations.
+    pub deferred_blocks: usize,
+    /// Side effects recorded and not carried out yet (see [`crate::intent`]).
+    pub pending_intents: usize,
+}
+
+/// The open proposals of `snapshot`, soonest deadline first and those without one last, with
+/// their deadlines from `deadline` and the blocks left to them from `tip`.
+pub fn open_proposals(
+    snapshot: &StoreSnapshot,
+    deadline: impl Fn(&GovernanceProposal) -> Option<u64>,
+    tip: Option<u64>,
+    limit: usize,
+) -> Capped<OpenProposal> {
+    let mut open: Vec<OpenProposal> = snapshot
+        .proposals
+        .iter()
+        .filter(|p| p.is_open())
+        .map(|p| {
+            let deadline_height = deadline(p);
+            OpenProposal {
+                proposal_id: p.proposal_id.clone(),
+                tier: p.tier.clone(),
+                status: p.status,
+                created_height: p.created_height,
+                deadline_height,
+                blocks_left: deadline_height
+                    .zip(tip)
+                    .map(|(deadline, tip)| deadline.saturating_sub(tip)),
+                tally: p.tally(),
+                delegated: snapshot.tallies.get(&p.proposal_id).map(|t| t.delegated),
+            }
+        })
+        .collect();
+    open.sort_by(|a, b| {
+        let key = |p: &OpenProposal| {
+            (
+                p.deadline_height.is_none(),
+                p.deadline_height,
+                p.created_height,
+            )
+        };
+        (key(a), &a.proposal_id).cmp(&(key(b), &b.proposal_id))
+    });
+    Capped::of(open, limit)
+}
+
+/// The merges and activations of `snapshot`, most recent first.
+pub fn recent(snapshot: &StoreSnapshot, limit: usize) -> Capped<RecentChange> {
+    let merges = snapshot
+        .proposals
+        .iter()
+        .filter(|p| p.status == ProposalStatus::Merged)
+        .filter_map(|p| {
+            Some(RecentChange {
+                proposal_id: p.proposal_id.clone(),
+                tier: p.tier.clone(),
+                change: ChangeKind::Merged,
+                height: p.closed_height?,
+            })
+        });
+    let activations = snapshot.activations.iter().filter_map(|a| {
+        Some(RecentChange {
+            proposal_id: a.proposal_id.clone(),
+            tier: a.tier.clone(),
+            change: ChangeKind::Activated,
+            height: a.activated_height?,
+        })
+    });
+    let mut changes: Vec<RecentChange> = merges.chain(activations).collect();
+    // An activation follows the merge of its proposal, so it goes first at the same height
+    changes.sort_by(|a, b| {
+        (Reverse(a.height), Reverse(a.change), &a.proposal_id).cmp(&(
+            Reverse(b.height),
+            Reverse(b.change),
+            &b.proposal_id,
+        ))
+    });
+    Capped::of(changes, limit)
+}
+
+/// The tallies of `tallies` with vetoes at [`NEAR_THRESHOLD`] of their threshold or more,
+/// closest to it first.
+pub fn near_threshold(tallies: Vec<VetoTally>, limit: usize) -> Capped<VetoStanding> {
+    let share = |t: &VetoStanding| {
+        if t.threshold_percent > 0.0 {
+            t.veto_percent / t.threshold_percent
+        } else {
+            f64::INFINITY
+        }
+    };
+    let mut near: Vec<VetoStanding> = tallies
+        .into_iter()
+        .filter(|t| t.vetoing_weight > 0.0)
+        .map(|t| VetoStanding {
+            veto_percent: t.veto_percent(),
+            crossed: t.crossed(),
+            proposal_id: t.proposal_id,
+            threshold_percent: t.threshold_percent,
+            vetoing_weight: t.vetoing_weight,
+            total_weight: t.total_weight,
+        })
+        .filter(|t| t.crossed || share(t) >= NEAR_THRESHOLD)
+        .collect();
+    near.sort_by(|a, b| {
+        share(b)
+            .total_cmp(&share(a))
+            .then_with(|| a.proposal_id.cmp(&b.proposal_id))
+    });
+    Capped::of(near, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::Activation;
+
+    fn proposal(
+        id: &str,
+        status: ProposalStatus,
+        created: u64,
+        closed: Option<u64>,
+    ) -> GovernanceProposal {
+        GovernanceProposal {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+            status,
+            votes: Vec::new(),
+            author: None,
+            created_height: Some(created),
+            closed_height: closed,
+            milestones: Vec::new(),
+        }
+    }
+
+    fn veto(id: &str, vetoing_weight: f64) -> VetoTally {
+        VetoTally {
+            proposal_id: id.to_string(),
+            total_weight: 100.0,
+            vetoing_weight,
+            threshold_percent: 30.0,
+            commitment: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_lists_in_order_and_capped() {
+        let mut activated = Activation::new("3", "standard", 110, 10, 0);
+        activated.activated_height = Some(120);
+        let snapshot = StoreSnapshot {
+            proposals: vec![
+                proposal("1", ProposalStatus::Voting, 100, None),
+                proposal("2", ProposalStatus::Created, 90, None),
+                proposal("4", ProposalStatus::Created, 80, None),
+                proposal("3", ProposalStatus::Merged, 50, Some(110)),
+                proposal("5", ProposalStatus::Merged, 60, Some(120)),
+                proposal("6", ProposalStatus::Rejected, 70, Some(130)),
+            ],
+            tallies: Default::default(),
+            activations: vec![activated],
+        };
+        // Proposal 4's tier has no voting window
+        let deadline =
+            |p: &GovernanceProposal| (p.proposal_id != "4").then(|| p.created_height.unwrap() + 20);
+        let open = open_proposals(&snapshot, deadline, Some(115), 10);
+        let ids: Vec<&str> = open.items.iter().map(|p| p.proposal_id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "1", "4"]);
+        assert_eq!(open.items[0].blocks_left, Some(0));
+        assert_eq!(open.items[1].blocks_left, Some(5));
+        assert_eq!(open.items[2].blocks_left, None);
+        let open = open_proposals(&snapshot, deadline, Some(115), 1);
+        assert_eq!((open.total, open.items.len()), (3, 1));
+
+        let recent = recent(&snapshot, 10);
+        let changes: Vec<(&str, ChangeKind, u64)> = recent
+            .items
+            .iter()
+            .map(|c| (c.proposal_id.as_str(), c.change, c.height))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("3", ChangeKind::Activated, 120),
+                ("5", ChangeKind::Merged, 120),
+                ("3", ChangeKind::Merged, 110),
+            ]
+        );
+
+        let vetoes = near_threshold(
+            vec![
+                veto("a", 10.0),
+                veto("b", 20.0),
+                veto("c", 35.0),
+                veto("d", 0.0),
+            ],
+            10,
+        );
+        let ids: Vec<(&str, bool)> = vetoes
+            .items
+            .iter()
+            .map(|v| (v.proposal_id.as_str(), v.crossed))
+            .collect();
+        assert_eq!(ids, vec![("c", true), ("b", false)]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Voting deadlines and reminders
+//!
+//! A proposal of a tier with a voting window under `[governance.deadlines.windows]` must be
+//! voted on by its deadline: its created height plus the window. As blocks arrive, the
+//! proposal store (see [`crate::proposals`]) sends a `voting_deadline_reminder` to the
+//! webhook when the blocks remaining first fall to one of `reminders`, and `voting_closed`,
+//! with the tally and whether it met the tier's quorum, once the deadline is reached.
+//!
+//! Each reminder is sent at most once per proposal: the ones sent are stored with the
+//! proposals, so a restart does not repeat them. When several thresholds are passed at once,
+//! e.g. after the module was stopped, only the nearest is sent. Proposals merged, rejected or
+//! expired get no more reminders. The deadline is not enforced: a proposal stays open until
+//! the node closes it.
+
+use serde::{Deserialize, Serialize};
+
+/// Deadline of a proposal created at `created_height` with a voting window of `window` blocks.
+pub fn deadline(created_height: u64, window: u64) -> u64 {
+    created_height.saturating_add(window)
+}
+
+/// A notification due for a proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    /// The blocks remaining fell to `threshold`.
+    Reminder {
+        threshold: u64,
+        blocks_remaining: u64,
+    },
+    /// The deadline was reached.
+    Closed,
+}
+
+impl Notice {
+    /// Event type of the webhook notification.
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::Reminder { .. } => "voting_deadline_reminder",
+            Self::Closed => "voting_closed",
+        }
+    }
+}
+
+/// Notifications sent for one proposal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reminders {
+    /// Thresholds, in blocks remaining, already passed.
+    pub sent: Vec<u64>,
+    /// Whether `voting_closed` was sent.
+    pub closed: bool,
+}
+
+impl Reminders {
+    /// The notification due at `height` for a proposal with deadline `deadline` and reminders
+    /// at `thresholds`, if any, recording it as sent.
+    pub fn advance(&mut self, deadline: u64, height: u64, thresholds: &[u64]) -> Option<Notice> {
+        if self.closed {
+            return None;
+        }
+        if height >= deadline {
+            self.closed = true;
+            return Some(Notice::Closed);
+        }
+        let blocks_remaining = deadline - height;
+        let passed: Vec<u64> = thresholds
+            .iter()
+            .copied()
+            .filter(|t| blocks_remaining <= *t && !self.sent.contains(t))
+            .collect();
+        let threshold = passed.iter().copied().min()?;
+        self.sent.extend(passed);
+        Some(Notice::Reminder {
+            threshold,
+            blocks_remaining,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reminders_fire_once_per_threshold() {
+        let thresholds = [1000, 100, 10];
+        let mut reminders = Reminders::default();
+        assert_eq!(reminders.advance(2000, 999, &thresholds), None);
+        assert_eq!(
+            reminders.advance(2000, 1000, &thresholds),
+            Some(Notice::Reminder {
+                threshold: 1000,
+                blocks_remaining: 1000
+            })
+        );
+        assert_eq!(reminders.advance(2000, 1001, &thresholds), None);
+
+        // Both remaining thresholds passed at once: only the nearest is sent
+        assert_eq!(
+            reminders.advance(2000, 1995, &thresholds),
+            Some(Notice::Reminder {
+                threshold: 10,
+                blocks_remaining: 5
+            })
+        );
+        assert_eq!(reminders.sent, vec![1000, 100, 10]);
+        assert_eq!(reminders.advance(2000, 1999, &thresholds), None);
+
+        assert_eq!(
+            reminders.advance(2000, 2003, &thresholds),
+            Some(Notice::Closed)
+        );
+        assert_eq!(reminders.advance(2000, 2004, &thresholds), None);
+    }
+}
@@ -0,0 +1,842 @@
+//! Vote delegation
+//!
+//! With `[governance.delegation] enabled`, a voter may delegate its vote to another identity.
+//! The [`DelegationRegistry`] records each delegation with the height it is in effect from
+//! and, optionally, the height it expires at; a later record for the same delegator replaces
+//! it from its own height, and a revocation ends it. Records come from:
+//!
+//! - the `record_delegation` and `revoke_delegation` module API methods, in effect from the
+//!   chain tip when called (see [`crate::api`]);
+//! - with `on_chain` set as well, `OP_RETURN` outputs in the blocks of `NewBlock` events, in
+//!   effect from the block's height ([`delegation_record`]):
+//!
+//! ```text
+//! OP_RETURN <"BLVD" 0x01 0x01 <len> <delegator> <len> <delegate> [<8-byte LE expiry height>]>
+//! OP_RETURN <"BLVD" 0x01 0x00 <len> <delegator>>
+//! ```
+//!
+//! The node publishes no delegation event, so these are the only sources. Identities are the
+//! voter names the node's vote events carry, and records are taken as published, as votes are.
+//!
+//! Delegations chain ([`resolve`]): an identity's vote counts for the first identity along its
+//! chain, itself first, that voted on the proposal, so a direct vote takes precedence over a
+//! delegation and a delegate that has not voted passes the weight on. A chain that comes back
+//! to an identity already on it is a cycle; unless someone on it voted, its weight is not
+//! counted. The proposal store keeps the raw and the delegated tally of each proposal (see
+//! [`crate::tally`]) and recomputes those of open proposals on every block and after every
+//! change through the API, so revoking a delegation mid-vote gives its weight back at once.
+//!
+//! Records are stored in the module database. When the pipeline rolls back a reorg
+//! ([`DelegationRegistry::roll_back_to`]), on-chain records above the fork are dropped; those
+//! made through the API are kept.
+//!
+//! Queries: [`DelegationRegistry::identity`] for one identity and
+//! [`DelegationRegistry::records`] for all of them; the `get_delegation` and `get_delegations`
+//! API methods and the `export-delegations` subcommand read them.
+
+use crate::config::DelegationConfig;
+use crate::content::op_return_data;
+use crate::error::GovernanceError;
+use crate::node_api::NodeApiIpc;
+use crate::proposals::ProposalVote;
+use crate::tally::Tally;
+use blvm_protocol::Hash;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+const DELEGATIONS_TREE: &str = "delegations";
+
+const RECORDS_KEY: &[u8] = b"records";
+
+/// First bytes of an on-chain delegation record's data.
+pub const DELEGATION_MAGIC: &[u8; 4] = b"BLVD";
+
+/// Version of the record format, after the magic.
+pub const DELEGATION_VERSION: u8 = 0x01;
+
+const OP_RETURN: u8 = 0x6a;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+
+/// Operation byte of a record, after the version.
+const DELEGATE: u8 = 0x01;
+const REVOKE: u8 = 0x00;
+
+/// A delegation record carried by an `OP_RETURN` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnChainDelegation {
+    pub delegator: String,
+    /// `None` for a revocation.
+    pub delegate: Option<String>,
+    pub expiry_height: Option<u64>,
+}
+
+/// A length-prefixed, non-empty UTF-8 identity at the start of `data`, and the bytes after it.
+fn identity(data: &[u8]) -> Option<(String, &[u8])> {
+    let (&len, rest) = data.split_first()?;
+    let identity = std::str::from_utf8(rest.get(..len as usize)?).ok()?;
+    (!identity.is_empty()).then(|| (identity.to_string(), &rest[len as usize..]))
+}
+
+/// The delegation record `script` carries, if it is one.
+pub fn delegation_record(script: &[u8]) -> Option<OnChainDelegation> {
+    let data = op_return_data(script)?;
+    let rest = data
+        .strip_prefix(DELEGATION_MAGIC.as_slice())?
+        .strip_prefix(&[DELEGATION_VERSION])?;
+    let (&operation, rest) = rest.split_first()?;
+    let (delegator, rest) = identity(rest)?;
+    match operation {
+        REVOKE if rest.is_empty() => Some(OnChainDelegation {
+            delegator,
+            delegate: None,
+            expiry_height: None,
+        }),
+        DELEGATE => {
+            let (delegate, rest) = identity(rest)?;
+            let expiry_height = match rest.len() {
+                0 => None,
+                8 => Some(u64::from_le_bytes(rest.try_into().ok()?)),
+                _ => return None,
+            };
+            Some(OnChainDelegation {
+                delegator,
+                delegate: Some(delegate),
+                expiry_height,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The script of `record`, or `None` if an identity is empty or longer than 255 bytes.
+pub fn delegation_script(record: &OnChainDelegation) -> Option<Vec<u8>> {
+    let mut data = DELEGATION_MAGIC.to_vec();
+    data.push(DELEGATION_VERSION);
+    data.push(if record.delegate.is_some() {
+        DELEGATE
+    } else {
+        REVOKE
+    });
+    for identity in std::iter::once(&record.delegator).chain(&record.delegate) {
+        let len = u8::try_from(identity.len()).ok().filter(|len| *len > 0)?;
+        data.push(len);
+        data.extend_from_slice(identity.as_bytes());
+    }
+    if let (Some(_), Some(expiry)) = (&record.delegate, record.expiry_height) {
+        data.extend_from_slice(&expiry.to_le_bytes());
+    }
+    let mut script = vec![OP_RETURN];
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend([OP_PUSHDATA1, len as u8]),
+        len => {
+            script.push(OP_PUSHDATA2);
+            script.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+    }
+    script.extend(data);
+    Some(script)
+}
+
+/// Where a delegation record came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationSource {
+    /// The module API.
+    Announced,
+    /// An `OP_RETURN` in the block `block_hash` (hex).
+    OnChain { block_hash: String },
+}
+
+/// A change to a delegator's delegation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationRecord {
+    pub delegator: String,
+    /// Identity delegated to; `None` revokes the delegation.
+    pub delegate: Option<String>,
+    /// Height from which it is in effect.
+    pub effective_height: u64,
+    /// Height from which a delegation is no longer in effect, if it expires.
+    pub expiry_height: Option<u64>,
+    pub source: DelegationSource,
+}
+
+impl DelegationRecord {
+    /// Whether it can be recorded: a delegation needs a delegate other than the delegator,
+    /// expiring after it takes effect.
+    pub fn validate(&self) -> Result<(), GovernanceError> {
+        let invalid = |field: &str, reason: &str| {
+            Err(GovernanceError::ValidationError {
+                field: field.to_string(),
+                reason: reason.to_string(),
+            })
+        };
+        if self.delegator.is_empty() {
+            return invalid("delegator", "must not be empty");
+        }
+        match &self.delegate {
+            Some(delegate) if delegate.is_empty() => invalid("delegate", "must not be empty"),
+            Some(delegate) if *delegate == self.delegator => {
+                invalid("delegate", "must not be the delegator")
+            }
+            None if self.expiry_height.is_some() => {
+                invalid("expiry_height", "a revocation does not expire")
+            }
+            _ if self
+                .expiry_height
+                .is_some_and(|expiry| expiry <= self.effective_height) =>
+            {
+                invalid("expiry_height", "must be above the effective height")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The delegation it puts in effect at `height`, if any.
+    fn in_effect(&self, height: u64) -> Option<Delegation> {
+        let delegate = self.delegate.as_ref()?;
+        let expired = self.expiry_height.is_some_and(|expiry| height >= expiry);
+        (self.effective_height <= height && !expired).then(|| Delegation {
+            delegator: self.delegator.clone(),
+            delegate: delegate.clone(),
+            effective_height: self.effective_height,
+            expiry_height: self.expiry_height,
+        })
+    }
+}
+
+/// A delegation in effect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delegation {
+    pub delegator: String,
+    pub delegate: String,
+    pub effective_height: u64,
+    pub expiry_height: Option<u64>,
+}
+
+/// Where an identity's delegation chain leads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    /// The identity and those it delegates to in turn, each delegating to the next.
+    pub chain: Vec<String>,
+    /// Whether the last delegates back to one earlier in the chain.
+    pub cycle: bool,
+}
+
+/// Follow `delegates` (delegator to delegate) from `identity` to an identity that delegates
+/// to no one, or back to one already on the chain.
+pub fn resolve(delegates: &BTreeMap<String, String>, identity: &str) -> Resolution {
+    let mut chain = vec![identity.to_string()];
+    while let Some(next) = delegates.get(chain.last().unwrap()) {
+        if chain.contains(next) {
+            return Resolution { chain, cycle: true };
+        }
+        chain.push(next.clone());
+    }
+    Resolution {
+        chain,
+        cycle: false,
+    }
+}
+
+/// The tally of `votes` with delegated weight: each voter and each delegator of `delegates`
+/// counts once, for the vote of the first identity along its chain that voted. Identities
+/// whose chain reaches no voter are not counted.
+pub fn delegated_tally(votes: &[ProposalVote], delegates: &BTreeMap<String, String>) -> Tally {
+    Tally::of(&attributed_votes(votes, delegates))
+}
+
+/// The vote each voter and each delegator of `delegates` counts for, as in
+/// [`delegated_tally`], by identity.
+pub fn attributed_votes(
+    votes: &[ProposalVote],
+    delegates: &BTreeMap<String, String>,
+) -> Vec<ProposalVote> {
+    let cast: HashMap<&str, &str> = votes
+        .iter()
+        .map(|v| (v.voter.as_str(), v.vote.as_str()))
+        .collect();
+    let identities: BTreeSet<&str> = cast
+        .keys()
+        .copied()
+        .chain(delegates.keys().map(String::as_str))
+        .collect();
+    identities
+        .into_iter()
+        .filter_map(|identity| {
+            let vote = resolve(delegates, identity)
+                .chain
+                .iter()
+                .find_map(|id| cast.get(id.as_str()))?;
+            Some(ProposalVote {
+                voter: identity.to_string(),
+                vote: vote.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Delegation records by delegator, each delegator's oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DelegationMap {
+    records: BTreeMap<String, Vec<DelegationRecord>>,
+}
+
+impl DelegationMap {
+    /// Add `record` after those of its delegator in effect from the same height or before.
+    /// Returns false if the same record is already there.
+    pub fn insert(&mut self, record: DelegationRecord) -> bool {
+        let records = self.records.entry(record.delegator.clone()).or_default();
+        if records.contains(&record) {
+            return false;
+        }
+        let at = records.partition_point(|r| r.effective_height <= record.effective_height);
+        records.insert(at, record);
+        true
+    }
+
+    /// The delegation of `delegator` in effect at `height`, if any.
+    pub fn delegation(&self, delegator: &str, height: u64) -> Option<Delegation> {
+        self.records
+            .get(delegator)?
+            .iter()
+            .rev()
+            .find(|r| r.effective_height <= height)?
+            .in_effect(height)
+    }
+
+    /// The delegate of each delegator with a delegation in effect at `height`.
+    pub fn delegates_at(&self, height: u64) -> BTreeMap<String, String> {
+        self.records
+            .keys()
+            .filter_map(|delegator| self.delegation(delegator, height))
+            .map(|d| (d.delegator, d.delegate))
+            .collect()
+    }
+
+    /// The records of `delegator`, oldest first.
+    pub fn history(&self, delegator: &str) -> &[DelegationRecord] {
+        self.records.get(delegator).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every record, by delegator and then oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &DelegationRecord> {
+        self.records.values().flatten()
+    }
+
+    /// Drop the on-chain records above `fork_height`. Returns how many there were.
+    pub fn roll_back_to(&mut self, fork_height: u64) -> usize {
+        let mut dropped = 0;
+        for records in self.records.values_mut() {
+            let before = records.len();
+            records.retain(|r| {
+                r.source == DelegationSource::Announced || r.effective_height <= fork_height
+            });
+            dropped += before - records.len();
+        }
+        self.records.retain(|_, records| !records.is_empty());
+        dropped
+    }
+}
+
+/// What the registry knows of one identity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityDelegation {
+    pub identity: String,
+    /// Height the delegations are in effect at; `None` for every record so far.
+    pub height: Option<u64>,
+    /// Its own delegation in effect, if any.
+    pub delegation: Option<Delegation>,
+    /// Its chain of delegations.
+    pub resolution: Resolution,
+    /// Identities delegating to it directly.
+    pub delegators: Vec<String>,
+    /// Its records as delegator, oldest first.
+    pub history: Vec<DelegationRecord>,
+}
+
+/// Columns of [`records_csv`].
+pub const RECORD_COLUMNS: [&str; 6] = [
+    "delegator",
+    "delegate",
+    "effective_height",
+    "expiry_height",
+    "source",
+    "block_hash",
+];
+
+/// `records` as CSV, one row per record; a revocation has no delegate.
+pub fn records_csv<'a>(records: impl Iterator<Item = &'a DelegationRecord>) -> String {
+    crate::economic_nodes::export::csv_table(
+        &RECORD_COLUMNS,
+        records.map(|r| {
+            let (source, block_hash) = match &r.source {
+                DelegationSource::Announced => ("announced", ""),
+                DelegationSource::OnChain { block_hash } => ("on_chain", block_hash.as_str()),
+            };
+            vec![
+                r.delegator.clone(),
+                r.delegate.clone().unwrap_or_default(),
+                r.effective_height.to_string(),
+                r.expiry_height.map(|h| h.to_string()).unwrap_or_default(),
+                source.to_string(),
+                block_hash.to_string(),
+            ]
+        }),
+    )
+}
+
+/// Records delegations and resolves them for tallies.
+pub struct DelegationRegistry {
+    config: DelegationConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    /// Client for the blocks of on-chain records, and the chain tip.
+    node_api: NodeApiIpc,
+    /// Held across each change and write.
+    map: Mutex<DelegationMap>,
+}
+
+impl DelegationRegistry {
+    /// The registry with the records stored in `db`.
+    pub fn open(
+        config: DelegationConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+        node_api: NodeApiIpc,
+    ) -> Result<Self, GovernanceError> {
+        let map = Self::load_from(&db)?;
+        Ok(Self {
+            config,
+            db,
+            node_api,
+            map: Mutex::new(map),
+        })
+    }
+
+    /// Whether delegations are read from blocks, so blocks are needed.
+    pub fn reads_chain(&self) -> bool {
+        self.config.on_chain
+    }
+
+    /// Record `record`, if valid and not recorded already. Returns whether it was recorded.
+    pub fn record(&self, record: DelegationRecord) -> Result<bool, GovernanceError> {
+        record.validate()?;
+        let mut map = self.map.lock().unwrap();
+        if !map.insert(record.clone()) {
+            return Ok(false);
+        }
+        self.save(&map)?;
+        match &record.delegate {
+            Some(delegate) => info!(
+                "{} delegates to {} from height {}",
+                record.delegator, delegate, record.effective_height
+            ),
+            None => info!(
+                "{} revokes its delegation from height {}",
+                record.delegator, record.effective_height
+            ),
+        }
+        Ok(true)
+    }
+
+    /// Record a delegation from `delegator` to `delegate`, or its revocation without one, made
+    /// through the module API, in effect from the chain tip.
+    pub fn announce(
+        &self,
+        delegator: &str,
+        delegate: Option<&str>,
+        expiry_height: Option<u64>,
+    ) -> Result<DelegationRecord, GovernanceError> {
+        let record = DelegationRecord {
+            delegator: delegator.to_string(),
+            delegate: delegate.map(str::to_string),
+            effective_height: self.height().unwrap_or(0),
+            expiry_height,
+            source: DelegationSource::Announced,
+        };
+        self.record(record.clone())?;
+        Ok(record)
+    }
+
+    /// The delegate of each delegator at `height`, or after every record without one.
+    pub fn delegates_at(&self, height: Option<u64>) -> BTreeMap<String, String> {
+        self.map
+            .lock()
+            .unwrap()
+            .delegates_at(height.unwrap_or(u64::MAX))
+    }
+
+    /// The tally of `votes` with the delegations in effect at `height` (see
+    /// [`delegated_tally`]).
+    pub fn tally(&self, votes: &[ProposalVote], height: Option<u64>) -> Tally {
+        delegated_tally(votes, &self.delegates_at(height))
+    }
+
+    /// The vote each identity counts for with the delegations in effect at `height` (see
+    /// [`attributed_votes`]).
+    pub fn attributed(&self, votes: &[ProposalVote], height: Option<u64>) -> Vec<ProposalVote> {
+        attributed_votes(votes, &self.delegates_at(height))
+    }
+
+    /// What is known of `identity` at `height`, or at the chain tip without one.
+    pub fn identity(&self, identity: &str, height: Option<u64>) -> IdentityDelegation {
+        let height = height.or_else(|| self.height());
+        let map = self.map.lock().unwrap();
+        let at = height.unwrap_or(u64::MAX);
+        let delegates = map.delegates_at(at);
+        IdentityDelegation {
+            identity: identity.to_string(),
+            height,
+            delegation: map.delegation(identity, at),
+            resolution: resolve(&delegates, identity),
+            delegators: delegates
+                .iter()
+                .filter(|(_, delegate)| *delegate == identity)
+                .map(|(delegator, _)| delegator.clone())
+                .collect(),
+            history: map.history(identity).to_vec(),
+        }
+    }
+
+    /// Every record, by delegator and then oldest first.
+    pub fn records(&self) -> Vec<DelegationRecord> {
+        self.map.lock().unwrap().records().cloned().collect()
+    }
+
+    /// Record the delegations in the `OP_RETURN` outputs of the block `hash` at `height`, from
+    /// a `NewBlock` event.
+    pub async fn handle_block(&self, hash: &Hash, height: u64) -> Result<(), GovernanceError> {
+        if !self.reads_chain() {
+            return Ok(());
+        }
+        let Some(block) = self.node_api.get_block(hash).await? else {
+            debug!("No block {} at {}", hex::encode(hash), height);
+            return Ok(());
+        };
+        let found = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .filter_map(|output| delegation_record(&output.script_pubkey));
+        for found in found {
+            let record = DelegationRecord {
+                delegator: found.delegator,
+                delegate: found.delegate,
+                effective_height: height,
+                expiry_height: found.expiry_height,
+                source: DelegationSource::OnChain {
+                    block_hash: hex::encode(hash),
+                },
+            };
+            if let Err(e) = self.record(record) {
+                warn!("Ignoring a delegation record at height {}: {}", height, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the on-chain records above `fork_height`, after a reorg replaced those blocks.
+    /// Returns how many there were.
+    pub fn roll_back_to(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        let mut map = self.map.lock().unwrap();
+        let dropped = map.roll_back_to(fork_height);
+        if dropped > 0 {
+            warn!(
+                "Reorg to height {} dropped {} delegation records",
+                fork_height, dropped
+            );
+            self.save(&map)?;
+        }
+        Ok(dropped as u64)
+    }
+
+    /// Height of the chain tip, if known.
+    fn height(&self) -> Option<u64> {
+        self.node_api.current_tip().map(|tip| tip.height)
+    }
+
+    fn save(&self, map: &DelegationMap) -> Result<(), GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(DELEGATIONS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(map).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(RECORDS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    /// Load stored records, e.g. for the CLI.
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<DelegationMap, GovernanceError> {
+        let tree = db
+            .open_tree(DELEGATIONS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(RECORDS_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(DelegationMap::default()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn vote(voter: &str, vote: &str) -> ProposalVote {
+        ProposalVote {
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        }
+    }
+
+    fn delegates(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(delegator, delegate)| (delegator.to_string(), delegate.to_string()))
+            .collect()
+    }
+
+    fn record(
+        delegator: &str,
+        delegate: Option<&str>,
+        effective_height: u64,
+        expiry_height: Option<u64>,
+    ) -> DelegationRecord {
+        DelegationRecord {
+            delegator: delegator.to_string(),
+            delegate: delegate.map(str::to_string),
+            effective_height,
+            expiry_height,
+            source: DelegationSource::Announced,
+        }
+    }
+
+    #[test]
+    fn test_record_script_round_trip() {
+        let records = [
+            OnChainDelegation {
+                delegator: "alice".to_string(),
+                delegate: Some("bob".to_string()),
+                expiry_height: Some(900_000),
+            },
+            OnChainDelegation {
+                delegator: "alice".to_string(),
+                delegate: Some("b".repeat(200)),
+                expiry_height: None,
+            },
+            OnChainDelegation {
+                delegator: "a".repeat(255),
+                delegate: Some("b".repeat(255)),
+                expiry_height: None,
+            },
+            OnChainDelegation {
+                delegator: "alice".to_string(),
+                delegate: None,
+                expiry_height: None,
+            },
+        ];
+        for record in records {
+            let script = delegation_script(&record).unwrap();
+            assert_eq!(delegation_record(&script), Some(record));
+        }
+
+        let unfit = OnChainDelegation {
+            delegator: "a".repeat(256),
+            delegate: None,
+            expiry_height: None,
+        };
+        assert_eq!(delegation_script(&unfit), None);
+        assert_eq!(
+            delegation_record(&crate::content::commitment_script(&[1u8; 32])),
+            None
+        );
+        // A revocation carries nothing after the delegator
+        let mut script = delegation_script(&OnChainDelegation {
+            delegator: "alice".to_string(),
+            delegate: None,
+            expiry_height: None,
+        })
+        .unwrap();
+        script[1] += 1;
+        script.push(0);
+        assert_eq!(delegation_record(&script), None);
+    }
+
+    #[test]
+    fn test_delegations_in_effect_by_height() {
+        let mut map = DelegationMap::default();
+        assert!(map.insert(record("alice", Some("bob"), 10, Some(20))));
+        assert!(map.insert(record("alice", None, 30, None)));
+        assert!(map.insert(record("alice", Some("carol"), 15, None)));
+        assert!(!map.insert(record("alice", Some("carol"), 15, None)));
+        assert!(map.insert(record("dave", Some("erin"), 10, Some(20))));
+
+        let delegate = |delegator: &str, height| {
+            map.delegation(delegator, height)
+                .map(|delegation| delegation.delegate)
+        };
+        assert_eq!(delegate("alice", 9), None);
+        assert_eq!(delegate("alice", 12).as_deref(), Some("bob"));
+        // The later record replaces the earlier one from its height
+        assert_eq!(delegate("alice", 15).as_deref(), Some("carol"));
+        assert_eq!(delegate("alice", 25).as_deref(), Some("carol"));
+        assert_eq!(delegate("alice", 30), None);
+        assert_eq!(delegate("dave", 19).as_deref(), Some("erin"));
+        assert_eq!(delegate("dave", 20), None);
+        assert_eq!(
+            map.delegates_at(19),
+            delegates(&[("alice", "carol"), ("dave", "erin")])
+        );
+    }
+
+    #[test]
+    fn test_records_are_validated() {
+        assert!(record("alice", Some("bob"), 10, Some(11))
+            .validate()
+            .is_ok());
+        assert!(record("alice", None, 10, None).validate().is_ok());
+        for invalid in [
+            record("", Some("bob"), 10, None),
+            record("alice", Some(""), 10, None),
+            record("alice", Some("alice"), 10, None),
+            record("alice", Some("bob"), 10, Some(10)),
+            record("alice", None, 10, Some(20)),
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_roll_back_drops_on_chain_records_above_the_fork() {
+        let on_chain = |delegator: &str, delegate: Option<&str>, height| DelegationRecord {
+            source: DelegationSource::OnChain {
+                block_hash: hex::encode([height as u8; 32]),
+            },
+            ..record(delegator, delegate, height, None)
+        };
+        let mut map = DelegationMap::default();
+        map.insert(on_chain("alice", Some("bob"), 10));
+        map.insert(on_chain("alice", None, 12));
+        map.insert(on_chain("carol", Some("bob"), 13));
+        map.insert(record("dave", Some("bob"), 13, None));
+
+        assert_eq!(map.roll_back_to(11), 2);
+        assert_eq!(
+            map.delegates_at(13),
+            delegates(&[("alice", "bob"), ("dave", "bob")])
+        );
+        assert!(map.history("carol").is_empty());
+    }
+
+    #[test]
+    fn test_delegated_tally() {
+        let votes = [vote("bob", "yes"), vote("gina", "no")];
+        let mut map = delegates(&[
+            ("carol", "bob"),
+            ("dave", "carol"),
+            ("erin", "fred"),
+            ("fred", "erin"),
+            ("gina", "bob"),
+        ]);
+        // gina's own vote counts; erin and fred delegate in a cycle no one on it voted in
+        let tally = delegated_tally(&votes, &map);
+        assert_eq!((tally.yes, tally.no, tally.abstain), (3, 1, 0));
+        assert_eq!(
+            resolve(&map, "erin"),
+            Resolution {
+                chain: vec!["erin".to_string(), "fred".to_string()],
+                cycle: true,
+            }
+        );
+        assert_eq!(resolve(&map, "dave").chain, ["dave", "carol", "bob"]);
+
+        // Revoked: carol and dave reach no voter any more
+        map.remove("carol");
+        let tally = delegated_tally(&votes, &map);
+        assert_eq!((tally.yes, tally.no, tally.abstain), (1, 1, 0));
+
+        // A vote on the cycle takes the weight of the rest of it
+        let votes = [vote("fred", "abstain")];
+        let tally = delegated_tally(&votes, &map);
+        assert_eq!((tally.yes, tally.no, tally.abstain), (0, 0, 2));
+    }
+
+    fn arb_delegates() -> impl Strategy<Value = BTreeMap<String, String>> {
+        prop::collection::btree_map("[a-f]", "[a-f]", 0..8)
+    }
+
+    fn arb_votes() -> impl Strategy<Value = Vec<ProposalVote>> {
+        let choice = prop::sample::select(vec!["yes", "no", "abstain", "maybe"]);
+        prop::collection::btree_map("[a-f]", choice, 0..6)
+            .prop_map(|votes| votes.iter().map(|(voter, v)| vote(voter, v)).collect())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_resolution_follows_delegations(
+            delegates in arb_delegates(),
+            identity in "[a-f]",
+        ) {
+            let resolution = resolve(&delegates, &identity);
+            prop_assert_eq!(&resolution.chain[0], &identity);
+            let distinct: BTreeSet<&String> = resolution.chain.iter().collect();
+            prop_assert_eq!(distinct.len(), resolution.chain.len());
+            for pair in resolution.chain.windows(2) {
+                prop_assert_eq!(delegates.get(&pair[0]), Some(&pair[1]));
+            }
+            match delegates.get(resolution.chain.last().unwrap()) {
+                Some(next) => {
+                    prop_assert!(resolution.cycle);
+                    prop_assert!(resolution.chain.contains(next));
+                }
+                None => prop_assert!(!resolution.cycle),
+            }
+        }
+
+        #[test]
+        fn prop_delegated_tally_counts_each_identity_at_most_once(
+            delegates in arb_delegates(),
+            votes in arb_votes(),
+        ) {
+            let raw = Tally::of(&votes);
+            let delegated = delegated_tally(&votes, &delegates);
+            let identities: BTreeSet<&str> = votes
+                .iter()
+                .map(|v| v.voter.as_str())
+                .chain(delegates.keys().map(String::as_str))
+                .collect();
+            // Every vote cast counts for its voter
+            prop_assert!(delegated.yes >= raw.yes);
+            prop_assert!(delegated.no >= raw.no);
+            prop_assert!(delegated.abstain >= raw.abstain);
+            prop_assert!(delegated.total() <= identities.len() as u64);
+            if delegates.is_empty() {
+                prop_assert_eq!(delegated, raw);
+            }
+            if votes.is_empty() {
+                prop_assert_eq!(delegated, Tally::default());
+            }
+        }
+
+        #[test]
+        fn prop_revoking_a_voters_delegation_changes_nothing(
+            delegates in arb_delegates(),
+            votes in arb_votes(),
+        ) {
+            let before = delegated_tally(&votes, &delegates);
+            for voter in votes.iter().map(|v| &v.voter) {
+                let mut revoked = delegates.clone();
+                revoked.remove(voter);
+                prop_assert_eq!(delegated_tally(&votes, &revoked), before);
+            }
+        }
+    }
+}
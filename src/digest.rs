@@ -0,0 +1,573 @@
+//! Periodic digests of webhook notifications
+//!
+//! With `[governance.digest] mode = "digest"`, notifications the webhook would send one by
+//! one (proposal events, tally milestones, deadlines, activations, registry changes, ...) are
+//! collected instead, and at the end of each period sent together as one
+//! `governance_digest` notification. The registry adds the vetoes cast and revoked
+//! (`veto_cast`, `veto_revoked`), which are not otherwise sent. Block notifications are still
+//! sent as they come. The webhook's event filter applies to what is collected; the digest
+//! itself is always sent.
+//!
+//! A period ends at each multiple of `every_secs` since the Unix epoch, or at each minute
+//! matching `schedule`, a cron expression ([`Cron`]). `clock = "wall"` measures it on the
+//! local clock, checked every [`DIGEST_TICK`]; `clock = "block"` on the timestamp of each
+//! block as it arrives, so no digest is sent while no blocks are. A period without
+//! notifications sends nothing, or with `all_quiet` a digest saying so.
+//!
+//! The digest's data groups the notifications by [`section`], each with its event type, data
+//! and the time it was collected, and carries a rendering for people in `text`. The
+//! notifications collected, the start of the current period and a digest being sent are kept
+//! in `digest.json` in the data directory's `state/`, written before the notification that
+//! added to it is marked done (see [`crate::intent`]), so a restart neither skips nor repeats
+//! any; a digest cut short by a crash is sent again on the next start. While outbound effects
+//! are paused (see [`crate::pause`]) digests are held and the notifications keep collecting.
+
+use crate::amendments;
+use crate::build_info::{civil_from_days, rfc3339};
+use crate::checkpoint::write_atomic;
+use crate::config::{DigestClock, DigestConfig};
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DIGEST_FILE: &str = "digest.json";
+
+/// Event type of a digest.
+pub const DIGEST_EVENT: &str = "governance_digest";
+
+/// Event types the registry adds for vetoes.
+pub const VETO_CAST_EVENT: &str = "veto_cast";
+pub const VETO_REVOKED_EVENT: &str = "veto_revoked";
+
+/// Sections of a digest, in the order they are rendered.
+pub const SECTIONS: [&str; 6] = [
+    "proposals",
+    "tallies",
+    "vetoes",
+    "activations",
+    "registry",
+    "other",
+];
+
+/// How often the wall clock is checked for the end of a period.
+pub const DIGEST_TICK: Duration = Duration::from_secs(15);
+
+/// Minutes a schedule is searched back over for a missed match: a year.
+const MAX_MINUTES_BACK: u64 = 366 * 24 * 60;
+
+/// The section of [`SECTIONS`] notifications of `event_type` are listed in.
+pub fn section(event_type: &str) -> &'static str {
+    match event_type {
+        "proposal_created" | "proposal_rejected" => "proposals",
+        amendments::AMENDED_EVENT | amendments::REJECTED_EVENT => "proposals",
+        "proposal_voted" | "proposal_quorum_reached" | "proposal_approved_pending_merge" => {
+            "tallies"
+        }
+        t if t.starts_with("voting_") => "tallies",
+        t if t.contains("veto") => "vetoes",
+        "proposal_merged" => "activations",
+        t if t.starts_with("proposal_activat") || t.starts_with("proposal_adoption") => {
+            "activations"
+        }
+        t if t.starts_with("registry_") => "registry",
+        _ => "other",
+    }
+}
+
+/// One collected notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestItem {
+    pub event_type: String,
+    pub data: Value,
+    /// Unix seconds when it was collected.
+    pub timestamp: u64,
+}
+
+/// Contents of `digest.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct DigestFile {
+    /// Start of the current period on the digest's clock; unset until first checked.
+    period_start: Option<u64>,
+    /// Oldest first.
+    items: Vec<DigestItem>,
+    /// Data of the digest being sent, until it is.
+    sending: Option<Value>,
+}
+
+/// A cron expression: minute, hour, day of month, month and day of week, in UTC.
+///
+/// Each field is `*`, a number, a range `a-b`, either with a step (`*/15`, `1-5/2`), or a
+/// comma-separated list of these. Day of week runs from 0 (Sunday) to 7 (Sunday again). As
+/// in cron, when both the day of month and the day of week are restricted a day matching
+/// either matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of month and the day of week are `*`.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Whether the minute starting at `secs` (Unix seconds) matches.
+    pub fn matches(&self, secs: u64) -> bool {
+        let (days, secs) = (secs / 86_400, secs % 86_400);
+        let (_, month, day) = civil_from_days(days as i64);
+        let weekday = (days + 4) % 7;
+        let bit = |mask: u64, n: u64| mask & (1 << n) != 0;
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => bit(self.days, day as u64),
+            (true, false) => bit(self.weekdays, weekday),
+            (false, false) => bit(self.days, day as u64) || bit(self.weekdays, weekday),
+        };
+        bit(self.minutes, secs % 3_600 / 60)
+            && bit(self.hours, secs / 3_600)
+            && bit(self.months, month as u64)
+            && day_matches
+    }
+}
+
+/// The values `field` allows between `min` and `max`, as a bit mask.
+fn parse_field(field: &str, name: &str, min: u64, max: u64) -> Result<u64, String> {
+    let number = |text: &str| {
+        text.parse::<u64>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("{} {:?} is not between {} and {}", name, text, min, max))
+    };
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u64>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("{} step {:?} is not a positive number", name, step)),
+            },
+            None => (part, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (number(first)?, number(last)?),
+            // `5/10` runs from 5 to the end
+            None if step > 1 => (number(range)?, max),
+            None => {
+                let n = number(range)?;
+                (n, n)
+            }
+        };
+        if first > last {
+            return Err(format!("{} range {:?} runs backwards", name, range));
+        }
+        for n in (first..=last).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), found {}",
+                fields.len()
+            ));
+        };
+        let mut weekday_mask = parse_field(weekdays, "day of week", 0, 7)?;
+        if weekday_mask & (1 << 7) != 0 {
+            weekday_mask |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minutes, "minute", 0, 59)?,
+            hours: parse_field(hours, "hour", 0, 23)?,
+            days: parse_field(days, "day of month", 1, 31)?,
+            months: parse_field(months, "month", 1, 12)?,
+            weekdays: weekday_mask,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+/// When periods end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// At each multiple of this many seconds since the Unix epoch.
+    Every(u64),
+    /// At each minute matching.
+    Cron(Cron),
+}
+
+impl Schedule {
+    pub fn from_config(config: &DigestConfig) -> Result<Self, String> {
+        match &config.schedule {
+            Some(schedule) => schedule.parse().map(Self::Cron),
+            None if config.every_secs > 0 => Ok(Self::Every(config.every_secs)),
+            None => Err("every_secs must be greater than 0 without a schedule".to_string()),
+        }
+    }
+
+    /// Whether a period that started at `start` has ended by `now`.
+    pub fn ended(&self, start: u64, now: u64) -> bool {
+        if now <= start {
+            return false;
+        }
+        match self {
+            Self::Every(secs) => now / secs > start / secs,
+            Self::Cron(cron) => {
+                let first = (start / 60 + 1).max((now / 60).saturating_sub(MAX_MINUTES_BACK));
+                (first..=now / 60).any(|minute| cron.matches(minute * 60))
+            }
+        }
+    }
+}
+
+/// The collected notifications, and when to send them.
+#[derive(Debug)]
+pub struct Digest {
+    path: PathBuf,
+    clock: DigestClock,
+    all_quiet: bool,
+    schedule: Schedule,
+    state: Mutex<DigestFile>,
+}
+
+impl Digest {
+    /// Load the notifications collected in `dir`, if there are any, to send as `config`
+    /// says.
+    pub fn open(dir: &Path, config: &DigestConfig) -> Result<Self, GovernanceError> {
+        let schedule = Schedule::from_config(config)
+            .map_err(|e| GovernanceError::ConfigError(format!("digest: {}", e)))?;
+        let path = dir.join(DIGEST_FILE);
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(GovernanceError::serialization(&path.display().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DigestFile::default(),
+            Err(e) => return Err(GovernanceError::io(path.display())(e)),
+        };
+        Ok(Self {
+            path,
+            clock: config.clock,
+            all_quiet: config.all_quiet,
+            schedule,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Apply `change` and write the result; the state is unchanged if the write fails.
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut DigestFile) -> Option<T>,
+    ) -> Result<Option<T>, GovernanceError> {
+        let mut state = self.state.lock().unwrap();
+        let mut next = state.clone();
+        let Some(result) = change(&mut next) else {
+            return Ok(None);
+        };
+        let data = serde_json::to_vec(&next).map_err(GovernanceError::serialization("digest"))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(GovernanceError::io(dir.display()))?;
+        }
+        write_atomic(&self.path, &data)?;
+        *state = next;
+        Ok(Some(result))
+    }
+
+    /// Whether periods are measured on block timestamps.
+    pub fn uses_block_time(&self) -> bool {
+        self.clock == DigestClock::Block
+    }
+
+    /// Collect the notification `event_type` with `data`, made at `timestamp`, for the next
+    /// digest.
+    pub fn record(
+        &self,
+        event_type: &str,
+        data: &Value,
+        timestamp: u64,
+    ) -> Result<(), GovernanceError> {
+        self.update(|state| {
+            state.items.push(DigestItem {
+                event_type: event_type.to_string(),
+                data: data.clone(),
+                timestamp,
+            });
+            Some(())
+        })
+        .map(|_| ())
+    }
+
+    /// Notifications collected for the next digest.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    /// The data of the digest to send at `now`, on the digest's clock: one left unsent, or
+    /// one for the period just ended. Its notifications are no longer collected; call
+    /// [`Self::sent`] once it is sent or given up on. `None` if there is nothing to send.
+    pub fn take_due(&self, now: u64) -> Result<Option<Value>, GovernanceError> {
+        if let Some(data) = self.state.lock().unwrap().sending.clone() {
+            return Ok(Some(data));
+        }
+        self.update(|state| {
+            let Some(start) = state.period_start else {
+                state.period_start = Some(now);
+                return Some(None);
+            };
+            if !self.schedule.ended(start, now) {
+                return None;
+            }
+            state.period_start = Some(now);
+            if state.items.is_empty() && !self.all_quiet {
+                return Some(None);
+            }
+            let items = std::mem::take(&mut state.items);
+            let data = build(&items, start, now);
+            state.sending = Some(data.clone());
+            Some(Some(data))
+        })
+        .map(Option::flatten)
+    }
+
+    /// The digest from [`Self::take_due`] is sent or given up on.
+    pub fn sent(&self) -> Result<(), GovernanceError> {
+        self.update(|state| state.sending.take().map(|_| ()))
+            .map(|_| ())
+    }
+}
+
+/// The data of a digest of `items` for the period from `start` to `end`.
+pub fn build(items: &[DigestItem], start: u64, end: u64) -> Value {
+    let mut sections = serde_json::Map::new();
+    let mut counts = serde_json::Map::new();
+    for name in SECTIONS {
+        let listed: Vec<&DigestItem> = items
+            .iter()
+            .filter(|item| section(&item.event_type) == name)
+            .collect();
+        if listed.is_empty() {
+            continue;
+        }
+        counts.insert(name.to_string(), listed.len().into());
+        sections.insert(name.to_string(), serde_json::json!(listed));
+    }
+    serde_json::json!({
+        "period_start": start,
+        "period_end": end,
+        "total": items.len(),
+        "counts": counts,
+        "sections": sections,
+        "text": render(items, start, end),
+    })
+}
+
+/// A rendering of a digest of `items` for people: a heading, then a line per notification
+/// under the heading of its section.
+pub fn render(items: &[DigestItem], start: u64, end: u64) -> String {
+    let period = format!("{} to {}", rfc3339(start), rfc3339(end));
+    if items.is_empty() {
+        return format!(
+            "Governance digest, {}: all quiet, no governance activity.",
+            period
+        );
+    }
+    let mut text = format!(
+        "Governance digest, {}: {} notification{}",
+        period,
+        items.len(),
+        if items.len() == 1 { "" } else { "s" }
+    );
+    for name in SECTIONS {
+        let lines: Vec<String> = items
+            .iter()
+            .filter(|item| section(&item.event_type) == name)
+            .map(|item| format!("- {}", headline(&item.event_type, &item.data)))
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        text.push_str(&format!(
+            "\n\n{} ({})\n{}",
+            capitalize(name),
+            lines.len(),
+            lines.join("\n")
+        ));
+    }
+    text
+}
+
+/// One line saying what the notification `event_type` with `data` is about.
+pub fn headline(event_type: &str, data: &Value) -> String {
+    if let Some((title, _)) = crate::feed::describe(event_type, data) {
+        return title;
+    }
+    let proposal = data["proposal_id"].as_str().unwrap_or("?");
+    let node = data["node_id"].as_str().map(short_id).unwrap_or("?");
+    match event_type {
+        "proposal_voted" => format!(
+            "{} voted {} on proposal {}",
+            data["voter"].as_str().unwrap_or("?"),
+            data["vote"].as_str().unwrap_or("?"),
+            proposal
+        ),
+        amendments::AMENDED_EVENT => format!(
+            "Proposal {} amended: {}",
+            proposal,
+            data["changes"].as_str().unwrap_or("?")
+        ),
+        amendments::REJECTED_EVENT => format!(
+            "Amendment of proposal {} rejected, voting had closed: {}",
+            proposal,
+            data["changes"].as_str().unwrap_or("?")
+        ),
+        VETO_CAST_EVENT => format!(
+            "Economic node {} vetoed proposal {}: {}",
+            node,
+            proposal,
+            data["reason"].as_str().unwrap_or("")
+        ),
+        VETO_REVOKED_EVENT => format!(
+            "Economic node {} revoked its veto on proposal {}",
+            node, proposal
+        ),
+        t if t.starts_with("registry_") => match data["node_id"].as_str() {
+            Some(_) => format!("Economic node {} {}", node, words(&t["registry_".len()..])),
+            None => format!("Registry {}", words(&t["registry_".len()..])),
+        },
+        t if data["proposal_id"].is_string() => format!(
+            "{} on proposal {}",
+            capitalize(&words(t.strip_prefix("proposal_").unwrap_or(t))),
+            proposal
+        ),
+        t => words(t),
+    }
+}
+
+/// The first 16 characters of a node id.
+fn short_id(id: &str) -> &str {
+    id.get(..16).unwrap_or(id)
+}
+
+/// `text` with its first letter in upper case.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `event_type` with its underscores as spaces.
+fn words(event_type: &str) -> String {
+    event_type.replace('_', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unix seconds of the given UTC time in 2025.
+    fn at(month: u64, day: u64, hour: u64, minute: u64) -> u64 {
+        let days_before = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let days = 20_089 + days_before[month as usize - 1] + day - 1;
+        days * 86_400 + hour * 3_600 + minute * 60
+    }
+
+    #[test]
+    fn test_cron() {
+        // 2025-01-01 was a Wednesday
+        let cron: Cron = "0 9 * * 1-5".parse().unwrap();
+        assert!(cron.matches(at(1, 1, 9, 0)));
+        assert!(!cron.matches(at(1, 1, 9, 1)));
+        assert!(!cron.matches(at(1, 4, 9, 0)));
+        let cron: Cron = "*/15 0,12 1 * 0".parse().unwrap();
+        assert!(cron.matches(at(2, 1, 12, 45)));
+        // Sunday, not the first
+        assert!(cron.matches(at(1, 5, 0, 30)));
+        assert!(!cron.matches(at(1, 6, 0, 30)));
+        assert!("0 9 * * 7".parse::<Cron>().unwrap().matches(at(1, 5, 9, 0)));
+        for bad in [
+            "0 9 * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(bad.parse::<Cron>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_periods() {
+        let daily = Schedule::Every(86_400);
+        assert!(!daily.ended(at(1, 1, 0, 0), at(1, 1, 23, 59)));
+        assert!(daily.ended(at(1, 1, 23, 59), at(1, 2, 0, 0)));
+        let cron = Schedule::Cron("30 9 * * *".parse().unwrap());
+        assert!(!cron.ended(at(1, 1, 9, 30), at(1, 2, 9, 29)));
+        assert!(cron.ended(at(1, 1, 9, 30), at(1, 2, 9, 30)));
+        assert!(cron.ended(at(1, 1, 9, 30), at(3, 1, 0, 0)));
+        assert!(!cron.ended(at(1, 2, 9, 30), at(1, 1, 9, 30)));
+    }
+
+    #[test]
+    fn test_render() {
+        let item = |event_type: &str, data: Value| DigestItem {
+            event_type: event_type.to_string(),
+            data,
+            timestamp: 0,
+        };
+        let items = [
+            item(
+                "registry_expired",
+                serde_json::json!({ "node_id": "ab".repeat(32) }),
+            ),
+            item(
+                "proposal_created",
+                serde_json::json!({ "proposal_id": "4", "tier": "standard" }),
+            ),
+            item(
+                "proposal_quorum_reached",
+                serde_json::json!({ "proposal_id": "4" }),
+            ),
+            item(
+                VETO_CAST_EVENT,
+                serde_json::json!({ "proposal_id": "4", "node_id": "cd".repeat(32), "reason": "unsafe" }),
+            ),
+        ];
+        assert_eq!(
+            render(&items, 0, 86_400),
+            "Governance digest, 1970-01-01T00:00:00Z to 1970-01-02T00:00:00Z: 4 notifications\n\
+             \n\
+             Proposals (1)\n\
+             - Proposal 4 created\n\
+             \n\
+             Tallies (1)\n\
+             - Quorum reached on proposal 4\n\
+             \n\
+             Vetoes (1)\n\
+             - Economic node cdcdcdcdcdcdcdcd vetoed proposal 4: unsafe\n\
+             \n\
+             Registry (1)\n\
+             - Economic node abababababababab expired"
+        );
+        let data = build(&items, 0, 86_400);
+        assert_eq!(data["total"], 4);
+        assert_eq!(data["counts"]["vetoes"], 1);
+        assert_eq!(
+            data["sections"]["registry"][0]["event_type"],
+            "registry_expired"
+        );
+        assert!(render(&[], 0, 60).ends_with("all quiet, no governance activity."));
+    }
+}
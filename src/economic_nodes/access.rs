@@ -0,0 +1,150 @@
+//! Allowlist and blocklist of economic node identities
+//!
+//! List files hold one identity per line: a node id (64 hex characters) or a compressed
+//! public key (66 hex characters). Blank lines and `#` comments are ignored.
+
+use crate::config::AccessListConfig;
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Loaded access lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessLists {
+    pub blocklist: HashSet<String>,
+    /// `None` unless allowlist mode is enabled.
+    pub allowlist: Option<HashSet<String>>,
+}
+
+/// Why an identity was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDenial {
+    /// Matched this blocklist entry.
+    Blocklisted(String),
+    /// Allowlist mode is on and no identity is listed.
+    NotAllowlisted,
+}
+
+impl std::fmt::Display for AccessDenial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blocklisted(entry) => write!(f, "blocklisted ({})", entry),
+            Self::NotAllowlisted => write!(f, "not on allowlist"),
+        }
+    }
+}
+
+/// Audit record of a retroactive access list action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessAuditEntry {
+    pub height: u64,
+    pub node_id: String,
+    /// "deactivated" or "reactivated".
+    pub action: String,
+    /// List entry that matched, if any.
+    pub entry: Option<String>,
+}
+
+/// Parse a list file's contents.
+pub fn parse_list(text: &str) -> HashSet<String> {
+    text.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim().to_ascii_lowercase())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+fn read_list(path: &Path) -> Result<HashSet<String>, GovernanceError> {
+    std::fs::read_to_string(path)
+        .map(|text| parse_list(&text))
+        .map_err(GovernanceError::io(path.display()))
+}
+
+/// Modification times of the configured list files, for change detection.
+pub fn modified_times(config: &AccessListConfig) -> Vec<Option<SystemTime>> {
+    [&config.blocklist_path, &config.allowlist_path]
+        .into_iter()
+        .map(|p| {
+            p.as_ref()
+                .and_then(|p| std::fs::metadata(p).ok())
+                .and_then(|m| m.modified().ok())
+        })
+        .collect()
+}
+
+impl AccessLists {
+    /// Load the lists referenced by `config`.
+    pub fn load(config: &AccessListConfig) -> Result<Self, GovernanceError> {
+        let blocklist = match &config.blocklist_path {
+            Some(path) => read_list(path)?,
+            None => HashSet::new(),
+        };
+        let allowlist = match (&config.allowlist_path, config.allowlist_mode) {
+            (Some(path), true) => Some(read_list(path)?),
+            (None, true) => Some(HashSet::new()),
+            (_, false) => None,
+        };
+        Ok(Self {
+            blocklist,
+            allowlist,
+        })
+    }
+
+    /// Blocklist entry matching any of `identities` (hex node ids or public keys).
+    pub fn blocked(&self, identities: &[String]) -> Option<String> {
+        identities
+            .iter()
+            .map(|i| i.to_ascii_lowercase())
+            .find(|i| self.blocklist.contains(i))
+    }
+
+    /// Check an identity set against both lists.
+    pub fn check(&self, identities: &[String]) -> Result<(), AccessDenial> {
+        if let Some(entry) = self.blocked(identities) {
+            return Err(AccessDenial::Blocklisted(entry));
+        }
+        if let Some(allow) = &self.allowlist {
+            if !identities
+                .iter()
+                .any(|i| allow.contains(&i.to_ascii_lowercase()))
+            {
+                return Err(AccessDenial::NotAllowlisted);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let list = parse_list("# abusive nodes\nAABB\n\n  ccdd  # spam\n");
+        assert_eq!(list.len(), 2);
+        assert!(list.contains("aabb"));
+        assert!(list.contains("ccdd"));
+    }
+
+    #[test]
+    fn test_check() {
+        let lists = AccessLists {
+            blocklist: parse_list("aa"),
+            allowlist: Some(parse_list("aa\nbb")),
+        };
+        assert_eq!(
+            lists.check(&["AA".to_string()]),
+            Err(AccessDenial::Blocklisted("aa".to_string()))
+        );
+        assert_eq!(lists.check(&["bb".to_string()]), Ok(()));
+        assert_eq!(
+            lists.check(&["cc".to_string()]),
+            Err(AccessDenial::NotAllowlisted)
+        );
+
+        let open = AccessLists::default();
+        assert_eq!(open.check(&["cc".to_string()]), Ok(()));
+    }
+}
@@ -0,0 +1,364 @@
+//! Signed-message proof of address ownership
+//!
+//! Instead of listing outpoints, a registrant can sign a challenge naming its node id and a
+//! recent block hash with the key behind an address. Two signature formats are accepted:
+//!
+//! - Legacy `signmessage` (BIP-137): a 65-byte recoverable signature, for P2PKH and P2WPKH.
+//! - BIP-322 "simple": the witness of the virtual `to_sign` transaction, for P2WPKH and P2TR
+//!   (key path only).
+//!
+//! The weight of a proven address is its confirmed balance, looked up on the node.
+
+use super::reserve::hash160;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{ecdsa, schnorr, Message, PublicKey, Secp256k1, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Address proof attached to a registration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressProof {
+    pub address: String,
+    /// Challenge block hash, hex in the usual (byte-reversed) display order.
+    pub block_hash: String,
+    /// Base64 signature over [`challenge_message`]: BIP-137 or BIP-322 simple.
+    pub signature: String,
+}
+
+/// Address types that can be proven.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    P2pkh([u8; 20]),
+    P2wpkh([u8; 20]),
+    P2tr([u8; 32]),
+}
+
+impl AddressKind {
+    /// Parse a mainnet, testnet or regtest address.
+    pub fn parse(address: &str) -> Option<Self> {
+        if let Ok((hrp, version, program)) = bech32::segwit::decode(address) {
+            use bech32::hrp;
+            if hrp != hrp::BC && hrp != hrp::TB && hrp != hrp::BCRT {
+                return None;
+            }
+            return match (version.to_u8(), program.len()) {
+                (0, 20) => Some(Self::P2wpkh(program.try_into().ok()?)),
+                (1, 32) => Some(Self::P2tr(program.try_into().ok()?)),
+                _ => None,
+            };
+        }
+        let payload = bs58::decode(address).with_check(None).into_vec().ok()?;
+        match payload.split_first() {
+            Some((0x00 | 0x6f, hash)) if hash.len() == 20 => {
+                Some(Self::P2pkh(hash.try_into().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Output script locking to this address.
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        match self {
+            Self::P2pkh(hash) => {
+                let mut script = vec![0x76, 0xa9, 0x14];
+                script.extend_from_slice(hash);
+                script.extend_from_slice(&[0x88, 0xac]);
+                script
+            }
+            Self::P2wpkh(hash) => {
+                let mut script = vec![0x00, 0x14];
+                script.extend_from_slice(hash);
+                script
+            }
+            Self::P2tr(key) => {
+                let mut script = vec![0x51, 0x20];
+                script.extend_from_slice(key);
+                script
+            }
+        }
+    }
+}
+
+/// Parse a block hash given in display order.
+pub fn parse_block_hash(s: &str) -> Option<[u8; 32]> {
+    let mut hash: [u8; 32] = hex::decode(s).ok()?.try_into().ok()?;
+    hash.reverse();
+    Some(hash)
+}
+
+/// Canonical challenge signed by an address proof.
+pub fn challenge_message(node_id: &[u8; 32], block_hash: &[u8; 32]) -> String {
+    let mut display = *block_hash;
+    display.reverse();
+    format!(
+        "blvm-governance address proof\nnode_id:{}\nblock:{}",
+        hex::encode(node_id),
+        hex::encode(display)
+    )
+}
+
+/// Verify a base64 `signature` over `message` by the key behind `address`.
+pub fn verify(address: &AddressKind, message: &str, signature: &str) -> bool {
+    let Ok(bytes) = BASE64.decode(signature) else {
+        return false;
+    };
+    match bytes.first() {
+        Some(27..=42) if bytes.len() == 65 => verify_legacy(address, message, &bytes),
+        _ => verify_bip322(address, message.as_bytes(), &bytes),
+    }
+}
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(data)
+        .finalize()
+        .into()
+}
+
+fn push_varint(out: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        _ => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+    }
+}
+
+fn read_varint(data: &mut &[u8]) -> Option<usize> {
+    let (&first, rest) = data.split_first()?;
+    let (n, rest) = match first {
+        0xfd => (
+            u16::from_le_bytes(rest.get(..2)?.try_into().ok()?) as usize,
+            &rest[2..],
+        ),
+        0xfe => (
+            u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize,
+            &rest[4..],
+        ),
+        0xff => return None,
+        n => (n as usize, rest),
+    };
+    *data = rest;
+    Some(n)
+}
+
+/// BIP-137 `signmessage` verification by public key recovery.
+fn verify_legacy(address: &AddressKind, message: &str, sig: &[u8]) -> bool {
+    let header = sig[0] - 27;
+    let compressed = header >= 4;
+    let Ok(recid) = RecoveryId::from_i32((header & 3) as i32) else {
+        return false;
+    };
+    let Ok(sig) = RecoverableSignature::from_compact(&sig[1..], recid) else {
+        return false;
+    };
+    let mut data = b"\x18Bitcoin Signed Message:\n".to_vec();
+    push_varint(&mut data, message.len());
+    data.extend_from_slice(message.as_bytes());
+    let msg = Message::from_digest(sha256d(&data));
+    let Ok(pk) = Secp256k1::verification_only().recover_ecdsa(&msg, &sig) else {
+        return false;
+    };
+    match address {
+        AddressKind::P2pkh(hash) if compressed => hash160(&pk.serialize()) == *hash,
+        AddressKind::P2pkh(hash) => hash160(&pk.serialize_uncompressed()) == *hash,
+        AddressKind::P2wpkh(hash) => compressed && hash160(&pk.serialize()) == *hash,
+        AddressKind::P2tr(_) => false,
+    }
+}
+
+/// Txid of the BIP-322 `to_spend` transaction committing to `message` and `script_pubkey`.
+fn to_spend_txid(message: &[u8], script_pubkey: &[u8]) -> [u8; 32] {
+    let mut tx = vec![0, 0, 0, 0, 1];
+    tx.extend_from_slice(&[0u8; 32]);
+    tx.extend_from_slice(&[0xff; 4]);
+    tx.extend_from_slice(&[0x22, 0x00, 0x20]);
+    tx.extend_from_slice(&tagged_hash("BIP0322-signed-message", message));
+    tx.extend_from_slice(&[0, 0, 0, 0, 1]);
+    tx.extend_from_slice(&[0u8; 8]);
+    push_varint(&mut tx, script_pubkey.len());
+    tx.extend_from_slice(script_pubkey);
+    tx.extend_from_slice(&[0, 0, 0, 0]);
+    sha256d(&tx)
+}
+
+/// The single `to_sign` output: zero value, `OP_RETURN`.
+const TO_SIGN_OUTPUT: [u8; 10] = [0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x6a];
+
+/// BIP-322 simple verification: the signature is the `to_sign` input witness.
+fn verify_bip322(address: &AddressKind, message: &[u8], sig: &[u8]) -> bool {
+    let mut data = sig;
+    let Some(count) = read_varint(&mut data) else {
+        return false;
+    };
+    let mut witness = Vec::with_capacity(count.min(2));
+    for _ in 0..count {
+        let Some(len) = read_varint(&mut data) else {
+            return false;
+        };
+        let Some(item) = data.get(..len) else {
+            return false;
+        };
+        witness.push(item);
+        data = &data[len..];
+    }
+    if !data.is_empty() {
+        return false;
+    }
+
+    let script_pubkey = address.script_pubkey();
+    let mut prevout = to_spend_txid(message, &script_pubkey).to_vec();
+    prevout.extend_from_slice(&[0, 0, 0, 0]);
+    let secp = Secp256k1::verification_only();
+    match (address, witness.as_slice()) {
+        (AddressKind::P2wpkh(hash), [sig, pubkey]) => {
+            let Some((&0x01, der)) = sig.split_last() else {
+                return false;
+            };
+            if hash160(pubkey) != *hash {
+                return false;
+            }
+            let (Ok(pk), Ok(mut sig)) = (
+                PublicKey::from_slice(pubkey),
+                ecdsa::Signature::from_der(der),
+            ) else {
+                return false;
+            };
+            sig.normalize_s();
+            // BIP-143 sighash, SIGHASH_ALL
+            let mut preimage = vec![0, 0, 0, 0];
+            preimage.extend_from_slice(&sha256d(&prevout));
+            preimage.extend_from_slice(&sha256d(&[0, 0, 0, 0]));
+            preimage.extend_from_slice(&prevout);
+            preimage.extend_from_slice(&[0x19, 0x76, 0xa9, 0x14]);
+            preimage.extend_from_slice(hash);
+            preimage.extend_from_slice(&[0x88, 0xac]);
+            preimage.extend_from_slice(&[0u8; 8]);
+            preimage.extend_from_slice(&[0, 0, 0, 0]);
+            preimage.extend_from_slice(&sha256d(&TO_SIGN_OUTPUT));
+            preimage.extend_from_slice(&[0, 0, 0, 0]);
+            preimage.extend_from_slice(&[1, 0, 0, 0]);
+            let msg = Message::from_digest(sha256d(&preimage));
+            secp.verify_ecdsa(&msg, &sig, &pk).is_ok()
+        }
+        (AddressKind::P2tr(key), [sig]) => {
+            let (sig, hash_type) = match sig.len() {
+                64 => (&sig[..], 0x00),
+                65 if sig[64] == 0x01 => (&sig[..64], 0x01),
+                _ => return false,
+            };
+            let (Ok(pk), Ok(sig)) = (
+                XOnlyPublicKey::from_slice(key),
+                schnorr::Signature::from_slice(sig),
+            ) else {
+                return false;
+            };
+            // BIP-341 signature message, key path spend of input 0
+            let mut spks = Vec::new();
+            push_varint(&mut spks, script_pubkey.len());
+            spks.extend_from_slice(&script_pubkey);
+            let mut sigmsg = vec![0x00, hash_type, 0, 0, 0, 0, 0, 0, 0, 0];
+            sigmsg.extend_from_slice(&Sha256::digest(&prevout));
+            sigmsg.extend_from_slice(&Sha256::digest([0u8; 8]));
+            sigmsg.extend_from_slice(&Sha256::digest(&spks));
+            sigmsg.extend_from_slice(&Sha256::digest([0u8; 4]));
+            sigmsg.extend_from_slice(&Sha256::digest(TO_SIGN_OUTPUT));
+            sigmsg.extend_from_slice(&[0x00, 0, 0, 0, 0]);
+            let msg = Message::from_digest(tagged_hash("TapSighash", &sigmsg));
+            secp.verify_schnorr(&sig, &msg, &pk).is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-322 test vectors
+    const P2WPKH: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const P2TR: &str = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3";
+
+    #[test]
+    fn test_parse_addresses() {
+        assert!(matches!(
+            AddressKind::parse(P2WPKH),
+            Some(AddressKind::P2wpkh(_))
+        ));
+        assert!(matches!(
+            AddressKind::parse(P2TR),
+            Some(AddressKind::P2tr(_))
+        ));
+        assert!(matches!(
+            AddressKind::parse("1AujvcaXqCi8UBmVHLRkjBHVhTVT7qMJct"),
+            Some(AddressKind::P2pkh(_))
+        ));
+        assert!(AddressKind::parse("1AujvcaXqCi8UBmVHLRkjBHVhTVT7qMJcu").is_none());
+    }
+
+    #[test]
+    fn test_to_spend_txid() {
+        let spk = AddressKind::parse(P2WPKH).unwrap().script_pubkey();
+        let mut txid = to_spend_txid(b"", &spk);
+        txid.reverse();
+        assert_eq!(
+            hex::encode(txid),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+    }
+
+    #[test]
+    fn test_bip322_p2wpkh() {
+        let address = AddressKind::parse(P2WPKH).unwrap();
+        let empty = "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        let hello = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert!(verify(&address, "", empty));
+        assert!(verify(&address, "Hello World", hello));
+        assert!(!verify(&address, "Hello World", empty));
+    }
+
+    #[test]
+    fn test_bip322_p2tr() {
+        let address = AddressKind::parse(P2TR).unwrap();
+        let sig = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+        assert!(verify(&address, "Hello World", sig));
+        assert!(!verify(&address, "Hello", sig));
+    }
+
+    #[test]
+    fn test_legacy_signmessage() {
+        let p2pkh = AddressKind::parse("1AujvcaXqCi8UBmVHLRkjBHVhTVT7qMJct").unwrap();
+        let sig = "IIW+b7xgURU6TwcbnguMcJ0HoIgPoaxIwxys4zF9saGZBqFQA3p1fMpQkj4tYCxm+qsZ4jUG//Qh8SYsnmDWFEo=";
+        assert!(verify(&p2pkh, "Hello World", sig));
+        assert!(!verify(&p2pkh, "Hello", sig));
+
+        let p2wpkh = AddressKind::parse("bc1qdjef0u2jf73swx9hc52wwzxhs2705hmlgw203u").unwrap();
+        let sig = "KIW+b7xgURU6TwcbnguMcJ0HoIgPoaxIwxys4zF9saGZBqFQA3p1fMpQkj4tYCxm+qsZ4jUG//Qh8SYsnmDWFEo=";
+        assert!(verify(&p2wpkh, "Hello World", sig));
+        assert!(!verify(
+            &AddressKind::parse(P2WPKH).unwrap(),
+            "Hello World",
+            sig
+        ));
+    }
+
+    #[test]
+    fn test_challenge_uses_display_order() {
+        let hash = parse_block_hash(&format!("{}ff", "00".repeat(31))).unwrap();
+        assert_eq!(hash[0], 0xff);
+        assert!(challenge_message(&[1u8; 32], &hash).ends_with(&format!("{}ff", "00".repeat(31))));
+    }
+}
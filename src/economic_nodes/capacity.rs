@@ -0,0 +1,100 @@
+//! Registry capacity and eviction policy
+//!
+//! When the registry holds `max_nodes` entries, a new registration is accepted only if it
+//! outranks the weakest evictable entry, which is then moved to the archive.
+//!
+//! Ranking (weakest first):
+//! 1. lowest verified weight,
+//! 2. then oldest `last_seen`,
+//! 3. then lowest node id, so ties resolve the same way on every node.
+//!
+//! An incoming registration outranks the weakest entry only if it is strictly stronger on
+//! (verified weight, last_seen); exact ties keep the existing entry. Nodes with a veto on a
+//! still-open proposal are never evicted.
+
+use super::EconomicNode;
+use std::collections::HashMap;
+
+/// Rank of an entry under the eviction policy; smaller is weaker.
+pub fn rank(node: &EconomicNode) -> (u64, u64) {
+    (node.verified_weight, node.last_seen)
+}
+
+/// Whether a node has vetoed a proposal that is still open.
+pub fn has_active_veto(node: &EconomicNode) -> bool {
+    node.veto_history.iter().any(|v| v.outcome.is_none())
+}
+
+/// Pick the entry to evict for an incoming registration with rank `incoming`.
+///
+/// Returns `None` if no evictable entry is outranked, in which case the incoming
+/// registration must be rejected.
+pub fn select_eviction(
+    nodes: &HashMap<[u8; 32], EconomicNode>,
+    incoming: (u64, u64),
+) -> Option<[u8; 32]> {
+    let weakest = nodes
+        .values()
+        .filter(|n| !has_active_veto(n))
+        .min_by(|a, b| rank(a).cmp(&rank(b)).then(a.node_id.cmp(&b.node_id)))?;
+    if incoming > rank(weakest) {
+        Some(weakest.node_id)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::VetoRecord;
+
+    fn node(id: u8, weight: u64, last_seen: u64) -> EconomicNode {
+        let mut n = EconomicNode::new([id; 32], 0);
+        n.verified_weight = weight;
+        n.last_seen = last_seen;
+        n
+    }
+
+    fn registry(nodes: Vec<EconomicNode>) -> HashMap<[u8; 32], EconomicNode> {
+        nodes.into_iter().map(|n| (n.node_id, n)).collect()
+    }
+
+    #[test]
+    fn test_evicts_lowest_weight() {
+        let nodes = registry(vec![node(1, 100, 10), node(2, 50, 20), node(3, 200, 5)]);
+        assert_eq!(select_eviction(&nodes, (60, 30)), Some([2u8; 32]));
+        assert_eq!(select_eviction(&nodes, (40, 30)), None);
+    }
+
+    #[test]
+    fn test_weight_tie_broken_by_last_seen() {
+        let nodes = registry(vec![node(1, 50, 10), node(2, 50, 5)]);
+        assert_eq!(select_eviction(&nodes, (50, 30)), Some([2u8; 32]));
+    }
+
+    #[test]
+    fn test_full_tie_broken_by_node_id_and_keeps_existing() {
+        let nodes = registry(vec![node(2, 50, 10), node(1, 50, 10)]);
+        // Incoming strictly newer: evict the lowest node id among the tied entries
+        assert_eq!(select_eviction(&nodes, (50, 11)), Some([1u8; 32]));
+        // Exact tie with the weakest: existing entry stays
+        assert_eq!(select_eviction(&nodes, (50, 10)), None);
+    }
+
+    #[test]
+    fn test_never_evicts_active_veto() {
+        let mut vetoing = node(1, 0, 0);
+        vetoing.veto_history.push(VetoRecord {
+            proposal_id: "open".to_string(),
+            height: 0,
+            reason: String::new(),
+            outcome: None,
+        });
+        let nodes = registry(vec![vetoing, node(2, 100, 10)]);
+        assert_eq!(select_eviction(&nodes, (500, 20)), Some([2u8; 32]));
+
+        let only_vetoing = registry(vec![nodes[&[1u8; 32]].clone()]);
+        assert_eq!(select_eviction(&only_vetoing, (500, 20)), None);
+    }
+}
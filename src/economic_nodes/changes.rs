@@ -0,0 +1,90 @@
+//! Registry change notifications
+//!
+//! A low-volume feed of registry-level changes (activation, expiry, pruning, re-verification,
+//! key rotation, snapshots) broadcast to in-process subscribers such as the webhook client.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Activated,
+    Expired,
+    Pruned,
+    Reverified,
+    KeyRotated,
+    SnapshotTaken,
+}
+
+impl ChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Activated => "activated",
+            Self::Expired => "expired",
+            Self::Pruned => "pruned",
+            Self::Reverified => "reverified",
+            Self::KeyRotated => "key_rotated",
+            Self::SnapshotTaken => "snapshot_taken",
+        }
+    }
+}
+
+/// What triggered a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSource {
+    /// Node events and registrations.
+    Organic,
+    /// Reconciliation against the node's economic node set.
+    Reconciliation,
+}
+
+/// One registry change, sent to the webhook as the data of its event type (see
+/// [`crate::schema`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryChange {
+    /// Hex node id; `None` for registry-wide changes such as snapshots.
+    pub node_id: Option<String>,
+    pub kind: ChangeKind,
+    pub before: Value,
+    pub after: Value,
+    pub height: u64,
+    pub source: ChangeSource,
+    /// Intent recorded for the webhook notification of the change, completed once it is
+    /// sent (see [`crate::intent`]).
+    #[serde(skip)]
+    pub intent: Option<u64>,
+}
+
+impl RegistryChange {
+    /// Webhook event type, e.g. `registry_expired`.
+    pub fn event_type(&self) -> String {
+        format!("registry_{}", self.kind.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type_and_payload() {
+        let change = RegistryChange {
+            node_id: Some("aa".to_string()),
+            kind: ChangeKind::KeyRotated,
+            before: Value::from("02aa"),
+            after: Value::from("02bb"),
+            height: 7,
+            source: ChangeSource::Reconciliation,
+            intent: Some(3),
+        };
+        assert_eq!(change.event_type(), "registry_key_rotated");
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["kind"], "key_rotated");
+        assert_eq!(json["source"], "reconciliation");
+        assert!(json.get("intent").is_none());
+    }
+}
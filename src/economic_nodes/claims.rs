@@ -0,0 +1,134 @@
+//! Reserve backing at most one node
+//!
+//! An outpoint, whether from a reserve claim or a Lightning channel, or a proven address,
+//! backs the weight of one verified, active node. The first node registered with it keeps
+//! it: a registration of another node id claiming it is refused, so one holder cannot
+//! multiply their weight by registering more ids. The index is built from the registry's
+//! nodes under the same lock as the registration, so it cannot drift from them.
+
+use super::address_proof::AddressKind;
+use super::{ClaimedOutpoint, EconomicNode, VerificationStatus};
+use crate::error::GovernanceError;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Something that backs a node's verified weight.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReserveSource {
+    Outpoint(ClaimedOutpoint),
+    /// A proven address, by its output script.
+    Address(Vec<u8>),
+}
+
+impl fmt::Display for ReserveSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Outpoint(outpoint) => write!(f, "outpoint {}", outpoint),
+            Self::Address(script) => write!(f, "address with script {}", hex::encode(script)),
+        }
+    }
+}
+
+/// Sources for `outpoints`.
+pub fn outpoints<'a>(
+    outpoints: impl IntoIterator<Item = &'a ClaimedOutpoint>,
+) -> Vec<ReserveSource> {
+    outpoints
+        .into_iter()
+        .map(|o| ReserveSource::Outpoint(*o))
+        .collect()
+}
+
+/// What backs `node`'s weight: its claimed and channel outpoints and its proven address.
+pub fn sources(node: &EconomicNode) -> Vec<ReserveSource> {
+    let claimed = node.reserve_claim.iter().flat_map(|c| c.outpoints.iter());
+    let channels = node
+        .lightning
+        .iter()
+        .flat_map(|l| l.channel_outpoints.iter());
+    let mut sources = outpoints(claimed.chain(channels));
+    let address = node
+        .address_proof
+        .as_ref()
+        .and_then(|p| AddressKind::parse(&p.address));
+    sources.extend(address.map(|a| ReserveSource::Address(a.script_pubkey())));
+    sources
+}
+
+/// The node each source backs, over verified, active nodes.
+pub fn index(nodes: &HashMap<[u8; 32], EconomicNode>) -> HashMap<ReserveSource, [u8; 32]> {
+    nodes
+        .values()
+        .filter(|n| n.deactivated.is_none())
+        .filter(|n| matches!(n.verification, VerificationStatus::Verified { .. }))
+        .flat_map(|n| sources(n).into_iter().map(move |s| (s, n.node_id)))
+        .collect()
+}
+
+/// Refuse `claimed` for `node_id` if another node already holds any of it. `field` names
+/// the registration field in the error.
+pub fn check_unclaimed(
+    nodes: &HashMap<[u8; 32], EconomicNode>,
+    node_id: &[u8; 32],
+    claimed: &[ReserveSource],
+    field: &str,
+) -> Result<(), GovernanceError> {
+    if claimed.is_empty() {
+        return Ok(());
+    }
+    let index = index(nodes);
+    for source in claimed {
+        if let Some(holder) = index.get(source).filter(|h| *h != node_id) {
+            return Err(GovernanceError::ValidationError {
+                field: field.to_string(),
+                reason: format!(
+                    "{} already backs economic node {}",
+                    source,
+                    hex::encode(holder)
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::ReserveClaim;
+
+    fn node(id: u8, outpoint: ClaimedOutpoint, verified: bool) -> EconomicNode {
+        let mut node = EconomicNode::new([id; 32], 0);
+        node.reserve_claim = Some(ReserveClaim {
+            public_key: vec![2; 33],
+            outpoints: vec![outpoint],
+            signature: vec![0; 64],
+        });
+        if verified {
+            node.verification = VerificationStatus::Verified { height: 0 };
+        }
+        node
+    }
+
+    #[test]
+    fn test_first_verified_holder_keeps_an_outpoint() {
+        let outpoint = ClaimedOutpoint {
+            txid: [7; 32],
+            vout: 0,
+        };
+        let claimed = outpoints([&outpoint]);
+        let mut nodes = HashMap::new();
+        nodes.insert([1; 32], node(1, outpoint, true));
+
+        assert!(check_unclaimed(&nodes, &[1; 32], &claimed, "reserve.outpoints").is_ok());
+        assert!(check_unclaimed(&nodes, &[2; 32], &claimed, "reserve.outpoints").is_err());
+
+        // Claims that failed verification, or of deactivated nodes, hold nothing
+        nodes.insert([1; 32], node(1, outpoint, false));
+        assert!(check_unclaimed(&nodes, &[2; 32], &claimed, "reserve.outpoints").is_ok());
+        let mut deactivated = node(1, outpoint, true);
+        deactivated.deactivated = Some("blocklisted".to_string());
+        nodes.insert([1; 32], deactivated);
+        assert!(check_unclaimed(&nodes, &[2; 32], &claimed, "reserve.outpoints").is_ok());
+    }
+}
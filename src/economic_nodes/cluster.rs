@@ -0,0 +1,354 @@
+//! Sybil clustering heuristics
+//!
+//! Groups registrations that look like one entity split across several node ids. Pairs of
+//! nodes are linked by shared signals, each with a fixed strength; a pair's confidence is
+//! `1 - Π(1 - strength)` over its signals and pairs at or above the configured minimum are
+//! merged into clusters. A cluster's confidence is that of its weakest link.
+//!
+//! Signals: the same x-only key (registration, threshold set, reserve or Lightning key),
+//! overlapping claimed outpoints, the same proven address, identical registration metadata,
+//! and registrations arriving in a tight burst. BIP32 sibling keys cannot be recognized from
+//! public keys alone (no chain codes are registered), so they are not detected.
+//!
+//! Analysis is read-only. With `cap_percentile` set, tallies scale the weight of each large
+//! cluster down to the cap; stored records are never changed.
+
+use super::{decay, EconomicNode};
+use crate::config::{DecayConfig, SybilConfig};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Evidence linking two registrations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterSignal {
+    SharedKey,
+    SharedOutpoint,
+    SharedAddress,
+    IdenticalMetadata,
+    RegistrationBurst,
+}
+
+impl ClusterSignal {
+    /// Probability-like strength of the signal on its own.
+    pub fn strength(self) -> f64 {
+        match self {
+            Self::SharedKey => 0.95,
+            Self::SharedOutpoint | Self::SharedAddress => 0.9,
+            Self::IdenticalMetadata => 0.4,
+            Self::RegistrationBurst => 0.3,
+        }
+    }
+
+    fn is_weak(self) -> bool {
+        matches!(self, Self::IdenticalMetadata | Self::RegistrationBurst)
+    }
+}
+
+/// A group of registrations suspected to be one entity.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuspectedCluster {
+    /// Member node ids (hex), sorted.
+    pub members: Vec<String>,
+    pub signals: BTreeSet<ClusterSignal>,
+    pub confidence: f64,
+    /// Sum of the members' effective weights.
+    pub combined_weight: f64,
+}
+
+/// Result of a clustering pass.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClusterReport {
+    pub height: u64,
+    /// Clusters, most confident first.
+    pub clusters: Vec<SuspectedCluster>,
+}
+
+fn xonly(key: &[u8]) -> Option<Vec<u8>> {
+    match key.len() {
+        32 => Some(key.to_vec()),
+        33 | 65 => Some(key[1..33].to_vec()),
+        _ => None,
+    }
+}
+
+fn identities(node: &EconomicNode) -> Vec<(ClusterSignal, Vec<u8>)> {
+    let mut keys: Vec<&[u8]> = vec![node.public_key.as_slice()];
+    if let Some(set) = &node.keys {
+        keys.extend(set.public_keys.iter().map(Vec::as_slice));
+    }
+    if let Some(claim) = &node.reserve_claim {
+        keys.push(&claim.public_key);
+    }
+    if let Some(ln) = &node.lightning {
+        keys.push(&ln.node_pubkey);
+    }
+    let mut out: Vec<(ClusterSignal, Vec<u8>)> = keys
+        .into_iter()
+        .filter_map(xonly)
+        .map(|k| (ClusterSignal::SharedKey, k))
+        .collect();
+    let outpoints = node
+        .reserve_claim
+        .iter()
+        .flat_map(|c| c.outpoints.iter())
+        .chain(
+            node.lightning
+                .iter()
+                .flat_map(|l| l.channel_outpoints.iter()),
+        );
+    for o in outpoints {
+        out.push((ClusterSignal::SharedOutpoint, o.to_string().into_bytes()));
+    }
+    if let Some(proof) = &node.address_proof {
+        out.push((
+            ClusterSignal::SharedAddress,
+            proof.address.to_ascii_lowercase().into_bytes(),
+        ));
+    }
+    if node.hashpower_percentage > 0.0 {
+        let metadata = format!("{}|{}", node.node_type, node.hashpower_percentage);
+        out.push((ClusterSignal::IdenticalMetadata, metadata.into_bytes()));
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn find(&mut self, i: usize) -> usize {
+        let parent = self.0[i];
+        if parent == i {
+            return i;
+        }
+        let root = self.find(parent);
+        self.0[i] = root;
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a] = b;
+    }
+}
+
+/// Cluster the given (non-deactivated) nodes.
+pub fn analyze<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    height: u64,
+    config: &SybilConfig,
+    decay_config: &DecayConfig,
+) -> ClusterReport {
+    let nodes: Vec<&EconomicNode> = nodes
+        .into_iter()
+        .filter(|n| n.deactivated.is_none())
+        .collect();
+
+    // Members of each bucket are linked in a chain; clustering is transitive anyway
+    let mut edges: HashMap<(usize, usize), BTreeSet<ClusterSignal>> = HashMap::new();
+    let mut link = |group: &[usize], signal: ClusterSignal| {
+        if signal.is_weak() && group.len() > config.max_weak_group {
+            return;
+        }
+        for pair in group.windows(2) {
+            let key = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+            edges.entry(key).or_default().insert(signal);
+        }
+    };
+
+    let mut buckets: HashMap<(ClusterSignal, Vec<u8>), Vec<usize>> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        for identity in identities(node) {
+            buckets.entry(identity).or_default().push(i);
+        }
+    }
+    for ((signal, _), members) in &buckets {
+        if members.len() > 1 {
+            link(members.as_slice(), *signal);
+        }
+    }
+
+    let mut by_arrival: Vec<usize> = (0..nodes.len()).collect();
+    by_arrival.sort_by_key(|&i| (nodes[i].registered_at, nodes[i].node_id));
+    let mut burst: Vec<usize> = Vec::new();
+    for i in by_arrival {
+        let close = burst.last().is_some_and(|&last| {
+            nodes[i].registered_at - nodes[last].registered_at <= config.burst_window_blocks
+        });
+        if !close {
+            link(burst.as_slice(), ClusterSignal::RegistrationBurst);
+            burst.clear();
+        }
+        burst.push(i);
+    }
+    link(burst.as_slice(), ClusterSignal::RegistrationBurst);
+
+    let mut uf = UnionFind((0..nodes.len()).collect());
+    let mut accepted = Vec::new();
+    for ((a, b), signals) in edges {
+        let confidence = 1.0 - signals.iter().map(|s| 1.0 - s.strength()).product::<f64>();
+        if confidence >= config.min_confidence {
+            uf.union(a, b);
+            accepted.push((a, signals, confidence));
+        }
+    }
+
+    let mut clusters: HashMap<usize, SuspectedCluster> = HashMap::new();
+    for (a, signals, confidence) in accepted {
+        let cluster = clusters
+            .entry(uf.find(a))
+            .or_insert_with(|| SuspectedCluster {
+                members: Vec::new(),
+                signals: BTreeSet::new(),
+                confidence: 1.0,
+                combined_weight: 0.0,
+            });
+        cluster.signals.extend(signals);
+        cluster.confidence = cluster.confidence.min(confidence);
+    }
+    for (i, node) in nodes.iter().enumerate() {
+        if let Some(cluster) = clusters.get_mut(&uf.find(i)) {
+            cluster.members.push(hex::encode(node.node_id));
+            cluster.combined_weight += decay::effective_weight(node, height, decay_config);
+        }
+    }
+    let mut clusters: Vec<SuspectedCluster> = clusters
+        .into_values()
+        .map(|mut c| {
+            c.members.sort();
+            c
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.members.cmp(&b.members))
+    });
+    ClusterReport { height, clusters }
+}
+
+/// Per-node weight factors capping each cluster's combined weight at the `percentile`-th
+/// percentile of individual effective weights. Nodes not listed keep full weight.
+pub fn weight_caps<'a>(
+    report: &ClusterReport,
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    percentile: f64,
+    decay_config: &DecayConfig,
+) -> HashMap<[u8; 32], f64> {
+    let mut weights: Vec<f64> = nodes
+        .into_iter()
+        .filter(|n| n.deactivated.is_none())
+        .map(|n| decay::effective_weight(n, report.height, decay_config))
+        .collect();
+    if weights.is_empty() {
+        return HashMap::new();
+    }
+    weights.sort_by(f64::total_cmp);
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * weights.len() as f64).ceil() as usize;
+    let cap = weights[rank.clamp(1, weights.len()) - 1];
+
+    let mut factors = HashMap::new();
+    for cluster in &report.clusters {
+        if cluster.combined_weight <= cap {
+            continue;
+        }
+        let factor = cap / cluster.combined_weight;
+        for member in &cluster.members {
+            if let Some(id) = super::parse_node_id(member) {
+                factors.insert(id, factor);
+            }
+        }
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::{ClaimedOutpoint, ReserveClaim, VerificationStatus};
+
+    fn node(id: u8, registered_at: u64, weight: f64) -> EconomicNode {
+        let mut n = EconomicNode::new([id; 32], registered_at);
+        n.hashpower_percentage = weight;
+        n.verified_weight = weight as u64;
+        n.verification = VerificationStatus::Verified { height: 0 };
+        n
+    }
+
+    fn key(prefix: u8, x: u8) -> Vec<u8> {
+        let mut key = vec![prefix];
+        key.extend_from_slice(&[x; 32]);
+        key
+    }
+
+    fn analyze_default(nodes: &[EconomicNode]) -> ClusterReport {
+        analyze(
+            nodes,
+            1000,
+            &SybilConfig::default(),
+            &DecayConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_shared_xonly_key_and_outpoint() {
+        let mut a = node(1, 10, 5.0);
+        a.public_key = key(0x02, 7);
+        let mut b = node(2, 500, 6.0);
+        b.public_key = key(0x03, 7);
+        let claim = |pk: u8| ReserveClaim {
+            public_key: vec![pk; 33],
+            outpoints: vec![ClaimedOutpoint {
+                txid: [9u8; 32],
+                vout: 0,
+            }],
+            signature: Vec::new(),
+        };
+        b.reserve_claim = Some(claim(4));
+        let mut c = node(3, 900, 7.0);
+        c.reserve_claim = Some(claim(5));
+        let d = node(4, 300, 8.0);
+
+        let report = analyze_default(&[a, b, c, d]);
+        assert_eq!(report.clusters.len(), 1);
+        let cluster = &report.clusters[0];
+        assert_eq!(cluster.members.len(), 3);
+        assert_eq!(cluster.combined_weight, 18.0);
+        assert!(cluster.signals.contains(&ClusterSignal::SharedKey));
+        assert!(cluster.signals.contains(&ClusterSignal::SharedOutpoint));
+        assert!((cluster.confidence - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_weak_signals_need_each_other() {
+        // Same metadata alone, or a burst alone, is not enough
+        let report = analyze_default(&[node(1, 10, 5.0), node(2, 500, 5.0)]);
+        assert!(report.clusters.is_empty());
+        let report = analyze_default(&[node(1, 10, 5.0), node(2, 10, 6.0)]);
+        assert!(report.clusters.is_empty());
+
+        // Together they are
+        let report = analyze_default(&[node(1, 10, 5.0), node(2, 11, 5.0)]);
+        assert_eq!(report.clusters.len(), 1);
+        assert!(report.clusters[0].confidence > 0.5);
+    }
+
+    #[test]
+    fn test_weight_caps() {
+        let mut nodes: Vec<EconomicNode> = (1..=8).map(|i| node(i, i as u64 * 100, 1.0)).collect();
+        for (i, n) in nodes.iter_mut().take(3).enumerate() {
+            n.verified_weight = 2 + i as u64;
+            n.public_key = vec![0x02; 33];
+        }
+        let report = analyze_default(&nodes);
+        assert_eq!(report.clusters[0].combined_weight, 9.0);
+
+        // 80th percentile of individual weights is 3.0
+        let caps = weight_caps(&report, &nodes, 80.0, &DecayConfig::default());
+        assert_eq!(caps.len(), 3);
+        assert_eq!(caps[&[1u8; 32]], 3.0 / 9.0);
+        assert!(!caps.contains_key(&[4u8; 32]));
+    }
+}
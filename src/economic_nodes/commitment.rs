@@ -0,0 +1,249 @@
+//! Deterministic registry commitment
+//!
+//! Independent nodes compare a merkle root over the registry to confirm they agree on the
+//! economic node set. The encoding below is part of the module's external contract: any
+//! change must bump [`COMMITMENT_VERSION`] and add a fixture for the new version under
+//! `tests/fixtures/commitment`. Older versions stay computable with [`compute_version`].
+//!
+//! Version 1 leaf encoding, per node (all integers big-endian):
+//!
+//! | field                          | encoding                          |
+//! |--------------------------------|-----------------------------------|
+//! | `node_id`                      | 32 bytes                          |
+//! | `public_key`                   | u32 length, then bytes            |
+//! | `hashpower_percentage`         | u64 IEEE-754 bit pattern          |
+//! | `economic_activity_percentage` | u64 IEEE-754 bit pattern          |
+//! | `registered_at`                | u64                               |
+//! | `verified_weight`              | u64                               |
+//! | `verification`                 | u8: 0 unverified, 1 verified, 2 failed |
+//!
+//! Version 2 covers only active entries: deactivated nodes are left out, so deactivating
+//! or reactivating a node changes the root. Each leaf is the version 1 encoding followed by:
+//!
+//! | field                          | encoding                          |
+//! |--------------------------------|-----------------------------------|
+//! | `keys`                         | u8 0 if none, else 1, u32 threshold, u32 key count, then per key u32 length and bytes |
+//! | `lightning`                    | u8 0 if none, else 1, u32 length and node key bytes, u32 channel count, then per channel 32-byte txid and u32 vout |
+//!
+//! Version 1 covers every entry it is given and has no key set or Lightning fields.
+//!
+//! Volatile fields (`last_seen`, liveness counters, veto history, verification height and
+//! failure reason) are excluded. Leaves are sorted by `node_id`, hashed as
+//! `SHA256d(0x00 || encoding)`, and combined pairwise as `SHA256d(0x01 || left || right)`,
+//! duplicating the last hash on odd levels. The root of an empty registry is all zeros.
+
+use super::{EconomicNode, VerificationStatus};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Version of the commitment encoding.
+pub const COMMITMENT_VERSION: u32 = 2;
+
+/// Registry commitment as exposed to queries and exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryCommitment {
+    pub version: u32,
+    #[serde(with = "hex_root")]
+    pub root: [u8; 32],
+    pub node_count: usize,
+}
+
+impl RegistryCommitment {
+    pub fn root_hex(&self) -> String {
+        hex::encode(self.root)
+    }
+}
+
+mod hex_root {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(root: &[u8; 32], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&hex::encode(root))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 32], D::Error> {
+        let s = String::deserialize(d)?;
+        let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+        bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("commitment root must be 32 bytes"))
+    }
+}
+
+/// Canonical version 1 encoding of one registry entry.
+pub fn encode_node_v1(node: &EconomicNode) -> Vec<u8> {
+    let mut out = Vec::with_capacity(32 + 4 + node.public_key.len() + 33);
+    out.extend_from_slice(&node.node_id);
+    out.extend_from_slice(&(node.public_key.len() as u32).to_be_bytes());
+    out.extend_from_slice(&node.public_key);
+    out.extend_from_slice(&node.hashpower_percentage.to_bits().to_be_bytes());
+    out.extend_from_slice(&node.economic_activity_percentage.to_bits().to_be_bytes());
+    out.extend_from_slice(&node.registered_at.to_be_bytes());
+    out.extend_from_slice(&node.verified_weight.to_be_bytes());
+    out.push(match node.verification {
+        VerificationStatus::Unverified => 0,
+        VerificationStatus::Verified { .. } => 1,
+        VerificationStatus::VerificationFailed { .. } => 2,
+    });
+    out
+}
+
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Canonical encoding of one registry entry at [`COMMITMENT_VERSION`].
+pub fn encode_node(node: &EconomicNode) -> Vec<u8> {
+    let mut out = encode_node_v1(node);
+    match &node.keys {
+        None => out.push(0),
+        Some(keys) => {
+            out.push(1);
+            out.extend_from_slice(&keys.threshold.to_be_bytes());
+            out.extend_from_slice(&(keys.public_keys.len() as u32).to_be_bytes());
+            for key in &keys.public_keys {
+                push_bytes(&mut out, key);
+            }
+        }
+    }
+    match &node.lightning {
+        None => out.push(0),
+        Some(identity) => {
+            out.push(1);
+            push_bytes(&mut out, &identity.node_pubkey);
+            out.extend_from_slice(&(identity.channel_outpoints.len() as u32).to_be_bytes());
+            for outpoint in &identity.channel_outpoints {
+                out.extend_from_slice(&outpoint.txid);
+                out.extend_from_slice(&outpoint.vout.to_be_bytes());
+            }
+        }
+    }
+    out
+}
+
+fn sha256d(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    Sha256::digest(hasher.finalize()).into()
+}
+
+/// Merkle root over the given leaf hashes.
+pub fn merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| sha256d(&[&[0x01u8][..], &pair[0][..], &pair[1][..]]))
+            .collect();
+    }
+    level[0]
+}
+
+/// Compute the commitment over a set of registry entries (order does not matter).
+pub fn compute<'a>(nodes: impl IntoIterator<Item = &'a EconomicNode>) -> RegistryCommitment {
+    compute_version(COMMITMENT_VERSION, nodes).expect("the current version is supported")
+}
+
+/// Compute the commitment under encoding `version`, or `None` if the version is unknown.
+pub fn compute_version<'a>(
+    version: u32,
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+) -> Option<RegistryCommitment> {
+    let (encode, active_only): (fn(&EconomicNode) -> Vec<u8>, bool) = match version {
+        1 => (encode_node_v1, false),
+        2 => (encode_node, true),
+        _ => return None,
+    };
+    let mut sorted: Vec<&EconomicNode> = nodes
+        .into_iter()
+        .filter(|n| !active_only || n.deactivated.is_none())
+        .collect();
+    sorted.sort_by_key(|n| n.node_id);
+    let leaves: Vec<[u8; 32]> = sorted
+        .iter()
+        .map(|n| sha256d(&[&[0x00u8][..], &encode(n)[..]]))
+        .collect();
+    Some(RegistryCommitment {
+        version,
+        root: merkle_root(leaves),
+        node_count: sorted.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<EconomicNode> {
+        let mut a = EconomicNode::new([1u8; 32], 100);
+        a.hashpower_percentage = 0.5;
+        let mut b = EconomicNode::new([2u8; 32], 200);
+        b.public_key = vec![0x02; 33];
+        b.verified_weight = 50_000;
+        b.verification = VerificationStatus::Verified { height: 210 };
+        let mut c = EconomicNode::new([3u8; 32], 300);
+        c.economic_activity_percentage = 12.5;
+        c.verification = VerificationStatus::VerificationFailed {
+            reason: "spent".to_string(),
+        };
+        vec![a, b, c]
+    }
+
+    #[test]
+    fn test_empty_registry() {
+        let c = compute(std::iter::empty::<&EconomicNode>());
+        assert_eq!(c.root, [0u8; 32]);
+        assert_eq!(c.node_count, 0);
+    }
+
+    /// Pinned value: changing it means the version 1 encoding changed.
+    #[test]
+    fn test_pinned_v1_root() {
+        let nodes = fixture();
+        let c = compute_version(1, &nodes).unwrap();
+        assert_eq!(c.version, 1);
+        assert_eq!(
+            c.root_hex(),
+            "253852f3aa559421f756835dea04b460932ef34a03369c57e2fefb443e64cad5"
+        );
+    }
+
+    #[test]
+    fn test_order_and_volatile_fields_ignored() {
+        let nodes = fixture();
+        let mut shuffled = vec![nodes[2].clone(), nodes[0].clone(), nodes[1].clone()];
+        shuffled[0].last_seen = 999_999;
+        shuffled[1].liveness_samples = 42;
+        shuffled[2].verification = VerificationStatus::Verified { height: 1 };
+        assert_eq!(compute(&nodes), compute(&shuffled));
+    }
+
+    #[test]
+    fn test_deactivated_entries_left_out() {
+        let nodes = fixture();
+        let mut deactivated = nodes.clone();
+        deactivated[0].deactivated = Some("blocklisted".to_string());
+        let c = compute(&deactivated);
+        assert_eq!(c.node_count, 2);
+        assert_eq!(c, compute(&nodes[1..]));
+        // Version 1 covers every entry
+        assert_eq!(compute_version(1, &deactivated).unwrap().node_count, 3);
+        assert!(compute_version(COMMITMENT_VERSION + 1, &nodes).is_none());
+    }
+
+    #[test]
+    fn test_weight_change_changes_root() {
+        let nodes = fixture();
+        let mut changed = nodes.clone();
+        changed[1].verified_weight += 1;
+        assert_ne!(compute(&nodes).root, compute(&changed).root);
+    }
+}
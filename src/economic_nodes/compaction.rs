@@ -0,0 +1,154 @@
+//! Registry store compaction
+//!
+//! Drops archived nodes past their retention, coalesces weight histories (no-op records,
+//! records at the same height, records beyond the configured retention) and re-encodes
+//! every record, so the store is rewritten without the garbage older writes left behind.
+
+use super::schema::Records;
+use super::{history, ArchivedNode, EconomicNode, ARCHIVE_KEY, STORAGE_KEY};
+use crate::config::CompactionConfig;
+use crate::error::GovernanceError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+
+/// What a compaction removed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CompactionSummary {
+    pub height: u64,
+    pub archived_dropped: usize,
+    pub history_records_dropped: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Stored size of the records, in bytes.
+pub fn size(records: &Records) -> u64 {
+    records
+        .iter()
+        .map(|(k, v)| (k.len() + v.len()) as u64)
+        .sum()
+}
+
+fn decode<T: DeserializeOwned + Default>(
+    records: &Records,
+    key: &[u8],
+) -> Result<T, GovernanceError> {
+    match records.get(key) {
+        Some(data) => bincode::deserialize(data).map_err(GovernanceError::encoding("deserialize")),
+        None => Ok(T::default()),
+    }
+}
+
+fn encode<T: Serialize>(
+    records: &mut Records,
+    key: &[u8],
+    value: &T,
+) -> Result<(), GovernanceError> {
+    let data = bincode::serialize(value).map_err(GovernanceError::encoding("serialize"))?;
+    records.insert(key.to_vec(), data);
+    Ok(())
+}
+
+/// Compact `records` (at the current schema version) as of `height`.
+pub fn compact(
+    records: &mut Records,
+    height: u64,
+    config: &CompactionConfig,
+    history_len: usize,
+) -> Result<CompactionSummary, GovernanceError> {
+    let bytes_before = size(records);
+    let mut nodes: HashMap<[u8; 32], EconomicNode> = decode(records, STORAGE_KEY)?;
+    let mut archive: HashMap<[u8; 32], ArchivedNode> = decode(records, ARCHIVE_KEY)?;
+
+    let archived_before = archive.len();
+    if config.archive_retention_blocks > 0 {
+        archive.retain(|_, a| {
+            a.archived_at
+                .saturating_add(config.archive_retention_blocks)
+                > height
+        });
+    }
+    let history_records_dropped: usize = nodes
+        .values_mut()
+        .chain(archive.values_mut().map(|a| &mut a.node))
+        .map(|node| history::coalesce(node, history_len))
+        .sum();
+
+    if records.contains_key(STORAGE_KEY) {
+        encode(records, STORAGE_KEY, &nodes)?;
+    }
+    if records.contains_key(ARCHIVE_KEY) {
+        encode(records, ARCHIVE_KEY, &archive)?;
+    }
+    Ok(CompactionSummary {
+        height,
+        archived_dropped: archived_before - archive.len(),
+        history_records_dropped,
+        bytes_before,
+        bytes_after: size(records),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::history::{WeightChangeReason, WeightPoint, WeightRecord};
+
+    fn archived(id: u8, archived_at: u64) -> ([u8; 32], ArchivedNode) {
+        (
+            [id; 32],
+            ArchivedNode {
+                node: EconomicNode::new([id; 32], 0),
+                archived_at,
+            },
+        )
+    }
+
+    #[test]
+    fn test_compact_drops_expired_archives_and_history() {
+        let mut node = EconomicNode::new([1u8; 32], 0);
+        let point = WeightPoint {
+            weight: 1.0,
+            verified_sats: 0,
+        };
+        node.weight_history = vec![WeightRecord {
+            height: 5,
+            old: point,
+            new: point,
+            reason: WeightChangeReason::Registration,
+        }];
+        let nodes = HashMap::from([(node.node_id, node)]);
+        let archive = HashMap::from([archived(2, 100), archived(3, 900)]);
+        let mut records = Records::new();
+        encode(&mut records, STORAGE_KEY, &nodes).unwrap();
+        encode(&mut records, ARCHIVE_KEY, &archive).unwrap();
+
+        let config = CompactionConfig {
+            archive_retention_blocks: 500,
+            auto_threshold_bytes: 0,
+        };
+        let summary = compact(&mut records, 1000, &config, 16).unwrap();
+        assert_eq!(summary.archived_dropped, 1);
+        assert_eq!(summary.history_records_dropped, 1);
+        assert!(summary.bytes_after < summary.bytes_before);
+
+        let archive: HashMap<[u8; 32], ArchivedNode> = decode(&records, ARCHIVE_KEY).unwrap();
+        assert!(archive.contains_key(&[3u8; 32]));
+        let nodes: HashMap<[u8; 32], EconomicNode> = decode(&records, STORAGE_KEY).unwrap();
+        assert!(nodes[&[1u8; 32]].weight_history.is_empty());
+    }
+
+    #[test]
+    fn test_zero_retention_keeps_archive() {
+        let archive = HashMap::from([archived(2, 0)]);
+        let mut records = Records::new();
+        encode(&mut records, ARCHIVE_KEY, &archive).unwrap();
+        let config = CompactionConfig {
+            archive_retention_blocks: 0,
+            auto_threshold_bytes: 0,
+        };
+        let summary = compact(&mut records, 1_000_000, &config, 16).unwrap();
+        assert_eq!(summary.archived_dropped, 0);
+        assert!(!records.contains_key(STORAGE_KEY));
+    }
+}
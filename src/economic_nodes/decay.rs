@@ -0,0 +1,110 @@
+//! Decay of stale verified weight
+//!
+//! A node carries weight only for a verified reserve; the claimed hashpower is informational.
+//! A reserve verified long ago carries only part of its weight: the effective weight is the
+//! verified weight times a factor of the blocks since the last verification. Re-verification
+//! resets the clock. Unverified nodes and nodes whose verification failed carry no weight.
+//! Effective weights are computed lazily when tallies are evaluated and frozen into epoch
+//! snapshots.
+
+use super::{EconomicNode, VerificationStatus};
+use crate::config::DecayConfig;
+
+/// Weight factor in `[0, 1]` after `blocks_since` blocks without re-verification.
+pub fn factor(config: &DecayConfig, blocks_since: u64) -> f64 {
+    if !config.steps.is_empty() {
+        let mut steps: Vec<_> = config.steps.iter().collect();
+        steps.sort_by_key(|s| s.after_blocks);
+        return steps
+            .iter()
+            .take_while(|s| s.after_blocks <= blocks_since)
+            .last()
+            .map(|s| s.factor.clamp(0.0, 1.0))
+            .unwrap_or(1.0);
+    }
+    if config.half_life_blocks == 0 {
+        return 1.0;
+    }
+    0.5f64.powf(blocks_since as f64 / config.half_life_blocks as f64)
+}
+
+/// Height of the node's last successful verification.
+pub fn verified_at(node: &EconomicNode) -> Option<u64> {
+    match node.verification {
+        VerificationStatus::Verified { height } => Some(height),
+        _ => None,
+    }
+}
+
+/// Undecayed weight of the node: its verified sats, or zero unless it is verified.
+pub fn raw_weight(node: &EconomicNode) -> f64 {
+    match verified_at(node) {
+        Some(_) => node.verified_weight as f64,
+        None => 0.0,
+    }
+}
+
+/// Weight the node carries at `height`.
+pub fn effective_weight(node: &EconomicNode, height: u64, config: &DecayConfig) -> f64 {
+    match verified_at(node) {
+        Some(verified) => raw_weight(node) * factor(config, height.saturating_sub(verified)),
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DecayStep;
+
+    #[test]
+    fn test_schedules() {
+        assert_eq!(factor(&DecayConfig::default(), 50_000), 1.0);
+
+        let exponential = DecayConfig {
+            half_life_blocks: 1000,
+            steps: Vec::new(),
+        };
+        assert_eq!(factor(&exponential, 0), 1.0);
+        assert_eq!(factor(&exponential, 2000), 0.25);
+
+        let piecewise = DecayConfig {
+            half_life_blocks: 1000,
+            steps: vec![
+                DecayStep {
+                    after_blocks: 50_000,
+                    factor: 0.0,
+                },
+                DecayStep {
+                    after_blocks: 10_000,
+                    factor: 0.5,
+                },
+            ],
+        };
+        assert_eq!(factor(&piecewise, 9_999), 1.0);
+        assert_eq!(factor(&piecewise, 10_000), 0.5);
+        assert_eq!(factor(&piecewise, 60_000), 0.0);
+    }
+
+    #[test]
+    fn test_only_verified_weight_counts() {
+        let config = DecayConfig {
+            half_life_blocks: 100,
+            steps: Vec::new(),
+        };
+        let mut node = EconomicNode::new([1u8; 32], 0);
+        node.hashpower_percentage = 50.0;
+        node.verified_weight = 10;
+        assert_eq!(effective_weight(&node, 1000, &config), 0.0);
+
+        node.verification = VerificationStatus::Verified { height: 800 };
+        assert_eq!(raw_weight(&node), 10.0);
+        assert_eq!(effective_weight(&node, 1000, &config), 2.5);
+
+        node.verification = VerificationStatus::VerificationFailed {
+            reason: "spent".to_string(),
+        };
+        assert_eq!(raw_weight(&node), 0.0);
+        assert_eq!(effective_weight(&node, 1000, &config), 0.0);
+    }
+}
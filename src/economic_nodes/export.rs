@@ -0,0 +1,223 @@
+//! Registry export
+//!
+//! A point-in-time export of node records, open veto tallies and suspected Sybil clusters,
+//! as one JSON document or as two CSV files (nodes and tallies). CSV follows RFC 4180:
+//! CRLF line endings, a header row, fixed column order, and fields containing commas,
+//! quotes or line breaks wrapped in double quotes with inner quotes doubled.
+
+use super::cluster::ClusterReport;
+use super::tally::VetoTally;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// One node record.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeRow {
+    pub node_id: String,
+    pub public_key: String,
+    pub category: String,
+    /// Verified weight before decay.
+    pub raw_weight: f64,
+    /// Weight after decay, as counted in tallies.
+    pub effective_weight: f64,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub active: bool,
+}
+
+/// One proposal's veto tally.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TallyRow {
+    pub proposal_id: String,
+    /// Proposal tier, empty if the proposal is unknown to the proposal store.
+    pub tier: String,
+    pub veto_weight: f64,
+    pub total_weight: f64,
+    pub threshold_percent: f64,
+    pub crossed: bool,
+}
+
+impl TallyRow {
+    pub fn new(tally: &VetoTally, tier: &str) -> Self {
+        Self {
+            proposal_id: tally.proposal_id.clone(),
+            tier: tier.to_string(),
+            veto_weight: tally.vetoing_weight,
+            total_weight: tally.total_weight,
+            threshold_percent: tally.threshold_percent,
+            crossed: tally.crossed(),
+        }
+    }
+}
+
+/// Full registry export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RegistryExport {
+    pub height: u64,
+    /// Nodes sorted by id.
+    pub nodes: Vec<NodeRow>,
+    /// Tallies sorted by proposal id.
+    pub tallies: Vec<TallyRow>,
+    pub clusters: ClusterReport,
+}
+
+pub const NODE_COLUMNS: [&str; 8] = [
+    "node_id",
+    "public_key",
+    "category",
+    "raw_weight",
+    "effective_weight",
+    "first_seen",
+    "last_seen",
+    "active",
+];
+
+pub const TALLY_COLUMNS: [&str; 6] = [
+    "proposal_id",
+    "tier",
+    "veto_weight",
+    "total_weight",
+    "threshold",
+    "crossed",
+];
+
+/// Quote a field if RFC 4180 requires it.
+pub fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// A CSV table of `columns` and `rows`, with CRLF line endings.
+pub(crate) fn csv_table(columns: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = columns.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<Cow<str>> = row.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+impl RegistryExport {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Node records as CSV, columns as in [`NODE_COLUMNS`].
+    pub fn nodes_csv(&self) -> String {
+        csv_table(
+            &NODE_COLUMNS,
+            self.nodes.iter().map(|n| {
+                vec![
+                    n.node_id.clone(),
+                    n.public_key.clone(),
+                    n.category.clone(),
+                    n.raw_weight.to_string(),
+                    n.effective_weight.to_string(),
+                    n.first_seen.to_string(),
+                    n.last_seen.to_string(),
+                    n.active.to_string(),
+                ]
+            }),
+        )
+    }
+
+    /// Veto tallies as CSV, columns as in [`TALLY_COLUMNS`].
+    pub fn tallies_csv(&self) -> String {
+        csv_table(
+            &TALLY_COLUMNS,
+            self.tallies.iter().map(|t| {
+                vec![
+                    t.proposal_id.clone(),
+                    t.tier.clone(),
+                    t.veto_weight.to_string(),
+                    t.total_weight.to_string(),
+                    t.threshold_percent.to_string(),
+                    t.crossed.to_string(),
+                ]
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal RFC 4180 reader for round-trip checks.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => row.push(std::mem::take(&mut field)),
+                (false, '\r') => {}
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(csv_field("miner"), "miner");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let export = RegistryExport {
+            height: 10,
+            nodes: vec![NodeRow {
+                node_id: "aa".to_string(),
+                public_key: "02bb".to_string(),
+                category: "pool, \"big\"\nregion".to_string(),
+                raw_weight: 12.5,
+                effective_weight: 6.25,
+                first_seen: 1,
+                last_seen: 10,
+                active: true,
+            }],
+            tallies: vec![TallyRow {
+                proposal_id: "p,1".to_string(),
+                tier: "core".to_string(),
+                veto_weight: 40.0,
+                total_weight: 100.0,
+                threshold_percent: 30.0,
+                crossed: true,
+            }],
+            clusters: ClusterReport::default(),
+        };
+
+        let nodes = parse_csv(&export.nodes_csv());
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0], NODE_COLUMNS);
+        assert_eq!(nodes[1][2], "pool, \"big\"\nregion");
+        assert_eq!(nodes[1][4].parse::<f64>().unwrap(), 6.25);
+        assert_eq!(nodes[1][7], "true");
+
+        let tallies = parse_csv(&export.tallies_csv());
+        assert_eq!(tallies[0], TALLY_COLUMNS);
+        assert_eq!(tallies[1][0], "p,1");
+        assert_eq!(tallies[1][5], "true");
+    }
+}
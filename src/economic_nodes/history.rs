@@ -0,0 +1,194 @@
+//! Per-node weight history
+//!
+//! Every change to a node's claimed or verified weight is appended to its history. Changes
+//! that leave both weights as they were are dropped, changes at the same height are
+//! coalesced into one record, and the oldest records are discarded beyond the configured
+//! retention.
+
+use super::EconomicNode;
+use serde::{Deserialize, Serialize};
+
+/// What caused a weight change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightChangeReason {
+    Registration,
+    Reconciliation,
+    Reverification,
+}
+
+/// A node's weights at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeightPoint {
+    /// Claimed weight (hashpower percent); tallies use the verified sats.
+    pub weight: f64,
+    pub verified_sats: u64,
+}
+
+impl WeightPoint {
+    pub fn of(node: &EconomicNode) -> Self {
+        Self {
+            weight: node.hashpower_percentage,
+            verified_sats: node.verified_weight,
+        }
+    }
+}
+
+/// One weight change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightRecord {
+    pub height: u64,
+    pub old: WeightPoint,
+    pub new: WeightPoint,
+    pub reason: WeightChangeReason,
+}
+
+/// Record the change from `before` to the node's current weights.
+pub fn record(
+    node: &mut EconomicNode,
+    before: WeightPoint,
+    height: u64,
+    reason: WeightChangeReason,
+    retention: usize,
+) {
+    let after = WeightPoint::of(node);
+    if after == before {
+        return;
+    }
+    match node.weight_history.last_mut() {
+        Some(last) if last.height == height => {
+            last.new = after;
+            last.reason = reason;
+            if last.old == last.new {
+                node.weight_history.pop();
+            }
+        }
+        _ => node.weight_history.push(WeightRecord {
+            height,
+            old: before,
+            new: after,
+            reason,
+        }),
+    }
+    let excess = node.weight_history.len().saturating_sub(retention);
+    node.weight_history.drain(..excess);
+}
+
+/// Drop records that changed nothing, merge records at the same height and keep the newest
+/// `retention`. Returns the number of records removed.
+pub fn coalesce(node: &mut EconomicNode, retention: usize) -> usize {
+    let before = node.weight_history.len();
+    let mut kept: Vec<WeightRecord> = Vec::with_capacity(before);
+    for record in node.weight_history.drain(..) {
+        match kept.last_mut() {
+            Some(last) if last.height == record.height => {
+                last.new = record.new;
+                last.reason = record.reason;
+            }
+            _ => kept.push(record),
+        }
+        if kept.last().is_some_and(|last| last.old == last.new) {
+            kept.pop();
+        }
+    }
+    let excess = kept.len().saturating_sub(retention);
+    kept.drain(..excess);
+    node.weight_history = kept;
+    before - node.weight_history.len()
+}
+
+/// Claimed weight the node had at `height`, or `None` if it was not registered yet.
+pub fn weight_at(node: &EconomicNode, height: u64) -> Option<f64> {
+    if height < node.registered_at {
+        return None;
+    }
+    let weight = match node.weight_history.iter().rev().find(|r| r.height <= height) {
+        Some(record) => record.new.weight,
+        None => match node.weight_history.first() {
+            Some(first) => first.old.weight,
+            None => node.hashpower_percentage,
+        },
+    };
+    Some(weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(node: &mut EconomicNode, weight: f64, height: u64, retention: usize) {
+        let before = WeightPoint::of(node);
+        node.hashpower_percentage = weight;
+        record(
+            node,
+            before,
+            height,
+            WeightChangeReason::Registration,
+            retention,
+        );
+    }
+
+    #[test]
+    fn test_weight_at_past_heights() {
+        let mut node = EconomicNode::new([1u8; 32], 100);
+        node.hashpower_percentage = 10.0;
+        change(&mut node, 20.0, 200, 16);
+        change(&mut node, 5.0, 300, 16);
+
+        assert_eq!(weight_at(&node, 50), None);
+        assert_eq!(weight_at(&node, 150), Some(10.0));
+        assert_eq!(weight_at(&node, 200), Some(20.0));
+        assert_eq!(weight_at(&node, 299), Some(20.0));
+        assert_eq!(weight_at(&node, 1000), Some(5.0));
+    }
+
+    #[test]
+    fn test_coalescing_and_retention() {
+        let mut node = EconomicNode::new([1u8; 32], 0);
+        node.hashpower_percentage = 10.0;
+        // No-op change is not recorded
+        change(&mut node, 10.0, 10, 2);
+        assert!(node.weight_history.is_empty());
+
+        // Two changes in one block coalesce; a round trip within a block cancels out
+        change(&mut node, 20.0, 20, 2);
+        change(&mut node, 30.0, 20, 2);
+        assert_eq!(node.weight_history.len(), 1);
+        assert_eq!(node.weight_history[0].old.weight, 10.0);
+        assert_eq!(node.weight_history[0].new.weight, 30.0);
+        change(&mut node, 10.0, 20, 2);
+        assert!(node.weight_history.is_empty());
+
+        for (i, w) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+            change(&mut node, w, 100 + i as u64, 2);
+        }
+        assert_eq!(node.weight_history.len(), 2);
+        assert_eq!(node.weight_history[0].new.weight, 2.0);
+    }
+
+    #[test]
+    fn test_coalesce_stored_history() {
+        let point = |weight| WeightPoint {
+            weight,
+            verified_sats: 0,
+        };
+        let rec = |height, old, new| WeightRecord {
+            height,
+            old: point(old),
+            new: point(new),
+            reason: WeightChangeReason::Reconciliation,
+        };
+        let mut node = EconomicNode::new([1u8; 32], 0);
+        // As written by older builds or under a larger retention
+        node.weight_history = vec![
+            rec(1, 1.0, 1.0),
+            rec(2, 1.0, 2.0),
+            rec(2, 2.0, 3.0),
+            rec(3, 3.0, 4.0),
+            rec(4, 4.0, 5.0),
+        ];
+        assert_eq!(coalesce(&mut node, 2), 3);
+        assert_eq!(node.weight_history, vec![rec(3, 3.0, 4.0), rec(4, 4.0, 5.0)]);
+        assert_eq!(coalesce(&mut node, 2), 0);
+    }
+}
@@ -0,0 +1,163 @@
+//! Lightning node identity for economic node registrations
+//!
+//! Routing nodes register with their Lightning node public key and a `signmessage`
+//! signature (as produced by `lncli signmessage` / `lightning-cli signmessage`) over the
+//! canonical registration message. The signature is zbase32 of a 65-byte recoverable
+//! signature over `SHA256d("Lightning Signed Message:" || message)`; it is valid if the key
+//! it recovers to is the claimed node key.
+//!
+//! Channel outpoints count as weight only with their funding witness script: a 2-of-2
+//! multisig with the node key as one of its keys, which the outpoint's P2WSH output must pay
+//! to. Without that, any unspent output could be listed as a channel.
+
+use super::reserve::ClaimedOutpoint;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, PublicKey, Secp256k1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Category given to nodes registered with a Lightning identity.
+pub const LIGHTNING_NODE_TYPE: &str = "lightning";
+
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+/// Lightning identity stored with a node record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightningIdentity {
+    /// Compressed node public key (33 bytes).
+    pub node_pubkey: Vec<u8>,
+    /// Channel funding outpoints offered as weight, if any.
+    pub channel_outpoints: Vec<ClaimedOutpoint>,
+}
+
+/// Decode zbase32, dropping trailing padding bits.
+pub fn zbase32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = ZBASE32_ALPHABET.iter().position(|&a| a == c)? as u32;
+        acc = (acc << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Digest signed by Lightning `signmessage`.
+pub fn message_digest(message: &str) -> [u8; 32] {
+    let mut data = b"Lightning Signed Message:".to_vec();
+    data.extend_from_slice(message.as_bytes());
+    Sha256::digest(Sha256::digest(&data)).into()
+}
+
+/// Public key a zbase32 `signmessage` signature over `message` recovers to.
+pub fn recover(message: &str, signature: &str) -> Option<PublicKey> {
+    let bytes = zbase32_decode(signature)?;
+    if bytes.len() != 65 || !(27..=34).contains(&bytes[0]) {
+        return None;
+    }
+    let recid = RecoveryId::from_i32(((bytes[0] - 27) & 3) as i32).ok()?;
+    let sig = RecoverableSignature::from_compact(&bytes[1..], recid).ok()?;
+    let msg = Message::from_digest(message_digest(message));
+    Secp256k1::verification_only()
+        .recover_ecdsa(&msg, &sig)
+        .ok()
+}
+
+/// Whether `signature` over `message` was made by `node_pubkey`.
+pub fn verify(node_pubkey: &[u8], message: &str, signature: &str) -> bool {
+    recover(message, signature).is_some_and(|pk| pk.serialize().as_slice() == node_pubkey)
+}
+
+/// Whether `witness_script` is a 2-of-2 multisig with `node_pubkey` as one of its keys, and
+/// `script_pubkey` the P2WSH output paying to it.
+pub fn funding_script_matches(
+    script_pubkey: &[u8],
+    witness_script: &[u8],
+    node_pubkey: &[u8],
+) -> bool {
+    // OP_2 <33-byte key> <33-byte key> OP_2 OP_CHECKMULTISIG
+    let [0x52, 0x21, keys @ .., 0x52, 0xae] = witness_script else {
+        return false;
+    };
+    if keys.len() != 67 || keys[33] != 0x21 {
+        return false;
+    }
+    let paid: [u8; 32] = Sha256::digest(witness_script).into();
+    script_pubkey.len() == 34
+        && script_pubkey[..2] == [0x00, 0x20]
+        && script_pubkey[2..] == paid
+        && (&keys[..33] == node_pubkey || &keys[34..] == node_pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Published `signmessage` outputs of lnd and Core Lightning: message, zbase32
+    // signature and the signing node's key
+    const VECTORS: [(&str, &str, &str); 3] = [
+        (
+            "is this compatible?",
+            "rbgfioj114mh48d8egqx8o9qxqw4fmhe8jbeeabdioxnjk8z3t1ma1hu1fiswpakgucwwzwo6ofycffbsqusqdimugbh41n1g698hr9t",
+            "02b80cabdf82638aac86948e4c06e82064f547768dcef977677b9ea931ea75bab5",
+        ),
+        (
+            "hi",
+            "rnrphcjswusbacjnmmmrynh9pqip7sy5cx695h6mfu64iac6qmcmsd8xnsyczwmpqp9shqkth3h4jmkgyqu5z47jfn1q7gpxtaqpx4xg",
+            "02de60d194e1ca5947b59fe8e2efd6aadeabfb67f2e89e13ae1a799c1e08e4a43b",
+        ),
+        (
+            "hi",
+            "ry8bbsopmduhxy3dr5d9ekfeabdpimfx95kagdem7914wtca79jwamtbw4rxh69hg7n6x9ty8cqk33knbxaqftgxsfsaeprxkn1k48p3",
+            "022b8ece90ee891cbcdac0c1cc6af46b73c47212d8defbce80265ac81a6b794931",
+        ),
+    ];
+
+    #[test]
+    fn test_zbase32_decode() {
+        assert_eq!(zbase32_decode("").unwrap(), Vec::<u8>::new());
+        assert_eq!(zbase32_decode("yy").unwrap(), vec![0]);
+        assert_eq!(zbase32_decode("99").unwrap(), vec![0xff]);
+        assert!(zbase32_decode("0l").is_none());
+        for (_, signature, _) in VECTORS {
+            assert_eq!(zbase32_decode(signature).unwrap().len(), 65);
+        }
+    }
+
+    #[test]
+    fn test_verify_signmessage() {
+        for (message, signature, node_pubkey) in VECTORS {
+            let pubkey = hex::decode(node_pubkey).unwrap();
+            assert!(verify(&pubkey, message, signature), "{}", node_pubkey);
+            assert!(!verify(&pubkey, "hello", signature));
+        }
+        // Same message, signed by another node
+        let other = hex::decode(VECTORS[2].2).unwrap();
+        assert!(!verify(&other, VECTORS[1].0, VECTORS[1].1));
+    }
+
+    #[test]
+    fn test_funding_script_matches() {
+        let node = hex::decode(VECTORS[0].2).unwrap();
+        let remote = hex::decode(VECTORS[1].2).unwrap();
+        let multisig =
+            |a: &[u8], b: &[u8]| [&[0x52, 0x21][..], a, &[0x21], b, &[0x52, 0xae]].concat();
+        let p2wsh = |script: &[u8]| [&[0x00, 0x20][..], &Sha256::digest(script)].concat();
+
+        let script = multisig(&remote, &node);
+        assert!(funding_script_matches(&p2wsh(&script), &script, &node));
+        // Paid to another script, or without the node key
+        let other = multisig(&remote, &hex::decode(VECTORS[2].2).unwrap());
+        assert!(!funding_script_matches(&p2wsh(&other), &script, &node));
+        assert!(!funding_script_matches(&p2wsh(&other), &other, &node));
+        // A P2WPKH output is not a channel
+        let p2wpkh = [&[0x00, 0x14][..], &[7u8; 20]].concat();
+        assert!(!funding_script_matches(&p2wpkh, &script, &node));
+    }
+}
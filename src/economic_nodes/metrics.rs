@@ -0,0 +1,143 @@
+//! Registry metrics
+//!
+//! Gauges are derived from the registry contents on demand; event counters are kept by the
+//! registry and persisted with it so they survive restarts.
+
+use super::decay::raw_weight;
+use super::rate_limit::RegistrationCountersSnapshot;
+use super::EconomicNode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Counters for registry events other than registrations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventCounters {
+    pub vetoes: u64,
+    pub revocations: u64,
+    /// Entries removed from the live registry (evictions and reconciliation removals).
+    pub prunes: u64,
+}
+
+/// Gauges for one node category.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryMetrics {
+    pub registered: usize,
+    pub active: usize,
+    pub expired: usize,
+    pub weight: f64,
+}
+
+/// Snapshot of registry gauges and counters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RegistryMetrics {
+    pub registered: usize,
+    pub active: usize,
+    pub expired: usize,
+    /// Registered but deactivated by an access list; not counted as active or expired.
+    pub deactivated: usize,
+    pub archived: usize,
+    /// Gauges per node category (`node_type`; empty types are reported as "unknown").
+    pub by_category: BTreeMap<String, CategoryMetrics>,
+    /// Verified weight, before decay.
+    pub total_weight: f64,
+    /// Weight of open vetoes per proposal.
+    pub veto_weight_by_proposal: BTreeMap<String, f64>,
+    pub total_veto_weight: f64,
+    pub registrations: RegistrationCountersSnapshot,
+    pub vetoes: u64,
+    pub revocations: u64,
+    pub prunes: u64,
+}
+
+/// Whether a node has announced itself within `expiry_blocks` (0 disables expiry).
+pub fn is_active(node: &EconomicNode, height: u64, expiry_blocks: u64) -> bool {
+    expiry_blocks == 0 || height.saturating_sub(node.last_announced) <= expiry_blocks
+}
+
+/// Compute metrics over the live registry.
+pub fn compute<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    archived: usize,
+    height: u64,
+    expiry_blocks: u64,
+    registrations: RegistrationCountersSnapshot,
+    counters: EventCounters,
+) -> RegistryMetrics {
+    let mut metrics = RegistryMetrics {
+        archived,
+        registrations,
+        vetoes: counters.vetoes,
+        revocations: counters.revocations,
+        prunes: counters.prunes,
+        ..RegistryMetrics::default()
+    };
+    for node in nodes {
+        if node.deactivated.is_some() {
+            metrics.registered += 1;
+            metrics.deactivated += 1;
+            continue;
+        }
+        let active = is_active(node, height, expiry_blocks);
+        let category = if node.node_type.is_empty() {
+            "unknown".to_string()
+        } else {
+            node.node_type.clone()
+        };
+        let weight = raw_weight(node);
+        let entry = metrics.by_category.entry(category).or_default();
+        entry.registered += 1;
+        entry.weight += weight;
+        metrics.registered += 1;
+        metrics.total_weight += weight;
+        if active {
+            entry.active += 1;
+            metrics.active += 1;
+        } else {
+            entry.expired += 1;
+            metrics.expired += 1;
+        }
+        for veto in node.veto_history.iter().filter(|v| v.outcome.is_none()) {
+            *metrics
+                .veto_weight_by_proposal
+                .entry(veto.proposal_id.clone())
+                .or_insert(0.0) += weight;
+            metrics.total_veto_weight += weight;
+        }
+    }
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::{VerificationStatus, VetoRecord};
+
+    #[test]
+    fn test_breakdown() {
+        let mut miner = EconomicNode::new([1u8; 32], 0);
+        miner.node_type = "miner".to_string();
+        miner.verified_weight = 10;
+        miner.verification = VerificationStatus::Verified { height: 100 };
+        miner.last_announced = 100;
+        miner.veto_history.push(VetoRecord {
+            proposal_id: "p1".to_string(),
+            height: 100,
+            reason: String::new(),
+            outcome: None,
+        });
+        let mut exchange = EconomicNode::new([2u8; 32], 0);
+        exchange.node_type = "exchange".to_string();
+        exchange.verified_weight = 5;
+        exchange.verification = VerificationStatus::Verified { height: 0 };
+        let untyped = EconomicNode::new([3u8; 32], 100);
+
+        let nodes = [miner, exchange, untyped];
+        let m = compute(&nodes, 1, 150, 100, Default::default(), Default::default());
+        assert_eq!((m.registered, m.active, m.expired, m.archived), (3, 2, 1, 1));
+        assert_eq!(m.by_category["exchange"].expired, 1);
+        assert_eq!(m.by_category["miner"].active, 1);
+        assert_eq!(m.by_category["unknown"].registered, 1);
+        assert_eq!(m.total_weight, 15.0);
+        assert_eq!(m.veto_weight_by_proposal["p1"], 10.0);
+    }
+}
@@ -0,0 +1,3192 @@
+//! Economic node registry
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blvm_node::module::ipc::protocol::EventPayload;
+    use blvm_node::module::ipc::protocol::ModuleMessage;
+    use crate::testing::MockNodeApi;
+    use blvm_node::module::EventType;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_economic_node_registration() {
+        let node_api = Arc::new(MockNodeApi::new(100));
+        let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+            .await
+            .unwrap();
+
+        let node_id = [1u8; 32];
+        let node_id_hex = hex::encode(node_id);
+        let event = ModuleMessage::Event(blvm_node::module::ipc::protocol::EventMessage {
+            event_type: EventType::EconomicNodeRegistered,
+            payload: EventPayload::EconomicNodeRegistered {
+                node_id: node_id_hex.to_string(),
+                node_type: "miner".to_string(),
+                hashpower_percent: Some(0.5),
+            },
+        });
+
+        registry
+            .handle_event(&event, node_api.as_ref())
+            .await
+            .unwrap();
+
+        let nodes = registry.nodes.read().await;
+        assert!(nodes.contains_key(&node_id));
+        let node = nodes.get(&node_id).unwrap();
+        assert_eq!(node.hashpower_percentage, 0.5);
+        assert_eq!(node.economic_activity_percentage, 0.0); // Not provided in EventPayload
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_keeps_registered_nodes() {
+        let node_api = Arc::new(MockNodeApi::new(100));
+        let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+            .await
+            .unwrap();
+        let event = ModuleMessage::Event(blvm_node::module::ipc::protocol::EventMessage {
+            event_type: EventType::EconomicNodeRegistered,
+            payload: EventPayload::EconomicNodeRegistered {
+                node_id: hex::encode([2u8; 32]),
+                node_type: "miner".to_string(),
+                hashpower_percent: Some(0.5),
+            },
+        });
+        registry
+            .handle_event(&event, node_api.as_ref())
+            .await
+            .unwrap();
+
+        let mut config = RegistryConfig::default();
+        config.veto.threshold_percent = 10.0;
+        config.veto.observe_only = true;
+        registry.reconfigure(config).await.unwrap();
+
+        assert_eq!(registry.config().veto.threshold_percent, 10.0);
+        // Only applies from the next connection
+        assert!(!registry.config().veto.observe_only);
+        assert!(registry.nodes.read().await.contains_key(&[2u8; 32]));
+    }
+}
+
+pub mod access;
+pub mod address_proof;
+pub mod capacity;
+pub mod changes;
+pub mod claims;
+pub mod cluster;
+pub mod commitment;
+pub mod compaction;
+pub mod decay;
+pub mod export;
+pub mod history;
+pub mod lightning;
+pub mod metrics;
+pub mod multisig;
+pub mod rate_limit;
+pub mod report;
+pub mod reputation;
+pub mod reserve;
+pub mod schema;
+pub mod simulate;
+pub mod snapshot;
+pub mod tally;
+pub mod validation;
+
+use crate::config::RegistryConfig;
+use crate::error::GovernanceError;
+use crate::node_api::{NodeApiIpc, NodeEconomicNode};
+use crate::veto_evidence::{RegistryEvidence, VetoSignatures};
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::ipc::protocol::ModuleMessage;
+use blvm_node::module::traits::NodeAPI;
+use blvm_node::module::EventType;
+use blvm_protocol::Hash;
+use hex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+pub use access::AccessAuditEntry;
+pub use changes::{ChangeKind, ChangeSource, RegistryChange};
+pub use cluster::ClusterReport;
+pub use export::RegistryExport;
+pub use address_proof::AddressProof;
+pub use commitment::RegistryCommitment;
+pub use compaction::CompactionSummary;
+pub use history::{WeightChangeReason, WeightRecord};
+pub use lightning::LightningIdentity;
+pub use metrics::RegistryMetrics;
+pub use multisig::{KeySet, KeySignature, SignedAt};
+pub use rate_limit::RegistrationCountersSnapshot;
+pub use reputation::Reputation;
+pub use reserve::{ClaimedOutpoint, PendingSpend, ReserveClaim, VerificationStatus};
+pub use simulate::{VetoScenario, VetoSimulation};
+pub use snapshot::RegistrySnapshot;
+pub use tally::VetoTally;
+pub use validation::ValidationRule;
+
+const REGISTRY_TREE: &str = "economic_nodes";
+const STORAGE_KEY: &[u8] = b"nodes";
+const ARCHIVE_KEY: &[u8] = b"archive";
+const COUNTERS_KEY: &[u8] = b"counters";
+const EPOCHS_KEY: &[u8] = b"epochs";
+const ACCESS_AUDIT_KEY: &[u8] = b"access_audit";
+const VETO_SIGNATURES_KEY: &[u8] = b"veto_signatures";
+/// Every key the registry writes to its tree.
+const RECORD_KEYS: [&[u8]; 7] = [
+    STORAGE_KEY,
+    ARCHIVE_KEY,
+    COUNTERS_KEY,
+    EPOCHS_KEY,
+    ACCESS_AUDIT_KEY,
+    VETO_SIGNATURES_KEY,
+    schema::SCHEMA_KEY,
+];
+
+/// Counters persisted alongside the registry.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct PersistedCounters {
+    registrations: RegistrationCountersSnapshot,
+    events: metrics::EventCounters,
+}
+
+/// Economic node information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EconomicNode {
+    pub node_id: [u8; 32],
+    /// Category announced at registration (e.g. "miner", "exchange").
+    pub node_type: String,
+    pub public_key: Vec<u8>,
+    pub hashpower_percentage: f64,
+    pub economic_activity_percentage: f64,
+    pub registered_at: u64,
+    pub last_seen: u64,
+    pub veto_count: u32,
+    /// Height of the most recent registration announcement from this node.
+    pub last_announced: u64,
+    /// Blocks at which liveness was sampled.
+    pub liveness_samples: u64,
+    /// Sampled blocks at which the node had announced within the liveness window.
+    pub liveness_hits: u64,
+    /// Number of re-registrations that changed the claimed weight.
+    pub weight_changes: u32,
+    /// Vetoes cast by this node and how the vetoed proposals turned out.
+    pub veto_history: Vec<VetoRecord>,
+    /// Reserve claim backing this node's weight, if any.
+    pub reserve_claim: Option<ReserveClaim>,
+    /// Verified reserve, in satoshis.
+    pub verified_weight: u64,
+    pub verification: VerificationStatus,
+    /// Threshold key set; when set, vetoes, revocations and key rotations need `threshold`
+    /// signatures from it.
+    pub keys: Option<KeySet>,
+    /// Why the node is deactivated (e.g. the blocklist entry it matched). Deactivated nodes
+    /// carry no weight and their vetoes are ignored.
+    pub deactivated: Option<String>,
+    /// Changes to claimed and verified weight, oldest first.
+    pub weight_history: Vec<WeightRecord>,
+    /// Signed-message proof of the address backing this node's weight, if any.
+    pub address_proof: Option<AddressProof>,
+    /// Lightning node identity, for nodes registered with their LN node key.
+    pub lightning: Option<LightningIdentity>,
+}
+
+impl EconomicNode {
+    /// A freshly registered node with no history.
+    pub fn new(node_id: [u8; 32], height: u64) -> Self {
+        Self {
+            node_id,
+            node_type: String::new(),
+            public_key: Vec::new(),
+            hashpower_percentage: 0.0,
+            economic_activity_percentage: 0.0,
+            registered_at: height,
+            last_seen: height,
+            veto_count: 0,
+            last_announced: height,
+            liveness_samples: 0,
+            liveness_hits: 0,
+            weight_changes: 0,
+            veto_history: Vec::new(),
+            reserve_claim: None,
+            verified_weight: 0,
+            verification: VerificationStatus::Unverified,
+            keys: None,
+            deactivated: None,
+            weight_history: Vec::new(),
+            address_proof: None,
+            lightning: None,
+        }
+    }
+}
+
+/// A veto cast by an economic node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VetoRecord {
+    pub proposal_id: String,
+    pub height: u64,
+    pub reason: String,
+    /// Unset while the proposal is still open.
+    pub outcome: Option<VetoOutcome>,
+}
+
+/// Final outcome of a vetoed proposal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VetoOutcome {
+    /// The proposal did not go through.
+    Failed,
+    /// The proposal was merged despite the veto.
+    MergedAnyway,
+}
+
+/// An evicted node in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedNode {
+    pub node: EconomicNode,
+    /// Height the node was evicted at.
+    pub archived_at: u64,
+}
+
+/// Full record of one economic node, as returned by [`EconomicNodeRegistry::get_node`].
+#[derive(Debug, Clone, Serialize)]
+pub struct EconomicNodeDetails {
+    #[serde(flatten)]
+    pub node: EconomicNode,
+    pub reputation: Reputation,
+    /// Whether the node was found in the archive rather than the live registry.
+    pub archived: bool,
+    /// Verified weight before decay; zero unless the node is verified.
+    pub raw_weight: f64,
+    /// Weight after decay of a stale verification.
+    pub effective_weight: f64,
+    /// Claimed outpoints being spent by unconfirmed transactions.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pending_spends: Vec<PendingSpend>,
+}
+
+/// Drift found and repaired by a reconciliation pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReconcileSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub updated: usize,
+}
+
+impl ReconcileSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.updated == 0
+    }
+}
+
+/// Economic node registry
+pub struct EconomicNodeRegistry {
+    nodes: Arc<RwLock<HashMap<[u8; 32], EconomicNode>>>,
+    /// Evicted nodes, kept for lookups and audits.
+    archive: Arc<RwLock<HashMap<[u8; 32], ArchivedNode>>>,
+    node_api: NodeApiIpc,
+    /// Swapped whole by [`Self::reconfigure`].
+    config: std::sync::RwLock<Arc<RegistryConfig>>,
+    current_height: Arc<RwLock<u64>>,
+    db: Option<Arc<dyn blvm_node::storage::database::Database>>,
+    limiter: std::sync::Mutex<rate_limit::RegistrationLimiter>,
+    registration_counters: rate_limit::RegistrationCounters,
+    last_commitment: std::sync::Mutex<Option<RegistryCommitment>>,
+    validation_counters: validation::ValidationCounters,
+    veto_reporter: report::VetoReporter,
+    event_counters: std::sync::Mutex<metrics::EventCounters>,
+    epochs: std::sync::Mutex<snapshot::EpochState>,
+    access: std::sync::RwLock<access::AccessLists>,
+    access_audit: std::sync::Mutex<Vec<AccessAuditEntry>>,
+    /// Key signatures of the signed vetoes, for evidence bundles.
+    veto_signatures: std::sync::Mutex<VetoSignatures>,
+    /// Where veto threshold crossings are added for feed readers, if anywhere.
+    feed: Option<Arc<crate::feed::Feed>>,
+    /// Where vetoes cast and revoked are collected for the webhook digest, if anywhere.
+    digest: Option<Arc<crate::digest::Digest>>,
+    /// Where veto tallies are followed through the escalation bands, if anywhere.
+    escalation: Option<Arc<crate::escalation::VetoEscalation>>,
+    /// Recent block hashes by height, newest last, for address proof challenges.
+    recent_blocks: std::sync::Mutex<VecDeque<(u64, Hash)>>,
+    changes: tokio::sync::broadcast::Sender<RegistryChange>,
+    /// Claimed outpoints spent in the mempool, by node, until the spend confirms.
+    pending_spends: std::sync::Mutex<HashMap<[u8; 32], Vec<PendingSpend>>>,
+    /// Mempool txids already checked for spends of claimed outpoints, and their size as
+    /// counted against `mempool_memory`.
+    mempool_seen: std::sync::Mutex<(HashSet<Hash>, Option<crate::memory::Charge>)>,
+    mempool_memory: Arc<crate::memory::Share>,
+    /// Where failed writes are reported.
+    errors: Option<Arc<crate::error_report::ErrorReporter>>,
+    /// Where changes and new commitments are recorded.
+    audit_log: Option<Arc<crate::audit_log::AuditLog>>,
+    /// Where webhook notifications of changes are recorded before they are broadcast.
+    intents: Option<Arc<crate::intent::IntentLog>>,
+    /// Saves of the registry since it was opened.
+    revision: std::sync::atomic::AtomicU64,
+}
+
+impl EconomicNodeRegistry {
+    /// Create a new economic node registry with the given configuration
+    pub async fn new(
+        config: RegistryConfig,
+        node_api: Arc<dyn NodeAPI>,
+    ) -> Result<Self, GovernanceError> {
+        let current_height = node_api.get_block_height().await.unwrap_or(0);
+        let access = match access::AccessLists::load(&config.access) {
+            Ok(lists) => lists,
+            Err(e) => {
+                warn!("Failed to load economic node access lists: {}", e.chain());
+                access::AccessLists::default()
+            }
+        };
+        Ok(Self {
+            nodes: Arc::new(RwLock::new(HashMap::new())),
+            archive: Arc::new(RwLock::new(HashMap::new())),
+            node_api: NodeApiIpc::new(node_api),
+            veto_reporter: report::VetoReporter::new(config.veto.observe_only),
+            config: std::sync::RwLock::new(Arc::new(config)),
+            current_height: Arc::new(RwLock::new(current_height)),
+            db: None,
+            limiter: std::sync::Mutex::new(rate_limit::RegistrationLimiter::default()),
+            registration_counters: rate_limit::RegistrationCounters::default(),
+            last_commitment: std::sync::Mutex::new(None),
+            validation_counters: validation::ValidationCounters::default(),
+            event_counters: std::sync::Mutex::new(metrics::EventCounters::default()),
+            epochs: std::sync::Mutex::new(snapshot::EpochState::default()),
+            access: std::sync::RwLock::new(access),
+            access_audit: std::sync::Mutex::new(Vec::new()),
+            veto_signatures: std::sync::Mutex::new(VetoSignatures::new()),
+            feed: None,
+            digest: None,
+            escalation: None,
+            recent_blocks: std::sync::Mutex::new(VecDeque::new()),
+            changes: tokio::sync::broadcast::channel(256).0,
+            pending_spends: std::sync::Mutex::new(HashMap::new()),
+            mempool_seen: std::sync::Mutex::new((HashSet::new(), None)),
+            mempool_memory: Arc::default(),
+            errors: None,
+            audit_log: None,
+            intents: None,
+            revision: std::sync::atomic::AtomicU64::new(0),
+        })
+    }
+
+    /// Bound requests to the node by `timeouts`, by method.
+    pub fn with_request_timeouts(mut self, timeouts: crate::node_api::MethodTimeouts) -> Self {
+        self.node_api = self.node_api.with_timeouts(timeouts);
+        self
+    }
+
+    /// Retry requests to the node that fail with a transient error as `policy` allows.
+    pub fn with_retry_policy(mut self, policy: crate::node_api::RetryPolicy) -> Self {
+        self.node_api = self.node_api.with_retry_policy(policy);
+        self
+    }
+
+    /// Report failed writes, and veto results the node rejects for good, to `errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<crate::error_report::ErrorReporter>) -> Self {
+        self.veto_reporter = self.veto_reporter.with_error_reporter(Arc::clone(&errors));
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Record changes and new commitments in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<crate::audit_log::AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Add veto tallies crossing the threshold to `feed` (see [`crate::feed`]).
+    pub fn with_feed(mut self, feed: Arc<crate::feed::Feed>) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    /// Add vetoes cast and revoked to `digest` (see [`crate::digest`]).
+    pub fn with_digest(mut self, digest: Arc<crate::digest::Digest>) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Follow veto tallies through the bands of `escalation` (see [`crate::escalation`]).
+    pub fn with_escalation(mut self, escalation: Arc<crate::escalation::VetoEscalation>) -> Self {
+        self.escalation = Some(escalation);
+        self
+    }
+
+    /// Add the veto event `event_type` to the digest, if there is one.
+    fn add_to_digest(&self, event_type: &str, data: serde_json::Value) {
+        let Some(digest) = &self.digest else {
+            return;
+        };
+        if let Err(e) = digest.record(event_type, &data, crate::clock::unix_now()) {
+            warn!("Failed to add {} to the digest: {}", event_type, e.chain());
+        }
+    }
+
+    /// Record the webhook notification of each change, and each veto result to report to the
+    /// node, in `intents` before carrying it out, so that one cut short by a crash is
+    /// carried out on the next start (see [`crate::intent`]).
+    pub fn with_intents(mut self, intents: Arc<crate::intent::IntentLog>) -> Self {
+        self.veto_reporter = self.veto_reporter.with_intents(Arc::clone(&intents));
+        self.intents = Some(intents);
+        self
+    }
+
+    /// Carry out an intent recorded before a crash: report the veto result it holds to the
+    /// node, unless a newer result for the proposal is already queued.
+    pub async fn carry_out(&self, intent: &crate::intent::Intent) -> Result<(), GovernanceError> {
+        if intent.kind != report::VETO_RESULT_INTENT {
+            return Err(GovernanceError::ModuleError(format!(
+                "not a veto result intent: {}",
+                intent.kind
+            )));
+        }
+        let tally: VetoTally = serde_json::from_value(intent.data.clone())
+            .map_err(GovernanceError::serialization("veto result intent"))?;
+        info!("Reporting veto result for {} left unsent", tally.proposal_id);
+        self.veto_reporter.resume(tally, intent.id);
+        self.veto_reporter.flush(&self.node_api).await.map(|_| ())
+    }
+
+    /// Allow submitting governance actions to the node, recorded in `audit`.
+    pub fn with_actions(mut self, allow: bool, audit: Arc<crate::audit::ActionAudit>) -> Self {
+        self.node_api = self
+            .node_api
+            .with_actions_allowed(allow)
+            .with_action_audit(audit);
+        self
+    }
+
+    /// Log governance actions and veto results instead of submitting them.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.node_api = self.node_api.with_dry_run(dry_run);
+        self
+    }
+
+    /// Hold veto results queued while `pause` is paused.
+    pub fn with_pause(mut self, pause: Arc<crate::pause::PauseControl>) -> Self {
+        self.node_api = self.node_api.with_pause(pause);
+        self
+    }
+
+    /// Share the connection's proposal cache.
+    pub fn with_proposal_cache(mut self, cache: Arc<crate::node_api::ProposalCache>) -> Self {
+        self.node_api = self.node_api.with_proposal_cache(cache);
+        self
+    }
+
+    /// Share the connection's chain tip tracker.
+    pub fn with_tip_tracker(mut self, tip: Arc<crate::node_api::TipTracker>) -> Self {
+        self.node_api = self.node_api.with_tip_tracker(tip);
+        self
+    }
+
+    /// Share the connection's in-flight request limit.
+    pub fn with_in_flight(mut self, permits: Arc<crate::node_api::RequestLimiter>) -> Self {
+        self.node_api = self.node_api.with_in_flight(permits);
+        self
+    }
+
+    /// Reject payloads to the node larger than `limit` bytes before sending them.
+    pub fn with_max_message_bytes(mut self, limit: usize) -> Self {
+        self.node_api = self.node_api.with_max_message_bytes(limit);
+        self
+    }
+
+    /// Count cached headers and transactions, and checked mempool txids, against `budget`.
+    pub fn with_memory_budget(mut self, budget: &crate::memory::MemoryBudget) -> Self {
+        self.node_api = self.node_api.with_memory_budget(budget);
+        self.mempool_memory = Arc::clone(budget.share(crate::memory::Component::MempoolSeen));
+        self
+    }
+
+    /// Record node requests in `metrics`.
+    pub fn with_ipc_metrics(mut self, metrics: Arc<crate::ipc_metrics::IpcMetrics>) -> Self {
+        self.node_api = self.node_api.with_metrics(metrics);
+        self
+    }
+
+    /// Persist the registry in the module DB, loading any previously stored nodes.
+    pub fn with_store(
+        mut self,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Self, GovernanceError> {
+        Self::migrate_store(&db)?;
+        if Self::size_exceeds(&db, &self.config().compaction)? {
+            self.compact_db(&db, self.height_now())?;
+        }
+        let nodes = Self::load_from(&db)?;
+        let commitment = commitment::compute(nodes.values());
+        debug!(
+            "Loaded {} economic nodes from store, commitment {}",
+            nodes.len(),
+            commitment.root_hex()
+        );
+        *self.last_commitment.lock().unwrap() = Some(commitment);
+        self.nodes = Arc::new(RwLock::new(nodes));
+        self.archive = Arc::new(RwLock::new(Self::load_archive_from(&db)?));
+        let counters = Self::load_counters_from(&db)?;
+        self.registration_counters.restore(&counters.registrations);
+        *self.event_counters.lock().unwrap() = counters.events;
+        *self.epochs.lock().unwrap() = Self::load_epochs_from(&db)?;
+        *self.access_audit.lock().unwrap() = Self::load_access_audit_from(&db)?;
+        *self.veto_signatures.lock().unwrap() = Self::load_veto_signatures_from(&db)?;
+        self.db = Some(db);
+        Ok(self)
+    }
+
+    /// Whether the registry is persisted in the module DB.
+    pub fn has_store(&self) -> bool {
+        self.db.is_some()
+    }
+
+    /// Registry configuration in effect.
+    pub fn config(&self) -> Arc<RegistryConfig> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    /// Switch to `config` while running, keeping the registry contents, and reload the access
+    /// lists. `veto.observe_only` and the reconciliation and access list reload intervals are
+    /// read by background tasks when they start, so changes to them apply from the next
+    /// connection; until then `observe_only` keeps its current value. If the new access list
+    /// files cannot be read the current configuration stays in effect.
+    pub async fn reconfigure(&self, mut config: RegistryConfig) -> Result<(), GovernanceError> {
+        let lists = access::AccessLists::load(&config.access)?;
+        let current = self.config();
+        if config.veto.observe_only != current.veto.observe_only {
+            warn!("veto.observe_only changed; it applies from the next connection");
+            config.veto.observe_only = current.veto.observe_only;
+        }
+        if config.reconcile_interval_secs != current.reconcile_interval_secs
+            || config.access.reload_interval_secs != current.access.reload_interval_secs
+            || config.reserve.mempool_watch_secs != current.reserve.mempool_watch_secs
+        {
+            info!("Registry task intervals changed; they apply from the next connection");
+        }
+        *self.config.write().unwrap() = Arc::new(config);
+        self.apply_access_lists(lists).await
+    }
+
+    /// Subscribe to registry change notifications.
+    pub fn subscribe_changes(&self) -> tokio::sync::broadcast::Receiver<RegistryChange> {
+        self.changes.subscribe()
+    }
+
+    fn emit(
+        &self,
+        node_id: Option<&[u8; 32]>,
+        kind: ChangeKind,
+        before: serde_json::Value,
+        after: serde_json::Value,
+        height: u64,
+        source: ChangeSource,
+    ) {
+        let mut change = RegistryChange {
+            node_id: node_id.map(hex::encode),
+            kind,
+            before,
+            after,
+            height,
+            source,
+            intent: None,
+        };
+        if let (Some(intents), Ok(data)) = (&self.intents, serde_json::to_value(&change)) {
+            change.intent = crate::webhook::notify_intent(intents, &change.event_type(), &data);
+        }
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.registry_change(&change);
+        }
+        // No subscribers is not an error
+        let _ = self.changes.send(change);
+    }
+
+    /// Height of the last block seen.
+    pub async fn current_height(&self) -> u64 {
+        *self.current_height.read().await
+    }
+
+    /// Registration accept/reject counters.
+    pub fn registration_counters(&self) -> RegistrationCountersSnapshot {
+        self.registration_counters.snapshot()
+    }
+
+    /// Validation rejections per rule.
+    pub fn validation_counters(&self) -> HashMap<ValidationRule, u64> {
+        self.validation_counters.snapshot()
+    }
+
+    /// Validate a registration payload, counting rejections.
+    fn validate(
+        &self,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        claim: Option<&ReserveClaim>,
+    ) -> Result<[u8; 32], GovernanceError> {
+        validation::validate_registration(
+            node_id,
+            node_type,
+            hashpower_percent,
+            claim,
+            &self.config().validation,
+        )
+        .map_err(|rejection| {
+            self.validation_counters.record(rejection.rule);
+            debug!(
+                "Invalid economic node registration {:?}: {}",
+                node_id, rejection.reason
+            );
+            rejection.into()
+        })
+    }
+
+    /// Check a registration or veto against the access lists, logging any suppression.
+    fn check_access(&self, node_id: &str, identities: Vec<String>, action: &str) -> bool {
+        let mut all = vec![node_id.to_ascii_lowercase()];
+        all.extend(identities);
+        match self.access.read().unwrap().check(&all) {
+            Ok(()) => true,
+            Err(denial) => {
+                warn!("Ignored {} from economic node {}: {}", action, node_id, denial);
+                false
+            }
+        }
+    }
+
+    /// Reload the access list files and apply them to registered nodes.
+    pub async fn reload_access_lists(&self) -> Result<(), GovernanceError> {
+        let lists = access::AccessLists::load(&self.config().access)?;
+        self.apply_access_lists(lists).await
+    }
+
+    async fn apply_access_lists(&self, lists: access::AccessLists) -> Result<(), GovernanceError> {
+        info!(
+            "Loaded access lists: {} blocklisted, allowlist {}",
+            lists.blocklist.len(),
+            match &lists.allowlist {
+                Some(allow) => format!("{} entries", allow.len()),
+                None => "off".to_string(),
+            }
+        );
+        *self.access.write().unwrap() = lists;
+        let mut nodes = self.nodes.write().await;
+        if self.apply_blocklist(&mut nodes).await > 0 {
+            self.save(&nodes)?;
+        }
+        Ok(())
+    }
+
+    /// Deactivate registered nodes that are now blocklisted and reactivate ones that no longer
+    /// are. Returns the number of nodes changed.
+    async fn apply_blocklist(&self, nodes: &mut HashMap<[u8; 32], EconomicNode>) -> usize {
+        let height = *self.current_height.read().await;
+        let lists = self.access.read().unwrap().clone();
+        let mut entries = Vec::new();
+        for node in nodes.values_mut() {
+            let node_id = hex::encode(node.node_id);
+            let mut identities = vec![node_id.clone()];
+            if !node.public_key.is_empty() {
+                identities.push(hex::encode(&node.public_key));
+            }
+            if let Some(keys) = &node.keys {
+                identities.extend(keys.public_keys.iter().map(hex::encode));
+            }
+            match (lists.blocked(&identities), &node.deactivated) {
+                (Some(entry), None) => {
+                    warn!(
+                        "Deactivated economic node {}: matched blocklist entry {}",
+                        node_id, entry
+                    );
+                    node.deactivated = Some(format!("blocklisted ({})", entry));
+                    entries.push(AccessAuditEntry {
+                        height,
+                        node_id,
+                        action: "deactivated".to_string(),
+                        entry: Some(entry),
+                    });
+                }
+                (None, Some(_)) => {
+                    info!("Reactivated economic node {}: no longer blocklisted", node_id);
+                    node.deactivated = None;
+                    entries.push(AccessAuditEntry {
+                        height,
+                        node_id,
+                        action: "reactivated".to_string(),
+                        entry: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+        let changed = entries.len();
+        if changed > 0 {
+            let mut audit = self.access_audit.lock().unwrap();
+            audit.extend(entries);
+            if let Err(e) = self.save_access_audit(&audit) {
+                warn!("Failed to persist access list audit entries: {}", e.chain());
+            }
+        }
+        changed
+    }
+
+    /// Audit trail of access list deactivations and reactivations.
+    pub fn access_audit(&self) -> Vec<AccessAuditEntry> {
+        self.access_audit.lock().unwrap().clone()
+    }
+
+    /// Reload the access lists whenever their files change, checking every
+    /// `reload_interval_secs` (no-op if 0 or no lists are configured).
+    pub fn spawn_access_reload(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let config = self.config();
+        let access = &config.access;
+        if access.reload_interval_secs == 0
+            || (access.blocklist_path.is_none() && access.allowlist_path.is_none())
+        {
+            return None;
+        }
+        let interval_secs = access.reload_interval_secs;
+        let registry = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            let mut last_modified = None;
+            loop {
+                interval.tick().await;
+                let modified = access::modified_times(&registry.config().access);
+                if last_modified.as_ref() == Some(&modified) {
+                    continue;
+                }
+                match registry.reload_access_lists().await {
+                    Ok(()) => last_modified = Some(modified),
+                    Err(e) => warn!("Failed to reload access lists: {}", e.chain()),
+                }
+            }
+        }))
+    }
+
+    /// Deterministic commitment over the current registry contents.
+    pub async fn commitment(&self) -> RegistryCommitment {
+        commitment::compute(self.nodes.read().await.values())
+    }
+
+    /// Gauges and counters over the registry.
+    pub async fn metrics(&self) -> RegistryMetrics {
+        let height = *self.current_height.read().await;
+        let archived = self.archive.read().await.len();
+        let nodes = self.nodes.read().await;
+        metrics::compute(
+            nodes.values(),
+            archived,
+            height,
+            self.config().expiry_blocks,
+            self.registration_counters.snapshot(),
+            *self.event_counters.lock().unwrap(),
+        )
+    }
+
+    /// Current veto tally for a proposal.
+    pub async fn veto_tally(&self, proposal_id: &str) -> VetoTally {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let commitment = commitment::compute(nodes.values());
+        self.tally_for(&nodes, proposal_id, &commitment, height)
+    }
+
+    /// Current veto tallies of the proposals with open vetoes, in proposal id order, from one
+    /// read of the registry.
+    pub async fn open_veto_tallies(&self) -> Vec<VetoTally> {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let commitment = commitment::compute(nodes.values());
+        tally::open_veto_proposals(nodes.values())
+            .iter()
+            .map(|proposal_id| self.tally_for(&nodes, proposal_id, &commitment, height))
+            .collect()
+    }
+
+    /// The registry's part of a veto evidence bundle on `proposal_id`, at the current height
+    /// (see [`crate::veto_evidence`]).
+    pub async fn veto_evidence(&self, proposal_id: &str) -> RegistryEvidence {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let epochs = self.epochs.lock().unwrap().clone();
+        let signatures = self.veto_signatures.lock().unwrap().clone();
+        crate::veto_evidence::registry_evidence(
+            &self.config(),
+            &epochs,
+            &nodes,
+            &signatures,
+            proposal_id,
+            height,
+        )
+    }
+
+    /// [`Self::veto_evidence`] from the registry stored in `db`, at the height it last saw.
+    pub fn veto_evidence_from_store(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        config: &RegistryConfig,
+        proposal_id: &str,
+    ) -> Result<RegistryEvidence, GovernanceError> {
+        let nodes = Self::load_from(db)?;
+        let epochs = Self::load_epochs_from(db)?;
+        let signatures = Self::load_veto_signatures_from(db)?;
+        let height = nodes.values().map(|n| n.last_seen).max().unwrap_or(0);
+        Ok(crate::veto_evidence::registry_evidence(
+            config,
+            &epochs,
+            &nodes,
+            &signatures,
+            proposal_id,
+            height,
+        ))
+    }
+
+    /// Tally against the proposal's epoch snapshot if configured and available, otherwise
+    /// against the live registry with weights decayed to `height`.
+    fn tally_for(
+        &self,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        proposal_id: &str,
+        commitment: &RegistryCommitment,
+        height: u64,
+    ) -> VetoTally {
+        let config = self.config();
+        let epochs = config
+            .veto
+            .use_epoch_snapshot
+            .then(|| self.epochs.lock().unwrap());
+        Self::tally_with(&config, epochs.as_deref(), nodes, proposal_id, commitment, height)
+    }
+
+    /// [`Self::tally_for`] with the given configuration and epoch snapshots.
+    fn tally_with(
+        config: &RegistryConfig,
+        epochs: Option<&snapshot::EpochState>,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        proposal_id: &str,
+        commitment: &RegistryCommitment,
+        height: u64,
+    ) -> VetoTally {
+        let threshold = config.veto.threshold_percent;
+        if config.veto.use_epoch_snapshot {
+            if let Some(snapshot) = epochs.and_then(|e| e.for_proposal(proposal_id)) {
+                return tally::compute_from_snapshot(
+                    nodes.values(),
+                    proposal_id,
+                    threshold,
+                    snapshot,
+                );
+            }
+        }
+        let decay = &config.decay;
+        let percentile = config.sybil.cap_percentile;
+        if percentile > 0.0 {
+            let report = cluster::analyze(nodes.values(), height, &config.sybil, decay);
+            let caps = cluster::weight_caps(&report, nodes.values(), percentile, decay);
+            return tally::compute_weighted(nodes.values(), proposal_id, threshold, commitment, |n| {
+                decay::effective_weight(n, height, decay) * caps.get(&n.node_id).unwrap_or(&1.0)
+            });
+        }
+        tally::compute(nodes.values(), proposal_id, threshold, commitment, height, decay)
+    }
+
+    /// Weights voters carry in proposal tallies (see [`crate::tally`]): the live registry's
+    /// effective weights as for vetoes, and with `snapshots` the epoch snapshots proposals
+    /// are pinned to.
+    pub async fn voter_weights(&self, snapshots: bool) -> crate::tally::VoterWeights {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let live = self.live_weights(&nodes, height);
+        let epochs = snapshots.then(|| self.epochs.lock().unwrap().clone());
+        crate::tally::VoterWeights::new(live, epochs)
+    }
+
+    /// Suspected Sybil clusters in the live registry.
+    pub async fn sybil_report(&self) -> ClusterReport {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        cluster::analyze(nodes.values(), height, &self.config().sybil, &self.config().decay)
+    }
+
+    /// Suspected Sybil clusters in the persisted registry, for offline CLI use. Weights are
+    /// decayed to the last height the registry saw.
+    pub fn sybil_report_from_store(&self) -> Result<ClusterReport, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(ClusterReport::default());
+        };
+        let nodes = Self::load_from(db)?;
+        let height = nodes.values().map(|n| n.last_seen).max().unwrap_or(0);
+        Ok(cluster::analyze(
+            nodes.values(),
+            height,
+            &self.config().sybil,
+            &self.config().decay,
+        ))
+    }
+
+    /// Export the live registry. `tiers` maps proposal ids to their tier.
+    pub async fn export(&self, tiers: &HashMap<String, String>) -> RegistryExport {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let epochs = self.epochs.lock().unwrap();
+        Self::build_export(&self.config(), &epochs, &nodes, height, tiers)
+    }
+
+    /// Export the persisted registry, for offline CLI use.
+    pub fn export_from_store(
+        &self,
+        tiers: &HashMap<String, String>,
+    ) -> Result<RegistryExport, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(RegistryExport::default());
+        };
+        Self::export_store(db, &self.config(), tiers)
+    }
+
+    /// Export the registry persisted in `db` without a registry or a node connection, for the
+    /// binary's `export-registry`. Weights are decayed to the last height any node was seen.
+    pub fn export_store(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        config: &RegistryConfig,
+        tiers: &HashMap<String, String>,
+    ) -> Result<RegistryExport, GovernanceError> {
+        let nodes = Self::load_from(db)?;
+        let epochs = Self::load_epochs_from(db)?;
+        let height = nodes.values().map(|n| n.last_seen).max().unwrap_or(0);
+        Ok(Self::build_export(config, &epochs, &nodes, height, tiers))
+    }
+
+    fn build_export(
+        config: &RegistryConfig,
+        epochs: &snapshot::EpochState,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        height: u64,
+        tiers: &HashMap<String, String>,
+    ) -> RegistryExport {
+        let decay = &config.decay;
+        let mut rows: Vec<export::NodeRow> = nodes
+            .values()
+            .map(|n| export::NodeRow {
+                node_id: hex::encode(n.node_id),
+                public_key: hex::encode(&n.public_key),
+                category: if n.node_type.is_empty() {
+                    "unknown".to_string()
+                } else {
+                    n.node_type.clone()
+                },
+                raw_weight: decay::raw_weight(n),
+                effective_weight: decay::effective_weight(n, height, decay),
+                first_seen: n.registered_at,
+                last_seen: n.last_seen,
+                active: n.deactivated.is_none()
+                    && metrics::is_active(n, height, config.expiry_blocks),
+            })
+            .collect();
+        rows.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        let commitment = commitment::compute(nodes.values());
+        let tallies = tally::open_veto_proposals(nodes.values())
+            .into_iter()
+            .map(|proposal_id| {
+                let tally = Self::tally_with(
+                    config,
+                    Some(epochs),
+                    nodes,
+                    &proposal_id,
+                    &commitment,
+                    height,
+                );
+                let tier = tiers.get(&proposal_id).map(String::as_str).unwrap_or("");
+                export::TallyRow::new(&tally, tier)
+            })
+            .collect();
+        RegistryExport {
+            height,
+            nodes: rows,
+            tallies,
+            clusters: cluster::analyze(nodes.values(), height, &config.sybil, decay),
+        }
+    }
+
+    /// Simulate hypothetical vetoes against the live registry. Nothing is recorded.
+    pub async fn simulate_veto(
+        &self,
+        scenario: &VetoScenario,
+    ) -> Result<VetoSimulation, GovernanceError> {
+        let height = *self.current_height.read().await;
+        let nodes = self.nodes.read().await;
+        let epochs = self.epochs.lock().unwrap();
+        self.build_simulation(&nodes, &epochs, height, scenario)
+    }
+
+    /// Simulate hypothetical vetoes against the persisted registry, for offline CLI use.
+    pub fn simulate_veto_from_store(
+        &self,
+        scenario: &VetoScenario,
+    ) -> Result<VetoSimulation, GovernanceError> {
+        let Some(db) = &self.db else {
+            return self.build_simulation(
+                &HashMap::new(),
+                &snapshot::EpochState::default(),
+                0,
+                scenario,
+            );
+        };
+        let nodes = Self::load_from(db)?;
+        let epochs = Self::load_epochs_from(db)?;
+        let height = nodes.values().map(|n| n.last_seen).max().unwrap_or(0);
+        self.build_simulation(&nodes, &epochs, height, scenario)
+    }
+
+    /// Weights come from the requested snapshot, else the proposal's pinned snapshot when
+    /// tallies use epoch snapshots, else the live registry weighted as in [`Self::tally_for`].
+    fn build_simulation(
+        &self,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        epochs: &snapshot::EpochState,
+        height: u64,
+        scenario: &VetoScenario,
+    ) -> Result<VetoSimulation, GovernanceError> {
+        if scenario.proposal_id.is_none() && scenario.tier.is_none() {
+            return Err(GovernanceError::ValidationError {
+                field: "tier".to_string(),
+                reason: "a proposal tier or id is required".to_string(),
+            });
+        }
+        let pinned = scenario
+            .proposal_id
+            .as_deref()
+            .filter(|_| self.config().veto.use_epoch_snapshot)
+            .and_then(|id| epochs.for_proposal(id));
+        let snapshot = match scenario.snapshot {
+            Some(id) => Some(epochs.snapshots.get(&id).ok_or_else(|| {
+                GovernanceError::EconomicNodeError(format!("Unknown snapshot: {}", id))
+            })?),
+            None => pinned,
+        };
+        let (entries, height) = match snapshot {
+            Some(snapshot) => (snapshot.nodes.clone(), snapshot.height),
+            None => (self.live_weights(nodes, height), height),
+        };
+        let already_vetoing = scenario
+            .proposal_id
+            .as_deref()
+            .map(|proposal_id| {
+                nodes
+                    .values()
+                    .filter(|n| n.deactivated.is_none())
+                    .filter(|n| {
+                        n.veto_history
+                            .iter()
+                            .any(|v| v.proposal_id == proposal_id && v.outcome.is_none())
+                    })
+                    .map(|n| n.node_id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let scenario = VetoScenario {
+            snapshot: snapshot.map(|s| s.id),
+            ..scenario.clone()
+        };
+        Ok(simulate::simulate(
+            &entries,
+            &scenario,
+            &already_vetoing,
+            self.config().veto.threshold_percent,
+            height,
+        ))
+    }
+
+    /// Tally weights of the live registry at `height`: decayed, and Sybil-capped if configured.
+    fn live_weights(
+        &self,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        height: u64,
+    ) -> Vec<snapshot::SnapshotEntry> {
+        let config = self.config();
+        let decay = &config.decay;
+        let percentile = config.sybil.cap_percentile;
+        let caps = if percentile > 0.0 {
+            let report = cluster::analyze(nodes.values(), height, &config.sybil, decay);
+            cluster::weight_caps(&report, nodes.values(), percentile, decay)
+        } else {
+            HashMap::new()
+        };
+        nodes
+            .values()
+            .filter(|n| n.deactivated.is_none())
+            .map(|n| snapshot::SnapshotEntry {
+                node_id: n.node_id,
+                node_type: n.node_type.clone(),
+                weight: decay::effective_weight(n, height, decay)
+                    * caps.get(&n.node_id).unwrap_or(&1.0),
+            })
+            .collect()
+    }
+
+    /// Stored epoch snapshots, oldest first.
+    pub fn list_snapshots(&self) -> Vec<RegistrySnapshot> {
+        self.epochs
+            .lock()
+            .unwrap()
+            .snapshots
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Epoch snapshot by id.
+    pub fn snapshot(&self, id: u64) -> Option<RegistrySnapshot> {
+        self.epochs.lock().unwrap().snapshots.get(&id).cloned()
+    }
+
+    /// Id of the snapshot a proposal is pinned to.
+    pub fn proposal_snapshot_id(&self, proposal_id: &str) -> Option<u64> {
+        self.epochs
+            .lock()
+            .unwrap()
+            .proposal_snapshots
+            .get(proposal_id)
+            .copied()
+    }
+
+    /// Pin a new proposal to the snapshot currently in effect.
+    fn pin_proposal(&self, proposal_id: &str) -> Result<(), GovernanceError> {
+        let mut epochs = self.epochs.lock().unwrap();
+        let Some(id) = epochs.current().map(|s| s.id) else {
+            return Ok(());
+        };
+        epochs.proposal_snapshots.insert(proposal_id.to_string(), id);
+        debug!("Proposal {} pinned to epoch snapshot {}", proposal_id, id);
+        self.save_epochs(&epochs)
+    }
+
+    /// Take the epoch snapshot if `height` is the first block of an epoch.
+    async fn maybe_snapshot(&self, height: u64) -> Result<(), GovernanceError> {
+        let length = self.config().epoch.length_blocks;
+        if length == 0 || !height.is_multiple_of(length) {
+            return Ok(());
+        }
+        let id = height / length;
+        if self.epochs.lock().unwrap().snapshots.contains_key(&id) {
+            return Ok(());
+        }
+        let snapshot = {
+            let nodes = self.nodes.read().await;
+            RegistrySnapshot::take(
+                id,
+                height,
+                nodes.values(),
+                self.config().expiry_blocks,
+                &self.config().decay,
+            )
+        };
+        info!(
+            "Epoch {} snapshot at height {}: {} active nodes, commitment {}",
+            id,
+            height,
+            snapshot.nodes.len(),
+            snapshot.commitment.root_hex()
+        );
+        self.emit(
+            None,
+            ChangeKind::SnapshotTaken,
+            serde_json::Value::Null,
+            serde_json::json!({
+                "id": id,
+                "nodes": snapshot.nodes.len(),
+                "total_weight": snapshot.total_weight(),
+                "commitment": snapshot.commitment.root_hex(),
+            }),
+            height,
+            ChangeSource::Organic,
+        );
+        {
+            let mut epochs = self.epochs.lock().unwrap();
+            epochs.snapshots.insert(id, snapshot);
+            let pruned = epochs.prune(self.config().epoch.retention);
+            if pruned > 0 {
+                debug!("Pruned {} old epoch snapshots", pruned);
+            }
+            self.save_epochs(&epochs)?;
+        }
+        // Once per epoch is often enough to notice the store outgrowing its threshold
+        if self.store_exceeds_threshold()? {
+            self.compact().await?;
+        }
+        Ok(())
+    }
+
+    /// List registered economic nodes (for RPC and tests).
+    pub async fn list_nodes(&self) -> Vec<EconomicNode> {
+        self.nodes.read().await.values().cloned().collect()
+    }
+
+    /// Saves of the registry since it was opened, e.g. to tell whether a read is still
+    /// current.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Number of registered economic nodes.
+    pub async fn node_count(&self) -> usize {
+        self.nodes.read().await.len()
+    }
+
+    /// List registered economic nodes with their current reputation.
+    pub async fn list_nodes_with_reputation(&self) -> Vec<(EconomicNode, Reputation)> {
+        let height = *self.current_height.read().await;
+        self.nodes
+            .read()
+            .await
+            .values()
+            .map(|n| (n.clone(), reputation::compute(n, height, &self.config().reputation)))
+            .collect()
+    }
+
+    /// Current reputation of a node, if registered.
+    pub async fn reputation(&self, node_id: &[u8; 32]) -> Option<Reputation> {
+        let height = *self.current_height.read().await;
+        self.nodes
+            .read()
+            .await
+            .get(node_id)
+            .map(|n| reputation::compute(n, height, &self.config().reputation))
+    }
+
+    /// Full record of a registered node, or `None` if it is not registered.
+    pub async fn get_node(&self, node_id: &[u8; 32]) -> Option<EconomicNodeDetails> {
+        let node = self.nodes.read().await.get(node_id).cloned()?;
+        Some(self.details(node, false).await)
+    }
+
+    /// Total claimed weight of nodes that were registered and not deactivated at `height`,
+    /// reconstructed from weight histories.
+    pub async fn total_weight_at(&self, height: u64) -> f64 {
+        self.nodes
+            .read()
+            .await
+            .values()
+            .filter(|n| n.deactivated.is_none())
+            .filter_map(|n| history::weight_at(n, height))
+            .sum()
+    }
+
+    /// Full record of an archived (evicted) node.
+    pub async fn get_archived_node(&self, node_id: &[u8; 32]) -> Option<EconomicNodeDetails> {
+        let archived = self.archive.read().await.get(node_id).cloned()?;
+        Some(self.details(archived.node, true).await)
+    }
+
+    /// Look a node up in the on-disk store, for CLI use outside the async runtime.
+    ///
+    /// Returns `Ok(None)` if the node is not found (or there is no store).
+    pub fn read_node_from_store(
+        &self,
+        node_id: &[u8; 32],
+        include_archived: bool,
+    ) -> Result<Option<EconomicNodeDetails>, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(None);
+        };
+        let height = self.current_height.try_read().map(|h| *h).ok();
+        let details = Self::node_from_store(db, &self.config(), node_id, include_archived, height)?;
+        Ok(details.map(|mut d| {
+            d.pending_spends = self.pending_spends(node_id);
+            d
+        }))
+    }
+
+    /// Look a node up in the registry persisted in `db` without a registry or a node
+    /// connection, for the binary's `show-node`. Reputation and weight are computed at
+    /// `height`, or the node's last-seen height if not given; mempool spends are not known.
+    pub fn node_from_store(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        config: &RegistryConfig,
+        node_id: &[u8; 32],
+        include_archived: bool,
+        height: Option<u64>,
+    ) -> Result<Option<EconomicNodeDetails>, GovernanceError> {
+        let (node, archived) = match Self::load_from(db)?.remove(node_id) {
+            Some(node) => (node, false),
+            None if include_archived => match Self::load_archive_from(db)?.remove(node_id) {
+                Some(archived) => (archived.node, true),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let height = height.unwrap_or(node.last_seen);
+        Ok(Some(EconomicNodeDetails {
+            reputation: reputation::compute(&node, height, &config.reputation),
+            raw_weight: decay::raw_weight(&node),
+            effective_weight: decay::effective_weight(&node, height, &config.decay),
+            pending_spends: Vec::new(),
+            node,
+            archived,
+        }))
+    }
+
+    async fn details(&self, node: EconomicNode, archived: bool) -> EconomicNodeDetails {
+        let height = *self.current_height.read().await;
+        EconomicNodeDetails {
+            reputation: reputation::compute(&node, height, &self.config().reputation),
+            raw_weight: decay::raw_weight(&node),
+            effective_weight: decay::effective_weight(&node, height, &self.config().decay),
+            pending_spends: self.pending_spends(&node.node_id),
+            node,
+            archived,
+        }
+    }
+
+    /// Claimed outpoints of a node being spent by unconfirmed transactions.
+    pub fn pending_spends(&self, node_id: &[u8; 32]) -> Vec<PendingSpend> {
+        self.pending_spends
+            .lock()
+            .unwrap()
+            .get(node_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Look for mempool transactions spending claimed outpoints. Each spend found is pending
+    /// until the block containing it arrives, when the claim is re-verified, or until the
+    /// transaction leaves the mempool. Returns the number of new pending spends.
+    pub async fn check_mempool(&self) -> Result<usize, GovernanceError> {
+        let claimed: HashMap<ClaimedOutpoint, [u8; 32]> = self
+            .nodes
+            .read()
+            .await
+            .values()
+            .filter_map(|n| n.reserve_claim.as_ref().map(|c| (n.node_id, c)))
+            .flat_map(|(node_id, c)| c.outpoints.iter().map(move |o| (*o, node_id)))
+            .collect();
+        if claimed.is_empty() {
+            self.pending_spends.lock().unwrap().clear();
+            return Ok(0);
+        }
+        // Background polling; leaves the reserved permits to event handling
+        let node_api = self.node_api.bulk();
+        let txids: HashSet<Hash> = node_api
+            .get_mempool_txids()
+            .await?
+            .into_iter()
+            .collect();
+        self.pending_spends.lock().unwrap().retain(|_, spends| {
+            spends.retain(|s| {
+                let current = txids.contains(&s.txid) && claimed.contains_key(&s.outpoint);
+                if !current {
+                    info!(
+                        "Pending spend of {} by {} left the mempool unconfirmed",
+                        s.outpoint,
+                        hex::encode(s.txid)
+                    );
+                }
+                current
+            });
+            !spends.is_empty()
+        });
+        let unseen: Vec<Hash> = {
+            let seen = self.mempool_seen.lock().unwrap();
+            txids.iter().filter(|t| !seen.0.contains(*t)).copied().collect()
+        };
+        let height = *self.current_height.read().await;
+        let mut found = Vec::new();
+        for txid in unseen {
+            let Some(tx) = node_api.get_mempool_transaction(&txid).await? else {
+                continue;
+            };
+            for input in tx.inputs.iter() {
+                let outpoint = ClaimedOutpoint {
+                    txid: input.prevout.hash,
+                    vout: input.prevout.index,
+                };
+                if let Some(node_id) = claimed.get(&outpoint) {
+                    let spend = PendingSpend {
+                        outpoint,
+                        txid,
+                        seen_at: height,
+                    };
+                    found.push((*node_id, spend));
+                }
+            }
+        }
+        // Only once every new transaction was checked, so a failed pass is retried in full
+        self.remember_mempool_seen(txids);
+        let mut pending = self.pending_spends.lock().unwrap();
+        for (node_id, spend) in &found {
+            warn!(
+                "Claimed outpoint {} of economic node {} is being spent by unconfirmed transaction {}",
+                spend.outpoint,
+                hex::encode(node_id),
+                hex::encode(spend.txid)
+            );
+            pending.entry(*node_id).or_default().push(spend.clone());
+        }
+        Ok(found.len())
+    }
+
+    /// Check the mempool every `mempool_watch_secs` (no-op if 0). Stops if the node has
+    /// mempool queries disabled.
+    pub fn spawn_mempool_watch(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval_secs = self.config().reserve.mempool_watch_secs;
+        if interval_secs == 0 {
+            return None;
+        }
+        let registry = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                match registry.check_mempool().await {
+                    Ok(_) => {}
+                    Err(e @ GovernanceError::MempoolDisabled { .. }) => {
+                        info!("Not watching the mempool for reserve spends: {}", e);
+                        return;
+                    }
+                    Err(e) => warn!("Mempool check failed: {}", e.chain()),
+                }
+            }
+        }))
+    }
+
+    /// Archived (evicted) nodes.
+    pub async fn list_archived(&self) -> Vec<EconomicNode> {
+        self.archive
+            .read()
+            .await
+            .values()
+            .map(|a| a.node.clone())
+            .collect()
+    }
+
+    /// For tests: get snapshot of registered nodes.
+    #[doc(hidden)]
+    pub async fn get_nodes_for_test(&self) -> HashMap<[u8; 32], EconomicNode> {
+        self.nodes.read().await.clone()
+    }
+
+    /// Record the outcome of a proposal on every veto cast against it.
+    pub async fn resolve_veto_outcomes(
+        &self,
+        proposal_id: &str,
+        outcome: VetoOutcome,
+    ) -> Result<(), GovernanceError> {
+        let mut nodes = self.nodes.write().await;
+        let mut changed = false;
+        for node in nodes.values_mut() {
+            for record in node.veto_history.iter_mut() {
+                if record.proposal_id == proposal_id && record.outcome.is_none() {
+                    record.outcome = Some(outcome);
+                    changed = true;
+                }
+            }
+        }
+        self.veto_reporter.forget(proposal_id);
+        {
+            let mut epochs = self.epochs.lock().unwrap();
+            if epochs.proposal_snapshots.remove(proposal_id).is_some() {
+                self.save_epochs(&epochs)?;
+            }
+        }
+        if changed {
+            self.save(&nodes)?;
+        }
+        Ok(())
+    }
+
+    /// Register (or re-register) a node. A reserve claim, if given, is verified against the
+    /// node's UTXO set; unverifiable claims still register, with zero verified weight.
+    ///
+    /// Returns `false` if the registration was rejected by the rate limiter, and a
+    /// `ValidationError` if the payload is malformed.
+    pub async fn register(
+        &self,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        claim: Option<ReserveClaim>,
+    ) -> Result<bool, GovernanceError> {
+        self.register_from(None, node_id, node_type, hashpower_percent, claim)
+            .await
+    }
+
+    /// [`Self::register`] a node on behalf of `origin`, the module that submitted it, which
+    /// is rate limited along with the node id.
+    ///
+    /// A node controlled by a key set is only updated through [`Self::register_multisig`];
+    /// unsigned registrations of it are refused with an `EconomicNodeError`.
+    pub async fn register_from(
+        &self,
+        origin: Option<&str>,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        claim: Option<ReserveClaim>,
+    ) -> Result<bool, GovernanceError> {
+        self.upsert(origin, node_id, node_type, hashpower_percent, claim, None, None)
+            .await
+    }
+
+    /// Insert or update a node, under `keys` if the registration was signed by them.
+    /// `proven_sats` is the reserve a caller already verified by other means than a claim.
+    #[allow(clippy::too_many_arguments)]
+    async fn upsert(
+        &self,
+        origin: Option<&str>,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        claim: Option<ReserveClaim>,
+        keys: Option<KeySet>,
+        proven_sats: Option<u64>,
+    ) -> Result<bool, GovernanceError> {
+        let node_id_bytes = self.validate(node_id, node_type, hashpower_percent, claim.as_ref())?;
+        let controlled = self
+            .nodes
+            .read()
+            .await
+            .get(&node_id_bytes)
+            .is_some_and(|n| n.keys.is_some());
+        if controlled && keys.is_none() {
+            return Err(GovernanceError::EconomicNodeError(format!(
+                "{} is controlled by a key set; register it with the set's signatures",
+                node_id
+            )));
+        }
+        let identities = claim
+            .iter()
+            .map(|c| hex::encode(&c.public_key))
+            .collect();
+        if !self.check_access(node_id, identities, "registration") {
+            return Ok(false);
+        }
+        let current_height = self.node_api.get_block_height().await.unwrap_or(0);
+        let hashpower = hashpower_percent.unwrap_or(0.0);
+
+        let known_sats = self
+            .nodes
+            .read()
+            .await
+            .get(&node_id_bytes)
+            .map(|n| decay::raw_weight(n) as u64);
+        let is_new = known_sats.is_none();
+        let checked = self.limiter.lock().unwrap().check(
+            &self.config().rate_limit,
+            &rate_limit::source_keys(node_id, origin),
+            current_height,
+            is_new,
+        );
+        if let Err(reason) = checked {
+            self.reject_registration(node_id, reason);
+            return Ok(false);
+        }
+
+        let verification = match &claim {
+            Some(claim) => Some(
+                reserve::verify_claim(
+                    &node_id_bytes,
+                    claim,
+                    current_height,
+                    &self.node_api,
+                    &self.config().reserve,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+        // The minimum applies to the reserve proven, not to the weight claimed
+        let verified_sats = match &verification {
+            Some(verification) => verification.verified_sats,
+            None => proven_sats.or(known_sats).unwrap_or(0),
+        };
+        if verified_sats < self.config().rate_limit.min_verified_sats {
+            self.reject_registration(node_id, rate_limit::RateLimitRejection::BelowMinimumStake);
+            return Ok(false);
+        }
+
+        let mut nodes = self.nodes.write().await;
+        if let Some(claim) = &claim {
+            let claimed = claims::outpoints(&claim.outpoints);
+            claims::check_unclaimed(&nodes, &node_id_bytes, &claimed, "reserve.outpoints")?;
+        }
+        if !nodes.contains_key(&node_id_bytes) && nodes.len() >= self.config().max_nodes {
+            let incoming = (
+                verification.as_ref().map(|v| v.verified_sats).unwrap_or(0),
+                current_height,
+            );
+            match capacity::select_eviction(&nodes, incoming) {
+                Some(victim) => {
+                    if let Some(evicted) = nodes.remove(&victim) {
+                        self.emit(
+                            Some(&victim),
+                            ChangeKind::Pruned,
+                            serde_json::json!({ "weight": evicted.hashpower_percentage }),
+                            serde_json::Value::Null,
+                            current_height,
+                            ChangeSource::Organic,
+                        );
+                        let mut archive = self.archive.write().await;
+                        archive.insert(
+                            victim,
+                            ArchivedNode {
+                                node: evicted,
+                                archived_at: current_height,
+                            },
+                        );
+                        self.save_archive(&archive)?;
+                    }
+                    self.registration_counters
+                        .evicted
+                        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    self.event_counters.lock().unwrap().prunes += 1;
+                    info!(
+                        "Registry full ({} nodes): evicted {} to archive for incoming {}",
+                        self.config().max_nodes,
+                        hex::encode(victim),
+                        node_id
+                    );
+                }
+                None => {
+                    self.registration_counters
+                        .rejected_capacity
+                        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    warn!(
+                        "Registry full ({} nodes): rejected {}, no weaker evictable entry",
+                        self.config().max_nodes, node_id
+                    );
+                    return Ok(false);
+                }
+            }
+        }
+        self.registration_counters
+            .accepted
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let node = nodes.entry(node_id_bytes).or_insert_with(|| {
+            let mut node = EconomicNode::new(node_id_bytes, current_height);
+            node.hashpower_percentage = hashpower;
+            node
+        });
+        if is_new || !metrics::is_active(node, current_height, self.config().expiry_blocks) {
+            self.emit(
+                Some(&node_id_bytes),
+                ChangeKind::Activated,
+                if is_new {
+                    serde_json::Value::Null
+                } else {
+                    serde_json::json!({ "last_announced": node.last_announced })
+                },
+                serde_json::json!({ "last_announced": current_height, "weight": hashpower }),
+                current_height,
+                ChangeSource::Organic,
+            );
+        }
+        let before = history::WeightPoint::of(node);
+        if node.hashpower_percentage != hashpower {
+            node.weight_changes += 1;
+            node.hashpower_percentage = hashpower;
+        }
+        if !node_type.is_empty() {
+            node.node_type = node_type.to_string();
+        }
+        node.last_announced = current_height;
+        node.last_seen = current_height;
+        if let (Some(claim), Some(verification)) = (claim, verification) {
+            if let VerificationStatus::VerificationFailed { reason } = &verification.status {
+                warn!("Reserve verification failed for economic node {}: {}", node_id, reason);
+            }
+            node.public_key = claim.public_key.clone();
+            node.reserve_claim = Some(claim);
+            node.address_proof = None;
+            node.verified_weight = verification.verified_sats;
+            node.verification = verification.status;
+        }
+        if keys.is_some() {
+            node.keys = keys;
+        }
+        history::record(
+            node,
+            before,
+            current_height,
+            WeightChangeReason::Registration,
+            self.config().weight_history_len,
+        );
+        self.save(&nodes)?;
+
+        info!(
+            "Registered economic node: {}, type: {}, hashpower: {:?}%",
+            node_id, node_type, hashpower_percent
+        );
+        Ok(true)
+    }
+
+    /// Count a rejected registration, logging one in every `rate_limit.log_sample_rate`.
+    fn reject_registration(&self, node_id: &str, reason: rate_limit::RateLimitRejection) {
+        let total = self.registration_counters.record_rejection(reason);
+        let sample = self.config().rate_limit.log_sample_rate.max(1);
+        if total % sample == 1 || sample == 1 {
+            warn!(
+                "Rejected economic node registration: {} ({}), {} rejections so far",
+                node_id, reason, total
+            );
+        }
+    }
+
+    /// Replace the checked mempool txids with `txids`, keeping only as many as fit in the
+    /// memory share; the others are checked again on the next pass.
+    fn remember_mempool_seen(&self, mut txids: HashSet<Hash>) {
+        use crate::memory::TXID_ENTRY_BYTES;
+        let mut seen = self.mempool_seen.lock().unwrap();
+        // Released first, so the new set gets the whole share
+        seen.1 = None;
+        let memory = &self.mempool_memory;
+        let fit = match memory.limit() {
+            0 => txids.len(),
+            limit => (limit.saturating_sub(memory.used()) / TXID_ENTRY_BYTES) as usize,
+        };
+        if txids.len() > fit {
+            memory.overflowed((txids.len() - fit) as u64);
+            txids = txids.into_iter().take(fit).collect();
+        }
+        let charge = memory.charge(txids.len() as u64 * TXID_ENTRY_BYTES);
+        *seen = (txids, Some(charge));
+    }
+
+    /// Challenge an address proof for `node_id` should sign, over the newest known block.
+    pub fn address_challenge(&self, node_id: &[u8; 32]) -> Option<String> {
+        let recent = self.recent_blocks.lock().unwrap();
+        let (_, hash) = recent.back()?;
+        Some(address_proof::challenge_message(node_id, hash))
+    }
+
+    /// Register a node whose weight is the confirmed balance of an address it proves control
+    /// of by signing [`address_proof::challenge_message`].
+    ///
+    /// Proofs over a block that is unknown or more than `reserve.max_challenge_depth` blocks
+    /// below the tip are rejected, as are invalid signatures. `origin` is rate limited as in
+    /// [`Self::register_from`].
+    pub async fn register_with_address_proof(
+        &self,
+        origin: Option<&str>,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        proof: AddressProof,
+    ) -> Result<bool, GovernanceError> {
+        let node_id_bytes = self.validate(node_id, node_type, hashpower_percent, None)?;
+        let invalid = |field: &str, reason: String| GovernanceError::ValidationError {
+            field: format!("address_proof.{}", field),
+            reason,
+        };
+        let address = address_proof::AddressKind::parse(&proof.address).ok_or_else(|| {
+            invalid(
+                "address",
+                "not a P2PKH, P2WPKH or P2TR address".to_string(),
+            )
+        })?;
+        let block_hash = address_proof::parse_block_hash(&proof.block_hash)
+            .ok_or_else(|| invalid("block_hash", "must be 32 bytes of hex".to_string()))?;
+        let challenge_height = self
+            .recent_blocks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, hash)| *hash == block_hash)
+            .map(|(height, _)| *height);
+        let current_height = *self.current_height.read().await;
+        let depth = self.config().reserve.max_challenge_depth;
+        match challenge_height {
+            Some(height) if current_height.saturating_sub(height) <= depth => {}
+            _ => {
+                return Err(invalid(
+                    "block_hash",
+                    format!("challenge expired: block is not among the last {} blocks", depth),
+                ))
+            }
+        }
+        let message = address_proof::challenge_message(&node_id_bytes, &block_hash);
+        if !address_proof::verify(&address, &message, &proof.signature) {
+            return Err(invalid("signature", "does not verify".to_string()));
+        }
+        let balance = self
+            .node_api
+            .get_address_balance(&address.script_pubkey(), self.config().reserve.min_confirmations)
+            .await?;
+        let claimed = [claims::ReserveSource::Address(address.script_pubkey())];
+        claims::check_unclaimed(
+            &*self.nodes.read().await,
+            &node_id_bytes,
+            &claimed,
+            "address_proof.address",
+        )?;
+
+        if !self
+            .upsert(origin, node_id, node_type, hashpower_percent, None, None, Some(balance))
+            .await?
+        {
+            return Ok(false);
+        }
+        let mut nodes = self.nodes.write().await;
+        // Another registration may have taken the address since the check above
+        claims::check_unclaimed(&nodes, &node_id_bytes, &claimed, "address_proof.address")?;
+        if let Some(node) = nodes.get_mut(&node_id_bytes) {
+            let before = history::WeightPoint::of(node);
+            node.verified_weight = balance;
+            node.verification = VerificationStatus::Verified {
+                height: current_height,
+            };
+            node.reserve_claim = None;
+            node.address_proof = Some(proof);
+            history::record(
+                node,
+                before,
+                current_height,
+                WeightChangeReason::Registration,
+                self.config().weight_history_len,
+            );
+            self.save(&nodes)?;
+        }
+        info!(
+            "Verified address proof for economic node {}: {} sats",
+            node_id, balance
+        );
+        Ok(true)
+    }
+
+    /// Register a Lightning routing node by its node key.
+    ///
+    /// `signature` is a zbase32 `signmessage` signature by the node key over
+    /// [`reserve::registration_message`] for the channel outpoints. The node is stored under
+    /// the [`lightning::LIGHTNING_NODE_TYPE`] category. Channel outpoints, if any, must be
+    /// distinct, unspent and sufficiently confirmed, and come with their funding witness
+    /// scripts (`funding_scripts`, in the same order), each a 2-of-2 with the node key that
+    /// the outpoint pays to; their total value becomes the verified weight. `origin` is rate
+    /// limited as in [`Self::register_from`].
+    pub async fn register_lightning(
+        &self,
+        origin: Option<&str>,
+        node_id: &str,
+        hashpower_percent: Option<f64>,
+        identity: LightningIdentity,
+        signature: &str,
+        funding_scripts: &[Vec<u8>],
+    ) -> Result<bool, GovernanceError> {
+        let node_type = lightning::LIGHTNING_NODE_TYPE;
+        let node_id_bytes = self.validate(node_id, node_type, hashpower_percent, None)?;
+        let invalid = |field: &str, reason: String| GovernanceError::ValidationError {
+            field: format!("lightning.{}", field),
+            reason,
+        };
+        if identity.channel_outpoints.len() > self.config().validation.max_outpoints {
+            return Err(invalid(
+                "channel_outpoints",
+                format!(
+                    "{} outpoints exceeds the maximum of {}",
+                    identity.channel_outpoints.len(),
+                    self.config().validation.max_outpoints
+                ),
+            ));
+        }
+        let mut seen = HashSet::new();
+        if let Some(dup) = identity.channel_outpoints.iter().find(|o| !seen.insert(**o)) {
+            self.validation_counters
+                .record(validation::ValidationRule::DuplicateOutpoint);
+            return Err(invalid(
+                "channel_outpoints",
+                format!("outpoint {} listed twice", dup),
+            ));
+        }
+        if funding_scripts.len() != identity.channel_outpoints.len() {
+            return Err(invalid(
+                "funding_scripts",
+                format!(
+                    "{} funding scripts for {} channel outpoints",
+                    funding_scripts.len(),
+                    identity.channel_outpoints.len()
+                ),
+            ));
+        }
+        let claimed = claims::outpoints(&identity.channel_outpoints);
+        claims::check_unclaimed(
+            &*self.nodes.read().await,
+            &node_id_bytes,
+            &claimed,
+            "lightning.channel_outpoints",
+        )?;
+        let message = reserve::registration_message(&node_id_bytes, &identity.channel_outpoints);
+        if !lightning::verify(&identity.node_pubkey, &message, signature) {
+            return Err(GovernanceError::ValidationError {
+                field: "lightning.signature".to_string(),
+                reason: "does not verify against the node public key".to_string(),
+            });
+        }
+
+        let current_height = self.node_api.get_block_height().await.unwrap_or(0);
+        let mut verification = None;
+        if !identity.channel_outpoints.is_empty() {
+            let mut total: u64 = 0;
+            let mut failure = None;
+            for (i, outpoint) in identity.channel_outpoints.iter().enumerate() {
+                let Some(utxo) = self.node_api.get_utxo(&outpoint.to_outpoint()).await? else {
+                    failure = Some(format!("channel outpoint {} is spent or unknown", outpoint));
+                    break;
+                };
+                let confirmations = current_height.saturating_sub(utxo.height) + 1;
+                if confirmations < self.config().reserve.min_confirmations {
+                    failure = Some(format!(
+                        "channel outpoint {} has {} confirmations, {} required",
+                        outpoint, confirmations, self.config().reserve.min_confirmations
+                    ));
+                    break;
+                }
+                if !lightning::funding_script_matches(
+                    &utxo.script_pubkey,
+                    &funding_scripts[i],
+                    &identity.node_pubkey,
+                ) {
+                    failure = Some(format!(
+                        "channel outpoint {} is not a 2-of-2 funding output with the node key",
+                        outpoint
+                    ));
+                    break;
+                }
+                total = total.saturating_add(utxo.value.max(0) as u64);
+            }
+            verification = Some(match failure {
+                Some(reason) => {
+                    warn!("Channel verification failed for economic node {}: {}", node_id, reason);
+                    (0, VerificationStatus::VerificationFailed { reason })
+                }
+                None => (
+                    total,
+                    VerificationStatus::Verified {
+                        height: current_height,
+                    },
+                ),
+            });
+        }
+
+        let proven_sats = verification.as_ref().map(|(sats, _)| *sats);
+        if !self
+            .upsert(origin, node_id, node_type, hashpower_percent, None, None, proven_sats)
+            .await?
+        {
+            return Ok(false);
+        }
+        let mut nodes = self.nodes.write().await;
+        // Another registration may have taken a channel since the check above
+        claims::check_unclaimed(&nodes, &node_id_bytes, &claimed, "lightning.channel_outpoints")?;
+        if let Some(node) = nodes.get_mut(&node_id_bytes) {
+            let before = history::WeightPoint::of(node);
+            node.public_key = identity.node_pubkey.clone();
+            node.lightning = Some(identity);
+            if let Some((sats, status)) = verification {
+                node.verified_weight = sats;
+                node.verification = status;
+            }
+            history::record(
+                node,
+                before,
+                current_height,
+                WeightChangeReason::Registration,
+                self.config().weight_history_len,
+            );
+            self.save(&nodes)?;
+        }
+        Ok(true)
+    }
+
+    /// Register a node controlled by a threshold key set.
+    ///
+    /// `signatures` must hold at least `keys.threshold` valid signatures over
+    /// [`multisig::registration_message`], which covers the claimed weight. A node already
+    /// registered under a different key set must go through [`Self::rotate_keys`] instead,
+    /// and one registered without a key set must show control of it first: its public key
+    /// must be in `keys` and have signed. `origin` is rate limited as in
+    /// [`Self::register_from`].
+    pub async fn register_multisig(
+        &self,
+        origin: Option<&str>,
+        node_id: &str,
+        node_type: &str,
+        hashpower_percent: Option<f64>,
+        keys: KeySet,
+        signatures: &[KeySignature],
+    ) -> Result<bool, GovernanceError> {
+        let node_id_bytes = self.validate(node_id, node_type, hashpower_percent, None)?;
+        keys.validate()
+            .map_err(|reason| GovernanceError::ValidationError {
+                field: "keys".to_string(),
+                reason,
+            })?;
+        let identities = keys.public_keys.iter().map(hex::encode).collect();
+        if !self.check_access(node_id, identities, "registration") {
+            return Ok(false);
+        }
+        let message = multisig::registration_message(&node_id_bytes, &keys, hashpower_percent);
+        Self::check_threshold(&keys, &message, signatures, "registration")?;
+        if let Some(existing) = self.nodes.read().await.get(&node_id_bytes) {
+            match &existing.keys {
+                Some(current) if current.canonical() != keys.canonical() => {
+                    return Err(GovernanceError::EconomicNodeError(format!(
+                        "{} is registered under a different key set; rotate keys instead",
+                        node_id
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    let signed = keys
+                        .public_keys
+                        .iter()
+                        .position(|k| *k == existing.public_key)
+                        .is_some_and(|index| {
+                            signatures.iter().any(|s| {
+                                s.key_index as usize == index
+                                    && reserve::verify_signature(
+                                        &existing.public_key,
+                                        &message,
+                                        &s.signature,
+                                    )
+                            })
+                        });
+                    if !signed {
+                        return Err(GovernanceError::EconomicNodeError(format!(
+                            "{} is registered without a key set; the new set must include \
+                             and be signed by its public key",
+                            node_id
+                        )));
+                    }
+                }
+            }
+        }
+
+        self.upsert(
+            origin,
+            node_id,
+            node_type,
+            hashpower_percent,
+            None,
+            Some(keys),
+            None,
+        )
+        .await
+    }
+
+    /// Record a veto, `signed` over [`multisig::veto_message`] by the node's key set if it
+    /// has one.
+    pub async fn veto(
+        &self,
+        proposal_id: &str,
+        node_id: &str,
+        reason: &str,
+        signed: Option<&SignedAt>,
+    ) -> Result<(), GovernanceError> {
+        let Some(id) = parse_node_id(node_id) else {
+            return Ok(());
+        };
+        self.authorize(
+            &id,
+            signed,
+            |height| multisig::veto_message(&id, proposal_id, reason, height),
+            "veto",
+        )
+        .await?;
+        if let Some(reason) = self
+            .nodes
+            .read()
+            .await
+            .get(&id)
+            .and_then(|n| n.deactivated.clone())
+        {
+            warn!("Ignored veto on {} from economic node {}: {}", proposal_id, node_id, reason);
+            return Ok(());
+        }
+        if !self.check_access(node_id, Vec::new(), "veto") {
+            return Ok(());
+        }
+        self.on_veto(proposal_id, node_id, reason).await?;
+        let signed = signed.filter(|s| !s.signatures.is_empty());
+        if let (Some(signed), true) = (signed, self.nodes.read().await.contains_key(&id)) {
+            let mut stored = self.veto_signatures.lock().unwrap();
+            stored
+                .entry(proposal_id.to_string())
+                .or_default()
+                .insert(hex::encode(id), signed.clone());
+            self.save_veto_signatures(&stored)?;
+        }
+        Ok(())
+    }
+
+    /// Withdraw a node's veto on a still-open proposal, `signed` over
+    /// [`multisig::revocation_message`] if the node has a key set. Returns `false` if there
+    /// was none.
+    pub async fn revoke_veto(
+        &self,
+        proposal_id: &str,
+        node_id: &str,
+        signed: Option<&SignedAt>,
+    ) -> Result<bool, GovernanceError> {
+        let Some(id) = parse_node_id(node_id) else {
+            return Ok(false);
+        };
+        self.authorize(
+            &id,
+            signed,
+            |height| multisig::revocation_message(&id, proposal_id, height),
+            "veto revocation",
+        )
+        .await?;
+        let mut nodes = self.nodes.write().await;
+        let Some(node) = nodes.get_mut(&id) else {
+            return Ok(false);
+        };
+        let before = node.veto_history.len();
+        node.veto_history
+            .retain(|v| !(v.proposal_id == proposal_id && v.outcome.is_none()));
+        let revoked = before - node.veto_history.len();
+        if revoked == 0 {
+            return Ok(false);
+        }
+        node.veto_count = node.veto_count.saturating_sub(revoked as u32);
+        self.event_counters.lock().unwrap().revocations += 1;
+        {
+            let mut stored = self.veto_signatures.lock().unwrap();
+            let signed = stored.get_mut(proposal_id);
+            if signed.and_then(|s| s.remove(&hex::encode(id))).is_some() {
+                stored.retain(|_, s| !s.is_empty());
+                self.save_veto_signatures(&stored)?;
+            }
+        }
+        info!(
+            "Economic node {} revoked its veto on proposal {}",
+            node_id, proposal_id
+        );
+        self.save(&nodes)?;
+        self.add_to_digest(
+            crate::digest::VETO_REVOKED_EVENT,
+            serde_json::json!({ "proposal_id": proposal_id, "node_id": node_id }),
+        );
+        Ok(true)
+    }
+
+    /// Replace a node's key set. Requires threshold signatures from the current set over
+    /// [`multisig::rotation_message`].
+    pub async fn rotate_keys(
+        &self,
+        node_id: &str,
+        new_keys: KeySet,
+        signed: &SignedAt,
+    ) -> Result<(), GovernanceError> {
+        let id = parse_node_id(node_id).ok_or_else(|| GovernanceError::ValidationError {
+            field: "node_id".to_string(),
+            reason: "node id must be 64 hex characters".to_string(),
+        })?;
+        new_keys
+            .validate()
+            .map_err(|reason| GovernanceError::ValidationError {
+                field: "keys".to_string(),
+                reason,
+            })?;
+        self.check_signed_height(signed.height, "key rotation")
+            .await?;
+        let height = *self.current_height.read().await;
+        let mut nodes = self.nodes.write().await;
+        let node = nodes.get_mut(&id).ok_or_else(|| {
+            GovernanceError::EconomicNodeError(format!("unknown economic node {}", node_id))
+        })?;
+        let current = node.keys.as_ref().ok_or_else(|| {
+            GovernanceError::EconomicNodeError(format!("{} has no key set to rotate", node_id))
+        })?;
+        let message = multisig::rotation_message(&id, &new_keys, signed.height);
+        Self::check_threshold(current, &message, &signed.signatures, "key rotation")?;
+        info!(
+            "Rotated key set of economic node {} to {} of {}",
+            node_id,
+            new_keys.threshold,
+            new_keys.public_keys.len()
+        );
+        self.emit(
+            Some(&id),
+            ChangeKind::KeyRotated,
+            serde_json::json!({ "threshold": current.threshold, "keys": current.public_keys.len() }),
+            serde_json::json!({ "threshold": new_keys.threshold, "keys": new_keys.public_keys.len() }),
+            height,
+            ChangeSource::Organic,
+        );
+        node.keys = Some(new_keys);
+        self.save(&nodes)?;
+        Ok(())
+    }
+
+    /// Check that `signatures` over `message` show control of the registered, active node
+    /// `node_id`: `threshold` of its key set, or, without a key set, one by its public key
+    /// (key index 0). Unlike vetoes received from the node, a node with no key at all fails.
+    pub async fn check_control(
+        &self,
+        node_id: &str,
+        message: &str,
+        signatures: &[KeySignature],
+        action: &str,
+    ) -> Result<(), GovernanceError> {
+        let id = parse_node_id(node_id).ok_or_else(|| GovernanceError::ValidationError {
+            field: "node_id".to_string(),
+            reason: "node id must be 64 hex characters".to_string(),
+        })?;
+        let nodes = self.nodes.read().await;
+        let node = nodes.get(&id).ok_or_else(|| {
+            GovernanceError::EconomicNodeError(format!("unknown economic node {}", node_id))
+        })?;
+        if let Some(reason) = &node.deactivated {
+            return Err(GovernanceError::EconomicNodeError(format!(
+                "{} is deactivated: {}",
+                node_id, reason
+            )));
+        }
+        match &node.keys {
+            Some(keys) => Self::check_threshold(keys, message, signatures, action),
+            None if node.public_key.is_empty() => Err(GovernanceError::EconomicNodeError(format!(
+                "{} has no key to sign {} with",
+                node_id, action
+            ))),
+            None => {
+                let signed = signatures.iter().any(|s| {
+                    s.key_index == 0
+                        && reserve::verify_signature(&node.public_key, message, &s.signature)
+                });
+                if !signed {
+                    return Err(GovernanceError::EconomicNodeError(format!(
+                        "{} needs a valid signature by the key of {}",
+                        action, node_id
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Require threshold signatures over the message `message` builds for the signed height
+    /// if the node has a key set.
+    async fn authorize(
+        &self,
+        node_id: &[u8; 32],
+        signed: Option<&SignedAt>,
+        message: impl FnOnce(u64) -> String,
+        action: &str,
+    ) -> Result<(), GovernanceError> {
+        let keys = self
+            .nodes
+            .read()
+            .await
+            .get(node_id)
+            .and_then(|n| n.keys.clone());
+        let Some(keys) = keys else {
+            return Ok(());
+        };
+        let signed = signed.ok_or_else(|| {
+            GovernanceError::EconomicNodeError(format!(
+                "{} needs {} of {} signatures, got none",
+                action,
+                keys.threshold,
+                keys.public_keys.len()
+            ))
+        })?;
+        self.check_signed_height(signed.height, action).await?;
+        Self::check_threshold(&keys, &message(signed.height), &signed.signatures, action)
+    }
+
+    /// Require a signed `height` at most `reserve.max_challenge_depth` blocks below the tip.
+    pub async fn check_signed_height(
+        &self,
+        height: u64,
+        action: &str,
+    ) -> Result<(), GovernanceError> {
+        let seen = *self.current_height.read().await;
+        let tip = self.node_api.get_block_height().await.unwrap_or(seen).max(seen);
+        let depth = self.config().reserve.max_challenge_depth;
+        if height > tip || tip - height > depth {
+            return Err(GovernanceError::EconomicNodeError(format!(
+                "{} signed at height {} must be within {} blocks below the tip at {}",
+                action, height, depth, tip
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_threshold(
+        keys: &KeySet,
+        message: &str,
+        signatures: &[KeySignature],
+        action: &str,
+    ) -> Result<(), GovernanceError> {
+        let valid = keys.count_valid(message, signatures);
+        if valid < keys.threshold as usize {
+            return Err(GovernanceError::EconomicNodeError(format!(
+                "{} needs {} of {} signatures, got {} valid",
+                action,
+                keys.threshold,
+                keys.public_keys.len(),
+                valid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fetch the node's economic node set and reconcile local state against it. Fails with
+    /// [`GovernanceError::Unsupported`], leaving local state alone, if no module serves it.
+    pub async fn reconcile_now(&self) -> Result<ReconcileSummary, GovernanceError> {
+        let remote = self.node_api.bulk().list_economic_nodes().await?;
+        self.reconcile(&remote).await
+    }
+
+    /// Reconcile local state against the node's authoritative economic node set.
+    ///
+    /// Nodes missing locally are added and nodes the node no longer knows are removed.
+    /// Local-only state (reputation signals, veto history, reserve verification) of nodes
+    /// present on both sides is preserved.
+    pub async fn reconcile(
+        &self,
+        remote: &[NodeEconomicNode],
+    ) -> Result<ReconcileSummary, GovernanceError> {
+        let height = *self.current_height.read().await;
+        let mut summary = ReconcileSummary::default();
+        let mut nodes = self.nodes.write().await;
+
+        let mut remote_ids = HashSet::new();
+        for entry in remote {
+            let node_id = match self.validate(
+                &entry.node_id,
+                &entry.node_type,
+                entry.hashpower_percent,
+                None,
+            ) {
+                Ok(node_id) => node_id,
+                Err(e) => {
+                    warn!("Ignoring invalid economic node from node during reconciliation: {}", e);
+                    continue;
+                }
+            };
+            remote_ids.insert(node_id);
+            let hashpower = entry.hashpower_percent.unwrap_or(0.0);
+            match nodes.get_mut(&node_id) {
+                Some(node) => {
+                    if !entry.node_type.is_empty() && node.node_type != entry.node_type {
+                        node.node_type = entry.node_type.clone();
+                    }
+                    if node.hashpower_percentage != hashpower {
+                        let before = history::WeightPoint::of(node);
+                        node.hashpower_percentage = hashpower;
+                        node.weight_changes += 1;
+                        history::record(
+                            node,
+                            before,
+                            height,
+                            WeightChangeReason::Reconciliation,
+                            self.config().weight_history_len,
+                        );
+                        summary.updated += 1;
+                    }
+                }
+                None => {
+                    let mut node = EconomicNode::new(node_id, height);
+                    node.node_type = entry.node_type.clone();
+                    node.hashpower_percentage = hashpower;
+                    nodes.insert(node_id, node);
+                    summary.added += 1;
+                    self.emit(
+                        Some(&node_id),
+                        ChangeKind::Activated,
+                        serde_json::Value::Null,
+                        serde_json::json!({ "weight": hashpower }),
+                        height,
+                        ChangeSource::Reconciliation,
+                    );
+                }
+            }
+        }
+        let before = nodes.len();
+        nodes.retain(|id, node| {
+            let keep = remote_ids.contains(id);
+            if !keep {
+                self.emit(
+                    Some(id),
+                    ChangeKind::Pruned,
+                    serde_json::json!({ "weight": node.hashpower_percentage }),
+                    serde_json::Value::Null,
+                    height,
+                    ChangeSource::Reconciliation,
+                );
+            }
+            keep
+        });
+        summary.removed = before - nodes.len();
+        self.event_counters.lock().unwrap().prunes += summary.removed as u64;
+        summary.updated += self.apply_blocklist(&mut nodes).await;
+
+        if summary.is_empty() {
+            debug!("Registry reconciliation: no drift ({} nodes)", nodes.len());
+        } else {
+            self.save(&nodes)?;
+            info!(
+                "Registry reconciliation: {} added, {} removed, {} updated",
+                summary.added, summary.removed, summary.updated
+            );
+        }
+        Ok(summary)
+    }
+
+    /// Reconcile now and then every `reconcile_interval_secs` (no-op if the interval is 0).
+    pub fn spawn_reconciliation(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval_secs = self.config().reconcile_interval_secs;
+        if interval_secs == 0 {
+            return None;
+        }
+        let registry = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                match registry.reconcile_now().await {
+                    Ok(_) => {}
+                    Err(e @ GovernanceError::Unsupported { .. }) => {
+                        info!("Node serves no economic node set, not reconciling: {}", e);
+                        return;
+                    }
+                    Err(e) => warn!("Registry reconciliation failed: {}", e.chain()),
+                }
+            }
+        }))
+    }
+
+    /// Deliver queued veto results to the node as they change, retrying failed sends every
+    /// `report_retry_secs`.
+    pub fn spawn_veto_reporting(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if self.config().veto.observe_only {
+            info!("Veto reporting disabled (observe-only mode)");
+            return None;
+        }
+        let retry = std::time::Duration::from_secs(self.config().veto.report_retry_secs.max(1));
+        let registry = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = registry.veto_reporter.notified() => {}
+                    _ = tokio::time::sleep(retry) => {}
+                }
+                // Errors are logged by the reporter; unsent tallies stay queued
+                let _ = registry.veto_reporter.flush(&registry.node_api).await;
+            }
+        }))
+    }
+
+    /// Send queued veto results now, e.g. before shutting down. Returns the number sent.
+    pub async fn flush_veto_reports(&self) -> Result<usize, GovernanceError> {
+        self.veto_reporter.flush(&self.node_api).await
+    }
+
+    /// Handle governance events
+    pub async fn handle_event(
+        &self,
+        event: &ModuleMessage,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let ModuleMessage::Event(event_msg) = event else {
+            return Ok(());
+        };
+        match (&event_msg.event_type, &event_msg.payload) {
+            (
+                EventType::EconomicNodeRegistered,
+                EventPayload::EconomicNodeRegistered {
+                    node_id,
+                    node_type,
+                    hashpower_percent,
+                },
+            ) => {
+                self.register(node_id, node_type, *hashpower_percent, None)
+                    .await?;
+            }
+            (
+                EventType::EconomicNodeVeto,
+                EventPayload::EconomicNodeVeto {
+                    proposal_id,
+                    node_id,
+                    reason,
+                },
+            ) => {
+                // Unsigned: accepted only for nodes without a threshold key set
+                self.veto(proposal_id, node_id, reason, None).await?;
+            }
+            (
+                EventType::GovernanceProposalCreated,
+                EventPayload::GovernanceProposalCreated { proposal_id, .. },
+            ) => {
+                self.pin_proposal(proposal_id)?;
+            }
+            (
+                EventType::GovernanceProposalMerged,
+                EventPayload::GovernanceProposalMerged { proposal_id, .. },
+            ) => {
+                self.resolve_veto_outcomes(proposal_id, VetoOutcome::MergedAnyway)
+                    .await?;
+            }
+            (EventType::NewBlock, EventPayload::NewBlock { block_hash, height }) => {
+                self.on_new_block(block_hash, *height).await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn on_veto(
+        &self,
+        proposal_id: &str,
+        node_id: &str,
+        reason: &str,
+    ) -> Result<(), GovernanceError> {
+        let height = *self.current_height.read().await;
+        let mut nodes = self.nodes.write().await;
+        if let Some(arr) = parse_node_id(node_id) {
+            if let Some(node) = nodes.get_mut(&arr) {
+                node.veto_count += 1;
+                self.event_counters.lock().unwrap().vetoes += 1;
+                node.veto_history.push(VetoRecord {
+                    proposal_id: proposal_id.to_string(),
+                    height,
+                    reason: reason.to_string(),
+                    outcome: None,
+                });
+                warn!(
+                    "Economic node vetoed: {}, proposal: {}, reason: {}, veto count: {}",
+                    node_id, proposal_id, reason, node.veto_count
+                );
+                self.save(&nodes)?;
+                self.add_to_digest(
+                    crate::digest::VETO_CAST_EVENT,
+                    serde_json::json!({
+                        "proposal_id": proposal_id,
+                        "node_id": node_id,
+                        "reason": reason,
+                        "height": height,
+                    }),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the vetoes cast above `fork_height`, and the blocks seen above it, after a reorg
+    /// replaced them. The nodes are saved once, with every veto removed. Returns the number of
+    /// vetoes removed.
+    pub async fn roll_back_to(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        {
+            let mut height = self.current_height.write().await;
+            *height = (*height).min(fork_height);
+        }
+        {
+            let mut recent = self.recent_blocks.lock().unwrap();
+            while recent.back().is_some_and(|(h, _)| *h > fork_height) {
+                recent.pop_back();
+            }
+        }
+        let mut nodes = self.nodes.write().await;
+        let mut removed = 0;
+        for (id, node) in nodes.iter_mut() {
+            let before = node.veto_history.len();
+            node.veto_history.retain(|v| v.height <= fork_height);
+            let reverted = before - node.veto_history.len();
+            if reverted == 0 {
+                continue;
+            }
+            warn!(
+                "Reorg to height {} removed {} vetoes of economic node {}",
+                fork_height,
+                reverted,
+                hex::encode(id)
+            );
+            node.veto_count = node.veto_count.saturating_sub(reverted as u32);
+            removed += reverted as u64;
+        }
+        if removed > 0 {
+            self.save(&nodes)?;
+        }
+        Ok(removed)
+    }
+
+    async fn on_new_block(&self, block_hash: &Hash, height: u64) -> Result<(), GovernanceError> {
+        *self.current_height.write().await = height;
+        {
+            let mut recent = self.recent_blocks.lock().unwrap();
+            // A reorg replaces blocks at and above this height
+            while recent.back().is_some_and(|(h, _)| *h >= height) {
+                recent.pop_back();
+            }
+            recent.push_back((height, *block_hash));
+            let keep = self.config().reserve.max_challenge_depth as usize + 1;
+            while recent.len() > keep {
+                recent.pop_front();
+            }
+        }
+        {
+            let window = self.config().reputation.liveness_window_blocks;
+            let expiry = self.config().expiry_blocks;
+            let mut nodes = self.nodes.write().await;
+            for node in nodes.values_mut() {
+                if expiry > 0
+                    && node.deactivated.is_none()
+                    && height == node.last_announced + expiry + 1
+                {
+                    self.emit(
+                        Some(&node.node_id),
+                        ChangeKind::Expired,
+                        serde_json::json!({ "last_announced": node.last_announced }),
+                        serde_json::Value::Null,
+                        height,
+                        ChangeSource::Organic,
+                    );
+                }
+                node.last_seen = height;
+                node.liveness_samples += 1;
+                if height.saturating_sub(node.last_announced) <= window {
+                    node.liveness_hits += 1;
+                }
+            }
+            self.save(&nodes)?;
+        }
+        self.maybe_snapshot(height).await?;
+        self.reverify_spent_claims(block_hash, height).await
+    }
+
+    /// Re-verify reserve claims whose outpoints are spent by the given block.
+    async fn reverify_spent_claims(
+        &self,
+        block_hash: &Hash,
+        height: u64,
+    ) -> Result<(), GovernanceError> {
+        let claims: Vec<([u8; 32], ReserveClaim)> = self
+            .nodes
+            .read()
+            .await
+            .values()
+            .filter_map(|n| n.reserve_claim.clone().map(|c| (n.node_id, c)))
+            .collect();
+        if claims.is_empty() {
+            return Ok(());
+        }
+        let Some(block) = self.node_api.get_block(block_hash).await? else {
+            return Ok(());
+        };
+        let spent: HashSet<ClaimedOutpoint> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .map(|input| ClaimedOutpoint {
+                txid: input.prevout.hash,
+                vout: input.prevout.index,
+            })
+            .collect();
+        self.pending_spends.lock().unwrap().retain(|node_id, spends| {
+            spends.retain(|s| {
+                if spent.contains(&s.outpoint) {
+                    info!(
+                        "Pending spend of {} by economic node {} confirmed at height {}",
+                        s.outpoint,
+                        hex::encode(node_id),
+                        height
+                    );
+                }
+                !spent.contains(&s.outpoint)
+            });
+            !spends.is_empty()
+        });
+
+        for (node_id, claim) in claims {
+            if !claim.outpoints.iter().any(|o| spent.contains(o)) {
+                continue;
+            }
+            let verification = reserve::verify_claim(
+                &node_id,
+                &claim,
+                height,
+                &self.node_api,
+                &self.config().reserve,
+            )
+            .await?;
+            info!(
+                "Re-verified economic node {} after claimed outpoint was spent: {} sats ({:?})",
+                hex::encode(node_id),
+                verification.verified_sats,
+                verification.status
+            );
+            let mut nodes = self.nodes.write().await;
+            if let Some(node) = nodes.get_mut(&node_id) {
+                let before = history::WeightPoint::of(node);
+                self.emit(
+                    Some(&node_id),
+                    ChangeKind::Reverified,
+                    serde_json::json!({
+                        "verified_sats": node.verified_weight,
+                        "verification": node.verification,
+                    }),
+                    serde_json::json!({
+                        "verified_sats": verification.verified_sats,
+                        "verification": verification.status,
+                    }),
+                    height,
+                    ChangeSource::Organic,
+                );
+                node.verified_weight = verification.verified_sats;
+                node.verification = verification.status;
+                history::record(
+                    node,
+                    before,
+                    height,
+                    WeightChangeReason::Reverification,
+                    self.config().weight_history_len,
+                );
+                self.save(&nodes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, nodes: &HashMap<[u8; 32], EconomicNode>) -> Result<(), GovernanceError> {
+        self.revision.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let commitment = commitment::compute(nodes.values());
+        self.log_commitment_change(commitment);
+        self.update_veto_tallies(nodes, &commitment);
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let result = self.write(db.as_ref(), nodes);
+        if let (Err(e), Some(errors)) = (&result, &self.errors) {
+            errors.report(
+                crate::error_report::ErrorReport::new(
+                    crate::error_report::ErrorCode::RegistryWriteFailed,
+                    e.chain().to_string(),
+                )
+                .with_context("tree", REGISTRY_TREE),
+            );
+        }
+        result
+    }
+
+    fn write(
+        &self,
+        db: &dyn blvm_node::storage::database::Database,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+    ) -> Result<(), GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(nodes)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(STORAGE_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let counters = PersistedCounters {
+            registrations: self.registration_counters.snapshot(),
+            events: *self.event_counters.lock().unwrap(),
+        };
+        let data = bincode::serialize(&counters)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(COUNTERS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn save_archive(
+        &self,
+        archive: &HashMap<[u8; 32], ArchivedNode>,
+    ) -> Result<(), GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(archive)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(ARCHIVE_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    /// Load archived (evicted) economic nodes.
+    pub fn load_archive_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<HashMap<[u8; 32], ArchivedNode>, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(ARCHIVE_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(HashMap::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    fn save_epochs(&self, epochs: &snapshot::EpochState) -> Result<(), GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(epochs)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(EPOCHS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn save_access_audit(&self, audit: &[AccessAuditEntry]) -> Result<(), GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(audit)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(ACCESS_AUDIT_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn save_veto_signatures(&self, signatures: &VetoSignatures) -> Result<(), GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(signatures)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(VETO_SIGNATURES_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn load_veto_signatures_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<VetoSignatures, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(VETO_SIGNATURES_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(VetoSignatures::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    fn load_access_audit_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Vec<AccessAuditEntry>, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(ACCESS_AUDIT_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    /// Load stored epoch snapshots.
+    pub fn load_epochs_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<snapshot::EpochState, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(EPOCHS_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(snapshot::EpochState::default()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    fn load_counters_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<PersistedCounters, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(COUNTERS_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(PersistedCounters::default()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+
+    fn log_commitment_change(&self, commitment: RegistryCommitment) {
+        let mut last = self.last_commitment.lock().unwrap();
+        if last.map(|c| c.root) != Some(commitment.root) {
+            info!(
+                "Registry commitment changed: {} ({} nodes)",
+                commitment.root_hex(),
+                commitment.node_count
+            );
+            if let Some(audit_log) = &self.audit_log {
+                audit_log.registry_commitment(&commitment, self.height_now());
+            }
+            *last = Some(commitment);
+        }
+    }
+
+    /// Recompute tallies of proposals with open vetoes or previously reported results.
+    fn update_veto_tallies(
+        &self,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        commitment: &RegistryCommitment,
+    ) {
+        let height = self.height_now();
+        let mut proposals = tally::open_veto_proposals(nodes.values());
+        proposals.extend(self.veto_reporter.tracked());
+        if let Some(feed) = &self.feed {
+            proposals.extend(feed.crossed());
+        }
+        if let Some(escalation) = &self.escalation {
+            proposals.extend(escalation.tracked());
+        }
+        for proposal_id in proposals {
+            let tally = self.tally_for(nodes, &proposal_id, commitment, height);
+            if let Some(feed) = &self.feed {
+                feed.observe_veto(&tally, height);
+            }
+            if let Some(escalation) = &self.escalation {
+                escalation.observe(&tally, height, || self.veto_contributors(nodes, &proposal_id, height));
+            }
+            self.veto_reporter.observe(tally);
+        }
+    }
+
+    /// The active nodes with an open veto on `proposal_id`, with the weight its tally counts
+    /// for them at `height`.
+    fn veto_contributors(
+        &self,
+        nodes: &HashMap<[u8; 32], EconomicNode>,
+        proposal_id: &str,
+        height: u64,
+    ) -> Vec<crate::escalation::Contributor> {
+        let vetoing: HashMap<String, &EconomicNode> = nodes
+            .values()
+            .filter(|n| n.deactivated.is_none())
+            .filter(|n| {
+                n.veto_history
+                    .iter()
+                    .any(|v| v.proposal_id == proposal_id && v.outcome.is_none())
+            })
+            .map(|n| (hex::encode(n.node_id), n))
+            .collect();
+        let epochs = self.epochs.lock().unwrap();
+        crate::veto_evidence::tally_weights(&self.config(), &epochs, nodes, proposal_id, height)
+            .nodes
+            .into_iter()
+            .filter_map(|entry| {
+                let node = vetoing.get(&entry.node_id)?;
+                Some(crate::escalation::Contributor {
+                    node_type: node.node_type.clone(),
+                    node_id: Some(entry.node_id),
+                    weight: entry.weight,
+                    share_percent: 0.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Current height for sync callers. The height lock is only ever held for a single
+    /// assignment, so this never spins for long.
+    fn height_now(&self) -> u64 {
+        loop {
+            if let Ok(height) = self.current_height.try_read() {
+                return *height;
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    fn read_records(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<schema::Records, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let mut records = schema::Records::new();
+        for key in RECORD_KEYS {
+            match tree.get(key) {
+                Ok(Some(data)) => {
+                    records.insert(key.to_vec(), data.to_vec());
+                }
+                Ok(None) => {}
+                Err(e) => return Err(GovernanceError::database("get")(e)),
+            }
+        }
+        Ok(records)
+    }
+
+    fn write_records(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        tree_name: &str,
+        records: &schema::Records,
+    ) -> Result<(), GovernanceError> {
+        let tree = db
+            .open_tree(tree_name)
+            .map_err(GovernanceError::database("open_tree"))?;
+        for (key, data) in records {
+            tree.insert(key, data)
+                .map_err(GovernanceError::database("insert"))?;
+        }
+        Ok(())
+    }
+
+    /// Bring the stored registry to the current schema version. The records are copied to
+    /// `economic_nodes_backup_v<old version>` before any migration rewrites them; a store
+    /// newer than this build is refused. Returns the versions migrated to.
+    pub fn migrate_store(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Vec<u32>, GovernanceError> {
+        let original = Self::read_records(db)?;
+        let version = schema::stored_version(&original)?;
+        let mut records = original.clone();
+        let applied = schema::migrate(&mut records)?;
+        if !applied.is_empty() {
+            let backup = format!("{}_backup_v{}", REGISTRY_TREE, version);
+            Self::write_records(db, &backup, &original)?;
+            info!(
+                "Migrating economic node registry from schema {} to {} (backup in {})",
+                version,
+                schema::SCHEMA_VERSION,
+                backup
+            );
+        }
+        if records != original {
+            Self::write_records(db, REGISTRY_TREE, &records)?;
+        }
+        Ok(applied)
+    }
+
+    fn size_exceeds(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        config: &crate::config::CompactionConfig,
+    ) -> Result<bool, GovernanceError> {
+        if config.auto_threshold_bytes == 0 {
+            return Ok(false);
+        }
+        Ok(compaction::size(&Self::read_records(db)?) > config.auto_threshold_bytes)
+    }
+
+    fn store_exceeds_threshold(&self) -> Result<bool, GovernanceError> {
+        match &self.db {
+            Some(db) => Self::size_exceeds(db, &self.config().compaction),
+            None => Ok(false),
+        }
+    }
+
+    /// Compact the stored records as of `height`. Callers hold the node and archive locks, or
+    /// run before the registry has loaded them.
+    fn compact_db(
+        &self,
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+        height: u64,
+    ) -> Result<CompactionSummary, GovernanceError> {
+        let mut records = Self::read_records(db)?;
+        let summary = compaction::compact(
+            &mut records,
+            height,
+            &self.config().compaction,
+            self.config().weight_history_len,
+        )?;
+        Self::write_records(db, REGISTRY_TREE, &records)?;
+        info!(
+            "Compacted economic node registry: {} archived nodes and {} history records dropped, {} -> {} bytes",
+            summary.archived_dropped,
+            summary.history_records_dropped,
+            summary.bytes_before,
+            summary.bytes_after
+        );
+        Ok(summary)
+    }
+
+    /// Copy the stored registry into `target` while the running registry keeps processing
+    /// events. Writers are held off only while the records are read, so the copy is
+    /// consistent with the returned commitment.
+    pub async fn copy_to(
+        &self,
+        target: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<RegistryCommitment, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Err(GovernanceError::Storage(
+                "registry has no store to back up".to_string(),
+            ));
+        };
+        let (records, commitment) = {
+            let nodes = self.nodes.read().await;
+            let _archive = self.archive.read().await;
+            let _epochs = self.epochs.lock().unwrap();
+            let _audit = self.access_audit.lock().unwrap();
+            let _signatures = self.veto_signatures.lock().unwrap();
+            (Self::read_records(db)?, commitment::compute(nodes.values()))
+        };
+        Self::write_records(target, REGISTRY_TREE, &records)?;
+        Ok(commitment)
+    }
+
+    /// Compact the store of the running registry and reload the compacted records.
+    pub async fn compact(&self) -> Result<CompactionSummary, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(CompactionSummary::default());
+        };
+        let mut nodes = self.nodes.write().await;
+        let mut archive = self.archive.write().await;
+        let summary = self.compact_db(db, self.height_now())?;
+        *nodes = Self::load_from(db)?;
+        *archive = Self::load_archive_from(db)?;
+        Ok(summary)
+    }
+
+    /// Compact the persisted registry, for offline CLI use. Archive retention is measured
+    /// from the last height the registry saw.
+    pub fn compact_store(&self) -> Result<CompactionSummary, GovernanceError> {
+        let Some(db) = &self.db else {
+            return Ok(CompactionSummary::default());
+        };
+        let height = Self::load_from(db)?
+            .values()
+            .map(|n| n.last_seen)
+            .max()
+            .unwrap_or(0);
+        self.compact_db(db, height)
+    }
+
+    /// Load stored economic nodes (also used by the CLI for read-only access).
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<HashMap<[u8; 32], EconomicNode>, GovernanceError> {
+        let tree = db
+            .open_tree(REGISTRY_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(STORAGE_KEY) {
+            Ok(Some(data)) => bincode::deserialize(&data)
+                .map_err(GovernanceError::encoding("deserialize")),
+            Ok(None) => Ok(HashMap::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+/// Parse a hex-encoded 32-byte node id.
+pub fn parse_node_id(node_id: &str) -> Option<[u8; 32]> {
+    if node_id.len() != 64 {
+        return None;
+    }
+    let bytes = hex::decode(node_id).ok()?;
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&bytes);
+    Some(arr)
+}
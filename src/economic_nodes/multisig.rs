@@ -0,0 +1,254 @@
+//! Threshold (m-of-n) key sets for economic nodes
+//!
+//! A node registered with a key set must back its registration, vetoes, veto revocations,
+//! and key rotations with at least `threshold` valid signatures from distinct keys, as well as
+//! votes and vetoes submitted through the local action endpoints (see [`crate::actions`]).
+//!
+//! Veto, vote, revocation and rotation messages name the block height they were signed at,
+//! and are accepted only while that height is at most `reserve.max_challenge_depth` blocks
+//! below the tip, so a signed action cannot be replayed once it has aged out.
+
+use super::reserve::verify_signature;
+use serde::{Deserialize, Serialize};
+
+/// Largest key set accepted.
+pub const MAX_KEYS: usize = 15;
+
+/// m-of-n key set controlling an economic node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySet {
+    /// Compressed secp256k1 public keys (33 bytes each).
+    pub public_keys: Vec<Vec<u8>>,
+    pub threshold: u32,
+}
+
+/// Signature by one key of a [`KeySet`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySignature {
+    /// Index of the signing key in `public_keys`.
+    pub key_index: u32,
+    /// 64-byte compact ECDSA or Schnorr signature.
+    pub signature: Vec<u8>,
+}
+
+/// Signatures over a message bound to the block height it was signed at.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedAt {
+    pub height: u64,
+    pub signatures: Vec<KeySignature>,
+}
+
+impl KeySet {
+    /// Check structural validity: 1 <= threshold <= n <= MAX_KEYS, valid distinct keys.
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.public_keys.len();
+        if n == 0 || n > MAX_KEYS {
+            return Err(format!("key set must have 1..={} keys, got {}", MAX_KEYS, n));
+        }
+        if self.threshold == 0 || self.threshold as usize > n {
+            return Err(format!(
+                "threshold {} must be between 1 and {}",
+                self.threshold, n
+            ));
+        }
+        for (i, key) in self.public_keys.iter().enumerate() {
+            if key.len() != 33 || secp256k1::PublicKey::from_slice(key).is_err() {
+                return Err(format!("public key {} is not a compressed secp256k1 key", i));
+            }
+            if self.public_keys[..i].contains(key) {
+                return Err(format!("public key {} is listed twice", i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Canonical encoding used inside signed messages: `threshold:<m>;keys:<sorted hex>`.
+    pub fn canonical(&self) -> String {
+        let mut keys: Vec<String> = self.public_keys.iter().map(hex::encode).collect();
+        keys.sort();
+        format!("threshold:{};keys:{}", self.threshold, keys.join(","))
+    }
+
+    /// Number of distinct keys with a valid signature over `message`.
+    pub fn count_valid(&self, message: &str, signatures: &[KeySignature]) -> usize {
+        let mut signed = vec![false; self.public_keys.len()];
+        for sig in signatures {
+            let Some(key) = self.public_keys.get(sig.key_index as usize) else {
+                continue;
+            };
+            if !signed[sig.key_index as usize] && verify_signature(key, message, &sig.signature) {
+                signed[sig.key_index as usize] = true;
+            }
+        }
+        signed.iter().filter(|s| **s).count()
+    }
+
+    /// Whether `signatures` meet the threshold for `message`.
+    pub fn verify(&self, message: &str, signatures: &[KeySignature]) -> bool {
+        self.count_valid(message, signatures) >= self.threshold as usize
+    }
+}
+
+/// Message signed to register a node under a key set, with the weight it claims.
+pub fn registration_message(
+    node_id: &[u8; 32],
+    keys: &KeySet,
+    hashpower_percent: Option<f64>,
+) -> String {
+    format!(
+        "blvm-governance multisig registration\nnode_id:{}\nhashpower:{}\n{}",
+        hex::encode(node_id),
+        hashpower_percent.unwrap_or(0.0),
+        keys.canonical()
+    )
+}
+
+/// Message signed at `height` to veto a proposal.
+pub fn veto_message(node_id: &[u8; 32], proposal_id: &str, reason: &str, height: u64) -> String {
+    format!(
+        "blvm-governance veto\nnode_id:{}\nheight:{}\nproposal_id:{}\nreason:{}",
+        hex::encode(node_id),
+        height,
+        proposal_id,
+        reason
+    )
+}
+
+/// Message signed at `height` to vote on a proposal.
+pub fn vote_message(node_id: &[u8; 32], proposal_id: &str, vote: &str, height: u64) -> String {
+    format!(
+        "blvm-governance vote\nnode_id:{}\nheight:{}\nproposal_id:{}\nvote:{}",
+        hex::encode(node_id),
+        height,
+        proposal_id,
+        vote
+    )
+}
+
+/// Message signed at `height` to revoke a veto.
+pub fn revocation_message(node_id: &[u8; 32], proposal_id: &str, height: u64) -> String {
+    format!(
+        "blvm-governance veto revocation\nnode_id:{}\nheight:{}\nproposal_id:{}",
+        hex::encode(node_id),
+        height,
+        proposal_id
+    )
+}
+
+/// Message signed at `height` by the old key set to rotate to `new_keys`.
+pub fn rotation_message(node_id: &[u8; 32], new_keys: &KeySet, height: u64) -> String {
+    format!(
+        "blvm-governance key rotation\nnode_id:{}\nheight:{}\n{}",
+        hex::encode(node_id),
+        height,
+        new_keys.canonical()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::reserve::message_digest;
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+    fn keys(seeds: &[u8]) -> (Vec<SecretKey>, Vec<Vec<u8>>) {
+        let secp = Secp256k1::new();
+        let sks: Vec<SecretKey> = seeds
+            .iter()
+            .map(|s| SecretKey::from_slice(&[*s; 32]).unwrap())
+            .collect();
+        let pks = sks
+            .iter()
+            .map(|sk| PublicKey::from_secret_key(&secp, sk).serialize().to_vec())
+            .collect();
+        (sks, pks)
+    }
+
+    fn sign(sk: &SecretKey, index: u32, message: &str) -> KeySignature {
+        let secp = Secp256k1::new();
+        let msg = Message::from_digest(message_digest(message));
+        KeySignature {
+            key_index: index,
+            signature: secp.sign_ecdsa(&msg, sk).serialize_compact().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_two_of_three() {
+        let (sks, pks) = keys(&[1, 2, 3]);
+        let set = KeySet {
+            public_keys: pks,
+            threshold: 2,
+        };
+        set.validate().unwrap();
+        let message = registration_message(&[9u8; 32], &set, Some(10.0));
+
+        let one = vec![sign(&sks[0], 0, &message)];
+        assert!(!set.verify(&message, &one));
+
+        // The same key twice does not count as two signatures
+        let repeated = vec![sign(&sks[0], 0, &message), sign(&sks[0], 0, &message)];
+        assert!(!set.verify(&message, &repeated));
+
+        let two = vec![sign(&sks[0], 0, &message), sign(&sks[2], 2, &message)];
+        assert!(set.verify(&message, &two));
+
+        // Signature attributed to the wrong index is invalid
+        let misattributed = vec![sign(&sks[0], 0, &message), sign(&sks[2], 1, &message)];
+        assert!(!set.verify(&message, &misattributed));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_sets() {
+        let (_, pks) = keys(&[1, 2]);
+        let zero = KeySet {
+            public_keys: pks.clone(),
+            threshold: 0,
+        };
+        assert!(zero.validate().is_err());
+        let too_high = KeySet {
+            public_keys: pks.clone(),
+            threshold: 3,
+        };
+        assert!(too_high.validate().is_err());
+        let duplicate = KeySet {
+            public_keys: vec![pks[0].clone(), pks[0].clone()],
+            threshold: 1,
+        };
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[test]
+    fn test_canonical_ignores_key_order() {
+        let (_, pks) = keys(&[1, 2, 3]);
+        let a = KeySet {
+            public_keys: pks.clone(),
+            threshold: 2,
+        };
+        let b = KeySet {
+            public_keys: vec![pks[2].clone(), pks[0].clone(), pks[1].clone()],
+            threshold: 2,
+        };
+        assert_eq!(a.canonical(), b.canonical());
+    }
+    #[test]
+    fn test_messages_bind_weight_and_height() {
+        let (_, pks) = keys(&[1]);
+        let set = KeySet {
+            public_keys: pks,
+            threshold: 1,
+        };
+        let id = [9u8; 32];
+        assert_ne!(
+            registration_message(&id, &set, Some(10.0)),
+            registration_message(&id, &set, Some(20.0))
+        );
+        assert_ne!(
+            veto_message(&id, "42", "unsafe", 100),
+            veto_message(&id, "42", "unsafe", 101)
+        );
+        assert_ne!(vote_message(&id, "42", "yes", 100), vote_message(&id, "42", "yes", 101));
+        assert_ne!(revocation_message(&id, "42", 100), revocation_message(&id, "42", 101));
+        assert_ne!(rotation_message(&id, &set, 100), rotation_message(&id, &set, 101));
+    }
+}
@@ -0,0 +1,240 @@
+//! Registration rate limiting and spam protection
+//!
+//! Windows are measured in blocks so limits behave the same on every node regardless of
+//! wall-clock drift.
+
+use crate::config::RateLimitConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why a registration was rejected by the limiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitRejection {
+    /// Source exceeded its registrations per window.
+    SourceRateLimited,
+    /// Too many new nodes registered in the current block.
+    BlockCapReached,
+    /// Verified reserve below the configured minimum.
+    BelowMinimumStake,
+}
+
+impl std::fmt::Display for RateLimitRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SourceRateLimited => write!(f, "source rate limited"),
+            Self::BlockCapReached => write!(f, "per-block registration cap reached"),
+            Self::BelowMinimumStake => write!(f, "verified reserve below minimum"),
+        }
+    }
+}
+
+/// Per-source and per-block registration limiter.
+#[derive(Debug, Default)]
+pub struct RegistrationLimiter {
+    /// Source -> (window start height, registrations in window).
+    sources: HashMap<String, (u64, u32)>,
+    /// (height, new registrations at that height).
+    block: (u64, u32),
+}
+
+impl RegistrationLimiter {
+    /// Check a registration from `sources` at `height` and record it against each of them if
+    /// accepted. A registration is limited by every source it comes from, e.g. the node id
+    /// and the submitting module (see [`source_keys`]).
+    ///
+    /// `is_new` is false for re-registrations of an already known node, which do not count
+    /// towards the per-block cap.
+    pub fn check(
+        &mut self,
+        config: &RateLimitConfig,
+        sources: &[String],
+        height: u64,
+        is_new: bool,
+    ) -> Result<(), RateLimitRejection> {
+        let mut windows = Vec::with_capacity(sources.len());
+        for source in sources {
+            let (window_start, count) = self
+                .sources
+                .get(source)
+                .copied()
+                .filter(|(start, _)| height.saturating_sub(*start) < config.window_blocks.max(1))
+                .unwrap_or((height, 0));
+            if count >= config.max_per_source_per_window {
+                return Err(RateLimitRejection::SourceRateLimited);
+            }
+            windows.push((source, window_start, count));
+        }
+
+        if self.block.0 != height {
+            self.block = (height, 0);
+        }
+        if is_new && self.block.1 >= config.max_new_per_block {
+            return Err(RateLimitRejection::BlockCapReached);
+        }
+
+        for (source, window_start, count) in windows {
+            self.sources
+                .insert(source.clone(), (window_start, count + 1));
+        }
+        if is_new {
+            self.block.1 += 1;
+        }
+        self.prune(config, height);
+        Ok(())
+    }
+
+    /// Drop per-source entries whose window has expired.
+    fn prune(&mut self, config: &RateLimitConfig, height: u64) {
+        let window = config.window_blocks.max(1);
+        self.sources
+            .retain(|_, (start, _)| height.saturating_sub(*start) < window);
+    }
+}
+
+/// Limiter keys of a registration of `node_id` submitted by `origin`, the calling module
+/// when known. Registrations delivered as node events carry no origin.
+pub fn source_keys(node_id: &str, origin: Option<&str>) -> Vec<String> {
+    let mut keys = vec![format!("node:{}", node_id)];
+    keys.extend(origin.map(|origin| format!("origin:{}", origin)));
+    keys
+}
+
+/// Counters for registration handling.
+#[derive(Debug, Default)]
+pub struct RegistrationCounters {
+    pub accepted: AtomicU64,
+    pub rejected_rate_limited: AtomicU64,
+    pub rejected_block_cap: AtomicU64,
+    pub rejected_min_stake: AtomicU64,
+    pub rejected_capacity: AtomicU64,
+    pub evicted: AtomicU64,
+}
+
+impl RegistrationCounters {
+    /// Count a rejection and return the total number of rejections so far.
+    pub fn record_rejection(&self, reason: RateLimitRejection) -> u64 {
+        let counter = match reason {
+            RateLimitRejection::SourceRateLimited => &self.rejected_rate_limited,
+            RateLimitRejection::BlockCapReached => &self.rejected_block_cap,
+            RateLimitRejection::BelowMinimumStake => &self.rejected_min_stake,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.snapshot().rejected_total()
+    }
+
+    /// Restore counters persisted by an earlier run.
+    pub fn restore(&self, snapshot: &RegistrationCountersSnapshot) {
+        self.accepted.store(snapshot.accepted, Ordering::Relaxed);
+        self.rejected_rate_limited
+            .store(snapshot.rejected_rate_limited, Ordering::Relaxed);
+        self.rejected_block_cap
+            .store(snapshot.rejected_block_cap, Ordering::Relaxed);
+        self.rejected_min_stake
+            .store(snapshot.rejected_min_stake, Ordering::Relaxed);
+        self.rejected_capacity
+            .store(snapshot.rejected_capacity, Ordering::Relaxed);
+        self.evicted.store(snapshot.evicted, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> RegistrationCountersSnapshot {
+        RegistrationCountersSnapshot {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected_rate_limited: self.rejected_rate_limited.load(Ordering::Relaxed),
+            rejected_block_cap: self.rejected_block_cap.load(Ordering::Relaxed),
+            rejected_min_stake: self.rejected_min_stake.load(Ordering::Relaxed),
+            rejected_capacity: self.rejected_capacity.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time copy of [`RegistrationCounters`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationCountersSnapshot {
+    pub accepted: u64,
+    pub rejected_rate_limited: u64,
+    pub rejected_block_cap: u64,
+    pub rejected_min_stake: u64,
+    pub rejected_capacity: u64,
+    pub evicted: u64,
+}
+
+impl RegistrationCountersSnapshot {
+    pub fn rejected_total(&self) -> u64 {
+        self.rejected_rate_limited
+            + self.rejected_block_cap
+            + self.rejected_min_stake
+            + self.rejected_capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(node_id: &str) -> Vec<String> {
+        source_keys(node_id, None)
+    }
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            window_blocks: 10,
+            max_per_source_per_window: 2,
+            max_new_per_block: 3,
+            min_verified_sats: 0,
+            log_sample_rate: 1,
+        }
+    }
+
+    #[test]
+    fn test_source_window() {
+        let config = config();
+        let mut limiter = RegistrationLimiter::default();
+        assert!(limiter.check(&config, &keys("a"), 100, true).is_ok());
+        assert!(limiter.check(&config, &keys("a"), 101, false).is_ok());
+        assert_eq!(
+            limiter.check(&config, &keys("a"), 105, false),
+            Err(RateLimitRejection::SourceRateLimited)
+        );
+        // Window expired
+        assert!(limiter.check(&config, &keys("a"), 110, false).is_ok());
+    }
+
+    #[test]
+    fn test_block_cap_only_counts_new_nodes() {
+        let config = config();
+        let mut limiter = RegistrationLimiter::default();
+        for source in ["a", "b", "c"] {
+            assert!(limiter.check(&config, &keys(source), 5, true).is_ok());
+        }
+        assert_eq!(
+            limiter.check(&config, &keys("d"), 5, true),
+            Err(RateLimitRejection::BlockCapReached)
+        );
+        assert!(limiter.check(&config, &keys("a"), 5, false).is_ok());
+        assert!(limiter.check(&config, &keys("d"), 6, true).is_ok());
+    }
+
+    #[test]
+    fn test_origin_limits_across_node_ids() {
+        let config = config();
+        let mut limiter = RegistrationLimiter::default();
+        let from = |node_id| source_keys(node_id, Some("spammer"));
+        assert!(limiter.check(&config, &from("a"), 100, true).is_ok());
+        assert!(limiter.check(&config, &from("b"), 100, true).is_ok());
+        assert_eq!(
+            limiter.check(&config, &from("c"), 101, true),
+            Err(RateLimitRejection::SourceRateLimited)
+        );
+        // The node id is still free from another origin, and a rejection records nothing
+        assert!(limiter
+            .check(&config, &source_keys("c", Some("other")), 101, true)
+            .is_ok());
+        assert!(limiter.check(&config, &keys("c"), 101, false).is_ok());
+        assert_eq!(
+            limiter.check(&config, &keys("c"), 102, false),
+            Err(RateLimitRejection::SourceRateLimited)
+        );
+    }
+}
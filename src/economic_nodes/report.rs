@@ -0,0 +1,394 @@
+//! Reporting veto results to the node
+//!
+//! The node enforces vetoes, so tallies that cross the threshold are sent to it over IPC.
+//! A tally is queued when it first crosses and whenever its result changes afterwards
+//! (including dropping back below the threshold). Queued tallies are retried until the send
+//! succeeds, and a result identical to the last one delivered is never sent twice. A tally
+//! the node rejects with an error that retrying cannot fix (see
+//! [`crate::error::Retryability`]) is dropped instead. Failing to reach the node at all
+//! (disconnected, timed out) does not count against a tally. A rejection that cannot be
+//! fixed by retrying is reported to the node as `node_rejected` (see
+//! [`crate::error_report`]).
+//!
+//! With an intent log ([`VetoReporter::with_intents`]), each queued tally is recorded as an
+//! intent until it is sent or dropped, so one queued when the module crashed is reported on
+//! the next start (see [`crate::intent`]). A tally replaced by a newer one completes its
+//! intent.
+
+use super::tally::VetoTally;
+use crate::error::{GovernanceError, Retryability};
+use crate::error_report::{ErrorCode, ErrorReport, ErrorReporter};
+use crate::intent::IntentLog;
+use crate::node_api::NodeApiIpc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Kind of the intents to report a veto result, recorded for the `economic_nodes` handler.
+pub const VETO_RESULT_INTENT: &str = "veto_result";
+
+/// Queue of veto results awaiting delivery to the node.
+#[derive(Debug, Default)]
+pub struct VetoReporter {
+    observe_only: bool,
+    /// Last tally delivered per proposal.
+    sent: Mutex<HashMap<String, VetoTally>>,
+    /// Latest undelivered tally per proposal.
+    pending: Mutex<HashMap<String, VetoTally>>,
+    /// Consecutive failed sends per proposal.
+    failures: Mutex<HashMap<String, u32>>,
+    notify: tokio::sync::Notify,
+    /// Where rejected results are reported.
+    errors: Option<Arc<ErrorReporter>>,
+    /// Where queued tallies are recorded until they are sent.
+    intents: Option<Arc<IntentLog>>,
+    /// Intent of the queued tally, per proposal.
+    intent_ids: Mutex<HashMap<String, u64>>,
+}
+
+impl VetoReporter {
+    pub fn new(observe_only: bool) -> Self {
+        Self {
+            observe_only,
+            ..Self::default()
+        }
+    }
+
+    /// Report results the node rejects for good to `errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Record queued tallies in `intents` until they are sent or dropped.
+    pub fn with_intents(mut self, intents: Arc<IntentLog>) -> Self {
+        self.intents = Some(intents);
+        self
+    }
+
+    /// Record the intent to send `tally`, completing the one for the tally it replaces.
+    fn intend(&self, tally: &VetoTally) {
+        let Some(intents) = &self.intents else {
+            return;
+        };
+        let data = match serde_json::to_value(tally) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to encode veto result intent: {}", e);
+                return;
+            }
+        };
+        match intents.begin("economic_nodes", VETO_RESULT_INTENT, data) {
+            Ok(id) => {
+                let replaced = self
+                    .intent_ids
+                    .lock()
+                    .unwrap()
+                    .insert(tally.proposal_id.clone(), id);
+                self.fulfil(replaced);
+            }
+            Err(e) => warn!(
+                "Failed to record veto result intent for {}: {}",
+                tally.proposal_id,
+                e.chain()
+            ),
+        }
+    }
+
+    /// Complete the intent of the tally queued for `proposal_id`, if any.
+    fn fulfil_proposal(&self, proposal_id: &str) {
+        let id = self.intent_ids.lock().unwrap().remove(proposal_id);
+        self.fulfil(id);
+    }
+
+    fn fulfil(&self, id: Option<u64>) {
+        let (Some(intents), Some(id)) = (&self.intents, id) else {
+            return;
+        };
+        if let Err(e) = intents.complete(id) {
+            warn!(
+                "Failed to complete veto result intent {}: {}",
+                id,
+                e.chain()
+            );
+        }
+    }
+
+    /// Queue `tally` again under intent `id`, recorded before a restart, unless a tally for
+    /// its proposal is already queued.
+    pub fn resume(&self, tally: VetoTally, id: u64) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.contains_key(&tally.proposal_id) {
+            return;
+        }
+        self.intent_ids
+            .lock()
+            .unwrap()
+            .insert(tally.proposal_id.clone(), id);
+        pending.insert(tally.proposal_id.clone(), tally);
+    }
+
+    /// Proposals whose results have been reported or are queued.
+    pub fn tracked(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.sent.lock().unwrap().keys().cloned().collect();
+        ids.extend(self.pending.lock().unwrap().keys().cloned());
+        ids
+    }
+
+    /// Queue a freshly computed tally if the node needs to hear about it.
+    pub fn observe(&self, tally: VetoTally) {
+        let sent = self.sent.lock().unwrap();
+        let previous = sent.get(&tally.proposal_id);
+        if previous.is_none() && !tally.crossed() {
+            self.pending.lock().unwrap().remove(&tally.proposal_id);
+            drop(sent);
+            self.fulfil_proposal(&tally.proposal_id);
+            return;
+        }
+        if previous.is_some_and(|p| p.same_result(&tally)) {
+            self.pending.lock().unwrap().remove(&tally.proposal_id);
+            drop(sent);
+            self.fulfil_proposal(&tally.proposal_id);
+            return;
+        }
+        drop(sent);
+
+        if self.observe_only {
+            info!(
+                "Veto tally for {} (observe-only, not reported): {:.2}% of weight, threshold {}%",
+                tally.proposal_id,
+                tally.veto_percent(),
+                tally.threshold_percent
+            );
+        } else {
+            self.intend(&tally);
+        }
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(tally.proposal_id.clone(), tally);
+        self.notify.notify_one();
+    }
+
+    /// Stop tracking a proposal that has been resolved.
+    pub fn forget(&self, proposal_id: &str) {
+        self.sent.lock().unwrap().remove(proposal_id);
+        self.pending.lock().unwrap().remove(proposal_id);
+        self.failures.lock().unwrap().remove(proposal_id);
+        self.fulfil_proposal(proposal_id);
+    }
+
+    /// Wait until a tally is queued.
+    pub async fn notified(&self) {
+        self.notify.notified().await
+    }
+
+    /// Send queued tallies. Failed sends stay queued for the next flush.
+    pub async fn flush(&self, node_api: &NodeApiIpc) -> Result<usize, GovernanceError> {
+        if self.observe_only {
+            self.pending.lock().unwrap().clear();
+            return Ok(0);
+        }
+        let queued: Vec<VetoTally> = self.pending.lock().unwrap().values().cloned().collect();
+        let mut delivered = 0;
+        for tally in queued {
+            if let Err(e) = node_api.submit_veto_result(&tally).await {
+                if matches!(e, GovernanceError::Paused { .. }) {
+                    debug!("Paused, veto result for {} stays queued", tally.proposal_id);
+                    return Err(e);
+                }
+                if matches!(
+                    e,
+                    GovernanceError::IpcDisconnected { .. }
+                        | GovernanceError::IpcTimeout { .. }
+                        | GovernanceError::NodeApiTimeout { .. }
+                ) {
+                    warn!(
+                        "Node unreachable reporting veto result for {}, will retry: {}",
+                        tally.proposal_id, e
+                    );
+                    return Err(e);
+                }
+                let failures = {
+                    let mut failures = self.failures.lock().unwrap();
+                    let count = failures.entry(tally.proposal_id.clone()).or_default();
+                    *count += 1;
+                    *count
+                };
+                let retryability = e.retryability();
+                if retryability.should_retry(failures) {
+                    warn!(
+                        "Failed to report veto result for {}, will retry: {}",
+                        tally.proposal_id, e
+                    );
+                    return Err(e);
+                }
+                warn!(
+                    "Failed to report veto result for {}, not retrying ({:?}): {}",
+                    tally.proposal_id, retryability, e
+                );
+                let rejected = retryability == Retryability::Fatal;
+                if let Some(errors) = self.errors.as_ref().filter(|_| rejected) {
+                    errors.report(
+                        ErrorReport::new(
+                            ErrorCode::NodeRejected,
+                            format!("veto result for {}: {}", tally.proposal_id, e),
+                        )
+                        .with_context("operation", "submit_veto_result"),
+                    );
+                }
+                self.dequeue(&tally);
+                continue;
+            }
+            debug!(
+                "Reported veto result for {}: {:.2}% (crossed: {})",
+                tally.proposal_id,
+                tally.veto_percent(),
+                tally.crossed()
+            );
+            self.dequeue(&tally);
+            self.sent
+                .lock()
+                .unwrap()
+                .insert(tally.proposal_id.clone(), tally);
+            delivered += 1;
+        }
+        Ok(delivered)
+    }
+
+    /// Stop retrying `tally`, unless a newer tally replaced it while it was being sent.
+    fn dequeue(&self, tally: &VetoTally) {
+        self.failures.lock().unwrap().remove(&tally.proposal_id);
+        let mut pending = self.pending.lock().unwrap();
+        if pending.get(&tally.proposal_id) == Some(tally) {
+            pending.remove(&tally.proposal_id);
+            drop(pending);
+            self.fulfil_proposal(&tally.proposal_id);
+        }
+    }
+
+    #[cfg(test)]
+    fn pending_ids(&self) -> Vec<String> {
+        self.pending.lock().unwrap().keys().cloned().collect()
+    }
+
+    #[cfg(test)]
+    fn mark_sent(&self, proposal_id: &str) {
+        if let Some(t) = self.pending.lock().unwrap().remove(proposal_id) {
+            self.sent.lock().unwrap().insert(proposal_id.to_string(), t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(vetoing: f64, commitment: &str) -> VetoTally {
+        VetoTally {
+            proposal_id: "p1".to_string(),
+            total_weight: 100.0,
+            vetoing_weight: vetoing,
+            threshold_percent: 30.0,
+            commitment: commitment.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_queues_only_once_crossed() {
+        let reporter = VetoReporter::new(false);
+        reporter.observe(tally(10.0, "a"));
+        assert!(reporter.pending_ids().is_empty());
+        reporter.observe(tally(35.0, "b"));
+        assert_eq!(reporter.pending_ids(), vec!["p1"]);
+    }
+
+    #[test]
+    fn test_deduplicates_delivered_result() {
+        let reporter = VetoReporter::new(false);
+        reporter.observe(tally(35.0, "a"));
+        reporter.mark_sent("p1");
+
+        // Same result from a different registry state: nothing new to tell the node
+        reporter.observe(tally(35.0, "b"));
+        assert!(reporter.pending_ids().is_empty());
+
+        // Dropping back below the threshold is a change the node must hear about
+        reporter.observe(tally(20.0, "c"));
+        assert_eq!(reporter.pending_ids(), vec!["p1"]);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_node_does_not_count_as_failure() {
+        let node_api = std::sync::Arc::new(crate::testing::MockNodeApi::new(100));
+        let ipc =
+            NodeApiIpc::new(node_api.clone()).with_retry_policy(crate::node_api::RetryPolicy {
+                attempts: 1,
+                backoff: std::time::Duration::from_millis(1),
+            });
+        let reporter = VetoReporter::new(false);
+        reporter.observe(tally(35.0, "a"));
+
+        node_api.respond("submit_veto_result", Err("connection reset".to_string()));
+        for _ in 0..10 {
+            assert!(reporter.flush(&ipc).await.is_err());
+        }
+        // Unrecognised errors are only retried a few times in a row
+        node_api.respond("submit_veto_result", Err("internal error".to_string()));
+        for _ in 1..crate::error::UNKNOWN_ERROR_MAX_ATTEMPTS {
+            assert!(reporter.flush(&ipc).await.is_err());
+        }
+        assert_eq!(reporter.pending_ids(), vec!["p1"]);
+        assert_eq!(reporter.flush(&ipc).await.unwrap(), 0);
+        assert!(reporter.pending_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unrouted_submission_is_unsupported() {
+        let node_api = std::sync::Arc::new(crate::testing::MockNodeApi::new(100));
+        let ipc = NodeApiIpc::new(node_api.clone());
+        let reporter = VetoReporter::new(false);
+        reporter.observe(tally(35.0, "a"));
+
+        // The router's answer when no module takes veto results: not retried
+        node_api.respond(
+            "submit_veto_result",
+            Err("Method 'submit_veto_result' not found in any module".to_string()),
+        );
+        assert!(matches!(
+            ipc.submit_veto_result(&tally(35.0, "a")).await,
+            Err(GovernanceError::Unsupported { .. })
+        ));
+        assert_eq!(reporter.flush(&ipc).await.unwrap(), 0);
+        assert!(reporter.pending_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_queued_tally_held_as_intent_until_sent() {
+        let dir = std::env::temp_dir().join(format!("blvm_veto_intents_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let intents = Arc::new(IntentLog::open(&dir).unwrap());
+        let reporter = VetoReporter::new(false).with_intents(Arc::clone(&intents));
+        reporter.observe(tally(35.0, "a"));
+        reporter.observe(tally(40.0, "b"));
+
+        // Only the newest result is left to send
+        let pending = intents.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].kind, VETO_RESULT_INTENT);
+        assert_eq!(pending[0].data["vetoing_weight"], 40.0);
+
+        let ipc = NodeApiIpc::new(Arc::new(crate::testing::MockNodeApi::new(100)));
+        assert_eq!(reporter.flush(&ipc).await.unwrap(), 1);
+        assert!(intents.pending().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_forget() {
+        let reporter = VetoReporter::new(false);
+        reporter.observe(tally(35.0, "a"));
+        reporter.forget("p1");
+        assert!(reporter.tracked().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Reputation scoring for economic nodes
+//!
+//! The score is a pure function of the signals stored on an [`EconomicNode`] and the
+//! current height, so two registries with the same history always agree on it.
+
+use super::{EconomicNode, VetoOutcome};
+use crate::config::ReputationConfig;
+use serde::{Deserialize, Serialize};
+
+/// Reputation score with its individual components (each in `[0, 1]`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Reputation {
+    pub longevity: f64,
+    pub liveness: f64,
+    pub stability: f64,
+    pub veto_record: f64,
+    /// Weighted combination of the components, in `[0, 1]`.
+    pub score: f64,
+}
+
+/// Longevity: blocks since first registration relative to the configured target, capped at 1.
+pub fn longevity(node: &EconomicNode, current_height: u64, config: &ReputationConfig) -> f64 {
+    if config.longevity_target_blocks == 0 {
+        return 1.0;
+    }
+    let age = current_height.saturating_sub(node.registered_at);
+    (age as f64 / config.longevity_target_blocks as f64).min(1.0)
+}
+
+/// Liveness: fraction of sampled blocks at which the node had announced itself recently.
+///
+/// A node that has not been sampled yet is treated as fully live.
+pub fn liveness(node: &EconomicNode) -> f64 {
+    if node.liveness_samples == 0 {
+        return 1.0;
+    }
+    node.liveness_hits.min(node.liveness_samples) as f64 / node.liveness_samples as f64
+}
+
+/// Stability: `1 / (1 + n)` where `n` is the number of times the claimed weight changed.
+pub fn stability(node: &EconomicNode) -> f64 {
+    1.0 / (1.0 + node.weight_changes as f64)
+}
+
+/// Veto record: fraction of resolved vetoes that were on proposals which ultimately failed.
+///
+/// Unresolved vetoes are ignored; a node without resolved vetoes scores 1.
+pub fn veto_record(node: &EconomicNode) -> f64 {
+    let mut resolved = 0u32;
+    let mut vindicated = 0u32;
+    for record in &node.veto_history {
+        match record.outcome {
+            Some(VetoOutcome::Failed) => {
+                resolved += 1;
+                vindicated += 1;
+            }
+            Some(VetoOutcome::MergedAnyway) => resolved += 1,
+            None => {}
+        }
+    }
+    if resolved == 0 {
+        return 1.0;
+    }
+    vindicated as f64 / resolved as f64
+}
+
+/// Compute the reputation of a node at `current_height`.
+pub fn compute(node: &EconomicNode, current_height: u64, config: &ReputationConfig) -> Reputation {
+    let longevity = longevity(node, current_height, config);
+    let liveness = liveness(node);
+    let stability = stability(node);
+    let veto_record = veto_record(node);
+
+    let weights = [
+        config.longevity_weight.max(0.0),
+        config.liveness_weight.max(0.0),
+        config.stability_weight.max(0.0),
+        config.veto_record_weight.max(0.0),
+    ];
+    let total: f64 = weights.iter().sum();
+    let score = if total > 0.0 {
+        (weights[0] * longevity
+            + weights[1] * liveness
+            + weights[2] * stability
+            + weights[3] * veto_record)
+            / total
+    } else {
+        0.0
+    };
+
+    Reputation {
+        longevity,
+        liveness,
+        stability,
+        veto_record,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::VetoRecord;
+
+    fn node(registered_at: u64) -> EconomicNode {
+        let mut node = EconomicNode::new([7u8; 32], registered_at);
+        node.hashpower_percentage = 1.0;
+        node
+    }
+
+    fn veto(proposal_id: &str, outcome: Option<VetoOutcome>) -> VetoRecord {
+        VetoRecord {
+            proposal_id: proposal_id.to_string(),
+            height: 0,
+            reason: String::new(),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_fresh_node() {
+        // longevity 0, everything else 1 => (0 + 1 + 1 + 1) / 4
+        let config = ReputationConfig::default();
+        let r = compute(&node(100), 100, &config);
+        assert_eq!(r.longevity, 0.0);
+        assert_eq!(r.score, 0.75);
+    }
+
+    #[test]
+    fn test_hand_computed_example() {
+        let config = ReputationConfig {
+            longevity_target_blocks: 1000,
+            ..ReputationConfig::default()
+        };
+        let mut n = node(0);
+        n.liveness_samples = 10;
+        n.liveness_hits = 8;
+        n.weight_changes = 1;
+        n.veto_history = vec![
+            veto("a", Some(VetoOutcome::Failed)),
+            veto("b", Some(VetoOutcome::MergedAnyway)),
+            veto("c", None),
+        ];
+        // longevity 500/1000 = 0.5, liveness 0.8, stability 0.5, veto record 0.5
+        let r = compute(&n, 500, &config);
+        assert_eq!(r.longevity, 0.5);
+        assert_eq!(r.liveness, 0.8);
+        assert_eq!(r.stability, 0.5);
+        assert_eq!(r.veto_record, 0.5);
+        assert!((r.score - 0.575).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_custom_weights() {
+        let config = ReputationConfig {
+            longevity_weight: 3.0,
+            liveness_weight: 1.0,
+            stability_weight: 0.0,
+            veto_record_weight: 0.0,
+            longevity_target_blocks: 100,
+            ..ReputationConfig::default()
+        };
+        let mut n = node(0);
+        n.liveness_samples = 4;
+        n.liveness_hits = 2;
+        // (3 * 1.0 + 1 * 0.5) / 4 = 0.875, longevity capped at 1
+        let r = compute(&n, 250, &config);
+        assert_eq!(r.longevity, 1.0);
+        assert_eq!(r.score, 0.875);
+    }
+
+    #[test]
+    fn test_same_history_same_score() {
+        let config = ReputationConfig::default();
+        let mut a = node(10);
+        a.weight_changes = 2;
+        a.liveness_samples = 7;
+        a.liveness_hits = 3;
+        let mut b = a.clone();
+        b.node_id = [8u8; 32];
+        assert_eq!(compute(&a, 5000, &config), compute(&b, 5000, &config));
+    }
+}
@@ -0,0 +1,267 @@
+//! Proof-of-reserve verification for claimed economic weight
+//!
+//! A reserve claim lists outpoints plus a public key and a signature over the canonical
+//! registration message. Each outpoint must be unspent, sufficiently confirmed, and locked
+//! to the claimed key (P2WPKH or P2TR key path). Script-path proofs are not supported yet.
+
+use crate::config::ReserveConfig;
+use crate::error::GovernanceError;
+use crate::node_api::NodeApiIpc;
+use secp256k1::{ecdsa, schnorr, Message, PublicKey, Secp256k1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// An outpoint referenced by a reserve claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClaimedOutpoint {
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl ClaimedOutpoint {
+    /// Parse `txid:vout` with a hex txid.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (txid, vout) = s.split_once(':')?;
+        let bytes = hex::decode(txid).ok()?;
+        if bytes.len() != 32 {
+            return None;
+        }
+        let mut arr = [0u8; 32];
+        arr.copy_from_slice(&bytes);
+        Some(Self {
+            txid: arr,
+            vout: vout.parse().ok()?,
+        })
+    }
+
+    pub fn to_outpoint(&self) -> blvm_protocol::OutPoint {
+        blvm_protocol::OutPoint {
+            hash: self.txid,
+            index: self.vout as _,
+        }
+    }
+}
+
+impl std::fmt::Display for ClaimedOutpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", hex::encode(self.txid), self.vout)
+    }
+}
+
+/// Reserve claim attached to a registration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReserveClaim {
+    /// Compressed secp256k1 public key (33 bytes).
+    pub public_key: Vec<u8>,
+    pub outpoints: Vec<ClaimedOutpoint>,
+    /// 64-byte compact ECDSA or BIP-340 Schnorr signature over [`registration_message`].
+    pub signature: Vec<u8>,
+}
+
+/// A claimed outpoint spent by a transaction that is still in the node's mempool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingSpend {
+    pub outpoint: ClaimedOutpoint,
+    /// The spending transaction.
+    pub txid: [u8; 32],
+    /// Height when it was first seen in the mempool.
+    pub seen_at: u64,
+}
+
+/// Verification state of a node's claimed weight.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VerificationStatus {
+    /// No reserve claim was made.
+    #[default]
+    Unverified,
+    /// Claim verified at the given height.
+    Verified { height: u64 },
+    /// Claim could not be verified; the node carries zero weight.
+    VerificationFailed { reason: String },
+}
+
+/// Result of verifying a reserve claim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReserveVerification {
+    /// Sum of verified outpoint values, in satoshis. Zero on failure.
+    pub verified_sats: u64,
+    pub status: VerificationStatus,
+}
+
+/// Canonical message signed by a registration: node id and the sorted claimed outpoints.
+pub fn registration_message(node_id: &[u8; 32], outpoints: &[ClaimedOutpoint]) -> String {
+    let mut sorted = outpoints.to_vec();
+    sorted.sort();
+    let outpoints: Vec<String> = sorted.iter().map(|o| o.to_string()).collect();
+    format!(
+        "blvm-governance registration\nnode_id:{}\noutpoints:{}",
+        hex::encode(node_id),
+        outpoints.join(",")
+    )
+}
+
+/// SHA256 digest of a message, as signed by registrants.
+pub fn message_digest(message: &str) -> [u8; 32] {
+    Sha256::digest(message.as_bytes()).into()
+}
+
+/// Verify `signature` over `message` by `public_key` (compact ECDSA or Schnorr).
+pub fn verify_signature(public_key: &[u8], message: &str, signature: &[u8]) -> bool {
+    let secp = Secp256k1::verification_only();
+    let Ok(pk) = PublicKey::from_slice(public_key) else {
+        return false;
+    };
+    let msg = Message::from_digest(message_digest(message));
+    if let Ok(sig) = ecdsa::Signature::from_compact(signature) {
+        if secp.verify_ecdsa(&msg, &sig, &pk).is_ok() {
+            return true;
+        }
+    }
+    if let Ok(sig) = schnorr::Signature::from_slice(signature) {
+        let (xonly, _) = pk.x_only_public_key();
+        if secp.verify_schnorr(&sig, &msg, &xonly).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// HASH160 (RIPEMD160 of SHA256).
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    use ripemd::Ripemd160;
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// Whether `script_pubkey` is a P2WPKH or P2TR (key path) output spendable by `public_key`.
+pub fn script_matches_key(script_pubkey: &[u8], public_key: &[u8]) -> bool {
+    match script_pubkey {
+        // OP_0 <20-byte key hash>
+        [0x00, 0x14, hash @ ..] if hash.len() == 20 => hash == hash160(public_key),
+        // OP_1 <32-byte x-only key>
+        [0x51, 0x20, xonly @ ..] if xonly.len() == 32 => {
+            public_key.len() == 33 && &public_key[1..] == xonly
+        }
+        _ => false,
+    }
+}
+
+/// Verify a reserve claim against the node's UTXO set.
+pub async fn verify_claim(
+    node_id: &[u8; 32],
+    claim: &ReserveClaim,
+    current_height: u64,
+    node_api: &NodeApiIpc,
+    config: &ReserveConfig,
+) -> Result<ReserveVerification, GovernanceError> {
+    let failed = |reason: String| ReserveVerification {
+        verified_sats: 0,
+        status: VerificationStatus::VerificationFailed { reason },
+    };
+
+    if claim.outpoints.is_empty() {
+        return Ok(failed("no outpoints claimed".to_string()));
+    }
+    let message = registration_message(node_id, &claim.outpoints);
+    if !verify_signature(&claim.public_key, &message, &claim.signature) {
+        return Ok(failed("invalid signature".to_string()));
+    }
+
+    let mut total: u64 = 0;
+    for outpoint in &claim.outpoints {
+        let Some(utxo) = node_api.get_utxo(&outpoint.to_outpoint()).await? else {
+            return Ok(failed(format!("outpoint {} is spent or unknown", outpoint)));
+        };
+        let confirmations = current_height.saturating_sub(utxo.height) + 1;
+        if confirmations < config.min_confirmations {
+            return Ok(failed(format!(
+                "outpoint {} has {} confirmations, {} required",
+                outpoint, confirmations, config.min_confirmations
+            )));
+        }
+        if !script_matches_key(&utxo.script_pubkey, &claim.public_key) {
+            return Ok(failed(format!(
+                "outpoint {} is not locked to the registration key",
+                outpoint
+            )));
+        }
+        total = total.saturating_add(utxo.value.max(0) as u64);
+    }
+
+    Ok(ReserveVerification {
+        verified_sats: total,
+        status: VerificationStatus::Verified {
+            height: current_height,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    #[test]
+    fn test_signature_roundtrip() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let pk = PublicKey::from_secret_key(&secp, &sk);
+        let node_id = [9u8; 32];
+        let outpoints = vec![ClaimedOutpoint {
+            txid: [1u8; 32],
+            vout: 0,
+        }];
+        let message = registration_message(&node_id, &outpoints);
+        let msg = Message::from_digest(message_digest(&message));
+        let sig = secp.sign_ecdsa(&msg, &sk).serialize_compact();
+
+        assert!(verify_signature(&pk.serialize(), &message, &sig));
+        let other = registration_message(&[8u8; 32], &outpoints);
+        assert!(!verify_signature(&pk.serialize(), &other, &sig));
+    }
+
+    #[test]
+    fn test_script_matching() {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[4u8; 32]).unwrap();
+        let pk = PublicKey::from_secret_key(&secp, &sk).serialize();
+
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&hash160(&pk));
+        assert!(script_matches_key(&p2wpkh, &pk));
+
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend_from_slice(&pk[1..]);
+        assert!(script_matches_key(&p2tr, &pk));
+
+        // P2PKH is not accepted
+        let mut p2pkh = vec![0x76, 0xa9, 0x14];
+        p2pkh.extend_from_slice(&hash160(&pk));
+        p2pkh.extend_from_slice(&[0x88, 0xac]);
+        assert!(!script_matches_key(&p2pkh, &pk));
+    }
+
+    #[test]
+    fn test_message_is_order_independent() {
+        let a = ClaimedOutpoint {
+            txid: [1u8; 32],
+            vout: 1,
+        };
+        let b = ClaimedOutpoint {
+            txid: [2u8; 32],
+            vout: 0,
+        };
+        assert_eq!(
+            registration_message(&[0u8; 32], &[a, b]),
+            registration_message(&[0u8; 32], &[b, a])
+        );
+    }
+
+    #[test]
+    fn test_parse_outpoint() {
+        let s = format!("{}:7", hex::encode([5u8; 32]));
+        let o = ClaimedOutpoint::parse(&s).unwrap();
+        assert_eq!(o.vout, 7);
+        assert_eq!(o.to_string(), s);
+        assert!(ClaimedOutpoint::parse("abcd:1").is_none());
+    }
+}
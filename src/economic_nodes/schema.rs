@@ -0,0 +1,145 @@
+//! Registry store schema versions and migrations
+//!
+//! The registry tree records its schema version under `schema_version`. Stores written
+//! before the version was recorded are version 1. At startup the registry's records are
+//! read into memory, pending migrations run over them in order, and the result is written
+//! back (after the original records are copied to a backup tree). A store with a newer
+//! version than this build knows is refused rather than read with the wrong layout.
+
+use super::{ArchivedNode, EconomicNode, ARCHIVE_KEY};
+use crate::error::GovernanceError;
+use std::collections::{BTreeMap, HashMap};
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 2;
+
+pub const SCHEMA_KEY: &[u8] = b"schema_version";
+
+/// Registry records by key.
+pub type Records = BTreeMap<Vec<u8>, Vec<u8>>;
+
+type Migration = fn(&mut Records) -> Result<(), GovernanceError>;
+
+/// `(version, migration)`: each migration takes a store from `version - 1` to `version`.
+const MIGRATIONS: &[(u32, Migration)] = &[(2, archive_heights)];
+
+/// Schema version of `records`; a store without records is new and already current.
+pub fn stored_version(records: &Records) -> Result<u32, GovernanceError> {
+    match records.get(SCHEMA_KEY) {
+        Some(data) => {
+            let bytes: [u8; 4] = data.as_slice().try_into().map_err(|_| {
+                GovernanceError::Storage("malformed registry schema version".to_string())
+            })?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+        None if records.is_empty() => Ok(SCHEMA_VERSION),
+        None => Ok(1),
+    }
+}
+
+pub fn encode_version(version: u32) -> Vec<u8> {
+    version.to_le_bytes().to_vec()
+}
+
+/// Versions of the migrations `version` still needs.
+pub fn pending(version: u32) -> Result<Vec<u32>, GovernanceError> {
+    if version > SCHEMA_VERSION {
+        return Err(GovernanceError::Storage(format!(
+            "registry store schema version {} is newer than the supported version {}; \
+             upgrade blvm-governance or restore a backup",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(MIGRATIONS
+        .iter()
+        .map(|(v, _)| *v)
+        .filter(|v| *v > version)
+        .collect())
+}
+
+/// Run the pending migrations over `records` and stamp the current version. Returns the
+/// versions migrated to.
+pub fn migrate(records: &mut Records) -> Result<Vec<u32>, GovernanceError> {
+    let applied = pending(stored_version(records)?)?;
+    for (version, migration) in MIGRATIONS.iter().filter(|(v, _)| applied.contains(v)) {
+        migration(records).map_err(|e| {
+            GovernanceError::Storage(format!("migration to schema {}: {}", version, e))
+        })?;
+    }
+    records.insert(SCHEMA_KEY.to_vec(), encode_version(SCHEMA_VERSION));
+    Ok(applied)
+}
+
+/// v2: archived nodes record the height they were evicted at, so compaction can expire
+/// them. Existing entries take their last-seen height.
+fn archive_heights(records: &mut Records) -> Result<(), GovernanceError> {
+    let Some(data) = records.get(ARCHIVE_KEY) else {
+        return Ok(());
+    };
+    let old: HashMap<[u8; 32], EconomicNode> =
+        bincode::deserialize(data).map_err(GovernanceError::encoding("deserialize"))?;
+    let archive: HashMap<[u8; 32], ArchivedNode> = old
+        .into_iter()
+        .map(|(id, node)| {
+            let archived_at = node.last_seen;
+            (id, ArchivedNode { node, archived_at })
+        })
+        .collect();
+    let data = bincode::serialize(&archive).map_err(GovernanceError::encoding("serialize"))?;
+    records.insert(ARCHIVE_KEY.to_vec(), data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::STORAGE_KEY;
+    use super::*;
+
+    /// A store as written by builds before schema versioning.
+    fn v1_store() -> Records {
+        let mut live = EconomicNode::new([1u8; 32], 10);
+        live.hashpower_percentage = 25.0;
+        let mut evicted = EconomicNode::new([2u8; 32], 5);
+        evicted.last_seen = 40;
+        let nodes = HashMap::from([(live.node_id, live)]);
+        let archive = HashMap::from([(evicted.node_id, evicted)]);
+        Records::from([
+            (STORAGE_KEY.to_vec(), bincode::serialize(&nodes).unwrap()),
+            (ARCHIVE_KEY.to_vec(), bincode::serialize(&archive).unwrap()),
+        ])
+    }
+
+    #[test]
+    fn test_migrates_v1_store() {
+        let mut records = v1_store();
+        let nodes_before = records[STORAGE_KEY].clone();
+        assert_eq!(stored_version(&records).unwrap(), 1);
+
+        assert_eq!(migrate(&mut records).unwrap(), vec![2]);
+        assert_eq!(stored_version(&records).unwrap(), SCHEMA_VERSION);
+        let archive: HashMap<[u8; 32], ArchivedNode> =
+            bincode::deserialize(&records[ARCHIVE_KEY]).unwrap();
+        assert_eq!(archive[&[2u8; 32]].archived_at, 40);
+        assert_eq!(records[STORAGE_KEY], nodes_before);
+
+        // Already current: nothing to do
+        assert!(migrate(&mut records).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_store_is_current() {
+        let mut records = Records::new();
+        assert!(migrate(&mut records).unwrap().is_empty());
+        assert_eq!(stored_version(&records).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_refuses_newer_store() {
+        let mut records = v1_store();
+        records.insert(SCHEMA_KEY.to_vec(), encode_version(SCHEMA_VERSION + 1));
+        let untouched = records.clone();
+        let err = migrate(&mut records).unwrap_err();
+        assert!(err.to_string().contains("newer than the supported version"));
+        assert_eq!(records, untouched);
+    }
+}
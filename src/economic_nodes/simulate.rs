@@ -0,0 +1,172 @@
+//! Veto what-if simulation
+//!
+//! Answers "if these nodes veto, does the proposal cross the threshold?" against a set of
+//! weighted entries (live decayed weights or a snapshot's frozen weights) without touching
+//! the registry.
+
+use super::snapshot::SnapshotEntry;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Hypothetical vetoes to simulate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VetoScenario {
+    /// Proposal to simulate; its open vetoes count alongside the hypothetical ones.
+    pub proposal_id: Option<String>,
+    /// Proposal tier, reported back for context.
+    pub tier: Option<String>,
+    /// Hypothetically vetoing node ids.
+    pub nodes: Vec<[u8; 32]>,
+    /// Hypothetically vetoing node categories (every node of the category vetoes).
+    pub categories: Vec<String>,
+    /// Use this snapshot's weights instead of the live registry's.
+    pub snapshot: Option<u64>,
+}
+
+/// Outcome of a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VetoSimulation {
+    pub proposal_id: Option<String>,
+    pub tier: Option<String>,
+    /// Snapshot the weights came from; `None` for live weights.
+    pub snapshot: Option<u64>,
+    pub height: u64,
+    pub threshold_percent: f64,
+    pub total_weight: f64,
+    pub vetoing_weight: f64,
+    pub veto_percent: f64,
+    pub crossed: bool,
+    /// Further vetoing weight needed to reach the threshold; 0 once crossed.
+    pub additional_weight_needed: f64,
+    /// Hex ids of the nodes counted as vetoing, sorted.
+    pub vetoing_nodes: Vec<String>,
+    /// Requested node ids that carry no weight in the chosen weights.
+    pub unknown_nodes: Vec<String>,
+}
+
+/// Simulate `scenario` over `entries`. `already_vetoing` are the nodes with an open veto on
+/// the proposal, if any.
+pub fn simulate(
+    entries: &[SnapshotEntry],
+    scenario: &VetoScenario,
+    already_vetoing: &BTreeSet<[u8; 32]>,
+    threshold_percent: f64,
+    height: u64,
+) -> VetoSimulation {
+    let requested: BTreeSet<[u8; 32]> = scenario.nodes.iter().copied().collect();
+    let mut total_weight = 0.0;
+    let mut vetoing_weight = 0.0;
+    let mut vetoing_nodes = Vec::new();
+    for entry in entries {
+        total_weight += entry.weight;
+        let vetoing = already_vetoing.contains(&entry.node_id)
+            || requested.contains(&entry.node_id)
+            || scenario.categories.contains(&entry.node_type);
+        if vetoing {
+            vetoing_weight += entry.weight;
+            vetoing_nodes.push(hex::encode(entry.node_id));
+        }
+    }
+    vetoing_nodes.sort();
+    let unknown_nodes = requested
+        .iter()
+        .filter(|id| !entries.iter().any(|e| &e.node_id == *id))
+        .map(hex::encode)
+        .collect();
+
+    let veto_percent = if total_weight <= 0.0 {
+        0.0
+    } else {
+        vetoing_weight / total_weight * 100.0
+    };
+    let crossed = vetoing_weight > 0.0 && veto_percent >= threshold_percent;
+    let additional_weight_needed = if crossed || total_weight <= 0.0 {
+        0.0
+    } else {
+        // A vetoing node's weight is already in the total, so the target is a fixed share
+        (total_weight * threshold_percent / 100.0 - vetoing_weight).max(0.0)
+    };
+    VetoSimulation {
+        proposal_id: scenario.proposal_id.clone(),
+        tier: scenario.tier.clone(),
+        snapshot: scenario.snapshot,
+        height,
+        threshold_percent,
+        total_weight,
+        vetoing_weight,
+        veto_percent,
+        crossed,
+        additional_weight_needed,
+        vetoing_nodes,
+        unknown_nodes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u8, node_type: &str, weight: f64) -> SnapshotEntry {
+        SnapshotEntry {
+            node_id: [id; 32],
+            node_type: node_type.to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_hypothetical_nodes_and_categories() {
+        let entries = vec![
+            entry(1, "exchange", 10.0),
+            entry(2, "exchange", 15.0),
+            entry(3, "miner", 50.0),
+            entry(4, "custodian", 25.0),
+        ];
+        let scenario = VetoScenario {
+            categories: vec!["exchange".to_string()],
+            ..Default::default()
+        };
+        let result = simulate(&entries, &scenario, &BTreeSet::new(), 30.0, 100);
+        assert_eq!(result.vetoing_weight, 25.0);
+        assert!(!result.crossed);
+        assert_eq!(result.additional_weight_needed, 5.0);
+
+        let scenario = VetoScenario {
+            nodes: vec![[4; 32], [9; 32]],
+            categories: vec!["exchange".to_string()],
+            ..Default::default()
+        };
+        let result = simulate(&entries, &scenario, &BTreeSet::new(), 30.0, 100);
+        assert_eq!(result.vetoing_weight, 50.0);
+        assert!(result.crossed);
+        assert_eq!(result.additional_weight_needed, 0.0);
+        assert_eq!(result.vetoing_nodes.len(), 3);
+        assert_eq!(result.unknown_nodes, vec![hex::encode([9u8; 32])]);
+    }
+
+    #[test]
+    fn test_open_vetoes_count_once() {
+        let entries = vec![entry(1, "exchange", 20.0), entry(2, "miner", 80.0)];
+        let scenario = VetoScenario {
+            proposal_id: Some("p1".to_string()),
+            nodes: vec![[1; 32]],
+            ..Default::default()
+        };
+        let already = BTreeSet::from([[1u8; 32]]);
+        let result = simulate(&entries, &scenario, &already, 30.0, 0);
+        assert_eq!(result.vetoing_weight, 20.0);
+        assert_eq!(result.additional_weight_needed, 10.0);
+    }
+
+    #[test]
+    fn test_empty_registry_never_crosses() {
+        let scenario = VetoScenario {
+            nodes: vec![[1; 32]],
+            ..Default::default()
+        };
+        let result = simulate(&[], &scenario, &BTreeSet::new(), 30.0, 0);
+        assert!(!result.crossed);
+        assert_eq!(result.additional_weight_needed, 0.0);
+        assert_eq!(result.unknown_nodes.len(), 1);
+    }
+}
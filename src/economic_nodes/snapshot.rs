@@ -0,0 +1,163 @@
+//! Epoch snapshots of the registry
+//!
+//! On the first block of each epoch the active nodes and their weights are frozen into an
+//! immutable snapshot whose id is the epoch number (`height / length_blocks`). Proposals are
+//! pinned to the snapshot in effect when they were created so their veto math does not move
+//! with the live registry.
+
+use super::commitment::{self, RegistryCommitment};
+use super::{decay, metrics, EconomicNode};
+use crate::config::DecayConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// One node as recorded in a snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub node_id: [u8; 32],
+    pub node_type: String,
+    /// Effective weight at the snapshot height.
+    pub weight: f64,
+}
+
+/// Registry state at an epoch boundary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    /// Epoch number.
+    pub id: u64,
+    pub height: u64,
+    /// Commitment over the snapshotted entries.
+    pub commitment: RegistryCommitment,
+    /// Entries sorted by node id.
+    pub nodes: Vec<SnapshotEntry>,
+}
+
+impl RegistrySnapshot {
+    /// Snapshot the nodes active at `height`, with weights decayed to `height`.
+    pub fn take<'a>(
+        id: u64,
+        height: u64,
+        nodes: impl IntoIterator<Item = &'a EconomicNode>,
+        expiry_blocks: u64,
+        decay: &DecayConfig,
+    ) -> Self {
+        let active: Vec<&EconomicNode> = nodes
+            .into_iter()
+            .filter(|n| n.deactivated.is_none() && metrics::is_active(n, height, expiry_blocks))
+            .collect();
+        let mut entries: Vec<SnapshotEntry> = active
+            .iter()
+            .map(|n| SnapshotEntry {
+                node_id: n.node_id,
+                node_type: n.node_type.clone(),
+                weight: decay::effective_weight(n, height, decay),
+            })
+            .collect();
+        entries.sort_by_key(|e| e.node_id);
+        Self {
+            id,
+            height,
+            commitment: commitment::compute(active),
+            nodes: entries,
+        }
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.nodes.iter().map(|e| e.weight).sum()
+    }
+
+    pub fn weight_of(&self, node_id: &[u8; 32]) -> Option<f64> {
+        self.nodes
+            .binary_search_by(|e| e.node_id.cmp(node_id))
+            .ok()
+            .map(|i| self.nodes[i].weight)
+    }
+}
+
+/// Snapshots and the proposals pinned to them; persisted with the registry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EpochState {
+    pub snapshots: BTreeMap<u64, RegistrySnapshot>,
+    /// Open proposal -> snapshot id in effect when it was created.
+    pub proposal_snapshots: HashMap<String, u64>,
+}
+
+impl EpochState {
+    /// Snapshot currently in effect (the latest one).
+    pub fn current(&self) -> Option<&RegistrySnapshot> {
+        self.snapshots.values().next_back()
+    }
+
+    /// Snapshot a proposal is pinned to.
+    pub fn for_proposal(&self, proposal_id: &str) -> Option<&RegistrySnapshot> {
+        self.proposal_snapshots
+            .get(proposal_id)
+            .and_then(|id| self.snapshots.get(id))
+    }
+
+    /// Drop snapshots beyond the newest `retention`, keeping any pinned by an open proposal.
+    /// Returns the number of snapshots removed.
+    pub fn prune(&mut self, retention: usize) -> usize {
+        let pinned: HashSet<u64> = self.proposal_snapshots.values().copied().collect();
+        let excess = self.snapshots.len().saturating_sub(retention);
+        let doomed: Vec<u64> = self
+            .snapshots
+            .keys()
+            .take(excess)
+            .filter(|id| !pinned.contains(id))
+            .copied()
+            .collect();
+        for id in &doomed {
+            self.snapshots.remove(id);
+        }
+        doomed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::VerificationStatus;
+
+    fn node(id: u8, weight: f64, last_announced: u64) -> EconomicNode {
+        let mut n = EconomicNode::new([id; 32], 0);
+        n.verified_weight = weight as u64;
+        n.verification = VerificationStatus::Verified { height: 0 };
+        n.last_announced = last_announced;
+        n
+    }
+
+    #[test]
+    fn test_take_excludes_expired() {
+        let nodes = [node(2, 10.0, 1000), node(1, 5.0, 990), node(3, 50.0, 0)];
+        let snapshot = RegistrySnapshot::take(1, 1000, &nodes, 100, &DecayConfig::default());
+        assert_eq!(snapshot.nodes.len(), 2);
+        assert_eq!(snapshot.nodes[0].node_id, [1u8; 32]);
+        assert_eq!(snapshot.total_weight(), 15.0);
+        assert_eq!(snapshot.weight_of(&[2u8; 32]), Some(10.0));
+        assert_eq!(snapshot.weight_of(&[3u8; 32]), None);
+    }
+
+    #[test]
+    fn test_prune_keeps_pinned() {
+        let mut state = EpochState::default();
+        for id in 1..=5 {
+            state.snapshots.insert(
+                id,
+                RegistrySnapshot::take(
+                    id,
+                    id * 10,
+                    std::iter::empty::<&EconomicNode>(),
+                    0,
+                    &DecayConfig::default(),
+                ),
+            );
+        }
+        state.proposal_snapshots.insert("p1".to_string(), 2);
+        assert_eq!(state.prune(2), 2);
+        let kept: Vec<u64> = state.snapshots.keys().copied().collect();
+        assert_eq!(kept, vec![2, 4, 5]);
+        assert_eq!(state.for_proposal("p1").map(|s| s.id), Some(2));
+        assert_eq!(state.current().map(|s| s.id), Some(5));
+    }
+}
@@ -0,0 +1,231 @@
+//! Veto aggregation
+//!
+//! A proposal's tally compares the effective (decayed) weight of nodes with an open veto on
+//! it against the total effective weight of the registry. Since weights decay with the
+//! height, a tally can cross the threshold without any new veto.
+
+use super::commitment::RegistryCommitment;
+use super::decay;
+use super::snapshot::RegistrySnapshot;
+use super::EconomicNode;
+use crate::config::DecayConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Aggregated veto weight for one proposal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VetoTally {
+    pub proposal_id: String,
+    pub total_weight: f64,
+    pub vetoing_weight: f64,
+    pub threshold_percent: f64,
+    /// Commitment root (hex) of the registry state the tally was computed from.
+    pub commitment: String,
+}
+
+impl VetoTally {
+    /// Vetoing weight as a percentage of total weight.
+    pub fn veto_percent(&self) -> f64 {
+        if self.total_weight <= 0.0 {
+            0.0
+        } else {
+            self.vetoing_weight / self.total_weight * 100.0
+        }
+    }
+
+    /// Whether the vetoes block the proposal.
+    pub fn crossed(&self) -> bool {
+        self.vetoing_weight > 0.0 && self.veto_percent() >= self.threshold_percent
+    }
+
+    /// Same outcome and weights, ignoring the commitment the tally was computed from.
+    pub fn same_result(&self, other: &VetoTally) -> bool {
+        self.proposal_id == other.proposal_id
+            && self.total_weight == other.total_weight
+            && self.vetoing_weight == other.vetoing_weight
+            && self.threshold_percent == other.threshold_percent
+    }
+}
+
+/// Proposals with at least one open veto.
+pub fn open_veto_proposals<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+) -> BTreeSet<String> {
+    nodes
+        .into_iter()
+        .flat_map(|n| n.veto_history.iter())
+        .filter(|v| v.outcome.is_none())
+        .map(|v| v.proposal_id.clone())
+        .collect()
+}
+
+/// Tally the open vetoes on `proposal_id` with weights decayed to `height`.
+pub fn compute<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    proposal_id: &str,
+    threshold_percent: f64,
+    commitment: &RegistryCommitment,
+    height: u64,
+    decay: &DecayConfig,
+) -> VetoTally {
+    compute_weighted(nodes, proposal_id, threshold_percent, commitment, |n| {
+        decay::effective_weight(n, height, decay)
+    })
+}
+
+/// Tally the open vetoes on `proposal_id`, weighing each node with `weight`.
+pub fn compute_weighted<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    proposal_id: &str,
+    threshold_percent: f64,
+    commitment: &RegistryCommitment,
+    weight: impl Fn(&EconomicNode) -> f64,
+) -> VetoTally {
+    let mut total_weight = 0.0;
+    let mut vetoing_weight = 0.0;
+    for node in nodes.into_iter().filter(|n| n.deactivated.is_none()) {
+        let weight = weight(node);
+        total_weight += weight;
+        let vetoing = node
+            .veto_history
+            .iter()
+            .any(|v| v.proposal_id == proposal_id && v.outcome.is_none());
+        if vetoing {
+            vetoing_weight += weight;
+        }
+    }
+    VetoTally {
+        proposal_id: proposal_id.to_string(),
+        total_weight,
+        vetoing_weight,
+        threshold_percent,
+        commitment: commitment.root_hex(),
+    }
+}
+
+/// Tally the open vetoes on `proposal_id` using the weights frozen in `snapshot`.
+///
+/// Only nodes present in the snapshot count, at their snapshotted (already decayed) weight.
+pub fn compute_from_snapshot<'a>(
+    nodes: impl IntoIterator<Item = &'a EconomicNode>,
+    proposal_id: &str,
+    threshold_percent: f64,
+    snapshot: &RegistrySnapshot,
+) -> VetoTally {
+    let vetoing_weight = nodes
+        .into_iter()
+        .filter(|n| n.deactivated.is_none())
+        .filter(|n| {
+            n.veto_history
+                .iter()
+                .any(|v| v.proposal_id == proposal_id && v.outcome.is_none())
+        })
+        .filter_map(|n| snapshot.weight_of(&n.node_id))
+        .sum();
+    VetoTally {
+        proposal_id: proposal_id.to_string(),
+        total_weight: snapshot.total_weight(),
+        vetoing_weight,
+        threshold_percent,
+        commitment: snapshot.commitment.root_hex(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::{commitment, VerificationStatus, VetoOutcome, VetoRecord};
+
+    fn node(id: u8, weight: f64, vetoes: &[(&str, Option<VetoOutcome>)]) -> EconomicNode {
+        let mut n = EconomicNode::new([id; 32], 0);
+        n.verified_weight = weight as u64;
+        n.verification = VerificationStatus::Verified { height: 0 };
+        n.veto_history = vetoes
+            .iter()
+            .map(|(p, outcome)| VetoRecord {
+                proposal_id: p.to_string(),
+                height: 0,
+                reason: String::new(),
+                outcome: *outcome,
+            })
+            .collect();
+        n
+    }
+
+    #[test]
+    fn test_tally_counts_open_vetoes_only() {
+        let nodes = vec![
+            node(1, 20.0, &[("p1", None)]),
+            node(2, 15.0, &[("p1", Some(VetoOutcome::MergedAnyway))]),
+            node(3, 65.0, &[]),
+        ];
+        let c = commitment::compute(&nodes);
+        let tally = compute(&nodes, "p1", 30.0, &c, 0, &DecayConfig::default());
+        assert_eq!(tally.total_weight, 100.0);
+        assert_eq!(tally.vetoing_weight, 20.0);
+        assert!(!tally.crossed());
+        assert_eq!(
+            open_veto_proposals(&nodes).into_iter().collect::<Vec<_>>(),
+            vec!["p1"]
+        );
+    }
+
+    #[test]
+    fn test_threshold_crossed() {
+        let nodes = vec![
+            node(1, 20.0, &[("p1", None)]),
+            node(2, 10.0, &[("p1", None)]),
+            node(3, 70.0, &[]),
+        ];
+        let c = commitment::compute(&nodes);
+        let tally = compute(&nodes, "p1", 30.0, &c, 0, &DecayConfig::default());
+        assert!(tally.crossed());
+        assert!(!compute(&nodes, "p2", 30.0, &c, 0, &DecayConfig::default()).crossed());
+    }
+
+    #[test]
+    fn test_deactivated_nodes_carry_no_weight() {
+        let mut blocked = node(1, 40.0, &[("p1", None)]);
+        blocked.deactivated = Some("blocklisted".to_string());
+        let nodes = vec![blocked, node(2, 60.0, &[])];
+        let c = commitment::compute(&nodes);
+        let tally = compute(&nodes, "p1", 30.0, &c, 0, &DecayConfig::default());
+        assert_eq!(tally.total_weight, 60.0);
+        assert_eq!(tally.vetoing_weight, 0.0);
+    }
+
+    #[test]
+    fn test_snapshot_weights() {
+        let snapshot_nodes = vec![node(1, 20.0, &[]), node(2, 80.0, &[])];
+        let snapshot = RegistrySnapshot::take(1, 0, &snapshot_nodes, 0, &DecayConfig::default());
+        // Live weights changed and node 3 joined after the snapshot
+        let live = vec![
+            node(1, 90.0, &[("p1", None)]),
+            node(2, 5.0, &[]),
+            node(3, 50.0, &[("p1", None)]),
+        ];
+        let tally = compute_from_snapshot(&live, "p1", 30.0, &snapshot);
+        assert_eq!(tally.total_weight, 100.0);
+        assert_eq!(tally.vetoing_weight, 20.0);
+        assert_eq!(tally.commitment, snapshot.commitment.root_hex());
+    }
+
+    #[test]
+    fn test_crosses_by_decay_of_denominator() {
+        let decay = DecayConfig {
+            half_life_blocks: 1000,
+            steps: Vec::new(),
+        };
+        let mut recent = node(1, 20.0, &[("p1", None)]);
+        recent.verification = VerificationStatus::Verified { height: 2000 };
+        let nodes = vec![recent, node(2, 80.0, &[])];
+        let c = commitment::compute(&nodes);
+
+        let fresh = compute(&nodes, "p1", 30.0, &c, 0, &decay);
+        assert!(!fresh.crossed());
+        // Two half-lives later the stale node carries 20: 20 / 40 = 50%
+        let later = compute(&nodes, "p1", 30.0, &c, 2000, &decay);
+        assert_eq!(later.total_weight, 40.0);
+        assert!(later.crossed());
+    }
+}
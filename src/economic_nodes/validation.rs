@@ -0,0 +1,319 @@
+//! Validation of registration payloads
+//!
+//! Every registration, whether from a live event or imported from the node, passes through
+//! [`validate_registration`] before it is stored.
+
+use super::reserve::ReserveClaim;
+use crate::config::ValidationConfig;
+use crate::error::GovernanceError;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Validation rule that rejected a registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationRule {
+    EmptyNodeId,
+    NodeIdTooLong,
+    NodeIdNotHex,
+    NodeTypeTooLong,
+    NonFiniteStake,
+    ZeroStake,
+    StakeOutOfRange,
+    InvalidPublicKey,
+    NoOutpoints,
+    TooManyOutpoints,
+    DuplicateOutpoint,
+    InvalidSignatureLength,
+}
+
+impl ValidationRule {
+    /// Registration field the rule applies to.
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::EmptyNodeId | Self::NodeIdTooLong | Self::NodeIdNotHex => "node_id",
+            Self::NodeTypeTooLong => "node_type",
+            Self::NonFiniteStake | Self::ZeroStake | Self::StakeOutOfRange => "hashpower_percent",
+            Self::InvalidPublicKey => "reserve.public_key",
+            Self::NoOutpoints | Self::TooManyOutpoints | Self::DuplicateOutpoint => {
+                "reserve.outpoints"
+            }
+            Self::InvalidSignatureLength => "reserve.signature",
+        }
+    }
+}
+
+/// A rejected registration: the rule that failed and a human-readable reason.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationRejection {
+    pub rule: ValidationRule,
+    pub reason: String,
+}
+
+impl ValidationRejection {
+    fn new(rule: ValidationRule, reason: impl Into<String>) -> Self {
+        Self {
+            rule,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<ValidationRejection> for GovernanceError {
+    fn from(r: ValidationRejection) -> Self {
+        GovernanceError::ValidationError {
+            field: r.rule.field().to_string(),
+            reason: r.reason,
+        }
+    }
+}
+
+/// Validate a registration and return the parsed node id.
+pub fn validate_registration(
+    node_id: &str,
+    node_type: &str,
+    hashpower_percent: Option<f64>,
+    claim: Option<&ReserveClaim>,
+    config: &ValidationConfig,
+) -> Result<[u8; 32], ValidationRejection> {
+    if node_id.is_empty() {
+        return Err(ValidationRejection::new(
+            ValidationRule::EmptyNodeId,
+            "node id is empty",
+        ));
+    }
+    if node_id.len() > config.max_node_id_len {
+        return Err(ValidationRejection::new(
+            ValidationRule::NodeIdTooLong,
+            format!(
+                "node id is {} characters, at most {} allowed",
+                node_id.len(),
+                config.max_node_id_len
+            ),
+        ));
+    }
+    let parsed = super::parse_node_id(node_id).ok_or_else(|| {
+        ValidationRejection::new(
+            ValidationRule::NodeIdNotHex,
+            "node id must be 64 hex characters",
+        )
+    })?;
+    if node_type.len() > config.max_node_type_len {
+        return Err(ValidationRejection::new(
+            ValidationRule::NodeTypeTooLong,
+            format!(
+                "node type is {} characters, at most {} allowed",
+                node_type.len(),
+                config.max_node_type_len
+            ),
+        ));
+    }
+
+    if let Some(stake) = hashpower_percent {
+        if !stake.is_finite() {
+            return Err(ValidationRejection::new(
+                ValidationRule::NonFiniteStake,
+                "claimed hashpower is not a finite number",
+            ));
+        }
+        if stake == 0.0 {
+            return Err(ValidationRejection::new(
+                ValidationRule::ZeroStake,
+                "claimed hashpower is zero",
+            ));
+        }
+        if stake < 0.0 || stake > config.max_hashpower_percent {
+            return Err(ValidationRejection::new(
+                ValidationRule::StakeOutOfRange,
+                format!(
+                    "claimed hashpower {} outside (0, {}]",
+                    stake, config.max_hashpower_percent
+                ),
+            ));
+        }
+    }
+
+    if let Some(claim) = claim {
+        if secp256k1::PublicKey::from_slice(&claim.public_key).is_err()
+            || claim.public_key.len() != 33
+        {
+            return Err(ValidationRejection::new(
+                ValidationRule::InvalidPublicKey,
+                "public key must be a valid 33-byte compressed secp256k1 key",
+            ));
+        }
+        if claim.outpoints.is_empty() {
+            return Err(ValidationRejection::new(
+                ValidationRule::NoOutpoints,
+                "reserve claim lists no outpoints",
+            ));
+        }
+        if claim.outpoints.len() > config.max_outpoints {
+            return Err(ValidationRejection::new(
+                ValidationRule::TooManyOutpoints,
+                format!(
+                    "reserve claim lists {} outpoints, at most {} allowed",
+                    claim.outpoints.len(),
+                    config.max_outpoints
+                ),
+            ));
+        }
+        let mut seen = std::collections::HashSet::new();
+        if let Some(dup) = claim.outpoints.iter().find(|o| !seen.insert(**o)) {
+            return Err(ValidationRejection::new(
+                ValidationRule::DuplicateOutpoint,
+                format!("outpoint {} listed twice", dup),
+            ));
+        }
+        if claim.signature.len() != 64 {
+            return Err(ValidationRejection::new(
+                ValidationRule::InvalidSignatureLength,
+                format!("signature is {} bytes, expected 64", claim.signature.len()),
+            ));
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Rejection counts per validation rule.
+#[derive(Debug, Default)]
+pub struct ValidationCounters {
+    counts: std::sync::Mutex<HashMap<ValidationRule, u64>>,
+}
+
+impl ValidationCounters {
+    pub fn record(&self, rule: ValidationRule) {
+        *self.counts.lock().unwrap().entry(rule).or_insert(0) += 1;
+    }
+
+    pub fn snapshot(&self) -> HashMap<ValidationRule, u64> {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::ClaimedOutpoint;
+
+    const VALID_ID: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+    fn config() -> ValidationConfig {
+        ValidationConfig::default()
+    }
+
+    fn claim() -> ReserveClaim {
+        let secp = secp256k1::Secp256k1::new();
+        let sk = secp256k1::SecretKey::from_slice(&[5u8; 32]).unwrap();
+        ReserveClaim {
+            public_key: secp256k1::PublicKey::from_secret_key(&secp, &sk)
+                .serialize()
+                .to_vec(),
+            outpoints: vec![ClaimedOutpoint {
+                txid: [1u8; 32],
+                vout: 0,
+            }],
+            signature: vec![0u8; 64],
+        }
+    }
+
+    fn rule_of(result: Result<[u8; 32], ValidationRejection>) -> Option<ValidationRule> {
+        result.err().map(|r| r.rule)
+    }
+
+    #[test]
+    fn test_valid_registration() {
+        assert_eq!(
+            validate_registration(VALID_ID, "miner", Some(0.5), Some(&claim()), &config()),
+            Ok([1u8; 32])
+        );
+        assert!(validate_registration(VALID_ID, "exchange", None, None, &config()).is_ok());
+    }
+
+    #[test]
+    fn test_node_id_rules() {
+        let c = config();
+        assert_eq!(
+            rule_of(validate_registration("", "miner", None, None, &c)),
+            Some(ValidationRule::EmptyNodeId)
+        );
+        let long = "a".repeat(65);
+        assert_eq!(
+            rule_of(validate_registration(&long, "miner", None, None, &c)),
+            Some(ValidationRule::NodeIdTooLong)
+        );
+        let not_hex = "z".repeat(64);
+        assert_eq!(
+            rule_of(validate_registration(&not_hex, "miner", None, None, &c)),
+            Some(ValidationRule::NodeIdNotHex)
+        );
+        assert_eq!(
+            rule_of(validate_registration("abcd", "miner", None, None, &c)),
+            Some(ValidationRule::NodeIdNotHex)
+        );
+    }
+
+    #[test]
+    fn test_node_type_rule() {
+        let long = "x".repeat(65);
+        assert_eq!(
+            rule_of(validate_registration(VALID_ID, &long, None, None, &config())),
+            Some(ValidationRule::NodeTypeTooLong)
+        );
+    }
+
+    #[test]
+    fn test_stake_rules() {
+        let c = config();
+        let check = |stake| rule_of(validate_registration(VALID_ID, "miner", Some(stake), None, &c));
+        assert_eq!(check(f64::NAN), Some(ValidationRule::NonFiniteStake));
+        assert_eq!(check(f64::INFINITY), Some(ValidationRule::NonFiniteStake));
+        assert_eq!(check(0.0), Some(ValidationRule::ZeroStake));
+        assert_eq!(check(-1.0), Some(ValidationRule::StakeOutOfRange));
+        assert_eq!(check(100.5), Some(ValidationRule::StakeOutOfRange));
+        assert_eq!(check(100.0), None);
+    }
+
+    #[test]
+    fn test_claim_rules() {
+        let c = config();
+        let check = |claim: ReserveClaim| {
+            rule_of(validate_registration(VALID_ID, "miner", None, Some(&claim), &c))
+        };
+
+        let mut bad_key = claim();
+        bad_key.public_key = vec![0x04; 33];
+        assert_eq!(check(bad_key), Some(ValidationRule::InvalidPublicKey));
+
+        let mut no_outpoints = claim();
+        no_outpoints.outpoints.clear();
+        assert_eq!(check(no_outpoints), Some(ValidationRule::NoOutpoints));
+
+        let mut too_many = claim();
+        too_many.outpoints = (0..=c.max_outpoints as u32)
+            .map(|vout| ClaimedOutpoint {
+                txid: [1u8; 32],
+                vout,
+            })
+            .collect();
+        assert_eq!(check(too_many), Some(ValidationRule::TooManyOutpoints));
+
+        let mut duplicate = claim();
+        duplicate.outpoints.push(duplicate.outpoints[0]);
+        assert_eq!(check(duplicate), Some(ValidationRule::DuplicateOutpoint));
+
+        let mut short_sig = claim();
+        short_sig.signature = vec![0u8; 10];
+        assert_eq!(check(short_sig), Some(ValidationRule::InvalidSignatureLength));
+    }
+
+    #[test]
+    fn test_rejection_maps_to_error() {
+        let rejection = validate_registration("", "miner", None, None, &config()).unwrap_err();
+        match GovernanceError::from(rejection) {
+            GovernanceError::ValidationError { field, .. } => assert_eq!(field, "node_id"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
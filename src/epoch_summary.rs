@@ -0,0 +1,439 @@
+//! Governance epoch summaries
+//!
+//! With `[governance.epoch_summary] length_blocks` set, the chain is divided into epochs of
+//! that many blocks, epoch `n` covering heights `n * length_blocks` to
+//! `(n + 1) * length_blocks - 1`. Boundaries come from block heights alone, so every module
+//! following the same chain summarizes the same epochs.
+//!
+//! An epoch is summarized once its last block is `confirmations` deep, i.e. at the first
+//! `NewBlock` at or above its last height plus `confirmations`. The [`EpochSummary`] gathers:
+//!
+//! - proposals created, merged, rejected and expired in the epoch, by the heights the proposal
+//!   store recorded (see [`crate::proposals`]);
+//! - votes applied while the tip was in the epoch ([`ProposalStore::votes_between`]);
+//! - vetoes cast, registrations and weight changes, from the heights in the registry's node
+//!   records, archived nodes included;
+//! - for each proposal with miner signaling configured, the epoch's blocks that signaled for
+//!   it, counted from the node's chain ([`SignalingTracker::count`]);
+//! - for each activated proposal with adoption followed, its adoption at the epoch's last
+//!   block ([`AdoptionTracker::levels_at`]);
+//! - with participation statistics kept, the share of eligible votes cast over the proposals
+//!   closed so far ([`Participation::rate`]).
+//!
+//! Summaries are stored in the module database and sent to the webhook as `epoch_summary`.
+//! The epochs missed while the module was stopped are summarized in order on the next block;
+//! when none has been summarized yet, only the latest settled epoch is. A summary is final:
+//! a reorg deeper than `confirmations` does not redo it.
+//!
+//! Queries: [`EpochSummarizer::summaries`] and [`EpochSummarizer::summary`]; the
+//! `list_epoch_summaries` and `get_epoch_summary` API methods and the `export-summaries`
+//! subcommand read them.
+//!
+//! [`Participation::rate`]: crate::participation::Participation::rate
+
+use crate::adoption::{AdoptionLevel, AdoptionTracker};
+use crate::config::EpochSummaryConfig;
+use crate::economic_nodes::{EconomicNode, EconomicNodeRegistry};
+use crate::error::GovernanceError;
+use crate::participation::ParticipationAnalyzer;
+use crate::proposals::{GovernanceProposal, ProposalStatus, ProposalStore};
+use crate::schema;
+use crate::signaling::{SignalingCount, SignalingTracker};
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::info;
+
+const SUMMARIES_TREE: &str = "epoch_summaries";
+
+const SUMMARIES_KEY: &[u8] = b"summaries";
+
+/// Event type of the webhook notification.
+pub const EVENT_TYPE: &str = "epoch_summary";
+
+/// Heights of `epoch`, with epochs of `length` blocks.
+pub fn heights(epoch: u64, length: u64) -> RangeInclusive<u64> {
+    let start = epoch.saturating_mul(length);
+    start..=start.saturating_add(length.saturating_sub(1))
+}
+
+/// The latest epoch whose last block is `confirmations` deep at tip `height`, if any.
+pub fn settled(height: u64, length: u64, confirmations: u64) -> Option<u64> {
+    if length == 0 {
+        return None;
+    }
+    let complete = height.saturating_add(1).checked_sub(confirmations)? / length;
+    complete.checked_sub(1)
+}
+
+/// Proposals that changed state in an epoch, by id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProposalActivity {
+    pub created: Vec<String>,
+    pub merged: Vec<String>,
+    pub rejected: Vec<String>,
+    pub expired: Vec<String>,
+}
+
+impl ProposalActivity {
+    /// Activity in `heights` of `proposals`.
+    pub fn of(proposals: &[GovernanceProposal], heights: &RangeInclusive<u64>) -> Self {
+        let in_epoch = |height: Option<u64>| height.is_some_and(|h| heights.contains(&h));
+        let mut activity = Self::default();
+        for proposal in proposals {
+            let id = proposal.proposal_id.clone();
+            if in_epoch(proposal.created_height) {
+                activity.created.push(id.clone());
+            }
+            if !in_epoch(proposal.closed_height) {
+                continue;
+            }
+            match proposal.status {
+                ProposalStatus::Merged => activity.merged.push(id),
+                ProposalStatus::Rejected => activity.rejected.push(id),
+                ProposalStatus::Expired => activity.expired.push(id),
+                ProposalStatus::Created | ProposalStatus::Voting => {}
+            }
+        }
+        for ids in [
+            &mut activity.created,
+            &mut activity.merged,
+            &mut activity.rejected,
+            &mut activity.expired,
+        ] {
+            ids.sort();
+        }
+        activity
+    }
+}
+
+/// Registry changes in an epoch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryActivity {
+    /// Nodes whose registration height is in the epoch.
+    pub registrations: u64,
+    pub weight_changes: u64,
+    pub vetoes: u64,
+    /// Vetoes cast, by proposal id.
+    pub vetoes_by_proposal: BTreeMap<String, u64>,
+}
+
+impl RegistryActivity {
+    /// Activity in `heights` recorded on `nodes`.
+    pub fn of(nodes: &[EconomicNode], heights: &RangeInclusive<u64>) -> Self {
+        let mut activity = Self::default();
+        for node in nodes {
+            if heights.contains(&node.registered_at) {
+                activity.registrations += 1;
+            }
+            activity.weight_changes += node
+                .weight_history
+                .iter()
+                .filter(|record| heights.contains(&record.height))
+                .count() as u64;
+            for veto in &node.veto_history {
+                if heights.contains(&veto.height) {
+                    activity.vetoes += 1;
+                    *activity
+                        .vetoes_by_proposal
+                        .entry(veto.proposal_id.clone())
+                        .or_default() += 1;
+                }
+            }
+        }
+        activity
+    }
+}
+
+/// The digest of one epoch, sent to the webhook as the data of [`EVENT_TYPE`] (see
+/// [`crate::schema`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EpochSummary {
+    pub epoch: u64,
+    pub start_height: u64,
+    pub end_height: u64,
+    pub proposals: ProposalActivity,
+    /// Votes applied, replaced votes included.
+    pub votes: u64,
+    pub registry: RegistryActivity,
+    pub signaling: Vec<SignalingCount>,
+    pub adoption: Vec<AdoptionLevel>,
+    /// Share of eligible votes cast over every proposal closed by the epoch's end, if
+    /// participation is counted.
+    pub participation_rate: Option<f64>,
+}
+
+/// Columns of [`summaries_csv`].
+pub const SUMMARY_COLUMNS: [&str; 11] = [
+    "epoch",
+    "start_height",
+    "end_height",
+    "created",
+    "merged",
+    "rejected",
+    "expired",
+    "votes",
+    "registrations",
+    "weight_changes",
+    "vetoes",
+];
+
+/// `summaries` as CSV, one row per epoch with the number of proposals in each state.
+pub fn summaries_csv<'a>(summaries: impl Iterator<Item = &'a EpochSummary>) -> String {
+    crate::economic_nodes::export::csv_table(
+        &SUMMARY_COLUMNS,
+        summaries.map(|s| {
+            [
+                s.epoch,
+                s.start_height,
+                s.end_height,
+                s.proposals.created.len() as u64,
+                s.proposals.merged.len() as u64,
+                s.proposals.rejected.len() as u64,
+                s.proposals.expired.len() as u64,
+                s.votes,
+                s.registry.registrations,
+                s.registry.weight_changes,
+                s.registry.vetoes,
+            ]
+            .iter()
+            .map(u64::to_string)
+            .collect()
+        }),
+    )
+}
+
+/// Summarizes each epoch once it is settled.
+pub struct EpochSummarizer {
+    config: EpochSummaryConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    proposals: Arc<ProposalStore>,
+    registry: Option<Arc<EconomicNodeRegistry>>,
+    signaling: Option<Arc<SignalingTracker>>,
+    adoption: Option<Arc<AdoptionTracker>>,
+    participation: Option<Arc<ParticipationAnalyzer>>,
+    /// Where summaries are sent.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    /// Held while epochs are summarized, so each is summarized once.
+    update: tokio::sync::Mutex<()>,
+}
+
+impl EpochSummarizer {
+    pub fn new(
+        config: EpochSummaryConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+        proposals: Arc<ProposalStore>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            proposals,
+            registry: None,
+            signaling: None,
+            adoption: None,
+            participation: None,
+            webhook: None,
+            update: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Count vetoes, registrations and weight changes from `registry`.
+    pub fn with_registry(mut self, registry: Arc<EconomicNodeRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Count signaling blocks for the proposals `signaling` tracks.
+    pub fn with_signaling(mut self, signaling: Arc<SignalingTracker>) -> Self {
+        self.signaling = Some(signaling);
+        self
+    }
+
+    /// Measure the adoption of the proposals `adoption` follows.
+    pub fn with_adoption(mut self, adoption: Arc<AdoptionTracker>) -> Self {
+        self.adoption = Some(adoption);
+        self
+    }
+
+    /// Record the participation rate `participation` keeps.
+    pub fn with_participation(mut self, participation: Arc<ParticipationAnalyzer>) -> Self {
+        self.participation = Some(participation);
+        self
+    }
+
+    /// Send summaries to `webhook`.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Whether epochs have a length, so blocks are needed.
+    pub fn is_enabled(&self) -> bool {
+        self.config.length_blocks > 0
+    }
+
+    /// Stored summaries, oldest first.
+    pub fn summaries(&self) -> Result<Vec<EpochSummary>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.into_values().collect())
+    }
+
+    /// The stored summary of `epoch`, if it has been summarized.
+    pub fn summary(&self, epoch: u64) -> Result<Option<EpochSummary>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.remove(&epoch))
+    }
+
+    /// The stored summary of the latest epoch summarized, if any.
+    pub fn latest(&self) -> Result<Option<EpochSummary>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.into_values().next_back())
+    }
+
+    /// Summarize the epochs settled at the `NewBlock` at `height` and not summarized yet, and
+    /// send their summaries.
+    pub async fn handle_block(
+        &self,
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let length = self.config.length_blocks;
+        let Some(settled) = settled(height, length, self.config.confirmations) else {
+            return Ok(());
+        };
+        let _update = self.update.lock().await;
+        let mut stored = Self::load_from(&self.db)?;
+        let first = match stored.keys().next_back() {
+            Some(last) => last + 1,
+            None => settled,
+        };
+        for epoch in first..=settled {
+            let summary = self.summarize(epoch).await?;
+            info!(
+                "Epoch {} ({}..={}): {} proposals created, {} merged, {} votes, {} vetoes",
+                epoch,
+                summary.start_height,
+                summary.end_height,
+                summary.proposals.created.len(),
+                summary.proposals.merged.len(),
+                summary.votes,
+                summary.registry.vetoes
+            );
+            stored.insert(epoch, summary.clone());
+            self.save(&stored)?;
+            if let Some(webhook) = &self.webhook {
+                let data = schema::to_data(&summary)?;
+                let intent = webhook.record_intent(EVENT_TYPE, &data);
+                webhook
+                    .notify_recorded(EVENT_TYPE, data, intent, node_api)
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gather the summary of `epoch` from the stores.
+    async fn summarize(&self, epoch: u64) -> Result<EpochSummary, GovernanceError> {
+        let heights = heights(epoch, self.config.length_blocks);
+        let proposals = ProposalActivity::of(&self.proposals.load_proposals()?, &heights);
+        let votes = self.proposals.votes_between(heights.clone())?;
+        let registry = match &self.registry {
+            Some(registry) => {
+                let mut nodes = registry.list_nodes().await;
+                nodes.extend(registry.list_archived().await);
+                RegistryActivity::of(&nodes, &heights)
+            }
+            None => RegistryActivity::default(),
+        };
+        let signaling = match &self.signaling {
+            Some(signaling) => signaling.count(heights.clone()).await?,
+            None => Vec::new(),
+        };
+        let adoption = match &self.adoption {
+            Some(adoption) => adoption.levels_at(*heights.end()).await?,
+            None => Vec::new(),
+        };
+        let participation_rate = match &self.participation {
+            Some(participation) if participation.is_enabled() => {
+                participation.participation()?.rate()
+            }
+            _ => None,
+        };
+        Ok(EpochSummary {
+            epoch,
+            start_height: *heights.start(),
+            end_height: *heights.end(),
+            proposals,
+            votes,
+            registry,
+            signaling,
+            adoption,
+            participation_rate,
+        })
+    }
+
+    fn save(&self, summaries: &BTreeMap<u64, EpochSummary>) -> Result<(), GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(SUMMARIES_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(summaries).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(SUMMARIES_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    /// Load stored summaries, by epoch, e.g. for the CLI.
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<BTreeMap<u64, EpochSummary>, GovernanceError> {
+        let tree = db
+            .open_tree(SUMMARIES_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(SUMMARIES_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(BTreeMap::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::VetoRecord;
+
+    #[test]
+    fn test_epochs_settle_after_confirmations() {
+        assert_eq!(heights(3, 10), 30..=39);
+        assert_eq!(settled(40, 10, 2), Some(2));
+        assert_eq!(settled(41, 10, 2), Some(3));
+        assert_eq!(settled(50, 10, 2), Some(3));
+        assert_eq!(settled(51, 10, 2), Some(4));
+        assert_eq!(settled(1, 10, 2), None);
+        assert_eq!(settled(9, 10, 0), Some(0));
+        assert_eq!(settled(1000, 0, 2), None);
+    }
+
+    #[test]
+    fn test_registry_activity_in_the_epoch() {
+        let veto = |proposal_id: &str, height| VetoRecord {
+            proposal_id: proposal_id.to_string(),
+            height,
+            reason: String::new(),
+            outcome: None,
+        };
+        let mut early = EconomicNode::new([1; 32], 5);
+        early.veto_history = vec![veto("7", 9), veto("8", 10), veto("8", 12)];
+        let mut late = EconomicNode::new([2; 32], 15);
+        late.veto_history = vec![veto("8", 19), veto("9", 20)];
+
+        let activity = RegistryActivity::of(&[early, late], &heights(1, 10));
+        assert_eq!(activity.registrations, 1);
+        assert_eq!(activity.vetoes, 3);
+        assert_eq!(activity.vetoes_by_proposal, [("8".to_string(), 3)].into());
+    }
+}
@@ -1,7 +1,31 @@
 //! Error types for Governance module
+//!
+//! Failures of requests to the node are typed by cause ([`GovernanceError::from_ipc`]):
+//! a lost connection, a timeout, something that does not exist, or a rejection by the node,
+//! which keeps the node's message and code. Responses that cannot be decoded are
+//! [`GovernanceError::Serialization`]. Failed webhook requests are
+//! [`GovernanceError::WebhookHttp`], with the status if the receiver answered, failed file
+//! operations are [`GovernanceError::Io`], and failures of the module store
+//! [`GovernanceError::Database`] or [`GovernanceError::Encoding`]. The string variants remain
+//! for failures with no structure to them; they are classified by message.
+//!
+//! Each typed variant keeps the error it was made from as its
+//! [`source`](std::error::Error::source), next to the operation that failed; the helpers
+//! ([`GovernanceError::io`], [`GovernanceError::database`], ...) build them in `map_err`. The
+//! display of a variant shows its immediate cause, but not what caused that: a webhook request
+//! that failed in DNS resolution or the TLS handshake only says so further down the chain.
+//! Logs print the whole chain with [`GovernanceError::chain`].
+//!
+//! Whether to try again is decided by [`GovernanceError::retryability`] (or
+//! [`GovernanceError::is_retryable`]), never by matching messages at the call site. Every
+//! variant's classification is pinned by `test_classification_table`.
 
+use std::fmt;
 use thiserror::Error;
 
+/// The source of an error from another crate whose type is not fixed here.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Debug, Error)]
 pub enum GovernanceError {
     #[error("Module error: {0}")]
@@ -10,6 +34,25 @@ pub enum GovernanceError {
     #[error("Webhook error: {0}")]
     WebhookError(String),
 
+    /// A webhook request failed: the receiver answered `status`, or, without one, the
+    /// request could not be made.
+    #[error("{url}: {reason}")]
+    WebhookHttp {
+        url: String,
+        status: Option<u16>,
+        retryable: Retryability,
+        reason: String,
+        /// The failed request; none if the receiver answered.
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+
+    #[error("Failed to create HTTP client: {source}")]
+    HttpClient {
+        #[source]
+        source: reqwest::Error,
+    },
+
     #[error("Economic node error: {0}")]
     EconomicNodeError(String),
 
@@ -18,10 +61,954 @@ pub enum GovernanceError {
 
     #[error("Storage error: {0}")]
     Storage(String),
+
+    /// A file operation failed; `operation` is usually the path.
+    #[error("{operation}: {source}")]
+    Io {
+        operation: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A read or write of the module store failed.
+    #[error("{operation}: {source}")]
+    Database {
+        operation: String,
+        #[source]
+        source: BoxError,
+    },
+
+    /// A record of the module store could not be encoded or decoded.
+    #[error("{operation}: {source}")]
+    Encoding {
+        operation: String,
+        #[source]
+        source: bincode::Error,
+    },
+
+    #[error("Validation error: {field}: {reason}")]
+    ValidationError { field: String, reason: String },
+
+    #[error("Timed out after {after:?}: {operation}")]
+    Timeout {
+        operation: String,
+        after: std::time::Duration,
+    },
+
+    #[error("{method}: no answer from the node after {elapsed:?}")]
+    NodeApiTimeout {
+        method: String,
+        elapsed: std::time::Duration,
+    },
+
+    /// The connection to the node was lost or is not open.
+    #[error("{operation}: disconnected from the node: {reason}")]
+    IpcDisconnected {
+        operation: String,
+        reason: String,
+        /// The IPC client's error, if there was one.
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// The IPC client or the node gave up waiting; see [`Self::NodeApiTimeout`] for the
+    /// module's own deadline.
+    #[error("{method}: IPC request timed out")]
+    IpcTimeout {
+        method: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// The node answered with an error.
+    #[error("{operation}: rejected by the node: {message}")]
+    NodeRejected {
+        operation: String,
+        /// Error code, if the node gave one.
+        code: Option<i64>,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// No module serves the method: the node's router has nothing registered under it.
+    #[error("{operation}: not served by the node: {message}")]
+    Unsupported {
+        operation: String,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("{operation}: not found: {what}")]
+    NotFound {
+        operation: String,
+        what: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("{operation}: invalid JSON: {source}")]
+    Serialization {
+        operation: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{operation}: message of {size} bytes exceeds the limit of {limit} bytes")]
+    MessageTooLarge {
+        operation: String,
+        size: usize,
+        limit: usize,
+    },
+
+    #[error("{operation}: the node has no transaction index")]
+    NotIndexed { operation: String },
+
+    #[error("{operation}: mempool queries are disabled on the node")]
+    MempoolDisabled { operation: String },
+
+    #[error("{action}: governance actions are disabled (governance.allow_actions)")]
+    ActionsDisabled { action: String },
+
+    #[error("{action}: outbound effects are paused (blvm-governance resume)")]
+    Paused { action: String },
+
+    #[error("Proposal not found: {proposal_id}")]
+    ProposalNotFound { proposal_id: String },
+
+    #[error("No block at height {height}")]
+    BlockNotFound { height: u64 },
+
+    #[error("Height {height} is above the node's tip at {tip}")]
+    BeyondTip { height: u64, tip: u64 },
+
+    #[error("No fee estimate for confirmation within {target_blocks} blocks")]
+    FeeEstimateUnavailable { target_blocks: u32 },
+
+    /// The node's wallet cannot fund a transaction it was asked to build.
+    #[error("{operation}: insufficient funds in the node's wallet")]
+    InsufficientFunds { operation: String },
+
+    /// The audit log does not replay from the start: an entry was altered, removed or
+    /// reordered at `line` of `file` (1-based).
+    #[error("{file}:{line}: audit chain broken: {problem}")]
+    AuditChainBroken {
+        file: String,
+        line: usize,
+        problem: String,
+    },
+
+    /// Data or a webhook of one network met a node on another (see [`crate::network`]).
+    #[error("{what} is for {expected}, but the node is on {actual}")]
+    NetworkMismatch {
+        what: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{operation}: failed after {attempts} attempts: {last}")]
+    RetriesExhausted {
+        operation: String,
+        attempts: u32,
+        /// The error of the last attempt.
+        #[source]
+        last: Box<GovernanceError>,
+    },
+}
+
+/// Whether an operation that failed is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retryability {
+    /// Transient: connection refused or reset, timeouts, server errors.
+    Retryable,
+    /// Retrying cannot succeed: version mismatch, authentication or validation rejections.
+    Fatal,
+    /// Not recognised; retried, but at most [`UNKNOWN_ERROR_MAX_ATTEMPTS`] times in a row.
+    Unknown,
+}
+
+/// Consecutive attempts allowed for an operation failing with [`Retryability::Unknown`].
+pub const UNKNOWN_ERROR_MAX_ATTEMPTS: u32 = 5;
+
+impl Retryability {
+    /// Whether to try again after `attempts` consecutive failures, the last one of this kind.
+    pub fn should_retry(self, attempts: u32) -> bool {
+        match self {
+            Retryability::Retryable => true,
+            Retryability::Fatal => false,
+            Retryability::Unknown => attempts < UNKNOWN_ERROR_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Classify an error by its message, for errors that only carry one.
+    pub fn of_message(message: &str) -> Self {
+        const FATAL: &[&str] = &[
+            "version mismatch",
+            "unsupported version",
+            "incompatible",
+            "unauthorized",
+            "authentication",
+            "permission denied",
+            "forbidden",
+            "unknown method",
+            "not supported",
+        ];
+        const RETRYABLE: &[&str] = &[
+            "connection refused",
+            "connection reset",
+            "connection aborted",
+            "broken pipe",
+            "timed out",
+            "timeout",
+            "temporarily unavailable",
+            "not connected",
+            "no such file",
+            "closed",
+        ];
+        let message = message.to_ascii_lowercase();
+        if FATAL.iter().any(|m| message.contains(m)) {
+            Retryability::Fatal
+        } else if RETRYABLE.iter().any(|m| message.contains(m)) {
+            Retryability::Retryable
+        } else {
+            Retryability::Unknown
+        }
+    }
+
+    /// Classify an HTTP response status: server errors, timeouts and rate limiting are
+    /// retryable, other client errors are not.
+    pub fn of_http_status(status: u16) -> Self {
+        match status {
+            408 | 425 | 429 => Retryability::Retryable,
+            400..=499 => Retryability::Fatal,
+            500..=599 => Retryability::Retryable,
+            _ => Retryability::Unknown,
+        }
+    }
+
+    /// Classify a failed file or socket operation. A missing file is not fatal: a socket or
+    /// directory may yet be created.
+    pub fn of_io(kind: std::io::ErrorKind) -> Self {
+        use std::io::ErrorKind;
+        match kind {
+            ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::TimedOut
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe => Retryability::Retryable,
+            ErrorKind::PermissionDenied
+            | ErrorKind::AlreadyExists
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+            | ErrorKind::Unsupported => Retryability::Fatal,
+            _ => Retryability::Unknown,
+        }
+    }
+
+    /// Classify a failed HTTP request.
+    pub fn of_http_error(error: &reqwest::Error) -> Self {
+        if let Some(status) = error.status() {
+            Self::of_http_status(status.as_u16())
+        } else if error.is_timeout() || error.is_connect() {
+            Retryability::Retryable
+        } else if error.is_builder() || error.is_redirect() {
+            Retryability::Fatal
+        } else {
+            Retryability::Unknown
+        }
+    }
+}
+
+/// Messages of IPC errors meaning the connection to the node is gone.
+const DISCONNECTED: &[&str] = &[
+    "connection refused",
+    "connection reset",
+    "connection aborted",
+    "connection closed",
+    "broken pipe",
+    "not connected",
+    "disconnected",
+    "channel closed",
+    "client closed",
+    "unexpected eof",
+    "no such file",
+];
+
+/// Numeric code in a node error message such as "code -5: no such transaction".
+fn error_code(message: &str) -> Option<i64> {
+    let (_, rest) = message.split_once("code")?;
+    let rest = rest.trim_start_matches([' ', ':', '=']);
+    let end = rest
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+        .map_or(rest.len(), |(i, _)| i);
+    rest[..end].parse().ok()
+}
+
+/// An error followed by the sources its display does not already show, separated by `": "`.
+pub struct Chain<'a>(pub &'a (dyn std::error::Error + 'static));
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut shown = self.0.to_string();
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            let text = error.to_string();
+            if !shown.contains(&text) {
+                shown.push_str(": ");
+                shown.push_str(&text);
+            }
+            source = error.source();
+        }
+        f.write_str(&shown)
+    }
+}
+
+impl GovernanceError {
+    /// Type an error the node or the IPC client answered `operation` with, keeping it as
+    /// the source.
+    pub fn from_ipc(operation: &str, error: impl Into<BoxError>) -> Self {
+        let error = error.into();
+        Self::classify(operation, &error.to_string(), Some(error))
+    }
+
+    /// Type an error the node or the IPC client answered `operation` with, by its message.
+    pub fn from_node(operation: &str, message: &str) -> Self {
+        Self::classify(operation, message, None)
+    }
+
+    fn classify(operation: &str, message: &str, source: Option<BoxError>) -> Self {
+        let lower = message.to_ascii_lowercase();
+        let operation = operation.to_string();
+        if DISCONNECTED.iter().any(|m| lower.contains(m)) {
+            GovernanceError::IpcDisconnected {
+                operation,
+                reason: message.to_string(),
+                source,
+            }
+        } else if lower.contains("timed out") || lower.contains("timeout") {
+            GovernanceError::IpcTimeout {
+                method: operation,
+                source,
+            }
+        } else if lower.contains("not found in any module") {
+            // The router's answer for a method nothing registered, not a missing object
+            GovernanceError::Unsupported {
+                operation,
+                message: message.to_string(),
+                source,
+            }
+        } else if lower.contains("not found") {
+            GovernanceError::NotFound {
+                operation,
+                what: message.to_string(),
+                source,
+            }
+        } else {
+            GovernanceError::NodeRejected {
+                operation,
+                code: error_code(&lower),
+                message: message.to_string(),
+                source,
+            }
+        }
+    }
+
+    /// This error and the causes behind it, for logs.
+    pub fn chain(&self) -> Chain<'_> {
+        Chain(self)
+    }
+
+    /// [`Self::Serialization`] for `operation`, for use with `map_err`.
+    pub fn serialization(operation: &str) -> impl FnOnce(serde_json::Error) -> Self + '_ {
+        move |source| GovernanceError::Serialization {
+            operation: operation.to_string(),
+            source,
+        }
+    }
+
+    /// [`Self::Database`] for `operation`, for use with `map_err`.
+    pub fn database<E: Into<BoxError>>(operation: impl fmt::Display) -> impl FnOnce(E) -> Self {
+        let operation = operation.to_string();
+        move |source| GovernanceError::Database {
+            operation,
+            source: source.into(),
+        }
+    }
+
+    /// [`Self::Encoding`] for `operation`, for use with `map_err`.
+    pub fn encoding(operation: &str) -> impl FnOnce(bincode::Error) -> Self + '_ {
+        move |source| GovernanceError::Encoding {
+            operation: operation.to_string(),
+            source,
+        }
+    }
+
+    /// [`Self::Io`] with `operation`, for use with `map_err`.
+    pub fn io(operation: impl std::fmt::Display) -> impl FnOnce(std::io::Error) -> Self {
+        let operation = operation.to_string();
+        move |source| GovernanceError::Io { operation, source }
+    }
+
+    /// [`Self::WebhookHttp`] for a response to `url` with the non-success `status`.
+    pub fn webhook_status(url: &str, status: reqwest::StatusCode) -> Self {
+        GovernanceError::WebhookHttp {
+            url: url.to_string(),
+            status: Some(status.as_u16()),
+            retryable: Retryability::of_http_status(status.as_u16()),
+            reason: format!("returned {}", status),
+            source: None,
+        }
+    }
+
+    /// [`Self::WebhookHttp`] for a request to `url` that failed.
+    pub fn webhook_request(url: &str, error: reqwest::Error) -> Self {
+        GovernanceError::WebhookHttp {
+            url: url.to_string(),
+            status: error.status().map(|s| s.as_u16()),
+            retryable: Retryability::of_http_error(&error),
+            reason: error.to_string(),
+            source: Some(error),
+        }
+    }
+
+    /// Whether trying again may succeed. Errors classified [`Retryability::Unknown`] are
+    /// not; loops that allow them a few attempts use [`Retryability::should_retry`].
+    pub fn is_retryable(&self) -> bool {
+        self.retryability() == Retryability::Retryable
+    }
+
+    pub fn retryability(&self) -> Retryability {
+        match self {
+            GovernanceError::WebhookHttp { retryable, .. } => *retryable,
+            GovernanceError::Io { source, .. } => Retryability::of_io(source.kind()),
+            GovernanceError::Timeout { .. }
+            | GovernanceError::NodeApiTimeout { .. }
+            | GovernanceError::IpcDisconnected { .. }
+            | GovernanceError::IpcTimeout { .. }
+            | GovernanceError::Paused { .. }
+            | GovernanceError::RetriesExhausted { .. } => Retryability::Retryable,
+            GovernanceError::NodeRejected { message, .. } => Retryability::of_message(message),
+            GovernanceError::Database { source, .. } => {
+                Retryability::of_message(&source.to_string())
+            }
+            GovernanceError::ConfigError(_)
+            | GovernanceError::HttpClient { .. }
+            | GovernanceError::Encoding { .. }
+            | GovernanceError::ValidationError { .. }
+            | GovernanceError::MessageTooLarge { .. }
+            | GovernanceError::NotIndexed { .. }
+            | GovernanceError::MempoolDisabled { .. }
+            | GovernanceError::ProposalNotFound { .. }
+            | GovernanceError::BlockNotFound { .. }
+            | GovernanceError::BeyondTip { .. }
+            | GovernanceError::FeeEstimateUnavailable { .. }
+            | GovernanceError::InsufficientFunds { .. }
+            | GovernanceError::AuditChainBroken { .. }
+            | GovernanceError::NetworkMismatch { .. }
+            | GovernanceError::Unsupported { .. }
+            | GovernanceError::NotFound { .. }
+            | GovernanceError::Serialization { .. }
+            | GovernanceError::ActionsDisabled { .. } => Retryability::Fatal,
+            GovernanceError::ModuleError(message)
+            | GovernanceError::WebhookError(message)
+            | GovernanceError::EconomicNodeError(message)
+            | GovernanceError::Storage(message) => Retryability::of_message(message),
+        }
+    }
 }
 
 impl From<GovernanceError> for blvm_node::module::traits::ModuleError {
     fn from(e: GovernanceError) -> Self {
-        Self::OperationError(e.to_string())
+        Self::OperationError(e.chain().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_governance_errors() {
+        let timeout = GovernanceError::Timeout {
+            operation: "get_block".to_string(),
+            after: std::time::Duration::from_secs(30),
+        };
+        assert_eq!(timeout.retryability(), Retryability::Retryable);
+        let node_timeout = GovernanceError::NodeApiTimeout {
+            method: "get_block".to_string(),
+            elapsed: std::time::Duration::from_secs(5),
+        };
+        assert_eq!(node_timeout.retryability(), Retryability::Retryable);
+        let too_large = GovernanceError::MessageTooLarge {
+            operation: "call_module".to_string(),
+            size: 2,
+            limit: 1,
+        };
+        assert_eq!(too_large.retryability(), Retryability::Fatal);
+        let config = GovernanceError::ConfigError("socket is writable by other users".into());
+        assert_eq!(config.retryability(), Retryability::Fatal);
+        let not_indexed = GovernanceError::NotIndexed {
+            operation: "get_transaction".to_string(),
+        };
+        assert_eq!(not_indexed.retryability(), Retryability::Fatal);
+        let not_found = GovernanceError::ProposalNotFound {
+            proposal_id: "7".to_string(),
+        };
+        assert_eq!(not_found.retryability(), Retryability::Fatal);
+        let disabled = GovernanceError::ActionsDisabled {
+            action: "veto".to_string(),
+        };
+        assert_eq!(disabled.retryability(), Retryability::Fatal);
+        // Worth trying again later, though not straight away
+        let exhausted = GovernanceError::RetriesExhausted {
+            operation: "get_block".to_string(),
+            attempts: 3,
+            last: Box::new(GovernanceError::from_node("get_block", "connection reset")),
+        };
+        assert_eq!(exhausted.retryability(), Retryability::Retryable);
+    }
+
+    #[test]
+    fn test_node_messages() {
+        let classify = |m: &str| GovernanceError::ModuleError(m.to_string()).retryability();
+        assert_eq!(
+            classify("get_block: Connection refused (os error 111)"),
+            Retryability::Retryable
+        );
+        assert_eq!(classify("IPC connection closed"), Retryability::Retryable);
+        assert_eq!(
+            classify("handshake failed: protocol version mismatch"),
+            Retryability::Fatal
+        );
+        assert_eq!(
+            classify("registration rejected: authentication failed"),
+            Retryability::Fatal
+        );
+        assert_eq!(classify("something odd"), Retryability::Unknown);
+    }
+
+    #[test]
+    fn test_node_errors_typed() {
+        let typed = |m: &str| GovernanceError::from_node("get_block", m);
+        for message in ["Connection reset by peer", "IPC client closed"] {
+            assert!(matches!(
+                typed(message),
+                GovernanceError::IpcDisconnected { .. }
+            ));
+            assert_eq!(typed(message).retryability(), Retryability::Retryable);
+        }
+        assert!(matches!(
+            typed("request timed out"),
+            GovernanceError::IpcTimeout { method, .. } if method == "get_block"
+        ));
+        assert!(matches!(
+            typed("block not found"),
+            GovernanceError::NotFound { .. }
+        ));
+        assert_eq!(typed("block not found").retryability(), Retryability::Fatal);
+        // The router's answer for a method no module serves
+        assert!(matches!(
+            typed("Method 'get_block' not found in any module"),
+            GovernanceError::Unsupported { .. }
+        ));
+
+        match typed("RPC error code -5: No such mempool transaction") {
+            GovernanceError::NodeRejected { code, message, .. } => {
+                assert_eq!(code, Some(-5));
+                assert_eq!(message, "RPC error code -5: No such mempool transaction");
+            }
+            other => panic!("expected NodeRejected, got {:?}", other),
+        }
+        // A rejection is as retryable as its message says
+        assert_eq!(typed("unknown method").retryability(), Retryability::Fatal);
+        assert_eq!(
+            typed("temporarily unavailable").retryability(),
+            Retryability::Retryable
+        );
+        assert_eq!(
+            typed("internal error").retryability(),
+            Retryability::Unknown
+        );
+        assert!(matches!(
+            typed("internal error"),
+            GovernanceError::NodeRejected { code: None, .. }
+        ));
+
+        // The IPC client's error is kept as the source
+        let typed = GovernanceError::from_ipc("get_block", std::io::Error::other("broken pipe"));
+        assert!(matches!(typed, GovernanceError::IpcDisconnected { .. }));
+        let source = std::error::Error::source(&typed).unwrap();
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+        assert!(std::error::Error::source(&GovernanceError::from_node("get_block", "x")).is_none());
+
+        let invalid = serde_json::from_str::<u64>("{")
+            .map_err(GovernanceError::serialization("get_proposal"))
+            .unwrap_err();
+        assert!(invalid
+            .to_string()
+            .starts_with("get_proposal: invalid JSON"));
+        assert_eq!(invalid.retryability(), Retryability::Fatal);
+    }
+
+    /// Name of `error`'s variant. No wildcard, so a new variant does not compile until it is
+    /// named here and given a row in `test_classification_table`.
+    fn variant(error: &GovernanceError) -> &'static str {
+        match error {
+            GovernanceError::ModuleError(_) => "ModuleError",
+            GovernanceError::WebhookError(_) => "WebhookError",
+            GovernanceError::WebhookHttp { .. } => "WebhookHttp",
+            GovernanceError::HttpClient { .. } => "HttpClient",
+            GovernanceError::EconomicNodeError(_) => "EconomicNodeError",
+            GovernanceError::ConfigError(_) => "ConfigError",
+            GovernanceError::Storage(_) => "Storage",
+            GovernanceError::Io { .. } => "Io",
+            GovernanceError::Database { .. } => "Database",
+            GovernanceError::Encoding { .. } => "Encoding",
+            GovernanceError::ValidationError { .. } => "ValidationError",
+            GovernanceError::Timeout { .. } => "Timeout",
+            GovernanceError::NodeApiTimeout { .. } => "NodeApiTimeout",
+            GovernanceError::IpcDisconnected { .. } => "IpcDisconnected",
+            GovernanceError::IpcTimeout { .. } => "IpcTimeout",
+            GovernanceError::NodeRejected { .. } => "NodeRejected",
+            GovernanceError::Unsupported { .. } => "Unsupported",
+            GovernanceError::NotFound { .. } => "NotFound",
+            GovernanceError::Serialization { .. } => "Serialization",
+            GovernanceError::MessageTooLarge { .. } => "MessageTooLarge",
+            GovernanceError::NotIndexed { .. } => "NotIndexed",
+            GovernanceError::MempoolDisabled { .. } => "MempoolDisabled",
+            GovernanceError::ActionsDisabled { .. } => "ActionsDisabled",
+            GovernanceError::Paused { .. } => "Paused",
+            GovernanceError::ProposalNotFound { .. } => "ProposalNotFound",
+            GovernanceError::BlockNotFound { .. } => "BlockNotFound",
+            GovernanceError::BeyondTip { .. } => "BeyondTip",
+            GovernanceError::FeeEstimateUnavailable { .. } => "FeeEstimateUnavailable",
+            GovernanceError::InsufficientFunds { .. } => "InsufficientFunds",
+            GovernanceError::AuditChainBroken { .. } => "AuditChainBroken",
+            GovernanceError::NetworkMismatch { .. } => "NetworkMismatch",
+            GovernanceError::RetriesExhausted { .. } => "RetriesExhausted",
+        }
+    }
+
+    const VARIANTS: usize = 32;
+
+    #[test]
+    fn test_classification_table() {
+        use std::io::ErrorKind;
+        use std::time::Duration;
+        use Retryability::*;
+        let op = || "get_block".to_string();
+        let io = |kind: ErrorKind| GovernanceError::Io {
+            operation: "/data/registry".to_string(),
+            source: kind.into(),
+        };
+        let webhook = |status: Option<u16>, retryable| GovernanceError::WebhookHttp {
+            url: "http://localhost/hook".to_string(),
+            status,
+            retryable,
+            reason: "failed".to_string(),
+            source: None,
+        };
+        let rejected = |message: &str| GovernanceError::NodeRejected {
+            operation: op(),
+            code: None,
+            message: message.to_string(),
+            source: None,
+        };
+        let table = [
+            (
+                GovernanceError::ModuleError("connection refused".into()),
+                Retryable,
+            ),
+            (
+                GovernanceError::ModuleError("something odd".into()),
+                Unknown,
+            ),
+            (GovernanceError::WebhookError("unauthorized".into()), Fatal),
+            (
+                webhook(Some(503), Retryability::of_http_status(503)),
+                Retryable,
+            ),
+            (
+                webhook(Some(429), Retryability::of_http_status(429)),
+                Retryable,
+            ),
+            (webhook(Some(404), Retryability::of_http_status(404)), Fatal),
+            (webhook(None, Unknown), Unknown),
+            (
+                GovernanceError::HttpClient {
+                    source: invalid_request(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::EconomicNodeError("timed out".into()),
+                Retryable,
+            ),
+            (
+                GovernanceError::ConfigError("webhook_url is not set".into()),
+                Fatal,
+            ),
+            (
+                GovernanceError::Storage("insert: something odd".into()),
+                Unknown,
+            ),
+            (io(ErrorKind::WouldBlock), Retryable),
+            (io(ErrorKind::Interrupted), Retryable),
+            (io(ErrorKind::PermissionDenied), Fatal),
+            // A socket or file that may yet appear
+            (io(ErrorKind::NotFound), Unknown),
+            (io(ErrorKind::Other), Unknown),
+            (GovernanceError::database("insert")("timed out"), Retryable),
+            (GovernanceError::database("get")("something odd"), Unknown),
+            (
+                bincode::deserialize::<u64>(&[])
+                    .map_err(GovernanceError::encoding("deserialize"))
+                    .unwrap_err(),
+                Fatal,
+            ),
+            (
+                GovernanceError::ValidationError {
+                    field: "node_id".into(),
+                    reason: "not hex".into(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::Timeout {
+                    operation: op(),
+                    after: Duration::from_secs(30),
+                },
+                Retryable,
+            ),
+            (
+                GovernanceError::NodeApiTimeout {
+                    method: op(),
+                    elapsed: Duration::from_secs(5),
+                },
+                Retryable,
+            ),
+            (
+                GovernanceError::IpcDisconnected {
+                    operation: op(),
+                    reason: "client closed".into(),
+                    source: None,
+                },
+                Retryable,
+            ),
+            (
+                GovernanceError::IpcTimeout {
+                    method: op(),
+                    source: None,
+                },
+                Retryable,
+            ),
+            (rejected("unknown method"), Fatal),
+            (rejected("temporarily unavailable"), Retryable),
+            (rejected("internal error"), Unknown),
+            (
+                GovernanceError::Unsupported {
+                    operation: op(),
+                    message: "Method 'get_block' not found in any module".into(),
+                    source: None,
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::NotFound {
+                    operation: op(),
+                    what: "block".into(),
+                    source: None,
+                },
+                Fatal,
+            ),
+            (
+                serde_json::from_str::<u64>("{")
+                    .map_err(GovernanceError::serialization("get_proposal"))
+                    .unwrap_err(),
+                Fatal,
+            ),
+            (
+                GovernanceError::MessageTooLarge {
+                    operation: op(),
+                    size: 2,
+                    limit: 1,
+                },
+                Fatal,
+            ),
+            (GovernanceError::NotIndexed { operation: op() }, Fatal),
+            (GovernanceError::MempoolDisabled { operation: op() }, Fatal),
+            (
+                GovernanceError::ActionsDisabled {
+                    action: "veto".into(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::Paused {
+                    action: "veto".into(),
+                },
+                Retryable,
+            ),
+            (
+                GovernanceError::ProposalNotFound {
+                    proposal_id: "7".into(),
+                },
+                Fatal,
+            ),
+            (GovernanceError::BlockNotFound { height: 7 }, Fatal),
+            (GovernanceError::BeyondTip { height: 8, tip: 7 }, Fatal),
+            (
+                GovernanceError::FeeEstimateUnavailable { target_blocks: 6 },
+                Fatal,
+            ),
+            (
+                GovernanceError::InsufficientFunds {
+                    operation: "fund_op_return".into(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::AuditChainBroken {
+                    file: "audit-000000000000.jsonl".into(),
+                    line: 3,
+                    problem: "hash mismatch".into(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::NetworkMismatch {
+                    what: "data directory /data".into(),
+                    expected: "mainnet".into(),
+                    actual: "testnet".into(),
+                },
+                Fatal,
+            ),
+            (
+                GovernanceError::RetriesExhausted {
+                    operation: op(),
+                    attempts: 3,
+                    last: Box::new(rejected("connection reset")),
+                },
+                Retryable,
+            ),
+        ];
+        for (error, expected) in &table {
+            assert_eq!(
+                error.retryability(),
+                *expected,
+                "{}: {}",
+                variant(error),
+                error
+            );
+            assert_eq!(error.is_retryable(), *expected == Retryable, "{}", error);
+        }
+        let covered: std::collections::HashSet<_> = table.iter().map(|(e, _)| variant(e)).collect();
+        assert_eq!(covered.len(), VARIANTS, "every variant needs a row");
+    }
+
+    /// A request reqwest refuses to build, for an error to hold.
+    fn invalid_request() -> reqwest::Error {
+        reqwest::Client::new().get("not a url").build().unwrap_err()
+    }
+
+    #[derive(Debug)]
+    struct Cause(&'static str, Option<Box<Cause>>);
+
+    impl fmt::Display for Cause {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl std::error::Error for Cause {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            self.1.as_deref().map(|c| c as _)
+        }
+    }
+
+    #[test]
+    fn test_chain_shows_every_cause_once() {
+        // A display that hides the cause of its cause, as reqwest's does
+        let request = Cause(
+            "error sending request",
+            Some(Box::new(Cause("dns error: no such host", None))),
+        );
+        let error = GovernanceError::Io {
+            operation: "/data/registry".to_string(),
+            source: std::io::Error::other(request),
+        };
+        assert_eq!(error.to_string(), "/data/registry: error sending request");
+        assert_eq!(
+            error.chain().to_string(),
+            "/data/registry: error sending request: dns error: no such host"
+        );
+
+        // Sources already in the display are not repeated
+        let exhausted = GovernanceError::RetriesExhausted {
+            operation: "get_block".to_string(),
+            attempts: 3,
+            last: Box::new(GovernanceError::from_ipc(
+                "get_block",
+                std::io::Error::other("connection reset"),
+            )),
+        };
+        assert_eq!(exhausted.chain().to_string(), exhausted.to_string());
+        let module: blvm_node::module::traits::ModuleError = exhausted.into();
+        assert!(module.to_string().contains("connection reset"));
+    }
+
+    #[test]
+    fn test_typed_constructors() {
+        let io = std::fs::read("/nonexistent/blvm-governance")
+            .map_err(GovernanceError::io("/nonexistent/blvm-governance"))
+            .unwrap_err();
+        assert!(io.to_string().starts_with("/nonexistent/blvm-governance: "));
+        assert!(!io.is_retryable());
+
+        let status = GovernanceError::webhook_status(
+            "http://localhost/hook",
+            reqwest::StatusCode::SERVICE_UNAVAILABLE,
+        );
+        assert_eq!(
+            status.to_string(),
+            "http://localhost/hook: returned 503 Service Unavailable"
+        );
+        assert!(matches!(
+            status,
+            GovernanceError::WebhookHttp {
+                status: Some(503),
+                ..
+            }
+        ));
+        assert!(status.is_retryable());
+
+        let request = GovernanceError::webhook_request("http://localhost/hook", invalid_request());
+        let source = std::error::Error::source(&request).unwrap();
+        assert!(source
+            .downcast_ref::<reqwest::Error>()
+            .unwrap()
+            .is_builder());
+        assert_eq!(request.retryability(), Retryability::Fatal);
+    }
+
+    #[test]
+    fn test_http_statuses() {
+        assert_eq!(Retryability::of_http_status(503), Retryability::Retryable);
+        assert_eq!(Retryability::of_http_status(429), Retryability::Retryable);
+        assert_eq!(Retryability::of_http_status(401), Retryability::Fatal);
+        assert_eq!(Retryability::of_http_status(404), Retryability::Fatal);
+    }
+
+    #[test]
+    fn test_unknown_errors_are_capped() {
+        assert!(Retryability::Unknown.should_retry(UNKNOWN_ERROR_MAX_ATTEMPTS - 1));
+        assert!(!Retryability::Unknown.should_retry(UNKNOWN_ERROR_MAX_ATTEMPTS));
+        assert!(Retryability::Retryable.should_retry(u32::MAX));
+        assert!(!Retryability::Fatal.should_retry(1));
     }
 }
@@ -0,0 +1,466 @@
+//! Error reports sent to the node
+//!
+//! Significant errors are reported to the node as well as logged, so the node operator sees
+//! them without reading the module's log. An [`ErrorReport`] carries a stable [`ErrorCode`]
+//! (a number and a name), a severity, the latest message, a context map saying where it
+//! happened (the webhook URL, the handler) and how often it has occurred.
+//!
+//! Occurrences with the same code and context are one report: a repeat only updates its
+//! message, count and last time. The [`ErrorReporter`] sends the reports that are due every
+//! `[governance.error_reports] batch_secs`, most severe first and at most `max_batch` in one
+//! `call_module(None, "module_error_report", ..)`; the rest wait for the next batch. A report
+//! that keeps recurring is sent again at most once every `repeat_secs`, with its updated
+//! count, and not at all while it does not recur. Reports made while disconnected are sent
+//! once the module reconnects. The most recent are also in the status report and served on
+//! the health endpoint's `/errors` (see [`crate::health`]), and every occurrence is counted
+//! for error rate alerts (see [`crate::alert`]).
+//!
+//! Codes are part of the module's interface: once released, a code keeps its number and
+//! name, and a retired code's number is never given to another error.
+
+use crate::alert::AlertMonitor;
+use crate::config::ErrorReportConfig;
+use crate::error::GovernanceError;
+use crate::node_api::with_timeout;
+use blvm_node::module::traits::NodeAPI;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+
+/// Distinct reports kept; beyond this the one seen longest ago is forgotten.
+pub const MAX_TRACKED: usize = 256;
+
+/// Longest wait for the node to take a batch.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stable codes of reported errors, by range: 1xxx webhook, 2xxx storage, 3xxx node, 4xxx
+/// event handling. New codes take the next free number in their range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u16)]
+pub enum ErrorCode {
+    /// A webhook delivery failed after its retries, or with an error retrying cannot fix.
+    WebhookUndeliverable = 1001,
+    /// A webhook delivery was blocked because the webhook is for another network than the
+    /// node's (see [`crate::network`]).
+    WebhookNetworkMismatch = 1002,
+    /// The registry store could not be read; the module cannot start.
+    RegistryUnreadable = 2001,
+    /// The event checkpoint could not be written; events may be processed again after a
+    /// restart.
+    CheckpointWriteFailed = 2002,
+    /// The registry could not be written; changes since the last write are lost on a
+    /// restart.
+    RegistryWriteFailed = 2003,
+    /// The node rejected a request in a way retrying cannot fix, such as a protocol version
+    /// mismatch.
+    NodeRejected = 3001,
+    /// A governance state anchor could not be broadcast or was not confirmed in time.
+    AnchorFailed = 3002,
+    /// An event handler failed to handle an event.
+    HandlerFailed = 4001,
+    /// An event handler panicked and gets no more events until a restart.
+    HandlerPanicked = 4002,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 9] = [
+        ErrorCode::WebhookUndeliverable,
+        ErrorCode::WebhookNetworkMismatch,
+        ErrorCode::RegistryUnreadable,
+        ErrorCode::CheckpointWriteFailed,
+        ErrorCode::RegistryWriteFailed,
+        ErrorCode::NodeRejected,
+        ErrorCode::AnchorFailed,
+        ErrorCode::HandlerFailed,
+        ErrorCode::HandlerPanicked,
+    ];
+
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::WebhookUndeliverable => "webhook_undeliverable",
+            ErrorCode::WebhookNetworkMismatch => "webhook_network_mismatch",
+            ErrorCode::RegistryUnreadable => "registry_unreadable",
+            ErrorCode::CheckpointWriteFailed => "checkpoint_write_failed",
+            ErrorCode::RegistryWriteFailed => "registry_write_failed",
+            ErrorCode::NodeRejected => "node_rejected",
+            ErrorCode::AnchorFailed => "anchor_failed",
+            ErrorCode::HandlerFailed => "handler_failed",
+            ErrorCode::HandlerPanicked => "handler_panicked",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            ErrorCode::HandlerFailed => Severity::Warning,
+            ErrorCode::WebhookUndeliverable
+            | ErrorCode::WebhookNetworkMismatch
+            | ErrorCode::CheckpointWriteFailed
+            | ErrorCode::RegistryWriteFailed
+            | ErrorCode::NodeRejected
+            | ErrorCode::AnchorFailed => Severity::Error,
+            ErrorCode::RegistryUnreadable | ErrorCode::HandlerPanicked => Severity::Critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+    /// The module cannot do its job until an operator steps in.
+    Critical,
+}
+
+/// One reported error, as sent to the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// Number of the [`ErrorCode`].
+    pub code: u16,
+    /// Name of the [`ErrorCode`].
+    pub name: String,
+    pub severity: Severity,
+    /// Message of the latest occurrence.
+    pub message: String,
+    /// Where it happened. Occurrences with the same code and context are one report.
+    pub context: BTreeMap<String, String>,
+    /// Occurrences since the module started.
+    pub count: u64,
+    /// Unix seconds.
+    pub first_seen: u64,
+    /// Unix seconds.
+    pub last_seen: u64,
+}
+
+impl ErrorReport {
+    /// One occurrence of `code`; the reporter sets when it was seen.
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code.code(),
+            name: code.as_str().to_string(),
+            severity: code.severity(),
+            message: message.into(),
+            context: BTreeMap::new(),
+            count: 1,
+            first_seen: 0,
+            last_seen: 0,
+        }
+    }
+
+    pub fn with_context(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.context.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// What makes two occurrences the same report.
+type ReportKey = (u16, BTreeMap<String, String>);
+
+#[derive(Debug)]
+struct Tracked {
+    report: ErrorReport,
+    /// Count when last sent to the node.
+    sent_count: u64,
+    /// When it was last sent, in Unix seconds.
+    sent_at: Option<u64>,
+}
+
+impl Tracked {
+    fn is_due(&self, now: u64, repeat_secs: u64) -> bool {
+        self.report.count > self.sent_count
+            && self
+                .sent_at
+                .is_none_or(|at| now >= at.saturating_add(repeat_secs))
+    }
+}
+
+/// Collects error reports and sends them to the node. Kept across connections.
+#[derive(Debug)]
+pub struct ErrorReporter {
+    config: ErrorReportConfig,
+    reports: Mutex<HashMap<ReportKey, Tracked>>,
+    /// Counts occurrences for error rate alerts.
+    alerts: Option<Arc<AlertMonitor>>,
+}
+
+impl Default for ErrorReporter {
+    fn default() -> Self {
+        Self::new(ErrorReportConfig::default())
+    }
+}
+
+impl ErrorReporter {
+    pub fn new(config: ErrorReportConfig) -> Self {
+        Self {
+            config,
+            reports: Mutex::default(),
+            alerts: None,
+        }
+    }
+
+    /// Count every occurrence in `alerts`, by code name.
+    pub fn with_alert_monitor(mut self, alerts: Arc<AlertMonitor>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Record an occurrence of `report` now.
+    pub fn report(&self, report: ErrorReport) {
+        self.record(report, crate::clock::unix_now());
+    }
+
+    /// Record an occurrence of `report` at `now` (Unix seconds).
+    pub fn record(&self, report: ErrorReport, now: u64) {
+        if let Some(alerts) = &self.alerts {
+            alerts.observe(&report.name, &report.message, now);
+        }
+        let key = (report.code, report.context.clone());
+        let mut reports = self.reports.lock().unwrap();
+        if let Some(tracked) = reports.get_mut(&key) {
+            tracked.report.message = report.message;
+            tracked.report.count += 1;
+            tracked.report.last_seen = now;
+            return;
+        }
+        if reports.len() >= MAX_TRACKED {
+            let oldest = reports
+                .iter()
+                .min_by_key(|(_, t)| t.report.last_seen)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                reports.remove(&oldest);
+            }
+        }
+        let report = ErrorReport {
+            count: 1,
+            first_seen: now,
+            last_seen: now,
+            ..report
+        };
+        reports.insert(
+            key,
+            Tracked {
+                report,
+                sent_count: 0,
+                sent_at: None,
+            },
+        );
+    }
+
+    /// Reports due to be sent at `now`, most severe first, then oldest first; at most
+    /// `max_batch`.
+    pub fn due(&self, now: u64) -> Vec<ErrorReport> {
+        let reports = self.reports.lock().unwrap();
+        let mut due: Vec<ErrorReport> = reports
+            .values()
+            .filter(|t| t.is_due(now, self.config.repeat_secs))
+            .map(|t| t.report.clone())
+            .collect();
+        due.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.first_seen.cmp(&b.first_seen))
+        });
+        due.truncate(self.config.max_batch.max(1));
+        due
+    }
+
+    /// Record that `batch`, taken from [`Self::due`], was sent at `now`. Occurrences since
+    /// are still due.
+    pub fn mark_sent(&self, batch: &[ErrorReport], now: u64) {
+        let mut reports = self.reports.lock().unwrap();
+        for report in batch {
+            if let Some(tracked) = reports.get_mut(&(report.code, report.context.clone())) {
+                tracked.sent_count = report.count;
+                tracked.sent_at = Some(now);
+            }
+        }
+    }
+
+    /// Send the reports due at `now` to the node. Returns how many were sent; on failure they
+    /// stay due.
+    pub async fn send(&self, node_api: &dyn NodeAPI, now: u64) -> Result<usize, GovernanceError> {
+        let batch = self.due(now);
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let payload = serde_json::to_vec(&serde_json::json!({ "reports": batch }))
+            .map_err(GovernanceError::serialization("module_error_report"))?;
+        with_timeout(
+            "module_error_report",
+            SEND_TIMEOUT,
+            node_api.call_module(None, "module_error_report", payload),
+        )
+        .await?;
+        self.mark_sent(&batch, now);
+        Ok(batch.len())
+    }
+
+    /// The most recent reports, up to `recent`, latest first.
+    pub fn recent(&self) -> Vec<ErrorReport> {
+        let mut recent: Vec<ErrorReport> = self
+            .reports
+            .lock()
+            .unwrap()
+            .values()
+            .map(|t| t.report.clone())
+            .collect();
+        recent.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then(a.code.cmp(&b.code)));
+        recent.truncate(self.config.recent);
+        recent
+    }
+
+    /// Occurrences of the tracked reports by code name.
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        let mut counts = BTreeMap::new();
+        for tracked in self.reports.lock().unwrap().values() {
+            *counts.entry(tracked.report.name.clone()).or_default() += tracked.report.count;
+        }
+        counts
+    }
+
+    /// Send due reports to the node every `batch_secs` until the task is aborted; `None`
+    /// if reporting is disabled.
+    pub fn spawn(
+        self: &Arc<Self>,
+        node_api: Arc<dyn NodeAPI>,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+        let reporter = Arc::clone(self);
+        let interval = Duration::from_secs(self.config.batch_secs.max(1));
+        Some(tokio::spawn(async move {
+            loop {
+                // Not logged as a warning: reaching the node is reported on its own
+                if let Err(e) = reporter
+                    .send(node_api.as_ref(), crate::clock::unix_now())
+                    .await
+                {
+                    debug!("Failed to send error reports to the node: {}", e);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reporter(max_batch: usize) -> ErrorReporter {
+        ErrorReporter::new(ErrorReportConfig {
+            max_batch,
+            repeat_secs: 300,
+            ..Default::default()
+        })
+    }
+
+    fn webhook_failure(url: &str, message: &str) -> ErrorReport {
+        ErrorReport::new(ErrorCode::WebhookUndeliverable, message).with_context("url", url)
+    }
+
+    #[test]
+    fn test_codes_are_unique_and_stable() {
+        let mut numbers: Vec<u16> = ErrorCode::ALL.iter().map(|c| c.code()).collect();
+        let mut names: Vec<&str> = ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(numbers.len(), ErrorCode::ALL.len());
+        assert_eq!(names.len(), ErrorCode::ALL.len());
+        // Released codes; changing one breaks whoever reads the reports
+        assert_eq!(ErrorCode::WebhookUndeliverable.code(), 1001);
+        assert_eq!(ErrorCode::WebhookNetworkMismatch.code(), 1002);
+        assert_eq!(ErrorCode::RegistryUnreadable.code(), 2001);
+        assert_eq!(ErrorCode::CheckpointWriteFailed.code(), 2002);
+        assert_eq!(ErrorCode::RegistryWriteFailed.code(), 2003);
+        assert_eq!(ErrorCode::NodeRejected.code(), 3001);
+        assert_eq!(ErrorCode::HandlerFailed.code(), 4001);
+        assert_eq!(ErrorCode::HandlerPanicked.code(), 4002);
+    }
+
+    #[test]
+    fn test_repeats_are_deduplicated() {
+        let errors = reporter(20);
+        for i in 0..100 {
+            errors.record(
+                webhook_failure("http://a", &format!("attempt {}", i)),
+                1000 + i,
+            );
+        }
+        errors.record(webhook_failure("http://b", "refused"), 1050);
+        let due = errors.due(1100);
+        assert_eq!(due.len(), 2);
+        let a = due.iter().find(|r| r.context["url"] == "http://a").unwrap();
+        assert_eq!(a.count, 100);
+        assert_eq!(a.message, "attempt 99");
+        assert_eq!((a.first_seen, a.last_seen), (1000, 1099));
+        assert_eq!(errors.counts()["webhook_undeliverable"], 101);
+    }
+
+    #[test]
+    fn test_repeating_error_is_rate_limited() {
+        let errors = reporter(20);
+        errors.record(webhook_failure("http://a", "503"), 0);
+        let batch = errors.due(0);
+        assert_eq!(batch.len(), 1);
+        errors.mark_sent(&batch, 0);
+
+        // Keeps failing: held back until `repeat_secs` after the last send
+        for now in 1..300 {
+            errors.record(webhook_failure("http://a", "503"), now);
+            assert!(errors.due(now).is_empty());
+        }
+        let batch = errors.due(300);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].count, 300);
+        errors.mark_sent(&batch, 300);
+
+        // Not sent again while it does not recur
+        assert!(errors.due(10_000).is_empty());
+    }
+
+    #[test]
+    fn test_batches_most_severe_first() {
+        let errors = reporter(3);
+        for i in 0..5 {
+            errors.record(
+                ErrorReport::new(ErrorCode::HandlerFailed, "failed")
+                    .with_context("handler", format!("h{}", i)),
+                i,
+            );
+        }
+        errors.record(
+            ErrorReport::new(ErrorCode::HandlerPanicked, "panicked").with_context("handler", "p"),
+            10,
+        );
+        let batch = errors.due(10);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0].severity, Severity::Critical);
+        assert_eq!(batch[1].context["handler"], "h0");
+        errors.mark_sent(&batch, 10);
+        // The rest follow in the next batch
+        assert_eq!(errors.due(10).len(), 3);
+    }
+
+    #[test]
+    fn test_tracked_reports_are_bounded() {
+        let errors = reporter(20);
+        for i in 0..MAX_TRACKED as u64 + 10 {
+            errors.record(webhook_failure(&format!("http://{}", i), "failed"), i);
+        }
+        let counts = errors.counts();
+        assert_eq!(counts["webhook_undeliverable"], MAX_TRACKED as u64);
+        assert!(errors.recent().len() <= 20);
+        assert_eq!(errors.recent()[0].last_seen, MAX_TRACKED as u64 + 9);
+    }
+}
@@ -0,0 +1,515 @@
+//! Escalating alerts as veto tallies approach the threshold
+//!
+//! `veto_threshold_crossed` comes when a proposal is already blocked, too late for its
+//! authors to talk to the vetoing parties. With `[governance.veto_escalation] bands` set,
+//! e.g. `[50.0, 75.0, 90.0]`, each band is a share of the veto threshold: as a proposal's
+//! veto tally, recomputed by the registry whenever it changes (see
+//! [`crate::economic_nodes::tally`]), enters a band, `veto_escalation` is sent to the webhook;
+//! as it falls back below one, through revocations or weights changing, `veto_deescalation`.
+//! Each message has the band, the tally's share of the threshold (`ratio_percent`), its
+//! weights, the `contributors` largest vetoing nodes and the blocks remaining until the
+//! proposal's voting deadline, if its tier has a window (see [`crate::deadlines`]). With
+//! `redact_node_ids`, the contributors are named by type and weight only.
+//!
+//! Each band sends each message at most once per proposal: the bands a tally is in and the
+//! messages sent are stored in the module database before the message is queued, so a restart
+//! repeats none, and a tally moving back and forth across a band sends nothing more. A tally
+//! entering or leaving several bands at once sends one message, for the highest band entered
+//! or the lowest left. A share within a billionth of a percent below a band counts as in it,
+//! so tallies that land on a band exactly are not missed to rounding.
+
+use crate::config::VetoEscalationConfig;
+use crate::economic_nodes::tally::VetoTally;
+use crate::error::GovernanceError;
+use crate::proposals::ProposalStore;
+use crate::schema::{self, VetoBandCrossing};
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::{info, warn};
+
+const ESCALATION_TREE: &str = "veto_escalation";
+
+const BANDS_KEY: &[u8] = b"bands";
+
+/// Event type of the message sent as a tally enters a band.
+pub const ESCALATION_EVENT: &str = "veto_escalation";
+
+/// Event type of the message sent as a tally falls back below a band.
+pub const DEESCALATION_EVENT: &str = "veto_deescalation";
+
+/// Percentage points below a band a share still counts as in it.
+const TOLERANCE: f64 = 1e-9;
+
+/// The vetoing weight of `tally` as a percentage of its threshold.
+pub fn ratio_percent(tally: &VetoTally) -> f64 {
+    if tally.threshold_percent <= 0.0 {
+        0.0
+    } else {
+        tally.veto_percent() / tally.threshold_percent * 100.0
+    }
+}
+
+/// Whether a share of `ratio` percent of the threshold is in the band at `band` percent.
+pub fn reaches(ratio: f64, band: f64) -> bool {
+    ratio + TOLERANCE >= band
+}
+
+/// A band a tally entered or left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossing {
+    Escalation { band: f64 },
+    Deescalation { band: f64 },
+}
+
+impl Crossing {
+    /// Event type of the webhook message.
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::Escalation { .. } => ESCALATION_EVENT,
+            Self::Deescalation { .. } => DEESCALATION_EVENT,
+        }
+    }
+
+    pub fn band(self) -> f64 {
+        match self {
+            Self::Escalation { band } | Self::Deescalation { band } => band,
+        }
+    }
+}
+
+/// The bands of one proposal's tally.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bands {
+    /// Bands the tally was in when last observed.
+    pub inside: Vec<f64>,
+    /// Bands whose `veto_escalation` was sent.
+    pub escalated: Vec<f64>,
+    /// Bands whose `veto_deescalation` was sent.
+    pub deescalated: Vec<f64>,
+}
+
+impl Bands {
+    /// Move to a share of `ratio` percent of the threshold under `bands`, returning the
+    /// message due, if any, and recording it as sent. Bands no longer configured are dropped
+    /// without one.
+    pub fn advance(&mut self, bands: &[f64], ratio: f64) -> Option<Crossing> {
+        let entered: Vec<f64> = bands
+            .iter()
+            .copied()
+            .filter(|band| reaches(ratio, *band) && !self.inside.contains(band))
+            .collect();
+        let left: Vec<f64> = self
+            .inside
+            .iter()
+            .copied()
+            .filter(|band| bands.contains(band) && !reaches(ratio, *band))
+            .collect();
+        self.inside
+            .retain(|band| bands.contains(band) && reaches(ratio, *band));
+        self.inside.extend(&entered);
+        if !entered.is_empty() {
+            let due = highest(entered.iter().filter(|b| !self.escalated.contains(b)));
+            for band in entered {
+                if !self.escalated.contains(&band) {
+                    self.escalated.push(band);
+                }
+            }
+            return due.map(|band| Crossing::Escalation { band });
+        }
+        let due = lowest(left.iter().filter(|b| !self.deescalated.contains(b)));
+        for band in left {
+            if !self.deescalated.contains(&band) {
+                self.deescalated.push(band);
+            }
+        }
+        due.map(|band| Crossing::Deescalation { band })
+    }
+}
+
+fn highest<'a>(bands: impl Iterator<Item = &'a f64>) -> Option<f64> {
+    bands.copied().reduce(f64::max)
+}
+
+fn lowest<'a>(bands: impl Iterator<Item = &'a f64>) -> Option<f64> {
+    bands.copied().reduce(f64::min)
+}
+
+/// A vetoing node's part of a tally.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Contributor {
+    /// Hex node id; left out with `redact_node_ids`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    pub node_type: String,
+    /// Weight counted in the tally.
+    pub weight: f64,
+    /// Share of the vetoing weight, in percent.
+    pub share_percent: f64,
+}
+
+/// The `count` largest of `contributors` to a vetoing weight of `vetoing_weight`, largest
+/// first, their ids left out if `redact`.
+pub fn largest(
+    mut contributors: Vec<Contributor>,
+    vetoing_weight: f64,
+    count: usize,
+    redact: bool,
+) -> Vec<Contributor> {
+    contributors.sort_by(|a, b| {
+        b.weight
+            .total_cmp(&a.weight)
+            .then_with(|| a.node_id.cmp(&b.node_id))
+    });
+    contributors.truncate(count);
+    for contributor in &mut contributors {
+        contributor.share_percent = if vetoing_weight > 0.0 {
+            contributor.weight / vetoing_weight * 100.0
+        } else {
+            0.0
+        };
+        if redact {
+            contributor.node_id = None;
+        }
+    }
+    contributors
+}
+
+/// A message recorded as an intent, awaiting delivery.
+struct Queued {
+    event_type: &'static str,
+    data: serde_json::Value,
+    intent: Option<u64>,
+}
+
+/// Follows veto tallies through the escalation bands.
+pub struct VetoEscalation {
+    config: VetoEscalationConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    /// Bands of each proposal whose tally has entered one, as stored.
+    bands: Mutex<BTreeMap<String, Bands>>,
+    /// Where deadlines are read from; set once the store exists, as it is created after the
+    /// registry.
+    proposals: OnceLock<Arc<ProposalStore>>,
+    /// Where messages are sent.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    queued: Mutex<Vec<Queued>>,
+    notify: tokio::sync::Notify,
+}
+
+impl VetoEscalation {
+    /// Follow tallies through the bands of `config`, with the state stored in `db`.
+    pub fn open(
+        config: VetoEscalationConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Self, GovernanceError> {
+        let bands = Self::load_from(&db)?;
+        Ok(Self {
+            config,
+            db,
+            bands: Mutex::new(bands),
+            proposals: OnceLock::new(),
+            webhook: None,
+            queued: Mutex::new(Vec::new()),
+            notify: tokio::sync::Notify::new(),
+        })
+    }
+
+    /// Send messages to `webhook`.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Read voting deadlines from `proposals`; only the first store set is used.
+    pub fn set_proposals(&self, proposals: Arc<ProposalStore>) {
+        let _ = self.proposals.set(proposals);
+    }
+
+    /// Whether any band is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.config.bands.is_empty()
+    }
+
+    /// The bands of each proposal followed, by proposal id.
+    pub fn bands(&self) -> BTreeMap<String, Bands> {
+        self.bands.lock().unwrap().clone()
+    }
+
+    /// Proposals whose tally is in a band, to follow until it falls back below.
+    pub fn tracked(&self) -> Vec<String> {
+        self.bands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, bands)| !bands.inside.is_empty())
+            .map(|(proposal_id, _)| proposal_id.clone())
+            .collect()
+    }
+
+    /// Follow `tally`, computed at `height`, and queue the message due, with the vetoing
+    /// nodes from `contributors`, if it entered or left a band.
+    pub fn observe(
+        &self,
+        tally: &VetoTally,
+        height: u64,
+        contributors: impl FnOnce() -> Vec<Contributor>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let ratio = ratio_percent(tally);
+        let crossing = {
+            let mut stored = self.bands.lock().unwrap();
+            let before = stored.get(&tally.proposal_id).cloned();
+            let mut bands = before.clone().unwrap_or_default();
+            let crossing = bands.advance(&self.config.bands, ratio);
+            if before.as_ref().unwrap_or(&Bands::default()) == &bands {
+                return;
+            }
+            stored.insert(tally.proposal_id.clone(), bands);
+            if let Err(e) = self.save(&stored) {
+                warn!(
+                    "Failed to store the escalation bands of {}: {}",
+                    tally.proposal_id,
+                    e.chain()
+                );
+                match before {
+                    Some(before) => stored.insert(tally.proposal_id.clone(), before),
+                    None => stored.remove(&tally.proposal_id),
+                };
+                return;
+            }
+            crossing
+        };
+        let Some(crossing) = crossing else {
+            return;
+        };
+        info!(
+            "Veto tally on {} {} the {}% band ({:.2}% of the threshold)",
+            tally.proposal_id,
+            match crossing {
+                Crossing::Escalation { .. } => "entered",
+                Crossing::Deescalation { .. } => "fell below",
+            },
+            crossing.band(),
+            ratio
+        );
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        let message = self.message(crossing, tally, ratio, height, contributors());
+        let data = match schema::to_data(&message) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to serialize the escalation message: {}", e.chain());
+                return;
+            }
+        };
+        let intent = webhook.record_intent(crossing.event_type(), &data);
+        self.queued.lock().unwrap().push(Queued {
+            event_type: crossing.event_type(),
+            data,
+            intent,
+        });
+        self.notify.notify_one();
+    }
+
+    /// The message for `crossing` of `tally`.
+    fn message(
+        &self,
+        crossing: Crossing,
+        tally: &VetoTally,
+        ratio: f64,
+        height: u64,
+        contributors: Vec<Contributor>,
+    ) -> VetoBandCrossing {
+        let deadline = self.proposals.get().and_then(|store| {
+            let proposal = store.proposal(&tally.proposal_id).ok().flatten()?;
+            store.deadline(&proposal)
+        });
+        VetoBandCrossing {
+            proposal_id: tally.proposal_id.clone(),
+            band_percent: crossing.band(),
+            ratio_percent: ratio,
+            veto_percent: tally.veto_percent(),
+            threshold_percent: tally.threshold_percent,
+            vetoing_weight: tally.vetoing_weight,
+            total_weight: tally.total_weight,
+            contributors: largest(
+                contributors,
+                tally.vetoing_weight,
+                self.config.contributors,
+                self.config.redact_node_ids,
+            ),
+            height,
+            deadline_height: deadline,
+            blocks_remaining: deadline.map(|d| d.saturating_sub(height)),
+        }
+    }
+
+    /// Send the queued messages. Returns the number sent.
+    pub async fn flush(&self, node_api: &dyn NodeAPI) -> usize {
+        let Some(webhook) = &self.webhook else {
+            return 0;
+        };
+        let queued = std::mem::take(&mut *self.queued.lock().unwrap());
+        let sent = queued.len();
+        for message in queued {
+            webhook
+                .notify_recorded(message.event_type, message.data, message.intent, node_api)
+                .await;
+        }
+        sent
+    }
+
+    /// Send messages as they are queued, if any band is configured.
+    pub fn spawn(
+        self: &Arc<Self>,
+        node_api: Arc<dyn NodeAPI>,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.is_enabled() || self.webhook.is_none() {
+            return None;
+        }
+        let escalation = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                escalation.notify.notified().await;
+                escalation.flush(node_api.as_ref()).await;
+            }
+        }))
+    }
+
+    fn save(&self, bands: &BTreeMap<String, Bands>) -> Result<(), GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(ESCALATION_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data = bincode::serialize(bands).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(BANDS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<BTreeMap<String, Bands>, GovernanceError> {
+        let tree = db
+            .open_tree(ESCALATION_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(BANDS_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(BTreeMap::new()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANDS: [f64; 3] = [50.0, 75.0, 90.0];
+
+    fn tally(vetoing_weight: f64, total_weight: f64, threshold_percent: f64) -> VetoTally {
+        VetoTally {
+            proposal_id: "1".to_string(),
+            total_weight,
+            vetoing_weight,
+            threshold_percent,
+            commitment: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_bands_reached_despite_rounding() {
+        // 1.65 of 10 is 16.5%, half of a 33% threshold, but divides to just under 50
+        let exact = ratio_percent(&tally(1.65, 10.0, 33.0));
+        assert!(exact < 50.0);
+        assert!(reaches(exact, 50.0));
+        // 2.025 of 9 is three quarters of a 30% threshold
+        assert!(reaches(ratio_percent(&tally(2.025, 9.0, 30.0)), 75.0));
+        // A weight just short is not
+        assert!(!reaches(ratio_percent(&tally(1.6499, 10.0, 33.0)), 50.0));
+        assert!(!reaches(49.999_999, 50.0));
+        assert_eq!(ratio_percent(&tally(1.0, 10.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_each_band_once_per_direction() {
+        let mut bands = Bands::default();
+        assert_eq!(bands.advance(&BANDS, 40.0), None);
+        assert_eq!(
+            bands.advance(&BANDS, 50.0),
+            Some(Crossing::Escalation { band: 50.0 })
+        );
+        assert_eq!(bands.advance(&BANDS, 60.0), None);
+        assert_eq!(
+            bands.advance(&BANDS, 49.0),
+            Some(Crossing::Deescalation { band: 50.0 })
+        );
+        // Back and forth across it again: nothing more
+        assert_eq!(bands.advance(&BANDS, 55.0), None);
+        assert_eq!(bands.advance(&BANDS, 45.0), None);
+        assert_eq!(bands.inside, Vec::<f64>::new());
+        assert_eq!(
+            bands.advance(&BANDS, 80.0),
+            Some(Crossing::Escalation { band: 75.0 })
+        );
+        assert_eq!(bands.inside, vec![50.0, 75.0]);
+    }
+
+    #[test]
+    fn test_several_bands_at_once() {
+        let mut bands = Bands::default();
+        assert_eq!(
+            bands.advance(&BANDS, 95.0),
+            Some(Crossing::Escalation { band: 90.0 })
+        );
+        assert_eq!(bands.escalated, vec![50.0, 75.0, 90.0]);
+        // Each band entered counts as escalated, so dropping to 80 and back sends nothing
+        assert_eq!(
+            bands.advance(&BANDS, 80.0),
+            Some(Crossing::Deescalation { band: 90.0 })
+        );
+        assert_eq!(bands.advance(&BANDS, 92.0), None);
+        assert_eq!(
+            bands.advance(&BANDS, 10.0),
+            Some(Crossing::Deescalation { band: 50.0 })
+        );
+        // A band no longer configured is dropped without a message
+        let mut bands = Bands::default();
+        bands.advance(&BANDS, 80.0);
+        assert_eq!(bands.advance(&[50.0], 80.0), None);
+        assert_eq!(bands.inside, vec![50.0]);
+    }
+
+    #[test]
+    fn test_largest_contributors() {
+        let contributor = |id: &str, weight: f64| Contributor {
+            node_id: Some(id.to_string()),
+            node_type: "miner".to_string(),
+            weight,
+            share_percent: 0.0,
+        };
+        let all = vec![
+            contributor("aa", 1.0),
+            contributor("bb", 3.0),
+            contributor("cc", 1.0),
+        ];
+        let top = largest(all.clone(), 5.0, 2, false);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].node_id.as_deref(), Some("bb"));
+        assert_eq!(top[0].share_percent, 60.0);
+        assert_eq!(top[1].node_id.as_deref(), Some("aa"));
+        let redacted = largest(all, 5.0, 3, true);
+        assert!(redacted.iter().all(|c| c.node_id.is_none()));
+        let json = serde_json::to_value(&redacted[0]).unwrap();
+        assert!(json.get("node_id").is_none());
+    }
+}
@@ -0,0 +1,375 @@
+//! Bounded queue between event dispatch and event processing
+//!
+//! Events from the node are queued for a single worker instead of being processed in the
+//! dispatcher, so a slow webhook endpoint cannot grow memory without bound. When the queue is
+//! full the configured [`OverflowPolicy`] applies: `backpressure` makes dispatch wait for
+//! room, which stops the module reading further events from the socket; `drop_low_priority`
+//! drops `NewBlock` events (counted, and logged at a sampled rate) while governance events
+//! always wait for room.
+//!
+//! Queued events also count their serialized size against the queue's memory share (see
+//! [`crate::memory`]) until they are processed. When the next event does not fit, it is
+//! written to a spill file instead, as is every event after it until the file is empty
+//! again; a task feeds them back into the queue in order as room is released. Spilled events
+//! are accepted as they arrive, so the overflow policy does not apply to them. The file is in
+//! the directory given to [`EventQueue::with_memory`], and removed once empty.
+
+use crate::config::{EventQueueConfig, OverflowPolicy};
+use crate::memory::{Charge, Share};
+use crate::shutdown::WorkGuard;
+use blvm_node::module::ipc::protocol::EventMessage;
+use blvm_node::module::EventType;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Log one in this many dropped events.
+const DROP_LOG_EVERY: u64 = 1000;
+
+/// Distinguishes the spill files of the queues of one process.
+static SPILL_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// An accepted event; shutdown waits for it to be processed.
+pub struct QueuedEvent {
+    pub event: EventMessage,
+    pub work: WorkGuard,
+    /// The event's size, counted against the queue's memory share until it is dropped.
+    pub memory: Charge,
+}
+
+/// Queue counters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EventQueueStats {
+    pub capacity: usize,
+    pub queued: usize,
+    pub policy: OverflowPolicy,
+    pub dropped: u64,
+    /// Times dispatch had to wait for room.
+    pub full: u64,
+    /// Events in the spill file, waiting for memory to be queued.
+    #[serde(default)]
+    pub spilled: usize,
+}
+
+pub struct EventQueue {
+    tx: mpsc::Sender<QueuedEvent>,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+    full: AtomicU64,
+    memory: Arc<Share>,
+    /// Where events go while the memory share is full; `None` keeps them in memory.
+    spill: Option<Arc<Spill>>,
+}
+
+/// Events that may be dropped under `drop_low_priority`.
+pub fn is_low_priority(event_type: &EventType) -> bool {
+    matches!(event_type, EventType::NewBlock)
+}
+
+impl EventQueue {
+    pub fn new(config: &EventQueueConfig) -> (Self, mpsc::Receiver<QueuedEvent>) {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let queue = Self {
+            tx,
+            policy: config.policy,
+            dropped: AtomicU64::new(0),
+            full: AtomicU64::new(0),
+            memory: Arc::default(),
+            spill: None,
+        };
+        (queue, rx)
+    }
+
+    /// Count queued events against `share`, spilling them to a file in `spill_dir` once it
+    /// is full. Without a directory they are kept in memory over the share.
+    pub fn with_memory(mut self, share: Arc<Share>, spill_dir: Option<PathBuf>) -> Self {
+        self.memory = share;
+        self.spill = spill_dir.map(|dir| Arc::new(Spill::new(&dir)));
+        self
+    }
+
+    /// The event as queued in memory, or `None` if it went to the spill file.
+    fn charge_or_spill(&self, event: EventMessage, work: WorkGuard) -> Option<QueuedEvent> {
+        let bytes = event_size(&event);
+        let Some(spill) = &self.spill else {
+            let memory = self.memory.charge(bytes);
+            return Some(QueuedEvent {
+                event,
+                work,
+                memory,
+            });
+        };
+        let mut state = spill.state.lock().unwrap();
+        if state.pending.is_empty() {
+            if let Some(memory) = admit(&self.memory, bytes) {
+                return Some(QueuedEvent {
+                    event,
+                    work,
+                    memory,
+                });
+            }
+        }
+        if let Err(e) = state.append(&spill.path, &event, work) {
+            warn!(
+                "Failed to spill {:?} event to {}, keeping it in memory: {}",
+                event.event_type,
+                spill.path.display(),
+                e.error
+            );
+            let memory = self.memory.charge(bytes);
+            return Some(QueuedEvent {
+                event,
+                work: e.work,
+                memory,
+            });
+        }
+        self.memory.overflowed(1);
+        if !state.pumping {
+            state.pumping = true;
+            tokio::spawn(pump(
+                Arc::clone(spill),
+                self.tx.clone(),
+                Arc::clone(&self.memory),
+            ));
+        }
+        None
+    }
+
+    /// Queue an event, waiting for room whatever the policy. Returns false if the worker has
+    /// stopped.
+    pub async fn send(&self, event: EventMessage, work: WorkGuard) -> bool {
+        match self.charge_or_spill(event, work) {
+            Some(queued) => self.tx.send(queued).await.is_ok(),
+            None => !self.tx.is_closed(),
+        }
+    }
+
+    /// Queue an event. Returns false if it was dropped (or the worker has stopped).
+    pub async fn push(&self, event: EventMessage, work: WorkGuard) -> bool {
+        let Some(queued) = self.charge_or_spill(event, work) else {
+            return !self.tx.is_closed();
+        };
+        let queued = match self.tx.try_send(queued) {
+            Ok(()) => return true,
+            Err(mpsc::error::TrySendError::Closed(_)) => return false,
+            Err(mpsc::error::TrySendError::Full(queued)) => queued,
+        };
+        if self.policy == OverflowPolicy::DropLowPriority
+            && is_low_priority(&queued.event.event_type)
+        {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped == 1 || dropped.is_multiple_of(DROP_LOG_EVERY) {
+                warn!(
+                    "Event queue full, dropped {:?} event ({} dropped so far)",
+                    queued.event.event_type, dropped
+                );
+            }
+            return false;
+        }
+        self.full.fetch_add(1, Ordering::Relaxed);
+        self.tx.send(queued).await.is_ok()
+    }
+
+    pub fn stats(&self) -> EventQueueStats {
+        EventQueueStats {
+            capacity: self.tx.max_capacity(),
+            queued: self.tx.max_capacity() - self.tx.capacity(),
+            policy: self.policy,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            full: self.full.load(Ordering::Relaxed),
+            spilled: self
+                .spill
+                .as_ref()
+                .map_or(0, |s| s.state.lock().unwrap().pending.len()),
+        }
+    }
+}
+
+/// Counted size of `event`: its serialized size.
+fn event_size(event: &EventMessage) -> u64 {
+    bincode::serialized_size(event).unwrap_or(0)
+}
+
+/// Count `bytes` against `share` if they fit, or if nothing else is queued, so that an event
+/// larger than the share still gets through.
+fn admit(share: &Arc<Share>, bytes: u64) -> Option<Charge> {
+    share
+        .try_charge(bytes)
+        .or_else(|| (share.used() == 0).then(|| share.charge(bytes)))
+}
+
+/// Events spilled to a file, oldest first.
+struct Spill {
+    path: PathBuf,
+    state: Mutex<SpillState>,
+}
+
+#[derive(Default)]
+struct SpillState {
+    /// Open while events are spilled.
+    file: Option<File>,
+    /// Offset of the oldest event in the file, and of the end.
+    read: u64,
+    write: u64,
+    /// Length in the file and work guard of each spilled event. The guard of the oldest is
+    /// taken while it is fed back; the entry stays until it is queued, so that later events
+    /// keep going to the file meanwhile.
+    pending: VecDeque<(u32, Option<WorkGuard>)>,
+    /// Whether a task is feeding events back.
+    pumping: bool,
+}
+
+/// A failed spill, with the guard of the event that was not spilled.
+struct SpillError {
+    error: std::io::Error,
+    work: WorkGuard,
+}
+
+impl Spill {
+    fn new(dir: &Path) -> Self {
+        let n = SPILL_FILES.fetch_add(1, Ordering::Relaxed);
+        Self {
+            path: dir.join(format!("events-{}-{}.spill", std::process::id(), n)),
+            state: Mutex::default(),
+        }
+    }
+
+    /// The oldest spilled event and its guard, leaving its entry in place; `None` once the
+    /// file is empty, which ends the feeding task. Events that cannot be read back are
+    /// dropped.
+    fn peek(&self) -> Option<(EventMessage, WorkGuard)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let Some((len, work)) = state.pending.front_mut() else {
+                self.clear(&mut state);
+                return None;
+            };
+            let (len, Some(work)) = (*len, work.take()) else {
+                state.pop();
+                continue;
+            };
+            match state.read_at_front(len) {
+                Ok(event) => return Some((event, work)),
+                Err(e) => {
+                    warn!(
+                        "Dropping spilled event unreadable from {}: {}",
+                        self.path.display(),
+                        e
+                    );
+                    state.pop();
+                }
+            }
+        }
+    }
+
+    /// Remove the oldest event, once it is queued.
+    fn pop(&self) {
+        self.state.lock().unwrap().pop();
+    }
+
+    /// Drop every spilled event and the file, and end the feeding task.
+    fn clear(&self, state: &mut SpillState) {
+        state.pending.clear();
+        state.pumping = false;
+        state.file = None;
+        state.read = 0;
+        state.write = 0;
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+impl SpillState {
+    fn append(
+        &mut self,
+        path: &Path,
+        event: &EventMessage,
+        work: WorkGuard,
+    ) -> Result<(), SpillError> {
+        let mut write = || -> std::io::Result<u32> {
+            let data = bincode::serialize(event).map_err(std::io::Error::other)?;
+            let len = u32::try_from(data.len()).map_err(std::io::Error::other)?;
+            if self.file.is_none() {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                self.file = Some(
+                    File::options()
+                        .read(true)
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)?,
+                );
+            }
+            let file = self.file.as_mut().unwrap();
+            file.seek(SeekFrom::Start(self.write))?;
+            file.write_all(&len.to_le_bytes())?;
+            file.write_all(&data)?;
+            Ok(len)
+        };
+        match write() {
+            Ok(len) => {
+                self.write += 4 + u64::from(len);
+                self.pending.push_back((len, Some(work)));
+                Ok(())
+            }
+            Err(error) => Err(SpillError { error, work }),
+        }
+    }
+
+    fn read_at_front(&mut self, len: u32) -> std::io::Result<EventMessage> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| std::io::Error::other("spill file closed"))?;
+        file.seek(SeekFrom::Start(self.read + 4))?;
+        let mut data = vec![0u8; len as usize];
+        file.read_exact(&mut data)?;
+        bincode::deserialize(&data).map_err(std::io::Error::other)
+    }
+
+    fn pop(&mut self) {
+        if let Some((len, _)) = self.pending.pop_front() {
+            self.read += 4 + u64::from(len);
+        }
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Feed spilled events back into the queue, oldest first, as the memory share allows.
+async fn pump(spill: Arc<Spill>, tx: mpsc::Sender<QueuedEvent>, memory: Arc<Share>) {
+    while let Some((event, work)) = spill.peek() {
+        let bytes = event_size(&event);
+        let charge = loop {
+            let released = memory.released();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            if let Some(charge) = admit(&memory, bytes) {
+                break charge;
+            }
+            released.await;
+        };
+        let queued = QueuedEvent {
+            event,
+            work,
+            memory: charge,
+        };
+        if tx.send(queued).await.is_err() {
+            // The worker has stopped; what is left is dropped
+            spill.clear(&mut spill.state.lock().unwrap());
+            return;
+        }
+        spill.pop();
+    }
+}
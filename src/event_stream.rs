@@ -0,0 +1,102 @@
+//! Missed-event detection
+//!
+//! Event messages from the node carry no sequence number, so gaps are inferred from
+//! `NewBlock` heights: a block more than one above the last one seen means events in
+//! between were missed. Lower or equal heights are reorgs, not gaps. State is reset for
+//! each connection, since events published while disconnected are never replayed.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Missed-event status of the current connection.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EventStreamStatus {
+    pub last_height: Option<u64>,
+    /// Gaps seen on this connection.
+    pub gaps: u64,
+    /// Blocks skipped across those gaps.
+    pub missed_blocks: u64,
+    /// Set once a gap has been seen.
+    pub possible_missed_events: bool,
+}
+
+/// A run of block heights that was never announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct EventStreamMonitor {
+    status: Mutex<EventStreamStatus>,
+    reconcile_on_gap: bool,
+}
+
+impl EventStreamMonitor {
+    /// `reconcile_on_gap`: whether a gap should trigger a registry reconciliation.
+    pub fn new(reconcile_on_gap: bool) -> Self {
+        Self {
+            status: Mutex::default(),
+            reconcile_on_gap,
+        }
+    }
+
+    pub fn reconcile_on_gap(&self) -> bool {
+        self.reconcile_on_gap
+    }
+
+    pub fn status(&self) -> EventStreamStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Start over for a new connection.
+    pub fn reset(&self) {
+        *self.status.lock().unwrap() = EventStreamStatus::default();
+    }
+
+    /// Record a `NewBlock` height. Returns the skipped heights if there is a gap.
+    pub fn observe_block(&self, height: u64) -> Option<Gap> {
+        let mut status = self.status.lock().unwrap();
+        let last = status.last_height.replace(height);
+        let last = last.filter(|&last| height > last.saturating_add(1))?;
+        let gap = Gap {
+            from: last + 1,
+            to: height - 1,
+        };
+        status.gaps += 1;
+        status.missed_blocks += gap.to - gap.from + 1;
+        status.possible_missed_events = true;
+        warn!(
+            "Missed NewBlock events for heights {}..={}; events may have been lost",
+            gap.from, gap.to
+        );
+        Some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps_from_block_heights() {
+        let monitor = EventStreamMonitor::new(false);
+        assert_eq!(monitor.observe_block(100), None);
+        assert_eq!(monitor.observe_block(101), None);
+        // Reorg back to 100 is not a gap
+        assert_eq!(monitor.observe_block(100), None);
+        assert_eq!(monitor.observe_block(101), None);
+        assert_eq!(monitor.observe_block(105), Some(Gap { from: 102, to: 104 }));
+        let status = monitor.status();
+        assert_eq!(status.gaps, 1);
+        assert_eq!(status.missed_blocks, 3);
+        assert!(status.possible_missed_events);
+
+        // A new connection starts from whatever height it sees first
+        monitor.reset();
+        assert_eq!(monitor.observe_block(200), None);
+        assert!(!monitor.status().possible_missed_events);
+    }
+}
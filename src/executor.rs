@@ -0,0 +1,54 @@
+//! Keyed lanes for concurrent event processing
+//!
+//! Items are routed by key to one of `parallelism` lanes, each processed by its own task in
+//! arrival order. Items with the same key always share a lane, so they are never reordered,
+//! while items with different keys can be processed concurrently.
+
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Items buffered per lane before dispatch waits.
+const LANE_CAPACITY: usize = 16;
+
+pub struct Lanes<T> {
+    senders: Vec<mpsc::Sender<T>>,
+}
+
+impl<T: Send + 'static> Lanes<T> {
+    /// Start `parallelism` lanes, each calling `handler` for its items one at a time.
+    pub fn spawn<F, Fut>(parallelism: usize, handler: F) -> (Self, Vec<JoinHandle<()>>)
+    where
+        F: Fn(T) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (senders, handles) = (0..parallelism.max(1))
+            .map(|_| {
+                let (tx, mut rx) = mpsc::channel::<T>(LANE_CAPACITY);
+                let handler = handler.clone();
+                let handle = tokio::spawn(async move {
+                    while let Some(item) = rx.recv().await {
+                        handler(item).await;
+                    }
+                });
+                (tx, handle)
+            })
+            .unzip();
+        (Self { senders }, handles)
+    }
+
+    /// Lane for `key`. Stable for the life of the process.
+    pub fn lane(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.senders.len() as u64) as usize
+    }
+
+    /// Queue `item` on the lane for `key`, waiting while that lane is full. Returns false if
+    /// the lane has stopped.
+    pub async fn dispatch(&self, key: &str, item: T) -> bool {
+        self.senders[self.lane(key)].send(item).await.is_ok()
+    }
+}
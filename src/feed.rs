@@ -0,0 +1,563 @@
+//! Atom and RSS feeds of governance activity
+//!
+//! With `[governance.health] feed = true`, the health listener (see [`crate::health`]) serves
+//! recent governance activity as `GET /feed.atom` (Atom 1.0) and `GET /feed.rss` (RSS 2.0),
+//! for people who would rather follow it in a feed reader than through the webhook. An entry
+//! is added when:
+//!
+//! - a proposal is first stored (`proposal_created`), or stored as merged
+//!   (`proposal_merged`), whether from its event, a lookup or an event held until the
+//!   proposal was known;
+//! - voting on it closes (`voting_closed`, see [`crate::deadlines`]), or it activates
+//!   (`proposal_activated`, see [`crate::activation`]);
+//! - the veto tally on it crosses the threshold (`veto_threshold_crossed`). A tally that drops
+//!   back below and crosses again adds another entry.
+//!
+//! Titles and summaries are rendered by [`describe`] from the data the webhook notification
+//! of the event carries. Each entry has a stable id made of the event type and the proposal
+//! id, and the height for crossings, so a reader never shows it twice, and is dated when it
+//! was recorded. The `feed_entries` most recent are kept in `feed.json` in the data
+//! directory's `state/`, so the feeds survive restarts.
+//!
+//! Feeds are answered with `Cache-Control: max-age=60`, `Last-Modified` and an `ETag` that
+//! changes with each entry; a request whose `If-None-Match` names the current one is answered
+//! 304 with no body. They need no token, as feed readers cannot send one.
+
+use crate::build_info::{civil_from_days, rfc3339};
+use crate::checkpoint::write_atomic;
+use crate::economic_nodes::tally::VetoTally;
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+pub const FEED_FILE: &str = "feed.json";
+
+/// Event type of the entry added when a veto tally crosses the threshold.
+pub const VETO_CROSSED_EVENT: &str = "veto_threshold_crossed";
+
+/// Event types that add an entry.
+pub const FEED_EVENTS: [&str; 5] = [
+    "proposal_created",
+    "voting_closed",
+    "proposal_merged",
+    "proposal_activated",
+    VETO_CROSSED_EVENT,
+];
+
+/// Prefix of the feed's and entries' ids, as tag URIs (RFC 4151).
+const TAG: &str = "tag:btcdecoded.org,2025:blvm-governance";
+
+/// Seconds a reader may cache a feed.
+const MAX_AGE_SECS: u64 = 60;
+
+/// One item of the feeds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedEntry {
+    /// Stable id, e.g. `proposal_merged/42`.
+    pub id: String,
+    /// Number of the entry, increasing across restarts.
+    pub seq: u64,
+    pub event_type: String,
+    pub proposal_id: String,
+    pub title: String,
+    pub summary: String,
+    /// Unix seconds when it was recorded.
+    pub updated: u64,
+}
+
+/// Contents of `feed.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct FeedFile {
+    next_seq: u64,
+    /// Oldest first.
+    entries: Vec<FeedEntry>,
+    /// Proposals whose veto tally is above the threshold.
+    crossed: BTreeSet<String>,
+}
+
+/// Format of a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    /// The format served on `path`, if any.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/feed.atom" => Some(Self::Atom),
+            "/feed.rss" => Some(Self::Rss),
+            _ => None,
+        }
+    }
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Atom => "/feed.atom",
+            Self::Rss => "/feed.rss",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Atom => "application/atom+xml; charset=utf-8",
+            Self::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+/// A feed as served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedResponse {
+    /// 200, or 304 with an empty body.
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+    /// Caching headers, each ending in CRLF.
+    pub headers: String,
+}
+
+/// The recorded entries, shared by both feeds.
+#[derive(Debug)]
+pub struct Feed {
+    path: PathBuf,
+    max_entries: usize,
+    state: Mutex<FeedFile>,
+}
+
+impl Feed {
+    /// Load the entries in `dir`, if there are any, keeping the `max_entries` most recent.
+    pub fn open(dir: &Path, max_entries: usize) -> Result<Self, GovernanceError> {
+        let path = dir.join(FEED_FILE);
+        let mut state: FeedFile = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(GovernanceError::serialization(&path.display().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => FeedFile::default(),
+            Err(e) => return Err(GovernanceError::io(path.display())(e)),
+        };
+        let excess = state.entries.len().saturating_sub(max_entries);
+        state.entries.drain(..excess);
+        Ok(Self {
+            path,
+            max_entries,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Apply `change` and write the result; the state is unchanged if the write fails.
+    fn update(&self, change: impl FnOnce(&mut FeedFile) -> bool) -> Result<(), GovernanceError> {
+        let mut state = self.state.lock().unwrap();
+        let mut next = state.clone();
+        if !change(&mut next) {
+            return Ok(());
+        }
+        let excess = next.entries.len().saturating_sub(self.max_entries);
+        next.entries.drain(..excess);
+        let data = serde_json::to_vec(&next).map_err(GovernanceError::serialization("feed"))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(GovernanceError::io(dir.display()))?;
+        }
+        write_atomic(&self.path, &data)?;
+        *state = next;
+        Ok(())
+    }
+
+    /// Add an entry for the notification `event_type` with `data`, unless the type is not one
+    /// of [`FEED_EVENTS`] or the entry is already there.
+    pub fn record(&self, event_type: &str, data: &Value) {
+        if !FEED_EVENTS.contains(&event_type) {
+            return;
+        }
+        let Some(proposal_id) = data["proposal_id"].as_str() else {
+            return;
+        };
+        let Some((title, summary)) = describe(event_type, data) else {
+            return;
+        };
+        let id = match event_type {
+            VETO_CROSSED_EVENT => format!("{}/{}/{}", event_type, proposal_id, data["height"]),
+            _ => format!("{}/{}", event_type, proposal_id),
+        };
+        let result = self.update(|state| {
+            if state.entries.iter().any(|e| e.id == id) {
+                return false;
+            }
+            state.entries.push(FeedEntry {
+                id,
+                seq: state.next_seq,
+                event_type: event_type.to_string(),
+                proposal_id: proposal_id.to_string(),
+                title,
+                summary,
+                updated: crate::clock::unix_now(),
+            });
+            state.next_seq += 1;
+            true
+        });
+        if let Err(e) = result {
+            warn!("Failed to record {} feed entry: {}", event_type, e.chain());
+        }
+    }
+
+    /// Follow the veto tally on a proposal at `height`, adding an entry when it crosses the
+    /// threshold.
+    pub fn observe_veto(&self, tally: &VetoTally, height: u64) {
+        let was = self
+            .state
+            .lock()
+            .unwrap()
+            .crossed
+            .contains(&tally.proposal_id);
+        if was == tally.crossed() {
+            return;
+        }
+        let result = self.update(|state| {
+            if tally.crossed() {
+                state.crossed.insert(tally.proposal_id.clone())
+            } else {
+                state.crossed.remove(&tally.proposal_id)
+            }
+        });
+        if let Err(e) = result {
+            warn!(
+                "Failed to record the veto tally on {} for the feed: {}",
+                tally.proposal_id,
+                e.chain()
+            );
+            return;
+        }
+        if tally.crossed() {
+            let data = serde_json::json!({
+                "proposal_id": tally.proposal_id,
+                "veto_percent": tally.veto_percent(),
+                "threshold_percent": tally.threshold_percent,
+                "height": height,
+            });
+            self.record(VETO_CROSSED_EVENT, &data);
+        }
+    }
+
+    /// Proposals whose veto tally was last seen above the threshold, to follow until it drops.
+    pub fn crossed(&self) -> Vec<String> {
+        self.state.lock().unwrap().crossed.iter().cloned().collect()
+    }
+
+    /// The entries, most recent first.
+    pub fn entries(&self) -> Vec<FeedEntry> {
+        let mut entries = self.state.lock().unwrap().entries.clone();
+        entries.reverse();
+        entries
+    }
+
+    /// The feed in `format`, with links under `base` (e.g. `http://127.0.0.1:9180`), given
+    /// the request's `If-None-Match` header.
+    pub fn respond(
+        &self,
+        format: FeedFormat,
+        base: &str,
+        if_none_match: Option<&str>,
+    ) -> FeedResponse {
+        let entries = self.entries();
+        let updated = entries.iter().map(|e| e.updated).max().unwrap_or(0);
+        let last = entries.first().map(|e| e.seq + 1).unwrap_or(0);
+        let etag = format!(
+            "\"{}-{}-{}\"",
+            format.path().trim_start_matches("/feed."),
+            last,
+            entries.len()
+        );
+        let headers = format!(
+            "Cache-Control: public, max-age={}\r\nLast-Modified: {}\r\nETag: {}\r\n",
+            MAX_AGE_SECS,
+            http_date(updated),
+            etag
+        );
+        let current = if_none_match.is_some_and(|given| given.split(',').any(|t| t.trim() == etag));
+        let (status, body) = if current {
+            (304, String::new())
+        } else {
+            let body = match format {
+                FeedFormat::Atom => atom(&entries, base),
+                FeedFormat::Rss => rss(&entries, base),
+            };
+            (200, body)
+        };
+        FeedResponse {
+            status,
+            content_type: format.content_type(),
+            body,
+            headers,
+        }
+    }
+}
+
+/// The title and summary of the notification `event_type` with `data`, for people; `None`
+/// for types it does not describe.
+pub fn describe(event_type: &str, data: &Value) -> Option<(String, String)> {
+    let id = data["proposal_id"].as_str()?;
+    let tier = data["tier"].as_str().unwrap_or("unknown");
+    let at = match data["height"].as_u64() {
+        Some(height) => format!(" at height {}", height),
+        None => String::new(),
+    };
+    let pull_request = match (data["repository"].as_str(), data["pr_number"].as_u64()) {
+        (Some(repository), Some(pr)) if !repository.is_empty() => {
+            format!(" ({}#{})", repository, pr)
+        }
+        _ => String::new(),
+    };
+    Some(match event_type {
+        "proposal_created" => (
+            format!("Proposal {} created", id),
+            format!(
+                "Proposal {}{}, {} tier, was created{}.",
+                id, pull_request, tier, at
+            ),
+        ),
+        "voting_closed" => {
+            let tally = &data["tally"];
+            let quorum = match data["quorum_met"].as_bool() {
+                Some(true) => ", quorum met",
+                Some(false) => ", quorum not met",
+                None => "",
+            };
+            (
+                format!("Voting closed on proposal {}", id),
+                format!(
+                    "Voting on proposal {}, {} tier, closed{}: {} yes, {} no, {} abstain{}.",
+                    id,
+                    tier,
+                    at,
+                    tally["yes"].as_u64().unwrap_or(0),
+                    tally["no"].as_u64().unwrap_or(0),
+                    tally["abstain"].as_u64().unwrap_or(0),
+                    quorum
+                ),
+            )
+        }
+        "proposal_merged" => (
+            format!("Proposal {} merged", id),
+            format!(
+                "Proposal {}{}, {} tier, was merged{}.",
+                id, pull_request, tier, at
+            ),
+        ),
+        "proposal_activated" => (
+            format!("Proposal {} activated", id),
+            format!(
+                "Proposal {}, {} tier, merged at height {}, activated{}.",
+                id, tier, data["merged_height"], at
+            ),
+        ),
+        VETO_CROSSED_EVENT => (
+            format!("Veto threshold crossed on proposal {}", id),
+            format!(
+                "Economic nodes holding {:.2}% of the weight have vetoed proposal {}, over the \
+                 {}% threshold{}.",
+                data["veto_percent"].as_f64().unwrap_or(0.0),
+                id,
+                data["threshold_percent"],
+                at
+            ),
+        ),
+        _ => return None,
+    })
+}
+
+/// `entries` as an Atom 1.0 feed (RFC 4287).
+pub fn atom(entries: &[FeedEntry], base: &str) -> String {
+    let updated = entries.iter().map(|e| e.updated).max().unwrap_or(0);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <id>{}/feed</id>\n", TAG));
+    xml.push_str("  <title>Governance activity</title>\n");
+    xml.push_str(&format!("  <updated>{}</updated>\n", rfc3339(updated)));
+    xml.push_str("  <author><name>blvm-governance</name></author>\n");
+    xml.push_str(&format!(
+        "  <link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>\n",
+        escape(&format!("{}/feed.atom", base))
+    ));
+    xml.push_str(&format!(
+        "  <generator version=\"{}\">blvm-governance</generator>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    for entry in entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>{}/{}</id>\n", TAG, encode(&entry.id)));
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            rfc3339(entry.updated)
+        ));
+        xml.push_str(&format!(
+            "    <link rel=\"alternate\" href=\"{}\"/>\n",
+            escape(&proposal_link(base, &entry.proposal_id))
+        ));
+        xml.push_str(&format!(
+            "    <category term=\"{}\"/>\n",
+            escape(&entry.event_type)
+        ));
+        xml.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape(&entry.summary)
+        ));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// `entries` as an RSS 2.0 feed.
+pub fn rss(entries: &[FeedEntry], base: &str) -> String {
+    let updated = entries.iter().map(|e| e.updated).max().unwrap_or(0);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n  <channel>\n");
+    xml.push_str("    <title>Governance activity</title>\n");
+    xml.push_str(&format!(
+        "    <link>{}</link>\n",
+        escape(&format!("{}/proposals", base))
+    ));
+    xml.push_str("    <description>Proposals created, closed, merged and activated, and veto thresholds crossed</description>\n");
+    xml.push_str(&format!(
+        "    <lastBuildDate>{}</lastBuildDate>\n",
+        http_date(updated)
+    ));
+    xml.push_str(&format!(
+        "    <atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{}\"/>\n",
+        escape(&format!("{}/feed.rss", base))
+    ));
+    xml.push_str("    <generator>blvm-governance</generator>\n");
+    for entry in entries {
+        xml.push_str("    <item>\n");
+        xml.push_str(&format!("      <title>{}</title>\n", escape(&entry.title)));
+        xml.push_str(&format!(
+            "      <link>{}</link>\n",
+            escape(&proposal_link(base, &entry.proposal_id))
+        ));
+        xml.push_str(&format!(
+            "      <description>{}</description>\n",
+            escape(&entry.summary)
+        ));
+        xml.push_str(&format!(
+            "      <category>{}</category>\n",
+            escape(&entry.event_type)
+        ));
+        xml.push_str(&format!(
+            "      <guid isPermaLink=\"false\">{}/{}</guid>\n",
+            TAG,
+            encode(&entry.id)
+        ));
+        xml.push_str(&format!(
+            "      <pubDate>{}</pubDate>\n",
+            http_date(entry.updated)
+        ));
+        xml.push_str("    </item>\n");
+    }
+    xml.push_str("  </channel>\n</rss>\n");
+    xml
+}
+
+/// `secs` since the Unix epoch as an HTTP date (RFC 9110), which RSS takes as an RFC 822
+/// date, e.g. `Thu, 01 Jan 1970 00:00:00 GMT`.
+pub fn http_date(secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Where the proposal is served as a query (see [`crate::query`]).
+fn proposal_link(base: &str, proposal_id: &str) -> String {
+    format!("{}/proposals/{}", base, encode(proposal_id))
+}
+
+/// `text` with the characters that are special in XML escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `text` percent-encoded for a URL path, `/` kept.
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_and_escaping() {
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(http_date(951_782_400), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(http_date(1_735_689_599), "Tue, 31 Dec 2024 23:59:59 GMT");
+        assert_eq!(escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+        assert_eq!(encode("proposal_merged/a b"), "proposal_merged/a%20b");
+    }
+
+    #[test]
+    fn test_describe() {
+        let data = serde_json::json!({
+            "proposal_id": "42",
+            "repository": "org/repo",
+            "pr_number": 7,
+            "tier": "standard",
+            "height": 100,
+        });
+        let (title, summary) = describe("proposal_merged", &data).unwrap();
+        assert_eq!(title, "Proposal 42 merged");
+        assert_eq!(
+            summary,
+            "Proposal 42 (org/repo#7), standard tier, was merged at height 100."
+        );
+        let data = serde_json::json!({
+            "proposal_id": "42",
+            "tier": "standard",
+            "height": 120,
+            "tally": {"yes": 3, "no": 1, "abstain": 0},
+            "quorum_met": true,
+        });
+        let (_, summary) = describe("voting_closed", &data).unwrap();
+        assert_eq!(
+            summary,
+            "Voting on proposal 42, standard tier, closed at height 120: 3 yes, 1 no, 0 abstain, \
+             quorum met."
+        );
+        assert!(describe("proposal_voted", &data).is_none());
+    }
+}
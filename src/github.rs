@@ -0,0 +1,432 @@
+//! GitHub webhook receiver
+//!
+//! Proposals are pull requests on GitHub. With `[governance.github] secret` set, the health
+//! listener (see [`crate::health`]) accepts the repository webhook's deliveries on
+//! `POST /integrations/github`, so proposals follow their pull requests without anyone
+//! syncing them by hand.
+//!
+//! Each delivery's `X-Hub-Signature-256` must be the HMAC-SHA256 of its body under the
+//! secret ([`signature`]); one that is not is refused with 401. `pull_request` events of the
+//! configured `repositories` are mapped to a [`PullRequestSync`] ([`map`]):
+//!
+//! - the proposal is named by a label with `proposal_label_prefix` (`proposal:42`), and
+//!   otherwise is the one already linked to the pull request;
+//! - a label with `tier_label_prefix` (`tier:standard`) gives its tier, and the pull
+//!   request's user its author;
+//! - `opened` and `reopened` open it, `closed` merges it if the pull request was merged and
+//!   closes it if not, and `edited`, `labeled`, `unlabeled` and `synchronize` only update
+//!   the metadata.
+//!
+//! The proposal store is updated with the link and metadata ([`ProposalStore::link_pull_request`]).
+//! Without `allow_actions` a merge or close is recorded there too (as merged or rejected);
+//! with it, a change of state is instead submitted to the node as a `sync_proposal` action,
+//! and the node's own events then update the store. Other events, other actions, other
+//! repositories and pull requests with no proposal are answered with 200 and ignored, and
+//! counted in the `github` section of the module status with the deliveries applied,
+//! submitted and refused.
+
+use crate::config::GithubConfig;
+use crate::error::GovernanceError;
+use crate::node_api::{GovernanceAction, NodeApiIpc};
+use crate::proposals::ProposalStore;
+use crate::status::StatusProvider;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Name of the action submitted for a change of state.
+pub const SYNC_ACTION: &str = "sync_proposal";
+
+/// `X-Hub-Signature-256` of `body` under `secret`: `sha256=` and the hex HMAC-SHA256.
+pub fn signature(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether `header` is the signature of `body` under `secret`, compared in constant time.
+pub fn verify(secret: &[u8], body: &[u8], header: Option<&str>) -> bool {
+    let Some(given) = header
+        .and_then(|h| h.trim().strip_prefix("sha256="))
+        .and_then(|h| hex::decode(h).ok())
+    else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(body);
+    mac.verify_slice(&given).is_ok()
+}
+
+/// What a pull request event does to its proposal's state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    Opened,
+    Merged,
+    Closed,
+}
+
+/// A proposal as a pull request event describes it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestSync {
+    /// From the proposal label; `None` leaves it to the link already stored.
+    pub proposal_id: Option<String>,
+    /// "owner/name".
+    pub repository: String,
+    pub pr_number: u64,
+    /// From the tier label.
+    pub tier: Option<String>,
+    /// Login of the pull request's user.
+    pub author: String,
+    /// `None` for metadata changes only.
+    pub transition: Option<Transition>,
+}
+
+/// Why a delivery was ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ignored {
+    /// Not a `pull_request` event.
+    Event,
+    /// A `pull_request` action that does not change proposals.
+    Action,
+    /// From a repository not configured.
+    Repository,
+}
+
+/// The parts of a `pull_request` event read.
+#[derive(Debug, Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    repository: Repository,
+    pull_request: PullRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    number: u64,
+    #[serde(default)]
+    merged: bool,
+    user: User,
+    #[serde(default)]
+    labels: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+}
+
+/// Map the delivery of `event` (`X-GitHub-Event`) with JSON `body` under `config`. Fails if
+/// a `pull_request` body does not parse.
+pub fn map(
+    config: &GithubConfig,
+    event: &str,
+    body: &[u8],
+) -> Result<Result<PullRequestSync, Ignored>, GovernanceError> {
+    if event != "pull_request" {
+        return Ok(Err(Ignored::Event));
+    }
+    let event: PullRequestEvent =
+        serde_json::from_slice(body).map_err(GovernanceError::serialization("pull_request"))?;
+    if !config
+        .repositories
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(&event.repository.full_name))
+    {
+        return Ok(Err(Ignored::Repository));
+    }
+    let pull_request = event.pull_request;
+    let transition = match event.action.as_str() {
+        "opened" | "reopened" => Some(Transition::Opened),
+        "closed" if pull_request.merged => Some(Transition::Merged),
+        "closed" => Some(Transition::Closed),
+        "edited" | "labeled" | "unlabeled" | "synchronize" => None,
+        _ => return Ok(Err(Ignored::Action)),
+    };
+    let labelled = |prefix: &str| {
+        pull_request
+            .labels
+            .iter()
+            .find_map(|label| label.name.strip_prefix(prefix))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    Ok(Ok(PullRequestSync {
+        proposal_id: labelled(&config.proposal_label_prefix),
+        repository: event.repository.full_name,
+        pr_number: pull_request.number,
+        tier: labelled(&config.tier_label_prefix),
+        author: pull_request.user.login,
+        transition,
+    }))
+}
+
+/// Deliveries by outcome.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GithubCounters {
+    /// Linked to a proposal in the store.
+    pub applied: u64,
+    /// Changes of state submitted to the node.
+    pub submitted: u64,
+    /// Refused for a missing or wrong signature.
+    pub bad_signature: u64,
+    /// Refused for a body that does not parse.
+    pub malformed: u64,
+    pub ignored_events: u64,
+    pub ignored_actions: u64,
+    pub ignored_repositories: u64,
+    /// Pull requests with no proposal label and no proposal linked.
+    pub unlinked: u64,
+}
+
+/// An answer to a delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GithubResponse {
+    pub status: u16,
+    /// JSON.
+    pub body: String,
+}
+
+impl GithubResponse {
+    fn new(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Receives the webhook's deliveries on one connection to the node.
+pub struct GithubReceiver {
+    config: GithubConfig,
+    proposals: Arc<ProposalStore>,
+    /// Where changes of state are submitted, with `allow_actions`.
+    actions: Option<NodeApiIpc>,
+    counters: Mutex<GithubCounters>,
+}
+
+impl GithubReceiver {
+    pub fn new(config: GithubConfig, proposals: Arc<ProposalStore>) -> Self {
+        Self {
+            config,
+            proposals,
+            actions: None,
+            counters: Mutex::new(GithubCounters::default()),
+        }
+    }
+
+    /// Submit changes of state to the node with `node_api` rather than record them.
+    pub fn with_actions(mut self, node_api: NodeApiIpc) -> Self {
+        self.actions = Some(node_api);
+        self
+    }
+
+    pub fn counters(&self) -> GithubCounters {
+        self.counters.lock().unwrap().clone()
+    }
+
+    fn count(&self, counter: impl FnOnce(&mut GithubCounters) -> &mut u64) {
+        *counter(&mut self.counters.lock().unwrap()) += 1;
+    }
+
+    /// Answer the delivery of `event` with the `X-Hub-Signature-256` header `signature`.
+    pub async fn handle(
+        &self,
+        event: Option<&str>,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> GithubResponse {
+        let secret = self.config.secret.as_deref().unwrap_or_default();
+        if secret.is_empty() || !verify(secret.as_bytes(), body, signature) {
+            self.count(|c| &mut c.bad_signature);
+            warn!("Refused a GitHub delivery with a missing or wrong signature");
+            return GithubResponse::new(401, serde_json::json!({ "error": "bad signature" }));
+        }
+        let event = event.unwrap_or_default();
+        let sync = match map(&self.config, event, body) {
+            Ok(Ok(sync)) => sync,
+            Ok(Err(ignored)) => {
+                debug!("Ignoring GitHub {} delivery: {:?}", event, ignored);
+                self.count(|c| match ignored {
+                    Ignored::Event => &mut c.ignored_events,
+                    Ignored::Action => &mut c.ignored_actions,
+                    Ignored::Repository => &mut c.ignored_repositories,
+                });
+                return GithubResponse::new(200, serde_json::json!({ "result": "ignored" }));
+            }
+            Err(e) => {
+                self.count(|c| &mut c.malformed);
+                warn!("Refused a GitHub {} delivery: {}", event, e);
+                return GithubResponse::new(400, serde_json::json!({ "error": e.to_string() }));
+            }
+        };
+        match self.apply(&sync).await {
+            Ok(result) => GithubResponse::new(200, serde_json::json!({ "result": result })),
+            Err(e) => {
+                warn!(
+                    "Failed to sync pull request {}#{}: {}",
+                    sync.repository, sync.pr_number, e
+                );
+                GithubResponse::new(502, serde_json::json!({ "error": e.to_string() }))
+            }
+        }
+    }
+
+    /// Update the store from `sync`, and submit its change of state with actions allowed.
+    async fn apply(&self, sync: &PullRequestSync) -> Result<&'static str, GovernanceError> {
+        let linked = self
+            .proposals
+            .link_pull_request(sync, self.actions.is_none())?;
+        let proposal_id = linked.as_ref().map(|p| p.proposal_id.clone());
+        if linked.is_some() {
+            self.count(|c| &mut c.applied);
+        }
+        let submit = self.actions.as_ref().filter(|_| sync.transition.is_some());
+        let Some(node_api) = submit else {
+            if linked.is_none() {
+                self.count(|c| &mut c.unlinked);
+                return Ok("unlinked");
+            }
+            return Ok("applied");
+        };
+        let mut params = serde_json::json!(sync);
+        params["proposal_id"] = serde_json::json!(proposal_id);
+        let outcome = node_api
+            .submit_governance_action(&GovernanceAction::Other {
+                name: SYNC_ACTION.to_string(),
+                params,
+            })
+            .await?;
+        self.count(|c| &mut c.submitted);
+        info!(
+            "Submitted {:?} of pull request {}#{}: {}",
+            sync.transition,
+            sync.repository,
+            sync.pr_number,
+            if outcome.accepted {
+                "accepted"
+            } else {
+                "rejected"
+            }
+        );
+        Ok("submitted")
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for GithubReceiver {
+    fn section(&self) -> &'static str {
+        "github"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        crate::status::section(&self.counters())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> GithubConfig {
+        GithubConfig {
+            secret: Some("s3cret".to_string()),
+            repositories: vec!["BTCDecoded/governance".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/tests/fixtures/github/{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_signature() {
+        // The example in GitHub's documentation on validating deliveries
+        let secret = b"It's a Secret to Everybody";
+        let expected = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert_eq!(signature(secret, b"Hello, World!"), expected);
+        assert!(verify(secret, b"Hello, World!", Some(expected)));
+
+        assert!(!verify(secret, b"Hello, World?", Some(expected)));
+        assert!(!verify(b"another secret", b"Hello, World!", Some(expected)));
+        assert!(!verify(secret, b"Hello, World!", Some(&expected[7..])));
+        assert!(!verify(secret, b"Hello, World!", Some("sha256=zz")));
+        assert!(!verify(secret, b"Hello, World!", None));
+    }
+
+    #[test]
+    fn test_map_pull_request_events() {
+        let config = config();
+        let map = |name: &str| map(&config, "pull_request", &fixture(name)).unwrap();
+
+        let opened = map("pull_request_opened").unwrap();
+        assert_eq!(
+            opened,
+            PullRequestSync {
+                proposal_id: Some("7".to_string()),
+                repository: "BTCDecoded/governance".to_string(),
+                pr_number: 17,
+                tier: Some("standard".to_string()),
+                author: "alice".to_string(),
+                transition: Some(Transition::Opened),
+            }
+        );
+        let labeled = map("pull_request_labeled").unwrap();
+        assert_eq!(
+            (labeled.tier.as_deref(), labeled.transition),
+            (Some("emergency"), None)
+        );
+        let merged = map("pull_request_closed_merged").unwrap();
+        assert_eq!(merged.transition, Some(Transition::Merged));
+        let closed = map("pull_request_closed_unmerged").unwrap();
+        assert_eq!(
+            (closed.proposal_id, closed.tier, closed.transition),
+            (None, None, Some(Transition::Closed))
+        );
+
+        assert_eq!(map("pull_request_review_requested"), Err(Ignored::Action));
+        assert_eq!(
+            map("pull_request_other_repository"),
+            Err(Ignored::Repository)
+        );
+        assert_eq!(
+            super::map(&config, "ping", &fixture("ping")).unwrap(),
+            Err(Ignored::Event)
+        );
+        assert!(super::map(&config, "pull_request", b"{}").is_err());
+    }
+
+    #[test]
+    fn test_label_scheme_is_configurable() {
+        let config = GithubConfig {
+            proposal_label_prefix: "bip:".to_string(),
+            tier_label_prefix: "proposal:".to_string(),
+            ..config()
+        };
+        let opened = map(&config, "pull_request", &fixture("pull_request_opened"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (opened.proposal_id, opened.tier.as_deref()),
+            (None, Some("7"))
+        );
+    }
+}
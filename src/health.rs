@@ -0,0 +1,785 @@
+//! Local HTTP health and readiness endpoints
+//!
+//! With `[governance.health] listen` set, the module serves two probes over plain HTTP on
+//! that address, for a supervisor or load balancer on the same host:
+//!
+//! - `GET /healthz`: the process is alive and not stuck. Fails when the runtime has not run a
+//!   one-second ticker for `stall_secs`, or when events are queued but none has finished
+//!   processing for `stall_secs`.
+//! - `GET /readyz`: the module is doing its job. Fails while disconnected from the node or
+//!   after missed heartbeats, with no event subscriptions, without the registry store, after
+//!   `webhook_failure_limit` consecutive failed webhook deliveries, while the local clock is
+//!   skewed from block time and payloads are stamped with it (see [`crate::clock`]), while
+//!   outbound effects are paused (see [`crate::pause`]), and once shutdown begins.
+//!
+//! Each answers 200 or 503 with a [`Probe`] as its JSON body; with `?detail`, the body also
+//! carries the [`ModuleStatus`] (see [`crate::status`]). `GET /version` answers with the
+//! module's [`BuildInfo`], and `GET /errors` with the most recent error reports as
+//! `{"reports": [..]}` (see [`crate::error_report`]). With `metrics` set, `GET /metrics` also
+//! serves the module's metrics to Prometheus (see [`crate::prometheus`]). With
+//! `actions_token` set, `POST /actions/veto` and `POST /actions/vote` submit signed vetoes
+//! and votes to the node (see [`crate::actions`]). With `[governance.github] secret` set,
+//! `POST /integrations/github` receives the GitHub webhook's pull request events (see
+//! [`crate::github`]). With `admin_token` set, `POST /admin/pause` and `POST /admin/resume`
+//! pause and resume outbound effects, with `Authorization: Bearer <token>` and an optional
+//! `{"actor": .., "reason": ..}` body for the audit log; they answer with the
+//! [`PauseStatus`], or 401 without the token. With `queries` set, `GET /proposals`,
+//! `/proposals/{id}`, `/economic-nodes`, `/epochs/{n}/summary`, `/status` and `/dashboard`
+//! answer read-only queries of the governance state, with an `ETag` to poll against (see
+//! [`crate::query`]). With `feed` set, `GET /feed.atom` and `GET /feed.rss` serve recent
+//! governance activity to feed readers (see [`crate::feed`]).
+//!
+//! Nothing listens unless `listen` is set. The listener keeps answering while a shutdown
+//! drains accepted work, so `/readyz` reports it, and closes before the module exits.
+
+use crate::actions::{ActionKind, LocalActions};
+use crate::build_info::BuildInfo;
+use crate::config::HealthConfig;
+use crate::error::GovernanceError;
+use crate::error_report::ErrorReporter;
+use crate::feed::{Feed, FeedFormat};
+use crate::github::GithubReceiver;
+use crate::heartbeat::Heartbeat;
+use crate::ipc_metrics::IpcMetrics;
+use crate::pause::{PauseControl, PauseStatus, Trigger};
+use crate::query::QueryApi;
+use crate::shutdown::Shutdown;
+use crate::status::{ModuleStatus, StatusCollector};
+use crate::status_report::StatusSources;
+use crate::subscriptions::EventSubscriptions;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// Longest request head read from a client.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Longest request body read from a client. GitHub's pull request deliveries run to tens of
+/// kilobytes.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Time a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One condition of a probe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Outcome of `/healthz` or `/readyz`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Probe {
+    /// "ok", or "fail" if any check failed.
+    pub status: String,
+    pub checks: BTreeMap<String, Check>,
+    /// The module's status, when asked for with `?detail`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<ModuleStatus>,
+}
+
+impl Probe {
+    fn new(checks: impl IntoIterator<Item = (&'static str, bool, String)>) -> Self {
+        let checks: BTreeMap<String, Check> = checks
+            .into_iter()
+            .map(|(name, ok, detail)| (name.to_string(), Check { ok, detail }))
+            .collect();
+        let status = if checks.values().all(|c| c.ok) {
+            "ok"
+        } else {
+            "fail"
+        };
+        Self {
+            status: status.to_string(),
+            checks,
+            module: None,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Health of the process, kept across connections.
+pub struct Health {
+    config: HealthConfig,
+    shutdown: Arc<Shutdown>,
+    heartbeat: Arc<Heartbeat>,
+    subscriptions: Arc<EventSubscriptions>,
+    /// Served on `/metrics` when set.
+    metrics: Option<Arc<IpcMetrics>>,
+    /// Served on `/errors`; none are reported without it.
+    errors: Option<Arc<ErrorReporter>>,
+    /// Added to the probes with `?detail`, and to `/metrics` as the info metric.
+    status: Option<Arc<StatusCollector>>,
+    /// Last run of the ticker task.
+    ticked: Mutex<Instant>,
+    /// Events processed on the current connection, and when that last changed or the queue
+    /// was last empty.
+    progress: Mutex<(u64, Instant)>,
+    /// What the probes look at on the current connection.
+    connection: Mutex<Option<StatusSources>>,
+    /// Serves the action endpoints on the current connection.
+    actions: Mutex<Option<Arc<LocalActions>>>,
+    /// Served on `/admin/*`, and checked by `/readyz`.
+    pause: Option<Arc<PauseControl>>,
+    /// Whether `/integrations/github` is served.
+    receives_github: bool,
+    /// Receives GitHub deliveries on the current connection.
+    github: Mutex<Option<Arc<GithubReceiver>>>,
+    /// Answers the queries on the current connection.
+    queries: Mutex<Option<Arc<QueryApi>>>,
+    /// Served on `/feed.atom` and `/feed.rss` when set.
+    feed: Option<Arc<Feed>>,
+}
+
+impl Health {
+    pub fn new(
+        config: HealthConfig,
+        shutdown: Arc<Shutdown>,
+        heartbeat: Arc<Heartbeat>,
+        subscriptions: Arc<EventSubscriptions>,
+    ) -> Self {
+        Self {
+            config,
+            shutdown,
+            heartbeat,
+            subscriptions,
+            metrics: None,
+            errors: None,
+            status: None,
+            ticked: Mutex::new(Instant::now()),
+            progress: Mutex::new((0, Instant::now())),
+            connection: Mutex::new(None),
+            actions: Mutex::new(None),
+            pause: None,
+            receives_github: false,
+            github: Mutex::new(None),
+            queries: Mutex::new(None),
+            feed: None,
+        }
+    }
+
+    /// Serve `metrics`, and those of each connection, on `/metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<IpcMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Serve the recent reports of `errors` on `/errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Add the status `status` collects to the probes with `?detail`, and to `/metrics`.
+    pub fn with_status(mut self, status: Arc<StatusCollector>) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Pause and resume `pause` on `/admin/pause` and `/admin/resume`, with `admin_token`,
+    /// and fail `/readyz` while it is paused.
+    pub fn with_pause(mut self, pause: Arc<PauseControl>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Serve the entries of `feed` on `/feed.atom` and `/feed.rss`.
+    pub fn with_feed(mut self, feed: Arc<Feed>) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    /// Serve `/integrations/github`, answering 503 until [`Self::serve_github`].
+    pub fn with_github(mut self) -> Self {
+        self.receives_github = true;
+        self
+    }
+
+    /// The module's status, if there is a collector.
+    async fn module_status(&self) -> Option<ModuleStatus> {
+        match &self.status {
+            Some(status) => Some(status.collect().await),
+            None => None,
+        }
+    }
+
+    /// Start reporting on a new connection.
+    pub fn connected(&self, sources: StatusSources) {
+        *self.progress.lock().unwrap() = (sources.checkpointer.sequence(), Instant::now());
+        *self.connection.lock().unwrap() = Some(sources);
+    }
+
+    /// Serve the action endpoints with `actions` until the connection drops. Without
+    /// `actions_token` they stay disabled.
+    pub fn serve_actions(&self, actions: Arc<LocalActions>) {
+        *self.actions.lock().unwrap() = Some(actions);
+    }
+
+    /// Receive GitHub deliveries with `github` until the connection drops. Without
+    /// [`Self::with_github`] they are not served.
+    pub fn serve_github(&self, github: Arc<GithubReceiver>) {
+        *self.github.lock().unwrap() = Some(github);
+    }
+
+    /// Answer the queries with `queries` until the connection drops. Without `queries` set
+    /// they are not served.
+    pub fn serve_queries(&self, queries: Arc<QueryApi>) {
+        *self.queries.lock().unwrap() = Some(queries);
+    }
+
+    /// The connection to the node has dropped.
+    pub fn disconnected(&self) {
+        *self.connection.lock().unwrap() = None;
+        *self.actions.lock().unwrap() = None;
+        *self.github.lock().unwrap() = None;
+        *self.queries.lock().unwrap() = None;
+    }
+
+    fn stall_after(&self) -> Duration {
+        Duration::from_secs(self.config.stall_secs.max(1))
+    }
+
+    /// Events queued on the current connection and how long since one last finished
+    /// processing, or `None` if it is keeping up (or there is no connection).
+    fn stalled_for(&self) -> Option<(usize, Duration)> {
+        let connection = self.connection.lock().unwrap();
+        let connection = connection.as_ref()?;
+        let queued = connection.events.stats().queued;
+        let sequence = connection.checkpointer.sequence();
+        let mut progress = self.progress.lock().unwrap();
+        if queued == 0 || sequence != progress.0 {
+            *progress = (sequence, Instant::now());
+            return None;
+        }
+        Some((queued, progress.1.elapsed()))
+    }
+
+    /// Why event processing counts as stalled, if it does: events are queued and none has
+    /// finished processing for `stall_secs`. Also checked by the systemd watchdog (see
+    /// [`crate::systemd`]), which runs whether or not the probes are served.
+    pub fn events_stalled(&self) -> Option<String> {
+        let (queued, stalled) = self.stalled_for()?;
+        (stalled >= self.stall_after()).then(|| {
+            format!(
+                "{} queued, none processed for {}s",
+                queued,
+                stalled.as_secs()
+            )
+        })
+    }
+
+    /// `/healthz`: the runtime is running tasks and events are being processed.
+    pub fn healthz(&self) -> Probe {
+        let stall_after = self.stall_after();
+        let tick_age = self.ticked.lock().unwrap().elapsed();
+        let runtime = (
+            "runtime",
+            tick_age < stall_after,
+            format!("last tick {}ms ago", tick_age.as_millis()),
+        );
+        let events = match self.stalled_for() {
+            None => ("event_processing", true, "keeping up".to_string()),
+            Some((queued, stalled)) => (
+                "event_processing",
+                stalled < stall_after,
+                format!(
+                    "{} queued, none processed for {}s",
+                    queued,
+                    stalled.as_secs()
+                ),
+            ),
+        };
+        Probe::new([runtime, events])
+    }
+
+    /// `/readyz`: connected to the node and able to process and deliver events.
+    pub fn readyz(&self) -> Probe {
+        let heartbeat = self.heartbeat.status();
+        let subscriptions = self.subscriptions.current().len();
+        let connection = self.connection.lock().unwrap();
+        let node = match (connection.as_ref(), heartbeat.dead) {
+            (None, _) => (false, "not connected".to_string()),
+            (Some(_), true) => (
+                false,
+                format!("{} heartbeats missed", heartbeat.consecutive_misses),
+            ),
+            (Some(_), false) => (true, "connected".to_string()),
+        };
+        let store = match connection.as_ref() {
+            Some(c) if c.registry.has_store() => (true, "open".to_string()),
+            Some(_) => (false, "registry not persisted".to_string()),
+            None => (false, "not connected".to_string()),
+        };
+        let failures = connection
+            .as_ref()
+            .map(|c| c.webhook.consecutive_failures())
+            .unwrap_or(0);
+        let limit = self.config.webhook_failure_limit;
+        let webhook = (
+            limit == 0 || failures < limit,
+            format!("{} consecutive failed deliveries", failures),
+        );
+        let clock = match connection.as_ref().map(|c| c.clock.status()) {
+            Some(status) => {
+                let detail = match status.skew_secs {
+                    Some(skew) => format!("{}s from block time", skew),
+                    None if status.syncing => "node syncing".to_string(),
+                    None => format!("{} blocks sampled", status.samples),
+                };
+                if status.using_block_time {
+                    (
+                        true,
+                        format!("{}, stamping payloads with block time", detail),
+                    )
+                } else {
+                    (!status.skewed, detail)
+                }
+            }
+            None => (true, "not connected".to_string()),
+        };
+        let paused = match self.pause.as_ref().map(|p| p.status()) {
+            Some(status) if status.paused => (
+                false,
+                format!("paused since {}", status.since.unwrap_or_default()),
+            ),
+            Some(status) if status.draining => (true, "sending the held backlog".to_string()),
+            _ => (true, "running".to_string()),
+        };
+        let stopping = self.shutdown.is_stopping();
+        Probe::new([
+            ("node", node.0, node.1),
+            (
+                "subscriptions",
+                subscriptions > 0,
+                format!("{} event types", subscriptions),
+            ),
+            ("registry_store", store.0, store.1),
+            ("webhook", webhook.0, webhook.1),
+            ("clock", clock.0, clock.1),
+            ("pause", paused.0, paused.1),
+            (
+                "shutdown",
+                !stopping,
+                if stopping { "shutting down" } else { "running" }.to_string(),
+            ),
+        ])
+    }
+
+    /// Serve the probes on `addr` until the returned task is aborted, and run the ticker
+    /// that `/healthz` watches.
+    pub async fn spawn(self: &Arc<Self>, addr: &str) -> Result<JoinHandle<()>, GovernanceError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(GovernanceError::io(format!("cannot listen on {}", addr)))?;
+        if let Ok(local) = listener.local_addr() {
+            info!("Serving /healthz and /readyz on http://{}", local);
+        }
+        Ok(self.serve(listener))
+    }
+
+    /// Serve the probes on an already bound `listener`.
+    pub fn serve(self: &Arc<Self>, listener: TcpListener) -> JoinHandle<()> {
+        let health = Arc::clone(self);
+        tokio::spawn(async move {
+            let ticker = Arc::clone(&health);
+            let ticker = tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(1));
+                loop {
+                    interval.tick().await;
+                    *ticker.ticked.lock().unwrap() = Instant::now();
+                    ticker.stalled_for();
+                }
+            });
+            // Aborting this task drops the listener and ends the ticker with it
+            struct AbortOnDrop(JoinHandle<()>);
+            impl Drop for AbortOnDrop {
+                fn drop(&mut self) {
+                    self.0.abort();
+                }
+            }
+            let _ticker = AbortOnDrop(ticker);
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        debug!("Health listener accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let health = Arc::clone(&health);
+                tokio::spawn(async move {
+                    if let Err(e) = health.respond(stream).await {
+                        debug!("Health request failed: {}", e);
+                    }
+                });
+            }
+        })
+    }
+
+    async fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+            .await
+            .unwrap_or(Ok(None))?;
+        let Some((head, body)) = request else {
+            return Ok(());
+        };
+        let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+        let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        // Probes may add a query string
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let detail = query
+            .split('&')
+            .any(|q| q == "detail" || q.starts_with("detail="));
+        // Only queries and feeds are tagged
+        let mut headers = String::new();
+        let (status, content_type, body) = match (method, path) {
+            ("GET", "/healthz") => self.answer(self.healthz(), detail).await,
+            ("GET", "/readyz") => self.answer(self.readyz(), detail).await,
+            ("GET", "/version") => (
+                "200 OK",
+                "application/json",
+                serde_json::to_string(BuildInfo::current()).unwrap_or_default(),
+            ),
+            ("GET", "/errors") => {
+                let reports = self.errors.as_ref().map(|e| e.recent()).unwrap_or_default();
+                (
+                    "200 OK",
+                    "application/json",
+                    serde_json::json!({ "reports": reports }).to_string(),
+                )
+            }
+            ("GET", "/metrics") if self.metrics.is_some() => (
+                "200 OK",
+                crate::prometheus::CONTENT_TYPE,
+                self.scrape().await,
+            ),
+            ("POST", "/actions/veto" | "/actions/vote") if self.config.actions_token.is_some() => {
+                self.act(path, &head, &body).await
+            }
+            ("POST", "/admin/pause" | "/admin/resume") if self.config.admin_token.is_some() => {
+                self.admin(path, &head, &body)
+            }
+            ("POST", "/integrations/github") if self.receives_github => {
+                self.receive_github(&head, &body).await
+            }
+            ("GET", path) if self.config.queries && crate::query::serves(path) => {
+                let (answer, tag) = self.query(path, query, &head).await;
+                if let Some(tag) = tag {
+                    headers = format!("ETag: {}\r\n", tag);
+                }
+                answer
+            }
+            ("GET", "/feed.atom" | "/feed.rss") if self.feed.is_some() => {
+                let (answer, caching) = self.feed(path, &head);
+                headers = caching;
+                answer
+            }
+            (_, "/healthz" | "/readyz" | "/version" | "/errors") => {
+                ("405 Method Not Allowed", "text/plain", String::new())
+            }
+            _ => ("404 Not Found", "text/plain", String::new()),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            headers,
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    /// `POST /actions/*`: submit a vote or veto, once connected to the node.
+    async fn act(
+        &self,
+        path: &str,
+        head: &str,
+        body: &[u8],
+    ) -> (&'static str, &'static str, String) {
+        let actions = self.actions.lock().unwrap().clone();
+        let (Some(kind), Some(actions)) = (ActionKind::from_path(path), actions) else {
+            let body = serde_json::json!({ "error": "not connected to the node" });
+            return (
+                "503 Service Unavailable",
+                "application/json",
+                body.to_string(),
+            );
+        };
+        let response = actions
+            .handle(kind, header(head, "authorization"), body)
+            .await;
+        let status = match response.status {
+            200 => "200 OK",
+            400 => "400 Bad Request",
+            401 => "401 Unauthorized",
+            403 => "403 Forbidden",
+            501 => "501 Not Implemented",
+            502 => "502 Bad Gateway",
+            _ => "503 Service Unavailable",
+        };
+        (status, "application/json", response.body)
+    }
+
+    /// `POST /admin/*`: pause or resume outbound effects.
+    fn admin(&self, path: &str, head: &str, body: &[u8]) -> (&'static str, &'static str, String) {
+        let error = |status, message: &str| {
+            let body = serde_json::json!({ "error": message });
+            (status, "application/json", body.to_string())
+        };
+        let token = self.config.admin_token.as_deref().unwrap_or_default();
+        let authorized = header(head, "authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| crate::actions::token_matches(given.trim(), token));
+        if !authorized {
+            return error("401 Unauthorized", "missing or wrong bearer token");
+        }
+        let Some(pause) = &self.pause else {
+            return error("503 Service Unavailable", "pause control not available");
+        };
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct Request {
+            actor: Option<String>,
+            reason: Option<String>,
+        }
+        let request: Request = if body.iter().all(u8::is_ascii_whitespace) {
+            Request::default()
+        } else {
+            match serde_json::from_slice(body) {
+                Ok(request) => request,
+                Err(e) => return error("400 Bad Request", &format!("invalid request body: {}", e)),
+            }
+        };
+        let trigger = Trigger {
+            actor: request.actor,
+            reason: request.reason,
+            ..Trigger::new("http")
+        };
+        let result: Result<PauseStatus, GovernanceError> = if path == "/admin/pause" {
+            pause.pause(trigger)
+        } else {
+            pause.resume(trigger)
+        };
+        match result {
+            Ok(status) => (
+                "200 OK",
+                "application/json",
+                serde_json::to_string(&status).unwrap_or_default(),
+            ),
+            Err(e) => error("500 Internal Server Error", &e.to_string()),
+        }
+    }
+
+    /// `GET` of a query, once connected to the node, and the tag of its answer.
+    async fn query(
+        &self,
+        path: &str,
+        query: &str,
+        head: &str,
+    ) -> ((&'static str, &'static str, String), Option<String>) {
+        let queries = self.queries.lock().unwrap().clone();
+        let Some(queries) = queries else {
+            let body = serde_json::json!({ "error": "not connected to the node" });
+            let answer = (
+                "503 Service Unavailable",
+                "application/json",
+                body.to_string(),
+            );
+            return (answer, None);
+        };
+        let response = queries
+            .handle(
+                path,
+                query,
+                header(head, "authorization"),
+                header(head, "if-none-match"),
+            )
+            .await;
+        let status = match response.status {
+            200 => "200 OK",
+            304 => "304 Not Modified",
+            400 => "400 Bad Request",
+            401 => "401 Unauthorized",
+            404 => "404 Not Found",
+            503 => "503 Service Unavailable",
+            _ => "500 Internal Server Error",
+        };
+        ((status, "application/json", response.body), response.etag)
+    }
+
+    /// `GET /feed.atom` or `/feed.rss`, linking to the address the request was sent to, and
+    /// its caching headers.
+    fn feed(&self, path: &str, head: &str) -> ((&'static str, &'static str, String), String) {
+        let (Some(feed), Some(format)) = (&self.feed, FeedFormat::from_path(path)) else {
+            return (
+                ("404 Not Found", "text/plain", String::new()),
+                String::new(),
+            );
+        };
+        let host = header(head, "host")
+            .or(self.config.listen.as_deref())
+            .unwrap_or("localhost");
+        let response = feed.respond(
+            format,
+            &format!("http://{}", host),
+            header(head, "if-none-match"),
+        );
+        let status = match response.status {
+            304 => "304 Not Modified",
+            _ => "200 OK",
+        };
+        (
+            (status, response.content_type, response.body),
+            response.headers,
+        )
+    }
+
+    /// `POST /integrations/github`: a webhook delivery, once connected to the node.
+    async fn receive_github(
+        &self,
+        head: &str,
+        body: &[u8],
+    ) -> (&'static str, &'static str, String) {
+        let github = self.github.lock().unwrap().clone();
+        let Some(github) = github else {
+            let body = serde_json::json!({ "error": "not connected to the node" });
+            return (
+                "503 Service Unavailable",
+                "application/json",
+                body.to_string(),
+            );
+        };
+        let response = github
+            .handle(
+                header(head, "x-github-event"),
+                header(head, "x-hub-signature-256"),
+                body,
+            )
+            .await;
+        let status = match response.status {
+            200 => "200 OK",
+            400 => "400 Bad Request",
+            401 => "401 Unauthorized",
+            _ => "502 Bad Gateway",
+        };
+        (status, "application/json", response.body)
+    }
+
+    async fn answer(&self, mut probe: Probe, detail: bool) -> (&'static str, &'static str, String) {
+        if detail {
+            probe.module = self.module_status().await;
+        }
+        let status = if probe.is_ok() {
+            "200 OK"
+        } else {
+            "503 Service Unavailable"
+        };
+        let body = serde_json::to_string(&probe).unwrap_or_default();
+        (status, "application/json", body)
+    }
+
+    /// The metrics in the Prometheus text format, empty without [`Self::with_metrics`].
+    pub async fn scrape(&self) -> String {
+        let Some(metrics) = &self.metrics else {
+            return String::new();
+        };
+        // The lock is not held across the await
+        let sources = self.connection.lock().unwrap().clone();
+        let status = self.module_status().await;
+        crate::prometheus::render(metrics, sources.as_ref(), status.as_ref()).await
+    }
+}
+
+/// The value of header `name` in `head`, matched without regard to case.
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// The request line and headers, and the body as long as `Content-Length` says, or `None` if
+/// the client closed the connection or sent too much.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<(String, Vec<u8>)>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    let (head, mut body) = loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        request.extend_from_slice(&buf[..n]);
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_string();
+            break (head, request.split_off(end + 4));
+        }
+        if request.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+    };
+    let length = header(&head, "content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Ok(None);
+    }
+    while body.len() < length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    body.truncate(length);
+    Ok(Some((head, body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health() -> Health {
+        Health::new(
+            HealthConfig::default(),
+            Arc::new(Shutdown::new()),
+            Arc::new(Heartbeat::new()),
+            Arc::new(EventSubscriptions::new([])),
+        )
+    }
+
+    #[test]
+    fn test_probes_before_connecting() {
+        let health = health();
+        assert!(health.healthz().is_ok());
+
+        let ready = health.readyz();
+        assert!(!ready.is_ok());
+        assert_eq!(ready.checks["node"].detail, "not connected");
+        assert!(!ready.checks["subscriptions"].ok);
+        assert!(ready.checks["webhook"].ok);
+        assert!(ready.checks["shutdown"].ok);
+
+        health.shutdown.begin();
+        assert!(!health.readyz().checks["shutdown"].ok);
+    }
+
+    #[test]
+    fn test_header() {
+        let head = "POST /actions/veto HTTP/1.1\r\nHost: x\r\nAuthorization:  Bearer t0k\r\n";
+        assert_eq!(header(head, "authorization"), Some("Bearer t0k"));
+        assert_eq!(header(head, "content-length"), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! IPC connection heartbeat
+//!
+//! A half-open socket (node frozen, fd still open) never closes the event receiver, so the
+//! module would wait forever. The heartbeat issues a cheap `get_block_height` request every
+//! `interval_secs`; a request that fails or gets no response within the interval is a miss,
+//! and `max_missed` consecutive misses mark the connection dead so it is torn down and the
+//! reconnect loop takes over. Heartbeats are ordinary requests, so they are correlated with
+//! their responses like any other and do not disturb requests in flight.
+
+use crate::config::HeartbeatConfig;
+use blvm_node::module::traits::NodeAPI;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// IPC liveness, for health reporting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeartbeatStatus {
+    /// Unix seconds of the last answered heartbeat on the current connection.
+    pub last_heartbeat_at: Option<u64>,
+    /// Round-trip time of the last answered heartbeat, in milliseconds.
+    pub round_trip_ms: Option<u64>,
+    pub consecutive_misses: u32,
+    pub dead: bool,
+}
+
+/// Heartbeat state of the current connection.
+#[derive(Debug, Default)]
+pub struct Heartbeat {
+    status: Mutex<HeartbeatStatus>,
+    dead: Notify,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> HeartbeatStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Start over for a new connection.
+    pub fn reset(&self) {
+        *self.status.lock().unwrap() = HeartbeatStatus::default();
+    }
+
+    fn record_response(&self, round_trip: Duration) {
+        let mut status = self.status.lock().unwrap();
+        status.last_heartbeat_at = Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        );
+        status.round_trip_ms = Some(round_trip.as_millis() as u64);
+        status.consecutive_misses = 0;
+    }
+
+    /// Record a missed heartbeat. Returns true when the connection is now dead.
+    fn record_miss(&self, max_missed: u32) -> bool {
+        let mut status = self.status.lock().unwrap();
+        status.consecutive_misses += 1;
+        if status.consecutive_misses >= max_missed.max(1) && !status.dead {
+            status.dead = true;
+            self.dead.notify_one();
+            return true;
+        }
+        false
+    }
+
+    /// Resolves once the connection has been marked dead.
+    pub async fn dead(&self) {
+        // A permit left over from a connection that has since been reset is ignored
+        loop {
+            self.dead.notified().await;
+            if self.status().dead {
+                return;
+            }
+        }
+    }
+
+    /// Send heartbeats over `node_api` until the task is aborted or the connection is dead.
+    pub fn spawn(
+        self: &Arc<Self>,
+        node_api: Arc<dyn NodeAPI>,
+        config: HeartbeatConfig,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if config.interval_secs == 0 {
+            return None;
+        }
+        let heartbeat = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let period = Duration::from_secs(config.interval_secs);
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let sent_at = Instant::now();
+                match tokio::time::timeout(period, node_api.get_block_height()).await {
+                    Ok(Ok(_)) => {
+                        heartbeat.record_response(sent_at.elapsed());
+                        continue;
+                    }
+                    Ok(Err(e)) => warn!("Heartbeat to node failed: {}", e),
+                    Err(_) => warn!("Heartbeat to node timed out after {:?}", period),
+                }
+                if heartbeat.record_miss(config.max_missed) {
+                    warn!(
+                        "No heartbeat response from node in {} attempts, dropping connection",
+                        config.max_missed
+                    );
+                    return;
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dead_after_max_missed() {
+        let heartbeat = Heartbeat::new();
+        assert!(!heartbeat.record_miss(3));
+        heartbeat.record_response(Duration::from_millis(7));
+        let status = heartbeat.status();
+        assert_eq!(status.consecutive_misses, 0);
+        assert_eq!(status.round_trip_ms, Some(7));
+        assert!(status.last_heartbeat_at.is_some());
+
+        assert!(!heartbeat.record_miss(3));
+        assert!(!heartbeat.record_miss(3));
+        assert!(heartbeat.record_miss(3));
+        assert!(heartbeat.status().dead);
+        // Notified before anyone waited; the permit is kept
+        tokio::time::timeout(Duration::from_secs(1), heartbeat.dead())
+            .await
+            .unwrap();
+
+        heartbeat.reset();
+        assert_eq!(heartbeat.status(), HeartbeatStatus::default());
+    }
+}
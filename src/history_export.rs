@@ -0,0 +1,704 @@
+//! Governance history export
+//!
+//! `blvm-governance export-history --out <dir>` writes the governance history kept in the
+//! data directory as flat tables, one file per table, for analysis outside the module:
+//!
+//! - `proposals`: each proposal with its tier, author, state, created and closed heights and
+//!   final tally, from the proposal store (see [`crate::proposals`]);
+//! - `votes`: every vote event received, in order, with the height of the last block received
+//!   before it, from the audit log (see [`crate::audit_log`]); empty without one;
+//! - `vetoes`: every veto cast, with the node's weight at the veto height and the outcome,
+//!   from the registry's node records, archived nodes included;
+//! - `weight_changes`: every change to a node's claimed or verified weight, from the same;
+//! - `signaling`: the share of each epoch's blocks that signaled for each proposal, from the
+//!   stored epoch summaries (see [`crate::epoch_summary`]);
+//! - `participation`: each voter's eligible proposals, votes, participation rate, mean vote
+//!   latency and vetoes, from the stored statistics (see [`crate::participation`]); empty
+//!   while they are not counted.
+//!
+//! `--from-height` and `--to-height` limit rows to a range of heights, bounds included:
+//! proposals by created height, signaling by the epoch's first height. Rows with no known
+//! height are only written without either bound; participation has no height, and is always
+//! written in full. Tables are CSV (RFC 4180, CRLF line endings,
+//! empty fields for unknown values), or Parquet with `--format parquet` when the crate is
+//! built with the `parquet` feature; Parquet columns are nullable UTF-8 strings holding the
+//! CSV field values.
+//!
+//! `manifest.json` lists the tables with their columns and row counts, under
+//! [`SCHEMA_VERSION`], which changes whenever a table's columns do. Rows are sorted, and
+//! nothing in the output depends on when the export ran, so the same store and audit log
+//! always give the same files. Rows are written as they are produced, and the audit log is
+//! read a line at a time, so its size does not bound the export.
+
+use crate::audit_log::{AuditEntry, AuditKind};
+use crate::economic_nodes::history::WeightChangeReason;
+use crate::economic_nodes::{EconomicNode, EconomicNodeRegistry, VetoOutcome};
+use crate::epoch_summary::EpochSummarizer;
+use crate::error::GovernanceError;
+use crate::participation::ParticipationAnalyzer;
+use crate::proposals::ProposalStore;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Version of the tables' layout, recorded in the manifest.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Name of the manifest file in the output directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A table and its columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [&'static str],
+}
+
+pub const PROPOSALS: Table = Table {
+    name: "proposals",
+    columns: &[
+        "proposal_id",
+        "repository",
+        "pr_number",
+        "tier",
+        "author",
+        "status",
+        "created_height",
+        "closed_height",
+        "yes",
+        "no",
+        "abstain",
+    ],
+};
+
+pub const VOTES: Table = Table {
+    name: "votes",
+    columns: &[
+        "audit_seq",
+        "timestamp",
+        "height",
+        "proposal_id",
+        "voter",
+        "vote",
+    ],
+};
+
+pub const VETOES: Table = Table {
+    name: "vetoes",
+    columns: &[
+        "height",
+        "node_id",
+        "proposal_id",
+        "weight",
+        "outcome",
+        "reason",
+        "archived",
+    ],
+};
+
+pub const WEIGHT_CHANGES: Table = Table {
+    name: "weight_changes",
+    columns: &[
+        "height",
+        "node_id",
+        "reason",
+        "old_weight",
+        "new_weight",
+        "old_verified_sats",
+        "new_verified_sats",
+    ],
+};
+
+pub const SIGNALING: Table = Table {
+    name: "signaling",
+    columns: &[
+        "epoch",
+        "start_height",
+        "end_height",
+        "proposal_id",
+        "bit",
+        "signaling",
+        "blocks",
+        "percent",
+    ],
+};
+
+pub const PARTICIPATION: Table = Table {
+    name: "participation",
+    columns: &[
+        "voter",
+        "eligible",
+        "voted",
+        "participation_rate",
+        "mean_latency_blocks",
+        "vetoes",
+    ],
+};
+
+/// File format of the tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryFormat {
+    Csv,
+    /// Needs the `parquet` feature.
+    Parquet,
+}
+
+impl HistoryFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+/// Heights rows are limited to, bounds included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryRange {
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+}
+
+impl HistoryRange {
+    fn is_unbounded(&self) -> bool {
+        self.from_height.is_none() && self.to_height.is_none()
+    }
+
+    /// Whether a row at `height` is in the range; one with no known height only is when
+    /// the range is unbounded.
+    pub fn contains(&self, height: Option<u64>) -> bool {
+        let Some(height) = height else {
+            return self.is_unbounded();
+        };
+        self.from_height.is_none_or(|from| height >= from)
+            && self.to_height.is_none_or(|to| height <= to)
+    }
+}
+
+/// One table as written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableManifest {
+    pub name: String,
+    pub file: String,
+    pub columns: Vec<String>,
+    pub rows: u64,
+}
+
+/// What an export wrote, as in `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Manifest {
+    pub schema_version: u32,
+    pub format: HistoryFormat,
+    #[serde(flatten)]
+    pub range: HistoryRange,
+    pub tables: Vec<TableManifest>,
+}
+
+/// Where a table's rows go.
+enum Sink {
+    Csv(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet_file::ParquetTable),
+}
+
+/// Writes the rows of one table to its file.
+struct TableWriter {
+    table: Table,
+    path: PathBuf,
+    sink: Sink,
+    rows: u64,
+}
+
+impl TableWriter {
+    fn create(dir: &Path, table: Table, format: HistoryFormat) -> Result<Self, GovernanceError> {
+        let path = dir.join(format!("{}.{}", table.name, format.extension()));
+        let file = File::create(&path).map_err(GovernanceError::io(path.display()))?;
+        let sink = match format {
+            HistoryFormat::Csv => {
+                let mut out = BufWriter::new(file);
+                write!(out, "{}\r\n", table.columns.join(","))
+                    .map_err(GovernanceError::io(path.display()))?;
+                Sink::Csv(out)
+            }
+            #[cfg(feature = "parquet")]
+            HistoryFormat::Parquet => Sink::Parquet(parquet_file::ParquetTable::create(
+                file,
+                table.columns,
+                &path,
+            )?),
+            #[cfg(not(feature = "parquet"))]
+            HistoryFormat::Parquet => unreachable!("checked by export_history"),
+        };
+        Ok(Self {
+            table,
+            path,
+            sink,
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, row: Vec<String>) -> Result<(), GovernanceError> {
+        debug_assert_eq!(row.len(), self.table.columns.len());
+        match &mut self.sink {
+            Sink::Csv(out) => {
+                let fields: Vec<_> = row
+                    .iter()
+                    .map(|f| crate::economic_nodes::export::csv_field(f))
+                    .collect();
+                write!(out, "{}\r\n", fields.join(","))
+                    .map_err(GovernanceError::io(self.path.display()))?;
+            }
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(table) => table.write(row, &self.path)?,
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<TableManifest, GovernanceError> {
+        match self.sink {
+            Sink::Csv(mut out) => out
+                .flush()
+                .map_err(GovernanceError::io(self.path.display()))?,
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(table) => table.finish(&self.path)?,
+        }
+        Ok(TableManifest {
+            name: self.table.name.to_string(),
+            file: self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            columns: self.table.columns.iter().map(|c| c.to_string()).collect(),
+            rows: self.rows,
+        })
+    }
+}
+
+/// `value`, or an empty field.
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Write the history in `db`, and the votes in the audit log in `audit_dir` if given, to
+/// `out`, which is created if needed, and return the manifest written with it.
+pub fn export_history(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    audit_dir: Option<&Path>,
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<Manifest, GovernanceError> {
+    if format == HistoryFormat::Parquet && !cfg!(feature = "parquet") {
+        return Err(GovernanceError::ConfigError(
+            "--format parquet needs a build with the parquet feature".to_string(),
+        ));
+    }
+    std::fs::create_dir_all(out).map_err(GovernanceError::io(out.display()))?;
+    let mut nodes: Vec<(EconomicNode, bool)> = EconomicNodeRegistry::load_from(db)?
+        .into_values()
+        .map(|node| (node, false))
+        .collect();
+    nodes.extend(
+        EconomicNodeRegistry::load_archive_from(db)?
+            .into_values()
+            .map(|archived| (archived.node, true)),
+    );
+    nodes.sort_by_key(|(node, archived)| (node.node_id, *archived));
+    let tables = vec![
+        write_proposals(db, range, out, format)?,
+        write_votes(audit_dir, range, out, format)?,
+        write_vetoes(&nodes, range, out, format)?,
+        write_weight_changes(&nodes, range, out, format)?,
+        write_signaling(db, range, out, format)?,
+        write_participation(db, out, format)?,
+    ];
+    let manifest = Manifest {
+        schema_version: SCHEMA_VERSION,
+        format,
+        range,
+        tables,
+    };
+    let path = out.join(MANIFEST_FILE);
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(GovernanceError::serialization("export-history"))?;
+    std::fs::write(&path, json).map_err(GovernanceError::io(path.display()))?;
+    Ok(manifest)
+}
+
+fn write_proposals(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut proposals: Vec<_> = ProposalStore::load_for_display(db)?
+        .into_iter()
+        .filter(|p| range.contains(p.created_height))
+        .collect();
+    proposals.sort_by(|a, b| {
+        (a.created_height, &a.proposal_id).cmp(&(b.created_height, &b.proposal_id))
+    });
+    let mut table = TableWriter::create(out, PROPOSALS, format)?;
+    for p in proposals {
+        let tally = p.tally();
+        table.write(vec![
+            p.proposal_id.clone(),
+            p.repository.clone(),
+            p.pr_number.to_string(),
+            p.tier.clone(),
+            p.author.clone().unwrap_or_default(),
+            p.status.as_str().to_string(),
+            optional(p.created_height),
+            optional(p.closed_height),
+            tally.yes.to_string(),
+            tally.no.to_string(),
+            tally.abstain.to_string(),
+        ])?;
+    }
+    table.finish()
+}
+
+/// Vote events in the audit log in `audit_dir`, read a line at a time.
+fn write_votes(
+    audit_dir: Option<&Path>,
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut table = TableWriter::create(out, VOTES, format)?;
+    let files = match audit_dir {
+        Some(dir) => crate::audit_log::files(dir)?,
+        None => Vec::new(),
+    };
+    let mut height = None;
+    for (_, path) in files {
+        let file = File::open(&path).map_err(GovernanceError::io(path.display()))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(GovernanceError::io(path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let operation = format!("{}:{}", path.display(), i + 1);
+            let entry: AuditEntry =
+                serde_json::from_str(&line).map_err(GovernanceError::serialization(&operation))?;
+            if entry.kind != AuditKind::Event {
+                continue;
+            }
+            let event = match crate::replay::decode::<EventMessage>(&entry.data, "event") {
+                Some(Ok(event)) => event,
+                Some(Err(e)) => {
+                    warn!("Skipping entry {} at {}: {}", entry.seq, operation, e);
+                    continue;
+                }
+                None => continue,
+            };
+            match event.payload {
+                EventPayload::NewBlock { height: h, .. } => height = Some(h),
+                EventPayload::GovernanceProposalVoted {
+                    proposal_id,
+                    voter,
+                    vote,
+                } if range.contains(height) => table.write(vec![
+                    entry.seq.to_string(),
+                    entry.timestamp.to_string(),
+                    optional(height),
+                    proposal_id,
+                    voter,
+                    vote,
+                ])?,
+                _ => {}
+            }
+        }
+    }
+    table.finish()
+}
+
+/// The claimed weight of `node` at `height`, from its weight history.
+fn weight_at(node: &EconomicNode, height: u64) -> f64 {
+    let history = &node.weight_history;
+    match history.iter().rev().find(|r| r.height <= height) {
+        Some(record) => record.new.weight,
+        None => history
+            .first()
+            .map_or(node.hashpower_percentage, |record| record.old.weight),
+    }
+}
+
+fn write_vetoes(
+    nodes: &[(EconomicNode, bool)],
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut rows = Vec::new();
+    for (node, archived) in nodes {
+        for veto in &node.veto_history {
+            if !range.contains(Some(veto.height)) {
+                continue;
+            }
+            let outcome = veto.outcome.map(|outcome| match outcome {
+                VetoOutcome::Failed => "failed",
+                VetoOutcome::MergedAnyway => "merged_anyway",
+            });
+            rows.push(vec![
+                veto.height.to_string(),
+                hex::encode(node.node_id),
+                veto.proposal_id.clone(),
+                weight_at(node, veto.height).to_string(),
+                optional(outcome),
+                veto.reason.clone(),
+                archived.to_string(),
+            ]);
+        }
+    }
+    write_sorted_by_height(rows, VETOES, out, format)
+}
+
+fn write_weight_changes(
+    nodes: &[(EconomicNode, bool)],
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut rows = Vec::new();
+    for (node, _) in nodes {
+        for record in &node.weight_history {
+            if !range.contains(Some(record.height)) {
+                continue;
+            }
+            let reason = match record.reason {
+                WeightChangeReason::Registration => "registration",
+                WeightChangeReason::Reconciliation => "reconciliation",
+                WeightChangeReason::Reverification => "reverification",
+            };
+            rows.push(vec![
+                record.height.to_string(),
+                hex::encode(node.node_id),
+                reason.to_string(),
+                record.old.weight.to_string(),
+                record.new.weight.to_string(),
+                record.old.verified_sats.to_string(),
+                record.new.verified_sats.to_string(),
+            ]);
+        }
+    }
+    write_sorted_by_height(rows, WEIGHT_CHANGES, out, format)
+}
+
+/// Write `rows`, whose first column is a height, by height and then the other columns.
+fn write_sorted_by_height(
+    mut rows: Vec<Vec<String>>,
+    table: Table,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    rows.sort_by(|a, b| {
+        let height = |row: &[String]| row[0].parse::<u64>().unwrap_or_default();
+        (height(a), &a[1..]).cmp(&(height(b), &b[1..]))
+    });
+    let mut writer = TableWriter::create(out, table, format)?;
+    for row in rows {
+        writer.write(row)?;
+    }
+    writer.finish()
+}
+
+fn write_signaling(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    range: HistoryRange,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut table = TableWriter::create(out, SIGNALING, format)?;
+    for summary in EpochSummarizer::load_from(db)?.into_values() {
+        if !range.contains(Some(summary.start_height)) {
+            continue;
+        }
+        for count in summary.signaling {
+            table.write(vec![
+                summary.epoch.to_string(),
+                summary.start_height.to_string(),
+                summary.end_height.to_string(),
+                count.proposal_id,
+                count.bit.to_string(),
+                count.signaling.to_string(),
+                count.blocks.to_string(),
+                count.percent.to_string(),
+            ])?;
+        }
+    }
+    table.finish()
+}
+
+fn write_participation(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    out: &Path,
+    format: HistoryFormat,
+) -> Result<TableManifest, GovernanceError> {
+    let mut table = TableWriter::create(out, PARTICIPATION, format)?;
+    for stats in ParticipationAnalyzer::load_from(db)?.voters.into_values() {
+        table.write(vec![
+            stats.voter.clone(),
+            stats.eligible.to_string(),
+            stats.voted.to_string(),
+            optional(stats.rate()),
+            optional(stats.mean_latency_blocks()),
+            stats.vetoes.to_string(),
+        ])?;
+    }
+    table.finish()
+}
+
+/// Parquet files of nullable UTF-8 columns, written a row group at a time.
+#[cfg(feature = "parquet")]
+mod parquet_file {
+    use crate::error::GovernanceError;
+    use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+    use parquet::data_type::{ByteArray, ByteArrayType};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::types::Type;
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// Rows buffered before they are written as one row group.
+    const ROW_GROUP_ROWS: usize = 10_000;
+
+    fn failed(path: &Path) -> impl FnOnce(parquet::errors::ParquetError) -> GovernanceError + '_ {
+        move |e| GovernanceError::Storage(format!("writing {}: {}", path.display(), e))
+    }
+
+    pub struct ParquetTable {
+        writer: SerializedFileWriter<File>,
+        rows: Vec<Vec<String>>,
+    }
+
+    impl ParquetTable {
+        pub fn create(file: File, columns: &[&str], path: &Path) -> Result<Self, GovernanceError> {
+            let fields = columns
+                .iter()
+                .map(|name| {
+                    Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+                        .with_repetition(Repetition::OPTIONAL)
+                        .with_logical_type(Some(LogicalType::String))
+                        .build()
+                        .map(Arc::new)
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(failed(path))?;
+            let schema = Type::group_type_builder("schema")
+                .with_fields(fields)
+                .build()
+                .map_err(failed(path))?;
+            let properties = WriterProperties::builder()
+                .set_created_by("blvm-governance".to_string())
+                .build();
+            let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+                .map_err(failed(path))?;
+            Ok(Self {
+                writer,
+                rows: Vec::new(),
+            })
+        }
+
+        pub fn write(&mut self, row: Vec<String>, path: &Path) -> Result<(), GovernanceError> {
+            self.rows.push(row);
+            if self.rows.len() >= ROW_GROUP_ROWS {
+                self.flush(path)?;
+            }
+            Ok(())
+        }
+
+        /// Write the buffered rows as a row group; empty fields are nulls.
+        fn flush(&mut self, path: &Path) -> Result<(), GovernanceError> {
+            if self.rows.is_empty() {
+                return Ok(());
+            }
+            let rows = std::mem::take(&mut self.rows);
+            let mut group = self.writer.next_row_group().map_err(failed(path))?;
+            let mut index = 0;
+            while let Some(mut column) = group.next_column().map_err(failed(path))? {
+                let values: Vec<ByteArray> = rows
+                    .iter()
+                    .filter(|row| !row[index].is_empty())
+                    .map(|row| ByteArray::from(row[index].as_str()))
+                    .collect();
+                let levels: Vec<i16> = rows
+                    .iter()
+                    .map(|row| i16::from(!row[index].is_empty()))
+                    .collect();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&levels), None)
+                    .map_err(failed(path))?;
+                column.close().map_err(failed(path))?;
+                index += 1;
+            }
+            group.close().map_err(failed(path))?;
+            Ok(())
+        }
+
+        pub fn finish(mut self, path: &Path) -> Result<(), GovernanceError> {
+            self.flush(path)?;
+            self.writer.close().map_err(failed(path))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_bounds() {
+        let range = HistoryRange {
+            from_height: Some(100),
+            to_height: Some(200),
+        };
+        assert!(range.contains(Some(100)));
+        assert!(range.contains(Some(200)));
+        assert!(!range.contains(Some(201)));
+        assert!(!range.contains(None));
+        assert!(HistoryRange::default().contains(None));
+    }
+
+    #[test]
+    fn test_weight_at_a_height() {
+        use crate::economic_nodes::history::{WeightPoint, WeightRecord};
+
+        let point = |weight| WeightPoint {
+            weight,
+            verified_sats: 0,
+        };
+        let mut node = EconomicNode::new([1; 32], 10);
+        node.hashpower_percentage = 30.0;
+        assert_eq!(weight_at(&node, 5), 30.0);
+        node.weight_history = vec![
+            WeightRecord {
+                height: 20,
+                old: point(10.0),
+                new: point(20.0),
+                reason: WeightChangeReason::Registration,
+            },
+            WeightRecord {
+                height: 40,
+                old: point(20.0),
+                new: point(30.0),
+                reason: WeightChangeReason::Reconciliation,
+            },
+        ];
+        assert_eq!(weight_at(&node, 15), 10.0);
+        assert_eq!(weight_at(&node, 20), 20.0);
+        assert_eq!(weight_at(&node, 39), 20.0);
+        assert_eq!(weight_at(&node, 100), 30.0);
+    }
+}
@@ -0,0 +1,165 @@
+//! Write-ahead log of side effects
+//!
+//! A side effect derived from an event, such as a webhook notification or a veto result
+//! submitted to the node, is recorded in `intents.json` in the data directory's `state/`
+//! before it is carried out, with the trace id of the event (see [`crate::trace`]), and
+//! marked complete afterwards. The file is written atomically on each change, as the
+//! checkpoint is (see [`crate::checkpoint`]).
+//!
+//! An intent still pending when the module starts was cut short by a crash or a lost
+//! connection. Before any new event is processed, the [`Pipeline`](crate::pipeline::Pipeline)
+//! passes each one to the handler that recorded it
+//! ([`EventHandler::execute_intent`](crate::pipeline::EventHandler::execute_intent)), oldest
+//! first, and completes it once the handler has carried it out. Handlers must execute their
+//! intents idempotently, as the effect may have happened before the crash: webhook receivers
+//! can tell a repeated notification by its `trace_id`, and the node takes the same veto
+//! result twice without harm. Re-executing an intent is how the effect is recovered; the
+//! event itself is not replayed, so the change that caused it is not applied twice.
+//!
+//! Intents that cannot be executed, because their handler is gone or failed again, stay
+//! pending for the next start.
+
+use crate::checkpoint::write_atomic;
+use crate::error::GovernanceError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const INTENTS_FILE: &str = "intents.json";
+
+/// A side effect recorded before it is carried out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Intent {
+    /// Number of the intent, increasing across restarts.
+    pub id: u64,
+    /// Name of the handler that carries it out.
+    pub handler: String,
+    /// What to do, as the handler names it.
+    pub kind: String,
+    /// What the handler needs to do it again.
+    pub data: serde_json::Value,
+    /// Trace id of the event it derives from, if any.
+    pub trace_id: Option<String>,
+    /// Unix seconds.
+    pub created: u64,
+}
+
+/// Contents of `intents.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct IntentsFile {
+    next_id: u64,
+    pending: Vec<Intent>,
+}
+
+#[derive(Debug)]
+pub struct IntentLog {
+    path: PathBuf,
+    state: Mutex<IntentsFile>,
+}
+
+impl IntentLog {
+    /// Load the intents in `dir`, if there are any.
+    pub fn open(dir: &Path) -> Result<Self, GovernanceError> {
+        let path = dir.join(INTENTS_FILE);
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(GovernanceError::serialization(&path.display().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => IntentsFile::default(),
+            Err(e) => return Err(GovernanceError::io(path.display())(e)),
+        };
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Apply `change` and write the result; the state is unchanged if the write fails.
+    fn update<T>(&self, change: impl FnOnce(&mut IntentsFile) -> T) -> Result<T, GovernanceError> {
+        let mut state = self.state.lock().unwrap();
+        let mut next = state.clone();
+        let result = change(&mut next);
+        let data = serde_json::to_vec(&next).map_err(GovernanceError::serialization("intents"))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(GovernanceError::io(dir.display()))?;
+        }
+        write_atomic(&self.path, &data)?;
+        *state = next;
+        Ok(result)
+    }
+
+    /// Record that `handler` is about to carry out `kind` with `data`, for the event being
+    /// processed. Returns the id to [`complete`](Self::complete) it with.
+    pub fn begin(
+        &self,
+        handler: &str,
+        kind: &str,
+        data: serde_json::Value,
+    ) -> Result<u64, GovernanceError> {
+        let intent = |id| Intent {
+            id,
+            handler: handler.to_string(),
+            kind: kind.to_string(),
+            data,
+            trace_id: crate::trace::current_id(),
+            created: crate::clock::unix_now(),
+        };
+        self.update(|state| {
+            let id = state.next_id;
+            state.next_id += 1;
+            state.pending.push(intent(id));
+            id
+        })
+    }
+
+    /// Record that intent `id` has been carried out. Completing it again does nothing.
+    pub fn complete(&self, id: u64) -> Result<(), GovernanceError> {
+        let pending = self
+            .state
+            .lock()
+            .unwrap()
+            .pending
+            .iter()
+            .any(|i| i.id == id);
+        if !pending {
+            return Ok(());
+        }
+        self.update(|state| state.pending.retain(|i| i.id != id))
+    }
+
+    /// Intents not yet completed, oldest first.
+    pub fn pending(&self) -> Vec<Intent> {
+        self.state.lock().unwrap().pending.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_until_completed_across_reopen() {
+        let dir = std::env::temp_dir().join(format!("blvm_intents_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let log = IntentLog::open(&dir).unwrap();
+        let first = log
+            .begin("webhook", "notify", serde_json::json!({ "n": 1 }))
+            .unwrap();
+        let second = log
+            .begin("webhook", "notify", serde_json::json!({ "n": 2 }))
+            .unwrap();
+        log.complete(first).unwrap();
+        log.complete(first).unwrap();
+
+        let reopened = IntentLog::open(&dir).unwrap();
+        let pending = reopened.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second);
+        assert_eq!(pending[0].data["n"], 2);
+        assert_eq!(pending[0].trace_id, None);
+        // Ids are not reused after a restart
+        let third = reopened.begin("webhook", "block", serde_json::Value::Null);
+        assert_eq!(third.unwrap(), second + 1);
+        assert!(!dir.join("intents.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,406 @@
+//! IPC request and event metrics
+//!
+//! Per-method request counters and latency histograms for calls to the node, the time
+//! requests wait for an in-flight permit by priority class, and counts of events received
+//! per type. Everything is a fixed set of atomics, so recording never allocates; snapshots
+//! are taken on demand for `get_ipc_status` and the periodic summary.
+
+use crate::node_api::Priority;
+use blvm_node::module::EventType;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Upper bounds of the latency buckets, in milliseconds; a last bucket holds the rest.
+const BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+/// Requests to the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcMethod {
+    GetBlockHeight,
+    GetChainInfo,
+    GetBlock,
+    GetBlockByHeight,
+    GetBlockHeader,
+    GetTransaction,
+    GetMempoolTxids,
+    GetMempoolTransaction,
+    GetMempoolEntry,
+    GetUtxo,
+    ListEconomicNodes,
+    GetAddressBalance,
+    GetProposal,
+    SubmitVetoResult,
+    SubmitGovernanceAction,
+    EstimateFeeRate,
+    GetMinRelayFee,
+    GetBlockChainwork,
+    FundOpReturn,
+    BroadcastTransaction,
+    GetNetwork,
+}
+
+impl IpcMethod {
+    pub const ALL: [IpcMethod; 21] = [
+        Self::GetBlockHeight,
+        Self::GetChainInfo,
+        Self::GetBlock,
+        Self::GetBlockByHeight,
+        Self::GetBlockHeader,
+        Self::GetTransaction,
+        Self::GetMempoolTxids,
+        Self::GetMempoolTransaction,
+        Self::GetMempoolEntry,
+        Self::GetUtxo,
+        Self::ListEconomicNodes,
+        Self::GetAddressBalance,
+        Self::GetProposal,
+        Self::SubmitVetoResult,
+        Self::SubmitGovernanceAction,
+        Self::EstimateFeeRate,
+        Self::GetMinRelayFee,
+        Self::GetBlockChainwork,
+        Self::FundOpReturn,
+        Self::BroadcastTransaction,
+        Self::GetNetwork,
+    ];
+
+    /// The method named `name`, as in [`Self::as_str`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.as_str() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::GetBlockHeight => "get_block_height",
+            Self::GetChainInfo => "get_chain_info",
+            Self::GetBlock => "get_block",
+            Self::GetBlockByHeight => "get_block_by_height",
+            Self::GetBlockHeader => "get_block_header",
+            Self::GetTransaction => "get_transaction",
+            Self::GetMempoolTxids => "get_mempool_transactions",
+            Self::GetMempoolTransaction => "get_mempool_transaction",
+            Self::GetMempoolEntry => "get_mempool_entry",
+            Self::GetUtxo => "get_utxo",
+            Self::ListEconomicNodes => "list_economic_nodes",
+            Self::GetAddressBalance => "get_address_balance",
+            Self::GetProposal => "get_proposal",
+            Self::SubmitVetoResult => "submit_veto_result",
+            Self::SubmitGovernanceAction => "submit_governance_action",
+            Self::EstimateFeeRate => "get_fee_estimate",
+            Self::GetMinRelayFee => "get_min_relay_fee",
+            Self::GetBlockChainwork => "get_block_chainwork",
+            Self::FundOpReturn => "fund_op_return",
+            Self::BroadcastTransaction => "broadcast_transaction",
+            Self::GetNetwork => "get_network",
+        }
+    }
+}
+
+/// Event types counted individually; anything else is counted as "other".
+const EVENT_TYPES: [(EventType, &str); 6] = [
+    (
+        EventType::GovernanceProposalCreated,
+        "GovernanceProposalCreated",
+    ),
+    (
+        EventType::GovernanceProposalVoted,
+        "GovernanceProposalVoted",
+    ),
+    (
+        EventType::GovernanceProposalMerged,
+        "GovernanceProposalMerged",
+    ),
+    (EventType::EconomicNodeRegistered, "EconomicNodeRegistered"),
+    (EventType::EconomicNodeVeto, "EconomicNodeVeto"),
+    (EventType::NewBlock, "NewBlock"),
+];
+
+/// How a request ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    Error,
+    Timeout,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+    sum_ms: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        self.sum_ms.fetch_add(ms, Ordering::Relaxed);
+        let i = BUCKETS_MS
+            .iter()
+            .position(|&b| ms <= b)
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count(&self) -> u64 {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Upper bound of the bucket holding quantile `q`; `None` without samples or when the
+    /// quantile falls past the last bound.
+    fn quantile_ms(&self, q: f64) -> Option<u64> {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((total as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKETS_MS.get(i).copied();
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodMetrics {
+    sent: AtomicU64,
+    ok: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    bytes_written: AtomicU64,
+    bytes_read: AtomicU64,
+    latency: Histogram,
+}
+
+/// Snapshot of one method's metrics.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MethodSnapshot {
+    pub sent: u64,
+    pub ok: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    /// Payload bytes of module calls; typed requests are not counted.
+    pub bytes_written: u64,
+    pub bytes_read: u64,
+    pub p50_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+/// Latency samples of one method, for exposition in full (see [`crate::prometheus`]).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyBuckets {
+    /// Upper bounds of the buckets, in milliseconds.
+    pub bounds_ms: &'static [u64],
+    /// Samples per bucket, one more than `bounds_ms` for those above the last bound.
+    pub counts: Vec<u64>,
+    pub sum_ms: u64,
+}
+
+/// Time requests of one priority class waited for an in-flight permit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WaitSnapshot {
+    pub requests: u64,
+    pub p50_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IpcMetricsSnapshot {
+    pub requests: BTreeMap<&'static str, MethodSnapshot>,
+    pub events: BTreeMap<&'static str, u64>,
+    /// Waits for an in-flight permit, by priority class.
+    pub queue_wait: BTreeMap<&'static str, WaitSnapshot>,
+}
+
+#[derive(Debug, Default)]
+pub struct IpcMetrics {
+    methods: [MethodMetrics; IpcMethod::ALL.len()],
+    events: [AtomicU64; EVENT_TYPES.len() + 1],
+    waits: [Histogram; Priority::ALL.len()],
+}
+
+impl IpcMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn method(&self, method: IpcMethod) -> &MethodMetrics {
+        &self.methods[method as usize]
+    }
+
+    /// Record a finished request.
+    pub fn record_request(&self, method: IpcMethod, elapsed: Duration, outcome: Outcome) {
+        let m = self.method(method);
+        m.sent.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            Outcome::Ok => m.ok.fetch_add(1, Ordering::Relaxed),
+            Outcome::Error => m.errors.fetch_add(1, Ordering::Relaxed),
+            Outcome::Timeout => m.timeouts.fetch_add(1, Ordering::Relaxed),
+        };
+        if outcome != Outcome::Timeout {
+            m.latency.record(elapsed);
+        }
+    }
+
+    /// Record how long a request of `priority` waited for an in-flight permit.
+    pub fn record_wait(&self, priority: Priority, waited: Duration) {
+        self.waits[priority as usize].record(waited);
+    }
+
+    /// Record payload sizes of a module call.
+    pub fn record_bytes(&self, method: IpcMethod, written: usize, read: usize) {
+        let m = self.method(method);
+        m.bytes_written.fetch_add(written as u64, Ordering::Relaxed);
+        m.bytes_read.fetch_add(read as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_event(&self, event_type: &EventType) {
+        let i = EVENT_TYPES
+            .iter()
+            .position(|(t, _)| t == event_type)
+            .unwrap_or(EVENT_TYPES.len());
+        self.events[i].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Every latency sample of `method` by bucket.
+    pub fn latency_buckets(&self, method: IpcMethod) -> LatencyBuckets {
+        let latency = &self.method(method).latency;
+        LatencyBuckets {
+            bounds_ms: &BUCKETS_MS,
+            counts: latency
+                .buckets
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+            sum_ms: latency.sum_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn snapshot(&self) -> IpcMetricsSnapshot {
+        let requests = IpcMethod::ALL
+            .iter()
+            .map(|&method| {
+                let m = self.method(method);
+                let snapshot = MethodSnapshot {
+                    sent: m.sent.load(Ordering::Relaxed),
+                    ok: m.ok.load(Ordering::Relaxed),
+                    errors: m.errors.load(Ordering::Relaxed),
+                    timeouts: m.timeouts.load(Ordering::Relaxed),
+                    bytes_written: m.bytes_written.load(Ordering::Relaxed),
+                    bytes_read: m.bytes_read.load(Ordering::Relaxed),
+                    p50_ms: m.latency.quantile_ms(0.5),
+                    p99_ms: m.latency.quantile_ms(0.99),
+                };
+                (method.as_str(), snapshot)
+            })
+            .collect();
+        let events = EVENT_TYPES
+            .iter()
+            .map(|(_, name)| *name)
+            .chain(["other"])
+            .zip(self.events.iter().map(|c| c.load(Ordering::Relaxed)))
+            .collect();
+        let queue_wait = Priority::ALL
+            .iter()
+            .map(|&priority| {
+                let waits = &self.waits[priority as usize];
+                let snapshot = WaitSnapshot {
+                    requests: waits.count(),
+                    p50_ms: waits.quantile_ms(0.5),
+                    p99_ms: waits.quantile_ms(0.99),
+                };
+                (priority.as_str(), snapshot)
+            })
+            .collect();
+        IpcMetricsSnapshot {
+            requests,
+            events,
+            queue_wait,
+        }
+    }
+
+    /// Log a one-line summary every `interval_secs` (0 disables).
+    pub fn spawn_summary(
+        self: &Arc<Self>,
+        interval_secs: u64,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if interval_secs == 0 {
+            return None;
+        }
+        let metrics = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let s = metrics.snapshot();
+                let total = |f: fn(&MethodSnapshot) -> u64| s.requests.values().map(f).sum::<u64>();
+                let slowest = s
+                    .requests
+                    .iter()
+                    .filter_map(|(name, m)| m.p99_ms.map(|p| (p, *name)))
+                    .max();
+                info!(
+                    "IPC: {} requests ({} errors, {} timeouts), slowest p99 {}, {} events",
+                    total(|m| m.sent),
+                    total(|m| m.errors),
+                    total(|m| m.timeouts),
+                    slowest.map_or("-".to_string(), |(p, name)| format!("{}ms ({})", p, name)),
+                    s.events.values().sum::<u64>()
+                );
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_latency() {
+        let metrics = IpcMetrics::new();
+        for ms in [1, 3, 3, 40, 4000] {
+            metrics.record_request(IpcMethod::GetBlock, Duration::from_millis(ms), Outcome::Ok);
+        }
+        metrics.record_request(
+            IpcMethod::GetBlock,
+            Duration::from_secs(30),
+            Outcome::Timeout,
+        );
+        metrics.record_bytes(IpcMethod::ListEconomicNodes, 0, 512);
+        metrics.record_event(&EventType::NewBlock);
+        metrics.record_event(&EventType::WebhookSent);
+
+        let s = metrics.snapshot();
+        let block = &s.requests["get_block"];
+        assert_eq!((block.sent, block.ok, block.timeouts), (6, 5, 1));
+        assert_eq!(block.p50_ms, Some(5));
+        assert_eq!(block.p99_ms, Some(5000));
+        assert_eq!(s.requests["list_economic_nodes"].bytes_read, 512);
+        assert_eq!(s.events["NewBlock"], 1);
+        assert_eq!(s.events["other"], 1);
+        assert_eq!(s.requests["get_utxo"].p50_ms, None);
+        let buckets = metrics.latency_buckets(IpcMethod::GetBlock);
+        assert_eq!(buckets.counts.iter().sum::<u64>(), 5);
+        assert_eq!(buckets.sum_ms, 4047);
+
+        metrics.record_wait(Priority::Bulk, Duration::from_millis(300));
+        metrics.record_wait(Priority::Interactive, Duration::ZERO);
+        let s = metrics.snapshot();
+        assert_eq!(s.queue_wait["bulk"].requests, 1);
+        assert_eq!(s.queue_wait["bulk"].p99_ms, Some(500));
+        assert_eq!(s.queue_wait["interactive"].p50_ms, Some(1));
+    }
+}
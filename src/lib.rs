@@ -1,12 +1,79 @@
 //! Governance webhook and economic node tracking module for blvm-node
 
+pub mod actions;
+pub mod activation;
+pub mod admin;
+pub mod adoption;
+pub mod alert;
+pub mod amendments;
+pub mod anchor;
 pub mod api;
+pub mod audit;
+pub mod audit_log;
+pub mod backup;
+pub mod build_info;
+pub mod chain_work;
+pub mod checkpoint;
+pub mod cli;
+pub mod clock;
 pub mod config;
+pub mod config_check;
+pub mod config_reload;
+pub mod config_template;
+pub mod conflicts;
+pub mod content;
+pub mod crash;
+pub mod dashboard;
+pub mod deadlines;
+pub mod delegation;
+pub mod digest;
 pub mod module;
 pub mod economic_nodes;
+pub mod epoch_summary;
 pub mod error;
+pub mod error_report;
+pub mod escalation;
+pub mod event_queue;
+pub mod event_stream;
+pub mod executor;
+pub mod feed;
+pub mod github;
+pub mod health;
+pub mod heartbeat;
+pub mod history_export;
+pub mod intent;
+pub mod ipc_metrics;
+pub mod log_file;
+pub mod log_forward;
+pub mod logging;
+pub mod memory;
+pub mod network;
+pub mod node_api;
+pub mod participation;
+pub mod payload_format;
+pub mod pause;
+pub mod pipeline;
+pub mod prometheus;
 pub mod proposals;
+pub mod query;
+pub mod reconnect;
+pub mod replay;
+pub mod schema;
+pub mod self_test;
+pub mod shutdown;
+pub mod signaling;
+pub mod socket_check;
+pub mod status;
+pub mod status_report;
 pub mod storage;
+pub mod subscriptions;
+pub mod systemd;
+pub mod tally;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod trace;
+pub mod veto_evidence;
+pub mod vote_check;
 pub mod webhook;
 
 pub use config::GovernanceConfig;
@@ -0,0 +1,251 @@
+//! Log files rotated by size
+//!
+//! With `[governance.logging] dir` set, log output is also written to `blvm-governance.log`
+//! in that directory. When a write would take the file past `max_file_bytes` it is renamed
+//! to `blvm-governance.log.1`, earlier rotations move up by one, and those past `max_files`
+//! are deleted. Rotation happens between records and each record is written in one piece, so
+//! a record is never split across files.
+//!
+//! Records are written by a thread of their own: tasks only queue the formatted record, and
+//! that thread alone owns the file, so concurrent tasks never interleave and a slow disk does
+//! not hold up the runtime (unless the queue fills, when logging waits rather than dropping
+//! records). [`LogFile::flush`] waits for everything queued to be written; the binary calls
+//! it, through [`crate::logging::flush`], before it exits and when anything panics.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Name of the active log file.
+pub const FILE_NAME: &str = "blvm-governance.log";
+
+/// Records queued for the writer thread at most.
+const QUEUE: usize = 10_000;
+
+/// How long [`LogFile::flush`] waits for the writer thread.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The active log file, rotated by size.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file in `dir`, creating `dir` if needed. Fails if the
+    /// directory cannot be created or the file cannot be opened for writing.
+    pub fn open(dir: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let file = Self::open_file(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes: max_bytes.max(1),
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Path of rotated file `n` (1 is the most recent).
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            // The oldest is overwritten by the rename below
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            if let Err(e) = self.rotate() {
+                // Keep writing to the current file rather than losing records
+                eprintln!("Failed to rotate {}: {}", self.path.display(), e);
+            }
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+enum Message {
+    Record(Vec<u8>),
+    /// Answered once everything queued before it is written.
+    Flush(SyncSender<()>),
+}
+
+/// A [`RotatingFile`] written by a thread of its own; a [`MakeWriter`] for the fmt layer.
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    queue: SyncSender<Message>,
+}
+
+impl LogFile {
+    /// Open the log file in `dir` (see [`RotatingFile::open`]) and start its writer thread.
+    pub fn start(dir: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = RotatingFile::open(dir, max_bytes, max_files)?;
+        let (queue, messages) = mpsc::sync_channel(QUEUE);
+        std::thread::Builder::new()
+            .name("log-file".to_string())
+            .spawn(move || write_records(file, messages))?;
+        Ok(Self { queue })
+    }
+
+    /// Wait until every record queued so far is written, for up to 5 seconds.
+    pub fn flush(&self) {
+        let (done, written) = mpsc::sync_channel(1);
+        if self.queue.send(Message::Flush(done)).is_ok() {
+            let _ = written.recv_timeout(FLUSH_TIMEOUT);
+        }
+    }
+}
+
+fn write_records(mut file: RotatingFile, messages: Receiver<Message>) {
+    for message in messages {
+        match message {
+            Message::Record(record) => {
+                if let Err(e) = file.write_all(&record) {
+                    eprintln!("Failed to write {}: {}", file.path.display(), e);
+                }
+            }
+            Message::Flush(done) => {
+                let _ = file.flush();
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl Write for LogFile {
+    /// The fmt layer writes each record with a single call, so a call is a record.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.queue
+            .send(Message::Record(buf.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "log file writer stopped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = LogFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("blvm_log_file_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let mut file = RotatingFile::open(&dir, 20, 2).unwrap();
+        for line in [
+            "first line\n",
+            "second line\n",
+            "third line\n",
+            "fourth line\n",
+        ] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read(FILE_NAME), "fourth line\n");
+        assert_eq!(read("blvm-governance.log.1"), "third line\n");
+        assert_eq!(read("blvm-governance.log.2"), "second line\n");
+        assert!(!dir.join("blvm-governance.log.3").exists());
+
+        // Appends to the existing file after a restart
+        let mut file = RotatingFile::open(&dir, 20, 2).unwrap();
+        file.write_all(b"fifth\n").unwrap();
+        assert_eq!(read(FILE_NAME), "fourth line\nfifth\n");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_unwritable_dir_fails() {
+        // A directory cannot be created under a file
+        let file = std::env::temp_dir().join(format!("blvm_log_not_dir_{}", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        assert!(LogFile::start(&file.join("logs"), 1024, 1).is_err());
+        std::fs::remove_file(&file).ok();
+    }
+
+    #[test]
+    fn test_concurrent_records_are_not_split() {
+        let dir = std::env::temp_dir().join(format!("blvm_log_threads_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let log = LogFile::start(&dir, 4096, 50).unwrap();
+        let writers: Vec<_> = (0..8)
+            .map(|t| {
+                let mut log = log.clone();
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        log.write_all(format!("thread {} record {:03}\n", t, i).as_bytes())
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        log.flush();
+
+        let mut lines = Vec::new();
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(content.len() <= 4096);
+            lines.extend(content.lines().map(str::to_string));
+        }
+        assert_eq!(lines.len(), 8 * 200);
+        assert!(lines
+            .iter()
+            .all(|l| l.starts_with("thread ") && l.len() == "thread 0 record 000".len()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
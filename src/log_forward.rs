@@ -0,0 +1,249 @@
+//! Forwarding module logs to the node
+//!
+//! A tracing layer copies records at or above `[governance.log_forward] level` (independent
+//! of the local log level) into a bounded buffer, and a task sends them to the node with
+//! `call_module(None, "module_log", ..)`, at most `max_per_sec` per second. While the module
+//! is disconnected the task is not running, so records accumulate up to `buffer` and the rest
+//! are dropped; the number dropped is reported to the node once forwarding resumes.
+//!
+//! The level and `max_per_sec` can be changed while running with
+//! [`LogForwarder::reconfigure`]; turning forwarding on or off and the buffer size need a
+//! restart.
+
+use crate::config::LogForwardConfig;
+use blvm_node::module::traits::NodeAPI;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// Records from this module are not forwarded, so forwarding failures cannot feed back.
+const OWN_TARGET: &str = module_path!();
+
+/// One forwarded log record.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogRecord {
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Unix seconds.
+    pub timestamp: u64,
+}
+
+/// Buffer between the tracing layer and the forwarding task.
+#[derive(Debug)]
+pub struct LogForwarder {
+    level: RwLock<Level>,
+    max_per_sec: AtomicU32,
+    capacity: usize,
+    buffer: Mutex<VecDeque<LogRecord>>,
+    dropped: AtomicU64,
+    notify: Notify,
+}
+
+/// The message followed by the other fields as `name=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields
+                .push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl LogForwarder {
+    /// `None` if forwarding is disabled or the level is not recognised.
+    pub fn new(config: &LogForwardConfig) -> Option<Self> {
+        let level = config.level.as_deref()?.parse::<Level>().ok()?;
+        Some(Self {
+            level: RwLock::new(level),
+            max_per_sec: AtomicU32::new(config.max_per_sec.max(1)),
+            capacity: config.buffer.max(1),
+            buffer: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+            notify: Notify::new(),
+        })
+    }
+
+    /// Apply the level and `max_per_sec` from `config`. Returns whether either changed; a
+    /// missing or unrecognised level leaves the current one in place.
+    pub fn reconfigure(&self, config: &LogForwardConfig) -> bool {
+        let mut changed = false;
+        if let Some(level) = config
+            .level
+            .as_deref()
+            .and_then(|l| l.parse::<Level>().ok())
+        {
+            let mut current = self.level.write().unwrap();
+            changed |= *current != level;
+            *current = level;
+        }
+        let max_per_sec = config.max_per_sec.max(1);
+        changed |= self.max_per_sec.swap(max_per_sec, Ordering::Relaxed) != max_per_sec;
+        changed
+    }
+
+    /// Records dropped because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn push(&self, record: LogRecord) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() >= self.capacity {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        buffer.push_back(record);
+        drop(buffer);
+        self.notify.notify_one();
+    }
+
+    /// Take up to `max` records, oldest first.
+    fn take(&self, max: usize) -> Vec<LogRecord> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let n = buffer.len().min(max);
+        buffer.drain(..n).collect()
+    }
+
+    /// Send buffered records to the node until the task is aborted.
+    pub fn spawn(
+        self: &Arc<Self>,
+        node_api: Arc<dyn NodeAPI>,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let forwarder = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut reported_dropped = 0;
+            loop {
+                let max_per_sec = forwarder.max_per_sec.load(Ordering::Relaxed) as usize;
+                let dropped = forwarder.dropped();
+                let mut records = Vec::new();
+                if dropped > reported_dropped {
+                    records.push(LogRecord {
+                        level: Level::WARN.to_string(),
+                        target: OWN_TARGET.to_string(),
+                        message: format!(
+                            "{} log records dropped while the buffer was full",
+                            dropped - reported_dropped
+                        ),
+                        timestamp: now(),
+                    });
+                    reported_dropped = dropped;
+                }
+                records.extend(forwarder.take(max_per_sec - records.len()));
+                if records.is_empty() {
+                    forwarder.notify.notified().await;
+                    continue;
+                }
+                for record in &records {
+                    let Ok(payload) = serde_json::to_vec(record) else {
+                        continue;
+                    };
+                    // Not logged: a failure here would be forwarded again
+                    let _ = node_api.call_module(None, "module_log", payload).await;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }))
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Tracing layer feeding a [`LogForwarder`].
+pub struct LogForwardLayer(pub Arc<LogForwarder>);
+
+impl<S: Subscriber> Layer<S> for LogForwardLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // Level ordering: more verbose levels compare greater
+        if *metadata.level() > *self.0.level.read().unwrap()
+            || metadata.target().starts_with(OWN_TARGET)
+        {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.0.push(LogRecord {
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message + &visitor.fields,
+            timestamp: now(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_forwards_at_level_and_bounds_buffer() {
+        let forwarder = Arc::new(
+            LogForwarder::new(&LogForwardConfig {
+                level: Some("warn".to_string()),
+                max_per_sec: 10,
+                buffer: 2,
+            })
+            .unwrap(),
+        );
+        let subscriber =
+            tracing_subscriber::registry().with(LogForwardLayer(Arc::clone(&forwarder)));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "blvm_governance::webhook", "not forwarded");
+            tracing::warn!(target: "blvm_governance::webhook", url = "x", "delivery failed");
+            tracing::error!(target: "blvm_governance::webhook", "second");
+            tracing::error!(target: "blvm_governance::webhook", "over capacity");
+        });
+        let records = forwarder.take(10);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, "WARN");
+        assert_eq!(records[0].message, "delivery failed url=\"x\"");
+        assert_eq!(forwarder.dropped(), 1);
+    }
+
+    #[test]
+    fn test_reconfigure_level() {
+        let mut config = LogForwardConfig {
+            level: Some("error".to_string()),
+            max_per_sec: 10,
+            buffer: 10,
+        };
+        let forwarder = Arc::new(LogForwarder::new(&config).unwrap());
+        assert!(!forwarder.reconfigure(&config));
+        config.level = Some("warn".to_string());
+        assert!(forwarder.reconfigure(&config));
+
+        let subscriber =
+            tracing_subscriber::registry().with(LogForwardLayer(Arc::clone(&forwarder)));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "blvm_governance::webhook", "forwarded after reload");
+        });
+        assert_eq!(forwarder.take(10).len(), 1);
+    }
+
+    #[test]
+    fn test_disabled_without_level() {
+        assert!(LogForwarder::new(&LogForwardConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,397 @@
+//! Local log output
+//!
+//! The binary installs its tracing subscriber first thing, before the bootstrap or anything
+//! else can log, so every line, early startup errors included, comes out in the configured
+//! format. The format is `--log-format`, else `[governance.logging] format`, else `full`.
+//! The level of local output is `--log-level`, else `[governance.logging] level`, else
+//! `RUST_LOG`, else `info`; a level applies to every target, so no filter syntax is needed.
+//! `[governance.logging.targets]` sets other levels for some targets, e.g. webhook deliveries
+//! at `debug` and the rest at `info`. The filter is replaced, without reinstalling anything,
+//! when a configuration reload changes the level or the targets (see [`set_filter`]); the
+//! module logs the one in effect at startup and after each reload.
+//!
+//! `json` writes one object per record with the fields of the record and of its spans as
+//! top-level keys, e.g. the `trace_id` of the event being processed (see [`crate::trace`]):
+//!
+//! ```text
+//! {"level":"WARN","message":"Webhook failed","target":"blvm_governance::webhook","timestamp":"2025-01-01T00:00:00.000000Z","trace_id":"..."}
+//! ```
+//!
+//! With `[governance.logging] dir` set, the same records also go to a log file rotated by
+//! size (see [`crate::log_file`]), without colors; `console = false` then stops writing them
+//! to the console. Forwarding to the node (see [`crate::log_forward`]) is a separate layer
+//! with its own level, whatever the local settings.
+
+use crate::config::{LogFormat, LogForwardConfig, LoggingConfig};
+use crate::log_file::LogFile;
+use crate::log_forward::{LogForwardLayer, LogForwarder};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+/// Values accepted as a log level.
+pub const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// Filter directives for local output: `level`, else `rust_log`, else `info`, then
+/// `target=level` for each of `targets`, which win for those targets.
+fn directives(
+    level: Option<&str>,
+    targets: &BTreeMap<String, String>,
+    rust_log: Option<String>,
+) -> String {
+    let mut directives = match level {
+        Some(level) => level.to_string(),
+        None => rust_log
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or_else(|| "info".to_string()),
+    };
+    for (target, level) in targets {
+        directives.push_str(&format!(",{}={}", target, level));
+    }
+    directives
+}
+
+/// Directives of the filter for local output as `config` says; `info` if they do not parse.
+fn filter_directives(config: &LoggingConfig) -> String {
+    let directives = directives(
+        config.level.as_deref(),
+        &config.targets,
+        std::env::var(EnvFilter::DEFAULT_ENV).ok(),
+    );
+    match EnvFilter::try_new(&directives) {
+        Ok(_) => directives,
+        Err(e) => {
+            eprintln!("Invalid log filter {:?} ({}), using info", directives, e);
+            "info".to_string()
+        }
+    }
+}
+
+/// The filters of the local output layers, replaced together.
+struct LocalFilter {
+    handles: Vec<reload::Handle<EnvFilter, Registry>>,
+    /// Directives in effect.
+    directives: Mutex<String>,
+}
+
+impl LocalFilter {
+    /// Filter each of `layers` by `directives`, which must parse.
+    fn apply(
+        layers: Vec<Box<dyn Layer<Registry> + Send + Sync>>,
+        directives: String,
+    ) -> (Vec<Box<dyn Layer<Registry> + Send + Sync>>, Self) {
+        let mut handles = Vec::new();
+        let layers = layers
+            .into_iter()
+            .map(|layer| {
+                let (filter, handle) = reload::Layer::new(EnvFilter::new(&directives));
+                handles.push(handle);
+                layer.with_filter(filter).boxed()
+            })
+            .collect();
+        let filter = Self {
+            handles,
+            directives: Mutex::new(directives),
+        };
+        (layers, filter)
+    }
+
+    fn set(&self, directives: String) {
+        for handle in &self.handles {
+            // Fails only once the subscriber is gone
+            let _ = handle.reload(EnvFilter::new(&directives));
+        }
+        *self.directives.lock().unwrap() = directives;
+    }
+
+    fn directives(&self) -> String {
+        self.directives.lock().unwrap().clone()
+    }
+}
+
+/// The filter of local output, once [`install`] installed the subscriber.
+static LOCAL_FILTER: OnceLock<LocalFilter> = OnceLock::new();
+
+/// Directives of the filter of local output, e.g. `info,blvm_governance::webhook=debug`;
+/// `None` until [`install`] has installed the subscriber.
+pub fn current_filter() -> Option<String> {
+    LOCAL_FILTER.get().map(LocalFilter::directives)
+}
+
+/// Replace the filter of local output with the one `config` gives, `level` (`--log-level`)
+/// winning over `config.level` if given. Returns the directives now in effect; `None` if
+/// [`install`] has not installed the subscriber.
+pub fn set_filter(config: &LoggingConfig, level: Option<&str>) -> Option<String> {
+    let local = LOCAL_FILTER.get()?;
+    let config = LoggingConfig {
+        level: level.map(str::to_string).or_else(|| config.level.clone()),
+        ..config.clone()
+    };
+    local.set(filter_directives(&config));
+    Some(local.directives())
+}
+
+/// Install the process's tracing subscriber, writing local output to `writer` and, if
+/// `config.dir` is set, to a log file there (see [`crate::log_file`]), as `config` says. When
+/// `forward` enables it, records are also forwarded to the node; the forwarder is returned if
+/// so. Fails if the log file cannot be opened. Must run before anything else installs a
+/// global subscriber; later calls have no effect.
+pub fn install<W>(
+    config: &LoggingConfig,
+    writer: W,
+    forward: Option<&LogForwardConfig>,
+) -> io::Result<Option<Arc<LogForwarder>>>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let mut local = Vec::new();
+    if config.dir.is_none() || config.console {
+        local.push(layer(config, writer, true));
+    }
+    if let Some(dir) = &config.dir {
+        let file = LogFile::start(dir, config.max_file_bytes, config.max_files).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot write logs to {}: {}", dir.display(), e),
+            )
+        })?;
+        local.push(layer(config, file.clone(), false));
+        if LOG_FILE.set(file).is_ok() {
+            flush_on_panic();
+        }
+    }
+    // Filtered layer by layer, so that the local level does not limit forwarding
+    let (local, filter) = LocalFilter::apply(local, filter_directives(config));
+    let forwarder = forward.and_then(LogForwarder::new).map(Arc::new);
+    if tracing_subscriber::registry()
+        .with(local)
+        .with(forwarder.clone().map(LogForwardLayer))
+        .try_init()
+        .is_err()
+    {
+        return Ok(None);
+    }
+    let _ = LOCAL_FILTER.set(filter);
+    Ok(forwarder)
+}
+
+/// A local output layer writing to `writer` in the configured format.
+fn layer<W>(config: &LoggingConfig, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match config.format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer
+            .fmt_fields(JsonFields::new())
+            .event_format(FlatJson)
+            .boxed(),
+    }
+}
+
+/// The log file, once [`install`] opened one.
+static LOG_FILE: OnceLock<LogFile> = OnceLock::new();
+
+/// Wait until everything logged so far is in the log file, if there is one. Call before
+/// exiting the process; records logged after that are still written.
+pub fn flush() {
+    if let Some(file) = LOG_FILE.get() {
+        file.flush();
+    }
+}
+
+/// Log panics, which otherwise only go to stderr, and flush the log file so that the record
+/// is in it even if the panic ends the process.
+fn flush_on_panic() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        flush();
+        previous(info);
+    }));
+}
+
+/// Formats a record as one JSON object, with span fields flattened into it.
+struct FlatJson;
+
+impl<S, N> FormatEvent<S, N> for FlatJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut record = Map::new();
+        // Outer spans first, so inner ones win on repeated names; span fields were stored as
+        // JSON by `JsonFields`
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>() {
+                    if let Ok(Value::Object(fields)) = serde_json::from_str(&fields.fields) {
+                        record.extend(fields);
+                    }
+                }
+            }
+        }
+        event.record(&mut JsonVisitor(&mut record));
+        let metadata = event.metadata();
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+        record.insert("timestamp".to_string(), timestamp.into());
+        record.insert("level".to_string(), metadata.level().as_str().into());
+        record.insert("target".to_string(), metadata.target().into());
+        record.entry("message").or_insert_with(|| "".into());
+        writeln!(writer, "{}", Value::Object(record))
+    }
+}
+
+/// Collects a record's fields into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Captures formatted output.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_flattens_span_fields() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(buffer.clone())
+                .fmt_fields(JsonFields::new())
+                .event_format(FlatJson),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let event = tracing::info_span!("event", trace_id = "abc", event_type = "created");
+            let _event = event.enter();
+            let handler = tracing::info_span!("handler", proposal_id = "42");
+            let _handler = handler.enter();
+            tracing::warn!(attempts = 3, "Webhook failed");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let record: Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(record["message"], "Webhook failed");
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["target"], module_path!());
+        assert_eq!(record["trace_id"], "abc");
+        assert_eq!(record["proposal_id"], "42");
+        assert_eq!(record["attempts"], 3);
+        assert!(record["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_level_overrides_rust_log() {
+        let none = BTreeMap::new();
+        let rust_log = || Some("blvm_governance=trace".to_string());
+        assert_eq!(directives(Some("warn"), &none, rust_log()), "warn");
+        assert_eq!(directives(None, &none, rust_log()), "blvm_governance=trace");
+        assert_eq!(directives(None, &none, Some(" ".to_string())), "info");
+        assert_eq!(directives(None, &none, None), "info");
+
+        let targets = BTreeMap::from([
+            ("blvm_governance::webhook".to_string(), "debug".to_string()),
+            ("reqwest".to_string(), "off".to_string()),
+        ]);
+        assert_eq!(
+            directives(Some("warn"), &targets, rust_log()),
+            "warn,blvm_governance::webhook=debug,reqwest=off"
+        );
+    }
+
+    #[test]
+    fn test_filter_is_replaced_in_place() {
+        let buffer = Buffer::default();
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(buffer.clone())
+            .with_ansi(false)
+            .without_time()
+            .boxed();
+        let (layers, filter) = LocalFilter::apply(vec![layer], "info".to_string());
+        let log = || {
+            tracing::debug!(target: "blvm_governance::webhook", "delivered");
+            tracing::debug!(target: "blvm_governance::economic_nodes", "registered");
+        };
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layers), || {
+            log();
+            filter.set("info,blvm_governance::webhook=debug".to_string());
+            log();
+        });
+
+        assert_eq!(filter.directives(), "info,blvm_governance::webhook=debug");
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{}", output);
+        assert!(lines[0].ends_with("blvm_governance::webhook: delivered"));
+    }
+}
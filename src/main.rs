@@ -1,73 +1,1106 @@
 //! blvm-governance - Governance webhook and economic node tracking module
 //!
 //! When spawned by the node: reads MODULE_ID, SOCKET_PATH, DATA_DIR from env.
-//! For manual testing: blvm-governance --module-id <id> --socket-path <path> --data-dir <dir>
+//! For manual testing: blvm-governance [run] --module-id <id> --socket-path <path> --data-dir <dir>
+//! Settings are read from `--config <path>` (or BLLVM_GOVERNANCE_CONFIG) if given, else from
+//! config.toml in the data dir; see `blvm_governance::config`. The `check-config`,
+//! `test-webhook`, `export-registry` and `show-node` subcommands work without the node (see
+//! `blvm_governance::cli`); `self-test` checks the connection to it (see
+//! `blvm_governance::self_test`). Logs are written to stdout (stderr for the subcommands), and to
+//! `[governance.logging] dir` if set, as `--log-format` and `--log-level` say; see
+//! `blvm_governance::logging`.
+//!
+//! The data directory is locked against a second module process, then checked and migrated
+//! from an older layout, before anything else uses it (see `blvm_governance::storage`). On each
+//! connection it is checked against the node's network, and refused if it holds another's
+//! unless `--force-network-migration` is given (see `blvm_governance::network`).
+//! Once the configuration is read, a panic writes a crash report there, tells the node and the
+//! webhook, and exits with code 4 (see `blvm_governance::crash`). The running module answers
+//! `blvm-governance status` on an admin socket in the directory (see `blvm_governance::admin`).
+//!
+//! If the connection to the node drops (e.g. the node restarts), the module reconnects with
+//! backoff and sets itself up again from the persisted store. The first connection is retried
+//! the same way until `connect_timeout_secs`, for modules started before the node's socket
+//! exists. SIGTERM/SIGINT shut down gracefully (see `blvm_governance::shutdown`); a second
+//! signal exits immediately. Under systemd, readiness and watchdog pings are reported through
+//! `NOTIFY_SOCKET` (see `blvm_governance::systemd`).
 
 use anyhow::Result;
-use blvm_governance::storage::up_v1;
+use blvm_governance::error::{GovernanceError, Retryability};
+use blvm_governance::storage::{up_v1, up_v2, up_v3, DataDir, InstanceLock};
 use blvm_governance::{
     api::GovernanceModuleApi,
-    economic_nodes, proposals, webhook,
+    actions, admin, adoption, alert, anchor, audit, audit_log, backup, build_info, checkpoint, cli, clock, config, config_check, config_reload, content, crash, delegation, digest, economic_nodes, epoch_summary, error_report, escalation, event_queue, event_stream, feed, github, health, heartbeat, intent, ipc_metrics, log_forward, logging,
+    memory, network, node_api, participation, pause, pipeline, proposals, query, reconnect, replay, self_test, shutdown, signaling, socket_check, status, status_report, subscriptions, systemd, webhook,
     GovernanceConfig, GovernanceModule,
 };
 use blvm_sdk::migrations;
+use clap::Parser;
 use blvm_sdk::module::{ModuleBootstrap, ModuleDb};
-use std::sync::Arc;
-use tracing::warn;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
 
 const MODULE_NAME: &str = "blvm-governance";
 
+/// Module and node API of a connection.
+type ActiveConnection = (GovernanceModule, Arc<dyn blvm_node::module::traits::NodeAPI>);
+
+/// Stop the module because of an error that reconnecting will not fix, telling the node why.
+async fn fatal(
+    shutdown: &shutdown::Shutdown,
+    node_api: &dyn blvm_node::module::traits::NodeAPI,
+    error: String,
+) -> blvm_node::module::traits::ModuleError {
+    let reason = shutdown::ShutdownReason::Fatal {
+        code: 1,
+        error: error.clone(),
+    };
+    if let Err(e) = shutdown::send_goodbye(node_api, &reason).await {
+        warn!("Failed to send final status to the node: {}", e.chain());
+    }
+    shutdown.fail(reason);
+    blvm_node::module::traits::ModuleError::Other(error)
+}
+
+/// Run an offline subcommand (see `blvm_governance::cli`), printing its output, or the
+/// error and exiting non-zero. These never connect to the node, except `self-test` and
+/// `replay`.
+async fn offline(args: &cli::Args) -> Result<()> {
+    if let Some(cli::Command::SelfTest { skip_node, skip_webhook }) = args.command {
+        return run_self_test(args, skip_node, skip_webhook).await;
+    }
+    if let Some(cli::Command::Replay(replay_args)) = &args.command {
+        return run_replay(args, replay_args).await;
+    }
+    match cli::execute(args).await {
+        Some(Ok(output)) => println!("{}", output.trim_end()),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// `self-test`: run the checks of `blvm_governance::self_test`, print the report, and exit
+/// non-zero if any failed.
+async fn run_self_test(args: &cli::Args, skip_node: bool, skip_webhook: bool) -> Result<()> {
+    let mut report = self_test::Report::default();
+    let config = report.run("configuration", self_test::configuration(&args.config_file())).await;
+    let db = report.run("store", self_test::store(&args.data_dir)).await;
+    let node_skipped = match (&config, db) {
+        _ if skip_node => Some("--skip-node"),
+        (Some(config), Some(db)) => {
+            check_node(&mut report, config, db).await;
+            None
+        }
+        _ => Some("needs the configuration and the store"),
+    };
+    if let Some(reason) = node_skipped {
+        report.skip("node connection", reason);
+        report.skip("node round trip", reason);
+    }
+    match &config {
+        _ if skip_webhook => report.skip("webhook", "--skip-webhook"),
+        Some(config) if config.webhook_url.is_none() => report.skip("webhook", "webhook_url is not set"),
+        Some(config) => {
+            report.run("webhook", self_test::webhook(config)).await;
+        }
+        None => report.skip("webhook", "needs the configuration"),
+    }
+    println!("{}", report);
+    logging::flush();
+    if !report.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The node checks of `self-test`, on a connection set up as the module's are. The setup
+/// hands the node API out and sets up an idle module; the checks run while the connection is
+/// polled, and dropping it at the end closes the socket.
+async fn check_node(report: &mut self_test::Report, config: &GovernanceConfig, db: Arc<dyn blvm_node::storage::database::Database>) {
+    let bootstrap = ModuleBootstrap::init_module(MODULE_NAME);
+    let (ctx, _) = bootstrap.context_with_config::<GovernanceConfig>(&bootstrap.data_dir);
+    let socket_path = std::path::PathBuf::from(&ctx.socket_path);
+    let (connected_tx, connected) = tokio::sync::oneshot::channel();
+    let connected_tx = Mutex::new(Some(connected_tx));
+    let setup = |node_api: Arc<dyn blvm_node::module::traits::NodeAPI>,
+                 db: Arc<dyn blvm_node::storage::database::Database>,
+                 data_dir: &std::path::Path| {
+        if let Some(tx) = connected_tx.lock().unwrap().take() {
+            let _ = tx.send(Arc::clone(&node_api));
+        }
+        let data_dir = data_dir.to_path_buf();
+        let config = config.clone();
+        async move {
+            let module = self_test::idle_module(node_api, db, &data_dir, &config)
+                .await
+                .map_err(|e| blvm_node::module::traits::ModuleError::Other(e.to_string()))?;
+            Ok((module.clone(), module))
+        }
+    };
+    let connection = async {
+        socket_check::check(&socket_path, &config.ipc)?;
+        let result = blvm_sdk::run_module! {
+            bootstrap: &bootstrap,
+            module_name: MODULE_NAME,
+            module_type: GovernanceModule,
+            cli_type: GovernanceModule,
+            db: db,
+            setup: setup,
+            event_types: Vec::new(),
+        };
+        result.map_err(anyhow::Error::from)
+    };
+    let mut connection = std::pin::pin!(connection);
+    let disconnected = |result: Result<()>| match result {
+        Ok(()) => "the node closed the connection".to_string(),
+        Err(e) => format!("{:#}", e),
+    };
+    let timeout = std::time::Duration::from_secs(config.ipc.request_timeout_secs);
+    let node_api = report
+        .run("node connection", async {
+            tokio::select! {
+                node_api = connected => node_api
+                    .map(|node_api| (node_api, format!("{}: handshake complete", socket_path.display())))
+                    .map_err(|_| "the connection closed during setup".to_string()),
+                result = &mut connection => Err(disconnected(result)),
+                _ = tokio::time::sleep(timeout) => Err(format!("no handshake within {}s", timeout.as_secs())),
+            }
+        })
+        .await;
+    let Some(node_api) = node_api else {
+        report.skip("node round trip", "needs the node connection");
+        return;
+    };
+    report
+        .run("node round trip", async {
+            tokio::select! {
+                checked = self_test::round_trip(node_api, config) => checked,
+                result = &mut connection => Err(disconnected(result)),
+            }
+        })
+        .await;
+}
+
+/// `replay`: feed the recorded events in range through the chosen handlers (see
+/// `blvm_governance::replay`), on a connection to the node or offline, print the summary, and
+/// exit non-zero if any event failed.
+async fn run_replay(args: &cli::Args, replay_args: &cli::ReplayArgs) -> Result<()> {
+    let config = args.read_config()?;
+    let db = cli::open_store(&args.data_dir)?;
+    let layout = DataDir::open(&args.data_dir)?;
+    let source = replay_args.file.clone().unwrap_or_else(|| layout.audit());
+    let recorded = replay::read(&source, &replay_args.range())?;
+    info!("Replaying {} events from {}", recorded.events.len(), source.display());
+    // Read for the pipeline, never written by a replay
+    let checkpointer = Arc::new(checkpoint::Checkpointer::open(&layout.state())?);
+    let dry_run = args.dry_run.unwrap_or(config.dry_run);
+    let dry_run_dir = config.dry_run_files.then(|| layout.dry_run());
+    let run = |node_api: Arc<dyn blvm_node::module::traits::NodeAPI>| {
+        let (config, db, recorded, checkpointer) = (&config, Arc::clone(&db), &recorded, checkpointer);
+        async move {
+            let handlers = replay::handlers(&replay_args.handlers, config, db, Arc::clone(&node_api), dry_run, dry_run_dir).await?;
+            Ok::<_, anyhow::Error>(replay::replay(recorded, handlers, checkpointer, node_api.as_ref()).await)
+        }
+    };
+    let summary = if replay_args.offline {
+        let node_api = replay::RecordedNodeApi::new(&recorded);
+        info!("Offline: serving {} recorded blocks", node_api.blocks());
+        run(Arc::new(node_api)).await?
+    } else {
+        on_node_connection(&config, Arc::clone(&db), run).await?
+    };
+    println!("{}", summary);
+    logging::flush();
+    if !summary.succeeded() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Connect to the node as the module does, with an idle module set up for the connection (see
+/// `check_node`), and run `work` with the connection's node API while the connection is
+/// polled. Dropping the connection at the end closes the socket.
+async fn on_node_connection<T, F, Fut>(config: &GovernanceConfig, db: Arc<dyn blvm_node::storage::database::Database>, work: F) -> Result<T>
+where
+    F: FnOnce(Arc<dyn blvm_node::module::traits::NodeAPI>) -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let bootstrap = ModuleBootstrap::init_module(MODULE_NAME);
+    let (ctx, _) = bootstrap.context_with_config::<GovernanceConfig>(&bootstrap.data_dir);
+    let socket_path = std::path::PathBuf::from(&ctx.socket_path);
+    let (connected_tx, connected) = tokio::sync::oneshot::channel();
+    let connected_tx = Mutex::new(Some(connected_tx));
+    let setup = |node_api: Arc<dyn blvm_node::module::traits::NodeAPI>,
+                 db: Arc<dyn blvm_node::storage::database::Database>,
+                 data_dir: &std::path::Path| {
+        if let Some(tx) = connected_tx.lock().unwrap().take() {
+            let _ = tx.send(Arc::clone(&node_api));
+        }
+        let data_dir = data_dir.to_path_buf();
+        let config = config.clone();
+        async move {
+            let module = self_test::idle_module(node_api, db, &data_dir, &config)
+                .await
+                .map_err(|e| blvm_node::module::traits::ModuleError::Other(e.to_string()))?;
+            Ok((module.clone(), module))
+        }
+    };
+    let connection = async {
+        socket_check::check(&socket_path, &config.ipc)?;
+        let result = blvm_sdk::run_module! {
+            bootstrap: &bootstrap,
+            module_name: MODULE_NAME,
+            module_type: GovernanceModule,
+            cli_type: GovernanceModule,
+            db: db,
+            setup: setup,
+            event_types: Vec::new(),
+        };
+        result.map_err(anyhow::Error::from)
+    };
+    let mut connection = std::pin::pin!(connection);
+    let timeout = std::time::Duration::from_secs(config.ipc.request_timeout_secs);
+    let node_api = tokio::select! {
+        node_api = connected => match node_api {
+            Ok(node_api) => node_api,
+            Err(_) => anyhow::bail!("the connection to {} closed during setup", socket_path.display()),
+        },
+        result = &mut connection => {
+            result?;
+            anyhow::bail!("the node closed the connection");
+        }
+        _ = tokio::time::sleep(timeout) => anyhow::bail!("no handshake with {} within {}s", socket_path.display(), timeout.as_secs()),
+    };
+    tokio::select! {
+        result = work(node_api) => result,
+        result = &mut connection => {
+            result?;
+            anyhow::bail!("the node closed the connection");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = cli::Args::parse();
+    // Only for the log settings; the configuration is read and checked again once logging is up
+    let file_config = GovernanceConfig::from_file(&args.config_file()).unwrap_or_default();
+    let log_settings = args.logging(&file_config.logging);
+    if !matches!(args.command, None | Some(cli::Command::Run)) {
+        // Their output goes to stdout, and their logs only to stderr
+        let log_settings = config::LoggingConfig { dir: None, ..log_settings };
+        logging::install(&log_settings, std::io::stderr, None)?;
+        return offline(&args).await;
+    }
+    // The subscriber has to be in place before the bootstrap sets up logging
+    let log_forwarder = match logging::install(&log_settings, std::io::stdout, Some(&file_config.log_forward)) {
+        Ok(forwarder) => forwarder,
+        Err(e) => {
+            eprintln!("Governance module failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = run(log_forwarder, args.dry_run, args.log_level.clone(), args.force_network_migration).await {
+        // Logged rather than returned, so that it is in the configured format
+        error!("Governance module failed: {:#}", e);
+        logging::flush();
+        std::process::exit(1);
+    }
+    logging::flush();
+    Ok(())
+}
+
+async fn run(
+    log_forwarder: Option<Arc<log_forward::LogForwarder>>,
+    dry_run: Option<config::DryRun>,
+    log_level: Option<String>,
+    force_network_migration: bool,
+) -> Result<()> {
     let bootstrap = ModuleBootstrap::init_module(MODULE_NAME);
-    let db = ModuleDb::open_with_migrations(&bootstrap.data_dir, migrations!(1 => up_v1))?;
+    // Held until the process exits; another module process on this data directory stops here
+    let _instance_lock = InstanceLock::acquire(&bootstrap.data_dir)?;
+    // Refuses a newer layout or an inconsistent directory before anything else touches it
+    let layout = DataDir::open(&bootstrap.data_dir)?;
+    let db = ModuleDb::open_with_migrations(layout.store(), migrations!(1 => up_v1, 2 => up_v2, 3 => up_v3))?;
+    // Background tasks of the current connection, aborted when it drops
+    let tasks: Arc<Mutex<Vec<JoinHandle<()>>>> = Arc::default();
+    // Liveness of the current connection; a dead connection is dropped and reconnected
+    let heartbeat = Arc::new(heartbeat::Heartbeat::new());
+    let shutdown = Arc::new(shutdown::Shutdown::new());
+    // Given by --config or BLLVM_GOVERNANCE_CONFIG, else config.toml in the data dir
+    let config_path = config::file_path(&bootstrap.data_dir);
+    if !config_path.exists() && config_path != bootstrap.data_dir.join("config.toml") {
+        anyhow::bail!("configuration file {} does not exist", config_path.display());
+    }
+    let (ctx, _) = bootstrap.context_with_config::<GovernanceConfig>(&bootstrap.data_dir);
+    // Values the node passes in the module context override the file
+    let config = config_check::read_valid(&config_path, &ctx.config)?;
+    info!("Read configuration from {}", config_path.display());
+    // Logging was set up from the file before it was checked, without the node's values
+    if let Some(filter) = logging::set_filter(&config.logging, log_level.as_deref()) {
+        info!("Log filter: {}", filter);
+    }
+    // Panics from here on are reported, to the node and webhook too while connected
+    let crash_reporter = Arc::new(crash::CrashReporter::new(
+        layout.crashes(),
+        std::time::Duration::from_secs(config.crash.report_timeout_secs),
+    ));
+    crash::install(Arc::clone(&crash_reporter));
+    // Shared by the event queues and caches of every connection; events spilled by an earlier
+    // process are not replayed (the checkpoint backfills missed blocks)
+    let memory = Arc::new(memory::MemoryBudget::new(config.memory.budget_bytes));
+    // Skew of the local clock from block time, kept across connections
+    let clock = Arc::new(clock::ClockMonitor::new(config.clock.clone()));
+    // Error rates by category, checked against the alert thresholds until the module exits
+    let alerts = Arc::new(alert::AlertMonitor::new(config.alerts.clone()).with_node_id(config.node_id.clone()));
+    let alert_checks = alerts.spawn();
+    // Significant errors, batched to the node and kept for the status report and /errors
+    let errors = Arc::new(
+        error_report::ErrorReporter::new(config.error_reports.clone()).with_alert_monitor(Arc::clone(&alerts)),
+    );
+    // Events, deliveries, registry changes and actions, chained; only configured at startup
+    let audit_log = if config.audit_log.enabled {
+        Some(Arc::new(audit_log::AuditLog::open(&layout.audit(), &config.audit_log)?))
+    } else {
+        None
+    };
+    if let Err(e) = std::fs::remove_dir_all(layout.spill()) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove {}: {}", layout.spill().display(), e);
+        }
+    }
+    // --dry-run wins over the configuration; neither changes until a restart
+    let dry_run = dry_run.unwrap_or(config.dry_run);
+    let dry_run_dir = config.dry_run_files.then(|| layout.dry_run());
+    // Given by the node; a reload that finds them changed reports that a restart is needed
+    let paths = config_reload::ModulePaths {
+        data_dir: bootstrap.data_dir.clone(),
+        socket_path: std::path::PathBuf::from(&ctx.socket_path),
+    };
+    // Notified on SIGHUP; the current connection's reloader re-reads the configuration
+    let hangup = Arc::new(tokio::sync::Notify::new());
+    config_reload::spawn_hangup_listener(Arc::clone(&hangup));
+    // Missed-event detection, reset for each connection
+    let stream = Arc::new(event_stream::EventStreamMonitor::new(config.ipc.reconcile_on_gap));
+    // Module and node API of the current connection, for the final steps of a shutdown
+    let active: Arc<Mutex<Option<ActiveConnection>>> = Arc::default();
+    // Source of truth for subscriptions; every connection subscribes to the current set
+    let subscriptions = Arc::new(subscriptions::EventSubscriptions::new(GovernanceModule::event_types()));
+    // Request and event counters, kept across connections
+    let metrics = Arc::new(ipc_metrics::IpcMetrics::new());
+    let started = std::time::Instant::now();
+    // Status of the module, by subsystem; each connection adds the providers it owns
+    let module_status = Arc::new(status::StatusCollector::new(started));
+    module_status.register(Arc::new(build_info::BuildInfo::current().clone()));
+    module_status.register(Arc::clone(&metrics));
+    module_status.register(Arc::clone(&heartbeat));
+    module_status.register(Arc::clone(&stream));
+    module_status.register(Arc::clone(&memory));
+    module_status.register(Arc::clone(&clock));
+    module_status.register(Arc::clone(&errors));
+    // Emergency stop of webhooks and actions, kept across connections and restarts
+    let pause = {
+        let pause = pause::PauseControl::open(&layout.state())?;
+        Arc::new(match &audit_log {
+            Some(log) => pause.with_audit_log(Arc::clone(log)),
+            None => pause,
+        })
+    };
+    module_status.register(Arc::clone(&pause));
+    // Answers `blvm-governance status`, `pause` and `resume`; the module runs without it if it
+    // cannot be bound
+    let admin_server = match admin::spawn(&layout.admin_socket(), Arc::clone(&module_status), Arc::clone(&pause)) {
+        Ok(server) => Some(server),
+        Err(e) => {
+            warn!("Not serving the admin socket: {}", e.chain());
+            None
+        }
+    };
+    // Recent proposal activity for feed readers, kept across connections and restarts
+    let feed = if config.health.feed {
+        Some(Arc::new(feed::Feed::open(&layout.state(), config.health.feed_entries)?))
+    } else {
+        None
+    };
+    // Webhook notifications collected for periodic digests, kept across connections and restarts
+    let digest = if config.digest.mode == config::DigestMode::Digest {
+        Some(Arc::new(digest::Digest::open(&layout.state(), &config.digest)?))
+    } else {
+        None
+    };
+    // Answers /healthz, /readyz, /errors, /metrics, /actions, /admin, /integrations/github and
+    // the feeds where configured; closed once a shutdown has drained
+    let mut health = health::Health::new(
+        config.health.clone(),
+        Arc::clone(&shutdown),
+        Arc::clone(&heartbeat),
+        Arc::clone(&subscriptions),
+    )
+    .with_error_reporter(Arc::clone(&errors))
+    .with_status(Arc::clone(&module_status))
+    .with_pause(Arc::clone(&pause));
+    if config.health.metrics {
+        health = health.with_metrics(Arc::clone(&metrics));
+    }
+    if config.github.secret.is_some() {
+        health = health.with_github();
+    }
+    if let Some(feed) = &feed {
+        health = health.with_feed(Arc::clone(feed));
+    }
+    let health = Arc::new(health);
+    let health_server = match &config.health.listen {
+        Some(addr) => Some(health.spawn(addr).await?),
+        None => None,
+    };
+    // Readiness, reloads and watchdog pings for systemd; does nothing when not run by it
+    let systemd = Arc::new(systemd::Notifier::from_env());
+    let watchdog = systemd.spawn_watchdog(Arc::clone(&health));
 
     let setup = |node_api: Arc<dyn blvm_node::module::traits::NodeAPI>,
                  db: Arc<dyn blvm_node::storage::database::Database>,
                  data_dir: &std::path::Path| {
         let bootstrap = bootstrap.clone();
         let data_dir = data_dir.to_path_buf();
+        let tasks = Arc::clone(&tasks);
+        let heartbeat = Arc::clone(&heartbeat);
+        let shutdown = Arc::clone(&shutdown);
+        let active = Arc::clone(&active);
+        let subscriptions = Arc::clone(&subscriptions);
+        let stream = Arc::clone(&stream);
+        let metrics = Arc::clone(&metrics);
+        let health = Arc::clone(&health);
+        let module_status = Arc::clone(&module_status);
+        let systemd = Arc::clone(&systemd);
+        let crash_reporter = Arc::clone(&crash_reporter);
+        let memory = Arc::clone(&memory);
+        let clock = Arc::clone(&clock);
+        let errors = Arc::clone(&errors);
+        let alerts = Arc::clone(&alerts);
+        let pause = Arc::clone(&pause);
+        let audit_log = audit_log.clone();
+        let feed = feed.clone();
+        let digest = digest.clone();
+        let log_forwarder = log_forwarder.clone();
+        let config_path = config_path.clone();
+        let startup_config = config.clone();
+        let paths = paths.clone();
+        let hangup = Arc::clone(&hangup);
+        let dry_run_dir = dry_run_dir.clone();
+        let layout = layout.clone();
+        let log_level = log_level.clone();
         async move {
-            let (ctx, config) = bootstrap.context_with_config::<GovernanceConfig>(&data_dir);
-            let webhook_url = config.webhook_url.clone();
-            let webhook_client = webhook::GovernanceWebhookClient::new(&ctx)
+            let (ctx, _) = bootstrap.context_with_config::<GovernanceConfig>(&data_dir);
+            let config = config_check::read_valid(&config_path, &ctx.config).unwrap_or_else(|e| {
+                warn!("Failed to read the configuration, using the one read at startup: {}", e);
+                startup_config
+            });
+            // Refuses data of another network before anything is stored; see blvm_governance::network
+            let network = match node_api::NodeApiIpc::new(Arc::clone(&node_api))
+                .with_timeouts(config.request_timeouts())
+                .with_metrics(Arc::clone(&metrics))
+                .get_network()
                 .await
-                .map_err(|e| blvm_node::module::traits::ModuleError::Other(format!("Failed to create webhook client: {}", e)))?;
-            let economic_nodes = Arc::new(
-                economic_nodes::EconomicNodeRegistry::new(&ctx, Arc::clone(&node_api))
-                    .await
-                    .map_err(|e| blvm_node::module::traits::ModuleError::Other(format!("Failed to create economic node registry: {}", e)))?,
+            {
+                Ok(network) => match network::check(&layout, &db, &network, force_network_migration) {
+                    Ok(status) => status,
+                    Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Refusing the node's network: {}", e)).await),
+                },
+                Err(e) => {
+                    warn!("Failed to get the node's network, leaving the data directory's unchecked: {}", e);
+                    network::NetworkStatus::default()
+                }
+            };
+            alerts.set_network(network.network.clone());
+            // Side effects of events, recorded before they are carried out; see blvm_governance::intent
+            let intents = match intent::IntentLog::open(&layout.state()) {
+                Ok(intents) => Arc::new(intents),
+                Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to load intent log: {}", e)).await),
+            };
+            let webhook_client = match webhook::GovernanceWebhookClient::new(&config).await {
+                Ok(client) => {
+                    let client = client
+                        .with_clock(Arc::clone(&clock))
+                        .with_error_reporter(Arc::clone(&errors))
+                        .with_intents(Arc::clone(&intents))
+                        .with_pause(Arc::clone(&pause))
+                        .with_network(network.network.clone());
+                    let client = match &audit_log {
+                        Some(log) => client.with_audit_log(Arc::clone(log)),
+                        None => client,
+                    };
+                    let client = match &digest {
+                        Some(digest) => client.with_digest(Arc::clone(digest)),
+                        None => client,
+                    };
+                    if dry_run.webhook() {
+                        Arc::new(client.with_dry_run(dry_run_dir))
+                    } else {
+                        Arc::new(client)
+                    }
+                }
+                Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to create webhook client: {}", e)).await),
+            };
+            // Requests in flight on this connection, across all clients
+            let in_flight = Arc::new(node_api::RequestLimiter::new(config.ipc.max_in_flight, config.ipc.reserved_interactive));
+            // Every veto or other action submitted to the node, whether or not actions are allowed now
+            let action_audit = match audit::ActionAudit::open(Arc::clone(&db)) {
+                Ok(audit) => Arc::new(match &audit_log {
+                    Some(log) => audit.with_audit_log(Arc::clone(log)),
+                    None => audit,
+                }),
+                Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to load action audit trail: {}", e)).await),
+            };
+            // Chain tip of this connection, read before any handler runs
+            let ipc = node_api::NodeApiIpc::new(Arc::clone(&node_api))
+                .with_timeouts(config.request_timeouts())
+                .with_retry_policy(config.ipc.retry_policy())
+                .with_metrics(Arc::clone(&metrics))
+                .with_in_flight(Arc::clone(&in_flight))
+                .with_fee_cache(Arc::new(node_api::FeeCache::new(std::time::Duration::from_secs(config.ipc.fee_cache_ttl_secs))))
+                .with_actions_allowed(config.allow_actions)
+                .with_action_audit(Arc::clone(&action_audit))
+                .with_dry_run(dry_run.actions())
+                .with_pause(Arc::clone(&pause))
+                .with_memory_budget(&memory);
+            match ipc.get_best_block().await {
+                Ok(tip) => info!("Node chain tip: {} at height {}", hex::encode(tip.hash), tip.height),
+                Err(e) => warn!("Failed to read the node's chain tip: {}", e.chain()),
+            }
+            let tip = Arc::clone(ipc.tip_tracker());
+            clock.connected(Arc::clone(&tip));
+            let proposal_cache = Arc::clone(ipc.proposal_cache());
+            // Veto tallies followed through the escalation bands, as the registry recomputes them
+            let escalation = match escalation::VetoEscalation::open(config.veto_escalation.clone(), Arc::clone(&db)) {
+                Ok(escalation) => Arc::new(escalation.with_webhook(Arc::clone(&webhook_client))),
+                Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to load veto escalation bands: {}", e.chain())).await),
+            };
+            let registry = economic_nodes::EconomicNodeRegistry::new(config.registry.clone(), Arc::clone(&node_api))
+                .await
+                .and_then(|r| {
+                    let r = match &audit_log {
+                        Some(log) => r.with_audit_log(Arc::clone(log)),
+                        None => r,
+                    };
+                    let r = match &feed {
+                        Some(feed) => r.with_feed(Arc::clone(feed)),
+                        None => r,
+                    };
+                    let r = match &digest {
+                        Some(digest) => r.with_digest(Arc::clone(digest)),
+                        None => r,
+                    };
+                    r.with_intents(Arc::clone(&intents))
+                        .with_escalation(Arc::clone(&escalation))
+                        .with_request_timeouts(config.request_timeouts())
+                        .with_retry_policy(config.ipc.retry_policy())
+                        .with_ipc_metrics(Arc::clone(&metrics))
+                        .with_max_message_bytes(config.ipc.max_message_bytes)
+                        .with_in_flight(Arc::clone(&in_flight))
+                        .with_tip_tracker(Arc::clone(&tip))
+                        .with_proposal_cache(Arc::clone(&proposal_cache))
+                        .with_actions(config.allow_actions, Arc::clone(&action_audit))
+                        .with_dry_run(dry_run.actions())
+                        .with_pause(Arc::clone(&pause))
+                        .with_memory_budget(&memory)
+                        .with_error_reporter(Arc::clone(&errors))
+                        .with_store(Arc::clone(&db))
+                });
+            let economic_nodes = match registry {
+                Ok(registry) => Arc::new(registry),
+                Err(e) => {
+                    errors.report(
+                        error_report::ErrorReport::new(error_report::ErrorCode::RegistryUnreadable, e.to_string())
+                            .with_context("path", layout.store().display().to_string()),
+                    );
+                    if let Err(e) = errors.send(node_api.as_ref(), clock::unix_now()).await {
+                        warn!("Failed to report the registry error to the node: {}", e.chain());
+                    }
+                    return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to create economic node registry: {}", e.chain())).await);
+                }
+            };
+            // Delegated votes counted in tallies
+            let delegations = if config.delegation.enabled {
+                match delegation::DelegationRegistry::open(config.delegation.clone(), Arc::clone(&db), ipc.clone()) {
+                    Ok(delegations) => Some(Arc::new(delegations)),
+                    Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to load delegations: {}", e.chain())).await),
+                }
+            } else {
+                None
+            };
+            let mut proposal_store = proposals::ProposalStore::new(Arc::clone(&db))
+                .with_node_api(ipc.clone())
+                .with_tally(config.tally.clone())
+                .with_deadlines(config.deadlines.clone())
+                .with_activation(config.activation.clone())
+                .with_webhook(Arc::clone(&webhook_client))
+                .with_registry(Arc::clone(&economic_nodes))
+                .with_conflicts(config.conflicts.clone())
+                .with_amendments(config.amendments.clone());
+            // Content of created proposals checked against its OP_RETURN commitment
+            if config.content.verify {
+                match content::ContentVerifier::new(config.content.clone(), ipc.clone()) {
+                    Ok(verifier) => proposal_store = proposal_store.with_content_verifier(Arc::new(verifier)),
+                    Err(e) => warn!("Not verifying proposal content: {}", e.chain()),
+                }
+            }
+            if let Some(delegations) = &delegations {
+                proposal_store = proposal_store.with_delegations(Arc::clone(delegations));
+            }
+            if let Some(log) = &audit_log {
+                proposal_store = proposal_store.with_audit_log(Arc::clone(log));
+            }
+            if let Some(feed) = &feed {
+                proposal_store = proposal_store.with_feed(Arc::clone(feed));
+            }
+            let proposal_store = Arc::new(proposal_store);
+            escalation.set_proposals(Arc::clone(&proposal_store));
+            // Re-reads the configuration on file changes, SIGHUP and `reload_config`
+            let mut reloader = config_reload::ConfigReloader::new(
+                {
+                    let bootstrap = bootstrap.clone();
+                    let data_dir = data_dir.clone();
+                    let config_path = config_path.clone();
+                    move || {
+                        let (ctx, _) = bootstrap.context_with_config::<GovernanceConfig>(&data_dir);
+                        let config = config_check::read_valid(&config_path, &ctx.config)?;
+                        let paths = config_reload::ModulePaths {
+                            data_dir: std::path::PathBuf::from(&ctx.data_dir),
+                            socket_path: std::path::PathBuf::from(&ctx.socket_path),
+                        };
+                        Ok((config, paths))
+                    }
+                },
+                config.clone(),
+                paths,
+                Arc::clone(&webhook_client),
+                Arc::clone(&economic_nodes),
+            );
+            if let Some(forwarder) = &log_forwarder {
+                forwarder.reconfigure(&config.log_forward);
+                reloader = reloader.with_log_forwarder(Arc::clone(forwarder));
+            }
+            alerts.reconfigure(&config.alerts);
+            reloader = reloader
+                .with_alerts(alerts)
+                .with_log_level(log_level)
+                .with_systemd(Arc::clone(&systemd));
+            let config_reload = Arc::new(reloader);
+            let (events, event_rx) = event_queue::EventQueue::new(&config.events);
+            let events = Arc::new(events.with_memory(
+                Arc::clone(memory.share(memory::Component::EventQueue)),
+                Some(layout.spill()),
+            ));
+            tasks.lock().unwrap().extend(
+                [
+                    economic_nodes.spawn_reconciliation(),
+                    economic_nodes.spawn_veto_reporting(),
+                    economic_nodes.spawn_access_reload(),
+                    economic_nodes.spawn_mempool_watch(),
+                    escalation.spawn(Arc::clone(&node_api)),
+                    Some(webhook_client.spawn_registry_feed(
+                        economic_nodes.subscribe_changes(),
+                        Arc::clone(&node_api),
+                        Arc::clone(&shutdown),
+                    )),
+                    webhook_client.spawn_digest(Arc::clone(&node_api), Arc::clone(&shutdown)),
+                    Some(config_reload.spawn(config_path.clone(), config.config_reload_secs, hangup)),
+                    heartbeat.spawn(Arc::clone(&node_api), config.heartbeat.clone()),
+                    metrics.spawn_summary(config.ipc.metrics_log_interval_secs),
+                    log_forwarder.as_ref().and_then(|f| f.spawn(Arc::clone(&node_api))),
+                    errors.spawn(Arc::clone(&node_api)),
+                    backup::spawn_scheduled(
+                        Arc::clone(&economic_nodes),
+                        Arc::clone(&proposal_store),
+                        layout.clone(),
+                        config.backup.clone(),
+                    ),
+                ]
+                .into_iter()
+                .flatten(),
+            );
+            let checkpointer = match checkpoint::Checkpointer::open(&layout.state()) {
+                Ok(checkpointer) => Arc::new(checkpointer),
+                Err(e) => return Err(fatal(&shutdown, node_api.as_ref(), format!("Failed to load event checkpoint: {}", e)).await),
+            };
+            let signaling = Arc::new(
+                signaling::SignalingTracker::new(config.signaling.clone(), ipc.clone())
+                    .with_webhook(Arc::clone(&webhook_client)),
+            );
+            let adoption = Arc::new(
+                adoption::AdoptionTracker::new(config.adoption.clone(), Arc::clone(&db), ipc.clone(), Arc::clone(&proposal_store), Arc::clone(&economic_nodes))
+                    .with_webhook(Arc::clone(&webhook_client)),
+            );
+            let participation = Arc::new(participation::ParticipationAnalyzer::new(config.participation.clone(), Arc::clone(&db), Arc::clone(&proposal_store), Arc::clone(&economic_nodes)));
+            let epoch_summaries = Arc::new(
+                epoch_summary::EpochSummarizer::new(config.epoch_summary.clone(), Arc::clone(&db), Arc::clone(&proposal_store))
+                    .with_registry(Arc::clone(&economic_nodes))
+                    .with_signaling(Arc::clone(&signaling))
+                    .with_adoption(Arc::clone(&adoption))
+                    .with_participation(Arc::clone(&participation))
+                    .with_webhook(Arc::clone(&webhook_client)),
             );
-            let proposal_store = Arc::new(proposals::ProposalStore::new(Arc::clone(&db)));
-            let governance_api = Arc::new(GovernanceModuleApi::new(
+            // Commitments to the governance state broadcast through the node's wallet
+            let anchorer = config.anchor.enabled.then(|| {
+                let anchorer = anchor::Anchorer::new(config.anchor.clone(), Arc::clone(&db), ipc.clone(), Arc::clone(&economic_nodes), Arc::clone(&proposal_store))
+                    .with_error_reporter(Arc::clone(&errors))
+                    .with_dry_run(dry_run.actions());
+                Arc::new(match &audit_log {
+                    Some(log) => anchorer.with_audit_log(Arc::clone(log)),
+                    None => anchorer,
+                })
+            });
+            // Every event goes through these, in order; see blvm_governance::pipeline
+            let mut handlers: Vec<Arc<dyn pipeline::EventHandler>> = vec![
+                Arc::clone(&webhook_client) as _,
+                Arc::clone(&economic_nodes) as _,
+                Arc::clone(&proposal_store) as _,
+                Arc::clone(&clock) as _,
+                Arc::clone(&signaling) as _,
+                Arc::clone(&adoption) as _,
+                // After the proposal store, so proposals closed by a block are counted on it
+                Arc::clone(&participation) as _,
+                // After the others, so the epoch's blocks are in every store
+                Arc::clone(&epoch_summaries) as _,
+            ];
+            // Last, so the state it commits to covers the block
+            if let Some(anchorer) = &anchorer {
+                handlers.push(Arc::clone(anchorer) as _);
+            }
+            // Ahead of the proposal store, so a block's delegations are in its tallies
+            if let Some(delegations) = &delegations {
+                handlers.insert(2, Arc::clone(delegations) as _);
+            }
+            // Ahead of the others, so an event is recorded before anything acts on it
+            if let Some(log) = &audit_log {
+                handlers.insert(0, Arc::clone(log) as _);
+            }
+            let mut pipeline = pipeline::Pipeline::new(Arc::clone(&checkpointer))
+                .with_handlers(handlers)
+                .with_panic_isolation(config.crash.isolate_handler_panics)
+                .with_error_reporter(Arc::clone(&errors))
+                .with_intents(intents)
+                .with_pause(Arc::clone(&pause));
+            // Rollbacks after reorgs are audited with the events
+            if let Some(log) = &audit_log {
+                pipeline = pipeline.with_audit_log(Arc::clone(log));
+            }
+            let pipeline = Arc::new(pipeline);
+            // Side effects a crash cut short, before any new event
+            let recovered = pipeline.recover_intents(node_api.as_ref()).await;
+            if recovered > 0 {
+                info!("Recovered {} intents", recovered);
+            }
+            // The adoption window from the node's chain, as it was not kept across the restart
+            if let Err(e) = adoption.rebuild(node_api.as_ref()).await {
+                warn!("Failed to measure adoption at startup: {}", e.chain());
+            }
+            // What was held while paused, sent on each resume
+            tasks.lock().unwrap().push(pause.spawn_drain(
+                Arc::clone(&pipeline),
+                Arc::clone(&node_api),
+                Arc::clone(&economic_nodes),
+            ));
+            let mut governance_api = GovernanceModuleApi::new(
                 Arc::clone(&proposal_store),
                 Arc::clone(&economic_nodes),
-                webhook_url,
+                Arc::clone(&webhook_client),
                 Arc::clone(&node_api),
-            ));
+            );
+            if config.heartbeat.interval_secs > 0 {
+                governance_api = governance_api.with_heartbeat(Arc::clone(&heartbeat));
+            }
+            governance_api = governance_api
+                .with_event_queue(Arc::clone(&events))
+                .with_subscriptions(Arc::clone(&subscriptions))
+                .with_event_stream(Arc::clone(&stream))
+                .with_ipc_metrics(Arc::clone(&metrics))
+                .with_pipeline(Arc::clone(&pipeline))
+                .with_shutdown(Arc::clone(&shutdown))
+                .with_config_reload(Arc::clone(&config_reload))
+                .with_epoch_summaries(Arc::clone(&epoch_summaries))
+                .with_adoption(Arc::clone(&adoption));
+            if let Some(delegations) = delegations {
+                governance_api = governance_api.with_delegations(delegations);
+            }
+            let governance_api = Arc::new(governance_api);
             if let Err(e) = node_api.register_module_api(governance_api).await {
                 warn!("Failed to register governance module API: {}", e);
             }
             tracing::info!("Governance module initialized and running");
             let module = GovernanceModule {
                 proposal_store,
-                webhook_client: Arc::new(webhook_client),
+                webhook_client,
                 economic_nodes,
+                shutdown,
+                events,
+                subscriptions,
+                stream,
+                metrics: Arc::clone(&metrics),
+                tip,
+                proposal_cache,
+                pipeline,
             };
+            tasks.lock().unwrap().extend(module.spawn_event_worker(
+                event_rx,
+                Arc::clone(&node_api),
+                config.events.parallelism,
+            ));
+            let sources = status_report::StatusSources {
+                metrics: Arc::clone(&metrics),
+                checkpointer: Arc::clone(&checkpointer),
+                events: Arc::clone(&module.events),
+                webhook: Arc::clone(&module.webhook_client),
+                registry: Arc::clone(&module.economic_nodes),
+                heartbeat: Arc::clone(&heartbeat),
+                stream: Arc::clone(&module.stream),
+                config_reload: Some(Arc::clone(&config_reload)),
+                memory: Arc::clone(&memory),
+                clock: Arc::clone(&clock),
+                errors: Arc::clone(&errors),
+                adoption: Some(adoption),
+                participation: config.participation.enabled.then(|| Arc::clone(&participation)),
+            };
+            // Pull request deliveries; changes of state go to the node when actions are allowed
+            let github = config.github.secret.as_ref().map(|_| {
+                let receiver = github::GithubReceiver::new(config.github.clone(), Arc::clone(&module.proposal_store));
+                Arc::new(if config.allow_actions { receiver.with_actions(ipc.clone()) } else { receiver })
+            });
+            let mut providers: Vec<Arc<dyn status::StatusProvider>> = vec![
+                Arc::clone(&checkpointer) as _,
+                Arc::clone(&module.events) as _,
+                Arc::clone(&module.webhook_client) as _,
+                Arc::clone(&module.economic_nodes) as _,
+                Arc::clone(&module.proposal_store) as _,
+                Arc::clone(&config_reload) as _,
+                Arc::new(network) as _,
+            ];
+            providers.extend(github.iter().map(|g| Arc::clone(g) as _));
+            providers.extend(anchorer.iter().map(|a| Arc::clone(a) as _));
+            module_status.connected(providers);
+            tasks.lock().unwrap().extend(status_report::spawn(
+                Arc::clone(&module_status),
+                Arc::clone(&node_api),
+                config.ipc.status_report_interval_secs,
+            ));
+            // Blocks announced while the module was down or disconnected, ahead of live events
+            if let Err(e) = checkpoint::backfill(&checkpointer, &ipc.bulk(), &module.events, &module.shutdown, config.ipc.max_backfill_blocks).await {
+                warn!("Failed to backfill missed blocks: {}", e.chain());
+            }
+            health.connected(sources);
+            // POST /actions/veto and /actions/vote on the health listener, where configured
+            if let Some(token) = &config.health.actions_token {
+                let actions = actions::LocalActions::new(token.clone(), Arc::clone(&module.economic_nodes), ipc.clone());
+                health.serve_actions(Arc::new(match &audit_log {
+                    Some(log) => actions.with_audit_log(Arc::clone(log)),
+                    None => actions,
+                }));
+            }
+            if let Some(github) = github {
+                health.serve_github(github);
+            }
+            // GET /proposals, /economic-nodes, /epochs/{n}/summary, /status, /dashboard and /participation/{id}, where configured
+            if config.health.queries {
+                let queries = query::QueryApi::new(config.health.actions_token.clone(), Arc::clone(&module.proposal_store), Arc::clone(&module.economic_nodes), epoch_summaries)
+                    .with_status(Arc::clone(&module_status))
+                    .with_webhook(Arc::clone(&module.webhook_client))
+                    .with_tip(Arc::clone(&module.tip));
+                let queries = if config.participation.enabled { queries.with_participation(Arc::clone(&participation)) } else { queries };
+                let queries = if config.heartbeat.interval_secs > 0 { queries.with_heartbeat(Arc::clone(&heartbeat)) } else { queries };
+                health.serve_queries(Arc::new(queries));
+            }
+            systemd.ready();
+            crash_reporter.connected(Arc::clone(&node_api), Arc::clone(&module.webhook_client));
+            *active.lock().unwrap() = Some((module.clone(), Arc::clone(&node_api)));
             Ok((module.clone(), module))
         }
     };
 
-    blvm_sdk::run_module! {
-        bootstrap: &bootstrap,
-        module_name: MODULE_NAME,
-        module_type: GovernanceModule,
-        cli_type: GovernanceModule,
-        db: db.as_db(),
-        setup: setup,
-        event_types: GovernanceModule::event_types(),
-    }?;
+    let mut backoff = reconnect::Backoff::new(config.reconnect.clone());
+    let mut reconnecting = false;
+    // Consecutive connection failures not recognised as retryable or fatal
+    let mut unknown_failures = 0;
 
-    warn!("Event receiver closed, module shutting down");
-    Ok(())
+    let socket_path = paths.socket_path.clone();
+    if config.ipc.insecure_socket {
+        warn!("Not checking the owner and permissions of {}", socket_path.display());
+    }
+    if config.reconnect.wait_for_socket {
+        if !socket_path.exists() {
+            info!("Waiting for node socket {}", socket_path.display());
+        }
+        if !reconnect::wait_for_socket(&socket_path, backoff.startup_remaining()).await {
+            anyhow::bail!(
+                "node socket {} did not appear within {}s",
+                socket_path.display(),
+                config.reconnect.connect_timeout_secs
+            );
+        }
+    }
+
+    tokio::spawn({
+        let shutdown = Arc::clone(&shutdown);
+        async move {
+            let signal = shutdown::signal().await;
+            info!("Received {}, shutting down", signal);
+            shutdown.begin();
+            let signal = shutdown::signal().await;
+            warn!("Received {} during shutdown, exiting immediately", signal);
+            logging::flush();
+            std::process::exit(1);
+        }
+    });
+
+    loop {
+        let connected_at = std::time::Instant::now();
+        let event_types = subscriptions.current();
+        if reconnecting {
+            info!("Re-subscribing to {} event types: {:?}", event_types.len(), event_types);
+        }
+        reconnecting = true;
+        heartbeat.reset();
+        stream.reset();
+        let connection = async {
+            // The node may have recreated the socket since the last connection
+            socket_check::check(&socket_path, &config.ipc)?;
+            let result = blvm_sdk::run_module! {
+                bootstrap: &bootstrap,
+                module_name: MODULE_NAME,
+                module_type: GovernanceModule,
+                cli_type: GovernanceModule,
+                db: db.as_db(),
+                setup: setup,
+                event_types: event_types,
+            };
+            result.map_err(anyhow::Error::from)
+        };
+        let mut connection = std::pin::pin!(connection);
+        // Dropping the connection future closes the socket
+        let mut finished = false;
+        let result = tokio::select! {
+            result = &mut connection => {
+                finished = true;
+                Some(result)
+            }
+            _ = heartbeat.dead() => Some(Err(GovernanceError::Timeout {
+                operation: "node heartbeats".to_string(),
+                after: std::time::Duration::from_secs(config.heartbeat.interval_secs * config.heartbeat.max_missed as u64),
+            }
+            .into())),
+            _ = shutdown.stopping() => None,
+        };
+        // A fatal setup error ends the connection and begins shutdown together
+        let result = result.filter(|_| !shutdown.is_stopping());
+        let Some(result) = result else {
+            // New events are ignored now; keep the connection up while accepted work finishes
+            systemd.stopping();
+            let reason = shutdown.reason();
+            let deadline = reason.drain_timeout(std::time::Duration::from_secs(config.shutdown.drain_timeout_secs));
+            let drained = tokio::time::timeout(deadline, async {
+                tokio::select! {
+                    _ = shutdown.idle() => {}
+                    _ = &mut connection, if !finished => shutdown.idle().await,
+                }
+            })
+            .await
+            .is_ok();
+            if !drained {
+                warn!(
+                    "Shutdown deadline of {:?} reached with {} tasks still running",
+                    deadline,
+                    shutdown.in_flight()
+                );
+            }
+            let current = active.lock().unwrap().take();
+            if let Some((module, node_api)) = current {
+                module.shutdown(node_api.as_ref(), reason.clone()).await;
+            }
+            for task in tasks.lock().unwrap().drain(..) {
+                task.abort();
+            }
+            for task in health_server.iter().chain(&admin_server).chain(&watchdog).chain([&alert_checks]) {
+                task.abort();
+            }
+            match &reason {
+                shutdown::ShutdownReason::Fatal { error, .. } => {
+                    error!("Governance module stopped: {}", error);
+                    logging::flush();
+                    std::process::exit(reason.exit_code());
+                }
+                shutdown::ShutdownReason::NodeRequested { .. } => {
+                    info!("Governance module stopped at the node's request");
+                    logging::flush();
+                    std::process::exit(reason.exit_code());
+                }
+                _ => {}
+            }
+            info!("Governance module stopped");
+            return Ok(());
+        };
+        // Setup only runs once the node accepted the connection
+        if active.lock().unwrap().take().is_some() {
+            backoff.mark_connected();
+        }
+        health.disconnected();
+        module_status.disconnected();
+        systemd.disconnected();
+        crash_reporter.disconnected();
+        clock.disconnected();
+        for task in tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        match &result {
+            Ok(()) => warn!("Event receiver closed, connection to node lost"),
+            Err(e) => warn!("Connection to node failed: {}", e),
+        }
+        if let Err(e) = &result {
+            let retryability = if let Some(e) = e.downcast_ref::<GovernanceError>() {
+                e.retryability()
+            } else if let Some(e) = e.downcast_ref::<std::io::Error>() {
+                Retryability::of_io(e.kind())
+            } else {
+                Retryability::of_message(&format!("{:#}", e))
+            };
+            unknown_failures = if retryability == Retryability::Unknown { unknown_failures + 1 } else { 0 };
+            if !retryability.should_retry(unknown_failures) {
+                error!("Not reconnecting: {:?} error connecting to node", retryability);
+                return result;
+            }
+        } else {
+            unknown_failures = 0;
+        }
+        if connected_at.elapsed() >= backoff.stable_after() {
+            backoff.reset();
+        }
+        let startup = backoff.startup_remaining().is_some();
+        let Some(delay) = backoff.next_delay() else {
+            if startup {
+                warn!(
+                    "Could not connect to node within {}s, module shutting down",
+                    config.reconnect.connect_timeout_secs
+                );
+            } else {
+                warn!("Giving up reconnecting to node, module shutting down");
+            }
+            result?;
+            return Ok(());
+        };
+        if startup {
+            info!("Retrying connection to node in {:?}", delay);
+        } else {
+            info!("Reconnecting to node in {:?}", delay);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.stopping() => {
+                info!("Governance module stopped");
+                return Ok(());
+            }
+        }
+    }
 }
@@ -0,0 +1,291 @@
+//! Memory budget
+//!
+//! `[governance.memory] budget_bytes` caps the memory the module's queues and caches hold,
+//! whatever the block sizes and however long the node or the webhook is unavailable. The
+//! budget is divided among the [`Component`]s in fixed shares, and each counts the
+//! approximate size of its entries against its [`Share`]: the serialized size of events and
+//! transactions, and fixed sizes for headers and txids.
+//!
+//! - `event_queue` (50%): events waiting for the event worker. Over its share, events are
+//!   spilled to a file in the data directory and fed back in order as the worker catches up
+//!   (see [`crate::event_queue`]). Webhook deliveries are made by the worker, so a slow
+//!   webhook backs up here.
+//! - `transactions` (30%): transactions looked up by txid (see [`crate::node_api`]).
+//! - `headers` (10%): block headers and their heights.
+//! - `mempool_seen` (10%): mempool txids the registry has already checked for spends of
+//!   claimed outpoints; txids that do not fit are checked again on the next pass.
+//!
+//! Caches evict their oldest entries to make room, and skip caching an entry larger than
+//! their whole share. The queue always takes an event when it holds none, so an event larger
+//! than the share is still processed. With no budget (the default), usage is counted but
+//! only the entry counts bound it. Usage is in the status report and, by component, in the
+//! Prometheus metrics.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Smallest budget accepted, so that every share holds a useful number of entries.
+pub const MIN_BUDGET_BYTES: u64 = 1024 * 1024;
+
+/// Counted size of a cached header: the header, its height and its hash.
+pub const HEADER_ENTRY_BYTES: u64 = 80 + 8 + 32;
+
+/// Counted size of a txid in a set.
+pub const TXID_ENTRY_BYTES: u64 = 32;
+
+/// What the budget is divided among.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Component {
+    EventQueue,
+    Transactions,
+    Headers,
+    MempoolSeen,
+}
+
+impl Component {
+    pub const ALL: [Component; 4] = [
+        Component::EventQueue,
+        Component::Transactions,
+        Component::Headers,
+        Component::MempoolSeen,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Component::EventQueue => "event_queue",
+            Component::Transactions => "transactions",
+            Component::Headers => "headers",
+            Component::MempoolSeen => "mempool_seen",
+        }
+    }
+
+    /// Percentage of the budget the component gets.
+    pub fn percent(self) -> u64 {
+        match self {
+            Component::EventQueue => 50,
+            Component::Transactions => 30,
+            Component::Headers => 10,
+            Component::MempoolSeen => 10,
+        }
+    }
+}
+
+/// Bytes one component holds, against its limit.
+#[derive(Debug, Default)]
+pub struct Share {
+    /// 0 is unlimited.
+    limit: u64,
+    used: AtomicU64,
+    /// Entries evicted, or events spilled to disk, to stay within the limit.
+    overflowed: AtomicU64,
+    /// Notified when bytes are released.
+    released: Notify,
+}
+
+impl Share {
+    /// A share of `limit` bytes; 0 only counts.
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Whether `bytes` more would stay within the limit.
+    pub fn fits(&self, bytes: u64) -> bool {
+        self.limit == 0 || self.used().saturating_add(bytes) <= self.limit
+    }
+
+    /// Count `bytes` if they fit.
+    pub fn try_charge(self: &Arc<Self>, bytes: u64) -> Option<Charge> {
+        let mut used = self.used();
+        loop {
+            if self.limit != 0 && used.saturating_add(bytes) > self.limit {
+                return None;
+            }
+            match self.used.compare_exchange_weak(
+                used,
+                used + bytes,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(self.charged(bytes)),
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    /// Count `bytes` whether or not they fit.
+    pub fn charge(self: &Arc<Self>, bytes: u64) -> Charge {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+        self.charged(bytes)
+    }
+
+    fn charged(self: &Arc<Self>, bytes: u64) -> Charge {
+        Charge {
+            share: Arc::clone(self),
+            bytes,
+        }
+    }
+
+    /// Count `entries` evicted or spilled.
+    pub fn overflowed(&self, entries: u64) {
+        self.overflowed.fetch_add(entries, Ordering::Relaxed);
+    }
+
+    /// Wait until bytes are released. Create the future before checking whether bytes fit,
+    /// so that a release in between is not missed.
+    pub fn released(&self) -> tokio::sync::futures::Notified<'_> {
+        self.released.notified()
+    }
+
+    pub fn usage(&self) -> ComponentUsage {
+        ComponentUsage {
+            used_bytes: self.used(),
+            limit_bytes: self.limit,
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Bytes counted against a [`Share`] until dropped.
+#[derive(Debug)]
+pub struct Charge {
+    share: Arc<Share>,
+    bytes: u64,
+}
+
+impl Charge {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.share.used.fetch_sub(self.bytes, Ordering::Relaxed);
+        self.share.released.notify_waiters();
+    }
+}
+
+/// Usage of one component, as reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentUsage {
+    pub used_bytes: u64,
+    /// 0 is unlimited.
+    pub limit_bytes: u64,
+    /// Entries evicted, or events spilled to disk, to stay within the limit.
+    pub overflowed: u64,
+}
+
+/// The budget of the process, divided into a [`Share`] per [`Component`].
+#[derive(Debug)]
+pub struct MemoryBudget {
+    budget_bytes: u64,
+    shares: [Arc<Share>; Component::ALL.len()],
+}
+
+/// The bytes of `budget_bytes` that `component` gets, rounded down.
+fn percent_of(budget_bytes: u64, component: Component) -> u64 {
+    let percent = component.percent();
+    budget_bytes / 100 * percent + budget_bytes % 100 * percent / 100
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl MemoryBudget {
+    /// Divide `budget_bytes` among the components; 0 only counts.
+    pub fn new(budget_bytes: u64) -> Self {
+        Self {
+            budget_bytes,
+            shares: Component::ALL.map(|c| Arc::new(Share::new(percent_of(budget_bytes, c)))),
+        }
+    }
+
+    pub fn budget_bytes(&self) -> u64 {
+        self.budget_bytes
+    }
+
+    pub fn share(&self, component: Component) -> &Arc<Share> {
+        &self.shares[component as usize]
+    }
+
+    /// Bytes held by all components together.
+    pub fn used(&self) -> u64 {
+        self.shares.iter().map(|s| s.used()).sum()
+    }
+
+    /// Usage by component name.
+    pub fn usage(&self) -> BTreeMap<String, ComponentUsage> {
+        Component::ALL
+            .iter()
+            .map(|&c| (c.as_str().to_string(), self.share(c).usage()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shares_add_up_to_budget() {
+        let budget = MemoryBudget::new(10 * MIN_BUDGET_BYTES);
+        let limits: u64 = Component::ALL
+            .iter()
+            .map(|&c| budget.share(c).limit())
+            .sum();
+        assert!(limits <= budget.budget_bytes());
+        assert_eq!(
+            budget.share(Component::EventQueue).limit(),
+            budget.budget_bytes() / 2
+        );
+        assert_eq!(MemoryBudget::default().share(Component::Headers).limit(), 0);
+    }
+
+    #[test]
+    fn test_charges_are_released_on_drop() {
+        let share = Arc::new(Share::new(100));
+        let first = share.try_charge(60).unwrap();
+        assert!(share.try_charge(41).is_none());
+        assert!(!share.fits(41));
+        let second = share.try_charge(40).unwrap();
+        assert_eq!(share.used(), 100);
+
+        // Charged over the limit when it has to be
+        let forced = share.charge(10);
+        assert_eq!(share.used(), 110);
+        drop((first, forced));
+        assert_eq!(share.used(), 40);
+        assert_eq!(second.bytes(), 40);
+        drop(second);
+        assert_eq!(
+            share.usage(),
+            ComponentUsage {
+                used_bytes: 0,
+                limit_bytes: 100,
+                overflowed: 0
+            }
+        );
+
+        // Unlimited shares only count
+        let unlimited = Arc::new(Share::new(0));
+        let _big = unlimited.try_charge(u64::MAX / 2).unwrap();
+        assert!(unlimited.fits(1));
+    }
+}
@@ -1,12 +1,20 @@
 //! Governance module: unified CLI via #[module] macro.
 
-use blvm_node::module::ipc::protocol::{EventMessage, ModuleMessage};
+use blvm_node::module::ipc::protocol::EventMessage;
 use blvm_sdk::module::prelude::*;
 use blvm_sdk_macros::module;
 use std::sync::Arc;
 
 use crate::economic_nodes::EconomicNodeRegistry;
+use crate::event_queue::{EventQueue, QueuedEvent};
+use crate::event_stream::EventStreamMonitor;
+use crate::executor::Lanes;
+use crate::ipc_metrics::IpcMetrics;
+use crate::node_api::{ProposalCache, TipTracker};
+use crate::pipeline::Pipeline;
 use crate::proposals::ProposalStore;
+use crate::shutdown::Shutdown;
+use crate::subscriptions::EventSubscriptions;
 use crate::webhook::GovernanceWebhookClient;
 
 /// Governance module: CLI + event handlers in one struct.
@@ -15,23 +23,24 @@ pub struct GovernanceModule {
     pub proposal_store: Arc<ProposalStore>,
     pub webhook_client: Arc<GovernanceWebhookClient>,
     pub economic_nodes: Arc<EconomicNodeRegistry>,
+    pub shutdown: Arc<Shutdown>,
+    pub events: Arc<EventQueue>,
+    pub subscriptions: Arc<EventSubscriptions>,
+    pub stream: Arc<EventStreamMonitor>,
+    pub metrics: Arc<IpcMetrics>,
+    /// Chain tip of the connection, advanced by `NewBlock` events
+    pub tip: Arc<TipTracker>,
+    /// Proposal records fetched from the node, dropped on votes and merges
+    pub proposal_cache: Arc<ProposalCache>,
+    /// Handlers every event is passed to, in order; see crate::pipeline
+    pub pipeline: Arc<Pipeline>,
 }
 
 #[module]
 impl GovernanceModule {
     #[on_event(GovernanceProposalCreated, GovernanceProposalVoted, GovernanceProposalMerged, EconomicNodeRegistered, EconomicNodeVeto, NewBlock)]
-    async fn on_governance_event(&self, event: &EventMessage, ctx: &InvocationContext) -> Result<(), ModuleError> {
-        let msg = ModuleMessage::Event(event.clone());
-        let api = ctx.node_api().expect("node_api required");
-        if let Err(e) = self.webhook_client.handle_event(&msg, api.as_ref()).await {
-            tracing::warn!("Error handling event in webhook client: {}", e);
-        }
-        if let Err(e) = self.economic_nodes.handle_event(&msg, api.as_ref()).await {
-            tracing::warn!("Error handling event in economic node registry: {}", e);
-        }
-        if let Err(e) = self.proposal_store.handle_event(&msg) {
-            tracing::warn!("Error handling event in proposal store: {}", e);
-        }
+    async fn on_governance_event(&self, event: &EventMessage, _ctx: &InvocationContext) -> Result<(), ModuleError> {
+        self.dispatch(event).await;
         Ok(())
     }
 
@@ -48,13 +57,9 @@ impl GovernanceModule {
         }
         let mut out = format!("Proposals ({}):\n", proposals.len());
         for (i, p) in proposals.iter().enumerate() {
-            let status = match &p.status {
-                crate::proposals::ProposalStatus::Created => "open",
-                crate::proposals::ProposalStatus::Merged => "merged",
-            };
             out.push_str(&format!(
                 "  {}. {} | {}#{} | {} | {} votes\n",
-                i + 1, p.proposal_id, p.repository, p.pr_number, status, p.votes.len(),
+                i + 1, p.proposal_id, p.repository, p.pr_number, p.status.as_str(), p.votes.len(),
             ));
         }
         Ok(out)
@@ -64,8 +69,8 @@ impl GovernanceModule {
     #[command]
     fn webhook_test(&self, _ctx: &InvocationContext) -> Result<String, ModuleError> {
         let data_dir = std::env::var("DATA_DIR").unwrap_or_else(|_| "data/modules/blvm-governance".into());
-        let config_path = std::path::Path::new(&data_dir).join("config.toml");
-        let config = crate::GovernanceConfig::load(&config_path).unwrap_or_default();
+        let config_path = crate::config::file_path(std::path::Path::new(&data_dir));
+        let config = crate::GovernanceConfig::from_file(&config_path).unwrap_or_default();
         let Some(url) = &config.webhook_url else {
             return Ok("Webhook not configured (governance.webhook_url). Set in config.toml.".into());
         };
@@ -82,16 +87,306 @@ impl GovernanceModule {
         match res {
             Ok(r) if r.status().is_success() => Ok(format!("Webhook test OK: {} {}", r.status(), url)),
             Ok(r) => Ok(format!("Webhook returned {}: {}", r.status(), url)),
-            Err(e) => Ok(format!("Webhook test failed: {} - {}", url, e)),
+            Err(e) => Ok(format!("Webhook test failed: {} - {}", url, crate::error::Chain(&e))),
+        }
+    }
+
+    /// Show one economic node's full record: show-node <node_id> [--include-archived]
+    #[command]
+    fn show_node(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        let include_archived = args.iter().any(|a| a == "--include-archived");
+        let Some(id_arg) = args.iter().find(|a| !a.starts_with("--")) else {
+            return Ok("Usage: show-node <node_id> [--include-archived]".into());
+        };
+        let Some(node_id) = crate::economic_nodes::parse_node_id(id_arg) else {
+            return Ok(format!("Invalid node id (expected 64 hex characters): {}", id_arg));
+        };
+        let details = self
+            .economic_nodes
+            .read_node_from_store(&node_id, include_archived)
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        let Some(d) = details else {
+            return Ok(format!("Economic node not found: {}", id_arg));
+        };
+        Ok(crate::cli::format_node(id_arg, &d))
+    }
+
+    /// Report suspected Sybil clusters in the persisted registry: sybil-report
+    #[command]
+    fn sybil_report(&self, _ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let report = self
+            .economic_nodes
+            .sybil_report_from_store()
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        if report.clusters.is_empty() {
+            return Ok(format!("No suspected clusters (height {})", report.height));
+        }
+        let mut out = format!(
+            "{} suspected clusters (height {}):\n",
+            report.clusters.len(),
+            report.height
+        );
+        for c in &report.clusters {
+            let signals: Vec<String> = c.signals.iter().map(|s| format!("{:?}", s)).collect();
+            out.push_str(&format!(
+                "  confidence {:.2}, {} nodes, combined weight {:.4}, signals: {}\n",
+                c.confidence,
+                c.members.len(),
+                c.combined_weight,
+                signals.join(", ")
+            ));
+            for m in &c.members {
+                out.push_str(&format!("    {}\n", m));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Export the persisted registry: export-registry [--format json|csv] [--out <dir>]
+    #[command]
+    fn export_registry(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        let flag = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .map(|v| v.as_str())
+        };
+        let format = flag("--format").unwrap_or("json");
+        let out_dir = std::path::PathBuf::from(flag("--out").unwrap_or("."));
+        let tiers: std::collections::HashMap<String, String> = self
+            .proposal_store
+            .load_proposals()
+            .map_err(|e| ModuleError::Other(e.to_string()))?
+            .into_iter()
+            .map(|p| (p.proposal_id, p.tier))
+            .collect();
+        let export = self
+            .economic_nodes
+            .export_from_store(&tiers)
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        let files = match format {
+            "json" => vec![(
+                "registry.json",
+                export.to_json().map_err(|e| ModuleError::Other(e.to_string()))?,
+            )],
+            "csv" => vec![
+                ("economic_nodes.csv", export.nodes_csv()),
+                ("veto_tallies.csv", export.tallies_csv()),
+            ],
+            other => return Ok(format!("Unknown format {:?} (expected json or csv)", other)),
+        };
+        let mut out = format!(
+            "Exported {} nodes and {} tallies at height {}:\n",
+            export.nodes.len(),
+            export.tallies.len(),
+            export.height
+        );
+        for (name, contents) in files {
+            let path = out_dir.join(name);
+            std::fs::write(&path, contents)
+                .map_err(|e| ModuleError::Other(format!("{}: {}", path.display(), e)))?;
+            out.push_str(&format!("  {}\n", path.display()));
+        }
+        Ok(out)
+    }
+
+    /// Back up the running module's registry and proposals to an empty directory:
+    /// backup <dir>. Restore by starting the module with --data-dir <dir>.
+    #[command]
+    fn backup(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        let Some(dir) = args.iter().find(|a| !a.starts_with("--")) else {
+            return Ok("Usage: backup <dir>".into());
+        };
+        let data_dir = std::env::var("DATA_DIR").unwrap_or_else(|_| "data/modules/blvm-governance".into());
+        let config_path = crate::config::file_path(std::path::Path::new(&data_dir));
+        // Commands are synchronous; the registry's locks are only held while records are read
+        let manifest = futures::executor::block_on(crate::backup::backup(
+            &self.economic_nodes,
+            &self.proposal_store,
+            Some(&config_path),
+            std::path::Path::new(dir),
+        ))
+        .map_err(|e| ModuleError::Other(e.to_string()))?;
+        Ok(format!(
+            "Backed up {} economic nodes to {} (schema {}, commitment {})",
+            manifest.node_count, dir, manifest.schema_version, manifest.commitment
+        ))
+    }
+
+    /// Subscribe to event types at runtime: subscribe-events <type>... (e.g. NewBlock)
+    #[command]
+    fn subscribe_events(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        if args.is_empty() {
+            return Ok(format!(
+                "Usage: subscribe-events <type>...\nSupported: {:?}",
+                self.subscriptions.supported()
+            ));
+        }
+        let added = self
+            .subscriptions
+            .parse(&args)
+            .and_then(|types| self.subscriptions.add(types))
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        Ok(format!(
+            "Subscribed to {:?}; now subscribed to {:?}",
+            added,
+            self.subscriptions.current()
+        ))
+    }
+
+    /// Unsubscribe from event types at runtime, e.g. NewBlock during initial sync:
+    /// unsubscribe-events <type>...
+    #[command]
+    fn unsubscribe_events(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        if args.is_empty() {
+            return Ok("Usage: unsubscribe-events <type>...".into());
+        }
+        let types = self
+            .subscriptions
+            .parse(&args)
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        let removed = self.subscriptions.remove(types);
+        Ok(format!(
+            "Unsubscribed from {:?}; now subscribed to {:?}",
+            removed,
+            self.subscriptions.current()
+        ))
+    }
+
+    /// Check a backup against its manifest before restoring it: verify-backup <dir>
+    #[command]
+    fn verify_backup(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        let Some(dir) = args.iter().find(|a| !a.starts_with("--")) else {
+            return Ok("Usage: verify-backup <dir>".into());
+        };
+        match crate::backup::verify(std::path::Path::new(dir)) {
+            Ok(m) => Ok(format!(
+                "Backup OK: {} economic nodes, schema {}, commitment {}, created at {}",
+                m.node_count, m.schema_version, m.commitment, m.created_at
+            )),
+            Err(e) => Ok(format!("Backup verification failed: {}", e.chain())),
         }
     }
 
+    /// Compact the persisted registry: drop expired archives, coalesce weight histories and
+    /// rewrite the store: compact-registry
+    #[command]
+    fn compact_registry(&self, _ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let summary = self
+            .economic_nodes
+            .compact_store()
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        Ok(format!(
+            "Compacted registry at height {}: {} archived nodes and {} history records dropped, \
+             {} -> {} bytes",
+            summary.height,
+            summary.archived_dropped,
+            summary.history_records_dropped,
+            summary.bytes_before,
+            summary.bytes_after
+        ))
+    }
+
+    /// Simulate vetoes against the persisted registry without recording anything:
+    /// simulate-veto --tier <tier> | --proposal <id> [--nodes a,b,c] [--categories x,y]
+    /// [--snapshot <id>]
+    #[command]
+    fn simulate_veto(&self, ctx: &InvocationContext) -> Result<String, ModuleError> {
+        let args = ctx.args();
+        let flag = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .map(|v| v.as_str())
+        };
+        let list = |name: &str| -> Vec<String> {
+            flag(name)
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default()
+        };
+        let mut nodes = Vec::new();
+        for id in list("--nodes") {
+            let Some(node_id) = crate::economic_nodes::parse_node_id(&id) else {
+                return Ok(format!("Invalid node id (expected 64 hex characters): {}", id));
+            };
+            nodes.push(node_id);
+        }
+        let snapshot = match flag("--snapshot").map(str::parse::<u64>) {
+            Some(Ok(id)) => Some(id),
+            Some(Err(_)) => return Ok("Invalid --snapshot (expected an epoch number)".into()),
+            None => None,
+        };
+        let proposal_id = flag("--proposal").map(String::from);
+        let mut tier = flag("--tier").map(String::from);
+        if tier.is_none() {
+            if let Some(id) = &proposal_id {
+                tier = self
+                    .proposal_store
+                    .load_proposals()
+                    .map_err(|e| ModuleError::Other(e.to_string()))?
+                    .into_iter()
+                    .find(|p| &p.proposal_id == id)
+                    .map(|p| p.tier);
+            }
+        }
+        if proposal_id.is_none() && tier.is_none() {
+            return Ok("Usage: simulate-veto --tier <tier> | --proposal <id> [--nodes a,b,c] \
+                [--categories x,y] [--snapshot <id>]".into());
+        }
+        let scenario = crate::economic_nodes::VetoScenario {
+            proposal_id,
+            tier,
+            nodes,
+            categories: list("--categories"),
+            snapshot,
+        };
+        let sim = self
+            .economic_nodes
+            .simulate_veto_from_store(&scenario)
+            .map_err(|e| ModuleError::Other(e.to_string()))?;
+        let mut out = format!(
+            "Veto simulation{}{} at height {} ({} weights)
+               vetoing weight: {:.4} of {:.4} ({:.2}%), threshold {}%
+               crossed: {}{}
+               vetoing nodes ({}):
+",
+            sim.proposal_id.as_deref().map(|p| format!(" for {}", p)).unwrap_or_default(),
+            sim.tier.as_deref().map(|t| format!(" [{}]", t)).unwrap_or_default(),
+            sim.height,
+            sim.snapshot.map(|id| format!("snapshot {}", id)).unwrap_or_else(|| "live".into()),
+            sim.vetoing_weight,
+            sim.total_weight,
+            sim.veto_percent,
+            sim.threshold_percent,
+            if sim.crossed { "yes" } else { "no" },
+            if sim.crossed {
+                String::new()
+            } else {
+                format!(" ({:.4} more weight needed)", sim.additional_weight_needed)
+            },
+            sim.vetoing_nodes.len(),
+        );
+        for id in &sim.vetoing_nodes {
+            out.push_str(&format!("    {}\n", id));
+        }
+        for id in &sim.unknown_nodes {
+            out.push_str(&format!("  not in registry: {}\n", id));
+        }
+        Ok(out)
+    }
+
     /// Show module status.
     #[command]
     fn status(&self, _ctx: &InvocationContext) -> Result<String, ModuleError> {
         let data_dir = std::env::var("DATA_DIR").unwrap_or_else(|_| "data/modules/blvm-governance".into());
-        let config_path = std::path::Path::new(&data_dir).join("config.toml");
-        let config = crate::GovernanceConfig::load(&config_path).unwrap_or_default();
+        let config_path = crate::config::file_path(std::path::Path::new(&data_dir));
+        let config = crate::GovernanceConfig::from_file(&config_path).unwrap_or_default();
         Ok(format!(
             "blvm-governance module\n\
              Config: {}\n\
@@ -102,6 +397,23 @@ impl GovernanceModule {
     }
 }
 
+/// Events with the same key are processed in order: proposal id for proposal events and
+/// vetoes, node id for registrations, and a single lane for all blocks.
+pub fn ordering_key(event: &EventMessage) -> String {
+    use blvm_node::module::ipc::protocol::EventPayload;
+    match &event.payload {
+        EventPayload::GovernanceProposalCreated { proposal_id, .. }
+        | EventPayload::GovernanceProposalVoted { proposal_id, .. }
+        | EventPayload::GovernanceProposalMerged { proposal_id, .. }
+        | EventPayload::EconomicNodeVeto { proposal_id, .. } => {
+            format!("proposal:{}", proposal_id)
+        }
+        EventPayload::EconomicNodeRegistered { node_id, .. } => format!("node:{}", node_id),
+        EventPayload::NewBlock { .. } => "blocks".to_string(),
+        _ => format!("{:?}", event.event_type),
+    }
+}
+
 impl GovernanceModule {
     /// Handle node events: webhook, economic nodes, proposal store.
     pub async fn handle_event(
@@ -118,8 +430,107 @@ impl GovernanceModule {
             .await
             .map_err(|e| blvm_node::module::traits::ModuleError::Other(e.to_string()))?;
         self.proposal_store
-            .handle_event(event)
+            .handle_event(event, node_api)
+            .await
             .map_err(|e| blvm_node::module::traits::ModuleError::Other(e.to_string()))?;
         Ok(())
     }
+
+    /// Filter an event from the node and queue it for the event worker.
+    pub async fn dispatch(&self, event: &EventMessage) {
+        self.metrics.record_event(&event.event_type);
+        if let blvm_node::module::ipc::protocol::EventPayload::GovernanceProposalVoted { proposal_id, .. }
+        | blvm_node::module::ipc::protocol::EventPayload::GovernanceProposalMerged { proposal_id, .. } = &event.payload
+        {
+            self.proposal_cache.invalidate(proposal_id);
+        }
+        if let blvm_node::module::ipc::protocol::EventPayload::NewBlock { block_hash, height } = &event.payload {
+            self.tip.observe(*block_hash, *height);
+            if self.stream.observe_block(*height).is_some() && self.stream.reconcile_on_gap() {
+                let registry = Arc::clone(&self.economic_nodes);
+                tokio::spawn(async move {
+                    match registry.reconcile_now().await {
+                        Ok(_) => tracing::info!("Reconciled economic node registry after missed events"),
+                        Err(e) => tracing::warn!("Reconciliation after missed events failed: {}", e.chain()),
+                    }
+                });
+            }
+        }
+        // Rolled back before the pipeline processes anything more; see crate::pipeline
+        if let Some(reorg) = self.tip.take_reorg() {
+            self.pipeline.reorged(reorg);
+        }
+        if !self.subscriptions.contains(&event.event_type) {
+            // Unsubscribed at runtime; the node keeps sending until the next connection
+            return;
+        }
+        if !self.pipeline.accepts(&event.event_type) {
+            // No handler needs it; counted in the pipeline's filtered events
+            return;
+        }
+        let Some(work) = self.shutdown.accept() else {
+            tracing::debug!("Shutting down, ignoring {:?} event", event.event_type);
+            return;
+        };
+        // Processed by the event worker; see crate::event_queue
+        self.events.push(event.clone(), work).await;
+    }
+
+    /// Process one event from the node through the pipeline: webhook, economic nodes,
+    /// proposal store. Errors are logged so one failing handler does not stop the others.
+    pub async fn process_event(
+        &self,
+        event: &EventMessage,
+        node_api: &dyn blvm_node::module::traits::NodeAPI,
+    ) {
+        self.pipeline.process(event, node_api).await;
+    }
+
+    /// Process queued events until the queue is dropped, up to `parallelism` at a time.
+    /// Events with the same [`ordering_key`] are processed in order; each is checkpointed
+    /// once every handler has processed it.
+    pub fn spawn_event_worker(
+        &self,
+        mut queue: tokio::sync::mpsc::Receiver<QueuedEvent>,
+        node_api: Arc<dyn blvm_node::module::traits::NodeAPI>,
+        parallelism: usize,
+    ) -> Vec<tokio::task::JoinHandle<()>> {
+        let module = self.clone();
+        let (lanes, mut handles) = Lanes::spawn(parallelism, move |queued: QueuedEvent| {
+            let module = module.clone();
+            let node_api = Arc::clone(&node_api);
+            async move {
+                module.process_event(&queued.event, node_api.as_ref()).await;
+                drop(queued.work);
+            }
+        });
+        handles.push(tokio::spawn(async move {
+            while let Some(queued) = queue.recv().await {
+                let key = ordering_key(&queued.event);
+                lanes.dispatch(&key, queued).await;
+            }
+        }));
+        handles
+    }
+
+    /// Final steps of a graceful shutdown, once accepted work has drained: send pending veto
+    /// results, deregister the module API and, last, tell the node why the module is going
+    /// away.
+    pub async fn shutdown(
+        &self,
+        node_api: &dyn blvm_node::module::traits::NodeAPI,
+        reason: crate::shutdown::ShutdownReason,
+    ) {
+        match self.economic_nodes.flush_veto_reports().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Sent {} pending veto results before shutdown", n),
+            Err(e) => tracing::warn!("Pending veto results not sent before shutdown: {}", e.chain()),
+        }
+        if let Err(e) = node_api.unregister_module_api().await {
+            tracing::warn!("Failed to deregister governance module API: {}", e);
+        }
+        if let Err(e) = crate::shutdown::send_goodbye(node_api, &reason).await {
+            tracing::warn!("Failed to send final status to the node: {}", e.chain());
+        }
+    }
 }
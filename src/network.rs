@@ -0,0 +1,176 @@
+//! Network the module's data belongs to
+//!
+//! Mainnet and testnet nodes run side by side on one host, and nothing in a data directory or
+//! a webhook says which network its governance data came from: a module instance of one
+//! network pointed at the other's would mix them silently. So on each connection the module
+//! asks the node which network it is on ([`NodeApiIpc::get_network`]) and [`check`]s it
+//! against the network the data directory is stamped with, before anything is stored. A
+//! directory without a stamp is stamped with the node's network. One stamped with another
+//! network is refused and the module stops, unless it was started with
+//! `--force-network-migration`, which stamps it with the node's network instead; the status
+//! says which network it was migrated from until the next connection. A node that cannot say
+//! leaves the stamp unchecked, with a warning.
+//!
+//! The stamp is kept in the `NETWORK` file of the data directory (see
+//! [`crate::storage::layout`]), readable without opening the store, and in the module store,
+//! which every store of the module shares, so that it is copied into backups (see
+//! [`crate::backup`]). A file and store that name different networks are refused as an
+//! inconsistent directory.
+//!
+//! Webhook payloads and module alerts carry the node's network as `network`. A webhook with
+//! `webhook_network` set gets no deliveries while the node is on another network; each one
+//! blocked is reported as `webhook_network_mismatch` (see [`crate::webhook`] and
+//! [`crate::error_report`]). The status has the network in its `network` section and as the
+//! `network` info entry (see [`crate::status`]).
+//!
+//! [`NodeApiIpc::get_network`]: crate::node_api::NodeApiIpc::get_network
+
+use crate::error::GovernanceError;
+use crate::storage::DataDir;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const NETWORK_TREE: &str = "network";
+const NETWORK_KEY: &[u8] = b"network";
+
+/// The network of the module's data on a connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStatus {
+    /// The node's network; `None` if it could not say, in which case nothing was checked.
+    pub network: Option<String>,
+    /// The network the data directory was stamped with, if `--force-network-migration`
+    /// stamped it with the node's.
+    pub migrated_from: Option<String>,
+}
+
+/// The network the module store `db` is stamped with, if any.
+pub fn stored_in(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+) -> Result<Option<String>, GovernanceError> {
+    let tree = db
+        .open_tree(NETWORK_TREE)
+        .map_err(GovernanceError::database("open_tree"))?;
+    match tree.get(NETWORK_KEY) {
+        Ok(Some(data)) => bincode::deserialize(&data)
+            .map(Some)
+            .map_err(GovernanceError::encoding("deserialize")),
+        Ok(None) => Ok(None),
+        Err(e) => Err(GovernanceError::database("get")(e)),
+    }
+}
+
+/// Stamp the module store `db` with `network`, e.g. a backup's with the network of the data
+/// it copies.
+pub fn stamp_store(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    network: &str,
+) -> Result<(), GovernanceError> {
+    let tree = db
+        .open_tree(NETWORK_TREE)
+        .map_err(GovernanceError::database("open_tree"))?;
+    let data = bincode::serialize(network).map_err(GovernanceError::encoding("serialize"))?;
+    tree.insert(NETWORK_KEY, &data)
+        .map_err(GovernanceError::database("insert"))?;
+    Ok(())
+}
+
+/// The network in the `NETWORK` file of `layout`, if there is one.
+fn read_file(layout: &DataDir) -> Result<Option<String>, GovernanceError> {
+    let path = layout.network();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(GovernanceError::io(path.display())(e)),
+    }
+}
+
+/// Write `network` to the `NETWORK` file of `layout` via a temporary file and rename.
+fn write_file(layout: &DataDir, network: &str) -> Result<(), GovernanceError> {
+    let path = layout.network();
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, format!("{}\n", network)).map_err(GovernanceError::io(tmp.display()))?;
+    std::fs::rename(&tmp, &path).map_err(GovernanceError::io(path.display()))
+}
+
+/// The stamps of the `NETWORK` file and of the module store, failing if they differ.
+fn stamps(
+    layout: &DataDir,
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+) -> Result<(Option<String>, Option<String>), GovernanceError> {
+    let (file, store) = (read_file(layout)?, stored_in(db)?);
+    if let (Some(file), Some(store)) = (&file, &store) {
+        if file != store {
+            return Err(GovernanceError::Storage(format!(
+                "data directory {} is inconsistent: {} names network {}, but the module store \
+                 {}; restore it from a backup, or fix it by hand",
+                layout.root().display(),
+                layout.network().display(),
+                file,
+                store
+            )));
+        }
+    }
+    Ok((file, store))
+}
+
+/// The network the data directory `layout`, with the module store `db`, is stamped with, if
+/// any.
+pub fn stored(
+    layout: &DataDir,
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+) -> Result<Option<String>, GovernanceError> {
+    let (file, store) = stamps(layout, db)?;
+    Ok(file.or(store))
+}
+
+/// Check the data directory `layout`, with the module store `db`, against `network`, the
+/// node's, and stamp it with it where it is not. Fails with
+/// [`GovernanceError::NetworkMismatch`] if it is stamped with another network, unless
+/// `force_migration`.
+pub fn check(
+    layout: &DataDir,
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    network: &str,
+    force_migration: bool,
+) -> Result<NetworkStatus, GovernanceError> {
+    let (file, store) = stamps(layout, db)?;
+    let migrated_from = match file.clone().or(store.clone()) {
+        Some(stamped) if stamped == network => None,
+        Some(stamped) if force_migration => {
+            warn!(
+                "Migrating data directory {} from network {} to the node's network {} \
+                 (--force-network-migration)",
+                layout.root().display(),
+                stamped,
+                network
+            );
+            Some(stamped)
+        }
+        Some(stamped) => {
+            return Err(GovernanceError::NetworkMismatch {
+                what: format!("data directory {}", layout.root().display()),
+                expected: stamped,
+                actual: network.to_string(),
+            })
+        }
+        None => {
+            info!(
+                "Stamping data directory {} with network {}",
+                layout.root().display(),
+                network
+            );
+            None
+        }
+    };
+    if store.as_deref() != Some(network) {
+        stamp_store(db, network)?;
+    }
+    if file.as_deref() != Some(network) {
+        write_file(layout, network)?;
+    }
+    Ok(NetworkStatus {
+        network: Some(network.to_string()),
+        migrated_from,
+    })
+}
@@ -0,0 +1,2288 @@
+//! Governance-side access to the node over IPC
+//!
+//! Wraps the node's `NodeAPI` with typed errors so handlers don't deal with `ModuleError`
+//! strings directly. Request/response correlation is done by the IPC client underneath, so
+//! requests are not serialized here: any number may be in flight at once, up to a shared
+//! limit beyond which callers wait for a permit. Every call is bounded by a timeout, so a
+//! dropped response fails the caller with [`GovernanceError::NodeApiTimeout`] instead of
+//! hanging it. Timeouts are per method ([`MethodTimeouts`]): a chain info query should answer
+//! in milliseconds, while a block may take seconds. A timed-out or cancelled call is dropped,
+//! which discards its pending response and releases its permit.
+//!
+//! Requests that fail with an error classified [`Retryability::Retryable`] are retried with
+//! exponential backoff, up to `attempts` per [`RetryPolicy`]. Retries share the request's
+//! timeout rather than each getting their own; when the last attempt fails the caller gets
+//! [`GovernanceError::RetriesExhausted`] and can set the work aside for later.
+//!
+//! The in-flight limit is a [`RequestLimiter`] with two priority classes: bulk requests
+//! (backfill, reconciliation, polling) cannot use the last `reserved_interactive` permits, so
+//! they can never starve the requests a governance event needs. Time spent waiting for a
+//! permit is recorded per class in [`IpcMetrics`], separately from request latency.
+//!
+//! Payloads sent through `call_module` are checked against a size limit before they are
+//! sent, failing with [`GovernanceError::MessageTooLarge`] rather than getting the
+//! connection dropped by the node. Frame length limits on the read side belong to the IPC
+//! codec in blvm-sdk.
+//!
+//! Runs of blocks are fetched with [`NodeApiIpc::get_blocks`] and
+//! [`NodeApiIpc::get_blocks_by_height`]. The node has no batched block request, so these
+//! pipeline single requests, up to `batch_concurrency` at a time, and return each block's
+//! result in request order. Longer runs are read with [`NodeApiIpc::stream_blocks`], which
+//! keeps the same number of requests ahead of the caller and yields blocks in height order
+//! as they are consumed, so only the lookahead is held in memory. A failed block is yielded
+//! as an error and the stream carries on, unless the client was built with
+//! [`NodeApiIpc::with_stream_stop_on_error`]. Dropping the stream drops the requests still
+//! in flight.
+//!
+//! Block headers are cached, since walking the chain back from the tip asks for the same
+//! ones repeatedly. A header's height is not part of the node's response; it is known when
+//! the header is the chain tip or the parent of a header whose height is known. Headers and
+//! transactions are counted against the memory budget of a client built with
+//! [`NodeApiIpc::with_memory_budget`], which evicts the oldest to stay within it (see
+//! [`crate::memory`]).
+//!
+//! The node's best block is tracked in a [`TipTracker`] shared by the clients of a
+//! connection, updated from [`NodeApiIpc::get_best_block`] and from `NewBlock` events, so
+//! [`NodeApiIpc::current_tip`] answers without a round trip. The tracker also keeps the
+//! hashes of blocks by height seen on the current chain, for
+//! [`NodeApiIpc::get_block_hash_at`]; they are all dropped when it observes a reorg, since a
+//! height's block can then change. The reorg itself is kept until taken with
+//! [`TipTracker::take_reorg`], for the pipeline to roll back (see [`crate::pipeline`]).
+//!
+//! Proposal records from [`NodeApiIpc::get_proposal`] are cached by id in a
+//! [`ProposalCache`] shared by the clients of a connection; an entry is dropped when a vote
+//! or merge event for its proposal arrives.
+//!
+//! Fee rates from [`NodeApiIpc::estimate_fee_rate`] and [`NodeApiIpc::get_min_relay_fee`]
+//! are kept for a short while in a [`FeeCache`], since a caller building a transaction may
+//! ask several times. A target the node cannot estimate for fails with
+//! [`GovernanceError::FeeEstimateUnavailable`]; that answer is not cached.
+//!
+//! Chain work and difficulty ([`NodeApiIpc::get_block_chainwork`],
+//! [`NodeApiIpc::get_difficulty_at`], [`NodeApiIpc::work_in_window`]) are computed from
+//! header `bits` with [`crate::chain_work`] where the node does not provide them, as
+//! 256-bit [`Work`] values.
+//!
+//! Governance actions such as vetoes are only submitted when allowed
+//! (`governance.allow_actions`), are never retried, and are recorded with the node's answer
+//! in the [`ActionAudit`] trail. With `--dry-run=all` they and veto results are logged and
+//! not submitted. Transactions the node builds from its wallet and broadcasts
+//! ([`NodeApiIpc::fund_op_return`], [`NodeApiIpc::broadcast_transaction`]) are actions too:
+//! only allowed with actions, never retried, and broadcasts are recorded in the trail. A
+//! wallet that cannot pay fails with [`GovernanceError::InsufficientFunds`]. While the
+//! module is paused (see [`crate::pause`]) actions fail with [`GovernanceError::Paused`]
+//! and veto results are not reported.
+//!
+//! Transactions are looked up by txid through the node's transaction index, failing with
+//! [`GovernanceError::NotIndexed`] if the node has none. If no module serves the lookup,
+//! the node's own transaction store is asked instead, and the block is found among the last
+//! [`TX_LOCATE_DEPTH`] blocks. Lookups of transactions buried deep
+//! enough not to be reorged out are cached; their confirmations are recomputed from the
+//! current height. Mempool queries fail with [`GovernanceError::MempoolDisabled`] if the
+//! node does not serve them.
+
+use crate::audit::{ActionAudit, ActionAuditEntry};
+use crate::chain_work::{self, Work};
+use crate::economic_nodes::tally::VetoTally;
+use crate::error::{BoxError, GovernanceError, Retryability};
+use crate::ipc_metrics::{IpcMethod, IpcMetrics, Outcome};
+use crate::memory::{Charge, Component, MemoryBudget, Share, HEADER_ENTRY_BYTES};
+use crate::pause::PauseControl;
+use crate::trace;
+use blvm_node::module::traits::NodeAPI;
+use blvm_protocol::{Block, BlockHeader, Hash, OutPoint, Transaction, UTXO};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+/// Default per-request timeout.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of a `method` request unless configured otherwise.
+pub fn default_timeout(method: IpcMethod) -> Duration {
+    match method {
+        IpcMethod::GetBlockHeight
+        | IpcMethod::GetChainInfo
+        | IpcMethod::GetBlockHeader
+        | IpcMethod::GetMempoolEntry
+        | IpcMethod::GetUtxo
+        | IpcMethod::EstimateFeeRate
+        | IpcMethod::GetMinRelayFee
+        | IpcMethod::GetBlockChainwork
+        | IpcMethod::GetNetwork => Duration::from_secs(5),
+        IpcMethod::GetTransaction
+        | IpcMethod::GetMempoolTxids
+        | IpcMethod::GetMempoolTransaction
+        | IpcMethod::GetAddressBalance
+        | IpcMethod::GetProposal => Duration::from_secs(10),
+        IpcMethod::GetBlock
+        | IpcMethod::GetBlockByHeight
+        | IpcMethod::ListEconomicNodes
+        | IpcMethod::SubmitVetoResult
+        | IpcMethod::SubmitGovernanceAction
+        | IpcMethod::FundOpReturn
+        | IpcMethod::BroadcastTransaction => DEFAULT_REQUEST_TIMEOUT,
+    }
+}
+
+/// Default attempts per request, the first included.
+pub const DEFAULT_REQUEST_ATTEMPTS: u32 = 3;
+
+/// Default wait before the first retry of a failed request.
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Default limit on requests in flight at once.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 64;
+
+/// Default permits out of the in-flight limit that only interactive requests may use.
+pub const DEFAULT_RESERVED_INTERACTIVE: usize = 8;
+
+/// Default limit on payloads sent to the node.
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Default limit on requests in flight for one [`NodeApiIpc::get_blocks`] call.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Block headers (and heights) kept by [`NodeApiIpc::get_block_header`].
+pub const HEADER_CACHE_SIZE: usize = 2016;
+
+/// Block hashes by height kept by the [`TipTracker`].
+pub const BLOCK_HASH_CACHE_SIZE: usize = 2016;
+
+/// Deepest block below the tip whose chain work [`NodeApiIpc::get_block_chainwork`] computes
+/// itself when the node does not answer chain work requests.
+pub const MAX_CHAINWORK_DEPTH: u64 = HEADER_CACHE_SIZE as u64;
+
+/// Proposals kept by [`NodeApiIpc::get_proposal`].
+pub const PROPOSAL_CACHE_SIZE: usize = 256;
+
+/// Default time fee rates are kept by [`NodeApiIpc::estimate_fee_rate`] and
+/// [`NodeApiIpc::get_min_relay_fee`].
+pub const DEFAULT_FEE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Transactions kept by [`NodeApiIpc::get_transaction`].
+pub const TX_CACHE_SIZE: usize = 1024;
+
+/// Confirmations a transaction needs before its lookup is cached.
+pub const TX_CACHE_MIN_CONFIRMATIONS: u64 = 6;
+
+/// Deepest block below the tip searched for a transaction's block by
+/// [`NodeApiIpc::get_transaction`] when the node's lookup does not give it.
+pub const TX_LOCATE_DEPTH: u64 = 144;
+
+/// Economic node as known to the node (authoritative view used for reconciliation).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeEconomicNode {
+    pub node_id: String,
+    #[serde(default)]
+    pub node_type: String,
+    #[serde(default)]
+    pub hashpower_percent: Option<f64>,
+}
+
+/// A governance proposal's record as kept by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalDetails {
+    pub proposal_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Hash of the proposal content, for proposals whose content is kept elsewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Txid of the transaction whose `OP_RETURN` commits to the content hash (see
+    /// [`crate::content`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment_txid: Option<String>,
+    /// Where the full text is published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_url: Option<String>,
+    pub tier: String,
+    pub author: String,
+    /// As the node reports it, e.g. "open" or "merged".
+    pub status: String,
+    pub created_height: u64,
+    /// Parameter or rule the proposal changes; open proposals changing the same one conflict
+    /// (see [`crate::conflicts`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_domain: Option<String>,
+    /// Ids of proposals this one is mutually exclusive with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+}
+
+/// A governance action submitted to the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GovernanceAction {
+    /// Veto a proposal on behalf of an economic node.
+    Veto {
+        proposal_id: String,
+        /// Hex node id of the vetoing economic node.
+        node_identity: String,
+        /// Hex signature by the node's key over the veto; for a key set, the signatures in
+        /// key order, comma-separated.
+        signature: String,
+        reason: String,
+    },
+    /// Vote on a proposal on behalf of an economic node.
+    Vote {
+        proposal_id: String,
+        /// Hex node id of the voting economic node.
+        node_identity: String,
+        /// "yes", "no" or "abstain".
+        vote: String,
+        /// As for [`GovernanceAction::Veto`], over the vote.
+        signature: String,
+    },
+    /// Any other action the node accepts, by name.
+    Other {
+        name: String,
+        params: serde_json::Value,
+    },
+}
+
+impl GovernanceAction {
+    pub fn name(&self) -> &str {
+        match self {
+            GovernanceAction::Veto { .. } => "veto",
+            GovernanceAction::Vote { .. } => "vote",
+            GovernanceAction::Other { name, .. } => name,
+        }
+    }
+}
+
+/// The node's answer to a submitted action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionOutcome {
+    pub accepted: bool,
+    /// Why the node rejected it, or any note on accepting it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Timeout of each request method, retries included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodTimeouts([Duration; IpcMethod::ALL.len()]);
+
+impl Default for MethodTimeouts {
+    fn default() -> Self {
+        Self(IpcMethod::ALL.map(default_timeout))
+    }
+}
+
+impl MethodTimeouts {
+    /// The same `timeout` for every method.
+    pub fn uniform(timeout: Duration) -> Self {
+        Self([timeout; IpcMethod::ALL.len()])
+    }
+
+    /// Each method's default, but no longer than `cap`.
+    pub fn capped(cap: Duration) -> Self {
+        Self(IpcMethod::ALL.map(|m| default_timeout(m).min(cap)))
+    }
+
+    pub fn get(&self, method: IpcMethod) -> Duration {
+        self.0[method as usize]
+    }
+
+    pub fn set(&mut self, method: IpcMethod, timeout: Duration) {
+        self.0[method as usize] = timeout;
+    }
+}
+
+/// How requests that fail with a retryable error are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per request, the first included.
+    pub attempts: u32,
+    /// Wait before the first retry; doubled before each further one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_REQUEST_ATTEMPTS,
+            backoff: DEFAULT_RETRY_BACKOFF,
+        }
+    }
+}
+
+/// A block header and its height, if known.
+#[derive(Debug, Clone)]
+pub struct HeaderInfo {
+    pub header: BlockHeader,
+    pub height: Option<u64>,
+}
+
+/// A confirmed transaction and the block it is in.
+#[derive(Debug, Clone)]
+pub struct TransactionInfo {
+    pub tx: Transaction,
+    pub block_hash: Hash,
+    pub height: u64,
+    pub confirmations: u64,
+}
+
+/// A transaction the node built and signed from its wallet, not yet broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundedTransaction {
+    pub txid: Hash,
+    /// The signed transaction, hex.
+    pub hex: String,
+    pub fee_sats: u64,
+    /// Virtual size, in vbytes.
+    pub vsize: u64,
+}
+
+/// An unconfirmed transaction in the node's mempool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolEntry {
+    pub fee_sats: u64,
+    /// Virtual size, in vbytes.
+    pub size: u64,
+    /// When the node accepted it, in seconds since the Unix epoch.
+    pub time: u64,
+}
+
+/// The node's best block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainTip {
+    pub hash: Hash,
+    pub height: u64,
+}
+
+/// A reorg observed by the [`TipTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reorg {
+    /// Highest block taken to be on both chains: the one below the new tip.
+    pub fork_height: u64,
+    /// Tip before the reorg, if one was known.
+    pub previous: Option<ChainTip>,
+    /// The block that replaced it.
+    pub tip: ChainTip,
+}
+
+impl Reorg {
+    /// This reorg and a `later` one not rolled back in between, as one: from the lower fork
+    /// to the later tip.
+    pub fn followed_by(self, later: Reorg) -> Reorg {
+        Reorg {
+            fork_height: self.fork_height.min(later.fork_height),
+            previous: self.previous,
+            tip: later.tip,
+        }
+    }
+}
+
+/// Last known chain tip, shared by the clients of a connection. Updated from the node's
+/// answers and from `NewBlock` events, whichever is latest; the height goes down on a reorg.
+pub struct TipTracker {
+    tip: Mutex<Option<ChainTip>>,
+    hashes: Mutex<BlockHashes>,
+    /// Observed and not yet taken.
+    reorg: Mutex<Option<Reorg>>,
+}
+
+/// Hashes of blocks on the current chain, by height.
+struct BlockHashes {
+    entries: BoundedMap<u64, Hash>,
+    /// Bumped by every reorg, so a lookup racing one does not cache a hash from the old chain.
+    generation: u64,
+}
+
+impl Default for TipTracker {
+    fn default() -> Self {
+        Self {
+            tip: Mutex::default(),
+            hashes: Mutex::new(BlockHashes {
+                entries: BoundedMap::new(BLOCK_HASH_CACHE_SIZE),
+                generation: 0,
+            }),
+            reorg: Mutex::default(),
+        }
+    }
+}
+
+impl TipTracker {
+    /// Last known tip, or `None` before the first update.
+    pub fn current(&self) -> Option<ChainTip> {
+        *self.tip.lock().unwrap()
+    }
+
+    /// Record `hash` at `height` as the tip. A lower tip, another block at the same height or
+    /// a block other than the one known at its height is a reorg, kept for
+    /// [`TipTracker::take_reorg`].
+    pub fn observe(&self, hash: Hash, height: u64) {
+        let mut tip = self.tip.lock().unwrap();
+        let mut hashes = self.hashes.lock().unwrap();
+        let replaced = hashes
+            .entries
+            .get(&height)
+            .is_some_and(|known| *known != hash);
+        let reorg = match *tip {
+            Some(previous) => {
+                height < previous.height
+                    || (height == previous.height && hash != previous.hash)
+                    || replaced
+            }
+            None => replaced,
+        };
+        if reorg {
+            if let Some(previous) = *tip {
+                tracing::info!(
+                    "Chain tip moved from {} at {} to {} at {} (reorg)",
+                    hex::encode(previous.hash),
+                    previous.height,
+                    hex::encode(hash),
+                    height
+                );
+            }
+            hashes.entries = BoundedMap::new(BLOCK_HASH_CACHE_SIZE);
+            hashes.generation += 1;
+        }
+        hashes.entries.insert(height, hash);
+        let previous = tip.replace(ChainTip { hash, height });
+        if reorg {
+            let reorg = Reorg {
+                fork_height: height.saturating_sub(1),
+                previous,
+                tip: ChainTip { hash, height },
+            };
+            let mut pending = self.reorg.lock().unwrap();
+            *pending = Some(match *pending {
+                Some(earlier) => earlier.followed_by(reorg),
+                None => reorg,
+            });
+        }
+    }
+
+    /// The reorg observed since the last call, if any. Several count as one, from the lowest
+    /// fork to the latest tip.
+    pub fn take_reorg(&self) -> Option<Reorg> {
+        self.reorg.lock().unwrap().take()
+    }
+
+    /// Hash of the block at `height`, if seen since the last reorg.
+    pub fn hash_at(&self, height: u64) -> Option<Hash> {
+        self.hashes.lock().unwrap().entries.get(&height).copied()
+    }
+
+    /// Reorgs observed so far.
+    pub fn reorgs(&self) -> u64 {
+        self.hashes.lock().unwrap().generation
+    }
+
+    /// Remember `hash` at `height` unless a reorg happened since `generation`.
+    fn record_hash(&self, generation: u64, height: u64, hash: Hash) {
+        let mut hashes = self.hashes.lock().unwrap();
+        if hashes.generation == generation {
+            hashes.entries.insert(height, hash);
+        }
+    }
+}
+
+/// A fee rate, in satoshis per 1000 virtual bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FeeRate(pub u64);
+
+impl FeeRate {
+    pub fn from_sat_per_vbyte(sat_per_vbyte: u64) -> Self {
+        Self(sat_per_vbyte.saturating_mul(1000))
+    }
+
+    pub fn sat_per_vbyte(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+
+    /// Fee for a transaction of `vsize` virtual bytes, rounded up.
+    pub fn fee(self, vsize: u64) -> u64 {
+        self.0.saturating_mul(vsize).div_ceil(1000)
+    }
+}
+
+/// Fee rates from the node, each kept for the cache's time to live, shared by the clients
+/// of a connection.
+pub struct FeeCache {
+    ttl: Duration,
+    /// Estimates by confirmation target, with when they were fetched.
+    estimates: Mutex<HashMap<u32, (Instant, FeeRate)>>,
+    min_relay: Mutex<Option<(Instant, FeeRate)>>,
+}
+
+impl Default for FeeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEE_CACHE_TTL)
+    }
+}
+
+impl FeeCache {
+    /// Keep each rate for `ttl`; zero disables the cache.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            estimates: Mutex::default(),
+            min_relay: Mutex::default(),
+        }
+    }
+
+    /// Forget every rate.
+    pub fn clear(&self) {
+        self.estimates.lock().unwrap().clear();
+        *self.min_relay.lock().unwrap() = None;
+    }
+
+    fn fresh(&self, entry: Option<(Instant, FeeRate)>) -> Option<FeeRate> {
+        entry
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+            .map(|(_, rate)| rate)
+    }
+
+    fn estimate(&self, target_blocks: u32) -> Option<FeeRate> {
+        let entry = self.estimates.lock().unwrap().get(&target_blocks).copied();
+        self.fresh(entry)
+    }
+
+    fn insert_estimate(&self, target_blocks: u32, rate: FeeRate) {
+        let mut estimates = self.estimates.lock().unwrap();
+        estimates.retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+        estimates.insert(target_blocks, (Instant::now(), rate));
+    }
+
+    fn min_relay(&self) -> Option<FeeRate> {
+        let entry = *self.min_relay.lock().unwrap();
+        self.fresh(entry)
+    }
+
+    fn insert_min_relay(&self, rate: FeeRate) {
+        *self.min_relay.lock().unwrap() = Some((Instant::now(), rate));
+    }
+}
+
+/// Proposal records by id, shared by the clients of a connection. A proposal's entry is
+/// dropped when a vote or merge for it arrives, so the next lookup sees the change.
+pub struct ProposalCache {
+    inner: Mutex<CachedProposals>,
+}
+
+struct CachedProposals {
+    entries: BoundedMap<String, ProposalDetails>,
+    /// Bumped by every invalidation, so a lookup racing one does not cache a stale record.
+    generation: u64,
+}
+
+impl Default for ProposalCache {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(CachedProposals {
+                entries: BoundedMap::new(PROPOSAL_CACHE_SIZE),
+                generation: 0,
+            }),
+        }
+    }
+}
+
+impl ProposalCache {
+    /// Forget `proposal_id`, e.g. after a vote on it.
+    pub fn invalidate(&self, proposal_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.remove(&proposal_id.to_string());
+        inner.generation += 1;
+    }
+
+    fn get(&self, proposal_id: &str) -> Option<ProposalDetails> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .get(&proposal_id.to_string())
+            .cloned()
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Cache `details` unless an invalidation happened since `generation`.
+    fn insert(&self, generation: u64, details: ProposalDetails) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation == generation {
+            inner.entries.insert(details.proposal_id.clone(), details);
+        }
+    }
+}
+
+/// Values by key, oldest evicted first once `capacity` are held, or once their sizes no
+/// longer fit in the memory share, if given one.
+struct BoundedMap<K, V> {
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+    capacity: usize,
+    memory: Option<Arc<Share>>,
+    /// Sizes of the entries counted against `memory`, by key.
+    charges: HashMap<K, Charge>,
+}
+
+impl<K: std::hash::Hash + Eq + Clone, V> BoundedMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+            memory: None,
+            charges: HashMap::new(),
+        }
+    }
+
+    /// Count entries inserted with a size against `share`.
+    fn with_memory(mut self, share: Arc<Share>) -> Self {
+        self.memory = Some(share);
+        self
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    fn make_room(&mut self, key: &K) {
+        if self.entries.contains_key(key) {
+            return;
+        }
+        if self.order.len() >= self.capacity {
+            self.evict_oldest();
+        }
+        self.order.push_back(key.clone());
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        let Some(oldest) = self.order.pop_front() else {
+            return false;
+        };
+        self.entries.remove(&oldest);
+        self.charges.remove(&oldest);
+        true
+    }
+
+    /// Count `bytes` against the memory share, evicting the oldest entries until they fit.
+    /// `None` if they do not fit even in an empty map.
+    fn charge(&mut self, share: &Arc<Share>, bytes: u64) -> Option<Charge> {
+        loop {
+            if let Some(charge) = share.try_charge(bytes) {
+                return Some(charge);
+            }
+            if !self.evict_oldest() {
+                return None;
+            }
+            share.overflowed(1);
+        }
+    }
+
+    /// Insert `value`, of `bytes`, unless it does not fit in the memory share at all.
+    fn insert_sized(&mut self, key: K, value: V, bytes: u64) {
+        if let Some(share) = self.memory.clone() {
+            self.remove(&key);
+            let Some(charge) = self.charge(&share, bytes) else {
+                return;
+            };
+            self.charges.insert(key.clone(), charge);
+        }
+        self.insert(key, value);
+    }
+
+    /// [`Self::entry`], counting a new entry as `bytes`.
+    fn entry_sized(&mut self, key: K, bytes: u64) -> &mut V
+    where
+        V: Default,
+    {
+        if let Some(share) = self.memory.clone() {
+            if !self.entries.contains_key(&key) {
+                let charge = self
+                    .charge(&share, bytes)
+                    .unwrap_or_else(|| share.charge(bytes));
+                self.charges.insert(key.clone(), charge);
+            }
+        }
+        self.entry(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.make_room(&key);
+        self.entries.insert(key, value);
+    }
+
+    fn entry(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.make_room(&key);
+        self.entries.entry(key).or_default()
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.order.retain(|k| k != key);
+        self.charges.remove(key);
+        self.entries.remove(key)
+    }
+}
+
+#[derive(Default)]
+struct CachedHeader {
+    header: Option<BlockHeader>,
+    height: Option<u64>,
+}
+
+/// Recently seen headers and heights by block hash.
+struct HeaderCache {
+    entries: BoundedMap<Hash, CachedHeader>,
+}
+
+impl Default for HeaderCache {
+    fn default() -> Self {
+        Self {
+            entries: BoundedMap::new(HEADER_CACHE_SIZE),
+        }
+    }
+}
+
+impl HeaderCache {
+    fn with_memory(share: Arc<Share>) -> Self {
+        Self {
+            entries: BoundedMap::new(HEADER_CACHE_SIZE).with_memory(share),
+        }
+    }
+
+    fn entry(&mut self, hash: Hash) -> &mut CachedHeader {
+        self.entries.entry_sized(hash, HEADER_ENTRY_BYTES)
+    }
+
+    fn get(&self, hash: &Hash) -> Option<HeaderInfo> {
+        let cached = self.entries.get(hash)?;
+        Some(HeaderInfo {
+            header: cached.header.clone()?,
+            height: cached.height,
+        })
+    }
+
+    fn set_height(&mut self, hash: Hash, height: u64) {
+        self.entry(hash).height = Some(height);
+        let parent = self
+            .entries
+            .get(&hash)
+            .and_then(|c| c.header.as_ref())
+            .map(|h| h.prev_block_hash);
+        if let (Some(parent), Some(parent_height)) = (parent, height.checked_sub(1)) {
+            self.entry(parent).height = Some(parent_height);
+        }
+    }
+
+    fn insert(&mut self, hash: Hash, header: BlockHeader) -> HeaderInfo {
+        let parent = header.prev_block_hash;
+        let entry = self.entry(hash);
+        entry.header = Some(header.clone());
+        let height = entry.height;
+        if let Some(parent_height) = height.and_then(|h| h.checked_sub(1)) {
+            self.entry(parent).height = Some(parent_height);
+        }
+        HeaderInfo { header, height }
+    }
+}
+
+/// Which share of the in-flight limit a request may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Handling of a governance event: may use every permit.
+    Interactive,
+    /// Backfill, reconciliation and other background work: may not use the reserved permits.
+    Bulk,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 2] = [Priority::Interactive, Priority::Bulk];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Bulk => "bulk",
+        }
+    }
+}
+
+/// Limit on requests in flight on a connection. Bulk requests hold a bulk permit as well as
+/// one of the shared ones, and there are `reserved` fewer bulk permits, so bulk work can
+/// never take the permits interactive requests rely on.
+#[derive(Debug)]
+pub struct RequestLimiter {
+    all: Semaphore,
+    bulk: Semaphore,
+}
+
+impl RequestLimiter {
+    /// Up to `max_in_flight` requests at once, of which `reserved` only for interactive
+    /// requests. At least one permit is always left for bulk requests.
+    pub fn new(max_in_flight: usize, reserved: usize) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        let reserved = reserved.min(max_in_flight - 1);
+        Self {
+            all: Semaphore::new(max_in_flight),
+            bulk: Semaphore::new(max_in_flight - reserved),
+        }
+    }
+
+    /// Wait for a permit for a request of `priority`.
+    pub async fn acquire(&self, priority: Priority) -> Option<RequestPermit<'_>> {
+        let bulk = match priority {
+            Priority::Interactive => None,
+            Priority::Bulk => Some(self.bulk.acquire().await.ok()?),
+        };
+        let all = self.all.acquire().await.ok()?;
+        Some(RequestPermit {
+            _bulk: bulk,
+            _all: all,
+        })
+    }
+
+    /// Permits free for a request of `priority`.
+    pub fn available(&self, priority: Priority) -> usize {
+        match priority {
+            Priority::Interactive => self.all.available_permits(),
+            Priority::Bulk => self
+                .all
+                .available_permits()
+                .min(self.bulk.available_permits()),
+        }
+    }
+}
+
+impl Default for RequestLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_IN_FLIGHT, DEFAULT_RESERVED_INTERACTIVE)
+    }
+}
+
+/// A request's place in the [`RequestLimiter`], given back when dropped.
+pub struct RequestPermit<'a> {
+    _bulk: Option<tokio::sync::SemaphorePermit<'a>>,
+    _all: tokio::sync::SemaphorePermit<'a>,
+}
+
+/// What a request is about, recorded on its span.
+#[derive(Clone, Copy)]
+enum Key<'a> {
+    None,
+    Hash(&'a Hash),
+    Number(u64),
+    Id(&'a str),
+    Script(&'a [u8]),
+    OutPoint(&'a OutPoint),
+}
+
+impl fmt::Display for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::None => Ok(()),
+            Key::Hash(hash) => f.write_str(&hex::encode(hash)),
+            Key::Number(n) => write!(f, "{}", n),
+            Key::Id(id) => f.write_str(id),
+            Key::Script(script) => f.write_str(&hex::encode(script)),
+            Key::OutPoint(outpoint) => {
+                write!(f, "{}:{}", hex::encode(outpoint.hash), outpoint.index)
+            }
+        }
+    }
+}
+
+/// NodeAPI client used by governance handlers
+#[derive(Clone)]
+pub struct NodeApiIpc {
+    inner: Arc<dyn NodeAPI>,
+    timeouts: MethodTimeouts,
+    metrics: Arc<IpcMetrics>,
+    max_message_bytes: usize,
+    in_flight: Arc<RequestLimiter>,
+    priority: Priority,
+    retry: RetryPolicy,
+    batch_concurrency: usize,
+    /// End [`NodeApiIpc::stream_blocks`] after the first error it yields.
+    stream_stop_on_error: bool,
+    headers: Arc<Mutex<HeaderCache>>,
+    transactions: Arc<Mutex<BoundedMap<Hash, TransactionInfo>>>,
+    /// Set once the node rejects `get_block_header`; headers then come from full blocks.
+    headers_unsupported: Arc<AtomicBool>,
+    /// Set once the node rejects `get_block_chainwork`; chain work is then computed here.
+    chainwork_unsupported: Arc<AtomicBool>,
+    tip: Arc<TipTracker>,
+    proposals: Arc<ProposalCache>,
+    fees: Arc<FeeCache>,
+    allow_actions: bool,
+    /// Where submitted actions are recorded.
+    audit: Arc<ActionAudit>,
+    /// Log actions and veto results instead of submitting them.
+    dry_run: bool,
+    /// Refuses actions and veto results while paused.
+    pause: Option<Arc<PauseControl>>,
+}
+
+/// Await a node request, failing with [`GovernanceError::Timeout`] after `timeout`.
+pub async fn with_timeout<T, E: Into<BoxError>>(
+    operation: &str,
+    timeout: Duration,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, GovernanceError> {
+    match tokio::time::timeout(timeout, request).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(GovernanceError::from_ipc(operation, e)),
+        Err(_) => Err(GovernanceError::Timeout {
+            operation: operation.to_string(),
+            after: timeout,
+        }),
+    }
+}
+
+/// [`with_timeout`], once one of `permits` is available. The wait for a permit does not
+/// count towards `timeout`.
+pub async fn with_permit<T, E: Into<BoxError>>(
+    operation: &str,
+    timeout: Duration,
+    permits: &Semaphore,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, GovernanceError> {
+    let _permit = permits
+        .acquire()
+        .await
+        .map_err(|_| GovernanceError::IpcDisconnected {
+            operation: operation.to_string(),
+            reason: "client closed".to_string(),
+            source: None,
+        })?;
+    with_timeout(operation, timeout, request).await
+}
+
+/// Send a request with `send`, retrying failures classified [`Retryability::Retryable`] as
+/// `policy` allows. All attempts and the waits between them share `timeout`. Fails with
+/// [`GovernanceError::RetriesExhausted`] if a retried request still failed.
+pub async fn with_retry<T, E: Into<BoxError>, F: Future<Output = Result<T, E>>>(
+    operation: &str,
+    timeout: Duration,
+    policy: RetryPolicy,
+    mut send: impl FnMut() -> F,
+) -> Result<T, GovernanceError> {
+    let deadline = Instant::now() + timeout;
+    let mut backoff = policy.backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let error = match with_timeout(operation, remaining, send()).await {
+            Ok(value) => return Ok(value),
+            Err(GovernanceError::Timeout { .. }) => {
+                return Err(GovernanceError::Timeout {
+                    operation: operation.to_string(),
+                    after: timeout,
+                })
+            }
+            Err(e) => e,
+        };
+        if error.retryability() != Retryability::Retryable {
+            return Err(error);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if attempts >= policy.attempts || remaining <= backoff {
+            if attempts == 1 {
+                return Err(error);
+            }
+            return Err(GovernanceError::RetriesExhausted {
+                operation: operation.to_string(),
+                attempts,
+                last: Box::new(error),
+            });
+        }
+        tracing::debug!(
+            "{} failed (attempt {}), retrying in {:?}: {}",
+            operation,
+            attempts,
+            backoff,
+            error.chain()
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = backoff.saturating_mul(2);
+    }
+}
+
+/// Fail with [`GovernanceError::MessageTooLarge`] if a payload of `size` bytes exceeds
+/// `limit`.
+pub fn check_message_size(
+    operation: &str,
+    size: usize,
+    limit: usize,
+) -> Result<(), GovernanceError> {
+    if size > limit {
+        return Err(GovernanceError::MessageTooLarge {
+            operation: operation.to_string(),
+            size,
+            limit,
+        });
+    }
+    Ok(())
+}
+
+/// Whether the node rejected a request as a method it does not implement.
+fn is_unsupported(error: &GovernanceError) -> bool {
+    matches!(error, GovernanceError::Unsupported { .. })
+        || node_message(error)
+            .is_some_and(|m| m.contains("unknown method") || m.contains("not supported"))
+}
+
+/// Whether the node answered that what was asked for does not exist.
+fn is_not_found(error: &GovernanceError) -> bool {
+    matches!(error, GovernanceError::NotFound { .. })
+}
+
+/// Whether the node answered that it has no fee estimate for the target asked for.
+fn is_estimate_unavailable(error: &GovernanceError) -> bool {
+    node_message(error).is_some_and(|m| {
+        m.contains("insufficient data")
+            || m.contains("estimate unavailable")
+            || m.contains("estimation unavailable")
+    })
+}
+
+/// Whether the node rejected a mempool query because it has mempool queries disabled.
+fn is_mempool_disabled(error: &GovernanceError) -> bool {
+    is_unsupported(error)
+        || node_message(error).is_some_and(|m| {
+            m.contains("mempool") && (m.contains("disabled") || m.contains("not enabled"))
+        })
+}
+
+/// Whether the node refused to fund a transaction for lack of spendable coins.
+fn is_insufficient_funds(error: &GovernanceError) -> bool {
+    node_message(error).is_some_and(|m| m.contains("insufficient funds"))
+}
+
+/// Whether the node rejected a transaction lookup for lack of a transaction index.
+fn is_not_indexed(error: &GovernanceError) -> bool {
+    is_unsupported(error)
+        || node_message(error).is_some_and(|m| m.contains("not indexed") || m.contains("txindex"))
+}
+
+/// The node's own message, lowercased, if `error` is an answer from the node.
+fn node_message(error: &GovernanceError) -> Option<String> {
+    match error {
+        GovernanceError::NodeRejected { message, .. }
+        | GovernanceError::NotFound { what: message, .. } => Some(message.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// [`GovernanceError::MempoolDisabled`] if `error` says the node does not serve mempool
+/// queries, otherwise `error`.
+fn mempool_error(method: IpcMethod, error: GovernanceError) -> GovernanceError {
+    if is_mempool_disabled(&error) {
+        GovernanceError::MempoolDisabled {
+            operation: method.as_str().to_string(),
+        }
+    } else {
+        error
+    }
+}
+
+impl NodeApiIpc {
+    pub fn new(inner: Arc<dyn NodeAPI>) -> Self {
+        Self {
+            inner,
+            timeouts: MethodTimeouts::default(),
+            metrics: Arc::default(),
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            in_flight: Arc::default(),
+            priority: Priority::Interactive,
+            retry: RetryPolicy::default(),
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            stream_stop_on_error: false,
+            headers: Arc::default(),
+            transactions: Arc::new(Mutex::new(BoundedMap::new(TX_CACHE_SIZE))),
+            headers_unsupported: Arc::default(),
+            chainwork_unsupported: Arc::default(),
+            tip: Arc::default(),
+            proposals: Arc::default(),
+            fees: Arc::default(),
+            allow_actions: false,
+            audit: Arc::default(),
+            dry_run: false,
+            pause: None,
+        }
+    }
+
+    /// Allow governance actions to be submitted. Otherwise submissions fail with
+    /// [`GovernanceError::ActionsDisabled`].
+    pub fn with_actions_allowed(mut self, allow: bool) -> Self {
+        self.allow_actions = allow;
+        self
+    }
+
+    /// Log governance actions and veto results instead of submitting them (`--dry-run=all`).
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Refuse actions and veto results while `pause` is paused.
+    pub fn with_pause(mut self, pause: Arc<PauseControl>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Whether outbound effects are paused.
+    pub fn is_paused(&self) -> bool {
+        self.pause.as_ref().is_some_and(|pause| pause.is_paused())
+    }
+
+    /// Fail `action` unless actions are allowed and not paused.
+    fn check_action(&self, action: &str) -> Result<(), GovernanceError> {
+        if !self.allow_actions {
+            return Err(GovernanceError::ActionsDisabled {
+                action: action.to_string(),
+            });
+        }
+        if self.is_paused() {
+            return Err(GovernanceError::Paused {
+                action: action.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Record submitted actions in `audit`.
+    pub fn with_action_audit(mut self, audit: Arc<ActionAudit>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Count cached headers and transactions against `budget`, starting with empty caches.
+    pub fn with_memory_budget(mut self, budget: &MemoryBudget) -> Self {
+        self.headers = Arc::new(Mutex::new(HeaderCache::with_memory(Arc::clone(
+            budget.share(Component::Headers),
+        ))));
+        self.transactions = Arc::new(Mutex::new(
+            BoundedMap::new(TX_CACHE_SIZE)
+                .with_memory(Arc::clone(budget.share(Component::Transactions))),
+        ));
+        self
+    }
+
+    /// Share `cache` with other clients on the same connection.
+    pub fn with_proposal_cache(mut self, cache: Arc<ProposalCache>) -> Self {
+        self.proposals = cache;
+        self
+    }
+
+    /// Share `cache` with other clients on the same connection.
+    pub fn with_fee_cache(mut self, cache: Arc<FeeCache>) -> Self {
+        self.fees = cache;
+        self
+    }
+
+    /// Share `tip` with other clients on the same connection.
+    pub fn with_tip_tracker(mut self, tip: Arc<TipTracker>) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Share `permits` with other clients on the same connection; each request in flight
+    /// holds one.
+    pub fn with_in_flight(mut self, permits: Arc<RequestLimiter>) -> Self {
+        self.in_flight = permits;
+        self
+    }
+
+    /// Send requests as `priority`.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// This client, sending requests as [`Priority::Bulk`].
+    pub fn bulk(&self) -> Self {
+        self.clone().with_priority(Priority::Bulk)
+    }
+
+    /// Use `timeout` for requests of every method.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts = MethodTimeouts::uniform(timeout);
+        self
+    }
+
+    /// Use `timeouts` for requests, by method.
+    pub fn with_timeouts(mut self, timeouts: MethodTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Record requests in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<IpcMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Retry failed requests as `policy` allows.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Keep up to `concurrency` requests in flight when fetching runs of blocks.
+    pub fn with_batch_concurrency(mut self, concurrency: usize) -> Self {
+        self.batch_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// End block streams at the first block that cannot be fetched, after yielding its error.
+    pub fn with_stream_stop_on_error(mut self, stop: bool) -> Self {
+        self.stream_stop_on_error = stop;
+        self
+    }
+
+    /// Reject payloads larger than `limit` bytes before sending them.
+    pub fn with_max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = limit;
+        self
+    }
+
+    /// Send a request in a `node_api` span recording the method, `key`, the current trace id
+    /// and the time taken.
+    async fn request<T, E: Into<BoxError>, F: Future<Output = Result<T, E>>>(
+        &self,
+        method: IpcMethod,
+        key: Key<'_>,
+        send: impl FnMut() -> F,
+    ) -> Result<T, GovernanceError> {
+        let span = tracing::debug_span!(
+            "node_api",
+            method = method.as_str(),
+            key = tracing::field::Empty,
+            trace_id = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        );
+        // Only worth formatting for a subscriber that wants the span
+        if !span.is_disabled() {
+            if !matches!(key, Key::None) {
+                span.record("key", tracing::field::display(key));
+            }
+            if let Some(trace_id) = trace::current_id() {
+                span.record("trace_id", trace_id.as_str());
+            }
+        }
+        let start = Instant::now();
+        let result = self
+            .send_request(method, send)
+            .instrument(span.clone())
+            .await;
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        result
+    }
+
+    async fn send_request<T, E: Into<BoxError>, F: Future<Output = Result<T, E>>>(
+        &self,
+        method: IpcMethod,
+        send: impl FnMut() -> F,
+    ) -> Result<T, GovernanceError> {
+        let queued = Instant::now();
+        let Some(_permit) = self.in_flight.acquire(self.priority).await else {
+            return Err(GovernanceError::IpcDisconnected {
+                operation: method.as_str().to_string(),
+                reason: "client closed".to_string(),
+                source: None,
+            });
+        };
+        self.metrics.record_wait(self.priority, queued.elapsed());
+        let start = Instant::now();
+        let timeout = self.timeouts.get(method);
+        let result = with_retry(method.as_str(), timeout, self.retry, send)
+            .await
+            .map_err(|e| match e {
+                GovernanceError::Timeout { .. } => GovernanceError::NodeApiTimeout {
+                    method: method.as_str().to_string(),
+                    elapsed: start.elapsed(),
+                },
+                e => e,
+            });
+        let outcome = match &result {
+            Ok(_) => Outcome::Ok,
+            Err(GovernanceError::NodeApiTimeout { .. }) => Outcome::Timeout,
+            Err(_) => Outcome::Error,
+        };
+        self.metrics
+            .record_request(method, start.elapsed(), outcome);
+        result
+    }
+
+    /// Call a node method through `call_module`.
+    async fn call(
+        &self,
+        method: IpcMethod,
+        key: Key<'_>,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, GovernanceError> {
+        let written = payload.len();
+        check_message_size(method.as_str(), written, self.max_message_bytes)?;
+        let response = self
+            .request(method, key, || {
+                self.inner
+                    .call_module(None, method.as_str(), payload.clone())
+            })
+            .await?;
+        self.metrics.record_bytes(method, written, response.len());
+        Ok(response)
+    }
+
+    /// Underlying NodeAPI.
+    pub fn inner(&self) -> &Arc<dyn NodeAPI> {
+        &self.inner
+    }
+
+    /// Current block height.
+    pub async fn get_block_height(&self) -> Result<u64, GovernanceError> {
+        self.request(IpcMethod::GetBlockHeight, Key::None, || {
+            self.inner.get_block_height()
+        })
+        .await
+    }
+
+    /// Chain tip hash and height, read together.
+    pub async fn get_chain_info(
+        &self,
+    ) -> Result<blvm_node::module::traits::ChainInfo, GovernanceError> {
+        let info = self
+            .request(IpcMethod::GetChainInfo, Key::None, || {
+                self.inner.get_chain_info()
+            })
+            .await?;
+        self.headers
+            .lock()
+            .unwrap()
+            .set_height(info.tip_hash, info.height);
+        self.tip.observe(info.tip_hash, info.height);
+        Ok(info)
+    }
+
+    /// Ask the node for its best block, updating [`Self::current_tip`].
+    pub async fn get_best_block(&self) -> Result<ChainTip, GovernanceError> {
+        let info = self.get_chain_info().await?;
+        Ok(ChainTip {
+            hash: info.tip_hash,
+            height: info.height,
+        })
+    }
+
+    /// The network the node is on, such as "mainnet" or "testnet" (see [`crate::network`]).
+    pub async fn get_network(&self) -> Result<String, GovernanceError> {
+        #[derive(Deserialize)]
+        struct Response {
+            network: String,
+        }
+        let response = self
+            .call(IpcMethod::GetNetwork, Key::None, Vec::new())
+            .await?;
+        let response: Response = serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("get_network"))?;
+        Ok(response.network)
+    }
+
+    /// Last known tip, without a round trip: from the latest of [`Self::get_best_block`]
+    /// and the `NewBlock` events passed to the [`TipTracker`].
+    pub fn current_tip(&self) -> Option<ChainTip> {
+        self.tip.current()
+    }
+
+    /// The tracker behind [`Self::current_tip`].
+    pub fn tip_tracker(&self) -> &Arc<TipTracker> {
+        &self.tip
+    }
+
+    /// Fetch a full block by hash.
+    pub async fn get_block(&self, hash: &Hash) -> Result<Option<Block>, GovernanceError> {
+        let block = self
+            .request(IpcMethod::GetBlock, Key::Hash(hash), || {
+                self.inner.get_block(hash)
+            })
+            .await?;
+        if let Some(block) = &block {
+            self.headers
+                .lock()
+                .unwrap()
+                .insert(*hash, block.header.clone());
+        }
+        Ok(block)
+    }
+
+    /// Fetch blocks by hash. Results are in the order of `hashes`, each with its own error.
+    pub async fn get_blocks(&self, hashes: &[Hash]) -> Vec<Result<Option<Block>, GovernanceError>> {
+        futures::stream::iter(hashes)
+            .map(|hash| self.get_block(hash))
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Fetch the blocks at `heights` on the node's current chain, in height order, each
+    /// with its own error.
+    pub async fn get_blocks_by_height(
+        &self,
+        heights: Range<u64>,
+    ) -> Vec<Result<Option<Block>, GovernanceError>> {
+        futures::stream::iter(heights)
+            .map(|height| {
+                self.request(
+                    IpcMethod::GetBlockByHeight,
+                    Key::Number(height),
+                    move || self.inner.get_block_by_height(height),
+                )
+            })
+            .buffered(self.batch_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Stream the blocks at `heights` on the node's current chain, in height order. Up to
+    /// `batch_concurrency` blocks are fetched ahead of the one being consumed. A height the
+    /// node has no block for yields [`GovernanceError::BlockNotFound`].
+    pub fn stream_blocks(
+        &self,
+        heights: Range<u64>,
+    ) -> impl Stream<Item = Result<(u64, Arc<Block>), GovernanceError>> + '_ {
+        let stop_on_error = self.stream_stop_on_error;
+        let mut failed = false;
+        futures::stream::iter(heights)
+            .map(move |height| async move {
+                match self.fetch_block_at(height).await? {
+                    Some(block) => Ok((height, Arc::new(block))),
+                    None => Err(GovernanceError::BlockNotFound { height }),
+                }
+            })
+            .buffered(self.batch_concurrency)
+            .take_while(move |result| {
+                let more = !failed;
+                failed = stop_on_error && result.is_err();
+                futures::future::ready(more)
+            })
+    }
+
+    /// The block at `height` on the node's current chain. Fails with
+    /// [`GovernanceError::BeyondTip`] above the node's tip.
+    pub async fn get_block_by_height(&self, height: u64) -> Result<Block, GovernanceError> {
+        match self.fetch_block_at(height).await? {
+            Some(block) => Ok(block),
+            None => {
+                let tip = self.get_best_block().await?;
+                if height > tip.height {
+                    Err(GovernanceError::BeyondTip {
+                        height,
+                        tip: tip.height,
+                    })
+                } else {
+                    Err(GovernanceError::BlockNotFound { height })
+                }
+            }
+        }
+    }
+
+    /// Hash of the block at `height` on the node's current chain, cached until the
+    /// [`TipTracker`] observes a reorg. Fails with [`GovernanceError::BeyondTip`] above the
+    /// node's tip.
+    pub async fn get_block_hash_at(&self, height: u64) -> Result<Hash, GovernanceError> {
+        if let Some(hash) = self.tip.hash_at(height) {
+            return Ok(hash);
+        }
+        let tip = match self.tip.current() {
+            Some(tip) if tip.height >= height => tip,
+            _ => self.get_best_block().await?,
+        };
+        if height > tip.height {
+            return Err(GovernanceError::BeyondTip {
+                height,
+                tip: tip.height,
+            });
+        }
+        if height == tip.height {
+            return Ok(tip.hash);
+        }
+        // The node has no hash-by-height request: a block's hash is its child's parent hash,
+        // which fetching the child caches
+        match self.fetch_block_at(height + 1).await? {
+            Some(child) => Ok(child.header.prev_block_hash),
+            None => Err(GovernanceError::BlockNotFound { height: height + 1 }),
+        }
+    }
+
+    /// Fetch the block at `height`, caching its parent's hash.
+    async fn fetch_block_at(&self, height: u64) -> Result<Option<Block>, GovernanceError> {
+        let generation = self.tip.reorgs();
+        let block = self
+            .request(IpcMethod::GetBlockByHeight, Key::Number(height), || {
+                self.inner.get_block_by_height(height)
+            })
+            .await?;
+        if let (Some(block), Some(parent)) = (&block, height.checked_sub(1)) {
+            self.tip
+                .record_hash(generation, parent, block.header.prev_block_hash);
+        }
+        Ok(block)
+    }
+
+    /// Fetch a block header by hash, from the cache if it was seen recently. Falls back to
+    /// fetching the full block if the node does not support header requests.
+    pub async fn get_block_header(
+        &self,
+        hash: &Hash,
+    ) -> Result<Option<HeaderInfo>, GovernanceError> {
+        if let Some(cached) = self.headers.lock().unwrap().get(hash) {
+            return Ok(Some(cached));
+        }
+        let header = if self.headers_unsupported.load(Ordering::Relaxed) {
+            None
+        } else {
+            match self
+                .request(IpcMethod::GetBlockHeader, Key::Hash(hash), || {
+                    self.inner.get_block_header(hash)
+                })
+                .await
+            {
+                Ok(header) => Some(header),
+                Err(e) if is_unsupported(&e) => {
+                    tracing::info!(
+                        "Node does not serve block headers, using full blocks: {}",
+                        e
+                    );
+                    self.headers_unsupported.store(true, Ordering::Relaxed);
+                    None
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let header = match header {
+            Some(header) => header,
+            None => self.get_block(hash).await?.map(|block| block.header.clone()),
+        };
+        Ok(header.map(|header| self.headers.lock().unwrap().insert(*hash, header)))
+    }
+
+    /// Total work of the chain up to and including block `hash`. If the node does not answer
+    /// chain work requests, it is the tip's chain work less the work of the blocks above
+    /// `hash`, which must then be within [`MAX_CHAINWORK_DEPTH`] blocks of the tip.
+    pub async fn get_block_chainwork(&self, hash: &Hash) -> Result<Work, GovernanceError> {
+        #[derive(Deserialize)]
+        struct Response {
+            chainwork: Work,
+        }
+        if !self.chainwork_unsupported.load(Ordering::Relaxed) {
+            let payload = serde_json::to_vec(&serde_json::json!({ "hash": hex::encode(hash) }))
+                .map_err(GovernanceError::serialization("get_block_chainwork"))?;
+            match self
+                .call(IpcMethod::GetBlockChainwork, Key::Hash(hash), payload)
+                .await
+            {
+                Ok(response) => {
+                    let response: Response = serde_json::from_slice(&response)
+                        .map_err(GovernanceError::serialization("get_block_chainwork"))?;
+                    return Ok(response.chainwork);
+                }
+                Err(e) if is_unsupported(&e) => {
+                    tracing::info!("Node does not serve chain work, computing it: {}", e);
+                    self.chainwork_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let info = self.get_chain_info().await?;
+        let mut work = Work::from_u128(info.chain_work as u128);
+        let mut current = info.tip_hash;
+        for _ in 0..=MAX_CHAINWORK_DEPTH {
+            if current == *hash {
+                return Ok(work);
+            }
+            let Some(found) = self.get_block_header(&current).await? else {
+                break;
+            };
+            work = work
+                .checked_sub(chain_work::work_from_bits(found.header.bits as u32))
+                .ok_or_else(|| {
+                    GovernanceError::ModuleError(
+                        "get_block_chainwork: tip chain work below its blocks' work".to_string(),
+                    )
+                })?;
+            current = found.header.prev_block_hash;
+        }
+        Err(GovernanceError::NotFound {
+            operation: "get_block_chainwork".to_string(),
+            what: format!(
+                "block {} within {} blocks of the tip",
+                hex::encode(hash),
+                MAX_CHAINWORK_DEPTH
+            ),
+            source: None,
+        })
+    }
+
+    /// Difficulty of the block at `height` on the node's current chain.
+    pub async fn get_difficulty_at(&self, height: u64) -> Result<f64, GovernanceError> {
+        let block = self
+            .request(IpcMethod::GetBlockByHeight, Key::Number(height), || {
+                self.inner.get_block_by_height(height)
+            })
+            .await?
+            .ok_or(GovernanceError::BlockNotFound { height })?;
+        Ok(chain_work::difficulty_from_bits(block.header.bits as u32))
+    }
+
+    /// Work of the blocks from `from_height` to `to_height`, both included, on the node's
+    /// current chain; zero if the window is empty.
+    pub async fn work_in_window(
+        &self,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Work, GovernanceError> {
+        let mut work = Work::ZERO;
+        let mut blocks =
+            std::pin::pin!(self.stream_blocks(from_height..to_height.saturating_add(1)));
+        while let Some(block) = blocks.next().await {
+            let (_, block) = block?;
+            work = work.saturating_add(chain_work::work_from_bits(block.header.bits as u32));
+        }
+        Ok(work)
+    }
+
+    /// Look up a confirmed transaction by txid. `None` if the node does not know it or it is
+    /// unconfirmed; [`GovernanceError::NotIndexed`] if the node keeps no transaction index.
+    /// Without a module serving the lookup, see [`Self::get_stored_transaction`].
+    pub async fn get_transaction(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<TransactionInfo>, GovernanceError> {
+        #[derive(Deserialize)]
+        struct Response {
+            tx: Transaction,
+            block_hash: String,
+            height: u64,
+            confirmations: u64,
+        }
+        let cached = self.transactions.lock().unwrap().get(txid).cloned();
+        if let Some(mut info) = cached {
+            let height = self.get_block_height().await?;
+            info.confirmations = (height + 1).saturating_sub(info.height);
+            return Ok(Some(info));
+        }
+        let payload = serde_json::to_vec(&serde_json::json!({ "txid": hex::encode(txid) }))
+            .map_err(GovernanceError::serialization("get_transaction"))?;
+        let response = match self
+            .call(IpcMethod::GetTransaction, Key::Hash(txid), payload)
+            .await
+        {
+            Ok(response) => response,
+            Err(e @ GovernanceError::Unsupported { .. }) => {
+                tracing::debug!("Looking up {} in the node's store: {}", hex::encode(txid), e);
+                return self.get_stored_transaction(txid).await;
+            }
+            Err(e) if is_not_indexed(&e) => {
+                return Err(GovernanceError::NotIndexed {
+                    operation: "get_transaction".to_string(),
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        // Counted against the memory budget if cached
+        let size = response.len() as u64;
+        let Some(response) = serde_json::from_slice::<Option<Response>>(&response)
+            .map_err(GovernanceError::serialization("get_transaction"))?
+        else {
+            return Ok(None);
+        };
+        let block_hash: Hash = hex::decode(&response.block_hash)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                GovernanceError::ModuleError(format!(
+                    "get_transaction: invalid response: bad block hash {}",
+                    response.block_hash
+                ))
+            })?;
+        let info = TransactionInfo {
+            tx: response.tx,
+            block_hash,
+            height: response.height,
+            confirmations: response.confirmations,
+        };
+        if info.confirmations >= TX_CACHE_MIN_CONFIRMATIONS {
+            self.transactions
+                .lock()
+                .unwrap()
+                .insert_sized(*txid, info.clone(), size);
+        }
+        Ok(Some(info))
+    }
+
+    /// Look up a transaction with the node's own [`NodeAPI::get_transaction`], which does not
+    /// say which block it is in: that is searched for among the last [`TX_LOCATE_DEPTH`]
+    /// blocks. Fails with [`GovernanceError::NotIndexed`] if the node cannot look it up or it
+    /// is buried deeper.
+    async fn get_stored_transaction(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<TransactionInfo>, GovernanceError> {
+        let not_indexed = || GovernanceError::NotIndexed {
+            operation: "get_transaction".to_string(),
+        };
+        let tx = match self
+            .request(IpcMethod::GetTransaction, Key::Hash(txid), || {
+                self.inner.get_transaction(txid)
+            })
+            .await
+        {
+            Ok(Some(tx)) => tx,
+            Ok(None) => return Ok(None),
+            Err(e) if is_not_indexed(&e) => return Err(not_indexed()),
+            Err(e) => return Err(e),
+        };
+        let tip = self.get_block_height().await?;
+        let from = tip.saturating_sub(TX_LOCATE_DEPTH);
+        let mut blocks = std::pin::pin!(self.stream_blocks(from..tip.saturating_add(1)));
+        while let Some(block) = blocks.next().await {
+            let (height, block) = block?;
+            if block.transactions.contains(&tx) {
+                return Ok(Some(TransactionInfo {
+                    tx,
+                    block_hash: self.get_block_hash_at(height).await?,
+                    height,
+                    confirmations: (tip + 1).saturating_sub(height),
+                }));
+            }
+        }
+        Err(not_indexed())
+    }
+
+    /// A proposal's full record, from the cache unless it was voted on or merged since it
+    /// was fetched. Fails with [`GovernanceError::ProposalNotFound`] if the node has no such
+    /// proposal, and with [`GovernanceError::Unsupported`] if no module serves proposals.
+    pub async fn get_proposal(
+        &self,
+        proposal_id: &str,
+    ) -> Result<ProposalDetails, GovernanceError> {
+        if let Some(cached) = self.proposals.get(proposal_id) {
+            return Ok(cached);
+        }
+        let generation = self.proposals.generation();
+        let not_found = || GovernanceError::ProposalNotFound {
+            proposal_id: proposal_id.to_string(),
+        };
+        let payload = serde_json::to_vec(&serde_json::json!({ "proposal_id": proposal_id }))
+            .map_err(GovernanceError::serialization("get_proposal"))?;
+        let response = match self
+            .call(IpcMethod::GetProposal, Key::Id(proposal_id), payload)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) if is_not_found(&e) => return Err(not_found()),
+            Err(e) => return Err(e),
+        };
+        let details = serde_json::from_slice::<Option<ProposalDetails>>(&response)
+            .map_err(GovernanceError::serialization("get_proposal"))?
+            .ok_or_else(not_found)?;
+        self.proposals.insert(generation, details.clone());
+        Ok(details)
+    }
+
+    /// Fee rate for confirmation within `target_blocks` blocks, as estimated by the node.
+    /// Fails with [`GovernanceError::FeeEstimateUnavailable`] if the node has no estimate
+    /// for that target, e.g. for lack of data after startup.
+    pub async fn estimate_fee_rate(&self, target_blocks: u32) -> Result<FeeRate, GovernanceError> {
+        if let Some(rate) = self.fees.estimate(target_blocks) {
+            return Ok(rate);
+        }
+        let unavailable = || GovernanceError::FeeEstimateUnavailable { target_blocks };
+        let rate = match self
+            .request(
+                IpcMethod::EstimateFeeRate,
+                Key::Number(target_blocks as u64),
+                || self.inner.get_fee_estimate(target_blocks),
+            )
+            .await
+        {
+            Ok(0) => return Err(unavailable()),
+            Ok(sat_per_vbyte) => FeeRate::from_sat_per_vbyte(sat_per_vbyte),
+            Err(e) if is_estimate_unavailable(&e) => return Err(unavailable()),
+            Err(e) => return Err(e),
+        };
+        self.fees.insert_estimate(target_blocks, rate);
+        Ok(rate)
+    }
+
+    /// The lowest fee rate the node relays transactions at.
+    pub async fn get_min_relay_fee(&self) -> Result<FeeRate, GovernanceError> {
+        #[derive(Deserialize)]
+        struct Response {
+            sat_per_kvb: u64,
+        }
+        if let Some(rate) = self.fees.min_relay() {
+            return Ok(rate);
+        }
+        let response = self
+            .call(IpcMethod::GetMinRelayFee, Key::None, Vec::new())
+            .await?;
+        let response: Response = serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("get_min_relay_fee"))?;
+        let rate = FeeRate(response.sat_per_kvb);
+        self.fees.insert_min_relay(rate);
+        Ok(rate)
+    }
+
+    /// The cache behind [`Self::estimate_fee_rate`] and [`Self::get_min_relay_fee`].
+    pub fn fee_cache(&self) -> &Arc<FeeCache> {
+        &self.fees
+    }
+
+    /// The cache behind [`Self::get_proposal`].
+    pub fn proposal_cache(&self) -> &Arc<ProposalCache> {
+        &self.proposals
+    }
+
+    /// Veto `proposal_id` as the economic node `node_identity`; see
+    /// [`Self::submit_governance_action`].
+    pub async fn submit_veto(
+        &self,
+        proposal_id: &str,
+        node_identity: &str,
+        signature: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, GovernanceError> {
+        self.submit_governance_action(&GovernanceAction::Veto {
+            proposal_id: proposal_id.to_string(),
+            node_identity: node_identity.to_string(),
+            signature: signature.to_string(),
+            reason: reason.to_string(),
+        })
+        .await
+    }
+
+    /// Submit `action` and return whether the node accepted it. Fails with
+    /// [`GovernanceError::ActionsDisabled`] unless actions are allowed, with
+    /// [`GovernanceError::Paused`] while paused, and with [`GovernanceError::Unsupported`] if
+    /// no module takes governance actions. The request and the
+    /// node's response are recorded in the audit trail whatever the outcome. Not retried,
+    /// since the node may have applied an action it failed to answer.
+    pub async fn submit_governance_action(
+        &self,
+        action: &GovernanceAction,
+    ) -> Result<ActionOutcome, GovernanceError> {
+        self.check_action(action.name())?;
+        let request = serde_json::to_string(action)
+            .map_err(GovernanceError::serialization("submit_governance_action"))?;
+        if self.dry_run {
+            tracing::info!(
+                "Dry run, {} action not submitted: {}",
+                action.name(),
+                request
+            );
+            return Ok(ActionOutcome {
+                accepted: false,
+                reason: Some("dry run: not submitted".to_string()),
+            });
+        }
+        let once = self.clone().with_retry_policy(RetryPolicy {
+            attempts: 1,
+            ..self.retry
+        });
+        let (response, outcome) = match once
+            .call(
+                IpcMethod::SubmitGovernanceAction,
+                Key::Id(action.name()),
+                request.clone().into_bytes(),
+            )
+            .await
+        {
+            Ok(response) => {
+                let outcome = serde_json::from_slice::<ActionOutcome>(&response)
+                    .map_err(GovernanceError::serialization("submit_governance_action"));
+                (
+                    Some(String::from_utf8_lossy(&response).into_owned()),
+                    outcome,
+                )
+            }
+            Err(e) => (None, Err(e)),
+        };
+        let entry = ActionAuditEntry {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            action: action.name().to_string(),
+            request,
+            response,
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+        };
+        if let Err(e) = self.audit.record(entry) {
+            tracing::warn!(
+                "Failed to persist audit entry for {}: {}",
+                action.name(),
+                e.chain()
+            );
+        }
+        match &outcome {
+            Ok(o) if o.accepted => tracing::info!("Node accepted {} action", action.name()),
+            Ok(o) => tracing::warn!(
+                "Node rejected {} action: {}",
+                action.name(),
+                o.reason.as_deref().unwrap_or("no reason given")
+            ),
+            Err(e) => tracing::warn!("Submitting {} action failed: {}", action.name(), e.chain()),
+        }
+        outcome
+    }
+
+    /// Have the node build a transaction from its wallet with one `OP_RETURN` output pushing
+    /// `data`, paying `fee_rate`, and sign it without broadcasting it. Fails with
+    /// [`GovernanceError::ActionsDisabled`] unless actions are allowed, and with
+    /// [`GovernanceError::InsufficientFunds`] if the wallet cannot pay for it. Not retried,
+    /// since the node may have reserved the coins of a transaction it failed to return.
+    pub async fn fund_op_return(
+        &self,
+        data: &[u8],
+        fee_rate: FeeRate,
+    ) -> Result<FundedTransaction, GovernanceError> {
+        #[derive(Deserialize)]
+        struct Response {
+            txid: String,
+            hex: String,
+            fee_sats: u64,
+            vsize: u64,
+        }
+        let method = IpcMethod::FundOpReturn;
+        self.check_action(method.as_str())?;
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "data": hex::encode(data),
+            "sat_per_kvb": fee_rate.0,
+        }))
+        .map_err(GovernanceError::serialization("fund_op_return"))?;
+        let once = self.clone().with_retry_policy(RetryPolicy {
+            attempts: 1,
+            ..self.retry
+        });
+        let response = match once.call(method, Key::None, payload).await {
+            Ok(response) => response,
+            Err(e) if is_insufficient_funds(&e) => {
+                return Err(GovernanceError::InsufficientFunds {
+                    operation: method.as_str().to_string(),
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        let response: Response = serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("fund_op_return"))?;
+        let txid = hex::decode(&response.txid)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                GovernanceError::ModuleError(format!(
+                    "fund_op_return: invalid response: bad txid {}",
+                    response.txid
+                ))
+            })?;
+        Ok(FundedTransaction {
+            txid,
+            hex: response.hex,
+            fee_sats: response.fee_sats,
+            vsize: response.vsize,
+        })
+    }
+
+    /// Broadcast `tx` through the node. Fails with [`GovernanceError::ActionsDisabled`]
+    /// unless actions are allowed. Recorded in the audit trail whatever the outcome, like a
+    /// governance action, and not retried.
+    pub async fn broadcast_transaction(
+        &self,
+        tx: &FundedTransaction,
+    ) -> Result<(), GovernanceError> {
+        let method = IpcMethod::BroadcastTransaction;
+        self.check_action(method.as_str())?;
+        let request = serde_json::json!({ "hex": tx.hex }).to_string();
+        let once = self.clone().with_retry_policy(RetryPolicy {
+            attempts: 1,
+            ..self.retry
+        });
+        let result = once
+            .call(method, Key::Hash(&tx.txid), request.clone().into_bytes())
+            .await;
+        let entry = ActionAuditEntry {
+            timestamp: crate::clock::unix_now(),
+            action: method.as_str().to_string(),
+            request,
+            response: result
+                .as_ref()
+                .ok()
+                .map(|r| String::from_utf8_lossy(r).into_owned()),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        if let Err(e) = self.audit.record(entry) {
+            tracing::warn!(
+                "Failed to persist audit entry for {}: {}",
+                method.as_str(),
+                e.chain()
+            );
+        }
+        result.map(|_| ())
+    }
+
+    /// Txids of the transactions in the node's mempool.
+    pub async fn get_mempool_txids(&self) -> Result<Vec<Hash>, GovernanceError> {
+        self.request(IpcMethod::GetMempoolTxids, Key::None, || {
+            self.inner.get_mempool_transactions()
+        })
+        .await
+        .map_err(|e| mempool_error(IpcMethod::GetMempoolTxids, e))
+    }
+
+    /// A transaction in the node's mempool. `None` once it has left the mempool.
+    pub async fn get_mempool_transaction(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<Transaction>, GovernanceError> {
+        self.request(IpcMethod::GetMempoolTransaction, Key::Hash(txid), || {
+            self.inner.get_mempool_transaction(txid)
+        })
+        .await
+        .map_err(|e| mempool_error(IpcMethod::GetMempoolTransaction, e))
+    }
+
+    /// Fee, size and arrival time of a mempool transaction. `None` if it is not in the
+    /// mempool.
+    pub async fn get_mempool_entry(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<MempoolEntry>, GovernanceError> {
+        let payload = serde_json::to_vec(&serde_json::json!({ "txid": hex::encode(txid) }))
+            .map_err(GovernanceError::serialization("get_mempool_entry"))?;
+        let response = self
+            .call(IpcMethod::GetMempoolEntry, Key::Hash(txid), payload)
+            .await
+            .map_err(|e| mempool_error(IpcMethod::GetMempoolEntry, e))?;
+        serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("get_mempool_entry"))
+    }
+
+    /// Look up an unspent output. `None` if it does not exist or is spent.
+    pub async fn get_utxo(&self, outpoint: &OutPoint) -> Result<Option<UTXO>, GovernanceError> {
+        self.request(IpcMethod::GetUtxo, Key::OutPoint(outpoint), || {
+            self.inner.get_utxo(outpoint)
+        })
+        .await
+    }
+
+    /// Full economic node set as known to the node.
+    pub async fn list_economic_nodes(&self) -> Result<Vec<NodeEconomicNode>, GovernanceError> {
+        let response = self
+            .call(IpcMethod::ListEconomicNodes, Key::None, Vec::new())
+            .await?;
+        serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("list_economic_nodes"))
+    }
+
+    /// Confirmed balance of an output script, counting outputs with at least
+    /// `min_confirmations` confirmations. Fails with [`GovernanceError::Unsupported`] if no
+    /// module serves balances.
+    pub async fn get_address_balance(
+        &self,
+        script_pubkey: &[u8],
+        min_confirmations: u64,
+    ) -> Result<u64, GovernanceError> {
+        #[derive(Deserialize)]
+        struct AddressBalance {
+            confirmed_sats: u64,
+        }
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "script_pubkey": hex::encode(script_pubkey),
+            "min_confirmations": min_confirmations,
+        }))
+        .map_err(GovernanceError::serialization("get_address_balance"))?;
+        let response = self
+            .call(
+                IpcMethod::GetAddressBalance,
+                Key::Script(script_pubkey),
+                payload,
+            )
+            .await?;
+        let balance: AddressBalance = serde_json::from_slice(&response)
+            .map_err(GovernanceError::serialization("get_address_balance"))?;
+        Ok(balance.confirmed_sats)
+    }
+
+    /// Report a proposal's aggregated veto result to the node, which enforces it. Fails with
+    /// [`GovernanceError::Paused`] while paused, and with [`GovernanceError::Unsupported`] if
+    /// no module takes veto results.
+    pub async fn submit_veto_result(&self, tally: &VetoTally) -> Result<(), GovernanceError> {
+        if self.is_paused() {
+            return Err(GovernanceError::Paused {
+                action: IpcMethod::SubmitVetoResult.as_str().to_string(),
+            });
+        }
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "proposal_id": tally.proposal_id,
+            "total_weight": tally.total_weight,
+            "vetoing_weight": tally.vetoing_weight,
+            "threshold_percent": tally.threshold_percent,
+            "threshold_crossed": tally.crossed(),
+            "registry_commitment": tally.commitment,
+        }))
+        .map_err(GovernanceError::serialization("submit_veto_result"))?;
+        if self.dry_run {
+            tracing::info!(
+                "Dry run, veto result not submitted: {}",
+                String::from_utf8_lossy(&payload)
+            );
+            return Ok(());
+        }
+        self.call(
+            IpcMethod::SubmitVetoResult,
+            Key::Id(&tally.proposal_id),
+            payload,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn respond_after(delay_ms: u64, value: u64) -> Result<u64, String> {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        Ok(value)
+    }
+
+    #[tokio::test]
+    async fn test_delayed_and_dropped_responses() {
+        let timeout = Duration::from_millis(50);
+        assert_eq!(
+            with_timeout("delayed", timeout, respond_after(10, 1))
+                .await
+                .unwrap(),
+            1
+        );
+        // A response that never arrives
+        let dropped = std::future::pending::<Result<u64, String>>();
+        assert!(matches!(
+            with_timeout("dropped", timeout, dropped).await,
+            Err(GovernanceError::Timeout { after, .. }) if after == timeout
+        ));
+        let failed = async { Err::<u64, _>("node error") };
+        assert!(matches!(
+            with_timeout("failed", timeout, failed).await,
+            Err(GovernanceError::NodeRejected { operation, .. }) if operation == "failed"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_out_of_order_responses() {
+        let timeout = Duration::from_millis(200);
+        // Responses arrive in the reverse order of the requests
+        let (slow, fast, lost) = tokio::join!(
+            with_timeout("slow", timeout, respond_after(40, 1)),
+            with_timeout("fast", timeout, respond_after(5, 2)),
+            with_timeout(
+                "lost",
+                timeout,
+                std::future::pending::<Result<u64, String>>()
+            ),
+        );
+        assert_eq!(slow.unwrap(), 1);
+        assert_eq!(fast.unwrap(), 2);
+        assert!(matches!(lost, Err(GovernanceError::Timeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_many_requests_in_flight_out_of_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let permits = Semaphore::new(16);
+        let (current, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let timeout = Duration::from_secs(5);
+        let requests = (0..100u64).map(|i| {
+            let (current, peak) = (&current, &peak);
+            with_permit("get_block", timeout, &permits, async move {
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Responses arrive out of request order
+                let result = respond_after(1 + (100 - i) % 7, i).await;
+                current.fetch_sub(1, Ordering::SeqCst);
+                result
+            })
+        });
+        let results = futures::future::join_all(requests).await;
+        for (i, result) in results.into_iter().enumerate() {
+            assert_eq!(result.unwrap(), i as u64);
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 16);
+
+        // A cancelled caller gives its permit back
+        let cancelled = with_permit(
+            "cancelled",
+            timeout,
+            &permits,
+            std::future::pending::<Result<u64, String>>(),
+        );
+        assert!(tokio::time::timeout(Duration::from_millis(10), cancelled)
+            .await
+            .is_err());
+        assert_eq!(permits.available_permits(), 16);
+    }
+
+    fn header(prev_block_hash: Hash) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_block_hash,
+            merkle_root: [0u8; 32],
+            timestamp: 0,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        }
+    }
+
+    #[test]
+    fn test_header_cache_heights() {
+        let mut cache = HeaderCache::default();
+        let (tip, parent, grandparent) = ([3u8; 32], [2u8; 32], [1u8; 32]);
+        cache.set_height(tip, 100);
+        assert_eq!(cache.insert(tip, header(parent)).height, Some(100));
+        // Heights follow the chain back from a header of known height
+        assert_eq!(cache.insert(parent, header(grandparent)).height, Some(99));
+        assert!(cache.get(&grandparent).is_none());
+        assert_eq!(cache.entries.get(&grandparent).unwrap().height, Some(98));
+        assert_eq!(cache.insert([9u8; 32], header([8u8; 32])).height, None);
+
+        for i in 0..HEADER_CACHE_SIZE as u64 {
+            let mut hash = [0xffu8; 32];
+            hash[..8].copy_from_slice(&i.to_le_bytes());
+            cache.insert(hash, header([0u8; 32]));
+        }
+        assert_eq!(cache.entries.entries.len(), HEADER_CACHE_SIZE);
+        assert!(cache.get(&tip).is_none());
+    }
+
+    #[test]
+    fn test_oversized_message_rejected() {
+        assert!(check_message_size("submit_veto_result", 1024, 1024).is_ok());
+        assert!(matches!(
+            check_message_size("submit_veto_result", 1025, 1024),
+            Err(GovernanceError::MessageTooLarge {
+                size: 1025,
+                limit: 1024,
+                ..
+            })
+        ));
+    }
+}
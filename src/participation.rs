@@ -0,0 +1,481 @@
+//! Per-voter participation statistics
+//!
+//! With `[governance.participation] enabled`, each proposal is counted once voting on it has
+//! closed (merged, rejected or expired), on the next block. For every voter it was eligible
+//! to, the [`VoterParticipation`] of that voter counts it as eligible, as voted if the voter's
+//! vote is among the proposal's votes, the blocks from the proposal's creation to the height
+//! that vote was cast at where both are known, and the vetoes cast on it.
+//!
+//! Voters are identified by registry node id in hex, as they vote. The voters eligible to a
+//! proposal are the registry's members during its window, taken from the epoch snapshots
+//! (see [`crate::economic_nodes::snapshot`]): the nodes of the snapshot in effect at its
+//! created height and of every snapshot taken from then until it closed. A voter who voted is
+//! eligible to that proposal whether or not a snapshot has it, so without snapshots the
+//! statistics only cover those who voted. Snapshots pruned by the registry's retention no
+//! longer count.
+//!
+//! The statistics are stored in the module database, updated as proposals close, and can be
+//! rebuilt from the stored proposals, ballots, snapshots and veto records at any time
+//! ([`ParticipationAnalyzer::recompute`], or the `recompute-participation` subcommand while
+//! the module is stopped). A reorg that reopens a proposal rebuilds them without it.
+//!
+//! Queries: `GET /participation/{id}` (see [`crate::query`]), the `participation` table of
+//! `export-history` (see [`crate::history_export`]), the `participation_rate` metric (see
+//! [`crate::prometheus`]) and the `participation_rate` of each epoch summary.
+
+use crate::config::ParticipationConfig;
+use crate::economic_nodes::snapshot::RegistrySnapshot;
+use crate::economic_nodes::{EconomicNode, EconomicNodeRegistry};
+use crate::error::GovernanceError;
+use crate::proposals::{GovernanceProposal, ProposalStore};
+use crate::vote_check::Ballot;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use tracing::info;
+
+const PARTICIPATION_TREE: &str = "participation";
+
+const PARTICIPATION_KEY: &[u8] = b"participation";
+
+/// The statistics of one voter, over the proposals counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoterParticipation {
+    pub voter: String,
+    /// Proposals the voter was eligible to.
+    pub eligible: u64,
+    /// Of those, proposals the voter voted on.
+    pub voted: u64,
+    /// Blocks from creation to the counted vote, summed over the votes whose height is known.
+    pub latency_blocks: u64,
+    /// Votes whose height is known.
+    pub timed_votes: u64,
+    /// Vetoes cast on the proposals counted.
+    pub vetoes: u64,
+}
+
+impl VoterParticipation {
+    /// Share of the eligible proposals voted on, if there were any.
+    pub fn rate(&self) -> Option<f64> {
+        (self.eligible > 0).then(|| self.voted as f64 / self.eligible as f64)
+    }
+
+    /// Mean blocks from creation to the vote, if any vote's height is known.
+    pub fn mean_latency_blocks(&self) -> Option<f64> {
+        (self.timed_votes > 0).then(|| self.latency_blocks as f64 / self.timed_votes as f64)
+    }
+}
+
+/// What the statistics are counted from.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub proposals: Vec<GovernanceProposal>,
+    /// Counted ballots, by proposal id and voter.
+    pub ballots: HashMap<String, HashMap<String, Ballot>>,
+    /// Epoch snapshots, oldest first.
+    pub snapshots: Vec<RegistrySnapshot>,
+    /// Voters who vetoed each proposal, by proposal id.
+    pub vetoes: HashMap<String, Vec<String>>,
+}
+
+impl History {
+    pub fn new(
+        proposals: Vec<GovernanceProposal>,
+        ballots: HashMap<String, HashMap<String, Ballot>>,
+        mut snapshots: Vec<RegistrySnapshot>,
+        nodes: &[EconomicNode],
+    ) -> Self {
+        snapshots.sort_by_key(|snapshot| snapshot.height);
+        let mut vetoes: HashMap<String, Vec<String>> = HashMap::new();
+        for node in nodes {
+            for veto in &node.veto_history {
+                vetoes
+                    .entry(veto.proposal_id.clone())
+                    .or_default()
+                    .push(hex::encode(node.node_id));
+            }
+        }
+        Self {
+            proposals,
+            ballots,
+            snapshots,
+            vetoes,
+        }
+    }
+
+    /// The history stored in `db`, archived nodes included, e.g. for the CLI.
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Self, GovernanceError> {
+        let store = ProposalStore::new(Arc::clone(db));
+        let mut nodes: Vec<EconomicNode> =
+            EconomicNodeRegistry::load_from(db)?.into_values().collect();
+        nodes.extend(
+            EconomicNodeRegistry::load_archive_from(db)?
+                .into_values()
+                .map(|archived| archived.node),
+        );
+        let snapshots = EconomicNodeRegistry::load_epochs_from(db)?
+            .snapshots
+            .into_values()
+            .collect();
+        Ok(Self::new(
+            store.load_proposals()?,
+            store.ballots()?,
+            snapshots,
+            &nodes,
+        ))
+    }
+
+    /// The voters eligible to `proposal`: the members of the snapshots in effect during its
+    /// window, and those who voted on it.
+    pub fn eligible(&self, proposal: &GovernanceProposal) -> BTreeSet<String> {
+        let mut eligible: BTreeSet<String> = proposal
+            .votes
+            .iter()
+            .map(|vote| vote.voter.clone())
+            .collect();
+        let Some(created) = proposal.created_height else {
+            return eligible;
+        };
+        let in_effect = self.snapshots.iter().rev().find(|s| s.height <= created);
+        let taken = self.snapshots.iter().filter(|s| {
+            s.height > created
+                && proposal
+                    .closed_height
+                    .is_some_and(|closed| s.height <= closed)
+        });
+        for snapshot in in_effect.into_iter().chain(taken) {
+            eligible.extend(
+                snapshot
+                    .nodes
+                    .iter()
+                    .map(|entry| hex::encode(entry.node_id)),
+            );
+        }
+        eligible
+    }
+}
+
+/// The statistics of every voter, and the proposals they count.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Participation {
+    /// Ids of the proposals counted.
+    pub counted: BTreeSet<String>,
+    /// By voter.
+    pub voters: BTreeMap<String, VoterParticipation>,
+}
+
+impl Participation {
+    /// Share of the eligible votes cast, over every voter, if any proposal had an eligible
+    /// voter.
+    pub fn rate(&self) -> Option<f64> {
+        let (voted, eligible) = self.voters.values().fold((0, 0), |(voted, eligible), v| {
+            (voted + v.voted, eligible + v.eligible)
+        });
+        (eligible > 0).then(|| voted as f64 / eligible as f64)
+    }
+
+    fn voter(&mut self, voter: &str) -> &mut VoterParticipation {
+        self.voters
+            .entry(voter.to_string())
+            .or_insert_with(|| VoterParticipation {
+                voter: voter.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Count the proposals in `history` whose voting has closed, at or below `closed_by` if
+    /// given, and that are not counted yet, oldest closed first. Returns how many were.
+    pub fn count_closed(&mut self, history: &History, closed_by: Option<u64>) -> usize {
+        let mut closed: Vec<&GovernanceProposal> = history
+            .proposals
+            .iter()
+            .filter(|p| !p.is_open() && !self.counted.contains(&p.proposal_id))
+            .filter(|p| match closed_by {
+                Some(height) => p.closed_height.is_some_and(|closed| closed <= height),
+                None => true,
+            })
+            .collect();
+        closed.sort_by(|a, b| {
+            (a.closed_height, &a.proposal_id).cmp(&(b.closed_height, &b.proposal_id))
+        });
+        for proposal in &closed {
+            self.count(proposal, history);
+        }
+        closed.len()
+    }
+
+    /// Count `proposal` for every voter eligible to it.
+    fn count(&mut self, proposal: &GovernanceProposal, history: &History) {
+        let id = &proposal.proposal_id;
+        for voter in history.eligible(proposal) {
+            self.voter(&voter).eligible += 1;
+        }
+        let ballots = history.ballots.get(id);
+        for vote in &proposal.votes {
+            let cast = ballots
+                .and_then(|ballots| ballots.get(&vote.voter))
+                .and_then(|ballot| ballot.height);
+            let stats = self.voter(&vote.voter);
+            stats.voted += 1;
+            if let (Some(cast), Some(created)) = (cast, proposal.created_height) {
+                stats.latency_blocks += cast.saturating_sub(created);
+                stats.timed_votes += 1;
+            }
+        }
+        for vetoer in history.vetoes.get(id).into_iter().flatten() {
+            self.voter(vetoer).vetoes += 1;
+        }
+        self.counted.insert(id.clone());
+    }
+}
+
+/// The statistics of every proposal in `history` whose voting has closed, from scratch.
+pub fn analyze(history: &History) -> Participation {
+    let mut participation = Participation::default();
+    participation.count_closed(history, None);
+    participation
+}
+
+/// Keeps the statistics as proposals close.
+pub struct ParticipationAnalyzer {
+    config: ParticipationConfig,
+    db: Arc<dyn blvm_node::storage::database::Database>,
+    proposals: Arc<ProposalStore>,
+    registry: Arc<EconomicNodeRegistry>,
+    /// Held while the statistics change, so each proposal is counted once.
+    update: tokio::sync::Mutex<()>,
+}
+
+impl ParticipationAnalyzer {
+    pub fn new(
+        config: ParticipationConfig,
+        db: Arc<dyn blvm_node::storage::database::Database>,
+        proposals: Arc<ProposalStore>,
+        registry: Arc<EconomicNodeRegistry>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            proposals,
+            registry,
+            update: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Whether proposals are counted, so blocks are needed.
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// The stored statistics.
+    pub fn participation(&self) -> Result<Participation, GovernanceError> {
+        Self::load_from(&self.db)
+    }
+
+    /// The stored statistics of `voter`, if any proposal counted it.
+    pub fn voter(&self, voter: &str) -> Result<Option<VoterParticipation>, GovernanceError> {
+        Ok(Self::load_from(&self.db)?.voters.remove(voter))
+    }
+
+    /// Count the proposals closed since the last update. Returns how many were.
+    pub async fn update(&self) -> Result<usize, GovernanceError> {
+        let _update = self.update.lock().await;
+        let history = self.history().await?;
+        let mut participation = Self::load_from(&self.db)?;
+        let counted = participation.count_closed(&history, None);
+        if counted > 0 {
+            info!(
+                "Counted participation in {} closed proposals, {} voters in all",
+                counted,
+                participation.voters.len()
+            );
+            self.save(&participation)?;
+        }
+        Ok(counted)
+    }
+
+    /// Rebuild the statistics from the stored history, replacing the stored ones.
+    pub async fn recompute(&self) -> Result<Participation, GovernanceError> {
+        let _update = self.update.lock().await;
+        let participation = analyze(&self.history().await?);
+        self.save(&participation)?;
+        Ok(participation)
+    }
+
+    /// Rebuild the statistics without the proposals that closed above `fork_height`, whose
+    /// closing a reorg replaced; those still closed are counted again on the next block.
+    /// Returns how many were left out.
+    pub async fn roll_back_to(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        let _update = self.update.lock().await;
+        let stored = Self::load_from(&self.db)?;
+        if stored.counted.is_empty() {
+            return Ok(0);
+        }
+        let mut participation = Participation::default();
+        participation.count_closed(&self.history().await?, Some(fork_height));
+        let removed = stored.counted.difference(&participation.counted).count() as u64;
+        if participation != stored {
+            self.save(&participation)?;
+        }
+        Ok(removed)
+    }
+
+    /// The history in the live stores.
+    async fn history(&self) -> Result<History, GovernanceError> {
+        let mut nodes = self.registry.list_nodes().await;
+        nodes.extend(self.registry.list_archived().await);
+        Ok(History::new(
+            self.proposals.load_proposals()?,
+            self.proposals.ballots()?,
+            self.registry.list_snapshots(),
+            &nodes,
+        ))
+    }
+
+    fn save(&self, participation: &Participation) -> Result<(), GovernanceError> {
+        save_to(&self.db, participation)
+    }
+
+    /// Load the stored statistics, e.g. for the CLI.
+    pub fn load_from(
+        db: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<Participation, GovernanceError> {
+        let tree = db
+            .open_tree(PARTICIPATION_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        match tree.get(PARTICIPATION_KEY) {
+            Ok(Some(data)) => {
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))
+            }
+            Ok(None) => Ok(Participation::default()),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        }
+    }
+}
+
+/// Store `participation` in `db`, replacing the stored statistics, e.g. after the CLI
+/// recomputed them.
+pub fn save_to(
+    db: &Arc<dyn blvm_node::storage::database::Database>,
+    participation: &Participation,
+) -> Result<(), GovernanceError> {
+    let tree = db
+        .open_tree(PARTICIPATION_TREE)
+        .map_err(GovernanceError::database("open_tree"))?;
+    let data = bincode::serialize(participation).map_err(GovernanceError::encoding("serialize"))?;
+    tree.insert(PARTICIPATION_KEY, &data)
+        .map_err(GovernanceError::database("insert"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::snapshot::SnapshotEntry;
+    use crate::economic_nodes::VetoRecord;
+    use crate::proposals::{ProposalStatus, ProposalVote};
+
+    fn snapshot(id: u64, height: u64, nodes: &[u8]) -> RegistrySnapshot {
+        let mut snapshot = RegistrySnapshot::take(
+            id,
+            height,
+            std::iter::empty::<&EconomicNode>(),
+            0,
+            &Default::default(),
+        );
+        snapshot.nodes = nodes
+            .iter()
+            .map(|&n| SnapshotEntry {
+                node_id: [n; 32],
+                node_type: "miner".to_string(),
+                weight: 10.0,
+            })
+            .collect();
+        snapshot
+    }
+
+    fn proposal(id: &str, created: u64, closed: Option<u64>, voters: &[u8]) -> GovernanceProposal {
+        GovernanceProposal {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+            status: match closed {
+                Some(_) => ProposalStatus::Merged,
+                None => ProposalStatus::Voting,
+            },
+            votes: voters
+                .iter()
+                .map(|&n| ProposalVote {
+                    voter: hex::encode([n; 32]),
+                    vote: "yes".to_string(),
+                })
+                .collect(),
+            author: None,
+            created_height: Some(created),
+            closed_height: closed,
+            milestones: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_counted_once_voting_closes() {
+        let voter = |n: u8| hex::encode([n; 32]);
+        let ballots = HashMap::from([(
+            "1".to_string(),
+            HashMap::from([(
+                voter(1),
+                Ballot {
+                    vote: "yes".to_string(),
+                    height: Some(112),
+                },
+            )]),
+        )]);
+        let mut vetoer = EconomicNode::new([2; 32], 50);
+        vetoer.veto_history = vec![VetoRecord {
+            proposal_id: "1".to_string(),
+            height: 115,
+            reason: String::new(),
+            outcome: None,
+        }];
+        let history = History::new(
+            vec![
+                proposal("1", 105, Some(125), &[1]),
+                proposal("2", 130, None, &[1, 2]),
+            ],
+            ballots,
+            // Node 3 joined during the window of 1, node 4 after it closed
+            vec![
+                snapshot(3, 130, &[1, 2, 3, 4]),
+                snapshot(1, 100, &[1, 2]),
+                snapshot(2, 120, &[1, 2, 3]),
+            ],
+            &[vetoer],
+        );
+        assert_eq!(
+            history.eligible(&history.proposals[0]),
+            [voter(1), voter(2), voter(3)].into()
+        );
+
+        let participation = analyze(&history);
+        assert_eq!(participation.counted, ["1".to_string()].into());
+        let first = &participation.voters[&voter(1)];
+        assert_eq!((first.eligible, first.voted), (1, 1));
+        assert_eq!(first.mean_latency_blocks(), Some(7.0));
+        let second = &participation.voters[&voter(2)];
+        assert_eq!((second.voted, second.vetoes), (0, 1));
+        assert_eq!(second.rate(), Some(0.0));
+        assert!(!participation.voters.contains_key(&voter(4)));
+        assert_eq!(participation.rate(), Some(1.0 / 3.0));
+
+        // Counted once, and not at all while closed above the bound
+        let mut again = participation.clone();
+        assert_eq!(again.count_closed(&history, None), 0);
+        assert_eq!(again, participation);
+        let mut bounded = Participation::default();
+        assert_eq!(bounded.count_closed(&history, Some(124)), 0);
+        assert_eq!(bounded.rate(), None);
+    }
+}
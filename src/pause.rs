@@ -0,0 +1,283 @@
+//! Emergency pause of outbound effects
+//!
+//! During an incident the operator can stop everything the module sends out without stopping
+//! the module and losing the event stream: `blvm-governance pause` (a request on the admin
+//! socket, see [`crate::admin`]) or `POST /admin/pause` on the health listener with
+//! `[governance.health] admin_token` set (see [`crate::health`]). While paused, events are
+//! still received, applied to the registry and proposal store, and checkpointed, but nothing
+//! leaves the module:
+//!
+//! - webhook notifications, block notifications included, are recorded in the intent log
+//!   (see [`crate::intent`]) and held there instead of being sent;
+//! - veto results stay queued for the node (see [`crate::economic_nodes`]);
+//! - governance actions, from the action endpoints, the GitHub receiver and the anchorer's
+//!   wallet transactions alike, are refused with [`GovernanceError::Paused`] (see
+//!   [`crate::node_api`]), and no anchors are made (see [`crate::anchor`]).
+//!
+//! Status reports, heartbeats, error reports and alerts still go out, so the paused module
+//! can be watched.
+//!
+//! Resuming (`blvm-governance resume`, `POST /admin/resume`) drains the backlog: the held
+//! intents are executed oldest first by [`Pipeline::recover_intents`] and the queued veto
+//! results are sent. New notifications are held until the backlog is empty, so they follow
+//! it in order. Resumed while disconnected, the backlog is drained when the next connection
+//! recovers its intents.
+//!
+//! The flag is kept in `pause.json` in the data directory's `state/`, written atomically as
+//! the checkpoint is, so a paused module starts paused and leaves the intents held before the
+//! restart pending. Every pause and resume is written to the audit log with what triggered it
+//! (see [`crate::audit_log`]), and the state is in the `pause` section of the status report
+//! and the `pause` check of `/readyz`.
+
+use crate::audit_log::AuditLog;
+use crate::checkpoint::write_atomic;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::pipeline::Pipeline;
+use blvm_node::module::traits::NodeAPI;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+pub const PAUSE_FILE: &str = "pause.json";
+
+/// What asked for a pause or resume.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trigger {
+    /// Where the request came from: "cli", "admin_socket" or "http".
+    #[serde(default)]
+    pub source: String,
+    /// Who made it, as they gave it, e.g. the user running the CLI.
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Trigger {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Contents of `pause.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct PauseFile {
+    paused: bool,
+    /// Unix seconds of the last pause or resume.
+    since: Option<u64>,
+    /// What made the last pause or resume.
+    trigger: Option<Trigger>,
+}
+
+/// Whether the module is paused, as the status report and the pause requests show it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PauseStatus {
+    pub paused: bool,
+    /// Resumed, with the held backlog still being sent.
+    pub draining: bool,
+    /// Unix seconds of the last pause or resume.
+    pub since: Option<u64>,
+    pub trigger: Option<Trigger>,
+}
+
+pub struct PauseControl {
+    path: PathBuf,
+    state: Mutex<PauseFile>,
+    draining: AtomicBool,
+    /// Woken on each resume.
+    resumed: Notify,
+    /// Where pauses and resumes are recorded.
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+impl PauseControl {
+    /// Load the flag in `dir`; a module never paused there is running.
+    pub fn open(dir: &Path) -> Result<Self, GovernanceError> {
+        let path = dir.join(PAUSE_FILE);
+        let state: PauseFile = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(GovernanceError::serialization(&path.display().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PauseFile::default(),
+            Err(e) => return Err(GovernanceError::io(path.display())(e)),
+        };
+        if state.paused {
+            warn!(
+                "Starting paused since {}; nothing is sent until resumed",
+                state.since.unwrap_or_default()
+            );
+        }
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+            draining: AtomicBool::new(false),
+            resumed: Notify::new(),
+            audit_log: None,
+        })
+    }
+
+    /// Record each pause and resume in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Whether outbound effects are paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    /// Whether new webhook notifications are held: while paused, and after a resume until the
+    /// backlog is drained.
+    pub fn holds(&self) -> bool {
+        self.is_paused() || self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> PauseStatus {
+        let state = self.state.lock().unwrap();
+        PauseStatus {
+            paused: state.paused,
+            draining: self.draining.load(Ordering::SeqCst),
+            since: state.since,
+            trigger: state.trigger.clone(),
+        }
+    }
+
+    /// Stop outbound effects. Pausing a paused module only records the request.
+    pub fn pause(&self, trigger: Trigger) -> Result<PauseStatus, GovernanceError> {
+        self.set(true, trigger)
+    }
+
+    /// Let outbound effects resume, starting with the backlog held while paused. Resuming a
+    /// running module only records the request.
+    pub fn resume(&self, trigger: Trigger) -> Result<PauseStatus, GovernanceError> {
+        self.set(false, trigger)
+    }
+
+    fn set(&self, paused: bool, trigger: Trigger) -> Result<PauseStatus, GovernanceError> {
+        let action = if paused { "pause" } else { "resume" };
+        let changed = {
+            let mut state = self.state.lock().unwrap();
+            let changed = state.paused != paused;
+            if changed {
+                let next = PauseFile {
+                    paused,
+                    since: Some(crate::clock::unix_now()),
+                    trigger: Some(trigger.clone()),
+                };
+                let data =
+                    serde_json::to_vec(&next).map_err(GovernanceError::serialization("pause"))?;
+                if let Some(dir) = self.path.parent() {
+                    std::fs::create_dir_all(dir).map_err(GovernanceError::io(dir.display()))?;
+                }
+                write_atomic(&self.path, &data)?;
+                *state = next;
+            }
+            changed
+        };
+        if changed {
+            warn!(
+                "Outbound effects {} by {} ({}): {}",
+                if paused { "paused" } else { "resumed" },
+                trigger.source,
+                trigger.actor.as_deref().unwrap_or("unknown"),
+                trigger.reason.as_deref().unwrap_or("no reason given")
+            );
+        } else {
+            info!("Asked to {} by {}, already done", action, trigger.source);
+        }
+        if let Some(log) = &self.audit_log {
+            log.pause(action, &trigger, changed);
+        }
+        if changed && !paused {
+            self.draining.store(true, Ordering::SeqCst);
+            self.resumed.notify_one();
+        }
+        Ok(self.status())
+    }
+
+    /// Send what was held while paused: execute the pending intents with `pipeline` until
+    /// none is left, then the queued veto results of `registry`. New notifications are sent
+    /// again once it is done, unless the module was paused again meanwhile.
+    pub async fn drain(
+        &self,
+        pipeline: &Pipeline,
+        node_api: &dyn NodeAPI,
+        registry: &EconomicNodeRegistry,
+    ) {
+        let mut sent = 0;
+        // Notifications held while this runs are recorded after those it has read
+        loop {
+            let completed = pipeline.recover_intents(node_api).await;
+            if completed == 0 {
+                break;
+            }
+            sent += completed;
+        }
+        if let Err(e) = registry.flush_veto_reports().await {
+            warn!("Queued veto results not sent on resume: {}", e.chain());
+        }
+        self.draining.store(false, Ordering::SeqCst);
+        info!("Sent {} held intents after resuming", sent);
+    }
+
+    /// Drain the backlog after each resume, and now if the module was resumed while
+    /// disconnected, for one connection.
+    pub fn spawn_drain(
+        self: &Arc<Self>,
+        pipeline: Arc<Pipeline>,
+        node_api: Arc<dyn NodeAPI>,
+        registry: Arc<EconomicNodeRegistry>,
+    ) -> JoinHandle<()> {
+        let pause = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                if pause.draining.load(Ordering::SeqCst) && !pause.is_paused() {
+                    pause.drain(&pipeline, node_api.as_ref(), &registry).await;
+                }
+                pause.resumed.notified().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("blvm_pause_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let pause = PauseControl::open(&dir).unwrap();
+        assert!(!pause.holds());
+        let trigger = Trigger {
+            actor: Some("alice".to_string()),
+            reason: Some("incident".to_string()),
+            ..Trigger::new("cli")
+        };
+        let status = pause.pause(trigger.clone()).unwrap();
+        assert!(status.paused && !status.draining);
+        assert_eq!(status.trigger, Some(trigger.clone()));
+        // Pausing again changes nothing
+        let again = pause.pause(Trigger::new("http")).unwrap();
+        assert_eq!(again.trigger, Some(trigger));
+
+        let reopened = PauseControl::open(&dir).unwrap();
+        assert!(reopened.is_paused() && reopened.holds());
+        let status = reopened.resume(Trigger::new("admin_socket")).unwrap();
+        assert!(!status.paused && status.draining);
+        // Held until the backlog is drained
+        assert!(reopened.holds());
+        assert!(!PauseControl::open(&dir).unwrap().is_paused());
+        assert!(!dir.join("pause.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
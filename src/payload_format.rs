@@ -0,0 +1,497 @@
+//! Wire formats of webhook deliveries
+//!
+//! `webhook_content_type` picks how the payloads of [`crate::schema`] are written to the
+//! webhook: `json`, the default, `cbor` (RFC 8949) or `msgpack` (MessagePack), each sent with
+//! its Content-Type ([`mime`]). The binary formats hold the value the JSON would: maps with
+//! string keys in the same order, arrays, strings, booleans, null, integers in the shortest
+//! form that holds them and other numbers as 64-bit floats. A consumer decoding them with
+//! serde_cbor, ciborium or rmp-serde into the schema types gets what it would from the JSON.
+//! Neither crate is a dependency of the module, so the encoders, and the decoders the tests
+//! check them with, are written here; they cover that data model only, and [`decode`] refuses
+//! byte strings, tags, extensions and indefinite lengths.
+//!
+//! A receiver answering 415 Unsupported Media Type to a binary delivery gets JSON from then on,
+//! with a warning, and the delivery is sent again as JSON at once (see [`crate::webhook`]).
+//! With `webhook_secret` set, the `X-Hub-Signature-256` of each delivery is the HMAC of its
+//! body as sent, binary or not (see [`crate::github::signature`]).
+
+use crate::config::WebhookContentType;
+use crate::error::GovernanceError;
+use serde_json::{Map, Number, Value};
+
+/// Deepest nesting [`decode`] accepts.
+const MAX_DEPTH: usize = 128;
+
+/// Content-Type of deliveries in `format`.
+pub fn mime(format: WebhookContentType) -> &'static str {
+    match format {
+        WebhookContentType::Json => "application/json",
+        WebhookContentType::Cbor => "application/cbor",
+        WebhookContentType::Msgpack => "application/msgpack",
+    }
+}
+
+/// `payload` written in `format`.
+pub fn encode(format: WebhookContentType, payload: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    match format {
+        WebhookContentType::Json => {
+            return serde_json::to_vec(payload).expect("JSON values serialize")
+        }
+        WebhookContentType::Cbor => cbor::write(&mut out, payload),
+        WebhookContentType::Msgpack => msgpack::write(&mut out, payload),
+    }
+    out
+}
+
+/// The payload `body` holds in `format`.
+pub fn decode(format: WebhookContentType, body: &[u8]) -> Result<Value, GovernanceError> {
+    let malformed = |what: String| {
+        GovernanceError::WebhookError(format!("malformed {} payload: {}", format.as_str(), what))
+    };
+    match format {
+        WebhookContentType::Json => {
+            serde_json::from_slice(body).map_err(GovernanceError::serialization("payload"))
+        }
+        WebhookContentType::Cbor | WebhookContentType::Msgpack => {
+            let mut reader = Reader { body, at: 0 };
+            let value = match format {
+                WebhookContentType::Cbor => cbor::read(&mut reader, 0),
+                _ => msgpack::read(&mut reader, 0),
+            }
+            .map_err(malformed)?;
+            if reader.at != body.len() {
+                return Err(malformed(format!(
+                    "{} bytes after the value",
+                    body.len() - reader.at
+                )));
+            }
+            Ok(value)
+        }
+    }
+}
+
+/// A number as a JSON value; not-a-number and infinities, which JSON cannot hold, are refused.
+fn float(value: f64) -> Result<Value, String> {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| format!("{} is not a JSON number", value))
+}
+
+/// Position in a body being decoded.
+struct Reader<'a> {
+    body: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .at
+            .checked_add(n)
+            .filter(|end| *end <= self.body.len())
+            .ok_or_else(|| format!("truncated at byte {}", self.at))?;
+        let bytes = &self.body[self.at..end];
+        self.at = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// A big-endian unsigned integer of `n` bytes.
+    fn uint(&mut self, n: usize) -> Result<u64, String> {
+        Ok(self
+            .take(n)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+    }
+
+    fn string(&mut self, len: u64) -> Result<String, String> {
+        let len = usize::try_from(len).map_err(|_| "string too long".to_string())?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| e.to_string())
+    }
+
+    /// A length prefix, checked against the bytes left so a bad one cannot allocate much.
+    fn len(&self, len: u64) -> Result<usize, String> {
+        usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= self.body.len() - self.at)
+            .ok_or_else(|| format!("length {} past the end at byte {}", len, self.at))
+    }
+}
+
+mod cbor {
+    use super::{float, Map, Reader, Value, MAX_DEPTH};
+
+    fn head(out: &mut Vec<u8>, major: u8, n: u64) {
+        let major = major << 5;
+        match n {
+            0..=23 => out.push(major | n as u8),
+            24..=0xff => out.extend([major | 24, n as u8]),
+            0x100..=0xffff => {
+                out.push(major | 25);
+                out.extend((n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(major | 26);
+                out.extend((n as u32).to_be_bytes());
+            }
+            _ => {
+                out.push(major | 27);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    }
+
+    pub(super) fn write(out: &mut Vec<u8>, value: &Value) {
+        match value {
+            Value::Null => out.push(0xf6),
+            Value::Bool(false) => out.push(0xf4),
+            Value::Bool(true) => out.push(0xf5),
+            Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(n), _) => head(out, 0, n),
+                (None, Some(n)) => head(out, 1, !(n as u64)),
+                _ => {
+                    out.push(0xfb);
+                    out.extend(n.as_f64().unwrap_or_default().to_be_bytes());
+                }
+            },
+            Value::String(s) => {
+                head(out, 3, s.len() as u64);
+                out.extend(s.as_bytes());
+            }
+            Value::Array(items) => {
+                head(out, 4, items.len() as u64);
+                for item in items {
+                    write(out, item);
+                }
+            }
+            Value::Object(fields) => {
+                head(out, 5, fields.len() as u64);
+                for (name, value) in fields {
+                    head(out, 3, name.len() as u64);
+                    out.extend(name.as_bytes());
+                    write(out, value);
+                }
+            }
+        }
+    }
+
+    /// A half-precision float, as decoders of other encoders may meet them.
+    fn half(bits: u16) -> f64 {
+        let exponent = (bits >> 10) & 0x1f;
+        let mantissa = f64::from(bits & 0x3ff);
+        let magnitude = match exponent {
+            0 => mantissa * 2f64.powi(-24),
+            0x1f if mantissa == 0.0 => f64::INFINITY,
+            0x1f => f64::NAN,
+            _ => (1024.0 + mantissa) * 2f64.powi(i32::from(exponent) - 25),
+        };
+        if bits & 0x8000 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub(super) fn read(reader: &mut Reader, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("nested too deep".to_string());
+        }
+        let initial = reader.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == 7 {
+            return match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 => Ok(Value::Null),
+                25 => float(half(reader.uint(2)? as u16)),
+                26 => float(f64::from(f32::from_bits(reader.uint(4)? as u32))),
+                27 => float(f64::from_bits(reader.uint(8)?)),
+                _ => Err(format!("unsupported simple value {}", info)),
+            };
+        }
+        let n = match info {
+            0..=23 => u64::from(info),
+            24 => reader.uint(1)?,
+            25 => reader.uint(2)?,
+            26 => reader.uint(4)?,
+            27 => reader.uint(8)?,
+            _ => {
+                return Err(format!(
+                    "unsupported length {} of major type {}",
+                    info, major
+                ))
+            }
+        };
+        match major {
+            0 => Ok(Value::from(n)),
+            1 => i64::try_from(n)
+                .map(|n| Value::from(-1 - n))
+                .map_err(|_| format!("-1 - {} is out of range", n)),
+            3 => reader.string(n).map(Value::String),
+            4 => {
+                let len = reader.len(n)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(read(reader, depth + 1)?);
+                }
+                Ok(Value::Array(items))
+            }
+            5 => {
+                let len = reader.len(n)?;
+                let mut fields = Map::new();
+                for _ in 0..len {
+                    let Value::String(name) = read(reader, depth + 1)? else {
+                        return Err("map key is not a string".to_string());
+                    };
+                    fields.insert(name, read(reader, depth + 1)?);
+                }
+                Ok(Value::Object(fields))
+            }
+            _ => Err(format!("unsupported major type {}", major)),
+        }
+    }
+}
+
+mod msgpack {
+    use super::{float, Map, Reader, Value, MAX_DEPTH};
+
+    fn string(out: &mut Vec<u8>, s: &str) {
+        match s.len() {
+            len @ 0..=31 => out.push(0xa0 | len as u8),
+            len @ 32..=0xff => out.extend([0xd9, len as u8]),
+            len @ 0x100..=0xffff => {
+                out.push(0xda);
+                out.extend((len as u16).to_be_bytes());
+            }
+            len => {
+                out.push(0xdb);
+                out.extend((len as u32).to_be_bytes());
+            }
+        }
+        out.extend(s.as_bytes());
+    }
+
+    /// The header of an array (`fix` 0x90, 16-bit 0xdc) or map (0x80, 0xde) of `len` entries.
+    fn collection(out: &mut Vec<u8>, fix: u8, wide: u8, len: usize) {
+        match len {
+            0..=15 => out.push(fix | len as u8),
+            16..=0xffff => {
+                out.push(wide);
+                out.extend((len as u16).to_be_bytes());
+            }
+            _ => {
+                out.push(wide + 1);
+                out.extend((len as u32).to_be_bytes());
+            }
+        }
+    }
+
+    fn int(out: &mut Vec<u8>, n: i64) {
+        match n {
+            -32..=0x7f => out.push(n as u8),
+            -0x80..=0x7f => out.extend([0xd0, n as u8]),
+            -0x8000..=0x7fff => {
+                out.push(0xd1);
+                out.extend((n as i16).to_be_bytes());
+            }
+            -0x8000_0000..=0x7fff_ffff => {
+                out.push(0xd2);
+                out.extend((n as i32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xd3);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    }
+
+    fn uint(out: &mut Vec<u8>, n: u64) {
+        match n {
+            0..=0x7f => out.push(n as u8),
+            0x80..=0xff => out.extend([0xcc, n as u8]),
+            0x100..=0xffff => {
+                out.push(0xcd);
+                out.extend((n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(0xce);
+                out.extend((n as u32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xcf);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    }
+
+    pub(super) fn write(out: &mut Vec<u8>, value: &Value) {
+        match value {
+            Value::Null => out.push(0xc0),
+            Value::Bool(false) => out.push(0xc2),
+            Value::Bool(true) => out.push(0xc3),
+            Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(n), _) => uint(out, n),
+                (None, Some(n)) => int(out, n),
+                _ => {
+                    out.push(0xcb);
+                    out.extend(n.as_f64().unwrap_or_default().to_be_bytes());
+                }
+            },
+            Value::String(s) => string(out, s),
+            Value::Array(items) => {
+                collection(out, 0x90, 0xdc, items.len());
+                for item in items {
+                    write(out, item);
+                }
+            }
+            Value::Object(fields) => {
+                collection(out, 0x80, 0xde, fields.len());
+                for (name, value) in fields {
+                    string(out, name);
+                    write(out, value);
+                }
+            }
+        }
+    }
+
+    fn array(reader: &mut Reader, len: u64, depth: usize) -> Result<Value, String> {
+        let len = reader.len(len)?;
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(read(reader, depth + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn map(reader: &mut Reader, len: u64, depth: usize) -> Result<Value, String> {
+        let len = reader.len(len)?;
+        let mut fields = Map::new();
+        for _ in 0..len {
+            let Value::String(name) = read(reader, depth + 1)? else {
+                return Err("map key is not a string".to_string());
+            };
+            fields.insert(name, read(reader, depth + 1)?);
+        }
+        Ok(Value::Object(fields))
+    }
+
+    pub(super) fn read(reader: &mut Reader, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("nested too deep".to_string());
+        }
+        let marker = reader.byte()?;
+        match marker {
+            0x00..=0x7f => Ok(Value::from(marker)),
+            0x80..=0x8f => map(reader, u64::from(marker & 0x0f), depth),
+            0x90..=0x9f => array(reader, u64::from(marker & 0x0f), depth),
+            0xa0..=0xbf => reader.string(u64::from(marker & 0x1f)).map(Value::String),
+            0xc0 => Ok(Value::Null),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xca => float(f64::from(f32::from_bits(reader.uint(4)? as u32))),
+            0xcb => float(f64::from_bits(reader.uint(8)?)),
+            0xcc => Ok(Value::from(reader.uint(1)?)),
+            0xcd => Ok(Value::from(reader.uint(2)?)),
+            0xce => Ok(Value::from(reader.uint(4)?)),
+            0xcf => Ok(Value::from(reader.uint(8)?)),
+            0xd0 => Ok(Value::from(reader.uint(1)? as u8 as i8)),
+            0xd1 => Ok(Value::from(reader.uint(2)? as u16 as i16)),
+            0xd2 => Ok(Value::from(reader.uint(4)? as u32 as i32)),
+            0xd3 => Ok(Value::from(reader.uint(8)? as i64)),
+            0xd9 => {
+                let len = reader.uint(1)?;
+                reader.string(len).map(Value::String)
+            }
+            0xda => {
+                let len = reader.uint(2)?;
+                reader.string(len).map(Value::String)
+            }
+            0xdb => {
+                let len = reader.uint(4)?;
+                reader.string(len).map(Value::String)
+            }
+            0xdc => {
+                let len = reader.uint(2)?;
+                array(reader, len, depth)
+            }
+            0xdd => {
+                let len = reader.uint(4)?;
+                array(reader, len, depth)
+            }
+            0xde => {
+                let len = reader.uint(2)?;
+                map(reader, len, depth)
+            }
+            0xdf => {
+                let len = reader.uint(4)?;
+                map(reader, len, depth)
+            }
+            0xe0..=0xff => Ok(Value::from(marker as i8)),
+            _ => Err(format!("unsupported marker {:#04x}", marker)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FORMATS: [WebhookContentType; 3] = [
+        WebhookContentType::Json,
+        WebhookContentType::Cbor,
+        WebhookContentType::Msgpack,
+    ];
+
+    #[test]
+    fn test_known_encodings() {
+        let value = json!({ "a": [1, -1, true, null], "b": 0.5 });
+        assert_eq!(
+            hex::encode(encode(WebhookContentType::Cbor, &value)),
+            // {"a": [1, -1, true, null], "b": 0.5}
+            "a26161840120f5f66162fb3fe0000000000000"
+        );
+        assert_eq!(
+            hex::encode(encode(WebhookContentType::Msgpack, &value)),
+            "82a1619401ffc3c0a162cb3fe0000000000000"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_at_the_boundaries() {
+        let long = "x".repeat(70_000);
+        let value = json!({
+            "ints": [0, 23, 24, 127, 128, 255, 256, 65_535, 65_536, u32::MAX, u64::MAX],
+            "negative": [-1, -24, -25, -32, -33, -128, -129, -32_768, -32_769, i64::MIN],
+            "floats": [0.1, -2.5, 1e300],
+            "strings": ["", "é", "x".repeat(31), "x".repeat(32), "x".repeat(256), long],
+            "nested": { "list": (0..20).collect::<Vec<u32>>(), "empty": {} },
+        });
+        for format in FORMATS {
+            let body = encode(format, &value);
+            assert_eq!(decode(format, &body).unwrap(), value, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_malformed_bodies_are_refused() {
+        for format in [WebhookContentType::Cbor, WebhookContentType::Msgpack] {
+            let body = encode(format, &json!({ "a": "bc" }));
+            assert!(decode(format, &body[..body.len() - 1]).is_err());
+            let mut trailing = body.clone();
+            trailing.push(0);
+            assert!(decode(format, &trailing).is_err());
+        }
+        // A byte string, and an array claiming more entries than there are bytes
+        assert!(decode(WebhookContentType::Cbor, &[0x41, 0x00]).is_err());
+        assert!(decode(WebhookContentType::Cbor, &[0x9b, 0xff, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(decode(WebhookContentType::Msgpack, &[0xdd, 0xff, 0xff, 0xff, 0xff]).is_err());
+        // Too deep
+        let deep = vec![0x81; MAX_DEPTH + 2];
+        assert!(decode(WebhookContentType::Cbor, &deep).is_err());
+    }
+}
@@ -0,0 +1,998 @@
+//! Event processing pipeline
+//!
+//! Every event from the node is passed to each registered [`EventHandler`] interested in its
+//! type, in registration order. A failing handler is logged and does not stop the others;
+//! events handled, errors and time spent are counted per handler. An event counts as
+//! processed, and is checkpointed (see [`crate::checkpoint`]), only once every handler has
+//! completed it. For blocks each handler's completion is checkpointed as well, so when a
+//! block is replayed after a crash only the handlers that had not completed it run again.
+//!
+//! Each handler declares the event types it needs, taking its own configuration into account
+//! (e.g. the webhook client needs none while no webhook is configured), and is asked again
+//! for each event so that reconfiguring a handler takes effect immediately. Events of types
+//! no handler needs are counted and dropped at dispatch, before they are queued. Payloads are
+//! deserialized by blvm-sdk before dispatch, so that cost is still paid for them.
+//!
+//! Each event is processed in an `event` span with a fresh trace id (see [`crate::trace`]),
+//! and each handler in a `handler` span under it. The event span also carries the
+//! `proposal_id` of proposal and veto events, and the `block_hash` and `height` of blocks, so
+//! every record logged while handling an event can be found by them.
+//!
+//! A panic in a handler crashes the module (see [`crate::crash`]), unless the pipeline
+//! isolates panics ([`Pipeline::with_panic_isolation`]): then the handler is marked
+//! unhealthy and gets no more events until the module restarts, while the others keep
+//! running. Events it would have handled are not checkpointed, so they are replayed after
+//! the restart.
+//!
+//! Handler errors and panics, and failures to write the checkpoint, are also reported to the
+//! node when the pipeline has an [`ErrorReporter`] (see [`crate::error_report`]).
+//!
+//! Side effects that handlers record in the intent log before carrying them out (see
+//! [`crate::intent`]) and that were left pending by a crash are executed again by
+//! [`Pipeline::recover_intents`], before any new event is processed. While outbound effects
+//! are paused ([`Pipeline::with_pause`], see [`crate::pause`]) they stay pending, and are
+//! executed the same way once resumed.
+//!
+//! A pipeline for a replay run ([`Pipeline::with_replay`], see [`crate::replay`]) passes
+//! every event to every interested handler, whether or not it was processed before, and
+//! leaves the checkpoint as it is.
+//!
+//! A reorg observed by the [`TipTracker`](crate::node_api::TipTracker) is handed to
+//! [`Pipeline::reorged`]. Before the next event, each handler undoes what it recorded above
+//! the fork ([`EventHandler::roll_back`]), last registered first, each in one step so no
+//! partly reverted state is visible; every rollback is recorded in the audit log when the
+//! pipeline has one ([`Pipeline::with_audit_log`]). The checkpoint is then moved back to the
+//! fork, so the blocks of the new chain are processed as they arrive and any the module
+//! misses are backfilled from there (see [`crate::checkpoint`]).
+
+use crate::adoption::AdoptionTracker;
+use crate::anchor::Anchorer;
+use crate::audit_log::AuditLog;
+use crate::checkpoint::Checkpointer;
+use crate::clock::ClockMonitor;
+use crate::delegation::DelegationRegistry;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::epoch_summary::EpochSummarizer;
+use crate::error::GovernanceError;
+use crate::error_report::{ErrorCode, ErrorReport, ErrorReporter};
+use crate::intent::{Intent, IntentLog};
+use crate::node_api::Reorg;
+use crate::participation::ParticipationAnalyzer;
+use crate::pause::PauseControl;
+use crate::proposals::ProposalStore;
+use crate::signaling::SignalingTracker;
+use crate::trace;
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::{EventType, NodeAPI};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{debug, error, info, warn, Instrument};
+
+/// One consumer of node events.
+#[async_trait::async_trait]
+pub trait EventHandler: Send + Sync {
+    /// Stable name, recorded in the checkpoint; renaming a handler re-runs it on replay.
+    fn name(&self) -> &'static str;
+
+    /// Event types this handler needs to see.
+    fn interested_events(&self) -> Vec<EventType>;
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError>;
+
+    /// Carry out `intent`, which this handler recorded but did not complete before the module
+    /// stopped (see [`crate::intent`]). The effect may already have happened, so executing it
+    /// again must be harmless.
+    async fn execute_intent(
+        &self,
+        intent: &Intent,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        Err(GovernanceError::ModuleError(format!(
+            "{} cannot execute {} intents",
+            self.name(),
+            intent.kind
+        )))
+    }
+
+    /// Undo what this handler recorded for blocks above `fork_height`, which a reorg
+    /// replaced, so that no partly reverted state is visible. Returns the number of records
+    /// reverted.
+    async fn roll_back(&self, _fork_height: u64) -> Result<u64, GovernanceError> {
+        Ok(0)
+    }
+}
+
+/// Per-handler counters, reported by [`Pipeline::handler_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HandlerStats {
+    pub handled: u64,
+    pub errors: u64,
+    /// Total time spent in the handler, in milliseconds.
+    pub busy_ms: u64,
+    pub panics: u64,
+    /// Message of the panic that took the handler out of the pipeline, if one did.
+    pub unhealthy: Option<String>,
+}
+
+struct Registered {
+    handler: Arc<dyn EventHandler>,
+    handled: AtomicU64,
+    errors: AtomicU64,
+    busy_ms: AtomicU64,
+    panics: AtomicU64,
+    unhealthy: Mutex<Option<String>>,
+}
+
+pub struct Pipeline {
+    handlers: Vec<Registered>,
+    checkpointer: Arc<Checkpointer>,
+    /// Catch handler panics instead of crashing.
+    isolate_panics: bool,
+    /// Events dropped at dispatch, by type.
+    filtered: Mutex<BTreeMap<String, u64>>,
+    /// Where handler failures are reported.
+    errors: Option<Arc<ErrorReporter>>,
+    /// Neither consult nor advance the checkpoint.
+    replay: bool,
+    /// Side effects of events, recovered by [`Pipeline::recover_intents`].
+    intents: Option<Arc<IntentLog>>,
+    /// Where rollbacks are recorded.
+    audit_log: Option<Arc<AuditLog>>,
+    /// Reorg to roll back before the next event.
+    reorg: Mutex<Option<Reorg>>,
+    /// Leaves intents pending while paused.
+    pause: Option<Arc<PauseControl>>,
+}
+
+impl Pipeline {
+    pub fn new(checkpointer: Arc<Checkpointer>) -> Self {
+        Self {
+            handlers: Vec::new(),
+            checkpointer,
+            isolate_panics: false,
+            filtered: Mutex::default(),
+            errors: None,
+            replay: false,
+            intents: None,
+            audit_log: None,
+            reorg: Mutex::default(),
+            pause: None,
+        }
+    }
+
+    /// Run `handler` after the handlers registered before it.
+    pub fn with_handler(mut self, handler: Arc<dyn EventHandler>) -> Self {
+        self.handlers.push(Registered {
+            handler,
+            handled: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            busy_ms: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            unhealthy: Mutex::new(None),
+        });
+        self
+    }
+
+    /// Run `handlers` in order, after any registered before.
+    pub fn with_handlers(self, handlers: Vec<Arc<dyn EventHandler>>) -> Self {
+        handlers.into_iter().fold(self, Self::with_handler)
+    }
+
+    /// With `isolate`, a handler that panics is marked unhealthy and skipped from then on,
+    /// instead of the panic crashing the module.
+    pub fn with_panic_isolation(mut self, isolate: bool) -> Self {
+        self.isolate_panics = isolate;
+        self
+    }
+
+    /// Report handler errors and panics, and checkpoint write failures, to `errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// For a replay run: handle events already processed again, and checkpoint nothing.
+    pub fn with_replay(mut self) -> Self {
+        self.replay = true;
+        self
+    }
+
+    /// Recover the side effects left pending in `intents` with [`Pipeline::recover_intents`].
+    pub fn with_intents(mut self, intents: Arc<IntentLog>) -> Self {
+        self.intents = Some(intents);
+        self
+    }
+
+    /// Record each handler's rollback after a reorg in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Leave intents pending in [`Pipeline::recover_intents`] while `pause` is paused.
+    pub fn with_pause(mut self, pause: Arc<PauseControl>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Roll the handlers back to the fork of `reorg` before the next event is processed.
+    pub fn reorged(&self, reorg: Reorg) {
+        let mut pending = self.reorg.lock().unwrap();
+        *pending = Some(match *pending {
+            Some(earlier) => earlier.followed_by(reorg),
+            None => reorg,
+        });
+    }
+
+    /// Have the handlers that recorded them execute the intents left pending by an earlier
+    /// run, oldest first, completing each that succeeds. Call before processing any event,
+    /// and on resuming after a pause. Returns the number completed; the others stay pending
+    /// for the next start. Nothing is executed while paused.
+    pub async fn recover_intents(&self, node_api: &dyn NodeAPI) -> usize {
+        let Some(intents) = &self.intents else {
+            return 0;
+        };
+        let pending = intents.pending();
+        if pending.is_empty() {
+            return 0;
+        }
+        if self.pause.as_ref().is_some_and(|p| p.is_paused()) {
+            info!("Paused, leaving {} intents pending", pending.len());
+            return 0;
+        }
+        info!("Recovering {} pending intents", pending.len());
+        let mut completed = 0;
+        for intent in pending {
+            let span = tracing::info_span!(
+                "intent",
+                id = intent.id,
+                handler = %intent.handler,
+                kind = %intent.kind,
+                trace_id = intent.trace_id.as_deref().unwrap_or_default()
+            );
+            if self
+                .recover_intent(intents, &intent, node_api)
+                .instrument(span)
+                .await
+            {
+                completed += 1;
+            }
+        }
+        completed
+    }
+
+    /// Execute and complete one pending intent. Returns whether it was completed.
+    async fn recover_intent(
+        &self,
+        intents: &IntentLog,
+        intent: &Intent,
+        node_api: &dyn NodeAPI,
+    ) -> bool {
+        let Some(registered) = self
+            .handlers
+            .iter()
+            .find(|h| h.handler.name() == intent.handler)
+        else {
+            warn!(
+                "No {} handler to execute intent {}",
+                intent.handler, intent.id
+            );
+            return false;
+        };
+        let trace_id = intent.trace_id.clone().unwrap_or_else(trace::new_id);
+        let executed = trace::with_id(
+            trace_id,
+            registered.handler.execute_intent(intent, node_api),
+        )
+        .await;
+        if let Err(e) = executed {
+            warn!("Failed to execute intent {}: {}", intent.id, e.chain());
+            return false;
+        }
+        if let Err(e) = intents.complete(intent.id) {
+            warn!("Failed to complete intent {}: {}", intent.id, e.chain());
+            return false;
+        }
+        true
+    }
+
+    fn report(&self, report: ErrorReport) {
+        if let Some(errors) = &self.errors {
+            errors.report(report);
+        }
+    }
+
+    /// Whether any handler needs events of `event_type`. Events that no handler needs are
+    /// counted as filtered.
+    pub fn accepts(&self, event_type: &EventType) -> bool {
+        let interested = self
+            .handlers
+            .iter()
+            .any(|h| h.handler.interested_events().contains(event_type));
+        if interested {
+            return true;
+        }
+        *self
+            .filtered
+            .lock()
+            .unwrap()
+            .entry(format!("{:?}", event_type))
+            .or_default() += 1;
+        false
+    }
+
+    /// Events dropped at dispatch because no handler needs them, by type.
+    pub fn filtered(&self) -> BTreeMap<String, u64> {
+        self.filtered.lock().unwrap().clone()
+    }
+
+    pub fn handlers(&self) -> Vec<&'static str> {
+        self.handlers.iter().map(|h| h.handler.name()).collect()
+    }
+
+    pub fn handler_stats(&self) -> BTreeMap<&'static str, HandlerStats> {
+        self.handlers
+            .iter()
+            .map(|h| {
+                let stats = HandlerStats {
+                    handled: h.handled.load(Ordering::Relaxed),
+                    errors: h.errors.load(Ordering::Relaxed),
+                    busy_ms: h.busy_ms.load(Ordering::Relaxed),
+                    panics: h.panics.load(Ordering::Relaxed),
+                    unhealthy: h.unhealthy.lock().unwrap().clone(),
+                };
+                (h.handler.name(), stats)
+            })
+            .collect()
+    }
+
+    pub fn checkpointer(&self) -> &Arc<Checkpointer> {
+        &self.checkpointer
+    }
+
+    /// Pass `event` to every interested handler that has not completed it yet. Returns
+    /// whether all of them completed it.
+    pub async fn process(&self, event: &EventMessage, node_api: &dyn NodeAPI) -> bool {
+        let trace_id = trace::new_id();
+        let span = tracing::info_span!(
+            "event",
+            event_type = ?event.event_type,
+            trace_id = %trace_id,
+            proposal_id = tracing::field::Empty,
+            block_hash = tracing::field::Empty,
+            height = tracing::field::Empty
+        );
+        match &event.payload {
+            EventPayload::NewBlock { block_hash, height } => {
+                span.record("block_hash", hex::encode(block_hash).as_str());
+                span.record("height", *height);
+            }
+            EventPayload::GovernanceProposalCreated { proposal_id, .. }
+            | EventPayload::GovernanceProposalVoted { proposal_id, .. }
+            | EventPayload::GovernanceProposalMerged { proposal_id, .. }
+            | EventPayload::EconomicNodeVeto { proposal_id, .. } => {
+                span.record("proposal_id", proposal_id.as_str());
+            }
+            _ => {}
+        }
+        trace::with_id(trace_id, self.dispatch(event, node_api))
+            .instrument(span)
+            .await
+    }
+
+    async fn dispatch(&self, event: &EventMessage, node_api: &dyn NodeAPI) -> bool {
+        let reorg = self.reorg.lock().unwrap().take();
+        if let Some(reorg) = reorg {
+            self.roll_back(&reorg, node_api).await;
+        }
+        // Only blocks are checkpointed per handler, and nothing is for a replay
+        let block = match &event.payload {
+            EventPayload::NewBlock { block_hash, height } if !self.replay => {
+                Some((*height, *block_hash))
+            }
+            _ => None,
+        };
+        if let Some((height, hash)) = block {
+            if self.checkpointer.is_processed(height, &hash) {
+                debug!("Block {} already processed, skipping", height);
+                return true;
+            }
+        }
+        let msg = ModuleMessage::Event(event.clone());
+        let mut complete = true;
+        for registered in &self.handlers {
+            if !registered
+                .handler
+                .interested_events()
+                .contains(&event.event_type)
+            {
+                continue;
+            }
+            let name = registered.handler.name();
+            if let Some((height, hash)) = block {
+                if self.checkpointer.is_completed(height, &hash, name) {
+                    debug!("Block {} already processed by {}, skipping", height, name);
+                    continue;
+                }
+            }
+            if registered.unhealthy.lock().unwrap().is_some() {
+                debug!(
+                    "Skipping {:?} event in unhealthy {}",
+                    event.event_type, name
+                );
+                complete = false;
+                continue;
+            }
+            let start = Instant::now();
+            let result = crate::crash::run_handler(
+                name,
+                self.isolate_panics,
+                registered.handler.handle(&msg, node_api),
+            )
+            .instrument(tracing::debug_span!("handler", handler = name))
+            .await;
+            registered
+                .busy_ms
+                .fetch_add(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+            registered.handled.fetch_add(1, Ordering::Relaxed);
+            let result = match result {
+                Ok(result) => result,
+                Err(panic) => {
+                    registered.panics.fetch_add(1, Ordering::Relaxed);
+                    error!(
+                        "{} panicked handling {:?} event and is marked unhealthy: {}",
+                        name, event.event_type, panic
+                    );
+                    self.report(
+                        ErrorReport::new(
+                            ErrorCode::HandlerPanicked,
+                            format!("panicked handling {:?} event: {}", event.event_type, panic),
+                        )
+                        .with_context("handler", name),
+                    );
+                    *registered.unhealthy.lock().unwrap() = Some(panic);
+                    complete = false;
+                    continue;
+                }
+            };
+            if let Err(e) = result {
+                registered.errors.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "Error handling {:?} event in {}: {}",
+                    event.event_type,
+                    name,
+                    e.chain()
+                );
+                self.report(
+                    ErrorReport::new(
+                        ErrorCode::HandlerFailed,
+                        format!("error handling {:?} event: {}", event.event_type, e.chain()),
+                    )
+                    .with_context("handler", name),
+                );
+                complete = false;
+                continue;
+            }
+            if let Some((height, hash)) = block {
+                if let Err(e) = self.checkpointer.record_handler(height, &hash, name) {
+                    warn!(
+                        "Failed to checkpoint {} for block {}: {}",
+                        name,
+                        height,
+                        e.chain()
+                    );
+                    self.report_checkpoint_failure(&e);
+                }
+            }
+        }
+        if !complete {
+            warn!(
+                "{:?} event not checkpointed: not every handler completed it",
+                event.event_type
+            );
+            return false;
+        }
+        if self.replay {
+            return true;
+        }
+        let recorded = match block {
+            Some((height, hash)) => self.checkpointer.record(height, &hash),
+            None => self.checkpointer.record_event(),
+        };
+        if let Err(e) = recorded {
+            warn!(
+                "Failed to checkpoint {:?} event: {}",
+                event.event_type,
+                e.chain()
+            );
+            self.report_checkpoint_failure(&e);
+        }
+        true
+    }
+
+    /// Have every handler undo what it recorded above the fork of `reorg`, then move the
+    /// checkpoint back to the fork. A handler that fails keeps its state; the failure is
+    /// logged, reported and audited like a failure to handle an event.
+    async fn roll_back(&self, reorg: &Reorg, node_api: &dyn NodeAPI) {
+        warn!(
+            "Reorg to height {}: rolling back to height {}",
+            reorg.tip.height, reorg.fork_height
+        );
+        // Last registered first, the reverse of the order events are applied in
+        for registered in self.handlers.iter().rev() {
+            let name = registered.handler.name();
+            if registered.unhealthy.lock().unwrap().is_some() {
+                debug!("Not rolling back unhealthy {}", name);
+                continue;
+            }
+            let result = crate::crash::run_handler(
+                name,
+                self.isolate_panics,
+                registered.handler.roll_back(reorg.fork_height),
+            )
+            .instrument(tracing::debug_span!("handler", handler = name))
+            .await;
+            let error = match result {
+                Ok(Ok(reverted)) => {
+                    if reverted > 0 {
+                        info!(
+                            "{} reverted {} records above height {}",
+                            name, reverted, reorg.fork_height
+                        );
+                    }
+                    if let Some(log) = &self.audit_log {
+                        log.rollback(name, reorg, Some(reverted), None);
+                    }
+                    continue;
+                }
+                Ok(Err(e)) => {
+                    registered.errors.fetch_add(1, Ordering::Relaxed);
+                    warn!("Error rolling back {}: {}", name, e.chain());
+                    self.report(
+                        ErrorReport::new(
+                            ErrorCode::HandlerFailed,
+                            format!("error rolling back: {}", e.chain()),
+                        )
+                        .with_context("handler", name),
+                    );
+                    e.chain().to_string()
+                }
+                Err(panic) => {
+                    registered.panics.fetch_add(1, Ordering::Relaxed);
+                    error!(
+                        "{} panicked rolling back and is marked unhealthy: {}",
+                        name, panic
+                    );
+                    self.report(
+                        ErrorReport::new(
+                            ErrorCode::HandlerPanicked,
+                            format!("panicked rolling back: {}", panic),
+                        )
+                        .with_context("handler", name),
+                    );
+                    *registered.unhealthy.lock().unwrap() = Some(panic.clone());
+                    panic
+                }
+            };
+            if let Some(log) = &self.audit_log {
+                log.rollback(name, reorg, None, Some(error.as_str()));
+            }
+        }
+        if self.replay {
+            return;
+        }
+        // The fork block's hash is its child's parent hash
+        let height = reorg.fork_height + 1;
+        let child = crate::node_api::with_retry(
+            "get_block_by_height",
+            crate::node_api::DEFAULT_REQUEST_TIMEOUT,
+            crate::node_api::RetryPolicy::default(),
+            || node_api.get_block_by_height(height),
+        )
+        .await;
+        let fork_hash = match child {
+            Ok(Some(child)) => child.header.prev_block_hash,
+            Ok(None) => {
+                warn!("No block at height {}; checkpoint not moved back", height);
+                return;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to fetch block {}; checkpoint not moved back: {}",
+                    height,
+                    e.chain()
+                );
+                return;
+            }
+        };
+        if let Err(e) = self.checkpointer.rewind(reorg.fork_height, &fork_hash) {
+            warn!(
+                "Failed to move the checkpoint back to height {}: {}",
+                reorg.fork_height,
+                e.chain()
+            );
+            self.report_checkpoint_failure(&e);
+        }
+    }
+
+    fn report_checkpoint_failure(&self, error: &GovernanceError) {
+        self.report(ErrorReport::new(
+            ErrorCode::CheckpointWriteFailed,
+            error.to_string(),
+        ));
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for AuditLog {
+    fn name(&self) -> &'static str {
+        "audit_log"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        crate::GovernanceModule::event_types().into_iter().collect()
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let ModuleMessage::Event(event) = event else {
+            return Ok(());
+        };
+        let block = match &event.payload {
+            EventPayload::NewBlock { block_hash, .. } if self.records_blocks() => {
+                let fetched = crate::node_api::with_retry(
+                    "get_block",
+                    crate::node_api::DEFAULT_REQUEST_TIMEOUT,
+                    crate::node_api::RetryPolicy::default(),
+                    || node_api.get_block(block_hash),
+                )
+                .await;
+                // The event is recorded without it rather than not at all
+                fetched.unwrap_or_else(|e| {
+                    warn!(
+                        "Failed to fetch block {} for the audit log: {}",
+                        hex::encode(block_hash),
+                        e.chain()
+                    );
+                    None
+                })
+            }
+            _ => None,
+        };
+        self.event_received(event, block.as_ref()).map(|_| ())
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for GovernanceWebhookClient {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        [
+            (EventType::NewBlock, "block"),
+            (EventType::GovernanceProposalCreated, "proposal_created"),
+            (EventType::GovernanceProposalVoted, "proposal_voted"),
+            (EventType::GovernanceProposalMerged, "proposal_merged"),
+        ]
+        .into_iter()
+        .filter(|(_, name)| self.wants(name))
+        .map(|(event_type, _)| event_type)
+        .collect()
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        self.handle_event(event, node_api).await
+    }
+
+    async fn execute_intent(
+        &self,
+        intent: &Intent,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        self.carry_out(intent, node_api).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for EconomicNodeRegistry {
+    fn name(&self) -> &'static str {
+        "economic_nodes"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        vec![
+            EventType::EconomicNodeRegistered,
+            EventType::EconomicNodeVeto,
+            EventType::GovernanceProposalCreated,
+            EventType::GovernanceProposalMerged,
+            EventType::NewBlock,
+        ]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        self.handle_event(event, node_api).await
+    }
+
+    async fn execute_intent(
+        &self,
+        intent: &Intent,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        self.carry_out(intent).await
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.roll_back_to(fork_height).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for ProposalStore {
+    fn name(&self) -> &'static str {
+        "proposals"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        let mut events = vec![
+            EventType::GovernanceProposalCreated,
+            EventType::GovernanceProposalVoted,
+            EventType::GovernanceProposalMerged,
+        ];
+        if self.follows_blocks() {
+            events.push(EventType::NewBlock);
+        }
+        events
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        self.handle_event(event, node_api).await
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.roll_back_to(fork_height)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for DelegationRegistry {
+    fn name(&self) -> &'static str {
+        "delegations"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.reads_chain() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { block_hash, height },
+                ..
+            }) => self.handle_block(block_hash, *height).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.roll_back_to(fork_height)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for SignalingTracker {
+    fn name(&self) -> &'static str {
+        "signaling"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { block_hash, height },
+                ..
+            }) => self.handle_block(block_hash, *height, node_api).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        Ok(self.roll_back_to(fork_height).await)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for AdoptionTracker {
+    fn name(&self) -> &'static str {
+        "adoption"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { block_hash, height },
+                ..
+            }) => self.handle_block(block_hash, *height, node_api).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        Ok(self.roll_back_to(fork_height).await)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for EpochSummarizer {
+    fn name(&self) -> &'static str {
+        "epoch_summary"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { height, .. },
+                ..
+            }) => self.handle_block(*height, node_api).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for ParticipationAnalyzer {
+    fn name(&self) -> &'static str {
+        "participation"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { .. },
+                ..
+            }) => self.update().await.map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.roll_back_to(fork_height).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for Anchorer {
+    fn name(&self) -> &'static str {
+        "anchor"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        _node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { height, .. },
+                ..
+            }) => self.handle_block(*height).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn roll_back(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.roll_back_to(fork_height).await
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for ClockMonitor {
+    fn name(&self) -> &'static str {
+        "clock"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        match event {
+            ModuleMessage::Event(EventMessage {
+                payload: EventPayload::NewBlock { block_hash, height },
+                ..
+            }) => self.check_block(block_hash, *height, node_api).await,
+            _ => Ok(()),
+        }
+    }
+}
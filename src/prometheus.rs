@@ -0,0 +1,410 @@
+//! Prometheus text exposition of the module's metrics
+//!
+//! Served on `GET /metrics` by the health listener (see [`crate::health`]). The values come
+//! from the counters the module already keeps: [`IpcMetrics`] for node requests and received
+//! events, which lasts for the process, the [`ModuleStatus`] for the labels of `info`, and the
+//! [`StatusSources`] of the current connection for the event queue, memory use, webhook
+//! deliveries and the registry. Those are only exposed while connected, and start over on
+//! each connection; Prometheus treats that as a counter reset.
+//!
+//! Every name starts with `bllvm_governance_` and is stable once released. Labels only take
+//! values from fixed sets (event type, node method, outcome), never ids other than those
+//! configured:
+//!
+//! | Metric | Type | Labels |
+//! |---|---|---|
+//! | `info` | gauge | `schema_version`, and each [`ModuleStatus::info`] entry (`version`, `git_commit`) |
+//! | `connected` | gauge | |
+//! | `events_received_total` | counter | `event_type` |
+//! | `node_requests_total` | counter | `method`, `outcome` (ok, error, timeout) |
+//! | `node_request_duration_seconds` | histogram | `method` |
+//! | `events_processed_total` | counter | |
+//! | `event_queue_depth`, `event_queue_capacity` | gauge | |
+//! | `events_dropped_total` | counter | |
+//! | `events_spilled` | gauge | |
+//! | `memory_used_bytes`, `memory_limit_bytes` | gauge | `component` (see [`crate::memory`]) |
+//! | `memory_overflow_total` | counter | `component` |
+//! | `webhook_deliveries_total` | counter | `outcome` (delivered, failed, dry_run) |
+//! | `webhook_consecutive_failures` | gauge | |
+//! | `registry_nodes` | gauge | |
+//! | `heartbeat_misses` | gauge | |
+//! | `last_block_height` | gauge | |
+//! | `clock_skew_seconds`, `clock_skewed` | gauge | |
+//! | `error_reports_total` | counter | `code` (see [`crate::error_report`]) |
+//! | `adoption_percent` | gauge | `proposal_id` (configured proposals only), `measure` (blocks, nodes); see [`crate::adoption`] |
+//! | `participation_rate` | gauge | |
+
+use crate::error_report::ErrorCode;
+use crate::ipc_metrics::{IpcMethod, IpcMetrics};
+use crate::status::ModuleStatus;
+use crate::status_report::StatusSources;
+use std::fmt::{Display, Write};
+use tracing::warn;
+
+/// Prefix of every metric name.
+pub const PREFIX: &str = "bllvm_governance_";
+
+/// Content type of [`render`]'s output.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Builds the exposition one metric family at a time.
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {}{} {}", PREFIX, name, help);
+        let _ = writeln!(self.0, "# TYPE {}{} {}", PREFIX, name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let _ = write!(self.0, "{}{}", PREFIX, name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+
+    /// A metric family with a single unlabelled sample.
+    fn single(&mut self, name: &str, kind: &str, help: &str, value: impl Display) {
+        self.family(name, kind, help);
+        self.sample(name, &[], value);
+    }
+}
+
+/// A label value with backslashes, quotes and newlines escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The metrics of `metrics`, the info metric of `status` and, while connected, the metrics of
+/// `sources`.
+pub async fn render(
+    metrics: &IpcMetrics,
+    sources: Option<&StatusSources>,
+    status: Option<&ModuleStatus>,
+) -> String {
+    let mut out = Exposition::default();
+    if let Some(status) = status {
+        let schema_version = status.schema_version.to_string();
+        let mut labels = vec![("schema_version", schema_version.as_str())];
+        labels.extend(status.info.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        out.family(
+            "info",
+            "gauge",
+            "Always 1; the labels identify the running module.",
+        );
+        out.sample("info", &labels, 1);
+    }
+    out.single(
+        "connected",
+        "gauge",
+        "Whether the module is connected to the node.",
+        u8::from(sources.is_some()),
+    );
+
+    let snapshot = metrics.snapshot();
+    out.family(
+        "events_received_total",
+        "counter",
+        "Events received from the node, by type.",
+    );
+    for (event_type, count) in &snapshot.events {
+        out.sample(
+            "events_received_total",
+            &[("event_type", *event_type)],
+            count,
+        );
+    }
+
+    out.family(
+        "node_requests_total",
+        "counter",
+        "Requests to the node, by method and outcome.",
+    );
+    for (method, m) in &snapshot.requests {
+        for (outcome, count) in [("ok", m.ok), ("error", m.errors), ("timeout", m.timeouts)] {
+            out.sample(
+                "node_requests_total",
+                &[("method", *method), ("outcome", outcome)],
+                count,
+            );
+        }
+    }
+
+    let name = "node_request_duration_seconds";
+    out.family(
+        name,
+        "histogram",
+        "Time to answer requests to the node that did not time out, by method.",
+    );
+    for method in IpcMethod::ALL {
+        let latency = metrics.latency_buckets(method);
+        let labels = [("method", method.as_str())];
+        let mut seen = 0;
+        for (bound, count) in latency.bounds_ms.iter().zip(&latency.counts) {
+            seen += count;
+            let le = format!("{}", *bound as f64 / 1000.0);
+            out.sample(
+                &format!("{}_bucket", name),
+                &[labels[0], ("le", le.as_str())],
+                seen,
+            );
+        }
+        let total: u64 = latency.counts.iter().sum();
+        out.sample(
+            &format!("{}_bucket", name),
+            &[labels[0], ("le", "+Inf")],
+            total,
+        );
+        out.sample(
+            &format!("{}_sum", name),
+            &labels,
+            latency.sum_ms as f64 / 1000.0,
+        );
+        out.sample(&format!("{}_count", name), &labels, total);
+    }
+
+    let Some(sources) = sources else {
+        return out.0;
+    };
+    let queue = sources.events.stats();
+    out.single(
+        "events_processed_total",
+        "counter",
+        "Events fully processed on the current connection.",
+        sources.checkpointer.sequence(),
+    );
+    out.single(
+        "event_queue_depth",
+        "gauge",
+        "Events waiting in the event queue.",
+        queue.queued,
+    );
+    out.single(
+        "event_queue_capacity",
+        "gauge",
+        "Capacity of the event queue.",
+        queue.capacity,
+    );
+    out.single(
+        "events_dropped_total",
+        "counter",
+        "Events dropped because the event queue was full.",
+        queue.dropped,
+    );
+    out.single(
+        "events_spilled",
+        "gauge",
+        "Events in the spill file, waiting for memory in the event queue.",
+        queue.spilled,
+    );
+
+    let memory = sources.memory.usage();
+    out.family(
+        "memory_used_bytes",
+        "gauge",
+        "Approximate memory held by each queue and cache.",
+    );
+    for (component, usage) in &memory {
+        out.sample(
+            "memory_used_bytes",
+            &[("component", component)],
+            usage.used_bytes,
+        );
+    }
+    out.family(
+        "memory_limit_bytes",
+        "gauge",
+        "Memory budget share of each queue and cache; 0 is unlimited.",
+    );
+    for (component, usage) in &memory {
+        out.sample(
+            "memory_limit_bytes",
+            &[("component", component)],
+            usage.limit_bytes,
+        );
+    }
+    out.family(
+        "memory_overflow_total",
+        "counter",
+        "Entries evicted, or events spilled to disk, to stay within the memory budget.",
+    );
+    for (component, usage) in &memory {
+        out.sample(
+            "memory_overflow_total",
+            &[("component", component)],
+            usage.overflowed,
+        );
+    }
+
+    let deliveries = sources.webhook.delivery_counts();
+    out.family(
+        "webhook_deliveries_total",
+        "counter",
+        "Webhook deliveries, after retries, by outcome; dry_run counts payloads logged instead of sent.",
+    );
+    out.sample(
+        "webhook_deliveries_total",
+        &[("outcome", "delivered")],
+        deliveries.delivered,
+    );
+    out.sample(
+        "webhook_deliveries_total",
+        &[("outcome", "failed")],
+        deliveries.failed,
+    );
+    out.sample(
+        "webhook_deliveries_total",
+        &[("outcome", "dry_run")],
+        deliveries.dry_run,
+    );
+    out.single(
+        "webhook_consecutive_failures",
+        "gauge",
+        "Webhook deliveries failed since the last successful one.",
+        sources.webhook.consecutive_failures(),
+    );
+
+    out.single(
+        "registry_nodes",
+        "gauge",
+        "Economic nodes in the registry.",
+        sources.registry.node_count().await,
+    );
+    out.single(
+        "heartbeat_misses",
+        "gauge",
+        "Consecutive unanswered heartbeats on the current connection.",
+        sources.heartbeat.status().consecutive_misses,
+    );
+    if let Some(checkpoint) = sources.checkpointer.last() {
+        out.single(
+            "last_block_height",
+            "gauge",
+            "Height of the last fully processed block.",
+            checkpoint.height,
+        );
+    }
+    let clock = sources.clock.status();
+    if let Some(skew) = clock.skew_secs {
+        out.single(
+            "clock_skew_seconds",
+            "gauge",
+            "Seconds the local clock is ahead of block timestamps (negative: behind).",
+            skew,
+        );
+    }
+    out.single(
+        "clock_skewed",
+        "gauge",
+        "Whether the local clock is skewed beyond the block timestamp slack.",
+        u8::from(clock.skewed),
+    );
+    let reported = sources.errors.counts();
+    out.family(
+        "error_reports_total",
+        "counter",
+        "Occurrences of significant errors reported to the node, by error code.",
+    );
+    for code in ErrorCode::ALL {
+        out.sample(
+            "error_reports_total",
+            &[("code", code.as_str())],
+            reported.get(code.as_str()).copied().unwrap_or(0),
+        );
+    }
+    if let Some(adoption) = &sources.adoption {
+        let levels = adoption.levels().await;
+        if !levels.is_empty() {
+            out.family(
+                "adoption_percent",
+                "gauge",
+                "Adoption of activated proposals over the configured window, in percent.",
+            );
+        }
+        for level in levels {
+            out.sample(
+                "adoption_percent",
+                &[
+                    ("proposal_id", &level.proposal_id),
+                    ("measure", level.measure.as_str()),
+                ],
+                level.percent,
+            );
+        }
+    }
+    let participation = sources
+        .participation
+        .as_ref()
+        .filter(|participation| participation.is_enabled());
+    if let Some(participation) = participation {
+        match participation.participation() {
+            Ok(participation) => {
+                if let Some(rate) = participation.rate() {
+                    out.single(
+                        "participation_rate",
+                        "gauge",
+                        "Share of eligible votes cast on the proposals closed so far.",
+                        rate,
+                    );
+                }
+            }
+            Err(e) => warn!("Failed to read participation for metrics: {}", e.chain()),
+        }
+    }
+    out.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc_metrics::Outcome;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_render_without_connection() {
+        let metrics = IpcMetrics::new();
+        metrics.record_request(IpcMethod::GetBlock, Duration::from_millis(3), Outcome::Ok);
+        let text = render(&metrics, None, None).await;
+
+        assert!(text
+            .contains("# TYPE bllvm_governance_connected gauge\nbllvm_governance_connected 0\n"));
+        assert!(text.contains(
+            "bllvm_governance_node_requests_total{method=\"get_block\",outcome=\"ok\"} 1\n"
+        ));
+        assert!(text.contains(
+            "bllvm_governance_node_request_duration_seconds_bucket{method=\"get_block\",le=\"0.002\"} 0\n"
+        ));
+        assert!(text.contains(
+            "bllvm_governance_node_request_duration_seconds_bucket{method=\"get_block\",le=\"0.005\"} 1\n"
+        ));
+        assert!(text.contains(
+            "bllvm_governance_node_request_duration_seconds_sum{method=\"get_block\"} 0.003\n"
+        ));
+        assert!(!text.contains("registry_nodes"));
+        assert!(!text.contains("bllvm_governance_info"));
+        let status = ModuleStatus {
+            schema_version: 2,
+            uptime_secs: 0,
+            connected: false,
+            info: [("version".to_string(), "0.1.0 \"beta\"".to_string())].into(),
+            sections: Default::default(),
+        };
+        let text = render(&metrics, None, Some(&status)).await;
+        assert!(text.contains(
+            "bllvm_governance_info{schema_version=\"2\",version=\"0.1.0 \\\"beta\\\"\"} 1\n"
+        ));
+        // Every sample line is prefixed
+        assert!(text
+            .lines()
+            .all(|l| l.starts_with('#') || l.starts_with(PREFIX)));
+    }
+}
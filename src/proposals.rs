@@ -1,160 +1,1959 @@
 //! Governance proposal storage
 //!
-//! Stores proposals when receiving GovernanceProposal* events so list-proposals can display them.
+//! Tracks each proposal through its lifecycle, from the node's GovernanceProposal* events and
+//! its proposal records:
+//!
+//! ```text
+//! Created ──vote──> Voting ──> Merged | Rejected | Expired
+//!    └─────────────────────────┘
+//! ```
+//!
+//! A proposal is recorded with its tier, author, created height, votes and, once it reaches a
+//! terminal state, the height it did. The node publishes events for creation, votes and merges
+//! only; the author, the created height and rejections or expiries come from
+//! [`NodeApiIpc::get_proposal`] when the store has a client for it
+//! ([`ProposalStore::with_node_api`]), looked up on a proposal's created event and by
+//! [`ProposalStore::refresh`]. A terminal state is final: later votes and merges are ignored.
+//!
+//! Events can arrive out of order. A vote or merge for a proposal the store has not seen is
+//! held, stored with the proposals so it survives a restart, until the proposal's created
+//! event arrives or a lookup of it succeeds, and is then applied in the order it arrived.
+//!
+//! Proposals of tiers with a voting window have a deadline ([`ProposalStore::deadline`]); as
+//! blocks arrive, reminders and `voting_closed` are sent for open ones (see
+//! [`crate::deadlines`]), and the reminders sent are stored with the proposals. Merged
+//! proposals of tiers with an activation delay are followed to their activation height the
+//! same way ([`ProposalStore::upcoming_activations`], see [`crate::activation`]); a reorg
+//! below the merge height reopens the proposal.
+//!
+//! The number of votes applied at each chain tip is kept as well, for epoch summaries
+//! ([`ProposalStore::votes_between`], see [`crate::epoch_summary`]).
+//!
+//! With a delegation registry ([`ProposalStore::with_delegations`]), each proposal's raw and
+//! delegated tallies are stored ([`ProposalStore::tallies`]) and the thresholds measured
+//! against the delegated one; those of open proposals are recomputed on every block and by
+//! [`ProposalStore::update_tallies`], so changed delegations are counted (see
+//! [`crate::delegation`]).
+//!
+//! With the economic node registry ([`ProposalStore::with_registry`]) and `tally.weights` set,
+//! each proposal's votes are weighted by their voters' registry weights as well
+//! ([`ProposalStore::weighted_tally`]), recomputed on every vote and every block, and tiers
+//! with weight-based thresholds reach their milestones by weight (see [`crate::tally`]).
+//!
+//! Votes applied in the last [`ROLLBACK_DEPTH`] blocks are journaled with the vote they
+//! replaced, so that when the pipeline rolls back a reorg ([`ProposalStore::roll_back_to`])
+//! the votes applied above the fork are undone, and merges recorded above it too, along with
+//! the tally milestones reached above it, in one write. A merge whose activation is followed
+//! is undone, and its activation cancelled, by the next block as above.
+//!
+//! Pull request events received from GitHub link proposals to their pull requests, and
+//! without `allow_actions` record merges and closes as well
+//! ([`ProposalStore::link_pull_request`], see [`crate::github`]).
+//!
+//! With a content verifier ([`ProposalStore::with_content_verifier`]), the content of each
+//! created proposal is checked against its on-chain commitment (see [`crate::content`]). The
+//! outcome is stored with the proposals ([`ProposalStore::content_check`]) and carried as
+//! `content_verified` by the tally, deadline and activation notifications; a mismatch is sent
+//! as `content_mismatch`.
+//!
+//! Proposals declaring a conflict domain or the proposals they conflict with, in the node's
+//! record of them, are kept in a conflict graph: conflicts with open and recently merged
+//! proposals are flagged, carried as `conflicts` by the tally, deadline and activation
+//! notifications, alerted on as `proposal_conflict` when both sides are merged, and cleared
+//! when one side is rejected or expired ([`ProposalStore::conflicts`], see
+//! [`crate::conflicts`]).
+//!
+//! Each vote is checked against the voter's counted one, stored with its height: the same
+//! vote again is dropped, and with `tally.vote_conflict_window_blocks` set a different one
+//! cast too close to it is resolved by [`vote_check::later_wins`], written to the audit log
+//! ([`ProposalStore::with_audit_log`]) and sent as `vote_conflict`. Both are counted
+//! ([`ProposalStore::vote_checks`], see [`crate::vote_check`]).
+//!
+//! A proposal's content hash and tier are compared with the node's record of it whenever it
+//! is looked up, and open proposals are looked up again every
+//! `amendments.refresh_interval_blocks` blocks ([`ProposalStore::refresh_open`]). A change is
+//! an amendment: a new content hash is checked again, a new tier takes its thresholds and
+//! voting window, and votes are kept or dropped by `amendments.votes`. Amendments are stored
+//! with the proposals ([`ProposalStore::amendments`]) and sent as `proposal_amended`; once
+//! voting has closed they are rejected and sent as `amendment_rejected` (see
+//! [`crate::amendments`]).
+//!
+//! With a feed ([`ProposalStore::with_feed`]), proposals newly stored, or newly stored as
+//! merged, and the `voting_closed` and `proposal_activated` notifications are added to it
+//! (see [`crate::feed`]).
+//!
+//! Queries: [`ProposalStore::proposal`] and [`ProposalStore::open_proposals`]; list-proposals,
+//! the `get_proposals` API and the CLI read them all.
 
+use crate::activation::{self, Activation};
+use crate::amendments::{self, Amendment, Amendments};
+use crate::audit_log::AuditLog;
+use crate::config::{
+    ActivationConfig, AmendmentConfig, ConflictConfig, DeadlineConfig, TallyConfig, WeightSource,
+};
+use crate::conflicts::{self, ConflictGraph, Declaration};
+use crate::content::{self, ContentCheck, ContentStatus, ContentVerifier};
+use crate::deadlines::{self, Reminders};
+use crate::delegation::DelegationRegistry;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::feed::Feed;
+use crate::github::{PullRequestSync, Transition};
+use crate::node_api::{NodeApiIpc, ProposalDetails};
+use crate::tally::{
+    self, MilestoneReached, RegistryTally, Tallies, Tally, VoterWeights, Weighting,
+};
+use crate::vote_check::{self, Ballot, Verdict, VoteChecks, VoteConflict};
+use crate::webhook::GovernanceWebhookClient;
 use blvm_node::module::ipc::protocol::{EventPayload, ModuleMessage};
-use blvm_node::module::EventType;
+use blvm_node::module::traits::NodeAPI;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
 
 const PROPOSALS_TREE: &str = "proposals";
-use std::collections::HashMap;
-use std::sync::Arc;
 
 const STORAGE_KEY: &[u8] = b"proposals";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Key of the events held for proposals the store has not seen.
+const PENDING_KEY: &[u8] = b"pending";
+
+/// Key of the deadline reminders sent for open proposals.
+const REMINDERS_KEY: &[u8] = b"reminders";
+
+/// Key of the activations of merged proposals.
+const ACTIVATIONS_KEY: &[u8] = b"activations";
+
+/// Key of the number of votes applied, by height.
+const VOTES_KEY: &[u8] = b"votes";
+
+/// Key of the content checks of proposals.
+const CONTENT_KEY: &[u8] = b"content";
+
+/// Key of the votes kept for rollback.
+const JOURNAL_KEY: &[u8] = b"journal";
+
+/// Key of the raw and delegated tallies of proposals.
+const TALLIES_KEY: &[u8] = b"tallies";
+
+/// Key of the weighted tallies of proposals.
+const WEIGHTED_KEY: &[u8] = b"weighted";
+
+/// Key of the conflict graph.
+const CONFLICTS_KEY: &[u8] = b"conflicts";
+
+/// Key of the counted votes of voters, with their heights.
+const BALLOTS_KEY: &[u8] = b"ballots";
+
+/// Key of the counts of duplicate and conflicting votes.
+const VOTE_CHECKS_KEY: &[u8] = b"vote_checks";
+
+/// Key of the amendment histories and content hashes of proposals.
+const AMENDMENTS_KEY: &[u8] = b"amendments";
+
+/// Blocks below the chain tip whose votes are kept for rollback after a reorg.
+pub const ROLLBACK_DEPTH: u64 = 144;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GovernanceProposal {
     pub proposal_id: String,
+    /// Empty, like `pr_number`, for a proposal known from a lookup only, until its created or
+    /// merge event arrives.
     pub repository: String,
     pub pr_number: u64,
     pub tier: String,
     pub status: ProposalStatus,
     pub votes: Vec<ProposalVote>,
+    /// From the node's record of the proposal.
+    pub author: Option<String>,
+    /// From the node's record, or the chain tip when its created event arrived.
+    pub created_height: Option<u64>,
+    /// Chain tip when the proposal reached its terminal state.
+    pub closed_height: Option<u64>,
+    /// Tally milestones reached, in order (see [`crate::tally`]).
+    pub milestones: Vec<MilestoneReached>,
+}
+
+impl GovernanceProposal {
+    fn new(proposal_id: &str, tier: &str, height: Option<u64>) -> Self {
+        Self {
+            proposal_id: proposal_id.to_string(),
+            repository: String::new(),
+            pr_number: 0,
+            tier: tier.to_string(),
+            status: ProposalStatus::Created,
+            votes: Vec::new(),
+            author: None,
+            created_height: height,
+            closed_height: None,
+            milestones: Vec::new(),
+        }
+    }
+
+    /// Whether the proposal is yet to be merged, rejected or expired.
+    pub fn is_open(&self) -> bool {
+        !self.status.is_terminal()
+    }
+
+    /// Votes by choice, one per voter.
+    pub fn tally(&self) -> Tally {
+        Tally::of(&self.votes)
+    }
+
+    /// Number of votes by value, e.g. `"yes"`.
+    pub fn vote_counts(&self) -> BTreeMap<String, u64> {
+        let mut counts = BTreeMap::new();
+        for vote in &self.votes {
+            *counts.entry(vote.vote.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Take the author, created height and any terminal state from the node's record. A new
+    /// tier is an amendment (see [`ProposalStore::amend`]).
+    fn update_from(&mut self, details: &ProposalDetails, height: Option<u64>) {
+        self.author = Some(details.author.clone());
+        self.created_height = Some(details.created_height);
+        match ProposalStatus::from_node(&details.status) {
+            Some(status) if status.is_terminal() && self.is_open() => {
+                self.status = status;
+                self.closed_height = height;
+            }
+            _ => {}
+        }
+    }
+
+    /// Undo the merge, after a reorg removed the block it was recorded at.
+    fn unmerge(&mut self) {
+        self.status = if self.votes.is_empty() {
+            ProposalStatus::Created
+        } else {
+            ProposalStatus::Voting
+        };
+        self.closed_height = None;
+    }
+
+    /// Apply `event`, unless the proposal is closed. Returns whether it was applied.
+    fn apply(&mut self, event: PendingEvent) -> bool {
+        if !self.is_open() {
+            warn!(
+                "Ignoring {} for proposal {}, already {}",
+                event.name(),
+                self.proposal_id,
+                self.status.as_str()
+            );
+            return false;
+        }
+        match event {
+            PendingEvent::Voted { voter, vote } => {
+                // A voter's later vote replaces the earlier one
+                match self.votes.iter_mut().find(|v| v.voter == voter) {
+                    Some(earlier) => earlier.vote = vote,
+                    None => self.votes.push(ProposalVote { voter, vote }),
+                }
+                self.status = ProposalStatus::Voting;
+            }
+            PendingEvent::Merged {
+                repository,
+                pr_number,
+                height,
+            } => {
+                self.repository = repository;
+                self.pr_number = pr_number;
+                self.status = ProposalStatus::Merged;
+                self.closed_height = height;
+            }
+        }
+        true
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProposalStatus {
     Created,
+    /// Voted on at least once.
+    Voting,
     Merged,
+    Rejected,
+    Expired,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ProposalStatus {
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Self::Merged | Self::Rejected | Self::Expired)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Voting => "voting",
+            Self::Merged => "merged",
+            Self::Rejected => "rejected",
+            Self::Expired => "expired",
+        }
+    }
+
+    /// The state of a [`ProposalDetails::status`], if it names one.
+    fn from_node(status: &str) -> Option<Self> {
+        match status.to_ascii_lowercase().as_str() {
+            "open" | "created" => Some(Self::Created),
+            "voting" => Some(Self::Voting),
+            "merged" => Some(Self::Merged),
+            "rejected" | "closed" => Some(Self::Rejected),
+            "expired" => Some(Self::Expired),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProposalVote {
     pub voter: String,
     pub vote: String,
 }
 
+/// An event for a proposal the store has not seen yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum PendingEvent {
+    Voted {
+        voter: String,
+        vote: String,
+    },
+    Merged {
+        repository: String,
+        pr_number: u64,
+        /// Chain tip when the event arrived.
+        height: Option<u64>,
+    },
+}
+
+impl PendingEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Voted { .. } => "vote",
+            Self::Merged { .. } => "merge",
+        }
+    }
+}
+
+/// A vote applied at a known height, kept for rollback.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AppliedVote {
+    height: u64,
+    proposal_id: String,
+    voter: String,
+    /// The voter's vote it replaced, if any.
+    previous: Option<String>,
+}
+
+/// A proposal with everything the store keeps about it, read at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProposalView {
+    pub proposal: GovernanceProposal,
+    pub tallies: Option<Tallies>,
+    pub weighted: Option<RegistryTally>,
+    pub content: Option<ContentCheck>,
+    /// Whether the content matched its commitment, as notifications carry it.
+    pub content_verified: Option<bool>,
+    /// Ids of the proposals it conflicts with.
+    pub conflicts: Vec<String>,
+    /// Its amendments, oldest first.
+    pub amendments: Vec<Amendment>,
+}
+
+/// Every proposal with its tallies, and the activations followed, read at once.
+#[derive(Debug, Clone, Default)]
+pub struct StoreSnapshot {
+    pub proposals: Vec<GovernanceProposal>,
+    /// Tallies as last computed, by proposal id.
+    pub tallies: HashMap<String, Tallies>,
+    pub activations: Vec<Activation>,
+}
+
+/// Everything the store keeps.
+#[derive(Debug, Default)]
+struct State {
+    proposals: HashMap<String, GovernanceProposal>,
+    pending: HashMap<String, Vec<PendingEvent>>,
+    reminders: HashMap<String, Reminders>,
+    activations: HashMap<String, Activation>,
+    /// Votes applied, by the chain tip when they were.
+    votes: BTreeMap<u64, u64>,
+    content: HashMap<String, ContentCheck>,
+    /// Votes applied in the last [`ROLLBACK_DEPTH`] blocks, oldest first.
+    journal: Vec<AppliedVote>,
+    tallies: HashMap<String, Tallies>,
+    weighted: HashMap<String, RegistryTally>,
+    conflicts: ConflictGraph,
+    /// Counted votes by proposal and voter.
+    ballots: HashMap<String, HashMap<String, Ballot>>,
+    vote_checks: VoteChecks,
+    amendments: Amendments,
+    /// `tally.vote_conflict_window_blocks`; not stored.
+    vote_window: Option<u64>,
+    /// Vote conflicts found since the state was loaded, to alert on; not stored.
+    vote_conflicts: Vec<VoteConflict>,
+}
+
+impl State {
+    /// `content_verified` of notifications about `proposal_id`.
+    fn content_verified(&self, proposal_id: &str) -> Option<bool> {
+        self.content
+            .get(proposal_id)
+            .and_then(ContentCheck::verified)
+    }
+}
+
+impl State {
+    /// Record the conflicts `details` declares with the open proposals and those merged
+    /// within `window` blocks of `height`, flagging the new ones. A closed proposal declares
+    /// nothing.
+    fn declare_conflicts(&mut self, details: &ProposalDetails, height: Option<u64>, window: u64) {
+        let id = &details.proposal_id;
+        let closed = |p: &GovernanceProposal| {
+            matches!(p.status, ProposalStatus::Rejected | ProposalStatus::Expired)
+        };
+        if self.proposals.get(id).is_some_and(closed) {
+            return;
+        }
+        let candidates: Vec<String> = self
+            .proposals
+            .values()
+            .filter(|p| match (p.status, p.closed_height, height) {
+                (ProposalStatus::Merged, Some(merged), Some(height)) => {
+                    height.saturating_sub(merged) <= window
+                }
+                _ => !closed(p),
+            })
+            .map(|p| p.proposal_id.clone())
+            .collect();
+        for conflict in self.conflicts.declare(id, Declaration::of(details), &candidates, height) {
+            warn!(
+                "Proposal {} conflicts with proposal {} ({:?})",
+                id,
+                conflict.partner(id).unwrap_or_default(),
+                conflict.reason
+            );
+        }
+    }
+
+    /// Clear the conflicts of proposals rejected or expired.
+    fn clear_resolved_conflicts(&mut self) {
+        let proposals = &self.proposals;
+        let resolved: Vec<String> = self
+            .conflicts
+            .conflicts
+            .iter()
+            .flat_map(|c| c.proposals.iter())
+            .filter(|id| {
+                proposals.get(*id).is_some_and(|p| {
+                    matches!(p.status, ProposalStatus::Rejected | ProposalStatus::Expired)
+                })
+            })
+            .cloned()
+            .collect();
+        for id in resolved {
+            let cleared = self.conflicts.clear(&id);
+            if cleared > 0 {
+                info!("Proposal {} closed, clearing {} conflicts", id, cleared);
+            }
+        }
+    }
+
+    /// Apply `event` to `proposal_id`, if it is known, counting a vote at `height` unless it
+    /// is a duplicate or loses a conflict.
+    fn apply(&mut self, proposal_id: &str, event: PendingEvent, height: Option<u64>) {
+        let Some(proposal) = self.proposals.get_mut(proposal_id) else {
+            return;
+        };
+        if let (PendingEvent::Voted { voter, vote }, true) = (&event, proposal.is_open()) {
+            // Votes counted before ballots were stored have no known height
+            let earlier = self
+                .ballots
+                .get(proposal_id)
+                .and_then(|ballots| ballots.get(voter))
+                .cloned()
+                .or_else(|| {
+                    proposal
+                        .votes
+                        .iter()
+                        .find(|v| v.voter == *voter)
+                        .map(|v| Ballot {
+                            vote: v.vote.clone(),
+                            height: None,
+                        })
+                });
+            let ballot = Ballot {
+                vote: vote.clone(),
+                height,
+            };
+            let window = self.vote_window;
+            match vote_check::check(proposal_id, voter, earlier.as_ref(), &ballot, window) {
+                Verdict::Duplicate => {
+                    debug!(
+                        "Dropping duplicate vote of {} on proposal {}",
+                        voter, proposal_id
+                    );
+                    self.vote_checks.duplicates_dropped += 1;
+                    return;
+                }
+                Verdict::Conflict { conflict, counted } => {
+                    self.vote_checks.conflicts_resolved += 1;
+                    self.vote_conflicts.push(conflict);
+                    if !counted {
+                        return;
+                    }
+                }
+                Verdict::New | Verdict::Replaces => {}
+            }
+            self.ballots
+                .entry(proposal_id.to_string())
+                .or_default()
+                .insert(voter.clone(), ballot);
+        }
+        let applied = match (&event, height) {
+            (PendingEvent::Voted { voter, .. }, Some(height)) => Some(AppliedVote {
+                height,
+                proposal_id: proposal_id.to_string(),
+                voter: voter.clone(),
+                previous: proposal
+                    .votes
+                    .iter()
+                    .find(|v| v.voter == *voter)
+                    .map(|v| v.vote.clone()),
+            }),
+            _ => None,
+        };
+        if !proposal.apply(event) {
+            return;
+        }
+        if let Some(applied) = applied {
+            *self.votes.entry(applied.height).or_default() += 1;
+            let oldest = applied.height.saturating_sub(ROLLBACK_DEPTH);
+            self.journal.retain(|v| v.height >= oldest);
+            self.journal.push(applied);
+        }
+    }
+
+    /// Undo the votes and merges recorded above `fork_height`. Returns how many were undone.
+    fn roll_back_to(&mut self, fork_height: u64) -> u64 {
+        let mut reverted = 0;
+        for proposal in self.proposals.values_mut() {
+            // Merges followed to activation are undone by the next block, which sends the
+            // cancellation (see `ProposalStore::follow_activations`)
+            if proposal.status == ProposalStatus::Merged
+                && proposal.closed_height.is_some_and(|h| h > fork_height)
+                && !self.activations.contains_key(&proposal.proposal_id)
+            {
+                warn!(
+                    "Reorg to height {} undid the merge of proposal {}",
+                    fork_height, proposal.proposal_id
+                );
+                proposal.unmerge();
+                reverted += 1;
+            }
+        }
+        let (undone, kept): (Vec<AppliedVote>, Vec<AppliedVote>) =
+            std::mem::take(&mut self.journal)
+                .into_iter()
+                .partition(|v| v.height > fork_height);
+        self.journal = kept;
+        // Newest first, so each vote replaced above the fork is restored in turn
+        for applied in undone.into_iter().rev() {
+            let Some(proposal) = self.proposals.get_mut(&applied.proposal_id) else {
+                continue;
+            };
+            let index = proposal.votes.iter().position(|v| v.voter == applied.voter);
+            let ballots = self.ballots.entry(applied.proposal_id.clone()).or_default();
+            match (index, applied.previous) {
+                (Some(index), Some(previous)) => {
+                    ballots.insert(
+                        applied.voter.clone(),
+                        Ballot {
+                            vote: previous.clone(),
+                            height: None,
+                        },
+                    );
+                    proposal.votes[index].vote = previous;
+                }
+                (Some(index), None) => {
+                    ballots.remove(&applied.voter);
+                    proposal.votes.remove(index);
+                }
+                (None, _) => continue,
+            }
+            if proposal.is_open() && proposal.votes.is_empty() {
+                proposal.status = ProposalStatus::Created;
+            }
+            reverted += 1;
+        }
+        for proposal in self.proposals.values_mut() {
+            proposal
+                .milestones
+                .retain(|m| m.height.is_none_or(|h| h <= fork_height));
+        }
+        self.votes.split_off(&(fork_height + 1));
+        reverted
+    }
+
+    /// Apply the events held for `proposal_id`, now that it is known at `height`.
+    fn release(&mut self, proposal_id: &str, height: Option<u64>) {
+        if !self.proposals.contains_key(proposal_id) {
+            return;
+        }
+        let Some(events) = self.pending.remove(proposal_id) else {
+            return;
+        };
+        debug!(
+            "Applying {} held events to proposal {}",
+            events.len(),
+            proposal_id
+        );
+        for event in events {
+            self.apply(proposal_id, event, height);
+        }
+    }
+}
+
+/// A tally milestone, deadline or activation notification to send to the webhook, its intent recorded.
+struct Announcement {
+    event_type: &'static str,
+    data: serde_json::Value,
+    intent: Option<u64>,
+}
+
 /// Proposal store backed by module DB
 pub struct ProposalStore {
     db: Arc<dyn blvm_node::storage::database::Database>,
+    /// Client for proposal records and the chain tip, if lookups are made.
+    node_api: Option<NodeApiIpc>,
+    /// Thresholds of the tally milestones.
+    tally: TallyConfig,
+    /// Voting windows and reminder thresholds.
+    deadlines: DeadlineConfig,
+    /// Activation delays and the countdown interval.
+    activation: ActivationConfig,
+    /// Where tally milestones, deadline and activation notifications are sent.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    /// Checks the content of created proposals, if configured.
+    content: Option<Arc<ContentVerifier>>,
+    /// Delegations counted in tallies, if tracked.
+    delegations: Option<Arc<DelegationRegistry>>,
+    /// Registry whose weights votes carry, if weighted.
+    registry: Option<Arc<EconomicNodeRegistry>>,
+    /// Window of conflicts with merged proposals.
+    conflicts: ConflictConfig,
+    /// Where vote conflicts are recorded, if anywhere.
+    audit_log: Option<Arc<AuditLog>>,
+    /// Where proposal activity is added for feed readers, if anywhere.
+    feed: Option<Arc<Feed>>,
+    /// How open proposals are looked up again, and the votes kept when they are amended.
+    amendment: AmendmentConfig,
+    /// Held across each read, change and write of the stored state.
+    update: Mutex<()>,
+    /// Writes of the stored state since the store was opened.
+    revision: AtomicU64,
 }
 
 impl ProposalStore {
     pub fn new(db: Arc<dyn blvm_node::storage::database::Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            node_api: None,
+            tally: TallyConfig::default(),
+            deadlines: DeadlineConfig::default(),
+            activation: ActivationConfig::default(),
+            webhook: None,
+            content: None,
+            delegations: None,
+            registry: None,
+            conflicts: ConflictConfig::default(),
+            audit_log: None,
+            feed: None,
+            amendment: AmendmentConfig::default(),
+            update: Mutex::new(()),
+            revision: AtomicU64::new(0),
+        }
+    }
+
+    /// Look proposals up with `node_api`, and take heights from its chain tip.
+    pub fn with_node_api(mut self, node_api: NodeApiIpc) -> Self {
+        self.node_api = Some(node_api);
+        self
+    }
+
+    /// Record the milestones of proposal tallies under the thresholds of `config` (see
+    /// [`crate::tally`]).
+    pub fn with_tally(mut self, config: TallyConfig) -> Self {
+        self.tally = config;
+        self
+    }
+
+    /// Track voting deadlines under the windows of `config`, and send its reminders (see
+    /// [`crate::deadlines`]).
+    pub fn with_deadlines(mut self, config: DeadlineConfig) -> Self {
+        self.deadlines = config;
+        self
+    }
+
+    /// Follow merged proposals to activation under the delays of `config` (see
+    /// [`crate::activation`]).
+    pub fn with_activation(mut self, config: ActivationConfig) -> Self {
+        self.activation = config;
+        self
+    }
+
+    /// Send tally milestones, deadline and activation notifications to `webhook`.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Check the content of each created proposal with `verifier`.
+    pub fn with_content_verifier(mut self, verifier: Arc<ContentVerifier>) -> Self {
+        self.content = Some(verifier);
+        self
+    }
+
+    /// Count the delegations of `delegations` in tallies (see [`crate::delegation`]).
+    pub fn with_delegations(mut self, delegations: Arc<DelegationRegistry>) -> Self {
+        self.delegations = Some(delegations);
+        self
+    }
+
+    /// Weigh votes by the weights voters carry in `registry`, as `tally.weights` says (see
+    /// [`crate::tally`]).
+    pub fn with_registry(mut self, registry: Arc<EconomicNodeRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Flag and alert on conflicts between proposals within the window of `config` (see
+    /// [`crate::conflicts`]).
+    pub fn with_conflicts(mut self, config: ConflictConfig) -> Self {
+        self.conflicts = config;
+        self
+    }
+
+    /// Record vote conflicts in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Add proposals created and merged, voting closed and activations to `feed`.
+    pub fn with_feed(mut self, feed: Arc<Feed>) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    /// Look open proposals up again and keep or drop the votes of amended ones under
+    /// `config` (see [`crate::amendments`]).
+    pub fn with_amendments(mut self, config: AmendmentConfig) -> Self {
+        self.amendment = config;
+        self
     }
 
     /// Load proposals for RPC/API (read-only).
-    pub fn load_proposals(&self) -> Result<Vec<GovernanceProposal>, crate::error::GovernanceError> {
+    pub fn load_proposals(&self) -> Result<Vec<GovernanceProposal>, GovernanceError> {
         Self::load_for_display(&self.db)
     }
 
-    pub fn handle_event(&self, event: &ModuleMessage) -> Result<(), crate::error::GovernanceError> {
+    /// The proposal `proposal_id`, if the store has seen it.
+    pub fn proposal(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<GovernanceProposal>, GovernanceError> {
+        Ok(self.load()?.proposals.remove(proposal_id))
+    }
+
+    /// `proposal_id` with its tallies, content check, conflicts and amendments, from one read
+    /// of the stored state.
+    pub fn view(&self, proposal_id: &str) -> Result<Option<ProposalView>, GovernanceError> {
+        let mut state = self.load()?;
+        let content_verified = state.content_verified(proposal_id);
+        let conflicts = state.conflicts.partners(proposal_id);
+        let amendments = state.amendments.history(proposal_id).to_vec();
+        Ok(state.proposals.remove(proposal_id).map(|proposal| ProposalView {
+            proposal,
+            tallies: state.tallies.remove(proposal_id),
+            weighted: state.weighted.remove(proposal_id),
+            content: state.content.remove(proposal_id),
+            content_verified,
+            conflicts,
+            amendments,
+        }))
+    }
+
+    /// The proposals, their tallies and the activations, from one read of the stored state.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, GovernanceError> {
+        let state = self.load()?;
+        Ok(StoreSnapshot {
+            proposals: state.proposals.into_values().collect(),
+            tallies: state.tallies,
+            activations: state.activations.into_values().collect(),
+        })
+    }
+
+    /// Writes of the stored state since the store was opened, e.g. to tell whether a read
+    /// is still current.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::Relaxed)
+    }
+
+    /// The content check of `proposal_id`, if its content was checked.
+    pub fn content_check(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<ContentCheck>, GovernanceError> {
+        Ok(self.load()?.content.remove(proposal_id))
+    }
+
+    /// The raw and delegated tallies of `proposal_id` as last computed, if it has been tallied.
+    pub fn tallies(&self, proposal_id: &str) -> Result<Option<Tallies>, GovernanceError> {
+        Ok(self.load()?.tallies.remove(proposal_id))
+    }
+
+    /// The tally of `proposal_id` by registry weight as last computed, if it has been weighed.
+    pub fn weighted_tally(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<RegistryTally>, GovernanceError> {
+        Ok(self.load()?.weighted.remove(proposal_id))
+    }
+
+    /// Proposals yet to be merged, rejected or expired, oldest first.
+    pub fn open_proposals(&self) -> Result<Vec<GovernanceProposal>, GovernanceError> {
+        let mut open: Vec<GovernanceProposal> = self
+            .load()?
+            .proposals
+            .into_values()
+            .filter(GovernanceProposal::is_open)
+            .collect();
+        open.sort_by(|a, b| {
+            (a.created_height, &a.proposal_id).cmp(&(b.created_height, &b.proposal_id))
+        });
+        Ok(open)
+    }
+
+    /// Height by which `proposal` must be voted on, if its tier has a voting window.
+    pub fn deadline(&self, proposal: &GovernanceProposal) -> Option<u64> {
+        let window = self.deadlines.windows.get(&proposal.tier)?;
+        Some(deadlines::deadline(proposal.created_height?, *window))
+    }
+
+    /// Merged proposals yet to activate, soonest first.
+    pub fn upcoming_activations(&self) -> Result<Vec<Activation>, GovernanceError> {
+        let mut upcoming: Vec<Activation> = self
+            .load()?
+            .activations
+            .into_values()
+            .filter(|a| !a.is_activated())
+            .collect();
+        upcoming.sort_by(|a, b| {
+            (a.activation_height, &a.proposal_id).cmp(&(b.activation_height, &b.proposal_id))
+        });
+        Ok(upcoming)
+    }
+
+    /// The conflict graph: declarations and the conflicts found.
+    pub fn conflicts(&self) -> Result<ConflictGraph, GovernanceError> {
+        Ok(self.load()?.conflicts)
+    }
+
+    /// Duplicate votes dropped and vote conflicts resolved.
+    pub fn vote_checks(&self) -> Result<VoteChecks, GovernanceError> {
+        Ok(self.load()?.vote_checks)
+    }
+
+    /// The counted ballots, by proposal id and voter, with the height each was cast at.
+    pub fn ballots(&self) -> Result<HashMap<String, HashMap<String, Ballot>>, GovernanceError> {
+        Ok(self.load()?.ballots)
+    }
+
+    /// The amendments of `proposal_id`, oldest first, rejected ones included.
+    pub fn amendments(&self, proposal_id: &str) -> Result<Vec<Amendment>, GovernanceError> {
+        Ok(self.load()?.amendments.history(proposal_id).to_vec())
+    }
+
+    /// Activations reached, by proposal id.
+    pub fn activated(&self) -> Result<HashMap<String, Activation>, GovernanceError> {
+        let mut activations = self.load()?.activations;
+        activations.retain(|_, a| a.is_activated());
+        Ok(activations)
+    }
+
+    /// Votes applied while the chain tip was in `heights`, replaced votes included.
+    pub fn votes_between(&self, heights: RangeInclusive<u64>) -> Result<u64, GovernanceError> {
+        Ok(self
+            .load()?
+            .votes
+            .range(heights)
+            .map(|(_, votes)| votes)
+            .sum())
+    }
+
+    /// Undo the votes and merges recorded above `fork_height`, after a reorg replaced those
+    /// blocks: votes are removed or restored to the ones they replaced, merged proposals are
+    /// open again (those followed to activation once the next block cancels it), and tally
+    /// milestones reached above it are dropped to be reached anew. Done in one write. Returns
+    /// the number of votes and merges undone.
+    pub fn roll_back_to(&self, fork_height: u64) -> Result<u64, GovernanceError> {
+        self.change(|state| state.roll_back_to(fork_height))
+    }
+
+    /// Whether any tier has a voting window or an activation delay, delegations or registry
+    /// weights are counted, or open proposals are looked up again, so blocks are needed.
+    pub fn follows_blocks(&self) -> bool {
+        !self.deadlines.windows.is_empty()
+            || !self.activation.delays.is_empty()
+            || self.delegations.is_some()
+            || self.weighs()
+            || self.refreshes()
+    }
+
+    /// Whether open proposals are looked up again every few blocks.
+    fn refreshes(&self) -> bool {
+        self.node_api.is_some() && self.amendment.refresh_interval_blocks > 0
+    }
+
+    /// Whether votes are weighted by registry weight.
+    fn weighs(&self) -> bool {
+        self.registry.is_some() && self.tally.weights != WeightSource::Off
+    }
+
+    /// The registry weights of voters now, if votes are weighted.
+    async fn voter_weights(&self) -> Option<VoterWeights> {
+        let registry = self.registry.as_ref().filter(|_| self.weighs())?;
+        let snapshots = self.tally.weights == WeightSource::Snapshot;
+        let weights = registry.voter_weights(snapshots).await;
+        Some(weights.with_default_weight(self.tally.default_weight))
+    }
+
+    /// Recompute the tallies of open proposals, after delegations changed, and send the
+    /// tally milestones they newly reach.
+    pub async fn update_tallies(&self, node_api: &dyn NodeAPI) -> Result<(), GovernanceError> {
+        let height = self.height();
+        let weights = self.voter_weights().await;
+        let reached = self.change(|state| self.retally(state, height, weights.as_ref()))?;
+        self.announce(reached, node_api).await;
+        Ok(())
+    }
+
+    /// Number of events held for proposals the store has not seen.
+    pub fn held_events(&self) -> Result<usize, GovernanceError> {
+        Ok(self.load()?.pending.values().map(Vec::len).sum())
+    }
+
+    pub async fn handle_event(
+        &self,
+        event: &ModuleMessage,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
         let ModuleMessage::Event(event_msg) = event else {
             return Ok(());
         };
-        let mut proposals = self.load()?;
-        match event_msg.event_type {
-            EventType::GovernanceProposalCreated => {
-                if let EventPayload::GovernanceProposalCreated {
-                    proposal_id,
-                    repository,
-                    pr_number,
-                    tier,
-                } = &event_msg.payload
-                {
-                    proposals.insert(
-                        proposal_id.clone(),
-                        GovernanceProposal {
-                            proposal_id: proposal_id.clone(),
-                            repository: repository.clone(),
-                            pr_number: *pr_number,
-                            tier: tier.clone(),
-                            status: ProposalStatus::Created,
-                            votes: Vec::new(),
-                        },
-                    );
-                    self.save(&proposals)?;
+        let height = self.height();
+        match &event_msg.payload {
+            EventPayload::GovernanceProposalCreated {
+                proposal_id,
+                repository,
+                pr_number,
+                tier,
+            } => {
+                let details = self.lookup(proposal_id).await;
+                let check = match (&self.content, &details) {
+                    (Some(verifier), Some(details)) => Some(verifier.check(details).await),
+                    _ => None,
+                };
+                let weights = self.voter_weights().await;
+                let reached = self.change(|state| {
+                    // A created event for a proposal already stored may come with an amendment
+                    let mut reached = match &details {
+                        Some(details) => self.amend(state, details, height),
+                        None => Vec::new(),
+                    };
+                    let tier = details.as_ref().map_or(tier, |details| &details.tier);
+                    let proposal = state
+                        .proposals
+                        .entry(proposal_id.clone())
+                        .or_insert_with(|| GovernanceProposal::new(proposal_id, tier, height));
+                    proposal.repository = repository.clone();
+                    proposal.pr_number = *pr_number;
+                    if proposal.tier.is_empty() {
+                        proposal.tier = tier.clone();
+                    }
+                    if let Some(details) = &details {
+                        proposal.update_from(details, height);
+                        state.amendments.record_first(details);
+                    }
+                    if let Some(check) = check {
+                        reached.extend(self.store_check(state, proposal_id, check, height));
+                    }
+                    if let Some(details) = &details {
+                        let window = self.conflicts.merge_window_blocks;
+                        state.declare_conflicts(details, height, window);
+                    }
+                    state.release(proposal_id, height);
+                    reached.extend(self.vote_conflict_alerts(state, height));
+                    reached.extend(self.conflict_alerts(state, proposal_id, height));
+                    reached.extend(self.settle(state, proposal_id, height, weights.as_ref()));
+                    reached
+                })?;
+                self.announce(reached, node_api).await;
+                Ok(())
+            }
+            EventPayload::GovernanceProposalVoted {
+                proposal_id,
+                voter,
+                vote,
+            } => {
+                let event = PendingEvent::Voted {
+                    voter: voter.clone(),
+                    vote: vote.clone(),
+                };
+                self.apply_or_hold(proposal_id, event, node_api).await
+            }
+            EventPayload::GovernanceProposalMerged {
+                proposal_id,
+                repository,
+                pr_number,
+            } => {
+                let event = PendingEvent::Merged {
+                    repository: repository.clone(),
+                    pr_number: *pr_number,
+                    height,
+                };
+                self.apply_or_hold(proposal_id, event, node_api).await
+            }
+            EventPayload::NewBlock { height, .. } if self.follows_blocks() => {
+                let interval = self.amendment.refresh_interval_blocks;
+                if self.refreshes() && *height % interval == 0 {
+                    self.refresh_open().await;
                 }
+                let weights = self.voter_weights().await;
+                let due = self.change(|state| {
+                    let mut due = self.follow_activations(state, *height);
+                    // Delegations and registry weights may have changed with the block
+                    if self.delegations.is_some() || weights.is_some() {
+                        due.extend(self.retally(state, Some(*height), weights.as_ref()));
+                    }
+                    due.extend(self.remind(state, *height, weights.as_ref()));
+                    due
+                })?;
+                self.announce(due, node_api).await;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply `event` to `proposal_id`, or hold it until the proposal is known, trying a
+    /// lookup first.
+    async fn apply_or_hold(
+        &self,
+        proposal_id: &str,
+        event: PendingEvent,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let height = self.height();
+        let weights = self.voter_weights().await;
+        let applied = self.change(|state| {
+            if !state.proposals.contains_key(proposal_id) {
+                state
+                    .pending
+                    .entry(proposal_id.to_string())
+                    .or_default()
+                    .push(event);
+                return None;
             }
-            EventType::GovernanceProposalVoted => {
-                if let EventPayload::GovernanceProposalVoted {
-                    proposal_id,
-                    voter,
-                    vote,
-                } = &event_msg.payload
-                {
-                    if let Some(p) = proposals.get_mut(proposal_id) {
-                        p.votes.push(ProposalVote {
-                            voter: voter.clone(),
-                            vote: vote.clone(),
-                        });
-                        self.save(&proposals)?;
+            state.apply(proposal_id, event, height);
+            let mut reached = self.vote_conflict_alerts(state, height);
+            reached.extend(self.conflict_alerts(state, proposal_id, height));
+            reached.extend(self.settle(state, proposal_id, height, weights.as_ref()));
+            Some(reached)
+        })?;
+        let reached = match applied {
+            Some(reached) => reached,
+            None => {
+                debug!("Holding an event for unknown proposal {}", proposal_id);
+                match self.lookup(proposal_id).await {
+                    Some(details) => {
+                        let check = self.check_amended(&details).await;
+                        self.record(&details, weights.as_ref(), check)?
                     }
+                    None => return Ok(()),
                 }
             }
-            EventType::GovernanceProposalMerged => {
-                if let EventPayload::GovernanceProposalMerged {
-                    proposal_id,
-                    repository,
-                    pr_number,
-                } = &event_msg.payload
-                {
-                    if let Some(p) = proposals.get_mut(proposal_id) {
-                        p.status = ProposalStatus::Merged;
-                        p.repository = repository.clone();
-                        p.pr_number = *pr_number;
-                        self.save(&proposals)?;
-                    }
+        };
+        self.announce(reached, node_api).await;
+        Ok(())
+    }
+
+    /// Look `proposal_id` up on the node and update its record, creating it if the store has
+    /// not seen it. Returns the record, or `None` if the node has no such proposal or the
+    /// store no client to ask.
+    pub async fn refresh(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<GovernanceProposal>, GovernanceError> {
+        let Some(node_api) = &self.node_api else {
+            return Ok(None);
+        };
+        let Some(details) = self.lookup(proposal_id).await else {
+            return Ok(None);
+        };
+        let check = self.check_amended(&details).await;
+        let weights = self.voter_weights().await;
+        let reached = self.record(&details, weights.as_ref(), check)?;
+        self.announce(reached, node_api.inner().as_ref()).await;
+        self.proposal(proposal_id)
+    }
+
+    /// Look every open proposal up on the node again, bypassing the proposal cache, so that
+    /// amendments are found (see [`crate::amendments`]). A failed lookup is logged and the
+    /// proposal left as it is.
+    pub async fn refresh_open(&self) {
+        let Some(node_api) = &self.node_api else {
+            return;
+        };
+        let open = match self.open_proposals() {
+            Ok(open) => open,
+            Err(e) => {
+                warn!("Failed to read open proposals to refresh: {}", e.chain());
+                return;
+            }
+        };
+        debug!("Refreshing {} open proposals", open.len());
+        for proposal in open {
+            node_api.proposal_cache().invalidate(&proposal.proposal_id);
+            if let Err(e) = self.refresh(&proposal.proposal_id).await {
+                warn!(
+                    "Failed to refresh proposal {}: {}",
+                    proposal.proposal_id,
+                    e.chain()
+                );
+            }
+        }
+    }
+
+    /// The check of `details`' content, if there is a content verifier and its content hash
+    /// is not the one stored for the proposal.
+    async fn check_amended(&self, details: &ProposalDetails) -> Option<ContentCheck> {
+        let verifier = self.content.as_ref()?;
+        let state = match self.load() {
+            Ok(state) => state,
+            Err(e) => {
+                warn!(
+                    "Failed to read proposals to compare content hashes: {}",
+                    e.chain()
+                );
+                return None;
+            }
+        };
+        let stored = state.amendments.content_hash(&details.proposal_id)?;
+        if *stored == details.content_hash {
+            return None;
+        }
+        Some(verifier.check(details).await)
+    }
+
+    /// Link a proposal to the pull request of a GitHub delivery (see [`crate::github`]): the
+    /// one `sync` names, else the one already linked to the pull request. It takes the
+    /// repository, the pull request number, the tier if labelled and the author if unknown,
+    /// and is created if the store has not seen it. With `transition`, the merge or close of
+    /// an open proposal is recorded too. Returns the proposal, `None` if none is linked.
+    pub fn link_pull_request(
+        &self,
+        sync: &PullRequestSync,
+        transition: bool,
+    ) -> Result<Option<GovernanceProposal>, GovernanceError> {
+        let height = self.height();
+        self.change(|state| {
+            let id = match &sync.proposal_id {
+                Some(id) => id.clone(),
+                None => state
+                    .proposals
+                    .values()
+                    .find(|p| p.repository == sync.repository && p.pr_number == sync.pr_number)
+                    .map(|p| p.proposal_id.clone())?,
+            };
+            let proposal = state.proposals.entry(id.clone()).or_insert_with(|| {
+                GovernanceProposal::new(&id, sync.tier.as_deref().unwrap_or_default(), height)
+            });
+            proposal.repository = sync.repository.clone();
+            proposal.pr_number = sync.pr_number;
+            if let Some(tier) = &sync.tier {
+                proposal.tier = tier.clone();
+            }
+            if proposal.author.is_none() {
+                proposal.author = Some(sync.author.clone());
+            }
+            let closed = match sync.transition {
+                Some(Transition::Merged) => Some(ProposalStatus::Merged),
+                Some(Transition::Closed) => Some(ProposalStatus::Rejected),
+                _ => None,
+            };
+            if let Some(status) = closed.filter(|_| transition && proposal.is_open()) {
+                info!("Proposal {} {} on GitHub", id, status.as_str());
+                proposal.status = status;
+                proposal.closed_height = height;
+            }
+            state.release(&id, height);
+            state.proposals.get(&id).cloned()
+        })
+    }
+
+    /// Update the record of `details`' proposal from it, taking any amendment and storing
+    /// `check` of its new content, and apply any events held for it.
+    fn record(
+        &self,
+        details: &ProposalDetails,
+        weights: Option<&VoterWeights>,
+        check: Option<ContentCheck>,
+    ) -> Result<Vec<Announcement>, GovernanceError> {
+        let height = self.height();
+        self.change(|state| {
+            let id = &details.proposal_id;
+            let mut reached = self.amend(state, details, height);
+            state
+                .proposals
+                .entry(id.clone())
+                .or_insert_with(|| GovernanceProposal::new(id, &details.tier, height))
+                .update_from(details, height);
+            state.amendments.record_first(details);
+            if let Some(check) = check {
+                reached.extend(self.store_check(state, id, check, height));
+            }
+            state.declare_conflicts(details, height, self.conflicts.merge_window_blocks);
+            state.release(id, height);
+            reached.extend(self.vote_conflict_alerts(state, height));
+            reached.extend(self.conflict_alerts(state, id, height));
+            reached.extend(self.settle(state, id, height, weights));
+            reached
+        })
+    }
+
+    /// Take the amendment `details` makes to its stored proposal, if any (see
+    /// [`crate::amendments`]): a new content hash drops the content check, a new tier the
+    /// milestones and reminders reached under the old one, and the votes are dropped if
+    /// `amendments.votes` says so. Once voting has closed it is rejected instead. Either way
+    /// it is added to the proposal's history and announced.
+    fn amend(
+        &self,
+        state: &mut State,
+        details: &ProposalDetails,
+        height: Option<u64>,
+    ) -> Vec<Announcement> {
+        let id = &details.proposal_id;
+        let Some(proposal) = state.proposals.get_mut(id) else {
+            return Vec::new();
+        };
+        // Proposals linked from GitHub before the node's record have no tier yet
+        if proposal.tier.is_empty() {
+            proposal.tier = details.tier.clone();
+        }
+        let deadline = self.deadline(proposal);
+        let closed = !proposal.is_open() || deadline.zip(height).is_some_and(|(d, h)| h >= d);
+        let Some(mut amendment) = state
+            .amendments
+            .find(&proposal.tier, details, height, closed)
+        else {
+            return Vec::new();
+        };
+        if amendment.accepted {
+            if let Some(change) = &amendment.tier {
+                proposal.tier = change.to.clone();
+                proposal.milestones.clear();
+                state.reminders.remove(id);
+            }
+            if amendment.resets_votes(self.amendment.votes) {
+                amendment.votes_reset = proposal.votes.len() as u64;
+                proposal.votes.clear();
+                if proposal.status == ProposalStatus::Voting {
+                    proposal.status = ProposalStatus::Created;
                 }
+                state.ballots.remove(id);
+                state.journal.retain(|v| v.proposal_id != *id);
+                state.tallies.remove(id);
+                state.weighted.remove(id);
+            }
+            if amendment.content_hash.is_some() {
+                state.content.remove(id);
             }
-            _ => {}
         }
-        Ok(())
+        let data = serde_json::json!({
+            "proposal_id": id,
+            "tier": proposal.tier,
+            "status": proposal.status.as_str(),
+            "amendment": amendment,
+            "changes": amendment.to_string(),
+            "deadline_height": self.deadline(proposal),
+            "height": height,
+        });
+        let event_type = if amendment.accepted {
+            info!(
+                "Proposal {} amended: {}, {} votes dropped",
+                id, amendment, amendment.votes_reset
+            );
+            amendments::AMENDED_EVENT
+        } else {
+            warn!(
+                "Rejecting amendment of proposal {} after voting closed: {}",
+                id, amendment
+            );
+            amendments::REJECTED_EVENT
+        };
+        state.amendments.add(id, amendment);
+        vec![self.announcement(event_type, data)]
     }
 
-    fn load(&self) -> Result<HashMap<String, GovernanceProposal>, crate::error::GovernanceError> {
-        let tree = self.db.open_tree(PROPOSALS_TREE).map_err(|e| {
-            crate::error::GovernanceError::Storage(format!("open_tree: {}", e))
-        })?;
-        match tree.get(STORAGE_KEY) {
-            Ok(Some(data)) => bincode::deserialize(&data).map_err(|e| {
-                crate::error::GovernanceError::Storage(format!("deserialize: {}", e))
-            }),
-            Ok(None) => Ok(HashMap::new()),
-            Err(e) => Err(crate::error::GovernanceError::Storage(format!("get: {}", e))),
+    /// Store `check` as the content check of `proposal_id`, unless it is of a content hash
+    /// the proposal does not have, from an amendment rejected. A mismatch is announced.
+    fn store_check(
+        &self,
+        state: &mut State,
+        proposal_id: &str,
+        check: ContentCheck,
+        height: Option<u64>,
+    ) -> Option<Announcement> {
+        if state.amendments.content_hash(proposal_id) != Some(&check.content_hash) {
+            return None;
         }
+        let tier = state.proposals.get(proposal_id).map(|p| p.tier.clone());
+        let mismatch = (check.status == ContentStatus::Mismatch).then(|| {
+            let data = serde_json::json!({
+                "proposal_id": proposal_id,
+                "tier": tier,
+                "check": check,
+                "height": height,
+            });
+            self.announcement(content::MISMATCH_EVENT, data)
+        });
+        state.content.insert(proposal_id.to_string(), check);
+        mismatch
     }
 
-    fn save(
+    /// `proposal`'s tallies at `height`, with delegated weight if delegations are counted.
+    fn tallies_of(&self, proposal: &GovernanceProposal, height: Option<u64>) -> Tallies {
+        let raw = proposal.tally();
+        let delegated = match &self.delegations {
+            Some(delegations) => delegations.tally(&proposal.votes, height),
+            None => raw,
+        };
+        Tallies {
+            raw,
+            delegated,
+            height,
+        }
+    }
+
+    /// `proposal`'s tally at `height` by the registry weights of `weights`, with delegated
+    /// votes if delegations are counted.
+    fn weigh(
         &self,
-        proposals: &HashMap<String, GovernanceProposal>,
-    ) -> Result<(), crate::error::GovernanceError> {
-        let tree = self.db.open_tree(PROPOSALS_TREE).map_err(|e| {
-            crate::error::GovernanceError::Storage(format!("open_tree: {}", e))
-        })?;
-        let data = bincode::serialize(proposals).map_err(|e| {
-            crate::error::GovernanceError::Storage(format!("serialize: {}", e))
-        })?;
-        tree.insert(STORAGE_KEY, &data).map_err(|e| {
-            crate::error::GovernanceError::Storage(format!("insert: {}", e))
-        })?;
-        Ok(())
+        weights: &VoterWeights,
+        proposal: &GovernanceProposal,
+        height: Option<u64>,
+    ) -> RegistryTally {
+        let id = &proposal.proposal_id;
+        match &self.delegations {
+            Some(delegations) => {
+                weights.tally(id, &delegations.attributed(&proposal.votes, height), height)
+            }
+            None => weights.tally(id, &proposal.votes, height),
+        }
     }
 
-    /// Load proposals for CLI (read-only)
-    pub fn load_for_display(db: &Arc<dyn blvm_node::storage::database::Database>) -> Result<Vec<GovernanceProposal>, crate::error::GovernanceError> {
-        let tree = db.open_tree(PROPOSALS_TREE).map_err(|e| {
-            crate::error::GovernanceError::Storage(format!("open_tree: {}", e))
-        })?;
-        match tree.get(STORAGE_KEY) {
-            Ok(Some(data)) => {
-                let map: HashMap<String, GovernanceProposal> = bincode::deserialize(&data)
-                    .map_err(|e| crate::error::GovernanceError::Storage(format!("deserialize: {}", e)))?;
-                Ok(map.into_values().collect())
+    /// Record the tallies of open proposals at `height`, and the milestones they newly reach.
+    fn retally(
+        &self,
+        state: &mut State,
+        height: Option<u64>,
+        weights: Option<&VoterWeights>,
+    ) -> Vec<Announcement> {
+        let mut open: Vec<String> = state
+            .proposals
+            .values()
+            .filter(|p| p.is_open())
+            .map(|p| p.proposal_id.clone())
+            .collect();
+        open.sort();
+        open.iter()
+            .flat_map(|id| self.settle(state, id, height, weights))
+            .collect()
+    }
+
+    /// Record the tallies of `proposal_id`, if open, weighted too with `weights`, and the
+    /// tally milestones it has newly reached, and the intents to send them. Tiers with
+    /// weight-based thresholds are measured by weight when there are weights.
+    fn settle(
+        &self,
+        state: &mut State,
+        proposal_id: &str,
+        height: Option<u64>,
+        weights: Option<&VoterWeights>,
+    ) -> Vec<Announcement> {
+        let content_verified = state.content_verified(proposal_id);
+        let conflicts = state.conflicts.partners(proposal_id);
+        let Some(proposal) = state.proposals.get_mut(proposal_id) else {
+            return Vec::new();
+        };
+        if !proposal.is_open() {
+            return Vec::new();
+        }
+        let tallies = self.tallies_of(proposal, height);
+        state.tallies.insert(proposal_id.to_string(), tallies);
+        let weighted = weights.map(|weights| self.weigh(weights, proposal, height));
+        if let Some(weighted) = weighted {
+            state.weighted.insert(proposal_id.to_string(), weighted);
+        }
+        let tally = tallies.delegated;
+        let (weighting, milestones) = match (self.tally.weighted.get(&proposal.tier), &weighted) {
+            (Some(thresholds), Some(weighted)) => (
+                Weighting::Weight,
+                tally::reached_by_weight(thresholds, weighted),
+            ),
+            _ => match self.tally.tiers.get(&proposal.tier) {
+                Some(thresholds) => (Weighting::Count, tally::reached(thresholds, &tally)),
+                None => return Vec::new(),
+            },
+        };
+        let mut reached = Vec::new();
+        for milestone in milestones {
+            if proposal.milestones.iter().any(|m| m.milestone == milestone) {
+                continue;
+            }
+            let counted = match &weighted {
+                Some(weighted) if weighting == Weighting::Weight => format!(
+                    "{:.2} yes, {:.2} no, {:.2} abstain of {:.2} weight",
+                    weighted.tally.yes,
+                    weighted.tally.no,
+                    weighted.tally.abstain,
+                    weighted.total_weight
+                ),
+                _ => format!(
+                    "{} yes, {} no, {} abstain",
+                    tally.yes, tally.no, tally.abstain
+                ),
+            };
+            info!(
+                "Proposal {} reached {:?} with {}",
+                proposal_id, milestone, counted
+            );
+            proposal.milestones.push(MilestoneReached {
+                milestone,
+                height,
+                tally,
+            });
+            let mut data = serde_json::json!({
+                "proposal_id": proposal_id,
+                "tier": proposal.tier,
+                "tally": tally,
+                "height": height,
+                "content_verified": content_verified,
+                "conflicts": conflicts,
+                "weighting": weighting.as_str(),
+            });
+            if self.delegations.is_some() {
+                data["raw_tally"] = serde_json::json!(tallies.raw);
+            }
+            if let Some(weighted) = &weighted {
+                data["weighted_tally"] = serde_json::json!(weighted);
+            }
+            reached.push(self.announcement(milestone.event_type(), data));
+        }
+        reached
+    }
+
+    /// Record the deadline notifications due at `height` for open proposals, and the intents
+    /// to send them. Proposals no longer open are forgotten.
+    fn remind(
+        &self,
+        state: &mut State,
+        height: u64,
+        weights: Option<&VoterWeights>,
+    ) -> Vec<Announcement> {
+        let proposals = &state.proposals;
+        state
+            .reminders
+            .retain(|id, _| proposals.get(id).is_some_and(GovernanceProposal::is_open));
+        let mut open: Vec<&GovernanceProposal> =
+            proposals.values().filter(|p| p.is_open()).collect();
+        open.sort_by(|a, b| a.proposal_id.cmp(&b.proposal_id));
+        let mut due = Vec::new();
+        for proposal in open {
+            let Some(deadline) = self.deadline(proposal) else {
+                continue;
+            };
+            let notice = state
+                .reminders
+                .entry(proposal.proposal_id.clone())
+                .or_default()
+                .advance(deadline, height, &self.deadlines.reminders);
+            let Some(notice) = notice else {
+                continue;
+            };
+            let tally = self.tallies_of(proposal, Some(height)).delegated;
+            let weighted = weights.map(|weights| self.weigh(weights, proposal, Some(height)));
+            let mut data = serde_json::json!({
+                "proposal_id": proposal.proposal_id,
+                "tier": proposal.tier,
+                "deadline_height": deadline,
+                "height": height,
+                "tally": tally,
+                "content_verified": state.content_verified(&proposal.proposal_id),
+                "conflicts": state.conflicts.partners(&proposal.proposal_id),
+            });
+            if let Some(weighted) = &weighted {
+                data["weighted_tally"] = serde_json::json!(weighted);
+            }
+            match notice {
+                deadlines::Notice::Reminder {
+                    threshold,
+                    blocks_remaining,
+                } => {
+                    debug!(
+                        "Proposal {} has {} blocks left to vote",
+                        proposal.proposal_id, blocks_remaining
+                    );
+                    data["threshold"] = threshold.into();
+                    data["blocks_remaining"] = blocks_remaining.into();
+                }
+                deadlines::Notice::Closed => {
+                    let by_weight = self.tally.weighted.get(&proposal.tier).zip(weighted);
+                    let (weighting, quorum_met) = match by_weight {
+                        Some((thresholds, weighted)) => {
+                            let participation = weighted.participation_percent().unwrap_or(0.0);
+                            (
+                                Weighting::Weight,
+                                Some(participation >= thresholds.quorum_percent),
+                            )
+                        }
+                        None => {
+                            let quorum_met = self
+                                .tally
+                                .tiers
+                                .get(&proposal.tier)
+                                .map(|thresholds| tally.total() >= thresholds.quorum);
+                            (Weighting::Count, quorum_met)
+                        }
+                    };
+                    info!(
+                        "Voting on proposal {} closed at height {}",
+                        proposal.proposal_id, deadline
+                    );
+                    data["quorum_met"] = serde_json::json!(quorum_met);
+                    data["weighting"] = weighting.as_str().into();
+                }
+            }
+            due.push(self.announcement(notice.event_type(), data));
+        }
+        due
+    }
+
+    /// Start the activations of newly merged proposals, and record the notifications due at
+    /// `height` for them and the intents to send them. A merge at or above `height` is undone.
+    fn follow_activations(&self, state: &mut State, height: u64) -> Vec<Announcement> {
+        let interval = self.activation.countdown_interval_blocks;
+        let mut merged: Vec<&mut GovernanceProposal> = state
+            .proposals
+            .values_mut()
+            .filter(|p| p.status == ProposalStatus::Merged)
+            .collect();
+        merged.sort_by(|a, b| a.proposal_id.cmp(&b.proposal_id));
+        let mut due = Vec::new();
+        for proposal in merged {
+            let id = &proposal.proposal_id;
+            let Some(delay) = self.activation.delays.get(&proposal.tier) else {
+                continue;
+            };
+            let activation = state.activations.entry(id.clone()).or_insert_with(|| {
+                // Merged before this block, at a tip not known then
+                let merged_height = proposal.closed_height.unwrap_or(height.saturating_sub(1));
+                Activation::new(id, &proposal.tier, merged_height, *delay, interval)
+            });
+            let Some(notice) = activation.advance(height, interval) else {
+                continue;
+            };
+            let mut data = serde_json::json!({
+                "proposal_id": id,
+                "tier": proposal.tier,
+                "merged_height": activation.merged_height,
+                "activation_height": activation.activation_height,
+                "height": height,
+                "content_verified": state.content.get(id).and_then(ContentCheck::verified),
+                "conflicts": state.conflicts.partners(id),
+            });
+            match notice {
+                activation::Notice::Countdown { blocks_remaining } => {
+                    data["blocks_remaining"] = blocks_remaining.into();
+                }
+                activation::Notice::Activated => {
+                    info!("Proposal {} activated at height {}", id, height);
+                }
+                activation::Notice::Cancelled => {
+                    warn!(
+                        "Reorg to height {} undid the merge of proposal {}; activation cancelled",
+                        height, id
+                    );
+                    state.activations.remove(id);
+                    proposal.unmerge();
+                }
             }
-            Ok(None) => Ok(Vec::new()),
-            Err(e) => Err(crate::error::GovernanceError::Storage(format!("get: {}", e))),
+            due.push(self.announcement(notice.event_type(), data));
         }
+        due
+    }
+
+    /// Record a `proposal_conflict` alert for each conflict of `proposal_id`, if merged, with
+    /// a proposal merged within the conflict window of it, not alerted on yet, and the intents
+    /// to send them.
+    fn conflict_alerts(
+        &self,
+        state: &mut State,
+        proposal_id: &str,
+        height: Option<u64>,
+    ) -> Vec<Announcement> {
+        let Some(merged) = state
+            .proposals
+            .get(proposal_id)
+            .filter(|p| p.status == ProposalStatus::Merged)
+        else {
+            return Vec::new();
+        };
+        let merged_height = merged.closed_height.or(height);
+        let window = self.conflicts.merge_window_blocks;
+        let mut due = Vec::new();
+        for conflict in state.conflicts.conflicts.iter_mut().filter(|c| !c.alerted) {
+            let Some(other) = conflict
+                .partner(proposal_id)
+                .and_then(|other| state.proposals.get(other))
+                .filter(|p| p.status == ProposalStatus::Merged)
+            else {
+                continue;
+            };
+            let within = match (merged_height, other.closed_height) {
+                (Some(merged), Some(other)) => merged.abs_diff(other) <= window,
+                _ => true,
+            };
+            if !within {
+                continue;
+            }
+            warn!(
+                "Proposal {} merged while conflicting proposal {} was merged",
+                proposal_id, other.proposal_id
+            );
+            conflict.alerted = true;
+            let data = serde_json::json!({
+                "proposal_id": proposal_id,
+                "tier": merged.tier,
+                "merged_height": merged_height,
+                "conflicting_proposal_id": other.proposal_id,
+                "conflicting_merged_height": other.closed_height,
+                "reason": conflict.reason,
+                "height": height,
+            });
+            due.push(self.announcement(conflicts::CONFLICT_EVENT, data));
+        }
+        due
+    }
+
+    /// Record the vote conflicts found, in the audit log and as `vote_conflict` alerts, and
+    /// the intents to send them.
+    fn vote_conflict_alerts(&self, state: &mut State, height: Option<u64>) -> Vec<Announcement> {
+        let mut due = Vec::new();
+        for conflict in self.note_vote_conflicts(state) {
+            let data = serde_json::json!({
+                "proposal_id": conflict.proposal_id,
+                "voter": conflict.voter,
+                "kept": conflict.kept,
+                "dropped": conflict.dropped,
+                "height": height,
+            });
+            due.push(self.announcement(vote_check::VOTE_CONFLICT_EVENT, data));
+        }
+        due
+    }
+
+    /// Log the vote conflicts found and write them to the audit log, returning them.
+    fn note_vote_conflicts(&self, state: &mut State) -> Vec<VoteConflict> {
+        let conflicts = std::mem::take(&mut state.vote_conflicts);
+        for conflict in &conflicts {
+            warn!(
+                "Conflicting votes of {} on proposal {}: counted {:?}, dropped {:?}",
+                conflict.voter, conflict.proposal_id, conflict.kept.vote, conflict.dropped.vote
+            );
+            if let Some(audit_log) = &self.audit_log {
+                audit_log.vote_conflict(conflict);
+            }
+        }
+        conflicts
+    }
+
+    /// `data` to send as `event_type`, its intent recorded and, if it is one the feed takes,
+    /// added to the feed.
+    fn announcement(&self, event_type: &'static str, data: serde_json::Value) -> Announcement {
+        if let Some(feed) = &self.feed {
+            feed.record(event_type, &data);
+        }
+        let intent = self
+            .webhook
+            .as_ref()
+            .and_then(|webhook| webhook.record_intent(event_type, &data));
+        Announcement {
+            event_type,
+            data,
+            intent,
+        }
+    }
+
+    /// Send notifications recorded by [`Self::settle`] and [`Self::remind`] to the webhook.
+    async fn announce(&self, reached: Vec<Announcement>, node_api: &dyn NodeAPI) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        for announcement in reached {
+            webhook
+                .notify_recorded(
+                    announcement.event_type,
+                    announcement.data,
+                    announcement.intent,
+                    node_api,
+                )
+                .await;
+        }
+    }
+
+    /// The node's record of `proposal_id`, if there is a client to ask and it has one.
+    async fn lookup(&self, proposal_id: &str) -> Option<ProposalDetails> {
+        let node_api = self.node_api.as_ref()?;
+        match node_api.get_proposal(proposal_id).await {
+            Ok(details) => Some(details),
+            Err(GovernanceError::ProposalNotFound { .. }) => None,
+            Err(e) => {
+                warn!("Failed to look up proposal {}: {}", proposal_id, e.chain());
+                None
+            }
+        }
+    }
+
+    /// Height of the chain tip, if known.
+    fn height(&self) -> Option<u64> {
+        self.node_api
+            .as_ref()
+            .and_then(|node_api| node_api.current_tip())
+            .map(|tip| tip.height)
+    }
+
+    /// Apply `change` to the stored state and write the result, with the conflicts of the
+    /// proposals it rejected or expired cleared and the vote conflicts it found and did not
+    /// alert on recorded.
+    fn change<T>(&self, change: impl FnOnce(&mut State) -> T) -> Result<T, GovernanceError> {
+        let _update = self.update.lock().unwrap();
+        let mut state = self.load()?;
+        let before: HashMap<String, ProposalStatus> = match &self.feed {
+            Some(_) => state.proposals.iter().map(|(id, p)| (id.clone(), p.status)).collect(),
+            None => HashMap::new(),
+        };
+        let result = change(&mut state);
+        state.clear_resolved_conflicts();
+        self.note_vote_conflicts(&mut state);
+        self.save(&state)?;
+        if let Some(feed) = &self.feed {
+            Self::add_to_feed(feed, &state, &before);
+        }
+        Ok(result)
+    }
+
+    /// Add the proposals in `state` that are new since `before`, or newly merged, to `feed`.
+    fn add_to_feed(feed: &Feed, state: &State, before: &HashMap<String, ProposalStatus>) {
+        let mut proposals: Vec<&GovernanceProposal> = state.proposals.values().collect();
+        proposals.sort_by(|a, b| a.proposal_id.cmp(&b.proposal_id));
+        for proposal in proposals {
+            let was = before.get(&proposal.proposal_id);
+            let data = |height: Option<u64>| {
+                serde_json::json!({
+                    "proposal_id": proposal.proposal_id,
+                    "repository": proposal.repository,
+                    "pr_number": proposal.pr_number,
+                    "tier": proposal.tier,
+                    "height": height,
+                })
+            };
+            if was.is_none() {
+                feed.record("proposal_created", &data(proposal.created_height));
+            }
+            if proposal.status == ProposalStatus::Merged && was != Some(&ProposalStatus::Merged) {
+                feed.record("proposal_merged", &data(proposal.closed_height));
+            }
+        }
+    }
+
+    fn load(&self) -> Result<State, GovernanceError> {
+        let tree = self
+            .db
+            .open_tree(PROPOSALS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let read = |key: &[u8]| match tree.get(key) {
+            Ok(Some(data)) => Ok(Some(data)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(GovernanceError::database("get")(e)),
+        };
+        let mut state = State {
+            vote_window: self.tally.vote_conflict_window_blocks,
+            ..State::default()
+        };
+        if let Some(data) = read(STORAGE_KEY)? {
+            state.proposals =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(PENDING_KEY)? {
+            state.pending =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(REMINDERS_KEY)? {
+            state.reminders =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(ACTIVATIONS_KEY)? {
+            state.activations =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(VOTES_KEY)? {
+            state.votes =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(CONTENT_KEY)? {
+            state.content =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(JOURNAL_KEY)? {
+            state.journal =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(TALLIES_KEY)? {
+            state.tallies =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(WEIGHTED_KEY)? {
+            state.weighted =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(CONFLICTS_KEY)? {
+            state.conflicts =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(BALLOTS_KEY)? {
+            state.ballots =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(VOTE_CHECKS_KEY)? {
+            state.vote_checks =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        if let Some(data) = read(AMENDMENTS_KEY)? {
+            state.amendments =
+                bincode::deserialize(&data).map_err(GovernanceError::encoding("deserialize"))?;
+        }
+        Ok(state)
+    }
+
+    fn save(&self, state: &State) -> Result<(), GovernanceError> {
+        self.revision.fetch_add(1, Ordering::Relaxed);
+        let tree = self
+            .db
+            .open_tree(PROPOSALS_TREE)
+            .map_err(GovernanceError::database("open_tree"))?;
+        let data =
+            bincode::serialize(&state.proposals).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(STORAGE_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.pending).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(PENDING_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.reminders).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(REMINDERS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data = bincode::serialize(&state.activations)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(ACTIVATIONS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.votes).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(VOTES_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.content).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(CONTENT_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.journal).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(JOURNAL_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.tallies).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(TALLIES_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.weighted).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(WEIGHTED_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.conflicts).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(CONFLICTS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data =
+            bincode::serialize(&state.ballots).map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(BALLOTS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data = bincode::serialize(&state.vote_checks)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(VOTE_CHECKS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        let data = bincode::serialize(&state.amendments)
+            .map_err(GovernanceError::encoding("serialize"))?;
+        tree.insert(AMENDMENTS_KEY, &data)
+            .map_err(GovernanceError::database("insert"))?;
+        Ok(())
+    }
+
+    /// The module store the proposals are kept in.
+    pub fn store(&self) -> &Arc<dyn blvm_node::storage::database::Database> {
+        &self.db
+    }
+
+    /// Copy the stored proposals, the events held for unknown ones, the reminders sent, the
+    /// activations, the vote counts, the content checks, the votes kept for rollback, the
+    /// tallies, weighted ones included, the conflict graph, the counted votes, the vote check
+    /// counts and the amendments into `target`, e.g. for a backup.
+    pub fn copy_to(
+        &self,
+        target: &Arc<dyn blvm_node::storage::database::Database>,
+    ) -> Result<(), GovernanceError> {
+        let state = self.load()?;
+        if state.proposals.is_empty() && state.pending.is_empty() {
+            return Ok(());
+        }
+        ProposalStore::new(Arc::clone(target)).save(&state)
+    }
+
+    /// The conflict graph stored in `db`, for export.
+    pub fn conflicts_from(db: &Arc<dyn blvm_node::storage::database::Database>) -> Result<ConflictGraph, GovernanceError> {
+        ProposalStore::new(Arc::clone(db)).conflicts()
+    }
+
+    /// Load proposals for CLI (read-only)
+    pub fn load_for_display(db: &Arc<dyn blvm_node::storage::database::Database>) -> Result<Vec<GovernanceProposal>, GovernanceError> {
+        Ok(ProposalStore::new(Arc::clone(db))
+            .load()?
+            .proposals
+            .into_values()
+            .collect())
     }
 }
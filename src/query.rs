@@ -0,0 +1,489 @@
+//! Read-only queries over the health listener
+//!
+//! With `[governance.health] queries = true`, the health listener (see [`crate::health`])
+//! also answers these with JSON, for dashboards that would otherwise have to rebuild the
+//! governance state from webhooks:
+//!
+//! - `GET /proposals[?state=open]`: the stored [`GovernanceProposal`]s, oldest first. `state`
+//!   is `open` (not merged, rejected or expired) or one status, e.g. `merged`.
+//! - `GET /proposals/{id}`: one [`ProposalView`]: the proposal with its tallies, content
+//!   check and conflicts, and its veto tally as `veto`.
+//! - `GET /economic-nodes[?offset=0&limit=100]`: a [`NodePage`] of the registered nodes in
+//!   id order, at most [`MAX_PAGE`] at a time.
+//! - `GET /epochs/{n}/summary`: the stored [`EpochSummary`] of epoch `n`.
+//! - `GET /status`: the module's [`ModuleStatus`] (see [`crate::status`]).
+//! - `GET /dashboard[?limit=10]`: a [`Dashboard`] of every part of the module at once, each
+//!   list cut to `limit` entries (see [`crate::dashboard`]).
+//! - `GET /participation/{id}`: the [`VoterParticipation`] of one voter, with its
+//!   `participation_rate` and `mean_latency_blocks` (see [`crate::participation`]); 404
+//!   while participation is not counted or no proposal counted the voter.
+//!
+//! With `actions_token` set, requests must carry `Authorization: Bearer <token>` like the
+//! action endpoints (see [`crate::actions`]), and are answered 401 without it. Answers other
+//! than `/status` and `/dashboard`, which change with every block, carry an `ETag` made of the revisions of the stores they read, which count
+//! their writes; a request whose `If-None-Match` names the current one is answered 304 with
+//! no body, so polling costs a comparison until something changes. Stored summaries are never
+//! rewritten, so their tag is the epoch.
+//!
+//! The proposal store and the summaries are read on the blocking pool, each with a single
+//! read of the stored state, and the registry under its read lock, so queries never hold up
+//! event processing. The dashboard reads each store once that way and is answered 503 if it
+//! takes longer than [`DASHBOARD_TIMEOUT`]. Errors are `{"error": ..}` with 400 for a bad
+//! parameter, 404 for something not stored and 500 when a store cannot be read.
+
+use crate::dashboard::{self, Dashboard, DeliveryHealth, NodeState, RegistryTotals};
+use crate::economic_nodes::{EconomicNode, EconomicNodeRegistry};
+use crate::epoch_summary::{EpochSummarizer, EpochSummary};
+use crate::error::GovernanceError;
+use crate::heartbeat::Heartbeat;
+use crate::node_api::TipTracker;
+use crate::participation::{ParticipationAnalyzer, VoterParticipation};
+use crate::proposals::{GovernanceProposal, ProposalStore, ProposalView};
+use crate::status::{ModuleStatus, StatusCollector};
+use crate::webhook::GovernanceWebhookClient;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Nodes in a page without `limit`.
+pub const DEFAULT_PAGE: usize = 100;
+
+/// Most nodes in a page.
+pub const MAX_PAGE: usize = 1000;
+
+/// Longest `GET /dashboard` takes before it is answered 503.
+pub const DASHBOARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Values of `state` in `GET /proposals`: `open` or a status.
+const STATES: [&str; 6] = ["open", "created", "voting", "merged", "rejected", "expired"];
+
+/// A page of `GET /economic-nodes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodePage {
+    /// Registered nodes in all.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub nodes: Vec<EconomicNode>,
+}
+
+/// An answer to a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResponse {
+    pub status: u16,
+    /// JSON, empty with 304.
+    pub body: String,
+    pub etag: Option<String>,
+}
+
+impl QueryResponse {
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message }).to_string(),
+            etag: None,
+        }
+    }
+}
+
+/// Whether `path` is one of the queries.
+pub fn serves(path: &str) -> bool {
+    path == "/proposals"
+        || path.starts_with("/proposals/")
+        || path == "/economic-nodes"
+        || path.starts_with("/epochs/")
+        || path == "/status"
+        || path == "/dashboard"
+        || path.starts_with("/participation/")
+}
+
+/// The value of `name` in the query string `query`, if given.
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then_some(value)
+    })
+}
+
+/// The number `name` in the query string `query`, or `default` without it.
+fn number(query: &str, name: &str, default: usize) -> Result<usize, QueryResponse> {
+    match param(query, name) {
+        None => Ok(default),
+        Some(value) => value.parse::<usize>().map_err(|_| {
+            QueryResponse::error(400, &format!("{} must be a number, got {:?}", name, value))
+        }),
+    }
+}
+
+/// Answers the queries from the stores of one connection.
+pub struct QueryApi {
+    /// Bearer token required, if any.
+    token: Option<String>,
+    proposals: Arc<ProposalStore>,
+    registry: Arc<EconomicNodeRegistry>,
+    summaries: Arc<EpochSummarizer>,
+    status: Option<Arc<StatusCollector>>,
+    /// Sources of the dashboard's delivery health, heartbeat and chain tip, if given.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    heartbeat: Option<Arc<Heartbeat>>,
+    tip: Option<Arc<TipTracker>>,
+    participation: Option<Arc<ParticipationAnalyzer>>,
+    /// Start of the tags, so that those of an earlier run, whose revisions started over,
+    /// never match.
+    instance: u64,
+}
+
+impl QueryApi {
+    pub fn new(
+        token: Option<String>,
+        proposals: Arc<ProposalStore>,
+        registry: Arc<EconomicNodeRegistry>,
+        summaries: Arc<EpochSummarizer>,
+    ) -> Self {
+        let instance = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Self {
+            token,
+            proposals,
+            registry,
+            summaries,
+            status: None,
+            webhook: None,
+            heartbeat: None,
+            tip: None,
+            participation: None,
+            instance,
+        }
+    }
+
+    /// Answer `/status` with the status `status` collects; 404 without it.
+    pub fn with_status(mut self, status: Arc<StatusCollector>) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Show the delivery health of `webhook` on the dashboard; absent without it.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Show the status of `heartbeat` on the dashboard, and the connection as dead once it
+    /// finds it so.
+    pub fn with_heartbeat(mut self, heartbeat: Arc<Heartbeat>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Show the chain tip `tip` tracks on the dashboard, and the blocks left to deadlines.
+    pub fn with_tip(mut self, tip: Arc<TipTracker>) -> Self {
+        self.tip = Some(tip);
+        self
+    }
+
+    /// Answer `/participation/{id}` from the statistics `participation` keeps; 404 without
+    /// it.
+    pub fn with_participation(mut self, participation: Arc<ParticipationAnalyzer>) -> Self {
+        self.participation = Some(participation);
+        self
+    }
+
+    /// Answer the query on `path` with `query` as its query string, given the request's
+    /// `Authorization` and `If-None-Match` headers.
+    pub async fn handle(
+        &self,
+        path: &str,
+        query: &str,
+        authorization: Option<&str>,
+        if_none_match: Option<&str>,
+    ) -> QueryResponse {
+        if let Some(token) = &self.token {
+            let authorized = authorization
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|given| crate::actions::token_matches(given.trim(), token));
+            if !authorized {
+                return QueryResponse::error(401, "missing or wrong bearer token");
+            }
+        }
+        let etag = self.etag(path);
+        if let (Some(etag), Some(given)) = (&etag, if_none_match) {
+            if given.split(',').any(|tag| tag.trim() == etag) {
+                return QueryResponse {
+                    status: 304,
+                    body: String::new(),
+                    etag: Some(etag.clone()),
+                };
+            }
+        }
+        let body = match self.answer(path, query).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
+        QueryResponse {
+            status: 200,
+            body,
+            etag,
+        }
+    }
+
+    /// The tag of the current answer on `path`, from the revisions of the stores it reads.
+    fn etag(&self, path: &str) -> Option<String> {
+        let tag = if path == "/proposals" {
+            format!("p{}", self.proposals.revision())
+        } else if path.starts_with("/proposals/") {
+            format!(
+                "p{}-r{}",
+                self.proposals.revision(),
+                self.registry.revision()
+            )
+        } else if path == "/economic-nodes" {
+            format!("r{}", self.registry.revision())
+        } else if let Some(epoch) = epoch_of(path) {
+            return Some(format!("\"e{}\"", epoch));
+        } else {
+            return None;
+        };
+        Some(format!("\"{}-{}\"", self.instance, tag))
+    }
+
+    async fn answer(&self, path: &str, query: &str) -> Result<String, QueryResponse> {
+        let body = if path == "/proposals" {
+            serde_json::to_string(&self.proposals_in(param(query, "state")).await?)
+        } else if let Some(proposal_id) = path.strip_prefix("/proposals/") {
+            serde_json::to_string(&self.proposal(proposal_id).await?)
+        } else if path == "/economic-nodes" {
+            serde_json::to_string(&self.nodes(query).await?)
+        } else if path.starts_with("/epochs/") {
+            let epoch = epoch_of(path).ok_or_else(|| QueryResponse::error(404, "no such query"))?;
+            serde_json::to_string(&self.summary(epoch).await?)
+        } else if path == "/status" {
+            serde_json::to_string(&self.module_status().await?)
+        } else if path == "/dashboard" {
+            serde_json::to_string(&self.dashboard(query).await?)
+        } else if let Some(voter) = path.strip_prefix("/participation/") {
+            serde_json::to_string(&self.participation(voter).await?)
+        } else {
+            return Err(QueryResponse::error(404, "no such query"));
+        };
+        body.map_err(|e| QueryResponse::error(500, &e.to_string()))
+    }
+
+    /// Read the proposal store with `read` on the blocking pool.
+    async fn read<T: Send + 'static>(
+        &self,
+        read: impl FnOnce(&ProposalStore) -> Result<T, GovernanceError> + Send + 'static,
+    ) -> Result<T, QueryResponse> {
+        let proposals = Arc::clone(&self.proposals);
+        match tokio::task::spawn_blocking(move || read(&proposals)).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(QueryResponse::error(500, &e.chain().to_string())),
+            Err(e) => Err(QueryResponse::error(500, &e.to_string())),
+        }
+    }
+
+    /// Read the stored summaries with `read` on the blocking pool.
+    async fn read_summaries<T: Send + 'static>(
+        &self,
+        read: impl FnOnce(&EpochSummarizer) -> Result<T, GovernanceError> + Send + 'static,
+    ) -> Result<T, QueryResponse> {
+        let summaries = Arc::clone(&self.summaries);
+        match tokio::task::spawn_blocking(move || read(&summaries)).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(QueryResponse::error(500, &e.chain().to_string())),
+            Err(e) => Err(QueryResponse::error(500, &e.to_string())),
+        }
+    }
+
+    /// `GET /proposals`.
+    async fn proposals_in(
+        &self,
+        state: Option<&str>,
+    ) -> Result<Vec<GovernanceProposal>, QueryResponse> {
+        if let Some(state) = state {
+            if !STATES.contains(&state) {
+                let message = format!("unknown state {:?}; expected one of {:?}", state, STATES);
+                return Err(QueryResponse::error(400, &message));
+            }
+        }
+        let mut proposals: Vec<GovernanceProposal> = self
+            .read(ProposalStore::load_proposals)
+            .await?
+            .into_iter()
+            .filter(|p| match state {
+                None => true,
+                Some("open") => p.is_open(),
+                Some(state) => p.status.as_str() == state,
+            })
+            .collect();
+        proposals.sort_by(|a, b| {
+            (a.created_height, &a.proposal_id).cmp(&(b.created_height, &b.proposal_id))
+        });
+        Ok(proposals)
+    }
+
+    /// `GET /proposals/{id}`, with the veto tally from the registry.
+    async fn proposal(&self, proposal_id: &str) -> Result<serde_json::Value, QueryResponse> {
+        let id = proposal_id.to_string();
+        let view: ProposalView = self
+            .read(move |store| store.view(&id))
+            .await?
+            .ok_or_else(|| QueryResponse::error(404, "no such proposal"))?;
+        let tally = self.registry.veto_tally(proposal_id).await;
+        let mut body = serde_json::json!(view);
+        body["veto"] = serde_json::json!({
+            "tally": tally,
+            "veto_percent": tally.veto_percent(),
+            "threshold_crossed": tally.crossed(),
+        });
+        Ok(body)
+    }
+
+    /// `GET /economic-nodes`.
+    async fn nodes(&self, query: &str) -> Result<NodePage, QueryResponse> {
+        let offset = number(query, "offset", 0)?;
+        let limit = number(query, "limit", DEFAULT_PAGE)?.min(MAX_PAGE);
+        let mut nodes = self.registry.list_nodes().await;
+        nodes.sort_by_key(|node| node.node_id);
+        let total = nodes.len();
+        let nodes = nodes.into_iter().skip(offset).take(limit).collect();
+        Ok(NodePage {
+            total,
+            offset,
+            limit,
+            nodes,
+        })
+    }
+
+    /// `GET /epochs/{n}/summary`.
+    async fn summary(&self, epoch: u64) -> Result<EpochSummary, QueryResponse> {
+        self.read_summaries(move |summaries| summaries.summary(epoch))
+            .await?
+            .ok_or_else(|| QueryResponse::error(404, "epoch not summarized"))
+    }
+
+    /// `GET /participation/{id}`, with the rate and mean latency worked out.
+    async fn participation(&self, voter: &str) -> Result<serde_json::Value, QueryResponse> {
+        let Some(participation) = self.participation.clone() else {
+            return Err(QueryResponse::error(404, "participation not counted"));
+        };
+        let voter = voter.to_string();
+        let stats: VoterParticipation =
+            match tokio::task::spawn_blocking(move || participation.voter(&voter)).await {
+                Ok(Ok(Some(stats))) => stats,
+                Ok(Ok(None)) => return Err(QueryResponse::error(404, "no such voter")),
+                Ok(Err(e)) => return Err(QueryResponse::error(500, &e.chain().to_string())),
+                Err(e) => return Err(QueryResponse::error(500, &e.to_string())),
+            };
+        let mut body = serde_json::json!(stats);
+        body["participation_rate"] = serde_json::json!(stats.rate());
+        body["mean_latency_blocks"] = serde_json::json!(stats.mean_latency_blocks());
+        Ok(body)
+    }
+
+    /// `GET /status`.
+    async fn module_status(&self) -> Result<ModuleStatus, QueryResponse> {
+        match &self.status {
+            Some(status) => Ok(status.collect().await),
+            None => Err(QueryResponse::error(404, "no status")),
+        }
+    }
+
+    /// `GET /dashboard`, given up after [`DASHBOARD_TIMEOUT`].
+    async fn dashboard(&self, query: &str) -> Result<Dashboard, QueryResponse> {
+        let limit = number(query, "limit", dashboard::DEFAULT_LIMIT)?.min(dashboard::MAX_LIMIT);
+        tokio::time::timeout(DASHBOARD_TIMEOUT, self.assemble(limit))
+            .await
+            .map_err(|_| QueryResponse::error(503, "the dashboard took too long to assemble"))?
+    }
+
+    /// The dashboard with lists of at most `limit` entries.
+    async fn assemble(&self, limit: usize) -> Result<Dashboard, QueryResponse> {
+        // Taken before the reads, so that a write during them shows as a new revision next time
+        let revision = format!(
+            "{}-p{}-r{}",
+            self.instance,
+            self.proposals.revision(),
+            self.registry.revision()
+        );
+        let tip_height = self
+            .tip
+            .as_ref()
+            .and_then(|tip| tip.current())
+            .map(|tip| tip.height);
+        let (open_proposals, recent) = self
+            .read(move |store| {
+                let snapshot = store.snapshot()?;
+                let deadline = |p: &GovernanceProposal| store.deadline(p);
+                Ok((
+                    dashboard::open_proposals(&snapshot, deadline, tip_height, limit),
+                    dashboard::recent(&snapshot, limit),
+                ))
+            })
+            .await?;
+        let vetoes = dashboard::near_threshold(self.registry.open_veto_tallies().await, limit);
+        let registry = RegistryTotals::of(self.registry.metrics().await);
+        let last_epoch = self.read_summaries(EpochSummarizer::latest).await?;
+        let heartbeat = self.heartbeat.as_ref().map(|heartbeat| heartbeat.status());
+        let node = NodeState {
+            connected: !heartbeat.as_ref().is_some_and(|status| status.dead),
+            tip_height,
+            heartbeat,
+        };
+        let delivery = self.webhook.as_ref().map(|webhook| DeliveryHealth {
+            deliveries: webhook.delivery_counts(),
+            consecutive_failures: webhook.consecutive_failures(),
+            deferred_blocks: webhook.deferred_blocks(),
+            pending_intents: webhook.pending_intents(),
+        });
+        let generated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Ok(Dashboard {
+            version: dashboard::DASHBOARD_VERSION,
+            generated_at,
+            revision,
+            node,
+            open_proposals,
+            recent,
+            vetoes,
+            registry,
+            delivery,
+            last_epoch,
+        })
+    }
+}
+
+/// The epoch `n` of `/epochs/{n}/summary`, if `path` is one.
+fn epoch_of(path: &str) -> Option<u64> {
+    path.strip_prefix("/epochs/")?
+        .strip_suffix("/summary")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_and_params() {
+        assert!(serves("/proposals"));
+        assert!(serves("/proposals/42"));
+        assert!(serves("/epochs/3/summary"));
+        assert!(serves("/dashboard"));
+        assert!(serves("/participation/ab"));
+        assert!(!serves("/healthz"));
+        assert_eq!(epoch_of("/epochs/3/summary"), Some(3));
+        assert_eq!(epoch_of("/epochs/x/summary"), None);
+        assert_eq!(epoch_of("/epochs/3"), None);
+        assert_eq!(param("state=open&limit=5", "limit"), Some("5"));
+        assert_eq!(param("detail", "detail"), Some(""));
+        assert_eq!(param("state=open", "offset"), None);
+        assert_eq!(number("limit=5", "limit", 10), Ok(5));
+        assert_eq!(number("", "limit", 10), Ok(10));
+        assert_eq!(number("limit=all", "limit", 10).unwrap_err().status, 400);
+    }
+}
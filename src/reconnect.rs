@@ -0,0 +1,229 @@
+//! Reconnection backoff
+//!
+//! Delays between attempts to reconnect to the node double from `initial_backoff_ms` up to
+//! `max_backoff_secs`, each drawn uniformly from the upper half of the current step so that
+//! modules restarted together do not reconnect in lockstep.
+//!
+//! Until the first connection succeeds, attempts stop `connect_timeout_secs` after start
+//! instead, so a module started before the node exits rather than waiting indefinitely.
+
+use crate::config::ReconnectConfig;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Backoff state across consecutive failed connections.
+#[derive(Debug)]
+pub struct Backoff {
+    config: ReconnectConfig,
+    attempt: u32,
+    /// When the current run of failures started.
+    since: Option<Instant>,
+    started: Instant,
+    connected: bool,
+}
+
+impl Backoff {
+    pub fn new(config: ReconnectConfig) -> Self {
+        Self {
+            config,
+            attempt: 0,
+            since: None,
+            started: Instant::now(),
+            connected: false,
+        }
+    }
+
+    /// Record that a connection was made; the startup deadline no longer applies.
+    pub fn mark_connected(&mut self) {
+        self.connected = true;
+    }
+
+    /// Time left until the startup deadline, or `None` if there is none.
+    pub fn startup_remaining(&self) -> Option<Duration> {
+        let timeout = self.config.connect_timeout_secs;
+        if self.connected || timeout == 0 {
+            return None;
+        }
+        Some(Duration::from_secs(timeout).saturating_sub(self.started.elapsed()))
+    }
+
+    /// Connections that last this long count as healthy and reset the backoff.
+    pub fn stable_after(&self) -> Duration {
+        Duration::from_secs(self.config.max_backoff_secs)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.since = None;
+    }
+
+    /// Delay before the next attempt, or `None` once `give_up_after_secs` (before the first
+    /// connection, `connect_timeout_secs` since start) has passed.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        self.next_delay_at(Instant::now(), jitter())
+    }
+
+    /// `jitter` in `[0, 1)` picks the delay within the upper half of the current step.
+    fn next_delay_at(&mut self, now: Instant, jitter: f64) -> Option<Duration> {
+        let since = *self.since.get_or_insert(now);
+        let (since, give_up) = if self.connected {
+            (since, self.config.give_up_after_secs)
+        } else {
+            (self.started, self.config.connect_timeout_secs)
+        };
+        if give_up > 0 && now.duration_since(since) >= Duration::from_secs(give_up) {
+            return None;
+        }
+        let initial = Duration::from_millis(self.config.initial_backoff_ms.max(1));
+        let max = Duration::from_secs(self.config.max_backoff_secs).max(initial);
+        let step = initial
+            .checked_mul(1u32 << self.attempt.min(31))
+            .map_or(max, |d| d.min(max));
+        self.attempt = self.attempt.saturating_add(1);
+        Some(step.mul_f64(0.5 + 0.5 * jitter.clamp(0.0, 1.0)))
+    }
+}
+
+/// Wait until `path` exists, polling. Returns false if it does not appear within `timeout`
+/// (`None` waits forever).
+pub async fn wait_for_socket(path: &Path, timeout: Option<Duration>) -> bool {
+    const POLL: Duration = Duration::from_millis(250);
+    const LOG_EVERY: Duration = Duration::from_secs(10);
+    let start = Instant::now();
+    let mut logged = start;
+    while !path.exists() {
+        let waited = start.elapsed();
+        if timeout.is_some_and(|t| waited >= t) {
+            return false;
+        }
+        if logged.elapsed() >= LOG_EVERY {
+            tracing::info!(
+                "Waiting for node socket {} ({}s so far)",
+                path.display(),
+                waited.as_secs()
+            );
+            logged = Instant::now();
+        }
+        let poll = timeout.map_or(POLL, |t| POLL.min(t.saturating_sub(waited)));
+        tokio::time::sleep(poll).await;
+    }
+    true
+}
+
+/// A value in `[0, 1)`. Each `RandomState` is freshly keyed, which is random enough to
+/// spread reconnects without a dependency on a random number generator.
+fn jitter() -> f64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(give_up_after_secs: u64) -> ReconnectConfig {
+        ReconnectConfig {
+            initial_backoff_ms: 1000,
+            max_backoff_secs: 8,
+            give_up_after_secs,
+            connect_timeout_secs: 0,
+            wait_for_socket: false,
+        }
+    }
+
+    #[test]
+    fn test_doubles_up_to_max() {
+        let mut backoff = Backoff::new(config(0));
+        let now = Instant::now();
+        let delays: Vec<u64> = (0..6)
+            .map(|_| backoff.next_delay_at(now, 1.0).unwrap().as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 8, 8]);
+
+        let low = Backoff::new(config(0)).next_delay_at(now, 0.0).unwrap();
+        assert_eq!(low, Duration::from_millis(500));
+
+        backoff.reset();
+        assert_eq!(
+            backoff.next_delay_at(now, 1.0),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_gives_up() {
+        let mut backoff = Backoff::new(config(30));
+        backoff.mark_connected();
+        let start = Instant::now();
+        assert!(backoff.next_delay_at(start, 0.5).is_some());
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(29), 0.5)
+            .is_some());
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(30), 0.5)
+            .is_none());
+
+        // Retrying forever
+        let mut backoff = Backoff::new(config(0));
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(86_400), 0.5)
+            .is_some());
+    }
+
+    #[test]
+    fn test_startup_deadline() {
+        let mut backoff = Backoff::new(ReconnectConfig {
+            connect_timeout_secs: 60,
+            ..config(0)
+        });
+        let start = backoff.started;
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(59), 0.5)
+            .is_some());
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(60), 0.5)
+            .is_none());
+
+        // Once connected, later disconnects fall back to give_up_after_secs
+        backoff.mark_connected();
+        assert_eq!(backoff.startup_remaining(), None);
+        assert!(backoff
+            .next_delay_at(start + Duration::from_secs(3600), 0.5)
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_socket_created_after_delay() {
+        let dir = std::env::temp_dir().join(format!("blvm_wait_socket_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("node.sock");
+        std::fs::remove_file(&path).ok();
+
+        assert!(!wait_for_socket(&path, Some(Duration::from_millis(300))).await);
+
+        let listener = tokio::spawn({
+            let path = path.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                tokio::net::UnixListener::bind(&path).unwrap()
+            }
+        });
+        let started = Instant::now();
+        assert!(wait_for_socket(&path, Some(Duration::from_secs(10))).await);
+        assert!(started.elapsed() >= Duration::from_millis(400));
+        let listener = listener.await.unwrap();
+        assert!(tokio::net::UnixStream::connect(&path).await.is_ok());
+        drop(listener);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_jitter_in_range() {
+        for _ in 0..100 {
+            let j = jitter();
+            assert!((0.0..1.0).contains(&j));
+        }
+    }
+}
@@ -0,0 +1,650 @@
+//! Replay of recorded events
+//!
+//! `blvm-governance replay` feeds events recorded in the audit log (see
+//! [`crate::audit_log`]), or in a file of audit log lines exported from it, through the
+//! handlers chosen with `--handlers` again: to send a new webhook what it missed, or to
+//! rebuild the registry after a fix. Events are read in order and can be limited to a range
+//! of heights (that of the last new block event recorded before each) and of times.
+//!
+//! Events go through a [`Pipeline`] like the module's, in replay mode: every interested
+//! handler gets every event, whether or not it was processed before, and the checkpoint is
+//! neither consulted nor advanced. Webhook payloads are marked `"replayed": true`;
+//! `--dry-run` logs them, and with `all` veto results, instead of sending them. Governance
+//! actions are never submitted by a replay.
+//!
+//! Handlers ask the node what they need as usual. With `--offline` the node is not
+//! connected: [`RecordedNodeApi`] serves the blocks recorded with the events (with
+//! `[governance.audit_log] record_blocks`), and other requests fail.
+//!
+//! Entries written before events were recorded in full cannot be replayed; they are counted
+//! and skipped.
+
+use crate::audit_log::{AuditEntry, AuditKind};
+use crate::config::{DryRun, GovernanceConfig};
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::pipeline::{EventHandler, HandlerStats, Pipeline};
+use crate::proposals::ProposalStore;
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::{EventType, ModuleError, NodeAPI};
+use blvm_node::storage::database::Database;
+use blvm_protocol::{Block, Hash, OutPoint, UTXO};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+/// A handler a replay can drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum ReplayHandler {
+    /// Webhook deliveries.
+    Webhook,
+    /// The economic node registry.
+    Registry,
+    /// The proposal store.
+    Proposals,
+}
+
+/// Which recorded events to replay. Bounds are inclusive; events recorded before any new
+/// block event have no height and are left out by height bounds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayRange {
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+    /// Unix seconds.
+    pub since: Option<u64>,
+    /// Unix seconds.
+    pub until: Option<u64>,
+}
+
+impl ReplayRange {
+    pub fn contains(&self, event: &RecordedEvent) -> bool {
+        let height_bounded = self.from_height.is_some() || self.to_height.is_some();
+        let in_heights = match event.height {
+            Some(height) => {
+                self.from_height.is_none_or(|from| height >= from)
+                    && self.to_height.is_none_or(|to| height <= to)
+            }
+            None => !height_bounded,
+        };
+        in_heights
+            && self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp <= until)
+    }
+}
+
+/// An event read back from the audit log.
+#[derive(Clone)]
+pub struct RecordedEvent {
+    /// Number of its audit log entry.
+    pub seq: u64,
+    /// When it was received, in Unix seconds.
+    pub timestamp: u64,
+    /// Height of the last new block event recorded up to it.
+    pub height: Option<u64>,
+    pub event: EventMessage,
+    /// The block of a new block event, if it was recorded.
+    pub block: Option<Block>,
+}
+
+/// Events to replay, in the order they were recorded.
+#[derive(Default)]
+pub struct Recorded {
+    pub events: Vec<RecordedEvent>,
+    /// Event entries that do not hold the event, whatever their time.
+    pub unreplayable: u64,
+}
+
+/// Decode the hex of a bincode encoding in `data[field]`, if there.
+pub(crate) fn decode<T: serde::de::DeserializeOwned>(
+    data: &serde_json::Value,
+    field: &str,
+) -> Option<Result<T, String>> {
+    let encoded = data.get(field)?.as_str()?;
+    Some(
+        hex::decode(encoded)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| bincode::deserialize(&bytes).map_err(|e| e.to_string())),
+    )
+}
+
+/// Read the events in `range` from `source`: an audit log directory, or a file of audit log
+/// lines. The chain is not checked; run `verify-audit` for that.
+pub fn read(source: &Path, range: &ReplayRange) -> Result<Recorded, GovernanceError> {
+    let files: Vec<PathBuf> = if source.is_dir() {
+        crate::audit_log::files(source)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect()
+    } else {
+        vec![source.to_path_buf()]
+    };
+    let mut recorded = Recorded::default();
+    let mut height = None;
+    for path in &files {
+        let file = std::fs::File::open(path).map_err(GovernanceError::io(path.display()))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(GovernanceError::io(path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let operation = format!("{}:{}", path.display(), i + 1);
+            let entry: AuditEntry =
+                serde_json::from_str(&line).map_err(GovernanceError::serialization(&operation))?;
+            if entry.kind != AuditKind::Event {
+                continue;
+            }
+            let event = match decode::<EventMessage>(&entry.data, "event") {
+                Some(Ok(event)) => event,
+                Some(Err(e)) => {
+                    warn!("Skipping entry {} at {}: {}", entry.seq, operation, e);
+                    recorded.unreplayable += 1;
+                    continue;
+                }
+                None => {
+                    recorded.unreplayable += 1;
+                    continue;
+                }
+            };
+            if let EventPayload::NewBlock { height: h, .. } = &event.payload {
+                height = Some(*h);
+            }
+            let block = match decode::<Block>(&entry.data, "block") {
+                Some(Ok(block)) => Some(block),
+                Some(Err(e)) => {
+                    warn!(
+                        "Ignoring the block of entry {} at {}: {}",
+                        entry.seq, operation, e
+                    );
+                    None
+                }
+                None => None,
+            };
+            let event = RecordedEvent {
+                seq: entry.seq,
+                timestamp: entry.timestamp,
+                height,
+                event,
+                block,
+            };
+            if range.contains(&event) {
+                recorded.events.push(event);
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// The chosen handlers, set up for a replay: the webhook client marks its payloads and
+/// honours `dry_run`, the registry and proposal store write to `db`, and nothing submits
+/// governance actions.
+pub async fn handlers(
+    selected: &[ReplayHandler],
+    config: &GovernanceConfig,
+    db: Arc<dyn Database>,
+    node_api: Arc<dyn NodeAPI>,
+    dry_run: DryRun,
+    dry_run_dir: Option<PathBuf>,
+) -> Result<Vec<Arc<dyn EventHandler>>, GovernanceError> {
+    let mut selected = selected.to_vec();
+    selected.sort();
+    selected.dedup();
+    let mut handlers: Vec<Arc<dyn EventHandler>> = Vec::new();
+    // Also sends the proposal store's tally milestones; set up first, as handlers sort
+    let mut webhook = None;
+    for handler in selected {
+        handlers.push(match handler {
+            ReplayHandler::Webhook => {
+                let client = GovernanceWebhookClient::new(config).await?.with_replay();
+                let client = if dry_run.webhook() {
+                    Arc::new(client.with_dry_run(dry_run_dir.clone()))
+                } else {
+                    Arc::new(client)
+                };
+                webhook = Some(Arc::clone(&client));
+                client
+            }
+            ReplayHandler::Registry => Arc::new(
+                EconomicNodeRegistry::new(config.registry.clone(), Arc::clone(&node_api))
+                    .await?
+                    .with_request_timeouts(config.request_timeouts())
+                    .with_retry_policy(config.ipc.retry_policy())
+                    .with_dry_run(dry_run.actions())
+                    .with_store(Arc::clone(&db))?,
+            ),
+            ReplayHandler::Proposals => {
+                let store = ProposalStore::new(Arc::clone(&db))
+                    .with_tally(config.tally.clone())
+                    .with_deadlines(config.deadlines.clone())
+                    .with_activation(config.activation.clone());
+                match &webhook {
+                    Some(webhook) => Arc::new(store.with_webhook(Arc::clone(webhook))),
+                    None => Arc::new(store),
+                }
+            }
+        });
+    }
+    Ok(handlers)
+}
+
+/// Outcome of [`replay`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplaySummary {
+    pub events: usize,
+    /// Events every interested handler completed.
+    pub completed: usize,
+    pub unreplayable: u64,
+    /// Sequence numbers of the first and last events replayed.
+    pub first: Option<u64>,
+    pub last: Option<u64>,
+    pub handlers: BTreeMap<&'static str, HandlerStats>,
+}
+
+impl ReplaySummary {
+    /// Whether every handler completed every event.
+    pub fn succeeded(&self) -> bool {
+        self.completed == self.events
+    }
+}
+
+impl fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => write!(
+                f,
+                "Replayed {} events (entries {} to {}): {} completed, {} with errors",
+                self.events,
+                first,
+                last,
+                self.completed,
+                self.events - self.completed
+            )?,
+            _ => write!(f, "No events to replay")?,
+        }
+        if self.unreplayable > 0 {
+            write!(
+                f,
+                "; {} recorded without the event were skipped",
+                self.unreplayable
+            )?;
+        }
+        for (name, stats) in &self.handlers {
+            write!(
+                f,
+                "\n  {}: {} handled, {} errors",
+                name, stats.handled, stats.errors
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Pass `recorded` through `handlers` in replay mode, with `checkpointer` left as it is.
+pub async fn replay(
+    recorded: &Recorded,
+    handlers: Vec<Arc<dyn EventHandler>>,
+    checkpointer: Arc<crate::checkpoint::Checkpointer>,
+    node_api: &dyn NodeAPI,
+) -> ReplaySummary {
+    let pipeline = Pipeline::new(checkpointer)
+        .with_handlers(handlers)
+        .with_replay();
+    let mut summary = ReplaySummary {
+        unreplayable: recorded.unreplayable,
+        ..Default::default()
+    };
+    for recorded in &recorded.events {
+        if !pipeline.accepts(&recorded.event.event_type) {
+            continue;
+        }
+        summary.events += 1;
+        summary.first.get_or_insert(recorded.seq);
+        summary.last = Some(recorded.seq);
+        if pipeline.process(&recorded.event, node_api).await {
+            summary.completed += 1;
+        }
+    }
+    summary.handlers = pipeline.handler_stats();
+    summary
+}
+
+fn offline(method: &str) -> ModuleError {
+    ModuleError::OperationError(format!("{} is not available offline", method))
+}
+
+/// [`NodeAPI`] for `replay --offline`: serves the recorded blocks, with the last of them as
+/// the tip, and fails every other request. Events published by handlers are dropped.
+#[derive(Default)]
+pub struct RecordedNodeApi {
+    blocks: HashMap<Hash, Block>,
+    heights: HashMap<u64, Hash>,
+    tip: (Hash, u64),
+}
+
+impl RecordedNodeApi {
+    pub fn new(recorded: &Recorded) -> Self {
+        let mut api = Self::default();
+        for recorded in &recorded.events {
+            if let (EventPayload::NewBlock { block_hash, height }, Some(block)) =
+                (&recorded.event.payload, &recorded.block)
+            {
+                api.blocks.insert(*block_hash, block.clone());
+                api.heights.insert(*height, *block_hash);
+                if *height >= api.tip.1 {
+                    api.tip = (*block_hash, *height);
+                }
+            }
+        }
+        api
+    }
+
+    /// Number of blocks it serves.
+    pub fn blocks(&self) -> usize {
+        self.blocks.len()
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeAPI for RecordedNodeApi {
+    async fn get_block_height(&self) -> Result<u64, ModuleError> {
+        Ok(self.tip.1)
+    }
+    async fn get_block(&self, hash: &Hash) -> Result<Option<Block>, ModuleError> {
+        Ok(self.blocks.get(hash).cloned())
+    }
+    async fn get_block_header(
+        &self,
+        hash: &Hash,
+    ) -> Result<Option<blvm_protocol::BlockHeader>, ModuleError> {
+        Ok(self.blocks.get(hash).map(|block| block.header.clone()))
+    }
+    async fn get_transaction(
+        &self,
+        _: &Hash,
+    ) -> Result<Option<blvm_protocol::Transaction>, ModuleError> {
+        Err(offline("get_transaction"))
+    }
+    async fn has_transaction(&self, _: &Hash) -> Result<bool, ModuleError> {
+        Err(offline("has_transaction"))
+    }
+    async fn get_chain_tip(&self) -> Result<Hash, ModuleError> {
+        Ok(self.tip.0)
+    }
+    async fn get_utxo(&self, _: &OutPoint) -> Result<Option<UTXO>, ModuleError> {
+        Err(offline("get_utxo"))
+    }
+    async fn subscribe_events(
+        &self,
+        _: Vec<EventType>,
+    ) -> Result<tokio::sync::mpsc::Receiver<ModuleMessage>, ModuleError> {
+        Err(offline("subscribe_events"))
+    }
+    async fn get_mempool_transactions(&self) -> Result<Vec<Hash>, ModuleError> {
+        Err(offline("get_mempool_transactions"))
+    }
+    async fn get_mempool_transaction(
+        &self,
+        _: &Hash,
+    ) -> Result<Option<blvm_protocol::Transaction>, ModuleError> {
+        Err(offline("get_mempool_transaction"))
+    }
+    async fn get_mempool_size(
+        &self,
+    ) -> Result<blvm_node::module::traits::MempoolSize, ModuleError> {
+        Err(offline("get_mempool_size"))
+    }
+    async fn get_network_stats(
+        &self,
+    ) -> Result<blvm_node::module::traits::NetworkStats, ModuleError> {
+        Err(offline("get_network_stats"))
+    }
+    async fn get_network_peers(
+        &self,
+    ) -> Result<Vec<blvm_node::module::traits::PeerInfo>, ModuleError> {
+        Err(offline("get_network_peers"))
+    }
+    async fn get_chain_info(&self) -> Result<blvm_node::module::traits::ChainInfo, ModuleError> {
+        Err(offline("get_chain_info"))
+    }
+    async fn get_block_by_height(&self, height: u64) -> Result<Option<Block>, ModuleError> {
+        Ok(self
+            .heights
+            .get(&height)
+            .and_then(|hash| self.blocks.get(hash))
+            .cloned())
+    }
+    async fn get_lightning_node_url(&self) -> Result<Option<String>, ModuleError> {
+        Err(offline("get_lightning_node_url"))
+    }
+    async fn get_lightning_info(
+        &self,
+    ) -> Result<Option<blvm_node::module::traits::LightningInfo>, ModuleError> {
+        Err(offline("get_lightning_info"))
+    }
+    async fn get_payment_state(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::traits::PaymentState>, ModuleError> {
+        Err(offline("get_payment_state"))
+    }
+    async fn check_transaction_in_mempool(&self, _: &Hash) -> Result<bool, ModuleError> {
+        Err(offline("check_transaction_in_mempool"))
+    }
+    async fn get_fee_estimate(&self, _: u32) -> Result<u64, ModuleError> {
+        Err(offline("get_fee_estimate"))
+    }
+    async fn read_file(&self, _: String) -> Result<Vec<u8>, ModuleError> {
+        Err(offline("read_file"))
+    }
+    async fn write_file(&self, _: String, _: Vec<u8>) -> Result<(), ModuleError> {
+        Err(offline("write_file"))
+    }
+    async fn delete_file(&self, _: String) -> Result<(), ModuleError> {
+        Err(offline("delete_file"))
+    }
+    async fn list_directory(&self, _: String) -> Result<Vec<String>, ModuleError> {
+        Err(offline("list_directory"))
+    }
+    async fn create_directory(&self, _: String) -> Result<(), ModuleError> {
+        Err(offline("create_directory"))
+    }
+    async fn get_file_metadata(
+        &self,
+        _: String,
+    ) -> Result<blvm_node::module::ipc::protocol::FileMetadata, ModuleError> {
+        Err(offline("get_file_metadata"))
+    }
+    async fn get_all_metrics(
+        &self,
+    ) -> Result<HashMap<String, Vec<blvm_node::module::metrics::manager::Metric>>, ModuleError>
+    {
+        Err(offline("get_all_metrics"))
+    }
+    async fn register_rpc_endpoint(&self, _: String, _: String) -> Result<(), ModuleError> {
+        Err(offline("register_rpc_endpoint"))
+    }
+    async fn unregister_rpc_endpoint(&self, _: &str) -> Result<(), ModuleError> {
+        Err(offline("unregister_rpc_endpoint"))
+    }
+    async fn register_timer(
+        &self,
+        _: u64,
+        _: Arc<dyn blvm_node::module::timers::manager::TimerCallback>,
+    ) -> Result<blvm_node::module::timers::manager::TimerId, ModuleError> {
+        Err(offline("register_timer"))
+    }
+    async fn cancel_timer(
+        &self,
+        _: blvm_node::module::timers::manager::TimerId,
+    ) -> Result<(), ModuleError> {
+        Err(offline("cancel_timer"))
+    }
+    async fn schedule_task(
+        &self,
+        _: u64,
+        _: Arc<dyn blvm_node::module::timers::manager::TaskCallback>,
+    ) -> Result<blvm_node::module::timers::manager::TaskId, ModuleError> {
+        Err(offline("schedule_task"))
+    }
+    async fn report_metric(
+        &self,
+        _: blvm_node::module::metrics::manager::Metric,
+    ) -> Result<(), ModuleError> {
+        Err(offline("report_metric"))
+    }
+    async fn get_module_metrics(
+        &self,
+        _: &str,
+    ) -> Result<Vec<blvm_node::module::metrics::manager::Metric>, ModuleError> {
+        Err(offline("get_module_metrics"))
+    }
+    async fn initialize_module(
+        &self,
+        _: String,
+        _: std::path::PathBuf,
+        _: std::path::PathBuf,
+    ) -> Result<(), ModuleError> {
+        Err(offline("initialize_module"))
+    }
+    async fn discover_modules(
+        &self,
+    ) -> Result<Vec<blvm_node::module::traits::ModuleInfo>, ModuleError> {
+        Err(offline("discover_modules"))
+    }
+    async fn get_module_info(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::traits::ModuleInfo>, ModuleError> {
+        Err(offline("get_module_info"))
+    }
+    async fn is_module_available(&self, _: &str) -> Result<bool, ModuleError> {
+        Err(offline("is_module_available"))
+    }
+    async fn publish_event(
+        &self,
+        _: EventType,
+        _: blvm_node::module::ipc::protocol::EventPayload,
+    ) -> Result<(), ModuleError> {
+        // Nobody to publish to
+        Ok(())
+    }
+    async fn call_module(
+        &self,
+        _: Option<&str>,
+        method: &str,
+        _: Vec<u8>,
+    ) -> Result<Vec<u8>, ModuleError> {
+        Err(offline(method))
+    }
+    async fn register_module_api(
+        &self,
+        _: Arc<dyn blvm_node::module::inter_module::api::ModuleAPI>,
+    ) -> Result<(), ModuleError> {
+        Err(offline("register_module_api"))
+    }
+    async fn unregister_module_api(&self) -> Result<(), ModuleError> {
+        Err(offline("unregister_module_api"))
+    }
+    async fn get_module_health(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::process::monitor::ModuleHealth>, ModuleError> {
+        Err(offline("get_module_health"))
+    }
+    async fn get_all_module_health(
+        &self,
+    ) -> Result<Vec<(String, blvm_node::module::process::monitor::ModuleHealth)>, ModuleError> {
+        Err(offline("get_all_module_health"))
+    }
+    async fn report_module_health(
+        &self,
+        _: blvm_node::module::process::monitor::ModuleHealth,
+    ) -> Result<(), ModuleError> {
+        Err(offline("report_module_health"))
+    }
+    async fn send_mesh_packet_to_module(
+        &self,
+        _: &str,
+        _: Vec<u8>,
+        _: String,
+    ) -> Result<(), ModuleError> {
+        Err(offline("send_mesh_packet_to_module"))
+    }
+    async fn send_mesh_packet_to_peer(&self, _: String, _: Vec<u8>) -> Result<(), ModuleError> {
+        Err(offline("send_mesh_packet_to_peer"))
+    }
+    async fn send_stratum_v2_message_to_peer(
+        &self,
+        _: String,
+        _: Vec<u8>,
+    ) -> Result<(), ModuleError> {
+        Err(offline("send_stratum_v2_message_to_peer"))
+    }
+    async fn get_block_template(
+        &self,
+        _: Vec<String>,
+        _: Option<Vec<u8>>,
+        _: Option<String>,
+    ) -> Result<blvm_protocol::mining::BlockTemplate, ModuleError> {
+        Err(offline("get_block_template"))
+    }
+    async fn submit_block(
+        &self,
+        _: Block,
+    ) -> Result<blvm_node::module::traits::SubmitBlockResult, ModuleError> {
+        Err(offline("submit_block"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded(height: Option<u64>, timestamp: u64) -> RecordedEvent {
+        RecordedEvent {
+            seq: 0,
+            timestamp,
+            height,
+            event: EventMessage {
+                event_type: EventType::GovernanceProposalCreated,
+                payload: EventPayload::GovernanceProposalCreated {
+                    proposal_id: "1".to_string(),
+                    repository: "test/repo".to_string(),
+                    pr_number: 1,
+                    tier: "standard".to_string(),
+                },
+            },
+            block: None,
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        let all = ReplayRange::default();
+        assert!(all.contains(&recorded(None, 0)));
+        assert!(all.contains(&recorded(Some(5), 10)));
+
+        let heights = ReplayRange {
+            from_height: Some(100),
+            to_height: Some(200),
+            ..Default::default()
+        };
+        assert!(!heights.contains(&recorded(None, 0)));
+        assert!(!heights.contains(&recorded(Some(99), 0)));
+        assert!(heights.contains(&recorded(Some(100), 0)));
+        assert!(heights.contains(&recorded(Some(200), 0)));
+        assert!(!heights.contains(&recorded(Some(201), 0)));
+
+        let times = ReplayRange {
+            since: Some(1_000),
+            until: Some(2_000),
+            ..Default::default()
+        };
+        assert!(times.contains(&recorded(None, 1_000)));
+        assert!(!times.contains(&recorded(Some(150), 999)));
+        assert!(!times.contains(&recorded(Some(150), 2_001)));
+    }
+}
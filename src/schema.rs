@@ -0,0 +1,902 @@
+//! Versioned schemas of the payloads the module sends
+//!
+//! Every webhook delivery is an [`Envelope`] around its event's `data`, except blocks, which
+//! are sent as a [`BlockNotification`]; alerts go to the alert URL as an [`Alert`]. The data
+//! of proposal lifecycle events ([`ProposalCreated`], [`ProposalVoted`], [`ProposalMerged`]),
+//! veto escalations ([`VetoBandCrossing`]), registry changes ([`RegistryChange`]) and epoch
+//! summaries ([`EpochSummary`]) is typed too. The send paths build these types rather than
+//! assembling JSON, and consumers can deserialize into them: each rejects unknown fields.
+//!
+//! Each type is a [`Payload`] with a `SCHEMA_VERSION`, bumped whenever its serialized shape
+//! changes. Envelopes and block notifications carry their version as `schema_version`; an
+//! envelope whose data is typed carries the data's as `data_version`. The data of the other
+//! events (milestones, deadline and activation notices, conflicts, amendments, signaling,
+//! adoption, digests, crashes) is not typed yet, and its envelopes have no `data_version`.
+//!
+//! `blvm-governance dump-schema` writes a JSON Schema document of each type (see
+//! [`documents`]). The documents are written by hand next to the types, not derived from
+//! them, and a test checks each against the type's example. `tests/schema_test.rs` compares
+//! the [`shape`] of each example with a snapshot in `tests/fixtures/schema`, so a change to a
+//! payload's shape fails until its version is bumped and the snapshot rewritten, with
+//! `UPDATE_SCHEMA_SNAPSHOTS=1`.
+
+use crate::adoption::{AdoptionLevel, Measure};
+use crate::alert::{Alert, AlertStatus, AlertThresholds};
+use crate::build_info::{BuildInfo, ProtocolRange};
+use crate::economic_nodes::{ChangeKind, ChangeSource, RegistryChange};
+use crate::epoch_summary::{EpochSummary, ProposalActivity, RegistryActivity};
+use crate::error::GovernanceError;
+use crate::escalation::Contributor;
+use crate::signaling::SignalingCount;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// JSON Schema dialect of the documents.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A payload type with a versioned schema.
+pub trait Payload: Serialize + DeserializeOwned {
+    /// Name of the schema, e.g. `proposal_created`.
+    const NAME: &'static str;
+    /// Version of the serialized shape.
+    const SCHEMA_VERSION: u32;
+    /// Event types whose `data` this is; empty for types sent whole.
+    const EVENT_TYPES: &'static [&'static str];
+    /// What the payload is, for the schema document.
+    const DESCRIPTION: &'static str;
+
+    /// JSON Schema of the serialized form.
+    fn json_schema() -> Value;
+
+    /// A value with every optional field set, whose shape the snapshot records.
+    fn example() -> Self;
+}
+
+/// The body of a webhook delivery other than a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Envelope {
+    pub event_type: String,
+    /// [`Envelope::SCHEMA_VERSION`](Payload::SCHEMA_VERSION).
+    pub schema_version: u32,
+    /// Schema version of `data`, if the event's data is typed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_version: Option<u32>,
+    pub data: Value,
+    pub node_id: Option<String>,
+    pub network: Option<String>,
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub trace_id: String,
+    /// Sent again by `replay`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub replayed: bool,
+    /// Sent by `test-webhook`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub test: bool,
+    /// Build of the module; in test payloads only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+}
+
+/// The body of a `block` webhook delivery.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockNotification {
+    /// [`BlockNotification::SCHEMA_VERSION`](Payload::SCHEMA_VERSION).
+    pub schema_version: u32,
+    /// Hex block hash.
+    pub block_hash: String,
+    pub block_height: i32,
+    /// The block, as the protocol serializes it.
+    pub block: Value,
+    /// The module's node id.
+    pub contributor_id: Option<String>,
+    pub network: Option<String>,
+    pub trace_id: String,
+    /// Sent again by `replay`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub replayed: bool,
+}
+
+/// Data of `proposal_created`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProposalCreated {
+    pub proposal_id: String,
+    pub repository: String,
+    pub pr_number: u64,
+    pub tier: String,
+}
+
+/// Data of `proposal_voted`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProposalVoted {
+    pub proposal_id: String,
+    pub voter: String,
+    pub vote: String,
+}
+
+/// Data of `proposal_merged`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProposalMerged {
+    pub proposal_id: String,
+    pub repository: String,
+    pub pr_number: u64,
+}
+
+/// Data of `veto_escalation` and `veto_deescalation` (see [`crate::escalation`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VetoBandCrossing {
+    pub proposal_id: String,
+    /// The band entered or left, in percent of the threshold.
+    pub band_percent: f64,
+    /// The tally's share of the threshold, in percent.
+    pub ratio_percent: f64,
+    pub veto_percent: f64,
+    pub threshold_percent: f64,
+    pub vetoing_weight: f64,
+    pub total_weight: f64,
+    /// The largest vetoing nodes, largest first.
+    pub contributors: Vec<Contributor>,
+    pub height: u64,
+    /// Last height of the proposal's voting window, if its tier has one.
+    pub deadline_height: Option<u64>,
+    pub blocks_remaining: Option<u64>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// `payload` as the `data` of an [`Envelope`].
+pub fn to_data<T: Payload>(payload: &T) -> Result<Value, GovernanceError> {
+    serde_json::to_value(payload).map_err(GovernanceError::serialization(T::NAME))
+}
+
+/// Schema version of the data of `event_type`, if it is typed.
+pub fn data_version(event_type: &str) -> Option<u32> {
+    registry()
+        .into_iter()
+        .find(|entry| entry.event_types.contains(&event_type))
+        .map(|entry| entry.schema_version)
+}
+
+/// A payload type of the registry.
+#[derive(Debug, Clone, Copy)]
+pub struct Registered {
+    pub name: &'static str,
+    pub schema_version: u32,
+    pub event_types: &'static [&'static str],
+    /// The JSON Schema document.
+    pub document: fn() -> Value,
+    /// The serialized example.
+    pub example: fn() -> Value,
+    /// Deserialize a value as the type and serialize it again.
+    pub round_trip: fn(Value) -> Result<Value, serde_json::Error>,
+}
+
+fn document_of<T: Payload>() -> Value {
+    let mut document = json!({
+        "$schema": DIALECT,
+        "title": T::NAME,
+        "description": T::DESCRIPTION,
+        "x-schema-version": T::SCHEMA_VERSION,
+        "x-event-types": T::EVENT_TYPES,
+    });
+    if let (Value::Object(document), Value::Object(schema)) = (&mut document, T::json_schema()) {
+        document.extend(schema);
+    }
+    document
+}
+
+fn example_of<T: Payload>() -> Value {
+    serde_json::to_value(T::example()).unwrap_or_default()
+}
+
+fn round_trip_of<T: Payload>(value: Value) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(serde_json::from_value::<T>(value)?)
+}
+
+fn entry<T: Payload>() -> Registered {
+    Registered {
+        name: T::NAME,
+        schema_version: T::SCHEMA_VERSION,
+        event_types: T::EVENT_TYPES,
+        document: document_of::<T>,
+        example: example_of::<T>,
+        round_trip: round_trip_of::<T>,
+    }
+}
+
+/// Every payload type with a schema.
+pub fn registry() -> Vec<Registered> {
+    vec![
+        entry::<Envelope>(),
+        entry::<BlockNotification>(),
+        entry::<ProposalCreated>(),
+        entry::<ProposalVoted>(),
+        entry::<ProposalMerged>(),
+        entry::<VetoBandCrossing>(),
+        entry::<RegistryChange>(),
+        entry::<EpochSummary>(),
+        entry::<Alert>(),
+    ]
+}
+
+/// The JSON Schema document of each payload type, by name.
+pub fn documents() -> BTreeMap<&'static str, Value> {
+    registry()
+        .into_iter()
+        .map(|entry| (entry.name, (entry.document)()))
+        .collect()
+}
+
+/// The shape of `value`: its objects with each field replaced by its shape, its arrays by
+/// the shape of their first element, and everything else by its JSON type.
+pub fn shape(value: &Value) -> Value {
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(_) => "boolean".into(),
+        Value::Number(n) if n.is_f64() => "number".into(),
+        Value::Number(_) => "integer".into(),
+        Value::String(_) => "string".into(),
+        Value::Array(items) => Value::Array(items.first().map(shape).into_iter().collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), shape(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn any() -> Value {
+    json!({})
+}
+
+fn one_of(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// `schema`, or null.
+fn nullable(mut schema: Value) -> Value {
+    let types = json!([schema["type"].take(), "null"]);
+    schema["type"] = types;
+    schema
+}
+
+/// An object of `properties`, all required but those in `optional`, and nothing else.
+fn object(properties: &[(&str, Value)], optional: &[&str]) -> Value {
+    let required: Vec<&str> = properties
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !optional.contains(name))
+        .collect();
+    let properties: serde_json::Map<String, Value> = properties
+        .iter()
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn build_info_schema() -> Value {
+    object(
+        &[
+            ("version", string()),
+            ("git_commit", nullable(string())),
+            ("git_dirty", boolean()),
+            ("build_timestamp", string()),
+            ("rustc_version", string()),
+            ("features", array(string())),
+            (
+                "ipc_protocol",
+                object(&[("min", integer()), ("max", integer())], &[]),
+            ),
+        ],
+        &[],
+    )
+}
+
+impl Payload for Envelope {
+    const NAME: &'static str = "envelope";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[];
+    const DESCRIPTION: &'static str = "Body of a webhook delivery other than a block";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("event_type", string()),
+                ("schema_version", integer()),
+                ("data_version", integer()),
+                ("data", any()),
+                ("node_id", nullable(string())),
+                ("network", nullable(string())),
+                ("timestamp", integer()),
+                ("trace_id", string()),
+                ("replayed", boolean()),
+                ("test", boolean()),
+                ("build", build_info_schema()),
+            ],
+            &["data_version", "replayed", "test", "build"],
+        )
+    }
+
+    fn example() -> Self {
+        Envelope {
+            event_type: "proposal_created".to_string(),
+            schema_version: Self::SCHEMA_VERSION,
+            data_version: Some(ProposalCreated::SCHEMA_VERSION),
+            data: Value::Object(Default::default()),
+            node_id: Some("01".repeat(32)),
+            network: Some("mainnet".to_string()),
+            timestamp: 1_700_000_000,
+            trace_id: "4bf92f3577b34da6".to_string(),
+            replayed: true,
+            test: true,
+            build: Some(BuildInfo {
+                version: "0.1.0".to_string(),
+                git_commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+                git_dirty: false,
+                build_timestamp: "2024-01-01T00:00:00Z".to_string(),
+                rustc_version: "rustc 1.80.0".to_string(),
+                features: vec!["testing".to_string()],
+                ipc_protocol: ProtocolRange { min: 1, max: 1 },
+            }),
+        }
+    }
+}
+
+impl Payload for BlockNotification {
+    const NAME: &'static str = "block";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[];
+    const DESCRIPTION: &'static str = "Body of a block webhook delivery";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("schema_version", integer()),
+                ("block_hash", string()),
+                ("block_height", integer()),
+                ("block", json!({ "type": "object" })),
+                ("contributor_id", nullable(string())),
+                ("network", nullable(string())),
+                ("trace_id", string()),
+                ("replayed", boolean()),
+            ],
+            &["replayed"],
+        )
+    }
+
+    fn example() -> Self {
+        BlockNotification {
+            schema_version: Self::SCHEMA_VERSION,
+            block_hash: "00".repeat(32),
+            block_height: 800_000,
+            block: Value::Object(Default::default()),
+            contributor_id: Some("01".repeat(32)),
+            network: Some("mainnet".to_string()),
+            trace_id: "4bf92f3577b34da6".to_string(),
+            replayed: true,
+        }
+    }
+}
+
+impl Payload for ProposalCreated {
+    const NAME: &'static str = "proposal_created";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &["proposal_created"];
+    const DESCRIPTION: &'static str = "A governance proposal was opened";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("proposal_id", string()),
+                ("repository", string()),
+                ("pr_number", integer()),
+                ("tier", string()),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        ProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "owner/repo".to_string(),
+            pr_number: 42,
+            tier: "standard".to_string(),
+        }
+    }
+}
+
+impl Payload for ProposalVoted {
+    const NAME: &'static str = "proposal_voted";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &["proposal_voted"];
+    const DESCRIPTION: &'static str = "A vote was cast on a governance proposal";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("proposal_id", string()),
+                ("voter", string()),
+                ("vote", string()),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        ProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "alice".to_string(),
+            vote: "approve".to_string(),
+        }
+    }
+}
+
+impl Payload for ProposalMerged {
+    const NAME: &'static str = "proposal_merged";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &["proposal_merged"];
+    const DESCRIPTION: &'static str = "A governance proposal was merged";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("proposal_id", string()),
+                ("repository", string()),
+                ("pr_number", integer()),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        ProposalMerged {
+            proposal_id: "1".to_string(),
+            repository: "owner/repo".to_string(),
+            pr_number: 42,
+        }
+    }
+}
+
+impl Payload for VetoBandCrossing {
+    const NAME: &'static str = "veto_escalation";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[
+        crate::escalation::ESCALATION_EVENT,
+        crate::escalation::DEESCALATION_EVENT,
+    ];
+    const DESCRIPTION: &'static str = "A veto tally entered or fell below an escalation band";
+
+    fn json_schema() -> Value {
+        let contributor = object(
+            &[
+                ("node_id", string()),
+                ("node_type", string()),
+                ("weight", number()),
+                ("share_percent", number()),
+            ],
+            &["node_id"],
+        );
+        object(
+            &[
+                ("proposal_id", string()),
+                ("band_percent", number()),
+                ("ratio_percent", number()),
+                ("veto_percent", number()),
+                ("threshold_percent", number()),
+                ("vetoing_weight", number()),
+                ("total_weight", number()),
+                ("contributors", array(contributor)),
+                ("height", integer()),
+                ("deadline_height", nullable(integer())),
+                ("blocks_remaining", nullable(integer())),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        VetoBandCrossing {
+            proposal_id: "1".to_string(),
+            band_percent: 50.0,
+            ratio_percent: 55.5,
+            veto_percent: 16.65,
+            threshold_percent: 30.0,
+            vetoing_weight: 16.65,
+            total_weight: 100.0,
+            contributors: vec![Contributor {
+                node_id: Some("01".repeat(32)),
+                node_type: "miner".to_string(),
+                weight: 16.65,
+                share_percent: 100.0,
+            }],
+            height: 150,
+            deadline_height: Some(244),
+            blocks_remaining: Some(94),
+        }
+    }
+}
+
+impl Payload for RegistryChange {
+    const NAME: &'static str = "registry_change";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[
+        "registry_activated",
+        "registry_expired",
+        "registry_pruned",
+        "registry_reverified",
+        "registry_key_rotated",
+        "registry_snapshot_taken",
+    ];
+    const DESCRIPTION: &'static str = "A registry-level change to the economic node registry";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("node_id", nullable(string())),
+                (
+                    "kind",
+                    one_of(&[
+                        "activated",
+                        "expired",
+                        "pruned",
+                        "reverified",
+                        "key_rotated",
+                        "snapshot_taken",
+                    ]),
+                ),
+                ("before", any()),
+                ("after", any()),
+                ("height", integer()),
+                ("source", one_of(&["organic", "reconciliation"])),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        RegistryChange {
+            node_id: Some("01".repeat(32)),
+            kind: ChangeKind::KeyRotated,
+            before: Value::from("02aa"),
+            after: Value::from("02bb"),
+            height: 150,
+            source: ChangeSource::Reconciliation,
+            intent: None,
+        }
+    }
+}
+
+impl Payload for EpochSummary {
+    const NAME: &'static str = "epoch_summary";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[crate::epoch_summary::EVENT_TYPE];
+    const DESCRIPTION: &'static str = "Digest of the governance activity of one epoch";
+
+    fn json_schema() -> Value {
+        let ids = || array(string());
+        object(
+            &[
+                ("epoch", integer()),
+                ("start_height", integer()),
+                ("end_height", integer()),
+                (
+                    "proposals",
+                    object(
+                        &[
+                            ("created", ids()),
+                            ("merged", ids()),
+                            ("rejected", ids()),
+                            ("expired", ids()),
+                        ],
+                        &[],
+                    ),
+                ),
+                ("votes", integer()),
+                (
+                    "registry",
+                    object(
+                        &[
+                            ("registrations", integer()),
+                            ("weight_changes", integer()),
+                            ("vetoes", integer()),
+                            (
+                                "vetoes_by_proposal",
+                                json!({ "type": "object", "additionalProperties": integer() }),
+                            ),
+                        ],
+                        &[],
+                    ),
+                ),
+                (
+                    "signaling",
+                    array(object(
+                        &[
+                            ("proposal_id", string()),
+                            ("bit", integer()),
+                            ("signaling", integer()),
+                            ("blocks", integer()),
+                            ("percent", number()),
+                        ],
+                        &[],
+                    )),
+                ),
+                (
+                    "adoption",
+                    array(object(
+                        &[
+                            ("proposal_id", string()),
+                            ("measure", one_of(&["blocks", "nodes"])),
+                            ("activation_height", integer()),
+                            ("height", integer()),
+                            ("adopted", integer()),
+                            ("total", integer()),
+                            ("percent", number()),
+                        ],
+                        &[],
+                    )),
+                ),
+                ("participation_rate", nullable(number())),
+            ],
+            &[],
+        )
+    }
+
+    fn example() -> Self {
+        EpochSummary {
+            epoch: 3,
+            start_height: 432,
+            end_height: 575,
+            proposals: ProposalActivity {
+                created: vec!["4".to_string()],
+                merged: vec!["1".to_string()],
+                rejected: vec!["2".to_string()],
+                expired: vec!["3".to_string()],
+            },
+            votes: 12,
+            registry: RegistryActivity {
+                registrations: 2,
+                weight_changes: 1,
+                vetoes: 1,
+                vetoes_by_proposal: [("4".to_string(), 1)].into(),
+            },
+            signaling: vec![SignalingCount {
+                proposal_id: "1".to_string(),
+                bit: 4,
+                signaling: 100,
+                blocks: 144,
+                percent: 69.5,
+            }],
+            adoption: vec![AdoptionLevel {
+                proposal_id: "1".to_string(),
+                measure: Measure::Blocks,
+                activation_height: 500,
+                height: 575,
+                adopted: 60,
+                total: 76,
+                percent: 78.9,
+            }],
+            participation_rate: Some(0.5),
+        }
+    }
+}
+
+impl Payload for Alert {
+    const NAME: &'static str = "module_alert";
+    const SCHEMA_VERSION: u32 = 1;
+    const EVENT_TYPES: &'static [&'static str] = &[];
+    const DESCRIPTION: &'static str = "Alert posted to the alert URL (see blvm_governance::alert)";
+
+    fn json_schema() -> Value {
+        object(
+            &[
+                ("event_type", json!({ "const": "module_alert" })),
+                ("status", one_of(&["firing", "resolved"])),
+                ("category", string()),
+                ("window_secs", integer()),
+                ("count", integer()),
+                ("rate_per_min", number()),
+                (
+                    "thresholds",
+                    object(&[("count", integer()), ("rate_per_min", number())], &[]),
+                ),
+                ("sample_error", nullable(string())),
+                ("node_id", nullable(string())),
+                ("network", nullable(string())),
+                ("timestamp", integer()),
+            ],
+            &["network"],
+        )
+    }
+
+    fn example() -> Self {
+        Alert {
+            event_type: "module_alert".to_string(),
+            status: AlertStatus::Firing,
+            category: "webhook_undeliverable".to_string(),
+            window_secs: 300,
+            count: 10,
+            rate_per_min: 2.0,
+            thresholds: AlertThresholds {
+                count: 5,
+                rate_per_min: 1.0,
+            },
+            sample_error: Some("HTTP 503".to_string()),
+            node_id: Some("01".repeat(32)),
+            network: Some("mainnet".to_string()),
+            timestamp: 1_700_000_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `value` is of the JSON Schema `type`, a name or a list of names.
+    fn of_type(value: &Value, types: &Value) -> bool {
+        let name = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_f64() => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        let matches = |t: &Value| t == name || (t == "number" && name == "integer");
+        match types {
+            Value::Array(types) => types.iter().any(matches),
+            Value::Null => true,
+            t => matches(t),
+        }
+    }
+
+    /// Check that `value` has the types and exactly the fields `schema` describes.
+    fn check(schema: &Value, value: &Value, path: &str) {
+        assert!(
+            of_type(value, &schema["type"]),
+            "{}: {} is not of type {}",
+            path,
+            value,
+            schema["type"]
+        );
+        if let Some(values) = schema["enum"].as_array() {
+            assert!(values.contains(value), "{}: {} not allowed", path, value);
+        }
+        match value {
+            Value::Object(fields) => {
+                let Some(properties) = schema["properties"].as_object() else {
+                    return;
+                };
+                for name in fields.keys() {
+                    assert!(
+                        properties.contains_key(name),
+                        "{}.{} undocumented",
+                        path,
+                        name
+                    );
+                }
+                for (name, property) in properties {
+                    match fields.get(name) {
+                        Some(field) => check(property, field, &format!("{}.{}", path, name)),
+                        None => panic!("{}.{} missing from the example", path, name),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    check(&schema["items"], item, &format!("{}[]", path));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_documents_describe_the_examples() {
+        for entry in registry() {
+            let document = (entry.document)();
+            assert_eq!(document["title"], entry.name);
+            assert_eq!(document["x-schema-version"], entry.schema_version);
+            check(&document, &(entry.example)(), entry.name);
+        }
+        assert_eq!(documents().len(), registry().len());
+    }
+
+    #[test]
+    fn test_examples_round_trip() {
+        for entry in registry() {
+            let example = (entry.example)();
+            assert_eq!(
+                (entry.round_trip)(example.clone()).unwrap(),
+                example,
+                "{}",
+                entry.name
+            );
+            let mut unknown = example;
+            unknown["unexpected"] = json!(1);
+            assert!(
+                (entry.round_trip)(unknown).is_err(),
+                "{} took an unknown field",
+                entry.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_data_versions() {
+        assert_eq!(
+            data_version("proposal_created"),
+            Some(ProposalCreated::SCHEMA_VERSION)
+        );
+        assert_eq!(
+            data_version("veto_deescalation"),
+            Some(VetoBandCrossing::SCHEMA_VERSION)
+        );
+        assert_eq!(
+            data_version(&RegistryChange::example().event_type()),
+            Some(RegistryChange::SCHEMA_VERSION)
+        );
+        assert_eq!(data_version("epoch_summary"), Some(1));
+        assert_eq!(data_version("governance_digest"), None);
+        // Sent whole, not as data
+        assert_eq!(data_version("module_alert"), None);
+        assert_eq!(data_version("block"), None);
+    }
+
+    #[test]
+    fn test_shape() {
+        let value = json!({
+            "a": 1,
+            "b": [{ "c": 0.5, "d": null }],
+            "e": [],
+            "f": "x",
+        });
+        assert_eq!(
+            shape(&value),
+            json!({
+                "a": "integer",
+                "b": [{ "c": "number", "d": "null" }],
+                "e": [],
+                "f": "string",
+            })
+        );
+    }
+}
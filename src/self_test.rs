@@ -0,0 +1,296 @@
+//! Self-test
+//!
+//! `blvm-governance self-test` checks, in order, what the module needs to run, and prints a
+//! [`Report`] with the outcome and duration of each check:
+//!
+//! - `configuration`: the configuration file reads and validates, as at startup.
+//! - `store`: the module store in the data directory opens and the registry loads from it.
+//! - `node connection`: the node socket accepts a connection and the handshake completes.
+//! - `node round trip`: the node answers a `get_best_block` request.
+//! - `webhook`: the configured webhook accepts a `ping` payload.
+//!
+//! A check that cannot run because an earlier one failed, or that `--skip-node` or
+//! `--skip-webhook` turns off, is skipped; the self-test fails if any check fails. Each check
+//! goes through the code the module itself uses: [`config_check::read_valid`], the store
+//! opened as the offline commands open it, [`NodeApiIpc`] with the configured timeouts and
+//! retries, and [`GovernanceWebhookClient`]. The node checks run in main.rs, which owns the
+//! connection; [`round_trip`] and [`idle_module`] are its parts that do not.
+
+use crate::checkpoint::Checkpointer;
+use crate::config::GovernanceConfig;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::event_queue::EventQueue;
+use crate::event_stream::EventStreamMonitor;
+use crate::ipc_metrics::IpcMetrics;
+use crate::module::GovernanceModule;
+use crate::node_api::NodeApiIpc;
+use crate::pipeline::Pipeline;
+use crate::proposals::ProposalStore;
+use crate::shutdown::Shutdown;
+use crate::storage::DataDir;
+use crate::subscriptions::EventSubscriptions;
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use blvm_node::storage::database::Database;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Event type of the payload sent to the webhook.
+pub const PING_EVENT: &str = "ping";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "PASS",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+        })
+    }
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub elapsed: Duration,
+    /// What was found, the error, or why the check was skipped.
+    pub detail: String,
+}
+
+/// The checks run so far, in order.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn push(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+
+    /// Run and record `check`, which gives a value for later checks and a description of what
+    /// it found. The value if it passed.
+    pub async fn run<T>(
+        &mut self,
+        name: &str,
+        check: impl Future<Output = Result<(T, String), String>>,
+    ) -> Option<T> {
+        let started = Instant::now();
+        let result = check.await;
+        let elapsed = started.elapsed();
+        let (status, detail, value) = match result {
+            Ok((value, detail)) => (Status::Pass, detail, Some(value)),
+            Err(e) => (Status::Fail, e, None),
+        };
+        self.push(Check {
+            name: name.to_string(),
+            status,
+            elapsed,
+            detail,
+        });
+        value
+    }
+
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.push(Check {
+            name: name.to_string(),
+            status: Status::Skip,
+            elapsed: Duration::ZERO,
+            detail: reason.to_string(),
+        });
+    }
+
+    /// Whether no check failed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for check in &self.checks {
+            let elapsed = match check.status {
+                Status::Skip => String::new(),
+                _ => format!("{} ms", check.elapsed.as_millis()),
+            };
+            writeln!(
+                f,
+                "{}  {:<width$}  {:>8}  {}",
+                check.status,
+                check.name,
+                elapsed,
+                check.detail,
+                width = width
+            )?;
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|c| c.status == Status::Fail)
+            .count();
+        if failed == 0 {
+            write!(f, "Self-test passed")
+        } else {
+            write!(
+                f,
+                "Self-test failed: {} of {} checks failed",
+                failed,
+                self.checks.len()
+            )
+        }
+    }
+}
+
+/// `configuration`: read and validate the file at `path`, as the module does at startup.
+pub async fn configuration(path: &Path) -> Result<(GovernanceConfig, String), String> {
+    if !path.exists() {
+        return Err(format!(
+            "configuration file {} does not exist",
+            path.display()
+        ));
+    }
+    let config =
+        crate::config_check::read_valid(path, &HashMap::new()).map_err(|e| e.to_string())?;
+    Ok((config, format!("{} is valid", path.display())))
+}
+
+/// `store`: open the store in `data_dir` and load the registry from it.
+pub async fn store(data_dir: &Path) -> Result<(Arc<dyn Database>, String), String> {
+    let db = crate::cli::open_store(data_dir).map_err(|e| e.to_string())?;
+    let nodes = EconomicNodeRegistry::load_from(&db).map_err(|e| e.to_string())?;
+    Ok((
+        db,
+        format!("{}: {} economic nodes", data_dir.display(), nodes.len()),
+    ))
+}
+
+/// `node round trip`: ask the node for its best block through the module's IPC client.
+pub async fn round_trip(
+    node_api: Arc<dyn NodeAPI>,
+    config: &GovernanceConfig,
+) -> Result<((), String), String> {
+    let ipc = NodeApiIpc::new(node_api)
+        .with_timeouts(config.request_timeouts())
+        .with_retry_policy(config.ipc.retry_policy());
+    let tip = ipc.get_best_block().await.map_err(|e| e.to_string())?;
+    Ok((
+        (),
+        format!(
+            "chain tip {} at height {}",
+            hex::encode(tip.hash),
+            tip.height
+        ),
+    ))
+}
+
+/// `webhook`: post a [`PING_EVENT`] payload to the configured webhook.
+pub async fn webhook(config: &GovernanceConfig) -> Result<((), String), String> {
+    let detail = crate::cli::test_webhook(config, PING_EVENT)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(((), detail))
+}
+
+/// A module that handles nothing, for the connection of the node checks: the node connects
+/// a module only once it is set up. It subscribes to no events and writes nothing.
+pub async fn idle_module(
+    node_api: Arc<dyn NodeAPI>,
+    db: Arc<dyn Database>,
+    data_dir: &Path,
+    config: &GovernanceConfig,
+) -> Result<GovernanceModule, GovernanceError> {
+    let webhook_client = Arc::new(GovernanceWebhookClient::new(config).await?);
+    let ipc = NodeApiIpc::new(Arc::clone(&node_api));
+    let tip = Arc::clone(ipc.tip_tracker());
+    let proposal_cache = Arc::clone(ipc.proposal_cache());
+    let economic_nodes = Arc::new(
+        EconomicNodeRegistry::new(config.registry.clone(), node_api)
+            .await?
+            .with_tip_tracker(Arc::clone(&tip))
+            .with_proposal_cache(Arc::clone(&proposal_cache)),
+    );
+    let (events, _) = EventQueue::new(&config.events);
+    let checkpointer = Arc::new(Checkpointer::open(&DataDir::open(data_dir)?.state())?);
+    Ok(GovernanceModule {
+        proposal_store: Arc::new(ProposalStore::new(db)),
+        webhook_client,
+        economic_nodes,
+        shutdown: Arc::new(Shutdown::new()),
+        events: Arc::new(events),
+        subscriptions: Arc::new(EventSubscriptions::new(Vec::new())),
+        stream: Arc::new(EventStreamMonitor::new(false)),
+        metrics: Arc::new(IpcMetrics::new()),
+        tip,
+        proposal_cache,
+        pipeline: Arc::new(Pipeline::new(checkpointer)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_report_records_checks_in_order() {
+        let mut report = Report::default();
+        let value = report
+            .run("first", async { Ok((7, "found seven".to_string())) })
+            .await;
+        assert_eq!(value, Some(7));
+        assert!(report.passed());
+
+        let value: Option<()> = report
+            .run("second", async { Err("refused".to_string()) })
+            .await;
+        assert_eq!(value, None);
+        report.skip("third", "needs second");
+        assert!(!report.passed());
+
+        let statuses: Vec<Status> = report.checks.iter().map(|c| c.status).collect();
+        assert_eq!(statuses, [Status::Pass, Status::Fail, Status::Skip]);
+        assert_eq!(report.checks[1].detail, "refused");
+    }
+
+    #[test]
+    fn test_report_display() {
+        let check = |name: &str, status, ms| Check {
+            name: name.to_string(),
+            status,
+            elapsed: Duration::from_millis(ms),
+            detail: format!("{} detail", name),
+        };
+        let mut report = Report {
+            checks: vec![
+                check("store", Status::Pass, 3),
+                check("node", Status::Skip, 0),
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "PASS  store      3 ms  store detail\n\
+             SKIP  node             node detail\n\
+             Self-test passed"
+        );
+        report.push(check("webhook", Status::Fail, 1200));
+        assert!(report
+            .to_string()
+            .contains("FAIL  webhook   1200 ms  webhook detail\n"));
+        assert!(report
+            .to_string()
+            .ends_with("Self-test failed: 1 of 3 checks failed"));
+    }
+}
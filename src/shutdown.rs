@@ -0,0 +1,285 @@
+//! Graceful shutdown
+//!
+//! On SIGTERM or SIGINT the module stops accepting events, lets accepted work (event
+//! handlers, queued webhook deliveries) finish within `drain_timeout_secs`, flushes pending
+//! veto reports, tells the node it is going away and exits 0. A second signal exits at once.
+//!
+//! The node can ask for the same shutdown through the `shutdown` module API method,
+//! optionally with a shorter drain deadline; the module then exits with
+//! [`NODE_REQUESTED_EXIT_CODE`] so supervisors can tell it from a crash.
+//!
+//! The last message to the node before the connection closes is a status message giving
+//! the [`ShutdownReason`], so the node can tell an operator shutdown from a crash.
+
+use crate::error::GovernanceError;
+use blvm_node::module::traits::NodeAPI;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Time allowed for the final status message, so a wedged socket cannot hold up exit.
+pub const GOODBYE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Exit code after a shutdown the node asked for.
+pub const NODE_REQUESTED_EXIT_CODE: i32 = 3;
+
+/// Why the module is going away, sent to the node in its final status message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum ShutdownReason {
+    /// SIGTERM or SIGINT.
+    Operator,
+    /// An error the module cannot recover from; `code` is the process exit code.
+    Fatal { code: i32, error: String },
+    /// The module will be started again, e.g. to apply new configuration.
+    Restart,
+    /// The node asked the module to stop, draining for at most `deadline_secs` if given.
+    NodeRequested { deadline_secs: Option<u64> },
+}
+
+impl ShutdownReason {
+    /// Process exit code after shutting down for this reason.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ShutdownReason::Operator | ShutdownReason::Restart => 0,
+            ShutdownReason::Fatal { code, .. } => *code,
+            ShutdownReason::NodeRequested { .. } => NODE_REQUESTED_EXIT_CODE,
+        }
+    }
+
+    /// Time to wait for accepted work, given the configured `drain` timeout.
+    pub fn drain_timeout(&self, drain: Duration) -> Duration {
+        match self {
+            ShutdownReason::NodeRequested {
+                deadline_secs: Some(secs),
+            } => drain.min(Duration::from_secs(*secs)),
+            _ => drain,
+        }
+    }
+}
+
+/// Tell the node the module is going away, with `call_module(None, "module_status", ..)`.
+pub async fn send_goodbye(
+    node_api: &dyn NodeAPI,
+    reason: &ShutdownReason,
+) -> Result<(), GovernanceError> {
+    let payload =
+        serde_json::to_vec(reason).map_err(GovernanceError::serialization("module_status"))?;
+    crate::node_api::with_timeout(
+        "module_status",
+        GOODBYE_TIMEOUT,
+        node_api.call_module(None, "module_status", payload),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Shutdown state shared by the event handlers, background tasks and main.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    stopping: AtomicBool,
+    in_flight: AtomicUsize,
+    stop: Notify,
+    idle: Notify,
+    /// Set by [`Shutdown::fail`]; otherwise the shutdown is an operator's.
+    reason: std::sync::Mutex<Option<ShutdownReason>>,
+}
+
+/// Accepted work; shutdown waits until every guard is dropped.
+#[derive(Debug)]
+pub struct WorkGuard(Arc<Shutdown>);
+
+impl Drop for WorkGuard {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop accepting new work.
+    pub fn begin(&self) {
+        self.stopping.store(true, Ordering::Release);
+        self.stop.notify_waiters();
+    }
+
+    /// Shut down because of `reason` rather than a signal.
+    pub fn fail(&self, reason: ShutdownReason) {
+        self.reason.lock().unwrap().get_or_insert(reason);
+        self.begin();
+    }
+
+    /// Shut down at the node's request. Returns false if shutdown had already begun.
+    pub fn request(&self, deadline_secs: Option<u64>) -> bool {
+        if self.is_stopping() {
+            return false;
+        }
+        self.fail(ShutdownReason::NodeRequested { deadline_secs });
+        true
+    }
+
+    pub fn reason(&self) -> ShutdownReason {
+        self.reason
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or(ShutdownReason::Operator)
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::Acquire)
+    }
+
+    /// Work still running.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Track new work, or `None` once shutdown has begun.
+    pub fn accept(self: &Arc<Self>) -> Option<WorkGuard> {
+        let guard = self.track();
+        if self.is_stopping() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    /// Track work that must finish before shutdown completes, even if shutdown has begun.
+    pub fn track(self: &Arc<Self>) -> WorkGuard {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        WorkGuard(Arc::clone(self))
+    }
+
+    /// Resolves once shutdown has begun.
+    pub async fn stopping(&self) {
+        let notified = self.stop.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if self.is_stopping() {
+            return;
+        }
+        notified.await
+    }
+
+    /// Resolves once no work is running.
+    pub async fn idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.in_flight() == 0 {
+                return;
+            }
+            notified.await
+        }
+    }
+}
+
+/// Wait for SIGTERM or SIGINT. Returns the signal name.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => tokio::select! {
+                _ = term.recv() => "SIGTERM",
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+            },
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_payload() {
+        let fatal = ShutdownReason::Fatal {
+            code: 1,
+            error: "store corrupted".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&fatal).unwrap(),
+            serde_json::json!({"reason": "fatal", "code": 1, "error": "store corrupted"})
+        );
+        assert_eq!(
+            serde_json::to_value(ShutdownReason::Operator).unwrap(),
+            serde_json::json!({"reason": "operator"})
+        );
+
+        let shutdown = Shutdown::new();
+        assert_eq!(shutdown.reason(), ShutdownReason::Operator);
+        shutdown.fail(fatal.clone());
+        assert!(shutdown.is_stopping());
+        assert_eq!(shutdown.reason(), fatal);
+        assert_eq!(fatal.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_node_requested() {
+        let shutdown = Shutdown::new();
+        assert!(shutdown.request(Some(5)));
+        assert!(!shutdown.request(None));
+        let reason = shutdown.reason();
+        assert_eq!(
+            serde_json::to_value(&reason).unwrap(),
+            serde_json::json!({"reason": "node_requested", "deadline_secs": 5})
+        );
+        assert_eq!(reason.exit_code(), NODE_REQUESTED_EXIT_CODE);
+        assert_eq!(
+            reason.drain_timeout(Duration::from_secs(30)),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            ShutdownReason::Operator.drain_timeout(Duration::from_secs(30)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_accepted_work() {
+        let shutdown = Arc::new(Shutdown::new());
+        let work = shutdown.accept().unwrap();
+        shutdown.begin();
+        assert!(shutdown.accept().is_none());
+        tokio::time::timeout(Duration::from_secs(1), shutdown.stopping())
+            .await
+            .unwrap();
+
+        // Accepted work holds up shutdown until it finishes
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), shutdown.idle())
+                .await
+                .is_err()
+        );
+        let tracked = shutdown.track();
+        drop(work);
+        assert_eq!(shutdown.in_flight(), 1);
+        let idle = tokio::spawn({
+            let shutdown = Arc::clone(&shutdown);
+            async move { shutdown.idle().await }
+        });
+        drop(tracked);
+        tokio::time::timeout(Duration::from_secs(1), idle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
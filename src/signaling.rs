@@ -0,0 +1,399 @@
+//! Miner signaling from block header versions
+//!
+//! A proposal that needs miner signaling is configured under
+//! `[governance.signaling.proposals.<proposal_id>]` with a version bit, a window length and a
+//! lock-in threshold. Windows are aligned to multiples of `window_blocks`, as BIP9 retarget
+//! periods are. A block signals when its header version has the BIP9 top bits (`001`) and
+//! the proposal's bit set.
+//!
+//! For each `NewBlock` the [`SignalingTracker`] fetches the header and counts it in the
+//! current window of each proposal. When the window's last block arrives,
+//! `signaling_window_closed` is sent to the webhook with the counts, the percentage
+//! signaling and whether it met `threshold_percent`, and `signaling_locked_in` as well if it
+//! did; a proposal that locked in is no longer tracked.
+//!
+//! The counts are kept in memory. After a restart, and whenever blocks of the current window
+//! are missing (the module was down, or a reorg went back into an earlier window), the
+//! blocks of the window up to the new one are re-read from the node's current chain. A
+//! block at a height already counted replaces it and every block above, so a reorg within a
+//! window recomputes it; a window closed and then reorged is closed, and sent, again. Lock-in
+//! is remembered until the module restarts, or a reorg goes back below it. When the pipeline
+//! rolls back a reorg ([`SignalingTracker::roll_back_to`]), the blocks counted above the fork
+//! and a lock-in above it are forgotten at once, and re-read from the new chain with its
+//! next block.
+//!
+//! [`SignalingTracker::count`] counts signaling blocks over any range of heights instead,
+//! from the node's current chain, for epoch summaries (see [`crate::epoch_summary`]).
+
+use crate::config::{SignalingConfig, SignalingRule};
+use crate::error::GovernanceError;
+use crate::node_api::NodeApiIpc;
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use blvm_protocol::Hash;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Bits of a header version that mark it as using version bits (BIP9).
+pub const VERSIONBITS_TOP_MASK: u32 = 0xE000_0000;
+
+/// Value of the top bits of a version using version bits.
+pub const VERSIONBITS_TOP_BITS: u32 = 0x2000_0000;
+
+/// Whether a block with header `version` signals for `bit`.
+pub fn signals(version: u32, bit: u8) -> bool {
+    version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS && bit < 29 && version & (1 << bit) != 0
+}
+
+/// The outcome of a closed window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowResult {
+    pub proposal_id: String,
+    pub bit: u8,
+    pub start_height: u64,
+    pub end_height: u64,
+    /// Blocks that signaled.
+    pub signaling: u64,
+    pub blocks: u64,
+    pub percent: f64,
+    pub threshold_percent: f64,
+    pub locked_in: bool,
+}
+
+/// Signaling for one proposal over a range of blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalingCount {
+    pub proposal_id: String,
+    pub bit: u8,
+    /// Blocks that signaled.
+    pub signaling: u64,
+    pub blocks: u64,
+    pub percent: f64,
+}
+
+/// The blocks of a window seen so far, and whether each signaled, by height.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    start: u64,
+    blocks: BTreeMap<u64, bool>,
+}
+
+impl Window {
+    /// The window of `rule` that `height` is in.
+    pub fn containing(height: u64, rule: &SignalingRule) -> Self {
+        let length = rule.window_blocks.max(1);
+        Self {
+            start: height / length * length,
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Count the block at `height`, replacing any counted at or above it.
+    pub fn record(&mut self, height: u64, signaled: bool) {
+        self.blocks.split_off(&height);
+        self.blocks.insert(height, signaled);
+    }
+
+    /// Forget the blocks counted above `height`. Returns how many there were.
+    pub fn truncate(&mut self, height: u64) -> usize {
+        self.blocks.split_off(&(height + 1)).len()
+    }
+
+    /// Heights of the window below `height` not counted yet.
+    pub fn missing(&self, height: u64) -> Vec<u64> {
+        (self.start..height)
+            .filter(|h| !self.blocks.contains_key(h))
+            .collect()
+    }
+
+    /// The outcome once the window's last block is counted under `rule`, `None` before.
+    pub fn result(&self, proposal_id: &str, rule: &SignalingRule) -> Option<WindowResult> {
+        let end_height = self.start + rule.window_blocks.max(1) - 1;
+        if !self.blocks.contains_key(&end_height) {
+            return None;
+        }
+        let blocks = self.blocks.len() as u64;
+        let signaling = self.blocks.values().filter(|s| **s).count() as u64;
+        let percent = signaling as f64 * 100.0 / blocks as f64;
+        Some(WindowResult {
+            proposal_id: proposal_id.to_string(),
+            bit: rule.bit,
+            start_height: self.start,
+            end_height,
+            signaling,
+            blocks,
+            percent,
+            threshold_percent: rule.threshold_percent,
+            locked_in: percent >= rule.threshold_percent,
+        })
+    }
+}
+
+/// Signaling followed for one proposal.
+#[derive(Debug, Default)]
+struct Tracked {
+    window: Option<Window>,
+    /// Height of the last block of the window that locked in.
+    locked_in: Option<u64>,
+}
+
+/// Counts signaling blocks per window for the configured proposals.
+pub struct SignalingTracker {
+    config: SignalingConfig,
+    node_api: NodeApiIpc,
+    /// Where closed windows and lock-ins are sent.
+    webhook: Option<Arc<GovernanceWebhookClient>>,
+    /// Held while a block is counted, re-reads included, so blocks are counted in order.
+    state: tokio::sync::Mutex<HashMap<String, Tracked>>,
+}
+
+impl SignalingTracker {
+    pub fn new(config: SignalingConfig, node_api: NodeApiIpc) -> Self {
+        Self {
+            config,
+            node_api,
+            webhook: None,
+            state: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Send closed windows and lock-ins to `webhook`.
+    pub fn with_webhook(mut self, webhook: Arc<GovernanceWebhookClient>) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Whether any proposal is configured, so blocks are needed.
+    pub fn is_enabled(&self) -> bool {
+        !self.config.proposals.is_empty()
+    }
+
+    /// Count the block `hash` at `height` from a `NewBlock` event, and send the windows it
+    /// closes.
+    pub async fn handle_block(
+        &self,
+        hash: &Hash,
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let Some(header) = self.node_api.get_block_header(hash).await? else {
+            debug!("No header for block {} at {}", hex::encode(hash), height);
+            return Ok(());
+        };
+        let version = header.header.version as u32;
+        let mut closed = Vec::new();
+        let mut state = self.state.lock().await;
+        for (proposal_id, rule) in &self.config.proposals {
+            let tracked = state.entry(proposal_id.clone()).or_default();
+            // A reorg went back below the lock-in
+            if tracked.locked_in.is_some_and(|at| height <= at) {
+                tracked.locked_in = None;
+            }
+            if tracked.locked_in.is_some() {
+                continue;
+            }
+            let current = Window::containing(height, rule);
+            let window = match &mut tracked.window {
+                Some(window) if window.start == current.start => window,
+                window => window.insert(current),
+            };
+            window.record(height, signals(version, rule.bit));
+            let missing = window.missing(height);
+            if let Some(first) = missing.first() {
+                debug!(
+                    "Re-reading {} blocks of the signaling window at {} for proposal {}",
+                    missing.len(),
+                    window.start,
+                    proposal_id
+                );
+                self.rescan(window, *first..height, rule).await?;
+            }
+            let Some(result) = window.result(proposal_id, rule) else {
+                continue;
+            };
+            info!(
+                "Signaling window {}..={} for proposal {} closed: {} of {} blocks ({:.1}%)",
+                result.start_height,
+                result.end_height,
+                proposal_id,
+                result.signaling,
+                result.blocks,
+                result.percent
+            );
+            tracked.window = None;
+            if result.locked_in {
+                tracked.locked_in = Some(result.end_height);
+            }
+            closed.push(result);
+        }
+        drop(state);
+        self.announce(closed, node_api).await;
+        Ok(())
+    }
+
+    /// Forget the blocks counted above `fork_height`, and a lock-in above it, after a reorg
+    /// replaced them. Returns the number of counted blocks forgotten.
+    pub async fn roll_back_to(&self, fork_height: u64) -> u64 {
+        let mut state = self.state.lock().await;
+        let mut forgotten = 0;
+        for (proposal_id, tracked) in state.iter_mut() {
+            if tracked.locked_in.is_some_and(|at| at > fork_height) {
+                info!(
+                    "Reorg to height {} undid the lock-in of proposal {}",
+                    fork_height, proposal_id
+                );
+                tracked.locked_in = None;
+            }
+            if let Some(window) = &mut tracked.window {
+                forgotten += window.truncate(fork_height) as u64;
+            }
+        }
+        forgotten
+    }
+
+    /// Blocks at `heights` of the node's current chain that signal for each configured
+    /// proposal, by proposal id.
+    pub async fn count(
+        &self,
+        heights: std::ops::RangeInclusive<u64>,
+    ) -> Result<Vec<SignalingCount>, GovernanceError> {
+        let mut counts: Vec<SignalingCount> = self
+            .config
+            .proposals
+            .iter()
+            .map(|(proposal_id, rule)| SignalingCount {
+                proposal_id: proposal_id.clone(),
+                bit: rule.bit,
+                signaling: 0,
+                blocks: 0,
+                percent: 0.0,
+            })
+            .collect();
+        if counts.is_empty() {
+            return Ok(counts);
+        }
+        let bulk = self.node_api.bulk();
+        let range = *heights.start()..heights.end().saturating_add(1);
+        let mut blocks = std::pin::pin!(bulk.stream_blocks(range));
+        while let Some(block) = blocks.next().await {
+            let (_, block) = block?;
+            for count in &mut counts {
+                count.blocks += 1;
+                if signals(block.header.version as u32, count.bit) {
+                    count.signaling += 1;
+                }
+            }
+        }
+        for count in &mut counts {
+            if count.blocks > 0 {
+                count.percent = count.signaling as f64 * 100.0 / count.blocks as f64;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Count the blocks at `heights` from the node's current chain into `window`.
+    async fn rescan(
+        &self,
+        window: &mut Window,
+        heights: std::ops::Range<u64>,
+        rule: &SignalingRule,
+    ) -> Result<(), GovernanceError> {
+        let bulk = self.node_api.bulk();
+        let mut blocks = std::pin::pin!(bulk.stream_blocks(heights));
+        while let Some(block) = blocks.next().await {
+            let (height, block) = block?;
+            let signaled = signals(block.header.version as u32, rule.bit);
+            window.blocks.insert(height, signaled);
+        }
+        Ok(())
+    }
+
+    async fn announce(&self, closed: Vec<WindowResult>, node_api: &dyn NodeAPI) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        for result in closed {
+            let data = serde_json::json!(result);
+            let mut sends = vec![("signaling_window_closed", data.clone())];
+            if result.locked_in {
+                sends.push(("signaling_locked_in", data));
+            }
+            for (event_type, data) in sends {
+                let intent = webhook.record_intent(event_type, &data);
+                webhook
+                    .notify_recorded(event_type, data, intent, node_api)
+                    .await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(window_blocks: u64, threshold_percent: f64) -> SignalingRule {
+        SignalingRule {
+            bit: 2,
+            window_blocks,
+            threshold_percent,
+        }
+    }
+
+    #[test]
+    fn test_signals() {
+        assert!(signals(0x2000_0004, 2));
+        assert!(!signals(0x2000_0004, 1));
+        // Not a version bits version
+        assert!(!signals(0x6000_0004, 2));
+        assert!(!signals(4, 2));
+    }
+
+    #[test]
+    fn test_window_at_the_threshold() {
+        let rule = rule(4, 75.0);
+        let mut window = Window::containing(9, &rule);
+        assert_eq!(window.start(), 8);
+        for (height, signaled) in [(8, true), (9, false), (10, true)] {
+            window.record(height, signaled);
+        }
+        assert_eq!(window.result("1", &rule), None);
+        window.record(11, true);
+        let result = window.result("1", &rule).unwrap();
+        assert_eq!((result.signaling, result.blocks), (3, 4));
+        assert!(result.locked_in);
+
+        // One block short of the threshold
+        window.record(11, false);
+        let result = window.result("1", &rule).unwrap();
+        assert_eq!(result.percent, 50.0);
+        assert!(!result.locked_in);
+    }
+
+    #[test]
+    fn test_reorg_replaces_blocks_above() {
+        let rule = rule(10, 90.0);
+        let mut window = Window::containing(0, &rule);
+        for height in 0..6 {
+            window.record(height, true);
+        }
+        window.record(3, false);
+        assert_eq!(window.missing(6), vec![4, 5]);
+        assert_eq!(window.blocks.len(), 4);
+
+        // Rolled back to the fork at 1
+        assert_eq!(window.truncate(1), 2);
+        assert_eq!(window.missing(4), vec![2, 3]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Socket ownership and permission check
+//!
+//! Anyone who can create or write to the node's socket can impersonate the node and feed the
+//! module fake governance events. Before each connection attempt the socket must be owned by
+//! the module's own user (or an allowed uid/gid from `[governance.ipc]`) and not writable by
+//! others. `insecure_socket = true` skips the check, for development. Only Unix sockets have
+//! these permissions; elsewhere the check always passes.
+
+use crate::config::IpcConfig;
+use crate::error::GovernanceError;
+use std::path::Path;
+
+/// Ownership and mode bits of a socket file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOwner {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+/// Check socket ownership and mode against `config` for a module running as `our_uid`.
+pub fn check_owner(
+    path: &Path,
+    owner: SocketOwner,
+    our_uid: u32,
+    config: &IpcConfig,
+) -> Result<(), GovernanceError> {
+    if config.insecure_socket {
+        return Ok(());
+    }
+    let owner_allowed = owner.uid == our_uid
+        || config.allowed_socket_uids.contains(&owner.uid)
+        || config.allowed_socket_gids.contains(&owner.gid);
+    if !owner_allowed {
+        return Err(GovernanceError::ConfigError(format!(
+            "socket {} is owned by uid {} gid {}, not this user (uid {}) or an allowed uid/gid",
+            path.display(),
+            owner.uid,
+            owner.gid,
+            our_uid
+        )));
+    }
+    if owner.mode & 0o002 != 0 {
+        return Err(GovernanceError::ConfigError(format!(
+            "socket {} is writable by other users (mode {:o})",
+            path.display(),
+            owner.mode & 0o777
+        )));
+    }
+    Ok(())
+}
+
+/// Check the socket at `path`, if it exists. A missing socket is left to the connection
+/// attempt to report.
+#[cfg(unix)]
+pub fn check(path: &Path, config: &IpcConfig) -> Result<(), GovernanceError> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(GovernanceError::ConfigError(format!(
+                "{}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+    let owner = SocketOwner {
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        mode: metadata.mode(),
+    };
+    // SAFETY: geteuid has no preconditions and cannot fail
+    let our_uid = unsafe { libc::geteuid() };
+    check_owner(path, owner, our_uid, config)
+}
+
+#[cfg(not(unix))]
+pub fn check(_path: &Path, _config: &IpcConfig) -> Result<(), GovernanceError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(uid: u32, gid: u32, mode: u32) -> SocketOwner {
+        SocketOwner { uid, gid, mode }
+    }
+
+    #[test]
+    fn test_owner_and_mode() {
+        let path = Path::new("/run/blvm/node.sock");
+        let mut config = IpcConfig::default();
+        assert!(check_owner(path, owner(1000, 1000, 0o140700), 1000, &config).is_ok());
+        // Group-writable is fine, world-writable is not
+        assert!(check_owner(path, owner(1000, 1000, 0o140770), 1000, &config).is_ok());
+        assert!(matches!(
+            check_owner(path, owner(1000, 1000, 0o140777), 1000, &config),
+            Err(GovernanceError::ConfigError(_))
+        ));
+        // Owned by another user
+        assert!(check_owner(path, owner(0, 0, 0o140700), 1000, &config).is_err());
+        config.allowed_socket_gids = vec![0];
+        assert!(check_owner(path, owner(0, 0, 0o140770), 1000, &config).is_ok());
+
+        config = IpcConfig {
+            insecure_socket: true,
+            ..IpcConfig::default()
+        };
+        assert!(check_owner(path, owner(0, 0, 0o140777), 1000, &config).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_socket_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("blvm_socket_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("node.sock");
+        std::fs::remove_file(&path).ok();
+        let config = IpcConfig::default();
+        // Not created yet
+        assert!(check(&path, &config).is_ok());
+
+        let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(check(&path, &config).is_ok());
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(check(&path, &config).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,411 @@
+//! Module status, as every report of it describes it
+//!
+//! [`ModuleStatus`] is the one description of the running module. It is sent to the node
+//! periodically (see [`crate::status_report`]), added to the `/healthz` and `/readyz` bodies
+//! when asked for with `?detail` (see [`crate::health`]), exported as the labels of the
+//! `bllvm_governance_info` metric (see [`crate::prometheus`]), and served on the admin socket
+//! to `blvm-governance status` (see [`crate::admin`]).
+//!
+//! A [`StatusCollector`] assembles it from [`StatusProvider`]s, one per subsystem, each
+//! owning one section; the providers of the module's subsystems are below. Adding a field to
+//! a section only touches its provider. Readers must ignore fields they do not know;
+//! [`STATUS_SCHEMA_VERSION`] is bumped when a field is removed or changes meaning.
+//! `tests/status_test.rs` compares the serialized status with a snapshot, so a schema change
+//! is always deliberate.
+
+use crate::build_info::BuildInfo;
+use crate::checkpoint::Checkpointer;
+use crate::clock::ClockMonitor;
+use crate::config_reload::ConfigReloader;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error_report::ErrorReporter;
+use crate::event_queue::EventQueue;
+use crate::event_stream::EventStreamMonitor;
+use crate::heartbeat::Heartbeat;
+use crate::ipc_metrics::IpcMetrics;
+use crate::memory::MemoryBudget;
+use crate::network::NetworkStatus;
+use crate::pause::PauseControl;
+use crate::proposals::ProposalStore;
+use crate::webhook::GovernanceWebhookClient;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Version of the [`ModuleStatus`] schema. Version 1 was the flat status report that came
+/// before it.
+pub const STATUS_SCHEMA_VERSION: u32 = 2;
+
+/// The module's status, by subsystem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleStatus {
+    pub schema_version: u32,
+    pub uptime_secs: u64,
+    /// Whether the module is connected to the node. Sections of subsystems that only exist
+    /// on a connection are absent while it is not.
+    pub connected: bool,
+    /// Values that identify the module rather than describe its state, such as its version;
+    /// these are the labels of the info metric.
+    pub info: BTreeMap<String, String>,
+    /// Each provider's section, by name.
+    pub sections: BTreeMap<String, serde_json::Value>,
+}
+
+impl ModuleStatus {
+    pub fn section(&self, name: &str) -> Option<&serde_json::Value> {
+        self.sections.get(name)
+    }
+}
+
+/// One field per line, section fields as `section.field`.
+impl fmt::Display for ModuleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "schema_version: {}", self.schema_version)?;
+        writeln!(f, "uptime_secs: {}", self.uptime_secs)?;
+        writeln!(f, "connected: {}", self.connected)?;
+        for (name, value) in &self.info {
+            writeln!(f, "info.{}: {}", name, value)?;
+        }
+        for (name, section) in &self.sections {
+            write_fields(f, name, section)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `value` under `path`, an object one line per field.
+fn write_fields(f: &mut fmt::Formatter<'_>, path: &str, value: &serde_json::Value) -> fmt::Result {
+    match value {
+        serde_json::Value::Object(fields) if !fields.is_empty() => {
+            for (name, field) in fields {
+                write_fields(f, &format!("{}.{}", path, name), field)?;
+            }
+            Ok(())
+        }
+        serde_json::Value::String(s) => writeln!(f, "{}: {}", path, s),
+        other => writeln!(f, "{}: {}", path, other),
+    }
+}
+
+/// A subsystem's part of the [`ModuleStatus`].
+#[async_trait::async_trait]
+pub trait StatusProvider: Send + Sync {
+    /// Name of the provider's section.
+    fn section(&self) -> &'static str;
+
+    /// Contents of the section, usually a serialized status type of the subsystem (see
+    /// [`section`]).
+    async fn status(&self) -> serde_json::Value;
+
+    /// Entries for [`ModuleStatus::info`]. They become metric labels, so each must take
+    /// few distinct values over the life of a process.
+    fn info(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// `status` as a section.
+pub fn section(status: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(status).unwrap_or_default()
+}
+
+/// Assembles the [`ModuleStatus`] from the providers registered with it.
+pub struct StatusCollector {
+    started: Instant,
+    /// Providers for the life of the process.
+    providers: Mutex<Vec<Arc<dyn StatusProvider>>>,
+    /// Providers of the current connection, if there is one.
+    connection: Mutex<Option<Vec<Arc<dyn StatusProvider>>>>,
+}
+
+impl StatusCollector {
+    /// A collector for a module started at `started`.
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            providers: Mutex::default(),
+            connection: Mutex::default(),
+        }
+    }
+
+    /// Add `provider` for the life of the process.
+    pub fn register<P: StatusProvider + 'static>(&self, provider: Arc<P>) {
+        self.providers.lock().unwrap().push(provider);
+    }
+
+    /// Add the providers of a new connection, replacing those of the last one.
+    pub fn connected(&self, providers: Vec<Arc<dyn StatusProvider>>) {
+        *self.connection.lock().unwrap() = Some(providers);
+    }
+
+    /// The connection to the node has dropped, and its providers with it.
+    pub fn disconnected(&self) {
+        *self.connection.lock().unwrap() = None;
+    }
+
+    pub async fn collect(&self) -> ModuleStatus {
+        // The locks are not held across the awaits
+        let connection = self.connection.lock().unwrap().clone();
+        let mut providers = self.providers.lock().unwrap().clone();
+        providers.extend(connection.iter().flatten().cloned());
+        let mut status = ModuleStatus {
+            schema_version: STATUS_SCHEMA_VERSION,
+            uptime_secs: self.started.elapsed().as_secs(),
+            connected: connection.is_some(),
+            info: BTreeMap::new(),
+            sections: BTreeMap::new(),
+        };
+        for provider in providers {
+            for (name, value) in provider.info() {
+                status.info.insert(name.to_string(), value);
+            }
+            status
+                .sections
+                .insert(provider.section().to_string(), provider.status().await);
+        }
+        status
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for BuildInfo {
+    fn section(&self) -> &'static str {
+        "build"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(self)
+    }
+
+    fn info(&self) -> Vec<(&'static str, String)> {
+        let mut info = vec![("version", self.version.clone())];
+        info.extend(self.git_commit.clone().map(|c| ("git_commit", c)));
+        info
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for IpcMetrics {
+    fn section(&self) -> &'static str {
+        "ipc"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({ "events_received": self.snapshot().events })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for Checkpointer {
+    fn section(&self) -> &'static str {
+        "checkpoint"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({
+            "last_block_height": self.last().map(|c| c.height),
+            "events_processed": self.sequence(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for EventQueue {
+    fn section(&self) -> &'static str {
+        "event_queue"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&self.stats())
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for GovernanceWebhookClient {
+    fn section(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({
+            "deliveries": self.delivery_counts(),
+            "consecutive_failures": self.consecutive_failures(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for EconomicNodeRegistry {
+    fn section(&self) -> &'static str {
+        "registry"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({
+            "nodes": self.node_count().await,
+            "persisted": self.has_store(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for Heartbeat {
+    fn section(&self) -> &'static str {
+        "heartbeat"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&Heartbeat::status(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for EventStreamMonitor {
+    fn section(&self) -> &'static str {
+        "event_stream"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&EventStreamMonitor::status(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for ConfigReloader {
+    fn section(&self) -> &'static str {
+        "config_reload"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({ "last": self.last() })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for MemoryBudget {
+    fn section(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&self.usage())
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for ClockMonitor {
+    fn section(&self) -> &'static str {
+        "clock"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&ClockMonitor::status(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for PauseControl {
+    fn section(&self) -> &'static str {
+        "pause"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(&PauseControl::status(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for ErrorReporter {
+    fn section(&self) -> &'static str {
+        "errors"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        json!({ "recent": self.recent(), "counts": self.counts() })
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for NetworkStatus {
+    fn section(&self) -> &'static str {
+        "network"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        section(self)
+    }
+
+    fn info(&self) -> Vec<(&'static str, String)> {
+        self.network
+            .clone()
+            .map(|n| ("network", n))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for ProposalStore {
+    fn section(&self) -> &'static str {
+        "votes"
+    }
+
+    async fn status(&self) -> serde_json::Value {
+        match self.vote_checks() {
+            Ok(checks) => section(&checks),
+            Err(e) => json!({ "error": e.chain().to_string() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, serde_json::Value);
+
+    #[async_trait::async_trait]
+    impl StatusProvider for Fixed {
+        fn section(&self) -> &'static str {
+            self.0
+        }
+
+        async fn status(&self) -> serde_json::Value {
+            self.1.clone()
+        }
+
+        fn info(&self) -> Vec<(&'static str, String)> {
+            vec![("name", self.0.to_string())]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_sections_follow_the_connection() {
+        let collector = StatusCollector::new(Instant::now());
+        collector.register(Arc::new(Fixed("process", serde_json::json!({ "a": 1 }))));
+        let status = collector.collect().await;
+        assert!(!status.connected);
+        assert_eq!(status.sections.len(), 1);
+
+        collector.connected(vec![Arc::new(Fixed(
+            "connection",
+            serde_json::json!({ "b": { "c": "d" }, "e": [1, 2] }),
+        ))]);
+        let status = collector.collect().await;
+        assert!(status.connected);
+        assert_eq!(status.section("connection").unwrap()["b"]["c"], "d");
+        // Later providers win a clash of info entries
+        assert_eq!(status.info["name"], "connection");
+        assert_eq!(
+            status.to_string(),
+            "schema_version: 2\nuptime_secs: 0\nconnected: true\ninfo.name: connection\n\
+             connection.b.c: d\nconnection.e: [1,2]\nprocess.a: 1\n"
+        );
+
+        collector.disconnected();
+        assert!(collector.collect().await.section("connection").is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Periodic status reports to the node
+//!
+//! Every `status_report_interval_secs` the module sends the node its
+//! [`ModuleStatus`](crate::status::ModuleStatus) with
+//! `call_module(None, "module_status_report", ..)`, so the node's module list can show the
+//! module's health and the outcome of the last configuration reload (see
+//! [`crate::config_reload`]). Reports are sent from their own task with a short timeout, so a
+//! slow node never holds up event processing, and a report that fails to send is dropped. The
+//! task belongs to a connection and stops with it, so nothing is sent while disconnected.
+//!
+//! Reports include the memory each queue and cache holds against its share of
+//! `[governance.memory] budget_bytes` (see [`crate::memory`]), and the skew of the local
+//! clock from block time (see [`crate::clock`]). The most recent error reports are included
+//! too, whether or not they have been sent to the node yet (see [`crate::error_report`]).
+//! Reports before schema version 2 were a flat object with a `version` field.
+
+use crate::adoption::AdoptionTracker;
+use crate::checkpoint::Checkpointer;
+use crate::clock::ClockMonitor;
+use crate::config_reload::ConfigReloader;
+use crate::economic_nodes::EconomicNodeRegistry;
+use crate::error::GovernanceError;
+use crate::error_report::ErrorReporter;
+use crate::event_queue::EventQueue;
+use crate::event_stream::EventStreamMonitor;
+use crate::heartbeat::Heartbeat;
+use crate::ipc_metrics::IpcMetrics;
+use crate::memory::MemoryBudget;
+use crate::participation::ParticipationAnalyzer;
+use crate::status::{ModuleStatus, StatusCollector};
+use crate::webhook::GovernanceWebhookClient;
+use blvm_node::module::traits::NodeAPI;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Time allowed for sending one report.
+pub const STATUS_REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the health probes and metrics look at on a connection (see [`crate::health`] and
+/// [`crate::prometheus`]).
+#[derive(Clone)]
+pub struct StatusSources {
+    pub metrics: Arc<IpcMetrics>,
+    pub checkpointer: Arc<Checkpointer>,
+    pub events: Arc<EventQueue>,
+    pub webhook: Arc<GovernanceWebhookClient>,
+    pub registry: Arc<EconomicNodeRegistry>,
+    pub heartbeat: Arc<Heartbeat>,
+    pub stream: Arc<EventStreamMonitor>,
+    pub config_reload: Option<Arc<ConfigReloader>>,
+    pub memory: Arc<MemoryBudget>,
+    pub clock: Arc<ClockMonitor>,
+    pub errors: Arc<ErrorReporter>,
+    pub adoption: Option<Arc<AdoptionTracker>>,
+    pub participation: Option<Arc<ParticipationAnalyzer>>,
+}
+
+/// Send `report` to the node.
+pub async fn send(node_api: &dyn NodeAPI, report: &ModuleStatus) -> Result<(), GovernanceError> {
+    let payload = serde_json::to_vec(report)
+        .map_err(GovernanceError::serialization("module_status_report"))?;
+    crate::node_api::with_timeout(
+        "module_status_report",
+        STATUS_REPORT_TIMEOUT,
+        node_api.call_module(None, "module_status_report", payload),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Send the status `collector` assembles every `interval_secs` (0 disables) until the task is
+/// aborted.
+pub fn spawn(
+    collector: Arc<StatusCollector>,
+    node_api: Arc<dyn NodeAPI>,
+    interval_secs: u64,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if interval_secs == 0 {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let report = collector.collect().await;
+            if let Err(e) = send(node_api.as_ref(), &report).await {
+                debug!("Status report not sent: {}", e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockNodeApi;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_report_is_the_module_status() {
+        let collector = StatusCollector::new(Instant::now());
+        collector.register(Arc::new(Heartbeat::new()));
+        let node_api = MockNodeApi::new(100);
+        send(&node_api, &collector.collect().await).await.unwrap();
+
+        let calls = node_api.module_calls();
+        assert_eq!(calls[0].0, "module_status_report");
+        let report: ModuleStatus = serde_json::from_slice(&calls[0].1).unwrap();
+        assert_eq!(report.schema_version, crate::status::STATUS_SCHEMA_VERSION);
+        assert!(!report.connected);
+        assert_eq!(report.sections["heartbeat"]["consecutive_misses"], 0);
+    }
+}
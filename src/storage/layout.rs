@@ -0,0 +1,402 @@
+//! Layout of the module data directory
+//!
+//! The node gives the module a data directory, and [`DataDir`] owns what goes in it:
+//! components ask it for their paths rather than joining names onto the directory.
+//!
+//! ```text
+//! <data_dir>/
+//!   LAYOUT_VERSION         layout of the directory, written once it is complete
+//!   NETWORK                network the data belongs to (see crate::network)
+//!   blvm-governance.lock   held by the module process using the directory (see super::lock)
+//!   admin.sock             admin socket of the running module (see crate::admin)
+//!   config.toml            configuration, unless --config names another file
+//!   ...                    the module store (registry, proposals, action audit trail)
+//!   state/                 checkpoint.json (see crate::checkpoint), intents.json (see
+//!                          crate::intent)
+//!   backups/               scheduled backups (see crate::backup)
+//!   dry-run/               webhook payloads, with dry_run_files
+//!   crashes/               crash reports (see crate::crash)
+//!   spill/                 events over the memory budget (see crate::event_queue)
+//!   audit/                 audit log, with [governance.audit_log] (see crate::audit_log)
+//! ```
+//!
+//! A directory without `LAYOUT_VERSION` is layout 1, from before the marker, when everything
+//! was kept at the top. Opening an older layout runs the directory migrations up to
+//! [`LAYOUT_VERSION`]. `LAYOUT_VERSION.migrating` names the migration in progress, so one
+//! interrupted by a crash is reported and finished on the next start; each migration can be
+//! run again. A layout newer than this binary knows is refused, and so is a directory that
+//! does not match its marker: an unreadable marker, a managed path that is not a directory,
+//! or files where an earlier layout kept them, as an older binary started on a migrated
+//! directory would leave.
+
+use crate::admin::ADMIN_SOCKET;
+use crate::backup::BACKUPS_DIR;
+use crate::checkpoint::CHECKPOINT_FILE;
+use crate::error::GovernanceError;
+use crate::storage::lock::LOCK_FILE;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Layout this binary creates and uses.
+pub const LAYOUT_VERSION: u32 = 2;
+
+/// Holds the layout version of the directory.
+pub const MARKER_FILE: &str = "LAYOUT_VERSION";
+
+/// Holds the layout version a migration in progress is moving to.
+pub const MIGRATING_FILE: &str = "LAYOUT_VERSION.migrating";
+
+const STATE_DIR: &str = "state";
+const DRY_RUN_DIR: &str = "dry-run";
+const CRASHES_DIR: &str = "crashes";
+const SPILL_DIR: &str = "spill";
+const AUDIT_DIR: &str = "audit";
+const NETWORK_FILE: &str = "NETWORK";
+
+/// Created when the directory is opened.
+const SUBDIRS: &[&str] = &[STATE_DIR, BACKUPS_DIR];
+
+/// Moves a directory from the layout before `version` to `version`.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    run: fn(&Path) -> Result<(), GovernanceError>,
+    /// Paths the migration moves away from; finding one in a later layout means an older
+    /// binary has used the directory since.
+    moved: &'static [&'static str],
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 2,
+    description: "moved checkpoint.json into state/",
+    run: move_checkpoint,
+    moved: &[CHECKPOINT_FILE],
+}];
+
+fn io_error(path: &Path, e: std::io::Error) -> GovernanceError {
+    GovernanceError::io(path.display())(e)
+}
+
+fn corrupt(root: &Path, problem: String) -> GovernanceError {
+    GovernanceError::Storage(format!(
+        "data directory {} is inconsistent: {}; restore it from a backup, or fix it by hand",
+        root.display(),
+        problem
+    ))
+}
+
+/// Layout 2: `checkpoint.json` moves from the top into `state/`.
+fn move_checkpoint(root: &Path) -> Result<(), GovernanceError> {
+    let from = root.join(CHECKPOINT_FILE);
+    let to = root.join(STATE_DIR).join(CHECKPOINT_FILE);
+    if !from.exists() {
+        return Ok(());
+    }
+    if to.exists() {
+        return Err(corrupt(
+            root,
+            format!("both {} and {} exist", from.display(), to.display()),
+        ));
+    }
+    std::fs::rename(&from, &to).map_err(|e| io_error(&from, e))
+}
+
+/// The module data directory, opened at the current layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    /// Open the data directory at `root`, creating it and its subdirectories on first use and
+    /// migrating an older layout. Fails for a newer layout or an inconsistent directory.
+    pub fn open(root: &Path) -> Result<Self, GovernanceError> {
+        std::fs::create_dir_all(root).map_err(|e| io_error(root, e))?;
+        let dir = Self {
+            root: root.to_path_buf(),
+        };
+        let marker = dir.read_version(MARKER_FILE)?;
+        let fresh = marker.is_none() && dir.is_empty()?;
+        let mut version = marker.unwrap_or(1);
+        if version > LAYOUT_VERSION {
+            return Err(GovernanceError::Storage(format!(
+                "data directory {} has layout version {}, but blvm-governance {} only knows \
+                 versions up to {}; run the newer release that wrote it, or restore a backup \
+                 taken before the upgrade",
+                root.display(),
+                version,
+                env!("CARGO_PKG_VERSION"),
+                LAYOUT_VERSION
+            )));
+        }
+        if let Some(target) = dir.read_version(MIGRATING_FILE)? {
+            if target > LAYOUT_VERSION {
+                return Err(corrupt(
+                    root,
+                    format!(
+                        "a newer release was interrupted migrating it to layout version {}",
+                        target
+                    ),
+                ));
+            }
+            warn!(
+                "Migration of data directory {} to layout version {} was interrupted, finishing it",
+                root.display(),
+                target
+            );
+        }
+        for subdir in SUBDIRS {
+            let path = root.join(subdir);
+            if path.exists() && !path.is_dir() {
+                return Err(corrupt(
+                    root,
+                    format!("{} is not a directory", path.display()),
+                ));
+            }
+            std::fs::create_dir_all(&path).map_err(|e| io_error(&path, e))?;
+        }
+
+        if fresh {
+            dir.write_version(MARKER_FILE, LAYOUT_VERSION)?;
+            info!(
+                "Created data directory {} with layout version {}",
+                root.display(),
+                LAYOUT_VERSION
+            );
+            return Ok(dir);
+        }
+        let from = version;
+        for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+            dir.write_version(MIGRATING_FILE, migration.version)?;
+            (migration.run)(root)?;
+            dir.write_version(MARKER_FILE, migration.version)?;
+            info!(
+                "Migrated data directory {} to layout version {}: {}",
+                root.display(),
+                migration.version,
+                migration.description
+            );
+            version = migration.version;
+        }
+        let migrating = root.join(MIGRATING_FILE);
+        if migrating.exists() {
+            std::fs::remove_file(&migrating).map_err(|e| io_error(&migrating, e))?;
+        }
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            for moved in migration.moved {
+                let path = root.join(moved);
+                if path.exists() {
+                    return Err(corrupt(
+                        root,
+                        format!(
+                            "{} was moved by layout version {} but has been written again, \
+                             probably by an older release",
+                            path.display(),
+                            migration.version
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(dir)
+    }
+
+    /// Whether the directory holds nothing but the instance lock, taken before it is opened.
+    fn is_empty(&self) -> Result<bool, GovernanceError> {
+        let entries = std::fs::read_dir(&self.root).map_err(|e| io_error(&self.root, e))?;
+        Ok(entries
+            .filter_map(Result::ok)
+            .all(|entry| entry.file_name() == LOCK_FILE))
+    }
+
+    /// The version in `file`, if it exists.
+    fn read_version(&self, file: &str) -> Result<Option<u32>, GovernanceError> {
+        let path = self.root.join(file);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => content.trim().parse().map(Some).map_err(|_| {
+                corrupt(
+                    &self.root,
+                    format!("{} does not hold a layout version", path.display()),
+                )
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+
+    /// Write `version` to `file` via a temporary file and rename.
+    fn write_version(&self, file: &str, version: u32) -> Result<(), GovernanceError> {
+        let path = self.root.join(file);
+        let tmp = self.root.join(format!("{}.tmp", file));
+        std::fs::write(&tmp, format!("{}\n", version)).map_err(|e| io_error(&tmp, e))?;
+        std::fs::rename(&tmp, &path).map_err(|e| io_error(&path, e))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where the module store is opened; the SDK names the files in it.
+    pub fn store(&self) -> &Path {
+        &self.root
+    }
+
+    /// Holds the event checkpoint and the intent log.
+    pub fn state(&self) -> PathBuf {
+        self.root.join(STATE_DIR)
+    }
+
+    /// Holds scheduled backups, one directory each.
+    pub fn backups(&self) -> PathBuf {
+        self.root.join(BACKUPS_DIR)
+    }
+
+    /// Holds dry-run webhook payloads; created when the first one is written.
+    pub fn dry_run(&self) -> PathBuf {
+        self.root.join(DRY_RUN_DIR)
+    }
+
+    /// Holds crash reports; created when the first one is written.
+    pub fn crashes(&self) -> PathBuf {
+        self.root.join(CRASHES_DIR)
+    }
+
+    /// Holds events spilled over the memory budget; created when the first one is spilled.
+    pub fn spill(&self) -> PathBuf {
+        self.root.join(SPILL_DIR)
+    }
+
+    /// Where the running module serves the admin socket.
+    pub fn admin_socket(&self) -> PathBuf {
+        self.root.join(ADMIN_SOCKET)
+    }
+
+    /// Holds the audit log; created when the first entry is written.
+    pub fn audit(&self) -> PathBuf {
+        self.root.join(AUDIT_DIR)
+    }
+
+    /// Names the network the data belongs to; written on the first connection.
+    pub fn network(&self) -> PathBuf {
+        self.root.join(NETWORK_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("blvm_layout_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn marker(dir: &Path) -> String {
+        std::fs::read_to_string(dir.join(MARKER_FILE)).unwrap()
+    }
+
+    #[test]
+    fn test_creates_layout_on_first_use() {
+        let root = temp_dir("fresh");
+        let _lock = crate::storage::InstanceLock::acquire(&root).unwrap();
+        let dir = DataDir::open(&root).unwrap();
+        assert_eq!(marker(&root), format!("{}\n", LAYOUT_VERSION));
+        assert!(dir.state().is_dir() && dir.backups().is_dir());
+        assert!(!dir.dry_run().exists() && !dir.crashes().exists());
+        assert!(!dir.spill().exists() && !dir.audit().exists());
+        assert_eq!(dir.store(), root);
+
+        // Opening again changes nothing
+        assert_eq!(DataDir::open(&root).unwrap(), dir);
+        assert_eq!(marker(&root), format!("{}\n", LAYOUT_VERSION));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_migrates_unversioned_layout() {
+        let root = temp_dir("legacy");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(CHECKPOINT_FILE), b"{}").unwrap();
+        std::fs::write(root.join("config.toml"), b"").unwrap();
+
+        let dir = DataDir::open(&root).unwrap();
+        assert_eq!(marker(&root), format!("{}\n", LAYOUT_VERSION));
+        assert!(!root.join(CHECKPOINT_FILE).exists());
+        assert_eq!(
+            std::fs::read(dir.state().join(CHECKPOINT_FILE)).unwrap(),
+            b"{}"
+        );
+        assert!(root.join("config.toml").exists());
+        assert!(!root.join(MIGRATING_FILE).exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_refuses_newer_layout() {
+        let root = temp_dir("newer");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(MARKER_FILE), format!("{}\n", LAYOUT_VERSION + 1)).unwrap();
+        let err = DataDir::open(&root).unwrap_err().to_string();
+        assert!(err.contains("newer release"), "{}", err);
+        // Left as it was
+        assert!(!root.join(STATE_DIR).exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_finishes_interrupted_migration() {
+        // Interrupted after moving the checkpoint, before writing the marker
+        let root = temp_dir("interrupted");
+        std::fs::create_dir_all(root.join(STATE_DIR)).unwrap();
+        std::fs::write(root.join(STATE_DIR).join(CHECKPOINT_FILE), b"{}").unwrap();
+        std::fs::write(root.join(MIGRATING_FILE), b"2\n").unwrap();
+
+        DataDir::open(&root).unwrap();
+        assert_eq!(marker(&root), format!("{}\n", LAYOUT_VERSION));
+        assert!(!root.join(MIGRATING_FILE).exists());
+        assert!(root.join(STATE_DIR).join(CHECKPOINT_FILE).exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_reports_inconsistent_directory() {
+        let root = temp_dir("inconsistent");
+        DataDir::open(&root).unwrap();
+
+        // Written at the top again, as a release before layout 2 would
+        std::fs::write(root.join(CHECKPOINT_FILE), b"{}").unwrap();
+        let err = DataDir::open(&root).unwrap_err().to_string();
+        assert!(
+            err.contains("inconsistent") && err.contains(CHECKPOINT_FILE),
+            "{}",
+            err
+        );
+        std::fs::remove_file(root.join(CHECKPOINT_FILE)).unwrap();
+
+        std::fs::write(root.join(MARKER_FILE), b"two").unwrap();
+        assert!(DataDir::open(&root).is_err());
+        std::fs::write(root.join(MARKER_FILE), b"2\n").unwrap();
+
+        std::fs::remove_dir_all(root.join(BACKUPS_DIR)).unwrap();
+        std::fs::write(root.join(BACKUPS_DIR), b"").unwrap();
+        assert!(DataDir::open(&root)
+            .unwrap_err()
+            .to_string()
+            .contains("not a directory"));
+        std::fs::remove_dir_all(&root).ok();
+
+        // Both the old and new place
+        let root = temp_dir("conflict");
+        std::fs::create_dir_all(root.join(STATE_DIR)).unwrap();
+        std::fs::write(root.join(CHECKPOINT_FILE), b"{}").unwrap();
+        std::fs::write(root.join(STATE_DIR).join(CHECKPOINT_FILE), b"{}").unwrap();
+        assert!(DataDir::open(&root)
+            .unwrap_err()
+            .to_string()
+            .contains("both"));
+        assert!(!root.join(MARKER_FILE).exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
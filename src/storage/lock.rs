@@ -0,0 +1,129 @@
+//! Instance lock
+//!
+//! One module process at a time may use a data directory: two would both write the store,
+//! the checkpoint and the backups. Before anything else touches the directory, the module
+//! takes an exclusive advisory lock (`flock`) on `blvm-governance.lock` in it and holds it
+//! until the process exits. The kernel releases the lock however the process ends, so a lock
+//! file left behind by a crash or `kill -9` does not block the next start; the file is never
+//! deleted, as deleting it would let two processes lock different files of the same name.
+//! It holds the PID of the holder, which a second process names in its error.
+//!
+//! On platforms without `flock` no lock is taken.
+
+use crate::error::GovernanceError;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+pub const LOCK_FILE: &str = "blvm-governance.lock";
+
+/// The lock on a data directory, held until dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Lock `data_dir`, creating it if needed. Fails if another process holds the lock.
+    pub fn acquire(data_dir: &Path) -> Result<Self, GovernanceError> {
+        let path = data_dir.join(LOCK_FILE);
+        let err = |e| GovernanceError::io(path.display())(e);
+        std::fs::create_dir_all(data_dir).map_err(err)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(err)?;
+        if !try_lock(&file).map_err(err)? {
+            // Empty if the holder has not written its PID yet
+            let mut content = String::new();
+            let _ = file.read_to_string(&mut content);
+            let holder = match content.trim().parse::<u32>() {
+                Ok(pid) => format!("another blvm-governance process (pid {})", pid),
+                Err(_) => "another blvm-governance process".to_string(),
+            };
+            return Err(GovernanceError::Storage(format!(
+                "data directory {} is in use by {}; stop it first, or start this one with \
+                 another --data-dir",
+                data_dir.display(),
+                holder
+            )));
+        }
+        file.set_len(0).map_err(err)?;
+        file.rewind().map_err(err)?;
+        writeln!(file, "{}", std::process::id()).map_err(err)?;
+        file.sync_all().map_err(err)?;
+        Ok(Self { path, _file: file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Take an exclusive lock on `file` without waiting; `false` if another holds it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the descriptor stays open for as long as `file` is borrowed
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let e = std::io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("blvm_lock_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+
+        let lock = InstanceLock::acquire(&dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(lock.path()).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        // Each open file has a lock of its own, so this conflicts within the process too
+        let err = InstanceLock::acquire(&dir).unwrap_err().to_string();
+        assert!(
+            err.contains(&format!("pid {}", std::process::id())),
+            "{}",
+            err
+        );
+
+        drop(lock);
+        InstanceLock::acquire(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_stale_lock_file_does_not_block() {
+        let dir = std::env::temp_dir().join(format!("blvm_lock_stale_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(LOCK_FILE), "4194304\nleftover\n").unwrap();
+
+        let lock = InstanceLock::acquire(&dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(lock.path()).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,115 @@
+//! Storage for blvm-governance
+//!
+//! Store migrations, the layout of the data directory around the store (see [`layout`]), and
+//! the lock that keeps a second module process out of it (see [`lock`]).
+//!
+//! v1: Migrate proposals from legacy "items" tree to "proposals".
+//! v2: Add the lifecycle fields to stored proposals (see [`crate::proposals`]).
+//! v3: Add tally milestones to stored proposals (see [`crate::tally`]).
+
+use crate::proposals::{GovernanceProposal, ProposalStatus, ProposalVote};
+use blvm_sdk::module::{MigrationContext, MigrationUp};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub mod layout;
+pub mod lock;
+
+pub use layout::DataDir;
+pub use lock::InstanceLock;
+
+const PROPOSALS_TREE: &str = "proposals";
+
+pub fn up_v1(ctx: &MigrationContext) -> anyhow::Result<()> {
+    let items_tree = ctx.open_tree("items")?;
+    if let Some(data) = items_tree.get(b"governance:proposals")? {
+        let proposals_tree = ctx.open_tree(PROPOSALS_TREE)?;
+        proposals_tree.insert(b"proposals", &data)?;
+    }
+    Ok(())
+}
+
+/// A proposal as stored before v2.
+#[derive(Deserialize)]
+struct ProposalV1 {
+    proposal_id: String,
+    repository: String,
+    pr_number: u64,
+    tier: String,
+    status: StatusV1,
+    votes: Vec<ProposalVote>,
+}
+
+#[derive(Deserialize)]
+enum StatusV1 {
+    Created,
+    Merged,
+}
+
+/// A proposal as stored by v2.
+#[derive(Serialize, Deserialize)]
+struct ProposalV2 {
+    proposal_id: String,
+    repository: String,
+    pr_number: u64,
+    tier: String,
+    status: ProposalStatus,
+    votes: Vec<ProposalVote>,
+    author: Option<String>,
+    created_height: Option<u64>,
+    closed_height: Option<u64>,
+}
+
+/// Rewrite the stored proposals from `Old` to `New` with `convert`.
+fn rewrite<Old, New>(ctx: &MigrationContext, convert: impl Fn(Old) -> New) -> anyhow::Result<()>
+where
+    Old: serde::de::DeserializeOwned,
+    New: Serialize,
+{
+    let tree = ctx.open_tree(PROPOSALS_TREE)?;
+    let Some(data) = tree.get(b"proposals")? else {
+        return Ok(());
+    };
+    let proposals: HashMap<String, Old> = bincode::deserialize(&data)?;
+    let proposals: HashMap<String, New> = proposals
+        .into_iter()
+        .map(|(id, p)| (id, convert(p)))
+        .collect();
+    tree.insert(b"proposals", &bincode::serialize(&proposals)?)?;
+    Ok(())
+}
+
+pub fn up_v2(ctx: &MigrationContext) -> anyhow::Result<()> {
+    rewrite(ctx, |p: ProposalV1| ProposalV2 {
+        status: match p.status {
+            StatusV1::Created if p.votes.is_empty() => ProposalStatus::Created,
+            StatusV1::Created => ProposalStatus::Voting,
+            StatusV1::Merged => ProposalStatus::Merged,
+        },
+        proposal_id: p.proposal_id,
+        repository: p.repository,
+        pr_number: p.pr_number,
+        tier: p.tier,
+        votes: p.votes,
+        author: None,
+        created_height: None,
+        closed_height: None,
+    })
+}
+
+pub fn up_v3(ctx: &MigrationContext) -> anyhow::Result<()> {
+    rewrite(ctx, |p: ProposalV2| GovernanceProposal {
+        proposal_id: p.proposal_id,
+        repository: p.repository,
+        pr_number: p.pr_number,
+        tier: p.tier,
+        status: p.status,
+        votes: p.votes,
+        author: p.author,
+        created_height: p.created_height,
+        closed_height: p.closed_height,
+        milestones: Vec::new(),
+    })
+}
+
+pub const MIGRATIONS: &[(u32, MigrationUp)] = &[(1, up_v1), (2, up_v2), (3, up_v3)];
@@ -0,0 +1,160 @@
+//! Event subscriptions
+//!
+//! The event types the module wants, kept for the life of the process so every connection
+//! to the node (including reconnects) subscribes to the same set. Types can be unsubscribed
+//! and re-subscribed at runtime, e.g. to turn off `NewBlock` during initial sync; the node
+//! API has no way to change the subscription of an open connection, so events of
+//! unsubscribed types are ignored at dispatch until the next connection, which subscribes
+//! to the current set only. Events already queued when a type is unsubscribed are still
+//! processed.
+
+use crate::error::GovernanceError;
+use blvm_node::module::EventType;
+use std::sync::Mutex;
+use tracing::info;
+
+/// Desired event subscriptions, out of the event types the module handles.
+#[derive(Debug, Default)]
+pub struct EventSubscriptions {
+    /// Types the module has handlers for, in subscription order.
+    supported: Vec<EventType>,
+    types: Mutex<Vec<EventType>>,
+}
+
+impl EventSubscriptions {
+    /// Subscribe to all of `types`, the event types the module handles.
+    pub fn new(types: impl IntoIterator<Item = EventType>) -> Self {
+        let mut supported = Vec::new();
+        for event_type in types {
+            if !supported.contains(&event_type) {
+                supported.push(event_type);
+            }
+        }
+        Self {
+            types: Mutex::new(supported.clone()),
+            supported,
+        }
+    }
+
+    /// Event types the module handles.
+    pub fn supported(&self) -> &[EventType] {
+        &self.supported
+    }
+
+    /// Resolve event type names (e.g. `NewBlock`, case-insensitive) among the supported types.
+    pub fn parse(&self, names: &[String]) -> Result<Vec<EventType>, GovernanceError> {
+        names
+            .iter()
+            .map(|name| {
+                self.supported
+                    .iter()
+                    .find(|t| format!("{:?}", t).eq_ignore_ascii_case(name.trim()))
+                    .cloned()
+                    .ok_or_else(|| GovernanceError::ValidationError {
+                        field: "event_types".to_string(),
+                        reason: format!(
+                            "unknown event type {} (supported: {:?})",
+                            name, self.supported
+                        ),
+                    })
+            })
+            .collect()
+    }
+
+    /// Subscribe to event types. Types already subscribed are ignored; returns the newly
+    /// added ones.
+    pub fn add(
+        &self,
+        types: impl IntoIterator<Item = EventType>,
+    ) -> Result<Vec<EventType>, GovernanceError> {
+        let types: Vec<EventType> = types.into_iter().collect();
+        if let Some(unsupported) = types.iter().find(|t| !self.supported.contains(t)) {
+            return Err(GovernanceError::ValidationError {
+                field: "event_types".to_string(),
+                reason: format!("no handler for event type {:?}", unsupported),
+            });
+        }
+        let mut current = self.types.lock().unwrap();
+        let mut added = Vec::new();
+        for event_type in types {
+            if !current.contains(&event_type) {
+                current.push(event_type);
+                added.push(event_type);
+            }
+        }
+        // Keep subscription order stable
+        current.sort_by_key(|t| self.supported.iter().position(|s| s == t));
+        if !added.is_empty() {
+            info!("Subscribed to event types: {:?}", added);
+        }
+        Ok(added)
+    }
+
+    /// Unsubscribe from event types; returns the ones that were subscribed.
+    pub fn remove(&self, types: impl IntoIterator<Item = EventType>) -> Vec<EventType> {
+        let mut current = self.types.lock().unwrap();
+        let mut removed = Vec::new();
+        for event_type in types {
+            if let Some(i) = current.iter().position(|t| *t == event_type) {
+                removed.push(current.remove(i));
+            }
+        }
+        if !removed.is_empty() {
+            info!("Unsubscribed from event types: {:?}", removed);
+        }
+        removed
+    }
+
+    pub fn contains(&self, event_type: &EventType) -> bool {
+        self.types.lock().unwrap().contains(event_type)
+    }
+
+    /// The event types to subscribe to on (re)connect.
+    pub fn current(&self) -> Vec<EventType> {
+        self.types.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_is_idempotent() {
+        let subscriptions = EventSubscriptions::new([
+            EventType::NewBlock,
+            EventType::NewBlock,
+            EventType::EconomicNodeVeto,
+        ]);
+        assert_eq!(
+            subscriptions.current(),
+            vec![EventType::NewBlock, EventType::EconomicNodeVeto]
+        );
+
+        assert_eq!(
+            subscriptions.remove([EventType::NewBlock, EventType::NewBlock]),
+            vec![EventType::NewBlock]
+        );
+        assert!(!subscriptions.contains(&EventType::NewBlock));
+        let added = subscriptions
+            .add([EventType::NewBlock, EventType::EconomicNodeVeto])
+            .unwrap();
+        assert_eq!(added, vec![EventType::NewBlock]);
+        assert!(subscriptions.add([EventType::NewBlock]).unwrap().is_empty());
+        assert_eq!(
+            subscriptions.current(),
+            vec![EventType::NewBlock, EventType::EconomicNodeVeto]
+        );
+    }
+
+    #[test]
+    fn test_only_handled_types() {
+        let subscriptions = EventSubscriptions::new([EventType::NewBlock]);
+        assert!(subscriptions.add([EventType::EconomicNodeVeto]).is_err());
+        assert_eq!(
+            subscriptions.parse(&["newblock".to_string()]).unwrap(),
+            vec![EventType::NewBlock]
+        );
+        assert!(subscriptions.parse(&["Bogus".to_string()]).is_err());
+    }
+}
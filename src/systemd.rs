@@ -0,0 +1,277 @@
+//! systemd service notifications
+//!
+//! When systemd starts the module with `NOTIFY_SOCKET` set (a `Type=notify` or
+//! `Type=notify-reload` unit), the module tells it how it is doing:
+//!
+//! - `READY=1` once connected to the node and subscribed to events, so units ordered after
+//!   this one wait for that rather than for the process to start;
+//! - `RELOADING=1` while the configuration is being reloaded (see [`crate::config_reload`]),
+//!   then `READY=1` again;
+//! - `STOPPING=1` when a shutdown begins;
+//! - `WATCHDOG=1` every half `WatchdogSec=`, from a task on the runtime, and only while event
+//!   processing is not stalled (see [`Health::events_stalled`]). A wedged runtime or event
+//!   loop stops the pings and systemd restarts the module.
+//!
+//! `STATUS=` lines say whether the module is connected. Without `NOTIFY_SOCKET` (or off
+//! Unix) every notification is a no-op.
+
+use crate::health::Health;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Socket to send notifications on, and systemd's address.
+#[cfg(unix)]
+type Socket = (
+    std::os::unix::net::UnixDatagram,
+    std::os::unix::net::SocketAddr,
+);
+#[cfg(not(unix))]
+type Socket = std::convert::Infallible;
+
+/// Sends notifications to systemd, if the module runs under it.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    socket: Option<Socket>,
+    /// Longest time systemd waits between watchdog pings.
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// A notifier for the socket in `NOTIFY_SOCKET`, or one that does nothing if it is unset
+    /// or unusable.
+    pub fn from_env() -> Self {
+        let watchdog = watchdog_timeout(
+            std::env::var("WATCHDOG_USEC").ok(),
+            std::env::var("WATCHDOG_PID").ok(),
+        );
+        match std::env::var_os("NOTIFY_SOCKET") {
+            Some(path) => Self::connect(std::path::Path::new(&path), watchdog),
+            None => Self::default(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn connect(path: &std::path::Path, watchdog: Option<Duration>) -> Self {
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+        let address = match path.to_str().and_then(|p| p.strip_prefix('@')) {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name)
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => Err(std::io::ErrorKind::Unsupported.into()),
+            None => SocketAddr::from_pathname(path),
+        };
+        match address.and_then(|address| Ok((UnixDatagram::unbound()?, address))) {
+            Ok(socket) => {
+                info!("Notifying systemd at {}", path.display());
+                Self {
+                    socket: Some(socket),
+                    watchdog,
+                }
+            }
+            Err(e) => {
+                warn!("Not notifying systemd at {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn connect(_path: &std::path::Path, _watchdog: Option<Duration>) -> Self {
+        Self::default()
+    }
+
+    /// Whether notifications are sent anywhere.
+    pub fn is_enabled(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// Connected to the node and subscribed.
+    pub fn ready(&self) {
+        self.notify("READY=1\nSTATUS=Connected to the node");
+    }
+
+    /// Lost the connection to the node; the module reconnects.
+    pub fn disconnected(&self) {
+        self.notify("STATUS=Reconnecting to the node");
+    }
+
+    /// A configuration reload has begun; [`Notifier::reloaded`] ends it.
+    pub fn reloading(&self) {
+        self.notify(&format!("RELOADING=1\nMONOTONIC_USEC={}", monotonic_usec()));
+    }
+
+    /// A configuration reload has finished, applied or not.
+    pub fn reloaded(&self) {
+        self.notify("READY=1");
+    }
+
+    /// A shutdown has begun.
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1\nSTATUS=Shutting down");
+    }
+
+    /// Ping the watchdog every half `WatchdogSec=` until the task is aborted, skipping pings
+    /// while `health` reports event processing stalled. `None` without a watchdog.
+    pub fn spawn_watchdog(self: &Arc<Self>, health: Arc<Health>) -> Option<JoinHandle<()>> {
+        let timeout = self.watchdog.filter(|_| self.is_enabled())?;
+        info!("Pinging the systemd watchdog every {:?}", timeout / 2);
+        let notifier = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(timeout / 2);
+            let mut stalled = false;
+            loop {
+                interval.tick().await;
+                let stall = health.events_stalled();
+                match &stall {
+                    None if stalled => {
+                        info!("Event processing resumed, pinging the systemd watchdog again");
+                        notifier.notify("WATCHDOG=1");
+                    }
+                    None => notifier.notify("WATCHDOG=1"),
+                    Some(detail) if !stalled => {
+                        warn!("Not pinging the systemd watchdog: {}", detail);
+                    }
+                    Some(_) => {}
+                }
+                stalled = stall.is_some();
+            }
+        }))
+    }
+
+    fn notify(&self, state: &str) {
+        #[cfg(unix)]
+        if let Some((socket, address)) = &self.socket {
+            if let Err(e) = socket.send_to_addr(state.as_bytes(), address) {
+                debug!("Failed to notify systemd: {}", e);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = state;
+    }
+}
+
+/// The watchdog timeout systemd gave this process: `WATCHDOG_USEC`, if `WATCHDOG_PID` is
+/// unset or names this process.
+fn watchdog_timeout(usec: Option<String>, pid: Option<String>) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = usec?.trim().parse::<u64>().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec))
+}
+
+/// `CLOCK_MONOTONIC` in microseconds, which systemd compares reload notifications against.
+#[cfg(unix)]
+fn monotonic_usec() -> u64 {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `now` is a valid timespec to write to, and CLOCK_MONOTONIC always exists
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+    now.tv_sec as u64 * 1_000_000 + now.tv_nsec as u64 / 1_000
+}
+
+#[cfg(not(unix))]
+fn monotonic_usec() -> u64 {
+    0
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::HealthConfig;
+    use crate::heartbeat::Heartbeat;
+    use crate::shutdown::Shutdown;
+    use crate::subscriptions::EventSubscriptions;
+    use std::os::unix::net::UnixDatagram;
+
+    /// A socket standing in for systemd's, and a notifier sending to it.
+    fn systemd(name: &str, watchdog: Option<Duration>) -> (UnixDatagram, Notifier) {
+        let path = std::env::temp_dir().join(format!("blvm_{}_{}", name, std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        // The notifier sends to the path, so it stays bound for the test
+        let notifier = Notifier::connect(&path, watchdog);
+        (socket, notifier)
+    }
+
+    fn receive(socket: &UnixDatagram) -> String {
+        let mut buf = [0u8; 256];
+        let len = socket.recv(&mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_notifications() {
+        let (socket, notifier) = systemd("notify", None);
+        assert!(notifier.is_enabled());
+        notifier.ready();
+        assert_eq!(receive(&socket), "READY=1\nSTATUS=Connected to the node");
+        notifier.reloading();
+        assert!(receive(&socket).starts_with("RELOADING=1\nMONOTONIC_USEC="));
+        notifier.reloaded();
+        assert_eq!(receive(&socket), "READY=1");
+        notifier.stopping();
+        assert_eq!(receive(&socket), "STOPPING=1\nSTATUS=Shutting down");
+
+        // Not under systemd
+        let notifier = Notifier::default();
+        assert!(!notifier.is_enabled());
+        notifier.ready();
+    }
+
+    #[test]
+    fn test_watchdog_timeout() {
+        let pid = std::process::id().to_string();
+        let usec = || Some("30000000".to_string());
+        assert_eq!(
+            watchdog_timeout(usec(), None),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            watchdog_timeout(usec(), Some(pid)),
+            Some(Duration::from_secs(30))
+        );
+        // Meant for another process
+        assert_eq!(watchdog_timeout(usec(), Some("1".to_string())), None);
+        assert_eq!(watchdog_timeout(Some("0".to_string()), None), None);
+        assert_eq!(watchdog_timeout(None, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_pings() {
+        let (socket, notifier) = systemd("watchdog", Some(Duration::from_millis(100)));
+        let health = Arc::new(Health::new(
+            HealthConfig::default(),
+            Arc::new(Shutdown::new()),
+            Arc::new(Heartbeat::new()),
+            Arc::new(EventSubscriptions::new([])),
+        ));
+        let watchdog = Arc::new(notifier).spawn_watchdog(health).unwrap();
+        let pings = tokio::task::spawn_blocking(move || [receive(&socket), receive(&socket)])
+            .await
+            .unwrap();
+        assert_eq!(pings, ["WATCHDOG=1", "WATCHDOG=1"]);
+        watchdog.abort();
+
+        assert!(Arc::new(Notifier::default())
+            .spawn_watchdog(Arc::new(Health::new(
+                HealthConfig::default(),
+                Arc::new(Shutdown::new()),
+                Arc::new(Heartbeat::new()),
+                Arc::new(EventSubscriptions::new([])),
+            )))
+            .is_none());
+    }
+}
@@ -0,0 +1,463 @@
+//! Vote tallies and thresholds
+//!
+//! A proposal's [`Tally`] counts its votes by choice, one per voter: a later vote from the
+//! same voter replaces the earlier one (see [`crate::proposals`]). Votes other than yes, no
+//! and abstain are not counted. The tally is computed from the stored votes, which only the
+//! node's vote events change, so replaying the events in the audit log (see
+//! [`crate::replay`]) gives the same tallies.
+//!
+//! Thresholds are set per tier under `[governance.tally.tiers.<tier>]`. A proposal reaches
+//! quorum once `quorum` voters have voted, abstentions included, and is approved pending merge
+//! once it has quorum and at least `approval_percent` of its yes and no votes are yes. Each
+//! [`Milestone`] is recorded with the proposal when it is first reached and sent to the webhook
+//! as `proposal_quorum_reached` or `proposal_approved_pending_merge`; a later vote that takes
+//! the tally back below the threshold does not undo it.
+//!
+//! With delegation on, each voter's delegators count too (see [`crate::delegation`]): the
+//! proposal store keeps the raw tally of the votes cast and the delegated tally of every
+//! identity's weight as [`Tallies`], and measures the thresholds against the delegated one.
+//! Without delegation the two are the same.
+//!
+//! With `weights` set under `[governance.tally]`, each proposal also has a [`RegistryTally`]:
+//! every vote, delegated ones included, weighted by its voter's economic weight in the
+//! registry. Voters are matched to registered nodes by their hex node id, and those not
+//! registered carry `default_weight`. The weights are the live registry's effective weights
+//! (`current`) or those of the epoch snapshot the proposal is pinned to (`snapshot`), read
+//! afresh each time the tally is computed ([`VoterWeights`]): on every vote and every block,
+//! so a voter whose weight changes after voting counts at the new weight from the next block.
+//! Tiers with thresholds under `[governance.tally.weighted.<tier>]` are measured against it
+//! instead ([`reached_by_weight`]): quorum as a percent of the total weight, approval as a
+//! percent of the yes and no weight. Notifications of the milestones say which weighting
+//! reached them ([`Weighting`]).
+
+use crate::config::{TierThresholds, WeightThresholds};
+use crate::economic_nodes::parse_node_id;
+use crate::economic_nodes::snapshot::{EpochState, RegistrySnapshot, SnapshotEntry};
+use crate::proposals::ProposalVote;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteChoice {
+    Yes,
+    No,
+    Abstain,
+}
+
+impl VoteChoice {
+    /// The choice a vote event's `vote` names, if any.
+    pub fn parse(vote: &str) -> Option<Self> {
+        match vote.trim().to_ascii_lowercase().as_str() {
+            "yes" | "approve" | "ack" => Some(Self::Yes),
+            "no" | "reject" | "nack" => Some(Self::No),
+            "abstain" => Some(Self::Abstain),
+            _ => None,
+        }
+    }
+}
+
+/// Votes on a proposal by choice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub yes: u64,
+    pub no: u64,
+    pub abstain: u64,
+}
+
+impl Tally {
+    /// The tally of `votes`, at most one per voter.
+    pub fn of(votes: &[ProposalVote]) -> Self {
+        let mut tally = Self::default();
+        for vote in votes {
+            match VoteChoice::parse(&vote.vote) {
+                Some(VoteChoice::Yes) => tally.yes += 1,
+                Some(VoteChoice::No) => tally.no += 1,
+                Some(VoteChoice::Abstain) => tally.abstain += 1,
+                None => {}
+            }
+        }
+        tally
+    }
+
+    /// Voters counted, abstentions included.
+    pub fn total(&self) -> u64 {
+        self.yes + self.no + self.abstain
+    }
+
+    /// Share of yes votes among yes and no votes, in percent; `None` without either.
+    pub fn approval_percent(&self) -> Option<f64> {
+        let decided = self.yes + self.no;
+        (decided > 0).then(|| self.yes as f64 * 100.0 / decided as f64)
+    }
+}
+
+/// A proposal's tallies as last computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tallies {
+    /// The votes cast.
+    pub raw: Tally,
+    /// With the weight of delegators given to the votes their delegations lead to.
+    pub delegated: Tally,
+    /// Chain tip they were computed at.
+    pub height: Option<u64>,
+}
+
+/// Votes on a proposal by choice, each with its voter's registry weight.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeightedTally {
+    pub yes: f64,
+    pub no: f64,
+    pub abstain: f64,
+}
+
+impl WeightedTally {
+    /// The tally of `votes`, at most one per voter, each counting `weight(voter)`.
+    pub fn of(votes: &[ProposalVote], weight: impl Fn(&str) -> f64) -> Self {
+        let mut tally = Self::default();
+        for vote in votes {
+            let weight = weight(&vote.voter);
+            match VoteChoice::parse(&vote.vote) {
+                Some(VoteChoice::Yes) => tally.yes += weight,
+                Some(VoteChoice::No) => tally.no += weight,
+                Some(VoteChoice::Abstain) => tally.abstain += weight,
+                None => {}
+            }
+        }
+        tally
+    }
+
+    /// Weight counted, abstentions included.
+    pub fn total(&self) -> f64 {
+        self.yes + self.no + self.abstain
+    }
+
+    /// Share of the yes weight among yes and no, in percent; `None` without either.
+    pub fn approval_percent(&self) -> Option<f64> {
+        let decided = self.yes + self.no;
+        (decided > 0.0).then(|| self.yes * 100.0 / decided)
+    }
+}
+
+/// A proposal's tally by registry weight as last computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryTally {
+    pub tally: WeightedTally,
+    /// Weight of every registered node, and of the unregistered voters counted.
+    pub total_weight: f64,
+    /// Epoch snapshot the weights came from, `None` for the live registry.
+    pub snapshot: Option<u64>,
+    /// Chain tip it was computed at.
+    pub height: Option<u64>,
+}
+
+impl RegistryTally {
+    /// Share of the total weight that voted, abstentions included, in percent; `None`
+    /// without any weight.
+    pub fn participation_percent(&self) -> Option<f64> {
+        (self.total_weight > 0.0).then(|| self.tally.total() * 100.0 / self.total_weight)
+    }
+}
+
+/// Registry weights of voters, read once for a pass over proposal tallies.
+#[derive(Debug, Clone, Default)]
+pub struct VoterWeights {
+    live: HashMap<[u8; 32], f64>,
+    /// With weights from epoch snapshots, the snapshots and the proposals pinned to them.
+    epochs: Option<EpochState>,
+    default_weight: f64,
+}
+
+impl VoterWeights {
+    /// The weights of `live` entries, and of the snapshots of `epochs` for the proposals
+    /// pinned to one.
+    pub fn new(live: Vec<SnapshotEntry>, epochs: Option<EpochState>) -> Self {
+        Self {
+            live: live.into_iter().map(|e| (e.node_id, e.weight)).collect(),
+            epochs,
+            default_weight: 0.0,
+        }
+    }
+
+    /// Give voters not in the registry `weight`.
+    pub fn with_default_weight(mut self, weight: f64) -> Self {
+        self.default_weight = weight;
+        self
+    }
+
+    /// The snapshot `proposal_id`'s weights come from, if any.
+    fn snapshot(&self, proposal_id: &str) -> Option<&RegistrySnapshot> {
+        self.epochs.as_ref()?.for_proposal(proposal_id)
+    }
+
+    /// Registry weight of `voter` in `proposal_id`'s tally, if registered.
+    fn registered(&self, proposal_id: &str, voter: &str) -> Option<f64> {
+        let node_id = parse_node_id(voter)?;
+        match self.snapshot(proposal_id) {
+            Some(snapshot) => snapshot.weight_of(&node_id),
+            None => self.live.get(&node_id).copied(),
+        }
+    }
+
+    /// Weight of `voter` in `proposal_id`'s tally.
+    pub fn weight_of(&self, proposal_id: &str, voter: &str) -> f64 {
+        self.registered(proposal_id, voter)
+            .unwrap_or(self.default_weight)
+    }
+
+    /// The tally of `votes` on `proposal_id` at `height`, weighted.
+    pub fn tally(
+        &self,
+        proposal_id: &str,
+        votes: &[ProposalVote],
+        height: Option<u64>,
+    ) -> RegistryTally {
+        let snapshot = self.snapshot(proposal_id);
+        let registered = match snapshot {
+            Some(snapshot) => snapshot.total_weight(),
+            None => self.live.values().sum(),
+        };
+        let unregistered: f64 = votes
+            .iter()
+            .filter(|v| self.registered(proposal_id, &v.voter).is_none())
+            .filter(|v| VoteChoice::parse(&v.vote).is_some())
+            .map(|_| self.default_weight)
+            .sum();
+        RegistryTally {
+            tally: WeightedTally::of(votes, |voter| self.weight_of(proposal_id, voter)),
+            total_weight: registered + unregistered,
+            snapshot: snapshot.map(|s| s.id),
+            height,
+        }
+    }
+}
+
+/// How the tally that reached a milestone was counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weighting {
+    /// One vote per identity.
+    Count,
+    /// By registry weight.
+    Weight,
+}
+
+impl Weighting {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Weight => "weight",
+        }
+    }
+}
+
+/// A threshold a proposal's tally has crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Milestone {
+    QuorumReached,
+    ApprovedPendingMerge,
+}
+
+impl Milestone {
+    /// Event type of the webhook notification.
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::QuorumReached => "proposal_quorum_reached",
+            Self::ApprovedPendingMerge => "proposal_approved_pending_merge",
+        }
+    }
+}
+
+/// A milestone as recorded with the proposal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MilestoneReached {
+    pub milestone: Milestone,
+    /// Chain tip when it was reached.
+    pub height: Option<u64>,
+    /// The tally that reached it.
+    pub tally: Tally,
+}
+
+/// The milestones `tally` has reached under `thresholds`, in order.
+pub fn reached(thresholds: &TierThresholds, tally: &Tally) -> Vec<Milestone> {
+    let mut reached = Vec::new();
+    if tally.total() < thresholds.quorum {
+        return reached;
+    }
+    if thresholds.quorum > 0 {
+        reached.push(Milestone::QuorumReached);
+    }
+    let approved = tally
+        .approval_percent()
+        .is_some_and(|percent| percent >= thresholds.approval_percent);
+    if thresholds.approval_percent > 0.0 && approved {
+        reached.push(Milestone::ApprovedPendingMerge);
+    }
+    reached
+}
+
+/// The milestones `tally` has reached under the weight-based `thresholds`, in order.
+pub fn reached_by_weight(thresholds: &WeightThresholds, tally: &RegistryTally) -> Vec<Milestone> {
+    let mut reached = Vec::new();
+    let participation = tally.participation_percent().unwrap_or(0.0);
+    if participation < thresholds.quorum_percent {
+        return reached;
+    }
+    if thresholds.quorum_percent > 0.0 {
+        reached.push(Milestone::QuorumReached);
+    }
+    let approved = tally
+        .tally
+        .approval_percent()
+        .is_some_and(|percent| percent >= thresholds.approval_percent);
+    if thresholds.approval_percent > 0.0 && approved {
+        reached.push(Milestone::ApprovedPendingMerge);
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(yes: u64, no: u64, abstain: u64) -> Tally {
+        Tally { yes, no, abstain }
+    }
+
+    #[test]
+    fn test_thresholds_are_reached_at_the_boundary() {
+        let thresholds = TierThresholds {
+            quorum: 4,
+            approval_percent: 75.0,
+        };
+        assert_eq!(reached(&thresholds, &tally(3, 0, 0)), vec![]);
+        // Abstentions count toward quorum only
+        assert_eq!(
+            reached(&thresholds, &tally(2, 1, 1)),
+            vec![Milestone::QuorumReached]
+        );
+        assert_eq!(
+            reached(&thresholds, &tally(3, 1, 0)),
+            vec![Milestone::QuorumReached, Milestone::ApprovedPendingMerge]
+        );
+        assert_eq!(
+            reached(&thresholds, &tally(0, 0, 4)),
+            vec![Milestone::QuorumReached]
+        );
+
+        // No quorum needed: approval alone
+        let thresholds = TierThresholds {
+            quorum: 0,
+            approval_percent: 50.0,
+        };
+        assert_eq!(reached(&thresholds, &tally(0, 0, 0)), vec![]);
+        assert_eq!(
+            reached(&thresholds, &tally(1, 1, 0)),
+            vec![Milestone::ApprovedPendingMerge]
+        );
+        assert_eq!(reached(&TierThresholds::default(), &tally(5, 0, 0)), vec![]);
+    }
+
+    #[test]
+    fn test_tally_of_votes() {
+        let vote = |vote: &str| ProposalVote {
+            voter: vote.to_string(),
+            vote: vote.to_string(),
+        };
+        let votes = [
+            vote("Yes"),
+            vote("ACK"),
+            vote("nack"),
+            vote("abstain"),
+            vote("maybe"),
+        ];
+        assert_eq!(Tally::of(&votes), tally(2, 1, 1));
+        assert_eq!(Tally::of(&votes).approval_percent(), Some(200.0 / 3.0));
+        assert_eq!(Tally::default().approval_percent(), None);
+    }
+
+    #[test]
+    fn test_weighted_thresholds_are_reached_at_the_boundary() {
+        let thresholds = WeightThresholds {
+            quorum_percent: 40.0,
+            approval_percent: 75.0,
+        };
+        let weighted = |yes: f64, no: f64, abstain: f64| RegistryTally {
+            tally: WeightedTally { yes, no, abstain },
+            total_weight: 10.0,
+            snapshot: None,
+            height: Some(100),
+        };
+        assert_eq!(
+            reached_by_weight(&thresholds, &weighted(3.5, 0.0, 0.0)),
+            vec![]
+        );
+        assert_eq!(
+            reached_by_weight(&thresholds, &weighted(2.0, 1.0, 1.0)),
+            vec![Milestone::QuorumReached]
+        );
+        assert_eq!(
+            reached_by_weight(&thresholds, &weighted(3.0, 1.0, 0.0)),
+            vec![Milestone::QuorumReached, Milestone::ApprovedPendingMerge]
+        );
+        // Nothing registered: no quorum to reach
+        let empty = RegistryTally::default();
+        assert_eq!(reached_by_weight(&thresholds, &empty), vec![]);
+    }
+
+    #[test]
+    fn test_voter_weights() {
+        let entry = |id: u8, weight: f64| SnapshotEntry {
+            node_id: [id; 32],
+            node_type: "miner".to_string(),
+            weight,
+        };
+        let vote = |voter: &str, vote: &str| ProposalVote {
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        };
+        let votes = [
+            vote(&hex::encode([1u8; 32]), "yes"),
+            vote(&hex::encode([2u8; 32]), "no"),
+            vote("alice", "yes"),
+        ];
+
+        let live = VoterWeights::new(vec![entry(1, 3.0), entry(2, 1.0), entry(3, 4.0)], None);
+        let weighted = live.tally("1", &votes, Some(100));
+        assert_eq!(
+            weighted.tally,
+            WeightedTally {
+                yes: 3.0,
+                no: 1.0,
+                abstain: 0.0
+            }
+        );
+        assert_eq!(weighted.total_weight, 8.0);
+        assert_eq!(weighted.participation_percent(), Some(50.0));
+
+        // Unregistered voters count at the default weight, toward the total too
+        let weighted = live.with_default_weight(2.0).tally("1", &votes, None);
+        assert_eq!(weighted.tally.yes, 5.0);
+        assert_eq!(weighted.total_weight, 10.0);
+
+        // Proposals pinned to a snapshot take its weights
+        let mut epochs = EpochState::default();
+        let mut snapshot = RegistrySnapshot::take(
+            1,
+            2016,
+            std::iter::empty::<&crate::economic_nodes::EconomicNode>(),
+            0,
+            &Default::default(),
+        );
+        snapshot.nodes = vec![entry(1, 1.0), entry(2, 1.0)];
+        epochs.snapshots.insert(1, snapshot);
+        epochs.proposal_snapshots.insert("1".to_string(), 1);
+        let pinned = VoterWeights::new(vec![entry(1, 3.0)], Some(epochs));
+        let weighted = pinned.tally("1", &votes, None);
+        assert_eq!((weighted.tally.yes, weighted.tally.no), (1.0, 1.0));
+        assert_eq!((weighted.total_weight, weighted.snapshot), (2.0, Some(1)));
+        let weighted = pinned.tally("2", &votes, None);
+        assert_eq!((weighted.tally.yes, weighted.snapshot), (3.0, None));
+    }
+}
@@ -0,0 +1,778 @@
+//! Fake node for tests
+//!
+//! [`MockNodeApi`] implements [`NodeAPI`] from in-memory state, so the webhook client, the
+//! registry, [`NodeApiIpc`](crate::node_api::NodeApiIpc) and `EventHandler`
+//! implementations built on them can be tested without a running node. Populate it with
+//! the `with_*` builders (or the matching `add_*` methods once it is shared), then check
+//! what the code under test asked for with [`MockNodeApi::lookups`] and
+//! [`MockNodeApi::module_calls`]. Requests can be slowed down or made to fail per method.
+//!
+//! Available with the `testing` feature. The API is semi-stable: methods may be added in
+//! any release, but existing ones keep their behaviour until the next minor version.
+//! Methods the module does not use answer with empty values or `Ok(())`.
+
+use crate::chain_work::Work;
+use crate::economic_nodes::reserve::{self, ClaimedOutpoint, ReserveClaim};
+use crate::node_api::{NodeEconomicNode, ProposalDetails};
+use blvm_node::module::ipc::protocol::ModuleMessage;
+use blvm_node::module::traits::{EventType, ModuleError, NodeAPI};
+use blvm_protocol::{Block, Hash, OutPoint, Transaction, UTXO};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory [`NodeAPI`]. See the [module documentation](self).
+#[derive(Default)]
+pub struct MockNodeApi {
+    block_height: u64,
+    /// Best block hash and height, once set; otherwise an all-zero hash at `block_height`.
+    tip: Mutex<Option<(Hash, u64)>>,
+    /// Blocks served by `get_block` and `get_block_header`, by hash.
+    blocks: Mutex<HashMap<Hash, Block>>,
+    /// Hashes of the blocks served by `get_block_by_height`, by height.
+    heights: Mutex<HashMap<u64, Hash>>,
+    /// Transactions served by `get_transaction`, by txid.
+    transactions: Mutex<HashMap<Hash, Transaction>>,
+    /// The mempool, by txid.
+    mempool: Mutex<HashMap<Hash, Transaction>>,
+    /// The UTXO set, by txid and output index.
+    utxos: Mutex<HashMap<(Hash, u64), UTXO>>,
+    /// Proposals served by `get_proposal`, by id.
+    proposals: Mutex<HashMap<String, ProposalDetails>>,
+    /// Chain work of the tip, reported by `get_chain_info`.
+    chain_work: Mutex<u128>,
+    /// Whether `get_chain_info` reports the node still syncing.
+    syncing: AtomicBool,
+    /// Chain work served by `get_block_chainwork`, by block hash. While empty, the method
+    /// is rejected as unknown.
+    block_chainwork: Mutex<HashMap<Hash, Work>>,
+    /// Fee estimates in sat/vB by confirmation target; other targets get 1.
+    fee_estimates: Mutex<HashMap<u32, u64>>,
+    /// Minimum relay fee in sat/kvB, 1000 unless set.
+    min_relay_fee: Mutex<Option<u64>>,
+    /// Network reported by `get_network`, "regtest" unless set.
+    network: Mutex<Option<String>>,
+    /// Economic nodes served by `list_economic_nodes`, by id, once any was added.
+    economic_nodes: Mutex<Option<BTreeMap<String, NodeEconomicNode>>>,
+    /// Event types published by the module, in order.
+    published: Mutex<Vec<EventType>>,
+    /// Every request received, by method name, in order.
+    lookups: Mutex<Vec<String>>,
+    /// `call_module` requests: method and payload.
+    calls: Mutex<Vec<(String, Vec<u8>)>>,
+    /// Responses to `call_module` by method, or the error to fail with.
+    responses: Mutex<HashMap<String, Result<Vec<u8>, String>>>,
+    /// Errors the next requests for a method fail with, in order, by method name.
+    failures: Mutex<HashMap<String, VecDeque<String>>>,
+    /// Errors single requests fail with, by method name and request number.
+    nth_failures: Mutex<HashMap<(String, usize), String>>,
+    /// Delay before each block is returned, as a round trip to the node would take.
+    latency: Mutex<Duration>,
+    /// Delay before answering requests for a method, by method name.
+    slow: Mutex<HashMap<String, Duration>>,
+}
+
+impl MockNodeApi {
+    /// A node at `block_height` with nothing else in it.
+    pub fn new(block_height: u64) -> Self {
+        Self {
+            block_height,
+            ..Self::default()
+        }
+    }
+
+    /// Serve `block` under `hash` at `height`.
+    pub fn with_block(self, height: u64, hash: Hash, block: Block) -> Self {
+        self.add_block(height, hash, block);
+        self
+    }
+
+    /// Serve `tx` as a confirmed transaction.
+    pub fn with_transaction(self, txid: Hash, tx: Transaction) -> Self {
+        self.add_transaction(txid, tx);
+        self
+    }
+
+    /// Put `tx` in the mempool.
+    pub fn with_mempool_transaction(self, txid: Hash, tx: Transaction) -> Self {
+        self.add_mempool_transaction(txid, tx);
+        self
+    }
+
+    /// Make `utxo` unspent at `outpoint`.
+    pub fn with_utxo(self, outpoint: OutPoint, utxo: UTXO) -> Self {
+        self.add_utxo(outpoint, utxo);
+        self
+    }
+
+    /// Serve `details` from `get_proposal`.
+    pub fn with_proposal(self, details: ProposalDetails) -> Self {
+        self.add_proposal(details);
+        self
+    }
+
+    /// List `node` in `list_economic_nodes`.
+    pub fn with_economic_node(self, node: NodeEconomicNode) -> Self {
+        self.add_economic_node(node);
+        self
+    }
+
+    /// Delay each block returned by `latency`.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.set_latency(latency);
+        self
+    }
+
+    /// Serve `block` under `hash` at `height`.
+    pub fn add_block(&self, height: u64, hash: Hash, block: Block) {
+        self.blocks.lock().unwrap().insert(hash, block);
+        self.heights.lock().unwrap().insert(height, hash);
+    }
+
+    /// Replace the chain from `height` with `blocks`, by hash, and make the last one the tip.
+    /// Heights above the new tip are no longer served; the old blocks still are by hash.
+    pub fn reorg(&self, height: u64, blocks: Vec<(Hash, Block)>) {
+        let mut heights = self.heights.lock().unwrap();
+        heights.retain(|h, _| *h < height);
+        let mut tip = None;
+        for (h, (hash, block)) in (height..).zip(blocks) {
+            self.blocks.lock().unwrap().insert(hash, block);
+            heights.insert(h, hash);
+            tip = Some((hash, h));
+        }
+        if tip.is_some() {
+            *self.tip.lock().unwrap() = tip;
+        }
+    }
+
+    /// Serve no block at `height`. The block there is still served by hash.
+    pub fn remove_height(&self, height: u64) {
+        self.heights.lock().unwrap().remove(&height);
+    }
+
+    /// Serve `tx` as a confirmed transaction.
+    pub fn add_transaction(&self, txid: Hash, tx: Transaction) {
+        self.transactions.lock().unwrap().insert(txid, tx);
+    }
+
+    /// Put `tx` in the mempool.
+    pub fn add_mempool_transaction(&self, txid: Hash, tx: Transaction) {
+        self.mempool.lock().unwrap().insert(txid, tx);
+    }
+
+    /// Empty the mempool, as when its transactions are mined.
+    pub fn clear_mempool(&self) {
+        self.mempool.lock().unwrap().clear();
+    }
+
+    /// Make `utxo` unspent at `outpoint`.
+    pub fn add_utxo(&self, outpoint: OutPoint, utxo: UTXO) {
+        self.utxos
+            .lock()
+            .unwrap()
+            .insert((outpoint.hash, outpoint.index as u64), utxo);
+    }
+
+    /// Lock `sats` in a new confirmed output to a key derived from `node_id`, and return a
+    /// reserve claim for the node that verifies to exactly those sats. Each call adds a new
+    /// output; the claim covers only that one.
+    pub fn add_reserve(&self, node_id: &[u8; 32], sats: u64) -> ReserveClaim {
+        let secp = Secp256k1::signing_only();
+        let sk = SecretKey::from_slice(&reserve::message_digest(&hex::encode(node_id)))
+            .expect("a SHA256 digest is a valid secret key");
+        let public_key = PublicKey::from_secret_key(&secp, &sk).serialize();
+        let mut utxos = self.utxos.lock().unwrap();
+        let outpoint = ClaimedOutpoint {
+            txid: reserve::message_digest(&format!("reserve {}", utxos.len())),
+            vout: 0,
+        };
+        let mut script_pubkey = vec![0x00, 0x14];
+        script_pubkey.extend_from_slice(&reserve::hash160(&public_key));
+        utxos.insert(
+            (outpoint.txid, 0),
+            UTXO {
+                value: sats as i64,
+                script_pubkey: script_pubkey.into(),
+                height: 0,
+                is_coinbase: false,
+            },
+        );
+        let message = reserve::registration_message(node_id, &[outpoint]);
+        let digest = Message::from_digest(reserve::message_digest(&message));
+        ReserveClaim {
+            public_key: public_key.to_vec(),
+            outpoints: vec![outpoint],
+            signature: secp.sign_ecdsa(&digest, &sk).serialize_compact().to_vec(),
+        }
+    }
+
+    /// Spend the output at `outpoint`.
+    pub fn spend_utxo(&self, outpoint: &OutPoint) {
+        self.utxos
+            .lock()
+            .unwrap()
+            .remove(&(outpoint.hash, outpoint.index as u64));
+    }
+
+    /// Serve `details` from `get_proposal`, replacing any earlier record.
+    pub fn add_proposal(&self, details: ProposalDetails) {
+        self.proposals
+            .lock()
+            .unwrap()
+            .insert(details.proposal_id.clone(), details);
+    }
+
+    /// List `node` in `list_economic_nodes`, replacing any earlier entry. Until a node is
+    /// added, `list_economic_nodes` is answered like any other `call_module` request.
+    pub fn add_economic_node(&self, node: NodeEconomicNode) {
+        self.economic_nodes
+            .lock()
+            .unwrap()
+            .get_or_insert_with(BTreeMap::new)
+            .insert(node.node_id.clone(), node);
+    }
+
+    /// Report `chain_work` as the tip's chain work.
+    pub fn set_chain_work(&self, chain_work: u128) {
+        *self.chain_work.lock().unwrap() = chain_work;
+    }
+
+    /// Serve `chainwork` as the chain work up to block `hash` from `get_block_chainwork`.
+    pub fn add_block_chainwork(&self, hash: Hash, chainwork: Work) {
+        self.block_chainwork.lock().unwrap().insert(hash, chainwork);
+    }
+
+    /// Estimate `sat_per_vbyte` for confirmation within `target_blocks`; 0 means no estimate.
+    pub fn set_fee_estimate(&self, target_blocks: u32, sat_per_vbyte: u64) {
+        self.fee_estimates
+            .lock()
+            .unwrap()
+            .insert(target_blocks, sat_per_vbyte);
+    }
+
+    /// Report the node as syncing, or not, from `get_chain_info`.
+    pub fn set_syncing(&self, syncing: bool) {
+        self.syncing.store(syncing, Ordering::Relaxed);
+    }
+
+    /// Relay transactions paying at least `sat_per_kvb`.
+    pub fn set_min_relay_fee(&self, sat_per_kvb: u64) {
+        *self.min_relay_fee.lock().unwrap() = Some(sat_per_kvb);
+    }
+
+    /// Report `network` from `get_network`.
+    pub fn set_network(&self, network: &str) {
+        *self.network.lock().unwrap() = Some(network.to_string());
+    }
+
+    /// Make `hash` at `height` the best block; lower than before is a reorg.
+    pub fn set_tip(&self, hash: Hash, height: u64) {
+        *self.tip.lock().unwrap() = Some((hash, height));
+    }
+
+    fn chain_tip(&self) -> (Hash, u64) {
+        self.tip
+            .lock()
+            .unwrap()
+            .unwrap_or(([0u8; 32], self.block_height))
+    }
+
+    /// Delay each block returned by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    async fn round_trip(&self) {
+        let latency = *self.latency.lock().unwrap();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    /// Answer requests for `method` only after `delay`.
+    pub fn slow_down(&self, method: &str, delay: Duration) {
+        self.slow.lock().unwrap().insert(method.to_string(), delay);
+    }
+
+    /// Fail the next requests for `method` with `errors`, one per request.
+    pub fn fail_next(&self, method: &str, errors: &[&str]) {
+        self.failures
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .extend(errors.iter().map(|e| e.to_string()));
+    }
+
+    /// Fail the `n`th request for `method`, counting from 1 since the mock was created,
+    /// with `error`.
+    pub fn fail_nth(&self, method: &str, n: usize, error: &str) {
+        self.nth_failures
+            .lock()
+            .unwrap()
+            .insert((method.to_string(), n), error.to_string());
+    }
+
+    /// Errors still waiting to fail requests for `method`, from [`MockNodeApi::fail_next`].
+    pub fn pending_failures(&self, method: &str) -> usize {
+        self.failures
+            .lock()
+            .unwrap()
+            .get(method)
+            .map_or(0, VecDeque::len)
+    }
+
+    /// Record a request for `method`, wait out its delay and fail it if scripted to.
+    async fn answer(&self, method: &str) -> Result<(), ModuleError> {
+        let n = {
+            let mut lookups = self.lookups.lock().unwrap();
+            lookups.push(method.to_string());
+            lookups.iter().filter(|m| *m == method).count()
+        };
+        let delay = self.slow.lock().unwrap().get(method).copied();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        let nth = self
+            .nth_failures
+            .lock()
+            .unwrap()
+            .remove(&(method.to_string(), n));
+        let next = || {
+            self.failures
+                .lock()
+                .unwrap()
+                .get_mut(method)
+                .and_then(VecDeque::pop_front)
+        };
+        match nth.or_else(next) {
+            Some(error) => Err(ModuleError::OperationError(error)),
+            None => Ok(()),
+        }
+    }
+
+    /// Methods of every request received, in order. `call_module` requests appear under the
+    /// method called.
+    pub fn lookups(&self) -> Vec<String> {
+        self.lookups.lock().unwrap().clone()
+    }
+
+    /// Requests received for `method`.
+    pub fn lookup_count(&self, method: &str) -> usize {
+        self.lookups
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| *m == method)
+            .count()
+    }
+
+    /// Event types published, in order.
+    pub fn published(&self) -> Vec<EventType> {
+        self.published.lock().unwrap().clone()
+    }
+
+    /// Answer `call_module` requests for `method` with `response`.
+    pub fn respond(&self, method: &str, response: Result<Vec<u8>, String>) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(method.to_string(), response);
+    }
+
+    /// Accept every governance action submitted.
+    pub fn accept_actions(&self) {
+        self.respond(
+            "submit_governance_action",
+            Ok(br#"{"accepted":true}"#.to_vec()),
+        );
+    }
+
+    /// Reject every governance action submitted, giving `reason`.
+    pub fn reject_actions(&self, reason: &str) {
+        let response = serde_json::json!({ "accepted": false, "reason": reason });
+        self.respond(
+            "submit_governance_action",
+            Ok(serde_json::to_vec(&response).unwrap()),
+        );
+    }
+
+    /// `call_module` requests received: method and payload, in order.
+    pub fn module_calls(&self) -> Vec<(String, Vec<u8>)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Methods of the `call_module` requests received, in order.
+    pub fn called(&self) -> Vec<String> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, _)| method.clone())
+            .collect()
+    }
+
+    /// The response to a `call_module` request for `method` with `payload`.
+    fn module_response(&self, method: &str, payload: &[u8]) -> Result<Vec<u8>, ModuleError> {
+        if let Some(response) = self.responses.lock().unwrap().get(method) {
+            return response.clone().map_err(ModuleError::OperationError);
+        }
+        match method {
+            "get_proposal" => {
+                let request: serde_json::Value =
+                    serde_json::from_slice(payload).unwrap_or_default();
+                let id = request["proposal_id"].as_str().unwrap_or_default();
+                let details = self.proposals.lock().unwrap().get(id).cloned();
+                Ok(serde_json::to_vec(&details).unwrap())
+            }
+            "get_block_chainwork" => {
+                let chainwork = self.block_chainwork.lock().unwrap();
+                if chainwork.is_empty() {
+                    return Err(ModuleError::OperationError(format!(
+                        "unknown method {}",
+                        method
+                    )));
+                }
+                let request: serde_json::Value =
+                    serde_json::from_slice(payload).unwrap_or_default();
+                let hash = hex::decode(request["hash"].as_str().unwrap_or_default())
+                    .ok()
+                    .and_then(|hash| Hash::try_from(hash).ok());
+                match hash.and_then(|hash| chainwork.get(&hash)) {
+                    Some(work) => {
+                        Ok(serde_json::to_vec(&serde_json::json!({ "chainwork": work })).unwrap())
+                    }
+                    None => Err(ModuleError::OperationError("block not found".to_string())),
+                }
+            }
+            "get_min_relay_fee" => {
+                let sat_per_kvb = self.min_relay_fee.lock().unwrap().unwrap_or(1000);
+                Ok(serde_json::to_vec(&serde_json::json!({ "sat_per_kvb": sat_per_kvb })).unwrap())
+            }
+            "get_network" => {
+                let network = self.network.lock().unwrap().clone();
+                let network = network.unwrap_or_else(|| "regtest".to_string());
+                Ok(serde_json::to_vec(&serde_json::json!({ "network": network })).unwrap())
+            }
+            "list_economic_nodes" => match &*self.economic_nodes.lock().unwrap() {
+                Some(nodes) => {
+                    let nodes: Vec<_> = nodes.values().collect();
+                    Ok(serde_json::to_vec(&nodes).unwrap())
+                }
+                None => Ok(Vec::new()),
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeAPI for MockNodeApi {
+    async fn get_block_height(&self) -> Result<u64, ModuleError> {
+        self.answer("get_block_height").await?;
+        Ok(self.chain_tip().1)
+    }
+    async fn get_block(&self, hash: &Hash) -> Result<Option<blvm_protocol::Block>, ModuleError> {
+        self.round_trip().await;
+        self.answer("get_block").await?;
+        Ok(self.blocks.lock().unwrap().get(hash).cloned())
+    }
+    async fn get_block_header(
+        &self,
+        hash: &Hash,
+    ) -> Result<Option<blvm_protocol::BlockHeader>, ModuleError> {
+        self.answer("get_block_header").await?;
+        Ok(self
+            .blocks
+            .lock()
+            .unwrap()
+            .get(hash)
+            .map(|block| block.header.clone()))
+    }
+    async fn get_transaction(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<blvm_protocol::Transaction>, ModuleError> {
+        self.answer("get_transaction").await?;
+        Ok(self.transactions.lock().unwrap().get(txid).cloned())
+    }
+    async fn has_transaction(&self, txid: &Hash) -> Result<bool, ModuleError> {
+        self.answer("has_transaction").await?;
+        Ok(self.transactions.lock().unwrap().contains_key(txid))
+    }
+    async fn get_chain_tip(&self) -> Result<Hash, ModuleError> {
+        self.answer("get_chain_tip").await?;
+        Ok(self.chain_tip().0)
+    }
+    async fn get_utxo(&self, outpoint: &OutPoint) -> Result<Option<UTXO>, ModuleError> {
+        self.answer("get_utxo").await?;
+        let key = (outpoint.hash, outpoint.index as u64);
+        Ok(self.utxos.lock().unwrap().get(&key).cloned())
+    }
+    async fn subscribe_events(
+        &self,
+        _: Vec<EventType>,
+    ) -> Result<tokio::sync::mpsc::Receiver<ModuleMessage>, ModuleError> {
+        let (_tx, rx) = tokio::sync::mpsc::channel(100);
+        Ok(rx)
+    }
+    async fn get_mempool_transactions(&self) -> Result<Vec<Hash>, ModuleError> {
+        self.answer("get_mempool_transactions").await?;
+        Ok(self.mempool.lock().unwrap().keys().copied().collect())
+    }
+    async fn get_mempool_transaction(
+        &self,
+        txid: &Hash,
+    ) -> Result<Option<blvm_protocol::Transaction>, ModuleError> {
+        self.answer("get_mempool_transaction").await?;
+        Ok(self.mempool.lock().unwrap().get(txid).cloned())
+    }
+    async fn get_mempool_size(
+        &self,
+    ) -> Result<blvm_node::module::traits::MempoolSize, ModuleError> {
+        Ok(blvm_node::module::traits::MempoolSize {
+            transaction_count: 0,
+            size_bytes: 0,
+            total_fee_sats: 0,
+        })
+    }
+    async fn get_network_stats(
+        &self,
+    ) -> Result<blvm_node::module::traits::NetworkStats, ModuleError> {
+        Ok(blvm_node::module::traits::NetworkStats {
+            peer_count: 0,
+            hash_rate: 0.0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        })
+    }
+    async fn get_network_peers(
+        &self,
+    ) -> Result<Vec<blvm_node::module::traits::PeerInfo>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn get_chain_info(&self) -> Result<blvm_node::module::traits::ChainInfo, ModuleError> {
+        self.answer("get_chain_info").await?;
+        let (tip_hash, height) = self.chain_tip();
+        Ok(blvm_node::module::traits::ChainInfo {
+            tip_hash,
+            height,
+            difficulty: 1,
+            chain_work: *self.chain_work.lock().unwrap() as _,
+            is_synced: !self.syncing.load(Ordering::Relaxed),
+        })
+    }
+    async fn get_block_by_height(
+        &self,
+        height: u64,
+    ) -> Result<Option<blvm_protocol::Block>, ModuleError> {
+        self.round_trip().await;
+        self.answer("get_block_by_height").await?;
+        let Some(hash) = self.heights.lock().unwrap().get(&height).copied() else {
+            return Ok(None);
+        };
+        Ok(self.blocks.lock().unwrap().get(&hash).cloned())
+    }
+    async fn get_lightning_node_url(&self) -> Result<Option<String>, ModuleError> {
+        Ok(None)
+    }
+    async fn get_lightning_info(
+        &self,
+    ) -> Result<Option<blvm_node::module::traits::LightningInfo>, ModuleError> {
+        Ok(None)
+    }
+    async fn get_payment_state(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::traits::PaymentState>, ModuleError> {
+        Ok(None)
+    }
+    async fn check_transaction_in_mempool(&self, _: &Hash) -> Result<bool, ModuleError> {
+        Ok(false)
+    }
+    async fn get_fee_estimate(&self, target_blocks: u32) -> Result<u64, ModuleError> {
+        self.answer("get_fee_estimate").await?;
+        let estimate = self
+            .fee_estimates
+            .lock()
+            .unwrap()
+            .get(&target_blocks)
+            .copied();
+        Ok(estimate.unwrap_or(1))
+    }
+    async fn read_file(&self, _: String) -> Result<Vec<u8>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn write_file(&self, _: String, _: Vec<u8>) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn delete_file(&self, _: String) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn list_directory(&self, _: String) -> Result<Vec<String>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn create_directory(&self, _: String) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn get_file_metadata(
+        &self,
+        _: String,
+    ) -> Result<blvm_node::module::ipc::protocol::FileMetadata, ModuleError> {
+        Ok(blvm_node::module::ipc::protocol::FileMetadata {
+            path: String::new(),
+            size: 0,
+            is_file: false,
+            is_directory: false,
+            modified: None,
+            created: None,
+        })
+    }
+    async fn get_all_metrics(
+        &self,
+    ) -> Result<HashMap<String, Vec<blvm_node::module::metrics::manager::Metric>>, ModuleError>
+    {
+        Ok(HashMap::new())
+    }
+    async fn register_rpc_endpoint(&self, _: String, _: String) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn unregister_rpc_endpoint(&self, _: &str) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn register_timer(
+        &self,
+        _: u64,
+        _: Arc<dyn blvm_node::module::timers::manager::TimerCallback>,
+    ) -> Result<blvm_node::module::timers::manager::TimerId, ModuleError> {
+        Ok(0)
+    }
+    async fn cancel_timer(
+        &self,
+        _: blvm_node::module::timers::manager::TimerId,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn schedule_task(
+        &self,
+        _: u64,
+        _: Arc<dyn blvm_node::module::timers::manager::TaskCallback>,
+    ) -> Result<blvm_node::module::timers::manager::TaskId, ModuleError> {
+        Ok(0)
+    }
+    async fn report_metric(
+        &self,
+        _: blvm_node::module::metrics::manager::Metric,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn get_module_metrics(
+        &self,
+        _: &str,
+    ) -> Result<Vec<blvm_node::module::metrics::manager::Metric>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn initialize_module(
+        &self,
+        _: String,
+        _: std::path::PathBuf,
+        _: std::path::PathBuf,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn discover_modules(
+        &self,
+    ) -> Result<Vec<blvm_node::module::traits::ModuleInfo>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn get_module_info(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::traits::ModuleInfo>, ModuleError> {
+        Ok(None)
+    }
+    async fn is_module_available(&self, _: &str) -> Result<bool, ModuleError> {
+        Ok(false)
+    }
+    async fn publish_event(
+        &self,
+        event_type: EventType,
+        _: blvm_node::module::ipc::protocol::EventPayload,
+    ) -> Result<(), ModuleError> {
+        self.published.lock().unwrap().push(event_type);
+        Ok(())
+    }
+    async fn call_module(
+        &self,
+        _: Option<&str>,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, ModuleError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push((method.to_string(), payload.clone()));
+        self.answer(method).await?;
+        self.module_response(method, &payload)
+    }
+    async fn register_module_api(
+        &self,
+        _: Arc<dyn blvm_node::module::inter_module::api::ModuleAPI>,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn unregister_module_api(&self) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn get_module_health(
+        &self,
+        _: &str,
+    ) -> Result<Option<blvm_node::module::process::monitor::ModuleHealth>, ModuleError> {
+        Ok(None)
+    }
+    async fn get_all_module_health(
+        &self,
+    ) -> Result<Vec<(String, blvm_node::module::process::monitor::ModuleHealth)>, ModuleError> {
+        Ok(Vec::new())
+    }
+    async fn report_module_health(
+        &self,
+        _: blvm_node::module::process::monitor::ModuleHealth,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn send_mesh_packet_to_module(
+        &self,
+        _: &str,
+        _: Vec<u8>,
+        _: String,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn send_mesh_packet_to_peer(&self, _: String, _: Vec<u8>) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn send_stratum_v2_message_to_peer(
+        &self,
+        _: String,
+        _: Vec<u8>,
+    ) -> Result<(), ModuleError> {
+        Ok(())
+    }
+    async fn get_block_template(
+        &self,
+        _: Vec<String>,
+        _: Option<Vec<u8>>,
+        _: Option<String>,
+    ) -> Result<blvm_protocol::mining::BlockTemplate, ModuleError> {
+        Err(ModuleError::Other("not implemented".into()))
+    }
+    async fn submit_block(
+        &self,
+        _: blvm_protocol::Block,
+    ) -> Result<blvm_node::module::traits::SubmitBlockResult, ModuleError> {
+        Err(ModuleError::Other("not implemented".into()))
+    }
+}
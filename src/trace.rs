@@ -0,0 +1,62 @@
+//! Correlation ids for tracing
+//!
+//! Each event from the node is processed under a fresh trace id, in an `event` span that
+//! the handler, NodeAPI request and webhook delivery spans nest under. The id is also
+//! recorded on the request spans, sent with webhook deliveries as `trace_id`, so the
+//! receiving app can join its logs with the module's, and recorded on audit log entries
+//! (see [`crate::audit_log`]). Outside event processing (reconciliation, the registry feed)
+//! there is no current id and each webhook delivery gets its own.
+//!
+//! NodeAPI and webhook spans are at debug level; their fields are only formatted when a
+//! subscriber enables them.
+
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// A new trace id: 32 hex digits, unique across processes.
+pub fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = Sha256::new();
+    hasher.update(std::process::id().to_le_bytes());
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// Trace id of the event the current task is processing, if any.
+pub fn current_id() -> Option<String> {
+    TRACE_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `future` with `trace_id` as the [`current_id`].
+pub async fn with_id<F: Future>(trace_id: String, future: F) -> F::Output {
+    TRACE_ID.scope(trace_id, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current_id_scoped_to_future() {
+        let (a, b) = (new_id(), new_id());
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 32);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+
+        assert_eq!(current_id(), None);
+        let seen = with_id(a.clone(), async { current_id() }).await;
+        assert_eq!(seen, Some(a));
+        assert_eq!(current_id(), None);
+    }
+}
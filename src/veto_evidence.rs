@@ -0,0 +1,586 @@
+//! Veto evidence bundles
+//!
+//! When a proposal's veto threshold is crossed, reported to the node or disputed, a bundle
+//! lets anyone check the tally without the module's store. `blvm-governance
+//! export-veto-evidence --proposal <id> --signing-key <file> --out <file>` writes one from the
+//! stored state, [`EconomicNodeRegistry::veto_evidence`] and [`assemble`] build one from the
+//! running stores, and `verify-veto-evidence <file>` ([`verify`]) checks one. A bundle holds:
+//!
+//! - the proposal as stored, with its content check, which carries the content hash (see
+//!   [`crate::content`]);
+//! - every open veto on it, with the key signatures it came with and the vetoing node's
+//!   registration and verification records: its key or key set, its reserve claim with the
+//!   claim's signature, its address proof and its verification status;
+//! - the weights the tally counted: those of the epoch snapshot the proposal is pinned to, or
+//!   those of the live registry at the bundle's height, decayed and capped, with the
+//!   commitment over the registry they came from;
+//! - the veto rules in effect: threshold, decay, Sybil cap and whether snapshots are used;
+//! - the tally: the weights summed in node id order, the veto percentage and whether it
+//!   crossed the threshold.
+//!
+//! The bundle is signed with a secp256k1 key: `signature` is the compact ECDSA signature by
+//! `signer` over the SHA-256 of the JSON of `evidence`. [`verify`] checks it, checks every
+//! veto's signatures against the node's key set, or its key at index 0 without one, checks
+//! every reserve claim and address proof, and recomputes the tally from the weights. Vetoes
+//! received from the node as events come without signatures and are reported as unsigned.
+//!
+//! Bundles are reproducible: nothing in them depends on the time or on map order, every list
+//! is sorted, and ECDSA signatures are deterministic (RFC 6979), so the same stored state,
+//! configuration and key give byte-identical files, and parties can compare their
+//! [`EvidenceBundle::hash`]es.
+//!
+//! [`EconomicNodeRegistry::veto_evidence`]: crate::economic_nodes::EconomicNodeRegistry::veto_evidence
+
+use crate::config::{DecayConfig, RegistryConfig};
+use crate::content::ContentCheck;
+use crate::economic_nodes::address_proof::{self, AddressKind, AddressProof};
+use crate::economic_nodes::snapshot::EpochState;
+use crate::economic_nodes::{
+    cluster, commitment, decay, multisig, reserve, EconomicNode, KeySet, KeySignature,
+    LightningIdentity, ReserveClaim, SignedAt, VerificationStatus, VetoTally,
+};
+use crate::error::GovernanceError;
+use crate::proposals::GovernanceProposal;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Version of the bundle format.
+pub const EVIDENCE_VERSION: u32 = 1;
+
+/// Key signatures of signed vetoes, by proposal id and hex node id.
+pub type VetoSignatures = BTreeMap<String, BTreeMap<String, SignedAt>>;
+
+/// The veto rules a tally was computed under.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TallyRules {
+    pub threshold_percent: f64,
+    pub use_epoch_snapshot: bool,
+    /// Percentile Sybil clusters are capped at; 0 for none. Live weights only.
+    pub sybil_cap_percentile: f64,
+    pub decay: DecayConfig,
+}
+
+/// A node's weight in the tally.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightEntry {
+    pub node_id: String,
+    pub weight: f64,
+}
+
+/// The weights a tally counted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TallyWeights {
+    /// Epoch snapshot they come from; live weights if unset.
+    pub snapshot: Option<u64>,
+    /// Hex commitment root of the registry state they come from.
+    pub commitment: String,
+    /// Active nodes, sorted by node id.
+    pub nodes: Vec<WeightEntry>,
+}
+
+/// A vetoing node's registration and verification records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeRecord {
+    pub node_type: String,
+    /// Hex public key, empty if none was registered.
+    pub public_key: String,
+    pub keys: Option<KeySet>,
+    pub registered_at: u64,
+    pub verification: VerificationStatus,
+    /// Verified reserve, in satoshis.
+    pub verified_weight: u64,
+    pub reserve_claim: Option<ReserveClaim>,
+    pub address_proof: Option<AddressProof>,
+    pub lightning: Option<LightningIdentity>,
+}
+
+/// An open veto.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VetoEntry {
+    pub node_id: String,
+    pub height: u64,
+    pub reason: String,
+    /// Over [`multisig::veto_message`]; empty for vetoes received from the node.
+    pub signatures: Vec<KeySignature>,
+    /// Height the signatures were made at, as named in the message.
+    #[serde(default)]
+    pub signed_height: u64,
+    pub node: NodeRecord,
+}
+
+/// What the registry contributes to a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryEvidence {
+    /// Height the live weights were decayed to.
+    pub height: u64,
+    pub rules: TallyRules,
+    pub weights: TallyWeights,
+    /// Sorted by node id.
+    pub vetoes: Vec<VetoEntry>,
+}
+
+/// Everything a bundle attests to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VetoEvidence {
+    pub version: u32,
+    pub proposal: GovernanceProposal,
+    pub content: Option<ContentCheck>,
+    pub registry: RegistryEvidence,
+    pub tally: VetoTally,
+    pub veto_percent: f64,
+    pub crossed: bool,
+}
+
+/// A signed [`VetoEvidence`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceBundle {
+    pub evidence: VetoEvidence,
+    /// Hex compressed public key of the signer.
+    pub signer: String,
+    /// Hex compact ECDSA signature over the SHA-256 of the JSON of `evidence`.
+    pub signature: String,
+}
+
+/// How a veto's signatures check out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureCheck {
+    Valid,
+    Invalid,
+    /// Received from the node, with no signatures to check.
+    Unsigned,
+}
+
+/// The checks of one veto.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VetoCheck {
+    pub node_id: String,
+    pub signatures: SignatureCheck,
+    /// Whether the reserve claim's signature is valid, if there is a claim.
+    pub reserve_claim: Option<bool>,
+    /// Whether the address proof is valid, if there is one.
+    pub address_proof: Option<bool>,
+}
+
+impl VetoCheck {
+    fn is_valid(&self) -> bool {
+        self.signatures != SignatureCheck::Invalid
+            && self.reserve_claim != Some(false)
+            && self.address_proof != Some(false)
+    }
+}
+
+/// The outcome of [`verify`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvidenceReport {
+    pub proposal_id: String,
+    pub signer: String,
+    pub signature_valid: bool,
+    pub vetoes: Vec<VetoCheck>,
+    /// The tally recomputed from the bundle's weights.
+    pub recomputed: VetoTally,
+    /// Whether it matches the bundle's, percentage and outcome included.
+    pub tally_matches: bool,
+}
+
+impl EvidenceReport {
+    /// Whether every check passed; unsigned vetoes do not fail it.
+    pub fn is_valid(&self) -> bool {
+        self.signature_valid && self.tally_matches && self.vetoes.iter().all(VetoCheck::is_valid)
+    }
+}
+
+/// One line per check.
+impl fmt::Display for EvidenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ok = |valid: bool| if valid { "ok" } else { "FAILED" };
+        writeln!(f, "proposal: {}", self.proposal_id)?;
+        writeln!(
+            f,
+            "bundle signature by {}: {}",
+            self.signer,
+            ok(self.signature_valid)
+        )?;
+        for veto in &self.vetoes {
+            write!(
+                f,
+                "veto by {}: signatures {:?}",
+                veto.node_id, veto.signatures
+            )?;
+            if let Some(valid) = veto.reserve_claim {
+                write!(f, ", reserve claim {}", ok(valid))?;
+            }
+            if let Some(valid) = veto.address_proof {
+                write!(f, ", address proof {}", ok(valid))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(
+            f,
+            "tally: {} of {} ({:.4}%, threshold {}%): {}",
+            self.recomputed.vetoing_weight,
+            self.recomputed.total_weight,
+            self.recomputed.veto_percent(),
+            self.recomputed.threshold_percent,
+            ok(self.tally_matches)
+        )
+    }
+}
+
+/// The weights `proposal_id`'s veto tally counts at `height`, as the registry computes it:
+/// the pinned epoch snapshot's if snapshots are used, else the live registry's, decayed and
+/// capped.
+pub(crate) fn tally_weights(
+    config: &RegistryConfig,
+    epochs: &EpochState,
+    nodes: &HashMap<[u8; 32], EconomicNode>,
+    proposal_id: &str,
+    height: u64,
+) -> TallyWeights {
+    if config.veto.use_epoch_snapshot {
+        if let Some(snapshot) = epochs.for_proposal(proposal_id) {
+            return TallyWeights {
+                snapshot: Some(snapshot.id),
+                commitment: snapshot.commitment.root_hex(),
+                nodes: snapshot
+                    .nodes
+                    .iter()
+                    .map(|e| WeightEntry {
+                        node_id: hex::encode(e.node_id),
+                        weight: e.weight,
+                    })
+                    .collect(),
+            };
+        }
+    }
+    let decay = &config.decay;
+    let percentile = config.sybil.cap_percentile;
+    let caps = (percentile > 0.0).then(|| {
+        let report = cluster::analyze(nodes.values(), height, &config.sybil, decay);
+        cluster::weight_caps(&report, nodes.values(), percentile, decay)
+    });
+    let mut entries: Vec<WeightEntry> = nodes
+        .values()
+        .filter(|n| n.deactivated.is_none())
+        .map(|n| {
+            let cap = caps
+                .as_ref()
+                .and_then(|c| c.get(&n.node_id))
+                .unwrap_or(&1.0);
+            WeightEntry {
+                node_id: hex::encode(n.node_id),
+                weight: decay::effective_weight(n, height, decay) * cap,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+    TallyWeights {
+        snapshot: None,
+        commitment: commitment::compute(nodes.values()).root_hex(),
+        nodes: entries,
+    }
+}
+
+/// The registry's part of the bundle on `proposal_id`: its weights at `height`, and its open
+/// vetoes from active nodes with `signatures`.
+pub fn registry_evidence(
+    config: &RegistryConfig,
+    epochs: &EpochState,
+    nodes: &HashMap<[u8; 32], EconomicNode>,
+    signatures: &VetoSignatures,
+    proposal_id: &str,
+    height: u64,
+) -> RegistryEvidence {
+    let signed = signatures.get(proposal_id);
+    let mut vetoes: Vec<VetoEntry> = nodes
+        .values()
+        .filter(|n| n.deactivated.is_none())
+        .filter_map(|n| {
+            let veto = n
+                .veto_history
+                .iter()
+                .find(|v| v.proposal_id == proposal_id && v.outcome.is_none())?;
+            let node_id = hex::encode(n.node_id);
+            let signed = signed
+                .and_then(|s| s.get(&node_id))
+                .cloned()
+                .unwrap_or_default();
+            Some(VetoEntry {
+                signatures: signed.signatures,
+                signed_height: signed.height,
+                node_id,
+                height: veto.height,
+                reason: veto.reason.clone(),
+                node: NodeRecord {
+                    node_type: n.node_type.clone(),
+                    public_key: hex::encode(&n.public_key),
+                    keys: n.keys.clone(),
+                    registered_at: n.registered_at,
+                    verification: n.verification.clone(),
+                    verified_weight: n.verified_weight,
+                    reserve_claim: n.reserve_claim.clone(),
+                    address_proof: n.address_proof.clone(),
+                    lightning: n.lightning.clone(),
+                },
+            })
+        })
+        .collect();
+    vetoes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+    RegistryEvidence {
+        height,
+        rules: TallyRules {
+            threshold_percent: config.veto.threshold_percent,
+            use_epoch_snapshot: config.veto.use_epoch_snapshot,
+            sybil_cap_percentile: config.sybil.cap_percentile,
+            decay: config.decay.clone(),
+        },
+        weights: tally_weights(config, epochs, nodes, proposal_id, height),
+        vetoes,
+    }
+}
+
+/// The tally of `registry`'s vetoes on `proposal_id`: its weights summed in node id order.
+pub fn tally(proposal_id: &str, registry: &RegistryEvidence) -> VetoTally {
+    let vetoing: BTreeSet<&str> = registry.vetoes.iter().map(|v| v.node_id.as_str()).collect();
+    let mut total_weight = 0.0;
+    let mut vetoing_weight = 0.0;
+    for entry in &registry.weights.nodes {
+        total_weight += entry.weight;
+        if vetoing.contains(entry.node_id.as_str()) {
+            vetoing_weight += entry.weight;
+        }
+    }
+    VetoTally {
+        proposal_id: proposal_id.to_string(),
+        total_weight,
+        vetoing_weight,
+        threshold_percent: registry.rules.threshold_percent,
+        commitment: registry.weights.commitment.clone(),
+    }
+}
+
+/// The evidence on `proposal`, with its content check and the registry's part.
+pub fn assemble(
+    proposal: GovernanceProposal,
+    content: Option<ContentCheck>,
+    registry: RegistryEvidence,
+) -> VetoEvidence {
+    let tally = tally(&proposal.proposal_id, &registry);
+    VetoEvidence {
+        version: EVIDENCE_VERSION,
+        veto_percent: tally.veto_percent(),
+        crossed: tally.crossed(),
+        proposal,
+        content,
+        registry,
+        tally,
+    }
+}
+
+/// Read a signing key: 32 bytes in hex, surrounding whitespace ignored.
+pub fn parse_signing_key(text: &str) -> Result<SecretKey, GovernanceError> {
+    let invalid = |reason: &str| GovernanceError::ValidationError {
+        field: "signing_key".to_string(),
+        reason: reason.to_string(),
+    };
+    let bytes = hex::decode(text.trim()).map_err(|_| invalid("expected 64 hex characters"))?;
+    SecretKey::from_slice(&bytes).map_err(|_| invalid("not a valid secp256k1 secret key"))
+}
+
+impl VetoEvidence {
+    /// The JSON signed, compact.
+    fn signed_json(&self) -> Result<String, GovernanceError> {
+        serde_json::to_string(self).map_err(GovernanceError::serialization("veto evidence"))
+    }
+
+    /// Sign the evidence with `key`.
+    pub fn sign(self, key: &SecretKey) -> Result<EvidenceBundle, GovernanceError> {
+        let secp = Secp256k1::signing_only();
+        let message = Message::from_digest(reserve::message_digest(&self.signed_json()?));
+        let signature = secp.sign_ecdsa(&message, key).serialize_compact();
+        Ok(EvidenceBundle {
+            signer: hex::encode(PublicKey::from_secret_key(&secp, key).serialize()),
+            signature: hex::encode(signature),
+            evidence: self,
+        })
+    }
+}
+
+impl EvidenceBundle {
+    /// The bundle as written: pretty JSON with a final newline.
+    pub fn to_json(&self) -> Result<String, GovernanceError> {
+        let mut json = serde_json::to_string_pretty(self)
+            .map_err(GovernanceError::serialization("veto evidence"))?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    /// Hex SHA-256 of [`Self::to_json`], for comparing bundles.
+    pub fn hash(&self) -> Result<String, GovernanceError> {
+        Ok(hex::encode(Sha256::digest(self.to_json()?.as_bytes())))
+    }
+}
+
+/// Check the signatures of `veto` on `proposal_id`.
+fn check_signatures(veto: &VetoEntry, proposal_id: &str) -> SignatureCheck {
+    if veto.signatures.is_empty() {
+        return SignatureCheck::Unsigned;
+    }
+    let Some(node_id) = crate::economic_nodes::parse_node_id(&veto.node_id) else {
+        return SignatureCheck::Invalid;
+    };
+    let message = multisig::veto_message(&node_id, proposal_id, &veto.reason, veto.signed_height);
+    let valid = match &veto.node.keys {
+        Some(keys) => keys.verify(&message, &veto.signatures),
+        None => {
+            let key = hex::decode(&veto.node.public_key).unwrap_or_default();
+            veto.signatures.iter().any(|s| {
+                s.key_index == 0 && reserve::verify_signature(&key, &message, &s.signature)
+            })
+        }
+    };
+    if valid {
+        SignatureCheck::Valid
+    } else {
+        SignatureCheck::Invalid
+    }
+}
+
+/// Check the reserve claim and the address proof of `veto`'s node, if it has them.
+fn check_records(veto: &VetoEntry) -> (Option<bool>, Option<bool>) {
+    let node_id = crate::economic_nodes::parse_node_id(&veto.node_id);
+    let reserve_claim = veto.node.reserve_claim.as_ref().map(|claim| {
+        node_id.is_some_and(|id| {
+            let message = reserve::registration_message(&id, &claim.outpoints);
+            reserve::verify_signature(&claim.public_key, &message, &claim.signature)
+        })
+    });
+    let address_proof = veto.node.address_proof.as_ref().map(|proof| {
+        let kind = AddressKind::parse(&proof.address);
+        let block_hash = address_proof::parse_block_hash(&proof.block_hash);
+        match (node_id, kind, block_hash) {
+            (Some(id), Some(kind), Some(block_hash)) => {
+                let message = address_proof::challenge_message(&id, &block_hash);
+                address_proof::verify(&kind, &message, &proof.signature)
+            }
+            _ => false,
+        }
+    });
+    (reserve_claim, address_proof)
+}
+
+/// Check `bundle` from its contents alone: its signature, every veto's signatures and
+/// records, and the tally recomputed from its weights.
+pub fn verify(bundle: &EvidenceBundle) -> Result<EvidenceReport, GovernanceError> {
+    let evidence = &bundle.evidence;
+    let proposal_id = &evidence.proposal.proposal_id;
+    let signer = hex::decode(&bundle.signer).unwrap_or_default();
+    let signature = hex::decode(&bundle.signature).unwrap_or_default();
+    let signature_valid = reserve::verify_signature(&signer, &evidence.signed_json()?, &signature);
+    let vetoes = evidence
+        .registry
+        .vetoes
+        .iter()
+        .map(|veto| {
+            let (reserve_claim, address_proof) = check_records(veto);
+            VetoCheck {
+                node_id: veto.node_id.clone(),
+                signatures: check_signatures(veto, proposal_id),
+                reserve_claim,
+                address_proof,
+            }
+        })
+        .collect();
+    let recomputed = tally(proposal_id, &evidence.registry);
+    let tally_matches = recomputed == evidence.tally
+        && recomputed.veto_percent() == evidence.veto_percent
+        && recomputed.crossed() == evidence.crossed;
+    Ok(EvidenceReport {
+        proposal_id: proposal_id.clone(),
+        signer: bundle.signer.clone(),
+        signature_valid,
+        vetoes,
+        recomputed,
+        tally_matches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_nodes::VetoRecord;
+    use crate::proposals::ProposalStatus;
+
+    fn vetoing(id: u8, weight: f64, proposal_id: &str) -> EconomicNode {
+        let mut node = EconomicNode::new([id; 32], 0);
+        node.verified_weight = weight as u64;
+        node.verification = VerificationStatus::Verified { height: 0 };
+        node.veto_history.push(VetoRecord {
+            proposal_id: proposal_id.to_string(),
+            height: 5,
+            reason: "unsafe".to_string(),
+            outcome: None,
+        });
+        node
+    }
+
+    #[test]
+    fn test_tally_is_recomputed_from_the_bundle() {
+        let mut quiet = EconomicNode::new([3u8; 32], 0);
+        quiet.verified_weight = 50;
+        quiet.verification = VerificationStatus::Verified { height: 0 };
+        let nodes: HashMap<[u8; 32], EconomicNode> =
+            [vetoing(1, 20.0, "p1"), vetoing(2, 30.0, "p1"), quiet]
+                .into_iter()
+                .map(|n| (n.node_id, n))
+                .collect();
+        let mut config = RegistryConfig::default();
+        config.veto.threshold_percent = 40.0;
+        let registry = registry_evidence(
+            &config,
+            &EpochState::default(),
+            &nodes,
+            &VetoSignatures::new(),
+            "p1",
+            10,
+        );
+        assert_eq!(registry.vetoes.len(), 2);
+        let evidence = assemble(
+            GovernanceProposal {
+                proposal_id: "p1".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+                status: ProposalStatus::Created,
+                votes: Vec::new(),
+                author: None,
+                created_height: Some(1),
+                closed_height: None,
+                milestones: Vec::new(),
+            },
+            None,
+            registry,
+        );
+        assert_eq!(evidence.tally.vetoing_weight, 50.0);
+        assert!(evidence.crossed);
+
+        let key = parse_signing_key(&"11".repeat(32)).unwrap();
+        let bundle = evidence.clone().sign(&key).unwrap();
+        let again = evidence.sign(&key).unwrap();
+        assert_eq!(bundle.to_json().unwrap(), again.to_json().unwrap());
+        let report = verify(&bundle).unwrap();
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(report.vetoes[0].signatures, SignatureCheck::Unsigned);
+
+        // Any change to the weights breaks the signature and the tally
+        let mut forged = bundle.clone();
+        forged.evidence.registry.weights.nodes[2].weight = 10.0;
+        let report = verify(&forged).unwrap();
+        assert!(!report.signature_valid);
+        assert!(!report.tally_matches);
+        assert!(!report.is_valid());
+    }
+}
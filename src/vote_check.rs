@@ -0,0 +1,196 @@
+//! Duplicate and conflicting votes
+//!
+//! Buggy clients have sent the same vote event twice, and two different votes from one voter
+//! on one proposal at nearly the same height. The proposal store checks each vote against the
+//! voter's counted one, its [`Ballot`], stored with the proposals along with the height it was
+//! cast at, so the checks hold across restarts:
+//!
+//! - The same vote again is a duplicate: it is dropped, and counted.
+//! - With `[governance.tally] vote_conflict_window_blocks` set, a different vote cast within
+//!   that many blocks of the counted one conflicts with it. The vote cast at the later height
+//!   wins; at the same height the one counting least towards approval wins, `no` over
+//!   `abstain` over `yes` over anything else, and between two others the first in byte order.
+//!   The conflict is written to the audit log as `vote_conflict` and sent to the webhook as
+//!   `vote_conflict`, naming the voter, the proposal and both votes.
+//! - Any other different vote replaces the counted one, as before.
+//!
+//! A vote restored by a reorg (see [`crate::proposals`]) has no known height and is not
+//! checked for conflicts with the next one.
+
+use crate::tally::VoteChoice;
+use serde::{Deserialize, Serialize};
+
+/// Event type of the webhook alert and kind of the audit entry.
+pub const VOTE_CONFLICT_EVENT: &str = "vote_conflict";
+
+/// A voter's counted vote on a proposal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ballot {
+    pub vote: String,
+    /// Chain tip when it was cast, if known.
+    pub height: Option<u64>,
+}
+
+/// Two different votes from one voter on one proposal, cast too close together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteConflict {
+    pub proposal_id: String,
+    pub voter: String,
+    /// The vote counted.
+    pub kept: Ballot,
+    /// The vote not counted.
+    pub dropped: Ballot,
+}
+
+/// What to do with a vote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The voter's first vote.
+    New,
+    /// The counted vote again; dropped.
+    Duplicate,
+    /// A different vote cast outside the window, or with no window; it replaces the counted
+    /// one.
+    Replaces,
+    /// A different vote cast inside the window; counted if `counted`.
+    Conflict {
+        conflict: VoteConflict,
+        counted: bool,
+    },
+}
+
+/// Votes dropped as duplicates and conflicts resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteChecks {
+    pub duplicates_dropped: u64,
+    pub conflicts_resolved: u64,
+}
+
+/// Rank of `vote` in a tie, lowest winning.
+fn tie_rank(vote: &str) -> u8 {
+    match VoteChoice::parse(vote) {
+        Some(VoteChoice::No) => 0,
+        Some(VoteChoice::Abstain) => 1,
+        Some(VoteChoice::Yes) => 2,
+        None => 3,
+    }
+}
+
+/// Whether `later`, cast after `earlier` by the same voter, wins over it.
+pub fn later_wins(earlier: &Ballot, later: &Ballot) -> bool {
+    match (earlier.height, later.height) {
+        (Some(earlier), Some(later)) if earlier != later => later > earlier,
+        _ => {
+            (tie_rank(&later.vote), later.vote.as_str())
+                < (tie_rank(&earlier.vote), earlier.vote.as_str())
+        }
+    }
+}
+
+/// Check `later`, a vote by `voter` on `proposal_id`, against the voter's counted vote
+/// `earlier`, with conflicts inside `window` blocks if set.
+pub fn check(
+    proposal_id: &str,
+    voter: &str,
+    earlier: Option<&Ballot>,
+    later: &Ballot,
+    window: Option<u64>,
+) -> Verdict {
+    let Some(earlier) = earlier else {
+        return Verdict::New;
+    };
+    if earlier.vote == later.vote {
+        return Verdict::Duplicate;
+    }
+    let within = match (earlier.height, later.height, window) {
+        (Some(earlier), Some(later), Some(window)) => earlier.abs_diff(later) <= window,
+        _ => false,
+    };
+    if !within {
+        return Verdict::Replaces;
+    }
+    let counted = later_wins(earlier, later);
+    let (kept, dropped) = if counted {
+        (later.clone(), earlier.clone())
+    } else {
+        (earlier.clone(), later.clone())
+    };
+    Verdict::Conflict {
+        conflict: VoteConflict {
+            proposal_id: proposal_id.to_string(),
+            voter: voter.to_string(),
+            kept,
+            dropped,
+        },
+        counted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballot(vote: &str, height: u64) -> Ballot {
+        Ballot {
+            vote: vote.to_string(),
+            height: Some(height),
+        }
+    }
+
+    #[test]
+    fn test_latest_height_wins_and_ties_go_to_the_stronger_objection() {
+        let yes = ballot("yes", 100);
+        assert_eq!(check("1", "bob", None, &yes, Some(1)), Verdict::New);
+        assert_eq!(
+            check("1", "bob", Some(&yes), &ballot("yes", 101), None),
+            Verdict::Duplicate
+        );
+        assert_eq!(
+            check("1", "bob", Some(&yes), &ballot("no", 102), Some(1)),
+            Verdict::Replaces
+        );
+
+        // Later height wins
+        let Verdict::Conflict { conflict, counted } =
+            check("1", "bob", Some(&yes), &ballot("no", 101), Some(1))
+        else {
+            panic!("expected a conflict");
+        };
+        assert!(counted);
+        assert_eq!(
+            (conflict.kept.vote.as_str(), conflict.dropped.vote.as_str()),
+            ("no", "yes")
+        );
+
+        // Same height: no over abstain over yes over anything else, whichever came first
+        assert!(later_wins(&yes, &ballot("no", 100)));
+        assert!(!later_wins(&ballot("no", 100), &yes));
+        assert!(later_wins(&yes, &ballot("abstain", 100)));
+        assert!(!later_wins(&ballot("abstain", 100), &yes));
+        assert!(later_wins(&ballot("maybe", 100), &yes));
+        assert!(later_wins(&ballot("maybe", 100), &ballot("later", 100)));
+        assert!(!later_wins(&ballot("later", 100), &ballot("maybe", 100)));
+        let Verdict::Conflict { conflict, counted } =
+            check("1", "bob", Some(&ballot("no", 100)), &yes, Some(0))
+        else {
+            panic!("expected a conflict");
+        };
+        assert!(!counted);
+        assert_eq!(conflict.kept, ballot("no", 100));
+        // Without a window votes never conflict
+        assert_eq!(
+            check("1", "bob", Some(&yes), &ballot("no", 100), None),
+            Verdict::Replaces
+        );
+
+        // A vote of unknown height is replaced
+        let restored = Ballot {
+            vote: "yes".to_string(),
+            height: None,
+        };
+        assert_eq!(
+            check("1", "bob", Some(&restored), &ballot("no", 100), Some(1)),
+            Verdict::Replaces
+        );
+    }
+}
@@ -1,76 +1,773 @@
 //! Governance webhook client
+//!
+//! The webhook URL, node ID and event filter can be changed while running with
+//! [`GovernanceWebhookClient::reconfigure`]. Each delivery uses the settings in effect when
+//! it is sent, so queued deliveries go to the new URL.
+//!
+//! In dry-run mode (`governance.dry_run` or `--dry-run`) payloads are built and filtered as
+//! usual, but the request that would be sent, with its URL password, query values and any
+//! credential headers masked, is logged instead, and written to a file if asked. Such sends
+//! are counted apart from deliveries and publish no `WebhookSent`/`WebhookFailed` event.
+//!
+//! Payloads are stamped with the local time, or with the time estimated from block
+//! timestamps while the local clock is skewed, if the [`ClockMonitor`] given to
+//! [`GovernanceWebhookClient::with_clock`] is set to (see [`crate::clock`]).
+//!
+//! A delivery that still fails after its retries is reported to the node as
+//! `webhook_undeliverable`, per URL, with an [`ErrorReporter`] given to
+//! [`GovernanceWebhookClient::with_error_reporter`] (see [`crate::error_report`]).
+//!
+//! A client for a replay run ([`GovernanceWebhookClient::with_replay`], see
+//! [`crate::replay`]) adds `"replayed": true` to its payloads.
+//!
+//! Each delivery, after its retries, is recorded in the audit log given to
+//! [`GovernanceWebhookClient::with_audit_log`] (see [`crate::audit_log`]).
+//!
+//! Notifications of proposal events, of registry changes (recorded by the registry when it
+//! makes them) and of proposal tally milestones (recorded by the proposal store, see
+//! [`crate::tally`]) are recorded as intents in the intent log given to
+//! [`GovernanceWebhookClient::with_intents`] before they are sent, and completed once sent or
+//! given up on (see [`crate::intent`]). One cut short by a crash is sent on the next start.
+//! Block notifications are not: a block the webhook had not completed is replayed from the
+//! checkpoint (see [`crate::checkpoint`]).
+//!
+//! While outbound effects are paused (see [`crate::pause`]), with the [`PauseControl`] given
+//! to [`GovernanceWebhookClient::with_pause`], notifications are recorded as intents as usual
+//! but not sent or completed, and block notifications are recorded as intents too; they are
+//! sent, oldest first, once the pause ends. A notification that cannot be recorded is
+//! dropped.
+//!
+//! With a [`Digest`] given to [`GovernanceWebhookClient::with_digest`], notifications other
+//! than blocks are collected into it instead of being sent, and sent together as a digest at
+//! the end of each period (see [`crate::digest`]).
+//!
+//! Payloads carry a `trace_id`: the id of the event being processed (see [`crate::trace`]),
+//! or a fresh one for deliveries not caused by an event. Each delivery, retries included, is
+//! sent in a `webhook` span.
+//!
+//! Payloads carry the node's network as `network`, as given to
+//! [`GovernanceWebhookClient::with_network`]. With `webhook_network` set, deliveries are
+//! blocked while the node is on another network: each is logged, counted as failed, recorded
+//! in the audit log and reported as `webhook_network_mismatch` (see [`crate::network`]).
+//!
+//! Payloads are built as the versioned types of [`crate::schema`]: an [`Envelope`] for
+//! events, a [`BlockNotification`] for blocks. They are sent as JSON, CBOR or MessagePack, as
+//! `webhook_content_type` says, and with `webhook_secret` set carry the HMAC of the body as
+//! sent in [`SIGNATURE_HEADER`] (see [`crate::payload_format`]).
 
-use crate::error::GovernanceError;
+use crate::audit_log::AuditLog;
+use crate::clock::ClockMonitor;
+use crate::config::{GovernanceConfig, WebhookContentType};
+use crate::digest::{Digest, DIGEST_EVENT, DIGEST_TICK};
+use crate::economic_nodes::RegistryChange;
+use crate::error::{Chain, GovernanceError, Retryability};
+use crate::error_report::{ErrorCode, ErrorReport, ErrorReporter};
+use crate::intent::{Intent, IntentLog};
+use crate::pause::PauseControl;
+use crate::payload_format;
+use crate::schema::{self, BlockNotification, Envelope, Payload};
+use crate::shutdown::Shutdown;
+use crate::trace;
 use blvm_node::module::ipc::protocol::EventPayload;
 use blvm_node::module::ipc::protocol::ModuleMessage;
 use blvm_node::module::traits::NodeAPI;
 use blvm_node::module::EventType;
 use reqwest::Client;
-use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn, Instrument};
+
+/// Delay before the first retry of a failed delivery; doubles with each further retry.
+pub const RETRY_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Block notifications kept for a later attempt while the node cannot serve the blocks.
+pub const MAX_DEFERRED_BLOCKS: usize = 100;
+
+/// Header carrying the HMAC-SHA256 of each delivery's body under `webhook_secret`, as
+/// `sha256=` and the hex digest.
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+/// Webhook settings, replaced together by [`GovernanceWebhookClient::reconfigure`].
+#[derive(Debug, Clone, Default, PartialEq)]
+struct WebhookSettings {
+    url: Option<String>,
+    node_id: Option<String>,
+    /// Event types to deliver; empty delivers all.
+    event_filter: HashSet<String>,
+    /// Retries of a delivery that failed with a retryable error.
+    retries: u32,
+    /// Network the webhook takes data of; any if unset.
+    network: Option<String>,
+    content_type: WebhookContentType,
+    /// Key of the deliveries' signatures.
+    secret: Option<String>,
+}
+
+impl WebhookSettings {
+    fn from_config(config: &GovernanceConfig) -> Self {
+        Self {
+            url: config.webhook_url.clone(),
+            node_id: config.node_id.clone(),
+            event_filter: config
+                .webhook_events
+                .iter()
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect(),
+            retries: config.webhook_retry_count,
+            network: config.webhook_network.clone(),
+            content_type: config.webhook_content_type,
+            secret: config.webhook_secret.clone(),
+        }
+    }
+
+    fn wants(&self, event_type: &str) -> bool {
+        self.event_filter.is_empty()
+            || self.event_filter.contains(event_type)
+            || event_type == DIGEST_EVENT
+    }
+}
+
+/// Webhook deliveries since the module started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryCounts {
+    pub delivered: u64,
+    pub failed: u64,
+    /// Payloads logged instead of sent in dry-run mode.
+    #[serde(default)]
+    pub dry_run: u64,
+}
 
 /// Governance webhook client
 pub struct GovernanceWebhookClient {
     client: Client,
-    webhook_url: Option<String>,
-    node_id: Option<String>,
-    enabled: bool,
+    settings: RwLock<Arc<WebhookSettings>>,
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    /// Deliveries failed since the last one that succeeded.
+    consecutive_failures: AtomicU64,
+    /// Log payloads instead of sending them.
+    dry_run: bool,
+    /// Where to also write payloads in dry-run mode.
+    dry_run_dir: Option<PathBuf>,
+    dry_runs: AtomicU64,
+    /// Blocks the node could not serve when they arrived, oldest first; retried with the next
+    /// block.
+    deferred_blocks: Mutex<Vec<([u8; 32], u64)>>,
+    /// Source of payload timestamps, if not the local clock.
+    clock: Option<Arc<ClockMonitor>>,
+    /// Where failed deliveries are reported.
+    errors: Option<Arc<ErrorReporter>>,
+    /// Where deliveries are recorded.
+    audit_log: Option<Arc<AuditLog>>,
+    /// Mark payloads as replayed.
+    replay: bool,
+    /// Where notifications are recorded before they are sent.
+    intents: Option<Arc<IntentLog>>,
+    /// Holds notifications while paused.
+    pause: Option<Arc<PauseControl>>,
+    /// Collects notifications to send as periodic digests.
+    digest: Option<Arc<Digest>>,
+    /// Network of the node, stamped on payloads.
+    network: Option<String>,
+    /// URLs that answered 415 to a binary payload, sent JSON since.
+    json_fallback: Mutex<HashSet<String>>,
+}
+
+/// Handler and kind of the intents to notify the webhook of a governance event.
+const NOTIFY_INTENT: (&str, &str) = ("webhook", "notify");
+
+/// Kind of the intents, for the same handler, to notify the webhook of a block held while
+/// paused.
+const BLOCK_INTENT: &str = "block";
+
+/// Hash and height of the block a [`BLOCK_INTENT`] is for.
+fn held_block(data: &serde_json::Value) -> Option<([u8; 32], u64)> {
+    let hash = hex::decode(data["block_hash"].as_str()?).ok()?;
+    Some((hash.try_into().ok()?, data["height"].as_u64()?))
+}
+
+/// Record in `intents` that the webhook is to be notified of `event_type` with `data`.
+/// Returns the intent's id, or `None` if it could not be recorded, in which case the
+/// notification is sent all the same.
+pub(crate) fn notify_intent(
+    intents: &IntentLog,
+    event_type: &str,
+    data: &serde_json::Value,
+) -> Option<u64> {
+    let (handler, kind) = NOTIFY_INTENT;
+    let data = serde_json::json!({ "event_type": event_type, "data": data });
+    match intents.begin(handler, kind, data) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!("Failed to record webhook intent: {}", e.chain());
+            None
+        }
+    }
 }
 
 impl GovernanceWebhookClient {
+    fn settings(&self) -> Arc<WebhookSettings> {
+        Arc::clone(&self.settings.read().unwrap())
+    }
+
     /// Whether the webhook is configured and enabled.
     pub fn is_enabled(&self) -> bool {
-        self.enabled
+        self.settings().url.is_some()
     }
 
     /// Webhook URL if configured.
-    pub fn webhook_url(&self) -> Option<&str> {
-        self.webhook_url.as_deref()
+    pub fn webhook_url(&self) -> Option<String> {
+        self.settings().url.clone()
     }
 
     /// Node ID if configured.
-    pub fn node_id(&self) -> Option<&str> {
-        self.node_id.as_deref()
+    pub fn node_id(&self) -> Option<String> {
+        self.settings().node_id.clone()
+    }
+
+    pub fn delivery_counts(&self) -> DeliveryCounts {
+        DeliveryCounts {
+            delivered: self.delivered.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dry_run: self.dry_runs.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Deliveries failed in a row, after retries, since the last successful one.
+    pub fn consecutive_failures(&self) -> u64 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    /// Side effects recorded in the client's intent log and not carried out yet, its own and
+    /// those of the handlers sharing the log; 0 without one.
+    pub fn pending_intents(&self) -> usize {
+        self.intents
+            .as_ref()
+            .map_or(0, |intents| intents.pending().len())
+    }
+
+    fn record_delivery(&self, delivered: bool) {
+        if delivered {
+            self.delivered.fetch_add(1, Ordering::Relaxed);
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+        } else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+            self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+        }
     }
 
-    /// Create a new webhook client
-    pub async fn new(
-        ctx: &blvm_node::module::traits::ModuleContext,
-    ) -> Result<Self, GovernanceError> {
-        let webhook_url = ctx.get_config("governance.webhook_url").cloned();
-        let node_id = ctx.get_config("governance.node_id").cloned();
-        let enabled = webhook_url.is_some();
+    /// Create a new webhook client with the webhook settings of `config`
+    pub async fn new(config: &GovernanceConfig) -> Result<Self, GovernanceError> {
+        let settings = WebhookSettings::from_config(config);
 
         let client = Client::builder()
             .timeout(std::time::Duration::from_secs(10))
             .build()
-            .map_err(|e| {
-                GovernanceError::WebhookError(format!("Failed to create HTTP client: {}", e))
-            })?;
+            .map_err(|source| GovernanceError::HttpClient { source })?;
 
-        if enabled {
-            info!(
-                "Governance webhook client initialized: {}",
-                webhook_url.as_ref().unwrap()
-            );
-        } else {
-            debug!("Governance webhook client disabled (no URL configured)");
+        match &settings.url {
+            Some(url) => info!("Governance webhook client initialized: {}", url),
+            None => debug!("Governance webhook client disabled (no URL configured)"),
         }
 
         Ok(Self {
             client,
-            webhook_url,
-            node_id,
-            enabled,
+            settings: RwLock::new(Arc::new(settings)),
+            delivered: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            consecutive_failures: AtomicU64::new(0),
+            dry_run: false,
+            dry_run_dir: None,
+            dry_runs: AtomicU64::new(0),
+            deferred_blocks: Mutex::new(Vec::new()),
+            clock: None,
+            errors: None,
+            audit_log: None,
+            replay: false,
+            intents: None,
+            pause: None,
+            digest: None,
+            network: None,
+            json_fallback: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// A delivery succeeded: count it, and record it in the audit log.
+    fn record_sent(&self, url: &str, event_type: &str, status: u16) {
+        self.record_delivery(true);
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.webhook_delivery(url, event_type, Some(status), None);
+        }
+    }
+
+    /// A delivery failed after its retries: count it, record it in the audit log, and report
+    /// it to the node.
+    fn record_failure(&self, url: &str, event_type: &str, status: Option<u16>, error: &str) {
+        self.record_delivery(false);
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.webhook_delivery(url, event_type, status, Some(error));
+        }
+        if let Some(errors) = &self.errors {
+            errors.report(
+                ErrorReport::new(
+                    ErrorCode::WebhookUndeliverable,
+                    format!("{} delivery failed: {}", event_type, error),
+                )
+                .with_context("url", url),
+            );
+        }
+    }
+
+    /// Log payloads instead of sending them, also writing each to a file in `dir` if given.
+    pub fn with_dry_run(mut self, dir: Option<PathBuf>) -> Self {
+        info!("Dry run: webhook payloads are logged, not sent");
+        self.dry_run = true;
+        self.dry_run_dir = dir;
+        self
+    }
+
+    /// Stamp payloads with the time `clock` gives.
+    pub fn with_clock(mut self, clock: Arc<ClockMonitor>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Report deliveries that fail after their retries to `errors`.
+    pub fn with_error_reporter(mut self, errors: Arc<ErrorReporter>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Record each delivery in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Record notifications of proposal events in `intents` before sending them.
+    pub fn with_intents(mut self, intents: Arc<IntentLog>) -> Self {
+        self.intents = Some(intents);
+        self
+    }
+
+    /// Hold notifications as intents while `pause` holds them.
+    pub fn with_pause(mut self, pause: Arc<PauseControl>) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    /// Collect notifications in `digest` and send them as a digest at the end of each period,
+    /// instead of one by one.
+    pub fn with_digest(mut self, digest: Arc<Digest>) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Stamp payloads with `network`, the node's, and block deliveries to a webhook for
+    /// another network.
+    pub fn with_network(mut self, network: Option<String>) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// The error blocking deliveries to `url`, the webhook of `settings`, if it is for
+    /// another network than the node's. Nothing is blocked while the node's is unknown.
+    fn network_mismatch(&self, settings: &WebhookSettings, url: &str) -> Option<GovernanceError> {
+        let (Some(expected), Some(actual)) = (&settings.network, &self.network) else {
+            return None;
+        };
+        (expected != actual).then(|| GovernanceError::NetworkMismatch {
+            what: format!("webhook {}", masked_url(url)),
+            expected: expected.clone(),
+            actual: actual.clone(),
         })
     }
 
+    /// Whether the delivery of `event_type` to `url` is blocked because the webhook is for
+    /// another network than the node's. A blocked delivery is counted as failed, recorded in
+    /// the audit log and reported.
+    fn blocked(&self, settings: &WebhookSettings, url: &str, event_type: &str) -> bool {
+        let Some(error) = self.network_mismatch(settings, url) else {
+            return false;
+        };
+        warn!("Blocked {} delivery: {}", event_type, error);
+        self.record_delivery(false);
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.webhook_delivery(url, event_type, None, Some(&error.to_string()));
+        }
+        if let Some(errors) = &self.errors {
+            errors.report(
+                ErrorReport::new(
+                    ErrorCode::WebhookNetworkMismatch,
+                    format!("{} delivery blocked: {}", event_type, error),
+                )
+                .with_context("url", url),
+            );
+        }
+        true
+    }
+
+    /// Whether a notification of `event_type` with intent `intent` is held by a pause rather
+    /// than sent. Its intent stays pending; one without an intent is dropped.
+    fn held(&self, event_type: &str, intent: Option<u64>) -> bool {
+        if !self.pause.as_ref().is_some_and(|p| p.holds()) {
+            return false;
+        }
+        match intent {
+            Some(id) => debug!(
+                "Paused, holding {} notification as intent {}",
+                event_type, id
+            ),
+            None if self.is_enabled() && self.wants(event_type) => warn!(
+                "Paused, dropping {} notification that could not be recorded",
+                event_type
+            ),
+            None => {}
+        }
+        true
+    }
+
+    /// Record the notification of block `height` as an intent and hold it, if paused.
+    /// Returns whether it was held.
+    fn hold_block(&self, block_hash: [u8; 32], height: u64) -> bool {
+        if !self.pause.as_ref().is_some_and(|p| p.holds()) {
+            return false;
+        }
+        let intent = match &self.intents {
+            Some(intents) if self.wants("block") => {
+                let data = serde_json::json!({
+                    "block_hash": hex::encode(block_hash),
+                    "height": height,
+                });
+                intents
+                    .begin(NOTIFY_INTENT.0, BLOCK_INTENT, data)
+                    .map_err(|e| warn!("Failed to record webhook intent: {}", e.chain()))
+                    .ok()
+            }
+            _ => None,
+        };
+        self.held("block", intent)
+    }
+
+    /// Mark intent `id` complete, if there is one.
+    fn fulfil(&self, id: Option<u64>) {
+        let (Some(intents), Some(id)) = (&self.intents, id) else {
+            return;
+        };
+        if let Err(e) = intents.complete(id) {
+            warn!("Failed to complete webhook intent {}: {}", id, e.chain());
+        }
+    }
+
+    /// Carry out an intent recorded before a crash or held by a pause: send the notification
+    /// it describes again.
+    pub async fn carry_out(
+        &self,
+        intent: &Intent,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        if intent.kind == BLOCK_INTENT {
+            if let Some((block_hash, height)) = held_block(&intent.data) {
+                info!("Sending block {} notification left unsent", height);
+                return self.notify_new_block(block_hash, height, node_api).await;
+            }
+        }
+        match (intent.kind.as_str(), intent.data["event_type"].as_str()) {
+            (kind, Some(event_type)) if kind == NOTIFY_INTENT.1 => {
+                info!("Sending {} notification left unsent", event_type);
+                self.notify_governance_event(event_type, intent.data["data"].clone(), node_api)
+                    .await
+            }
+            _ => Err(GovernanceError::ModuleError(format!(
+                "not a webhook notification intent: {} {}",
+                intent.kind, intent.data
+            ))),
+        }
+    }
+
+    /// Mark each payload `"replayed": true`, for a replay run.
+    pub fn with_replay(mut self) -> Self {
+        self.replay = true;
+        self
+    }
+
+    /// An [`Envelope`] of `event_type` with `data`, stamped with `timestamp` and `trace_id`.
+    fn envelope(
+        &self,
+        settings: &WebhookSettings,
+        event_type: &str,
+        data: serde_json::Value,
+        timestamp: u64,
+        trace_id: String,
+    ) -> Envelope {
+        Envelope {
+            event_type: event_type.to_string(),
+            schema_version: Envelope::SCHEMA_VERSION,
+            data_version: schema::data_version(event_type),
+            data,
+            node_id: settings.node_id.clone(),
+            network: self.network.clone(),
+            timestamp,
+            trace_id,
+            replayed: self.replay,
+            test: false,
+            build: None,
+        }
+    }
+
+    /// Unix seconds to stamp a payload with.
+    fn timestamp(&self) -> u64 {
+        match &self.clock {
+            Some(clock) => clock.payload_time(),
+            None => crate::clock::unix_now(),
+        }
+    }
+
+    /// In dry-run mode, log the request that would POST `payload` to `url`, write it to a
+    /// file if asked, and count it. Returns whether it did, in which case nothing is sent.
+    fn dry_run(&self, url: &str, event_type: &str, payload: &serde_json::Value) -> bool {
+        if !self.dry_run {
+            return false;
+        }
+        let settings = self.settings();
+        let format = self.format(&settings, url);
+        let headers: serde_json::Map<String, serde_json::Value> =
+            match self.request(&settings, url, payload, format).build() {
+                Ok(request) => request
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = if is_credential(name.as_str()) {
+                            "***".to_string()
+                        } else {
+                            String::from_utf8_lossy(value.as_bytes()).into_owned()
+                        };
+                        (name.to_string(), value.into())
+                    })
+                    .collect(),
+                Err(e) => {
+                    warn!("Dry run: failed to build webhook request to {}: {}", url, e);
+                    Default::default()
+                }
+            };
+        let request = serde_json::json!({
+            "method": "POST",
+            "url": masked_url(url),
+            "headers": headers,
+            "payload": payload,
+        });
+        info!(event_type, "Dry run, webhook not sent: {}", request);
+        let n = self.dry_runs.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(dir) = &self.dry_run_dir {
+            let millis = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = dir.join(format!("{}-{:06}-{}.json", millis, n, event_type));
+            let written = std::fs::create_dir_all(dir).and_then(|_| {
+                std::fs::write(
+                    &path,
+                    serde_json::to_vec_pretty(&request).unwrap_or_default(),
+                )
+            });
+            if let Err(e) = written {
+                warn!("Dry run: failed to write {}: {}", path.display(), e);
+            }
+        }
+        true
+    }
+
+    /// Whether events of this type are delivered.
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.settings().wants(event_type)
+    }
+
+    /// Replace the URL, node ID and event filter with those in `config`. Deliveries already
+    /// being sent finish with the old settings. Returns whether anything changed.
+    pub fn reconfigure(&self, config: &GovernanceConfig) -> bool {
+        let new = WebhookSettings::from_config(config);
+        let mut settings = self.settings.write().unwrap();
+        if **settings == new {
+            return false;
+        }
+        if settings.url != new.url {
+            match &new.url {
+                Some(url) => info!("Governance webhook URL changed to {}", url),
+                None => info!("Governance webhook disabled (no URL configured)"),
+            }
+        }
+        if settings.node_id != new.node_id || settings.event_filter != new.event_filter {
+            info!("Governance webhook node ID or event filter changed");
+        }
+        if settings.network != new.network {
+            info!("Governance webhook network changed");
+        }
+        if settings.content_type != new.content_type {
+            info!(
+                "Governance webhook content type changed to {}",
+                new.content_type.as_str()
+            );
+            // Endpoints that refused the old format get a chance at the new one
+            self.json_fallback.lock().unwrap().clear();
+        }
+        *settings = Arc::new(new);
+        true
+    }
+
+    /// Forward registry change notifications to the webhook until the registry goes away or
+    /// shutdown begins; changes queued before shutdown are still delivered. Runs while the
+    /// webhook is disabled too, since [`Self::reconfigure`] may enable it.
+    pub fn spawn_registry_feed(
+        self: &Arc<Self>,
+        mut changes: broadcast::Receiver<RegistryChange>,
+        node_api: Arc<dyn NodeAPI>,
+        shutdown: Arc<Shutdown>,
+    ) -> tokio::task::JoinHandle<()> {
+        let client = Arc::clone(self);
+        tokio::spawn(async move {
+            // Held until the queue is drained, so shutdown waits for these deliveries
+            let _work = shutdown.track();
+            loop {
+                let received = tokio::select! {
+                    biased;
+                    received = changes.recv() => received,
+                    _ = shutdown.stopping() => break,
+                };
+                match received {
+                    Ok(change) => client.deliver_change(&change, node_api.as_ref()).await,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Webhook registry feed lagged, {} changes dropped", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            loop {
+                match changes.try_recv() {
+                    Ok(change) => client.deliver_change(&change, node_api.as_ref()).await,
+                    Err(broadcast::error::TryRecvError::Lagged(missed)) => {
+                        warn!("Webhook registry feed lagged, {} changes dropped", missed);
+                    }
+                    Err(_) => break,
+                }
+            }
+        })
+    }
+
+    /// Send the digest due at `now`, Unix seconds on the digest's clock, if there is one and
+    /// outbound effects are not paused.
+    pub async fn send_digest(&self, now: u64, node_api: &dyn NodeAPI) {
+        let Some(digest) = &self.digest else {
+            return;
+        };
+        if self.pause.as_ref().is_some_and(|p| p.holds()) {
+            return;
+        }
+        let data = match digest.take_due(now) {
+            Ok(Some(data)) => data,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to close the digest period: {}", e.chain());
+                return;
+            }
+        };
+        info!(
+            "Sending the governance digest of {} notifications",
+            data["total"]
+        );
+        if let Err(e) = self
+            .notify_governance_event(DIGEST_EVENT, data, node_api)
+            .await
+        {
+            warn!("Failed to deliver the digest to webhook: {}", e.chain());
+        }
+        if let Err(e) = digest.sent() {
+            warn!("Failed to record the digest as sent: {}", e.chain());
+        }
+    }
+
+    /// With a digest measured on block time, send it if the block `hash`, just arrived, ends
+    /// its period.
+    async fn digest_at_block(&self, hash: &[u8; 32], node_api: &dyn NodeAPI) {
+        if !self.digest.as_ref().is_some_and(|d| d.uses_block_time()) {
+            return;
+        }
+        let header = crate::node_api::with_timeout(
+            "get_block_header",
+            crate::node_api::default_timeout(crate::ipc_metrics::IpcMethod::GetBlockHeader),
+            node_api.get_block_header(hash),
+        )
+        .await;
+        match header {
+            Ok(Some(header)) => self.send_digest(header.timestamp, node_api).await,
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Failed to read the block header the digest is timed by: {}",
+                e.chain()
+            ),
+        }
+    }
+
+    /// Send digests measured on the local clock as their periods end, until shutdown begins.
+    /// `None` without such a digest.
+    pub fn spawn_digest(
+        self: &Arc<Self>,
+        node_api: Arc<dyn NodeAPI>,
+        shutdown: Arc<Shutdown>,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if self.digest.as_ref().is_none_or(|d| d.uses_block_time()) {
+            return None;
+        }
+        let client = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DIGEST_TICK);
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = shutdown.stopping() => return,
+                    _ = interval.tick() => {}
+                }
+                client
+                    .send_digest(client.timestamp(), node_api.as_ref())
+                    .await;
+            }
+        }))
+    }
+
+    async fn deliver_change(&self, change: &RegistryChange, node_api: &dyn NodeAPI) {
+        let data = match schema::to_data(change) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to serialize registry change: {}", e.chain());
+                return;
+            }
+        };
+        if self.held(&change.event_type(), change.intent) {
+            return;
+        }
+        if let Err(e) = self
+            .notify_governance_event(&change.event_type(), data, node_api)
+            .await
+        {
+            warn!(
+                "Failed to deliver registry change to webhook: {}",
+                e.chain()
+            );
+        }
+        self.fulfil(change.intent);
+    }
+
     /// Handle an event from the node
     pub async fn handle_event(
         &self,
         event: &ModuleMessage,
         node_api: &dyn NodeAPI,
     ) -> Result<(), GovernanceError> {
-        if !self.enabled {
+        if !self.is_enabled() {
             return Ok(());
         }
 
@@ -79,10 +776,12 @@ impl GovernanceWebhookClient {
                 match event_msg.event_type {
                     EventType::NewBlock => {
                         if let EventPayload::NewBlock { block_hash, height } = &event_msg.payload {
-                            // Get block data
-                            if let Ok(Some(block)) = node_api.get_block(block_hash).await {
-                                self.notify_block(&block, *height, node_api).await?;
+                            self.digest_at_block(block_hash, node_api).await;
+                            if self.hold_block(*block_hash, *height) {
+                                return Ok(());
                             }
+                            self.notify_new_block(*block_hash, *height, node_api)
+                                .await?;
                         }
                     }
                     EventType::GovernanceProposalCreated => {
@@ -97,17 +796,14 @@ impl GovernanceWebhookClient {
                                 "Governance proposal created: id={}, repository={}, pr={}, tier={}",
                                 proposal_id, repository, pr_number, tier
                             );
-                            self.notify_governance_event(
-                                "proposal_created",
-                                serde_json::json!({
-                                    "proposal_id": proposal_id,
-                                    "repository": repository,
-                                    "pr_number": pr_number,
-                                    "tier": tier,
-                                }),
-                                node_api,
-                            )
-                            .await?;
+                            let data = schema::to_data(&schema::ProposalCreated {
+                                proposal_id: proposal_id.clone(),
+                                repository: repository.clone(),
+                                pr_number: *pr_number,
+                                tier: tier.clone(),
+                            })?;
+                            self.notify_with_intent("proposal_created", data, node_api)
+                                .await?;
                         }
                     }
                     EventType::GovernanceProposalVoted => {
@@ -121,16 +817,13 @@ impl GovernanceWebhookClient {
                                 "Governance proposal voted: id={}, voter={}, vote={}",
                                 proposal_id, voter, vote
                             );
-                            self.notify_governance_event(
-                                "proposal_voted",
-                                serde_json::json!({
-                                    "proposal_id": proposal_id,
-                                    "voter": voter,
-                                    "vote": vote,
-                                }),
-                                node_api,
-                            )
-                            .await?;
+                            let data = schema::to_data(&schema::ProposalVoted {
+                                proposal_id: proposal_id.clone(),
+                                voter: voter.clone(),
+                                vote: vote.clone(),
+                            })?;
+                            self.notify_with_intent("proposal_voted", data, node_api)
+                                .await?;
                         }
                     }
                     EventType::GovernanceProposalMerged => {
@@ -144,16 +837,13 @@ impl GovernanceWebhookClient {
                                 "Governance proposal merged: id={}, repository={}, pr={}",
                                 proposal_id, repository, pr_number
                             );
-                            self.notify_governance_event(
-                                "proposal_merged",
-                                serde_json::json!({
-                                    "proposal_id": proposal_id,
-                                    "repository": repository,
-                                    "pr_number": pr_number,
-                                }),
-                                node_api,
-                            )
-                            .await?;
+                            let data = schema::to_data(&schema::ProposalMerged {
+                                proposal_id: proposal_id.clone(),
+                                repository: repository.clone(),
+                                pr_number: *pr_number,
+                            })?;
+                            self.notify_with_intent("proposal_merged", data, node_api)
+                                .await?;
                         }
                     }
                     _ => {
@@ -169,6 +859,158 @@ impl GovernanceWebhookClient {
         Ok(())
     }
 
+    /// POST a synthetic `event_type` payload, marked `"test": true` and carrying the module's
+    /// `build` information, to the configured URL once, whatever the event filter. It is not retried or counted as a delivery. Returns
+    /// the URL and the response status.
+    pub async fn send_test(
+        &self,
+        event_type: &str,
+    ) -> Result<(String, reqwest::StatusCode), GovernanceError> {
+        let settings = self.settings();
+        let url = settings
+            .url
+            .clone()
+            .ok_or_else(|| GovernanceError::ConfigError("webhook_url is not set".to_string()))?;
+        let data = match event_type {
+            "proposal_created" => schema::to_data(&schema::ProposalCreated {
+                proposal_id: "test".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            })?,
+            "proposal_merged" => schema::to_data(&schema::ProposalMerged {
+                proposal_id: "test".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+            })?,
+            "proposal_voted" => schema::to_data(&schema::ProposalVoted {
+                proposal_id: "test".to_string(),
+                voter: "test".to_string(),
+                vote: "approve".to_string(),
+            })?,
+            _ => serde_json::Value::Object(Default::default()),
+        };
+        if let Some(e) = self.network_mismatch(&settings, &url) {
+            return Err(e);
+        }
+        let envelope = Envelope {
+            replayed: false,
+            test: true,
+            build: Some(crate::build_info::BuildInfo::current().clone()),
+            ..self.envelope(
+                &settings,
+                event_type,
+                data,
+                self.timestamp(),
+                trace::new_id(),
+            )
+        };
+        let payload = serde_json::to_value(&envelope)
+            .map_err(GovernanceError::serialization("webhook payload"))?;
+        let response = self
+            .send(&url, event_type, &payload, 0)
+            .await
+            .map_err(|e| GovernanceError::webhook_request(&url, e))?;
+        Ok((url, response.status()))
+    }
+
+    /// POST a [`crate::crash::CRASH_EVENT`] payload with `report` as its data once, if the
+    /// webhook wants that event, failing after `timeout`. Sent by the panic hook; it is not
+    /// retried or counted as a delivery.
+    pub async fn notify_crash(
+        &self,
+        report: &crate::crash::CrashReport,
+        timeout: std::time::Duration,
+    ) -> Result<(), GovernanceError> {
+        let event_type = crate::crash::CRASH_EVENT;
+        let settings = self.settings();
+        let Some(url) = settings.url.as_ref().filter(|_| settings.wants(event_type)) else {
+            return Ok(());
+        };
+        if let Some(e) = self.network_mismatch(&settings, url) {
+            return Err(e);
+        }
+        let data =
+            serde_json::to_value(report).map_err(GovernanceError::serialization("crash report"))?;
+        let envelope = Envelope {
+            replayed: false,
+            ..self.envelope(
+                &settings,
+                event_type,
+                data,
+                report.timestamp,
+                trace::new_id(),
+            )
+        };
+        let payload = serde_json::to_value(&envelope)
+            .map_err(GovernanceError::serialization("webhook payload"))?;
+        if self.dry_run(url, event_type, &payload) {
+            return Ok(());
+        }
+        let response = tokio::time::timeout(timeout, self.send(url, event_type, &payload, 0))
+            .await
+            .map_err(|_| GovernanceError::Timeout {
+                operation: format!("{} webhook", event_type),
+                after: timeout,
+            })?
+            .map_err(|e| GovernanceError::webhook_request(url, e))?;
+        if !response.status().is_success() {
+            return Err(GovernanceError::webhook_status(url, response.status()));
+        }
+        Ok(())
+    }
+
+    /// [`Self::notify_governance_event`], recorded as an intent until it is sent or given up
+    /// on, if deliveries of `event_type` are on.
+    async fn notify_with_intent(
+        &self,
+        event_type: &str,
+        data: serde_json::Value,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let intent = self.record_intent(event_type, &data);
+        if self.held(event_type, intent) {
+            return Ok(());
+        }
+        let result = self
+            .notify_governance_event(event_type, data, node_api)
+            .await;
+        self.fulfil(intent);
+        result
+    }
+
+    /// Record the intent to notify the webhook of `event_type` with `data`, if deliveries of
+    /// it are on. Returns the id to pass to [`Self::notify_recorded`].
+    pub fn record_intent(&self, event_type: &str, data: &serde_json::Value) -> Option<u64> {
+        match &self.intents {
+            Some(intents) if self.is_enabled() && self.wants(event_type) => {
+                notify_intent(intents, event_type, data)
+            }
+            _ => None,
+        }
+    }
+
+    /// Send a notification recorded with [`Self::record_intent`], such as one derived from a
+    /// proposal's tally, and complete its intent once sent or given up on.
+    pub async fn notify_recorded(
+        &self,
+        event_type: &str,
+        data: serde_json::Value,
+        intent: Option<u64>,
+        node_api: &dyn NodeAPI,
+    ) {
+        if self.held(event_type, intent) {
+            return;
+        }
+        if let Err(e) = self
+            .notify_governance_event(event_type, data, node_api)
+            .await
+        {
+            warn!("Failed to deliver {} to webhook: {}", event_type, e.chain());
+        }
+        self.fulfil(intent);
+    }
+
     /// Notify governance app about a governance event
     async fn notify_governance_event(
         &self,
@@ -176,101 +1018,302 @@ impl GovernanceWebhookClient {
         data: serde_json::Value,
         node_api: &dyn NodeAPI,
     ) -> Result<(), GovernanceError> {
-        if !self.enabled {
+        let settings = self.settings();
+        let Some(url) = settings.url.as_ref().filter(|_| settings.wants(event_type)) else {
             return Ok(());
+        };
+        if let Some(digest) = self.digest.as_ref().filter(|_| event_type != DIGEST_EVENT) {
+            match digest.record(event_type, &data, self.timestamp()) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!(
+                    "Failed to add {} to the digest, sending it on its own: {}",
+                    event_type,
+                    e.chain()
+                ),
+            }
         }
 
-        let url = self.webhook_url.as_ref().unwrap();
+        if self.blocked(&settings, url, event_type) {
+            return Ok(());
+        }
 
         // Prepare payload
-        let payload = serde_json::json!({
-            "event_type": event_type,
-            "data": data,
-            "node_id": self.node_id.as_deref(),
-            "timestamp": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        });
+        let envelope = self.envelope(
+            &settings,
+            event_type,
+            data,
+            self.timestamp(),
+            trace::current_id().unwrap_or_else(trace::new_id),
+        );
+        let payload = serde_json::to_value(&envelope)
+            .map_err(GovernanceError::serialization("webhook payload"))?;
+        if self.dry_run(url, event_type, &payload) {
+            return Ok(());
+        }
 
         // Send webhook and publish WebhookSent/WebhookFailed
-        match self.client.post(url).json(&payload).send().await {
+        match self.send(url, event_type, &payload, settings.retries).await {
             Ok(response) => {
                 if response.status().is_success() {
                     debug!(
                         "Governance webhook sent successfully: event_type={}",
                         event_type
                     );
+                    self.record_sent(url, event_type, response.status().as_u16());
                     let payload = EventPayload::WebhookSent {
                         webhook_url: url.clone(),
                         event_type: event_type.to_string(),
                         success: true,
                     };
-                    let _ = node_api.publish_event(EventType::WebhookSent, payload).await;
+                    let _ = node_api
+                        .publish_event(EventType::WebhookSent, payload)
+                        .await;
                 } else {
                     warn!(
                         "Governance webhook returned error status {} for event_type={}",
                         response.status(),
                         event_type
                     );
+                    let error = format!("HTTP {}", response.status());
+                    let status = Some(response.status().as_u16());
+                    self.record_failure(url, event_type, status, &error);
                     let payload = EventPayload::WebhookFailed {
                         webhook_url: url.clone(),
                         event_type: event_type.to_string(),
-                        error: format!("HTTP {}", response.status()),
+                        error,
                     };
-                    let _ = node_api.publish_event(EventType::WebhookFailed, payload).await;
+                    let _ = node_api
+                        .publish_event(EventType::WebhookFailed, payload)
+                        .await;
                 }
             }
             Err(e) => {
+                let error = Chain(&e).to_string();
                 warn!(
                     "Failed to send governance webhook for event_type={}: {}",
-                    event_type, e
+                    event_type, error
                 );
+                self.record_failure(url, event_type, None, &error);
                 let payload = EventPayload::WebhookFailed {
                     webhook_url: url.clone(),
                     event_type: event_type.to_string(),
-                    error: e.to_string(),
+                    error,
                 };
-                let _ = node_api.publish_event(EventType::WebhookFailed, payload).await;
+                let _ = node_api
+                    .publish_event(EventType::WebhookFailed, payload)
+                    .await;
             }
         }
 
         Ok(())
     }
 
+    /// The format deliveries to `url` are sent in: the configured one, or JSON once the
+    /// endpoint has refused it.
+    fn format(&self, settings: &WebhookSettings, url: &str) -> WebhookContentType {
+        if self.json_fallback.lock().unwrap().contains(url) {
+            WebhookContentType::Json
+        } else {
+            settings.content_type
+        }
+    }
+
+    /// Send JSON to `url` from now on, after it answered 415 to `format`.
+    fn fall_back_to_json(&self, url: &str, format: WebhookContentType) {
+        if self.json_fallback.lock().unwrap().insert(url.to_string()) {
+            warn!(
+                "Webhook {} does not accept {}, sending JSON instead",
+                url,
+                payload_format::mime(format)
+            );
+        }
+    }
+
+    /// The POST of `payload` to `url` in `format`, signed when a secret is set.
+    fn request(
+        &self,
+        settings: &WebhookSettings,
+        url: &str,
+        payload: &serde_json::Value,
+        format: WebhookContentType,
+    ) -> reqwest::RequestBuilder {
+        let body = payload_format::encode(format, payload);
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, payload_format::mime(format));
+        if let Some(secret) = &settings.secret {
+            request = request.header(
+                SIGNATURE_HEADER,
+                crate::github::signature(secret.as_bytes(), &body),
+            );
+        }
+        request.body(body)
+    }
+
+    /// POST `payload` to `url`. Failures classified as retryable are retried up to `retries`
+    /// times, with delays doubling from [`RETRY_INITIAL_DELAY`]; others are returned at once.
+    async fn send(
+        &self,
+        url: &str,
+        event_type: &str,
+        payload: &serde_json::Value,
+        retries: u32,
+    ) -> reqwest::Result<reqwest::Response> {
+        let span = tracing::debug_span!(
+            "webhook",
+            event_type,
+            trace_id = payload["trace_id"].as_str().unwrap_or_default(),
+            attempts = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty
+        );
+        let start = std::time::Instant::now();
+        let mut attempts = 0;
+        let settings = self.settings();
+        let mut format = self.format(&settings, url);
+        let result = async {
+            let mut delay = RETRY_INITIAL_DELAY;
+            loop {
+                let result = self.request(&settings, url, payload, format).send().await;
+                attempts += 1;
+                let (retryability, error) = match &result {
+                    Ok(response) if response.status().is_success() => return result,
+                    Ok(response)
+                        if response.status() == reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE
+                            && format != WebhookContentType::Json =>
+                    {
+                        self.fall_back_to_json(url, format);
+                        format = WebhookContentType::Json;
+                        continue;
+                    }
+                    Ok(response) => (
+                        Retryability::of_http_status(response.status().as_u16()),
+                        format!("returned {}", response.status()),
+                    ),
+                    Err(e) => (Retryability::of_http_error(e), Chain(e).to_string()),
+                };
+                if attempts > retries || !retryability.should_retry(attempts) {
+                    return result;
+                }
+                debug!(
+                    "Webhook delivery failed (attempt {}), retrying in {:?}: {}",
+                    attempts, delay, error
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        span.record("attempts", attempts);
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        result
+    }
+
     /// Notify governance app about a new block
+    /// Notify `block_hash` and any blocks deferred before it. When the node still fails with a
+    /// retryable error after [`crate::node_api::with_retry`], the remaining blocks are deferred
+    /// to the next `NewBlock` instead of being dropped.
+    async fn notify_new_block(
+        &self,
+        block_hash: [u8; 32],
+        height: u64,
+        node_api: &dyn NodeAPI,
+    ) -> Result<(), GovernanceError> {
+        let mut blocks = std::mem::take(&mut *self.deferred_blocks.lock().unwrap());
+        blocks.push((block_hash, height));
+        let mut pending = blocks.into_iter();
+        while let Some((hash, height)) = pending.next() {
+            let fetched = crate::node_api::with_retry(
+                "get_block",
+                crate::node_api::DEFAULT_REQUEST_TIMEOUT,
+                crate::node_api::RetryPolicy::default(),
+                || node_api.get_block(&hash),
+            )
+            .instrument(tracing::debug_span!(
+                "node_api",
+                method = "get_block",
+                key = %hex::encode(hash),
+                trace_id = trace::current_id().unwrap_or_default()
+            ))
+            .await;
+            let result = match fetched {
+                Ok(Some(block)) => self.notify_block(&block, height, node_api).await,
+                Ok(None) => Ok(()),
+                Err(e) if e.is_retryable() => {
+                    warn!("Block {} notification deferred: {}", height, e.chain());
+                    self.defer_blocks(std::iter::once((hash, height)).chain(pending));
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.defer_blocks(pending);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep `blocks` for the next `NewBlock`, dropping the oldest beyond
+    /// [`MAX_DEFERRED_BLOCKS`].
+    fn defer_blocks(&self, blocks: impl Iterator<Item = ([u8; 32], u64)>) {
+        let mut deferred = self.deferred_blocks.lock().unwrap();
+        deferred.extend(blocks);
+        if deferred.len() > MAX_DEFERRED_BLOCKS {
+            let excess = deferred.len() - MAX_DEFERRED_BLOCKS;
+            warn!("Dropping {} deferred block notifications", excess);
+            deferred.drain(..excess);
+        }
+    }
+
+    /// Blocks waiting for the node before their notification can be sent.
+    pub fn deferred_blocks(&self) -> usize {
+        self.deferred_blocks.lock().unwrap().len()
+    }
+
     async fn notify_block(
         &self,
         block: &blvm_protocol::Block,
         height: u64,
         node_api: &dyn NodeAPI,
     ) -> Result<(), GovernanceError> {
-        if !self.enabled {
+        let settings = self.settings();
+        let Some(url) = settings.url.as_ref().filter(|_| settings.wants("block")) else {
+            return Ok(());
+        };
+        if self.blocked(&settings, url, "block") {
             return Ok(());
         }
 
-        let url = self.webhook_url.as_ref().unwrap();
-
         // Calculate block hash
         let block_hash = self.calculate_block_hash(block);
 
         // Serialize block to JSON
-        let block_json = serde_json::to_value(block).map_err(|e| {
-            GovernanceError::WebhookError(format!("Failed to serialize block: {}", e))
-        })?;
+        let block_json =
+            serde_json::to_value(block).map_err(GovernanceError::serialization("block webhook"))?;
 
         // Prepare payload
-        let payload = serde_json::json!({
-            "block_hash": hex::encode(block_hash),
-            "block_height": height as i32,
-            "block": block_json,
-            "contributor_id": self.node_id.as_deref(),
-        });
+        let notification = BlockNotification {
+            schema_version: BlockNotification::SCHEMA_VERSION,
+            block_hash: hex::encode(block_hash),
+            block_height: height as i32,
+            block: block_json,
+            contributor_id: settings.node_id.clone(),
+            network: self.network.clone(),
+            trace_id: trace::current_id().unwrap_or_else(trace::new_id),
+            replayed: self.replay,
+        };
+        let payload = serde_json::to_value(&notification)
+            .map_err(GovernanceError::serialization("block webhook"))?;
 
         let event_type = "block";
+        if self.dry_run(url, event_type, &payload) {
+            return Ok(());
+        }
 
         // Send webhook and publish WebhookSent/WebhookFailed
-        match self.client.post(url).json(&payload).send().await {
+        match self.send(url, event_type, &payload, settings.retries).await {
             Ok(response) => {
                 if response.status().is_success() {
                     debug!(
@@ -278,12 +1321,15 @@ impl GovernanceWebhookClient {
                         hex::encode(block_hash),
                         height
                     );
+                    self.record_sent(url, event_type, response.status().as_u16());
                     let payload = EventPayload::WebhookSent {
                         webhook_url: url.clone(),
                         event_type: event_type.to_string(),
                         success: true,
                     };
-                    let _ = node_api.publish_event(EventType::WebhookSent, payload).await;
+                    let _ = node_api
+                        .publish_event(EventType::WebhookSent, payload)
+                        .await;
                 } else {
                     warn!(
                         "Governance webhook returned error status {} for block {} at height {}",
@@ -291,27 +1337,36 @@ impl GovernanceWebhookClient {
                         hex::encode(block_hash),
                         height
                     );
+                    let error = format!("HTTP {}", response.status());
+                    let status = Some(response.status().as_u16());
+                    self.record_failure(url, event_type, status, &error);
                     let payload = EventPayload::WebhookFailed {
                         webhook_url: url.clone(),
                         event_type: event_type.to_string(),
-                        error: format!("HTTP {}", response.status()),
+                        error,
                     };
-                    let _ = node_api.publish_event(EventType::WebhookFailed, payload).await;
+                    let _ = node_api
+                        .publish_event(EventType::WebhookFailed, payload)
+                        .await;
                 }
             }
             Err(e) => {
+                let error = Chain(&e).to_string();
                 warn!(
                     "Failed to send governance webhook for block {} at height {}: {}",
                     hex::encode(block_hash),
                     height,
-                    e
+                    error
                 );
+                self.record_failure(url, event_type, None, &error);
                 let payload = EventPayload::WebhookFailed {
                     webhook_url: url.clone(),
                     event_type: event_type.to_string(),
-                    error: e.to_string(),
+                    error,
                 };
-                let _ = node_api.publish_event(EventType::WebhookFailed, payload).await;
+                let _ = node_api
+                    .publish_event(EventType::WebhookFailed, payload)
+                    .await;
             }
         }
 
@@ -340,3 +1395,36 @@ impl GovernanceWebhookClient {
         hash
     }
 }
+
+/// Whether a header carries credentials, which dry-run logs mask.
+fn is_credential(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    [
+        "authorization",
+        "cookie",
+        "secret",
+        "signature",
+        "token",
+        "api-key",
+    ]
+    .iter()
+    .any(|part| name.contains(part))
+}
+
+/// `url` with its password and query values replaced by `***`, for logs.
+pub(crate) fn masked_url(url: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    if parsed.password().is_some() {
+        let _ = parsed.set_password(Some("***"));
+    }
+    if parsed.query().is_some() {
+        let keys: Vec<String> = parsed.query_pairs().map(|(k, _)| k.into_owned()).collect();
+        parsed
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(keys.iter().map(|k| (k, "***")));
+    }
+    parsed.to_string()
+}
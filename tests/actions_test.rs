@@ -0,0 +1,198 @@
+//! Votes and vetoes submitted over the local action endpoints
+
+mod common;
+
+use blvm_governance::actions::LocalActions;
+use blvm_governance::audit_log::AuditLog;
+use blvm_governance::config::{AuditLogConfig, HealthConfig, RegistryConfig};
+use blvm_governance::economic_nodes::reserve::message_digest;
+use blvm_governance::economic_nodes::{multisig, EconomicNodeRegistry, KeySet, KeySignature};
+use blvm_governance::health::Health;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::shutdown::Shutdown;
+use blvm_governance::subscriptions::EventSubscriptions;
+use common::MockNodeApi;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use std::sync::Arc;
+
+const TOKEN: &str = "t0k";
+
+fn sign(sk: &SecretKey, message: &str) -> Vec<u8> {
+    let digest = Message::from_digest(message_digest(message));
+    Secp256k1::new()
+        .sign_ecdsa(&digest, sk)
+        .serialize_compact()
+        .to_vec()
+}
+
+/// A registry holding node [1; 32], controlled by a 1-of-1 key set of `sk`.
+async fn registry(node_api: &Arc<MockNodeApi>, sk: &SecretKey) -> Arc<EconomicNodeRegistry> {
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    let keys = KeySet {
+        public_keys: vec![PublicKey::from_secret_key(&Secp256k1::new(), sk)
+            .serialize()
+            .to_vec()],
+        threshold: 1,
+    };
+    let signature = KeySignature {
+        key_index: 0,
+        signature: sign(sk, &multisig::registration_message(&[1u8; 32], &keys, None)),
+    };
+    assert!(registry
+        .register_multisig(
+            None,
+            &hex::encode([1u8; 32]),
+            "exchange",
+            None,
+            keys,
+            &[signature]
+        )
+        .await
+        .unwrap());
+    Arc::new(registry)
+}
+
+fn veto(height: u64, signature: &[u8]) -> String {
+    serde_json::json!({
+        "proposal_id": "42",
+        "node_id": hex::encode([1u8; 32]),
+        "action": "veto",
+        "reason": "unsafe",
+        "height": height,
+        "signatures": [{ "key_index": 0, "signature": hex::encode(signature) }],
+    })
+    .to_string()
+}
+
+async fn post(base: &str, path: &str, token: &str, body: String) -> (u16, serde_json::Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}{}", base, path))
+        .bearer_auth(token)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_signed_veto_is_forwarded_and_audited() {
+    let dir = std::env::temp_dir().join(format!("blvm_actions_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let audit_log = Arc::new(
+        AuditLog::open(
+            &dir,
+            &AuditLogConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let node_api = Arc::new(MockNodeApi::new(100));
+    node_api.accept_actions();
+    let sk = SecretKey::from_slice(&[3u8; 32]).unwrap();
+    let registry = registry(&node_api, &sk).await;
+    let ipc = NodeApiIpc::new(node_api.clone()).with_actions_allowed(true);
+
+    let health = Arc::new(Health::new(
+        HealthConfig {
+            actions_token: Some(TOKEN.to_string()),
+            ..Default::default()
+        },
+        Arc::new(Shutdown::new()),
+        Arc::new(Heartbeat::new()),
+        Arc::new(EventSubscriptions::new([])),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+
+    let message = multisig::veto_message(&[1u8; 32], "42", "unsafe", 100);
+    let signed = veto(100, &sign(&sk, &message));
+    // Not connected to the node yet
+    let (status, _) = post(&base, "/actions/veto", TOKEN, signed.clone()).await;
+    assert_eq!(status, 503);
+
+    health.serve_actions(Arc::new(
+        LocalActions::new(TOKEN.to_string(), registry, ipc).with_audit_log(audit_log),
+    ));
+    let (status, _) = post(&base, "/actions/veto", "wrong", signed.clone()).await;
+    assert_eq!(status, 401);
+    // Signed by another key
+    let other = SecretKey::from_slice(&[4u8; 32]).unwrap();
+    let forged = veto(100, &sign(&other, &message));
+    let (status, body) = post(&base, "/actions/veto", TOKEN, forged).await;
+    assert_eq!(status, 403, "{}", body);
+    // Signed for a block the node has not reached
+    let ahead = multisig::veto_message(&[1u8; 32], "42", "unsafe", 101);
+    let (status, body) = post(&base, "/actions/veto", TOKEN, veto(101, &sign(&sk, &ahead))).await;
+    assert_eq!(status, 403, "{}", body);
+    let (status, _) = post(&base, "/actions/vote", TOKEN, signed.clone()).await;
+    assert_eq!(status, 400);
+    assert!(node_api.module_calls().is_empty());
+
+    let (status, body) = post(&base, "/actions/veto", TOKEN, signed).await;
+    assert_eq!((status, body["accepted"].as_bool()), (200, Some(true)));
+    let calls = node_api.module_calls();
+    assert_eq!(calls.len(), 1);
+    let request: serde_json::Value = serde_json::from_slice(&calls[0].1).unwrap();
+    assert_eq!(request["type"], "veto");
+    assert_eq!(request["node_identity"], hex::encode([1u8; 32]));
+
+    // No module on the node takes governance actions
+    node_api.respond(
+        "submit_governance_action",
+        Err("Method 'submit_governance_action' not found in any module".to_string()),
+    );
+    let message = multisig::veto_message(&[1u8; 32], "42", "unsafe", 100);
+    let (status, body) = post(
+        &base,
+        "/actions/veto",
+        TOKEN,
+        veto(100, &sign(&sk, &message)),
+    )
+    .await;
+    assert_eq!(status, 501, "{}", body);
+
+    // Every request after connecting is in the audit log, with its status
+    let mut statuses = Vec::new();
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let text = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        for line in text.lines() {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            if entry["kind"] == "action_request" {
+                statuses.push(entry["data"]["status"].as_u64().unwrap());
+            }
+        }
+    }
+    assert_eq!(statuses, vec![401, 403, 403, 400, 200, 501]);
+    server.abort();
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_disabled_without_token() {
+    let health = Arc::new(Health::new(
+        HealthConfig::default(),
+        Arc::new(Shutdown::new()),
+        Arc::new(Heartbeat::new()),
+        Arc::new(EventSubscriptions::new([])),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+    let response = reqwest::Client::new()
+        .post(format!("{}/actions/veto", base))
+        .bearer_auth(TOKEN)
+        .body("{}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    server.abort();
+}
@@ -0,0 +1,176 @@
+//! Adoption of an activated proposal measured from signaling headers and node reports
+
+mod common;
+
+use blvm_governance::adoption::Measure;
+use blvm_governance::config::{ActivationConfig, AdoptionConfig, AdoptionRule};
+use blvm_governance::node_api::NodeEconomicNode;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::time::Duration;
+
+/// Proposal 1 followed on bit 1 and proposal 2 on the `v2transport` capability, over windows
+/// of 4 blocks, with standard proposals activating 2 blocks after merge.
+fn config(url: String) -> GovernanceConfig {
+    GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_events: vec!["proposal_adoption_milestone".to_string()],
+        activation: ActivationConfig {
+            delays: [("standard".to_string(), 2)].into(),
+            countdown_interval_blocks: 0,
+        },
+        adoption: AdoptionConfig {
+            window_blocks: 4,
+            milestones_percent: vec![50.0, 90.0],
+            proposals: [
+                (
+                    "1".to_string(),
+                    AdoptionRule {
+                        bit: Some(1),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "2".to_string(),
+                    AdoptionRule {
+                        capability: Some("v2transport".to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+        },
+        ..Default::default()
+    }
+}
+
+/// Create and merge `proposal_id` at the node's tip, 100.
+async fn merge(node: &MockNode, proposal_id: &str) {
+    node.node_api.add_proposal(common::proposal(proposal_id));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+}
+
+/// Connect the block at `height`, signaling bit 1 if `signaled`.
+async fn block(node: &MockNode, height: u64, signaled: bool) {
+    let hash = [height as u8; 32];
+    let mut block = common::block([height as u8 - 1; 32], Vec::new());
+    block.header.version = if signaled { 0x2000_0002 } else { 0x2000_0000 };
+    node.node_api.add_block(height, hash, block);
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash,
+            height,
+        },
+    )
+    .await;
+}
+
+/// Proposal id, milestone and height of each milestone received.
+async fn milestones(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+) -> Vec<(String, f64, u64)> {
+    let mut received = Vec::new();
+    while let Ok(Some(payload)) = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+        let data = &payload["data"];
+        received.push((
+            data["proposal_id"].as_str().unwrap().to_string(),
+            data["milestone_percent"].as_f64().unwrap(),
+            data["height"].as_u64().unwrap(),
+        ));
+    }
+    received
+}
+
+#[tokio::test]
+async fn test_milestones_from_blocks_after_activation() {
+    let (url, mut rx) = common::webhook_server().await;
+    let node = MockNode::start("adoption_blocks", config(url)).await;
+    let adoption = node.adoption.as_ref().unwrap();
+    merge(&node, "1").await;
+
+    // Signaling before activation at 102 does not count
+    block(&node, 101, true).await;
+    assert!(adoption.levels().await.is_empty());
+    for (height, signaled) in [
+        (102, false),
+        (103, true),
+        (104, true),
+        (105, true),
+        (106, true),
+    ] {
+        block(&node, height, signaled).await;
+    }
+    let levels = adoption.levels().await;
+    assert_eq!(levels.len(), 1);
+    assert_eq!(levels[0].measure, Measure::Blocks);
+    assert_eq!((levels[0].adopted, levels[0].total), (4, 4));
+    assert_eq!(levels[0].activation_height, 102);
+    assert_eq!(
+        milestones(&mut rx).await,
+        vec![("1".to_string(), 50.0, 103), ("1".to_string(), 90.0, 106)]
+    );
+
+    // Rebuilt from the node's chain as after a restart: same level, no milestone sent again
+    node.node_api.set_tip([106u8; 32], 106);
+    adoption.rebuild(node.node_api.as_ref()).await.unwrap();
+    assert_eq!(adoption.levels().await, levels);
+    block(&node, 107, false).await;
+    assert_eq!(adoption.levels().await[0].percent, 75.0);
+    assert!(milestones(&mut rx).await.is_empty());
+    assert_eq!(adoption.milestones().unwrap()["1"], vec![50.0, 90.0]);
+}
+
+#[tokio::test]
+async fn test_capabilities_of_active_nodes() {
+    let (url, mut rx) = common::webhook_server().await;
+    let node = MockNode::start("adoption_nodes", config(url)).await;
+    let adoption = node.adoption.as_ref().unwrap();
+    let ids: Vec<String> = (1..=4u8).map(|i| hex::encode([i; 32])).collect();
+    let remote: Vec<NodeEconomicNode> = ids
+        .iter()
+        .map(|node_id| NodeEconomicNode {
+            node_id: node_id.clone(),
+            node_type: "exchange".to_string(),
+            hashpower_percent: None,
+        })
+        .collect();
+    node.module.economic_nodes.reconcile(&remote).await.unwrap();
+    let capabilities = ["v2transport".to_string()].into();
+    adoption.report_capabilities(&ids[0], capabilities).unwrap();
+    merge(&node, "2").await;
+    block(&node, 101, false).await;
+    block(&node, 102, false).await;
+    let levels = adoption.levels().await;
+    assert_eq!(levels[0].measure, Measure::Nodes);
+    assert_eq!((levels[0].adopted, levels[0].total), (1, 4));
+
+    let capabilities = ["v2transport".to_string(), "compact_filters".to_string()].into();
+    adoption.report_capabilities(&ids[1], capabilities).unwrap();
+    block(&node, 103, false).await;
+    assert_eq!(adoption.levels().await[0].percent, 50.0);
+    assert_eq!(
+        milestones(&mut rx).await,
+        vec![("2".to_string(), 50.0, 103)]
+    );
+}
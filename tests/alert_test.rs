@@ -0,0 +1,172 @@
+//! Error rate alerts posted to the alert URL
+
+mod common;
+
+use blvm_governance::alert::AlertMonitor;
+use blvm_governance::clock::unix_now;
+use blvm_governance::config::AlertConfig;
+use blvm_governance::config_reload::{ConfigReloader, ModulePaths};
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::error_report::{ErrorCode, ErrorReport, ErrorReporter};
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const WINDOW_SECS: u64 = 60;
+
+fn proposal_created(id: &str) -> ModuleMessage {
+    ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    })
+}
+
+fn alert_config(url: &str, count: u64) -> AlertConfig {
+    AlertConfig {
+        url: Some(url.to_string()),
+        window_secs: WINDOW_SECS,
+        count,
+        rate_per_min: 0.0,
+        cooldown_secs: 600,
+    }
+}
+
+fn registry_write_failed() -> ErrorReport {
+    ErrorReport::new(
+        ErrorCode::RegistryWriteFailed,
+        "Database error during insert",
+    )
+    .with_context("tree", "economic_nodes")
+}
+
+#[tokio::test]
+async fn test_failing_deliveries_raise_one_alert_and_one_recovery() {
+    let (alert_url, mut received) = common::webhook_server().await;
+    let alerts = Arc::new(
+        AlertMonitor::new(alert_config(&alert_url, 3)).with_node_id(Some("test_node".into())),
+    );
+    let errors = Arc::new(ErrorReporter::default().with_alert_monitor(Arc::clone(&alerts)));
+    let config = GovernanceConfig {
+        webhook_url: Some("http://127.0.0.1:1/webhook".to_string()),
+        webhook_retry_count: 0,
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_error_reporter(Arc::clone(&errors));
+    let node_api = MockNodeApi::new(100);
+
+    // A burst of failed deliveries
+    for id in 0..5 {
+        client
+            .handle_event(&proposal_created(&id.to_string()), &node_api)
+            .await
+            .unwrap();
+    }
+    let now = unix_now();
+    assert_eq!(alerts.check(now).await.unwrap(), 1);
+    let alert = received.recv().await.unwrap();
+    assert_eq!(alert["event_type"], "module_alert");
+    assert_eq!(alert["status"], "firing");
+    assert_eq!(alert["category"], "webhook_undeliverable");
+    assert_eq!(alert["window_secs"], WINDOW_SECS);
+    assert_eq!(alert["count"], 5);
+    assert_eq!(alert["thresholds"]["count"], 3);
+    assert_eq!(alert["node_id"], "test_node");
+    assert!(alert["sample_error"]
+        .as_str()
+        .unwrap()
+        .contains("delivery failed"));
+
+    // Still failing: nothing more while the alert stands
+    for id in 5..10 {
+        client
+            .handle_event(&proposal_created(&id.to_string()), &node_api)
+            .await
+            .unwrap();
+    }
+    assert_eq!(alerts.check(now + 1).await.unwrap(), 0);
+    assert_eq!(alerts.check(now + 2).await.unwrap(), 0);
+
+    // Recovered once the failures have left the window
+    assert_eq!(alerts.check(now + WINDOW_SECS + 5).await.unwrap(), 1);
+    let recovery = received.recv().await.unwrap();
+    assert_eq!(recovery["status"], "resolved");
+    assert_eq!(recovery["category"], "webhook_undeliverable");
+    assert_eq!(recovery["count"], 0);
+    assert_eq!(alerts.check(now + WINDOW_SECS + 6).await.unwrap(), 0);
+    assert!(received.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_thresholds_apply_on_reload() {
+    let (alert_url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        alerts: alert_config("http://127.0.0.1:1/alerts", 10),
+        ..Default::default()
+    };
+    let alerts = Arc::new(AlertMonitor::new(config.alerts.clone()));
+    let errors = ErrorReporter::default().with_alert_monitor(Arc::clone(&alerts));
+
+    let node_api = Arc::new(MockNodeApi::new(100));
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let registry = Arc::new(
+        EconomicNodeRegistry::new(config.registry.clone(), node_api)
+            .await
+            .unwrap(),
+    );
+    let paths = ModulePaths {
+        data_dir: PathBuf::from("/var/lib/governance"),
+        socket_path: PathBuf::from("/run/blvm/node.sock"),
+    };
+    let next = Arc::new(Mutex::new((config.clone(), paths.clone())));
+    let reloader = ConfigReloader::new(
+        {
+            let next = Arc::clone(&next);
+            move || Ok(next.lock().unwrap().clone())
+        },
+        config.clone(),
+        paths,
+        webhook,
+        registry,
+    )
+    .with_alerts(Arc::clone(&alerts));
+
+    // Below the configured count
+    let now = unix_now();
+    for _ in 0..4 {
+        errors.record(registry_write_failed(), now);
+    }
+    assert_eq!(alerts.check(now).await.unwrap(), 0);
+
+    // Lowered, but the alert URL is down: the alert stays due
+    let mut lowered = config.clone();
+    lowered.alerts.count = 4;
+    next.lock().unwrap().0 = lowered.clone();
+    let summary = reloader.reload("SIGHUP").await;
+    assert_eq!(summary.applied, vec!["alerts"]);
+    assert!(summary.restart_required.is_empty());
+    assert!(alerts.check(now + 1).await.is_err());
+
+    lowered.alerts.url = Some(alert_url);
+    next.lock().unwrap().0 = lowered;
+    assert_eq!(reloader.reload("file").await.applied, vec!["alerts"]);
+    assert_eq!(alerts.check(now + 2).await.unwrap(), 1);
+    let alert = received.recv().await.unwrap();
+    assert_eq!(alert["status"], "firing");
+    assert_eq!(alert["category"], "registry_write_failed");
+    assert_eq!(alert["count"], 4);
+    assert_eq!(alert["thresholds"]["count"], 4);
+    assert_eq!(alert["sample_error"], "Database error during insert");
+    assert_eq!(alerts.check(now + 3).await.unwrap(), 0);
+}
@@ -0,0 +1,155 @@
+//! Amendments of proposals' content and tier, found by looking open proposals up again
+
+mod common;
+
+use blvm_governance::amendments::Change;
+use blvm_governance::config::{AmendedVotes, AmendmentConfig, DeadlineConfig};
+use blvm_governance::node_api::ProposalDetails;
+use blvm_governance::proposals::ProposalStatus;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::time::Duration;
+
+async fn block(node: &MockNode, height: u64) {
+    let hash = [height as u8; 32];
+    node.node_api
+        .add_block(height, hash, common::block([0u8; 32], Vec::new()));
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash,
+            height,
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_amendments_are_taken_until_voting_closes() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        deadlines: DeadlineConfig {
+            windows: [("standard".to_string(), 50), ("core".to_string(), 200)].into(),
+            reminders: Vec::new(),
+        },
+        amendments: AmendmentConfig {
+            refresh_interval_blocks: 10,
+            votes: AmendedVotes::ResetOnContent,
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("amendments", config).await;
+    let store = &node.module.proposal_store;
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "bob".to_string(),
+            vote: "yes".to_string(),
+        },
+    )
+    .await;
+    assert!(store.amendments("1").unwrap().is_empty());
+
+    // Re-classified: found on the next refresh, with the new tier's window and the votes kept
+    node.node_api.add_proposal(ProposalDetails {
+        tier: "core".to_string(),
+        ..common::proposal("1")
+    });
+    block(&node, 105).await;
+    assert_eq!(store.proposal("1").unwrap().unwrap().tier, "standard");
+    block(&node, 110).await;
+    let proposal = store.proposal("1").unwrap().unwrap();
+    assert_eq!((proposal.tier.as_str(), proposal.votes.len()), ("core", 1));
+    assert_eq!(store.deadline(&proposal), Some(300));
+
+    // New content: the votes are dropped
+    let content_hash = "cd".repeat(32);
+    node.node_api.add_proposal(ProposalDetails {
+        tier: "core".to_string(),
+        content_hash: Some(content_hash.clone()),
+        ..common::proposal("1")
+    });
+    store.refresh_open().await;
+    let view = store.view("1").unwrap().unwrap();
+    assert!(view.proposal.votes.is_empty());
+    assert_eq!(view.proposal.status, ProposalStatus::Created);
+    assert_eq!(view.amendments.len(), 2);
+    let amendment = &view.amendments[1];
+    assert_eq!(
+        amendment.content_hash,
+        Some(Change {
+            from: Some("ab".repeat(32)),
+            to: Some(content_hash.clone()),
+        })
+    );
+    assert_eq!((amendment.tier.clone(), amendment.votes_reset), (None, 1));
+
+    // Once merged, an amendment is rejected, and alerted on once
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    node.node_api.add_proposal(ProposalDetails {
+        tier: "standard".to_string(),
+        content_hash: Some(content_hash),
+        ..common::proposal("1")
+    });
+    store.refresh("1").await.unwrap();
+    store.refresh("1").await.unwrap();
+    let amendments = store.amendments("1").unwrap();
+    assert_eq!(amendments.len(), 3);
+    assert!(!amendments[2].accepted);
+    assert_eq!(store.proposal("1").unwrap().unwrap().tier, "core");
+
+    let mut sent = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        // Block notifications have no event type
+        let event_type = payload["event_type"].as_str().unwrap_or_default();
+        if event_type.contains("amend") {
+            sent.push((event_type.to_string(), payload["data"]["changes"].clone()));
+        }
+    }
+    assert_eq!(
+        sent,
+        vec![
+            (
+                "proposal_amended".to_string(),
+                serde_json::json!("tier standard to core")
+            ),
+            (
+                "proposal_amended".to_string(),
+                serde_json::json!(format!(
+                    "content hash {} to {}",
+                    "ab".repeat(32),
+                    "cd".repeat(32)
+                ))
+            ),
+            (
+                "amendment_rejected".to_string(),
+                serde_json::json!("tier core to standard")
+            ),
+        ]
+    );
+}
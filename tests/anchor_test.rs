@@ -0,0 +1,202 @@
+//! Governance state commitments anchored on-chain through the mock node's wallet
+
+mod common;
+
+use blvm_governance::anchor::{self, AnchorData, AnchorStatus};
+use blvm_governance::config::AuditLogConfig;
+use blvm_governance::status::StatusProvider;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+
+const TXID: [u8; 32] = [7u8; 32];
+
+async fn block(node: &MockNode, height: u64) {
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [height as u8; 32],
+            height,
+        },
+    )
+    .await;
+}
+
+fn config() -> GovernanceConfig {
+    let mut config = GovernanceConfig {
+        allow_actions: true,
+        audit_log: AuditLogConfig {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    config.anchor.enabled = true;
+    config.anchor.interval_blocks = 2;
+    config
+}
+
+/// Have the wallet fund every transaction asked for as `TXID`.
+fn fund(node: &MockNode) {
+    let funded = serde_json::json!({
+        "txid": hex::encode(TXID),
+        "hex": "02000000",
+        "fee_sats": 4000,
+        "vsize": 125,
+    });
+    node.node_api
+        .respond("fund_op_return", Ok(serde_json::to_vec(&funded).unwrap()));
+}
+
+/// Have the node answer `get_transaction` for `TXID` as confirmed at `height`, or unknown.
+fn confirmed(node: &MockNode, height: Option<u64>) {
+    let response = height.map(|height| {
+        serde_json::json!({
+            "tx": common::spending_tx(blvm_protocol::OutPoint {
+                hash: [9u8; 32],
+                index: 0,
+            }),
+            "block_hash": hex::encode([height as u8; 32]),
+            "height": height,
+            "confirmations": 1,
+        })
+    });
+    node.node_api.respond(
+        "get_transaction",
+        Ok(serde_json::to_vec(&response).unwrap()),
+    );
+}
+
+#[tokio::test]
+async fn test_state_is_anchored_and_confirmed() {
+    let node = MockNode::start("anchor", config()).await;
+    let anchorer = node.anchorer.as_ref().unwrap();
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    // Above the configured limit of 50 sat/vB
+    node.node_api.set_fee_estimate(6, 80);
+    fund(&node);
+    confirmed(&node, None);
+
+    // Off the interval
+    block(&node, 101).await;
+    assert_eq!(node.node_api.lookup_count("fund_op_return"), 0);
+
+    block(&node, 102).await;
+    let registry = node.module.economic_nodes.commitment().await;
+    let proposals = node.module.proposal_store.load_proposals().unwrap();
+    let expected = AnchorData {
+        height: 102,
+        registry_root: registry.root,
+        proposals_hash: anchor::proposals_hash(&proposals).unwrap(),
+    };
+    let (_, payload) = node
+        .node_api
+        .module_calls()
+        .into_iter()
+        .find(|(method, _)| method == "fund_op_return")
+        .unwrap();
+    let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(request["data"], hex::encode(expected.to_bytes()));
+    assert_eq!(request["sat_per_kvb"], 50_000);
+    assert_eq!(AnchorData::from_script(&expected.script()), Some(expected));
+    let anchored = anchorer.anchor(102).unwrap().unwrap();
+    assert_eq!(anchored.status, AnchorStatus::Broadcast);
+    assert_eq!(anchored.txid, Some(hex::encode(TXID)));
+    assert_eq!(anchored.proposal_count, 1);
+    assert_eq!(anchored.data(), Some(expected));
+
+    // Confirmed in the next block
+    confirmed(&node, Some(103));
+    block(&node, 103).await;
+    let anchored = anchorer.anchor(102).unwrap().unwrap();
+    assert_eq!(
+        (anchored.status, anchored.confirmed_height),
+        (AnchorStatus::Confirmed, Some(103))
+    );
+    assert_eq!(node.node_api.lookup_count("broadcast_transaction"), 1);
+
+    // The txid is recorded with the commitment when broadcast and when confirmed
+    let entries: Vec<serde_json::Value> = node
+        .audit_entries()
+        .into_iter()
+        .filter(|entry| entry["kind"] == "anchor")
+        .collect();
+    assert_eq!(entries.len(), 2);
+    for (entry, status) in entries.iter().zip(["broadcast", "confirmed"]) {
+        assert_eq!(entry["data"]["status"], status);
+        assert_eq!(entry["data"]["txid"], hex::encode(TXID));
+        assert_eq!(entry["data"]["registry_root"], registry.root_hex());
+    }
+
+    let status = anchorer.status().await;
+    assert_eq!(status["latest"]["txid"], hex::encode(TXID));
+    assert_eq!(status["last_confirmed"]["height"], 102);
+    assert_eq!(status["anchors"]["confirmed"], 1);
+}
+
+#[tokio::test]
+async fn test_failures_alert_and_are_not_retried_unboundedly() {
+    let mut config = config();
+    config.anchor.max_attempts = 2;
+    config.anchor.confirmation_timeout_blocks = 2;
+    let node = MockNode::start("anchor_failures", config).await;
+    let anchorer = node.anchorer.as_ref().unwrap();
+
+    // An empty wallet gives the anchor up at once
+    node.node_api
+        .respond("fund_op_return", Err("insufficient funds".to_string()));
+    block(&node, 102).await;
+    block(&node, 103).await;
+    let anchored = anchorer.anchor(102).unwrap().unwrap();
+    assert_eq!(
+        (anchored.status, anchored.attempts),
+        (AnchorStatus::Failed, 1)
+    );
+    assert_eq!(node.node_api.lookup_count("fund_op_return"), 1);
+    assert_eq!(node.errors.counts()["anchor_failed"], 1);
+
+    // A transient failure is tried again at the next block, up to the limit
+    node.node_api
+        .respond("fund_op_return", Err("connection reset".to_string()));
+    block(&node, 104).await;
+    assert_eq!(
+        anchorer.anchor(104).unwrap().unwrap().status,
+        AnchorStatus::Pending
+    );
+    block(&node, 105).await;
+    let anchored = anchorer.anchor(104).unwrap().unwrap();
+    assert_eq!(
+        (anchored.status, anchored.attempts),
+        (AnchorStatus::Failed, 2)
+    );
+    assert_eq!(node.node_api.lookup_count("fund_op_return"), 3);
+    assert_eq!(node.errors.counts()["anchor_failed"], 3);
+
+    // Broadcast but never confirmed: given up, not rebroadcast; the next interval anchors
+    // the state afresh
+    fund(&node);
+    confirmed(&node, None);
+    for height in 106..=108 {
+        block(&node, height).await;
+    }
+    let anchored = anchorer.anchor(106).unwrap().unwrap();
+    assert_eq!(anchored.status, AnchorStatus::Failed);
+    assert!(anchored.error.unwrap().contains("not confirmed"));
+    assert_eq!(
+        anchorer.anchor(108).unwrap().unwrap().status,
+        AnchorStatus::Broadcast
+    );
+    assert_eq!(node.node_api.lookup_count("broadcast_transaction"), 2);
+    assert_eq!(node.errors.counts()["anchor_failed"], 4);
+}
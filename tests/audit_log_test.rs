@@ -0,0 +1,211 @@
+//! Audit log of events, webhook deliveries, registry changes and actions
+
+mod common;
+
+use blvm_governance::audit::{ActionAudit, ActionAuditEntry};
+use blvm_governance::audit_log::{self, AuditEntry, AuditKind, AuditLog};
+use blvm_governance::cli;
+use blvm_governance::config::AuditLogConfig;
+use blvm_governance::economic_nodes::{ChangeKind, ChangeSource, RegistryChange};
+use blvm_governance::error::GovernanceError;
+use blvm_governance::pipeline::EventHandler;
+use blvm_governance::storage::DataDir;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn proposal_created(id: &str) -> ModuleMessage {
+    ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    })
+}
+
+/// A fresh data directory named after `name`.
+fn data_dir(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("blvm_audit_log_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&root).ok();
+    root
+}
+
+/// Every entry in `dir`, in order.
+fn entries(dir: &Path) -> Vec<AuditEntry> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    files
+        .iter()
+        .flat_map(|path| {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_records_are_chained_and_tampering_is_found() {
+    let root = data_dir("chain");
+    let dir = DataDir::open(&root).unwrap().audit();
+    let log = Arc::new(
+        AuditLog::open(
+            &dir,
+            &AuditLogConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(format!("{}?token=secret", url)),
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_audit_log(Arc::clone(&log));
+    let node_api = MockNodeApi::new(100);
+
+    // An event, as the pipeline passes it to the log and then to the webhook
+    let event = proposal_created("1");
+    log.handle(&event, &node_api).await.unwrap();
+    client.handle_event(&event, &node_api).await.unwrap();
+    received.recv().await.unwrap();
+
+    log.registry_change(&RegistryChange {
+        node_id: Some(hex::encode([1u8; 32])),
+        kind: ChangeKind::Expired,
+        before: serde_json::Value::Null,
+        after: serde_json::Value::Null,
+        height: 100,
+        source: ChangeSource::Organic,
+        intent: None,
+    });
+    let actions = ActionAudit::default().with_audit_log(Arc::clone(&log));
+    actions
+        .record(ActionAuditEntry {
+            timestamp: 1_700_000_000,
+            action: "veto".to_string(),
+            request: "{}".to_string(),
+            response: None,
+            error: Some("rejected".to_string()),
+        })
+        .unwrap();
+
+    let recorded = entries(&dir);
+    let kinds: Vec<AuditKind> = recorded.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AuditKind::Event,
+            AuditKind::WebhookDelivery,
+            AuditKind::RegistryChange,
+            AuditKind::Action,
+        ]
+    );
+    assert_eq!(recorded[0].data["event_type"], "GovernanceProposalCreated");
+    let delivery = &recorded[1].data;
+    assert_eq!(delivery["event_type"], "proposal_created");
+    assert_eq!(delivery["delivered"], true);
+    assert_eq!(delivery["status"], 200);
+    assert!(!delivery["url"].as_str().unwrap().contains("secret"));
+    assert_eq!(recorded[3].data["error"], "rejected");
+
+    let output = cli::verify_audit(&root).unwrap();
+    assert!(output.contains("4 entries in 1 files, chain intact"));
+    assert!(output.contains(&recorded[3].hash));
+
+    // Alter the delivery, keeping the line valid JSON
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(
+        &path,
+        content.replacen("\"status\":200", "\"status\":500", 1),
+    )
+    .unwrap();
+    match audit_log::verify(&dir) {
+        Err(GovernanceError::AuditChainBroken { file, line, .. }) => {
+            assert_eq!(file, path.display().to_string());
+            assert_eq!(line, 2);
+        }
+        other => panic!("expected a broken chain, got {:?}", other),
+    }
+    assert!(cli::verify_audit(&root)
+        .unwrap_err()
+        .to_string()
+        .contains(":2: audit chain broken"));
+
+    // Removing it instead breaks the link of the next entry
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.contains("\"status\":200"))
+        .collect();
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    match audit_log::verify(&dir) {
+        Err(GovernanceError::AuditChainBroken { line, problem, .. }) => {
+            assert_eq!(line, 2);
+            assert!(problem.contains("entry 2 where entry 1 should be"));
+        }
+        other => panic!("expected a broken chain, got {:?}", other),
+    }
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_failed_delivery_is_recorded_with_its_error() {
+    let root = data_dir("failed");
+    let dir = DataDir::open(&root).unwrap().audit();
+    let log = Arc::new(
+        AuditLog::open(
+            &dir,
+            &AuditLogConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let config = GovernanceConfig {
+        webhook_url: Some("http://127.0.0.1:1/webhook".to_string()),
+        webhook_retry_count: 0,
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_audit_log(Arc::clone(&log));
+    client
+        .handle_event(&proposal_created("1"), &MockNodeApi::new(100))
+        .await
+        .unwrap();
+
+    let recorded = entries(&dir);
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].kind, AuditKind::WebhookDelivery);
+    assert_eq!(recorded[0].data["delivered"], false);
+    assert!(recorded[0].data["status"].is_null());
+    assert!(recorded[0].data["error"].is_string());
+    assert_eq!(log.head().unwrap().hash, recorded[0].hash);
+    assert_eq!(audit_log::verify(&dir).unwrap().entries, 1);
+    std::fs::remove_dir_all(&root).ok();
+}
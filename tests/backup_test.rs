@@ -0,0 +1,115 @@
+//! Backup and restore of the module store
+
+mod common;
+
+use blvm_governance::backup;
+use blvm_governance::config::RegistryConfig;
+use blvm_governance::economic_nodes::{commitment, EconomicNodeRegistry};
+use blvm_governance::proposals::ProposalStore;
+use blvm_sdk::module::ModuleDb;
+use std::path::Path;
+use std::sync::Arc;
+
+fn open_db(dir: &Path) -> ModuleDb {
+    ModuleDb::open_with_migrations(
+        dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+}
+
+async fn registry(db: Arc<dyn blvm_node::storage::database::Database>) -> EconomicNodeRegistry {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    EconomicNodeRegistry::new(RegistryConfig::default(), node_api)
+        .await
+        .unwrap()
+        .with_store(db)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_backup_restores_by_pointing_at_directory() {
+    let root = std::env::temp_dir().join(format!("blvm_backup_test_{}", std::process::id()));
+    let data_dir = root.join("data");
+    let backup_dir = root.join("backup");
+    std::fs::create_dir_all(&data_dir).unwrap();
+    std::fs::write(data_dir.join("config.toml"), "[governance]\n").unwrap();
+
+    let db = open_db(&data_dir).as_db();
+    let live = registry(Arc::clone(&db)).await;
+    live.register(&hex::encode([1u8; 32]), "miner", Some(40.0), None)
+        .await
+        .unwrap();
+    live.register(&hex::encode([2u8; 32]), "exchange", Some(60.0), None)
+        .await
+        .unwrap();
+    let proposals = ProposalStore::new(db);
+
+    let manifest = backup::backup(
+        &live,
+        &proposals,
+        Some(&data_dir.join("config.toml")),
+        &backup_dir,
+    )
+    .await
+    .unwrap();
+    assert_eq!(manifest.node_count, 2);
+    assert_eq!(manifest.commitment, live.commitment().await.root_hex());
+    assert!(backup_dir.join("config.toml").exists());
+
+    // Changes after the backup are not in it
+    live.register(&hex::encode([3u8; 32]), "miner", Some(5.0), None)
+        .await
+        .unwrap();
+
+    assert_eq!(backup::verify(&backup_dir).unwrap(), manifest);
+    let restored = registry(open_db(&backup_dir).as_db()).await;
+    assert_eq!(restored.list_nodes().await.len(), 2);
+    assert_eq!(restored.commitment().await.root_hex(), manifest.commitment);
+
+    // A backup never overwrites an existing one
+    assert!(backup::backup(&live, &proposals, None, &backup_dir)
+        .await
+        .is_err());
+
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_unversioned_manifest_is_checked_at_commitment_version_1() {
+    let root = std::env::temp_dir().join(format!("blvm_backup_v1_test_{}", std::process::id()));
+    let backup_dir = root.join("backup");
+    std::fs::create_dir_all(root.join("data")).unwrap();
+    let db = open_db(&root.join("data")).as_db();
+    let live = registry(Arc::clone(&db)).await;
+    live.register(&hex::encode([1u8; 32]), "miner", Some(40.0), None)
+        .await
+        .unwrap();
+    let proposals = ProposalStore::new(db);
+    let manifest = backup::backup(&live, &proposals, None, &backup_dir)
+        .await
+        .unwrap();
+    assert_eq!(manifest.commitment_version, commitment::COMMITMENT_VERSION);
+
+    // As written before the version was recorded
+    let path = backup_dir.join(backup::MANIFEST_FILE);
+    let mut old: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    old.as_object_mut().unwrap().remove("commitment_version");
+    std::fs::write(&path, serde_json::to_vec(&old).unwrap()).unwrap();
+    assert!(backup::verify(&backup_dir).is_err());
+
+    let nodes = live.list_nodes().await;
+    old["commitment"] = commitment::compute_version(1, &nodes)
+        .unwrap()
+        .root_hex()
+        .into();
+    std::fs::write(&path, serde_json::to_vec(&old).unwrap()).unwrap();
+    assert_eq!(backup::verify(&backup_dir).unwrap().commitment_version, 1);
+
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,415 @@
+//! Offline subcommands of the binary, run through their entry functions
+
+mod common;
+
+use blvm_governance::audit_log::AuditLog;
+use blvm_governance::build_info::BuildInfo;
+use blvm_governance::cli::{self, ExportFormat};
+use blvm_governance::config::{AuditLogConfig, GovernanceConfig, RegistryConfig};
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::error::GovernanceError;
+use blvm_governance::history_export::{HistoryFormat, HistoryRange};
+use blvm_governance::pipeline::EventHandler;
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::storage::DataDir;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use blvm_sdk::module::ModuleDb;
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blvm_cli_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A registry stored in `dir`; the store is closed when it is dropped.
+async fn registry_in(dir: &Path) -> EconomicNodeRegistry {
+    let db = ModuleDb::open_with_migrations(
+        dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    EconomicNodeRegistry::new(RegistryConfig::default(), node_api)
+        .await
+        .unwrap()
+        .with_store(db)
+        .unwrap()
+}
+
+/// A data directory holding a registry with two nodes; the store is closed on return.
+async fn data_dir_with_nodes(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    let registry = registry_in(&dir).await;
+    registry
+        .register(&hex::encode([1u8; 32]), "miner", Some(40.0), None)
+        .await
+        .unwrap();
+    registry
+        .register(&hex::encode([2u8; 32]), "exchange", Some(60.0), None)
+        .await
+        .unwrap();
+    dir
+}
+
+#[test]
+fn test_check_config() {
+    let dir = temp_dir("check_config");
+    let path = dir.join("config.toml");
+    assert!(matches!(
+        cli::check_config(&path),
+        Err(GovernanceError::ConfigError(_))
+    ));
+
+    std::fs::write(
+        &path,
+        "[governance]\nwebhook_url = \"https://example.com/hook\"\n",
+    )
+    .unwrap();
+    assert!(cli::check_config(&path)
+        .unwrap()
+        .ends_with("configuration is valid"));
+
+    std::fs::write(&path, "[governance]\nwebhook_url = \"not a url\"\n").unwrap();
+    let error = cli::check_config(&path).unwrap_err().to_string();
+    assert!(error.contains("governance.webhook_url"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_init_config_passes_check_config() {
+    let dir = temp_dir("init_config");
+    let path = dir.join("config.toml");
+    assert_eq!(
+        cli::init_config(Some(&path), false).unwrap(),
+        format!("Wrote {}", path.display())
+    );
+    cli::check_config(&path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("\n[governance.registry.veto]\n"));
+    assert!(text.contains("\n# webhook_url = \"\"\n"));
+    assert_eq!(cli::init_config(None, false).unwrap(), text);
+
+    // Not replaced without --force
+    std::fs::write(&path, "[governance]\n").unwrap();
+    assert!(cli::init_config(Some(&path), false).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[governance]\n");
+    cli::init_config(Some(&path), true).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+}
+
+#[tokio::test]
+async fn test_test_webhook_posts_synthetic_event() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url.clone()),
+        // The test payload is sent whatever the filter
+        webhook_events: vec!["proposal_merged".to_string()],
+        ..Default::default()
+    };
+    let output = cli::test_webhook(&config, "proposal_created")
+        .await
+        .unwrap();
+    assert!(
+        output.contains(&url) && output.contains("200"),
+        "{}",
+        output
+    );
+    let payload = received.recv().await.unwrap();
+    assert_eq!(payload["event_type"], "proposal_created");
+    assert_eq!(payload["test"], true);
+    assert_eq!(payload["data"]["tier"], "standard");
+    assert_eq!(payload["build"]["version"], env!("CARGO_PKG_VERSION"));
+
+    assert!(matches!(
+        cli::test_webhook(&GovernanceConfig::default(), "proposal_created").await,
+        Err(GovernanceError::ConfigError(_))
+    ));
+}
+
+#[test]
+fn test_dump_schema() {
+    let documents: serde_json::Value =
+        serde_json::from_str(&cli::dump_schema(None).unwrap()).unwrap();
+    let created = &documents["proposal_created"];
+    assert_eq!(created["x-schema-version"], 1);
+    assert_eq!(created["additionalProperties"], false);
+    assert_eq!(created["properties"]["pr_number"]["type"], "integer");
+    assert_eq!(
+        documents["veto_escalation"]["x-event-types"][1],
+        "veto_deescalation"
+    );
+
+    let dir = temp_dir("dump_schema");
+    cli::dump_schema(Some(&dir)).unwrap();
+    let envelope: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(dir.join("envelope.schema.json")).unwrap()).unwrap();
+    assert_eq!(envelope, documents["envelope"]);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_version() {
+    let version = |args: &[&str]| {
+        let args = cli::Args::try_parse_from(
+            std::iter::once("blvm-governance").chain(args.iter().copied()),
+        )
+        .unwrap();
+        async move { cli::execute(&args).await.unwrap().unwrap() }
+    };
+    let text = version(&["version"]).await;
+    assert!(text.starts_with(&format!("blvm-governance {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(text.contains("\nipc protocol: 1..=1"));
+
+    let info: BuildInfo = serde_json::from_str(&version(&["version", "--json"]).await).unwrap();
+    assert_eq!(info, *BuildInfo::current());
+    assert!(!info.rustc_version.is_empty());
+
+    // --version prints the same details
+    let error = cli::Args::try_parse_from(["blvm-governance", "--version"]).unwrap_err();
+    assert_eq!(error.kind(), clap::error::ErrorKind::DisplayVersion);
+    assert!(error.to_string().contains("\ncommit:"));
+}
+
+#[tokio::test]
+async fn test_export_registry() {
+    let dir = data_dir_with_nodes("export").await;
+    let config = GovernanceConfig::default();
+
+    let json = dir.join("registry.json");
+    let output = cli::export_registry(&dir, &config, &json, ExportFormat::Json).unwrap();
+    assert!(output.starts_with("Exported 2 nodes"), "{}", output);
+    let export: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(export["nodes"].as_array().unwrap().len(), 2);
+
+    let csv = dir.join("nodes.csv");
+    cli::export_registry(&dir, &config, &csv, ExportFormat::Csv).unwrap();
+    let nodes = std::fs::read_to_string(&csv).unwrap();
+    assert_eq!(nodes.lines().count(), 3);
+    assert!(nodes.contains(&hex::encode([2u8; 32])));
+    assert!(dir.join("nodes.csv.tallies.csv").exists());
+
+    assert!(matches!(
+        cli::export_registry(
+            Path::new("/nonexistent"),
+            &config,
+            &json,
+            ExportFormat::Json
+        ),
+        Err(GovernanceError::Storage(_))
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_export_history() {
+    let dir = data_dir_with_nodes("history").await;
+    // A registration is not a weight change; a re-announcement with a new weight is
+    registry_in(&dir)
+        .await
+        .register(&hex::encode([1u8; 32]), "miner", Some(45.0), None)
+        .await
+        .unwrap();
+    let audit = AuditLog::open(
+        &DataDir::open(&dir).unwrap().audit(),
+        &AuditLogConfig {
+            enabled: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let node_api = common::MockNodeApi::new(101);
+    let events = [
+        (
+            EventType::NewBlock,
+            EventPayload::NewBlock {
+                block_hash: [7u8; 32],
+                height: 101,
+            },
+        ),
+        (
+            EventType::GovernanceProposalVoted,
+            EventPayload::GovernanceProposalVoted {
+                proposal_id: "4".to_string(),
+                voter: "bob, jr".to_string(),
+                vote: "yes".to_string(),
+            },
+        ),
+    ];
+    for (event_type, payload) in events {
+        let message = ModuleMessage::Event(EventMessage {
+            event_type,
+            payload,
+        });
+        audit.handle(&message, &node_api).await.unwrap();
+    }
+    drop(audit);
+
+    let out = dir.join("history");
+    let output =
+        cli::export_history(&dir, HistoryRange::default(), &out, HistoryFormat::Csv).unwrap();
+    assert!(output.contains("weight_changes.csv: 1 rows"), "{}", output);
+    let votes = std::fs::read_to_string(out.join("votes.csv")).unwrap();
+    let rows: Vec<_> = votes.split("\r\n").collect();
+    assert_eq!(rows[0], "audit_seq,timestamp,height,proposal_id,voter,vote");
+    assert!(rows[1].ends_with(",101,4,\"bob, jr\",yes"), "{}", rows[1]);
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["schema_version"], 2);
+    let rows: Vec<_> = manifest["tables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["name"].as_str().unwrap(), t["rows"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("proposals", 0),
+            ("votes", 1),
+            ("vetoes", 0),
+            ("weight_changes", 1),
+            ("signaling", 0),
+            ("participation", 0)
+        ]
+    );
+
+    // The change at 100 is left out, and the same export gives the same files
+    let range = HistoryRange {
+        from_height: Some(101),
+        to_height: None,
+    };
+    cli::export_history(&dir, range, &out, HistoryFormat::Csv).unwrap();
+    let changes = std::fs::read_to_string(out.join("weight_changes.csv")).unwrap();
+    assert_eq!(changes.lines().count(), 1);
+    let again = dir.join("again");
+    cli::export_history(&dir, range, &again, HistoryFormat::Csv).unwrap();
+    for file in ["votes.csv", "manifest.json"] {
+        assert_eq!(
+            std::fs::read(out.join(file)).unwrap(),
+            std::fs::read(again.join(file)).unwrap()
+        );
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_export_veto_evidence() {
+    let dir = temp_dir("veto_evidence");
+    {
+        let db = ModuleDb::open_with_migrations(
+            &dir,
+            blvm_sdk::migrations!(
+                1 => blvm_governance::storage::up_v1,
+                2 => blvm_governance::storage::up_v2,
+                3 => blvm_governance::storage::up_v3
+            ),
+        )
+        .unwrap()
+        .as_db();
+        let node_api = Arc::new(common::MockNodeApi::new(100));
+        let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+            .await
+            .unwrap()
+            .with_store(Arc::clone(&db))
+            .unwrap();
+        for (id, node_type, weight) in [(1u8, "miner", 40.0), (2, "exchange", 60.0)] {
+            registry
+                .register(&hex::encode([id; 32]), node_type, Some(weight), None)
+                .await
+                .unwrap();
+        }
+        registry
+            .veto("p1", &hex::encode([2u8; 32]), "unsafe", None)
+            .await
+            .unwrap();
+        let message = ModuleMessage::Event(EventMessage {
+            event_type: EventType::GovernanceProposalCreated,
+            payload: EventPayload::GovernanceProposalCreated {
+                proposal_id: "p1".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            },
+        });
+        ProposalStore::new(db)
+            .handle_event(&message, node_api.as_ref())
+            .await
+            .unwrap();
+    }
+    let config = GovernanceConfig::default();
+    let key = dir.join("signing.key");
+    std::fs::write(&key, format!("{}\n", hex::encode([9u8; 32]))).unwrap();
+
+    let out = dir.join("evidence.json");
+    let output = cli::export_veto_evidence(&dir, &config, "p1", &key, &out).unwrap();
+    assert!(output.starts_with("Exported 1 vetoes on p1"), "{}", output);
+    let bundle: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(bundle["evidence"]["proposal"]["proposal_id"], "p1");
+    assert_eq!(
+        bundle["evidence"]["registry"]["vetoes"][0]["node_id"],
+        hex::encode([2u8; 32])
+    );
+    let report = cli::verify_veto_evidence(&out).unwrap();
+    assert!(report.contains("signatures Unsigned"), "{}", report);
+
+    // The same state and key give the same bytes
+    let again = dir.join("again.json");
+    cli::export_veto_evidence(&dir, &config, "p1", &key, &again).unwrap();
+    assert_eq!(std::fs::read(&out).unwrap(), std::fs::read(&again).unwrap());
+
+    // Editing the evidence breaks the bundle's signature
+    let tampered = std::fs::read_to_string(&out)
+        .unwrap()
+        .replace("\"reason\": \"unsafe\"", "\"reason\": \"spam\"");
+    std::fs::write(&again, tampered).unwrap();
+    assert!(matches!(
+        cli::verify_veto_evidence(&again),
+        Err(GovernanceError::ValidationError { .. })
+    ));
+
+    assert!(matches!(
+        cli::export_veto_evidence(&dir, &config, "p2", &key, &out),
+        Err(GovernanceError::NotFound { .. })
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_show_node() {
+    let dir = data_dir_with_nodes("show_node").await;
+    let config = GovernanceConfig::default();
+
+    let id = hex::encode([1u8; 32]);
+    let output = cli::show_node(&dir, &config, &id, false).unwrap();
+    assert!(
+        output.starts_with(&format!("Economic node {}", id)),
+        "{}",
+        output
+    );
+    assert!(output.contains("type: miner"));
+    // Registered without a reserve claim, so it carries no weight
+    assert!(output.contains("weight: 0 raw, 0 effective"), "{}", output);
+
+    assert!(matches!(
+        cli::show_node(&dir, &config, &hex::encode([3u8; 32]), true),
+        Err(GovernanceError::NotFound { .. })
+    ));
+    assert!(matches!(
+        cli::show_node(&dir, &config, "abc", false),
+        Err(GovernanceError::ValidationError { .. })
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+}
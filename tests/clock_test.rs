@@ -0,0 +1,133 @@
+//! Local clock check against the timestamps of new blocks
+
+mod common;
+
+use blvm_governance::clock::{unix_now, ClockMonitor, BLOCK_TIME_SLACK_SECS};
+use blvm_governance::config::ClockConfig;
+use blvm_governance::node_api::TipTracker;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use common::MockNodeApi;
+use std::sync::Arc;
+
+const SAMPLES: usize = 5;
+
+fn monitor(use_block_time: bool) -> (Arc<ClockMonitor>, Arc<TipTracker>) {
+    let clock = Arc::new(ClockMonitor::new(ClockConfig {
+        samples: SAMPLES,
+        use_block_time,
+        ..Default::default()
+    }));
+    let tip = Arc::new(TipTracker::default());
+    clock.connected(Arc::clone(&tip));
+    (clock, tip)
+}
+
+/// Announce the block at `height` stamped `timestamp`, as dispatch and the clock handler do.
+async fn new_block(
+    clock: &ClockMonitor,
+    tip: &TipTracker,
+    node_api: &MockNodeApi,
+    height: u64,
+    timestamp: u64,
+) {
+    let hash = [height as u8 + 1; 32];
+    let mut block = common::block([height as u8; 32], Vec::new());
+    block.header.timestamp = timestamp as _;
+    node_api.add_block(height, hash, block);
+    tip.observe(hash, height);
+    clock.check_block(&hash, height, node_api).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fast_clock_is_reported_and_corrected() {
+    let node_api = MockNodeApi::new(100);
+    let (clock, tip) = monitor(true);
+    let ahead = 3 * 60 * 60;
+
+    for height in 100..100 + SAMPLES as u64 {
+        assert!(!clock.status().skewed);
+        new_block(&clock, &tip, &node_api, height, unix_now() - ahead).await;
+    }
+    let status = clock.status();
+    assert!(status.skewed && status.using_block_time);
+    assert!(status.skew_secs.unwrap().abs_diff(ahead as i64) < 5);
+
+    // Payloads are stamped with block time
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let webhook = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock));
+    webhook.send_test("proposal_created").await.unwrap();
+    let payload = received.recv().await.unwrap();
+    let timestamp = payload["timestamp"].as_u64().unwrap();
+    assert!(timestamp.abs_diff(unix_now() - ahead) < 5);
+
+    // Back within the slack once the clock is fixed
+    for height in 200..200 + SAMPLES as u64 {
+        new_block(&clock, &tip, &node_api, height, unix_now() - 30).await;
+    }
+    assert!(!clock.status().skewed);
+    assert!(clock.payload_time().abs_diff(unix_now()) < 5);
+}
+
+#[tokio::test]
+async fn test_initial_sync_is_not_reported() {
+    let node_api = MockNodeApi::new(0);
+    node_api.set_syncing(true);
+    let (clock, tip) = monitor(false);
+
+    // Historical blocks streaming past, ten minutes of chain time apart
+    let genesis = 1_231_006_505;
+    for height in 1..50u64 {
+        new_block(&clock, &tip, &node_api, height, genesis + height * 600).await;
+        let status = clock.status();
+        assert!(!status.skewed);
+        assert!(status.samples < SAMPLES);
+    }
+    assert!(clock.status().syncing);
+    // Asked again only once each window fills
+    assert_eq!(node_api.lookup_count("get_chain_info"), 49 / SAMPLES);
+
+    // Caught up: blocks arrive in real time
+    node_api.set_syncing(false);
+    for height in 50..50 + SAMPLES as u64 {
+        new_block(&clock, &tip, &node_api, height, unix_now() - 60).await;
+    }
+    let status = clock.status();
+    assert!(!status.skewed && !status.syncing);
+    assert!(status.skew_secs.unwrap().abs_diff(60) < 5);
+}
+
+#[tokio::test]
+async fn test_block_timestamp_slack_is_tolerated() {
+    let node_api = MockNodeApi::new(100);
+    let (clock, tip) = monitor(false);
+
+    // Miners may stamp blocks up to two hours ahead
+    for height in 100..100 + SAMPLES as u64 {
+        new_block(
+            &clock,
+            &tip,
+            &node_api,
+            height,
+            unix_now() + BLOCK_TIME_SLACK_SECS,
+        )
+        .await;
+    }
+    let status = clock.status();
+    assert!(status.skew_secs.unwrap() < 0);
+    assert!(!status.skewed);
+    assert_eq!(node_api.lookup_count("get_chain_info"), 0);
+
+    // Blocks below the tip, replayed or backfilled, are not sampled
+    let lookups = node_api.lookup_count("get_block_header");
+    clock.check_block(&[1; 32], 50, &node_api).await.unwrap();
+    assert_eq!(node_api.lookup_count("get_block_header"), lookups);
+    assert_eq!(clock.status(), status);
+}
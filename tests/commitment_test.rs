@@ -0,0 +1,62 @@
+//! Registry commitments against the pinned fixture of each encoding version
+
+use blvm_governance::economic_nodes::commitment::{self, COMMITMENT_VERSION};
+use blvm_governance::economic_nodes::EconomicNode;
+
+/// One registry per encoding version, `v<version>.json`, with its pinned root.
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/commitment");
+
+/// The fixture of `version`: its nodes, pinned root and node count.
+fn fixture(version: u32) -> (Vec<EconomicNode>, String, usize) {
+    let path = format!("{}/v{}.json", FIXTURES, version);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    let fixture: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(fixture["version"], version);
+    (
+        serde_json::from_value(fixture["nodes"].clone()).unwrap(),
+        fixture["root"].as_str().unwrap().to_string(),
+        fixture["node_count"].as_u64().unwrap() as usize,
+    )
+}
+
+#[test]
+fn test_every_version_matches_its_fixture() {
+    for version in 1..=COMMITMENT_VERSION {
+        let (nodes, root, node_count) = fixture(version);
+        let c = commitment::compute_version(version, &nodes).unwrap();
+        assert_eq!(c.version, version);
+        assert_eq!(
+            (c.root_hex(), c.node_count),
+            (root, node_count),
+            "version {}",
+            version
+        );
+    }
+    let none = std::iter::empty::<&EconomicNode>();
+    assert!(commitment::compute_version(COMMITMENT_VERSION + 1, none).is_none());
+}
+
+#[test]
+fn test_current_version_is_computed_by_default() {
+    let (nodes, root, _) = fixture(COMMITMENT_VERSION);
+    let c = commitment::compute(&nodes);
+    assert_eq!((c.version, c.root_hex()), (COMMITMENT_VERSION, root));
+}
+
+#[test]
+fn test_versions_disagree_on_the_same_registry() {
+    // Version 1 sees neither the key set, the Lightning identity nor the deactivation
+    let (nodes, root, _) = fixture(2);
+    let v1 = commitment::compute_version(1, &nodes).unwrap();
+    assert_eq!(
+        v1.root_hex(),
+        "6b598a06eabf6049d000d209c9a918acc3f758ac5627cb8176262f708322ce8c"
+    );
+    assert_eq!(v1.node_count, 4);
+    assert_ne!(v1.root_hex(), root);
+
+    // A registry without any of them still gets a new root: the leaves grew
+    let (nodes, root, _) = fixture(1);
+    let v2 = commitment::compute_version(2, &nodes).unwrap();
+    assert_ne!(v2.root_hex(), root);
+}
@@ -1,388 +1,397 @@
 //! Shared test utilities for governance tests
+//!
+//! [`MockNodeApi`] (from the `testing` feature) stands in for the node. [`MockNode`] runs the
+//! module on top of it, wired as in main.rs, so a scenario is a list of `send_event` calls
+//! followed by assertions.
 
-use blvm_node::module::ipc::protocol::{EventMessage, ModuleMessage};
-use blvm_node::module::traits::{EventType, NodeAPI};
+#![allow(dead_code)]
+
+use blvm_governance::adoption::AdoptionTracker;
+use blvm_governance::anchor::Anchorer;
+use blvm_governance::audit_log::AuditLog;
+use blvm_governance::checkpoint::Checkpointer;
+use blvm_governance::delegation::DelegationRegistry;
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::epoch_summary::EpochSummarizer;
+use blvm_governance::error_report::ErrorReporter;
+use blvm_governance::escalation::VetoEscalation;
+use blvm_governance::event_queue::EventQueue;
+use blvm_governance::event_stream::EventStreamMonitor;
+use blvm_governance::intent::IntentLog;
+use blvm_governance::ipc_metrics::IpcMetrics;
+use blvm_governance::node_api::{NodeApiIpc, ProposalDetails};
+use blvm_governance::participation::ParticipationAnalyzer;
+use blvm_governance::pause::PauseControl;
+use blvm_governance::pipeline::{EventHandler, Pipeline};
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::shutdown::Shutdown;
+use blvm_governance::subscriptions::EventSubscriptions;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::{GovernanceConfig, GovernanceModule};
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload};
+use blvm_node::module::traits::EventType;
 use blvm_protocol::Hash;
-use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-/// Minimal MockNodeAPI for governance tests - implements only required NodeAPI methods.
-pub struct MockNodeAPI {
-    pub block_height: u64,
-}
+pub use blvm_governance::testing::MockNodeApi;
 
-#[async_trait::async_trait]
-impl NodeAPI for MockNodeAPI {
-    async fn get_block_height(&self) -> Result<u64, blvm_node::module::traits::ModuleError> {
-        Ok(self.block_height)
-    }
-    async fn get_block(
-        &self,
-        _: &Hash,
-    ) -> Result<Option<blvm_protocol::Block>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn get_block_header(
-        &self,
-        _: &Hash,
-    ) -> Result<Option<blvm_protocol::BlockHeader>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn get_transaction(
-        &self,
-        _: &Hash,
-    ) -> Result<Option<blvm_protocol::Transaction>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn has_transaction(
-        &self,
-        _: &Hash,
-    ) -> Result<bool, blvm_node::module::traits::ModuleError> {
-        Ok(false)
-    }
-    async fn get_chain_tip(&self) -> Result<Hash, blvm_node::module::traits::ModuleError> {
-        Ok([0u8; 32])
-    }
-    async fn get_utxo(
-        &self,
-        _: &blvm_protocol::OutPoint,
-    ) -> Result<Option<blvm_protocol::UTXO>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn subscribe_events(
-        &self,
-        _: Vec<EventType>,
-    ) -> Result<
-        tokio::sync::mpsc::Receiver<ModuleMessage>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        let (_tx, rx) = tokio::sync::mpsc::channel(100);
-        Ok(rx)
-    }
-    async fn get_mempool_transactions(
-        &self,
-    ) -> Result<Vec<Hash>, blvm_node::module::traits::ModuleError> {
-        Ok(Vec::new())
-    }
-    async fn get_mempool_transaction(
-        &self,
-        _: &Hash,
-    ) -> Result<Option<blvm_protocol::Transaction>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn get_mempool_size(
-        &self,
-    ) -> Result<blvm_node::module::traits::MempoolSize, blvm_node::module::traits::ModuleError>
-    {
-        Ok(blvm_node::module::traits::MempoolSize {
-            transaction_count: 0,
-            size_bytes: 0,
-            total_fee_sats: 0,
-        })
-    }
-    async fn get_network_stats(
-        &self,
-    ) -> Result<
-        blvm_node::module::traits::NetworkStats,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(blvm_node::module::traits::NetworkStats {
-            peer_count: 0,
-            hash_rate: 0.0,
-            bytes_sent: 0,
-            bytes_received: 0,
-        })
-    }
-    async fn get_network_peers(
-        &self,
-    ) -> Result<
-        Vec<blvm_node::module::traits::PeerInfo>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(Vec::new())
-    }
-    async fn get_chain_info(
-        &self,
-    ) -> Result<blvm_node::module::traits::ChainInfo, blvm_node::module::traits::ModuleError>
-    {
-        Ok(blvm_node::module::traits::ChainInfo {
-            tip_hash: [0u8; 32],
-            height: self.block_height,
-            difficulty: 1,
-            chain_work: 0,
-            is_synced: true,
-        })
-    }
-    async fn get_block_by_height(
-        &self,
-        _: u64,
-    ) -> Result<Option<blvm_protocol::Block>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn get_lightning_node_url(
-        &self,
-    ) -> Result<Option<String>, blvm_node::module::traits::ModuleError> {
-        Ok(None)
-    }
-    async fn get_lightning_info(
-        &self,
-    ) -> Result<
-        Option<blvm_node::module::traits::LightningInfo>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(None)
-    }
-    async fn get_payment_state(
-        &self,
-        _: &str,
-    ) -> Result<
-        Option<blvm_node::module::traits::PaymentState>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(None)
-    }
-    async fn check_transaction_in_mempool(
-        &self,
-        _: &Hash,
-    ) -> Result<bool, blvm_node::module::traits::ModuleError> {
-        Ok(false)
-    }
-    async fn get_fee_estimate(
-        &self,
-        _: u32,
-    ) -> Result<u64, blvm_node::module::traits::ModuleError> {
-        Ok(1)
-    }
-    async fn read_file(
-        &self,
-        _: String,
-    ) -> Result<Vec<u8>, blvm_node::module::traits::ModuleError> {
-        Ok(Vec::new())
-    }
-    async fn write_file(
-        &self,
-        _: String,
-        _: Vec<u8>,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn delete_file(
-        &self,
-        _: String,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn list_directory(
-        &self,
-        _: String,
-    ) -> Result<Vec<String>, blvm_node::module::traits::ModuleError> {
-        Ok(Vec::new())
-    }
-    async fn create_directory(
-        &self,
-        _: String,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn get_file_metadata(
-        &self,
-        _: String,
-    ) -> Result<
-        blvm_node::module::ipc::protocol::FileMetadata,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(blvm_node::module::ipc::protocol::FileMetadata {
-            path: String::new(),
-            size: 0,
-            is_file: false,
-            is_directory: false,
-            modified: None,
-            created: None,
-        })
-    }
-    async fn get_all_metrics(
-        &self,
-    ) -> Result<
-        HashMap<String, Vec<blvm_node::module::metrics::manager::Metric>>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(HashMap::new())
-    }
-    async fn register_rpc_endpoint(
-        &self,
-        _: String,
-        _: String,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn unregister_rpc_endpoint(
-        &self,
-        _: &str,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
+/// An open standard-tier proposal created at height 100.
+pub fn proposal(proposal_id: &str) -> ProposalDetails {
+    ProposalDetails {
+        proposal_id: proposal_id.to_string(),
+        title: format!("Proposal {}", proposal_id),
+        description: None,
+        content_hash: Some("ab".repeat(32)),
+        commitment_txid: None,
+        content_url: None,
+        tier: "standard".to_string(),
+        author: "alice".to_string(),
+        status: "open".to_string(),
+        created_height: 100,
+        conflict_domain: None,
+        conflicts_with: Vec::new(),
     }
-    async fn register_timer(
-        &self,
-        _: u64,
-        _: Arc<dyn blvm_node::module::timers::manager::TimerCallback>,
-    ) -> Result<
-        blvm_node::module::timers::manager::TimerId,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(0)
-    }
-    async fn cancel_timer(
-        &self,
-        _: blvm_node::module::timers::manager::TimerId,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn schedule_task(
-        &self,
-        _: u64,
-        _: Arc<dyn blvm_node::module::timers::manager::TaskCallback>,
-    ) -> Result<
-        blvm_node::module::timers::manager::TaskId,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(0)
-    }
-    async fn report_metric(
-        &self,
-        _: blvm_node::module::metrics::manager::Metric,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn get_module_metrics(
-        &self,
-        _: &str,
-    ) -> Result<
-        Vec<blvm_node::module::metrics::manager::Metric>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(Vec::new())
-    }
-    async fn initialize_module(
-        &self,
-        _: String,
-        _: std::path::PathBuf,
-        _: std::path::PathBuf,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn discover_modules(
-        &self,
-    ) -> Result<
-        Vec<blvm_node::module::traits::ModuleInfo>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(Vec::new())
-    }
-    async fn get_module_info(
-        &self,
-        _: &str,
-    ) -> Result<
-        Option<blvm_node::module::traits::ModuleInfo>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(None)
-    }
-    async fn is_module_available(
-        &self,
-        _: &str,
-    ) -> Result<bool, blvm_node::module::traits::ModuleError> {
-        Ok(false)
-    }
-    async fn publish_event(
-        &self,
-        _: EventType,
-        _: blvm_node::module::ipc::protocol::EventPayload,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn call_module(
-        &self,
-        _: Option<&str>,
-        _: &str,
-        _: Vec<u8>,
-    ) -> Result<Vec<u8>, blvm_node::module::traits::ModuleError> {
-        Ok(Vec::new())
-    }
-    async fn register_module_api(
-        &self,
-        _: Arc<dyn blvm_node::module::inter_module::api::ModuleAPI>,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn unregister_module_api(
-        &self,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
-    }
-    async fn get_module_health(
-        &self,
-        _: &str,
-    ) -> Result<
-        Option<blvm_node::module::process::monitor::ModuleHealth>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(None)
-    }
-    async fn get_all_module_health(
-        &self,
-    ) -> Result<
-        Vec<(String, blvm_node::module::process::monitor::ModuleHealth)>,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Ok(Vec::new())
+}
+
+/// A transaction spending `prevout`, with no outputs.
+pub fn spending_tx(prevout: blvm_protocol::OutPoint) -> blvm_protocol::Transaction {
+    blvm_protocol::Transaction {
+        version: 1,
+        inputs: vec![blvm_protocol::TransactionInput {
+            prevout,
+            script_sig: Vec::new(),
+            sequence: 0xffff_ffff,
+        }]
+        .into(),
+        outputs: Vec::new().into(),
+        lock_time: 0,
     }
-    async fn report_module_health(
-        &self,
-        _: blvm_node::module::process::monitor::ModuleHealth,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
+}
+
+/// A block on top of `prev_block_hash` holding `transactions`.
+pub fn block(
+    prev_block_hash: Hash,
+    transactions: Vec<blvm_protocol::Transaction>,
+) -> blvm_protocol::Block {
+    blvm_protocol::Block {
+        header: blvm_protocol::BlockHeader {
+            version: 1,
+            prev_block_hash,
+            merkle_root: [0u8; 32],
+            timestamp: 0,
+            bits: 0x1d00ffff,
+            nonce: 0,
+        },
+        transactions: transactions.into(),
     }
-    async fn send_mesh_packet_to_module(
-        &self,
-        _: &str,
-        _: Vec<u8>,
-        _: String,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
+}
+
+/// The module running against a [`MockNodeApi`], set up as main.rs sets up a connection.
+/// Its data directory is removed on drop.
+pub struct MockNode {
+    pub node_api: Arc<MockNodeApi>,
+    pub module: GovernanceModule,
+    /// With `delegation.enabled`.
+    pub delegations: Option<Arc<DelegationRegistry>>,
+    /// With `anchor.enabled`.
+    pub anchorer: Option<Arc<Anchorer>>,
+    /// With proposals under `adoption.proposals`.
+    pub adoption: Option<Arc<AdoptionTracker>>,
+    /// With `participation.enabled`.
+    pub participation: Option<Arc<ParticipationAnalyzer>>,
+    /// With `veto_escalation.bands` set.
+    pub escalation: Option<Arc<VetoEscalation>>,
+    /// Summarizes epochs with `epoch_summary.length_blocks` set.
+    pub epoch_summaries: Arc<EpochSummarizer>,
+    /// Errors reported by the module's subsystems.
+    pub errors: Arc<ErrorReporter>,
+    /// Pauses the module's outbound effects, kept in the data directory.
+    pub pause: Arc<PauseControl>,
+    dir: PathBuf,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl MockNode {
+    /// Start the module with `config` and a fresh data directory named after `name`.
+    pub async fn start(name: &str, config: GovernanceConfig) -> Self {
+        let dir = std::env::temp_dir().join(format!("blvm_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+            &dir,
+            blvm_sdk::migrations!(
+                1 => blvm_governance::storage::up_v1,
+                2 => blvm_governance::storage::up_v2,
+                3 => blvm_governance::storage::up_v3
+            ),
+        )
+        .unwrap()
+        .as_db();
+        let node_api = Arc::new(MockNodeApi::new(100));
+        let intents = Arc::new(IntentLog::open(&dir).unwrap());
+        let pause = Arc::new(PauseControl::open(&dir).unwrap());
+        let webhook_client = Arc::new(
+            GovernanceWebhookClient::new(&config)
+                .await
+                .unwrap()
+                .with_intents(Arc::clone(&intents))
+                .with_pause(Arc::clone(&pause)),
+        );
+        // As in main.rs, the tip is read before any handler runs
+        let ipc = NodeApiIpc::new(node_api.clone()).with_pause(Arc::clone(&pause));
+        ipc.get_best_block().await.unwrap();
+        let tip = Arc::clone(ipc.tip_tracker());
+        let proposal_cache = Arc::clone(ipc.proposal_cache());
+        let escalation = (!config.veto_escalation.bands.is_empty()).then(|| {
+            Arc::new(
+                VetoEscalation::open(config.veto_escalation.clone(), Arc::clone(&db))
+                    .unwrap()
+                    .with_webhook(Arc::clone(&webhook_client)),
+            )
+        });
+        let mut economic_nodes =
+            EconomicNodeRegistry::new(config.registry.clone(), node_api.clone())
+                .await
+                .unwrap()
+                .with_tip_tracker(Arc::clone(&tip))
+                .with_proposal_cache(Arc::clone(&proposal_cache))
+                .with_intents(Arc::clone(&intents))
+                .with_pause(Arc::clone(&pause));
+        if let Some(escalation) = &escalation {
+            economic_nodes = economic_nodes.with_escalation(Arc::clone(escalation));
+        }
+        let economic_nodes = Arc::new(economic_nodes.with_store(Arc::clone(&db)).unwrap());
+        let delegations = config.delegation.enabled.then(|| {
+            Arc::new(
+                DelegationRegistry::open(config.delegation.clone(), Arc::clone(&db), ipc.clone())
+                    .unwrap(),
+            )
+        });
+        let audit_log = config
+            .audit_log
+            .enabled
+            .then(|| Arc::new(AuditLog::open(&dir.join("audit"), &config.audit_log).unwrap()));
+        let mut proposal_store = ProposalStore::new(Arc::clone(&db))
+            .with_node_api(ipc.clone())
+            .with_tally(config.tally.clone())
+            .with_deadlines(config.deadlines.clone())
+            .with_activation(config.activation.clone())
+            .with_webhook(Arc::clone(&webhook_client))
+            .with_registry(Arc::clone(&economic_nodes))
+            .with_conflicts(config.conflicts.clone())
+            .with_amendments(config.amendments.clone());
+        if let Some(delegations) = &delegations {
+            proposal_store = proposal_store.with_delegations(Arc::clone(delegations));
+        }
+        if let Some(log) = &audit_log {
+            proposal_store = proposal_store.with_audit_log(Arc::clone(log));
+        }
+        let proposal_store = Arc::new(proposal_store);
+        if let Some(escalation) = &escalation {
+            escalation.set_proposals(Arc::clone(&proposal_store));
+        }
+        let errors = Arc::new(ErrorReporter::new(config.error_reports.clone()));
+        let anchorer = config.anchor.enabled.then(|| {
+            let anchorer = Anchorer::new(
+                config.anchor.clone(),
+                Arc::clone(&db),
+                ipc.clone().with_actions_allowed(config.allow_actions),
+                Arc::clone(&economic_nodes),
+                Arc::clone(&proposal_store),
+            )
+            .with_error_reporter(Arc::clone(&errors));
+            Arc::new(match &audit_log {
+                Some(log) => anchorer.with_audit_log(Arc::clone(log)),
+                None => anchorer,
+            })
+        });
+        let adoption = (!config.adoption.proposals.is_empty()).then(|| {
+            Arc::new(
+                AdoptionTracker::new(
+                    config.adoption.clone(),
+                    Arc::clone(&db),
+                    ipc.clone(),
+                    Arc::clone(&proposal_store),
+                    Arc::clone(&economic_nodes),
+                )
+                .with_webhook(Arc::clone(&webhook_client)),
+            )
+        });
+        let participation = config.participation.enabled.then(|| {
+            Arc::new(ParticipationAnalyzer::new(
+                config.participation.clone(),
+                Arc::clone(&db),
+                Arc::clone(&proposal_store),
+                Arc::clone(&economic_nodes),
+            ))
+        });
+        let mut epoch_summaries = EpochSummarizer::new(
+            config.epoch_summary.clone(),
+            Arc::clone(&db),
+            Arc::clone(&proposal_store),
+        )
+        .with_registry(Arc::clone(&economic_nodes))
+        .with_webhook(Arc::clone(&webhook_client));
+        if let Some(participation) = &participation {
+            epoch_summaries = epoch_summaries.with_participation(Arc::clone(participation));
+        }
+        let epoch_summaries = Arc::new(epoch_summaries);
+        let (events, event_rx) = EventQueue::new(&config.events);
+        let checkpointer = Arc::new(Checkpointer::open(&dir).unwrap());
+        let mut handlers: Vec<Arc<dyn EventHandler>> = vec![
+            Arc::clone(&webhook_client) as _,
+            Arc::clone(&economic_nodes) as _,
+            Arc::clone(&proposal_store) as _,
+        ];
+        if let Some(adoption) = &adoption {
+            handlers.push(Arc::clone(adoption) as _);
+        }
+        if let Some(participation) = &participation {
+            handlers.push(Arc::clone(participation) as _);
+        }
+        handlers.push(Arc::clone(&epoch_summaries) as _);
+        if let Some(anchorer) = &anchorer {
+            handlers.push(Arc::clone(anchorer) as _);
+        }
+        if let Some(delegations) = &delegations {
+            handlers.insert(2, Arc::clone(delegations) as _);
+        }
+        let mut pipeline = Pipeline::new(checkpointer)
+            .with_intents(intents)
+            .with_pause(Arc::clone(&pause));
+        if let Some(log) = audit_log {
+            handlers.insert(0, Arc::clone(&log) as _);
+            pipeline = pipeline.with_audit_log(log);
+        }
+        let module = GovernanceModule {
+            proposal_store,
+            webhook_client,
+            economic_nodes,
+            shutdown: Arc::new(Shutdown::new()),
+            events: Arc::new(events),
+            subscriptions: Arc::new(EventSubscriptions::new(GovernanceModule::event_types())),
+            stream: Arc::new(EventStreamMonitor::new(false)),
+            metrics: Arc::new(IpcMetrics::new()),
+            tip,
+            proposal_cache,
+            pipeline: Arc::new(pipeline.with_handlers(handlers)),
+        };
+        let mut tasks =
+            module.spawn_event_worker(event_rx, node_api.clone(), config.events.parallelism);
+        tasks.extend(
+            escalation
+                .as_ref()
+                .and_then(|escalation| escalation.spawn(node_api.clone())),
+        );
+        tasks.push(pause.spawn_drain(
+            Arc::clone(&module.pipeline),
+            node_api.clone(),
+            Arc::clone(&module.economic_nodes),
+        ));
+        Self {
+            node_api,
+            module,
+            delegations,
+            anchorer,
+            adoption,
+            participation,
+            escalation,
+            epoch_summaries,
+            errors,
+            pause,
+            dir,
+            tasks,
+        }
     }
-    async fn send_mesh_packet_to_peer(
-        &self,
-        _: String,
-        _: Vec<u8>,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
+
+    /// Entries of the audit log, when `audit_log.enabled`, oldest first.
+    pub fn audit_entries(&self) -> Vec<serde_json::Value> {
+        let mut entries: Vec<serde_json::Value> = Vec::new();
+        for file in std::fs::read_dir(self.dir.join("audit")).unwrap() {
+            let text = std::fs::read_to_string(file.unwrap().path()).unwrap();
+            entries.extend(text.lines().map(|line| serde_json::from_str(line).unwrap()));
+        }
+        entries.sort_by_key(|entry| entry["seq"].as_u64());
+        entries
     }
-    async fn send_stratum_v2_message_to_peer(
-        &self,
-        _: String,
-        _: Vec<u8>,
-    ) -> Result<(), blvm_node::module::traits::ModuleError> {
-        Ok(())
+
+    /// Register economic node `[id; 32]` as `node_type` with a verified reserve of `sats`,
+    /// the weight it then carries in tallies.
+    pub async fn register_verified(&self, id: u8, node_type: &str, sats: u64) {
+        let claim = self.node_api.add_reserve(&[id; 32], sats);
+        self.module
+            .economic_nodes
+            .register(&hex::encode([id; 32]), node_type, None, Some(claim))
+            .await
+            .unwrap();
     }
-    async fn get_block_template(
-        &self,
-        _: Vec<String>,
-        _: Option<Vec<u8>>,
-        _: Option<String>,
-    ) -> Result<blvm_protocol::mining::BlockTemplate, blvm_node::module::traits::ModuleError> {
-        Err(blvm_node::module::traits::ModuleError::Other(
-            "not implemented".into(),
-        ))
+
+    /// Deliver an event to the module as the node would, and wait until it is processed.
+    pub async fn send_event(&self, event_type: EventType, payload: EventPayload) {
+        self.module
+            .dispatch(&EventMessage {
+                event_type,
+                payload,
+            })
+            .await;
+        tokio::time::timeout(Duration::from_secs(10), self.module.shutdown.idle())
+            .await
+            .expect("event not processed");
     }
-    async fn submit_block(
-        &self,
-        _: blvm_protocol::Block,
-    ) -> Result<
-        blvm_node::module::traits::SubmitBlockResult,
-        blvm_node::module::traits::ModuleError,
-    > {
-        Err(blvm_node::module::traits::ModuleError::Other(
-            "not implemented".into(),
-        ))
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        std::fs::remove_dir_all(&self.dir).ok();
     }
 }
+
+/// Accept HTTP requests on a local port, answer 200 and forward each request body.
+pub async fn webhook_server() -> (
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let body = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    return;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let len = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= len {
+                        break body.to_string();
+                    }
+                }
+            };
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tx.send(serde_json::from_str(&body).unwrap());
+        }
+    });
+    (url, rx)
+}
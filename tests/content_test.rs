@@ -0,0 +1,199 @@
+//! Proposal content checked against its OP_RETURN commitment
+
+mod common;
+
+use blvm_governance::config::ContentConfig;
+use blvm_governance::content::{self, ContentStatus, ContentVerifier};
+use blvm_governance::node_api::{NodeApiIpc, ProposalDetails};
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const TEXT: &str = "Raise the maintainer signature threshold for tier 3.";
+
+fn text_hash() -> [u8; 32] {
+    Sha256::digest(TEXT.as_bytes()).into()
+}
+
+/// A transaction with a commitment to `hash` among its outputs.
+fn committing_tx(hash: &[u8; 32]) -> blvm_protocol::Transaction {
+    let mut tx = common::spending_tx(blvm_protocol::OutPoint {
+        hash: [9u8; 32],
+        index: 0,
+    });
+    tx.outputs = vec![
+        blvm_protocol::TransactionOutput {
+            value: 1000,
+            script_pubkey: [[0x00, 0x14].as_slice(), &[7u8; 20]].concat(),
+        },
+        blvm_protocol::TransactionOutput {
+            value: 0,
+            script_pubkey: content::commitment_script(hash),
+        },
+    ]
+    .into();
+    tx
+}
+
+/// Have the node answer `get_transaction` with `tx`, confirmed at height 95.
+fn serve_tx(node_api: &common::MockNodeApi, tx: Option<blvm_protocol::Transaction>) {
+    let response = tx.map(|tx| {
+        serde_json::json!({
+            "tx": tx,
+            "block_hash": hex::encode([5u8; 32]),
+            "height": 95,
+            "confirmations": 6,
+        })
+    });
+    node_api.respond(
+        "get_transaction",
+        Ok(serde_json::to_vec(&response).unwrap()),
+    );
+}
+
+fn details(proposal_id: &str, txid: Option<u8>, url: Option<&str>) -> ProposalDetails {
+    ProposalDetails {
+        content_hash: Some(hex::encode(text_hash())),
+        commitment_txid: txid.map(|b| hex::encode([b; 32])),
+        content_url: url.map(str::to_string),
+        ..common::proposal(proposal_id)
+    }
+}
+
+async fn created(store: &ProposalStore, node_api: &common::MockNodeApi, proposal_id: &str) {
+    let message = ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    });
+    store.handle_event(&message, node_api).await.unwrap();
+}
+
+/// Serve `body` over HTTP on a local port, to every request.
+async fn text_server(body: &'static str) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/proposal.md", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_content_is_checked_against_its_commitment() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let dir = std::env::temp_dir().join(format!("blvm_content_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    let verifier = ContentVerifier::new(
+        ContentConfig {
+            verify: true,
+            fetch_text: true,
+            ..Default::default()
+        },
+        ipc.clone(),
+    )
+    .unwrap();
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let store = ProposalStore::new(db)
+        .with_node_api(ipc)
+        .with_webhook(webhook)
+        .with_content_verifier(Arc::new(verifier));
+
+    // Committed, and the full text matches
+    let matching = text_server(TEXT).await;
+    node_api.add_proposal(details("1", Some(1), Some(&matching)));
+    serve_tx(&node_api, Some(committing_tx(&text_hash())));
+    created(&store, &node_api, "1").await;
+    let check = store.content_check("1").unwrap().unwrap();
+    assert_eq!(check.status, ContentStatus::Verified);
+    assert_eq!(check.commitment_height, Some(95));
+    assert_eq!(check.text_hash, Some(hex::encode(text_hash())));
+
+    // Committed to another hash
+    node_api.add_proposal(details("2", Some(2), None));
+    serve_tx(&node_api, Some(committing_tx(&[0xee; 32])));
+    created(&store, &node_api, "2").await;
+    let check = store.content_check("2").unwrap().unwrap();
+    assert_eq!(check.status, ContentStatus::Mismatch);
+    assert_eq!(check.committed_hash, Some("ee".repeat(32)));
+
+    // The commitment matches, but the published text was changed
+    let changed = text_server("Lower the threshold instead.").await;
+    node_api.add_proposal(details("3", Some(3), Some(&changed)));
+    serve_tx(&node_api, Some(committing_tx(&text_hash())));
+    created(&store, &node_api, "3").await;
+    assert_eq!(
+        store.content_check("3").unwrap().unwrap().status,
+        ContentStatus::Mismatch
+    );
+
+    // Missing commitments are unverified, not failures
+    node_api.add_proposal(details("4", None, None));
+    created(&store, &node_api, "4").await;
+    node_api.add_proposal(details("5", Some(5), None));
+    serve_tx(&node_api, None);
+    created(&store, &node_api, "5").await;
+    node_api.add_proposal(details("6", Some(6), None));
+    serve_tx(
+        &node_api,
+        Some(common::spending_tx(blvm_protocol::OutPoint {
+            hash: [8u8; 32],
+            index: 0,
+        })),
+    );
+    created(&store, &node_api, "6").await;
+    for id in ["4", "5", "6"] {
+        let check = store.content_check(id).unwrap().unwrap();
+        assert_eq!(check.status, ContentStatus::Unverified, "{}", id);
+        assert_eq!(check.verified(), None);
+    }
+
+    let mut mismatched = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        if payload["event_type"] == content::MISMATCH_EVENT {
+            assert_eq!(payload["data"]["check"]["status"], "mismatch");
+            mismatched.push(payload["data"]["proposal_id"].as_str().unwrap().to_string());
+        }
+    }
+    mismatched.sort();
+    assert_eq!(mismatched, vec!["2", "3"]);
+    drop(store);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,200 @@
+//! Crash reports, and the panic hook in a module process
+//!
+//! The processes are this test binary run again, with `CRASH_ENV` set, to run only `crasher`.
+#![cfg(unix)]
+
+mod common;
+
+use blvm_governance::checkpoint::Checkpointer;
+use blvm_governance::crash::{self, CrashReport, CrashReporter, CRASH_EVENT, CRASH_EXIT_CODE};
+use blvm_governance::error::GovernanceError;
+use blvm_governance::pipeline::{EventHandler, Pipeline};
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::{EventType, NodeAPI};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Data directory of the child process.
+const CRASH_ENV: &str = "BLVM_TEST_CRASH_DIR";
+/// Webhook URL of the child process.
+const WEBHOOK_ENV: &str = "BLVM_TEST_CRASH_WEBHOOK";
+/// Set to run the child's handler with panics isolated.
+const ISOLATE_ENV: &str = "BLVM_TEST_CRASH_ISOLATE";
+
+struct PanickingHandler;
+
+#[async_trait::async_trait]
+impl EventHandler for PanickingHandler {
+    fn name(&self) -> &'static str {
+        "panicking"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(&self, _: &ModuleMessage, _: &dyn NodeAPI) -> Result<(), GovernanceError> {
+        panic!("simulated handler panic");
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blvm_crash_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn crash_files(dir: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => entries.map(|e| e.unwrap().path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// In a child process: install the panic hook reporting to `CRASH_ENV`/crashes and to the
+/// webhook at `WEBHOOK_ENV`, then pass a block to a handler that panics. Prints `survived`
+/// if the process is still running afterwards. Does nothing otherwise.
+#[tokio::test(flavor = "multi_thread")]
+async fn crasher() {
+    let Ok(dir) = std::env::var(CRASH_ENV) else {
+        return;
+    };
+    let dir = PathBuf::from(dir);
+    let config = GovernanceConfig {
+        webhook_url: std::env::var(WEBHOOK_ENV).ok(),
+        ..Default::default()
+    };
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let reporter = Arc::new(CrashReporter::new(
+        dir.join("crashes"),
+        Duration::from_secs(2),
+    ));
+    reporter.connected(
+        node_api.clone(),
+        Arc::new(GovernanceWebhookClient::new(&config).await.unwrap()),
+    );
+    crash::install(reporter);
+
+    let pipeline = Pipeline::new(Arc::new(Checkpointer::open(&dir).unwrap()))
+        .with_handler(Arc::new(PanickingHandler))
+        .with_panic_isolation(std::env::var(ISOLATE_ENV).is_ok());
+    let block = EventMessage {
+        event_type: EventType::NewBlock,
+        payload: EventPayload::NewBlock {
+            block_hash: [1; 32],
+            height: 1,
+        },
+    };
+    pipeline.process(&block, node_api.as_ref()).await;
+    println!("survived");
+}
+
+async fn run_crasher(dir: &Path, webhook: &str, isolate: bool) -> std::process::Output {
+    let mut command = tokio::process::Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["crasher", "--exact", "--nocapture", "--test-threads=1"])
+        .env(CRASH_ENV, dir)
+        .env(WEBHOOK_ENV, webhook);
+    if isolate {
+        command.env(ISOLATE_ENV, "1");
+    }
+    command.output().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_panic_is_reported_before_exiting() {
+    let dir = temp_dir("hook");
+    let (url, mut received) = common::webhook_server().await;
+
+    let output = run_crasher(&dir, &url, false).await;
+    assert_eq!(output.status.code(), Some(CRASH_EXIT_CODE));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("survived"));
+
+    let files = crash_files(&dir.join("crashes"));
+    assert_eq!(files.len(), 1);
+    let report: CrashReport = serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+    assert_eq!(report.message, "simulated handler panic");
+    assert_eq!(report.handler.as_deref(), Some("panicking"));
+    assert!(report.location.unwrap().contains("crash_test.rs"));
+
+    let payload = received.recv().await.unwrap();
+    assert_eq!(payload["event_type"], CRASH_EVENT);
+    assert_eq!(payload["data"]["message"], "simulated handler panic");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_isolated_panic_keeps_process_running() {
+    let dir = temp_dir("isolated");
+    let (url, mut received) = common::webhook_server().await;
+
+    let output = run_crasher(&dir, &url, true).await;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("survived"));
+    assert!(crash_files(&dir.join("crashes")).is_empty());
+    assert!(received.try_recv().is_err());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_report_reaches_node_and_webhook() {
+    let dir = temp_dir("report");
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let reporter = Arc::new(CrashReporter::new(dir.clone(), Duration::from_secs(2)));
+
+    // Without a connection only the file is written
+    let report = CrashReport::new("first".to_string(), None);
+    let written = {
+        let reporter = Arc::clone(&reporter);
+        let report = report.clone();
+        tokio::task::spawn_blocking(move || reporter.report(&report))
+            .await
+            .unwrap()
+    };
+    assert_eq!(
+        written.as_deref().and_then(Path::parent),
+        Some(dir.as_path())
+    );
+    assert!(node_api.module_calls().is_empty());
+
+    reporter.connected(
+        node_api.clone(),
+        Arc::new(GovernanceWebhookClient::new(&config).await.unwrap()),
+    );
+    let report = CrashReport::new("second".to_string(), Some("src/lib.rs:1:1".to_string()));
+    {
+        let reporter = Arc::clone(&reporter);
+        let report = report.clone();
+        tokio::task::spawn_blocking(move || reporter.report(&report))
+            .await
+            .unwrap();
+    }
+    let calls = node_api.module_calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "module_status");
+    let status: serde_json::Value = serde_json::from_slice(&calls[0].1).unwrap();
+    assert_eq!(status["reason"], "fatal");
+    assert_eq!(status["code"], CRASH_EXIT_CODE);
+    assert_eq!(status["error"], report.summary());
+
+    let payload = received.recv().await.unwrap();
+    assert_eq!(payload["event_type"], CRASH_EVENT);
+    assert_eq!(payload["data"]["location"], "src/lib.rs:1:1");
+
+    // Gone with the connection
+    reporter.disconnected();
+    tokio::task::spawn_blocking(move || reporter.report(&report))
+        .await
+        .unwrap();
+    assert_eq!(node_api.module_calls().len(), 1);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,273 @@
+//! The dashboard snapshot of the module, as status pages read it
+
+mod common;
+
+use blvm_governance::config::{DeadlineConfig, EpochSummaryConfig};
+use blvm_governance::dashboard::{
+    Capped, ChangeKind, Dashboard, DeliveryHealth, NodeState, OpenProposal, RecentChange,
+    RegistryTotals, VetoStanding,
+};
+use blvm_governance::economic_nodes::metrics::CategoryMetrics;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::proposals::ProposalStatus;
+use blvm_governance::query::QueryApi;
+use blvm_governance::tally::Tally;
+use blvm_governance::webhook::DeliveryCounts;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const TOKEN: &str = "t0k";
+
+/// `GET /dashboard` with `query`: the status and the body.
+async fn get(queries: &QueryApi, query: &str) -> (u16, serde_json::Value) {
+    let bearer = format!("Bearer {}", TOKEN);
+    let response = queries
+        .handle("/dashboard", query, Some(&bearer), None)
+        .await;
+    assert_eq!(response.etag, None);
+    let body = serde_json::from_str(&response.body).unwrap();
+    (response.status, body)
+}
+
+async fn created(node: &MockNode, proposal_id: &str) {
+    node.node_api.add_proposal(common::proposal(proposal_id));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_dashboard_of_the_module() {
+    let config = GovernanceConfig {
+        epoch_summary: EpochSummaryConfig {
+            length_blocks: 10,
+            confirmations: 0,
+        },
+        deadlines: DeadlineConfig {
+            windows: BTreeMap::from([("standard".to_string(), 100)]),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("dashboard", config).await;
+    let queries = QueryApi::new(
+        Some(TOKEN.to_string()),
+        Arc::clone(&node.module.proposal_store),
+        Arc::clone(&node.module.economic_nodes),
+        Arc::clone(&node.epoch_summaries),
+    )
+    .with_webhook(Arc::clone(&node.module.webhook_client))
+    .with_heartbeat(Arc::new(Heartbeat::new()))
+    .with_tip(Arc::clone(&node.module.tip));
+
+    // Epoch 10 is summarized once its last block, 109, connects
+    let hash = [109u8; 32];
+    node.node_api
+        .add_block(109, hash, common::block([108u8; 32], Vec::new()));
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash,
+            height: 109,
+        },
+    )
+    .await;
+    for proposal_id in ["1", "2", "3"] {
+        created(&node, proposal_id).await;
+    }
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "2".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "bob".to_string(),
+            vote: "yes".to_string(),
+        },
+    )
+    .await;
+    node.register_verified(1, "miner", 40).await;
+    let miner = hex::encode([1u8; 32]);
+    node.module
+        .economic_nodes
+        .veto("3", &miner, "unsafe", None)
+        .await
+        .unwrap();
+
+    let (status, body) = get(&queries, "").await;
+    assert_eq!(status, 200);
+    let dashboard: Dashboard = serde_json::from_value(body).unwrap();
+    assert_eq!(dashboard.version, 1);
+    assert!(dashboard.generated_at > 0);
+    assert_eq!(
+        (dashboard.node.connected, dashboard.node.tip_height),
+        (true, Some(109))
+    );
+    assert!(dashboard.node.heartbeat.is_some());
+
+    let open = &dashboard.open_proposals;
+    assert_eq!(open.total, 2);
+    let first = &open.items[0];
+    assert_eq!(
+        (
+            first.proposal_id.as_str(),
+            first.deadline_height,
+            first.blocks_left
+        ),
+        ("1", Some(200), Some(91))
+    );
+    assert_eq!(first.tally.yes, 1);
+    assert_eq!(
+        dashboard
+            .recent
+            .items
+            .iter()
+            .map(|c| (c.proposal_id.as_str(), c.change, c.height))
+            .collect::<Vec<_>>(),
+        vec![("2", ChangeKind::Merged, 109)]
+    );
+    assert_eq!(dashboard.vetoes.total, 1);
+    assert_eq!(dashboard.vetoes.items[0].proposal_id, "3");
+    assert!(dashboard.vetoes.items[0].crossed);
+    assert_eq!(dashboard.registry.registered, 1);
+    assert_eq!(dashboard.registry.by_category["miner"].registered, 1);
+    let delivery = dashboard.delivery.unwrap();
+    assert_eq!(delivery.consecutive_failures, 0);
+    // The harness runs neither the change forwarder nor the veto reporter, so the
+    // registration's notification and the veto report stay pending
+    assert_eq!(delivery.pending_intents, 2);
+    assert_eq!(dashboard.last_epoch.unwrap().end_height, 109);
+
+    // Lists are cut to the limit, with their full length alongside
+    let (status, body) = get(&queries, "limit=1").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["open_proposals"]["total"], 2);
+    assert_eq!(body["open_proposals"]["items"].as_array().unwrap().len(), 1);
+    let (status, _) = get(&queries, "limit=10000").await;
+    assert_eq!(status, 200);
+    let (status, _) = get(&queries, "limit=all").await;
+    assert_eq!(status, 400);
+    let response = queries.handle("/dashboard", "", None, None).await;
+    assert_eq!(response.status, 401);
+}
+
+#[test]
+fn test_serialization_snapshot() {
+    let dashboard = Dashboard {
+        version: 1,
+        generated_at: 1_760_000_000,
+        revision: "7-p3-r2".to_string(),
+        node: NodeState {
+            connected: true,
+            tip_height: Some(150),
+            heartbeat: None,
+        },
+        open_proposals: Capped {
+            total: 1,
+            items: vec![OpenProposal {
+                proposal_id: "1".to_string(),
+                tier: "standard".to_string(),
+                status: ProposalStatus::Voting,
+                created_height: Some(100),
+                deadline_height: Some(200),
+                blocks_left: Some(50),
+                tally: Tally {
+                    yes: 2,
+                    no: 1,
+                    abstain: 0,
+                },
+                delegated: None,
+            }],
+        },
+        recent: Capped {
+            total: 1,
+            items: vec![RecentChange {
+                proposal_id: "2".to_string(),
+                tier: "standard".to_string(),
+                change: ChangeKind::Merged,
+                height: 140,
+            }],
+        },
+        vetoes: Capped {
+            total: 1,
+            items: vec![VetoStanding {
+                proposal_id: "3".to_string(),
+                veto_percent: 20.0,
+                threshold_percent: 30.0,
+                vetoing_weight: 20.0,
+                total_weight: 100.0,
+                crossed: false,
+            }],
+        },
+        registry: RegistryTotals {
+            registered: 2,
+            active: 2,
+            expired: 0,
+            deactivated: 0,
+            archived: 0,
+            total_weight: 100.0,
+            by_category: BTreeMap::from([(
+                "miner".to_string(),
+                CategoryMetrics {
+                    registered: 2,
+                    active: 2,
+                    expired: 0,
+                    weight: 100.0,
+                },
+            )]),
+        },
+        delivery: Some(DeliveryHealth {
+            deliveries: DeliveryCounts {
+                delivered: 5,
+                failed: 1,
+                dry_run: 0,
+            },
+            consecutive_failures: 0,
+            deferred_blocks: 0,
+            pending_intents: 1,
+        }),
+        last_epoch: None,
+    };
+    let json = serde_json::to_string(&dashboard).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"version":1,"generated_at":1760000000,"revision":"7-p3-r2","#,
+            r#""node":{"connected":true,"tip_height":150,"heartbeat":null},"#,
+            r#""open_proposals":{"total":1,"items":[{"proposal_id":"1","tier":"standard","#,
+            r#""status":"Voting","created_height":100,"deadline_height":200,"blocks_left":50,"#,
+            r#""tally":{"yes":2,"no":1,"abstain":0},"delegated":null}]},"#,
+            r#""recent":{"total":1,"items":[{"proposal_id":"2","tier":"standard","#,
+            r#""change":"merged","height":140}]},"#,
+            r#""vetoes":{"total":1,"items":[{"proposal_id":"3","veto_percent":20.0,"#,
+            r#""threshold_percent":30.0,"vetoing_weight":20.0,"total_weight":100.0,"#,
+            r#""crossed":false}]},"#,
+            r#""registry":{"registered":2,"active":2,"expired":0,"deactivated":0,"archived":0,"#,
+            r#""total_weight":100.0,"by_category":{"miner":{"registered":2,"active":2,"#,
+            r#""expired":0,"weight":100.0}}},"#,
+            r#""delivery":{"deliveries":{"delivered":5,"failed":1,"dry_run":0},"#,
+            r#""consecutive_failures":0,"deferred_blocks":0,"pending_intents":1},"#,
+            r#""last_epoch":null}"#,
+        )
+    );
+    assert_eq!(serde_json::from_str::<Dashboard>(&json).unwrap(), dashboard);
+    assert_eq!(blvm_governance::dashboard::DASHBOARD_VERSION, 1);
+}
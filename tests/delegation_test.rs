@@ -0,0 +1,147 @@
+//! Vote delegations from the module API and on-chain records, counted in proposal tallies
+
+mod common;
+
+use blvm_governance::config::TierThresholds;
+use blvm_governance::delegation::{self, DelegationSource, OnChainDelegation};
+use blvm_governance::tally::{Milestone, Tally};
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use blvm_protocol::Hash;
+use common::MockNode;
+
+fn hash(height: u64) -> Hash {
+    let mut hash = [3u8; 32];
+    hash[..8].copy_from_slice(&height.to_le_bytes());
+    hash
+}
+
+async fn voted(node: &MockNode, voter: &str, vote: &str) {
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        },
+    )
+    .await;
+}
+
+/// Mine a block at `height` holding an on-chain delegation from `delegator` to `delegate`.
+async fn delegated_on_chain(node: &MockNode, height: u64, delegator: &str, delegate: &str) {
+    let script = delegation::delegation_script(&OnChainDelegation {
+        delegator: delegator.to_string(),
+        delegate: Some(delegate.to_string()),
+        expiry_height: None,
+    })
+    .unwrap();
+    let mut tx = common::spending_tx(blvm_protocol::OutPoint {
+        hash: [9u8; 32],
+        index: 0,
+    });
+    tx.outputs = vec![blvm_protocol::TransactionOutput {
+        value: 0,
+        script_pubkey: script,
+    }]
+    .into();
+    node.node_api.add_block(
+        height,
+        hash(height),
+        common::block(hash(height - 1), vec![tx]),
+    );
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash(height),
+            height,
+        },
+    )
+    .await;
+}
+
+fn tally(yes: u64, no: u64) -> Tally {
+    Tally {
+        yes,
+        no,
+        abstain: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_delegated_votes_are_counted_until_revoked() {
+    let mut config = GovernanceConfig::default();
+    config.delegation.enabled = true;
+    config.delegation.on_chain = true;
+    config.tally.tiers.insert(
+        "standard".to_string(),
+        TierThresholds {
+            quorum: 3,
+            approval_percent: 66.0,
+        },
+    );
+    let node = MockNode::start("delegation", config).await;
+    let delegations = node.delegations.as_ref().unwrap();
+    let store = &node.module.proposal_store;
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    voted(&node, "alice", "yes").await;
+
+    // Bob and carol delegate to alice, which reaches quorum on the delegated tally only
+    delegations.announce("bob", Some("alice"), None).unwrap();
+    delegations.announce("carol", Some("alice"), None).unwrap();
+    store.update_tallies(node.node_api.as_ref()).await.unwrap();
+    let tallies = store.tallies("1").unwrap().unwrap();
+    assert_eq!((tallies.raw, tallies.delegated), (tally(1, 0), tally(3, 0)));
+    let proposal = store.proposal("1").unwrap().unwrap();
+    assert_eq!(proposal.milestones[0].milestone, Milestone::QuorumReached);
+
+    // Dave delegates to bob on chain, and so to alice through him
+    delegated_on_chain(&node, 101, "dave", "bob").await;
+    let tallies = store.tallies("1").unwrap().unwrap();
+    assert_eq!(
+        (tallies.delegated, tallies.height),
+        (tally(4, 0), Some(101))
+    );
+
+    // Bob revokes mid-vote: his weight and dave's no longer go to alice
+    let revoked = delegations.announce("bob", None, None).unwrap();
+    assert_eq!(revoked.effective_height, 101);
+    store.update_tallies(node.node_api.as_ref()).await.unwrap();
+    assert_eq!(store.tallies("1").unwrap().unwrap().delegated, tally(2, 0));
+
+    // Bob's own vote carries dave's
+    voted(&node, "bob", "no").await;
+    let tallies = store.tallies("1").unwrap().unwrap();
+    assert_eq!((tallies.raw, tallies.delegated), (tally(1, 1), tally(2, 2)));
+
+    let bob = delegations.identity("bob", None);
+    assert!(bob.delegation.is_none());
+    assert_eq!(bob.delegators, vec!["dave".to_string()]);
+    assert_eq!(bob.history.len(), 2);
+    let carol = delegations.identity("carol", None);
+    assert_eq!(carol.resolution.chain, vec!["carol", "alice"]);
+    assert!(!carol.resolution.cycle);
+
+    let records = delegations.records();
+    assert_eq!(records.len(), 4);
+    let dave = records.iter().find(|r| r.delegator == "dave").unwrap();
+    assert_eq!(
+        dave.source,
+        DelegationSource::OnChain {
+            block_hash: hex::encode(hash(101))
+        }
+    );
+    let csv = delegation::records_csv(records.iter());
+    assert_eq!(csv.lines().count(), 5);
+}
@@ -0,0 +1,186 @@
+//! Webhook notifications collected into periodic digests
+
+mod common;
+
+use blvm_governance::config::{DigestConfig, DigestMode, GovernanceConfig, RegistryConfig};
+use blvm_governance::digest::Digest;
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// An hour boundary.
+const T0: u64 = 1_760_000_400;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("blvm_digest_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn hourly() -> DigestConfig {
+    DigestConfig {
+        mode: DigestMode::Digest,
+        every_secs: 3_600,
+        ..Default::default()
+    }
+}
+
+async fn client(url: &str, digest: Arc<Digest>) -> GovernanceWebhookClient {
+    let config = GovernanceConfig {
+        webhook_url: Some(url.to_string()),
+        ..Default::default()
+    };
+    GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_digest(digest)
+}
+
+fn open(dir: &Path, config: &DigestConfig) -> Arc<Digest> {
+    Arc::new(Digest::open(dir, config).unwrap())
+}
+
+async fn next(received: &mut UnboundedReceiver<serde_json::Value>) -> Option<serde_json::Value> {
+    tokio::time::timeout(Duration::from_millis(500), received.recv())
+        .await
+        .ok()
+        .flatten()
+}
+
+#[tokio::test]
+async fn test_digest_of_a_period() {
+    let dir = temp_dir("period");
+    let (url, mut received) = common::webhook_server().await;
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let digest = open(&dir, &hourly());
+    let webhook = client(&url, Arc::clone(&digest)).await;
+    // The first check starts the period
+    webhook.send_digest(T0 + 10, node_api.as_ref()).await;
+
+    for (event_type, payload) in [
+        (
+            EventType::GovernanceProposalCreated,
+            EventPayload::GovernanceProposalCreated {
+                proposal_id: "4".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            },
+        ),
+        (
+            EventType::GovernanceProposalVoted,
+            EventPayload::GovernanceProposalVoted {
+                proposal_id: "4".to_string(),
+                voter: "bob".to_string(),
+                vote: "yes".to_string(),
+            },
+        ),
+    ] {
+        let message = ModuleMessage::Event(EventMessage {
+            event_type,
+            payload,
+        });
+        webhook
+            .handle_event(&message, node_api.as_ref())
+            .await
+            .unwrap();
+    }
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap()
+        .with_digest(Arc::clone(&digest));
+    let node = hex::encode([1u8; 32]);
+    registry
+        .register(&node, "miner", Some(40.0), None)
+        .await
+        .unwrap();
+    registry.veto("4", &node, "unsafe", None).await.unwrap();
+    assert_eq!(next(&mut received).await, None);
+    assert_eq!(digest.pending(), 3);
+
+    // Not over yet
+    webhook.send_digest(T0 + 3_599, node_api.as_ref()).await;
+    assert_eq!(next(&mut received).await, None);
+
+    // Kept across a restart, and sent once
+    drop((webhook, registry, digest));
+    let digest = open(&dir, &hourly());
+    let webhook = client(&url, Arc::clone(&digest)).await;
+    webhook.send_digest(T0 + 3_605, node_api.as_ref()).await;
+    let payload = next(&mut received).await.unwrap();
+    assert_eq!(payload["event_type"], "governance_digest");
+    let data = &payload["data"];
+    assert_eq!(data["period_start"], T0 + 10);
+    assert_eq!(data["period_end"], T0 + 3_605);
+    assert_eq!(data["total"], 3);
+    assert_eq!(
+        data["counts"],
+        serde_json::json!({ "proposals": 1, "tallies": 1, "vetoes": 1 })
+    );
+    assert_eq!(data["sections"]["tallies"][0]["data"]["voter"], "bob");
+    let text = data["text"].as_str().unwrap();
+    assert!(text.contains(": 3 notifications"), "{}", text);
+    assert!(
+        text.contains("Proposals (1)\n- Proposal 4 created"),
+        "{}",
+        text
+    );
+    assert!(text.contains("- bob voted yes on proposal 4"), "{}", text);
+    assert!(
+        text.contains("- Economic node 0101010101010101 vetoed proposal 4: unsafe"),
+        "{}",
+        text
+    );
+    assert_eq!(digest.pending(), 0);
+    webhook.send_digest(T0 + 3_606, node_api.as_ref()).await;
+    assert_eq!(next(&mut received).await, None);
+
+    // A quiet hour sends nothing, unless asked to
+    webhook.send_digest(T0 + 7_200, node_api.as_ref()).await;
+    assert_eq!(next(&mut received).await, None);
+    drop((webhook, digest));
+    let quiet = DigestConfig {
+        all_quiet: true,
+        ..hourly()
+    };
+    let webhook = client(&url, open(&dir, &quiet)).await;
+    webhook.send_digest(T0 + 10_800, node_api.as_ref()).await;
+    let payload = next(&mut received).await.unwrap();
+    assert_eq!(payload["data"]["total"], 0);
+    assert!(payload["data"]["text"]
+        .as_str()
+        .unwrap()
+        .ends_with("all quiet, no governance activity."));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_digest_cut_short_is_sent_again() {
+    let dir = temp_dir("crash");
+    let (url, mut received) = common::webhook_server().await;
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let digest = open(&dir, &hourly());
+    digest.take_due(T0).unwrap();
+    let data = serde_json::json!({ "proposal_id": "4" });
+    digest.record("proposal_merged", &data, T0 + 1).unwrap();
+    // Taken for sending, then the module stops before it is sent
+    let taken = digest.take_due(T0 + 3_600).unwrap().unwrap();
+    assert_eq!(taken["total"], 1);
+    drop(digest);
+
+    let digest = open(&dir, &hourly());
+    let webhook = client(&url, Arc::clone(&digest)).await;
+    webhook.send_digest(T0 + 3_700, node_api.as_ref()).await;
+    let payload = next(&mut received).await.unwrap();
+    assert_eq!(payload["data"], taken);
+    webhook.send_digest(T0 + 3_800, node_api.as_ref()).await;
+    assert_eq!(next(&mut received).await, None);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -2,24 +2,16 @@
 
 mod common;
 
+use blvm_governance::config::RegistryConfig;
 use blvm_governance::economic_nodes::EconomicNodeRegistry;
 use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
-use blvm_node::module::traits::{EventType, ModuleContext};
-use std::collections::HashMap;
+use blvm_node::module::traits::EventType;
 use std::sync::Arc;
 
 #[tokio::test]
 async fn test_economic_node_registration() {
-    let temp = std::env::temp_dir();
-    let ctx = ModuleContext {
-        module_id: "test".to_string(),
-        config: HashMap::new(),
-        data_dir: temp.to_string_lossy().to_string(),
-        socket_path: temp.join("blvm_test.sock").to_string_lossy().into_owned(),
-    };
-
-    let node_api = Arc::new(common::MockNodeAPI { block_height: 100 });
-    let registry = EconomicNodeRegistry::new(&ctx, node_api.clone())
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
         .await
         .unwrap();
 
@@ -46,3 +38,752 @@ async fn test_economic_node_registration() {
     assert_eq!(node.economic_activity_percentage, 0.0);
     assert_eq!(node.registered_at, 100);
 }
+
+#[tokio::test]
+async fn test_reregistration_tracks_weight_changes() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    let node_id = [2u8; 32];
+    for hashpower in [0.5, 0.5, 0.7] {
+        let event = ModuleMessage::Event(EventMessage {
+            event_type: EventType::EconomicNodeRegistered,
+            payload: EventPayload::EconomicNodeRegistered {
+                node_id: hex::encode(node_id),
+                node_type: "miner".to_string(),
+                hashpower_percent: Some(hashpower),
+            },
+        });
+        registry
+            .handle_event(&event, node_api.as_ref())
+            .await
+            .unwrap();
+    }
+
+    let nodes = registry.get_nodes_for_test().await;
+    let node = nodes.get(&node_id).unwrap();
+    assert_eq!(node.registered_at, 100);
+    assert_eq!(node.weight_changes, 1);
+    assert_eq!(node.hashpower_percentage, 0.7);
+
+    let reputation = registry.reputation(&node_id).await.unwrap();
+    assert_eq!(reputation.stability, 0.5);
+}
+
+/// Register node `[id; 32]` as submitted by the module `origin`.
+async fn register_from(registry: &EconomicNodeRegistry, origin: &str, id: u8) -> bool {
+    registry
+        .register_from(
+            Some(origin),
+            &hex::encode([id; 32]),
+            "miner",
+            Some(1.0),
+            None,
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_registrations_are_limited_by_origin() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let mut config = RegistryConfig::default();
+    config.rate_limit.max_per_source_per_window = 2;
+    config.max_nodes = 2;
+    let registry = EconomicNodeRegistry::new(config, node_api).await.unwrap();
+
+    assert!(register_from(&registry, "spammer", 1).await);
+    assert!(register_from(&registry, "spammer", 2).await);
+    // New node ids do not get around the limit of the module submitting them
+    assert!(!register_from(&registry, "spammer", 3).await);
+    // From another module, the full registry turns it away, and it is not counted accepted
+    assert!(!register_from(&registry, "other", 3).await);
+
+    let counters = registry.registration_counters();
+    assert_eq!(
+        (
+            counters.accepted,
+            counters.rejected_rate_limited,
+            counters.rejected_capacity
+        ),
+        (2, 1, 1)
+    );
+}
+
+#[tokio::test]
+async fn test_minimum_stake_applies_to_verified_reserve() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let mut config = RegistryConfig::default();
+    config.rate_limit.min_verified_sats = 100;
+    let registry = EconomicNodeRegistry::new(config, node_api.clone())
+        .await
+        .unwrap();
+
+    // Claiming hashpower proves nothing, nor does a reserve short of the minimum
+    assert!(!registry
+        .register(&hex::encode([1u8; 32]), "miner", Some(50.0), None)
+        .await
+        .unwrap());
+    let short = node_api.add_reserve(&[2u8; 32], 60);
+    assert!(!registry
+        .register(&hex::encode([2u8; 32]), "exchange", None, Some(short))
+        .await
+        .unwrap());
+
+    let enough = node_api.add_reserve(&[3u8; 32], 150);
+    assert!(registry
+        .register(&hex::encode([3u8; 32]), "exchange", None, Some(enough))
+        .await
+        .unwrap());
+    // Re-registering without the claim keeps the reserve already verified
+    assert!(registry
+        .register(&hex::encode([3u8; 32]), "exchange", None, None)
+        .await
+        .unwrap());
+
+    let counters = registry.registration_counters();
+    assert_eq!((counters.accepted, counters.rejected_min_stake), (2, 2));
+}
+
+#[tokio::test]
+async fn test_reconcile_preserves_local_state() {
+    use blvm_governance::node_api::NodeEconomicNode;
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    let kept = hex::encode([1u8; 32]);
+    let dropped = hex::encode([2u8; 32]);
+    for node_id in [&kept, &dropped] {
+        registry
+            .register(node_id, "exchange", Some(1.0), None)
+            .await
+            .unwrap();
+    }
+    let veto = ModuleMessage::Event(EventMessage {
+        event_type: EventType::EconomicNodeVeto,
+        payload: EventPayload::EconomicNodeVeto {
+            proposal_id: "p1".to_string(),
+            node_id: kept.clone(),
+            reason: "test".to_string(),
+        },
+    });
+    registry.handle_event(&veto, node_api.as_ref()).await.unwrap();
+
+    let added = hex::encode([3u8; 32]);
+    let remote = vec![
+        NodeEconomicNode {
+            node_id: kept.clone(),
+            node_type: "exchange".to_string(),
+            hashpower_percent: Some(1.0),
+        },
+        NodeEconomicNode {
+            node_id: added,
+            node_type: "miner".to_string(),
+            hashpower_percent: Some(2.0),
+        },
+    ];
+    let summary = registry.reconcile(&remote).await.unwrap();
+    assert_eq!((summary.added, summary.removed, summary.updated), (1, 1, 0));
+
+    let nodes = registry.get_nodes_for_test().await;
+    assert_eq!(nodes.len(), 2);
+    assert!(!nodes.contains_key(&[2u8; 32]));
+    assert_eq!(nodes[&[1u8; 32]].veto_history.len(), 1);
+}
+
+#[tokio::test]
+async fn test_reconcile_without_a_node_set_keeps_local_state() {
+    use blvm_governance::error::GovernanceError;
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    registry
+        .register(&hex::encode([1u8; 32]), "exchange", Some(1.0), None)
+        .await
+        .unwrap();
+
+    // What the node's router answers when no module serves the set
+    node_api.respond(
+        "list_economic_nodes",
+        Err("Method 'list_economic_nodes' not found in any module".to_string()),
+    );
+    assert!(matches!(
+        registry.reconcile_now().await,
+        Err(GovernanceError::Unsupported { .. })
+    ));
+    assert_eq!(registry.get_nodes_for_test().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_metrics_after_event_sequence() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    let miner = hex::encode([1u8; 32]);
+    let exchange = hex::encode([2u8; 32]);
+    let claim = node_api.add_reserve(&[1u8; 32], 20);
+    registry
+        .register(&miner, "miner", Some(20.0), Some(claim))
+        .await
+        .unwrap();
+    let claim = node_api.add_reserve(&[2u8; 32], 5);
+    registry
+        .register(&exchange, "exchange", Some(5.0), Some(claim))
+        .await
+        .unwrap();
+    // Rejected by validation: not counted as accepted
+    assert!(registry.register("bad", "miner", None, None).await.is_err());
+
+    for (proposal_id, node_id) in [("p1", &miner), ("p1", &exchange), ("p2", &miner)] {
+        let veto = ModuleMessage::Event(EventMessage {
+            event_type: EventType::EconomicNodeVeto,
+            payload: EventPayload::EconomicNodeVeto {
+                proposal_id: proposal_id.to_string(),
+                node_id: node_id.clone(),
+                reason: "test".to_string(),
+            },
+        });
+        registry.handle_event(&veto, node_api.as_ref()).await.unwrap();
+    }
+    assert!(registry.revoke_veto("p2", &miner, None).await.unwrap());
+    registry
+        .reconcile(&[blvm_governance::node_api::NodeEconomicNode {
+            node_id: miner.clone(),
+            node_type: "miner".to_string(),
+            hashpower_percent: Some(20.0),
+        }])
+        .await
+        .unwrap();
+
+    let metrics = registry.metrics().await;
+    assert_eq!(metrics.registrations.accepted, 2);
+    assert_eq!(metrics.vetoes, 3);
+    assert_eq!(metrics.revocations, 1);
+    assert_eq!(metrics.prunes, 1);
+    assert_eq!((metrics.registered, metrics.active, metrics.expired), (1, 1, 0));
+    assert_eq!(metrics.by_category["miner"].registered, 1);
+    assert!(!metrics.by_category.contains_key("exchange"));
+    assert_eq!(metrics.veto_weight_by_proposal.get("p1"), Some(&20.0));
+    assert!(!metrics.veto_weight_by_proposal.contains_key("p2"));
+}
+
+#[tokio::test]
+async fn test_proposal_tallied_against_epoch_snapshot() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let mut config = RegistryConfig::default();
+    config.epoch.length_blocks = 100;
+    config.veto.use_epoch_snapshot = true;
+    let registry = EconomicNodeRegistry::new(config, node_api.clone())
+        .await
+        .unwrap();
+
+    let a = hex::encode([1u8; 32]);
+    let b = hex::encode([2u8; 32]);
+    let claim = node_api.add_reserve(&[1u8; 32], 40);
+    registry.register(&a, "miner", None, Some(claim)).await.unwrap();
+    let claim = node_api.add_reserve(&[2u8; 32], 60);
+    registry.register(&b, "miner", None, Some(claim)).await.unwrap();
+
+    let events = [
+        (
+            EventType::NewBlock,
+            EventPayload::NewBlock {
+                block_hash: [0u8; 32],
+                height: 200,
+            },
+        ),
+        (
+            EventType::GovernanceProposalCreated,
+            EventPayload::GovernanceProposalCreated {
+                proposal_id: "p1".to_string(),
+                repository: "repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            },
+        ),
+    ];
+    for (event_type, payload) in events {
+        let event = ModuleMessage::Event(EventMessage {
+            event_type,
+            payload,
+        });
+        registry.handle_event(&event, node_api.as_ref()).await.unwrap();
+    }
+    assert_eq!(registry.proposal_snapshot_id("p1"), Some(2));
+
+    // Weight change after the epoch boundary does not affect p1
+    let claim = node_api.add_reserve(&[1u8; 32], 5);
+    registry.register(&a, "miner", None, Some(claim)).await.unwrap();
+    registry.veto("p1", &a, "test", None).await.unwrap();
+
+    let tally = registry.veto_tally("p1").await;
+    assert_eq!(tally.total_weight, 100.0);
+    assert_eq!(tally.vetoing_weight, 40.0);
+    assert!(tally.crossed());
+}
+
+#[tokio::test]
+async fn test_blocklist_deactivates_registered_node() {
+    let temp = std::env::temp_dir();
+    let blocklist = temp.join(format!("blvm_blocklist_{}.txt", std::process::id()));
+    std::fs::write(&blocklist, "# empty\n").unwrap();
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let mut config = RegistryConfig::default();
+    config.access.blocklist_path = Some(blocklist.clone());
+    let registry = EconomicNodeRegistry::new(config, node_api.clone())
+        .await
+        .unwrap();
+
+    let abusive = hex::encode([1u8; 32]);
+    let honest = hex::encode([2u8; 32]);
+    let claim = node_api.add_reserve(&[1u8; 32], 40);
+    registry.register(&abusive, "miner", None, Some(claim)).await.unwrap();
+    let claim = node_api.add_reserve(&[2u8; 32], 60);
+    registry.register(&honest, "miner", None, Some(claim)).await.unwrap();
+    registry.veto("p1", &abusive, "spam", None).await.unwrap();
+    assert!(registry.veto_tally("p1").await.crossed());
+
+    std::fs::write(&blocklist, format!("{}  # abusive registrations\n", abusive)).unwrap();
+    registry.reload_access_lists().await.unwrap();
+
+    let tally = registry.veto_tally("p1").await;
+    assert_eq!(tally.total_weight, 60.0);
+    assert_eq!(tally.vetoing_weight, 0.0);
+    assert!(!tally.crossed());
+
+    let nodes = registry.get_nodes_for_test().await;
+    assert!(nodes[&[1u8; 32]].deactivated.is_some());
+    let audit = registry.access_audit();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].action, "deactivated");
+    assert_eq!(audit[0].entry.as_deref(), Some(abusive.as_str()));
+
+    // Further registrations from the blocklisted node are ignored
+    assert!(!registry.register(&abusive, "miner", Some(40.0), None).await.unwrap());
+
+    std::fs::remove_file(&blocklist).ok();
+}
+
+#[tokio::test]
+async fn test_simulate_veto_does_not_mutate() {
+    use blvm_governance::economic_nodes::VetoScenario;
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    for (id, node_type, sats) in [(1u8, "exchange", 10), (2, "exchange", 10), (3, "miner", 80)] {
+        let claim = node_api.add_reserve(&[id; 32], sats);
+        registry
+            .register(&hex::encode([id; 32]), node_type, None, Some(claim))
+            .await
+            .unwrap();
+    }
+    let a = hex::encode([1u8; 32]);
+    registry.veto("p1", &a, "test", None).await.unwrap();
+    let commitment = registry.commitment().await;
+
+    let scenario = VetoScenario {
+        proposal_id: Some("p1".to_string()),
+        categories: vec!["exchange".to_string()],
+        ..Default::default()
+    };
+    let sim = registry.simulate_veto(&scenario).await.unwrap();
+    assert_eq!(sim.vetoing_weight, 20.0);
+    assert!(!sim.crossed);
+    assert_eq!(sim.additional_weight_needed, 10.0);
+
+    let scenario = VetoScenario {
+        tier: Some("core".to_string()),
+        nodes: vec![[3u8; 32]],
+        ..Default::default()
+    };
+    assert!(registry.simulate_veto(&scenario).await.unwrap().crossed);
+
+    assert_eq!(registry.commitment().await, commitment);
+    assert_eq!(registry.veto_tally("p1").await.vetoing_weight, 10.0);
+    assert!(registry.simulate_veto(&VetoScenario::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_mempool_spend_pending_until_confirmed() {
+    use blvm_governance::economic_nodes::reserve;
+    use blvm_governance::economic_nodes::{ClaimedOutpoint, ReserveClaim, VerificationStatus};
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[3u8; 32]).unwrap();
+    let node_id = [4u8; 32];
+    let outpoint = ClaimedOutpoint {
+        txid: [1u8; 32],
+        vout: 0,
+    };
+    let message = reserve::registration_message(&node_id, &[outpoint]);
+    let digest = Message::from_digest(reserve::message_digest(&message));
+    let claim = ReserveClaim {
+        public_key: PublicKey::from_secret_key(&secp, &sk).serialize().to_vec(),
+        outpoints: vec![outpoint],
+        signature: secp.sign_ecdsa(&digest, &sk).serialize_compact().to_vec(),
+    };
+    assert!(registry
+        .register(&hex::encode(node_id), "miner", Some(0.5), Some(claim))
+        .await
+        .unwrap());
+
+    // Nothing spends the claim yet
+    assert_eq!(registry.check_mempool().await.unwrap(), 0);
+
+    let spend = common::spending_tx(outpoint.to_outpoint());
+    let spend_txid = [9u8; 32];
+    node_api.add_mempool_transaction(spend_txid, spend.clone());
+    assert_eq!(registry.check_mempool().await.unwrap(), 1);
+    // Already seen: not reported again, still pending
+    assert_eq!(registry.check_mempool().await.unwrap(), 0);
+    let pending = registry.get_node(&node_id).await.unwrap().pending_spends;
+    assert_eq!(pending.len(), 1);
+    assert_eq!((pending[0].outpoint, pending[0].txid), (outpoint, spend_txid));
+
+    // The spend confirms
+    let block_hash = [7u8; 32];
+    node_api.add_block(101, block_hash, common::block([6u8; 32], vec![spend]));
+    node_api.clear_mempool();
+    let event = ModuleMessage::Event(EventMessage {
+        event_type: EventType::NewBlock,
+        payload: EventPayload::NewBlock {
+            block_hash,
+            height: 101,
+        },
+    });
+    registry
+        .handle_event(&event, node_api.as_ref())
+        .await
+        .unwrap();
+
+    let details = registry.get_node(&node_id).await.unwrap();
+    assert!(details.pending_spends.is_empty());
+    // Re-verified against the UTXO set, where the outpoint is gone
+    assert!(matches!(
+        details.node.verification,
+        VerificationStatus::VerificationFailed { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_address_proof_balance_counts_in_tallies() {
+    use blvm_governance::economic_nodes::{reserve, AddressProof};
+    use blvm_governance::error::GovernanceError;
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+    use sha2::{Digest, Sha256};
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    let block_hash = [7u8; 32];
+    let event = ModuleMessage::Event(EventMessage {
+        event_type: EventType::NewBlock,
+        payload: EventPayload::NewBlock {
+            block_hash,
+            height: 101,
+        },
+    });
+    registry.handle_event(&event, node_api.as_ref()).await.unwrap();
+
+    // A legacy signmessage signature over the challenge, by the key behind a P2WPKH address
+    let node_id = [1u8; 32];
+    let challenge = registry.address_challenge(&node_id).unwrap();
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[5u8; 32]).unwrap();
+    let pk = PublicKey::from_secret_key(&secp, &sk).serialize();
+    let mut data = b"\x18Bitcoin Signed Message:\n".to_vec();
+    data.push(challenge.len() as u8);
+    data.extend_from_slice(challenge.as_bytes());
+    let digest = Message::from_digest(Sha256::digest(Sha256::digest(&data)).into());
+    let (recid, sig) = secp.sign_ecdsa_recoverable(&digest, &sk).serialize_compact();
+    let mut signature = vec![31 + recid.to_i32() as u8];
+    signature.extend_from_slice(&sig);
+    let proof = AddressProof {
+        address: bech32::segwit::encode(
+            bech32::hrp::BCRT,
+            bech32::segwit::VERSION_0,
+            &reserve::hash160(&pk),
+        )
+        .unwrap(),
+        block_hash: hex::encode(block_hash),
+        signature: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, signature),
+    };
+    // No module serves balances: the proof cannot be weighed, nothing is registered
+    node_api.respond(
+        "get_address_balance",
+        Err("Method 'get_address_balance' not found in any module".to_string()),
+    );
+    assert!(matches!(
+        registry
+            .register_with_address_proof(
+                None,
+                &hex::encode(node_id),
+                "exchange",
+                Some(1.0),
+                proof.clone()
+            )
+            .await,
+        Err(GovernanceError::Unsupported { .. })
+    ));
+    assert!(registry.get_node(&node_id).await.is_none());
+
+    node_api.respond(
+        "get_address_balance",
+        Ok(serde_json::to_vec(&serde_json::json!({ "confirmed_sats": 40 })).unwrap()),
+    );
+    assert!(registry
+        .register_with_address_proof(None, &hex::encode(node_id), "exchange", Some(1.0), proof)
+        .await
+        .unwrap());
+
+    let claim = node_api.add_reserve(&[2u8; 32], 60);
+    registry
+        .register(&hex::encode([2u8; 32]), "miner", Some(50.0), Some(claim))
+        .await
+        .unwrap();
+    registry
+        .veto("p1", &hex::encode(node_id), "unsafe", None)
+        .await
+        .unwrap();
+
+    // The proven balance is the node's weight, not its claimed hashpower
+    let tally = registry.veto_tally("p1").await;
+    assert_eq!((tally.vetoing_weight, tally.total_weight), (40.0, 100.0));
+    assert!(tally.crossed());
+}
+
+/// zbase32, as Lightning `signmessage` encodes signatures.
+fn zbase32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+    let mut out = String::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(acc >> bits) as usize & 31] as char);
+        }
+        acc &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(acc << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// A Lightning identity for the key `sk` offering `channels`, and its `signmessage`
+/// signature over the registration message of `node_id`.
+fn lightning_identity(
+    sk: &secp256k1::SecretKey,
+    node_id: &[u8; 32],
+    channels: Vec<blvm_governance::economic_nodes::ClaimedOutpoint>,
+) -> (blvm_governance::economic_nodes::LightningIdentity, String) {
+    use blvm_governance::economic_nodes::{lightning, reserve, LightningIdentity};
+    use secp256k1::{Message, PublicKey, Secp256k1};
+
+    let secp = Secp256k1::new();
+    let message = reserve::registration_message(node_id, &channels);
+    let digest = Message::from_digest(lightning::message_digest(&message));
+    let (recid, sig) = secp.sign_ecdsa_recoverable(&digest, sk).serialize_compact();
+    let mut signature = vec![31 + recid.to_i32() as u8];
+    signature.extend_from_slice(&sig);
+    let identity = LightningIdentity {
+        node_pubkey: PublicKey::from_secret_key(&secp, sk).serialize().to_vec(),
+        channel_outpoints: channels,
+    };
+    (identity, zbase32(&signature))
+}
+
+/// A 2-of-2 funding witness script of `node_pubkey` and a remote key, and the P2WSH output
+/// paying to it.
+fn funding_output(node_pubkey: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    use sha2::{Digest, Sha256};
+    let remote = [&[0x03][..], &[9u8; 32]].concat();
+    let script = [&[0x52, 0x21][..], &remote, &[0x21], node_pubkey, &[0x52, 0xae]].concat();
+    let script_pubkey = [&[0x00, 0x20][..], &Sha256::digest(&script)].concat();
+    (script, script_pubkey)
+}
+
+#[tokio::test]
+async fn test_lightning_channel_weight_counts_in_tallies() {
+    use blvm_governance::economic_nodes::ClaimedOutpoint;
+    use secp256k1::SecretKey;
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+
+    let channel = ClaimedOutpoint {
+        txid: [3u8; 32],
+        vout: 1,
+    };
+    let node_id = [1u8; 32];
+    let sk = SecretKey::from_slice(&[6u8; 32]).unwrap();
+    let (identity, signature) = lightning_identity(&sk, &node_id, vec![channel]);
+    let (funding_script, script_pubkey) = funding_output(&identity.node_pubkey);
+    node_api.add_utxo(
+        channel.to_outpoint(),
+        blvm_protocol::UTXO {
+            value: 70,
+            script_pubkey: script_pubkey.into(),
+            ..Default::default()
+        },
+    );
+    assert!(registry
+        .register_lightning(
+            None,
+            &hex::encode(node_id),
+            None,
+            identity,
+            &signature,
+            &[funding_script]
+        )
+        .await
+        .unwrap());
+
+    let claim = node_api.add_reserve(&[2u8; 32], 30);
+    registry
+        .register(&hex::encode([2u8; 32]), "miner", Some(50.0), Some(claim))
+        .await
+        .unwrap();
+    registry
+        .veto("p1", &hex::encode(node_id), "unsafe", None)
+        .await
+        .unwrap();
+
+    // The channel capacity is the routing node's weight
+    let tally = registry.veto_tally("p1").await;
+    assert_eq!((tally.vetoing_weight, tally.total_weight), (70.0, 100.0));
+    assert!(tally.crossed());
+}
+
+#[tokio::test]
+async fn test_lightning_channels_must_be_distinct_funding_outputs() {
+    use blvm_governance::economic_nodes::{ClaimedOutpoint, ValidationRule, VerificationStatus};
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    let sk = SecretKey::from_slice(&[6u8; 32]).unwrap();
+
+    // A funding output listed three times is refused
+    let channel = ClaimedOutpoint {
+        txid: [3u8; 32],
+        vout: 1,
+    };
+    let node_pubkey = PublicKey::from_secret_key(&Secp256k1::new(), &sk).serialize();
+    let (funding_script, script_pubkey) = funding_output(&node_pubkey);
+    node_api.add_utxo(
+        channel.to_outpoint(),
+        blvm_protocol::UTXO {
+            value: 70,
+            script_pubkey: script_pubkey.into(),
+            ..Default::default()
+        },
+    );
+    let (identity, signature) = lightning_identity(&sk, &[1u8; 32], vec![channel; 3]);
+    let repeated = registry
+        .register_lightning(
+            None,
+            &hex::encode([1u8; 32]),
+            None,
+            identity,
+            &signature,
+            &vec![funding_script.clone(); 3],
+        )
+        .await;
+    assert!(repeated.is_err());
+    assert_eq!(
+        registry.validation_counters().get(&ValidationRule::DuplicateOutpoint),
+        Some(&1)
+    );
+
+    // Any other unspent output, even with a script naming the node key, carries no weight
+    let unrelated = ClaimedOutpoint {
+        txid: [4u8; 32],
+        vout: 0,
+    };
+    node_api.add_utxo(
+        unrelated.to_outpoint(),
+        blvm_protocol::UTXO {
+            value: 500,
+            script_pubkey: [&[0x00, 0x14][..], &[7u8; 20]].concat().into(),
+            ..Default::default()
+        },
+    );
+    let (identity, signature) = lightning_identity(&sk, &[2u8; 32], vec![unrelated]);
+    assert!(registry
+        .register_lightning(
+            None,
+            &hex::encode([2u8; 32]),
+            None,
+            identity,
+            &signature,
+            &[funding_script]
+        )
+        .await
+        .unwrap());
+    let node = registry.get_nodes_for_test().await[&[2u8; 32]].clone();
+    assert_eq!(node.verified_weight, 0);
+    assert!(matches!(node.verification, VerificationStatus::VerificationFailed { .. }));
+}
+
+#[tokio::test]
+async fn test_outpoint_backs_only_its_first_node() {
+    use blvm_governance::economic_nodes::{reserve, ReserveClaim};
+    use secp256k1::{Message, Secp256k1, SecretKey};
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    let first = [1u8; 32];
+    let claim = node_api.add_reserve(&first, 50);
+    assert!(registry
+        .register(&hex::encode(first), "exchange", None, Some(claim.clone()))
+        .await
+        .unwrap());
+
+    // The same holder signs the same outpoint over to a second node id
+    let second = [2u8; 32];
+    let sk = SecretKey::from_slice(&reserve::message_digest(&hex::encode(first))).unwrap();
+    let message = reserve::registration_message(&second, &claim.outpoints);
+    let digest = Message::from_digest(reserve::message_digest(&message));
+    let reused = ReserveClaim {
+        signature: Secp256k1::new()
+            .sign_ecdsa(&digest, &sk)
+            .serialize_compact()
+            .to_vec(),
+        ..claim
+    };
+    assert!(registry
+        .register(&hex::encode(second), "exchange", None, Some(reused))
+        .await
+        .is_err());
+    assert!(!registry.get_nodes_for_test().await.contains_key(&second));
+    assert_eq!(registry.veto_tally("p1").await.total_weight, 50.0);
+}
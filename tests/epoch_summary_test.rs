@@ -0,0 +1,184 @@
+//! Epoch summaries gathered from the proposal store and signaling blocks
+
+mod common;
+
+use blvm_governance::config::{EpochSummaryConfig, SignalingConfig, SignalingRule};
+use blvm_governance::epoch_summary::EpochSummarizer;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::signaling::SignalingTracker;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn hash(height: u64) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash[..8].copy_from_slice(&height.to_le_bytes());
+    hash
+}
+
+/// Pass a proposal event straight to `store`, as the pipeline would.
+async fn handle(store: &ProposalStore, node_api: &common::MockNodeApi, payload: EventPayload) {
+    let event_type = match &payload {
+        EventPayload::GovernanceProposalCreated { .. } => EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalVoted { .. } => EventType::GovernanceProposalVoted,
+        _ => EventType::GovernanceProposalMerged,
+    };
+    let message = ModuleMessage::Event(EventMessage {
+        event_type,
+        payload,
+    });
+    store.handle_event(&message, node_api).await.unwrap();
+}
+
+fn created(proposal_id: &str) -> EventPayload {
+    EventPayload::GovernanceProposalCreated {
+        proposal_id: proposal_id.to_string(),
+        repository: "test/repo".to_string(),
+        pr_number: 1,
+        tier: "standard".to_string(),
+    }
+}
+
+fn voted(proposal_id: &str, voter: &str) -> EventPayload {
+    EventPayload::GovernanceProposalVoted {
+        proposal_id: proposal_id.to_string(),
+        voter: voter.to_string(),
+        vote: "yes".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_epochs_are_summarized_once_settled() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let dir = std::env::temp_dir().join(format!("blvm_epoch_summary_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    // Even heights signal for bit 1
+    for height in 90..=125 {
+        let mut block = common::block([0u8; 32], vec![]);
+        block.header.version = 0x2000_0000 | if height % 2 == 0 { 2 } else { 0 };
+        node_api.add_block(height, hash(height), block);
+    }
+    node_api.add_proposal(common::proposal("4"));
+    node_api.add_proposal(common::proposal("6"));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    ipc.get_best_block().await.unwrap();
+    let store = Arc::new(ProposalStore::new(Arc::clone(&db)).with_node_api(ipc.clone()));
+    for event in [
+        created("4"),
+        created("6"),
+        voted("4", "bob"),
+        voted("4", "carol"),
+    ] {
+        handle(&store, &node_api, event).await;
+    }
+    ipc.tip_tracker().observe(hash(105), 105);
+    handle(
+        &store,
+        &node_api,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "4".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    handle(&store, &node_api, voted("6", "dave")).await;
+    ipc.tip_tracker().observe(hash(112), 112);
+    handle(&store, &node_api, voted("6", "erin")).await;
+
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let signaling = SignalingConfig {
+        proposals: [(
+            "42".to_string(),
+            SignalingRule {
+                bit: 1,
+                ..Default::default()
+            },
+        )]
+        .into(),
+    };
+    let signaling = Arc::new(SignalingTracker::new(signaling, ipc.clone()));
+    let summarizer = || {
+        EpochSummarizer::new(
+            EpochSummaryConfig {
+                length_blocks: 10,
+                confirmations: 2,
+            },
+            Arc::clone(&db),
+            Arc::clone(&store),
+        )
+        .with_signaling(Arc::clone(&signaling))
+        .with_webhook(Arc::clone(&webhook))
+    };
+
+    let first = summarizer();
+    // Only the latest settled epoch is summarized on the first block; epoch 10 ends at 109
+    // and settles 2 blocks later
+    first.handle_block(110, node_api.as_ref()).await.unwrap();
+    assert_eq!(
+        first
+            .summaries()
+            .unwrap()
+            .iter()
+            .map(|s| s.epoch)
+            .collect::<Vec<_>>(),
+        vec![9]
+    );
+    first.handle_block(111, node_api.as_ref()).await.unwrap();
+
+    // Not summarized again after a restart
+    let second = summarizer();
+    second.handle_block(111, node_api.as_ref()).await.unwrap();
+    second.handle_block(122, node_api.as_ref()).await.unwrap();
+
+    let summaries = second.summaries().unwrap();
+    assert_eq!(
+        summaries.iter().map(|s| s.epoch).collect::<Vec<_>>(),
+        vec![9, 10, 11]
+    );
+    let epoch = &summaries[1];
+    assert_eq!((epoch.start_height, epoch.end_height), (100, 109));
+    assert_eq!(epoch.proposals.created, vec!["4", "6"]);
+    assert_eq!(epoch.proposals.merged, vec!["4"]);
+    assert_eq!(epoch.votes, 3);
+    assert_eq!(
+        (epoch.signaling[0].signaling, epoch.signaling[0].blocks),
+        (5, 10)
+    );
+    assert_eq!(summaries[2].votes, 1);
+    assert!(summaries[2].proposals.created.is_empty());
+    assert_eq!(second.summary(11).unwrap().as_ref(), Some(&summaries[2]));
+
+    let mut sent = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        assert_eq!(payload["event_type"], "epoch_summary");
+        sent.push(payload["data"]["epoch"].as_u64().unwrap());
+    }
+    assert_eq!(sent, vec![9, 10, 11]);
+    drop(first);
+    drop(second);
+    drop(store);
+    drop(db);
+    std::fs::remove_dir_all(&dir).ok();
+}
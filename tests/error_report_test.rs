@@ -0,0 +1,121 @@
+//! Error reports from failing webhook deliveries, batched to the node
+
+mod common;
+
+use blvm_governance::config::{ErrorReportConfig, GovernanceConfig};
+use blvm_governance::error_report::{ErrorCode, ErrorReporter};
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::sync::Arc;
+
+const REPEAT_SECS: u64 = 300;
+
+fn proposal_created(id: &str) -> ModuleMessage {
+    ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    })
+}
+
+/// A webhook client posting to a URL nothing listens on, reporting to a new reporter.
+async fn failing_client() -> (GovernanceWebhookClient, Arc<ErrorReporter>) {
+    let config = GovernanceConfig {
+        webhook_url: Some("http://127.0.0.1:1/webhook".to_string()),
+        webhook_retry_count: 0,
+        ..Default::default()
+    };
+    let errors = Arc::new(ErrorReporter::new(ErrorReportConfig {
+        repeat_secs: REPEAT_SECS,
+        ..Default::default()
+    }));
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_error_reporter(Arc::clone(&errors));
+    (client, errors)
+}
+
+/// The reports of each `module_error_report` call received by `node_api`.
+fn sent(node_api: &MockNodeApi) -> Vec<Vec<serde_json::Value>> {
+    node_api
+        .module_calls()
+        .into_iter()
+        .filter(|(method, _)| method == "module_error_report")
+        .map(|(_, payload)| {
+            let payload: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+            payload["reports"].as_array().unwrap().clone()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_repeated_failures_are_deduplicated_and_rate_limited() {
+    let node_api = MockNodeApi::new(100);
+    let (client, errors) = failing_client().await;
+    for id in ["1", "2", "3"] {
+        client
+            .handle_event(&proposal_created(id), &node_api)
+            .await
+            .unwrap();
+    }
+    // One report, counted three times
+    let recent = errors.recent();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].code, ErrorCode::WebhookUndeliverable.code());
+    assert_eq!(recent[0].count, 3);
+    assert_eq!(recent[0].context["url"], "http://127.0.0.1:1/webhook");
+
+    let now = 1_700_000_000;
+    assert_eq!(errors.send(&node_api, now).await.unwrap(), 1);
+    let batches = sent(&node_api);
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].len(), 1);
+    assert_eq!(batches[0][0]["code"], 1001);
+    assert_eq!(batches[0][0]["severity"], "error");
+    assert_eq!(batches[0][0]["count"], 3);
+
+    // Nothing new, then more failures within the repeat interval: held back
+    assert_eq!(errors.send(&node_api, now + 1).await.unwrap(), 0);
+    client
+        .handle_event(&proposal_created("4"), &node_api)
+        .await
+        .unwrap();
+    assert_eq!(
+        errors.send(&node_api, now + REPEAT_SECS - 1).await.unwrap(),
+        0
+    );
+    assert_eq!(sent(&node_api).len(), 1);
+
+    // Sent again once it has passed, with the updated count
+    assert_eq!(errors.send(&node_api, now + REPEAT_SECS).await.unwrap(), 1);
+    let batches = sent(&node_api);
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[1][0]["count"], 4);
+    assert_eq!(errors.counts()["webhook_undeliverable"], 4);
+}
+
+#[tokio::test]
+async fn test_reports_stay_due_when_the_node_rejects_them() {
+    let node_api = MockNodeApi::new(100);
+    let (client, errors) = failing_client().await;
+    client
+        .handle_event(&proposal_created("1"), &node_api)
+        .await
+        .unwrap();
+
+    let now = 1_700_000_000;
+    node_api.respond("module_error_report", Err("unknown method".to_string()));
+    assert!(errors.send(&node_api, now).await.is_err());
+    assert_eq!(errors.due(now + 1).len(), 1);
+
+    node_api.respond("module_error_report", Ok(Vec::new()));
+    assert_eq!(errors.send(&node_api, now + 1).await.unwrap(), 1);
+    assert!(errors.due(now + 2).is_empty());
+}
@@ -0,0 +1,177 @@
+//! Escalation messages as veto tallies enter and leave the bands below the threshold
+
+mod common;
+
+use blvm_governance::config::{DeadlineConfig, VetoEscalationConfig};
+use blvm_governance::economic_nodes::VetoTally;
+use blvm_governance::escalation::VetoEscalation;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::{MockNode, MockNodeApi};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn node_id(id: u8) -> String {
+    hex::encode([id; 32])
+}
+
+fn escalation_config() -> VetoEscalationConfig {
+    VetoEscalationConfig {
+        bands: vec![50.0, 75.0, 90.0],
+        contributors: 1,
+        redact_node_ids: false,
+    }
+}
+
+/// The escalation messages received within a second, as (event type, band).
+async fn received(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+) -> Vec<(String, f64)> {
+    let mut messages = Vec::new();
+    while let Ok(Some(payload)) = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+        let event_type = payload["event_type"].as_str().unwrap().to_string();
+        if event_type.contains("escalation") {
+            messages.push((
+                event_type,
+                payload["data"]["band_percent"].as_f64().unwrap(),
+            ));
+        }
+    }
+    messages
+}
+
+#[tokio::test]
+async fn test_messages_as_vetoes_are_cast_and_revoked() {
+    let (url, mut rx) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        veto_escalation: escalation_config(),
+        deadlines: DeadlineConfig {
+            windows: [("standard".to_string(), 144)].into(),
+            reminders: Vec::new(),
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("escalation", config).await;
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    let registry = &node.module.economic_nodes;
+    // Against the default threshold of 30%, the bands are at 15%, 22.5% and 27% of the weight
+    for (id, sats) in [(1, 16), (2, 8), (3, 40), (4, 36)] {
+        node.register_verified(id, "miner", sats).await;
+    }
+
+    registry
+        .veto("1", &node_id(1), "unsafe", None)
+        .await
+        .unwrap();
+    let payload = loop {
+        let payload = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if payload["event_type"] == "veto_escalation" {
+            break payload;
+        }
+    };
+    let data = &payload["data"];
+    assert_eq!(data["band_percent"], 50.0);
+    assert!((data["ratio_percent"].as_f64().unwrap() - 160.0 / 3.0).abs() < 1e-9);
+    assert_eq!(data["contributors"][0]["node_id"], node_id(1));
+    assert_eq!(data["contributors"][0]["share_percent"], 100.0);
+    assert_eq!(data["deadline_height"], 244);
+    assert_eq!(data["blocks_remaining"], 144);
+
+    registry
+        .veto("1", &node_id(2), "unsafe", None)
+        .await
+        .unwrap();
+    registry.revoke_veto("1", &node_id(1), None).await.unwrap();
+    // Back into both bands: each was escalated already
+    registry
+        .veto("1", &node_id(1), "unsafe", None)
+        .await
+        .unwrap();
+    assert_eq!(
+        received(&mut rx).await,
+        vec![
+            ("veto_escalation".to_string(), 75.0),
+            ("veto_deescalation".to_string(), 50.0),
+        ]
+    );
+    let bands = node.escalation.as_ref().unwrap().bands();
+    assert_eq!(bands["1"].inside, vec![50.0, 75.0]);
+    assert_eq!(bands["1"].deescalated, vec![50.0, 75.0]);
+}
+
+#[tokio::test]
+async fn test_bands_fire_once_across_restarts() {
+    let (url, mut rx) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let dir = std::env::temp_dir().join(format!("blvm_escalation_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = MockNodeApi::new(100);
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let open = || {
+        VetoEscalation::open(escalation_config(), Arc::clone(&db))
+            .unwrap()
+            .with_webhook(Arc::clone(&webhook))
+    };
+    // 1.65 of 10 against 33% is exactly half the threshold, short of it in floating point
+    let tally = |vetoing_weight: f64| VetoTally {
+        proposal_id: "1".to_string(),
+        total_weight: 10.0,
+        vetoing_weight,
+        threshold_percent: 33.0,
+        commitment: String::new(),
+    };
+
+    let escalation = open();
+    escalation.observe(&tally(1.6499), 100, Vec::new);
+    assert_eq!(escalation.flush(&node_api).await, 0);
+    escalation.observe(&tally(1.65), 101, Vec::new);
+    assert_eq!(escalation.flush(&node_api).await, 1);
+    drop(escalation);
+
+    let escalation = open();
+    assert_eq!(escalation.tracked(), vec!["1".to_string()]);
+    escalation.observe(&tally(1.65), 102, Vec::new);
+    assert_eq!(escalation.flush(&node_api).await, 0);
+    escalation.observe(&tally(1.0), 103, Vec::new);
+    escalation.observe(&tally(1.7), 104, Vec::new);
+    escalation.observe(&tally(1.0), 105, Vec::new);
+    assert_eq!(escalation.flush(&node_api).await, 1);
+    assert!(escalation.tracked().is_empty());
+    assert_eq!(
+        received(&mut rx).await,
+        vec![
+            ("veto_escalation".to_string(), 50.0),
+            ("veto_deescalation".to_string(), 50.0),
+        ]
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,97 @@
+//! Event queue overflow under load
+
+use blvm_governance::config::{EventQueueConfig, OverflowPolicy};
+use blvm_governance::event_queue::EventQueue;
+use blvm_governance::shutdown::Shutdown;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload};
+use blvm_node::module::traits::EventType;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn new_block(height: u64) -> EventMessage {
+    EventMessage {
+        event_type: EventType::NewBlock,
+        payload: EventPayload::NewBlock {
+            block_hash: [0u8; 32],
+            height,
+        },
+    }
+}
+
+fn proposal_created(id: u64) -> EventMessage {
+    EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: id,
+            tier: "standard".to_string(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_block_storm_drops_blocks_and_keeps_governance_events() {
+    let shutdown = Arc::new(Shutdown::new());
+    let (queue, mut rx) = EventQueue::new(&EventQueueConfig {
+        capacity: 8,
+        policy: OverflowPolicy::DropLowPriority,
+        ..Default::default()
+    });
+    let queue = Arc::new(queue);
+
+    // Slow consumer
+    let consumer = tokio::spawn(async move {
+        let mut governance = 0;
+        while let Some(queued) = rx.recv().await {
+            if queued.event.event_type == EventType::GovernanceProposalCreated {
+                governance += 1;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        governance
+    });
+
+    for i in 0..5_000u64 {
+        queue.push(new_block(i), shutdown.track()).await;
+        if i % 100 == 0 {
+            assert!(queue.push(proposal_created(i), shutdown.track()).await);
+        }
+        assert!(queue.stats().queued <= 8);
+    }
+    let stats = queue.stats();
+    assert!(stats.dropped > 0);
+    assert!(stats.dropped < 5_000);
+
+    drop(queue);
+    assert_eq!(consumer.await.unwrap(), 50);
+    // Every accepted event was processed
+    assert_eq!(shutdown.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_backpressure_waits_for_room() {
+    let shutdown = Arc::new(Shutdown::new());
+    let (queue, mut rx) = EventQueue::new(&EventQueueConfig {
+        capacity: 2,
+        policy: OverflowPolicy::Backpressure,
+        ..Default::default()
+    });
+    assert!(queue.push(new_block(1), shutdown.track()).await);
+    assert!(queue.push(new_block(2), shutdown.track()).await);
+
+    // Full: the third push waits until the consumer takes an event
+    assert!(tokio::time::timeout(
+        Duration::from_millis(50),
+        queue.push(new_block(3), shutdown.track())
+    )
+    .await
+    .is_err());
+    rx.recv().await.unwrap();
+    assert!(queue.push(new_block(3), shutdown.track()).await);
+
+    let stats = queue.stats();
+    assert_eq!(stats.dropped, 0);
+    assert_eq!(stats.full, 1);
+    assert_eq!(stats.queued, 2);
+}
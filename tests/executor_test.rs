@@ -0,0 +1,59 @@
+//! Keyed lanes under load
+
+use blvm_governance::executor::Lanes;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Process 32 keys x 10 items with pseudo-random delays; returns the elapsed time and the
+/// order items were processed in per key. With more keys than lanes, several keys share
+/// the busiest lane, which bounds the speedup.
+async fn run(parallelism: usize) -> (Duration, HashMap<u32, Vec<u32>>) {
+    let seen: Arc<Mutex<HashMap<u32, Vec<u32>>>> = Arc::default();
+    let (lanes, handles) = Lanes::spawn(parallelism, {
+        let seen = Arc::clone(&seen);
+        move |(key, seq, delay_ms): (u32, u32, u64)| {
+            let seen = Arc::clone(&seen);
+            async move {
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                seen.lock().unwrap().entry(key).or_default().push(seq);
+            }
+        }
+    });
+    let start = Instant::now();
+    let mut state = 0x2545_f491_u64;
+    for seq in 0..10 {
+        for key in 0..32 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let delay_ms = 1 + (state >> 33) % 4;
+            assert!(lanes.dispatch(&key.to_string(), (key, seq, delay_ms)).await);
+        }
+    }
+    drop(lanes);
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let seen = seen.lock().unwrap().clone();
+    (start.elapsed(), seen)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_lanes_keep_per_key_order() {
+    let (serial, serial_seen) = run(1).await;
+    let (parallel, parallel_seen) = run(8).await;
+
+    for seen in [&serial_seen, &parallel_seen] {
+        assert_eq!(seen.len(), 32);
+        for order in seen.values() {
+            assert_eq!(*order, (0..10).collect::<Vec<_>>());
+        }
+    }
+    assert!(
+        parallel * 2 < serial,
+        "parallel {:?} vs serial {:?}",
+        parallel,
+        serial
+    );
+}
@@ -0,0 +1,440 @@
+//! Atom and RSS feeds of governance activity over the health listener
+
+mod common;
+
+use blvm_governance::config::{ActivationConfig, DeadlineConfig, HealthConfig, RegistryConfig};
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::feed::Feed;
+use blvm_governance::health::Health;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::shutdown::Shutdown;
+use blvm_governance::subscriptions::EventSubscriptions;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blvm_feed_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// An element of a parsed document: its path from the root, attributes and text.
+#[derive(Debug)]
+struct Element {
+    path: String,
+    attributes: BTreeMap<String, String>,
+    text: String,
+}
+
+/// The elements of `xml` in document order, failing on anything that is not well-formed:
+/// unbalanced tags, a bare `&` or `<` in text, a missing declaration or more than one root.
+fn parse(xml: &str) -> Vec<Element> {
+    let rest = xml
+        .strip_prefix("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n")
+        .expect("XML declaration");
+    let mut elements: Vec<Element> = Vec::new();
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut roots = 0;
+    let mut rest = rest;
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        check_text(text);
+        if let Some((_, index)) = open.last() {
+            elements[*index].text.push_str(text);
+        } else {
+            assert!(text.trim().is_empty(), "text outside the root: {:?}", text);
+        }
+        let end = rest[start..].find('>').expect("unterminated tag") + start;
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            let (open_name, _) = open.pop().expect("closing tag without opening");
+            assert_eq!(open_name, name, "mismatched closing tag");
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, mut attributes_text) = tag.split_once(' ').unwrap_or((tag, ""));
+        let mut attributes = BTreeMap::new();
+        while let Some((key, value)) = attributes_text.trim_start().split_once("=\"") {
+            let (value, after) = value.split_once('"').expect("unterminated attribute");
+            check_text(value);
+            attributes.insert(key.trim().to_string(), value.to_string());
+            attributes_text = after;
+        }
+        if open.is_empty() {
+            roots += 1;
+        }
+        let path = match open.last() {
+            Some((_, index)) => format!("{}/{}", elements[*index].path, name),
+            None => name.to_string(),
+        };
+        elements.push(Element {
+            path,
+            attributes,
+            text: String::new(),
+        });
+        if !self_closing {
+            open.push((name.to_string(), elements.len() - 1));
+        }
+    }
+    assert!(open.is_empty(), "unclosed tags: {:?}", open);
+    assert!(rest.trim().is_empty());
+    assert_eq!(roots, 1);
+    elements
+}
+
+/// Text may only hold the predefined entities.
+fn check_text(text: &str) {
+    assert!(!text.contains('<'), "{:?}", text);
+    for (i, _) in text.match_indices('&') {
+        let entity = &text[i..text[i..].find(';').map_or(text.len(), |end| i + end + 1)];
+        assert!(
+            ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"].contains(&entity),
+            "bare & in {:?}",
+            text
+        );
+    }
+}
+
+fn is_rfc3339(date: &str) -> bool {
+    let digits = |range: std::ops::Range<usize>| date[range].bytes().all(|b| b.is_ascii_digit());
+    date.len() == 20
+        && digits(0..4)
+        && &date[4..5] == "-"
+        && digits(5..7)
+        && &date[10..11] == "T"
+        && digits(11..13)
+        && date.ends_with('Z')
+}
+
+fn is_rfc822(date: &str) -> bool {
+    let parts: Vec<&str> = date.split(' ').collect();
+    parts.len() == 6
+        && parts[0].ends_with(',')
+        && parts[1].len() == 2
+        && parts[3].len() == 4
+        && parts[4].len() == 8
+        && parts[5] == "GMT"
+}
+
+/// Elements of `elements` at `path`.
+fn at<'a>(elements: &'a [Element], path: &str) -> Vec<&'a Element> {
+    elements.iter().filter(|e| e.path == path).collect()
+}
+
+/// Check `xml` against the requirements of RFC 4287 on a feed and its entries, returning the
+/// entries' titles.
+fn validate_atom(xml: &str) -> Vec<String> {
+    let elements = parse(xml);
+    let feed = &elements[0];
+    assert_eq!(feed.path, "feed");
+    assert_eq!(feed.attributes["xmlns"], "http://www.w3.org/2005/Atom");
+    for required in ["feed/id", "feed/title", "feed/updated"] {
+        assert_eq!(at(&elements, required).len(), 1, "{}", required);
+    }
+    assert!(is_rfc3339(&at(&elements, "feed/updated")[0].text));
+    assert_eq!(at(&elements, "feed/author/name").len(), 1);
+    let links = at(&elements, "feed/link");
+    assert!(links.iter().any(|l| l.attributes["rel"] == "self"));
+
+    let entries = at(&elements, "feed/entry").len();
+    let mut ids = HashSet::new();
+    for id in at(&elements, "feed/entry/id") {
+        assert!(id.text.starts_with("tag:"), "{}", id.text);
+        assert!(!id.text.contains(' '));
+        assert!(ids.insert(id.text.clone()), "duplicate id {}", id.text);
+    }
+    assert_eq!(ids.len(), entries);
+    for date in at(&elements, "feed/entry/updated") {
+        assert!(is_rfc3339(&date.text), "{}", date.text);
+    }
+    assert_eq!(at(&elements, "feed/entry/updated").len(), entries);
+    assert_eq!(at(&elements, "feed/entry/summary").len(), entries);
+    let alternates = at(&elements, "feed/entry/link")
+        .into_iter()
+        .filter(|l| l.attributes["rel"] == "alternate")
+        .count();
+    assert_eq!(alternates, entries);
+    at(&elements, "feed/entry/title")
+        .into_iter()
+        .map(|t| t.text.clone())
+        .collect()
+}
+
+/// Check `xml` against the required elements of RSS 2.0, returning the items' titles.
+fn validate_rss(xml: &str) -> Vec<String> {
+    let elements = parse(xml);
+    assert_eq!(elements[0].path, "rss");
+    assert_eq!(elements[0].attributes["version"], "2.0");
+    for required in [
+        "rss/channel/title",
+        "rss/channel/link",
+        "rss/channel/description",
+    ] {
+        assert_eq!(at(&elements, required).len(), 1, "{}", required);
+    }
+    assert!(is_rfc822(
+        &at(&elements, "rss/channel/lastBuildDate")[0].text
+    ));
+    let items = at(&elements, "rss/channel/item").len();
+    let guids: HashSet<&str> = at(&elements, "rss/channel/item/guid")
+        .iter()
+        .map(|g| g.text.as_str())
+        .collect();
+    assert_eq!(guids.len(), items);
+    for date in at(&elements, "rss/channel/item/pubDate") {
+        assert!(is_rfc822(&date.text), "{}", date.text);
+    }
+    assert_eq!(at(&elements, "rss/channel/item/description").len(), items);
+    at(&elements, "rss/channel/item/title")
+        .into_iter()
+        .map(|t| t.text.clone())
+        .collect()
+}
+
+async fn handle(
+    store: &ProposalStore,
+    node_api: &common::MockNodeApi,
+    event_type: EventType,
+    payload: EventPayload,
+) {
+    let message = ModuleMessage::Event(EventMessage {
+        event_type,
+        payload,
+    });
+    store.handle_event(&message, node_api).await.unwrap();
+}
+
+fn created(proposal_id: &str) -> EventPayload {
+    EventPayload::GovernanceProposalCreated {
+        proposal_id: proposal_id.to_string(),
+        repository: "test/repo".to_string(),
+        pr_number: 1,
+        tier: "standard".to_string(),
+    }
+}
+
+fn block(height: u64) -> EventPayload {
+    EventPayload::NewBlock {
+        block_hash: [height as u8; 32],
+        height,
+    }
+}
+
+/// GET `path`: the status, the headers asked for and the body.
+async fn get(
+    base: &str,
+    path: &str,
+    if_none_match: Option<&str>,
+) -> (u16, BTreeMap<&'static str, String>, String) {
+    let mut request = reqwest::Client::new().get(format!("{}{}", base, path));
+    if let Some(etag) = if_none_match {
+        request = request.header("If-None-Match", etag);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    let headers = ["content-type", "cache-control", "etag", "last-modified"]
+        .into_iter()
+        .filter_map(|name| {
+            let value = response.headers().get(name)?;
+            Some((name, value.to_str().unwrap().to_string()))
+        })
+        .collect();
+    (status, headers, response.text().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_feeds_of_proposal_activity() {
+    let dir = temp_dir("activity");
+    let feed = Arc::new(Feed::open(&dir, 50).unwrap());
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        dir.join("store"),
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.add_proposal(common::proposal("4"));
+    node_api.add_proposal(common::proposal("6 & <b>"));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    ipc.get_best_block().await.unwrap();
+    let store = ProposalStore::new(Arc::clone(&db))
+        .with_node_api(ipc)
+        .with_deadlines(DeadlineConfig {
+            windows: [("standard".to_string(), 20)].into(),
+            reminders: Vec::new(),
+        })
+        .with_activation(ActivationConfig {
+            delays: [("standard".to_string(), 5)].into(),
+            countdown_interval_blocks: 0,
+        })
+        .with_feed(Arc::clone(&feed));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap()
+        .with_feed(Arc::clone(&feed));
+    let (a, b) = (hex::encode([1u8; 32]), hex::encode([2u8; 32]));
+    let claim = node_api.add_reserve(&[1u8; 32], 40);
+    registry
+        .register(&a, "miner", None, Some(claim))
+        .await
+        .unwrap();
+    let claim = node_api.add_reserve(&[2u8; 32], 60);
+    registry
+        .register(&b, "miner", None, Some(claim))
+        .await
+        .unwrap();
+
+    let api = node_api.as_ref();
+    handle(
+        &store,
+        api,
+        EventType::GovernanceProposalCreated,
+        created("4"),
+    )
+    .await;
+    handle(
+        &store,
+        api,
+        EventType::GovernanceProposalCreated,
+        created("6 & <b>"),
+    )
+    .await;
+    let merged = EventPayload::GovernanceProposalMerged {
+        proposal_id: "6 & <b>".to_string(),
+        repository: "test/repo".to_string(),
+        pr_number: 1,
+    };
+    handle(
+        &store,
+        api,
+        EventType::GovernanceProposalMerged,
+        merged.clone(),
+    )
+    .await;
+    // Merged again: no second entry
+    handle(&store, api, EventType::GovernanceProposalMerged, merged).await;
+    handle(&store, api, EventType::NewBlock, block(105)).await;
+    registry.veto("4", &a, "unsafe", None).await.unwrap();
+    handle(&store, api, EventType::NewBlock, block(120)).await;
+
+    let types: Vec<String> = feed.entries().into_iter().map(|e| e.event_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            "voting_closed",
+            "veto_threshold_crossed",
+            "proposal_activated",
+            "proposal_merged",
+            "proposal_created",
+            "proposal_created",
+        ]
+    );
+
+    let health = Arc::new(
+        Health::new(
+            HealthConfig {
+                feed: true,
+                ..Default::default()
+            },
+            Arc::new(Shutdown::new()),
+            Arc::new(Heartbeat::new()),
+            Arc::new(EventSubscriptions::new([])),
+        )
+        .with_feed(Arc::clone(&feed)),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+
+    let (status, headers, atom) = get(&base, "/feed.atom", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        headers["content-type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    assert_eq!(headers["cache-control"], "public, max-age=60");
+    assert!(headers["last-modified"].ends_with(" GMT"));
+    let titles = validate_atom(&atom);
+    assert_eq!(titles[0], "Voting closed on proposal 4");
+    assert_eq!(titles[1], "Veto threshold crossed on proposal 4");
+    assert_eq!(titles[3], "Proposal 6 &amp; &lt;b&gt; merged");
+    assert!(atom.contains(&format!("href=\"{}/feed.atom\"", base)));
+    assert!(atom.contains("/proposals/6%20%26%20%3Cb%3E\""));
+
+    let (status, headers, rss) = get(&base, "/feed.rss", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        headers["content-type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    assert_eq!(validate_rss(&rss).len(), 6);
+
+    // Unchanged until the next entry
+    let etag = &headers["etag"];
+    let (status, _, body) = get(&base, "/feed.rss", Some(etag)).await;
+    assert_eq!((status, body.as_str()), (304, ""));
+    let (status, _, _) = get(&base, "/feed.atom", Some(etag)).await;
+    assert_eq!(status, 200);
+    handle(
+        &store,
+        api,
+        EventType::GovernanceProposalCreated,
+        created("7"),
+    )
+    .await;
+    let (status, _, _) = get(&base, "/feed.rss", Some(etag)).await;
+    assert_eq!(status, 200);
+    server.abort();
+
+    // Kept across restarts, the most recent first, as many as configured
+    let reopened = Feed::open(&dir, 3).unwrap();
+    let ids: Vec<String> = reopened.entries().into_iter().map(|e| e.id).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "proposal_created/7",
+            "voting_closed/4",
+            "veto_threshold_crossed/4/100"
+        ]
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_feeds_not_served_unless_configured() {
+    let health = Arc::new(Health::new(
+        HealthConfig::default(),
+        Arc::new(Shutdown::new()),
+        Arc::new(Heartbeat::new()),
+        Arc::new(EventSubscriptions::new([])),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+    let (status, _, _) = get(&base, "/feed.atom", None).await;
+    assert_eq!(status, 404);
+
+    // An empty feed is still valid
+    let dir = temp_dir("empty");
+    let feed = Feed::open(&dir, 10).unwrap();
+    let response = feed.respond(
+        blvm_governance::feed::FeedFormat::Atom,
+        "http://localhost",
+        None,
+    );
+    assert!(validate_atom(&response.body).is_empty());
+    server.abort();
+    std::fs::remove_dir_all(&dir).ok();
+}
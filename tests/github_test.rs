@@ -0,0 +1,224 @@
+//! GitHub webhook deliveries received on the health listener
+
+mod common;
+
+use blvm_governance::config::{GithubConfig, HealthConfig};
+use blvm_governance::github::{self, GithubCounters, GithubReceiver};
+use blvm_governance::health::Health;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::proposals::{ProposalStatus, ProposalStore};
+use blvm_governance::shutdown::Shutdown;
+use blvm_governance::subscriptions::EventSubscriptions;
+use common::MockNodeApi;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const SECRET: &str = "s3cret";
+
+fn config() -> GithubConfig {
+    GithubConfig {
+        secret: Some(SECRET.to_string()),
+        repositories: vec!["BTCDecoded/governance".to_string()],
+        ..Default::default()
+    }
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/github")
+        .join(format!("{}.json", name));
+    std::fs::read(path).unwrap()
+}
+
+fn store(name: &str) -> (PathBuf, Arc<ProposalStore>) {
+    let dir = std::env::temp_dir().join(format!("blvm_github_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    (dir, Arc::new(ProposalStore::new(db)))
+}
+
+/// A listener receiving GitHub deliveries, not yet connected.
+async fn listen() -> (Arc<Health>, String, tokio::task::JoinHandle<()>) {
+    let health = Arc::new(
+        Health::new(
+            HealthConfig::default(),
+            Arc::new(Shutdown::new()),
+            Arc::new(Heartbeat::new()),
+            Arc::new(EventSubscriptions::new([])),
+        )
+        .with_github(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+    (health, base, server)
+}
+
+/// Deliver fixture `name` as `event`, signed with `secret`.
+async fn deliver(base: &str, event: &str, name: &str, secret: &str) -> (u16, serde_json::Value) {
+    let body = fixture(name);
+    let response = reqwest::Client::new()
+        .post(format!("{}/integrations/github", base))
+        .header("Content-Type", "application/json")
+        .header("X-GitHub-Event", event)
+        .header("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958")
+        .header(
+            "X-Hub-Signature-256",
+            github::signature(secret.as_bytes(), &body),
+        )
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_pull_requests_update_the_store() {
+    let (dir, store) = store("store");
+    let (health, base, server) = listen().await;
+
+    // Not connected to the node yet
+    let (status, _) = deliver(&base, "pull_request", "pull_request_opened", SECRET).await;
+    assert_eq!(status, 503);
+
+    let receiver = Arc::new(GithubReceiver::new(config(), Arc::clone(&store)));
+    health.serve_github(Arc::clone(&receiver));
+    let (status, _) = deliver(&base, "pull_request", "pull_request_opened", "wrong").await;
+    assert_eq!(status, 401);
+    let (status, body) = deliver(&base, "ping", "ping", SECRET).await;
+    assert_eq!((status, body["result"].as_str()), (200, Some("ignored")));
+    assert!(store.load_proposals().unwrap().is_empty());
+
+    let (status, body) = deliver(&base, "pull_request", "pull_request_opened", SECRET).await;
+    assert_eq!((status, body["result"].as_str()), (200, Some("applied")));
+    let proposal = store.proposal("7").unwrap().unwrap();
+    assert_eq!(
+        (
+            proposal.repository.as_str(),
+            proposal.pr_number,
+            proposal.tier.as_str(),
+            proposal.author.as_deref(),
+        ),
+        ("BTCDecoded/governance", 17, "standard", Some("alice"))
+    );
+    assert!(proposal.is_open());
+
+    deliver(&base, "pull_request", "pull_request_labeled", SECRET).await;
+    assert_eq!(store.proposal("7").unwrap().unwrap().tier, "emergency");
+    for name in [
+        "pull_request_review_requested",
+        "pull_request_other_repository",
+    ] {
+        let (status, body) = deliver(&base, "pull_request", name, SECRET).await;
+        assert_eq!((status, body["result"].as_str()), (200, Some("ignored")));
+    }
+    // Pull request 18 has no proposal label and none is linked to it
+    let (status, body) = deliver(
+        &base,
+        "pull_request",
+        "pull_request_closed_unmerged",
+        SECRET,
+    )
+    .await;
+    assert_eq!((status, body["result"].as_str()), (200, Some("unlinked")));
+    assert_eq!(store.load_proposals().unwrap().len(), 1);
+
+    deliver(&base, "pull_request", "pull_request_closed_merged", SECRET).await;
+    assert_eq!(
+        store.proposal("7").unwrap().unwrap().status,
+        ProposalStatus::Merged
+    );
+
+    assert_eq!(
+        receiver.counters(),
+        GithubCounters {
+            applied: 3,
+            bad_signature: 1,
+            ignored_events: 1,
+            ignored_actions: 1,
+            ignored_repositories: 1,
+            unlinked: 1,
+            ..Default::default()
+        }
+    );
+    // Not served once the connection drops
+    health.disconnected();
+    let (status, _) = deliver(&base, "pull_request", "pull_request_opened", SECRET).await;
+    assert_eq!(status, 503);
+    server.abort();
+    drop(store);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_changes_of_state_are_submitted_with_actions_allowed() {
+    let (dir, store) = store("actions");
+    let (health, base, server) = listen().await;
+    let node_api = Arc::new(MockNodeApi::new(100));
+    node_api.accept_actions();
+    let ipc = NodeApiIpc::new(node_api.clone()).with_actions_allowed(true);
+    let receiver = Arc::new(GithubReceiver::new(config(), Arc::clone(&store)).with_actions(ipc));
+    health.serve_github(Arc::clone(&receiver));
+
+    let (status, body) = deliver(&base, "pull_request", "pull_request_labeled", SECRET).await;
+    assert_eq!((status, body["result"].as_str()), (200, Some("applied")));
+    assert!(node_api.module_calls().is_empty());
+
+    let (status, body) = deliver(&base, "pull_request", "pull_request_closed_merged", SECRET).await;
+    assert_eq!((status, body["result"].as_str()), (200, Some("submitted")));
+    let calls = node_api.module_calls();
+    assert_eq!(calls.len(), 1);
+    let request: serde_json::Value = serde_json::from_slice(&calls[0].1).unwrap();
+    assert_eq!(request["name"], github::SYNC_ACTION);
+    assert_eq!(request["params"]["proposal_id"], "7");
+    assert_eq!(request["params"]["transition"], "merged");
+    // The node's events record the merge, not the delivery
+    let proposal = store.proposal("7").unwrap().unwrap();
+    assert_eq!((proposal.pr_number, proposal.is_open()), (17, true));
+    assert_eq!(
+        (receiver.counters().applied, receiver.counters().submitted),
+        (2, 1)
+    );
+    server.abort();
+    drop(store);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_disabled_without_secret() {
+    let health = Arc::new(Health::new(
+        HealthConfig::default(),
+        Arc::new(Shutdown::new()),
+        Arc::new(Heartbeat::new()),
+        Arc::new(EventSubscriptions::new([])),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+    let body = fixture("pull_request_opened");
+    let response = reqwest::Client::new()
+        .post(format!("{}/integrations/github", base))
+        .header("X-GitHub-Event", "pull_request")
+        .header(
+            "X-Hub-Signature-256",
+            github::signature(SECRET.as_bytes(), &body),
+        )
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    server.abort();
+}
@@ -0,0 +1,210 @@
+//! Health, readiness and metrics endpoints over HTTP
+
+mod common;
+
+use blvm_governance::build_info::BuildInfo;
+use blvm_governance::config::HealthConfig;
+use blvm_governance::health::{Health, Probe};
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::status::StatusCollector;
+use blvm_governance::status_report::StatusSources;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::sync::Arc;
+use std::time::Instant;
+
+fn proposal_created(proposal_id: &str) -> EventPayload {
+    EventPayload::GovernanceProposalCreated {
+        proposal_id: proposal_id.to_string(),
+        repository: "test/repo".to_string(),
+        pr_number: 1,
+        tier: "standard".to_string(),
+    }
+}
+
+/// What the health listener reports on for `node`'s connection, as main.rs builds it.
+fn sources(node: &MockNode, heartbeat: &Arc<Heartbeat>) -> StatusSources {
+    let module = &node.module;
+    StatusSources {
+        metrics: Arc::clone(&module.metrics),
+        checkpointer: Arc::clone(module.pipeline.checkpointer()),
+        events: Arc::clone(&module.events),
+        webhook: Arc::clone(&module.webhook_client),
+        registry: Arc::clone(&module.economic_nodes),
+        heartbeat: Arc::clone(heartbeat),
+        stream: Arc::clone(&module.stream),
+        config_reload: None,
+        memory: Arc::default(),
+        clock: Arc::default(),
+        errors: Arc::default(),
+        adoption: None,
+        participation: None,
+    }
+}
+
+/// Serve `health` on a free local port, returning its base URL.
+async fn serve(health: &Arc<Health>) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    (base, health.serve(listener))
+}
+
+async fn get(base: &str, path: &str) -> (u16, String) {
+    let response = reqwest::get(format!("{}{}", base, path)).await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.text().await.unwrap())
+}
+
+async fn probe(base: &str, path: &str) -> (u16, Probe) {
+    let (status, body) = get(base, path).await;
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_probes_follow_connection_and_shutdown() {
+    // A webhook nothing listens on
+    let config = GovernanceConfig {
+        webhook_url: Some("http://127.0.0.1:1/webhook".to_string()),
+        webhook_retry_count: 0,
+        ..Default::default()
+    };
+    let node = MockNode::start("health_probes", config).await;
+    let heartbeat = Arc::new(Heartbeat::new());
+    let collector = Arc::new(StatusCollector::new(Instant::now()));
+    collector.register(Arc::clone(&heartbeat));
+    let health = Arc::new(
+        Health::new(
+            HealthConfig {
+                webhook_failure_limit: 1,
+                ..Default::default()
+            },
+            Arc::clone(&node.module.shutdown),
+            Arc::clone(&heartbeat),
+            Arc::clone(&node.module.subscriptions),
+        )
+        .with_status(Arc::clone(&collector)),
+    );
+    let (base, server) = serve(&health).await;
+
+    let (status, healthz) = probe(&base, "/healthz").await;
+    assert_eq!(status, 200);
+    assert!(healthz.is_ok());
+    let (status, readyz) = probe(&base, "/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(readyz.checks["node"].detail, "not connected");
+    assert!(readyz.module.is_none());
+    // Not served without metrics enabled
+    assert_eq!(get(&base, "/metrics").await.0, 404);
+    let (status, version) = get(&base, "/version").await;
+    assert_eq!(status, 200);
+    let version: serde_json::Value = serde_json::from_str(&version).unwrap();
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(get(&base, "/errors").await, (200, r#"{"reports":[]}"#.to_string()));
+
+    health.connected(sources(&node, &heartbeat));
+    collector.connected(vec![Arc::clone(&node.module.webhook_client) as _]);
+    let (status, readyz) = probe(&base, "/readyz?detail").await;
+    assert_eq!(status, 200, "{:?}", readyz);
+    let module = readyz.module.unwrap();
+    assert!(module.connected);
+    assert_eq!(module.sections["heartbeat"]["consecutive_misses"], 0);
+    assert_eq!(module.sections["webhook"]["consecutive_failures"], 0);
+
+    node.send_event(EventType::GovernanceProposalCreated, proposal_created("1"))
+        .await;
+    assert_eq!(node.module.webhook_client.consecutive_failures(), 1);
+    let (status, readyz) = probe(&base, "/readyz").await;
+    assert_eq!(status, 503);
+    assert!(!readyz.checks["webhook"].ok);
+    assert!(readyz.checks["node"].ok && readyz.checks["registry_store"].ok);
+
+    // Not ready while a shutdown drains; the listener closes after it
+    node.module.shutdown.begin();
+    let (status, readyz) = probe(&base, "/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(readyz.checks["shutdown"].detail, "shutting down");
+    server.abort();
+    let _ = server.await;
+    assert!(reqwest::get(format!("{}/healthz", base)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_metrics_after_event_sequence() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_events: vec!["proposal_created".to_string()],
+        ..Default::default()
+    };
+    let node = MockNode::start("health_metrics", config).await;
+    let heartbeat = Arc::new(Heartbeat::new());
+    let collector = Arc::new(StatusCollector::new(Instant::now()));
+    collector.register(Arc::new(BuildInfo::current().clone()));
+    let health = Arc::new(
+        Health::new(
+            HealthConfig::default(),
+            Arc::clone(&node.module.shutdown),
+            Arc::clone(&heartbeat),
+            Arc::clone(&node.module.subscriptions),
+        )
+        .with_metrics(Arc::clone(&node.module.metrics))
+        .with_status(collector),
+    );
+    health.connected(sources(&node, &heartbeat));
+    let (base, _server) = serve(&health).await;
+
+    for id in ["1", "2"] {
+        node.send_event(EventType::GovernanceProposalCreated, proposal_created(id))
+            .await;
+        received.recv().await.unwrap();
+    }
+    node.send_event(
+        EventType::EconomicNodeRegistered,
+        EventPayload::EconomicNodeRegistered {
+            node_id: hex::encode([7u8; 32]),
+            node_type: "miner".to_string(),
+            hashpower_percent: Some(0.5),
+        },
+    )
+    .await;
+    let ipc = NodeApiIpc::new(node.node_api.clone()).with_metrics(Arc::clone(&node.module.metrics));
+    ipc.get_block_height().await.unwrap();
+
+    let (status, text) = get(&base, "/metrics").await;
+    assert_eq!(status, 200);
+    for line in [
+        "bllvm_governance_connected 1",
+        "bllvm_governance_events_received_total{event_type=\"GovernanceProposalCreated\"} 2",
+        "bllvm_governance_events_received_total{event_type=\"EconomicNodeRegistered\"} 1",
+        "bllvm_governance_events_received_total{event_type=\"NewBlock\"} 0",
+        "bllvm_governance_events_processed_total 3",
+        "bllvm_governance_webhook_deliveries_total{outcome=\"delivered\"} 2",
+        "bllvm_governance_webhook_deliveries_total{outcome=\"failed\"} 0",
+        "bllvm_governance_registry_nodes 1",
+        "bllvm_governance_event_queue_depth 0",
+        "bllvm_governance_events_spilled 0",
+        "bllvm_governance_memory_limit_bytes{component=\"event_queue\"} 0",
+        "bllvm_governance_node_requests_total{method=\"get_block_height\",outcome=\"ok\"} 1",
+        "bllvm_governance_node_request_duration_seconds_count{method=\"get_block_height\"} 1",
+        "bllvm_governance_error_reports_total{code=\"webhook_undeliverable\"} 0",
+    ] {
+        assert!(
+            text.lines().any(|l| l == line),
+            "{} missing from\n{}",
+            line,
+            text
+        );
+    }
+    let info = text
+        .lines()
+        .find(|l| l.starts_with("bllvm_governance_info{"))
+        .unwrap();
+    assert!(info.starts_with("bllvm_governance_info{schema_version=\"2\","));
+    assert!(info.contains(&format!("version=\"{}\"", env!("CARGO_PKG_VERSION"))));
+    assert!(info.ends_with("} 1"));
+    // No proposal or node ids in labels
+    assert!(!text.contains(&hex::encode([7u8; 32])));
+}
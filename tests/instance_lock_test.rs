@@ -0,0 +1,86 @@
+//! Two module processes against one data directory
+//!
+//! The processes are this test binary run again, with `HOLD_ENV` set, to run only
+//! `lock_holder`.
+#![cfg(unix)]
+
+use blvm_governance::storage::lock::{InstanceLock, LOCK_FILE};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+const HOLD_ENV: &str = "BLVM_TEST_LOCK_DIR";
+
+/// In a child process: lock the data directory named by `HOLD_ENV`, print `locked <pid>` and
+/// hold the lock until stdin closes, or print `failed <error>`. Does nothing otherwise.
+#[test]
+fn lock_holder() {
+    let Ok(dir) = std::env::var(HOLD_ENV) else {
+        return;
+    };
+    match InstanceLock::acquire(Path::new(&dir)) {
+        Ok(lock) => {
+            println!("locked {}", std::process::id());
+            let _ = std::io::stdin().read_line(&mut String::new());
+            drop(lock);
+        }
+        Err(e) => println!("failed {}", e),
+    }
+}
+
+fn spawn_instance(dir: &Path) -> Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["lock_holder", "--exact", "--nocapture", "--test-threads=1"])
+        .env(HOLD_ENV, dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// What the instance printed after `locked ` or `failed `; the test harness prints around it.
+fn outcome(stdout: &mut ChildStdout) -> String {
+    BufReader::new(stdout)
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| {
+            ["locked ", "failed "]
+                .iter()
+                .find_map(|prefix| line.find(prefix).map(|at| line[at..].to_string()))
+        })
+        .expect("instance exited without taking or failing to take the lock")
+}
+
+#[test]
+fn test_second_instance_is_refused() {
+    let dir = std::env::temp_dir().join(format!("blvm_instance_lock_test_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+
+    let mut first = spawn_instance(&dir);
+    assert_eq!(
+        outcome(first.stdout.as_mut().unwrap()),
+        format!("locked {}", first.id())
+    );
+
+    let mut second = spawn_instance(&dir);
+    let refused = outcome(second.stdout.as_mut().unwrap());
+    assert!(
+        refused.starts_with("failed ") && refused.contains(&format!("(pid {})", first.id())),
+        "{}",
+        refused
+    );
+    second.wait().unwrap();
+
+    // Killed without a chance to clean up: the lock file stays, the lock does not
+    first.kill().unwrap();
+    first.wait().unwrap();
+    assert!(dir.join(LOCK_FILE).exists());
+    let mut third = spawn_instance(&dir);
+    assert_eq!(
+        outcome(third.stdout.as_mut().unwrap()),
+        format!("locked {}", third.id())
+    );
+    drop(third.stdin.take());
+    assert!(third.wait().unwrap().success());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,117 @@
+//! Side effects recorded as intents, and carried out after a crash
+
+mod common;
+
+use blvm_governance::checkpoint::Checkpointer;
+use blvm_governance::economic_nodes::report::VETO_RESULT_INTENT;
+use blvm_governance::economic_nodes::{EconomicNodeRegistry, VetoTally};
+use blvm_governance::intent::IntentLog;
+use blvm_governance::pipeline::{EventHandler, Pipeline};
+use blvm_governance::trace;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_pending_intents_are_carried_out_on_restart() {
+    let dir = std::env::temp_dir().join(format!("blvm_intent_restart_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+
+    // Recorded while handling an event, then the module crashed
+    let trace_id = trace::new_id();
+    let tally = VetoTally {
+        proposal_id: "p1".to_string(),
+        total_weight: 100.0,
+        vetoing_weight: 40.0,
+        threshold_percent: 30.0,
+        commitment: "aa".to_string(),
+    };
+    let intents = IntentLog::open(&dir).unwrap();
+    trace::with_id(trace_id.clone(), async {
+        let change = serde_json::json!({
+            "event_type": "registry_activated",
+            "data": { "node_id": "aa" },
+        });
+        intents.begin("webhook", "notify", change).unwrap();
+        let tally = serde_json::to_value(&tally).unwrap();
+        intents
+            .begin("economic_nodes", VETO_RESULT_INTENT, tally)
+            .unwrap();
+        intents
+            .begin("clock", "adjust", serde_json::Value::Null)
+            .unwrap();
+    })
+    .await;
+    drop(intents);
+
+    // Restarted
+    let intents = Arc::new(IntentLog::open(&dir).unwrap());
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let node_api = Arc::new(MockNodeApi::new(100));
+    let webhook = Arc::new(
+        GovernanceWebhookClient::new(&config)
+            .await
+            .unwrap()
+            .with_intents(Arc::clone(&intents)),
+    );
+    let registry = Arc::new(
+        EconomicNodeRegistry::new(config.registry.clone(), node_api.clone())
+            .await
+            .unwrap()
+            .with_intents(Arc::clone(&intents)),
+    );
+    let pipeline = Pipeline::new(Arc::new(Checkpointer::open(&dir).unwrap()))
+        .with_handlers(vec![webhook.clone() as Arc<dyn EventHandler>, registry])
+        .with_intents(Arc::clone(&intents));
+    assert_eq!(pipeline.recover_intents(node_api.as_ref()).await, 2);
+
+    let delivered = received.recv().await.unwrap();
+    assert_eq!(delivered["event_type"], "registry_activated");
+    assert_eq!(delivered["data"]["node_id"], "aa");
+    assert_eq!(delivered["trace_id"], trace_id.as_str());
+    let submitted: Vec<serde_json::Value> = node_api
+        .module_calls()
+        .iter()
+        .filter(|(method, _)| method == "submit_veto_result")
+        .map(|(_, payload)| serde_json::from_slice(payload).unwrap())
+        .collect();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0]["proposal_id"], "p1");
+    assert_eq!(submitted[0]["threshold_crossed"], true);
+
+    // No handler here carries out the last one; it waits for the next start
+    let pending = intents.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].handler, "clock");
+    assert_eq!(pipeline.recover_intents(node_api.as_ref()).await, 0);
+    assert_eq!(IntentLog::open(&dir).unwrap().pending(), pending);
+
+    // A notification sent in the normal course is completed once sent
+    let event = ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: "2".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 2,
+            tier: "standard".to_string(),
+        },
+    });
+    webhook
+        .handle_event(&event, node_api.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(
+        received.recv().await.unwrap()["event_type"],
+        "proposal_created"
+    );
+    assert_eq!(intents.pending(), pending);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
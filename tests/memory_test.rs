@@ -0,0 +1,129 @@
+//! Memory budget of the event queue and caches under oversized blocks
+
+mod common;
+
+use blvm_governance::config::{EventQueueConfig, OverflowPolicy};
+use blvm_governance::event_queue::EventQueue;
+use blvm_governance::memory::{Component, MemoryBudget, MIN_BUDGET_BYTES};
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::shutdown::Shutdown;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload};
+use blvm_node::module::traits::EventType;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Size of the script of each synthetic transaction, so that a handful fill a share.
+const SCRIPT_BYTES: usize = 64 * 1024;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("blvm_memory_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+/// A block at `height` holding one transaction with an oversized input script.
+fn oversized_block(height: u64) -> blvm_protocol::Block {
+    let mut tx = common::spending_tx(blvm_protocol::OutPoint {
+        hash: [height as u8; 32],
+        index: 0,
+    });
+    let mut inputs = tx.inputs.to_vec();
+    inputs[0].script_sig = vec![height as u8; SCRIPT_BYTES];
+    tx.inputs = inputs.into();
+    common::block([height as u8; 32], vec![tx])
+}
+
+/// An event about `height` carrying an oversized field, as the queue counts it.
+fn oversized_event(height: u64) -> EventMessage {
+    EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: height.to_string(),
+            repository: "x".repeat(SCRIPT_BYTES),
+            pr_number: height,
+            tier: "standard".to_string(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_transaction_cache_stays_within_budget() {
+    let budget = MemoryBudget::new(MIN_BUDGET_BYTES);
+    let share = budget.share(Component::Transactions);
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone()).with_memory_budget(&budget);
+
+    for height in 0..64u64 {
+        let block = oversized_block(height);
+        let tx = block.transactions[0].clone();
+        let response = serde_json::json!({
+            "tx": tx,
+            "block_hash": hex::encode([height as u8 + 1; 32]),
+            "height": height,
+            "confirmations": 100 - height,
+        });
+        node_api.respond(
+            "get_transaction",
+            Ok(serde_json::to_vec(&response).unwrap()),
+        );
+        let txid = [height as u8 + 1; 32];
+        let info = ipc.get_transaction(&txid).await.unwrap().unwrap();
+        assert_eq!(info.tx, tx);
+
+        assert!(share.used() <= share.limit());
+        assert!(budget.used() <= budget.budget_bytes());
+    }
+    // Far more was looked up than fits, so the oldest were evicted
+    let usage = share.usage();
+    assert!(usage.used_bytes > usage.limit_bytes / 2);
+    assert!(usage.overflowed > 0);
+}
+
+#[tokio::test]
+async fn test_event_queue_spills_over_budget_and_keeps_order() {
+    let dir = temp_dir("spill");
+    let budget = MemoryBudget::new(MIN_BUDGET_BYTES);
+    let share = Arc::clone(budget.share(Component::EventQueue));
+    let shutdown = Arc::new(Shutdown::new());
+    let (queue, mut rx) = EventQueue::new(&EventQueueConfig {
+        capacity: 1000,
+        policy: OverflowPolicy::Backpressure,
+        ..Default::default()
+    });
+    let queue = queue.with_memory(Arc::clone(&share), Some(dir.clone()));
+
+    // No consumer yet: what does not fit in memory goes to disk
+    for height in 0..32u64 {
+        assert!(queue.send(oversized_event(height), shutdown.track()).await);
+        assert!(share.used() <= share.limit());
+        assert!(budget.used() <= budget.budget_bytes());
+    }
+    let stats = queue.stats();
+    assert!(stats.spilled > 0);
+    assert_eq!(stats.queued + stats.spilled, 32);
+    assert_eq!(share.usage().overflowed, stats.spilled as u64);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    // Everything comes back, in order, as the consumer releases memory
+    for height in 0..32u64 {
+        let queued = rx.recv().await.unwrap();
+        let EventPayload::GovernanceProposalCreated { pr_number, .. } = queued.event.payload else {
+            panic!("unexpected event {:?}", queued.event.event_type);
+        };
+        assert_eq!(pr_number, height);
+        assert!(share.used() <= share.limit());
+    }
+    // The feeding task removes the file once it finds it empty
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while queue.stats().spilled > 0 || std::fs::read_dir(&dir).unwrap().count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(share.used(), 0);
+    assert_eq!(shutdown.in_flight(), 0);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,115 @@
+//! End-to-end scenarios against the mock node harness
+
+mod common;
+
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn proposal_created(proposal_id: &str) -> EventPayload {
+    EventPayload::GovernanceProposalCreated {
+        proposal_id: proposal_id.to_string(),
+        repository: "test/repo".to_string(),
+        pr_number: 1,
+        tier: "standard".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_proposal_event_reaches_webhook_and_store() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..GovernanceConfig::default()
+    };
+    let node = MockNode::start("mock_node_proposal", config).await;
+
+    node.send_event(EventType::GovernanceProposalCreated, proposal_created("42"))
+        .await;
+
+    let delivered = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered["data"]["proposal_id"], "42");
+    // The id of the event span the delivery was made under
+    assert_eq!(delivered["trace_id"].as_str().unwrap().len(), 32);
+    assert!(node.node_api.published().contains(&EventType::WebhookSent));
+    let proposals = node.module.proposal_store.load_proposals().unwrap();
+    assert!(proposals.iter().any(|p| p.proposal_id == "42"));
+    assert_eq!(node.module.pipeline.checkpointer().sequence(), 1);
+}
+
+#[tokio::test]
+async fn test_registration_event_updates_registry() {
+    let node = MockNode::start("mock_node_registry", GovernanceConfig::default()).await;
+    let node_id = [7u8; 32];
+
+    node.send_event(
+        EventType::EconomicNodeRegistered,
+        EventPayload::EconomicNodeRegistered {
+            node_id: hex::encode(node_id),
+            node_type: "miner".to_string(),
+            hashpower_percent: Some(0.5),
+        },
+    )
+    .await;
+
+    assert_eq!(node.module.economic_nodes.node_count().await, 1);
+    assert!(node
+        .module
+        .economic_nodes
+        .get_node(&node_id)
+        .await
+        .is_some());
+
+    // Without a webhook URL the webhook handler takes no events, and none are sent
+    node.send_event(EventType::GovernanceProposalCreated, proposal_created("1"))
+        .await;
+    assert_eq!(node.module.webhook_client.delivery_counts().delivered, 0);
+}
+
+#[tokio::test]
+async fn test_new_block_advances_tip() {
+    let node = MockNode::start("mock_node_tip", GovernanceConfig::default()).await;
+    // Read from the node at startup
+    assert_eq!(node.module.tip.current().unwrap().height, 100);
+
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [9u8; 32],
+            height: 101,
+        },
+    )
+    .await;
+    let tip = node.module.tip.current().unwrap();
+    assert_eq!((tip.hash, tip.height), ([9u8; 32], 101));
+}
+
+#[tokio::test]
+async fn test_vote_invalidates_cached_proposal() {
+    let node = MockNode::start("mock_node_proposal_cache", GovernanceConfig::default()).await;
+    let ipc = NodeApiIpc::new(node.node_api.clone())
+        .with_proposal_cache(Arc::clone(&node.module.proposal_cache));
+    node.node_api.add_proposal(common::proposal("3"));
+    assert_eq!(ipc.get_proposal("3").await.unwrap().author, "alice");
+
+    let mut edited = common::proposal("3");
+    edited.title = "Edited".to_string();
+    node.node_api.add_proposal(edited);
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "3".to_string(),
+            voter: "bob".to_string(),
+            vote: "approve".to_string(),
+        },
+    )
+    .await;
+    assert_eq!(ipc.get_proposal("3").await.unwrap().title, "Edited");
+}
@@ -0,0 +1,227 @@
+//! Economic nodes controlled by threshold key sets
+
+mod common;
+
+use blvm_governance::config::RegistryConfig;
+use blvm_governance::economic_nodes::reserve::message_digest;
+use blvm_governance::economic_nodes::{
+    multisig, EconomicNodeRegistry, KeySet, KeySignature, SignedAt,
+};
+use common::MockNodeApi;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use std::sync::Arc;
+
+fn sign(sk: &SecretKey, key_index: u32, message: &str) -> KeySignature {
+    let digest = Message::from_digest(message_digest(message));
+    KeySignature {
+        key_index,
+        signature: Secp256k1::new()
+            .sign_ecdsa(&digest, sk)
+            .serialize_compact()
+            .to_vec(),
+    }
+}
+
+fn public_key(sk: &SecretKey) -> Vec<u8> {
+    PublicKey::from_secret_key(&Secp256k1::new(), sk)
+        .serialize()
+        .to_vec()
+}
+
+/// The key [`MockNodeApi::add_reserve`] signs `node_id`'s claims with.
+fn reserve_key(node_id: &[u8; 32]) -> SecretKey {
+    SecretKey::from_slice(&message_digest(&hex::encode(node_id))).unwrap()
+}
+
+async fn registry(node_api: &Arc<MockNodeApi>) -> EconomicNodeRegistry {
+    EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_registration_signs_the_claimed_weight() {
+    let node_api = Arc::new(MockNodeApi::new(500));
+    let registry = registry(&node_api).await;
+    let id = [1u8; 32];
+    let sk = SecretKey::from_slice(&[3u8; 32]).unwrap();
+    let keys = KeySet {
+        public_keys: vec![public_key(&sk)],
+        threshold: 1,
+    };
+    let signed = sign(
+        &sk,
+        0,
+        &multisig::registration_message(&id, &keys, Some(5.0)),
+    );
+
+    let inflated = registry
+        .register_multisig(
+            None,
+            &hex::encode(id),
+            "miner",
+            Some(50.0),
+            keys.clone(),
+            std::slice::from_ref(&signed),
+        )
+        .await;
+    assert!(inflated.is_err());
+    assert!(registry
+        .register_multisig(None, &hex::encode(id), "miner", Some(5.0), keys, &[signed])
+        .await
+        .unwrap());
+
+    // Unsigned registrations, as from IPC or node events, cannot change it
+    assert!(registry
+        .register(&hex::encode(id), "miner", Some(50.0), None)
+        .await
+        .is_err());
+    let node = registry.get_nodes_for_test().await[&id].clone();
+    assert_eq!(node.hashpower_percentage, 5.0);
+}
+
+#[tokio::test]
+async fn test_key_set_needs_the_existing_key() {
+    let node_api = Arc::new(MockNodeApi::new(500));
+    let registry = registry(&node_api).await;
+    let other = SecretKey::from_slice(&[4u8; 32]).unwrap();
+
+    // Registered without any key: nothing to show control with
+    let keyless = [1u8; 32];
+    registry
+        .register(&hex::encode(keyless), "exchange", None, None)
+        .await
+        .unwrap();
+    let keys = KeySet {
+        public_keys: vec![public_key(&other)],
+        threshold: 1,
+    };
+    let message = multisig::registration_message(&keyless, &keys, None);
+    assert!(registry
+        .register_multisig(
+            None,
+            &hex::encode(keyless),
+            "exchange",
+            None,
+            keys,
+            &[sign(&other, 0, &message)]
+        )
+        .await
+        .is_err());
+
+    // Registered with a reserve claim: its key must join the set and sign
+    let claimed = [2u8; 32];
+    let claim = node_api.add_reserve(&claimed, 1_000);
+    registry
+        .register(&hex::encode(claimed), "exchange", None, Some(claim))
+        .await
+        .unwrap();
+    let owner = reserve_key(&claimed);
+    let keys = KeySet {
+        public_keys: vec![public_key(&owner), public_key(&other)],
+        threshold: 1,
+    };
+    let message = multisig::registration_message(&claimed, &keys, None);
+    assert!(registry
+        .register_multisig(
+            None,
+            &hex::encode(claimed),
+            "exchange",
+            None,
+            keys.clone(),
+            &[sign(&other, 1, &message)]
+        )
+        .await
+        .is_err());
+    assert!(registry
+        .register_multisig(
+            None,
+            &hex::encode(claimed),
+            "exchange",
+            None,
+            keys,
+            &[sign(&owner, 0, &message)]
+        )
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_signed_actions_are_bound_to_a_recent_height() {
+    let node_api = Arc::new(MockNodeApi::new(500));
+    let registry = registry(&node_api).await;
+    let id = [1u8; 32];
+    let node = hex::encode(id);
+    let sk = SecretKey::from_slice(&[3u8; 32]).unwrap();
+    let keys = KeySet {
+        public_keys: vec![public_key(&sk)],
+        threshold: 1,
+    };
+    let message = multisig::registration_message(&id, &keys, None);
+    registry
+        .register_multisig(
+            None,
+            &node,
+            "exchange",
+            None,
+            keys,
+            &[sign(&sk, 0, &message)],
+        )
+        .await
+        .unwrap();
+    let signed_at = |height: u64, message: String| SignedAt {
+        height,
+        signatures: vec![sign(&sk, 0, &message)],
+    };
+
+    // Unsigned, too old, ahead of the tip, or signed for another height
+    assert!(registry.veto("p1", &node, "unsafe", None).await.is_err());
+    let stale = signed_at(355, multisig::veto_message(&id, "p1", "unsafe", 355));
+    assert!(registry
+        .veto("p1", &node, "unsafe", Some(&stale))
+        .await
+        .is_err());
+    let ahead = signed_at(501, multisig::veto_message(&id, "p1", "unsafe", 501));
+    assert!(registry
+        .veto("p1", &node, "unsafe", Some(&ahead))
+        .await
+        .is_err());
+    let relabelled = signed_at(500, multisig::veto_message(&id, "p1", "unsafe", 400));
+    assert!(registry
+        .veto("p1", &node, "unsafe", Some(&relabelled))
+        .await
+        .is_err());
+    assert_eq!(registry.get_nodes_for_test().await[&id].veto_count, 0);
+
+    let veto = signed_at(356, multisig::veto_message(&id, "p1", "unsafe", 356));
+    registry
+        .veto("p1", &node, "unsafe", Some(&veto))
+        .await
+        .unwrap();
+    assert_eq!(registry.get_nodes_for_test().await[&id].veto_count, 1);
+
+    let revocation = signed_at(500, multisig::revocation_message(&id, "p1", 500));
+    assert!(registry
+        .revoke_veto("p1", &node, Some(&revocation))
+        .await
+        .unwrap());
+
+    let new_keys = KeySet {
+        public_keys: vec![public_key(&SecretKey::from_slice(&[5u8; 32]).unwrap())],
+        threshold: 1,
+    };
+    let stale = signed_at(300, multisig::rotation_message(&id, &new_keys, 300));
+    assert!(registry
+        .rotate_keys(&node, new_keys.clone(), &stale)
+        .await
+        .is_err());
+    let rotation = signed_at(500, multisig::rotation_message(&id, &new_keys, 500));
+    registry
+        .rotate_keys(&node, new_keys.clone(), &rotation)
+        .await
+        .unwrap();
+    assert_eq!(
+        registry.get_nodes_for_test().await[&id].keys,
+        Some(new_keys)
+    );
+}
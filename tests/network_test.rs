@@ -0,0 +1,146 @@
+//! The node's network, checked against the data directory's and the webhook's
+
+mod common;
+
+use blvm_governance::error::GovernanceError;
+use blvm_governance::error_report::ErrorReporter;
+use blvm_governance::network;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::storage::DataDir;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blvm_network_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+fn created() -> ModuleMessage {
+    ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    })
+}
+
+#[test]
+fn test_data_directory_is_stamped_and_checked() {
+    let root = data_dir("stamp");
+    let layout = DataDir::open(&root).unwrap();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        layout.store(),
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+
+    // A fresh directory takes the node's network
+    assert_eq!(network::stored(&layout, &db).unwrap(), None);
+    let status = network::check(&layout, &db, "testnet", false).unwrap();
+    assert_eq!(status.network.as_deref(), Some("testnet"));
+    assert_eq!(status.migrated_from, None);
+    assert_eq!(
+        std::fs::read_to_string(layout.network()).unwrap(),
+        "testnet\n"
+    );
+    assert_eq!(network::stored_in(&db).unwrap().as_deref(), Some("testnet"));
+
+    // Another network is refused, and the stamp left alone
+    match network::check(&layout, &db, "mainnet", false) {
+        Err(GovernanceError::NetworkMismatch {
+            expected, actual, ..
+        }) => assert_eq!((expected.as_str(), actual.as_str()), ("testnet", "mainnet")),
+        other => panic!("expected a network mismatch, got {:?}", other),
+    }
+    assert_eq!(
+        network::stored(&layout, &db).unwrap().as_deref(),
+        Some("testnet")
+    );
+
+    // Unless forced
+    let status = network::check(&layout, &db, "mainnet", true).unwrap();
+    assert_eq!(status.migrated_from.as_deref(), Some("testnet"));
+    assert_eq!(
+        network::stored(&layout, &db).unwrap().as_deref(),
+        Some("mainnet")
+    );
+    assert_eq!(
+        network::check(&layout, &db, "mainnet", false)
+            .unwrap()
+            .migrated_from,
+        None
+    );
+
+    // A file that disagrees with the store is inconsistent
+    std::fs::write(layout.network(), "signet\n").unwrap();
+    assert!(matches!(
+        network::check(&layout, &db, "mainnet", true),
+        Err(GovernanceError::Storage(_))
+    ));
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_node_reports_its_network() {
+    let node_api = Arc::new(MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    assert_eq!(ipc.get_network().await.unwrap(), "regtest");
+    node_api.set_network("mainnet");
+    assert_eq!(ipc.get_network().await.unwrap(), "mainnet");
+}
+
+#[tokio::test]
+async fn test_webhook_of_another_network_is_blocked() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_network: Some("mainnet".to_string()),
+        ..Default::default()
+    };
+    let node_api = MockNodeApi::new(100);
+    let errors = Arc::new(ErrorReporter::default());
+
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_error_reporter(Arc::clone(&errors))
+        .with_network(Some("testnet".to_string()));
+    client.handle_event(&created(), &node_api).await.unwrap();
+    let counts = client.delivery_counts();
+    assert_eq!((counts.delivered, counts.failed), (0, 1));
+    assert_eq!(errors.counts()["webhook_network_mismatch"], 1);
+    assert!(matches!(
+        client.send_test("proposal_created").await,
+        Err(GovernanceError::NetworkMismatch { .. })
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(received.try_recv().is_err());
+
+    // On its network, the payload says which it is
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_network(Some("mainnet".to_string()));
+    client.handle_event(&created(), &node_api).await.unwrap();
+    let payload = tokio::time::timeout(Duration::from_secs(1), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(payload["network"], "mainnet");
+    assert_eq!(client.delivery_counts().delivered, 1);
+}
@@ -0,0 +1,845 @@
+//! NodeApiIpc requests against the mock node
+
+mod common;
+
+use blvm_governance::audit::ActionAudit;
+use blvm_governance::chain_work::Work;
+use blvm_governance::checkpoint::{self, Checkpoint};
+use blvm_governance::error::GovernanceError;
+use blvm_governance::ipc_metrics::{IpcMethod, IpcMetrics};
+use blvm_governance::node_api::{
+    ActionOutcome, ChainTip, FeeCache, FeeRate, NodeApiIpc, Priority, RequestLimiter, RetryPolicy,
+};
+use blvm_governance::GovernanceConfig;
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_get_transaction_lookups() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    let txid = [5u8; 32];
+
+    // Unknown or unconfirmed
+    node_api.respond("get_transaction", Ok(b"null".to_vec()));
+    assert!(ipc.get_transaction(&txid).await.unwrap().is_none());
+    let (method, payload) = node_api.module_calls()[0].clone();
+    assert_eq!(method, "get_transaction");
+    let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(request["txid"], hex::encode(txid));
+
+    // A node without a transaction index
+    for error in [
+        "transaction index not enabled (txindex=0)",
+        "unknown method",
+    ] {
+        node_api.respond("get_transaction", Err(error.to_string()));
+        assert!(matches!(
+            ipc.get_transaction(&txid).await,
+            Err(GovernanceError::NotIndexed { .. })
+        ));
+    }
+
+    // Other failures are passed through
+    node_api.respond("get_transaction", Err("internal error".to_string()));
+    assert!(matches!(
+        ipc.get_transaction(&txid).await,
+        Err(GovernanceError::NodeRejected { .. })
+    ));
+
+    // No module serves the lookup: the node's store has it, in a recent block
+    node_api.respond(
+        "get_transaction",
+        Err("Method 'get_transaction' not found in any module".to_string()),
+    );
+    assert!(ipc.get_transaction(&txid).await.unwrap().is_none());
+    serve_chain(&node_api, 101);
+    let spend = |hash| common::spending_tx(blvm_protocol::OutPoint { hash, index: 0 });
+    let tx = spend([6u8; 32]);
+    node_api.add_transaction(txid, tx.clone());
+    node_api.add_block(98, [99u8; 32], common::block([98u8; 32], vec![tx.clone()]));
+    let info = ipc.get_transaction(&txid).await.unwrap().unwrap();
+    assert_eq!(info.tx, tx);
+    assert_eq!(
+        (info.block_hash, info.height, info.confirmations),
+        ([99u8; 32], 98, 3)
+    );
+
+    // Buried deeper than it is searched for
+    let old = [7u8; 32];
+    node_api.add_transaction(old, spend([7u8; 32]));
+    assert!(matches!(
+        ipc.get_transaction(&old).await,
+        Err(GovernanceError::NotIndexed { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_get_blocks_pipelined_in_order() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let mut hashes = Vec::new();
+    for height in 0..32u64 {
+        let hash = [height as u8 + 1; 32];
+        let prev = [height as u8; 32];
+        node_api.add_block(height, hash, common::block(prev, Vec::new()));
+        hashes.push(hash);
+    }
+    let unknown = [0xffu8; 32];
+    hashes.insert(5, unknown);
+    let latency = Duration::from_millis(20);
+    node_api.set_latency(latency);
+    let ipc = NodeApiIpc::new(node_api.clone()).with_batch_concurrency(8);
+
+    let start = Instant::now();
+    let blocks = ipc.get_blocks(&hashes).await;
+    let pipelined = start.elapsed();
+    assert_eq!(blocks.len(), hashes.len());
+    for (i, block) in blocks.into_iter().enumerate() {
+        let block = block.unwrap();
+        if hashes[i] == unknown {
+            assert!(block.is_none());
+        } else {
+            assert_eq!(block.unwrap().header.prev_block_hash[0] + 1, hashes[i][0]);
+        }
+    }
+    // 33 round trips one at a time would take at least 660ms
+    let serial = latency * hashes.len() as u32;
+    assert!(
+        pipelined < serial / 3,
+        "pipelined fetch took {:?}, serial would take {:?}",
+        pipelined,
+        serial
+    );
+
+    let by_height = ipc.get_blocks_by_height(30..34).await;
+    let found: Vec<bool> = by_height.iter().map(|b| matches!(b, Ok(Some(_)))).collect();
+    assert_eq!(found, vec![true, true, false, false]);
+    assert_eq!(
+        by_height[0]
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .header
+            .prev_block_hash,
+        [30u8; 32]
+    );
+}
+
+#[tokio::test]
+async fn test_retries_transient_failures() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let hash = [1u8; 32];
+    node_api.add_block(100, hash, common::block([0u8; 32], Vec::new()));
+    let ipc = NodeApiIpc::new(node_api.clone()).with_retry_policy(RetryPolicy {
+        attempts: 3,
+        backoff: Duration::from_millis(10),
+    });
+
+    // Fails twice, then succeeds
+    node_api.fail_next("get_block", &["connection reset", "broken pipe"]);
+    assert!(ipc.get_block(&hash).await.unwrap().is_some());
+
+    // Still failing after the last attempt
+    node_api.fail_next("get_block", &["connection reset"; 3]);
+    match ipc.get_block(&hash).await {
+        Err(GovernanceError::RetriesExhausted { attempts, last, .. }) => {
+            assert_eq!(attempts, 3);
+            assert!(last.to_string().contains("connection reset"));
+        }
+        other => panic!("expected RetriesExhausted, got {:?}", other.map(|_| ())),
+    }
+
+    // Fatal errors are not retried
+    node_api.fail_next("get_block", &["unknown method", "connection reset"]);
+    assert!(matches!(
+        ipc.get_block(&hash).await,
+        Err(GovernanceError::NodeRejected { .. })
+    ));
+    assert_eq!(node_api.pending_failures("get_block"), 1);
+}
+
+/// Name of `error`'s variant.
+fn variant(error: &GovernanceError) -> &'static str {
+    match error {
+        GovernanceError::IpcDisconnected { .. } => "IpcDisconnected",
+        GovernanceError::IpcTimeout { .. } => "IpcTimeout",
+        GovernanceError::NodeRejected { .. } => "NodeRejected",
+        GovernanceError::NotFound { .. } => "NotFound",
+        GovernanceError::Serialization { .. } => "Serialization",
+        _ => "other",
+    }
+}
+
+#[tokio::test]
+async fn test_node_failures_are_typed() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone()).with_retry_policy(RetryPolicy {
+        attempts: 1,
+        backoff: Duration::from_millis(1),
+    });
+    let cases = [
+        ("connection reset by peer", "IpcDisconnected"),
+        ("request timed out", "IpcTimeout"),
+        ("block not found", "NotFound"),
+        ("code -8: invalid parameter", "NodeRejected"),
+    ];
+    for (message, expected) in cases {
+        node_api.fail_next("get_block", &[message]);
+        let error = ipc.get_block(&[1u8; 32]).await.unwrap_err();
+        assert_eq!(variant(&error), expected, "{}", message);
+
+        node_api.respond("list_economic_nodes", Err(message.to_string()));
+        let error = ipc.list_economic_nodes().await.unwrap_err();
+        assert_eq!(variant(&error), expected, "{}", message);
+    }
+    node_api.fail_next("get_block", &["code -8: invalid parameter"]);
+    assert!(matches!(
+        ipc.get_block(&[1u8; 32]).await,
+        Err(GovernanceError::NodeRejected { code: Some(-8), .. })
+    ));
+
+    // Responses that cannot be decoded
+    node_api.respond("list_economic_nodes", Ok(b"{".to_vec()));
+    let error = ipc.list_economic_nodes().await.unwrap_err();
+    assert_eq!(variant(&error), "Serialization");
+    assert!(error
+        .to_string()
+        .starts_with("list_economic_nodes: invalid JSON"));
+}
+
+#[tokio::test]
+async fn test_retries_stay_within_request_timeout() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.set_latency(Duration::from_millis(40));
+    node_api.fail_next("get_block", &["connection reset"; 50]);
+    let timeout = Duration::from_millis(200);
+    let ipc = NodeApiIpc::new(node_api.clone())
+        .with_timeout(timeout)
+        .with_retry_policy(RetryPolicy {
+            attempts: 50,
+            backoff: Duration::from_millis(10),
+        });
+
+    let start = Instant::now();
+    let result = ipc.get_block(&[1u8; 32]).await;
+    assert!(
+        start.elapsed() < timeout + Duration::from_millis(100),
+        "retries took {:?}",
+        start.elapsed()
+    );
+    assert!(matches!(
+        result,
+        Err(GovernanceError::RetriesExhausted { .. } | GovernanceError::NodeApiTimeout { .. })
+    ));
+    assert!(node_api.pending_failures("get_block") > 40);
+}
+
+#[tokio::test]
+async fn test_tip_tracking() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    assert_eq!(ipc.current_tip(), None);
+
+    node_api.set_tip([1u8; 32], 100);
+    let tip = ipc.get_best_block().await.unwrap();
+    assert_eq!(
+        tip,
+        ChainTip {
+            hash: [1u8; 32],
+            height: 100
+        }
+    );
+    assert_eq!(ipc.current_tip(), Some(tip));
+
+    // Announced blocks move the tip without asking the node, for every client sharing it
+    let other = NodeApiIpc::new(node_api.clone()).with_tip_tracker(Arc::clone(ipc.tip_tracker()));
+    other.tip_tracker().observe([2u8; 32], 101);
+    assert_eq!(ipc.current_tip().unwrap().height, 101);
+
+    // A reorg to a shorter chain lowers it
+    node_api.set_tip([3u8; 32], 99);
+    assert_eq!(ipc.get_best_block().await.unwrap().height, 99);
+    assert_eq!(
+        other.current_tip(),
+        Some(ChainTip {
+            hash: [3u8; 32],
+            height: 99
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_get_proposal_cached_until_invalidated() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    node_api.add_proposal(common::proposal("7"));
+
+    let details = ipc.get_proposal("7").await.unwrap();
+    assert_eq!(details, common::proposal("7"));
+    let request: serde_json::Value = serde_json::from_slice(&node_api.module_calls()[0].1).unwrap();
+    assert_eq!(request["proposal_id"], "7");
+
+    // Served from the cache until a vote or merge for it
+    let mut merged = common::proposal("7");
+    merged.status = "merged".to_string();
+    node_api.add_proposal(merged);
+    assert_eq!(ipc.get_proposal("7").await.unwrap().status, "open");
+    assert_eq!(node_api.called().len(), 1);
+    ipc.proposal_cache().invalidate("7");
+    assert_eq!(ipc.get_proposal("7").await.unwrap().status, "merged");
+    assert_eq!(node_api.called().len(), 2);
+
+    // Unknown, whether the node answers null or fails
+    assert!(matches!(
+        ipc.get_proposal("8").await,
+        Err(GovernanceError::ProposalNotFound { proposal_id }) if proposal_id == "8"
+    ));
+    node_api.respond("get_proposal", Err("proposal 9 not found".to_string()));
+    assert!(matches!(
+        ipc.get_proposal("9").await,
+        Err(GovernanceError::ProposalNotFound { .. })
+    ));
+
+    // No module serves proposals: not a missing proposal
+    node_api.respond(
+        "get_proposal",
+        Err("Method 'get_proposal' not found in any module".to_string()),
+    );
+    assert!(matches!(
+        ipc.get_proposal("10").await,
+        Err(GovernanceError::Unsupported { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_submit_actions_audited() {
+    let dir = std::env::temp_dir().join(format!("blvm_action_audit_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let audit = Arc::new(ActionAudit::open(Arc::clone(&db)).unwrap());
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let veto = |ipc: NodeApiIpc| async move {
+        ipc.submit_veto("7", &"ab".repeat(32), "3045", "too risky")
+            .await
+    };
+
+    // Off by default: nothing is sent
+    let ipc = NodeApiIpc::new(node_api.clone()).with_action_audit(Arc::clone(&audit));
+    assert!(matches!(
+        veto(ipc.clone()).await,
+        Err(GovernanceError::ActionsDisabled { action }) if action == "veto"
+    ));
+    assert!(node_api.called().is_empty());
+    assert!(audit.entries().is_empty());
+
+    let ipc = ipc.with_actions_allowed(true);
+    node_api.accept_actions();
+    assert!(veto(ipc.clone()).await.unwrap().accepted);
+    let request: serde_json::Value = serde_json::from_slice(&node_api.module_calls()[0].1).unwrap();
+    assert_eq!(request["type"], "veto");
+    assert_eq!(request["proposal_id"], "7");
+    assert_eq!(request["reason"], "too risky");
+
+    node_api.reject_actions("not registered");
+    assert_eq!(
+        veto(ipc.clone()).await.unwrap(),
+        ActionOutcome {
+            accepted: false,
+            reason: Some("not registered".to_string())
+        }
+    );
+
+    // A failed submission is not retried, since the node may have applied it
+    node_api.respond(
+        "submit_governance_action",
+        Err("connection reset".to_string()),
+    );
+    assert!(veto(ipc.clone()).await.is_err());
+    assert_eq!(node_api.called().len(), 3);
+
+    // No module takes governance actions: unsupported, not a missing proposal
+    node_api.respond(
+        "submit_governance_action",
+        Err("Method 'submit_governance_action' not found in any module".to_string()),
+    );
+    assert!(matches!(
+        veto(ipc.clone()).await,
+        Err(GovernanceError::Unsupported { .. })
+    ));
+
+    // A dry run neither sends nor records anything
+    let outcome = veto(ipc.clone().with_dry_run(true)).await.unwrap();
+    assert!(!outcome.accepted);
+    assert_eq!(node_api.called().len(), 4);
+
+    // Every submission is in the audit trail, with the full request and response
+    let entries = audit.entries();
+    assert_eq!(entries.len(), 4);
+    assert!(entries.iter().all(|e| e.action == "veto"));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&entries[0].request).unwrap(),
+        request
+    );
+    assert_eq!(entries[0].response.as_deref(), Some(r#"{"accepted":true}"#));
+    assert!(entries[1]
+        .response
+        .as_deref()
+        .unwrap()
+        .contains("not registered"));
+    assert_eq!(entries[2].response, None);
+    assert!(entries[2]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("connection reset"));
+    assert_eq!(ActionAudit::open(db).unwrap().entries(), entries);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_op_return_funded_and_broadcast() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let audit = Arc::new(ActionAudit::default());
+    let ipc = NodeApiIpc::new(node_api.clone()).with_action_audit(Arc::clone(&audit));
+    let rate = FeeRate::from_sat_per_vbyte(3);
+
+    // Off by default: nothing is sent
+    assert!(matches!(
+        ipc.fund_op_return(b"data", rate).await,
+        Err(GovernanceError::ActionsDisabled { action }) if action == "fund_op_return"
+    ));
+    assert!(node_api.called().is_empty());
+
+    let ipc = ipc.with_actions_allowed(true);
+    let funded = serde_json::json!({
+        "txid": hex::encode([4u8; 32]),
+        "hex": "0100",
+        "fee_sats": 540,
+        "vsize": 180,
+    });
+    node_api.respond("fund_op_return", Ok(serde_json::to_vec(&funded).unwrap()));
+    let tx = ipc.fund_op_return(b"data", rate).await.unwrap();
+    assert_eq!((tx.txid, tx.fee_sats, tx.vsize), ([4u8; 32], 540, 180));
+    let request: serde_json::Value = serde_json::from_slice(&node_api.module_calls()[0].1).unwrap();
+    assert_eq!(request["data"], hex::encode(b"data"));
+    assert_eq!(request["sat_per_kvb"], 3000);
+
+    ipc.broadcast_transaction(&tx).await.unwrap();
+    node_api.respond("broadcast_transaction", Err("connection reset".to_string()));
+    assert!(ipc.broadcast_transaction(&tx).await.is_err());
+    // Not retried, and both broadcasts are in the audit trail
+    assert_eq!(node_api.lookup_count("broadcast_transaction"), 2);
+    let entries = audit.entries();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.action == "broadcast_transaction"));
+    assert!(entries[0].request.contains("0100"));
+    assert!(entries[1]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("connection reset"));
+
+    // An empty wallet
+    node_api.respond(
+        "fund_op_return",
+        Err("Insufficient funds: 0 sats available".to_string()),
+    );
+    assert!(matches!(
+        ipc.fund_op_return(b"data", rate).await,
+        Err(GovernanceError::InsufficientFunds { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_interactive_request_not_starved_by_bulk() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let hash = [1u8; 32];
+    node_api.add_block(100, hash, common::block([0u8; 32], Vec::new()));
+    node_api.set_latency(Duration::from_millis(100));
+    let limiter = Arc::new(RequestLimiter::new(16, 4));
+    let metrics = Arc::new(IpcMetrics::new());
+    let ipc = NodeApiIpc::new(node_api.clone())
+        .with_in_flight(Arc::clone(&limiter))
+        .with_metrics(Arc::clone(&metrics));
+
+    // A backfill with 100 block requests queued or in flight
+    let bulk = ipc.bulk();
+    let backfill = tokio::spawn(async move {
+        let requests = (0..100).map(|_| bulk.get_block(&hash));
+        futures::future::join_all(requests).await
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(limiter.available(Priority::Bulk), 0);
+    assert_eq!(limiter.available(Priority::Interactive), 4);
+
+    let start = Instant::now();
+    ipc.get_chain_info().await.unwrap();
+    assert!(
+        start.elapsed() < Duration::from_millis(50),
+        "interactive request took {:?}",
+        start.elapsed()
+    );
+    assert!(!backfill.is_finished());
+
+    let results = backfill.await.unwrap();
+    assert!(results.iter().all(|r| matches!(r, Ok(Some(_)))));
+    let waits = metrics.snapshot().queue_wait;
+    assert_eq!(waits["bulk"].requests, 100);
+    assert!(waits["bulk"].p99_ms.unwrap_or(u64::MAX) >= 500);
+    assert_eq!(waits["interactive"].requests, 1);
+    assert_eq!(waits["interactive"].p99_ms, Some(1));
+}
+
+/// Request `method` from the node through `ipc`, discarding the answer.
+async fn request(ipc: &NodeApiIpc, method: &str) -> Result<(), GovernanceError> {
+    let hash = [1u8; 32];
+    match method {
+        "get_block_height" => ipc.get_block_height().await.map(drop),
+        "get_chain_info" => ipc.get_chain_info().await.map(drop),
+        "get_block" => ipc.get_block(&hash).await.map(drop),
+        "get_block_header" => ipc.get_block_header(&hash).await.map(drop),
+        "get_transaction" => ipc.get_transaction(&hash).await.map(drop),
+        "get_mempool_transactions" => ipc.get_mempool_txids().await.map(drop),
+        "get_utxo" => {
+            let outpoint = blvm_protocol::OutPoint { hash, index: 0 };
+            ipc.get_utxo(&outpoint).await.map(drop)
+        }
+        "get_proposal" => ipc.get_proposal("1").await.map(drop),
+        _ => unreachable!("{}", method),
+    }
+}
+
+#[tokio::test]
+async fn test_per_method_timeouts() {
+    let table = [
+        ("get_block_height", "50ms", 50),
+        ("get_chain_info", "50ms", 50),
+        ("get_block", "150ms", 150),
+        ("get_block_header", "80ms", 80),
+        ("get_transaction", "100ms", 100),
+        ("get_mempool_transactions", "100ms", 100),
+        ("get_utxo", "50ms", 50),
+        ("get_proposal", "120ms", 120),
+    ];
+    let mut config = GovernanceConfig {
+        nodeapi_timeouts: table
+            .iter()
+            .map(|(method, timeout, _)| (method.to_string(), timeout.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        ..GovernanceConfig::default()
+    };
+    // Unknown methods and unreadable durations are skipped
+    config
+        .nodeapi_timeouts
+        .insert("get_everything".to_string(), "1s".to_string());
+    config
+        .nodeapi_timeouts
+        .insert("get_mempool_entry".to_string(), "soon".to_string());
+    let timeouts = config.request_timeouts();
+    assert_eq!(
+        timeouts.get(IpcMethod::GetMempoolEntry),
+        Duration::from_secs(5)
+    );
+    assert_eq!(
+        timeouts.get(IpcMethod::GetBlock),
+        Duration::from_millis(150)
+    );
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let ipc = NodeApiIpc::new(node_api.clone()).with_timeouts(timeouts);
+    for (method, _, timeout_ms) in table {
+        node_api.slow_down(method, Duration::from_millis(300));
+        let start = Instant::now();
+        let result = request(&ipc, method).await;
+        match result {
+            Err(GovernanceError::NodeApiTimeout {
+                method: timed_out,
+                elapsed,
+            }) => {
+                assert_eq!(timed_out, method);
+                assert!(elapsed >= Duration::from_millis(timeout_ms));
+                assert!(
+                    elapsed < Duration::from_millis(timeout_ms + 100),
+                    "{} gave up after {:?}",
+                    method,
+                    elapsed
+                );
+            }
+            other => panic!("{}: expected a timeout, got {:?}", method, other),
+        }
+        assert!(start.elapsed() < Duration::from_millis(timeout_ms + 100));
+    }
+}
+
+#[tokio::test]
+async fn test_dropped_request_releases_permit() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.slow_down("get_block", Duration::from_secs(5));
+    let limiter = Arc::new(RequestLimiter::new(4, 1));
+    let ipc = NodeApiIpc::new(node_api.clone()).with_in_flight(Arc::clone(&limiter));
+
+    let pending = {
+        let ipc = ipc.clone();
+        tokio::spawn(async move { ipc.get_block(&[1u8; 32]).await })
+    };
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(limiter.available(Priority::Interactive), 3);
+
+    // The caller gives up: the permit is released without waiting for the node
+    pending.abort();
+    assert!(pending.await.unwrap_err().is_cancelled());
+    assert_eq!(limiter.available(Priority::Interactive), 4);
+    assert_eq!(limiter.available(Priority::Bulk), 3);
+}
+
+/// Serve a chain of `len` blocks, block `h` with hash `[h + 1; 32]`.
+fn serve_chain(node_api: &common::MockNodeApi, len: u64) {
+    for height in 0..len {
+        let block = common::block([height as u8; 32], Vec::new());
+        node_api.add_block(height, [height as u8 + 1; 32], block);
+    }
+}
+
+#[tokio::test]
+async fn test_stream_blocks_in_order() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    serve_chain(&node_api, 32);
+    node_api.remove_height(14);
+    node_api.fail_next("get_block_by_height", &["connection reset"]);
+    let ipc = NodeApiIpc::new(node_api.clone())
+        .with_batch_concurrency(4)
+        .with_retry_policy(RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+        });
+
+    // The gap is yielded as an error and the stream carries on; the failure is retried
+    let results: Vec<_> = ipc.stream_blocks(10..20).collect().await;
+    assert_eq!(results.len(), 10);
+    for (result, height) in results.iter().zip(10u64..) {
+        match result {
+            Ok((h, block)) => {
+                assert_eq!(*h, height);
+                assert_eq!(block.header.prev_block_hash, [height as u8; 32]);
+            }
+            Err(GovernanceError::BlockNotFound { height: 14 }) => assert_eq!(height, 14),
+            Err(e) => panic!("height {}: {}", height, e),
+        }
+    }
+    assert_eq!(node_api.pending_failures("get_block_by_height"), 0);
+
+    let stopping = ipc.clone().with_stream_stop_on_error(true);
+    let heights: Vec<_> = stopping
+        .stream_blocks(10..20)
+        .map(|result| result.map(|(height, _)| height))
+        .collect()
+        .await;
+    let streamed: Vec<Option<u64>> = heights.iter().map(|h| h.as_ref().ok().copied()).collect();
+    assert_eq!(streamed, vec![Some(10), Some(11), Some(12), Some(13), None]);
+    assert!(matches!(
+        heights[4],
+        Err(GovernanceError::BlockNotFound { height: 14 })
+    ));
+    node_api.add_block(14, [15u8; 32], common::block([14u8; 32], Vec::new()));
+
+    // Backfill hashes come from the streamed blocks' parents
+    node_api.set_tip([32u8; 32], 31);
+    let checkpoint = Checkpoint {
+        height: 27,
+        block_hash: hex::encode([28u8; 32]),
+    };
+    let missed = checkpoint::missed_blocks(&ipc, &checkpoint, 1000)
+        .await
+        .unwrap();
+    let expected: Vec<_> = (28..32u64)
+        .map(|height| (height, [height as u8 + 1; 32]))
+        .collect();
+    assert_eq!(missed, expected);
+}
+
+#[tokio::test]
+async fn test_block_hash_cache_invalidated_by_reorg() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    serve_chain(&node_api, 10);
+    node_api.set_tip([10u8; 32], 9);
+    let ipc = NodeApiIpc::new(node_api.clone());
+
+    assert_eq!(ipc.get_block_hash_at(5).await.unwrap(), [6u8; 32]);
+    assert_eq!(ipc.get_block_hash_at(5).await.unwrap(), [6u8; 32]);
+    assert_eq!(node_api.lookup_count("get_block_by_height"), 1);
+    // The tip's hash is known without a block, and a block gives its parent's
+    assert_eq!(ipc.get_block_hash_at(9).await.unwrap(), [10u8; 32]);
+    let block = ipc.get_block_by_height(3).await.unwrap();
+    assert_eq!(block.header.prev_block_hash, [3u8; 32]);
+    assert_eq!(ipc.get_block_hash_at(2).await.unwrap(), [3u8; 32]);
+    assert_eq!(node_api.lookup_count("get_block_by_height"), 2);
+
+    assert!(matches!(
+        ipc.get_block_hash_at(12).await,
+        Err(GovernanceError::BeyondTip { height: 12, tip: 9 })
+    ));
+    assert!(matches!(
+        ipc.get_block_by_height(12).await,
+        Err(GovernanceError::BeyondTip { height: 12, tip: 9 })
+    ));
+
+    // Blocks 5 to 9 are replaced by a shorter fork
+    let mut parent = [5u8; 32];
+    let fork: Vec<_> = (5..9u8)
+        .map(|height| {
+            let block = common::block(parent, Vec::new());
+            parent = [0x50 + height; 32];
+            (parent, block)
+        })
+        .collect();
+    node_api.reorg(5, fork);
+    let checkpoint = Checkpoint {
+        height: 7,
+        block_hash: hex::encode([8u8; 32]),
+    };
+    // Answered from the cache until the new tip is seen
+    assert_eq!(ipc.get_block_hash_at(5).await.unwrap(), [6u8; 32]);
+    assert_eq!(ipc.tip_tracker().reorgs(), 0);
+
+    // Backfill reads the tip, and replaces the checkpointed block too
+    let missed = checkpoint::missed_blocks(&ipc, &checkpoint, 1000)
+        .await
+        .unwrap();
+    assert_eq!(missed, vec![(7, [0x57u8; 32]), (8, [0x58u8; 32])]);
+    assert_eq!(ipc.tip_tracker().reorgs(), 1);
+    assert_eq!(ipc.get_block_hash_at(5).await.unwrap(), [0x55u8; 32]);
+    assert_eq!(ipc.get_block_hash_at(2).await.unwrap(), [3u8; 32]);
+    assert!(matches!(
+        ipc.get_block_hash_at(9).await,
+        Err(GovernanceError::BeyondTip { height: 9, tip: 8 })
+    ));
+
+    // So does a `NewBlock` event for a lower tip
+    ipc.tip_tracker().observe([0x60u8; 32], 6);
+    assert_eq!(ipc.tip_tracker().reorgs(), 2);
+    assert_eq!(ipc.tip_tracker().hash_at(5), None);
+    // Both are rolled back as one, from the lower fork
+    let reorg = ipc.tip_tracker().take_reorg().unwrap();
+    assert_eq!(reorg.fork_height, 5);
+    assert_eq!(reorg.previous.map(|tip| tip.height), Some(9));
+    assert_eq!(reorg.tip.hash, [0x60u8; 32]);
+    assert_eq!(ipc.tip_tracker().take_reorg(), None);
+}
+
+#[tokio::test]
+async fn test_dropped_stream_stops_fetching() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    serve_chain(&node_api, 32);
+    node_api.set_latency(Duration::from_millis(50));
+    let limiter = Arc::new(RequestLimiter::new(16, 4));
+    let ipc = NodeApiIpc::new(node_api.clone())
+        .with_in_flight(Arc::clone(&limiter))
+        .with_batch_concurrency(4);
+
+    let mut stream = Box::pin(ipc.stream_blocks(0..32));
+    // Only the lookahead is fetched ahead of the consumer
+    let waiting = tokio::time::timeout(Duration::from_millis(10), stream.next()).await;
+    assert!(waiting.is_err());
+    assert_eq!(limiter.available(Priority::Interactive), 12);
+    drop(stream);
+    assert_eq!(limiter.available(Priority::Interactive), 16);
+}
+
+#[tokio::test]
+async fn test_fee_rates_cached() {
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.set_fee_estimate(6, 12);
+    node_api.set_fee_estimate(2, 0);
+    node_api.set_min_relay_fee(500);
+    let ipc = NodeApiIpc::new(node_api.clone())
+        .with_fee_cache(Arc::new(FeeCache::new(Duration::from_millis(100))));
+
+    let rate = ipc.estimate_fee_rate(6).await.unwrap();
+    assert_eq!(rate.sat_per_vbyte(), 12.0);
+    assert_eq!(rate.fee(141), 1692);
+    assert_eq!(ipc.estimate_fee_rate(6).await.unwrap(), rate);
+    assert_eq!(ipc.get_min_relay_fee().await.unwrap(), FeeRate(500));
+    assert_eq!(ipc.get_min_relay_fee().await.unwrap().fee(141), 71);
+    assert_eq!(node_api.lookup_count("get_fee_estimate"), 1);
+    assert_eq!(node_api.lookup_count("get_min_relay_fee"), 1);
+
+    // No estimate, whether answered as zero or as an error; neither is cached
+    for _ in 0..2 {
+        assert!(matches!(
+            ipc.estimate_fee_rate(2).await,
+            Err(GovernanceError::FeeEstimateUnavailable { target_blocks: 2 })
+        ));
+    }
+    node_api.fail_next("get_fee_estimate", &["insufficient data"]);
+    assert!(matches!(
+        ipc.estimate_fee_rate(3).await,
+        Err(GovernanceError::FeeEstimateUnavailable { target_blocks: 3 })
+    ));
+    assert_eq!(node_api.lookup_count("get_fee_estimate"), 4);
+
+    // Asked again once the rate is older than the time to live
+    node_api.set_fee_estimate(6, 20);
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(
+        ipc.estimate_fee_rate(6).await.unwrap(),
+        FeeRate::from_sat_per_vbyte(20)
+    );
+    assert_eq!(node_api.lookup_count("get_fee_estimate"), 5);
+}
+
+#[tokio::test]
+async fn test_chain_work_queries() {
+    // Ten difficulty-1 blocks
+    let block_work = 0x1_0001_0001u128;
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    serve_chain(&node_api, 10);
+    node_api.set_tip([10u8; 32], 9);
+    node_api.set_chain_work(10 * block_work);
+    let ipc = NodeApiIpc::new(node_api.clone());
+
+    // Computed from the tip when the node does not serve it
+    let work = ipc.get_block_chainwork(&[6u8; 32]).await.unwrap();
+    assert_eq!(work, Work::from_u128(6 * block_work));
+    assert_eq!(
+        ipc.get_block_chainwork(&[10u8; 32]).await.unwrap(),
+        Work::from_u128(10 * block_work)
+    );
+    assert_eq!(node_api.lookup_count("get_block_chainwork"), 1);
+    assert!(ipc.get_block_chainwork(&[0xffu8; 32]).await.is_err());
+
+    assert_eq!(ipc.get_difficulty_at(3).await.unwrap(), 1.0);
+    assert!(matches!(
+        ipc.get_difficulty_at(50).await,
+        Err(GovernanceError::BlockNotFound { height: 50 })
+    ));
+    assert_eq!(
+        ipc.work_in_window(2, 4).await.unwrap(),
+        Work::from_u128(3 * block_work)
+    );
+    assert_eq!(ipc.work_in_window(5, 4).await.unwrap(), Work::ZERO);
+
+    // Taken from the node when it serves it
+    let serving = Arc::new(common::MockNodeApi::new(100));
+    let chainwork: Work = "4e9235f043634662e0cb".parse().unwrap();
+    serving.add_block_chainwork([6u8; 32], chainwork);
+    let ipc = NodeApiIpc::new(serving.clone());
+    assert_eq!(
+        ipc.get_block_chainwork(&[6u8; 32]).await.unwrap(),
+        chainwork
+    );
+    assert!(ipc.get_block_chainwork(&[7u8; 32]).await.is_err());
+}
@@ -0,0 +1,125 @@
+//! Per-voter participation, counted as proposals close
+
+mod common;
+
+use blvm_governance::config::{EpochSummaryConfig, ParticipationConfig};
+use blvm_governance::node_api::ProposalDetails;
+use blvm_governance::query::QueryApi;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::sync::Arc;
+
+/// Node id of voter `id`, as it votes.
+fn voter(id: u8) -> String {
+    hex::encode([id; 32])
+}
+
+async fn block(node: &MockNode, height: u64) {
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [height as u8; 32],
+            height,
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_participation_of_each_voter() {
+    let mut config = GovernanceConfig {
+        participation: ParticipationConfig { enabled: true },
+        epoch_summary: EpochSummaryConfig {
+            length_blocks: 10,
+            confirmations: 0,
+        },
+        ..Default::default()
+    };
+    config.registry.epoch.length_blocks = 101;
+    let node = MockNode::start("participation", config).await;
+    let participation = node.participation.clone().unwrap();
+    let registry = &node.module.economic_nodes;
+    for id in [1, 2] {
+        registry
+            .register(&voter(id), "miner", Some(10.0), None)
+            .await
+            .unwrap();
+    }
+    // Both are in the snapshot of epoch 1, in effect when the proposal is created
+    block(&node, 101).await;
+    node.node_api.add_proposal(ProposalDetails {
+        created_height: 102,
+        ..common::proposal("1")
+    });
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    block(&node, 105).await;
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: voter(1),
+            vote: "yes".to_string(),
+        },
+    )
+    .await;
+    registry.veto("1", &voter(2), "unsafe", None).await.unwrap();
+    assert!(participation.participation().unwrap().counted.is_empty());
+
+    // Counted on the block after it merged
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    block(&node, 109).await;
+    let first = participation.voter(&voter(1)).unwrap().unwrap();
+    assert_eq!((first.eligible, first.voted), (1, 1));
+    assert_eq!(first.mean_latency_blocks(), Some(3.0));
+    let second = participation.voter(&voter(2)).unwrap().unwrap();
+    assert_eq!((second.eligible, second.voted, second.vetoes), (1, 0, 1));
+    assert_eq!(participation.participation().unwrap().rate(), Some(0.5));
+    let summary = node.epoch_summaries.summary(10).unwrap().unwrap();
+    assert_eq!(summary.participation_rate, Some(0.5));
+
+    let queries = QueryApi::new(
+        None,
+        Arc::clone(&node.module.proposal_store),
+        Arc::clone(registry),
+        Arc::clone(&node.epoch_summaries),
+    )
+    .with_participation(Arc::clone(&participation));
+    let path = format!("/participation/{}", voter(1));
+    let response = queries.handle(&path, "", None, None).await;
+    assert_eq!(response.status, 200);
+    let body: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(body["voted"], 1);
+    assert_eq!(body["participation_rate"], 1.0);
+    assert_eq!(body["mean_latency_blocks"], 3.0);
+    let path = format!("/participation/{}", voter(3));
+    let response = queries.handle(&path, "", None, None).await;
+    assert_eq!(response.status, 404);
+
+    // Rebuilt from the stored history, the same; left out after a reorg below its close,
+    // and counted again on the next block while it stays merged
+    let stored = participation.participation().unwrap();
+    assert_eq!(participation.recompute().await.unwrap(), stored);
+    assert_eq!(participation.roll_back_to(104).await.unwrap(), 1);
+    assert!(participation.participation().unwrap().voters.is_empty());
+    block(&node, 110).await;
+    assert_eq!(participation.participation().unwrap(), stored);
+}
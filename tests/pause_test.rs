@@ -0,0 +1,167 @@
+//! Outbound effects held while paused and sent on resume
+
+mod common;
+
+use blvm_governance::config::HealthConfig;
+use blvm_governance::error::GovernanceError;
+use blvm_governance::health::{Health, Probe};
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::{FeeRate, NodeApiIpc};
+use blvm_governance::pause::Trigger;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn block(node: &MockNode, height: u64) {
+    let hash = [height as u8; 32];
+    node.node_api.add_block(
+        height,
+        hash,
+        common::block([height as u8 - 1; 32], Vec::new()),
+    );
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash,
+            height,
+        },
+    )
+    .await;
+}
+
+/// Payloads posted to the webhook until none comes for a while.
+async fn received(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+) -> Vec<serde_json::Value> {
+    let mut payloads = Vec::new();
+    while let Ok(Some(payload)) = tokio::time::timeout(Duration::from_millis(300), rx.recv()).await
+    {
+        payloads.push(payload);
+    }
+    payloads
+}
+
+#[tokio::test]
+async fn test_effects_are_held_and_sent_in_order_on_resume() {
+    let (url, mut rx) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let node = MockNode::start("pause", config).await;
+    let trigger = Trigger {
+        reason: Some("incident".to_string()),
+        ..Trigger::new("cli")
+    };
+    assert!(node.pause.pause(trigger).unwrap().paused);
+
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    block(&node, 101).await;
+    block(&node, 102).await;
+
+    // Applied and checkpointed, but nothing sent
+    assert!(received(&mut rx).await.is_empty());
+    assert!(node.module.proposal_store.proposal("1").unwrap().is_some());
+    assert_eq!(
+        node.module.pipeline.checkpointer().last().unwrap().height,
+        102
+    );
+    let actions = NodeApiIpc::new(node.node_api.clone())
+        .with_actions_allowed(true)
+        .with_pause(Arc::clone(&node.pause));
+    assert!(matches!(
+        actions.fund_op_return(&[1], FeeRate(1000)).await,
+        Err(GovernanceError::Paused { .. })
+    ));
+    assert_eq!(node.node_api.lookup_count("fund_op_return"), 0);
+
+    // The backlog goes out oldest first, then new notifications follow it
+    node.pause.resume(Trigger::new("http")).unwrap();
+    let payloads = received(&mut rx).await;
+    assert_eq!(payloads[0]["event_type"], "proposal_created");
+    assert_eq!(payloads[0]["data"]["proposal_id"], "1");
+    let heights: Vec<u64> = payloads
+        .iter()
+        .filter_map(|payload| payload["block_height"].as_u64())
+        .collect();
+    assert_eq!(heights, [101, 102]);
+    assert!(!node.pause.holds());
+
+    block(&node, 103).await;
+    let payloads = received(&mut rx).await;
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0]["block_height"], 103);
+}
+
+#[tokio::test]
+async fn test_pause_over_http() {
+    let node = MockNode::start("pause_http", GovernanceConfig::default()).await;
+    let health = Arc::new(
+        Health::new(
+            HealthConfig {
+                admin_token: Some("t0k".to_string()),
+                ..Default::default()
+            },
+            Arc::clone(&node.module.shutdown),
+            Arc::new(Heartbeat::new()),
+            Arc::clone(&node.module.subscriptions),
+        )
+        .with_pause(Arc::clone(&node.pause)),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let _server = health.serve(listener);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/admin/pause", base))
+        .bearer_auth("wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(!node.pause.is_paused());
+
+    let response = client
+        .post(format!("{}/admin/pause", base))
+        .bearer_auth("t0k")
+        .body(r#"{"actor": "ops", "reason": "incident"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(status["paused"], true);
+    assert_eq!(status["trigger"]["source"], "http");
+    assert_eq!(status["trigger"]["actor"], "ops");
+
+    let readyz: Probe = reqwest::get(format!("{}/readyz", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(!readyz.checks["pause"].ok);
+
+    let response = client
+        .post(format!("{}/admin/resume", base))
+        .bearer_auth("t0k")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!node.pause.is_paused());
+}
@@ -0,0 +1,148 @@
+//! Webhook deliveries in binary formats, their signatures, and the fallback to JSON
+
+mod common;
+
+use blvm_governance::config::WebhookContentType;
+use blvm_governance::github;
+use blvm_governance::payload_format;
+use blvm_governance::schema::{Envelope, ProposalCreated};
+use blvm_governance::webhook::{GovernanceWebhookClient, SIGNATURE_HEADER};
+use blvm_governance::GovernanceConfig;
+
+/// A request as the server received it.
+struct Delivery {
+    content_type: String,
+    signature: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A webhook server answering 415 to any content type not in `accepted`, and 200 otherwise.
+async fn server(
+    accepted: &'static [&'static str],
+) -> (String, tokio::sync::mpsc::UnboundedReceiver<Delivery>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let (head, body) = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    return;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&request[..end]).to_ascii_lowercase();
+                let len = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .map(|v| v.trim().parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + len {
+                    break (head, request[end + 4..end + 4 + len].to_vec());
+                }
+            };
+            let header = |name: &str| {
+                head.lines()
+                    .find_map(|l| l.strip_prefix(&format!("{}:", name.to_ascii_lowercase())))
+                    .map(|v| v.trim().to_string())
+            };
+            let content_type = header("content-type").unwrap_or_default();
+            let status = if accepted.contains(&content_type.as_str()) {
+                "200 OK"
+            } else {
+                "415 Unsupported Media Type"
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            let _ = tx.send(Delivery {
+                content_type,
+                signature: header(SIGNATURE_HEADER),
+                body,
+            });
+        }
+    });
+    (url, rx)
+}
+
+#[tokio::test]
+async fn test_binary_deliveries_decode_to_the_json_payload() {
+    for format in [WebhookContentType::Cbor, WebhookContentType::Msgpack] {
+        let (url, mut rx) = server(&["application/cbor", "application/msgpack"]).await;
+        let config = GovernanceConfig {
+            webhook_url: Some(url),
+            webhook_content_type: format,
+            webhook_secret: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        let client = GovernanceWebhookClient::new(&config).await.unwrap();
+        let (_, status) = client.send_test("proposal_created").await.unwrap();
+        assert!(status.is_success());
+
+        let delivery = rx.recv().await.unwrap();
+        assert_eq!(delivery.content_type, payload_format::mime(format));
+        // Signed over the bytes as sent, not over a JSON rendering of them
+        assert_eq!(
+            delivery.signature.as_deref(),
+            Some(github::signature(b"s3cret", &delivery.body).as_str())
+        );
+        let payload = payload_format::decode(format, &delivery.body).unwrap();
+        let envelope: Envelope = serde_json::from_value(payload).unwrap();
+        assert_eq!(envelope.event_type, "proposal_created");
+        assert!(envelope.test);
+        let data: ProposalCreated = serde_json::from_value(envelope.data).unwrap();
+        assert_eq!(data.proposal_id, "test");
+        assert_eq!(data.pr_number, 1);
+    }
+}
+
+#[tokio::test]
+async fn test_unsigned_without_a_secret() {
+    let (url, mut rx) = server(&["application/json"]).await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config).await.unwrap();
+    client.send_test("proposal_created").await.unwrap();
+
+    let delivery = rx.recv().await.unwrap();
+    assert_eq!(delivery.content_type, "application/json");
+    assert_eq!(delivery.signature, None);
+    let payload: serde_json::Value = serde_json::from_slice(&delivery.body).unwrap();
+    assert_eq!(payload["event_type"], "proposal_created");
+}
+
+#[tokio::test]
+async fn test_falls_back_to_json_on_415() {
+    let (url, mut rx) = server(&["application/json"]).await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_content_type: WebhookContentType::Cbor,
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config).await.unwrap();
+    let (_, status) = client.send_test("proposal_created").await.unwrap();
+    assert!(status.is_success());
+    let (_, status) = client.send_test("proposal_merged").await.unwrap();
+    assert!(status.is_success());
+
+    // Refused once, then JSON for the rest of the client's life
+    let mut content_types = Vec::new();
+    for _ in 0..3 {
+        content_types.push(rx.recv().await.unwrap().content_type);
+    }
+    assert_eq!(
+        content_types,
+        vec!["application/cbor", "application/json", "application/json"]
+    );
+}
@@ -0,0 +1,469 @@
+//! Event pipeline checkpointing across a crash between handlers
+
+mod common;
+
+use blvm_governance::audit_log::AuditLog;
+use blvm_governance::checkpoint::Checkpointer;
+use blvm_governance::config::AuditLogConfig;
+use blvm_governance::error::GovernanceError;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::pipeline::{EventHandler, Pipeline};
+use blvm_governance::trace;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::{EventType, NodeAPI};
+use futures::FutureExt;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Counts the events it handles, noting the trace id of each; fails while `failing` is set
+/// and panics while `panicking` is.
+struct CountingHandler {
+    name: &'static str,
+    handled: AtomicUsize,
+    failing: AtomicBool,
+    panicking: AtomicBool,
+    trace_ids: Mutex<Vec<Option<String>>>,
+}
+
+impl CountingHandler {
+    fn new(name: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            handled: AtomicUsize::new(0),
+            failing: AtomicBool::new(false),
+            panicking: AtomicBool::new(false),
+            trace_ids: Mutex::default(),
+        })
+    }
+
+    fn handled(&self) -> usize {
+        self.handled.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for CountingHandler {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        vec![EventType::NewBlock, EventType::GovernanceProposalCreated]
+    }
+
+    async fn handle(&self, _: &ModuleMessage, _: &dyn NodeAPI) -> Result<(), GovernanceError> {
+        self.trace_ids.lock().unwrap().push(trace::current_id());
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(GovernanceError::ModuleError("simulated crash".to_string()));
+        }
+        if self.panicking.load(Ordering::SeqCst) {
+            panic!("simulated panic");
+        }
+        self.handled.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn new_block(height: u64) -> EventMessage {
+    EventMessage {
+        event_type: EventType::NewBlock,
+        payload: EventPayload::NewBlock {
+            block_hash: [height as u8; 32],
+            height,
+        },
+    }
+}
+
+fn pipeline(dir: &std::path::Path, handlers: &[&Arc<CountingHandler>]) -> Pipeline {
+    let checkpointer = Arc::new(Checkpointer::open(dir).unwrap());
+    let handlers: Vec<Arc<dyn EventHandler>> = handlers
+        .iter()
+        .map(|h| Arc::clone(*h) as Arc<dyn EventHandler>)
+        .collect();
+    Pipeline::new(checkpointer).with_handlers(handlers)
+}
+
+#[tokio::test]
+async fn test_replay_reruns_only_incomplete_handlers() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let webhook = CountingHandler::new("webhook");
+    let registry = CountingHandler::new("economic_nodes");
+
+    let first = pipeline(&dir, &[&webhook, &registry]);
+    assert_eq!(first.handlers(), vec!["webhook", "economic_nodes"]);
+    assert!(first.process(&new_block(1), &node_api).await);
+    assert_eq!(first.checkpointer().last().unwrap().height, 1);
+
+    // The registry fails on block 2 after the webhook handled it
+    registry.failing.store(true, Ordering::SeqCst);
+    assert!(!first.process(&new_block(2), &node_api).await);
+    assert_eq!(first.checkpointer().last().unwrap().height, 1);
+    drop(first);
+
+    // After a restart the block is replayed; only the registry runs again
+    registry.failing.store(false, Ordering::SeqCst);
+    let restarted = pipeline(&dir, &[&webhook, &registry]);
+    assert!(restarted.process(&new_block(2), &node_api).await);
+    assert_eq!(webhook.handled(), 2);
+    assert_eq!(registry.handled(), 2);
+    assert_eq!(restarted.checkpointer().last().unwrap().height, 2);
+    assert_eq!(restarted.checkpointer().sequence(), 2);
+
+    // A fully processed block is not handled again
+    assert!(restarted.process(&new_block(2), &node_api).await);
+    assert_eq!(webhook.handled(), 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_filters_event_types_no_handler_needs() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_filter_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pipeline = pipeline(&dir, &[&CountingHandler::new("blocks")]);
+    assert!(pipeline.accepts(&EventType::NewBlock));
+    assert!(!pipeline.accepts(&EventType::EconomicNodeVeto));
+    assert!(!pipeline.accepts(&EventType::EconomicNodeVeto));
+    assert_eq!(pipeline.filtered().get("EconomicNodeVeto"), Some(&2));
+    assert_eq!(pipeline.filtered().get("NewBlock"), None);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_failing_handler_does_not_stop_others() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_isolation_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let failing = CountingHandler::new("failing");
+    let after = CountingHandler::new("after");
+    failing.failing.store(true, Ordering::SeqCst);
+
+    let pipeline = pipeline(&dir, &[&failing, &after]);
+    let event = EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    };
+    assert!(!pipeline.process(&event, &node_api).await);
+    assert_eq!(after.handled(), 1);
+    assert_eq!(pipeline.checkpointer().sequence(), 0);
+
+    failing.failing.store(false, Ordering::SeqCst);
+    assert!(pipeline.process(&event, &node_api).await);
+    assert_eq!(pipeline.checkpointer().sequence(), 1);
+
+    let stats = pipeline.handler_stats();
+    assert_eq!((stats["failing"].handled, stats["failing"].errors), (2, 1));
+    assert_eq!((stats["after"].handled, stats["after"].errors), (2, 0));
+
+    // Handlers are only given the event types they are interested in
+    let veto = EventMessage {
+        event_type: EventType::EconomicNodeVeto,
+        payload: EventPayload::EconomicNodeVeto {
+            proposal_id: "1".to_string(),
+            node_id: "node".to_string(),
+            reason: "test".to_string(),
+        },
+    };
+    assert!(pipeline.process(&veto, &node_api).await);
+    assert_eq!(after.handled(), 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_isolated_panic_marks_handler_unhealthy() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_panic_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let panicking = CountingHandler::new("panicking");
+    let after = CountingHandler::new("after");
+    panicking.panicking.store(true, Ordering::SeqCst);
+
+    let pipeline = pipeline(&dir, &[&panicking, &after]).with_panic_isolation(true);
+    assert!(!pipeline.process(&new_block(1), &node_api).await);
+    assert_eq!(after.handled(), 1);
+    assert!(pipeline.checkpointer().last().is_none());
+
+    // The handler gets no more events, even once it would handle them
+    panicking.panicking.store(false, Ordering::SeqCst);
+    assert!(!pipeline.process(&new_block(2), &node_api).await);
+    assert_eq!(panicking.handled(), 0);
+    assert_eq!(after.handled(), 2);
+    assert!(pipeline.checkpointer().last().is_none());
+
+    let stats = pipeline.handler_stats();
+    assert_eq!(
+        (stats["panicking"].handled, stats["panicking"].panics),
+        (1, 1)
+    );
+    assert_eq!(
+        stats["panicking"].unhealthy.as_deref(),
+        Some("simulated panic")
+    );
+    assert_eq!(
+        (stats["after"].panics, stats["after"].unhealthy.clone()),
+        (0, None)
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_panic_propagates_without_isolation() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_unisolated_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let panicking = CountingHandler::new("panicking");
+    let after = CountingHandler::new("after");
+    panicking.panicking.store(true, Ordering::SeqCst);
+
+    // Left to the panic hook, which reports the crash and exits; see tests/crash_test.rs
+    let pipeline = pipeline(&dir, &[&panicking, &after]);
+    let result = AssertUnwindSafe(pipeline.process(&new_block(1), &node_api))
+        .catch_unwind()
+        .await;
+    assert!(result.is_err());
+    assert_eq!(after.handled(), 0);
+    assert_eq!(pipeline.handler_stats()["panicking"].unhealthy, None);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_handlers_share_event_trace_id() {
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_trace_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let first = CountingHandler::new("first");
+    let second = CountingHandler::new("second");
+    let pipeline = pipeline(&dir, &[&first, &second]);
+
+    assert!(pipeline.process(&new_block(1), &node_api).await);
+    assert!(pipeline.process(&new_block(2), &node_api).await);
+    let first_ids = first.trace_ids.lock().unwrap().clone();
+    let second_ids = second.trace_ids.lock().unwrap().clone();
+    assert_eq!(first_ids, second_ids);
+    assert!(first_ids.iter().all(Option::is_some));
+    assert_ne!(first_ids[0], first_ids[1]);
+    assert_eq!(trace::current_id(), None);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// A span seen by [`SpanRecorder`].
+#[derive(Debug, Clone)]
+struct RecordedSpan {
+    name: &'static str,
+    parent: Option<usize>,
+    fields: BTreeMap<String, String>,
+}
+
+/// Records every span with its fields and parent, and every log record with its fields and
+/// those of the spans it was logged in.
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+    records: Arc<Mutex<Vec<BTreeMap<String, String>>>>,
+    /// Index in `spans` by span id; ids are reused once a span closes.
+    index: Arc<Mutex<HashMap<u64, usize>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let mut index = self.index.lock().unwrap();
+        let parent = ctx
+            .span(id)
+            .and_then(|span| span.parent())
+            .and_then(|parent| index.get(&parent.id().into_u64()).copied());
+        let mut spans = self.spans.lock().unwrap();
+        index.insert(id.into_u64(), spans.len());
+        spans.push(RecordedSpan {
+            name: attrs.metadata().name(),
+            parent,
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _: Context<'_, S>) {
+        if let Some(&i) = self.index.lock().unwrap().get(&id.into_u64()) {
+            values.record(&mut FieldVisitor(&mut self.spans.lock().unwrap()[i].fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            let index = self.index.lock().unwrap();
+            let spans = self.spans.lock().unwrap();
+            for span in scope.from_root() {
+                if let Some(&i) = index.get(&span.id().into_u64()) {
+                    fields.extend(spans[i].fields.clone());
+                }
+            }
+        }
+        event.record(&mut FieldVisitor(&mut fields));
+        self.records.lock().unwrap().push(fields);
+    }
+}
+
+/// Looks up a block through [`NodeApiIpc`] for each event.
+struct LookupHandler {
+    ipc: NodeApiIpc,
+}
+
+#[async_trait::async_trait]
+impl EventHandler for LookupHandler {
+    fn name(&self) -> &'static str {
+        "lookup"
+    }
+
+    fn interested_events(&self) -> Vec<EventType> {
+        vec![EventType::NewBlock]
+    }
+
+    async fn handle(&self, _: &ModuleMessage, _: &dyn NodeAPI) -> Result<(), GovernanceError> {
+        self.ipc.get_block(&[3u8; 32]).await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_node_api_spans_nest_under_event() {
+    let recorder = SpanRecorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_spans_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.add_block(0, [3u8; 32], common::block([0u8; 32], Vec::new()));
+    let handler = Arc::new(LookupHandler {
+        ipc: NodeApiIpc::new(node_api.clone()),
+    });
+    let checkpointer = Arc::new(Checkpointer::open(&dir).unwrap());
+    let pipeline = Pipeline::new(checkpointer).with_handler(handler);
+    assert!(pipeline.process(&new_block(1), node_api.as_ref()).await);
+
+    let spans = recorder.spans.lock().unwrap().clone();
+    let request = spans.iter().find(|s| s.name == "node_api").unwrap();
+    assert_eq!(request.fields["method"], "get_block");
+    assert_eq!(request.fields["key"], hex::encode([3u8; 32]));
+    assert!(request.fields.contains_key("elapsed_ms"));
+    let handler = &spans[request.parent.unwrap()];
+    assert_eq!(
+        (handler.name, handler.fields["handler"].as_str()),
+        ("handler", "lookup")
+    );
+    let event = &spans[handler.parent.unwrap()];
+    assert_eq!(event.name, "event");
+    assert_eq!(event.fields["event_type"], "NewBlock");
+    assert_eq!(event.fields["trace_id"], request.fields["trace_id"]);
+    assert_eq!(event.fields["trace_id"].len(), 32);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_event_fields_on_records_and_audit_entries() {
+    let recorder = SpanRecorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+    let dir = std::env::temp_dir().join(format!("blvm_pipeline_fields_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let node_api = common::MockNodeApi::new(100);
+    let audit_dir = dir.join("audit");
+    let log = Arc::new(
+        AuditLog::open(
+            &audit_dir,
+            &AuditLogConfig {
+                enabled: true,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    );
+    let failing = CountingHandler::new("failing");
+    failing.failing.store(true, Ordering::SeqCst);
+    let checkpointer = Arc::new(Checkpointer::open(&dir).unwrap());
+    let pipeline = Pipeline::new(checkpointer)
+        .with_handlers(vec![log.clone() as Arc<dyn EventHandler>, failing.clone()]);
+
+    let proposal = EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: "42".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    };
+    assert!(!pipeline.process(&new_block(7), &node_api).await);
+    assert!(!pipeline.process(&proposal, &node_api).await);
+
+    // The handler failures are logged with the fields of the event they were handling
+    let records = recorder.records.lock().unwrap().clone();
+    let failures: Vec<_> = records
+        .iter()
+        .filter(|r| r["message"].starts_with("Error handling"))
+        .collect();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0]["event_type"], "NewBlock");
+    assert_eq!(failures[0]["block_hash"], hex::encode([7u8; 32]));
+    assert_eq!(failures[0]["height"], "7");
+    assert!(!failures[0].contains_key("proposal_id"));
+    assert_eq!(failures[1]["event_type"], "GovernanceProposalCreated");
+    assert_eq!(failures[1]["proposal_id"], "42");
+    assert!(!failures[1].contains_key("height"));
+    assert_ne!(failures[0]["trace_id"], failures[1]["trace_id"]);
+
+    // The audit log records each event with the same id
+    let content = std::fs::read_dir(&audit_dir)
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect::<String>();
+    let trace_ids: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            entry["data"]["trace_id"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(
+        trace_ids,
+        vec![
+            failures[0]["trace_id"].clone(),
+            failures[1]["trace_id"].clone()
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,675 @@
+//! Proposal lifecycle tracking from events and the node's proposal records
+
+mod common;
+
+use blvm_governance::config::{ActivationConfig, AuditLogConfig, DeadlineConfig, TierThresholds};
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::proposals::{ProposalStatus, ProposalStore, ProposalVote};
+use blvm_governance::tally::{Milestone, Tally};
+use blvm_governance::vote_check::VoteChecks;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn created(proposal_id: &str) -> (EventType, EventPayload) {
+    (
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+}
+
+fn voted(proposal_id: &str, voter: &str, vote: &str) -> (EventType, EventPayload) {
+    (
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: proposal_id.to_string(),
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        },
+    )
+}
+
+fn merged(proposal_id: &str) -> (EventType, EventPayload) {
+    (
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+}
+
+fn block(height: u64) -> (EventType, EventPayload) {
+    (
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [height as u8; 32],
+            height,
+        },
+    )
+}
+
+async fn send(node: &MockNode, (event_type, payload): (EventType, EventPayload)) {
+    node.send_event(event_type, payload).await;
+}
+
+/// Pass an event straight to `store`, as the pipeline would.
+async fn handle(
+    store: &ProposalStore,
+    node_api: &common::MockNodeApi,
+    (event_type, payload): (EventType, EventPayload),
+) {
+    let message = ModuleMessage::Event(EventMessage {
+        event_type,
+        payload,
+    });
+    store.handle_event(&message, node_api).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lifecycle_from_created_to_merged() {
+    let node = MockNode::start("proposals_lifecycle", GovernanceConfig::default()).await;
+    node.node_api.add_proposal(common::proposal("7"));
+    let store = &node.module.proposal_store;
+
+    send(&node, created("7")).await;
+    let proposal = store.proposal("7").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Created);
+    assert_eq!(proposal.author.as_deref(), Some("alice"));
+    assert_eq!(proposal.created_height, Some(100));
+
+    send(&node, voted("7", "bob", "yes")).await;
+    send(&node, voted("7", "carol", "yes")).await;
+    send(&node, voted("7", "dave", "no")).await;
+    let proposal = store.proposal("7").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Voting);
+    assert_eq!(proposal.vote_counts()["yes"], 2);
+    assert_eq!(proposal.vote_counts()["no"], 1);
+    assert_eq!(store.open_proposals().unwrap().len(), 1);
+
+    send(&node, merged("7")).await;
+    // Closed: later votes are ignored
+    send(&node, voted("7", "erin", "no")).await;
+    let proposal = store.proposal("7").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Merged);
+    assert_eq!(proposal.closed_height, Some(100));
+    assert_eq!(proposal.votes.len(), 3);
+    assert!(store.open_proposals().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_events_before_created_are_held() {
+    let node = MockNode::start("proposals_held", GovernanceConfig::default()).await;
+    let store = &node.module.proposal_store;
+
+    // The node has no record of the proposal yet, so nothing fills the gap
+    send(&node, voted("9", "bob", "yes")).await;
+    assert!(store.proposal("9").unwrap().is_none());
+    assert_eq!(store.held_events().unwrap(), 1);
+
+    send(&node, created("9")).await;
+    let proposal = store.proposal("9").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Voting);
+    assert_eq!(proposal.repository, "test/repo");
+    assert_eq!(
+        proposal.votes,
+        vec![ProposalVote {
+            voter: "bob".to_string(),
+            vote: "yes".to_string(),
+        }]
+    );
+    assert_eq!(store.held_events().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_lookup_fills_the_gap_and_closes_proposals() {
+    let node = MockNode::start("proposals_lookup", GovernanceConfig::default()).await;
+    node.node_api.add_proposal(common::proposal("8"));
+    let store = &node.module.proposal_store;
+
+    send(&node, voted("8", "bob", "no")).await;
+    let proposal = store.proposal("8").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Voting);
+    assert_eq!(proposal.tier, "standard");
+    assert_eq!(proposal.created_height, Some(100));
+    assert_eq!(store.held_events().unwrap(), 0);
+
+    // Rejections are only known from the node's record
+    let mut rejected = common::proposal("8");
+    rejected.status = "rejected".to_string();
+    node.node_api.add_proposal(rejected);
+    node.module.proposal_cache.invalidate("8");
+    let proposal = store.refresh("8").await.unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Rejected);
+    assert_eq!(proposal.closed_height, Some(100));
+    assert!(store.refresh("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_tally_milestones_are_announced_once() {
+    let (url, mut received) = common::webhook_server().await;
+    let mut config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    config.tally.tiers.insert(
+        "standard".to_string(),
+        TierThresholds {
+            quorum: 3,
+            approval_percent: 66.0,
+        },
+    );
+    let node = MockNode::start("proposals_tally", config).await;
+    node.node_api.add_proposal(common::proposal("5"));
+    let store = &node.module.proposal_store;
+
+    send(&node, created("5")).await;
+    send(&node, voted("5", "bob", "yes")).await;
+    send(&node, voted("5", "carol", "no")).await;
+    // A second vote replaces the first: still two voters
+    send(&node, voted("5", "carol", "yes")).await;
+    let proposal = store.proposal("5").unwrap().unwrap();
+    assert_eq!(
+        proposal.tally(),
+        Tally {
+            yes: 2,
+            no: 0,
+            abstain: 0
+        }
+    );
+    assert!(proposal.milestones.is_empty());
+
+    send(&node, voted("5", "dave", "abstain")).await;
+    // Below the approval threshold again, but milestones stay reached
+    send(&node, voted("5", "erin", "no")).await;
+    send(&node, voted("5", "frank", "no")).await;
+    let proposal = store.proposal("5").unwrap().unwrap();
+    assert_eq!(
+        proposal.tally(),
+        Tally {
+            yes: 2,
+            no: 2,
+            abstain: 1
+        }
+    );
+    let milestones: Vec<Milestone> = proposal.milestones.iter().map(|m| m.milestone).collect();
+    assert_eq!(
+        milestones,
+        vec![Milestone::QuorumReached, Milestone::ApprovedPendingMerge]
+    );
+    assert_eq!(
+        proposal.milestones[0].tally,
+        Tally {
+            yes: 2,
+            no: 0,
+            abstain: 1
+        }
+    );
+
+    let mut announced = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        let event_type = payload["event_type"].as_str().unwrap().to_string();
+        if event_type.starts_with("proposal_quorum") || event_type.starts_with("proposal_approved")
+        {
+            assert_eq!(payload["data"]["proposal_id"], "5");
+            assert_eq!(payload["data"]["tally"]["yes"], 2);
+            announced.push(event_type);
+        }
+    }
+    announced.sort();
+    assert_eq!(
+        announced,
+        vec!["proposal_approved_pending_merge", "proposal_quorum_reached"]
+    );
+}
+
+#[tokio::test]
+async fn test_deadline_reminders_are_sent_once() {
+    let (url, mut received) = common::webhook_server().await;
+    let mut config = GovernanceConfig {
+        webhook_url: Some(url),
+        deadlines: DeadlineConfig {
+            windows: [("standard".to_string(), 50)].into(),
+            reminders: vec![20, 5],
+        },
+        ..Default::default()
+    };
+    config.tally.tiers.insert(
+        "standard".to_string(),
+        TierThresholds {
+            quorum: 2,
+            approval_percent: 0.0,
+        },
+    );
+    let dir = std::env::temp_dir().join(format!("blvm_proposals_deadlines_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.add_proposal(common::proposal("4"));
+    node_api.add_proposal(common::proposal("6"));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    ipc.get_best_block().await.unwrap();
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let open = || {
+        ProposalStore::new(Arc::clone(&db))
+            .with_node_api(ipc.clone())
+            .with_tally(config.tally.clone())
+            .with_deadlines(config.deadlines.clone())
+            .with_webhook(Arc::clone(&webhook))
+    };
+
+    let store = open();
+    for event in [
+        created("4"),
+        created("6"),
+        voted("4", "bob", "yes"),
+        voted("4", "carol", "no"),
+        block(129),
+        block(130),
+        // Merged before the deadline: no more reminders
+        merged("6"),
+    ] {
+        handle(&store, &node_api, event).await;
+    }
+    let proposal = store.proposal("4").unwrap().unwrap();
+    assert_eq!(store.deadline(&proposal), Some(150));
+
+    // Reminders sent before a restart are not sent again
+    drop(store);
+    let store = open();
+    for height in [130, 131, 146, 150, 151] {
+        handle(&store, &node_api, block(height)).await;
+    }
+
+    let mut notices = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        let event_type = payload["event_type"].as_str().unwrap().to_string();
+        // Tally milestones are sent too
+        if !event_type.starts_with("voting_") {
+            continue;
+        }
+        let data = &payload["data"];
+        notices.push((
+            event_type,
+            data["proposal_id"].as_str().unwrap().to_string(),
+            data["height"].as_u64().unwrap(),
+        ));
+        if notices.last().unwrap().0 == "voting_closed" {
+            assert_eq!(data["deadline_height"], 150);
+            assert_eq!(data["quorum_met"], true);
+            assert_eq!(data["tally"]["no"], 1);
+        }
+    }
+    let notice =
+        |event_type: &str, id: &str, height| (event_type.to_string(), id.to_string(), height);
+    assert_eq!(
+        notices,
+        vec![
+            notice("voting_deadline_reminder", "4", 130),
+            notice("voting_deadline_reminder", "6", 130),
+            notice("voting_deadline_reminder", "4", 146),
+            notice("voting_closed", "4", 150),
+        ]
+    );
+    drop(store);
+    drop(db);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_activation_countdown_and_reorg() {
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_events: vec![
+            "proposal_activation_countdown".to_string(),
+            "proposal_activated".to_string(),
+            "proposal_activation_cancelled".to_string(),
+        ],
+        activation: ActivationConfig {
+            delays: [("standard".to_string(), 10)].into(),
+            countdown_interval_blocks: 4,
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("proposals_activation", config).await;
+    node.node_api.add_proposal(common::proposal("2"));
+    let store = &node.module.proposal_store;
+
+    send(&node, created("2")).await;
+    send(&node, merged("2")).await;
+    send(&node, block(101)).await;
+    send(&node, block(102)).await;
+    let upcoming = store.upcoming_activations().unwrap();
+    assert_eq!(upcoming.len(), 1);
+    assert_eq!(upcoming[0].merged_height, 100);
+    assert_eq!(upcoming[0].activation_height, 110);
+
+    // The block the merge was recorded at is replaced
+    send(&node, block(100)).await;
+    assert!(store.upcoming_activations().unwrap().is_empty());
+    let proposal = store.proposal("2").unwrap().unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Created);
+    assert_eq!(proposal.closed_height, None);
+
+    // Merged again on the new chain
+    send(&node, merged("2")).await;
+    for height in 101..=111 {
+        send(&node, block(height)).await;
+    }
+    assert!(store.upcoming_activations().unwrap().is_empty());
+
+    let mut notices = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        notices.push((
+            payload["event_type"].as_str().unwrap().to_string(),
+            payload["data"]["height"].as_u64().unwrap(),
+        ));
+    }
+    let notice = |event_type: &str, height| (event_type.to_string(), height);
+    assert_eq!(
+        notices,
+        vec![
+            notice("proposal_activation_countdown", 102),
+            notice("proposal_activation_cancelled", 100),
+            notice("proposal_activation_countdown", 102),
+            notice("proposal_activation_countdown", 106),
+            notice("proposal_activated", 110),
+        ]
+    );
+}
+
+/// A proposal as stored before the lifecycle fields were added.
+#[derive(Serialize)]
+struct LegacyProposal {
+    proposal_id: String,
+    repository: String,
+    pr_number: u64,
+    tier: String,
+    status: LegacyStatus,
+    votes: Vec<ProposalVote>,
+}
+
+#[derive(Serialize)]
+enum LegacyStatus {
+    Created,
+    #[allow(dead_code)]
+    Merged,
+}
+
+#[test]
+fn test_stored_proposals_are_migrated() {
+    let dir = std::env::temp_dir().join(format!("blvm_proposals_migrate_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let legacy = HashMap::from([(
+        "3".to_string(),
+        LegacyProposal {
+            proposal_id: "3".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 3,
+            tier: "standard".to_string(),
+            status: LegacyStatus::Created,
+            votes: vec![ProposalVote {
+                voter: "bob".to_string(),
+                vote: "yes".to_string(),
+            }],
+        },
+    )]);
+    {
+        let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+            &dir,
+            blvm_sdk::migrations!(1 => blvm_governance::storage::up_v1),
+        )
+        .unwrap()
+        .as_db();
+        db.open_tree("proposals")
+            .unwrap()
+            .insert(b"proposals", &bincode::serialize(&legacy).unwrap())
+            .unwrap();
+    }
+
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let proposal = ProposalStore::new(Arc::clone(&db))
+        .proposal("3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Voting);
+    assert_eq!(proposal.pr_number, 3);
+    assert_eq!(proposal.votes.len(), 1);
+    assert_eq!(proposal.author, None);
+    assert!(proposal.milestones.is_empty());
+    drop(db);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_conflicts_are_flagged_alerted_and_cleared() {
+    let (url, mut received) = common::webhook_server().await;
+    let mut config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    config.tally.tiers.insert(
+        "standard".to_string(),
+        TierThresholds {
+            quorum: 1,
+            approval_percent: 0.0,
+        },
+    );
+    let node = MockNode::start("proposals_conflicts", config).await;
+    let store = &node.module.proposal_store;
+    for (id, domain, conflicts_with) in [
+        ("1", Some("fees"), vec![]),
+        ("2", Some("fees"), vec![]),
+        ("3", None, vec!["4".to_string()]),
+        ("4", None, vec![]),
+    ] {
+        let mut details = common::proposal(id);
+        details.conflict_domain = domain.map(str::to_string);
+        details.conflicts_with = conflicts_with;
+        node.node_api.add_proposal(details);
+        send(&node, created(id)).await;
+    }
+    let graph = store.conflicts().unwrap();
+    assert_eq!(graph.partners("1"), vec!["2"]);
+    assert_eq!(graph.partners("4"), vec!["3"]);
+
+    // Both merged within the window: alerted once
+    send(&node, merged("1")).await;
+    send(&node, merged("2")).await;
+    send(&node, merged("2")).await;
+    assert!(
+        store
+            .conflicts()
+            .unwrap()
+            .between("1", "2")
+            .unwrap()
+            .alerted
+    );
+
+    // Rejecting one side resolves the conflict
+    send(&node, voted("3", "bob", "yes")).await;
+    let mut rejected = common::proposal("4");
+    rejected.status = "rejected".to_string();
+    node.node_api.add_proposal(rejected);
+    node.module.proposal_cache.invalidate("4");
+    store.refresh("4").await.unwrap();
+    let graph = store.conflicts().unwrap();
+    assert!(graph.partners("3").is_empty());
+    assert_eq!(graph.conflicts.len(), 1);
+
+    let mut alerts = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        let data = &payload["data"];
+        match payload["event_type"].as_str().unwrap() {
+            "proposal_conflict" => alerts.push(data.clone()),
+            "proposal_quorum_reached" if data["proposal_id"] == "3" => {
+                assert_eq!(data["conflicts"], serde_json::json!(["4"]));
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["proposal_id"], "2");
+    assert_eq!(alerts[0]["conflicting_proposal_id"], "1");
+    assert_eq!(alerts[0]["reason"]["kind"], "domain");
+}
+
+#[tokio::test]
+async fn test_duplicate_and_conflicting_votes() {
+    let (url, mut received) = common::webhook_server().await;
+    let mut config = GovernanceConfig {
+        webhook_url: Some(url),
+        audit_log: AuditLogConfig {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    config.tally.vote_conflict_window_blocks = Some(1);
+    let node = MockNode::start("proposals_vote_checks", config).await;
+    node.node_api.add_proposal(common::proposal("1"));
+    let store = &node.module.proposal_store;
+
+    send(&node, created("1")).await;
+    send(&node, voted("1", "bob", "yes")).await;
+    send(&node, voted("1", "bob", "yes")).await;
+    // Both at the tip: `no` wins the tie whichever came first
+    send(&node, voted("1", "carol", "yes")).await;
+    send(&node, voted("1", "carol", "no")).await;
+    send(&node, voted("1", "carol", "yes")).await;
+    let proposal = store.proposal("1").unwrap().unwrap();
+    assert_eq!(
+        proposal.tally(),
+        Tally {
+            yes: 1,
+            no: 1,
+            abstain: 0
+        }
+    );
+    assert_eq!(
+        store.vote_checks().unwrap(),
+        VoteChecks {
+            duplicates_dropped: 1,
+            conflicts_resolved: 2,
+        }
+    );
+
+    let mut alerts = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        if payload["event_type"] == "vote_conflict" {
+            alerts.push(payload["data"].clone());
+        }
+    }
+    assert_eq!(alerts.len(), 2);
+    for alert in &alerts {
+        assert_eq!(alert["proposal_id"], "1");
+        assert_eq!(alert["voter"], "carol");
+        assert_eq!(alert["kept"]["vote"], "no");
+        assert_eq!(alert["dropped"]["vote"], "yes");
+        assert_eq!(alert["kept"]["height"], 100);
+    }
+    let audited: Vec<serde_json::Value> = node
+        .audit_entries()
+        .into_iter()
+        .filter(|entry| entry["kind"] == "vote_conflict")
+        .collect();
+    assert_eq!(audited.len(), 2);
+    assert_eq!(audited[0]["data"]["voter"], "carol");
+    assert_eq!(audited[0]["data"]["dropped"]["vote"], "yes");
+}
+
+#[tokio::test]
+async fn test_vote_checks_survive_a_restart() {
+    let mut config = GovernanceConfig::default();
+    config.tally.vote_conflict_window_blocks = Some(0);
+    let dir = std::env::temp_dir().join(format!("blvm_proposals_votes_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let db = blvm_sdk::module::ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.add_proposal(common::proposal("2"));
+    let ipc = NodeApiIpc::new(node_api.clone());
+    ipc.get_best_block().await.unwrap();
+    let open = || {
+        ProposalStore::new(Arc::clone(&db))
+            .with_node_api(ipc.clone())
+            .with_tally(config.tally.clone())
+    };
+
+    let store = open();
+    for event in [
+        created("2"),
+        voted("2", "bob", "no"),
+        voted("2", "bob", "no"),
+    ] {
+        handle(&store, &node_api, event).await;
+    }
+    drop(store);
+
+    // The counted vote and its height are stored: still a duplicate, and still the winner
+    let store = open();
+    handle(&store, &node_api, voted("2", "bob", "no")).await;
+    handle(&store, &node_api, voted("2", "bob", "yes")).await;
+    let proposal = store.proposal("2").unwrap().unwrap();
+    assert_eq!(proposal.tally().no, 1);
+    assert_eq!(proposal.tally().yes, 0);
+    assert_eq!(
+        store.vote_checks().unwrap(),
+        VoteChecks {
+            duplicates_dropped: 2,
+            conflicts_resolved: 1,
+        }
+    );
+    drop(store);
+    drop(db);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,188 @@
+//! Read-only queries of the governance state over the health listener
+
+mod common;
+
+use blvm_governance::config::{EpochSummaryConfig, HealthConfig};
+use blvm_governance::health::Health;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::node_api::NodeEconomicNode;
+use blvm_governance::query::QueryApi;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::sync::Arc;
+
+const TOKEN: &str = "t0k";
+
+/// GET `path` with the token, and `If-None-Match: etag` if given: the status, the `ETag`
+/// and the body, `null` when empty.
+async fn get(
+    base: &str,
+    path: &str,
+    etag: Option<&str>,
+) -> (u16, Option<String>, serde_json::Value) {
+    let mut request = reqwest::Client::new()
+        .get(format!("{}{}", base, path))
+        .bearer_auth(TOKEN);
+    if let Some(etag) = etag {
+        request = request.header("If-None-Match", etag);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    let etag = response
+        .headers()
+        .get("etag")
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = response.text().await.unwrap();
+    let body = serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+    (status, etag, body)
+}
+
+async fn created(node: &MockNode, proposal_id: &str) {
+    node.node_api.add_proposal(common::proposal(proposal_id));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_queries_with_etags() {
+    let config = GovernanceConfig {
+        epoch_summary: EpochSummaryConfig {
+            length_blocks: 10,
+            confirmations: 0,
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("query", config).await;
+    let health = Arc::new(Health::new(
+        HealthConfig {
+            queries: true,
+            ..Default::default()
+        },
+        Arc::clone(&node.module.shutdown),
+        Arc::new(Heartbeat::new()),
+        Arc::clone(&node.module.subscriptions),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = health.serve(listener);
+
+    // Not connected to the node yet
+    let (status, _, _) = get(&base, "/proposals", None).await;
+    assert_eq!(status, 503);
+    health.serve_queries(Arc::new(QueryApi::new(
+        Some(TOKEN.to_string()),
+        Arc::clone(&node.module.proposal_store),
+        Arc::clone(&node.module.economic_nodes),
+        Arc::clone(&node.epoch_summaries),
+    )));
+    let response = reqwest::get(format!("{}/proposals", base)).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    created(&node, "1").await;
+    created(&node, "2").await;
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "2".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    let (status, etag, body) = get(&base, "/proposals?state=open", None).await;
+    assert_eq!(status, 200);
+    let ids: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["proposal_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["1"]);
+    let (status, _, body) = get(&base, "/proposals", None).await;
+    assert_eq!((status, body.as_array().unwrap().len()), (200, 2));
+    let (status, _, _) = get(&base, "/proposals?state=closed", None).await;
+    assert_eq!(status, 400);
+
+    // Unchanged: answered from the tag, until a vote writes the store
+    let etag = etag.unwrap();
+    let (status, same, body) = get(&base, "/proposals?state=open", Some(&etag)).await;
+    assert_eq!(
+        (status, same.as_deref(), body),
+        (304, Some(etag.as_str()), serde_json::Value::Null)
+    );
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "bob".to_string(),
+            vote: "yes".to_string(),
+        },
+    )
+    .await;
+    let (status, changed, _) = get(&base, "/proposals?state=open", Some(&etag)).await;
+    assert_eq!(status, 200);
+    assert_ne!(changed.unwrap(), etag);
+
+    let (status, _, body) = get(&base, "/proposals/1", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["proposal"]["proposal_id"], "1");
+    assert_eq!(body["proposal"]["votes"].as_array().unwrap().len(), 1);
+    assert_eq!(body["veto"]["threshold_crossed"], false);
+    let (status, _, _) = get(&base, "/proposals/9", None).await;
+    assert_eq!(status, 404);
+
+    // Pages of the registry in id order
+    let remote: Vec<NodeEconomicNode> = (1..=3u8)
+        .map(|i| NodeEconomicNode {
+            node_id: hex::encode([i; 32]),
+            node_type: "exchange".to_string(),
+            hashpower_percent: None,
+        })
+        .collect();
+    node.module.economic_nodes.reconcile(&remote).await.unwrap();
+    let (status, _, page) = get(&base, "/economic-nodes?limit=2", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        (page["total"].as_u64(), page["limit"].as_u64()),
+        (Some(3), Some(2))
+    );
+    assert_eq!(page["nodes"].as_array().unwrap().len(), 2);
+    let (_, _, page) = get(&base, "/economic-nodes?offset=2", None).await;
+    assert_eq!(
+        page["nodes"][0]["node_id"],
+        serde_json::to_value([3u8; 32]).unwrap()
+    );
+    let (status, _, _) = get(&base, "/economic-nodes?limit=all", None).await;
+    assert_eq!(status, 400);
+
+    // Epoch 10 is summarized once its last block, 109, connects
+    let (status, _, _) = get(&base, "/epochs/10/summary", None).await;
+    assert_eq!(status, 404);
+    let hash = [109u8; 32];
+    node.node_api
+        .add_block(109, hash, common::block([108u8; 32], Vec::new()));
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: hash,
+            height: 109,
+        },
+    )
+    .await;
+    let (status, etag, summary) = get(&base, "/epochs/10/summary", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(summary["end_height"], 109);
+    let (status, _, _) = get(&base, "/epochs/10/summary", etag.as_deref()).await;
+    assert_eq!(status, 304);
+    server.abort();
+}
@@ -0,0 +1,217 @@
+//! Governance state rolled back across a reorg of the mock node's chain
+
+mod common;
+
+use blvm_governance::checkpoint;
+use blvm_governance::config::AuditLogConfig;
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::proposals::{ProposalStatus, ProposalVote};
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use blvm_protocol::Hash;
+use common::MockNode;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const NODE: [u8; 32] = [7u8; 32];
+
+/// Hash of the block at `height` on chain `chain`.
+fn hash(height: u64, chain: u8) -> Hash {
+    let mut hash = [chain; 32];
+    hash[..8].copy_from_slice(&height.to_le_bytes());
+    hash
+}
+
+async fn block(node: &MockNode, height: u64, chain: u8) {
+    let block_hash = hash(height, chain);
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock { block_hash, height },
+    )
+    .await;
+}
+
+async fn created(node: &MockNode, proposal_id: &str) {
+    node.node_api.add_proposal(common::proposal(proposal_id));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+}
+
+async fn voted(node: &MockNode, voter: &str, vote: &str) {
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        },
+    )
+    .await;
+}
+
+fn vote(voter: &str, vote: &str) -> ProposalVote {
+    ProposalVote {
+        voter: voter.to_string(),
+        vote: vote.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_three_block_reorg_is_rolled_back() {
+    let config = GovernanceConfig {
+        audit_log: AuditLogConfig {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let node = MockNode::start("reorg", config).await;
+    for height in 101..=105 {
+        node.node_api.add_block(
+            height,
+            hash(height, 0),
+            common::block(hash(height - 1, 0), Vec::new()),
+        );
+    }
+    node.send_event(
+        EventType::EconomicNodeRegistered,
+        EventPayload::EconomicNodeRegistered {
+            node_id: hex::encode(NODE),
+            node_type: "miner".to_string(),
+            hashpower_percent: Some(0.5),
+        },
+    )
+    .await;
+    created(&node, "1").await;
+    created(&node, "2").await;
+    voted(&node, "alice", "yes").await;
+
+    // Chain 0: bob votes at the fork, then carol votes, alice changes her vote, the node
+    // vetoes proposal 1 and proposal 2 is merged above it
+    block(&node, 101, 0).await;
+    block(&node, 102, 0).await;
+    voted(&node, "bob", "no").await;
+    block(&node, 103, 0).await;
+    voted(&node, "carol", "yes").await;
+    voted(&node, "alice", "no").await;
+    block(&node, 104, 0).await;
+    node.send_event(
+        EventType::EconomicNodeVeto,
+        EventPayload::EconomicNodeVeto {
+            proposal_id: "1".to_string(),
+            node_id: hex::encode(NODE),
+            reason: "unsafe".to_string(),
+        },
+    )
+    .await;
+    block(&node, 105, 0).await;
+    node.send_event(
+        EventType::GovernanceProposalMerged,
+        EventPayload::GovernanceProposalMerged {
+            proposal_id: "2".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+        },
+    )
+    .await;
+    let store = &node.module.proposal_store;
+    assert_eq!(store.proposal("1").unwrap().unwrap().votes.len(), 3);
+    assert_eq!(
+        store.proposal("2").unwrap().unwrap().status,
+        ProposalStatus::Merged
+    );
+    assert_eq!(
+        node.module.economic_nodes.get_nodes_for_test().await[&NODE].veto_count,
+        1
+    );
+
+    // Blocks 103 to 105 replaced by chain 1, which is one block longer
+    let mut parent = hash(102, 0);
+    let replacement = (103..=106)
+        .map(|height| {
+            let block = common::block(parent, Vec::new());
+            parent = hash(height, 1);
+            (parent, block)
+        })
+        .collect();
+    node.node_api.reorg(103, replacement);
+    block(&node, 103, 1).await;
+
+    let proposal = store.proposal("1").unwrap().unwrap();
+    assert_eq!(
+        proposal.votes,
+        vec![vote("alice", "yes"), vote("bob", "no")]
+    );
+    assert_eq!(proposal.status, ProposalStatus::Voting);
+    let proposal = store.proposal("2").unwrap().unwrap();
+    assert_eq!(
+        (proposal.status, proposal.closed_height),
+        (ProposalStatus::Created, None)
+    );
+    assert_eq!(store.votes_between(103..=105).unwrap(), 0);
+    assert_eq!(store.votes_between(100..=102).unwrap(), 2);
+    let nodes = node.module.economic_nodes.get_nodes_for_test().await;
+    assert_eq!(nodes[&NODE].veto_count, 0);
+    assert!(nodes[&NODE].veto_history.is_empty());
+
+    // The checkpoint went back to the fork, so the rest of chain 1 is backfilled
+    let checkpointer = node.module.pipeline.checkpointer();
+    assert_eq!(
+        checkpointer.last().unwrap().block_hash,
+        hex::encode(hash(103, 1))
+    );
+    let queued = checkpoint::backfill(
+        checkpointer,
+        &NodeApiIpc::new(node.node_api.clone()),
+        &node.module.events,
+        &node.module.shutdown,
+        100,
+    )
+    .await
+    .unwrap();
+    assert_eq!(queued, 3);
+    tokio::time::timeout(Duration::from_secs(10), node.module.shutdown.idle())
+        .await
+        .expect("backfilled blocks not processed");
+    let last = checkpointer.last().unwrap();
+    assert_eq!(
+        (last.height, last.block_hash),
+        (106, hex::encode(hash(106, 1)))
+    );
+    assert_eq!(store.proposal("1").unwrap().unwrap().votes.len(), 2);
+
+    // One audit entry per handler, each with what it reverted
+    let reverted: BTreeMap<String, u64> = node
+        .audit_entries()
+        .iter()
+        .filter(|entry| entry["kind"] == "rollback")
+        .map(|entry| {
+            assert_eq!(entry["data"]["fork_height"], 102);
+            assert_eq!(entry["data"]["previous_tip"]["height"], 105);
+            assert_eq!(entry["data"]["tip"]["hash"], hex::encode(hash(103, 1)));
+            (
+                entry["data"]["handler"].as_str().unwrap().to_string(),
+                entry["data"]["reverted"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        reverted,
+        BTreeMap::from([
+            ("audit_log".to_string(), 0),
+            ("economic_nodes".to_string(), 1),
+            ("epoch_summary".to_string(), 0),
+            ("proposals".to_string(), 3),
+            ("webhook".to_string(), 0),
+        ])
+    );
+}
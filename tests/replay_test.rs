@@ -0,0 +1,162 @@
+//! Replay of events recorded in the audit log
+
+mod common;
+
+use blvm_governance::audit_log::{AuditKind, AuditLog};
+use blvm_governance::checkpoint::Checkpointer;
+use blvm_governance::cli;
+use blvm_governance::config::{AuditLogConfig, DryRun};
+use blvm_governance::pipeline::EventHandler;
+use blvm_governance::replay::{self, RecordedNodeApi, ReplayHandler, ReplayRange};
+use blvm_governance::storage::DataDir;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
+use blvm_node::module::traits::EventType;
+use common::MockNodeApi;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A fresh data directory named after `name`.
+fn data_dir(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("blvm_replay_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&root).ok();
+    root
+}
+
+fn audit_config() -> AuditLogConfig {
+    AuditLogConfig {
+        enabled: true,
+        record_blocks: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_offline_replay_marks_deliveries_and_leaves_the_checkpoint() {
+    let root = data_dir("offline");
+    let layout = DataDir::open(&root).unwrap();
+    let hash = [7u8; 32];
+    let node_api = MockNodeApi::new(101).with_block(101, hash, common::block([0u8; 32], vec![]));
+
+    // Recorded as the module records them, and processed
+    let log = AuditLog::open(&layout.audit(), &audit_config()).unwrap();
+    let events = [
+        EventMessage {
+            event_type: EventType::NewBlock,
+            payload: EventPayload::NewBlock {
+                block_hash: hash,
+                height: 101,
+            },
+        },
+        EventMessage {
+            event_type: EventType::GovernanceProposalCreated,
+            payload: EventPayload::GovernanceProposalCreated {
+                proposal_id: "1".to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            },
+        },
+    ];
+    for event in &events {
+        log.handle(&ModuleMessage::Event(event.clone()), &node_api)
+            .await
+            .unwrap();
+    }
+    let checkpointer = Arc::new(Checkpointer::open(&layout.state()).unwrap());
+    checkpointer.record(101, &hash).unwrap();
+    checkpointer.record_event().unwrap();
+    let sequence = checkpointer.sequence();
+
+    let recorded = replay::read(&layout.audit(), &ReplayRange::default()).unwrap();
+    assert_eq!(recorded.events.len(), 2);
+    assert_eq!(recorded.unreplayable, 0);
+    assert!(recorded.events[0].block.is_some());
+    assert_eq!(recorded.events[1].height, Some(101));
+
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let offline: Arc<RecordedNodeApi> = Arc::new(RecordedNodeApi::new(&recorded));
+    assert_eq!(offline.blocks(), 1);
+    let handlers = replay::handlers(
+        &[ReplayHandler::Webhook],
+        &config,
+        cli::open_store(&root).unwrap(),
+        offline.clone(),
+        DryRun::Off,
+        None,
+    )
+    .await
+    .unwrap();
+    let summary = replay::replay(
+        &recorded,
+        handlers,
+        Arc::clone(&checkpointer),
+        offline.as_ref(),
+    )
+    .await;
+    assert_eq!((summary.events, summary.completed), (2, 2));
+    assert!(summary.succeeded());
+    assert_eq!(summary.handlers["webhook"].handled, 2);
+
+    // The block already processed is delivered again, from the recorded block
+    let block = received.recv().await.unwrap();
+    assert_eq!(block["block_height"], 101);
+    assert_eq!(block["replayed"], true);
+    let proposal = received.recv().await.unwrap();
+    assert_eq!(proposal["event_type"], "proposal_created");
+    assert_eq!(proposal["replayed"], true);
+
+    assert_eq!(checkpointer.sequence(), sequence);
+    let reopened = Checkpointer::open(&layout.state()).unwrap();
+    assert_eq!(reopened.sequence(), sequence);
+
+    // Nothing recorded above the range
+    let later = ReplayRange {
+        from_height: Some(102),
+        ..Default::default()
+    };
+    assert!(replay::read(&layout.audit(), &later)
+        .unwrap()
+        .events
+        .is_empty());
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[tokio::test]
+async fn test_entries_without_the_event_are_skipped() {
+    let root = data_dir("skipped");
+    let dir = DataDir::open(&root).unwrap().audit();
+    let log = AuditLog::open(&dir, &audit_config()).unwrap();
+    log.record_at(
+        AuditKind::Event,
+        serde_json::json!({ "event_type": "NewBlock" }),
+        1_700_000_000,
+    )
+    .unwrap();
+    log.record_at(
+        AuditKind::WebhookDelivery,
+        serde_json::json!({ "delivered": true }),
+        1_700_000_001,
+    )
+    .unwrap();
+
+    let recorded = replay::read(&dir, &ReplayRange::default()).unwrap();
+    assert!(recorded.events.is_empty());
+    assert_eq!(recorded.unreplayable, 1);
+    let summary = replay::replay(
+        &recorded,
+        Vec::new(),
+        Arc::new(Checkpointer::open(&root).unwrap()),
+        &MockNodeApi::new(100),
+    )
+    .await;
+    assert_eq!(
+        summary.to_string(),
+        "No events to replay; 1 recorded without the event were skipped"
+    );
+    std::fs::remove_dir_all(&root).ok();
+}
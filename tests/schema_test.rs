@@ -0,0 +1,150 @@
+//! Outbound payloads against their versioned schemas and the snapshots of their shapes
+
+mod common;
+
+use blvm_governance::schema::{
+    self, BlockNotification, Envelope, Payload, ProposalCreated, ProposalVoted,
+};
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::path::Path;
+use std::time::Duration;
+
+/// Snapshots of the shapes of the payloads, one file per schema.
+const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/schema");
+
+/// Fails when a payload's shape differs from its snapshot, with a note of whether its version
+/// needs a bump. With `UPDATE_SCHEMA_SNAPSHOTS` set, rewrites the snapshots instead.
+#[test]
+fn test_shapes_match_snapshots() {
+    let update = std::env::var_os("UPDATE_SCHEMA_SNAPSHOTS").is_some();
+    let mut failures = Vec::new();
+    for entry in schema::registry() {
+        let path = Path::new(SNAPSHOTS).join(format!("{}.json", entry.name));
+        let snapshot = serde_json::json!({
+            "schema_version": entry.schema_version,
+            "shape": schema::shape(&(entry.example)()),
+        });
+        if update {
+            std::fs::create_dir_all(SNAPSHOTS).unwrap();
+            let text = serde_json::to_string_pretty(&snapshot).unwrap();
+            std::fs::write(&path, text + "\n").unwrap();
+            continue;
+        }
+        let stored: Option<serde_json::Value> = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok());
+        match stored {
+            Some(stored) if stored == snapshot => {}
+            Some(stored) if stored["schema_version"] == entry.schema_version => {
+                failures.push(format!(
+                    "{}: shape changed without a version bump; bump its SCHEMA_VERSION",
+                    entry.name
+                ))
+            }
+            Some(_) => failures.push(format!(
+                "{}: version {} does not match the snapshot",
+                entry.name, entry.schema_version
+            )),
+            None => failures.push(format!("{}: no snapshot at {}", entry.name, path.display())),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nOnce the versions are right, rewrite the snapshots with UPDATE_SCHEMA_SNAPSHOTS=1",
+        failures.join("\n")
+    );
+}
+
+#[tokio::test]
+async fn test_deliveries_decode_as_schema_types() {
+    let (url, mut rx) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let node = MockNode::start("schema", config).await;
+    node.node_api.add_proposal(common::proposal("1"));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 7,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "bob".to_string(),
+            vote: "approve".to_string(),
+        },
+    )
+    .await;
+    node.node_api
+        .add_block(101, [1; 32], common::block([0; 32], Vec::new()));
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [1; 32],
+            height: 101,
+        },
+    )
+    .await;
+
+    let mut payloads = Vec::new();
+    while let Ok(Some(payload)) = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+        payloads.push(payload);
+    }
+    let envelopes: Vec<Envelope> = payloads
+        .iter()
+        .filter(|payload| payload.get("event_type").is_some())
+        .map(|payload| serde_json::from_value(payload.clone()).unwrap())
+        .collect();
+    assert!(envelopes
+        .iter()
+        .all(|e| e.schema_version == Envelope::SCHEMA_VERSION));
+
+    let created = envelopes
+        .iter()
+        .find(|e| e.event_type == "proposal_created")
+        .unwrap();
+    assert_eq!(created.data_version, Some(ProposalCreated::SCHEMA_VERSION));
+    assert_eq!(
+        serde_json::from_value::<ProposalCreated>(created.data.clone()).unwrap(),
+        ProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 7,
+            tier: "standard".to_string(),
+        }
+    );
+    let voted = envelopes
+        .iter()
+        .find(|e| e.event_type == "proposal_voted")
+        .unwrap();
+    let voted: ProposalVoted = serde_json::from_value(voted.data.clone()).unwrap();
+    assert_eq!(
+        (voted.voter.as_str(), voted.vote.as_str()),
+        ("bob", "approve")
+    );
+
+    let block: BlockNotification = payloads
+        .iter()
+        .find(|payload| payload.get("block_height").is_some())
+        .map(|payload| serde_json::from_value(payload.clone()).unwrap())
+        .unwrap();
+    assert_eq!(block.schema_version, BlockNotification::SCHEMA_VERSION);
+    // The webhook hashes the header it fetched, not the hash in the event
+    assert_eq!(
+        block.block_hash,
+        "5e95af134996df19e127558990dd3d8d9adaeb92678d4870ce25e49e4466db6d"
+    );
+    assert_eq!(block.block_height, 101);
+    assert!(!block.replayed);
+}
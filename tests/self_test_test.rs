@@ -0,0 +1,128 @@
+//! The checks of `self-test`, against the mock node and a local webhook
+
+mod common;
+
+use blvm_governance::self_test::{self, Report, Status, PING_EVENT};
+use blvm_governance::GovernanceConfig;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("blvm_self_test_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_checks_pass() {
+    let dir = temp_dir("pass");
+    let (url, mut received) = common::webhook_server().await;
+    let config_path = dir.join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!("[governance]\nwebhook_url = \"{}\"\n", url),
+    )
+    .unwrap();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.set_tip([0xab; 32], 812_000);
+
+    let mut report = Report::default();
+    let config = report
+        .run("configuration", self_test::configuration(&config_path))
+        .await
+        .unwrap();
+    assert_eq!(config.webhook_url.as_deref(), Some(url.as_str()));
+    let db = report.run("store", self_test::store(&dir)).await.unwrap();
+    report
+        .run(
+            "node round trip",
+            self_test::round_trip(node_api.clone(), &config),
+        )
+        .await
+        .unwrap();
+    report
+        .run("webhook", self_test::webhook(&config))
+        .await
+        .unwrap();
+    assert!(report.passed(), "{}", report);
+    assert_eq!(
+        report.checks[1].detail,
+        format!("{}: 0 economic nodes", dir.display())
+    );
+    assert_eq!(
+        report.checks[2].detail,
+        format!("chain tip {} at height 812000", "ab".repeat(32))
+    );
+    let payload = received.recv().await.unwrap();
+    assert_eq!(payload["event_type"], PING_EVENT);
+    assert_eq!(payload["test"], true);
+
+    // What the connection is set up with subscribes to nothing
+    let module = self_test::idle_module(node_api, db, &dir, &config)
+        .await
+        .unwrap();
+    assert!(module.subscriptions.current().is_empty());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn test_failures_are_reported() {
+    let dir = temp_dir("fail");
+    let mut report = Report::default();
+
+    let missing = dir.join("missing.toml");
+    assert!(report
+        .run("configuration", self_test::configuration(&missing))
+        .await
+        .is_none());
+    let invalid = dir.join("invalid.toml");
+    std::fs::write(
+        &invalid,
+        "[governance]\nwebhook_url = \"ftp://example.com\"\n",
+    )
+    .unwrap();
+    assert!(report
+        .run("configuration", self_test::configuration(&invalid))
+        .await
+        .is_none());
+    assert!(report
+        .run("store", self_test::store(&dir.join("no-data-dir")))
+        .await
+        .is_none());
+
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    node_api.fail_next("get_chain_info", &["code -8: invalid parameter"]);
+    let config = GovernanceConfig::default();
+    assert!(report
+        .run("node round trip", self_test::round_trip(node_api, &config))
+        .await
+        .is_none());
+
+    // Nothing listens on the port of a closed listener
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+    drop(listener);
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    assert!(report
+        .run("webhook", self_test::webhook(&config))
+        .await
+        .is_none());
+
+    assert!(report.checks.iter().all(|c| c.status == Status::Fail));
+    assert!(!report.passed());
+    assert!(report.checks[0].detail.contains("does not exist"));
+    assert!(
+        report.checks[1].detail.contains("governance.webhook_url"),
+        "{}",
+        report.checks[1].detail
+    );
+    assert!(report.checks[3].detail.contains("-8"), "{}", report);
+    assert!(report
+        .to_string()
+        .ends_with("Self-test failed: 5 of 5 checks failed"));
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,86 @@
+//! Node-requested shutdown through the module API
+
+mod common;
+
+use blvm_governance::api::GovernanceModuleApi;
+use blvm_governance::config::{GovernanceConfig, RegistryConfig};
+use blvm_governance::economic_nodes::EconomicNodeRegistry;
+use blvm_governance::proposals::ProposalStore;
+use blvm_governance::shutdown::{self, Shutdown, ShutdownReason};
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_node::module::inter_module::api::ModuleAPI;
+use blvm_sdk::module::ModuleDb;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_node_requested_shutdown() {
+    let dir = std::env::temp_dir().join(format!("blvm_shutdown_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = ModuleDb::open_with_migrations(
+        &dir,
+        blvm_sdk::migrations!(
+            1 => blvm_governance::storage::up_v1,
+            2 => blvm_governance::storage::up_v2,
+            3 => blvm_governance::storage::up_v3
+        ),
+    )
+    .unwrap()
+    .as_db();
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let registry = EconomicNodeRegistry::new(RegistryConfig::default(), node_api.clone())
+        .await
+        .unwrap();
+    let shutdown = Arc::new(Shutdown::new());
+    let api = GovernanceModuleApi::new(
+        Arc::new(ProposalStore::new(db)),
+        Arc::new(registry),
+        Arc::new(
+            GovernanceWebhookClient::new(&GovernanceConfig::default())
+                .await
+                .unwrap(),
+        ),
+        node_api.clone(),
+    )
+    .with_shutdown(Arc::clone(&shutdown));
+
+    // Work accepted before the request still finishes
+    let work = shutdown.accept().unwrap();
+    let response = api
+        .handle_request("shutdown", br#"{"deadline_secs": 5}"#, "node")
+        .await
+        .unwrap();
+    let ack: serde_json::Value = serde_json::from_slice(&response).unwrap();
+    assert_eq!(ack["accepted"], true);
+    assert_eq!(ack["in_flight"], 1);
+    assert_eq!(ack["exit_code"], shutdown::NODE_REQUESTED_EXIT_CODE);
+    assert!(shutdown.is_stopping());
+    assert!(shutdown.accept().is_none());
+
+    let reason = shutdown.reason();
+    assert_eq!(
+        reason,
+        ShutdownReason::NodeRequested {
+            deadline_secs: Some(5)
+        }
+    );
+    assert_eq!(
+        reason.drain_timeout(Duration::from_secs(30)),
+        Duration::from_secs(5)
+    );
+    drop(work);
+    tokio::time::timeout(Duration::from_secs(1), shutdown.idle())
+        .await
+        .unwrap();
+    shutdown::send_goodbye(node_api.as_ref(), &reason)
+        .await
+        .unwrap();
+
+    // A repeated request is acknowledged but changes nothing
+    let response = api.handle_request("shutdown", b"", "node").await.unwrap();
+    let ack: serde_json::Value = serde_json::from_slice(&response).unwrap();
+    assert_eq!(ack["accepted"], false);
+    assert_eq!(shutdown.reason(), reason);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,130 @@
+//! Miner signaling windows counted from synthetic header sequences
+
+mod common;
+
+use blvm_governance::config::{SignalingConfig, SignalingRule};
+use blvm_governance::node_api::NodeApiIpc;
+use blvm_governance::signaling::SignalingTracker;
+use blvm_governance::webhook::GovernanceWebhookClient;
+use blvm_governance::GovernanceConfig;
+use common::MockNodeApi;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn hash(height: u64, chain: u8) -> [u8; 32] {
+    let mut hash = [chain; 32];
+    hash[..8].copy_from_slice(&height.to_le_bytes());
+    hash
+}
+
+/// Serve blocks from `from` up on chain `chain`, signaling for bit 1 as `signaled` says.
+fn add_blocks(node_api: &MockNodeApi, from: u64, chain: u8, signaled: &[bool]) {
+    for (height, signaled) in (from..).zip(signaled) {
+        let mut block = common::block([0u8; 32], vec![]);
+        block.header.version = if *signaled { 0x2000_0002 } else { 0x2000_0000 };
+        node_api.add_block(height, hash(height, chain), block);
+    }
+}
+
+/// A tracker for proposal 42 on bit 1, in windows of 4 blocks with a threshold of 75%.
+async fn tracker(node_api: &Arc<MockNodeApi>, url: &str) -> SignalingTracker {
+    let config = GovernanceConfig {
+        webhook_url: Some(url.to_string()),
+        ..Default::default()
+    };
+    let webhook = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let signaling = SignalingConfig {
+        proposals: [(
+            "42".to_string(),
+            SignalingRule {
+                bit: 1,
+                window_blocks: 4,
+                threshold_percent: 75.0,
+            },
+        )]
+        .into(),
+    };
+    SignalingTracker::new(signaling, NodeApiIpc::new(node_api.clone())).with_webhook(webhook)
+}
+
+async fn new_block(tracker: &SignalingTracker, node_api: &MockNodeApi, height: u64, chain: u8) {
+    tracker
+        .handle_block(&hash(height, chain), height, node_api)
+        .await
+        .unwrap();
+}
+
+/// Event type, window start and signaling blocks of each payload received.
+async fn received(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+) -> Vec<(String, u64, u64)> {
+    let mut received = Vec::new();
+    while let Ok(Some(payload)) = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+        let data = &payload["data"];
+        received.push((
+            payload["event_type"].as_str().unwrap().to_string(),
+            data["start_height"].as_u64().unwrap(),
+            data["signaling"].as_u64().unwrap(),
+        ));
+    }
+    received
+}
+
+#[tokio::test]
+async fn test_windows_around_the_threshold() {
+    let (url, mut rx) = common::webhook_server().await;
+    let node_api = Arc::new(MockNodeApi::new(200));
+    // 2 of 4, then 3 of 4
+    add_blocks(
+        &node_api,
+        100,
+        0,
+        &[true, false, true, false, true, true, false, true, true],
+    );
+    let first = tracker(&node_api, &url).await;
+    for height in 100..=105 {
+        new_block(&first, &node_api, height, 0).await;
+    }
+
+    // Restarted mid-window: the window's earlier blocks are read again
+    let second = tracker(&node_api, &url).await;
+    for height in 106..=108 {
+        new_block(&second, &node_api, height, 0).await;
+    }
+
+    let closed = |event_type: &str, start, signaling| (event_type.to_string(), start, signaling);
+    assert_eq!(
+        received(&mut rx).await,
+        vec![
+            closed("signaling_window_closed", 100, 2),
+            closed("signaling_window_closed", 104, 3),
+            closed("signaling_locked_in", 104, 3),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_reorg_recomputes_the_window() {
+    let (url, mut rx) = common::webhook_server().await;
+    let node_api = Arc::new(MockNodeApi::new(200));
+    add_blocks(&node_api, 100, 0, &[true, true, false, false, true]);
+    let tracker = tracker(&node_api, &url).await;
+    for height in 100..=104 {
+        new_block(&tracker, &node_api, height, 0).await;
+    }
+
+    // Blocks 102 and 103 replaced by signaling ones, back in the closed window
+    add_blocks(&node_api, 102, 1, &[true, true]);
+    new_block(&tracker, &node_api, 102, 1).await;
+    new_block(&tracker, &node_api, 103, 1).await;
+
+    let closed = |event_type: &str, start, signaling| (event_type.to_string(), start, signaling);
+    assert_eq!(
+        received(&mut rx).await,
+        vec![
+            closed("signaling_window_closed", 100, 2),
+            closed("signaling_window_closed", 100, 4),
+            closed("signaling_locked_in", 100, 4),
+        ]
+    );
+}
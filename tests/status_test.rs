@@ -0,0 +1,171 @@
+//! Schema of the module status, as the node, the probes and the CLI read it
+
+mod common;
+
+use blvm_governance::build_info::BuildInfo;
+use blvm_governance::clock::ClockMonitor;
+use blvm_governance::config_reload::{ConfigReloader, ModulePaths};
+use blvm_governance::error::GovernanceError;
+use blvm_governance::error_report::ErrorReporter;
+use blvm_governance::heartbeat::Heartbeat;
+use blvm_governance::memory::MemoryBudget;
+use blvm_governance::network::NetworkStatus;
+use blvm_governance::status::{ModuleStatus, StatusCollector, StatusProvider};
+use blvm_governance::GovernanceConfig;
+use common::MockNode;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Every field of a fresh module's status, as `blvm-governance status` prints them. A change
+/// here is a change to what readers of the status see: bump `STATUS_SCHEMA_VERSION` if a
+/// field was removed or changed meaning.
+const FIELDS: &[&str] = &[
+    "build.build_timestamp",
+    "build.features",
+    "build.git_commit",
+    "build.git_dirty",
+    "build.ipc_protocol.max",
+    "build.ipc_protocol.min",
+    "build.rustc_version",
+    "build.version",
+    "checkpoint.events_processed",
+    "checkpoint.last_block_height",
+    "clock.samples",
+    "clock.skew_secs",
+    "clock.skewed",
+    "clock.syncing",
+    "clock.using_block_time",
+    "config_reload.last",
+    "connected",
+    "errors.counts",
+    "errors.recent",
+    "event_queue.capacity",
+    "event_queue.dropped",
+    "event_queue.full",
+    "event_queue.policy",
+    "event_queue.queued",
+    "event_queue.spilled",
+    "event_stream.gaps",
+    "event_stream.last_height",
+    "event_stream.missed_blocks",
+    "event_stream.possible_missed_events",
+    "heartbeat.consecutive_misses",
+    "heartbeat.dead",
+    "heartbeat.last_heartbeat_at",
+    "heartbeat.round_trip_ms",
+    "ipc.events_received.EconomicNodeRegistered",
+    "ipc.events_received.EconomicNodeVeto",
+    "ipc.events_received.GovernanceProposalCreated",
+    "ipc.events_received.GovernanceProposalMerged",
+    "ipc.events_received.GovernanceProposalVoted",
+    "ipc.events_received.NewBlock",
+    "ipc.events_received.other",
+    "memory.event_queue.limit_bytes",
+    "memory.event_queue.overflowed",
+    "memory.event_queue.used_bytes",
+    "memory.headers.limit_bytes",
+    "memory.headers.overflowed",
+    "memory.headers.used_bytes",
+    "memory.mempool_seen.limit_bytes",
+    "memory.mempool_seen.overflowed",
+    "memory.mempool_seen.used_bytes",
+    "memory.transactions.limit_bytes",
+    "memory.transactions.overflowed",
+    "memory.transactions.used_bytes",
+    "network.migrated_from",
+    "network.network",
+    "pause.draining",
+    "pause.paused",
+    "pause.since",
+    "pause.trigger",
+    "registry.nodes",
+    "registry.persisted",
+    "schema_version",
+    "uptime_secs",
+    "webhook.consecutive_failures",
+    "webhook.deliveries.delivered",
+    "webhook.deliveries.dry_run",
+    "webhook.deliveries.failed",
+];
+
+/// A collector with the providers main.rs registers, on `node`'s connection.
+fn collector(node: &MockNode) -> StatusCollector {
+    let module = &node.module;
+    let collector = StatusCollector::new(Instant::now());
+    collector.register(Arc::new(BuildInfo::current().clone()));
+    collector.register(Arc::clone(&module.metrics));
+    collector.register(Arc::new(Heartbeat::new()));
+    collector.register(Arc::clone(&module.stream));
+    collector.register(Arc::new(MemoryBudget::new(0)));
+    collector.register(Arc::new(ClockMonitor::default()));
+    collector.register(Arc::new(ErrorReporter::default()));
+    collector.register(Arc::clone(&node.pause));
+    let reloader = ConfigReloader::new(
+        || Err(GovernanceError::ConfigError("not reloaded".to_string())),
+        GovernanceConfig::default(),
+        ModulePaths {
+            data_dir: std::env::temp_dir(),
+            socket_path: std::env::temp_dir().join("node.sock"),
+        },
+        Arc::clone(&module.webhook_client),
+        Arc::clone(&module.economic_nodes),
+    );
+    let connection: Vec<Arc<dyn StatusProvider>> = vec![
+        Arc::clone(module.pipeline.checkpointer()) as _,
+        Arc::clone(&module.events) as _,
+        Arc::clone(&module.webhook_client) as _,
+        Arc::clone(&module.economic_nodes) as _,
+        Arc::new(reloader) as _,
+        Arc::new(NetworkStatus {
+            network: Some("regtest".to_string()),
+            migrated_from: None,
+        }) as _,
+    ];
+    collector.connected(connection);
+    collector
+}
+
+#[tokio::test]
+async fn test_fields_match_the_snapshot() {
+    let node = MockNode::start("status_fields", GovernanceConfig::default()).await;
+    let status = collector(&node).collect().await;
+
+    // The info entries depend on the build
+    let mut fields: Vec<String> = status
+        .to_string()
+        .lines()
+        .filter(|line| !line.starts_with("info."))
+        .map(|line| line.split(": ").next().unwrap().to_string())
+        .collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        FIELDS,
+        "the status schema changed; update FIELDS if that was intended:\n{}",
+        fields.join("\n")
+    );
+    assert_eq!(status.info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(status.section("registry").unwrap()["persisted"], true);
+}
+
+#[test]
+fn test_serialization_snapshot() {
+    let status = ModuleStatus {
+        schema_version: 2,
+        uptime_secs: 90,
+        connected: true,
+        info: BTreeMap::from([("version".to_string(), "0.1.0".to_string())]),
+        sections: BTreeMap::from([(
+            "webhook".to_string(),
+            serde_json::json!({ "consecutive_failures": 0 }),
+        )]),
+    };
+    let json = serde_json::to_string(&status).unwrap();
+    assert_eq!(
+        json,
+        r#"{"schema_version":2,"uptime_secs":90,"connected":true,"info":{"version":"0.1.0"},"sections":{"webhook":{"consecutive_failures":0}}}"#
+    );
+    assert_eq!(serde_json::from_str::<ModuleStatus>(&json).unwrap(), status);
+    assert_eq!(blvm_governance::status::STATUS_SCHEMA_VERSION, 2);
+}
@@ -2,24 +2,15 @@
 
 mod common;
 
+use blvm_governance::config::GovernanceConfig;
 use blvm_governance::webhook::GovernanceWebhookClient;
 use blvm_node::module::ipc::protocol::{EventMessage, EventPayload, ModuleMessage};
-use blvm_node::module::traits::{EventType, ModuleContext};
-use std::collections::HashMap;
+use blvm_node::module::traits::EventType;
 use std::sync::Arc;
 
 #[tokio::test]
 async fn test_webhook_client_disabled() {
-    let mut config = HashMap::new();
-    let temp = std::env::temp_dir();
-    let ctx = ModuleContext {
-        module_id: "test".to_string(),
-        config,
-        data_dir: temp.to_string_lossy().to_string(),
-        socket_path: temp.join("blvm_test.sock").to_string_lossy().into_owned(),
-    };
-
-    let client = GovernanceWebhookClient::new(&ctx).await.unwrap();
+    let client = GovernanceWebhookClient::new(&GovernanceConfig::default()).await.unwrap();
     assert!(!client.is_enabled());
 
     let event = ModuleMessage::Event(EventMessage {
@@ -32,30 +23,180 @@ async fn test_webhook_client_disabled() {
         },
     });
 
-    let node_api = Arc::new(common::MockNodeAPI { block_height: 100 });
+    let node_api = Arc::new(common::MockNodeApi::new(100));
     let result = client.handle_event(&event, node_api.as_ref()).await;
     assert!(result.is_ok());
 }
 
 #[tokio::test]
 async fn test_webhook_client_enabled() {
-    let mut config = HashMap::new();
-    config.insert(
-        "governance.webhook_url".to_string(),
-        "http://localhost:8080/webhook".to_string(),
-    );
-    config.insert("governance.node_id".to_string(), "test_node".to_string());
-
-    let temp = std::env::temp_dir();
-    let ctx = ModuleContext {
-        module_id: "test".to_string(),
-        config,
-        data_dir: temp.to_string_lossy().to_string(),
-        socket_path: temp.join("blvm_test.sock").to_string_lossy().into_owned(),
+    let config = GovernanceConfig {
+        webhook_url: Some("http://localhost:8080/webhook".to_string()),
+        node_id: Some("test_node".to_string()),
+        ..Default::default()
     };
 
-    let client = GovernanceWebhookClient::new(&ctx).await.unwrap();
+    let client = GovernanceWebhookClient::new(&config).await.unwrap();
     assert!(client.is_enabled());
     assert_eq!(client.webhook_url().unwrap(), "http://localhost:8080/webhook");
     assert_eq!(client.node_id().unwrap(), "test_node");
 }
+
+#[tokio::test]
+async fn test_registry_feed_delivers_queued_changes_on_shutdown() {
+    use blvm_governance::economic_nodes::{ChangeKind, ChangeSource, RegistryChange};
+    use blvm_governance::shutdown::Shutdown;
+
+    let (url, mut received) = common::webhook_server().await;
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        ..Default::default()
+    };
+    let client = Arc::new(GovernanceWebhookClient::new(&config).await.unwrap());
+    let node_api = Arc::new(common::MockNodeApi::new(100));
+    let shutdown = Arc::new(Shutdown::new());
+    let (changes, rx) = tokio::sync::broadcast::channel(16);
+    let feed = client.spawn_registry_feed(rx, node_api, Arc::clone(&shutdown));
+
+    // Accepted just before the signal
+    changes
+        .send(RegistryChange {
+            node_id: Some(hex::encode([1u8; 32])),
+            kind: ChangeKind::Expired,
+            before: serde_json::Value::Null,
+            after: serde_json::Value::Null,
+            height: 100,
+            source: ChangeSource::Organic,
+            intent: None,
+        })
+        .unwrap();
+    shutdown.begin();
+    tokio::time::timeout(std::time::Duration::from_secs(5), shutdown.idle())
+        .await
+        .expect("shutdown did not drain");
+
+    feed.await.unwrap();
+
+    let delivered = tokio::time::timeout(std::time::Duration::from_secs(1), received.recv())
+        .await
+        .unwrap()
+        .expect("change not delivered before shutdown");
+    assert_eq!(delivered["event_type"], "registry_expired");
+}
+
+#[tokio::test]
+async fn test_reconfigure_switches_webhook_url() {
+    let (first_url, mut first) = common::webhook_server().await;
+    let (second_url, mut second) = common::webhook_server().await;
+    let mut config = GovernanceConfig {
+        webhook_url: Some(first_url),
+        node_id: Some("test_node".to_string()),
+        ..Default::default()
+    };
+    let client = GovernanceWebhookClient::new(&config).await.unwrap();
+    let node_api = common::MockNodeApi::new(100);
+    let proposal = |id: &str| {
+        ModuleMessage::Event(EventMessage {
+            event_type: EventType::GovernanceProposalCreated,
+            payload: EventPayload::GovernanceProposalCreated {
+                proposal_id: id.to_string(),
+                repository: "test/repo".to_string(),
+                pr_number: 1,
+                tier: "standard".to_string(),
+            },
+        })
+    };
+    let timeout = std::time::Duration::from_secs(5);
+
+    client
+        .handle_event(&proposal("1"), &node_api)
+        .await
+        .unwrap();
+    let delivered = tokio::time::timeout(timeout, first.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered["data"]["proposal_id"], "1");
+
+    // Unchanged settings are not reapplied
+    assert!(!client.reconfigure(&config));
+    config.webhook_url = Some(second_url.clone());
+    assert!(client.reconfigure(&config));
+    assert_eq!(client.webhook_url(), Some(second_url));
+
+    client
+        .handle_event(&proposal("2"), &node_api)
+        .await
+        .unwrap();
+    let delivered = tokio::time::timeout(timeout, second.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered["data"]["proposal_id"], "2");
+    assert_eq!(delivered["node_id"], "test_node");
+    assert!(first.try_recv().is_err());
+
+    // Removing the URL disables delivery
+    config.webhook_url = None;
+    assert!(client.reconfigure(&config));
+    assert!(!client.is_enabled());
+}
+
+#[tokio::test]
+async fn test_dry_run_logs_instead_of_sending() {
+    let (url, mut received) = common::webhook_server().await;
+    let url = url.replace("http://", "http://user:hunter2@") + "?token=abc";
+    let config = GovernanceConfig {
+        webhook_url: Some(url),
+        webhook_events: vec!["proposal_created".to_string()],
+        ..Default::default()
+    };
+    let dir = std::env::temp_dir().join(format!("blvm_dry_run_{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let client = GovernanceWebhookClient::new(&config)
+        .await
+        .unwrap()
+        .with_dry_run(Some(dir.clone()));
+    let node_api = common::MockNodeApi::new(100);
+
+    let created = ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalCreated,
+        payload: EventPayload::GovernanceProposalCreated {
+            proposal_id: "1".to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    });
+    // Filtered out as it would be when sending
+    let voted = ModuleMessage::Event(EventMessage {
+        event_type: EventType::GovernanceProposalVoted,
+        payload: EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: "alice".to_string(),
+            vote: "approve".to_string(),
+        },
+    });
+    client.handle_event(&created, &node_api).await.unwrap();
+    client.handle_event(&voted, &node_api).await.unwrap();
+
+    let counts = client.delivery_counts();
+    assert_eq!((counts.delivered, counts.failed, counts.dry_run), (0, 0, 1));
+    assert!(node_api.published().is_empty());
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(received.try_recv().is_err());
+
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].to_string_lossy().ends_with("-000001-proposal_created.json"));
+    let request: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+    assert_eq!(request["method"], "POST");
+    let logged_url = request["url"].as_str().unwrap();
+    assert!(logged_url.contains("user:***@") && logged_url.ends_with("?token=***"));
+    assert!(!logged_url.contains("hunter2"));
+    assert_eq!(request["headers"]["content-type"], "application/json");
+    assert_eq!(request["payload"]["event_type"], "proposal_created");
+    assert_eq!(request["payload"]["data"]["proposal_id"], "1");
+    std::fs::remove_dir_all(&dir).ok();
+}
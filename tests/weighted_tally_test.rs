@@ -0,0 +1,143 @@
+//! Proposal tallies weighted by the economic weight of voters in the registry
+
+mod common;
+
+use blvm_governance::config::{WeightSource, WeightThresholds};
+use blvm_governance::tally::Milestone;
+use blvm_governance::GovernanceConfig;
+use blvm_node::module::ipc::protocol::EventPayload;
+use blvm_node::module::traits::EventType;
+use common::MockNode;
+use std::time::Duration;
+
+/// Node id of voter `id`, as it votes.
+fn voter(id: u8) -> String {
+    hex::encode([id; 32])
+}
+
+async fn registered(node: &MockNode, id: u8, sats: u64) {
+    node.register_verified(id, "exchange", sats).await;
+}
+
+async fn created(node: &MockNode, proposal_id: &str) {
+    node.node_api.add_proposal(common::proposal(proposal_id));
+    node.send_event(
+        EventType::GovernanceProposalCreated,
+        EventPayload::GovernanceProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            repository: "test/repo".to_string(),
+            pr_number: 1,
+            tier: "standard".to_string(),
+        },
+    )
+    .await;
+}
+
+async fn voted(node: &MockNode, voter: &str, vote: &str) {
+    node.send_event(
+        EventType::GovernanceProposalVoted,
+        EventPayload::GovernanceProposalVoted {
+            proposal_id: "1".to_string(),
+            voter: voter.to_string(),
+            vote: vote.to_string(),
+        },
+    )
+    .await;
+}
+
+async fn block(node: &MockNode, height: u64) {
+    node.send_event(
+        EventType::NewBlock,
+        EventPayload::NewBlock {
+            block_hash: [height as u8; 32],
+            height,
+        },
+    )
+    .await;
+}
+
+fn config(weights: WeightSource) -> GovernanceConfig {
+    let mut config = GovernanceConfig::default();
+    config.tally.weights = weights;
+    config.tally.weighted.insert(
+        "standard".to_string(),
+        WeightThresholds {
+            quorum_percent: 60.0,
+            approval_percent: 50.0,
+        },
+    );
+    config
+}
+
+#[tokio::test]
+async fn test_weight_change_after_a_vote_counts_from_the_next_block() {
+    let (url, mut received) = common::webhook_server().await;
+    let mut config = config(WeightSource::Current);
+    config.webhook_url = Some(url);
+    // An unregistered voter counts for nothing
+    config.tally.default_weight = 0.0;
+    let node = MockNode::start("weighted_current", config).await;
+    registered(&node, 1, 5).await;
+    registered(&node, 2, 3).await;
+    registered(&node, 3, 2).await;
+    created(&node, "1").await;
+    let store = &node.module.proposal_store;
+
+    voted(&node, &voter(1), "yes").await;
+    voted(&node, "alice", "no").await;
+    let weighted = store.weighted_tally("1").unwrap().unwrap();
+    assert_eq!((weighted.tally.yes, weighted.tally.no), (5.0, 0.0));
+    assert_eq!((weighted.total_weight, weighted.snapshot), (10.0, None));
+    // Half the weight voted: short of quorum, though two voters did
+    assert!(store.proposal("1").unwrap().unwrap().milestones.is_empty());
+
+    // The voter's weight grows after the vote; the tally sees it at the next block
+    registered(&node, 1, 8).await;
+    assert_eq!(store.weighted_tally("1").unwrap().unwrap().tally.yes, 5.0);
+    block(&node, 101).await;
+    let weighted = store.weighted_tally("1").unwrap().unwrap();
+    assert_eq!((weighted.tally.yes, weighted.total_weight), (8.0, 13.0));
+    assert_eq!(weighted.height, Some(101));
+    let proposal = store.proposal("1").unwrap().unwrap();
+    let milestones: Vec<Milestone> = proposal.milestones.iter().map(|m| m.milestone).collect();
+    assert_eq!(
+        milestones,
+        vec![Milestone::QuorumReached, Milestone::ApprovedPendingMerge]
+    );
+    // The count-based tally is kept alongside
+    assert_eq!(proposal.milestones[0].tally.total(), 2);
+
+    let mut announced = Vec::new();
+    while let Ok(Some(payload)) =
+        tokio::time::timeout(Duration::from_secs(1), received.recv()).await
+    {
+        if payload["event_type"] == "proposal_quorum_reached" {
+            announced.push(payload["data"].clone());
+        }
+    }
+    assert_eq!(announced.len(), 1);
+    assert_eq!(announced[0]["weighting"], "weight");
+    assert_eq!(announced[0]["weighted_tally"]["tally"]["yes"], 8.0);
+    assert_eq!(announced[0]["tally"]["yes"], 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_weights_stay_with_the_proposal() {
+    let mut config = config(WeightSource::Snapshot);
+    config.registry.epoch.length_blocks = 101;
+    let node = MockNode::start("weighted_snapshot", config).await;
+    registered(&node, 1, 5).await;
+    registered(&node, 2, 5).await;
+    // Epoch 1 starts, and the proposal is pinned to its snapshot
+    block(&node, 101).await;
+    created(&node, "1").await;
+    let store = &node.module.proposal_store;
+    voted(&node, &voter(1), "yes").await;
+
+    registered(&node, 1, 20).await;
+    block(&node, 102).await;
+    let weighted = store.weighted_tally("1").unwrap().unwrap();
+    assert_eq!((weighted.tally.yes, weighted.total_weight), (5.0, 10.0));
+    assert_eq!(weighted.snapshot, Some(1));
+    assert!(store.proposal("1").unwrap().unwrap().milestones.is_empty());
+}